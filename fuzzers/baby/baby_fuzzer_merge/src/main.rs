@@ -0,0 +1,100 @@
+use std::{env, path::PathBuf, process, ptr::write};
+
+use libafl::{
+    corpus::{CorpusMerger, OnDiskCorpus},
+    events::SimpleEventManager,
+    executors::{inprocess::InProcessExecutor, ExitKind},
+    feedbacks::{CrashFeedback, MaxMapFeedback},
+    fuzzer::StdFuzzer,
+    inputs::{BytesInput, HasTargetBytes},
+    monitors::SimpleMonitor,
+    observers::StdMapObserver,
+    schedulers::QueueScheduler,
+    state::StdState,
+    Error,
+};
+use libafl_bolts::{rands::StdRand, tuples::tuple_list, AsSlice};
+
+/// Coverage map with explicit assignments due to the lack of instrumentation
+static mut SIGNALS: [u8; 16] = [0; 16];
+static mut SIGNALS_PTR: *mut u8 = unsafe { SIGNALS.as_mut_ptr() };
+
+/// Assign a signal to the signals map
+fn signals_set(idx: usize) {
+    unsafe { write(SIGNALS_PTR.add(idx), 1) };
+}
+
+#[allow(clippy::similar_names)]
+pub fn main() -> Result<(), Error> {
+    let mut args: Vec<PathBuf> = env::args().skip(1).map(PathBuf::from).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: baby_fuzzer_merge <out_dir> <in_dir>...");
+        process::exit(1);
+    }
+    let out_dir = args.remove(0);
+    let in_dirs = args;
+
+    // The closure that we want to replay
+    let mut harness = |input: &BytesInput| {
+        let target = input.target_bytes();
+        let buf = target.as_slice();
+        signals_set(0);
+        if !buf.is_empty() && buf[0] == b'a' {
+            signals_set(1);
+            if buf.len() > 1 && buf[1] == b'b' {
+                signals_set(2);
+                if buf.len() > 2 && buf[2] == b'c' {
+                    return ExitKind::Crash;
+                }
+            }
+        }
+        ExitKind::Ok
+    };
+
+    // Create an observation channel using the signals map
+    let observer = unsafe { StdMapObserver::from_mut_ptr("signals", SIGNALS_PTR, SIGNALS.len()) };
+
+    // Only the inputs adding coverage to this feedback make it into the merged corpus
+    let mut feedback = MaxMapFeedback::new(&observer);
+
+    // A feedback to choose if an input is a solution or not
+    let mut objective = CrashFeedback::new();
+
+    // The Monitor trait define how the fuzzer stats are displayed to the user
+    let mon = SimpleMonitor::new(|s| println!("{s}"));
+
+    let mut mgr = SimpleEventManager::new(mon);
+
+    // A fresh state, so that the feedback starts without any coverage
+    let mut state = StdState::new(
+        StdRand::new(),
+        // The merged corpus, written to the output directory
+        OnDiskCorpus::new(out_dir)?,
+        // Crashing inputs are kept apart
+        OnDiskCorpus::new(PathBuf::from("./solutions"))?,
+        &mut feedback,
+        &mut objective,
+    )?;
+
+    // The scheduler doesn't matter here, nothing is fuzzed
+    let scheduler = QueueScheduler::new();
+
+    let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+    // Create the executor for an in-process function with just one observer
+    let mut executor = InProcessExecutor::new(
+        &mut harness,
+        tuple_list!(observer),
+        &mut fuzzer,
+        &mut state,
+        &mut mgr,
+    )?;
+
+    let stats =
+        CorpusMerger::new(in_dirs).merge(&mut fuzzer, &mut executor, &mut mgr, &mut state)?;
+    println!(
+        "Kept {} of {} files ({} duplicates, {} solutions)",
+        stats.kept, stats.files, stats.duplicates, stats.solutions
+    );
+    Ok(())
+}
@@ -0,0 +1,45 @@
+// A small parser with a bug, to try the fuzzer on the device.
+// Build it with:
+// xcrun -sdk iphoneos clang -arch arm64 -O1 -shared harness.c -o libharness.dylib
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define RECORD_MAX 16
+
+struct header {
+  char     magic[4];
+  uint16_t records;
+  uint16_t record_size;
+};
+
+static int parse_record(const uint8_t *data, uint16_t size) {
+  uint8_t *record = malloc(RECORD_MAX);
+  if (!record) { return -1; }
+  // The size is not checked against RECORD_MAX, a heap overflow for large records
+  memcpy(record, data, size);
+  int sum = 0;
+  for (uint16_t i = 0; i < size && i < RECORD_MAX; i++) {
+    sum += record[i];
+  }
+  free(record);
+  return sum;
+}
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size) {
+  struct header header;
+  if (size < sizeof(header)) { return 0; }
+  memcpy(&header, data, sizeof(header));
+  if (memcmp(header.magic, "IOSF", 4) != 0) { return 0; }
+
+  data += sizeof(header);
+  size -= sizeof(header);
+  for (uint16_t i = 0; i < header.records; i++) {
+    if (size < header.record_size) { return 0; }
+    if (header.record_size > RECORD_MAX && data[0] == 'X') { __builtin_trap(); }
+    parse_record(data, header.record_size);
+    data += header.record_size;
+    size -= header.record_size;
+  }
+  return 0;
+}
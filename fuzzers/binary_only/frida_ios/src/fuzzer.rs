@@ -0,0 +1,201 @@
+//! A Frida-based fuzzer for libraries on iOS devices, with llmp-multiprocessing and restarts.
+//! iOS does not allow `fork`, so the launcher spawns the clients as new processes instead.
+use std::{env, path::PathBuf};
+
+use frida_gum::Gum;
+use libafl::{
+    corpus::{CachedOnDiskCorpus, Corpus, OnDiskCorpus},
+    events::{launcher::Launcher, llmp::LlmpRestartingEventManager, EventConfig},
+    executors::{inprocess::InProcessExecutor, ExitKind},
+    feedback_or, feedback_or_fast,
+    feedbacks::{CrashFeedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback},
+    fuzzer::{Fuzzer, StdFuzzer},
+    inputs::{BytesInput, HasTargetBytes},
+    monitors::MultiMonitor,
+    mutators::{
+        havoc_mutations::havoc_mutations,
+        scheduled::{tokens_mutations, StdScheduledMutator},
+        token_mutations::Tokens,
+    },
+    observers::{CanTrack, HitcountsMapObserver, StdMapObserver, TimeObserver},
+    schedulers::{IndexesLenTimeMinimizerScheduler, QueueScheduler},
+    stages::StdMutationalStage,
+    state::{HasCorpus, StdState},
+    Error, HasMetadata,
+};
+use libafl_bolts::{
+    cli::{parse_args, FuzzerOptions},
+    os::mach_exceptions::MachExceptionHandler,
+    rands::StdRand,
+    shmem::{ShMemProvider, StdShMemProvider},
+    tuples::{tuple_list, Merge},
+    AsSlice,
+};
+use libafl_frida::{
+    asan::{
+        asan_rt::AsanRuntime,
+        errors::{AsanErrorsFeedback, AsanErrorsObserver},
+    },
+    coverage_rt::{CoverageRuntime, MAP_SIZE},
+    executor::FridaInProcessExecutor,
+    helper::FridaInstrumentationHelper,
+};
+
+/// The main fn, usually parsing parameters, and starting the fuzzer
+pub fn main() {
+    env_logger::init();
+    let options = parse_args();
+
+    // The working directory of a process started on the device is usually not writable,
+    // while the shmem server socket and the discovered corpus are created relative to it.
+    env::set_current_dir(env::temp_dir()).expect("Failed to change to the temp directory");
+
+    unsafe {
+        match fuzz(&options) {
+            Ok(()) | Err(Error::ShuttingDown) => println!("\nFinished fuzzing. Good bye."),
+            Err(e) => panic!("Error during fuzzing: {e:?}"),
+        }
+    }
+}
+
+/// The actual fuzzer
+#[allow(clippy::too_many_lines)]
+unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
+    log::info!("Frida fuzzer starting up.");
+
+    // 'While the stats are state, they are usually used in the broker - which is likely never restarted
+    let monitor = MultiMonitor::new(|s| println!("{s}"));
+
+    // On iOS, the shared maps are unlinked files in the temp dir, shared by fd through the shmem server
+    let shmem_provider = StdShMemProvider::new()?;
+
+    let mut run_client =
+        |state: Option<_>, mut mgr: LlmpRestartingEventManager<_, _, _>, _core_id| {
+            // Report the crashes right away, instead of waiting for the system crash reporter
+            let _exception_handler = MachExceptionHandler::install()?;
+
+            let lib = libloading::Library::new(options.clone().harness.unwrap()).unwrap();
+            let target_func: libloading::Symbol<
+                unsafe extern "C" fn(data: *const u8, size: usize) -> i32,
+            > = lib.get(options.harness_function.as_bytes()).unwrap();
+
+            let mut frida_harness = |input: &BytesInput| {
+                let target = input.target_bytes();
+                let buf = target.as_slice();
+                (target_func)(buf.as_ptr(), buf.len());
+                ExitKind::Ok
+            };
+
+            let gum = Gum::obtain();
+
+            // Every client runs with the Frida ASan runtime, on-device targets being mostly memory bugs
+            let coverage = CoverageRuntime::new();
+            let asan = AsanRuntime::new(options);
+
+            let mut frida_helper =
+                FridaInstrumentationHelper::new(&gum, options, tuple_list!(coverage, asan));
+
+            // Create an observation channel using the coverage map
+            let edges_observer = HitcountsMapObserver::new(StdMapObserver::from_mut_ptr(
+                "edges",
+                frida_helper.map_mut_ptr().unwrap(),
+                MAP_SIZE,
+            ))
+            .track_indices();
+
+            // Create an observation channel to keep track of the execution time
+            let time_observer = TimeObserver::new("time");
+            let asan_observer = AsanErrorsObserver::from_static_asan_errors();
+
+            // Feedback to rate the interestingness of an input
+            // This one is composed by two Feedbacks in OR
+            let mut feedback = feedback_or!(
+                // New maximization map feedback linked to the edges observer and the feedback state
+                MaxMapFeedback::new(&edges_observer),
+                // Time feedback, this one does not need a feedback state
+                TimeFeedback::new(&time_observer)
+            );
+
+            // Feedbacks to recognize an input as solution
+            let mut objective = feedback_or_fast!(
+                CrashFeedback::new(),
+                TimeoutFeedback::new(),
+                AsanErrorsFeedback::new(&asan_observer)
+            );
+
+            // If not restarting, create a State from scratch
+            let mut state = state.unwrap_or_else(|| {
+                StdState::new(
+                    // RNG
+                    StdRand::new(),
+                    // Corpus that will be evolved, we keep it in memory for performance
+                    CachedOnDiskCorpus::no_meta(PathBuf::from("./corpus_discovered"), 64).unwrap(),
+                    // Corpus in which we store solutions (crashes in this example),
+                    // on disk so the user can get them after stopping the fuzzer
+                    OnDiskCorpus::new(options.output.clone()).unwrap(),
+                    &mut feedback,
+                    &mut objective,
+                )
+                .unwrap()
+            });
+
+            println!("We're a client, let's fuzz :)");
+
+            // Add the magic of the example harness, if not existing
+            if state.metadata_map().get::<Tokens>().is_none() {
+                state.add_metadata(Tokens::from([b"IOSF".to_vec()]));
+            }
+
+            // Setup a basic mutator with a mutational stage
+            let mutator = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
+
+            // A minimization+queue policy to get testcasess from the corpus
+            let scheduler =
+                IndexesLenTimeMinimizerScheduler::new(&edges_observer, QueueScheduler::new());
+
+            // A fuzzer with feedbacks and a corpus scheduler
+            let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+            let observers = tuple_list!(edges_observer, time_observer, asan_observer);
+
+            // Create the executor for an in-process function with just one observer for edge coverage
+            let mut executor = FridaInProcessExecutor::new(
+                &gum,
+                InProcessExecutor::new(
+                    &mut frida_harness,
+                    observers,
+                    &mut fuzzer,
+                    &mut state,
+                    &mut mgr,
+                )?,
+                &mut frida_helper,
+            );
+
+            // In case the corpus is empty (on first run), reset
+            if state.must_load_initial_inputs() {
+                state
+                    .load_initial_inputs(&mut fuzzer, &mut executor, &mut mgr, &options.input)
+                    .unwrap_or_else(|_| {
+                        panic!("Failed to load initial corpus at {:?}", &options.input)
+                    });
+                println!("We imported {} inputs from disk.", state.corpus().count());
+            }
+
+            let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+
+            fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)?;
+
+            Ok(())
+        };
+
+    Launcher::builder()
+        .configuration(EventConfig::AlwaysUnique)
+        .shmem_provider(shmem_provider)
+        .monitor(monitor)
+        .run_client(&mut run_client)
+        .cores(&options.cores)
+        .broker_port(options.broker_port)
+        .remote_broker_addr(options.remote_broker_addr)
+        .build()
+        .launch()
+}
@@ -0,0 +1,4 @@
+mod fuzzer;
+pub fn main() {
+    fuzzer::main();
+}
@@ -37,6 +37,8 @@ use libafl_bolts::{
     rands::StdRand,
     tuples::{tuple_list, Merge, Prepend},
 };
+#[cfg(feature = "injections")]
+use libafl_qemu::modules::injections::InjectionFeedback;
 use libafl_qemu::{
     elf::EasyElf,
     modules::{
@@ -151,7 +153,15 @@ impl<M: Monitor> Instance<'_, M> {
         );
 
         // A feedback to choose if an input is a solution or not
+        #[cfg(not(feature = "injections"))]
         let mut objective = feedback_or_fast!(CrashFeedback::new(), TimeoutFeedback::new());
+        // The injections found are reported as crashes, and attached to the solutions
+        #[cfg(feature = "injections")]
+        let mut objective = feedback_or_fast!(
+            InjectionFeedback::new(),
+            CrashFeedback::new(),
+            TimeoutFeedback::new()
+        );
 
         // // If not restarting, create a State from scratch
         let mut state = match state {
@@ -6,11 +6,9 @@ use libafl_bolts::core_affinity::Cores;
 use crate::Opt;
 
 pub fn parse_envs(opt: &mut Opt) -> Result<(), Error> {
-    if let Ok(res) = std::env::var("AFL_CORES") {
-        opt.cores = Some(Cores::from_cmdline(&res)?);
-    } else {
-        return Err(Error::illegal_argument("Missing AFL_CORES"));
-    }
+    // Without AFL_CORES, run a single client, like afl-fuzz
+    let cores = std::env::var("AFL_CORES").unwrap_or_else(|_| "0".to_string());
+    opt.cores = Some(Cores::from_cmdline(&cores)?);
     if let Ok(res) = std::env::var("AFL_INPUT_LEN_MAX") {
         opt.max_input_len = Some(res.parse()?);
     }
@@ -25,8 +23,6 @@ pub fn parse_envs(opt: &mut Opt) -> Result<(), Error> {
     }
     if let Ok(res) = std::env::var("AFL_HANG_TMOUT") {
         opt.hang_timeout = res.parse()?;
-    } else {
-        opt.hang_timeout = 100;
     }
     if let Ok(res) = std::env::var("AFL_DEBUG_CHILD") {
         opt.debug_child = parse_bool(&res)?;
@@ -114,6 +110,11 @@ pub fn parse_envs(opt: &mut Opt) -> Result<(), Error> {
     if let Ok(res) = std::env::var("AFL_USE_FASAN") {
         opt.frida_asan = parse_bool(&res)?;
     }
+    if opt.secondary_name.is_some() && !opt.foreign_sync_dirs.is_empty() {
+        return Err(Error::illegal_argument(
+            "-F requires -M, secondary fuzzers do not sync foreign fuzzers",
+        ));
+    }
     Ok(())
 }
 
@@ -566,6 +566,9 @@ fn base_forkserver_builder<'a>(
         .min_input_size(opt.min_input_len.unwrap_or(AFL_DEFAULT_INPUT_LEN_MIN))
         .max_input_size(opt.max_input_len.unwrap_or(AFL_DEFAULT_INPUT_LEN_MAX))
         .timeout(Duration::from_millis(opt.hang_timeout));
+    if let Some(memory_limit) = opt.memory_limit {
+        executor = executor.memlimit(memory_limit);
+    }
     if let Some(target_env) = &opt.target_env {
         executor = executor.envs(target_env);
     }
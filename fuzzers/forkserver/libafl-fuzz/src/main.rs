@@ -141,7 +141,7 @@ fn main() {
         .monitor(monitor)
         .main_run_client(|state: Option<_>, mgr: _, core_id: CoreId| {
             println!("run primary client on core {}", core_id.0);
-            let fuzzer_dir = opt.output_dir.join("fuzzer_main");
+            let fuzzer_dir = opt.fuzzer_dir(core_id, true);
             let _ = check_autoresume(&fuzzer_dir, opt.auto_resume).unwrap();
             let res = run_client(state, mgr, &fuzzer_dir, core_id, &opt, true);
             let _ = remove_main_node_file(&fuzzer_dir);
@@ -149,9 +149,7 @@ fn main() {
         })
         .secondary_run_client(|state: Option<_>, mgr: _, core_id: CoreId| {
             println!("run secondary client on core {}", core_id.0);
-            let fuzzer_dir = opt
-                .output_dir
-                .join(format!("fuzzer_secondary_{}", core_id.0));
+            let fuzzer_dir = opt.fuzzer_dir(core_id, false);
             let _ = check_autoresume(&fuzzer_dir, opt.auto_resume).unwrap();
             run_client(state, mgr, &fuzzer_dir, core_id, &opt, false)
         })
@@ -161,7 +159,7 @@ fn main() {
         .launch();
     #[cfg(feature = "fuzzbench")]
     let res = {
-        let fuzzer_dir = opt.output_dir.join("fuzzer_main");
+        let fuzzer_dir = opt.fuzzer_dir(CoreId(0), true);
         let _ = check_autoresume(&fuzzer_dir, opt.auto_resume).unwrap();
         let mgr = SimpleEventManager::new(monitor);
         let res = run_client(None, mgr, &fuzzer_dir, CoreId(0), &opt, true);
@@ -217,16 +215,22 @@ struct Opt {
     /// sequential queue selection instead of weighted random
     #[arg(short = 'Z')]
     sequential_queue: bool,
-    // TODO: enforce
-    #[arg(short = 'm')]
-    memory_limit: Option<usize>,
+    /// memory limit for the child process, in MB unless suffixed with k, M, G or T (none = no limit [default])
+    #[arg(short = 'm', value_parser = parse_memory_limit)]
+    memory_limit: Option<u64>,
     // TODO: enforce
     #[arg(short = 'V')]
     fuzz_for_seconds: Option<usize>,
 
-    /// timeout for each run
-    #[arg(short = 't', default_value_t = 1000)]
+    /// timeout for each run, in ms; a trailing `+` is accepted for compatibility and ignored
+    #[arg(short = 't', default_value = "1000", value_parser = parse_timeout)]
     hang_timeout: u64,
+    /// name of the main fuzzer directory in the output directory, the other clients append their core id
+    #[arg(short = 'M', conflicts_with = "secondary_name")]
+    main_name: Option<String>,
+    /// like -M, but the clients do not sync the foreign fuzzers given with -F
+    #[arg(short = 'S')]
+    secondary_name: Option<String>,
 
     // Environment Variables
     #[clap(skip)]
@@ -359,6 +363,51 @@ impl From<&str> for CmplogFileSize {
     }
 }
 
+impl Opt {
+    /// The directory of the client on `core_id` in the output directory, named like with afl-fuzz `-M`/`-S`
+    fn fuzzer_dir(&self, core_id: CoreId, is_main_node: bool) -> PathBuf {
+        let name = self.main_name.as_ref().or(self.secondary_name.as_ref());
+        let dir_name = match (name, is_main_node) {
+            (Some(name), true) => name.clone(),
+            (Some(name), false) => format!("{name}_{}", core_id.0),
+            (None, true) => "fuzzer_main".to_string(),
+            (None, false) => format!("fuzzer_secondary_{}", core_id.0),
+        };
+        self.output_dir.join(dir_name)
+    }
+}
+
+/// Parse `-m` like afl-fuzz: `none`, or a size in MB with an optional `k`, `M`, `G` or `T` suffix
+fn parse_memory_limit(s: &str) -> Result<u64, String> {
+    if s == "none" {
+        return Ok(0);
+    }
+    let (digits, scale): (&str, fn(u64) -> Option<u64>) = match s.chars().last() {
+        Some('k') => (&s[..s.len() - 1], |kb| Some(kb >> 10)),
+        Some('M') => (&s[..s.len() - 1], Some),
+        Some('G') => (&s[..s.len() - 1], |gb| gb.checked_mul(1 << 10)),
+        Some('T') => (&s[..s.len() - 1], |tb| tb.checked_mul(1 << 20)),
+        _ => (s, Some),
+    };
+    let (raw, limit) = digits
+        .parse()
+        .ok()
+        .and_then(|raw| Some((raw, scale(raw)?)))
+        .ok_or_else(|| format!("invalid memory limit {s}"))?;
+    if raw != 0 && limit < 5 {
+        return Err(format!("memory limit {s} is below 5 MB"));
+    }
+    Ok(limit)
+}
+
+/// Parse `-t` like afl-fuzz, which accepts a trailing `+` to skip timing out seeds
+fn parse_timeout(s: &str) -> Result<u64, String> {
+    s.strip_suffix('+')
+        .unwrap_or(s)
+        .parse()
+        .map_err(|_| format!("invalid timeout {s}"))
+}
+
 #[allow(clippy::unnecessary_wraps)] // we need to be compatible with Clap's value_parser
 fn parse_cmplog_args(s: &str) -> Result<CmplogOpts, String> {
     Ok(CmplogOpts {
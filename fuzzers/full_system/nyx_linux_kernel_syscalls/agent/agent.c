@@ -0,0 +1,255 @@
+// The agent running as `/init` in the guest: it executes the syscall programs of the fuzzer,
+// encoded by `libafl_kernel::agent`, and forwards the kernel messages of each program.
+//
+// Built statically against the `nyx.h` of the Nyx packer, see `setup_share_dir.sh`.
+
+#define _GNU_SOURCE
+
+#include <errno.h>
+#include <fcntl.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <sys/ioctl.h>
+#include <sys/mman.h>
+#include <sys/mount.h>
+#include <sys/syscall.h>
+#include <unistd.h>
+
+#include "nyx.h"
+
+// Keep in sync with `libafl_kernel::agent`
+#define PROGRAM_MAGIC 0x50534b4c  // "LKSP"
+#define ARG_INT 0
+#define ARG_BUFFER 1
+#define ARG_RESULT 2
+#define INVALID_SYSCALL UINT64_MAX
+#define INVALID_RESOURCE UINT64_MAX
+#define MAX_ARGS 6
+
+#define KCOV_PATH "/sys/kernel/debug/kcov"
+#define KCOV_INIT_TRACE _IOR('c', 1, unsigned long)
+#define KCOV_ENABLE _IO('c', 100)
+#define KCOV_DISABLE _IO('c', 101)
+#define KCOV_TRACE_PC 0
+#define KCOV_SIZE (1 << 20)
+
+// The payload buffer, filled by Nyx with the program before each run
+static kAFL_payload *payload;
+// The buffers of the program, copied with a terminating 0 byte for paths and names
+static uint8_t *arena;
+static size_t arena_size;
+// The coverage map shared with the fuzzer, if the agent traces with kcov
+static uint8_t *trace_buffer;
+static size_t trace_buffer_size;
+static int kcov_fd = -1;
+static unsigned long *kcov_area;
+// Reads the kernel messages
+static int kmsg_fd = -1;
+static char kmsg_record[4096];
+
+static void *map_pinned(size_t size) {
+  void *buf = mmap(NULL, size, PROT_READ | PROT_WRITE,
+                   MAP_PRIVATE | MAP_ANONYMOUS | MAP_POPULATE, -1, 0);
+  if (buf == MAP_FAILED) { habort("Failed to map a buffer of the agent"); }
+  // Nyx needs the buffers resident before the snapshot
+  memset(buf, 0, size);
+  mlock(buf, size);
+  return buf;
+}
+
+static void mount_filesystems(void) {
+  mount("proc", "/proc", "proc", 0, NULL);
+  mount("sysfs", "/sys", "sysfs", 0, NULL);
+  mount("debugfs", "/sys/kernel/debug", "debugfs", 0, NULL);
+  mount("devtmpfs", "/dev", "devtmpfs", 0, NULL);
+  mount("tmpfs", "/tmp", "tmpfs", 0, NULL);
+}
+
+// Nyx reports a crash if the kernel reaches `panic`, instead of waiting for the timeout
+static void submit_panic_handler(void) {
+  int fd = open("/proc/sys/kernel/kptr_restrict", O_WRONLY);
+  if (fd >= 0) {
+    write(fd, "0", 1);
+    close(fd);
+  }
+  FILE *kallsyms = fopen("/proc/kallsyms", "r");
+  if (!kallsyms) { return; }
+  char line[256];
+  while (fgets(line, sizeof(line), kallsyms)) {
+    unsigned long long addr;
+    char type;
+    char name[128];
+    if (sscanf(line, "%llx %c %127s", &addr, &type, name) == 3 &&
+        !strcmp(name, "panic")) {
+      kAFL_hypercall(HYPERCALL_KAFL_SUBMIT_PANIC, addr);
+      break;
+    }
+  }
+  fclose(kallsyms);
+}
+
+// Uses kcov for the coverage, if the kernel has it, otherwise Nyx traces the VM with Intel PT
+static int open_kcov(void) {
+  kcov_fd = open(KCOV_PATH, O_RDWR);
+  if (kcov_fd < 0) { return 0; }
+  if (ioctl(kcov_fd, KCOV_INIT_TRACE, KCOV_SIZE)) { habort("Failed to initialize kcov"); }
+  kcov_area = mmap(NULL, KCOV_SIZE * sizeof(unsigned long), PROT_READ | PROT_WRITE,
+                   MAP_SHARED, kcov_fd, 0);
+  if (kcov_area == MAP_FAILED) { habort("Failed to map the kcov buffer"); }
+  return 1;
+}
+
+// The same as `libafl_kernel::kcov::kcov_edges_into`
+static uint64_t mix(uint64_t pc) {
+  return ((pc ^ (pc >> 33)) * 0xff51afd7ed558ccdULL) >> 16;
+}
+
+static void kcov_edges_into_trace_buffer(void) {
+  uint64_t len = __atomic_load_n(&kcov_area[0], __ATOMIC_RELAXED);
+  if (len > KCOV_SIZE - 1) { len = KCOV_SIZE - 1; }
+  uint64_t prev = 0;
+  for (uint64_t i = 0; i < len; i++) {
+    uint64_t cur = mix(kcov_area[i + 1]);
+    uint64_t idx = (cur ^ prev) % trace_buffer_size;
+    if (trace_buffer[idx] != 0xff) { trace_buffer[idx]++; }
+    prev = cur >> 1;
+  }
+}
+
+// Forwards the kernel messages since the last call, one record per line
+static void forward_kmsg(void) {
+  for (;;) {
+    ssize_t len = read(kmsg_fd, kmsg_record, sizeof(kmsg_record) - 1);
+    if (len < 0) {
+      // EPIPE: the ring buffer overwrote records, the next read continues after them
+      if (errno == EPIPE) { continue; }
+      break;
+    }
+    kmsg_record[len] = 0;
+    hprintf("%s", kmsg_record);
+  }
+}
+
+struct reader {
+  const uint8_t *pos;
+  const uint8_t *end;
+};
+
+static int read_bytes(struct reader *reader, void *out, size_t len) {
+  if ((size_t)(reader->end - reader->pos) < len) { return 0; }
+  memcpy(out, reader->pos, len);
+  reader->pos += len;
+  return 1;
+}
+
+// Runs the calls of the program, and stops at the first malformed one
+static void run_program(const uint8_t *program, size_t size) {
+  struct reader reader = {program, program + size};
+  uint32_t magic, call_count;
+  if (!read_bytes(&reader, &magic, 4) || magic != PROGRAM_MAGIC ||
+      !read_bytes(&reader, &call_count, 4)) {
+    return;
+  }
+  uint64_t *results = (uint64_t *)arena;
+  if ((size_t)call_count * sizeof(uint64_t) > arena_size) { return; }
+  uint8_t *buffers = arena + (size_t)call_count * sizeof(uint64_t);
+  uint8_t *buffers_end = arena + arena_size;
+
+  for (uint32_t idx = 0; idx < call_count; idx++) {
+    uint64_t nr;
+    uint32_t arg_count;
+    uint64_t args[MAX_ARGS] = {0};
+    if (!read_bytes(&reader, &nr, 8) || !read_bytes(&reader, &arg_count, 4)) { return; }
+    for (uint32_t arg = 0; arg < arg_count; arg++) {
+      uint8_t tag;
+      uint64_t value;
+      uint32_t len;
+      if (!read_bytes(&reader, &tag, 1)) { return; }
+      switch (tag) {
+        case ARG_INT:
+          if (!read_bytes(&reader, &value, 8)) { return; }
+          break;
+        case ARG_BUFFER:
+          if (!read_bytes(&reader, &len, 4) || (size_t)(reader.end - reader.pos) < len ||
+              (size_t)(buffers_end - buffers) < (size_t)len + 8) {
+            return;
+          }
+          memcpy(buffers, reader.pos, len);
+          buffers[len] = 0;
+          reader.pos += len;
+          value = (uint64_t)(uintptr_t)buffers;
+          buffers += (len + 8) & ~7u;
+          break;
+        case ARG_RESULT:
+          if (!read_bytes(&reader, &len, 4) || len >= idx) { return; }
+          value = results[len];
+          break;
+        default:
+          return;
+      }
+      if (arg < MAX_ARGS) { args[arg] = value; }
+    }
+    if (nr == INVALID_SYSCALL) {
+      results[idx] = INVALID_RESOURCE;
+      continue;
+    }
+    // A failing call returns -1, the same as `INVALID_RESOURCE`
+    results[idx] = (uint64_t)syscall(nr, args[0], args[1], args[2], args[3], args[4], args[5]);
+  }
+}
+
+int main(void) {
+  mount_filesystems();
+
+  host_config_t host_config;
+  kAFL_hypercall(HYPERCALL_KAFL_GET_HOST_CONFIG, (uintptr_t)&host_config);
+  if (host_config.host_magic != NYX_HOST_MAGIC || host_config.host_version != NYX_HOST_VERSION) {
+    habort("The agent does not match the Nyx version of the host");
+  }
+
+  payload = map_pinned(host_config.payload_buffer_size);
+  arena_size = 2 * host_config.payload_buffer_size;
+  arena = map_pinned(arena_size);
+
+  agent_config_t agent_config = {0};
+  agent_config.agent_magic = NYX_AGENT_MAGIC;
+  agent_config.agent_version = NYX_AGENT_VERSION;
+  if (open_kcov()) {
+    trace_buffer_size = host_config.bitmap_size;
+    trace_buffer = map_pinned(trace_buffer_size);
+    agent_config.agent_tracing = 1;
+    agent_config.trace_buffer_vaddr = (uintptr_t)trace_buffer;
+    agent_config.coverage_bitmap_size = host_config.bitmap_size;
+  }
+  kAFL_hypercall(HYPERCALL_KAFL_SET_AGENT_CONFIG, (uintptr_t)&agent_config);
+
+  submit_panic_handler();
+
+  // Only the messages of the programs count, not the ones of the boot
+  kmsg_fd = open("/dev/kmsg", O_RDONLY | O_NONBLOCK);
+  lseek(kmsg_fd, 0, SEEK_END);
+
+  kAFL_hypercall(HYPERCALL_KAFL_GET_PAYLOAD, (uintptr_t)payload);
+  kAFL_hypercall(HYPERCALL_KAFL_SUBMIT_CR3, 0);
+
+  // Nyx takes the snapshot at the first `NEXT_PAYLOAD`, and restores it at each `RELEASE`,
+  // so every program runs from here, on the same kernel.
+  kAFL_hypercall(HYPERCALL_KAFL_NEXT_PAYLOAD, 0);
+  kAFL_hypercall(HYPERCALL_KAFL_ACQUIRE, 0);
+
+  if (kcov_fd >= 0) {
+    __atomic_store_n(&kcov_area[0], 0, __ATOMIC_RELAXED);
+    ioctl(kcov_fd, KCOV_ENABLE, KCOV_TRACE_PC);
+  }
+  run_program(payload->data, (size_t)payload->size);
+  if (kcov_fd >= 0) {
+    ioctl(kcov_fd, KCOV_DISABLE, 0);
+    kcov_edges_into_trace_buffer();
+  }
+  forward_kmsg();
+
+  kAFL_hypercall(HYPERCALL_KAFL_RELEASE, 0);
+  return 0;
+}
@@ -0,0 +1,22 @@
+#!/bin/bash
+# Builds the agent into an initramfs, and writes the Nyx share directory booting it with the kernel at $KERNEL
+
+KERNEL=${KERNEL:?"Set KERNEL to the bzImage of a kernel built with CONFIG_KCOV, CONFIG_KASAN and CONFIG_DEBUG_FS"}
+SHARE_DIR=/tmp/nyx_linux_kernel
+PACKER=../../../libafl_nyx/packer/packer
+
+# The packer is checked out by the build script of libafl_nyx
+if [ ! -e "$PACKER/nyx.h" ]; then
+    cargo build --release || exit
+fi
+
+mkdir -p build/initramfs/dev build/initramfs/proc build/initramfs/sys build/initramfs/tmp || exit
+gcc -static -O2 -I "$PACKER" -o build/initramfs/init agent/agent.c || exit
+(cd build/initramfs && find . | cpio -o -H newc | gzip) > build/initramfs.cpio.gz || exit
+
+mkdir -p "$SHARE_DIR" || exit
+python3 "$PACKER/nyx_config_gen.py" "$SHARE_DIR" Kernel || exit
+sed -i \
+    -e "s#kernel: \".*\"#kernel: \"$(realpath "$KERNEL")\"#" \
+    -e "s#ramfs: \".*\"#ramfs: \"$(realpath build/initramfs.cpio.gz)\"#" \
+    "$SHARE_DIR/config.ron" || exit
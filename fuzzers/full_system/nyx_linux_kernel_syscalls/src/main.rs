@@ -0,0 +1,313 @@
+//! Fuzzes the syscalls of a Linux kernel, booted with the agent in Nyx VMs on all cores.
+//!
+//! See the README for how to build the kernel and the share directory.
+use std::{path::PathBuf, time::Duration};
+
+use libafl::{
+    corpus::{CachedOnDiskCorpus, Corpus, OnDiskCorpus},
+    events::{launcher::Launcher, EventConfig},
+    feedback_or_fast,
+    feedbacks::{CrashFeedback, MaxMapFeedback},
+    generators::SyscallSequenceGenerator,
+    inputs::{SyscallArgType, SyscallDescription, SyscallTable},
+    monitors::MultiMonitor,
+    mutators::{
+        StdScheduledMutator, SyscallInsertMutator, SyscallMutateArgMutator, SyscallSpliceMutator,
+    },
+    observers::StdOutObserver,
+    schedulers::RandScheduler,
+    stages::StdMutationalStage,
+    state::{HasCorpus, StdState},
+    Error, Fuzzer, StdFuzzer,
+};
+use libafl_bolts::{
+    core_affinity::{CoreId, Cores},
+    nonzero,
+    rands::StdRand,
+    shmem::{ShMemProvider, StdShMemProvider},
+    tuples::{tuple_list, Handled},
+};
+use libafl_kernel::{KernelOopsFeedback, KernelVm};
+
+/// The Nyx share directory written by `setup_share_dir.sh`
+const SHARE_DIR: &str = "/tmp/nyx_linux_kernel";
+
+/// The time a program may run, before Nyx restores the VM
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The most calls in a generated program
+const MAX_CALLS: usize = 16;
+
+const O_WRONLY: u64 = 0o1;
+const O_RDWR: u64 = 0o2;
+const O_CREAT: u64 = 0o100;
+const O_TRUNC: u64 = 0o1000;
+const O_APPEND: u64 = 0o2000;
+const O_NONBLOCK: u64 = 0o4000;
+const O_CLOEXEC: u64 = 0o2000000;
+
+const SOCK_STREAM: u64 = 1;
+const SOCK_DGRAM: u64 = 2;
+const SOCK_RAW: u64 = 3;
+const SOCK_SEQPACKET: u64 = 5;
+
+const MSG_OOB: u64 = 0x1;
+const MSG_PEEK: u64 = 0x2;
+const MSG_DONTWAIT: u64 = 0x40;
+const MSG_MORE: u64 = 0x8000;
+
+/// The `x86_64` syscalls of files, sockets and event descriptors, and the types of their arguments
+#[allow(clippy::too_many_lines)]
+fn syscall_table() -> SyscallTable {
+    let fd = || SyscallArgType::Resource { kind: "fd".into() };
+    let len = |max: u64| SyscallArgType::Range { min: 0, max };
+    let fd_flags = || SyscallArgType::Flags(vec![O_NONBLOCK, O_CLOEXEC]);
+    SyscallTable::new(vec![
+        SyscallDescription::new(
+            "open",
+            2,
+            vec![
+                SyscallArgType::Buffer { max_len: 32 },
+                SyscallArgType::Flags(vec![
+                    O_WRONLY, O_RDWR, O_CREAT, O_TRUNC, O_APPEND, O_NONBLOCK,
+                ]),
+                SyscallArgType::Range { min: 0, max: 0o777 },
+            ],
+            Some("fd"),
+        ),
+        SyscallDescription::new(
+            "memfd_create",
+            319,
+            vec![
+                SyscallArgType::Buffer { max_len: 16 },
+                SyscallArgType::Flags(vec![0x1, 0x2]),
+            ],
+            Some("fd"),
+        ),
+        SyscallDescription::new(
+            "eventfd2",
+            290,
+            vec![SyscallArgType::Int { bits: 32 }, fd_flags()],
+            Some("fd"),
+        ),
+        SyscallDescription::new(
+            "timerfd_create",
+            283,
+            vec![SyscallArgType::Range { min: 0, max: 11 }, fd_flags()],
+            Some("fd"),
+        ),
+        SyscallDescription::new("epoll_create1", 291, vec![fd_flags()], Some("fd")),
+        SyscallDescription::new(
+            "epoll_ctl",
+            233,
+            vec![
+                fd(),
+                SyscallArgType::Range { min: 1, max: 3 },
+                fd(),
+                SyscallArgType::Buffer { max_len: 12 },
+            ],
+            None,
+        ),
+        SyscallDescription::new(
+            "socket",
+            41,
+            vec![
+                SyscallArgType::Range { min: 0, max: 45 },
+                SyscallArgType::Flags(vec![
+                    SOCK_STREAM,
+                    SOCK_DGRAM,
+                    SOCK_RAW,
+                    SOCK_SEQPACKET,
+                    O_NONBLOCK,
+                    O_CLOEXEC,
+                ]),
+                SyscallArgType::Int { bits: 8 },
+            ],
+            Some("fd"),
+        ),
+        SyscallDescription::new(
+            "bind",
+            49,
+            vec![fd(), SyscallArgType::Buffer { max_len: 128 }, len(128)],
+            None,
+        ),
+        SyscallDescription::new(
+            "connect",
+            42,
+            vec![fd(), SyscallArgType::Buffer { max_len: 128 }, len(128)],
+            None,
+        ),
+        SyscallDescription::new(
+            "listen",
+            50,
+            vec![fd(), SyscallArgType::Int { bits: 8 }],
+            None,
+        ),
+        SyscallDescription::new(
+            "setsockopt",
+            54,
+            vec![
+                fd(),
+                SyscallArgType::Range { min: 0, max: 300 },
+                SyscallArgType::Range { min: 0, max: 100 },
+                SyscallArgType::Buffer { max_len: 64 },
+                len(64),
+            ],
+            None,
+        ),
+        SyscallDescription::new(
+            "sendto",
+            44,
+            vec![
+                fd(),
+                SyscallArgType::Buffer { max_len: 256 },
+                len(256),
+                SyscallArgType::Flags(vec![MSG_OOB, MSG_DONTWAIT, MSG_MORE]),
+            ],
+            None,
+        ),
+        SyscallDescription::new(
+            "recvfrom",
+            45,
+            vec![
+                fd(),
+                SyscallArgType::Buffer { max_len: 256 },
+                len(256),
+                SyscallArgType::Flags(vec![MSG_OOB, MSG_PEEK, MSG_DONTWAIT]),
+            ],
+            None,
+        ),
+        SyscallDescription::new(
+            "read",
+            0,
+            vec![fd(), SyscallArgType::Buffer { max_len: 256 }, len(256)],
+            None,
+        ),
+        SyscallDescription::new(
+            "write",
+            1,
+            vec![fd(), SyscallArgType::Buffer { max_len: 256 }, len(256)],
+            None,
+        ),
+        SyscallDescription::new(
+            "lseek",
+            8,
+            vec![fd(), SyscallArgType::Int { bits: 32 }, len(4)],
+            None,
+        ),
+        SyscallDescription::new("ftruncate", 77, vec![fd(), len(0x10000)], None),
+        SyscallDescription::new(
+            "ioctl",
+            16,
+            vec![
+                fd(),
+                SyscallArgType::Int { bits: 32 },
+                SyscallArgType::Buffer { max_len: 64 },
+            ],
+            None,
+        ),
+        SyscallDescription::new(
+            "fcntl",
+            72,
+            vec![fd(), len(1040), SyscallArgType::Int { bits: 32 }],
+            None,
+        ),
+        SyscallDescription::new("dup", 32, vec![fd()], Some("fd")),
+        SyscallDescription::new("close", 3, vec![fd()], None),
+    ])
+}
+
+fn main() {
+    let table = syscall_table();
+
+    let shmem_provider = StdShMemProvider::new().expect("Failed to init shared memory");
+    let broker_port = 7777;
+
+    let monitor = MultiMonitor::new(|s| println!("{s}"));
+
+    let cores = Cores::all().expect("unable to get all core id");
+    // The VM on the first core boots the kernel and takes the snapshot, the others start from it
+    let snapshot_core = *cores.ids.first().expect("unable to get first core id");
+
+    let mut run_client = |state: Option<_>, mut restarting_mgr, core_id: CoreId| {
+        let vm = KernelVm::launch(SHARE_DIR, core_id, Some(snapshot_core), TIMEOUT)?;
+
+        // The agent fills the coverage map from kcov, or Nyx from Intel PT
+        let coverage = unsafe { vm.coverage_observer("coverage") };
+        // The kernel messages the agent forwards after each program
+        let console = StdOutObserver::new("console");
+
+        let mut feedback = MaxMapFeedback::new(&coverage);
+
+        // A crash of the VM, or an oops in the kernel messages, each title only once
+        let mut objective = feedback_or_fast!(
+            CrashFeedback::new(),
+            KernelOopsFeedback::new(&console).unique()
+        );
+
+        // If not restarting, create a State from scratch
+        let mut state = state.unwrap_or_else(|| {
+            StdState::new(
+                StdRand::new(),
+                CachedOnDiskCorpus::new(PathBuf::from("./corpus_discovered"), 64).unwrap(),
+                OnDiskCorpus::new(PathBuf::from("./crashes")).unwrap(),
+                &mut feedback,
+                &mut objective,
+            )
+            .unwrap()
+        });
+
+        let scheduler = RandScheduler::new();
+        let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+        let console_handle = console.handle();
+        let mut executor = vm.executor(
+            table.clone(),
+            console_handle,
+            tuple_list!(coverage, console),
+        );
+
+        // Generator of programs of up to `MAX_CALLS` calls
+        let mut generator = SyscallSequenceGenerator::new(&table, nonzero!(MAX_CALLS));
+
+        if state.corpus().count() == 0 {
+            state.generate_initial_inputs_forced(
+                &mut fuzzer,
+                &mut executor,
+                &mut generator,
+                &mut restarting_mgr,
+                16,
+            )?;
+        }
+
+        println!("We're a client, let's fuzz :)");
+        let mutator = StdScheduledMutator::with_max_stack_pow(
+            tuple_list!(
+                SyscallInsertMutator::new(&generator),
+                SyscallMutateArgMutator::new(&generator),
+                SyscallMutateArgMutator::new(&generator),
+                SyscallSpliceMutator::new(&generator)
+            ),
+            2,
+        );
+        let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+
+        fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut restarting_mgr)?;
+        Ok(())
+    };
+
+    match Launcher::builder()
+        .shmem_provider(shmem_provider)
+        .configuration(EventConfig::from_name("default"))
+        .monitor(monitor)
+        .run_client(&mut run_client)
+        .cores(&cores)
+        .broker_port(broker_port)
+        .build()
+        .launch()
+    {
+        Ok(()) => (),
+        Err(Error::ShuttingDown) => println!("Fuzzing stopped by user. Good bye."),
+        Err(err) => panic!("Failed to run launcher: {err:?}"),
+    }
+}
@@ -0,0 +1,6 @@
+/* Memory layout of the ARM MPS2 AN385 board */
+MEMORY
+{
+  FLASH : ORIGIN = 0x00000000, LENGTH = 4M
+  RAM : ORIGIN = 0x20000000, LENGTH = 4M
+}
@@ -0,0 +1,253 @@
+//! A bare-metal fuzzer for a Cortex-M board, without an operating system and with a fixed memory budget.
+//!
+//! It fuzzes a small record parser on the board itself, keeps its corpus in a [`FixedCorpus`],
+//! and streams its events over the UART with a [`RingEventManager`].
+#![no_std]
+#![no_main]
+
+use core::{
+    panic::PanicInfo,
+    ptr::{read_volatile, write, write_volatile},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use cortex_m::peripheral::syst::SystClkSource;
+use cortex_m_rt::{entry, exception};
+use cortex_m_semihosting::{debug, hprintln};
+use libafl::{
+    corpus::FixedCorpus,
+    events::{EventTransport, RingEventManager},
+    executors::{inprocess::InProcessExecutor, ExitKind},
+    feedbacks::{CrashFeedback, MaxMapFeedback},
+    fuzzer::{Fuzzer, StdFuzzer},
+    generators::RandBytesGenerator,
+    inputs::{BytesInput, HasTargetBytes},
+    mutators::{havoc_mutations::havoc_mutations, scheduled::StdScheduledMutator},
+    observers::StdMapObserver,
+    schedulers::QueueScheduler,
+    stages::mutational::StdMutationalStage,
+    state::StdState,
+    Error,
+};
+use libafl_bolts::{nonzero, rands::StdRand, tuples::tuple_list, AsSlice};
+use static_alloc::Bump;
+
+/// The heap for the few allocations left, like the inputs and the metadata of the state
+#[global_allocator]
+static A: Bump<[u8; 1024 * 1024]> = Bump::uninit();
+
+/// The frequency of the core clock of the `mps2-an385` board
+const CORE_CLOCK_HZ: u32 = 25_000_000;
+
+/// The base address of the first UART of the `mps2-an385` board
+const UART0_BASE: usize = 0x4000_4000;
+
+/// The size of the ring buffer for the outgoing events, in bytes
+const EVENT_RING_SIZE: usize = 2048;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    // A panic in the parser is the crash we are looking for
+    hprintln!("Crash found: {}", info);
+    debug::exit(debug::EXIT_FAILURE);
+    loop {
+        // On hardware, there's not much left to do.
+    }
+}
+
+/// Coverage map with explicit assignments due to the lack of instrumentation
+static mut SIGNALS: [u8; 16] = [0; 16];
+static mut SIGNALS_PTR: *mut u8 = unsafe { SIGNALS.as_mut_ptr() };
+
+/// Assign a signal to the signals map
+fn signals_set(idx: usize) {
+    unsafe { write(SIGNALS_PTR.add(idx), 1) };
+}
+
+/// Milliseconds since boot, counted by the `SysTick` exception
+static MILLIS: AtomicU32 = AtomicU32::new(0);
+
+#[exception]
+fn SysTick() {
+    MILLIS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Provide custom time in `no_std` environment
+#[no_mangle]
+pub extern "C" fn external_current_millis() -> u64 {
+    u64::from(MILLIS.load(Ordering::Relaxed))
+}
+
+/// The transmit side of a CMSDK APB UART
+struct Uart {
+    base: usize,
+}
+
+impl Uart {
+    const DATA: usize = 0x0;
+    const STATE: usize = 0x4;
+    const CTRL: usize = 0x8;
+    const BAUDDIV: usize = 0x10;
+    const STATE_TX_FULL: u32 = 1;
+    const CTRL_TX_ENABLE: u32 = 1;
+
+    /// Enables the transmitter of the UART at `base`, at 115200 baud
+    ///
+    /// # Safety
+    /// `base` has to be the address of a CMSDK APB UART, not used by anything else.
+    unsafe fn new(base: usize) -> Self {
+        write_volatile((base + Self::BAUDDIV) as *mut u32, CORE_CLOCK_HZ / 115_200);
+        write_volatile((base + Self::CTRL) as *mut u32, Self::CTRL_TX_ENABLE);
+        Self { base }
+    }
+
+    fn tx_full(&self) -> bool {
+        unsafe { read_volatile((self.base + Self::STATE) as *const u32) & Self::STATE_TX_FULL != 0 }
+    }
+}
+
+impl EventTransport for Uart {
+    /// Fills the transmit buffer of the UART, without waiting for it to drain
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let mut written = 0;
+        for byte in buf {
+            if self.tx_full() {
+                break;
+            }
+            unsafe { write_volatile((self.base + Self::DATA) as *mut u32, u32::from(*byte)) };
+            written += 1;
+        }
+        Ok(written)
+    }
+}
+
+/// Parses a sequence of records, each a tag byte, a length byte and the value, and returns their number.
+///
+/// The bug: a list record following a number record trusts its length, and indexes past the end of the input.
+fn parse_records(buf: &[u8]) -> usize {
+    let mut records = 0;
+    let mut pos = 0;
+    let mut after_number = false;
+    while pos + 2 <= buf.len() {
+        let (tag, len) = (buf[pos], usize::from(buf[pos + 1]));
+        pos += 2;
+        match tag {
+            b'T' if len <= buf.len() - pos => {
+                // A text record
+                signals_set(1);
+                after_number = false;
+            }
+            b'N' if len == 4 && len <= buf.len() - pos => {
+                // A 32 bit number record
+                signals_set(2);
+                after_number = true;
+            }
+            b'L' if after_number => {
+                // A list of 2 byte entries, as many as the number before it says
+                signals_set(3);
+                if len > 0 {
+                    signals_set(4);
+                    let last = &buf[pos + 2 * (len - 1)..pos + 2 * len];
+                    if last == b"\xff\xff" {
+                        signals_set(5);
+                    }
+                }
+                pos += 2 * len;
+                records += 1;
+                continue;
+            }
+            _ => break,
+        }
+        pos += len;
+        records += 1;
+        signals_set(5 + records.min(10));
+    }
+    records
+}
+
+#[entry]
+fn main() -> ! {
+    // Count the milliseconds, for the stats in the events
+    let mut core = cortex_m::Peripherals::take().unwrap();
+    core.SYST.set_clock_source(SystClkSource::Core);
+    core.SYST.set_reload(CORE_CLOCK_HZ / 1000 - 1);
+    core.SYST.clear_current();
+    core.SYST.enable_counter();
+    core.SYST.enable_interrupt();
+
+    hprintln!("Fuzzing the record parser, events go out on UART0");
+
+    // The closure that we want to fuzz
+    let mut harness = |input: &BytesInput| {
+        let target = input.target_bytes();
+        signals_set(0);
+        parse_records(target.as_slice());
+        ExitKind::Ok
+    };
+
+    // Create an observation channel using the signals map
+    let observer = unsafe { StdMapObserver::from_mut_ptr("signals", SIGNALS_PTR, SIGNALS.len()) };
+
+    // Feedback to rate the interestingness of an input
+    let mut feedback = MaxMapFeedback::new(&observer);
+
+    // A feedback to choose if an input is a solution or not
+    let mut objective = CrashFeedback::new();
+
+    // create a State from scratch
+    let mut state = StdState::new(
+        // RNG
+        StdRand::with_seed(external_current_millis()),
+        // Corpus that will be evolved, in slots allocated up front.
+        // With a map of 16 signals, the feedback keeps far fewer inputs.
+        FixedCorpus::<_, 32>::new(),
+        // Corpus in which we store solutions
+        FixedCorpus::<_, 4>::new(),
+        // States of the feedbacks.
+        // The feedbacks can report the data that should persist in the State.
+        &mut feedback,
+        // Same for objective feedbacks
+        &mut objective,
+    )
+    .unwrap();
+
+    // The event manager serializes the events into a ring buffer, and drains it into the UART.
+    // The host splits the received bytes at each 0 byte, and decodes them with `libafl::events::decode_event`.
+    let uart = unsafe { Uart::new(UART0_BASE) };
+    let mut mgr = RingEventManager::<_, _, EVENT_RING_SIZE>::new(uart);
+
+    // A queue policy to get testcasess from the corpus
+    let scheduler = QueueScheduler::new();
+
+    // A fuzzer with feedbacks and a corpus scheduler
+    let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+    // Create the executor for an in-process function with just one observer
+    let mut executor = InProcessExecutor::new(
+        &mut harness,
+        tuple_list!(observer),
+        &mut fuzzer,
+        &mut state,
+        &mut mgr,
+    )
+    .expect("Failed to create the Executor");
+
+    // Generator of random bytearrays of max size 16
+    let mut generator = RandBytesGenerator::new(nonzero!(16));
+
+    // Generate 8 initial inputs
+    state
+        .generate_initial_inputs(&mut fuzzer, &mut executor, &mut generator, &mut mgr, 8)
+        .expect("Failed to generate the initial corpus");
+
+    // Setup a mutational stage with a basic bytes mutator
+    let mutator = StdScheduledMutator::new(havoc_mutations());
+    let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+
+    fuzzer
+        .fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)
+        .expect("Error in the fuzzing loop");
+
+    debug::exit(debug::EXIT_SUCCESS);
+    loop {}
+}
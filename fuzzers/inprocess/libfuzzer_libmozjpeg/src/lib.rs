@@ -27,6 +27,7 @@ use libafl::{
     Error, HasMetadata,
 };
 use libafl_bolts::{
+    compress::CompressionCodec,
     rands::StdRand,
     tuples::{tuple_list, Merge},
     AsSlice,
@@ -65,17 +66,21 @@ fn fuzz(corpus_dirs: &[PathBuf], objective_dir: PathBuf, broker_port: u16) -> Re
     let monitor = SimpleMonitor::new(|s| println!("{s}"));
 
     // The restarting state will spawn the same process again as child, then restarted it each time it crashes.
-    let (state, mut restarting_mgr) =
-        match setup_restarting_mgr_std(monitor, broker_port, EventConfig::from_name("default")) {
-            Ok(tuple) => tuple,
-            Err(Error::ShuttingDown) => {
-                println!("\nFinished fuzzing. Good bye.");
-                return Ok(());
-            }
-            Err(err) => {
-                panic!("Failed to setup the restarter: {err:?}");
-            }
-        };
+    let (state, mut restarting_mgr) = match setup_restarting_mgr_std(
+        monitor,
+        broker_port,
+        EventConfig::from_name("default"),
+        CompressionCodec::default(),
+    ) {
+        Ok(tuple) => tuple,
+        Err(Error::ShuttingDown) => {
+            println!("\nFinished fuzzing. Good bye.");
+            return Ok(());
+        }
+        Err(err) => {
+            panic!("Failed to setup the restarter: {err:?}");
+        }
+    };
 
     // Create an observation channel using the coverage map
     let edges_observer = unsafe { std_edges_map_observer("edges") };
@@ -28,6 +28,7 @@ use libafl::{
     Error, HasMetadata,
 };
 use libafl_bolts::{
+    compress::CompressionCodec,
     rands::StdRand,
     tuples::{tuple_list, Merge},
     AsSlice,
@@ -65,18 +66,22 @@ fn fuzz(corpus_dirs: &[PathBuf], objective_dir: PathBuf, broker_port: u16) -> Re
     let monitor = MultiMonitor::new(|s| println!("{s}"));
 
     // The restarting state will spawn the same process again as child, then restarted it each time it crashes.
-    let (state, mut restarting_mgr) =
-        match setup_restarting_mgr_std(monitor, broker_port, EventConfig::AlwaysUnique) {
-            Ok(res) => res,
-            Err(err) => match err {
-                Error::ShuttingDown => {
-                    return Ok(());
-                }
-                _ => {
-                    panic!("Failed to setup the restarter: {err}");
-                }
-            },
-        };
+    let (state, mut restarting_mgr) = match setup_restarting_mgr_std(
+        monitor,
+        broker_port,
+        EventConfig::AlwaysUnique,
+        CompressionCodec::default(),
+    ) {
+        Ok(res) => res,
+        Err(err) => match err {
+            Error::ShuttingDown => {
+                return Ok(());
+            }
+            _ => {
+                panic!("Failed to setup the restarter: {err}");
+            }
+        },
+    };
 
     // Create an observation channel using the coverage map
     let edges_observer = unsafe {
@@ -4,11 +4,9 @@ mod input;
 use std::ptr::write_volatile;
 use std::{path::PathBuf, ptr::write};
 
-use input::{
-    CustomInput, CustomInputGenerator, ToggleBooleanMutator, ToggleOptionalByteArrayMutator,
-};
+use input::{CustomInput, CustomInputGenerator};
 #[cfg(feature = "simple_interface")]
-use libafl::mutators::havoc_mutations::{mapped_havoc_mutations, optional_mapped_havoc_mutations};
+use libafl::mutators::MappedMutations;
 use libafl::{
     corpus::{InMemoryCorpus, OnDiskCorpus},
     events::SimpleEventManager,
@@ -22,18 +20,18 @@ use libafl::{
     stages::mutational::StdMutationalStage,
     state::StdState,
 };
-use libafl_bolts::{
-    current_nanos, nonzero,
-    rands::StdRand,
-    tuples::{tuple_list, Merge, Prepend},
-};
+use libafl_bolts::{current_nanos, nonzero, rands::StdRand, tuples::tuple_list};
 #[cfg(not(feature = "simple_interface"))]
 use {
     libafl::mutators::{
         havoc_mutations::{havoc_crossover_with_corpus_mapper, havoc_mutations_no_crossover},
-        mapping::{ToMappedInputFunctionMappingMutatorMapper, ToOptionMappingMutatorMapper},
+        mapped_mutations::{ToggleBoolMutator, ToggleOptionalBytesMutator},
+        mapping::{
+            FunctionMappingMutator, ToMappedInputFunctionMappingMutatorMapper,
+            ToOptionMappingMutatorMapper,
+        },
     },
-    libafl_bolts::tuples::Map,
+    libafl_bolts::tuples::{Map, Merge, Prepend},
 };
 
 /// Coverage map with explicit assignments due to the lack of instrumentation
@@ -49,6 +47,18 @@ fn signals_set(idx: usize) {
     unsafe { write(SIGNALS_PTR.add(idx), 1) };
 }
 
+/// Maps the custom input to its optional byte array, for the toggle mutator
+#[cfg(not(feature = "simple_interface"))]
+fn optional_byte_array_field(input: &mut CustomInput) -> &mut Option<Vec<u8>> {
+    &mut input.optional_byte_array
+}
+
+/// Maps the custom input to its boolean, for the toggle mutator
+#[cfg(not(feature = "simple_interface"))]
+fn boolean_field(input: &mut CustomInput) -> &mut bool {
+    &mut input.boolean
+}
+
 #[allow(clippy::similar_names, clippy::manual_assert)]
 pub fn main() {
     // The closure that we want to fuzz
@@ -135,22 +145,13 @@ pub fn main() {
         .generate_initial_inputs(&mut fuzzer, &mut executor, &mut generator, &mut mgr, 8)
         .expect("Failed to generate the initial corpus");
 
+    // The mutations of all the parts of the custom input, generated by `#[derive(MappedMutations)]`
     #[cfg(feature = "simple_interface")]
-    let (mapped_mutators, optional_mapped_mutators) = {
-        // Creating mutators that will operate on input.byte_array
-        let mapped_mutators =
-            mapped_havoc_mutations(CustomInput::byte_array_mut, CustomInput::byte_array);
-
-        // Creating mutators that will operate on input.optional_byte_array
-        let optional_mapped_mutators = optional_mapped_havoc_mutations(
-            CustomInput::optional_byte_array_mut,
-            CustomInput::optional_byte_array,
-        );
-        (mapped_mutators, optional_mapped_mutators)
-    };
+    let mutators = CustomInput::mapped_mutations();
 
+    // The same mutations, mapped to the parts by hand
     #[cfg(not(feature = "simple_interface"))]
-    let (mapped_mutators, optional_mapped_mutators) = {
+    let mutators = {
         // Creating mutators that will operate on input.byte_array
         let mapped_mutators = havoc_mutations_no_crossover()
             .merge(havoc_crossover_with_corpus_mapper(CustomInput::byte_array))
@@ -168,21 +169,25 @@ pub fn main() {
                 CustomInput::optional_byte_array_mut,
             ));
 
-        (mapped_mutators, optional_mapped_mutators)
+        // Merging multiple lists of mutators that mutate a sub-part of the custom input
+        // This collection could be expanded with default or custom mutators as needed for the input
+        tuple_list!()
+            // First, mutators for the simple byte array
+            .merge(mapped_mutators)
+            // Then, mutators for the optional byte array, these return MutationResult::Skipped if the part is not present
+            .merge(optional_mapped_mutators)
+            // A mutator that sets the optional byte array to None if present, and generates a random byte array of length 1 if it is not
+            .prepend(FunctionMappingMutator::new(
+                optional_byte_array_field,
+                ToggleOptionalBytesMutator::new(),
+            ))
+            // Finally, a mutator that toggles the boolean part of the input
+            .prepend(FunctionMappingMutator::new(
+                boolean_field,
+                ToggleBoolMutator::new(),
+            ))
     };
 
-    // Merging multiple lists of mutators that mutate a sub-part of the custom input
-    // This collection could be expanded with default or custom mutators as needed for the input
-    let mutators = tuple_list!()
-        // First, mutators for the simple byte array
-        .merge(mapped_mutators)
-        // Then, mutators for the optional byte array, these return MutationResult::Skipped if the part is not present
-        .merge(optional_mapped_mutators)
-        // A custom mutator that sets the optional byte array to None if present, and generates a random byte array of length 1 if it is not
-        .prepend(ToggleOptionalByteArrayMutator::new(nonzero!(1)))
-        // Finally, a custom mutator that toggles the boolean part of the input
-        .prepend(ToggleBooleanMutator);
-
     // Scheduling layer for the mutations
     let mutator_scheduler = StdScheduledMutator::new(mutators);
     // Defining the mutator stage
@@ -0,0 +1,266 @@
+use std::{cell::RefCell, path::PathBuf, ptr::write, rc::Rc};
+
+use libafl::{
+    corpus::{InMemoryCorpus, OnDiskCorpus},
+    events::SimpleEventManager,
+    executors::{inprocess::InProcessExecutor, ExitKind},
+    feedbacks::{CrashFeedback, MaxMapFeedback},
+    fuzzer::{Fuzzer, StdFuzzer},
+    generators::SyscallSequenceGenerator,
+    inputs::{
+        SyscallArg, SyscallArgType, SyscallDescription, SyscallSequenceInput, SyscallTable,
+        INVALID_RESOURCE,
+    },
+    monitors::SimpleMonitor,
+    mutators::{
+        StdScheduledMutator, SyscallInsertMutator, SyscallMutateArgMutator, SyscallSpliceMutator,
+    },
+    observers::StdMapObserver,
+    schedulers::QueueScheduler,
+    stages::mutational::StdMutationalStage,
+    state::StdState,
+};
+use libafl_bolts::{current_nanos, nonzero, rands::StdRand, tuples::tuple_list};
+
+/// Coverage map with explicit assignments due to the lack of instrumentation
+const SIGNALS_LEN: usize = 16;
+static mut SIGNALS: [u8; SIGNALS_LEN] = [0; SIGNALS_LEN];
+static mut SIGNALS_PTR: *mut u8 = unsafe { &raw mut SIGNALS as _ };
+
+/// Assign a signal to the signals map
+fn signals_set(idx: usize) {
+    unsafe { write(SIGNALS_PTR.add(idx), 1) };
+}
+
+const SYS_READ: u64 = 0;
+const SYS_WRITE: u64 = 1;
+const SYS_OPEN: u64 = 2;
+const SYS_CLOSE: u64 = 3;
+const SYS_DUP: u64 = 32;
+
+const O_CREAT: u64 = 0x40;
+
+/// The calls of the toy file API, and the types of their arguments
+fn syscall_table() -> SyscallTable {
+    let fd = || SyscallArgType::Resource { kind: "fd".into() };
+    SyscallTable::new(vec![
+        SyscallDescription::new(
+            "open",
+            SYS_OPEN,
+            vec![
+                SyscallArgType::Buffer { max_len: 8 },
+                SyscallArgType::Flags(vec![0x1, 0x2, O_CREAT, 0x200, 0x400]),
+            ],
+            Some("fd"),
+        ),
+        SyscallDescription::new(
+            "read",
+            SYS_READ,
+            vec![fd(), SyscallArgType::Range { min: 0, max: 64 }],
+            None,
+        ),
+        SyscallDescription::new(
+            "write",
+            SYS_WRITE,
+            vec![
+                fd(),
+                SyscallArgType::Buffer { max_len: 16 },
+                SyscallArgType::Int { bits: 16 },
+            ],
+            None,
+        ),
+        SyscallDescription::new("dup", SYS_DUP, vec![fd()], Some("fd")),
+        SyscallDescription::new("close", SYS_CLOSE, vec![fd()], None),
+    ])
+}
+
+/// A file of the toy file API
+#[derive(Debug, Default)]
+struct File {
+    data: Vec<u8>,
+    freed: bool,
+}
+
+/// The toy file API, with a use-after-free bug:
+/// `close` frees the file even if another descriptor still references it.
+#[derive(Debug, Default)]
+struct Kernel {
+    fds: Vec<Option<Rc<RefCell<File>>>>,
+    use_after_free: bool,
+}
+
+impl Kernel {
+    fn file(&self, fd: u64) -> Option<Rc<RefCell<File>>> {
+        self.fds.get(usize::try_from(fd).ok()?)?.clone()
+    }
+
+    fn install(&mut self, file: Rc<RefCell<File>>) -> u64 {
+        self.fds.push(Some(file));
+        self.fds.len() as u64 - 1
+    }
+
+    fn syscall(&mut self, nr: u64, args: &[SyscallArg], results: &[u64]) -> u64 {
+        let int = |i: usize| {
+            args.get(i)
+                .and_then(|arg| arg.value(results))
+                .unwrap_or(INVALID_RESOURCE)
+        };
+        let buf = |i: usize| match args.get(i) {
+            Some(SyscallArg::Buffer(buf)) => buf.as_slice(),
+            _ => &[],
+        };
+        match nr {
+            SYS_OPEN => {
+                signals_set(1);
+                if buf(0).is_empty() || int(1) & O_CREAT == 0 {
+                    return INVALID_RESOURCE;
+                }
+                signals_set(2);
+                self.install(Rc::default())
+            }
+            SYS_READ => {
+                signals_set(3);
+                let Some(file) = self.file(int(0)) else {
+                    return INVALID_RESOURCE;
+                };
+                signals_set(4);
+                let len = file.borrow().data.len().min(int(1) as usize);
+                len as u64
+            }
+            SYS_WRITE => {
+                signals_set(5);
+                let Some(file) = self.file(int(0)) else {
+                    return INVALID_RESOURCE;
+                };
+                signals_set(6);
+                let data = buf(1);
+                let len = data.len().min(int(2) as usize);
+                if len == 0 {
+                    return 0;
+                }
+                signals_set(7);
+                let mut file = file.borrow_mut();
+                if file.freed {
+                    self.use_after_free = true;
+                    return INVALID_RESOURCE;
+                }
+                file.data.extend_from_slice(&data[..len]);
+                len as u64
+            }
+            SYS_DUP => {
+                signals_set(8);
+                let Some(file) = self.file(int(0)) else {
+                    return INVALID_RESOURCE;
+                };
+                signals_set(9);
+                self.install(file)
+            }
+            SYS_CLOSE => {
+                signals_set(10);
+                let Some(file) = self.fds.get_mut(int(0) as usize).and_then(Option::take) else {
+                    return INVALID_RESOURCE;
+                };
+                signals_set(11);
+                // the bug: the file is freed even if it is still referenced by a duplicate
+                file.borrow_mut().freed = true;
+                0
+            }
+            _ => INVALID_RESOURCE,
+        }
+    }
+}
+
+#[allow(clippy::similar_names, clippy::manual_assert)]
+pub fn main() {
+    let table = syscall_table();
+
+    // The closure that we want to fuzz
+    let mut harness = |input: &SyscallSequenceInput| {
+        let mut kernel = Kernel::default();
+        let mut results = Vec::with_capacity(input.calls().len());
+        signals_set(0);
+        for call in input.calls() {
+            let nr = table.get(call.syscall).map_or(u64::MAX, |desc| desc.nr);
+            let result = kernel.syscall(nr, &call.args, &results);
+            if kernel.use_after_free {
+                println!("{}", input.display(&table));
+                panic!("Artificial bug triggered =) use-after-free in write");
+            }
+            results.push(result);
+        }
+        ExitKind::Ok
+    };
+
+    // Create an observation channel using the signals map
+    let observer = unsafe { StdMapObserver::from_mut_ptr("signals", SIGNALS_PTR, SIGNALS_LEN) };
+
+    // Feedback to rate the interestingness of an input
+    let mut feedback = MaxMapFeedback::new(&observer);
+
+    // A feedback to choose if an input is a solution or not
+    let mut objective = CrashFeedback::new();
+
+    // create a State from scratch
+    let mut state = StdState::new(
+        // RNG
+        StdRand::with_seed(current_nanos()),
+        // Corpus that will be evolved, we keep it in memory for performance
+        InMemoryCorpus::new(),
+        // Corpus in which we store solutions (crashes in this example),
+        // on disk so the user can get them after stopping the fuzzer
+        OnDiskCorpus::new(PathBuf::from("./crashes")).unwrap(),
+        // States of the feedbacks.
+        // The feedbacks can report the data that should persist in the State.
+        &mut feedback,
+        // Same for objective feedbacks
+        &mut objective,
+    )
+    .unwrap();
+
+    // The Monitor trait define how the fuzzer stats are reported to the user
+    let monitor = SimpleMonitor::new(|s| println!("{s}"));
+
+    // The event manager handle the various events generated during the fuzzing loop
+    // such as the notification of the addition of a new item to the corpus
+    let mut mgr = SimpleEventManager::new(monitor);
+
+    // A queue policy to get testcasess from the corpus
+    let scheduler = QueueScheduler::new();
+
+    // A fuzzer with feedbacks and a corpus scheduler
+    let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+    // Create the executor for an in-process function with just one observer
+    let mut executor = InProcessExecutor::new(
+        &mut harness,
+        tuple_list!(observer),
+        &mut fuzzer,
+        &mut state,
+        &mut mgr,
+    )
+    .expect("Failed to create the Executor");
+
+    // Generator of sequences of up to 8 calls
+    let mut generator = SyscallSequenceGenerator::new(&table, nonzero!(8));
+
+    // Generate 8 initial inputs
+    state
+        .generate_initial_inputs_forced(&mut fuzzer, &mut executor, &mut generator, &mut mgr, 8)
+        .expect("Failed to generate the initial corpus");
+
+    // Setup a mutational stage with the syscall sequence mutators
+    let mutator = StdScheduledMutator::with_max_stack_pow(
+        tuple_list!(
+            SyscallInsertMutator::new(&generator),
+            SyscallMutateArgMutator::new(&generator),
+            SyscallMutateArgMutator::new(&generator),
+            SyscallSpliceMutator::new(&generator)
+        ),
+        2,
+    );
+    let mut stages = tuple_list!(StdMutationalStage::new(mutator));
+
+    fuzzer
+        .fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)
+        .expect("Error in the fuzzing loop");
+}
@@ -1,6 +1,7 @@
 //! This is a basic SymCC runtime.
 //! It traces the execution to the shared memory region that should be passed through the environment by the fuzzer process.
-//! Additionally, it concretizes all floating point operations for simplicity.
+//! Additionally, it concretizes all floating point operations for simplicity,
+//! and the input bytes out of the regions the fuzzer may set in `LIBAFL_SELECTIVE_SYMBOLICATION`.
 //! Refer to the `symcc_runtime` crate documentation for building your own runtime.
 
 // The lib needs to be named SymRuntime for SymCC to find it
@@ -8,17 +9,18 @@
 
 use symcc_runtime::{
     export_runtime,
-    filter::{CallStackCoverage, NoFloat},
-    tracing::{self, StdShMemMessageFileWriter},
+    filter::{CallStackCoverage, NoFloat, SymbolicRegions},
+    tracing::{self, StdShMemMessageFileWriter, TracingOptions},
     Runtime,
 };
 
 export_runtime!(
+    SymbolicRegions::from_env() => SymbolicRegions;
     NoFloat => NoFloat;
     CallStackCoverage::default() => CallStackCoverage; // QSym-style expression pruning
-    tracing::TracingRuntime::new(
+    tracing::TracingRuntime::with_options(
         StdShMemMessageFileWriter::from_stdshmem_default_env()
             .expect("unable to construct tracing runtime writer. (missing env?)"),
-        false
+        TracingOptions::from_env()
     ) => tracing::TracingRuntime
 );
@@ -1,7 +1,7 @@
 use alloc::{borrow::ToOwned, string::String, vec::Vec};
 use core::num::NonZero;
 
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 use libafl_bolts::{
     nonzero,
     rands::{Rand, RomuDuoJrRand},
@@ -10,9 +10,10 @@ use pyo3::prelude::PyObject;
 
 use super::{
     newtypes::{NTermId, RuleId},
-    rule::{Rule, RuleIdOrCustom},
+    rule::{Rule, RuleChild, RuleIdOrCustom},
     tree::Tree,
 };
+use crate::Error;
 
 #[derive(Debug, Clone)]
 pub struct Context {
@@ -84,6 +85,26 @@ impl Context {
         rid
     }
 
+    /// Like [`Context::add_rule`], but returns an error if a nonterminal of the `format` is invalid
+    pub fn try_add_rule(&mut self, nt: &str, format: &[u8]) -> Result<RuleId, Error> {
+        let rid = self.rules.len().into();
+        let rule = Rule::try_from_format(self, nt, format)?;
+        let ntid = self.aquire_nt_id(nt);
+        self.rules.push(rule);
+        self.nts_to_rules.entry(ntid).or_default().push(rid);
+        Ok(rid)
+    }
+
+    /// Adds a plain rule producing the `children`, see [`Rule::from_children`]
+    pub fn add_plain_rule(&mut self, nt: &str, children: Vec<RuleChild>) -> RuleId {
+        let rid = self.rules.len().into();
+        let rule = Rule::from_children(self, nt, children);
+        let ntid = self.aquire_nt_id(nt);
+        self.rules.push(rule);
+        self.nts_to_rules.entry(ntid).or_default().push(rid);
+        rid
+    }
+
     pub fn add_script(&mut self, nt: &str, nts: &[String], script: PyObject) -> RuleId {
         let rid = self.rules.len().into();
         let rule = Rule::from_script(self, nt, nts, script);
@@ -130,6 +151,47 @@ impl Context {
         self.nt_ids_to_name[&nt].clone()
     }
 
+    /// The number of rules of this context
+    #[must_use]
+    pub fn rules_len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// The first rule using a nonterminal that has no rule, and that nonterminal
+    #[must_use]
+    pub fn find_undefined_nonterm(&self) -> Option<(RuleId, NTermId)> {
+        (0..self.rules.len()).map(RuleId::from).find_map(|rid| {
+            self.get_rule(rid)
+                .nonterms()
+                .iter()
+                .find(|nt| !self.nts_to_rules.contains_key(*nt))
+                .map(|nt| (rid, *nt))
+        })
+    }
+
+    /// The rules that never produce a finite tree, for which [`Context::initialize`] panics,
+    /// for example `A => {A}` without a non recursive rule for `A`
+    #[must_use]
+    pub fn find_unproductive_rules(&self) -> Vec<RuleId> {
+        let mut productive = HashSet::new();
+        let mut unknown_rules = (0..self.rules.len()).map(RuleId::from).collect::<Vec<_>>();
+        loop {
+            let last_len = unknown_rules.len();
+            unknown_rules.retain(|rule| {
+                let rule = self.get_rule(*rule);
+                if rule.nonterms().iter().all(|nt| productive.contains(nt)) {
+                    productive.insert(rule.nonterm());
+                    false
+                } else {
+                    true
+                }
+            });
+            if last_len == unknown_rules.len() {
+                return unknown_rules;
+            }
+        }
+    }
+
     fn calc_min_len_for_rule(&self, r: RuleId) -> Option<usize> {
         let mut res = 1;
         for nt_id in self.get_rule(r).nonterms() {
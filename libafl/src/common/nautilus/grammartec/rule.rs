@@ -15,6 +15,7 @@ use crate::common::nautilus::{
     },
     regex_mutator,
 };
+use crate::Error;
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum RuleChild {
@@ -43,20 +44,25 @@ impl RuleChild {
     }
 
     pub fn from_nt(nt: &str, ctx: &mut Context) -> Self {
-        let (nonterm, _) = RuleChild::split_nt_description(nt);
-        RuleChild::NTerm(ctx.aquire_nt_id(&nonterm))
+        Self::try_from_nt(nt, ctx).unwrap_or_else(|err| panic!("{err}"))
     }
 
-    fn split_nt_description(nonterm: &str) -> (String, String) {
+    /// Like [`RuleChild::from_nt`], but returns an error if the nonterminal description is invalid
+    pub fn try_from_nt(nt: &str, ctx: &mut Context) -> Result<Self, Error> {
+        let (nonterm, _) = RuleChild::split_nt_description(nt)?;
+        Ok(RuleChild::NTerm(ctx.aquire_nt_id(&nonterm)))
+    }
+
+    fn split_nt_description(nonterm: &str) -> Result<(String, String), Error> {
         let splitter = SPLITTER.get_or_init(|| {
             regex::Regex::new(r"^\{([A-Z][a-zA-Z_\-0-9]*)(?::([a-zA-Z_\-0-9]*))?\}$")
                 .expect("RAND_1363289094")
         });
 
         //splits {A:a} or {A} into A and maybe a
-        let descr = splitter.captures(nonterm).unwrap_or_else(|| panic!("could not interpret Nonterminal {nonterm:?}. Nonterminal Descriptions need to match start with a capital letter and con only contain [a-zA-Z_-0-9]"));
+        let descr = splitter.captures(nonterm).ok_or_else(|| Error::illegal_argument(format!("could not interpret Nonterminal {nonterm:?}. Nonterminal Descriptions need to match start with a capital letter and con only contain [a-zA-Z_-0-9]")))?;
         //let name = descr.get(2).map(|m| m.as_str().into()).unwrap_or(default.to_string()));
-        (descr[1].into(), String::new())
+        Ok((descr[1].into(), String::new()))
     }
 
     fn debug_show(&self, ctx: &Context) -> String {
@@ -196,7 +202,17 @@ impl Rule {
     }
 
     pub fn from_format(ctx: &mut Context, nonterm: &str, format: &[u8]) -> Self {
-        let children = Rule::tokenize(format, ctx);
+        Self::try_from_format(ctx, nonterm, format).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Like [`Rule::from_format`], but returns an error if a nonterminal of the `format` is invalid
+    pub fn try_from_format(ctx: &mut Context, nonterm: &str, format: &[u8]) -> Result<Self, Error> {
+        let children = Rule::tokenize(format, ctx)?;
+        Ok(Self::from_children(ctx, nonterm, children))
+    }
+
+    /// A plain rule producing the `children`, without parsing a format
+    pub fn from_children(ctx: &mut Context, nonterm: &str, children: Vec<RuleChild>) -> Self {
         let nonterms = children
             .iter()
             .filter_map(|c| {
@@ -251,7 +267,7 @@ impl Rule {
         res
     }
 
-    fn tokenize(format: &[u8], ctx: &mut Context) -> Vec<RuleChild> {
+    fn tokenize(format: &[u8], ctx: &mut Context) -> Result<Vec<RuleChild>, Error> {
         let tokenizer = TOKENIZER.get_or_init(|| {
             regex::bytes::RegexBuilder::new(r"(?-u)(\{[^}\\]+\})|((?:[^{\\]|\\\{|\\\}|\\)+)")
                 .dot_matches_new_line(true)
@@ -265,18 +281,19 @@ impl Rule {
             .map(|cap| {
                 if let Some(sub) = cap.get(1) {
                     //println!("cap.get(1): {}", sub.as_str());
-                    RuleChild::from_nt(
-                        std::str::from_utf8(sub.as_bytes())
-                            .expect("nonterminals need to be valid strings"),
+                    RuleChild::try_from_nt(
+                        std::str::from_utf8(sub.as_bytes()).map_err(|_| {
+                            Error::illegal_argument("nonterminals need to be valid strings")
+                        })?,
                         ctx,
                     )
                 } else if let Some(sub) = cap.get(2) {
-                    RuleChild::from_lit(&Self::unescape(sub.as_bytes()))
+                    Ok(RuleChild::from_lit(&Self::unescape(sub.as_bytes())))
                 } else {
                     unreachable!()
                 }
             })
-            .collect::<Vec<_>>()
+            .collect::<Result<Vec<_>, _>>()
     }
 
     #[must_use]
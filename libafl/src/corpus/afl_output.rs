@@ -0,0 +1,459 @@
+//! Lays out the fuzzer output directory exactly like AFL++ does.
+//!
+//! ```text
+//! out/
+//! ├── fuzzer_stats
+//! ├── plot_data
+//! ├── queue/id:000000,time:0,execs:0,orig:seed
+//! ├── queue/id:000001,src:000000,time:1523,execs:8412,op:havoc,rep:4
+//! ├── crashes/id:000000,sig:00,src:000001,time:4012,execs:20103,op:havoc,rep:2
+//! └── hangs/id:000000,src:000001,time:5012,execs:30481,op:havoc,rep:8
+//! ```
+//!
+//! Use [`AflOutputDir::queue_corpus`] as corpus, [`AflOutputDir::solutions_corpus`] as solutions,
+//! [`crate::feedbacks::AflFilenameFeedback`] in both the feedback and the objective to name the entries,
+//! and point [`crate::stages::AflStatsStage`] at [`AflOutputDir::fuzzer_stats_path`] and [`AflOutputDir::plot_data_path`].
+//! Existing triage tooling, such as `afl-whatsup`, `afl-plot`, or `casr-afl`, then works unmodified.
+
+use alloc::{borrow::Cow, string::String};
+use core::{cell::RefCell, fmt::Write};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{CachedOnDiskCorpus, Corpus, CorpusId, InMemoryCorpus, Testcase},
+    inputs::Input,
+    Error, HasMetadata,
+};
+
+/// Where an entry of the AFL++ output directory belongs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AflEntryKind {
+    /// An interesting input, stored in `queue/`
+    Queue,
+    /// A crashing input, stored in `crashes/`
+    Crash,
+    /// A timeout, stored in `hangs/`
+    Hang,
+}
+
+impl AflEntryKind {
+    /// The subdirectory of the output directory for this kind of entry
+    #[must_use]
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            AflEntryKind::Queue => "queue",
+            AflEntryKind::Crash => "crashes",
+            AflEntryKind::Hang => "hangs",
+        }
+    }
+}
+
+/// The pieces of an AFL++ filename, attached to a [`Testcase`] by [`crate::feedbacks::AflFilenameFeedback`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AflFilenameMetadata {
+    /// Where this entry belongs
+    pub kind: AflEntryKind,
+    /// The corpus entry this entry was derived from, `None` for initial seeds
+    pub src: Option<CorpusId>,
+    /// Milliseconds since the start of the campaign
+    pub time: u64,
+    /// Executions since the start of the campaign
+    pub execs: u64,
+    /// The operation (stage) that produced this entry, for example `havoc`
+    pub op: Cow<'static, str>,
+    /// The amount of stacked mutations, if known
+    pub rep: Option<usize>,
+    /// The signal that killed the target, for crashes, if known
+    pub sig: Option<i32>,
+    /// The original filename of an initial seed
+    pub orig: Option<String>,
+}
+
+libafl_bolts::impl_serdeany!(AflFilenameMetadata);
+
+impl AflFilenameMetadata {
+    /// Formats the AFL++ filename of this entry, for the given `id` in its directory.
+    #[must_use]
+    pub fn filename(&self, id: CorpusId) -> String {
+        let mut name = format!("id:{:06}", id.0);
+        if self.kind == AflEntryKind::Crash {
+            // AFL++ always writes a signal, `00` if it's unknown to us.
+            write!(name, ",sig:{:02}", self.sig.unwrap_or(0)).unwrap();
+        }
+        if let Some(src) = self.src {
+            write!(name, ",src:{:06}", src.0).unwrap();
+        }
+        write!(name, ",time:{},execs:{}", self.time, self.execs).unwrap();
+        match (&self.orig, self.src) {
+            (Some(orig), _) => write!(name, ",orig:{orig}").unwrap(),
+            (None, None) => name.push_str(",orig:seed"),
+            (None, Some(_)) => {
+                write!(name, ",op:{}", self.op).unwrap();
+                if let Some(rep) = self.rep {
+                    write!(name, ",rep:{rep}").unwrap();
+                }
+            }
+        }
+        name
+    }
+}
+
+/// An AFL++-style output directory, with `queue/`, `crashes/` and `hangs/` subdirectories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AflOutputDir {
+    root: PathBuf,
+}
+
+impl AflOutputDir {
+    /// Creates (or reuses) the output directory at `root`, including all subdirectories.
+    pub fn new<P>(root: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let out = Self {
+            root: root.as_ref().to_path_buf(),
+        };
+        for kind in [AflEntryKind::Queue, AflEntryKind::Crash, AflEntryKind::Hang] {
+            match fs::create_dir_all(out.dir(kind)) {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(out)
+    }
+
+    /// The root of the output directory
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The directory storing entries of the given [`AflEntryKind`]
+    #[must_use]
+    pub fn dir(&self, kind: AflEntryKind) -> PathBuf {
+        self.root.join(kind.dir_name())
+    }
+
+    /// The `queue/` directory
+    #[must_use]
+    pub fn queue_dir(&self) -> PathBuf {
+        self.dir(AflEntryKind::Queue)
+    }
+
+    /// The `crashes/` directory
+    #[must_use]
+    pub fn crashes_dir(&self) -> PathBuf {
+        self.dir(AflEntryKind::Crash)
+    }
+
+    /// The `hangs/` directory
+    #[must_use]
+    pub fn hangs_dir(&self) -> PathBuf {
+        self.dir(AflEntryKind::Hang)
+    }
+
+    /// The `fuzzer_stats` file, see [`crate::stages::AflStatsStage`]
+    #[must_use]
+    pub fn fuzzer_stats_path(&self) -> PathBuf {
+        self.root.join("fuzzer_stats")
+    }
+
+    /// The `plot_data` file, see [`crate::stages::AflStatsStage`]
+    #[must_use]
+    pub fn plot_data_path(&self) -> PathBuf {
+        self.root.join("plot_data")
+    }
+
+    /// A corpus storing entries in `queue/`, keeping at most `cache_max_len` inputs in memory.
+    ///
+    /// No metadata or lock files are written, so the directory only contains inputs, like for AFL++.
+    pub fn queue_corpus<I>(&self, cache_max_len: usize) -> Result<CachedOnDiskCorpus<I>, Error>
+    where
+        I: Input,
+    {
+        CachedOnDiskCorpus::with_meta_format_and_prefix(
+            self.queue_dir(),
+            cache_max_len,
+            None,
+            None,
+            false,
+        )
+    }
+
+    /// A solutions corpus storing crashes in `crashes/` and timeouts in `hangs/`.
+    #[must_use]
+    pub fn solutions_corpus<I>(&self) -> AflSolutionsCorpus<I> {
+        AflSolutionsCorpus {
+            inner: InMemoryCorpus::new(),
+            out: self.clone(),
+            crashes: 0,
+            hangs: 0,
+        }
+    }
+}
+
+/// A solutions [`Corpus`] storing objectives in the `crashes/` or `hangs/` directory of an [`AflOutputDir`].
+///
+/// The directory is picked based on the [`AflFilenameMetadata`] of the [`Testcase`], defaulting to `crashes/`.
+/// Like AFL++, ids are counted per directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AflSolutionsCorpus<I> {
+    inner: InMemoryCorpus<I>,
+    out: AflOutputDir,
+    crashes: usize,
+    hangs: usize,
+}
+
+impl<I> AflSolutionsCorpus<I>
+where
+    I: Input,
+{
+    /// The number of entries written to `crashes/`
+    #[must_use]
+    pub fn saved_crashes(&self) -> usize {
+        self.crashes
+    }
+
+    /// The number of entries written to `hangs/`
+    #[must_use]
+    pub fn saved_hangs(&self) -> usize {
+        self.hangs
+    }
+
+    fn save_testcase(&mut self, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        let meta = testcase
+            .metadata_map()
+            .get::<AflFilenameMetadata>()
+            .cloned();
+        let kind = match meta.as_ref().map(|meta| meta.kind) {
+            Some(AflEntryKind::Hang) => AflEntryKind::Hang,
+            _ => AflEntryKind::Crash,
+        };
+        let counter = if kind == AflEntryKind::Hang {
+            &mut self.hangs
+        } else {
+            &mut self.crashes
+        };
+        let id = CorpusId(*counter);
+        *counter += 1;
+
+        let filename = match meta {
+            Some(mut meta) => {
+                meta.kind = kind;
+                meta.filename(id)
+            }
+            None => testcase
+                .filename()
+                .clone()
+                .unwrap_or_else(|| format!("id:{:06}", id.0)),
+        };
+        let file_path = self.out.dir(kind).join(&filename);
+        *testcase.filename_mut() = Some(filename);
+        *testcase.file_path_mut() = Some(file_path);
+        self.store_input_from(testcase)
+    }
+}
+
+impl<I> Corpus for AflSolutionsCorpus<I>
+where
+    I: Input,
+{
+    type Input = I;
+
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    #[inline]
+    fn count_disabled(&self) -> usize {
+        self.inner.count_disabled()
+    }
+
+    #[inline]
+    fn count_all(&self) -> usize {
+        self.inner.count_all()
+    }
+
+    fn add(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.save_testcase(&mut testcase)?;
+        self.inner.add(testcase)
+    }
+
+    fn add_disabled(&mut self, mut testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.save_testcase(&mut testcase)?;
+        self.inner.add_disabled(testcase)
+    }
+
+    fn replace(&mut self, id: CorpusId, mut testcase: Testcase<I>) -> Result<Testcase<I>, Error> {
+        self.save_testcase(&mut testcase)?;
+        let old = self.inner.replace(id, testcase)?;
+        if let Some(path) = old.file_path() {
+            fs::remove_file(path)?;
+        }
+        Ok(old)
+    }
+
+    fn remove(&mut self, id: CorpusId) -> Result<Testcase<I>, Error> {
+        let old = self.inner.remove(id)?;
+        if let Some(path) = old.file_path() {
+            fs::remove_file(path)?;
+        }
+        Ok(old)
+    }
+
+    #[inline]
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.inner.get(id)
+    }
+
+    #[inline]
+    fn get_from_all(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.inner.get_from_all(id)
+    }
+
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    #[inline]
+    fn peek_free_id(&self) -> CorpusId {
+        self.inner.peek_free_id()
+    }
+
+    #[inline]
+    fn prev(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(id)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    #[inline]
+    fn nth_from_all(&self, nth: usize) -> CorpusId {
+        self.inner.nth_from_all(nth)
+    }
+
+    fn load_input_into(&self, testcase: &mut Testcase<I>) -> Result<(), Error> {
+        if testcase.input().is_none() {
+            let Some(file_path) = testcase.file_path().as_ref() else {
+                return Err(Error::illegal_argument(
+                    "No file path set for testcase. Could not load inputs.",
+                ));
+            };
+            let input = I::from_file(file_path)?;
+            testcase.set_input(input);
+        }
+        Ok(())
+    }
+
+    fn store_input_from(&self, testcase: &Testcase<I>) -> Result<(), Error> {
+        let Some(file_path) = testcase.file_path() else {
+            return Err(Error::illegal_argument(
+                "No file path set for testcase. Could not store input to disk.",
+            ));
+        };
+        let Some(input) = testcase.input() else {
+            return Err(Error::illegal_argument(
+                "No input available for testcase. Could not store anything.",
+            ));
+        };
+        input.to_file(file_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::borrow::Cow;
+    use std::{env, fs};
+
+    use super::{AflEntryKind, AflFilenameMetadata, AflOutputDir};
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        inputs::BytesInput,
+        HasMetadata,
+    };
+
+    fn meta(kind: AflEntryKind, src: Option<CorpusId>) -> AflFilenameMetadata {
+        AflFilenameMetadata {
+            kind,
+            src,
+            time: 1523,
+            execs: 8412,
+            op: Cow::Borrowed("havoc"),
+            rep: Some(4),
+            sig: None,
+            orig: None,
+        }
+    }
+
+    #[test]
+    fn test_afl_filenames() {
+        assert_eq!(
+            meta(AflEntryKind::Queue, Some(CorpusId(0))).filename(CorpusId(1)),
+            "id:000001,src:000000,time:1523,execs:8412,op:havoc,rep:4"
+        );
+        assert_eq!(
+            meta(AflEntryKind::Queue, None).filename(CorpusId(0)),
+            "id:000000,time:1523,execs:8412,orig:seed"
+        );
+        let mut crash = meta(AflEntryKind::Crash, Some(CorpusId(7)));
+        crash.sig = Some(11);
+        assert_eq!(
+            crash.filename(CorpusId(2)),
+            "id:000002,sig:11,src:000007,time:1523,execs:8412,op:havoc,rep:4"
+        );
+    }
+
+    #[test]
+    fn test_afl_solutions_corpus() {
+        let root = env::temp_dir().join("libafl_test_afl_output");
+        drop(fs::remove_dir_all(&root));
+        let out = AflOutputDir::new(&root).unwrap();
+        let mut solutions = out.solutions_corpus::<BytesInput>();
+
+        let mut hang = Testcase::new(BytesInput::new(b"hang".to_vec()));
+        hang.add_metadata(meta(AflEntryKind::Hang, Some(CorpusId(3))));
+        solutions.add(hang).unwrap();
+        let mut crash = Testcase::new(BytesInput::new(b"crash".to_vec()));
+        crash.add_metadata(meta(AflEntryKind::Crash, Some(CorpusId(3))));
+        solutions.add(crash).unwrap();
+
+        assert_eq!(solutions.saved_hangs(), 1);
+        assert_eq!(solutions.saved_crashes(), 1);
+        assert!(out
+            .hangs_dir()
+            .join("id:000000,src:000003,time:1523,execs:8412,op:havoc,rep:4")
+            .exists());
+        assert!(out
+            .crashes_dir()
+            .join("id:000000,sig:00,src:000003,time:1523,execs:8412,op:havoc,rep:4")
+            .exists());
+        assert!(out.queue_dir().is_dir());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -11,24 +11,26 @@ use crate::{
         inmemory_ondisk::InMemoryOnDiskCorpus, ondisk::OnDiskMetadataFormat, Corpus, CorpusId,
         HasTestcase, Testcase,
     },
-    inputs::Input,
+    inputs::{Input, InputCodec, NativeCodec},
     Error,
 };
 
 /// A corpus that keeps a maximum number of [`Testcase`]s in memory
 /// and load them from disk, when they are being used.
 /// The eviction policy is FIFO.
+/// The inputs are written with the [`InputCodec`] `C`, see [`CachedOnDiskCorpus::with_codec`].
 #[cfg(feature = "std")]
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
-pub struct CachedOnDiskCorpus<I> {
-    inner: InMemoryOnDiskCorpus<I>,
+pub struct CachedOnDiskCorpus<I, C = NativeCodec> {
+    inner: InMemoryOnDiskCorpus<I, C>,
     cached_indexes: RefCell<VecDeque<CorpusId>>,
     cache_max_len: usize,
 }
 
-impl<I> CachedOnDiskCorpus<I>
+impl<I, C> CachedOnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     fn cache_testcase<'a>(
         &'a self,
@@ -56,9 +58,10 @@ where
         Ok(())
     }
 }
-impl<I> Corpus for CachedOnDiskCorpus<I>
+impl<I, C> Corpus for CachedOnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     type Input = I;
 
@@ -178,11 +181,17 @@ where
     fn store_input_from(&self, testcase: &Testcase<Self::Input>) -> Result<(), Error> {
         self.inner.store_input_from(testcase)
     }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
 }
 
-impl<I> HasTestcase for CachedOnDiskCorpus<I>
+impl<I, C> HasTestcase for CachedOnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     fn testcase(&self, id: CorpusId) -> Result<core::cell::Ref<Testcase<I>>, Error> {
         Ok(self.get(id)?.borrow())
@@ -276,9 +285,23 @@ impl<I> CachedOnDiskCorpus<I> {
             cache_max_len,
         })
     }
+}
+
+impl<I, C> CachedOnDiskCorpus<I, C> {
+    /// Writes and reads the inputs with the given [`InputCodec`], see [`InMemoryOnDiskCorpus::with_codec`]
+    pub fn with_codec<C2>(self, codec: C2) -> CachedOnDiskCorpus<I, C2>
+    where
+        C2: InputCodec<I>,
+    {
+        CachedOnDiskCorpus {
+            inner: self.inner.with_codec(codec),
+            cached_indexes: self.cached_indexes,
+            cache_max_len: self.cache_max_len,
+        }
+    }
 
     /// Fetch the inner corpus
-    pub fn inner(&self) -> &InMemoryOnDiskCorpus<I> {
+    pub fn inner(&self) -> &InMemoryOnDiskCorpus<I, C> {
         &self.inner
     }
 }
@@ -0,0 +1,286 @@
+//! A corpus with a fixed number of slots, allocated up front, for targets with a tight memory budget.
+//!
+//! The [`FixedCorpus`] never grows: adding to a full corpus fails, instead of allocating more memory.
+//! It keeps no maps or lists next to the slots, so its memory use is known before the fuzzer starts,
+//! which is what bare-metal and other `no_std` targets need.
+
+use alloc::{format, vec::Vec};
+use core::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    Error,
+};
+
+/// A slot of a [`FixedCorpus`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Slot<I> {
+    testcase: RefCell<Testcase<I>>,
+    enabled: bool,
+}
+
+/// A corpus holding at most `N` [`Testcase`]s, in slots allocated when it is created.
+///
+/// The [`CorpusId`] of a testcase is the index of its slot; the slots of removed testcases are reused.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedCorpus<I, const N: usize> {
+    slots: Vec<Option<Slot<I>>>,
+    enabled: usize,
+    disabled: usize,
+    current: Option<CorpusId>,
+}
+
+impl<I, const N: usize> FixedCorpus<I, N> {
+    /// Creates a new [`FixedCorpus`], allocating all `N` slots
+    #[must_use]
+    pub fn new() -> Self {
+        let mut slots = Vec::with_capacity(N);
+        slots.resize_with(N, || None);
+        Self {
+            slots,
+            enabled: 0,
+            disabled: 0,
+            current: None,
+        }
+    }
+
+    /// The number of testcases this corpus can hold
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    fn insert(&mut self, testcase: Testcase<I>, enabled: bool) -> Result<CorpusId, Error> {
+        let Some(idx) = self.slots.iter().position(Option::is_none) else {
+            return Err(Error::illegal_state(format!(
+                "The FixedCorpus is full ({N} entries), pick a larger capacity"
+            )));
+        };
+        self.slots[idx] = Some(Slot {
+            testcase: RefCell::new(testcase),
+            enabled,
+        });
+        if enabled {
+            self.enabled += 1;
+        } else {
+            self.disabled += 1;
+        }
+        Ok(CorpusId(idx))
+    }
+
+    /// The enabled slot with the given id
+    fn enabled_slot(&self, id: CorpusId) -> Option<&Slot<I>> {
+        self.slots.get(id.0)?.as_ref().filter(|slot| slot.enabled)
+    }
+
+    /// The ids of the enabled testcases, in order
+    fn enabled_ids(&self) -> impl DoubleEndedIterator<Item = CorpusId> + '_ {
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.as_ref().is_some_and(|slot| slot.enabled))
+            .map(|(idx, _)| CorpusId(idx))
+    }
+}
+
+impl<I, const N: usize> Default for FixedCorpus<I, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, const N: usize> Corpus for FixedCorpus<I, N> {
+    type Input = I;
+
+    /// Returns the number of all enabled entries
+    #[inline]
+    fn count(&self) -> usize {
+        self.enabled
+    }
+
+    /// Returns the number of all disabled entries
+    #[inline]
+    fn count_disabled(&self) -> usize {
+        self.disabled
+    }
+
+    /// Returns the number of elements including disabled entries
+    #[inline]
+    fn count_all(&self) -> usize {
+        self.enabled + self.disabled
+    }
+
+    /// Add an enabled testcase to a free slot and return its index, fails if the corpus is full
+    #[inline]
+    fn add(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.insert(testcase, true)
+    }
+
+    /// Add a disabled testcase to a free slot and return its index, fails if the corpus is full
+    #[inline]
+    fn add_disabled(&mut self, testcase: Testcase<I>) -> Result<CorpusId, Error> {
+        self.insert(testcase, false)
+    }
+
+    /// Replaces the testcase at the given id
+    fn replace(&mut self, id: CorpusId, testcase: Testcase<I>) -> Result<Testcase<I>, Error> {
+        self.enabled_slot(id)
+            .map(|slot| slot.testcase.replace(testcase))
+            .ok_or_else(|| {
+                Error::key_not_found(format!("Index {id} not found, could not replace."))
+            })
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present; considers both enabled and disabled testcases
+    fn remove(&mut self, id: CorpusId) -> Result<Testcase<I>, Error> {
+        let slot = self
+            .slots
+            .get_mut(id.0)
+            .and_then(Option::take)
+            .ok_or_else(|| Error::key_not_found(format!("Index {id} not found")))?;
+        if slot.enabled {
+            self.enabled -= 1;
+        } else {
+            self.disabled -= 1;
+        }
+        Ok(slot.testcase.into_inner())
+    }
+
+    /// Get by id; considers only enabled testcases
+    #[inline]
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.enabled_slot(id)
+            .map(|slot| &slot.testcase)
+            .ok_or_else(|| Error::key_not_found(format!("Index {id} not found")))
+    }
+
+    /// Get by id; considers both enabled and disabled testcases
+    #[inline]
+    fn get_from_all(&self, id: CorpusId) -> Result<&RefCell<Testcase<I>>, Error> {
+        self.slots
+            .get(id.0)
+            .and_then(Option::as_ref)
+            .map(|slot| &slot.testcase)
+            .ok_or_else(|| Error::key_not_found(format!("Index {id} not found")))
+    }
+
+    /// Current testcase scheduled
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        &self.current
+    }
+
+    /// Current testcase scheduled (mutable)
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        &mut self.current
+    }
+
+    /// Peek the next free corpus id, the capacity if the corpus is full
+    #[inline]
+    fn peek_free_id(&self) -> CorpusId {
+        CorpusId(self.slots.iter().position(Option::is_none).unwrap_or(N))
+    }
+
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.enabled_ids().find(|next| *next > id)
+    }
+
+    fn prev(&self, id: CorpusId) -> Option<CorpusId> {
+        self.enabled_ids().rev().find(|prev| *prev < id)
+    }
+
+    fn first(&self) -> Option<CorpusId> {
+        self.enabled_ids().next()
+    }
+
+    fn last(&self) -> Option<CorpusId> {
+        self.enabled_ids().next_back()
+    }
+
+    /// Get the nth corpus id; considers only enabled testcases
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.enabled_ids()
+            .nth(nth)
+            .expect("Failed to get the {nth} CorpusId")
+    }
+
+    /// Get the nth corpus id; considers both enabled and disabled testcases.
+    /// Like for the [`crate::corpus::InMemoryCorpus`], the enabled testcases come first.
+    fn nth_from_all(&self, nth: usize) -> CorpusId {
+        if nth < self.enabled {
+            return self.nth(nth);
+        }
+        self.slots
+            .iter()
+            .enumerate()
+            .filter(|(_, slot)| slot.as_ref().is_some_and(|slot| !slot.enabled))
+            .map(|(idx, _)| CorpusId(idx))
+            .nth(nth - self.enabled)
+            .expect("Failed to get the {nth} CorpusId")
+    }
+
+    #[inline]
+    fn load_input_into(&self, _: &mut Testcase<Self::Input>) -> Result<(), Error> {
+        // Inputs never get evicted, nothing to load here.
+        Ok(())
+    }
+
+    #[inline]
+    fn store_input_from(&self, _: &Testcase<Self::Input>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<I, const N: usize> HasTestcase for FixedCorpus<I, N> {
+    fn testcase(
+        &self,
+        id: CorpusId,
+    ) -> Result<core::cell::Ref<'_, Testcase<<Self::Corpus as Corpus>::Input>>, Error> {
+        Ok(self.get(id)?.borrow())
+    }
+
+    fn testcase_mut(
+        &self,
+        id: CorpusId,
+    ) -> Result<core::cell::RefMut<'_, Testcase<<Self::Corpus as Corpus>::Input>>, Error> {
+        Ok(self.get(id)?.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::FixedCorpus;
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        inputs::BytesInput,
+    };
+
+    #[test]
+    fn test_fixed_corpus() {
+        let mut corpus = FixedCorpus::<BytesInput, 3>::new();
+        let testcase = |byte| Testcase::new(BytesInput::new(vec![byte]));
+        assert_eq!(corpus.add(testcase(0)).unwrap(), CorpusId(0));
+        assert_eq!(corpus.add_disabled(testcase(1)).unwrap(), CorpusId(1));
+        assert_eq!(corpus.add(testcase(2)).unwrap(), CorpusId(2));
+        assert!(corpus.add(testcase(3)).is_err());
+        assert_eq!(corpus.peek_free_id(), CorpusId(3));
+
+        assert_eq!(corpus.ids().collect::<Vec<_>>(), [CorpusId(0), CorpusId(2)]);
+        assert_eq!(corpus.nth(1), CorpusId(2));
+        assert_eq!(corpus.nth_from_all(2), CorpusId(1));
+        assert!(corpus.get(CorpusId(1)).is_err());
+        assert!(corpus.get_from_all(CorpusId(1)).is_ok());
+
+        corpus.remove(CorpusId(0)).unwrap();
+        assert_eq!((corpus.count(), corpus.count_all()), (1, 2));
+        assert_eq!(corpus.add(testcase(4)).unwrap(), CorpusId(0));
+        assert_eq!(corpus.first(), Some(CorpusId(0)));
+        assert_eq!(corpus.next(CorpusId(0)), Some(CorpusId(2)));
+        assert_eq!(corpus.prev(CorpusId(0)), None);
+    }
+}
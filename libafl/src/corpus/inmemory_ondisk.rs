@@ -4,16 +4,22 @@
 //! For a lower memory footprint, consider using [`crate::corpus::CachedOnDiskCorpus`]
 //! which only stores a certain number of [`Testcase`]s and removes additional ones in a FIFO manner.
 
-use alloc::string::String;
-use core::cell::RefCell;
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{cell::RefCell, fmt};
 #[cfg(feature = "std")]
 use std::{fs, fs::File, io::Write};
 use std::{
     fs::OpenOptions,
     io,
     path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, SyncSender, TryRecvError},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
 };
 
+use hashbrown::HashSet;
 #[cfg(feature = "gzip")]
 use libafl_bolts::compress::GzipCompressor;
 use serde::{Deserialize, Serialize};
@@ -24,7 +30,7 @@ use super::{
 };
 use crate::{
     corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
-    inputs::Input,
+    inputs::{Input, InputCodec, NativeCodec},
     Error, HasMetadata,
 };
 
@@ -47,22 +53,214 @@ fn try_create_new<P: AsRef<Path>>(path: P) -> Result<Option<File>, io::Error> {
     }
 }
 
+/// A job for the background writer of an [`InMemoryOnDiskCorpus`]
+enum WriteJob<I> {
+    /// Write `input` to `path`, and the already serialized metadata, if any
+    Write {
+        path: PathBuf,
+        input: I,
+        metadata: Option<(PathBuf, Vec<u8>)>,
+    },
+    /// Sync all previous writes to disk, then acknowledge
+    Flush(SyncSender<()>),
+}
+
+/// The background writer of an [`InMemoryOnDiskCorpus`], see [`InMemoryOnDiskCorpus::with_write_behind`].
+///
+/// Dropping it waits until all queued writes hit the disk.
+struct WriteBehind<I> {
+    sender: Option<SyncSender<WriteJob<I>>>,
+    /// Input files that are queued, but not yet synced to disk
+    pending: Arc<Mutex<HashSet<PathBuf>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl<I> fmt::Debug for WriteBehind<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WriteBehind")
+            .field("pending", &self.pending.lock().unwrap().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Writes an input to a file, with the [`InputCodec`] of the corpus
+type WriteInput<I> = Box<dyn Fn(&I, &Path) -> Result<(), Error> + Send>;
+
+impl<I> WriteBehind<I>
+where
+    I: Send + 'static,
+{
+    fn spawn(
+        queue_len: usize,
+        fsync_batch: usize,
+        write_input: WriteInput<I>,
+    ) -> Result<Self, Error> {
+        let (sender, receiver) = mpsc::sync_channel(queue_len);
+        let pending = Arc::new(Mutex::new(HashSet::new()));
+        let thread_pending = pending.clone();
+        let handle = thread::Builder::new()
+            .name("corpus_writer".into())
+            .spawn(move || {
+                Self::run(&receiver, &thread_pending, fsync_batch.max(1), &write_input);
+            })?;
+        Ok(Self {
+            sender: Some(sender),
+            pending,
+            handle: Some(handle),
+        })
+    }
+
+    fn run(
+        receiver: &Receiver<WriteJob<I>>,
+        pending: &Mutex<HashSet<PathBuf>>,
+        fsync_batch: usize,
+        write_input: &WriteInput<I>,
+    ) {
+        // Files written, but not yet synced
+        let mut batch = Vec::with_capacity(fsync_batch);
+        loop {
+            let job = match receiver.try_recv() {
+                Ok(job) => job,
+                Err(TryRecvError::Empty) => {
+                    // We caught up, good time to sync.
+                    Self::sync(&mut batch, pending);
+                    match receiver.recv() {
+                        Ok(job) => job,
+                        Err(_) => break,
+                    }
+                }
+                Err(TryRecvError::Disconnected) => break,
+            };
+            match job {
+                WriteJob::Write {
+                    path,
+                    input,
+                    metadata,
+                } => {
+                    if let Some((metafile_path, serialized)) = metadata {
+                        if let Err(err) = write_metadata(&metafile_path, &serialized) {
+                            log::error!(
+                                "Failed to write metadata {}: {err}",
+                                metafile_path.display()
+                            );
+                        }
+                        batch.push(metafile_path);
+                    }
+                    if let Err(err) = write_input(&input, &path) {
+                        log::error!("Failed to write testcase {}: {err}", path.display());
+                    }
+                    batch.push(path);
+                    if batch.len() >= fsync_batch {
+                        Self::sync(&mut batch, pending);
+                    }
+                }
+                WriteJob::Flush(ack) => {
+                    Self::sync(&mut batch, pending);
+                    _ = ack.send(());
+                }
+            }
+        }
+        Self::sync(&mut batch, pending);
+    }
+
+    /// `fsync`s all written files in the batch, and marks them as no longer pending
+    fn sync(batch: &mut Vec<PathBuf>, pending: &Mutex<HashSet<PathBuf>>) {
+        if batch.is_empty() {
+            return;
+        }
+        for path in batch.iter() {
+            if let Err(err) = File::open(path).and_then(|file| file.sync_all()) {
+                log::error!("Failed to sync {}: {err}", path.display());
+            }
+        }
+        let mut pending = pending.lock().unwrap();
+        for path in batch.drain(..) {
+            pending.remove(&path);
+        }
+    }
+}
+
+impl<I> WriteBehind<I> {
+    fn queue(
+        &self,
+        path: PathBuf,
+        input: I,
+        metadata: Option<(PathBuf, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        self.pending.lock().unwrap().insert(path.clone());
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(WriteJob::Write {
+                path,
+                input,
+                metadata,
+            })
+            .map_err(|_| Error::illegal_state("The corpus writer thread has exited"))
+    }
+
+    fn is_pending(&self, path: &Path) -> bool {
+        self.pending.lock().unwrap().contains(path)
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        let exited = || Error::illegal_state("The corpus writer thread has exited");
+        let (ack_sender, ack_receiver) = mpsc::sync_channel(1);
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(WriteJob::Flush(ack_sender))
+            .map_err(|_| exited())?;
+        ack_receiver.recv().map_err(|_| exited())
+    }
+}
+
+impl<I> Drop for WriteBehind<I> {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        if let Some(handle) = self.handle.take() {
+            if handle.join().is_err() {
+                log::error!("The corpus writer thread panicked");
+            }
+        }
+    }
+}
+
+/// Writes serialized metadata to `metafile_path`, using a temporary file
+fn write_metadata(metafile_path: &Path, serialized: &[u8]) -> Result<(), Error> {
+    let mut tmpfile_path = metafile_path.to_path_buf();
+    tmpfile_path.set_file_name(format!(
+        ".{}.tmp",
+        metafile_path.file_name().unwrap().to_string_lossy()
+    ));
+    let mut tmpfile = File::create(&tmpfile_path)?;
+    tmpfile.write_all(serialized)?;
+    fs::rename(&tmpfile_path, metafile_path)?;
+    Ok(())
+}
+
 /// A corpus able to store [`Testcase`]s to disk, while also keeping all of them in memory.
 ///
 /// Metadata is written to a `.<filename>.metadata` file in the same folder by default.
+/// The inputs are written with the [`InputCodec`] `C`, see [`InMemoryOnDiskCorpus::with_codec`].
+/// To move disk writes off the fuzzing thread, see [`InMemoryOnDiskCorpus::with_write_behind`].
 #[cfg(feature = "std")]
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
-pub struct InMemoryOnDiskCorpus<I> {
+pub struct InMemoryOnDiskCorpus<I, C = NativeCodec> {
     inner: InMemoryCorpus<I>,
     dir_path: PathBuf,
     meta_format: Option<OnDiskMetadataFormat>,
     prefix: Option<String>,
     locking: bool,
+    codec: C,
+    #[serde(skip, default = "Option::default")]
+    write_behind: Option<Arc<WriteBehind<I>>>,
 }
 
-impl<I> Corpus for InMemoryOnDiskCorpus<I>
+impl<I, C> Corpus for InMemoryOnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     type Input = I;
 
@@ -190,7 +388,8 @@ where
                     "No file path set for testcase. Could not load inputs.",
                 ));
             };
-            let input = I::from_file(file_path)?;
+            self.flush_pending(file_path)?;
+            let input = self.codec.read_file(file_path)?;
             testcase.set_input(input);
         }
         Ok(())
@@ -208,13 +407,21 @@ where
                 "No input available for testcase. Could not store anything.",
             ));
         };
-        input.to_file(file_path)
+        // Don't let a queued write overwrite this one later
+        self.flush_pending(file_path)?;
+        self.codec.write_file(input, file_path)
+    }
+
+    /// Waits for the queued writes of the write-behind writer, if any
+    fn flush(&mut self) -> Result<(), Error> {
+        InMemoryOnDiskCorpus::flush(self)
     }
 }
 
-impl<I> HasTestcase for InMemoryOnDiskCorpus<I>
+impl<I, C> HasTestcase for InMemoryOnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     fn testcase(
         &self,
@@ -313,8 +520,78 @@ impl<I> InMemoryOnDiskCorpus<I> {
             meta_format,
             prefix,
             locking,
+            codec: NativeCodec,
+            write_behind: None,
         })
     }
+}
+
+impl<I, C> InMemoryOnDiskCorpus<I, C> {
+    /// Writes and reads the inputs with the given [`InputCodec`], instead of the [`NativeCodec`]
+    /// using [`Input::to_file`] and [`Input::from_file`].
+    ///
+    /// The inputs already in the corpus directory have to be encoded with the same codec.
+    /// Call it before [`InMemoryOnDiskCorpus::with_write_behind`], the write-behind writer is dropped otherwise.
+    pub fn with_codec<C2>(self, codec: C2) -> InMemoryOnDiskCorpus<I, C2>
+    where
+        C2: InputCodec<I>,
+    {
+        InMemoryOnDiskCorpus {
+            inner: self.inner,
+            dir_path: self.dir_path,
+            meta_format: self.meta_format,
+            prefix: self.prefix,
+            locking: self.locking,
+            codec,
+            write_behind: None,
+        }
+    }
+
+    /// The [`InputCodec`] of the inputs of this corpus
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Moves all disk writes of this corpus to a background thread, so that adding a [`Testcase`]
+    /// does not block the fuzzer on slow storage.
+    ///
+    /// At most `queue_len` writes can be outstanding; after that, adding blocks until the writer caught up.
+    /// Written files are `fsync`ed in batches of `fsync_batch`, or whenever the writer is idle.
+    /// Loading an input that is still queued waits for it to be written.
+    /// All queued writes are flushed on drop, and when a [`crate::state::StdState`] restarts.
+    ///
+    /// The writer is not serialized with the corpus, so it has to be enabled again after a restart.
+    pub fn with_write_behind(mut self, queue_len: usize, fsync_batch: usize) -> Result<Self, Error>
+    where
+        I: Input + Send + 'static,
+        C: InputCodec<I> + Clone + Send + 'static,
+    {
+        let codec = self.codec.clone();
+        self.write_behind = Some(Arc::new(WriteBehind::spawn(
+            queue_len,
+            fsync_batch,
+            Box::new(move |input, path| codec.write_file(input, path)),
+        )?));
+        Ok(self)
+    }
+
+    /// Waits until all writes queued by the write-behind writer are on disk.
+    ///
+    /// Does nothing if [`InMemoryOnDiskCorpus::with_write_behind`] was not used.
+    pub fn flush(&self) -> Result<(), Error> {
+        match &self.write_behind {
+            Some(write_behind) => write_behind.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flushes the write-behind writer, if the file at `path` is not written yet
+    fn flush_pending(&self, path: &Path) -> Result<(), Error> {
+        match &self.write_behind {
+            Some(write_behind) if write_behind.is_pending(path) => write_behind.flush(),
+            _ => Ok(()),
+        }
+    }
 
     /// Sets the filename for a [`Testcase`].
     /// If an error gets returned from the corpus (i.e., file exists), we'll have to retry with a different filename.
@@ -350,6 +627,7 @@ impl<I> InMemoryOnDiskCorpus<I> {
 
             let new_file_path = self.dir_path.join(&new_filename);
 
+            self.flush_pending(testcase.file_path().as_ref().unwrap())?;
             fs::rename(testcase.file_path().as_ref().unwrap(), &new_file_path)?;
 
             let new_metadata_path = {
@@ -379,6 +657,7 @@ impl<I> InMemoryOnDiskCorpus<I> {
     fn save_testcase(&self, testcase: &mut Testcase<I>, id: CorpusId) -> Result<(), Error>
     where
         I: Input,
+        C: InputCodec<I>,
     {
         let file_name_orig = testcase.filename_mut().take().unwrap_or_else(|| {
             // TODO walk entry metadata to ask for pieces of filename (e.g. :havoc in AFL)
@@ -410,19 +689,16 @@ impl<I> InMemoryOnDiskCorpus<I> {
         }
         *testcase.filename_mut() = Some(file_name);
 
+        let mut metadata = None;
         if self.meta_format.is_some() {
             let metafile_name = format!(".{}.metadata", testcase.filename().as_ref().unwrap());
             let metafile_path = self.dir_path.join(&metafile_name);
-            let mut tmpfile_path = metafile_path.clone();
-            tmpfile_path.set_file_name(format!(".{metafile_name}.tmp",));
 
             let ondisk_meta = OnDiskMetadata {
                 metadata: testcase.metadata_map(),
                 exec_time: testcase.exec_time(),
             };
 
-            let mut tmpfile = File::create(&tmpfile_path)?;
-
             let json_error =
                 |err| Error::serialize(format!("Failed to json-ify metadata: {err:?}"));
 
@@ -438,17 +714,29 @@ impl<I> InMemoryOnDiskCorpus<I> {
                 OnDiskMetadataFormat::JsonGzip => GzipCompressor::new()
                     .compress(&serde_json::to_vec_pretty(&ondisk_meta).map_err(json_error)?),
             };
-            tmpfile.write_all(&serialized)?;
-            fs::rename(&tmpfile_path, &metafile_path)?;
-            *testcase.metadata_path_mut() = Some(metafile_path);
+            *testcase.metadata_path_mut() = Some(metafile_path.clone());
+            metadata = Some((metafile_path, serialized));
         }
 
+        if let Some(write_behind) = &self.write_behind {
+            let (Some(file_path), Some(input)) = (testcase.file_path(), testcase.input()) else {
+                return Err(Error::illegal_argument(
+                    "No file path or input set for testcase. Could not queue it for writing.",
+                ));
+            };
+            return write_behind.queue(file_path.clone(), input.clone(), metadata);
+        }
+
+        if let Some((metafile_path, serialized)) = metadata {
+            write_metadata(&metafile_path, &serialized)?;
+        }
         self.store_input_from(testcase)?;
         Ok(())
     }
 
     fn remove_testcase(&self, testcase: &Testcase<I>) -> Result<(), Error> {
         if let Some(filename) = testcase.filename() {
+            self.flush_pending(&self.dir_path.join(filename))?;
             fs::remove_file(self.dir_path.join(filename))?;
             if self.meta_format.is_some() {
                 fs::remove_file(self.dir_path.join(format!(".{filename}.metadata")))?;
@@ -473,7 +761,11 @@ impl<I> InMemoryOnDiskCorpus<I> {
 mod tests {
     use std::{env, fs, io::Write};
 
-    use super::{create_new, try_create_new};
+    use super::{create_new, try_create_new, InMemoryOnDiskCorpus};
+    use crate::{
+        corpus::{Corpus, Testcase},
+        inputs::{BytesInput, InputCodec, JsonCodec, PostcardCodec},
+    };
 
     #[test]
     fn test() {
@@ -491,4 +783,60 @@ mod tests {
         drop(f);
         fs::remove_file(path).unwrap();
     }
+
+    #[test]
+    fn test_write_behind() {
+        let dir = env::temp_dir().join("libafl_write_behind_test");
+        _ = fs::remove_dir_all(&dir);
+        let mut corpus = InMemoryOnDiskCorpus::<BytesInput>::new(&dir)
+            .unwrap()
+            .with_write_behind(2, 4)
+            .unwrap();
+
+        for i in 0..10_u8 {
+            let mut testcase = Testcase::new(BytesInput::new(vec![i; 16]));
+            *testcase.filename_mut() = Some(format!("tc{i}"));
+            corpus.add(testcase).unwrap();
+        }
+
+        // Loading waits for pending writes
+        let id = corpus.last().unwrap();
+        let input = corpus.cloned_input_for_id(id).unwrap();
+        assert_eq!(input, BytesInput::new(vec![9; 16]));
+
+        corpus.flush().unwrap();
+        assert!(dir.join(".tc0.metadata").exists());
+        assert_eq!(fs::read(dir.join("tc3")).unwrap(), [3; 16]);
+
+        corpus.remove(id).unwrap();
+        assert!(!dir.join("tc9").exists());
+
+        drop(corpus);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_codec() {
+        let dir = env::temp_dir().join("libafl_codec_test");
+        _ = fs::remove_dir_all(&dir);
+        let mut corpus = InMemoryOnDiskCorpus::<BytesInput>::no_meta(&dir)
+            .unwrap()
+            .with_codec(JsonCodec);
+
+        let mut testcase = Testcase::new(BytesInput::new(b"libafl".to_vec()));
+        *testcase.filename_mut() = Some("tc".into());
+        let id = corpus.add(testcase).unwrap();
+        assert!(fs::read(dir.join("tc"))
+            .unwrap()
+            .starts_with(b"#libafl-input json v1\n"));
+        let input = corpus.cloned_input_for_id(id).unwrap();
+        assert_eq!(input, BytesInput::new(b"libafl".to_vec()));
+
+        // inputs of another codec are rejected
+        let decoded: Result<BytesInput, _> = PostcardCodec.read_file(&dir.join("tc"));
+        assert!(decoded.is_err());
+
+        drop(corpus);
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,200 @@
+//! Merges several corpora into one, keeping only the entries that add coverage.
+//!
+//! This is the equivalent of `libfuzzer -merge=1` or `afl-cmin`, but driven by the executor, observers and feedbacks
+//! of the fuzzer at hand: every input of the source directories is replayed, smallest first, and kept only if the
+//! feedback of the fuzzer deems it interesting. The kept inputs end up in the corpus of the state, so a fresh state
+//! with an [`crate::corpus::OnDiskCorpus`] and a [`crate::feedbacks::MaxMapFeedback`] yields the merged corpus on disk.
+
+use alloc::vec::Vec;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use hashbrown::HashSet;
+use libafl_bolts::hash_std;
+
+use crate::{
+    events::{EventFirer, LogSeverity},
+    fuzzer::{Evaluator, ExecuteInputResult},
+    inputs::Input,
+    state::UsesState,
+    Error,
+};
+
+/// What a [`CorpusMerger`] did with the inputs it found
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MergeStats {
+    /// Files found in the source directories
+    pub files: usize,
+    /// Files skipped because another file had the same content
+    pub duplicates: usize,
+    /// Files that could not be loaded as an input
+    pub invalid: usize,
+    /// Inputs added to the corpus, as they contributed coverage
+    pub kept: usize,
+    /// Inputs that triggered the objective, and went to the solutions
+    pub solutions: usize,
+}
+
+/// Replays the inputs of several directories and keeps the ones adding coverage.
+///
+/// Inputs are deduplicated by content and replayed from the smallest to the largest, so that
+/// the smallest input reaching a coverage point first is the one kept.
+/// Files and directories starting with a `.`, such as corpus metadata, are ignored.
+#[derive(Debug, Clone)]
+pub struct CorpusMerger {
+    dirs: Vec<PathBuf>,
+    max_size: Option<usize>,
+}
+
+impl CorpusMerger {
+    /// Creates a new [`CorpusMerger`] for the inputs found, recursively, in `dirs`
+    #[must_use]
+    pub fn new(dirs: Vec<PathBuf>) -> Self {
+        Self {
+            dirs,
+            max_size: None,
+        }
+    }
+
+    /// Skips the files larger than `max_size` bytes
+    #[must_use]
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = Some(max_size);
+        self
+    }
+
+    /// The directories the inputs are loaded from
+    #[must_use]
+    pub fn dirs(&self) -> &[PathBuf] {
+        &self.dirs
+    }
+
+    /// Replays all inputs through `fuzzer`, which adds the interesting ones to the corpus of `state`.
+    ///
+    /// The state should be fresh, else the feedback already knows some coverage and fewer inputs are kept.
+    pub fn merge<E, EM, Z>(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        manager: &mut EM,
+        state: &mut E::State,
+    ) -> Result<MergeStats, Error>
+    where
+        E: UsesState,
+        EM: EventFirer<State = E::State>,
+        Z: Evaluator<E, EM, State = E::State>,
+    {
+        let mut stats = MergeStats::default();
+        let mut files = Vec::new();
+        for dir in &self.dirs {
+            if !dir.exists() {
+                return Err(Error::illegal_argument(format!(
+                    "Corpus directory {} does not exist",
+                    dir.display()
+                )));
+            }
+            collect_files(dir, &mut files)?;
+        }
+        stats.files = files.len();
+
+        let mut seen = HashSet::new();
+        let mut candidates = Vec::with_capacity(files.len());
+        for path in files {
+            let bytes = fs::read(&path)?;
+            if self.max_size.is_some_and(|max_size| bytes.len() > max_size) {
+                continue;
+            }
+            if seen.insert(hash_std(&bytes)) {
+                candidates.push((bytes.len(), path));
+            } else {
+                stats.duplicates += 1;
+            }
+        }
+        candidates.sort_unstable();
+
+        manager.log(
+            state,
+            LogSeverity::Info,
+            format!(
+                "Merging {} unique inputs out of {} files",
+                candidates.len(),
+                stats.files
+            ),
+        )?;
+
+        for (_, path) in candidates {
+            let input = match E::Input::from_file(&path) {
+                Ok(input) => input,
+                Err(err) => {
+                    log::warn!("Skipping {}: {err}", path.display());
+                    stats.invalid += 1;
+                    continue;
+                }
+            };
+            match fuzzer.evaluate_input(state, executor, manager, input)?.0 {
+                ExecuteInputResult::Corpus => stats.kept += 1,
+                ExecuteInputResult::Solution => stats.solutions += 1,
+                ExecuteInputResult::None => {}
+            }
+        }
+
+        manager.log(
+            state,
+            LogSeverity::Info,
+            format!(
+                "Merge done: kept {} inputs, {} solutions, {} duplicates, {} invalid",
+                stats.kept, stats.solutions, stats.duplicates, stats.invalid
+            ),
+        )?;
+        Ok(stats)
+    }
+}
+
+/// Walks `dir` recursively, skipping hidden entries
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if dir.is_file() {
+        files.push(dir.to_path_buf());
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = fs::metadata(&path)?;
+        if file_type.is_dir() {
+            collect_files(&path, files)?;
+        } else if file_type.is_file() && file_type.len() > 0 {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{fs, path::PathBuf};
+
+    use super::collect_files;
+
+    #[test]
+    fn test_collect_files_skips_hidden() {
+        let dir = std::env::temp_dir().join(format!("libafl-merge-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("queue")).unwrap();
+        fs::create_dir_all(dir.join(".state")).unwrap();
+        fs::write(dir.join("queue/a"), b"a").unwrap();
+        fs::write(dir.join("queue/.a.metadata"), b"{}").unwrap();
+        fs::write(dir.join(".state/b"), b"b").unwrap();
+        fs::write(dir.join("empty"), b"").unwrap();
+
+        let mut files: Vec<PathBuf> = Vec::new();
+        collect_files(&dir, &mut files).unwrap();
+        assert_eq!(files, vec![dir.join("queue/a")]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
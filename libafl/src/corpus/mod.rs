@@ -6,6 +6,12 @@ pub use testcase::{HasTestcase, SchedulerTestcaseMetadata, Testcase};
 pub mod inmemory;
 pub use inmemory::InMemoryCorpus;
 
+pub mod fixed;
+pub use fixed::FixedCorpus;
+
+pub mod query;
+pub use query::{CorpusQuery, QueryableCorpus};
+
 #[cfg(feature = "std")]
 pub mod inmemory_ondisk;
 #[cfg(feature = "std")]
@@ -21,6 +27,21 @@ pub mod cached;
 #[cfg(feature = "std")]
 pub use cached::CachedOnDiskCorpus;
 
+#[cfg(feature = "std")]
+pub mod afl_output;
+#[cfg(feature = "std")]
+pub use afl_output::{AflEntryKind, AflFilenameMetadata, AflOutputDir, AflSolutionsCorpus};
+
+#[cfg(feature = "std")]
+pub mod merge;
+#[cfg(feature = "std")]
+pub use merge::{CorpusMerger, MergeStats};
+
+#[cfg(feature = "remote_corpus")]
+pub mod remote;
+#[cfg(feature = "remote_corpus")]
+pub use remote::{HttpObjectStore, RemoteBackend, RemoteCorpus};
+
 #[cfg(all(feature = "cmin", unix))]
 pub mod minimizer;
 use core::{cell::RefCell, fmt};
@@ -197,6 +218,13 @@ pub trait Corpus: Sized {
         let mut testcase = self.get(id)?.borrow_mut();
         Ok(testcase.load_input(self)?.clone())
     }
+
+    /// Waits until the pending writes of the corpus are done, for example before stopping.
+    ///
+    /// Does nothing by default, for the corpora writing synchronously.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Trait for types which track the current corpus index
@@ -17,7 +17,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     corpus::{CachedOnDiskCorpus, Corpus, CorpusId, HasTestcase, Testcase},
-    inputs::Input,
+    inputs::{Input, InputCodec, NativeCodec},
     Error,
 };
 
@@ -49,17 +49,19 @@ pub struct OnDiskMetadata<'a> {
 /// A corpus able to store [`Testcase`]s to disk, and load them from disk, when they are being used.
 ///
 /// Metadata is written to a `.<filename>.metadata` file in the same folder by default.
+/// The inputs are written with the [`InputCodec`] `C`, see [`OnDiskCorpus::with_codec`].
 #[derive(Default, Serialize, Deserialize, Clone, Debug)]
-pub struct OnDiskCorpus<I> {
+pub struct OnDiskCorpus<I, C = NativeCodec> {
     /// The root directory backing this corpus
     dir_path: PathBuf,
     /// We wrapp a cached corpus and set its size to 1.
-    inner: CachedOnDiskCorpus<I>,
+    inner: CachedOnDiskCorpus<I, C>,
 }
 
-impl<I> Corpus for OnDiskCorpus<I>
+impl<I, C> Corpus for OnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     type Input = I;
     /// Returns the number of all enabled entries
@@ -173,11 +175,17 @@ where
     fn store_input_from(&self, testcase: &Testcase<Self::Input>) -> Result<(), Error> {
         self.inner.store_input_from(testcase)
     }
+
+    #[inline]
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
 }
 
-impl<I> HasTestcase for OnDiskCorpus<I>
+impl<I, C> HasTestcase for OnDiskCorpus<I, C>
 where
     I: Input,
+    C: InputCodec<I>,
 {
     fn testcase(&self, id: CorpusId) -> Result<Ref<Testcase<I>>, Error> {
         Ok(self.get(id)?.borrow())
@@ -271,6 +279,19 @@ impl<I> OnDiskCorpus<I> {
             )?,
         })
     }
+}
+
+impl<I, C> OnDiskCorpus<I, C> {
+    /// Writes and reads the inputs with the given [`InputCodec`], see [`crate::corpus::InMemoryOnDiskCorpus::with_codec`]
+    pub fn with_codec<C2>(self, codec: C2) -> OnDiskCorpus<I, C2>
+    where
+        C2: InputCodec<I>,
+    {
+        OnDiskCorpus {
+            dir_path: self.dir_path,
+            inner: self.inner.with_codec(codec),
+        }
+    }
 
     /// Path to the corpus directory associated with this corpus
     pub fn dir_path(&self) -> &PathBuf {
@@ -0,0 +1,359 @@
+//! Queries over the [`Testcase`]s of a [`Corpus`], filtered and sorted by their metadata.
+//!
+//! Pruning stages, minimizers and schedulers often need "all entries with metadata `M`",
+//! or "the fastest entries first". Instead of hand-rolling a full scan with downcasts,
+//! use [`QueryableCorpus::query`]:
+//!
+//! ```rust
+//! # use libafl::corpus::{Corpus, InMemoryCorpus, QueryableCorpus, Testcase};
+//! # use libafl::inputs::BytesInput;
+//! # use core::time::Duration;
+//! let mut corpus = InMemoryCorpus::<BytesInput>::new();
+//! let mut testcase = Testcase::new(BytesInput::new(vec![0]));
+//! testcase.set_exec_time(Duration::from_millis(3));
+//! corpus.add(testcase).unwrap();
+//!
+//! let fast = corpus
+//!     .query()
+//!     .exec_time_in(..Duration::from_millis(10))
+//!     .sort_by_exec_time()
+//!     .ids();
+//! assert_eq!(fast.len(), 1);
+//! ```
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{cmp::Ordering, fmt, ops::RangeBounds, time::Duration};
+
+use libafl_bolts::serdeany::SerdeAny;
+
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase},
+    feedbacks::MapIndexesMetadata,
+    HasMetadata,
+};
+
+/// The coverage size of a [`Testcase`], i.e., the number of map indexes it covers.
+///
+/// This is only known if a feedback tracked the indexes, see [`struct@MapIndexesMetadata`].
+#[must_use]
+pub fn coverage_size<I>(testcase: &Testcase<I>) -> Option<usize> {
+    testcase
+        .metadata_map()
+        .get::<MapIndexesMetadata>()
+        .map(|meta| meta.list.len())
+}
+
+type Predicate<'a, I> = Box<dyn Fn(&Testcase<I>) -> bool + 'a>;
+type Comparator<'a, I> = Box<dyn Fn(&Testcase<I>, &Testcase<I>) -> Ordering + 'a>;
+
+/// A lazily evaluated query over the entries of a [`Corpus`].
+///
+/// Created by [`QueryableCorpus::query`]. All filters must match for an entry to be returned.
+/// The query is evaluated when calling [`CorpusQuery::ids`], [`CorpusQuery::count`] or [`CorpusQuery::first`].
+pub struct CorpusQuery<'a, C>
+where
+    C: Corpus,
+{
+    corpus: &'a C,
+    include_disabled: bool,
+    filters: Vec<Predicate<'a, C::Input>>,
+    sort: Option<Comparator<'a, C::Input>>,
+    reverse: bool,
+}
+
+impl<C> fmt::Debug for CorpusQuery<'_, C>
+where
+    C: Corpus,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CorpusQuery")
+            .field("include_disabled", &self.include_disabled)
+            .field("filters", &self.filters.len())
+            .field("sorted", &self.sort.is_some())
+            .field("reverse", &self.reverse)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, C> CorpusQuery<'a, C>
+where
+    C: Corpus,
+{
+    /// Creates a new query matching all enabled entries of `corpus`
+    #[must_use]
+    pub fn new(corpus: &'a C) -> Self {
+        Self {
+            corpus,
+            include_disabled: false,
+            filters: Vec::new(),
+            sort: None,
+            reverse: false,
+        }
+    }
+
+    /// Also consider disabled entries
+    #[must_use]
+    pub fn include_disabled(mut self) -> Self {
+        self.include_disabled = true;
+        self
+    }
+
+    /// Only match entries for which `predicate` returns `true`
+    #[must_use]
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Testcase<C::Input>) -> bool + 'a,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Only match entries carrying metadata of type `M`
+    #[must_use]
+    pub fn with_metadata<M>(self) -> Self
+    where
+        M: SerdeAny,
+    {
+        self.filter(HasMetadata::has_metadata::<M>)
+    }
+
+    /// Only match entries that do not carry metadata of type `M`
+    #[must_use]
+    pub fn without_metadata<M>(self) -> Self
+    where
+        M: SerdeAny,
+    {
+        self.filter(|testcase| !testcase.has_metadata::<M>())
+    }
+
+    /// Only match entries with metadata of type `M` for which `predicate` returns `true`
+    #[must_use]
+    pub fn metadata_matches<M, F>(self, predicate: F) -> Self
+    where
+        M: SerdeAny,
+        F: Fn(&M) -> bool + 'a,
+    {
+        self.filter(move |testcase| testcase.metadata_map().get::<M>().is_some_and(&predicate))
+    }
+
+    /// Only match entries whose execution time is known and lies in `range`
+    #[must_use]
+    pub fn exec_time_in<R>(self, range: R) -> Self
+    where
+        R: RangeBounds<Duration> + 'a,
+    {
+        self.filter(move |testcase| {
+            testcase
+                .exec_time()
+                .is_some_and(|exec_time| range.contains(&exec_time))
+        })
+    }
+
+    /// Only match entries whose [`coverage_size`] is known and lies in `range`
+    #[must_use]
+    pub fn coverage_size_in<R>(self, range: R) -> Self
+    where
+        R: RangeBounds<usize> + 'a,
+    {
+        self.filter(move |testcase| {
+            coverage_size(testcase).is_some_and(|size| range.contains(&size))
+        })
+    }
+
+    /// Sort the resulting entries using `compare`
+    #[must_use]
+    pub fn sort_by<F>(mut self, compare: F) -> Self
+    where
+        F: Fn(&Testcase<C::Input>, &Testcase<C::Input>) -> Ordering + 'a,
+    {
+        self.sort = Some(Box::new(compare));
+        self
+    }
+
+    /// Sort the resulting entries by the key extracted with `key`
+    #[must_use]
+    pub fn sort_by_key<K, F>(self, key: F) -> Self
+    where
+        K: Ord,
+        F: Fn(&Testcase<C::Input>) -> K + 'a,
+    {
+        self.sort_by(move |a, b| key(a).cmp(&key(b)))
+    }
+
+    /// Sort the resulting entries by ascending execution time, entries without execution time last
+    #[must_use]
+    pub fn sort_by_exec_time(self) -> Self {
+        self.sort_by_key(|testcase| (testcase.exec_time().is_none(), *testcase.exec_time()))
+    }
+
+    /// Sort the resulting entries by ascending [`coverage_size`], entries without coverage information first
+    #[must_use]
+    pub fn sort_by_coverage_size(self) -> Self {
+        self.sort_by_key(coverage_size)
+    }
+
+    /// Reverse the order of the results
+    #[must_use]
+    pub fn reverse(mut self) -> Self {
+        self.reverse = !self.reverse;
+        self
+    }
+
+    fn matches(&self, testcase: &Testcase<C::Input>) -> bool {
+        self.filters.iter().all(|predicate| predicate(testcase))
+    }
+
+    fn candidates(&self) -> Vec<CorpusId> {
+        if self.include_disabled {
+            (0..self.corpus.count_all())
+                .map(|nth| self.corpus.nth_from_all(nth))
+                .collect()
+        } else {
+            self.corpus.ids().collect()
+        }
+    }
+
+    fn testcase(&self, id: CorpusId) -> core::cell::Ref<'a, Testcase<C::Input>> {
+        self.corpus
+            .get_from_all(id)
+            .expect("CorpusId returned by the corpus itself must be valid")
+            .borrow()
+    }
+
+    /// Evaluates the query, returning the ids of all matching entries
+    ///
+    /// # Panics
+    /// Panics if one of the entries is currently borrowed mutably.
+    #[must_use]
+    pub fn ids(&self) -> Vec<CorpusId> {
+        let mut ids: Vec<CorpusId> = self
+            .candidates()
+            .into_iter()
+            .filter(|id| self.matches(&self.testcase(*id)))
+            .collect();
+        if let Some(compare) = &self.sort {
+            ids.sort_by(|a, b| compare(&self.testcase(*a), &self.testcase(*b)));
+        }
+        if self.reverse {
+            ids.reverse();
+        }
+        ids
+    }
+
+    /// Evaluates the query, returning the number of matching entries
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.candidates()
+            .into_iter()
+            .filter(|id| self.matches(&self.testcase(*id)))
+            .count()
+    }
+
+    /// Evaluates the query, returning the first matching entry, according to the sort order
+    #[must_use]
+    pub fn first(&self) -> Option<CorpusId> {
+        if self.sort.is_none() && !self.reverse {
+            self.candidates()
+                .into_iter()
+                .find(|id| self.matches(&self.testcase(*id)))
+        } else {
+            self.ids().first().copied()
+        }
+    }
+}
+
+/// Extension trait to run a [`CorpusQuery`] on any [`Corpus`]
+pub trait QueryableCorpus: Corpus {
+    /// Starts a new [`CorpusQuery`] over all enabled entries of this corpus
+    fn query(&self) -> CorpusQuery<'_, Self> {
+        CorpusQuery::new(self)
+    }
+}
+
+impl<C> QueryableCorpus for C where C: Corpus {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+    use core::time::Duration;
+
+    use libafl_bolts::impl_serdeany;
+    use serde::{Deserialize, Serialize};
+
+    use super::QueryableCorpus;
+    use crate::{
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        feedbacks::MapIndexesMetadata,
+        inputs::BytesInput,
+        HasMetadata,
+    };
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct MarkerMetadata(u8);
+    impl_serdeany!(MarkerMetadata);
+
+    fn corpus() -> InMemoryCorpus<BytesInput> {
+        let mut corpus = InMemoryCorpus::new();
+        for (i, millis) in [30_u64, 10, 20].into_iter().enumerate() {
+            let mut testcase = Testcase::new(BytesInput::new(vec![i as u8]));
+            testcase.set_exec_time(Duration::from_millis(millis));
+            testcase.add_metadata(MapIndexesMetadata::new(vec![0; i + 1]));
+            if i != 1 {
+                testcase.add_metadata(MarkerMetadata(i as u8));
+            }
+            corpus.add(testcase).unwrap();
+        }
+        corpus
+            .add_disabled(Testcase::new(BytesInput::new(vec![3])))
+            .unwrap();
+        corpus
+    }
+
+    #[test]
+    fn test_corpus_query() {
+        let corpus = corpus();
+
+        assert_eq!(corpus.query().count(), 3);
+        assert_eq!(corpus.query().include_disabled().count(), 4);
+        assert_eq!(
+            corpus.query().with_metadata::<MarkerMetadata>().ids(),
+            [CorpusId(0), CorpusId(2)]
+        );
+        assert_eq!(
+            corpus
+                .query()
+                .include_disabled()
+                .without_metadata::<MarkerMetadata>()
+                .ids(),
+            [CorpusId(1), CorpusId(3)]
+        );
+        assert_eq!(
+            corpus
+                .query()
+                .metadata_matches(|meta: &MarkerMetadata| meta.0 == 2)
+                .ids(),
+            [CorpusId(2)]
+        );
+        assert_eq!(
+            corpus.query().sort_by_exec_time().ids(),
+            [CorpusId(1), CorpusId(2), CorpusId(0)]
+        );
+        assert_eq!(
+            corpus
+                .query()
+                .exec_time_in(Duration::from_millis(15)..)
+                .sort_by_exec_time()
+                .reverse()
+                .first(),
+            Some(CorpusId(0))
+        );
+        assert_eq!(
+            corpus.query().coverage_size_in(2..).ids(),
+            [CorpusId(1), CorpusId(2)]
+        );
+        assert_eq!(
+            corpus.query().sort_by_coverage_size().reverse().first(),
+            Some(CorpusId(2))
+        );
+    }
+}
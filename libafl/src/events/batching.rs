@@ -0,0 +1,568 @@
+//! Batching and rate limiting of fired [`Event`]s, for large fleets of clients.
+//!
+//! Wrap an [`EventManager`] in a [`BatchingEventManager`] to coalesce stats updates to a fixed interval,
+//! queue new testcases and flush them in bursts, and limit the bytes per second sent to the broker.
+
+use alloc::{borrow::Cow, boxed::Box, collections::VecDeque, string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+
+use hashbrown::HashMap;
+use libafl_bolts::current_time;
+use serde::Serialize;
+use typed_builder::TypedBuilder;
+
+use crate::{
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, LogSeverity,
+        ProgressReporter,
+    },
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasExecutions, HasLastReportTime, UsesState},
+    Error, HasMetadata,
+};
+
+/// What to do with stats updates that exceed the byte budget of a [`BatchingPolicy`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsDropPolicy {
+    /// Keep the latest value and send it once the budget allows
+    #[default]
+    Delay,
+    /// Discard the update; the next interval sends a fresh value
+    Drop,
+}
+
+/// How a [`BatchingEventManager`] batches and rate limits events.
+#[derive(Debug, Clone, Copy, TypedBuilder)]
+pub struct BatchingPolicy {
+    /// Stats updates are coalesced and sent at most once per interval. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub stats_interval: Duration,
+    /// New testcases are flushed once this many are queued. Defaults to 64.
+    #[builder(default = 64)]
+    pub max_batch: usize,
+    /// New testcases are flushed at the latest this long after they were queued. Defaults to 100ms.
+    #[builder(default = Duration::from_millis(100))]
+    pub flush_interval: Duration,
+    /// The maximum bytes per second sent through the wrapped manager. Unlimited by default.
+    #[builder(default, setter(strip_option))]
+    pub max_bytes_per_sec: Option<u64>,
+    /// What to do with stats updates exceeding `max_bytes_per_sec`
+    #[builder(default)]
+    pub stats_drop: StatsDropPolicy,
+}
+
+impl Default for BatchingPolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// A token bucket of bytes, refilled with `rate` bytes per second, holding at most one second worth of bytes.
+#[derive(Debug, Clone, Copy)]
+struct ByteBudget {
+    rate: u64,
+    available: u64,
+    last_refill: Duration,
+}
+
+impl ByteBudget {
+    fn new(rate: u64) -> Self {
+        Self {
+            rate,
+            available: rate,
+            last_refill: current_time(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = current_time();
+        let elapsed = now.saturating_sub(self.last_refill);
+        let refill = elapsed.as_micros() * u128::from(self.rate) / 1_000_000;
+        self.available = self
+            .available
+            .saturating_add(u64::try_from(refill).unwrap_or(u64::MAX))
+            .min(self.rate);
+        self.last_refill = now;
+    }
+
+    /// Takes `size` bytes from the budget, if available.
+    /// Events larger than the whole budget pass once the bucket is full, so they are delayed but never stuck.
+    fn take(&mut self, size: usize) -> bool {
+        self.refill();
+        let size = size as u64;
+        if self.available >= size || self.available == self.rate {
+            self.available = self.available.saturating_sub(size);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Charges `size` bytes for an event that has to be sent regardless of the budget
+    fn charge(&mut self, size: usize) {
+        self.refill();
+        self.available = self.available.saturating_sub(size as u64);
+    }
+}
+
+/// An [`EventManager`] wrapping another manager, batching and rate limiting the events it fires.
+///
+/// [`Event::UpdateExecStats`], [`Event::UpdateUserStats`] and [`Event::UpdatePerfMonitor`] are coalesced,
+/// only the latest value of each is sent once per [`BatchingPolicy::stats_interval`].
+/// [`Event::NewTestcase`]s are queued and flushed in bursts. All other events are sent right away.
+/// Pending events are flushed in [`EventProcessor::process`], and before restarting or exiting.
+#[derive(Debug)]
+pub struct BatchingEventManager<EM>
+where
+    EM: UsesState,
+{
+    inner: EM,
+    policy: BatchingPolicy,
+    budget: Option<ByteBudget>,
+    testcases: VecDeque<(Event<<EM::State as UsesInput>::Input>, usize)>,
+    oldest_testcase: Duration,
+    exec_stats: Option<Event<<EM::State as UsesInput>::Input>>,
+    #[cfg(feature = "introspection")]
+    perf_monitor: Option<Event<<EM::State as UsesInput>::Input>>,
+    user_stats: HashMap<Cow<'static, str>, Event<<EM::State as UsesInput>::Input>>,
+    last_stats_flush: Duration,
+    dropped_stats: u64,
+}
+
+impl<EM> BatchingEventManager<EM>
+where
+    EM: EventFirer,
+{
+    /// Creates a new [`BatchingEventManager`], batching the events fired by `inner` according to `policy`
+    pub fn new(inner: EM, policy: BatchingPolicy) -> Self {
+        Self {
+            inner,
+            budget: policy.max_bytes_per_sec.map(ByteBudget::new),
+            policy,
+            testcases: VecDeque::new(),
+            oldest_testcase: Duration::ZERO,
+            exec_stats: None,
+            #[cfg(feature = "introspection")]
+            perf_monitor: None,
+            user_stats: HashMap::new(),
+            last_stats_flush: current_time(),
+            dropped_stats: 0,
+        }
+    }
+
+    /// The wrapped event manager
+    #[must_use]
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+
+    /// The wrapped event manager (mutable)
+    pub fn inner_mut(&mut self) -> &mut EM {
+        &mut self.inner
+    }
+
+    /// The number of stats updates discarded because of [`StatsDropPolicy::Drop`]
+    #[must_use]
+    pub fn dropped_stats(&self) -> u64 {
+        self.dropped_stats
+    }
+
+    /// The number of new testcases waiting to be sent
+    #[must_use]
+    pub fn queued_testcases(&self) -> usize {
+        self.testcases.len()
+    }
+
+    /// Sends all pending events right away, ignoring intervals and the byte budget
+    pub fn flush(&mut self, state: &mut EM::State) -> Result<(), Error> {
+        self.flush_testcases(state, true)?;
+        self.flush_stats(state, true)
+    }
+
+    /// Sends the pending events whose interval has passed, as far as the byte budget allows
+    fn flush_due(&mut self, state: &mut EM::State) -> Result<(), Error> {
+        let now = current_time();
+        if self.testcases.len() >= self.policy.max_batch
+            || (!self.testcases.is_empty()
+                && now.saturating_sub(self.oldest_testcase) >= self.policy.flush_interval)
+        {
+            self.flush_testcases(state, false)?;
+        }
+        if now.saturating_sub(self.last_stats_flush) >= self.policy.stats_interval {
+            self.flush_stats(state, false)?;
+        }
+        Ok(())
+    }
+
+    fn flush_testcases(&mut self, state: &mut EM::State, force: bool) -> Result<(), Error> {
+        while let Some((_, size)) = self.testcases.front() {
+            if !force && self.budget.as_mut().is_some_and(|b| !b.take(*size)) {
+                // Keep the rest queued, testcases are never dropped
+                break;
+            }
+            let (event, _) = self.testcases.pop_front().unwrap();
+            self.inner.fire(state, event)?;
+        }
+        if !self.testcases.is_empty() {
+            self.oldest_testcase = current_time();
+        }
+        Ok(())
+    }
+
+    fn flush_stats(&mut self, state: &mut EM::State, force: bool) -> Result<(), Error> {
+        self.last_stats_flush = current_time();
+        #[allow(unused_mut)]
+        let mut pending: Vec<_> = self
+            .exec_stats
+            .take()
+            .into_iter()
+            .chain(self.user_stats.drain().map(|(_, event)| event))
+            .collect();
+        #[cfg(feature = "introspection")]
+        pending.extend(self.perf_monitor.take());
+        for event in pending {
+            if force {
+                self.inner.fire(state, event)?;
+                continue;
+            }
+            let size = postcard::to_allocvec(&event)?.len();
+            if self.budget.as_mut().is_some_and(|b| !b.take(size)) {
+                match self.policy.stats_drop {
+                    StatsDropPolicy::Delay => self.coalesce(event),
+                    StatsDropPolicy::Drop => self.dropped_stats += 1,
+                }
+            } else {
+                self.inner.fire(state, event)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Keeps `event` as the latest value of its stat
+    fn coalesce(&mut self, event: Event<<EM::State as UsesInput>::Input>) {
+        match event {
+            Event::UpdateUserStats { ref name, .. } => {
+                self.user_stats.insert(name.clone(), event);
+            }
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => self.perf_monitor = Some(event),
+            _ => self.exec_stats = Some(event),
+        }
+    }
+}
+
+impl<EM> UsesState for BatchingEventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for BatchingEventManager<EM>
+where
+    EM: EventFirer,
+{
+    fn should_send(&self) -> bool {
+        self.inner.should_send()
+    }
+
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        match event {
+            Event::UpdateExecStats { .. } | Event::UpdateUserStats { .. } => self.coalesce(event),
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => self.coalesce(event),
+            Event::NewTestcase { .. } => {
+                let size = if self.budget.is_some() {
+                    postcard::to_allocvec(&event)?.len()
+                } else {
+                    0
+                };
+                if self.testcases.is_empty() {
+                    self.oldest_testcase = current_time();
+                }
+                self.testcases.push_back((event, size));
+            }
+            _ => {
+                if let Some(budget) = self.budget.as_mut() {
+                    budget.charge(postcard::to_allocvec(&event)?.len());
+                }
+                self.inner.fire(state, event)?;
+            }
+        }
+        self.flush_due(state)
+    }
+
+    #[inline]
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.fire(
+            state,
+            Event::Log {
+                severity_level,
+                message,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<<Self as UsesInput>::Input, Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for BatchingEventManager<EM>
+where
+    EM: EventRestarter + EventFirer,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.flush(state)?;
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for BatchingEventManager<EM>
+where
+    EM: EventProcessor<E, Z> + EventFirer,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.flush_due(state)?;
+        self.inner.process(fuzzer, state, executor)
+    }
+
+    fn on_shutdown(&mut self) -> Result<(), Error> {
+        self.inner.on_shutdown()
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for BatchingEventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    Self::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasCustomBufHandlers for BatchingEventManager<EM>
+where
+    EM: HasCustomBufHandlers,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+/// Progress reports are fired through the [`BatchingEventManager`], so they are coalesced as well.
+impl<EM> ProgressReporter for BatchingEventManager<EM>
+where
+    EM: EventFirer,
+    Self::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasEventManagerId for BatchingEventManager<EM>
+where
+    EM: HasEventManagerId + UsesState,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, string::String, vec::Vec};
+    use core::{marker::PhantomData, time::Duration};
+
+    use super::{BatchingEventManager, BatchingPolicy};
+    use crate::{
+        events::{Event, EventConfig, EventFirer},
+        executors::ExitKind,
+        inputs::{BytesInput, UsesInput},
+        monitors::{AggregatorOps, UserStats, UserStatsValue},
+        state::{NopState, UsesState},
+        Error,
+    };
+
+    /// Records the names of all fired events
+    #[derive(Debug, Default)]
+    struct RecordingEventManager {
+        fired: Vec<String>,
+    }
+
+    impl UsesState for RecordingEventManager {
+        type State = NopState<BytesInput>;
+    }
+
+    impl EventFirer for RecordingEventManager {
+        fn fire(
+            &mut self,
+            _state: &mut Self::State,
+            event: Event<<Self::State as UsesInput>::Input>,
+        ) -> Result<(), Error> {
+            self.fired.push(event.name().into());
+            Ok(())
+        }
+
+        fn should_send(&self) -> bool {
+            true
+        }
+    }
+
+    fn testcase() -> Event<BytesInput> {
+        Event::NewTestcase {
+            input: BytesInput::new(vec![0; 16]),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        }
+    }
+
+    #[test]
+    fn test_batching() {
+        let mut state = NopState::new();
+        let policy = BatchingPolicy::builder()
+            .stats_interval(Duration::MAX)
+            .flush_interval(Duration::MAX)
+            .max_batch(3)
+            .build();
+        let mut mgr = BatchingEventManager::new(RecordingEventManager::default(), policy);
+
+        for executions in 0..100 {
+            mgr.fire(
+                &mut state,
+                Event::UpdateExecStats {
+                    time: Duration::ZERO,
+                    executions,
+                    phantom: PhantomData,
+                },
+            )
+            .unwrap();
+            mgr.fire(
+                &mut state,
+                Event::UpdateUserStats {
+                    name: Cow::Borrowed("edges"),
+                    value: UserStats::new(UserStatsValue::Number(executions), AggregatorOps::None),
+                    phantom: PhantomData,
+                },
+            )
+            .unwrap();
+        }
+        mgr.fire(&mut state, testcase()).unwrap();
+        mgr.fire(&mut state, testcase()).unwrap();
+        assert!(mgr.inner().fired.is_empty());
+        assert_eq!(mgr.queued_testcases(), 2);
+
+        // The third testcase fills the batch
+        mgr.fire(&mut state, testcase()).unwrap();
+        assert_eq!(mgr.inner().fired, ["Testcase"; 3]);
+
+        // Other events are sent right away, stats only once, when flushed
+        mgr.fire(&mut state, Event::Stop).unwrap();
+        mgr.flush(&mut state).unwrap();
+        assert_eq!(
+            mgr.inner().fired[3..],
+            ["Stop", "Client Heartbeat", "UserStats"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "introspection")]
+    fn test_perf_monitor_coalesced_separately() {
+        use alloc::boxed::Box;
+
+        use crate::monitors::ClientPerfMonitor;
+
+        let mut state = NopState::new();
+        let policy = BatchingPolicy::builder()
+            .stats_interval(Duration::MAX)
+            .build();
+        let mut mgr = BatchingEventManager::new(RecordingEventManager::default(), policy);
+
+        mgr.fire(
+            &mut state,
+            Event::UpdateExecStats {
+                time: Duration::ZERO,
+                executions: 1,
+                phantom: PhantomData,
+            },
+        )
+        .unwrap();
+        mgr.fire(
+            &mut state,
+            Event::UpdatePerfMonitor {
+                time: Duration::ZERO,
+                executions: 1,
+                introspection_monitor: Box::new(ClientPerfMonitor::new()),
+                phantom: PhantomData,
+            },
+        )
+        .unwrap();
+        mgr.flush(&mut state).unwrap();
+
+        // The perf monitor update does not replace the exec stats
+        assert_eq!(mgr.inner().fired, ["Client Heartbeat", "PerfMonitor"]);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut state = NopState::new();
+        let policy = BatchingPolicy::builder()
+            .max_batch(1)
+            .max_bytes_per_sec(1)
+            .build();
+        let mut mgr = BatchingEventManager::new(RecordingEventManager::default(), policy);
+
+        // The first testcase drains the budget, the second has to wait for it to refill
+        mgr.fire(&mut state, testcase()).unwrap();
+        mgr.fire(&mut state, testcase()).unwrap();
+        assert_eq!(mgr.inner().fired, ["Testcase"]);
+        assert_eq!(mgr.queued_testcases(), 1);
+    }
+}
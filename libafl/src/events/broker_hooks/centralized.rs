@@ -1,16 +1,12 @@
 use alloc::vec::Vec;
 use core::{fmt::Debug, marker::PhantomData};
 
-#[cfg(feature = "llmp_compression")]
-use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
 use libafl_bolts::{
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
     shmem::ShMemProvider,
     ClientId, Error,
 };
 
-#[cfg(feature = "llmp_compression")]
-use crate::events::COMPRESS_THRESHOLD;
 use crate::{
     events::{BrokerEventResult, Event, _LLMP_TAG_TO_MAIN},
     inputs::Input,
@@ -18,8 +14,6 @@ use crate::{
 
 /// An LLMP-backed event manager for scalable multi-processed fuzzing
 pub struct CentralizedLlmpHook<I> {
-    #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
     phantom: PhantomData<I>,
 }
 
@@ -38,15 +32,13 @@ where
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
         if *msg_tag == _LLMP_TAG_TO_MAIN {
-            #[cfg(feature = "llmp_compression")]
-            let compressor = &self.compressor;
             #[cfg(not(feature = "llmp_compression"))]
             let event_bytes = msg;
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if *_msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
+            let event_bytes = if let Some(codec) = _msg_flags.compression_codec() {
+                compressed = codec.decompress(msg)?;
                 &compressed
             } else {
                 &*msg
@@ -64,12 +56,7 @@ where
 
 impl<I> Debug for CentralizedLlmpHook<I> {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        let mut debug_struct = f.debug_struct("CentralizedLlmpHook");
-
-        #[cfg(feature = "llmp_compression")]
-        let debug_struct = debug_struct.field("compressor", &self.compressor);
-
-        debug_struct
+        f.debug_struct("CentralizedLlmpHook")
             .field("phantom", &self.phantom)
             .finish_non_exhaustive()
     }
@@ -82,8 +69,6 @@ where
     /// Create an event broker from a raw broker.
     pub fn new() -> Result<Self, Error> {
         Ok(Self {
-            #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
             phantom: PhantomData,
         })
     }
@@ -95,7 +80,14 @@ where
         event: &Event<I>,
     ) -> Result<BrokerEventResult, Error> {
         match &event {
-            Event::NewTestcase { .. } | Event::Stop => Ok(BrokerEventResult::Forward),
+            Event::NewTestcase { .. }
+            | Event::Objective { .. }
+            | Event::UpdateUserStats { .. }
+            | Event::Stop
+            | Event::SetStopPolicy { .. } => Ok(BrokerEventResult::Forward),
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => Ok(BrokerEventResult::Forward),
+            // Executions are only sent as heartbeat, to keep the secondaries alive
             _ => Ok(BrokerEventResult::Handled),
         }
     }
@@ -0,0 +1,272 @@
+//! Broker-side suppression of duplicate testcases.
+//!
+//! With many clients, most [`Event::NewTestcase`]s forwarded by the broker are inputs some other client already found.
+//! The [`DedupLlmpHook`] remembers the hashes of recently forwarded inputs and drops repeated ones,
+//! so the other clients don't have to re-evaluate them.
+//!
+//! Clients may additionally announce the coverage hash of each new testcase using the
+//! [`crate::feedbacks::CoverageSummaryFeedback`]. Testcases reaching coverage identical to
+//! a recently forwarded testcase are then dropped as well, even if the inputs differ.
+
+use alloc::{collections::VecDeque, vec::Vec};
+use core::marker::PhantomData;
+
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::{
+    hash_std,
+    llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
+    shmem::ShMemProvider,
+    ClientId,
+};
+
+use crate::{
+    events::{llmp::LLMP_TAG_EVENT_TO_BOTH, Event},
+    inputs::Input,
+    Error,
+};
+
+/// The tag of the [`Event::CustomBuf`] a client announces the coverage hash of its next testcase with.
+/// The buffer holds the hash as little-endian `u64`.
+pub const COVERAGE_SUMMARY_TAG: &str = "libafl_coverage_summary";
+
+/// The default amount of input and coverage hashes a [`DedupLlmpHook`] remembers
+pub const DEFAULT_DEDUP_WINDOW: usize = 1 << 16;
+
+/// Statistics of a [`DedupLlmpHook`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DedupStats {
+    /// Testcases passed on to the clients
+    pub forwarded: u64,
+    /// Testcases dropped, as the same input was forwarded recently
+    pub duplicate_inputs: u64,
+    /// Testcases dropped, as a testcase with the same coverage was forwarded recently
+    pub duplicate_coverage: u64,
+}
+
+/// The most recent `window` hashes
+#[derive(Debug)]
+struct RecentHashes {
+    set: HashSet<u64>,
+    order: VecDeque<u64>,
+    window: usize,
+}
+
+impl RecentHashes {
+    fn new(window: usize) -> Self {
+        Self {
+            set: HashSet::new(),
+            order: VecDeque::new(),
+            window,
+        }
+    }
+
+    /// Remembers `hash`, returns `false` if it was seen recently
+    fn insert(&mut self, hash: u64) -> bool {
+        if !self.set.insert(hash) {
+            return false;
+        }
+        self.order.push_back(hash);
+        if self.order.len() > self.window {
+            if let Some(oldest) = self.order.pop_front() {
+                self.set.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// An LLMP broker hook dropping [`Event::NewTestcase`]s with inputs, or coverage, identical to a recently forwarded one.
+///
+/// Add it to the hooks of the [`libafl_bolts::llmp::LlmpBroker`] after the [`super::StdLlmpEventHook`],
+/// so the monitor still sees every testcase.
+#[derive(Debug)]
+pub struct DedupLlmpHook<I> {
+    inputs: RecentHashes,
+    coverage: RecentHashes,
+    /// The coverage hash each client announced for the event directly following the announcement
+    announced: HashMap<ClientId, u64>,
+    stats: DedupStats,
+    phantom: PhantomData<I>,
+}
+
+impl<I> DedupLlmpHook<I>
+where
+    I: Input,
+{
+    /// Creates a new [`DedupLlmpHook`], remembering the last `window` inputs and coverage hashes
+    #[must_use]
+    pub fn new(window: usize) -> Self {
+        Self {
+            inputs: RecentHashes::new(window),
+            coverage: RecentHashes::new(window),
+            announced: HashMap::new(),
+            stats: DedupStats::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The statistics of this hook
+    #[must_use]
+    pub fn stats(&self) -> &DedupStats {
+        &self.stats
+    }
+
+    /// Decides whether to forward an `event` sent by `client_id`
+    fn handle_event(
+        &mut self,
+        client_id: ClientId,
+        event: &Event<I>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        // An announcement is only valid for the event right after it
+        let announced = self.announced.remove(&client_id);
+
+        match event {
+            Event::CustomBuf { tag, buf } if tag == COVERAGE_SUMMARY_TAG => {
+                let hash = buf.as_slice().try_into().map_err(|_| {
+                    Error::illegal_argument("Coverage summaries must be exactly 8 bytes")
+                })?;
+                self.announced.insert(client_id, u64::from_le_bytes(hash));
+                Ok(LlmpMsgHookResult::Handled)
+            }
+            Event::NewTestcase { input, .. } => {
+                let new_input = self.inputs.insert(hash_std(&postcard::to_allocvec(input)?));
+                let new_coverage = match announced {
+                    Some(hash) => self.coverage.insert(hash),
+                    None => true,
+                };
+                if !new_input {
+                    self.stats.duplicate_inputs += 1;
+                    Ok(LlmpMsgHookResult::Handled)
+                } else if !new_coverage {
+                    self.stats.duplicate_coverage += 1;
+                    Ok(LlmpMsgHookResult::Handled)
+                } else {
+                    self.stats.forwarded += 1;
+                    Ok(LlmpMsgHookResult::ForwardToClients)
+                }
+            }
+            _ => Ok(LlmpMsgHookResult::ForwardToClients),
+        }
+    }
+}
+
+impl<I> Default for DedupLlmpHook<I>
+where
+    I: Input,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_DEDUP_WINDOW)
+    }
+}
+
+impl<I, SP> LlmpHook<SP> for DedupLlmpHook<I>
+where
+    I: Input,
+    SP: ShMemProvider,
+{
+    fn on_new_message(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SP>,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        #[cfg(feature = "llmp_compression")] msg_flags: &mut Flags,
+        #[cfg(not(feature = "llmp_compression"))] _msg_flags: &mut Flags,
+        msg: &mut [u8],
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if let Some(codec) = msg_flags.compression_codec() {
+            compressed = codec.decompress(msg)?;
+            &compressed
+        } else {
+            &*msg
+        };
+        let event: Event<I> = postcard::from_bytes(event_bytes)?;
+        self.handle_event(client_id, &event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+    use core::time::Duration;
+
+    use libafl_bolts::{llmp::LlmpMsgHookResult, ClientId};
+
+    use super::{DedupLlmpHook, COVERAGE_SUMMARY_TAG};
+    use crate::{
+        events::{Event, EventConfig},
+        executors::ExitKind,
+        inputs::BytesInput,
+    };
+
+    fn testcase(input: &[u8]) -> Event<BytesInput> {
+        Event::NewTestcase {
+            input: BytesInput::new(input.to_vec()),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        }
+    }
+
+    fn summary(hash: u64) -> Event<BytesInput> {
+        Event::CustomBuf {
+            buf: hash.to_le_bytes().to_vec(),
+            tag: COVERAGE_SUMMARY_TAG.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_dedup() {
+        let mut hook = DedupLlmpHook::new(2);
+        let forwarded = |res: LlmpMsgHookResult| matches!(res, LlmpMsgHookResult::ForwardToClients);
+        let mut send = |client: u32, event: Event<BytesInput>| {
+            forwarded(hook.handle_event(ClientId(client), &event).unwrap())
+        };
+
+        assert!(send(1, testcase(b"a")));
+        assert!(!send(2, testcase(b"a")));
+        assert!(send(2, testcase(b"b")));
+
+        // Coverage summaries are consumed by the broker
+        assert!(!send(1, summary(42)));
+        assert!(send(1, testcase(b"c")));
+        assert!(!send(2, summary(42)));
+        assert!(!send(2, testcase(b"d")));
+        // A summary only applies to the event directly following it
+        assert!(!send(2, summary(42)));
+        assert!(send(2, Event::Stop));
+        assert!(send(2, testcase(b"e")));
+
+        // "a" left the window
+        assert!(send(1, testcase(b"a")));
+
+        let stats = hook.stats();
+        assert_eq!(stats.forwarded, 5);
+        assert_eq!(stats.duplicate_inputs, 1);
+        assert_eq!(stats.duplicate_coverage, 1);
+
+        let mut hook = DedupLlmpHook::<BytesInput>::new(2);
+        assert!(hook
+            .handle_event(
+                ClientId(0),
+                &Event::CustomBuf {
+                    buf: Vec::new(),
+                    tag: COVERAGE_SUMMARY_TAG.to_string(),
+                },
+            )
+            .is_err());
+    }
+}
@@ -0,0 +1,792 @@
+//! Broker-to-broker federation across machines.
+//!
+//! A [`BrokerFederationHook`] connects an LLMP broker to other brokers over TCP, in the tree of
+//! [`crate::events::multi_machine`]: the [`NodeDescriptor`] names the parent to connect to, the port children
+//! connect to, and the [`NodePolicy`] decides whether testcases go up to the parent, down to the children, or both.
+//! Every [`Event::NewTestcase`] of a local client is sent to the connected brokers,
+//! and testcases received from other brokers are handed to the local clients, then passed on to the other peers.
+//! Each message carries the [`FederationNodeId`] of the broker it originates from, and a sequence number,
+//! so messages are never delivered twice, even if the brokers form a cycle.
+//!
+//! Messages to a peer are sent in batches, and can be limited to a maximum bandwidth.
+//! If a peer cannot keep up, new messages for it are dropped, instead of stalling the local broker.
+//!
+//! Brokers authenticate each other, and encrypt their connections, with the same [`TcpSecurity`]
+//! settings as the [`crate::events::tcp::TcpEventBroker`] uses for its clients.
+
+use alloc::{collections::VecDeque, string::ToString, sync::Arc, vec::Vec};
+use core::{
+    fmt::Display,
+    marker::PhantomData,
+    net::SocketAddr,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    time::Duration,
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    process,
+    sync::{
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError, TrySendError},
+        Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::Instant,
+};
+
+use enumflags2::BitFlags;
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::{
+    current_nanos, hash_std,
+    llmp::{
+        llmp_page_size, Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag,
+        LLMP_FLAG_FROM_B2B, LLMP_FLAG_FROM_MM,
+    },
+    shmem::ShMemProvider,
+    ClientId,
+};
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+
+use crate::{
+    events::{
+        llmp::LLMP_TAG_EVENT_TO_BOTH,
+        multi_machine::{NodeDescriptor, NodePolicy},
+        tcp::{TcpConnection, TcpSecurity, HANDSHAKE_TIMEOUT},
+        Event,
+    },
+    inputs::Input,
+    Error,
+};
+
+/// An upper bound of the bytes a [`FederatedMsg`] adds to its payload in a serialized batch
+const MSG_OVERHEAD: usize = 64;
+
+/// How long a peer connection waits for data from the peer, before it looks for messages to send again.
+/// The background threads notice the shutdown of the hook within this interval, too.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The largest message sent to the federation: a message does not grow beyond an LLMP page either.
+fn max_msg_len() -> usize {
+    llmp_page_size()
+}
+
+/// The largest batch we accept from a peer. Batches stop growing at [`max_msg_len`], so they are at most twice as large.
+fn max_frame_len() -> usize {
+    2 * (max_msg_len() + MSG_OVERHEAD)
+}
+
+/// The id of a broker in a federation, chosen randomly on startup
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FederationNodeId(pub u64);
+
+impl FederationNodeId {
+    /// Generates a new, random [`FederationNodeId`]
+    #[must_use]
+    pub fn random() -> Self {
+        let mut seed = current_nanos().to_le_bytes().to_vec();
+        seed.extend_from_slice(&process::id().to_le_bytes());
+        Self(hash_std(&seed))
+    }
+}
+
+/// A testcase event, as it travels between brokers
+#[derive(Debug, Serialize, Deserialize)]
+struct FederatedMsg {
+    /// The broker this message was first sent by
+    origin: FederationNodeId,
+    /// The sequence number of this message at `origin`
+    seq: u64,
+    /// The llmp flags of the message
+    flags: u32,
+    /// The serialized (and maybe compressed) event
+    payload: Vec<u8>,
+}
+
+/// The settings of a [`BrokerFederationHook`], next to its place in the tree given by its [`NodeDescriptor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct FederationConfig {
+    /// A batch is sent once it reaches this many bytes. Defaults to 1 MiB.
+    #[builder(default = 1024 * 1024)]
+    pub max_batch_bytes: usize,
+    /// A batch is sent at the latest this long after its first message. Defaults to 50ms.
+    #[builder(default = Duration::from_millis(50))]
+    pub max_batch_delay: Duration,
+    /// The maximum bandwidth used for each peer, in bytes per second. Unlimited by default.
+    #[builder(default, setter(strip_option))]
+    pub max_bytes_per_sec: Option<u64>,
+    /// The amount of messages queued for a peer, after which new messages for it are dropped. Defaults to 4096.
+    #[builder(default = 4096)]
+    pub max_queued_msgs: usize,
+    /// The time to wait before reconnecting to the parent broker. Defaults to 5s.
+    #[builder(default = Duration::from_secs(5))]
+    pub reconnect_interval: Duration,
+    /// The amount of recently seen messages remembered to prevent loops. Defaults to 65536.
+    #[builder(default = 65536)]
+    pub seen_window: usize,
+    /// Authentication and encryption of the connections between brokers.
+    /// The parent is the server, the child the client of [`TcpSecurity`].
+    #[builder(default)]
+    pub security: TcpSecurity,
+}
+
+/// Counters of a [`BrokerFederationHook`], see [`BrokerFederationHook::stats`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederationStats {
+    /// Testcases of local clients sent to the federation
+    pub sent: u64,
+    /// Testcases received from other brokers and handed to the local clients
+    pub received: u64,
+    /// Testcases received more than once, or that originated here, and were therefore dropped
+    pub duplicates: u64,
+    /// Messages dropped because a peer could not keep up, or because they were too large
+    pub dropped: u64,
+    /// Bytes written to all peers
+    pub bytes_sent: u64,
+}
+
+#[derive(Debug, Default)]
+struct AtomicStats {
+    sent: AtomicU64,
+    received: AtomicU64,
+    duplicates: AtomicU64,
+    dropped: AtomicU64,
+    bytes_sent: AtomicU64,
+}
+
+/// A connected broker
+#[derive(Debug)]
+struct Peer {
+    /// If the peer is our parent, else it is one of our children
+    is_parent: bool,
+    /// The outgoing queue of the peer
+    sender: SyncSender<Arc<FederatedMsg>>,
+}
+
+/// State shared between the hook and the connection threads
+#[derive(Debug)]
+struct Shared {
+    node_id: FederationNodeId,
+    config: FederationConfig,
+    /// Which peers get the testcases, see [`NodeDescriptor::flags`]
+    policy: BitFlags<NodePolicy>,
+    peers: Mutex<HashMap<usize, Peer>>,
+    next_peer_id: AtomicUsize,
+    stats: AtomicStats,
+    /// Set when the hook is dropped, to stop all background threads
+    shutdown: AtomicBool,
+}
+
+impl Shared {
+    /// Handles a connection to another broker until it is closed, or the hook is dropped.
+    /// The child, which connected to its parent, is the client of the [`TcpSecurity`] handshake.
+    fn run_peer(
+        self: &Arc<Self>,
+        stream: TcpStream,
+        is_parent: bool,
+        inbound: &Sender<(usize, FederatedMsg)>,
+    ) -> Result<(), Error> {
+        stream.set_nodelay(true)?;
+        // A stalled peer must not keep its thread forever.
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let security = &self.config.security;
+        let mut connection = if is_parent {
+            security.connect(stream)?
+        } else {
+            security.accept(stream)?
+        };
+
+        // Protocol: after the handshake, both sides send their node id
+        connection.write_all(&self.node_id.0.to_le_bytes())?;
+        connection.flush()?;
+        let mut remote_id = [0; 8];
+        connection.read_exact(&mut remote_id)?;
+        let remote_id = FederationNodeId(u64::from_le_bytes(remote_id));
+        if remote_id == self.node_id {
+            return Err(Error::illegal_argument(
+                "Connected to ourselves, check the parent address",
+            ));
+        }
+        log::info!("Federation: connected to {remote_id:?}");
+
+        let peer_id = self.next_peer_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::sync_channel(self.config.max_queued_msgs);
+        self.peers
+            .lock()
+            .unwrap()
+            .insert(peer_id, Peer { is_parent, sender });
+
+        connection.tcp().set_read_timeout(Some(POLL_INTERVAL))?;
+        let ret = self.exchange_batches(&mut connection, peer_id, &receiver, inbound);
+        self.peers.lock().unwrap().remove(&peer_id);
+        drop(connection.tcp().shutdown(Shutdown::Both));
+        ret
+    }
+
+    /// Collects queued messages into batches, and sends them to a peer, respecting the bandwidth limit.
+    /// In between, reads batches from the peer, and hands all messages to the hook.
+    ///
+    /// A single thread does both, as a TLS connection cannot be shared by a reading and a writing thread.
+    fn exchange_batches(
+        &self,
+        connection: &mut TcpConnection,
+        peer_id: usize,
+        receiver: &Receiver<Arc<FederatedMsg>>,
+        inbound: &Sender<(usize, FederatedMsg)>,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let max_batch_bytes = config.max_batch_bytes.min(max_msg_len());
+        let mut received = Vec::new();
+        let mut chunk = vec![0; 64 * 1024];
+        let mut batch: Vec<Arc<FederatedMsg>> = Vec::new();
+        let mut batch_bytes = 0;
+        let mut batch_deadline = Instant::now();
+        let mut next_send = Instant::now();
+        while !self.shutdown.load(Ordering::Relaxed) {
+            match connection.read(&mut chunk) {
+                Ok(0) => return Err(Error::illegal_state("Peer closed the connection")),
+                Ok(len) => received.extend_from_slice(&chunk[..len]),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(err) => return Err(err.into()),
+            }
+            while let Some(frame) = take_frame(&mut received)? {
+                let msgs: Vec<FederatedMsg> = postcard::from_bytes(&frame)?;
+                for msg in msgs {
+                    if inbound.send((peer_id, msg)).is_err() {
+                        // The hook is gone
+                        return Ok(());
+                    }
+                }
+            }
+
+            // While we wait for bandwidth, the batch keeps growing.
+            while batch_bytes < max_batch_bytes {
+                match receiver.try_recv() {
+                    Ok(msg) => {
+                        if batch.is_empty() {
+                            batch_deadline = Instant::now() + config.max_batch_delay;
+                        }
+                        batch_bytes += msg.payload.len() + MSG_OVERHEAD;
+                        batch.push(msg);
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+            let now = Instant::now();
+            if batch.is_empty()
+                || now < next_send
+                || (batch_bytes < max_batch_bytes && now < batch_deadline)
+            {
+                continue;
+            }
+
+            let msgs: Vec<&FederatedMsg> = batch.iter().map(AsRef::as_ref).collect();
+            let serialized = postcard::to_allocvec(&msgs)?;
+            connection.write_all(&u32::try_from(serialized.len())?.to_le_bytes())?;
+            connection.write_all(&serialized)?;
+            connection.flush()?;
+            batch.clear();
+            batch_bytes = 0;
+
+            let sent = serialized.len() as u64 + 4;
+            self.stats.bytes_sent.fetch_add(sent, Ordering::Relaxed);
+            if let Some(max_bytes_per_sec) = config.max_bytes_per_sec {
+                let nanos = u128::from(sent) * 1_000_000_000 / u128::from(max_bytes_per_sec.max(1));
+                next_send =
+                    Instant::now() + Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX));
+            }
+        }
+        Ok(())
+    }
+
+    /// Queues `msg` for all peers the [`NodePolicy`] sends testcases to, except for `except`
+    fn broadcast(&self, msg: &Arc<FederatedMsg>, except: Option<usize>) {
+        for (peer_id, peer) in &*self.peers.lock().unwrap() {
+            let direction = if peer.is_parent {
+                NodePolicy::SendToParent
+            } else {
+                NodePolicy::SendToChildren
+            };
+            if Some(*peer_id) == except || !self.policy.contains(direction) {
+                continue;
+            }
+            match peer.sender.try_send(msg.clone()) {
+                Ok(()) | Err(TrySendError::Disconnected(_)) => {}
+                Err(TrySendError::Full(_)) => {
+                    self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+}
+
+impl Shared {
+    /// Sleeps for `duration`, returns `false` early once the hook is dropped
+    fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        while !self.shutdown.load(Ordering::Relaxed) {
+            let now = Instant::now();
+            if now >= deadline {
+                return true;
+            }
+            thread::sleep(POLL_INTERVAL.min(deadline - now));
+        }
+        false
+    }
+
+    /// Accepts the children of this broker, until the hook is dropped
+    fn accept_children(
+        self: &Arc<Self>,
+        listener: &TcpListener,
+        inbound: &Sender<(usize, FederatedMsg)>,
+    ) {
+        let mut children: Vec<JoinHandle<()>> = Vec::new();
+        loop {
+            children.retain(|child| !child.is_finished());
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    if self.sleep(POLL_INTERVAL) {
+                        continue;
+                    }
+                    break;
+                }
+                Err(err) => {
+                    log::warn!("Federation: accepting a peer failed: {err}");
+                    continue;
+                }
+            };
+            let shared = self.clone();
+            let inbound = inbound.clone();
+            let child = thread::Builder::new()
+                .name("federation_peer".into())
+                .spawn(move || {
+                    let ret = stream
+                        .set_nonblocking(false)
+                        .map_err(Error::from)
+                        .and_then(|()| shared.run_peer(stream, false, &inbound));
+                    if let Err(err) = ret {
+                        log::info!("Federation: peer disconnected: {err}");
+                    }
+                });
+            match child {
+                Ok(child) => children.push(child),
+                Err(err) => log::warn!("Federation: cannot spawn a peer thread: {err}"),
+            }
+        }
+        for child in children {
+            drop(child.join());
+        }
+    }
+
+    /// Keeps a connection to the parent broker, until the hook is dropped
+    fn connect_parent(self: &Arc<Self>, parent: &str, inbound: &Sender<(usize, FederatedMsg)>) {
+        loop {
+            let connected = parent
+                .to_socket_addrs()
+                .map_err(Error::from)
+                .and_then(|mut addrs| {
+                    addrs
+                        .next()
+                        .ok_or_else(|| Error::illegal_argument(format!("Cannot resolve {parent}")))
+                })
+                .and_then(|addr| Ok(TcpStream::connect_timeout(&addr, HANDSHAKE_TIMEOUT)?));
+            match connected {
+                Ok(stream) => {
+                    if let Err(err) = self.run_peer(stream, true, inbound) {
+                        log::info!("Federation: parent {parent} disconnected: {err}");
+                    }
+                }
+                Err(err) => {
+                    log::debug!("Federation: cannot connect to {parent}: {err}");
+                }
+            }
+            if !self.sleep(self.config.reconnect_interval) {
+                return;
+            }
+        }
+    }
+}
+
+/// Removes the first complete batch frame from the bytes `received` from a peer, if there is one
+fn take_frame(received: &mut Vec<u8>) -> Result<Option<Vec<u8>>, Error> {
+    let Some(len_buf) = received.first_chunk::<4>() else {
+        return Ok(None);
+    };
+    let len = u32::from_le_bytes(*len_buf) as usize;
+    if len > max_frame_len() {
+        return Err(Error::illegal_state(format!(
+            "Peer sent an oversized batch of {len} bytes"
+        )));
+    }
+    if received.len() < 4 + len {
+        return Ok(None);
+    }
+    let frame = received[4..4 + len].to_vec();
+    received.drain(..4 + len);
+    Ok(Some(frame))
+}
+
+/// An LLMP broker hook federating this broker with brokers on other machines.
+///
+/// Add it to the hooks of the [`libafl_bolts::llmp::LlmpBroker`], next to the [`super::StdLlmpEventHook`].
+/// Testcases from other brokers are handed to the local clients on the next round of the broker,
+/// even if no local client sent anything.
+/// All brokers need to agree on the `llmp_compression` feature, the input type, the `security` settings and the LLMP page size.
+///
+/// Dropping the hook closes all connections, and joins its background threads.
+#[derive(Debug)]
+pub struct BrokerFederationHook<I> {
+    shared: Arc<Shared>,
+    inbound: Receiver<(usize, FederatedMsg)>,
+    local_addr: Option<SocketAddr>,
+    threads: Vec<JoinHandle<()>>,
+    next_seq: u64,
+    /// Recently seen messages, for loop prevention
+    seen: HashSet<(FederationNodeId, u64)>,
+    seen_order: VecDeque<(FederationNodeId, u64)>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> BrokerFederationHook<I>
+where
+    I: Input,
+{
+    /// Creates a new [`BrokerFederationHook`] at the place in the tree described by `node`,
+    /// connecting to its parent and accepting its children in the background.
+    ///
+    /// Fails if the `node_listening_port` of the `node` cannot be bound.
+    pub fn new<A>(node: &NodeDescriptor<A>, config: FederationConfig) -> Result<Self, Error>
+    where
+        A: Display,
+    {
+        let shared = Arc::new(Shared {
+            node_id: FederationNodeId::random(),
+            config,
+            policy: node.flags,
+            peers: Mutex::new(HashMap::new()),
+            next_peer_id: AtomicUsize::new(0),
+            stats: AtomicStats::default(),
+            shutdown: AtomicBool::new(false),
+        });
+        let (inbound_sender, inbound) = mpsc::channel();
+        let mut threads = Vec::new();
+
+        let mut local_addr = None;
+        if let Some(port) = node.node_listening_port {
+            let listener = TcpListener::bind(("0.0.0.0", port))?;
+            listener.set_nonblocking(true)?;
+            local_addr = Some(listener.local_addr()?);
+            let shared = shared.clone();
+            let inbound_sender = inbound_sender.clone();
+            threads.push(
+                thread::Builder::new()
+                    .name("federation_listener".into())
+                    .spawn(move || shared.accept_children(&listener, &inbound_sender))?,
+            );
+        }
+
+        if let Some(parent) = &node.parent_addr {
+            let parent = parent.to_string();
+            let shared = shared.clone();
+            threads.push(
+                thread::Builder::new()
+                    .name("federation_parent".into())
+                    .spawn(move || shared.connect_parent(&parent, &inbound_sender))?,
+            );
+        }
+
+        Ok(Self {
+            shared,
+            inbound,
+            local_addr,
+            threads,
+            next_seq: 0,
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// The id of this broker in the federation
+    #[must_use]
+    pub fn node_id(&self) -> FederationNodeId {
+        self.shared.node_id
+    }
+
+    /// The address children can connect to, if a `node_listening_port` was set
+    #[must_use]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// The amount of currently connected brokers
+    #[must_use]
+    pub fn peer_count(&self) -> usize {
+        self.shared.peers.lock().unwrap().len()
+    }
+
+    /// The current counters of this hook
+    #[must_use]
+    pub fn stats(&self) -> FederationStats {
+        let stats = &self.shared.stats;
+        FederationStats {
+            sent: stats.sent.load(Ordering::Relaxed),
+            received: stats.received.load(Ordering::Relaxed),
+            duplicates: stats.duplicates.load(Ordering::Relaxed),
+            dropped: stats.dropped.load(Ordering::Relaxed),
+            bytes_sent: stats.bytes_sent.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Remembers a message, returns `false` if it was seen before
+    fn remember(&mut self, key: (FederationNodeId, u64)) -> bool {
+        if !self.seen.insert(key) {
+            return false;
+        }
+        self.seen_order.push_back(key);
+        while self.seen_order.len() > self.shared.config.seen_window {
+            let oldest = self.seen_order.pop_front().unwrap();
+            self.seen.remove(&oldest);
+        }
+        true
+    }
+
+    /// Sends a message of a local client to the federation, if it is a new testcase
+    fn handle_local(&mut self, tag: Tag, flags: Flags, msg: &[u8]) -> Result<(), Error> {
+        if tag != LLMP_TAG_EVENT_TO_BOTH
+            || flags & (LLMP_FLAG_FROM_B2B | LLMP_FLAG_FROM_MM) != Flags(0)
+        {
+            return Ok(());
+        }
+
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if let Some(codec) = flags.compression_codec() {
+            compressed = codec.decompress(msg)?;
+            &compressed
+        } else {
+            msg
+        };
+        let event: Event<I> = postcard::from_bytes(event_bytes)?;
+        if !event.is_new_testcase() {
+            return Ok(());
+        }
+        if msg.len() > max_msg_len() {
+            log::warn!(
+                "Federation: not sending a testcase of {} bytes, larger than an LLMP page",
+                msg.len()
+            );
+            self.shared.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        }
+
+        let msg = FederatedMsg {
+            origin: self.shared.node_id,
+            seq: self.next_seq,
+            flags: flags.0,
+            payload: msg.to_vec(),
+        };
+        self.next_seq += 1;
+        self.remember((msg.origin, msg.seq));
+        self.shared.stats.sent.fetch_add(1, Ordering::Relaxed);
+        self.shared.broadcast(&Arc::new(msg), None);
+        Ok(())
+    }
+
+    /// Hands all testcases received from other brokers to the local clients, and passes them on to the other peers
+    fn receive(&mut self, new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>) {
+        while let Ok((peer_id, msg)) = self.inbound.try_recv() {
+            if msg.origin == self.shared.node_id || !self.remember((msg.origin, msg.seq)) {
+                self.shared.stats.duplicates.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+            self.shared.stats.received.fetch_add(1, Ordering::Relaxed);
+            new_msgs.push((
+                LLMP_TAG_EVENT_TO_BOTH,
+                Flags(msg.flags) | LLMP_FLAG_FROM_MM,
+                msg.payload.clone(),
+            ));
+            self.shared.broadcast(&Arc::new(msg), Some(peer_id));
+        }
+    }
+}
+
+impl<I> Drop for BrokerFederationHook<I> {
+    fn drop(&mut self) {
+        self.shared.shutdown.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                log::error!("Federation: a background thread panicked");
+            }
+        }
+    }
+}
+
+impl<I, SP> LlmpHook<SP> for BrokerFederationHook<I>
+where
+    I: Input,
+    SP: ShMemProvider,
+{
+    fn on_new_message(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SP>,
+        _client_id: ClientId,
+        msg_tag: &mut Tag,
+        msg_flags: &mut Flags,
+        msg: &mut [u8],
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        self.handle_local(*msg_tag, *msg_flags, msg)?;
+        Ok(LlmpMsgHookResult::ForwardToClients)
+    }
+
+    fn poll_new_msgs(&mut self, new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>) -> Result<(), Error> {
+        self.receive(new_msgs);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{net::SocketAddr, time::Duration};
+    use std::{net::TcpStream, thread, time::Instant};
+
+    use libafl_bolts::{
+        llmp::{Flags, LlmpHook, LLMP_FLAG_FROM_MM},
+        shmem::StdShMemProvider,
+    };
+
+    use super::{BrokerFederationHook, FederationConfig};
+    use crate::{
+        events::{
+            llmp::LLMP_TAG_EVENT_TO_BOTH, multi_machine::NodeDescriptor, tcp::TcpSecurity, Event,
+            EventConfig,
+        },
+        executors::ExitKind,
+        inputs::BytesInput,
+    };
+
+    fn wait_for<F: FnMut() -> bool>(mut condition: F) {
+        let start = Instant::now();
+        while !condition() {
+            assert!(start.elapsed() < Duration::from_secs(10), "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// A root of the tree, listening on a free local port
+    fn root(config: FederationConfig) -> BrokerFederationHook<BytesInput> {
+        let node = NodeDescriptor::builder()
+            .parent_addr(None::<SocketAddr>)
+            .node_listening_port(Some(0))
+            .build();
+        BrokerFederationHook::new(&node, config).unwrap()
+    }
+
+    /// A leaf of the tree below `parent`
+    fn leaf(parent: SocketAddr, config: FederationConfig) -> BrokerFederationHook<BytesInput> {
+        let node = NodeDescriptor::builder()
+            .parent_addr(Some(parent))
+            .node_listening_port(None)
+            .build();
+        BrokerFederationHook::new(&node, config).unwrap()
+    }
+
+    #[test]
+    fn test_federation() {
+        let mut upstream = root(FederationConfig::builder().build());
+        let addr = upstream.local_addr().unwrap();
+        let mut downstream = leaf(
+            addr,
+            FederationConfig::builder()
+                .max_batch_delay(Duration::from_millis(1))
+                .build(),
+        );
+        wait_for(|| upstream.peer_count() == 1 && downstream.peer_count() == 1);
+
+        let event = Event::NewTestcase {
+            input: BytesInput::new(vec![1, 2, 3]),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 1,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        };
+        let serialized = postcard::to_allocvec(&event).unwrap();
+        downstream
+            .handle_local(LLMP_TAG_EVENT_TO_BOTH, Flags(0), &serialized)
+            .unwrap();
+
+        // The upstream broker has no local clients sending anything, it polls every round.
+        let mut new_msgs = vec![];
+        wait_for(|| {
+            LlmpHook::<StdShMemProvider>::poll_new_msgs(&mut upstream, &mut new_msgs).unwrap();
+            !new_msgs.is_empty()
+        });
+        assert_eq!(
+            new_msgs,
+            [(LLMP_TAG_EVENT_TO_BOTH, LLMP_FLAG_FROM_MM, serialized)]
+        );
+        assert_eq!(downstream.stats().sent, 1);
+        assert_eq!(upstream.stats().received, 1);
+
+        // Nothing is sent back to where it came from.
+        thread::sleep(Duration::from_millis(50));
+        let mut echoed = vec![];
+        downstream.receive(&mut echoed);
+        assert!(echoed.is_empty());
+    }
+
+    #[test]
+    fn test_federation_auth_token() {
+        let upstream = root(
+            FederationConfig::builder()
+                .security(TcpSecurity::new().with_auth_token("secret"))
+                .build(),
+        );
+        let addr = upstream.local_addr().unwrap();
+        let connect = |token: &str| {
+            leaf(
+                addr,
+                FederationConfig::builder()
+                    .security(TcpSecurity::new().with_auth_token(token))
+                    .build(),
+            )
+        };
+
+        let guessed = connect("guessed");
+        thread::sleep(Duration::from_millis(200));
+        assert_eq!(upstream.peer_count(), 0);
+        assert_eq!(guessed.peer_count(), 0);
+
+        let trusted = connect("secret");
+        wait_for(|| upstream.peer_count() == 1 && trusted.peer_count() == 1);
+    }
+
+    #[test]
+    fn test_federation_drop_joins_threads() {
+        let upstream = root(FederationConfig::builder().build());
+        let addr = upstream.local_addr().unwrap();
+        let downstream = leaf(
+            addr,
+            FederationConfig::builder()
+                .reconnect_interval(Duration::from_millis(10))
+                .build(),
+        );
+        wait_for(|| upstream.peer_count() == 1 && downstream.peer_count() == 1);
+
+        // Dropping the hook joins the listener and its peer threads, so the port is closed afterwards.
+        drop(upstream);
+        assert!(TcpStream::connect(addr).is_err());
+        wait_for(|| downstream.peer_count() == 0);
+        drop(downstream);
+    }
+}
@@ -3,7 +3,7 @@ use alloc::vec::Vec;
 use core::marker::PhantomData;
 
 #[cfg(feature = "llmp_compression")]
-use libafl_bolts::{compress::GzipCompressor, llmp::LLMP_FLAG_COMPRESSED};
+use hashbrown::HashMap;
 use libafl_bolts::{
     llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag},
     shmem::ShMemProvider,
@@ -11,7 +11,7 @@ use libafl_bolts::{
 };
 
 #[cfg(feature = "llmp_compression")]
-use crate::events::llmp::COMPRESS_THRESHOLD;
+use crate::monitors::{AggregatorOps, UserStats, UserStatsValue};
 use crate::{
     events::{llmp::LLMP_TAG_EVENT_TO_BOTH, BrokerEventResult, Event},
     inputs::Input,
@@ -19,6 +19,10 @@ use crate::{
     Error,
 };
 
+/// The name of the user stat the [`StdLlmpEventHook`] reports the compression ratio of each client with
+#[cfg(feature = "llmp_compression")]
+pub const COMPRESSION_STATS_NAME: &str = "llmp_compression";
+
 /// centralized hook
 #[cfg(all(unix, feature = "std"))]
 pub mod centralized;
@@ -31,12 +35,29 @@ pub mod centralized_multi_machine;
 #[cfg(all(unix, feature = "multi_machine"))]
 pub use centralized_multi_machine::*;
 
+/// Suppression of duplicate testcases
+pub mod dedup;
+pub use dedup::*;
+
+/// Mirroring events to message queues
+#[cfg(feature = "std")]
+pub mod mq_bridge;
+#[cfg(feature = "std")]
+pub use mq_bridge::*;
+
+/// Broker-to-broker federation
+#[cfg(all(unix, feature = "multi_machine"))]
+pub mod federation;
+#[cfg(all(unix, feature = "multi_machine"))]
+pub use federation::*;
+
 /// An LLMP-backed event hook for scalable multi-processed fuzzing
 #[derive(Debug)]
 pub struct StdLlmpEventHook<I, MT> {
     monitor: MT,
+    /// Bytes received from each client, as `(on the wire, decompressed)`
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compression_stats: HashMap<ClientId, (u64, u64)>,
     phantom: PhantomData<I>,
 }
 
@@ -57,8 +78,6 @@ where
         _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
     ) -> Result<LlmpMsgHookResult, Error> {
         let monitor = &mut self.monitor;
-        #[cfg(feature = "llmp_compression")]
-        let compressor = &self.compressor;
 
         if *msg_tag == LLMP_TAG_EVENT_TO_BOTH {
             #[cfg(not(feature = "llmp_compression"))]
@@ -66,12 +85,20 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if *msg_flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = compressor.decompress(msg)?;
+            let event_bytes = if let Some(codec) = msg_flags.compression_codec() {
+                compressed = codec.decompress(msg)?;
                 &compressed
             } else {
                 &*msg
             };
+            #[cfg(feature = "llmp_compression")]
+            Self::update_compression_stats(
+                monitor,
+                &mut self.compression_stats,
+                client_id,
+                msg.len(),
+                event_bytes.len(),
+            );
             let event: Event<I> = postcard::from_bytes(event_bytes)?;
             match Self::handle_in_broker(monitor, client_id, &event)? {
                 BrokerEventResult::Forward => Ok(LlmpMsgHookResult::ForwardToClients),
@@ -98,11 +125,33 @@ where
         Ok(Self {
             monitor,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compression_stats: HashMap::new(),
             phantom: PhantomData,
         })
     }
 
+    /// Accounts the bytes of an event received from `client_id`,
+    /// and reports the resulting compression ratio to the monitor
+    #[cfg(feature = "llmp_compression")]
+    fn update_compression_stats(
+        monitor: &mut MT,
+        compression_stats: &mut HashMap<ClientId, (u64, u64)>,
+        client_id: ClientId,
+        wire_len: usize,
+        raw_len: usize,
+    ) {
+        let (wire, raw) = compression_stats.entry(client_id).or_default();
+        *wire += wire_len as u64;
+        *raw += raw_len as u64;
+        let value = UserStats::new(UserStatsValue::Ratio(*wire, *raw), AggregatorOps::Avg);
+
+        monitor.client_stats_insert(client_id);
+        monitor
+            .client_stats_mut_for(client_id)
+            .update_user_stats(COMPRESSION_STATS_NAME.into(), value);
+        monitor.aggregate(COMPRESSION_STATS_NAME);
+    }
+
     /// Handle arriving events in the broker
     #[allow(clippy::unnecessary_wraps)]
     fn handle_in_broker(
@@ -195,7 +244,7 @@ where
                 Ok(BrokerEventResult::Handled)
             }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
-            Event::Stop => Ok(BrokerEventResult::Forward),
+            Event::Stop | Event::SetStopPolicy { .. } => Ok(BrokerEventResult::Forward),
             //_ => Ok(BrokerEventResult::Forward),
         }
     }
@@ -0,0 +1,599 @@
+//! Mirroring broker events to a message queue, such as NATS or Kafka.
+//!
+//! The [`MqBridgeLlmpHook`] publishes new corpus entries, objectives and stats of all clients
+//! as JSON [`MqRecord`]s to the configured topics, so fuzzers feed into existing data pipelines.
+//! Optionally, it consumes seeds from another topic and hands them to all clients.
+//!
+//! A client for NATS, [`NatsQueue`], is built in. Other queues, such as Kafka, can be plugged in
+//! by implementing [`MessageQueue`], for example on top of the `rdkafka` crate.
+
+use alloc::{
+    borrow::Cow,
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, time::Duration};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use libafl_bolts::{
+    current_time,
+    llmp::{Flags, LlmpBrokerInner, LlmpHook, LlmpMsgHookResult, Tag, LLMP_FLAG_INITIALIZED},
+    shmem::ShMemProvider,
+    ClientId,
+};
+use serde::Serialize;
+use typed_builder::TypedBuilder;
+
+use crate::{
+    events::{llmp::LLMP_TAG_EVENT_TO_BOTH, Event, EventConfig},
+    executors::ExitKind,
+    inputs::Input,
+    Error,
+};
+
+/// A message queue the [`MqBridgeLlmpHook`] publishes to, and consumes seeds from
+pub trait MessageQueue {
+    /// Publishes `payload` to `topic`
+    fn publish(&mut self, topic: &str, payload: &[u8]) -> Result<(), Error>;
+
+    /// Subscribes to `topic`, messages on it are returned by [`MessageQueue::poll`]
+    fn subscribe(&mut self, topic: &str) -> Result<(), Error>;
+
+    /// Returns the messages received on all subscribed topics since the last call, without blocking.
+    ///
+    /// Called regularly, even without subscriptions, so clients can answer keep-alives of the server here.
+    fn poll(&mut self) -> Result<Vec<Vec<u8>>, Error>;
+}
+
+/// A minimal, blocking client for the [NATS](https://nats.io) text protocol, without TLS or authentication.
+///
+/// The server disconnects clients not answering its `PING`s, they are answered on every publish and poll.
+#[derive(Debug)]
+pub struct NatsQueue {
+    stream: TcpStream,
+    /// Received bytes not parsed yet
+    buf: Vec<u8>,
+    /// Received messages not polled yet
+    messages: Vec<Vec<u8>>,
+    next_sid: u32,
+}
+
+impl NatsQueue {
+    /// Connects to the NATS server at `addr`, usually on port 4222
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(
+            b"CONNECT {\"verbose\":false,\"pedantic\":false,\"lang\":\"rust\",\"name\":\"libafl\"}\r\n",
+        )?;
+        Ok(Self {
+            stream,
+            buf: Vec::new(),
+            messages: Vec::new(),
+            next_sid: 1,
+        })
+    }
+
+    /// Reads everything the server sent so far without blocking, and answers its `PING`s
+    fn receive(&mut self) -> Result<(), Error> {
+        self.stream.set_nonblocking(true)?;
+        let mut chunk = [0; 4096];
+        let read = loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    break Err(Error::illegal_state(
+                        "The NATS server closed the connection",
+                    ))
+                }
+                Ok(len) => self.buf.extend_from_slice(&chunk[..len]),
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(err) => break Err(err.into()),
+            }
+        };
+        self.stream.set_nonblocking(false)?;
+        read?;
+
+        let pings = parse_nats(&mut self.buf, &mut self.messages)?;
+        for _ in 0..pings {
+            self.stream.write_all(b"PONG\r\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl MessageQueue for NatsQueue {
+    fn publish(&mut self, topic: &str, payload: &[u8]) -> Result<(), Error> {
+        self.receive()?;
+        let mut msg = format!("PUB {topic} {}\r\n", payload.len()).into_bytes();
+        msg.extend_from_slice(payload);
+        msg.extend_from_slice(b"\r\n");
+        self.stream.write_all(&msg)?;
+        Ok(())
+    }
+
+    fn subscribe(&mut self, topic: &str) -> Result<(), Error> {
+        self.stream
+            .write_all(format!("SUB {topic} {}\r\n", self.next_sid).as_bytes())?;
+        self.next_sid += 1;
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<Vec<Vec<u8>>, Error> {
+        self.receive()?;
+        Ok(core::mem::take(&mut self.messages))
+    }
+}
+
+/// Parses all complete NATS protocol messages at the start of `buf`, removing them from `buf`.
+/// The payloads of `MSG`s are added to `messages`. Returns the number of `PING`s to answer.
+fn parse_nats(buf: &mut Vec<u8>, messages: &mut Vec<Vec<u8>>) -> Result<usize, Error> {
+    let mut pings = 0;
+    let mut pos = 0;
+    while let Some(line_len) = buf[pos..].windows(2).position(|w| w == b"\r\n") {
+        let line = String::from_utf8_lossy(&buf[pos..pos + line_len]).into_owned();
+        let payload_start = pos + line_len + 2;
+        let mut args = line.split_ascii_whitespace();
+        match args.next() {
+            Some("MSG") => {
+                // MSG <subject> <sid> [reply-to] <#bytes>
+                let len: usize = args
+                    .last()
+                    .and_then(|len| len.parse().ok())
+                    .ok_or_else(|| Error::illegal_state(format!("Invalid NATS message: {line}")))?;
+                if buf.len() < payload_start + len + 2 {
+                    // The payload did not fully arrive yet
+                    break;
+                }
+                messages.push(buf[payload_start..payload_start + len].to_vec());
+                pos = payload_start + len + 2;
+                continue;
+            }
+            Some("PING") => pings += 1,
+            Some("-ERR") => log::error!("NATS server error: {line}"),
+            // INFO, +OK, PONG
+            _ => {}
+        }
+        pos = payload_start;
+    }
+    buf.drain(..pos);
+    Ok(pings)
+}
+
+/// An event, as published by the [`MqBridgeLlmpHook`], serialized as JSON
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MqRecord<'a, I> {
+    /// A client found a new corpus entry
+    Testcase {
+        /// The client that found it
+        client_id: u32,
+        /// The input
+        input: &'a I,
+        /// The exit kind of the input
+        exit_kind: ExitKind,
+        /// The corpus size of the client
+        corpus_size: usize,
+        /// The time the client found it
+        time: Duration,
+    },
+    /// A client found a new objective
+    Objective {
+        /// The client that found it
+        client_id: u32,
+        /// The objective corpus size of the client
+        objective_size: usize,
+        /// The time the client found it
+        time: Duration,
+    },
+    /// The executions of a client
+    ExecStats {
+        /// The client
+        client_id: u32,
+        /// The executions of this client
+        executions: u64,
+        /// The time the client sent the stats
+        time: Duration,
+    },
+    /// A user stat of a client
+    UserStats {
+        /// The client
+        client_id: u32,
+        /// The name of the stat
+        name: Cow<'a, str>,
+        /// The value of the stat, formatted for humans
+        value: String,
+    },
+}
+
+/// The topics a [`MqBridgeLlmpHook`] publishes to, and consumes from.
+/// Events for topics set to `None` are not mirrored.
+#[derive(Debug, Clone, Default, TypedBuilder)]
+pub struct MqTopics {
+    /// The topic to publish new corpus entries to
+    #[builder(default, setter(strip_option, into))]
+    pub testcases: Option<String>,
+    /// The topic to publish new objectives to
+    #[builder(default, setter(strip_option, into))]
+    pub objectives: Option<String>,
+    /// The topic to publish execution and user stats to
+    #[builder(default, setter(strip_option, into))]
+    pub stats: Option<String>,
+    /// The topic to consume seeds from, handed to all clients as new testcases
+    #[builder(default, setter(strip_option, into))]
+    pub seeds: Option<String>,
+}
+
+/// Statistics of a [`MqBridgeLlmpHook`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MqBridgeStats {
+    /// Records published to the queue
+    pub published: u64,
+    /// Records that could not be published
+    pub publish_errors: u64,
+    /// Seeds handed to the clients
+    pub seeds: u64,
+    /// Seeds that could not be parsed
+    pub invalid_seeds: u64,
+}
+
+/// The function turning the payload of a seed message into an input
+pub type SeedParserFn<I> = dyn FnMut(&[u8]) -> Result<I, Error>;
+
+/// How often the [`MqBridgeLlmpHook`] polls its [`MessageQueue`], for seeds and to keep the connection alive
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// An LLMP broker hook mirroring events of all clients to a [`MessageQueue`], and injecting seeds from it.
+///
+/// The queue is polled while messages from clients arrive, which is the case as long as any client is alive.
+pub struct MqBridgeLlmpHook<I, Q> {
+    queue: Q,
+    topics: MqTopics,
+    parse_seed: Box<SeedParserFn<I>>,
+    stats: MqBridgeStats,
+    last_poll: Option<Duration>,
+}
+
+impl<I, Q> fmt::Debug for MqBridgeLlmpHook<I, Q>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqBridgeLlmpHook")
+            .field("queue", &self.queue)
+            .field("topics", &self.topics)
+            .field("stats", &self.stats)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<I, Q> MqBridgeLlmpHook<I, Q>
+where
+    I: Input,
+    Q: MessageQueue,
+{
+    /// Creates a new [`MqBridgeLlmpHook`], subscribing to the seeds topic, if any.
+    /// Seed messages are turned into inputs using `parse_seed`, for example
+    /// `|bytes| Ok(BytesInput::new(bytes.to_vec()))`.
+    pub fn new<F>(mut queue: Q, topics: MqTopics, parse_seed: F) -> Result<Self, Error>
+    where
+        F: FnMut(&[u8]) -> Result<I, Error> + 'static,
+    {
+        if let Some(seeds) = &topics.seeds {
+            queue.subscribe(seeds)?;
+        }
+        Ok(Self {
+            queue,
+            topics,
+            parse_seed: Box::new(parse_seed),
+            stats: MqBridgeStats::default(),
+            last_poll: None,
+        })
+    }
+
+    /// The statistics of this hook
+    #[must_use]
+    pub fn stats(&self) -> &MqBridgeStats {
+        &self.stats
+    }
+
+    /// The message queue
+    #[must_use]
+    pub fn queue(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Publishes the `event` of `client_id`, if it is mirrored
+    fn publish_event(&mut self, client_id: ClientId, event: &Event<I>) -> Result<(), Error> {
+        let client_id = client_id.0;
+        let (topic, record) = match event {
+            Event::NewTestcase {
+                input,
+                exit_kind,
+                corpus_size,
+                time,
+                forward_id,
+                ..
+            } => (
+                &self.topics.testcases,
+                MqRecord::Testcase {
+                    client_id: forward_id.map_or(client_id, |id| id.0),
+                    input,
+                    exit_kind: *exit_kind,
+                    corpus_size: *corpus_size,
+                    time: *time,
+                },
+            ),
+            Event::Objective {
+                objective_size,
+                time,
+            } => (
+                &self.topics.objectives,
+                MqRecord::Objective {
+                    client_id,
+                    objective_size: *objective_size,
+                    time: *time,
+                },
+            ),
+            Event::UpdateExecStats {
+                time, executions, ..
+            } => (
+                &self.topics.stats,
+                MqRecord::ExecStats {
+                    client_id,
+                    executions: *executions,
+                    time: *time,
+                },
+            ),
+            Event::UpdateUserStats { name, value, .. } => (
+                &self.topics.stats,
+                MqRecord::UserStats {
+                    client_id,
+                    name: Cow::Borrowed(name.as_ref()),
+                    value: value.to_string(),
+                },
+            ),
+            _ => return Ok(()),
+        };
+        let Some(topic) = topic else {
+            return Ok(());
+        };
+        let payload = serde_json::to_vec(&record)
+            .map_err(|err| Error::serialize(format!("Failed to json-ify event: {err:?}")))?;
+        match self.queue.publish(topic, &payload) {
+            Ok(()) => self.stats.published += 1,
+            Err(err) => {
+                // A broken queue should not stop the fuzzing campaign
+                log::warn!("Could not publish to {topic}: {err}");
+                self.stats.publish_errors += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Polls the queue, even without a seeds topic so it can answer keep-alives,
+    /// and hands all seeds received to the clients
+    fn inject_seeds(&mut self, new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>) -> Result<(), Error> {
+        let now = current_time();
+        if self
+            .last_poll
+            .is_some_and(|last_poll| now.saturating_sub(last_poll) < QUEUE_POLL_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_poll = Some(now);
+        let seeds = match self.queue.poll() {
+            Ok(seeds) => seeds,
+            Err(err) => {
+                log::warn!("Could not receive seeds: {err}");
+                return Ok(());
+            }
+        };
+        if self.topics.seeds.is_none() {
+            return Ok(());
+        }
+        for seed in seeds {
+            let input = match (self.parse_seed)(&seed) {
+                Ok(input) => input,
+                Err(err) => {
+                    log::warn!("Dropping invalid seed: {err}");
+                    self.stats.invalid_seeds += 1;
+                    continue;
+                }
+            };
+            let event = Event::NewTestcase {
+                input,
+                observers_buf: None,
+                exit_kind: ExitKind::Ok,
+                corpus_size: 0,
+                client_config: EventConfig::AlwaysUnique,
+                time: current_time(),
+                forward_id: None,
+                #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+                node_id: None,
+            };
+            new_msgs.push((
+                LLMP_TAG_EVENT_TO_BOTH,
+                LLMP_FLAG_INITIALIZED,
+                postcard::to_allocvec(&event)?,
+            ));
+            self.stats.seeds += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<I, Q, SP> LlmpHook<SP> for MqBridgeLlmpHook<I, Q>
+where
+    I: Input,
+    Q: MessageQueue,
+    SP: ShMemProvider,
+{
+    fn on_new_message(
+        &mut self,
+        _broker_inner: &mut LlmpBrokerInner<SP>,
+        client_id: ClientId,
+        msg_tag: &mut Tag,
+        #[cfg(feature = "llmp_compression")] msg_flags: &mut Flags,
+        #[cfg(not(feature = "llmp_compression"))] _msg_flags: &mut Flags,
+        msg: &mut [u8],
+        new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<LlmpMsgHookResult, Error> {
+        self.inject_seeds(new_msgs)?;
+        if *msg_tag != LLMP_TAG_EVENT_TO_BOTH {
+            return Ok(LlmpMsgHookResult::ForwardToClients);
+        }
+        #[cfg(not(feature = "llmp_compression"))]
+        let event_bytes = msg;
+        #[cfg(feature = "llmp_compression")]
+        let compressed;
+        #[cfg(feature = "llmp_compression")]
+        let event_bytes = if let Some(codec) = msg_flags.compression_codec() {
+            compressed = codec.decompress(msg)?;
+            &compressed
+        } else {
+            &*msg
+        };
+        let event: Event<I> = postcard::from_bytes(event_bytes)?;
+        self.publish_event(client_id, &event)?;
+        Ok(LlmpMsgHookResult::ForwardToClients)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::time::Duration;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::{parse_nats, MessageQueue, MqBridgeLlmpHook, MqTopics, NatsQueue};
+    use crate::{
+        events::{Event, EventConfig},
+        executors::ExitKind,
+        inputs::BytesInput,
+        Error,
+    };
+
+    #[derive(Debug, Default)]
+    struct TestQueue {
+        published: Vec<(String, Vec<u8>)>,
+        subscribed: Vec<String>,
+        pending: Vec<Vec<u8>>,
+    }
+
+    impl MessageQueue for TestQueue {
+        fn publish(&mut self, topic: &str, payload: &[u8]) -> Result<(), Error> {
+            self.published.push((topic.to_string(), payload.to_vec()));
+            Ok(())
+        }
+
+        fn subscribe(&mut self, topic: &str) -> Result<(), Error> {
+            self.subscribed.push(topic.to_string());
+            Ok(())
+        }
+
+        fn poll(&mut self) -> Result<Vec<Vec<u8>>, Error> {
+            Ok(core::mem::take(&mut self.pending))
+        }
+    }
+
+    #[test]
+    fn test_mq_bridge() {
+        let topics = MqTopics::builder()
+            .testcases("corpus")
+            .seeds("seeds")
+            .build();
+        let mut hook = MqBridgeLlmpHook::new(TestQueue::default(), topics, |bytes: &[u8]| {
+            Ok(BytesInput::new(bytes.to_vec()))
+        })
+        .unwrap();
+        assert_eq!(hook.queue().subscribed, ["seeds"]);
+
+        let testcase = Event::NewTestcase {
+            input: BytesInput::new(b"abc".to_vec()),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size: 7,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            forward_id: None,
+            #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
+            node_id: None,
+        };
+        hook.publish_event(ClientId(2), &testcase).unwrap();
+        // No topic for objectives
+        hook.publish_event(
+            ClientId(2),
+            &Event::Objective {
+                objective_size: 1,
+                time: Duration::ZERO,
+            },
+        )
+        .unwrap();
+        assert_eq!(hook.queue().published.len(), 1);
+        let (topic, payload) = &hook.queue().published[0];
+        assert_eq!(topic, "corpus");
+        let record: serde_json::Value = serde_json::from_slice(payload).unwrap();
+        assert_eq!(record["kind"], "testcase");
+        assert_eq!(record["client_id"], 2);
+        assert_eq!(record["corpus_size"], 7);
+
+        hook.queue.pending.push(b"seed".to_vec());
+        let mut new_msgs = Vec::new();
+        hook.inject_seeds(&mut new_msgs).unwrap();
+        assert_eq!(new_msgs.len(), 1);
+        let event: Event<BytesInput> = postcard::from_bytes(&new_msgs[0].2).unwrap();
+        assert!(
+            matches!(event, Event::NewTestcase { input, .. } if input == BytesInput::new(b"seed".to_vec()))
+        );
+        assert_eq!(hook.stats().published, 1);
+        assert_eq!(hook.stats().seeds, 1);
+    }
+
+    #[test]
+    fn test_parse_nats() {
+        let mut buf =
+            b"INFO {}\r\nMSG seeds 1 3\r\nabc\r\nPING\r\nMSG seeds 1 reply 4\r\nde".to_vec();
+        let mut messages = Vec::new();
+        assert_eq!(parse_nats(&mut buf, &mut messages).unwrap(), 1);
+        assert_eq!(messages, [b"abc".to_vec()]);
+        // The incomplete message stays in the buffer
+        assert_eq!(buf, b"MSG seeds 1 reply 4\r\nde");
+
+        buf.extend_from_slice(b"fg\r\n");
+        assert_eq!(parse_nats(&mut buf, &mut messages).unwrap(), 0);
+        assert_eq!(messages, [b"abc".to_vec(), b"defg".to_vec()]);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_nats_answers_ping_on_publish() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"PING\r\n").unwrap();
+            let mut lines = BufReader::new(stream).lines();
+            let connect = lines.next().unwrap().unwrap();
+            assert!(connect.starts_with("CONNECT "));
+            lines.map(Result::unwrap).take(2).collect::<Vec<_>>()
+        });
+
+        let mut queue = NatsQueue::connect(addr).unwrap();
+        // Wait for the PING, a bridge without seeds topic may only ever publish
+        queue.stream.peek(&mut [0]).unwrap();
+        queue.publish("corpus", b"x").unwrap();
+        assert_eq!(server.join().unwrap(), ["PONG", "PUB corpus 1"]);
+    }
+}
@@ -7,14 +7,14 @@
 // 3. The "main evaluator", the evaluator node that will evaluate all the testcases pass by the centralized event manager to see if the testcases are worth propagating
 // 4. The "main broker", the gathers the stats from the fuzzer clients and broadcast the newly found testcases from the main evaluator.
 
-use alloc::{boxed::Box, string::String, vec::Vec};
-use core::{fmt::Debug, time::Duration};
+use alloc::{boxed::Box, rc::Rc, string::String, vec::Vec};
+use core::{cell::Cell, fmt::Debug, time::Duration};
 use std::{marker::PhantomData, process};
 
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{CompressionCodec, Compressor},
+    llmp::{Flags, LLMP_FLAG_INITIALIZED},
 };
 use libafl_bolts::{
     llmp::{LlmpClient, LlmpClientDescription, Tag},
@@ -31,8 +31,8 @@ use crate::events::llmp::COMPRESS_THRESHOLD;
 use crate::state::HasScalabilityMonitor;
 use crate::{
     events::{
-        AdaptiveSerializer, CustomBufEventResult, Event, EventConfig, EventFirer, EventManager,
-        EventManagerHooksTuple, EventManagerId, EventProcessor, EventRestarter,
+        AdaptiveSerializer, CustomBufEventResult, CustomEvent, Event, EventConfig, EventFirer,
+        EventManager, EventManagerHooksTuple, EventManagerId, EventProcessor, EventRestarter,
         HasCustomBufHandlers, HasEventManagerId, LogSeverity, ProgressReporter,
     },
     executors::{Executor, HasObservers},
@@ -45,6 +45,57 @@ use crate::{
 
 pub(crate) const _LLMP_TAG_TO_MAIN: Tag = Tag(0x3453453);
 
+/// Which events secondary nodes forward to the main node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ForwardPolicy {
+    /// Forward objectives only, new testcases go to the main broker directly, without evaluation by the main node
+    Objectives,
+    /// Forward objectives and new testcases, the latter are only broadcast if the main node deems them interesting
+    #[default]
+    ObjectivesAndTestcases,
+    /// Forward objectives, new testcases and all user stats
+    Full,
+}
+
+impl ForwardPolicy {
+    /// If new testcases are sent to the main node for evaluation
+    #[must_use]
+    pub fn forwards_testcases(self) -> bool {
+        self != Self::Objectives
+    }
+
+    /// If user stats are sent to the main node
+    #[must_use]
+    pub fn forwards_stats(self) -> bool {
+        self == Self::Full
+    }
+}
+
+/// The policy of a [`CentralizedEventManager`].
+///
+/// The main node can switch the policy of all secondary nodes at runtime, using [`CentralizedEventManager::set_policy`].
+/// It is sent as a [`CustomEvent`], applied by secondaries that [`CentralizedEventManager::follow_policy_updates`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CentralizedPolicy {
+    /// Which events secondary nodes forward to the main node
+    pub forward: ForwardPolicy,
+    /// If the main node broadcasts the testcases it deemed interesting back to all nodes
+    pub rebroadcast: bool,
+}
+
+impl Default for CentralizedPolicy {
+    fn default() -> Self {
+        Self {
+            forward: ForwardPolicy::default(),
+            rebroadcast: true,
+        }
+    }
+}
+
+impl CustomEvent for CentralizedPolicy {
+    const NAME: &'static str = "libafl_centralized_policy";
+}
+
 /// A wrapper manager to implement a main-secondary architecture with another broker
 #[derive(Debug)]
 pub struct CentralizedEventManager<EM, EMH, S, SP>
@@ -58,10 +109,11 @@ where
     /// The centralized LLMP client for inter process communication
     client: LlmpClient<SP>,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: Compressor,
     time_ref: Option<Handle<TimeObserver>>,
     hooks: EMH,
     is_main: bool,
+    policy: Rc<Cell<CentralizedPolicy>>,
     phantom: PhantomData<S>,
 }
 
@@ -84,6 +136,9 @@ impl
 #[derive(Debug)]
 pub struct CentralizedEventManagerBuilder {
     is_main: bool,
+    policy: CentralizedPolicy,
+    #[cfg(feature = "llmp_compression")]
+    compressor: Compressor,
 }
 
 impl Default for CentralizedEventManagerBuilder {
@@ -96,13 +151,35 @@ impl CentralizedEventManagerBuilder {
     /// The constructor
     #[must_use]
     pub fn new() -> Self {
-        Self { is_main: false }
+        Self {
+            is_main: false,
+            policy: CentralizedPolicy::default(),
+            #[cfg(feature = "llmp_compression")]
+            compressor: Compressor::new(CompressionCodec::Gzip, COMPRESS_THRESHOLD),
+        }
     }
 
     /// Make this a main evaluator node
     #[must_use]
     pub fn is_main(self, is_main: bool) -> Self {
-        Self { is_main }
+        Self { is_main, ..self }
+    }
+
+    /// The initial [`CentralizedPolicy`] of this node
+    #[must_use]
+    pub fn policy(self, policy: CentralizedPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
+    /// Compress the events sent to the centralized broker of at least `threshold` bytes using `codec`.
+    /// Defaults to gzip for events of at least [`COMPRESS_THRESHOLD`] bytes.
+    #[cfg(feature = "llmp_compression")]
+    #[must_use]
+    pub fn compression(self, codec: CompressionCodec, threshold: usize) -> Self {
+        Self {
+            compressor: Compressor::new(codec, threshold),
+            ..self
+        }
     }
 
     /// Creates a new [`CentralizedEventManager`].
@@ -124,9 +201,10 @@ impl CentralizedEventManagerBuilder {
             hooks,
             client,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             time_ref: time_obs,
             is_main: self.is_main,
+            policy: Rc::new(Cell::new(self.policy)),
             phantom: PhantomData,
         })
     }
@@ -156,9 +234,10 @@ impl CentralizedEventManagerBuilder {
             hooks,
             client,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             time_ref: time_obs,
             is_main: self.is_main,
+            policy: Rc::new(Cell::new(self.policy)),
             phantom: PhantomData,
         })
     }
@@ -185,9 +264,10 @@ impl CentralizedEventManagerBuilder {
             hooks,
             client: LlmpClient::on_existing_from_env(shmem_provider, env_name)?,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             time_ref: time_obs,
             is_main: self.is_main,
+            policy: Rc::new(Cell::new(self.policy)),
             phantom: PhantomData,
         })
     }
@@ -213,9 +293,10 @@ impl CentralizedEventManagerBuilder {
             hooks,
             client: LlmpClient::existing_client_from_description(shmem_provider, description)?,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             time_ref: time_obs,
             is_main: self.is_main,
+            policy: Rc::new(Cell::new(self.policy)),
             phantom: PhantomData,
         })
     }
@@ -287,16 +368,21 @@ where
     ) -> Result<(), Error> {
         if !self.is_main {
             // secondary node
+            let forward = self.policy.get().forward;
             let mut is_tc = false;
-            // Forward to main only if new tc or heartbeat
+            // Forward to main only what the policy asks for, and heartbeats
             let should_be_forwarded = match &mut event {
-                Event::NewTestcase { forward_id, .. } => {
+                Event::NewTestcase { forward_id, .. } if forward.forwards_testcases() => {
                     *forward_id = Some(ClientId(self.inner.mgr_id().0 as u32));
                     is_tc = true;
                     true
                 }
                 Event::UpdateExecStats { .. } => true, // send it but this guy won't be handled. the only purpose is to keep this client alive else the broker thinks it is dead and will dc it
-                Event::Stop => true,
+                Event::Objective { .. } => true,
+                Event::UpdateUserStats { .. } => forward.forwards_stats(),
+                #[cfg(feature = "introspection")]
+                Event::UpdatePerfMonitor { .. } => forward.forwards_stats(),
+                Event::Stop | Event::SetStopPolicy { .. } => true,
                 _ => false,
             };
 
@@ -481,6 +567,47 @@ where
     pub fn is_main(&self) -> bool {
         self.is_main
     }
+
+    /// The current [`CentralizedPolicy`] of this node
+    pub fn policy(&self) -> CentralizedPolicy {
+        self.policy.get()
+    }
+
+    /// Applies the [`CentralizedPolicy`] updates sent by the main node, see [`CentralizedEventManager::set_policy`].
+    pub fn follow_policy_updates(&mut self)
+    where
+        EM: HasCustomBufHandlers,
+    {
+        let policy = self.policy.clone();
+        self.inner
+            .add_custom_event_handler(move |_state, update: CentralizedPolicy| {
+                log::info!("Switching to centralized policy {update:?}");
+                policy.set(update);
+                Ok(CustomBufEventResult::Handled)
+            });
+    }
+}
+
+impl<EM, EMH, S, SP> CentralizedEventManager<EM, EMH, S, SP>
+where
+    EM: EventFirer,
+    EMH: EventManagerHooksTuple<EM::State>,
+    S: State,
+    SP: ShMemProvider,
+{
+    /// Switches to a new [`CentralizedPolicy`].
+    /// On the main node, the policy is also sent to all secondary nodes that [`CentralizedEventManager::follow_policy_updates`].
+    pub fn set_policy(
+        &mut self,
+        state: &mut EM::State,
+        policy: CentralizedPolicy,
+    ) -> Result<(), Error> {
+        self.policy.set(policy);
+        if self.is_main {
+            self.inner.fire_custom(state, &policy)?;
+        }
+        Ok(())
+    }
 }
 
 impl<EM, EMH, S, SP> CentralizedEventManager<EM, EMH, S, SP>
@@ -502,7 +629,7 @@ where
             Some(comp_buf) => {
                 self.client.send_buf_with_flags(
                     _LLMP_TAG_TO_MAIN,
-                    flags | LLMP_FLAG_COMPRESSED,
+                    flags | Flags::compressed_with(self.compressor.codec()),
                     &comp_buf,
                 )?;
             }
@@ -555,8 +682,8 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+            let event_bytes = if let Some(codec) = _flags.compression_codec() {
+                compressed = codec.decompress(msg)?;
                 &compressed
             } else {
                 msg
@@ -564,7 +691,10 @@ where
             let event: Event<<<Self as UsesState>::State as UsesInput>::Input> =
                 postcard::from_bytes(event_bytes)?;
             log::debug!("Processor received message {}", event.name_detailed());
-            self.handle_in_main(fuzzer, executor, state, client_id, event)?;
+            if self.hooks.pre_exec_all(state, client_id, &event)? {
+                self.handle_in_main(fuzzer, executor, state, client_id, event)?;
+                self.hooks.post_exec_all(state, client_id)?;
+            }
             count += 1;
         }
         Ok(count)
@@ -650,6 +780,14 @@ where
                     };
 
                 if let Some(item) = res.1 {
+                    if !self.policy.get().rebroadcast {
+                        log::debug!(
+                            "[{}] Added received Testcase {} as item #{item}, not broadcasting it",
+                            process::id(),
+                            event_name
+                        );
+                        return Ok(());
+                    }
                     let event = Event::NewTestcase {
                         input,
                         client_config,
@@ -675,9 +813,20 @@ where
                     log::debug!("[{}] {} was discarded...)", process::id(), event_name);
                 }
             }
+            Event::Objective { .. } | Event::UpdateUserStats { .. } => {
+                // Already reported to the main broker by the secondary, only of interest for the hooks
+                log::debug!("Received {event_name} from {client_id:?}");
+            }
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => {
+                log::debug!("Received {event_name} from {client_id:?}");
+            }
             Event::Stop => {
                 state.request_stop();
             }
+            Event::SetStopPolicy { policy } => {
+                state.add_metadata(policy);
+            }
             _ => {
                 return Err(Error::unknown(format!(
                     "Received illegal message that message should not have arrived: {:?}.",
@@ -0,0 +1,444 @@
+//! Persistence of fired [`Event`]s, for post-mortem analysis and replay of fuzzing campaigns.
+//!
+//! Wrap any [`EventManager`] in an [`EventLog`] to append each [`Event`] it fires to a file,
+//! each event as a little-endian `u32` length, followed by the postcard-serialized [`Event`].
+//! Events received from other clients never pass the wrapper. To log them as well, add an [`EventLogHook`]
+//! to the hooks of the wrapped manager.
+//! Use an [`EventLogReader`] to iterate over a recorded log, or to replay its testcases into a fresh state.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::marker::PhantomData;
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::ClientId;
+use serde::Serialize;
+
+use crate::{
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerHook,
+        EventManagerId, EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId,
+        LogSeverity, ProgressReporter,
+    },
+    fuzzer::Evaluator,
+    inputs::{Input, UsesInput},
+    observers::ObserversTuple,
+    state::{HasExecutions, HasLastReportTime, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// The maximum length of a single serialized [`Event`] in the log.
+/// Longer events are not written, and a log announcing a longer event is rejected by the [`EventLogReader`].
+pub const MAX_EVENT_LEN: usize = 1 << 28;
+
+/// Opens the log file at `path` for appending, creating it if needed
+fn open_log(path: &Path) -> Result<BufWriter<File>, Error> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(BufWriter::new(file))
+}
+
+/// Appends an event to the log.
+/// The log is flushed after each event, so it is complete even if the fuzzer crashes.
+fn append_event<I>(writer: &mut BufWriter<File>, event: &Event<I>) -> Result<(), Error>
+where
+    I: Input,
+{
+    let serialized = postcard::to_allocvec(event)?;
+    if serialized.len() > MAX_EVENT_LEN {
+        return Err(Error::illegal_argument("Event too large for the event log"));
+    }
+    writer.write_all(&u32::try_from(serialized.len())?.to_le_bytes())?;
+    writer.write_all(&serialized)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// An [`EventManager`] wrapping another manager, appending every fired [`Event`] to a log file.
+///
+/// The log is opened in append mode, so restarted fuzzers continue the log of their previous runs.
+#[derive(Debug)]
+pub struct EventLog<EM> {
+    inner: EM,
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl<EM> EventLog<EM> {
+    /// Creates a new [`EventLog`], appending all events fired by `inner` to the file at `path`
+    pub fn new<P>(inner: EM, path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_path_buf();
+        Ok(Self {
+            inner,
+            writer: open_log(&path)?,
+            path,
+        })
+    }
+
+    /// The path of the log file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The wrapped event manager
+    #[must_use]
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+
+    /// The wrapped event manager (mutable)
+    pub fn inner_mut(&mut self) -> &mut EM {
+        &mut self.inner
+    }
+
+    /// Flushes the log and returns the wrapped event manager
+    pub fn into_inner(mut self) -> Result<EM, Error> {
+        self.writer.flush()?;
+        Ok(self.inner)
+    }
+
+    /// Creates an [`EventLogHook`] appending to the same log, to add to the hooks of the wrapped manager.
+    /// That way, the log contains the events received from other clients, too.
+    pub fn receive_hook(&self) -> Result<EventLogHook, Error> {
+        EventLogHook::new(&self.path)
+    }
+}
+
+impl<EM> UsesState for EventLog<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for EventLog<EM>
+where
+    EM: EventFirer,
+{
+    fn should_send(&self) -> bool {
+        self.inner.should_send()
+    }
+
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        append_event(&mut self.writer, &event)?;
+        self.inner.fire(state, event)
+    }
+
+    #[inline]
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.fire(
+            state,
+            Event::Log {
+                severity_level,
+                message,
+                phantom: PhantomData,
+            },
+        )
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<<Self as UsesInput>::Input, Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for EventLog<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for EventLog<EM>
+where
+    EM: EventProcessor<E, Z>,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.inner.process(fuzzer, state, executor)
+    }
+
+    fn on_shutdown(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.inner.on_shutdown()
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for EventLog<EM>
+where
+    EM: EventManager<E, Z>,
+    Self::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasCustomBufHandlers for EventLog<EM>
+where
+    Self: UsesState,
+    EM: HasCustomBufHandlers<State = Self::State>,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+/// Progress reports are fired through the [`EventLog`], so they end up in the log as well.
+impl<EM> ProgressReporter for EventLog<EM>
+where
+    EM: EventFirer,
+    Self::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasEventManagerId for EventLog<EM>
+where
+    EM: HasEventManagerId,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+/// An [`EventManagerHook`] appending every [`Event`] received from other clients to a log file,
+/// in the format of the [`EventLog`].
+///
+/// Events are logged before the manager handles them, and are never filtered.
+#[derive(Debug)]
+pub struct EventLogHook {
+    writer: BufWriter<File>,
+}
+
+impl EventLogHook {
+    /// Creates a new [`EventLogHook`], appending all received events to the file at `path`
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            writer: open_log(path.as_ref())?,
+        })
+    }
+}
+
+impl<S> EventManagerHook<S> for EventLogHook
+where
+    S: State,
+{
+    fn pre_exec(
+        &mut self,
+        _state: &mut S,
+        _client_id: ClientId,
+        event: &Event<S::Input>,
+    ) -> Result<bool, Error> {
+        append_event(&mut self.writer, event)?;
+        Ok(true)
+    }
+}
+
+/// Reads the [`Event`]s recorded by an [`EventLog`], in the order they were fired or received.
+#[derive(Debug)]
+pub struct EventLogReader<I> {
+    reader: BufReader<File>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> EventLogReader<I>
+where
+    I: Input,
+{
+    /// Opens the event log at `path`
+    pub fn open<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            reader: BufReader::new(File::open(path)?),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Reads the next event, `None` at the end of the log
+    pub fn next_event(&mut self) -> Result<Option<Event<I>>, Error> {
+        let mut len = [0_u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len > MAX_EVENT_LEN {
+            return Err(Error::illegal_state(format!(
+                "The event log announces an event of {len} bytes, more than the maximum of {MAX_EVENT_LEN}"
+            )));
+        }
+        let mut buf = vec![0_u8; len];
+        self.reader.read_exact(&mut buf).map_err(|err| {
+            if err.kind() == ErrorKind::UnexpectedEof {
+                Error::illegal_state("The event log ends with a truncated event")
+            } else {
+                err.into()
+            }
+        })?;
+        Ok(Some(postcard::from_bytes(&buf)?))
+    }
+
+    /// Feeds the inputs of all [`Event::NewTestcase`]s in the log to `fuzzer`, evaluating them in the given `state`.
+    /// All other events are skipped.
+    /// Returns the number of replayed testcases.
+    pub fn replay<E, EM, S, Z>(
+        mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<usize, Error>
+    where
+        S: State + UsesInput<Input = I>,
+        Z: Evaluator<E, EM, State = S>,
+    {
+        let mut count = 0;
+        while let Some(event) = self.next_event()? {
+            if let Event::NewTestcase { input, .. } = event {
+                fuzzer.evaluate_input(state, executor, manager, input)?;
+                count += 1;
+            }
+        }
+        log::info!("Replayed {count} testcases from the event log");
+        Ok(count)
+    }
+}
+
+impl<I> Iterator for EventLogReader<I>
+where
+    I: Input,
+{
+    type Item = Result<Event<I>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::marker::PhantomData;
+    use std::{env, fs, process};
+
+    use libafl_bolts::ClientId;
+
+    use super::{EventLog, EventLogReader};
+    use crate::{
+        events::{Event, EventFirer, EventManagerHook, LogSeverity, NopEventManager},
+        inputs::BytesInput,
+        state::NopState,
+        Error,
+    };
+
+    #[test]
+    fn test_event_log() {
+        let path = env::temp_dir().join(format!("libafl_event_log_{}", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut state = NopState::<BytesInput>::new();
+
+        let mut mgr = EventLog::new(NopEventManager::new(), &path).unwrap();
+        mgr.log(&mut state, LogSeverity::Info, "hello".into())
+            .unwrap();
+        mgr.fire(
+            &mut state,
+            Event::UpdateExecStats {
+                time: core::time::Duration::from_secs(1),
+                executions: 42,
+                phantom: PhantomData,
+            },
+        )
+        .unwrap();
+        mgr.into_inner().unwrap();
+
+        // Reopening appends to the existing log
+        let mut mgr = EventLog::new(NopEventManager::new(), &path).unwrap();
+        mgr.fire(&mut state, Event::Stop).unwrap();
+
+        // Received events are logged by the hook
+        let mut hook = mgr.receive_hook().unwrap();
+        assert!(hook
+            .pre_exec(&mut state, ClientId(1), &Event::Stop)
+            .unwrap());
+        drop(mgr);
+
+        let events = EventLogReader::<BytesInput>::open(&path)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], Event::Log { message, .. } if message == "hello"));
+        assert!(matches!(
+            events[1],
+            Event::UpdateExecStats { executions: 42, .. }
+        ));
+        assert!(matches!(events[2], Event::Stop));
+        assert!(matches!(events[3], Event::Stop));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_event_log_rejects_oversized_header() {
+        let path = env::temp_dir().join(format!("libafl_event_log_oversized_{}", process::id()));
+        fs::write(&path, u32::MAX.to_le_bytes()).unwrap();
+
+        let mut reader = EventLogReader::<BytesInput>::open(&path).unwrap();
+        assert!(matches!(reader.next(), Some(Err(Error::IllegalState(..)))));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,1151 @@
+//! gRPC-backed event manager, to integrate `LibAFL` nodes into existing service meshes.
+//!
+//! The [`GrpcEventBroker`] serves the `libafl.events.EventBroker` service, described in `proto/events.proto`.
+//! [`GrpcEventManager`]s connect to it to exchange events with all other fuzzer clients.
+//! Orchestrators, written in any language, can subscribe to the stream of all events the broker receives.
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    convert::Infallible,
+    marker::PhantomData,
+    sync::atomic::{AtomicU32, Ordering},
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    net::SocketAddr,
+    sync::{mpsc as std_mpsc, Arc, Mutex},
+    thread,
+};
+
+use hashbrown::HashMap;
+use libafl_bolts::{current_time, ClientId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use tonic::{
+    body::BoxBody,
+    codec::ProstCodec,
+    codegen::{http, BoxFuture, BoxStream, Service},
+    server::{Grpc, NamedService, ServerStreamingService, StreamingService},
+    transport::{Endpoint, Server},
+    Request, Response, Status, Streaming,
+};
+
+use self::proto::{EventEnvelope, SubscribeRequest, Summary};
+use uuid::Uuid;
+
+use super::{tokens_match, CustomBufEventResult, CustomBufHandlerFn};
+use crate::{
+    events::{
+        BrokerEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerHooksTuple,
+        EventManagerId, EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId,
+        ProgressReporter,
+    },
+    executors::{Executor, HasObservers},
+    fuzzer::{EvaluatorObservers, ExecutionProcessor},
+    inputs::{Input, UsesInput},
+    monitors::Monitor,
+    observers::ObserversTuple,
+    state::{HasExecutions, HasImported, HasLastReportTime, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// The protobuf messages of `proto/events.proto`
+#[allow(missing_docs)]
+pub mod proto {
+    use alloc::{string::String, vec::Vec};
+
+    /// Subscribes to all events the broker receives
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SubscribeRequest {}
+
+    /// A `LibAFL` [`crate::events::Event`], as sent over the wire
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct EventEnvelope {
+        /// The client that fired the event
+        #[prost(uint32, tag = "1")]
+        pub client_id: u32,
+        /// The name of the event
+        #[prost(string, tag = "2")]
+        pub name: String,
+        /// The postcard-serialized event
+        #[prost(bytes = "vec", tag = "3")]
+        pub payload: Vec<u8>,
+        /// A summary of the event, for subscribers that can't deserialize the payload
+        #[prost(oneof = "Summary", tags = "4, 5, 6, 7, 8, 9, 10")]
+        pub summary: Option<Summary>,
+    }
+
+    /// The summary of an [`EventEnvelope`]
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Summary {
+        #[prost(message, tag = "4")]
+        NewTestcase(NewTestcase),
+        #[prost(message, tag = "5")]
+        ExecStats(ExecStats),
+        #[prost(message, tag = "6")]
+        UserStats(UserStats),
+        #[prost(message, tag = "7")]
+        Objective(Objective),
+        #[prost(message, tag = "8")]
+        Log(Log),
+        #[prost(message, tag = "9")]
+        CustomBuf(CustomBuf),
+        #[prost(message, tag = "10")]
+        Stop(Stop),
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NewTestcase {
+        #[prost(uint64, tag = "1")]
+        pub corpus_size: u64,
+        #[prost(string, tag = "2")]
+        pub exit_kind: String,
+        #[prost(uint64, tag = "3")]
+        pub time_nanos: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ExecStats {
+        #[prost(uint64, tag = "1")]
+        pub executions: u64,
+        #[prost(uint64, tag = "2")]
+        pub time_nanos: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct UserStats {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(string, tag = "2")]
+        pub value: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Objective {
+        #[prost(uint64, tag = "1")]
+        pub objective_size: u64,
+        #[prost(uint64, tag = "2")]
+        pub time_nanos: u64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Log {
+        #[prost(string, tag = "1")]
+        pub severity: String,
+        #[prost(string, tag = "2")]
+        pub message: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct CustomBuf {
+        #[prost(string, tag = "1")]
+        pub tag: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub buf: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Stop {}
+}
+
+/// The gRPC metadata key a client sends its previous client id in, and receives its (new) client id in
+const CLIENT_ID_KEY: &str = "libafl-client-id";
+
+/// The gRPC metadata key of the secret the broker hands out with each client id.
+/// Reconnecting clients only keep their id if they send it along.
+const CLIENT_TOKEN_KEY: &str = "libafl-client-token";
+
+/// The name of the gRPC service
+const SERVICE_NAME: &str = "libafl.events.EventBroker";
+
+/// The path of the bidirectional `Connect` call, for fuzzer clients
+const CONNECT_PATH: &str = "/libafl.events.EventBroker/Connect";
+
+/// The path of the `Subscribe` call, for orchestrators
+const SUBSCRIBE_PATH: &str = "/libafl.events.EventBroker/Subscribe";
+
+/// How many events the broker buffers for each client and subscriber
+const CHANNEL_CAPACITY: usize = 65536;
+
+fn time_nanos(time: Duration) -> u64 {
+    u64::try_from(time.as_nanos()).unwrap_or(u64::MAX)
+}
+
+impl EventEnvelope {
+    /// Wraps an `event` fired by `client_id`
+    pub fn new<I>(client_id: ClientId, event: &Event<I>) -> Result<Self, Error>
+    where
+        I: Input,
+    {
+        let summary = match event {
+            Event::NewTestcase {
+                corpus_size,
+                exit_kind,
+                time,
+                ..
+            } => Some(Summary::NewTestcase(proto::NewTestcase {
+                corpus_size: *corpus_size as u64,
+                exit_kind: format!("{exit_kind:?}"),
+                time_nanos: time_nanos(*time),
+            })),
+            Event::UpdateExecStats {
+                time, executions, ..
+            } => Some(Summary::ExecStats(proto::ExecStats {
+                executions: *executions,
+                time_nanos: time_nanos(*time),
+            })),
+            Event::UpdateUserStats { name, value, .. } => {
+                Some(Summary::UserStats(proto::UserStats {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                }))
+            }
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { .. } => None,
+            Event::Objective {
+                objective_size,
+                time,
+            } => Some(Summary::Objective(proto::Objective {
+                objective_size: *objective_size as u64,
+                time_nanos: time_nanos(*time),
+            })),
+            Event::Log {
+                severity_level,
+                message,
+                ..
+            } => Some(Summary::Log(proto::Log {
+                severity: severity_level.to_string(),
+                message: message.clone(),
+            })),
+            Event::CustomBuf { tag, buf } => Some(Summary::CustomBuf(proto::CustomBuf {
+                tag: tag.clone(),
+                buf: buf.clone(),
+            })),
+            Event::Stop => Some(Summary::Stop(proto::Stop {})),
+            Event::SetStopPolicy { .. } => None,
+        };
+        Ok(Self {
+            client_id: client_id.0,
+            name: event.name().to_string(),
+            payload: postcard::to_allocvec(event)?,
+            summary,
+        })
+    }
+
+    /// Deserializes the wrapped [`Event`]
+    pub fn event<I>(&self) -> Result<Event<I>, Error>
+    where
+        I: Input,
+    {
+        Ok(postcard::from_bytes(&self.payload)?)
+    }
+}
+
+/// The state the gRPC service shares with the [`GrpcEventBroker`]
+#[derive(Debug)]
+struct BrokerShared {
+    next_client_id: AtomicU32,
+    /// The token of each client id handed out so far
+    tokens: Mutex<HashMap<u32, String>>,
+    /// Events of all clients, to be handled by the broker
+    incoming: mpsc::Sender<EventEnvelope>,
+    /// Events the broker forwards to the clients
+    to_clients: broadcast::Sender<EventEnvelope>,
+    /// All events, for subscribers
+    to_subscribers: broadcast::Sender<EventEnvelope>,
+}
+
+/// Turns a broadcast receiver into a response stream, skipping events of `skip_client`
+fn broadcast_stream(
+    rx: broadcast::Receiver<EventEnvelope>,
+    skip_client: Option<u32>,
+) -> BoxStream<EventEnvelope> {
+    Box::pin(
+        BroadcastStream::new(rx).filter_map(move |envelope| match envelope {
+            Ok(envelope) if Some(envelope.client_id) == skip_client => None,
+            Ok(envelope) => Some(Ok(envelope)),
+            Err(BroadcastStreamRecvError::Lagged(num)) => {
+                log::error!("gRPC receiver lagged, skipping {num} events");
+                None
+            }
+        }),
+    )
+}
+
+/// Handles the `Connect` calls of fuzzer clients
+struct ConnectHandler(Arc<BrokerShared>);
+
+impl StreamingService<EventEnvelope> for ConnectHandler {
+    type Response = EventEnvelope;
+    type ResponseStream = BoxStream<EventEnvelope>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, request: Request<Streaming<EventEnvelope>>) -> Self::Future {
+        let shared = self.0.clone();
+        Box::pin(async move {
+            // Protocol: the broker assigns all ids. Reconnecting clients keep theirs, if they send its token along.
+            let metadata = request.metadata();
+            let claimed = metadata
+                .get(CLIENT_ID_KEY)
+                .and_then(|id| id.to_str().ok()?.parse::<u32>().ok())
+                .zip(metadata.get(CLIENT_TOKEN_KEY));
+            let (client_id, token) = {
+                let mut tokens = shared.tokens.lock().unwrap();
+                match claimed {
+                    Some((id, token))
+                        if tokens.get(&id).is_some_and(|known| {
+                            tokens_match(known.as_bytes(), token.as_bytes())
+                        }) =>
+                    {
+                        (id, tokens[&id].clone())
+                    }
+                    _ => {
+                        if let Some((id, _)) = claimed {
+                            log::warn!("gRPC client claimed id {id} with a wrong token, assigning a new one");
+                        }
+                        let id = shared.next_client_id.fetch_add(1, Ordering::Relaxed);
+                        let token = Uuid::new_v4().to_string();
+                        tokens.insert(id, token.clone());
+                        (id, token)
+                    }
+                }
+            };
+            log::info!("gRPC client {client_id} connected");
+
+            let mut inbound = request.into_inner();
+            let incoming = shared.incoming.clone();
+            tokio::spawn(async move {
+                loop {
+                    match inbound.message().await {
+                        Ok(Some(mut envelope)) => {
+                            // Clients can't send events on behalf of others
+                            envelope.client_id = client_id;
+                            if incoming.send(envelope).await.is_err() {
+                                return;
+                            }
+                        }
+                        Ok(None) => {
+                            log::info!("gRPC client {client_id} disconnected");
+                            return;
+                        }
+                        Err(status) => {
+                            log::info!("gRPC client {client_id} disconnected: {status}");
+                            return;
+                        }
+                    }
+                }
+            });
+
+            let mut response = Response::new(broadcast_stream(
+                shared.to_clients.subscribe(),
+                Some(client_id),
+            ));
+            response
+                .metadata_mut()
+                .insert(CLIENT_ID_KEY, client_id.into());
+            response.metadata_mut().insert(
+                CLIENT_TOKEN_KEY,
+                token
+                    .parse()
+                    .map_err(|_| Status::internal("Invalid client token"))?,
+            );
+            Ok(response)
+        })
+    }
+}
+
+/// Handles the `Subscribe` calls of orchestrators
+struct SubscribeHandler(Arc<BrokerShared>);
+
+impl ServerStreamingService<SubscribeRequest> for SubscribeHandler {
+    type Response = EventEnvelope;
+    type ResponseStream = BoxStream<EventEnvelope>;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    fn call(&mut self, _request: Request<SubscribeRequest>) -> Self::Future {
+        let rx = self.0.to_subscribers.subscribe();
+        Box::pin(async move { Ok(Response::new(broadcast_stream(rx, None))) })
+    }
+}
+
+/// The `libafl.events.EventBroker` gRPC service
+#[derive(Debug, Clone)]
+struct EventBrokerService(Arc<BrokerShared>);
+
+impl NamedService for EventBrokerService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl Service<http::Request<BoxBody>> for EventBrokerService {
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<BoxBody>) -> Self::Future {
+        let shared = self.0.clone();
+        match req.uri().path() {
+            CONNECT_PATH => Box::pin(async move {
+                Ok(Grpc::new(ProstCodec::default())
+                    .streaming(ConnectHandler(shared), req)
+                    .await)
+            }),
+            SUBSCRIBE_PATH => Box::pin(async move {
+                Ok(Grpc::new(ProstCodec::default())
+                    .server_streaming(SubscribeHandler(shared), req)
+                    .await)
+            }),
+            path => {
+                let status = Status::unimplemented(format!("Unknown method {path}"));
+                Box::pin(async move { Ok(status.into_http()) })
+            }
+        }
+    }
+}
+
+/// A gRPC broker, serving the `libafl.events.EventBroker` service described in `proto/events.proto`.
+///
+/// It displays the stats of all connected [`GrpcEventManager`]s in its [`Monitor`],
+/// forwards testcases between them, and streams all events to subscribers.
+#[derive(Debug)]
+pub struct GrpcEventBroker<I, MT>
+where
+    I: Input,
+    MT: Monitor,
+{
+    monitor: MT,
+    addr: SocketAddr,
+    /// Stops [`Self::broker_loop()`], see [`Self::shutdown_handle()`]
+    shutdown: Arc<Notify>,
+    phantom: PhantomData<I>,
+}
+
+/// Stops a running [`GrpcEventBroker::broker_loop()`], from another thread
+#[derive(Debug, Clone)]
+pub struct GrpcBrokerShutdown(Arc<Notify>);
+
+impl GrpcBrokerShutdown {
+    /// Makes the broker drop all connections and return from its loop
+    pub fn shutdown(&self) {
+        self.0.notify_one();
+    }
+}
+
+impl<I, MT> GrpcEventBroker<I, MT>
+where
+    I: Input,
+    MT: Monitor,
+{
+    /// Create a gRPC broker, listening on the given address
+    pub fn new(addr: SocketAddr, monitor: MT) -> Self {
+        Self {
+            monitor,
+            addr,
+            shutdown: Arc::new(Notify::new()),
+            phantom: PhantomData,
+        }
+    }
+
+    /// A handle to stop the [`Self::broker_loop()`] from another thread
+    #[must_use]
+    pub fn shutdown_handle(&self) -> GrpcBrokerShutdown {
+        GrpcBrokerShutdown(self.shutdown.clone())
+    }
+
+    /// Run the broker, until the gRPC server fails or gets shut down
+    // TODO: remove allow(clippy::needless_return) when clippy is fixed
+    #[tokio::main(flavor = "current_thread")]
+    #[allow(clippy::needless_return)]
+    pub async fn broker_loop(&mut self) -> Result<(), Error> {
+        let (incoming, mut rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let (to_clients, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let (to_subscribers, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let shared = Arc::new(BrokerShared {
+            next_client_id: AtomicU32::new(0),
+            tokens: Mutex::new(HashMap::new()),
+            incoming,
+            to_clients,
+            to_subscribers,
+        });
+
+        let mut server = tokio::spawn(
+            Server::builder()
+                .add_service(EventBrokerService(shared.clone()))
+                .serve(self.addr),
+        );
+        log::info!("gRPC broker listening on {}", self.addr);
+
+        loop {
+            tokio::select! {
+                res = &mut server => {
+                    return match res {
+                        Ok(Ok(())) => Err(Error::shutting_down()),
+                        Ok(Err(err)) => Err(Error::unknown(format!("The gRPC server failed: {err}"))),
+                        Err(err) => Err(Error::unknown(format!("The gRPC server panicked: {err}"))),
+                    };
+                }
+                () = self.shutdown.notified() => {
+                    // Also drops all connections, a graceful shutdown would wait for the clients to hang up
+                    server.abort();
+                    return Err(Error::shutting_down());
+                }
+                Some(envelope) = rx.recv() => {
+                    let client_id = ClientId(envelope.client_id);
+                    let event: Event<I> = match envelope.event() {
+                        Ok(event) => event,
+                        Err(err) => {
+                            log::warn!("Dropping invalid event from gRPC client {client_id:?}: {err}");
+                            continue;
+                        }
+                    };
+                    // Nobody may be subscribed, that's fine.
+                    let _ = shared.to_subscribers.send(envelope.clone());
+                    match Self::handle_in_broker(&mut self.monitor, client_id, &event)? {
+                        BrokerEventResult::Forward => {
+                            let _ = shared.to_clients.send(envelope);
+                        }
+                        BrokerEventResult::Handled => (),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Handle arriving events in the broker
+    #[allow(clippy::unnecessary_wraps)]
+    fn handle_in_broker(
+        monitor: &mut MT,
+        client_id: ClientId,
+        event: &Event<I>,
+    ) -> Result<BrokerEventResult, Error> {
+        match &event {
+            Event::NewTestcase {
+                corpus_size,
+                forward_id,
+                ..
+            } => {
+                let id = forward_id.unwrap_or(client_id);
+                monitor.client_stats_insert(id);
+                let client = monitor.client_stats_mut_for(id);
+                client.update_corpus_size(*corpus_size as u64);
+                monitor.display(event.name(), id);
+                Ok(BrokerEventResult::Forward)
+            }
+            Event::UpdateExecStats {
+                time,
+                executions,
+                phantom: _,
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_executions(*executions, *time);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::UpdateUserStats {
+                name,
+                value,
+                phantom: _,
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_user_stats(name.clone(), value.clone());
+                monitor.aggregate(name);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor {
+                time,
+                executions,
+                introspection_monitor,
+                phantom: _,
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_executions(*executions, *time);
+                client.update_introspection_monitor((**introspection_monitor).clone());
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::Objective { objective_size, .. } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_objective_size(*objective_size as u64);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::Log {
+                severity_level,
+                message,
+                phantom: _,
+            } => {
+                log::log!((*severity_level).into(), "{message}");
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::CustomBuf { .. } | Event::Stop | Event::SetStopPolicy { .. } => {
+                Ok(BrokerEventResult::Forward)
+            }
+        }
+    }
+}
+
+/// An [`EventManager`] that exchanges all events with other fuzzers through a [`GrpcEventBroker`].
+pub struct GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// We send message every `throttle` second
+    throttle: Option<Duration>,
+    /// When we sent the last message
+    last_sent: Duration,
+    hooks: EMH,
+    /// Our `ClientId`, assigned by the broker
+    client_id: ClientId,
+    /// The secret to keep our `ClientId` when reconnecting
+    client_token: String,
+    /// Events to stream to the broker
+    outgoing: mpsc::UnboundedSender<EventEnvelope>,
+    /// Events of other clients, received from the broker in a background thread
+    incoming: std_mpsc::Receiver<EventEnvelope>,
+    /// The custom buf handler
+    custom_buf_handlers: Vec<Box<CustomBufHandlerFn<S>>>,
+    /// The configuration defines this specific fuzzer.
+    /// A node will not re-use the observer values sent over gRPC
+    /// from nodes with other configurations.
+    configuration: EventConfig,
+    phantom: PhantomData<S>,
+}
+
+impl<S> GrpcEventManager<(), S>
+where
+    S: State,
+{
+    /// Create a builder for [`GrpcEventManager`]
+    #[must_use]
+    pub fn builder() -> GrpcEventManagerBuilder<(), S> {
+        GrpcEventManagerBuilder::new()
+    }
+}
+
+/// Builder for `GrpcEventManager`
+#[derive(Debug, Clone)]
+pub struct GrpcEventManagerBuilder<EMH, S> {
+    throttle: Option<Duration>,
+    hooks: EMH,
+    client_id: Option<(ClientId, String)>,
+    phantom: PhantomData<S>,
+}
+
+impl<S> Default for GrpcEventManagerBuilder<(), S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> GrpcEventManagerBuilder<(), S> {
+    /// Create a new `GrpcEventManagerBuilder`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            throttle: None,
+            hooks: (),
+            client_id: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Set the hooks
+    #[must_use]
+    pub fn hooks<EMH>(self, hooks: EMH) -> GrpcEventManagerBuilder<EMH, S> {
+        GrpcEventManagerBuilder {
+            throttle: self.throttle,
+            hooks,
+            client_id: self.client_id,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<EMH, S> GrpcEventManagerBuilder<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// Set the throttle
+    #[must_use]
+    pub fn throttle(mut self, throttle: Duration) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Reconnect as the client with the given id, for example after a restart.
+    /// The `token` is the one the broker handed out with the id, see [`GrpcEventManager::client_token`].
+    /// By default, or if the token is wrong, the broker assigns a new id.
+    #[must_use]
+    pub fn client_id(mut self, client_id: ClientId, token: String) -> Self {
+        self.client_id = Some((client_id, token));
+        self
+    }
+
+    /// Connects to the [`GrpcEventBroker`] at `broker_uri`, such as `http://127.0.0.1:1337`
+    pub fn build(
+        self,
+        broker_uri: &str,
+        configuration: EventConfig,
+    ) -> Result<GrpcEventManager<EMH, S>, Error> {
+        let endpoint = Endpoint::from_shared(broker_uri.to_string()).map_err(|err| {
+            Error::illegal_argument(format!("Invalid broker uri {broker_uri}: {err}"))
+        })?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (outgoing, outgoing_rx) = mpsc::unbounded_channel();
+
+        let (mut inbound, client_id, client_token) = runtime.block_on(async {
+            let channel = endpoint.connect().await.map_err(|err| {
+                Error::illegal_state(format!(
+                    "Could not connect to the gRPC broker at {broker_uri}: {err}"
+                ))
+            })?;
+            let mut grpc = tonic::client::Grpc::new(channel);
+            grpc.ready().await.map_err(|err| {
+                Error::illegal_state(format!("The gRPC broker is not ready: {err}"))
+            })?;
+
+            let mut request = Request::new(UnboundedReceiverStream::new(outgoing_rx));
+            if let Some((client_id, token)) = &self.client_id {
+                let token = token
+                    .parse()
+                    .map_err(|_| Error::illegal_argument("Invalid client token"))?;
+                let metadata = request.metadata_mut();
+                metadata.insert(CLIENT_ID_KEY, client_id.0.into());
+                metadata.insert(CLIENT_TOKEN_KEY, token);
+            }
+            let response = grpc
+                .streaming(
+                    request,
+                    http::uri::PathAndQuery::from_static(CONNECT_PATH),
+                    ProstCodec::default(),
+                )
+                .await
+                .map_err(|status| {
+                    Error::illegal_state(format!("The gRPC broker refused us: {status}"))
+                })?;
+            let metadata = response.metadata();
+            let client_id = metadata
+                .get(CLIENT_ID_KEY)
+                .and_then(|id| id.to_str().ok()?.parse().ok())
+                .ok_or_else(|| {
+                    Error::illegal_state("The gRPC broker did not assign a client id")
+                })?;
+            let client_token = metadata
+                .get(CLIENT_TOKEN_KEY)
+                .and_then(|token| token.to_str().ok())
+                .ok_or_else(|| {
+                    Error::illegal_state("The gRPC broker did not hand out a client token")
+                })?
+                .to_string();
+            Ok::<_, Error>((response.into_inner(), ClientId(client_id), client_token))
+        })?;
+        log::info!("Our client id: {client_id:?}");
+
+        // The background thread drives the connection, and hands events of other clients to `process`.
+        let (incoming_tx, incoming) = std_mpsc::channel();
+        thread::spawn(move || {
+            runtime.block_on(async move {
+                loop {
+                    match inbound.message().await {
+                        Ok(Some(envelope)) => {
+                            if incoming_tx.send(envelope).is_err() {
+                                // The manager is gone
+                                return;
+                            }
+                        }
+                        Ok(None) => return,
+                        Err(status) => {
+                            log::error!("Lost the connection to the gRPC broker: {status}");
+                            return;
+                        }
+                    }
+                }
+            });
+        });
+
+        Ok(GrpcEventManager {
+            throttle: self.throttle,
+            last_sent: Duration::from_secs(0),
+            hooks: self.hooks,
+            client_id,
+            client_token,
+            outgoing,
+            incoming,
+            custom_buf_handlers: vec![],
+            configuration,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<EMH, S> core::fmt::Debug for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GrpcEventManager")
+            .field("client_id", &self.client_id)
+            .field("throttle", &self.throttle)
+            .field("configuration", &self.configuration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<EMH, S> GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// The secret to pass to [`GrpcEventManagerBuilder::client_id`], to keep our client id when reconnecting
+    #[must_use]
+    pub fn client_token(&self) -> &str {
+        &self.client_token
+    }
+}
+
+impl<EMH, S> GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasImported,
+{
+    // Handle arriving events in the client
+    fn handle_in_client<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        client_id: ClientId,
+        event: Event<S::Input>,
+    ) -> Result<(), Error>
+    where
+        E: Executor<Self, Z, State = S> + HasObservers,
+        E::Observers: Serialize + ObserversTuple<S::Input, S>,
+        for<'a> E::Observers: Deserialize<'a>,
+        Z: ExecutionProcessor<Self, E::Observers, State = S>
+            + EvaluatorObservers<Self, E::Observers>,
+    {
+        if !self.hooks.pre_exec_all(state, client_id, &event)? {
+            return Ok(());
+        }
+        match event {
+            Event::NewTestcase {
+                input,
+                client_config,
+                exit_kind,
+                observers_buf,
+                forward_id,
+                ..
+            } => {
+                log::info!("Received new Testcase from {client_id:?} ({client_config:?}, forward {forward_id:?})");
+
+                let res = match observers_buf {
+                    Some(observers_buf) if client_config.match_with(&self.configuration) => {
+                        let observers: E::Observers = postcard::from_bytes(&observers_buf)?;
+                        #[cfg(feature = "scalability_introspection")]
+                        {
+                            state.scalability_monitor_mut().testcase_with_observers += 1;
+                        }
+                        fuzzer
+                            .evaluate_execution(state, self, input, &observers, &exit_kind, false)?
+                    }
+                    _ => {
+                        #[cfg(feature = "scalability_introspection")]
+                        {
+                            state.scalability_monitor_mut().testcase_without_observers += 1;
+                        }
+                        fuzzer.evaluate_input_with_observers::<E>(
+                            state, executor, self, input, false,
+                        )?
+                    }
+                };
+                if let Some(item) = res.1 {
+                    *state.imported_mut() += 1;
+                    log::info!("Added received Testcase as item #{item}");
+                }
+            }
+            Event::CustomBuf { tag, buf } => {
+                for handler in &mut self.custom_buf_handlers {
+                    if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
+                        break;
+                    }
+                }
+            }
+            Event::Stop => {
+                state.request_stop();
+            }
+            Event::SetStopPolicy { policy } => {
+                state.add_metadata(policy);
+            }
+            _ => {
+                return Err(Error::unknown(format!(
+                    "Received illegal message that message should not have arrived: {:?}.",
+                    event.name()
+                )))
+            }
+        }
+        self.hooks.post_exec_all(state, client_id)?;
+        Ok(())
+    }
+}
+
+impl<EMH, S> UsesState for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    type State = S;
+}
+
+impl<EMH, S> EventFirer for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn should_send(&self) -> bool {
+        if let Some(throttle) = self.throttle {
+            current_time().saturating_sub(self.last_sent) > throttle
+        } else {
+            true
+        }
+    }
+
+    fn fire(
+        &mut self,
+        _state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        let envelope = EventEnvelope::new(self.client_id, &event)?;
+        self.outgoing
+            .send(envelope)
+            .map_err(|_| Error::illegal_state("Lost the connection to the gRPC broker"))?;
+        self.last_sent = current_time();
+        Ok(())
+    }
+
+    fn configuration(&self) -> EventConfig {
+        self.configuration
+    }
+}
+
+impl<EMH, S> EventRestarter for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+}
+
+impl<E, EMH, S, Z> EventProcessor<E, Z> for GrpcEventManager<EMH, S>
+where
+    E: HasObservers + Executor<Self, Z, State = S>,
+    E::Observers: Serialize + ObserversTuple<S::Input, S>,
+    for<'a> E::Observers: Deserialize<'a>,
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasImported,
+    Z: EvaluatorObservers<Self, E::Observers, State = S>
+        + ExecutionProcessor<Self, E::Observers, State = S>,
+{
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+        loop {
+            let envelope = match self.incoming.try_recv() {
+                Ok(envelope) => envelope,
+                Err(std_mpsc::TryRecvError::Empty) => break,
+                Err(std_mpsc::TryRecvError::Disconnected) => {
+                    return Err(Error::illegal_state(
+                        "Lost the connection to the gRPC broker",
+                    ))
+                }
+            };
+            let event = envelope.event()?;
+            self.handle_in_client(fuzzer, executor, state, ClientId(envelope.client_id), event)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn on_shutdown(&mut self) -> Result<(), Error> {
+        self.send_exiting()
+    }
+}
+
+impl<E, EMH, S, Z> EventManager<E, Z> for GrpcEventManager<EMH, S>
+where
+    E: HasObservers + Executor<Self, Z, State = S>,
+    E::Observers: Serialize + ObserversTuple<S::Input, S>,
+    for<'a> E::Observers: Deserialize<'a>,
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime + HasImported,
+    Z: EvaluatorObservers<Self, E::Observers, State = S>
+        + ExecutionProcessor<Self, E::Observers, State = S>,
+{
+}
+
+impl<EMH, S> HasCustomBufHandlers for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<dyn FnMut(&mut S, &str, &[u8]) -> Result<CustomBufEventResult, Error>>,
+    ) {
+        self.custom_buf_handlers.push(handler);
+    }
+}
+
+impl<EMH, S> ProgressReporter for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime,
+{
+}
+
+impl<EMH, S> HasEventManagerId for GrpcEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn mgr_id(&self) -> EventManagerId {
+        EventManagerId(self.client_id.0 as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, string::ToString};
+    use core::marker::PhantomData;
+
+    use core::time::Duration;
+    use std::{net::TcpListener, thread};
+
+    use libafl_bolts::ClientId;
+    use prost::Message;
+
+    use super::{
+        proto::{EventEnvelope, Summary},
+        GrpcEventBroker, GrpcEventManager, GrpcEventManagerBuilder,
+    };
+    use crate::{
+        events::{Event, EventConfig, EventFirer},
+        inputs::BytesInput,
+        monitors::{AggregatorOps, NopMonitor, UserStats, UserStatsValue},
+        state::NopState,
+        Error,
+    };
+
+    type TestManager = GrpcEventManager<(), NopState<BytesInput>>;
+
+    /// Connects to the broker at `uri`, waiting for it to come up
+    fn connect<F>(uri: &str, builder: F) -> TestManager
+    where
+        F: Fn() -> GrpcEventManagerBuilder<(), NopState<BytesInput>>,
+    {
+        for _ in 0..100 {
+            if let Ok(mgr) = builder().build(uri, EventConfig::AlwaysUnique) {
+                return mgr;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("Could not connect to the gRPC broker at {uri}");
+    }
+
+    #[test]
+    fn test_envelope() {
+        let event = Event::<BytesInput>::UpdateUserStats {
+            name: Cow::Borrowed("edges"),
+            value: UserStats::new(UserStatsValue::Ratio(2, 4), AggregatorOps::Avg),
+            phantom: PhantomData,
+        };
+        let envelope = EventEnvelope::new(ClientId(3), &event).unwrap();
+        let decoded = EventEnvelope::decode(envelope.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.client_id, 3);
+        assert_eq!(decoded.name, "UserStats");
+        assert!(
+            matches!(&decoded.summary, Some(Summary::UserStats(stats)) if stats.name == "edges" && stats.value == "2/4 (50%)")
+        );
+        assert!(matches!(
+            decoded.event::<BytesInput>().unwrap(),
+            Event::UpdateUserStats { name, .. } if name == "edges"
+        ));
+    }
+
+    #[test]
+    fn test_broker_round_trip() {
+        let addr = TcpListener::bind(("127.0.0.1", 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let mut broker = GrpcEventBroker::<BytesInput, _>::new(addr, NopMonitor::new());
+        let shutdown = broker.shutdown_handle();
+        let broker = thread::spawn(move || broker.broker_loop());
+        let uri = format!("http://{addr}");
+
+        let mut sender = connect(&uri, GrpcEventManager::builder);
+        let receiver = connect(&uri, GrpcEventManager::builder);
+        assert_ne!(sender.client_id, receiver.client_id);
+
+        let mut state = NopState::new();
+        sender
+            .fire(
+                &mut state,
+                Event::CustomBuf {
+                    tag: "test".into(),
+                    buf: vec![1, 2, 3],
+                },
+            )
+            .unwrap();
+        let envelope = receiver
+            .incoming
+            .recv_timeout(Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(ClientId(envelope.client_id), sender.client_id);
+        assert!(matches!(
+            envelope.event::<BytesInput>().unwrap(),
+            Event::CustomBuf { tag, buf } if tag == "test" && buf == [1, 2, 3]
+        ));
+
+        // Only the broker assigns ids, a client keeps its id only with the right token
+        let resumed = connect(&uri, || {
+            GrpcEventManager::builder()
+                .client_id(sender.client_id, sender.client_token().to_string())
+        });
+        assert_eq!(resumed.client_id, sender.client_id);
+        let impostor = connect(&uri, || {
+            GrpcEventManager::builder().client_id(sender.client_id, "guessed".to_string())
+        });
+        assert_ne!(impostor.client_id, sender.client_id);
+        assert_ne!(impostor.client_id, receiver.client_id);
+
+        shutdown.shutdown();
+        assert!(matches!(broker.join().unwrap(), Err(Error::ShuttingDown)));
+    }
+}
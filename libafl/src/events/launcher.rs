@@ -11,9 +11,26 @@
 //!
 //! On `Unix` systems, the [`Launcher`] will use `fork` if the `fork` feature is used for `LibAFL`.
 //! Else, it will start subsequent nodes with the same commandline, and will set special `env` variables accordingly.
+//!
+//! To run different clients on different cores, for example `cmplog` clients on some cores and plain havoc on the others,
+//! give the [`Launcher`] a [`ClientRole`] for each kind of client. Each role has its own `run_client` closure,
+//! which builds its own stages, and its own `env` variables.
+//!
+//! With a [`RespawnPolicy`], the [`Launcher`] supervises the clients (this requires the `fork` feature on `Unix`):
+//! it runs the broker in a child process, respawns the clients that die, backing off exponentially
+//! if a client keeps dying, and reports the deaths to the broker. A respawned client continues from the
+//! state its predecessor saved last, on a map owned by the launcher, with a new connection to the broker.
+//! If there is no saved state, its `run_client` closure gets none, and loads the corpus again,
+//! for example from the on-disk corpus or from a snapshot of the state.
+//!
+//! On `Unix`, the [`Launcher`] also starts clients on other hosts, over SSH, with the broker: see [`RemoteNode`].
 
 use alloc::string::ToString;
 #[cfg(feature = "std")]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+#[cfg(all(unix, feature = "std", feature = "fork"))]
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
 use core::time::Duration;
 use core::{
     fmt::{self, Debug, Formatter},
@@ -28,20 +45,33 @@ use std::process::Stdio;
 #[cfg(all(unix, feature = "std"))]
 use std::{fs::File, os::unix::io::AsRawFd};
 
+#[cfg(all(unix, feature = "std"))]
+use super::remote_nodes::{remote_broker_addr_from_env, RemoteNode, RemoteNodeProcess};
+#[cfg(all(feature = "std", feature = "llmp_compression"))]
+use libafl_bolts::compress::CompressionCodec;
+#[cfg(feature = "std")]
+use libafl_bolts::core_affinity::NumaTopology;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::llmp::Broker;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::llmp::Brokers;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::llmp::LlmpBroker;
+#[cfg(all(unix, feature = "std", feature = "fork"))]
+use libafl_bolts::llmp::LlmpClient;
 #[cfg(all(unix, feature = "std"))]
 use libafl_bolts::os::dup2;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
 use libafl_bolts::os::startable_self;
+#[cfg(feature = "std")]
+use libafl_bolts::Named;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use libafl_bolts::{
     core_affinity::get_core_ids,
+    current_time,
     os::{fork, ForkResult},
+    shmem::ShMemDescription,
+    staterestore::StateRestorer,
 };
 use libafl_bolts::{
     core_affinity::{CoreId, Cores},
@@ -54,14 +84,20 @@ use typed_builder::TypedBuilder;
 use super::EventManagerHooksTuple;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use super::StdLlmpEventHook;
+#[cfg(all(unix, feature = "std", feature = "fork", feature = "llmp_compression"))]
+use crate::events::llmp::COMPRESS_THRESHOLD;
 #[cfg(all(unix, feature = "std", feature = "fork", feature = "multi_machine"))]
 use crate::events::multi_machine::NodeDescriptor;
 #[cfg(all(unix, feature = "std", feature = "fork", feature = "multi_machine"))]
 use crate::events::multi_machine::TcpMultiMachineHooks;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
-use crate::events::{centralized::CentralizedEventManager, CentralizedLlmpHook};
+use crate::events::{
+    centralized::{CentralizedEventManager, CentralizedPolicy},
+    llmp::LLMP_TAG_EVENT_TO_BOTH,
+    CentralizedLlmpHook, Event, HasCustomBufHandlers, LogSeverity,
+};
 #[cfg(all(unix, feature = "std", feature = "fork"))]
-use crate::inputs::UsesInput;
+use crate::inputs::{Input, UsesInput};
 use crate::observers::TimeObserver;
 #[cfg(all(unix, feature = "std", feature = "fork"))]
 use crate::state::UsesState;
@@ -83,6 +119,127 @@ const _AFL_LAUNCHER_CLIENT: &str = "AFL_LAUNCHER_CLIENT";
 #[cfg(all(feature = "fork", unix))]
 const LIBAFL_DEBUG_OUTPUT: &str = "LIBAFL_DEBUG_OUTPUT";
 
+/// The `env` variable holding the name of the [`ClientRole`] of a client
+pub const LIBAFL_CLIENT_ROLE: &str = "LIBAFL_CLIENT_ROLE";
+
+/// How often the [`Launcher`] checks on its clients, when it supervises them
+#[cfg(all(unix, feature = "std", feature = "fork"))]
+#[allow(unknown_lints, clippy::duration_suboptimal_units)] // `from_millis` reads better than a fraction of a second
+const SUPERVISION_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the map a supervised client saves its state to, as the one of the [`RestartingMgr`]
+#[cfg(all(unix, feature = "std", feature = "fork"))]
+const STATE_MAP_SIZE: usize = 256 * 1024 * 1024;
+
+/// How the [`Launcher`] respawns the clients that die, see [`Launcher::launch_with_hooks`]
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RespawnPolicy {
+    /// The count of respawns of a client, after which it is given up
+    pub max_restarts: u32,
+    /// The delay before respawning a client that died, doubled each time it dies again soon after
+    pub initial_backoff: Duration,
+    /// The maximum delay before respawning a client
+    pub max_backoff: Duration,
+    /// A client that ran for this long before dying is respawned after the initial backoff again
+    pub reset_after: Duration,
+}
+
+#[cfg(feature = "std")]
+impl Default for RespawnPolicy {
+    #[allow(unknown_lints, clippy::duration_suboptimal_units)] // `from_mins` is too recent for our MSRV
+    fn default() -> Self {
+        Self {
+            max_restarts: 10,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            reset_after: Duration::from_secs(300),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl RespawnPolicy {
+    /// The delay before respawning a client that died right after `consecutive_restarts` respawns
+    #[must_use]
+    pub fn backoff(&self, consecutive_restarts: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(1_u32.checked_shl(consecutive_restarts).unwrap_or(u32::MAX))
+            .min(self.max_backoff)
+    }
+}
+
+/// A client forked by a supervising [`Launcher`]
+#[cfg(all(unix, feature = "std", feature = "fork"))]
+#[derive(Debug)]
+struct SupervisedClient {
+    pid: libc::pid_t,
+    /// The index of the core of the client
+    id: usize,
+    bind_to: CoreId,
+    started: Duration,
+    restarts: u32,
+    consecutive_restarts: u32,
+    /// The map the client saves its state to, owned by the launcher
+    state_map: ShMemDescription,
+}
+
+/// A kind of clients of a [`Launcher`], run on some of its cores by their own `run_client` closure.
+///
+/// As the closures of all roles have the same type, box them if they differ.
+#[cfg(feature = "std")]
+#[allow(missing_debug_implementations)]
+pub struct ClientRole<CF> {
+    name: Cow<'static, str>,
+    cores: Cores,
+    env: Vec<(String, String)>,
+    run_client: Option<CF>,
+}
+
+#[cfg(feature = "std")]
+impl<CF> Named for ClientRole<CF> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+#[cfg(feature = "std")]
+impl<CF> ClientRole<CF> {
+    /// Creates a new [`ClientRole`] running `run_client` on the given `cores`
+    pub fn new<N>(name: N, cores: Cores, run_client: CF) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        Self {
+            name: name.into(),
+            cores,
+            env: Vec::new(),
+            run_client: Some(run_client),
+        }
+    }
+
+    /// Sets the `env` variable `key` to `value` in the clients of this role
+    #[must_use]
+    pub fn env<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// The cores of this role
+    pub fn cores(&self) -> &Cores {
+        &self.cores
+    }
+
+    /// The `env` variables of the clients of this role
+    pub fn env_vars(&self) -> &[(String, String)] {
+        &self.env
+    }
+}
+
 /// Provides a [`Launcher`], which can be used to launch a fuzzing run on a specified list of cores
 ///
 /// Will hide child output, unless the settings indicate otherwise, or the `LIBAFL_DEBUG_OUTPUT` env variable is set.
@@ -103,6 +260,10 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// The 'main' function to run for each client forked. This probably shouldn't return
     #[builder(default, setter(strip_option))]
     run_client: Option<CF>,
+    /// The roles of the clients, each running its own closure on some of the [`Self::cores`].
+    /// The cores without a role run [`Self::run_client`].
+    #[builder(default = Vec::new())]
+    roles: Vec<ClientRole<CF>>,
     /// The broker port to use (or to attach to, in case [`Self::spawn_broker`] is `false`)
     #[builder(default = 1337_u16)]
     broker_port: u16,
@@ -147,6 +308,24 @@ pub struct Launcher<'a, CF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The codec to compress the events of the clients with
+    #[cfg(feature = "llmp_compression")]
+    #[builder(default)]
+    compression: CompressionCodec,
+    /// If each client should allocate its memory, including its LLMP and coverage shared maps,
+    /// on the NUMA node of its core
+    #[builder(default = false)]
+    numa_aware: bool,
+    /// The NUMA topology to use instead of the detected one, if [`Self::numa_aware`]
+    #[builder(default = None)]
+    numa_topology: Option<NumaTopology>,
+    /// Supervise the clients, respawning the ones that die (only with the `fork` feature on `Unix`)
+    #[builder(default = None)]
+    respawn: Option<RespawnPolicy>,
+    /// The hosts to start clients on, over SSH, along with the broker
+    #[cfg(unix)]
+    #[builder(default = Vec::new())]
+    remote_nodes: Vec<RemoteNode>,
 }
 
 impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
@@ -156,13 +335,25 @@ impl<CF, MT, SP> Debug for Launcher<'_, CF, MT, SP> {
             .field("configuration", &self.configuration)
             .field("broker_port", &self.broker_port)
             .field("core", &self.cores)
+            .field(
+                "roles",
+                &self
+                    .roles
+                    .iter()
+                    .map(|role| (role.name(), role.cores()))
+                    .collect::<Vec<_>>(),
+            )
             .field("spawn_broker", &self.spawn_broker)
-            .field("remote_broker_addr", &self.remote_broker_addr);
+            .field("remote_broker_addr", &self.remote_broker_addr)
+            .field("numa_aware", &self.numa_aware)
+            .field("numa_topology", &self.numa_topology)
+            .field("respawn", &self.respawn);
         #[cfg(all(unix, feature = "std"))]
         {
             dbg_struct
                 .field("stdout_file", &self.stdout_file)
-                .field("stderr_file", &self.stderr_file);
+                .field("stderr_file", &self.stderr_file)
+                .field("remote_nodes", &self.remote_nodes);
         }
 
         dbg_struct.finish_non_exhaustive()
@@ -189,6 +380,138 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<CF, MT, SP> Launcher<'_, CF, MT, SP> {
+    /// The NUMA topology to place the clients on, if [`Self::numa_aware`]
+    fn numa_topology(&self) -> Result<Option<NumaTopology>, Error> {
+        if !self.numa_aware {
+            return Ok(None);
+        }
+        let topology = match &self.numa_topology {
+            Some(topology) => topology.clone(),
+            None => NumaTopology::detect()?,
+        };
+        log::info!("NUMA topology: {topology:?}");
+        Ok(Some(topology))
+    }
+
+    /// Connects the broker to the one given by a [`RemoteNode`] starting this launcher, if any
+    #[cfg(unix)]
+    fn remote_broker_addr_from_env(&mut self) -> Result<(), Error> {
+        if self.remote_broker_addr.is_none() {
+            self.remote_broker_addr = remote_broker_addr_from_env()?;
+        }
+        Ok(())
+    }
+
+    /// Starts the [`RemoteNode`]s, connected to the broker of this launcher
+    #[cfg(unix)]
+    fn spawn_remote_nodes(&self) -> Result<Vec<RemoteNodeProcess>, Error> {
+        let mut processes = Vec::with_capacity(self.remote_nodes.len());
+        for node in &self.remote_nodes {
+            match node.spawn(self.broker_port) {
+                Ok(process) => processes.push(process),
+                Err(err) => {
+                    stop_remote_nodes(processes);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(processes)
+    }
+
+    /// The role of the clients on `core_id`, if any
+    fn role_of(&self, core_id: CoreId) -> Option<&ClientRole<CF>> {
+        self.roles.iter().find(|role| role.cores.contains(core_id))
+    }
+
+    /// Checks that the roles run on the cores of the launcher, and that each core has a closure to run
+    fn check_roles(&self) -> Result<(), Error> {
+        for role in &self.roles {
+            if let Some(core_id) = role.cores.ids.iter().find(|id| !self.cores.contains(**id)) {
+                return Err(Error::illegal_argument(format!(
+                    "Role {} runs on core {}, which is not one of the cores of the launcher",
+                    role.name, core_id.0
+                )));
+            }
+        }
+        if self.run_client.is_none() {
+            if let Some(core_id) = self
+                .cores
+                .ids
+                .iter()
+                .find(|id| self.role_of(**id).is_none())
+            {
+                return Err(Error::illegal_argument(format!(
+                    "No client callback provided for core {}, and it has no role",
+                    core_id.0
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets the `env` variables of the role of `core_id` in this process
+    #[cfg(all(unix, feature = "fork"))]
+    fn set_role_env(&self, core_id: CoreId) {
+        if let Some(role) = self.role_of(core_id) {
+            std::env::set_var(LIBAFL_CLIENT_ROLE, role.name.as_ref());
+            for (key, value) in &role.env {
+                std::env::set_var(key, value);
+            }
+        }
+    }
+
+    /// Takes the closure to run on `core_id`: the one of its role, else [`Self::run_client`]
+    fn take_run_client(&mut self, core_id: CoreId) -> Result<CF, Error> {
+        let run_client = match self
+            .roles
+            .iter_mut()
+            .find(|role| role.cores.contains(core_id))
+        {
+            Some(role) => role.run_client.take(),
+            None => self.run_client.take(),
+        };
+        run_client.ok_or_else(|| {
+            Error::illegal_state(format!("No client callback left for core {}", core_id.0))
+        })
+    }
+}
+
+/// Stops the clients started on other hosts
+#[cfg(all(unix, feature = "std"))]
+fn stop_remote_nodes(processes: Vec<RemoteNodeProcess>) {
+    for mut process in processes {
+        if let Err(err) = process.kill() {
+            log::warn!("Failed to stop the clients on {}: {err}", process.host());
+        }
+    }
+}
+
+/// Makes the client bound to `core_id` allocate its memory on the NUMA node of this core.
+///
+/// The client then creates its shared memory, and the pages end up on the same node.
+#[cfg(feature = "std")]
+fn bind_memory_to_core_node(topology: Option<&NumaTopology>, core_id: CoreId) -> Result<(), Error> {
+    let Some(topology) = topology else {
+        return Ok(());
+    };
+    if let Some(node) = topology.node_of(core_id) {
+        log::info!(
+            "Client on core {} uses the memory of NUMA node {}",
+            core_id.0,
+            node.id
+        );
+        node.set_memory_policy()
+    } else {
+        log::warn!(
+            "Core {} is on no NUMA node, not binding the memory of its client",
+            core_id.0
+        );
+        Ok(())
+    }
+}
+
 #[cfg(feature = "std")]
 impl<CF, MT, SP> Launcher<'_, CF, MT, SP>
 where
@@ -210,13 +533,14 @@ where
             ));
         }
 
-        if self.run_client.is_none() {
-            return Err(Error::illegal_argument(
-                "No client callback provided".to_string(),
-            ));
+        self.check_roles()?;
+        self.remote_broker_addr_from_env()?;
+        if !self.spawn_broker && !self.remote_nodes.is_empty() {
+            log::warn!("The remote nodes are started with the broker, not starting them (spawn_broker is false)");
         }
 
         let core_ids = get_core_ids()?;
+        let numa_topology = self.numa_topology()?;
         let mut handles = vec![];
 
         log::info!("spawning on cores: {:?}", self.cores);
@@ -232,76 +556,73 @@ where
 
         // Spawn clients
         let mut index = 0_u64;
+        let mut clients = vec![];
+        // Supervised clients save their states to maps kept alive here, for the respawned clients.
+        let mut state_maps = vec![];
         for (id, bind_to) in core_ids.iter().enumerate() {
             if self.cores.ids.iter().any(|&x| x == id.into()) {
                 for _ in 0..self.overcommit {
                     index += 1;
-                    self.shmem_provider.pre_fork()?;
-                    // # Safety
-                    // Fork is safe in general, apart from potential side effects to the OS and other threads
-                    match unsafe { fork() }? {
-                        ForkResult::Parent(child) => {
-                            self.shmem_provider.post_fork(false)?;
-                            handles.push(child.pid);
-                            log::info!("child spawned and bound to core {id}");
-                        }
-                        ForkResult::Child => {
-                            // # Safety
-                            // A call to `getpid` is safe.
-                            log::info!("{:?} PostFork", unsafe { libc::getpid() });
-                            self.shmem_provider.post_fork(true)?;
-
-                            std::thread::sleep(Duration::from_millis(index * self.launch_delay));
-
-                            if !debug_output {
-                                if let Some(file) = &self.opened_stdout_file {
-                                    dup2(file.as_raw_fd(), libc::STDOUT_FILENO)?;
-                                    if let Some(stderr) = &self.opened_stderr_file {
-                                        dup2(stderr.as_raw_fd(), libc::STDERR_FILENO)?;
-                                    } else {
-                                        dup2(file.as_raw_fd(), libc::STDERR_FILENO)?;
-                                    }
-                                }
-                            }
-
-                            // Fuzzer client. keeps retrying the connection to broker till the broker starts
-                            let builder = RestartingMgr::<EMH, MT, S, SP>::builder()
-                                .shmem_provider(self.shmem_provider.clone())
-                                .broker_port(self.broker_port)
-                                .kind(ManagerKind::Client {
-                                    cpu_core: Some(*bind_to),
-                                })
-                                .configuration(self.configuration)
-                                .serialize_state(self.serialize_state)
-                                .hooks(hooks);
-                            let builder = builder.time_ref(self.time_ref.clone());
-                            let (state, mgr) = builder.build().launch()?;
-
-                            return (self.run_client.take().unwrap())(state, mgr, *bind_to);
-                        }
+                    let launch_delay = Duration::from_millis(index * self.launch_delay);
+                    let state_map = if self.respawn.is_some() {
+                        let state_map = StateRestorer::<SP>::new(
+                            self.shmem_provider.new_shmem(STATE_MAP_SIZE)?,
+                        );
+                        let description = state_map.description();
+                        state_maps.push(state_map);
+                        Some(description)
+                    } else {
+                        None
                     };
+                    let Some(pid) = self.fork_client::<EMH, S>(
+                        hooks,
+                        id,
+                        *bind_to,
+                        numa_topology.as_ref(),
+                        launch_delay,
+                        debug_output,
+                        state_map,
+                    )?
+                    else {
+                        // We were the client, and are done fuzzing
+                        return Ok(());
+                    };
+                    handles.push(pid);
+                    if let Some(state_map) = state_map {
+                        clients.push(SupervisedClient {
+                            pid,
+                            id,
+                            bind_to: *bind_to,
+                            started: current_time(),
+                            restarts: 0,
+                            consecutive_restarts: 0,
+                            state_map,
+                        });
+                    }
                 }
             }
         }
 
+        if let Some(policy) = self.respawn {
+            let ret = self.supervise::<EMH, S>(
+                hooks,
+                &policy,
+                clients,
+                numa_topology.as_ref(),
+                debug_output,
+            );
+            drop(state_maps);
+            return ret;
+        }
+
         if self.spawn_broker {
             log::info!("I am broker!!.");
 
             // TODO we don't want always a broker here, think about using different laucher process to spawn different configurations
-            let builder = RestartingMgr::<EMH, MT, S, SP>::builder()
-                .shmem_provider(self.shmem_provider.clone())
-                .monitor(Some(self.monitor.clone()))
-                .broker_port(self.broker_port)
-                .kind(ManagerKind::Broker)
-                .remote_broker_addr(self.remote_broker_addr)
-                .exit_cleanly_after(Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()))
-                .configuration(self.configuration)
-                .serialize_state(self.serialize_state)
-                .hooks(hooks);
-
-            let builder = builder.time_ref(self.time_ref.clone());
-
-            builder.build().launch()?;
+            self.launch_broker::<EMH, S>(
+                hooks,
+                Some(NonZeroUsize::try_from(self.cores.ids.len()).unwrap()),
+            )?;
 
             // Broker exited. kill all clients.
             for handle in &handles {
@@ -327,6 +648,288 @@ where
         Ok(())
     }
 
+    /// Forks a client on the core `id`, returning its pid in the parent, and `None` in the client when it is done.
+    ///
+    /// A supervised client saves its state to the given `state_map`, and continues from the state found there.
+    #[cfg(all(unix, feature = "fork"))]
+    #[allow(clippy::too_many_arguments)]
+    fn fork_client<EMH, S>(
+        &mut self,
+        hooks: EMH,
+        id: usize,
+        bind_to: CoreId,
+        numa_topology: Option<&NumaTopology>,
+        launch_delay: Duration,
+        debug_output: bool,
+        state_map: Option<ShMemDescription>,
+    ) -> Result<Option<libc::pid_t>, Error>
+    where
+        S: State + HasExecutions,
+        EMH: EventManagerHooksTuple<S> + Clone + Copy,
+        CF: FnOnce(Option<S>, LlmpRestartingEventManager<EMH, S, SP>, CoreId) -> Result<(), Error>,
+    {
+        self.shmem_provider.pre_fork()?;
+        // # Safety
+        // Fork is safe in general, apart from potential side effects to the OS and other threads
+        match unsafe { fork() }? {
+            ForkResult::Parent(child) => {
+                self.shmem_provider.post_fork(false)?;
+                log::info!("child spawned and bound to core {id}");
+                Ok(Some(child.pid))
+            }
+            ForkResult::Child => {
+                // # Safety
+                // A call to `getpid` is safe.
+                log::info!("{:?} PostFork", unsafe { libc::getpid() });
+                self.shmem_provider.post_fork(true)?;
+                bind_memory_to_core_node(numa_topology, bind_to)?;
+                self.set_role_env(id.into());
+
+                std::thread::sleep(launch_delay);
+
+                if !debug_output {
+                    if let Some(file) = &self.opened_stdout_file {
+                        dup2(file.as_raw_fd(), libc::STDOUT_FILENO)?;
+                        if let Some(stderr) = &self.opened_stderr_file {
+                            dup2(stderr.as_raw_fd(), libc::STDERR_FILENO)?;
+                        } else {
+                            dup2(file.as_raw_fd(), libc::STDERR_FILENO)?;
+                        }
+                    }
+                }
+
+                // Fuzzer client. keeps retrying the connection to broker till the broker starts
+                let builder = RestartingMgr::<EMH, MT, S, SP>::builder()
+                    .shmem_provider(self.shmem_provider.clone())
+                    .broker_port(self.broker_port)
+                    .kind(ManagerKind::Client {
+                        cpu_core: Some(bind_to),
+                    })
+                    .configuration(self.configuration)
+                    .serialize_state(self.serialize_state)
+                    .hooks(hooks)
+                    .state_map(state_map);
+                #[cfg(feature = "llmp_compression")]
+                let builder = builder.compression(self.compression);
+                let builder = builder.time_ref(self.time_ref.clone());
+                let (state, mgr) = builder.build().launch()?;
+
+                (self.take_run_client(id.into())?)(state, mgr, bind_to)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs the broker in this process, until it exits
+    #[cfg(all(unix, feature = "fork"))]
+    fn launch_broker<EMH, S>(
+        &mut self,
+        hooks: EMH,
+        exit_cleanly_after: Option<NonZeroUsize>,
+    ) -> Result<(), Error>
+    where
+        S: State + HasExecutions,
+        EMH: EventManagerHooksTuple<S> + Clone + Copy,
+    {
+        let builder = RestartingMgr::<EMH, MT, S, SP>::builder()
+            .shmem_provider(self.shmem_provider.clone())
+            .monitor(Some(self.monitor.clone()))
+            .broker_port(self.broker_port)
+            .kind(ManagerKind::Broker)
+            .remote_broker_addr(self.remote_broker_addr)
+            .exit_cleanly_after(exit_cleanly_after)
+            .configuration(self.configuration)
+            .serialize_state(self.serialize_state)
+            .hooks(hooks);
+        #[cfg(feature = "llmp_compression")]
+        let builder = builder.compression(self.compression);
+
+        let builder = builder.time_ref(self.time_ref.clone());
+
+        let remote_nodes = self.spawn_remote_nodes()?;
+        let res = builder.build().launch();
+        stop_remote_nodes(remote_nodes);
+        res?;
+        Ok(())
+    }
+
+    /// Runs the broker in a child process, and respawns the clients that die, following the `policy`.
+    ///
+    /// Returns when the broker exits, or when all clients are done or given up.
+    #[cfg(all(unix, feature = "fork"))]
+    #[allow(clippy::too_many_lines)]
+    fn supervise<EMH, S>(
+        &mut self,
+        hooks: EMH,
+        policy: &RespawnPolicy,
+        mut clients: Vec<SupervisedClient>,
+        numa_topology: Option<&NumaTopology>,
+        debug_output: bool,
+    ) -> Result<(), Error>
+    where
+        S: State + HasExecutions,
+        EMH: EventManagerHooksTuple<S> + Clone + Copy,
+        CF: FnOnce(Option<S>, LlmpRestartingEventManager<EMH, S, SP>, CoreId) -> Result<(), Error>,
+    {
+        let broker = if self.spawn_broker {
+            log::info!("Spawning the broker");
+            self.shmem_provider.pre_fork()?;
+            // # Safety
+            // Fork is safe in general, apart from potential side effects to the OS and other threads
+            match unsafe { fork() }? {
+                ForkResult::Parent(child) => {
+                    self.shmem_provider.post_fork(false)?;
+                    Some(child.pid)
+                }
+                ForkResult::Child => {
+                    self.shmem_provider.post_fork(true)?;
+                    // The supervisor decides when the campaign is over, as respawned clients connect again
+                    return self.launch_broker::<EMH, S>(hooks, None);
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut reporter = None;
+        let mut pending: Vec<(Duration, SupervisedClient)> = vec![];
+        loop {
+            let mut status = 0;
+            // # Safety
+            // Normal libc call, `status` is a valid pointer
+            let pid = unsafe { libc::waitpid(-1, &raw mut status, libc::WNOHANG) };
+            if pid > 0 {
+                if Some(pid) == broker {
+                    log::info!("The broker exited, stopping the clients");
+                    for client in &clients {
+                        // # Safety
+                        // Normal libc call, no dereferences whatsoever
+                        unsafe {
+                            libc::kill(client.pid, libc::SIGINT);
+                        }
+                    }
+                    return Ok(());
+                }
+                let Some(pos) = clients.iter().position(|client| client.pid == pid) else {
+                    continue;
+                };
+                let mut client = clients.swap_remove(pos);
+                if libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0 {
+                    log::info!("Client on core {} is done", client.id);
+                    continue;
+                }
+
+                let now = current_time();
+                if now.saturating_sub(client.started) >= policy.reset_after {
+                    client.consecutive_restarts = 0;
+                }
+                let cause = if libc::WIFSIGNALED(status) {
+                    format!("was killed by signal {}", libc::WTERMSIG(status))
+                } else {
+                    format!("exited with status {}", libc::WEXITSTATUS(status))
+                };
+                if client.restarts >= policy.max_restarts {
+                    self.report_to_broker::<S::Input>(
+                        &mut reporter,
+                        LogSeverity::Error,
+                        format!(
+                            "Client on core {} (pid {pid}) {cause}, giving up after {} respawns",
+                            client.id, client.restarts
+                        ),
+                    );
+                    continue;
+                }
+                let backoff = policy.backoff(client.consecutive_restarts);
+                self.report_to_broker::<S::Input>(
+                    &mut reporter,
+                    LogSeverity::Warn,
+                    format!(
+                        "Client on core {} (pid {pid}) {cause}, respawning it in {backoff:?}",
+                        client.id
+                    ),
+                );
+                client.restarts += 1;
+                client.consecutive_restarts += 1;
+                pending.push((now + backoff, client));
+                continue;
+            }
+
+            let now = current_time();
+            while let Some(pos) = pending.iter().position(|(due, _)| *due <= now) {
+                let (_, mut client) = pending.swap_remove(pos);
+                let Some(pid) = self.fork_client::<EMH, S>(
+                    hooks,
+                    client.id,
+                    client.bind_to,
+                    numa_topology,
+                    Duration::ZERO,
+                    debug_output,
+                    Some(client.state_map),
+                )?
+                else {
+                    // We were the respawned client, and are done fuzzing
+                    return Ok(());
+                };
+                client.pid = pid;
+                client.started = now;
+                clients.push(client);
+            }
+
+            if clients.is_empty() && pending.is_empty() {
+                log::info!("All clients are done, or given up");
+                if let Some(mut client) = reporter {
+                    client.sender_mut().send_exiting()?;
+                }
+                if let Some(broker) = broker {
+                    // # Safety
+                    // Normal libc calls, `status` is a valid pointer
+                    unsafe {
+                        libc::kill(broker, libc::SIGINT);
+                        libc::waitpid(broker, &raw mut status, 0);
+                    }
+                }
+                return Ok(());
+            }
+
+            std::thread::sleep(SUPERVISION_INTERVAL);
+        }
+    }
+
+    /// Logs `message`, and sends it to the broker as an [`Event::Log`], through the `reporter` client
+    #[cfg(all(unix, feature = "fork"))]
+    fn report_to_broker<I>(
+        &mut self,
+        reporter: &mut Option<LlmpClient<SP>>,
+        severity_level: LogSeverity,
+        message: String,
+    ) where
+        I: Input,
+    {
+        log::log!(severity_level.into(), "{message}");
+        let event = Event::<I>::Log {
+            severity_level,
+            message,
+            phantom: PhantomData,
+        };
+        let res = postcard::to_allocvec(&event)
+            .map_err(Error::from)
+            .and_then(|buf| {
+                if reporter.is_none() {
+                    *reporter = Some(LlmpClient::create_attach_to_tcp(
+                        self.shmem_provider.clone(),
+                        self.broker_port,
+                    )?);
+                }
+                reporter
+                    .as_mut()
+                    .unwrap()
+                    .send_buf(LLMP_TAG_EVENT_TO_BOTH, &buf)
+            });
+        if let Err(err) = res {
+            log::warn!("Failed to report to the broker: {err}");
+        }
+    }
+
     /// Launch the broker and the clients and fuzz
     #[cfg(any(windows, not(feature = "fork")))]
     #[allow(unused_mut, clippy::match_wild_err_arm, clippy::too_many_lines)]
@@ -344,6 +947,7 @@ where
             Ok(core_conf) => {
                 let core_id = core_conf.parse()?;
                 // the actual client. do the fuzzing
+                bind_memory_to_core_node(self.numa_topology()?.as_ref(), CoreId(core_id))?;
 
                 let builder = RestartingMgr::<EMH, MT, S, SP>::builder()
                     .shmem_provider(self.shmem_provider.clone())
@@ -354,17 +958,33 @@ where
                     .configuration(self.configuration)
                     .serialize_state(self.serialize_state)
                     .hooks(hooks);
+                #[cfg(feature = "llmp_compression")]
+                let builder = builder.compression(self.compression);
 
                 let builder = builder.time_ref(self.time_ref.clone());
 
                 let (state, mgr) = builder.build().launch()?;
 
-                return (self.run_client.take().unwrap())(state, mgr, CoreId(core_id));
+                return (self.take_run_client(CoreId(core_id))?)(state, mgr, CoreId(core_id));
             }
             Err(std::env::VarError::NotPresent) => {
                 // I am a broker
                 // before going to the broker loop, spawn n clients
 
+                self.check_roles()?;
+                #[cfg(unix)]
+                self.remote_broker_addr_from_env()?;
+                if self.respawn.is_some() {
+                    log::warn!(
+                        "Respawning clients needs the `fork` feature on Unix, not supervising them"
+                    );
+                }
+                // Do not leave the clients running if we die
+                #[cfg(windows)]
+                if let Err(err) = libafl_bolts::os::windows_jobs::kill_children_on_exit() {
+                    log::warn!("The clients will outlive the launcher if it crashes: {err}");
+                }
+
                 let core_ids = core_affinity::get_core_ids().unwrap();
                 let num_cores = core_ids.len();
                 let mut handles = vec![];
@@ -412,6 +1032,10 @@ where
 
                             std::env::set_var(_AFL_LAUNCHER_CLIENT, id.to_string());
                             let mut child = startable_self()?;
+                            if let Some(role) = self.role_of(id.into()) {
+                                child.env(LIBAFL_CLIENT_ROLE, role.name.as_ref());
+                                child.envs(role.env.iter().map(|(key, value)| (key, value)));
+                            }
                             let child = (if debug_output {
                                 &mut child
                             } else {
@@ -449,10 +1073,17 @@ where
                 .configuration(self.configuration)
                 .serialize_state(self.serialize_state)
                 .hooks(hooks);
+            #[cfg(feature = "llmp_compression")]
+            let builder = builder.compression(self.compression);
 
             let builder = builder.time_ref(self.time_ref.clone());
 
-            builder.build().launch()?;
+            #[cfg(unix)]
+            let remote_nodes = self.spawn_remote_nodes()?;
+            let res = builder.build().launch();
+            #[cfg(unix)]
+            stop_remote_nodes(remote_nodes);
+            res?;
 
             //broker exited. kill all clients.
             for handle in &mut handles {
@@ -539,6 +1170,13 @@ pub struct CentralizedLauncher<'a, CF, MF, MT, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The codec to compress the events of the clients with
+    #[cfg(feature = "llmp_compression")]
+    #[builder(default)]
+    compression: CompressionCodec,
+    /// The initial policy of the centralized nodes. The main node can switch it at runtime.
+    #[builder(default)]
+    policy: CentralizedPolicy,
 }
 
 #[cfg(all(unix, feature = "std", feature = "fork"))]
@@ -593,6 +1231,8 @@ where
                 .configuration(centralized_launcher.configuration)
                 .serialize_state(centralized_launcher.serialize_state)
                 .hooks(tuple_list!());
+            #[cfg(feature = "llmp_compression")]
+            let builder = builder.compression(centralized_launcher.compression);
 
             let builder = builder.time_ref(centralized_launcher.time_obs.clone());
 
@@ -622,7 +1262,7 @@ where
         S: State,
         S::Input: Send + Sync + 'static,
         CF: FnOnce(Option<S>, CentralizedEventManager<EM, (), S, SP>, CoreId) -> Result<(), Error>,
-        EM: UsesState<State = S>,
+        EM: HasCustomBufHandlers<State = S>,
         EMB: FnOnce(&Self, CoreId) -> Result<(Option<S>, EM), Error>,
         MF: FnOnce(
             Option<S>,
@@ -699,8 +1339,15 @@ where
 
                             let mut centralized_event_manager_builder =
                                 CentralizedEventManager::builder();
-                            centralized_event_manager_builder =
-                                centralized_event_manager_builder.is_main(true);
+                            centralized_event_manager_builder = centralized_event_manager_builder
+                                .is_main(true)
+                                .policy(self.policy);
+                            #[cfg(feature = "llmp_compression")]
+                            {
+                                centralized_event_manager_builder =
+                                    centralized_event_manager_builder
+                                        .compression(self.compression, COMPRESS_THRESHOLD);
+                            }
 
                             let c_mgr = centralized_event_manager_builder.build_on_port(
                                 mgr,
@@ -719,15 +1366,20 @@ where
                             let (state, mgr) =
                                 secondary_inner_mgr_builder.take().unwrap()(self, *bind_to)?;
 
-                            let centralized_builder = CentralizedEventManager::builder();
+                            let centralized_builder =
+                                CentralizedEventManager::builder().policy(self.policy);
+                            #[cfg(feature = "llmp_compression")]
+                            let centralized_builder = centralized_builder
+                                .compression(self.compression, COMPRESS_THRESHOLD);
 
-                            let c_mgr = centralized_builder.build_on_port(
+                            let mut c_mgr = centralized_builder.build_on_port(
                                 mgr,
                                 tuple_list!(),
                                 self.shmem_provider.clone(),
                                 self.centralized_broker_port,
                                 self.time_obs.clone(),
                             )?;
+                            c_mgr.follow_policy_updates();
 
                             self.secondary_run_client.take().unwrap()(state, c_mgr, *bind_to)?;
                             Err(Error::shutting_down())
@@ -10,8 +10,8 @@ use std::net::TcpStream;
 
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{CompressionCodec, Compressor},
+    llmp::{Flags, LLMP_FLAG_INITIALIZED},
 };
 use libafl_bolts::{
     current_time,
@@ -29,9 +29,11 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "llmp_compression")]
 use crate::events::llmp::COMPRESS_THRESHOLD;
+#[cfg(feature = "std")]
+use crate::events::{reconnect::UserStatsCache, ReconnectPolicy};
 use crate::{
     events::{
-        llmp::{LLMP_TAG_EVENT_TO_BOTH, _LLMP_TAG_EVENT_TO_BROKER},
+        llmp::{_LLMP_TAG_EVENT_TO_BROKER, LLMP_TAG_EVENT_TO_BOTH},
         AdaptiveSerializer, CustomBufEventResult, CustomBufHandlerFn, Event, EventConfig,
         EventFirer, EventManager, EventManagerHooksTuple, EventManagerId, EventProcessor,
         EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
@@ -44,6 +46,26 @@ use crate::{
     Error, HasMetadata,
 };
 
+/// The state needed to reconnect to a restarted broker
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct BrokerReconnect {
+    port: u16,
+    policy: ReconnectPolicy,
+    user_stats: UserStatsCache,
+}
+
+#[cfg(feature = "std")]
+impl BrokerReconnect {
+    fn new(port: u16, policy: ReconnectPolicy) -> Self {
+        Self {
+            port,
+            policy,
+            user_stats: UserStatsCache::default(),
+        }
+    }
+}
+
 /// An [`EventManager`] that forwards all events to other attached fuzzers on shared maps or via tcp,
 /// using low-level message passing, `llmp`.
 pub struct LlmpEventManager<EMH, S, SP>
@@ -63,7 +85,7 @@ where
     /// The custom buf handler
     custom_buf_handlers: Vec<Box<CustomBufHandlerFn<S>>>,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: Compressor,
     /// The configuration defines this specific fuzzer.
     /// A node will not re-use the observer values sent over LLMP
     /// from nodes with other configurations.
@@ -73,6 +95,8 @@ where
     serializations_cnt: usize,
     should_serialize_cnt: usize,
     pub(crate) time_ref: Option<Handle<TimeObserver>>,
+    #[cfg(feature = "std")]
+    reconnect: Option<BrokerReconnect>,
     phantom: PhantomData<S>,
 }
 
@@ -90,6 +114,10 @@ pub struct LlmpEventManagerBuilder<EMH> {
     throttle: Option<Duration>,
     hooks: EMH,
     always_interesting: bool,
+    #[cfg(feature = "llmp_compression")]
+    compressor: Compressor,
+    #[cfg(feature = "std")]
+    reconnect: Option<(u16, ReconnectPolicy)>,
 }
 
 impl Default for LlmpEventManagerBuilder<()> {
@@ -106,6 +134,10 @@ impl LlmpEventManagerBuilder<()> {
             throttle: None,
             hooks: (),
             always_interesting: false,
+            #[cfg(feature = "llmp_compression")]
+            compressor: Compressor::new(CompressionCodec::Gzip, COMPRESS_THRESHOLD),
+            #[cfg(feature = "std")]
+            reconnect: None,
         }
     }
 
@@ -115,6 +147,10 @@ impl LlmpEventManagerBuilder<()> {
             throttle: self.throttle,
             hooks,
             always_interesting: self.always_interesting,
+            #[cfg(feature = "llmp_compression")]
+            compressor: self.compressor,
+            #[cfg(feature = "std")]
+            reconnect: self.reconnect,
         }
     }
 
@@ -125,6 +161,10 @@ impl LlmpEventManagerBuilder<()> {
             throttle: self.throttle,
             hooks: self.hooks,
             always_interesting,
+            #[cfg(feature = "llmp_compression")]
+            compressor: self.compressor,
+            #[cfg(feature = "std")]
+            reconnect: self.reconnect,
         }
     }
 }
@@ -137,6 +177,27 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
         self
     }
 
+    /// Compress outgoing events of at least `threshold` bytes using `codec`.
+    /// Defaults to gzip for events of at least [`COMPRESS_THRESHOLD`] bytes.
+    /// Use [`CompressionCodec::None`] to disable compression altogether.
+    ///
+    /// Incoming events are always decompressed, no matter the codec they were sent with.
+    #[cfg(feature = "llmp_compression")]
+    #[must_use]
+    pub fn compression(mut self, codec: CompressionCodec, threshold: usize) -> Self {
+        self.compressor = Compressor::new(codec, threshold);
+        self
+    }
+
+    /// Reattach to the broker on `broker_port`, following the `policy`, if the broker dies or restarts.
+    /// After reconnecting, the latest user stats of this client are sent to the new broker again.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn reconnect(mut self, broker_port: u16, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some((broker_port, policy));
+        self
+    }
+
     /// Create a manager from a raw LLMP client
     pub fn build_from_client<S, SP>(
         self,
@@ -155,13 +216,17 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             always_interesting: self.always_interesting,
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             configuration,
             serialization_time: Duration::ZERO,
             deserialization_time: Duration::ZERO,
             serializations_cnt: 0,
             should_serialize_cnt: 0,
             time_ref,
+            #[cfg(feature = "std")]
+            reconnect: self
+                .reconnect
+                .map(|(port, policy)| BrokerReconnect::new(port, policy)),
             phantom: PhantomData,
             custom_buf_handlers: vec![],
         })
@@ -189,13 +254,17 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             always_interesting: self.always_interesting,
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             configuration,
             serialization_time: Duration::ZERO,
             deserialization_time: Duration::ZERO,
             serializations_cnt: 0,
             should_serialize_cnt: 0,
             time_ref,
+            #[cfg(feature = "std")]
+            reconnect: self
+                .reconnect
+                .map(|(port, policy)| BrokerReconnect::new(port, policy)),
             phantom: PhantomData,
             custom_buf_handlers: vec![],
         })
@@ -223,13 +292,17 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             always_interesting: self.always_interesting,
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             configuration,
             serialization_time: Duration::ZERO,
             deserialization_time: Duration::ZERO,
             serializations_cnt: 0,
             should_serialize_cnt: 0,
             time_ref,
+            #[cfg(feature = "std")]
+            reconnect: self
+                .reconnect
+                .map(|(port, policy)| BrokerReconnect::new(port, policy)),
             phantom: PhantomData,
             custom_buf_handlers: vec![],
         })
@@ -255,13 +328,17 @@ impl<EMH> LlmpEventManagerBuilder<EMH> {
             always_interesting: self.always_interesting,
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: self.compressor,
             configuration,
             serialization_time: Duration::ZERO,
             deserialization_time: Duration::ZERO,
             serializations_cnt: 0,
             should_serialize_cnt: 0,
             time_ref,
+            #[cfg(feature = "std")]
+            reconnect: self
+                .reconnect
+                .map(|(port, policy)| BrokerReconnect::new(port, policy)),
             phantom: PhantomData,
             custom_buf_handlers: vec![],
         })
@@ -371,6 +448,38 @@ where
         Ok(())
     }
 
+    /// Checks if the broker is still alive, if a [`ReconnectPolicy`] is set.
+    /// The broker counts as lost if it exited, or if we did not see its heartbeat for the `broker_timeout` of the policy.
+    /// Then, reattaches to the (new) broker and re-announces our user stats.
+    ///
+    /// Call this only after reading all incoming messages: the ones we did not read yet are lost on reattach,
+    /// and so are our messages the old broker did not forward anymore.
+    #[cfg(feature = "std")]
+    fn maybe_reconnect(&mut self, state: &mut S, broker_exited: bool) -> Result<(), Error> {
+        let Some(reconnect) = &mut self.reconnect else {
+            return Ok(());
+        };
+        let silent_for = current_time().saturating_sub(self.llmp.receiver().last_msg_time());
+        if !broker_exited && silent_for < reconnect.policy.broker_timeout {
+            return Ok(());
+        }
+
+        let port = reconnect.port;
+        log::warn!("The broker on port {port} went away (silent for {silent_for:?}), reconnecting");
+        let llmp = &mut self.llmp;
+        reconnect.policy.retry(|| llmp.reattach_to_tcp(port))?;
+        let user_stats: Vec<_> = reconnect.user_stats.events().collect();
+        log::info!(
+            "Reconnected to the broker on port {port} as {:?}",
+            self.llmp.sender().id()
+        );
+
+        for event in user_stats {
+            self.fire(state, event)?;
+        }
+        Ok(())
+    }
+
     /// Describe the client event manager's LLMP parts in a restorable fashion
     pub fn describe(&self) -> Result<LlmpClientDescription, Error> {
         self.llmp.describe()
@@ -471,6 +580,9 @@ where
             Event::Stop => {
                 state.request_stop();
             }
+            Event::SetStopPolicy { policy } => {
+                state.add_metadata(policy);
+            }
             _ => {
                 return Err(Error::unknown(format!(
                     "Received illegal message that message should not have arrived: {:?}.",
@@ -520,6 +632,10 @@ where
         _state: &mut Self::State,
         event: Event<<Self::State as UsesInput>::Input>,
     ) -> Result<(), Error> {
+        #[cfg(feature = "std")]
+        if let Some(reconnect) = &mut self.reconnect {
+            reconnect.user_stats.record(&event);
+        }
         let serialized = postcard::to_allocvec(&event)?;
         let flags = LLMP_FLAG_INITIALIZED;
 
@@ -527,7 +643,7 @@ where
             Some(comp_buf) => {
                 self.llmp.send_buf_with_flags(
                     LLMP_TAG_EVENT_TO_BOTH,
-                    flags | LLMP_FLAG_COMPRESSED,
+                    flags | Flags::compressed_with(self.compressor.codec()),
                     &comp_buf,
                 )?;
             }
@@ -546,6 +662,10 @@ where
         _state: &mut Self::State,
         event: Event<<Self::State as UsesInput>::Input>,
     ) -> Result<(), Error> {
+        #[cfg(feature = "std")]
+        if let Some(reconnect) = &mut self.reconnect {
+            reconnect.user_stats.record(&event);
+        }
         let serialized = postcard::to_allocvec(&event)?;
         self.llmp.send_buf(LLMP_TAG_EVENT_TO_BOTH, &serialized)?;
         Ok(())
@@ -603,7 +723,17 @@ where
         // TODO: Get around local event copy by moving handle_in_client
         let self_id = self.llmp.sender().id();
         let mut count = 0;
-        while let Some((client_id, tag, flags, msg)) = self.llmp.recv_buf_with_flags()? {
+        #[cfg(feature = "std")]
+        let mut broker_exited = false;
+        while let Some((client_id, tag, flags, msg)) = match self.llmp.recv_buf_with_flags() {
+            // With a reconnect policy, we wait for the next broker instead
+            #[cfg(feature = "std")]
+            Err(Error::ShuttingDown) if self.reconnect.is_some() => {
+                broker_exited = true;
+                None
+            }
+            ret => ret?,
+        } {
             assert!(
                 tag != _LLMP_TAG_EVENT_TO_BROKER,
                 "EVENT_TO_BROKER parcel should not have arrived in the client!"
@@ -617,8 +747,8 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+            let event_bytes = if let Some(codec) = flags.compression_codec() {
+                compressed = codec.decompress(msg)?;
                 &compressed
             } else {
                 msg
@@ -635,6 +765,9 @@ where
             self.handle_in_client(fuzzer, executor, state, client_id, event)?;
             count += 1;
         }
+
+        #[cfg(feature = "std")]
+        self.maybe_reconnect(state, broker_exited)?;
         Ok(count)
     }
 
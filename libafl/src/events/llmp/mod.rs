@@ -5,8 +5,8 @@ use core::{marker::PhantomData, time::Duration};
 
 #[cfg(feature = "llmp_compression")]
 use libafl_bolts::{
-    compress::GzipCompressor,
-    llmp::{LLMP_FLAG_COMPRESSED, LLMP_FLAG_INITIALIZED},
+    compress::{CompressionCodec, Compressor},
+    llmp::{Flags, LLMP_FLAG_INITIALIZED},
 };
 use libafl_bolts::{
     llmp::{LlmpClient, LlmpClientDescription, Tag},
@@ -98,7 +98,7 @@ where
     /// The custom buf handler
     custom_buf_handlers: Vec<Box<CustomBufHandlerFn<S>>>,
     #[cfg(feature = "llmp_compression")]
-    compressor: GzipCompressor,
+    compressor: Compressor,
     converter: Option<IC>,
     converter_back: Option<ICB>,
     phantom: PhantomData<S>,
@@ -160,7 +160,7 @@ impl LlmpEventConverterBuilder {
             last_sent: Duration::from_secs(0),
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: Compressor::new(CompressionCodec::Gzip, COMPRESS_THRESHOLD),
             converter,
             converter_back,
             phantom: PhantomData,
@@ -190,7 +190,7 @@ impl LlmpEventConverterBuilder {
             last_sent: Duration::from_secs(0),
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: Compressor::new(CompressionCodec::Gzip, COMPRESS_THRESHOLD),
             converter,
             converter_back,
             phantom: PhantomData,
@@ -220,7 +220,7 @@ impl LlmpEventConverterBuilder {
             last_sent: Duration::from_secs(0),
             llmp,
             #[cfg(feature = "llmp_compression")]
-            compressor: GzipCompressor::with_threshold(COMPRESS_THRESHOLD),
+            compressor: Compressor::new(CompressionCodec::Gzip, COMPRESS_THRESHOLD),
             converter,
             converter_back,
             phantom: PhantomData,
@@ -329,7 +329,7 @@ where
                 }
                 Ok(())
             }
-            Event::Stop => Ok(()),
+            Event::Stop | Event::SetStopPolicy { .. } => Ok(()),
             _ => Err(Error::unknown(format!(
                 "Received illegal message that message should not have arrived: {:?}.",
                 event.name()
@@ -369,8 +369,8 @@ where
             #[cfg(feature = "llmp_compression")]
             let compressed;
             #[cfg(feature = "llmp_compression")]
-            let event_bytes = if _flags & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
-                compressed = self.compressor.decompress(msg)?;
+            let event_bytes = if let Some(codec) = _flags.compression_codec() {
+                compressed = codec.decompress(msg)?;
                 &compressed
             } else {
                 msg
@@ -457,7 +457,7 @@ where
             Some(comp_buf) => {
                 self.llmp.send_buf_with_flags(
                     LLMP_TAG_EVENT_TO_BOTH,
-                    flags | LLMP_FLAG_COMPRESSED,
+                    flags | Flags::compressed_with(self.compressor.codec()),
                     &comp_buf,
                 )?;
             }
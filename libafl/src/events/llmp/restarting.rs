@@ -12,6 +12,8 @@ use core::{marker::PhantomData, num::NonZeroUsize};
 #[cfg(feature = "std")]
 use std::net::SocketAddr;
 
+#[cfg(feature = "llmp_compression")]
+use libafl_bolts::compress::CompressionCodec;
 #[cfg(feature = "std")]
 use libafl_bolts::core_affinity::CoreId;
 #[cfg(all(feature = "std", any(windows, not(feature = "fork"))))]
@@ -20,23 +22,30 @@ use libafl_bolts::os::startable_self;
 use libafl_bolts::os::unix_signals::setup_signal_handler;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 use libafl_bolts::os::{fork, ForkResult};
-#[cfg(feature = "std")]
-use libafl_bolts::{
-    llmp::LlmpConnection, os::CTRL_C_EXIT, shmem::StdShMemProvider, staterestore::StateRestorer,
-};
 use libafl_bolts::{
     llmp::{Broker, LlmpBroker},
     shmem::ShMemProvider,
     tuples::{tuple_list, Handle},
 };
+#[cfg(feature = "std")]
+use libafl_bolts::{
+    llmp::{LlmpClientDescription, LlmpConnection},
+    os::CTRL_C_EXIT,
+    shmem::{ShMemDescription, StdShMemProvider},
+    staterestore::StateRestorer,
+};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use typed_builder::TypedBuilder;
 
+#[cfg(feature = "llmp_compression")]
+use crate::events::llmp::COMPRESS_THRESHOLD;
 #[cfg(all(unix, feature = "std", not(miri)))]
 use crate::events::EVENTMGR_SIGHANDLER_STATE;
 #[cfg(feature = "std")]
-use crate::events::{AdaptiveSerializer, CustomBufEventResult, HasCustomBufHandlers};
+use crate::events::{
+    AdaptiveSerializer, CustomBufEventResult, HasCustomBufHandlers, ReconnectPolicy,
+};
 use crate::{
     events::{
         Event, EventConfig, EventFirer, EventManager, EventManagerHooksTuple, EventManagerId,
@@ -269,6 +278,12 @@ const _ENV_FUZZER_RECEIVER: &str = "_AFL_ENV_FUZZER_RECEIVER";
 /// The llmp (2 way) connection from a fuzzer to the broker (broadcasting all other fuzzer messages)
 const _ENV_FUZZER_BROKER_CLIENT_INITIAL: &str = "_AFL_ENV_FUZZER_BROKER_CLIENT";
 
+/// How often a broker tries to connect to its remote broker
+const B2B_CONNECT_TRIES: usize = 10;
+
+/// The delay between two tries to connect to the remote broker
+const B2B_CONNECT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 #[cfg(feature = "std")]
 impl<EMH, S, SP> LlmpRestartingEventManager<EMH, S, SP>
 where
@@ -339,12 +354,14 @@ pub enum ManagerKind {
 ///
 /// The restarting mgr is a combination of restarter and runner, that can be used on systems with and without `fork` support.
 /// The restarter will spawn a new process each time the child crashes or timeouts.
+/// The events are compressed with `compression`, if the `llmp_compression` feature is enabled.
 #[cfg(feature = "std")]
 #[allow(clippy::type_complexity)]
 pub fn setup_restarting_mgr_std<MT, S>(
     monitor: MT,
     broker_port: u16,
     configuration: EventConfig,
+    #[cfg(feature = "llmp_compression")] compression: CompressionCodec,
 ) -> Result<
     (
         Option<S>,
@@ -356,14 +373,14 @@ where
     MT: Monitor + Clone,
     S: State,
 {
-    RestartingMgr::builder()
+    let builder = RestartingMgr::builder()
         .shmem_provider(StdShMemProvider::new()?)
         .monitor(Some(monitor))
         .broker_port(broker_port)
-        .configuration(configuration)
-        .hooks(tuple_list!())
-        .build()
-        .launch()
+        .configuration(configuration);
+    #[cfg(feature = "llmp_compression")]
+    let builder = builder.compression(compression);
+    builder.hooks(tuple_list!()).build().launch()
 }
 
 /// Sets up a restarting fuzzer, using the [`StdShMemProvider`], and standard features.
@@ -371,6 +388,7 @@ where
 /// The restarting mgr is a combination of restarter and runner, that can be used on systems with and without `fork` support.
 /// The restarter will spawn a new process each time the child crashes or timeouts.
 /// This one, additionally uses the timeobserver for the adaptive serialization
+/// The events are compressed with `compression`, if the `llmp_compression` feature is enabled.
 #[cfg(feature = "std")]
 #[allow(clippy::type_complexity)]
 pub fn setup_restarting_mgr_std_adaptive<MT, S>(
@@ -378,6 +396,7 @@ pub fn setup_restarting_mgr_std_adaptive<MT, S>(
     broker_port: u16,
     configuration: EventConfig,
     time_obs: Handle<TimeObserver>,
+    #[cfg(feature = "llmp_compression")] compression: CompressionCodec,
 ) -> Result<
     (
         Option<S>,
@@ -389,11 +408,14 @@ where
     MT: Monitor + Clone,
     S: State,
 {
-    RestartingMgr::builder()
+    let builder = RestartingMgr::builder()
         .shmem_provider(StdShMemProvider::new()?)
         .monitor(Some(monitor))
         .broker_port(broker_port)
-        .configuration(configuration)
+        .configuration(configuration);
+    #[cfg(feature = "llmp_compression")]
+    let builder = builder.compression(compression);
+    builder
         .hooks(tuple_list!())
         .time_ref(Some(time_obs))
         .build()
@@ -440,10 +462,21 @@ pub struct RestartingMgr<EMH, MT, S, SP> {
     /// Tell the manager to serialize or not the state on restart
     #[builder(default = LlmpShouldSaveState::OnRestart)]
     serialize_state: LlmpShouldSaveState,
+    /// The codec to compress the events of the clients with
+    #[cfg(feature = "llmp_compression")]
+    #[builder(default)]
+    compression: CompressionCodec,
     /// The hooks passed to event manager:
     hooks: EMH,
     #[builder(default = None)]
     time_ref: Option<Handle<TimeObserver>>,
+    /// If set, fuzzing clients reattach to a restarted broker on `broker_port`, following this policy
+    #[builder(default = None)]
+    reconnect: Option<ReconnectPolicy>,
+    /// The map of the [`StateRestorer`] of a client that died before, to continue from its last saved state.
+    /// Only the state is restored, the client connects to the broker anew.
+    #[builder(default = None)]
+    state_map: Option<ShMemDescription>,
     #[builder(setter(skip), default = PhantomData)]
     phantom_data: PhantomData<(EMH, S)>,
 }
@@ -460,13 +493,24 @@ where
     /// Launch the broker and the clients and fuzz
     pub fn launch(&mut self) -> Result<(Option<S>, LlmpRestartingEventManager<EMH, S, SP>), Error> {
         // We start ourselves as child process to actually fuzz
-        let (staterestorer, new_shmem_provider, core_id) = if std::env::var(_ENV_FUZZER_SENDER)
-            .is_err()
+        let (staterestorer, new_shmem_provider, core_id, inherited) = if std::env::var(
+            _ENV_FUZZER_SENDER,
+        )
+        .is_err()
         {
             let broker_things = |mut broker: LlmpBroker<_, SP>, remote_broker_addr| {
                 if let Some(remote_broker_addr) = remote_broker_addr {
                     log::info!("B2b: Connecting to {:?}", &remote_broker_addr);
-                    broker.inner_mut().connect_b2b(remote_broker_addr)?;
+                    // The remote broker may still be starting up
+                    let mut tries = 1;
+                    while let Err(err) = broker.inner_mut().connect_b2b(remote_broker_addr) {
+                        if tries >= B2B_CONNECT_TRIES {
+                            return Err(err);
+                        }
+                        log::info!("B2b: Connecting failed ({err}), retrying");
+                        std::thread::sleep(B2B_CONNECT_RETRY_DELAY);
+                        tries += 1;
+                    }
                 };
 
                 if let Some(exit_cleanly_after) = self.exit_cleanly_after {
@@ -504,14 +548,16 @@ where
                             return Err(Error::shutting_down());
                         }
                         LlmpConnection::IsClient { client } => {
-                            let mgr: LlmpEventManager<EMH, S, SP> = LlmpEventManager::builder()
+                            let builder = LlmpEventManager::builder()
                                 .always_interesting(self.always_interesting)
-                                .hooks(self.hooks)
-                                .build_from_client(
-                                    client,
-                                    self.configuration,
-                                    self.time_ref.clone(),
-                                )?;
+                                .hooks(self.hooks);
+                            #[cfg(feature = "llmp_compression")]
+                            let builder = builder.compression(self.compression, COMPRESS_THRESHOLD);
+                            let mgr: LlmpEventManager<EMH, S, SP> = builder.build_from_client(
+                                client,
+                                self.configuration,
+                                self.time_ref.clone(),
+                            )?;
                             (mgr, None)
                         }
                     }
@@ -530,15 +576,17 @@ where
                 }
                 ManagerKind::Client { cpu_core } => {
                     // We are a client
-                    let mgr = LlmpEventManager::builder()
+                    let builder = LlmpEventManager::builder()
                         .always_interesting(self.always_interesting)
-                        .hooks(self.hooks)
-                        .build_on_port(
-                            self.shmem_provider.clone(),
-                            self.broker_port,
-                            self.configuration,
-                            self.time_ref.clone(),
-                        )?;
+                        .hooks(self.hooks);
+                    #[cfg(feature = "llmp_compression")]
+                    let builder = builder.compression(self.compression, COMPRESS_THRESHOLD);
+                    let mgr = builder.build_on_port(
+                        self.shmem_provider.clone(),
+                        self.broker_port,
+                        self.configuration,
+                        self.time_ref.clone(),
+                    )?;
 
                     (mgr, cpu_core)
                 }
@@ -554,13 +602,14 @@ where
             mgr.to_env(_ENV_FUZZER_BROKER_CLIENT_INITIAL);
 
             // First, create a channel from the current fuzzer to the next to store state between restarts.
-            #[cfg(unix)]
-            let staterestorer: StateRestorer<SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
-
-            #[cfg(not(unix))]
-            let staterestorer: StateRestorer<SP> =
-                StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?);
+            // A client that died before left its state in the given one.
+            let staterestorer: StateRestorer<SP> = match self.state_map {
+                Some(description) => {
+                    StateRestorer::from_description(&mut self.shmem_provider, description)?
+                }
+                None => StateRestorer::new(self.shmem_provider.new_shmem(256 * 1024 * 1024)?),
+            };
+            let inherited = staterestorer.has_content();
             // Store the information to a map.
             staterestorer.write_to_env(_ENV_FUZZER_SENDER)?;
 
@@ -588,7 +637,13 @@ where
                                 std::process::id()
                             );
                             self.shmem_provider.post_fork(true)?;
-                            break (staterestorer, self.shmem_provider.clone(), core_id);
+                            // Later children restore what the previous child saved, with its own connection
+                            break (
+                                staterestorer,
+                                self.shmem_provider.clone(),
+                                core_id,
+                                inherited && ctr == 0,
+                            );
                         }
                     }
                 };
@@ -645,6 +700,7 @@ where
                 StateRestorer::from_env(&mut self.shmem_provider, _ENV_FUZZER_SENDER)?,
                 self.shmem_provider.clone(),
                 None,
+                false,
             )
         };
 
@@ -661,17 +717,25 @@ where
             core_id.set_affinity()?;
         }
 
+        let mut mgr_builder = LlmpEventManager::builder().hooks(self.hooks);
+        #[cfg(feature = "llmp_compression")]
+        {
+            mgr_builder = mgr_builder.compression(self.compression, COMPRESS_THRESHOLD);
+        }
+        if let Some(policy) = self.reconnect {
+            mgr_builder = mgr_builder.reconnect(self.broker_port, policy);
+        }
+
         // If we're restarting, deserialize the old state.
-        let (state, mut mgr) =
-            if let Some((state_opt, mgr_description)) = staterestorer.restore()? {
-                let llmp_mgr = LlmpEventManager::builder()
-                    .hooks(self.hooks)
-                    .build_existing_client_from_description(
-                        new_shmem_provider,
-                        &mgr_description,
-                        self.configuration,
-                        self.time_ref.clone(),
-                    )?;
+        let restored = staterestorer.restore::<(Option<S>, LlmpClientDescription)>()?;
+        let (state, mut mgr) = match restored {
+            Some((state_opt, mgr_description)) if !inherited => {
+                let llmp_mgr = mgr_builder.build_existing_client_from_description(
+                    new_shmem_provider,
+                    &mgr_description,
+                    self.configuration,
+                    self.time_ref.clone(),
+                )?;
                 (
                     state_opt,
                     LlmpRestartingEventManager::with_save_state(
@@ -680,27 +744,33 @@ where
                         self.serialize_state,
                     ),
                 )
-            } else {
-                log::info!("First run. Let's set it all up");
+            }
+            restored => {
+                // The connection of a client that died before is gone, only its state is kept.
+                let state_opt = restored.and_then(|(state_opt, _)| state_opt);
+                if state_opt.is_some() {
+                    log::info!("Continuing from the state of a previous client");
+                } else {
+                    log::info!("First run. Let's set it all up");
+                }
                 // Mgr to send and receive msgs from/to all other fuzzer instances
-                let mgr = LlmpEventManager::builder()
-                    .hooks(self.hooks)
-                    .build_existing_client_from_env(
-                        new_shmem_provider,
-                        _ENV_FUZZER_BROKER_CLIENT_INITIAL,
-                        self.configuration,
-                        self.time_ref.clone(),
-                    )?;
+                let mgr = mgr_builder.build_existing_client_from_env(
+                    new_shmem_provider,
+                    _ENV_FUZZER_BROKER_CLIENT_INITIAL,
+                    self.configuration,
+                    self.time_ref.clone(),
+                )?;
 
                 (
-                    None,
+                    state_opt,
                     LlmpRestartingEventManager::with_save_state(
                         mgr,
                         staterestorer,
                         self.serialize_state,
                     ),
                 )
-            };
+            }
+        };
         // We reset the staterestorer, the next staterestorer and receiver (after crash) will reuse the page from the initial message.
         if self.serialize_state.oom_safe() {
             mgr.intermediate_save()?;
@@ -6,19 +6,43 @@ pub use events_hooks::*;
 
 pub mod simple;
 pub use simple::*;
+pub mod ring;
+pub use ring::{decode_event, EventTransport, RingEventManager};
+#[cfg(feature = "std")]
+pub mod batching;
+#[cfg(feature = "std")]
+pub use batching::{BatchingEventManager, BatchingPolicy, StatsDropPolicy};
 #[cfg(all(unix, feature = "std"))]
 pub mod centralized;
 #[cfg(all(unix, feature = "std"))]
 pub use centralized::*;
 #[cfg(feature = "std")]
+pub mod event_log;
+#[cfg(feature = "std")]
+pub use event_log::*;
+#[cfg(feature = "std")]
+pub mod reconnect;
+#[cfg(all(unix, feature = "std"))]
+pub mod remote_nodes;
+#[cfg(feature = "std")]
+pub use reconnect::ReconnectPolicy;
+#[cfg(all(unix, feature = "std"))]
+pub use remote_nodes::{RemoteBinary, RemoteNode, RemoteNodeProcess};
+#[cfg(feature = "std")]
 #[allow(clippy::ignored_unit_patterns)]
 pub mod launcher;
 #[allow(clippy::ignored_unit_patterns)]
 pub mod llmp;
 pub use llmp::*;
+#[cfg(feature = "grpc_manager")]
+pub mod grpc;
+#[cfg(feature = "async_io")]
+pub mod pump;
 #[cfg(feature = "tcp_manager")]
 #[allow(clippy::ignored_unit_patterns)]
 pub mod tcp;
+#[cfg(all(feature = "tcp_manager", feature = "async_io"))]
+pub mod tcp_async;
 
 pub mod broker_hooks;
 use alloc::{borrow::Cow, boxed::Box, string::String, vec::Vec};
@@ -42,7 +66,7 @@ use libafl_bolts::{
     tuples::{Handle, MatchNameRef},
     ClientId,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 #[cfg(feature = "std")]
 use uuid::Uuid;
 
@@ -51,16 +75,13 @@ use crate::state::HasClientPerfMonitor;
 use crate::{
     executors::ExitKind,
     inputs::Input,
-    monitors::UserStats,
+    monitors::{UserStats, UserStatsDef, UserStatsValue},
     observers::ObserversTuple,
     state::{HasExecutions, HasLastReportTime, State},
     Error, HasMetadata,
 };
 #[cfg(feature = "scalability_introspection")]
-use crate::{
-    monitors::{AggregatorOps, UserStatsValue},
-    state::HasScalabilityMonitor,
-};
+use crate::{monitors::AggregatorOps, state::HasScalabilityMonitor};
 
 /// Multi-machine mode
 #[cfg(all(unix, feature = "std", feature = "multi_machine"))]
@@ -105,6 +126,17 @@ impl SignalHandler for ShutdownSignalData {
     }
 }
 
+/// Compares two secret tokens, taking the same time no matter where they differ
+#[cfg(any(feature = "tcp_manager", feature = "grpc_manager"))]
+pub(crate) fn tokens_match(expected: &[u8], received: &[u8]) -> bool {
+    expected.len() == received.len()
+        && expected
+            .iter()
+            .zip(received)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
 /// A per-fuzzer unique `ID`, usually starting with `0` and increasing
 /// by `1` in multiprocessed [`EventManager`]s, such as [`LlmpEventManager`].
 #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -119,7 +151,8 @@ use crate::events::multi_machine::NodeId;
 #[cfg(feature = "introspection")]
 use crate::monitors::ClientPerfMonitor;
 use crate::{
-    inputs::UsesInput, observers::TimeObserver, stages::HasCurrentStageId, state::UsesState,
+    fuzzer::StopPolicy, inputs::UsesInput, observers::TimeObserver, stages::HasCurrentStageId,
+    state::UsesState,
 };
 
 /// The log event severity
@@ -166,6 +199,15 @@ pub enum CustomBufEventResult {
     Next,
 }
 
+/// A harness-specific event, exchanged between clients as [`Event::CustomBuf`] tagged with its [`CustomEvent::NAME`].
+///
+/// Fire it using [`EventFirer::fire_custom`], and handle it on the other clients
+/// using [`HasCustomBufHandlers::add_custom_event_handler`].
+pub trait CustomEvent: Serialize + DeserializeOwned {
+    /// The unique name of this kind of event
+    const NAME: &'static str;
+}
+
 /// Indicate if an event worked or not
 #[derive(Serialize, Deserialize, Debug, Copy, Clone)]
 pub enum BrokerEventResult {
@@ -349,6 +391,11 @@ where
     },
     /// Exit gracefully
     Stop,
+    /// Sets the [`StopPolicy`] of the clients, see [`StopPolicy::broadcast`]
+    SetStopPolicy {
+        /// The new policy
+        policy: StopPolicy,
+    },
     /*/// A custom type
     Custom {
         // TODO: Allow custom events
@@ -375,6 +422,7 @@ where
                 sender_id: _, /*custom_event} => custom_event.name()*/
             } => "todo",*/
             Event::Stop => "Stop",
+            Event::SetStopPolicy { .. } => "SetStopPolicy",
         }
     }
 
@@ -392,6 +440,7 @@ where
             Event::Log { .. } => Cow::Borrowed("Log"),
             Event::CustomBuf { .. } => Cow::Borrowed("CustomBuf"),
             Event::Stop => Cow::Borrowed("Stop"),
+            Event::SetStopPolicy { .. } => Cow::Borrowed("SetStopPolicy"),
             /*Event::Custom {
                 sender_id: _, /*custom_event} => custom_event.name()*/
             } => "todo",*/
@@ -438,6 +487,42 @@ pub trait EventFirer: UsesState {
         )
     }
 
+    /// Send off a [`CustomEvent`] to the other clients.
+    /// This is a shortcut for [`EventFirer::fire`] with the postcard-serialized event in an [`Event::CustomBuf`].
+    fn fire_custom<CE>(&mut self, state: &mut Self::State, event: &CE) -> Result<(), Error>
+    where
+        CE: CustomEvent,
+    {
+        self.fire(
+            state,
+            Event::CustomBuf {
+                buf: postcard::to_allocvec(event)?,
+                tag: CE::NAME.into(),
+            },
+        )
+    }
+
+    /// Send off an update of the user stat declared by `stat` to the broker.
+    /// This is a shortcut for [`EventFirer::fire`] with [`Event::UpdateUserStats`] as argument.
+    fn fire_user_stats<V>(
+        &mut self,
+        state: &mut Self::State,
+        stat: &UserStatsDef,
+        value: V,
+    ) -> Result<(), Error>
+    where
+        V: Into<UserStatsValue>,
+    {
+        self.fire(
+            state,
+            Event::UpdateUserStats {
+                name: stat.name().clone(),
+                value: stat.with_value(value),
+                phantom: PhantomData,
+            },
+        )
+    }
+
     /// Serialize all observers for this type and manager
     fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
     where
@@ -608,6 +693,22 @@ type CustomBufHandlerFn<S> = dyn FnMut(&mut S, &str, &[u8]) -> Result<CustomBufE
 pub trait HasCustomBufHandlers: UsesState {
     /// Adds a custom buffer handler that will run for each incoming `CustomBuf` event.
     fn add_custom_buf_handler(&mut self, handler: Box<CustomBufHandlerFn<Self::State>>);
+
+    /// Adds a handler that will run for each incoming [`CustomEvent`] of type `CE`.
+    /// `CustomBuf` events with other tags are passed on to the next handler.
+    fn add_custom_event_handler<CE, F>(&mut self, mut handler: F)
+    where
+        CE: CustomEvent,
+        F: FnMut(&mut Self::State, CE) -> Result<CustomBufEventResult, Error> + 'static,
+    {
+        self.add_custom_buf_handler(Box::new(move |state, tag, buf| {
+            if tag == CE::NAME {
+                handler(state, postcard::from_bytes(buf)?)
+            } else {
+                Ok(CustomBufEventResult::Next)
+            }
+        }));
+    }
 }
 
 /// An eventmgr for tests, and as placeholder if you really don't need an event manager.
@@ -942,14 +1043,23 @@ pub trait AdaptiveSerializer {
 #[cfg(test)]
 mod tests {
 
+    use alloc::{rc::Rc, string::String, vec::Vec};
+    use core::cell::RefCell;
+
     use libafl_bolts::{current_time, tuples::tuple_list, Named};
+    use serde::{Deserialize, Serialize};
     use tuple_list::tuple_list_type;
 
     use crate::{
-        events::{Event, EventConfig},
+        events::{
+            CustomBufEventResult, CustomEvent, Event, EventConfig, EventFirer, EventProcessor,
+            HasCustomBufHandlers, SimpleEventManager,
+        },
         executors::ExitKind,
         inputs::bytes::BytesInput,
+        monitors::NopMonitor,
         observers::StdMapObserver,
+        state::NopState,
     };
 
     static mut MAP: [u32; 4] = [0; 4];
@@ -989,4 +1099,50 @@ mod tests {
             _ => panic!("mistmatch"),
         };
     }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Hint {
+        token: String,
+    }
+
+    impl CustomEvent for Hint {
+        const NAME: &'static str = "hint";
+    }
+
+    #[test]
+    fn test_custom_event() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut mgr = SimpleEventManager::new(NopMonitor::new());
+
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let hints = received.clone();
+        mgr.add_custom_event_handler(move |_state, hint: Hint| {
+            hints.borrow_mut().push(hint);
+            Ok(CustomBufEventResult::Handled)
+        });
+
+        mgr.fire_custom(
+            &mut state,
+            &Hint {
+                token: "magic".into(),
+            },
+        )
+        .unwrap();
+        mgr.fire(
+            &mut state,
+            Event::CustomBuf {
+                buf: vec![1, 2, 3],
+                tag: "other".into(),
+            },
+        )
+        .unwrap();
+        EventProcessor::<(), ()>::process(&mut mgr, &mut (), &mut state, &mut ()).unwrap();
+
+        assert_eq!(
+            *received.borrow(),
+            [Hint {
+                token: "magic".into()
+            }]
+        );
+    }
 }
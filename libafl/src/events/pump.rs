@@ -0,0 +1,257 @@
+//! Pumps events between the synchronous fuzzing loop and async network IO on `tokio`.
+//!
+//! The [`EventPump`] runs a transport future on a `tokio` runtime in a background thread.
+//! The fuzzer hands outgoing items to it through a bounded channel and picks up incoming items without blocking,
+//! so a slow or unreachable peer never stalls the executions.
+
+use alloc::collections::VecDeque;
+use core::{future::Future, time::Duration};
+use std::{
+    sync::mpsc as std_mpsc,
+    thread::{self, JoinHandle},
+};
+
+use tokio::{
+    runtime::Runtime,
+    sync::{mpsc, mpsc::error::TrySendError},
+};
+
+use crate::Error;
+
+/// The number of outgoing items an [`EventPump`] buffers, by default
+pub const DEFAULT_PUMP_CAPACITY: usize = 4096;
+
+/// Moves outgoing items of type `O` from the fuzzing loop to an async transport, and incoming items of type `I` back.
+///
+/// Outgoing items are queued in a bounded channel. Once it is full, droppable items, like stats updates,
+/// are discarded, all others are kept in a backlog and sent in order once the transport caught up.
+/// The fuzzing loop never waits for the transport, so the pump may also be used from within a `tokio` runtime.
+#[derive(Debug)]
+pub struct EventPump<O, I> {
+    outgoing: Option<mpsc::Sender<O>>,
+    incoming: std_mpsc::Receiver<I>,
+    /// The number of droppable items discarded because the channel was full
+    dropped: u64,
+    /// Items that did not fit into the channel, sent before any newer item
+    backlog: VecDeque<O>,
+    thread: Option<JoinHandle<Result<(), Error>>>,
+}
+
+impl<O, I> EventPump<O, I>
+where
+    O: Send + 'static,
+    I: Send + 'static,
+{
+    /// Spawns a background thread driving the future returned by `transport` on a new current-thread runtime.
+    ///
+    /// The `transport` receives the outgoing items and sends the incoming ones.
+    /// It should return once the outgoing channel is closed and drained.
+    pub fn spawn<F, Fut>(capacity: usize, transport: F) -> Result<Self, Error>
+    where
+        F: FnOnce(mpsc::Receiver<O>, std_mpsc::Sender<I>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Self::with_runtime(runtime, capacity, transport)
+    }
+
+    /// Like [`Self::spawn`], on the given `runtime`, for example one that already established the connection
+    pub fn with_runtime<F, Fut>(
+        runtime: Runtime,
+        capacity: usize,
+        transport: F,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce(mpsc::Receiver<O>, std_mpsc::Sender<I>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), Error>>,
+    {
+        let (outgoing, outgoing_rx) = mpsc::channel(capacity);
+        let (incoming_tx, incoming) = std_mpsc::channel();
+        let thread = thread::Builder::new()
+            .name("event_pump".into())
+            .spawn(move || runtime.block_on(transport(outgoing_rx, incoming_tx)))?;
+        Ok(Self {
+            outgoing: Some(outgoing),
+            incoming,
+            dropped: 0,
+            backlog: VecDeque::new(),
+            thread: Some(thread),
+        })
+    }
+
+    /// Queues an outgoing `item`.
+    ///
+    /// If the channel is full, a `droppable` item is discarded, any other item is kept in the backlog.
+    pub fn send(&mut self, item: O, droppable: bool) -> Result<(), Error> {
+        if self.outgoing.is_none() {
+            return Err(Error::illegal_state("The event pump is closed"));
+        }
+        self.send_backlog()?;
+        if !self.backlog.is_empty() {
+            // Keep the order of the backlog
+            if droppable {
+                self.dropped += 1;
+            } else {
+                self.backlog.push_back(item);
+            }
+            return Ok(());
+        }
+        match self.try_send(item) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) if droppable => {
+                self.dropped += 1;
+                Ok(())
+            }
+            Err(TrySendError::Full(item)) => {
+                log::debug!("The event pump is full, keeping the item in the backlog");
+                self.backlog.push_back(item);
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(self.exited()),
+        }
+    }
+
+    /// Takes the next incoming item, if one arrived
+    pub fn try_recv(&mut self) -> Result<Option<I>, Error> {
+        if !self.backlog.is_empty() {
+            self.send_backlog()?;
+        }
+        match self.incoming.try_recv() {
+            Ok(item) => Ok(Some(item)),
+            Err(std_mpsc::TryRecvError::Empty) => Ok(None),
+            Err(std_mpsc::TryRecvError::Disconnected) => Err(self.exited()),
+        }
+    }
+
+    /// The number of droppable items discarded so far, because the transport could not keep up
+    #[must_use]
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The number of items waiting in the backlog, because the transport could not keep up
+    #[must_use]
+    pub fn backlogged(&self) -> usize {
+        self.backlog.len()
+    }
+
+    /// Closes the outgoing channel and waits for the transport to send the queued and backlogged items and return
+    pub fn close(&mut self) -> Result<(), Error> {
+        while !self.backlog.is_empty() && self.outgoing.is_some() {
+            self.send_backlog()?;
+            if !self.backlog.is_empty() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        drop(self.outgoing.take());
+        match self.thread.take() {
+            Some(thread) => thread
+                .join()
+                .map_err(|_| Error::illegal_state("The event pump thread panicked"))?,
+            None => Ok(()),
+        }
+    }
+
+    /// Moves as many items from the backlog into the channel as fit
+    fn send_backlog(&mut self) -> Result<(), Error> {
+        while let Some(item) = self.backlog.pop_front() {
+            match self.try_send(item) {
+                Ok(()) => {}
+                Err(TrySendError::Full(item)) => {
+                    self.backlog.push_front(item);
+                    break;
+                }
+                Err(TrySendError::Closed(_)) => return Err(self.exited()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts `item` into the outgoing channel, if there is room
+    fn try_send(&self, item: O) -> Result<(), TrySendError<O>> {
+        match &self.outgoing {
+            Some(outgoing) => outgoing.try_send(item),
+            None => Err(TrySendError::Closed(item)),
+        }
+    }
+
+    /// The error the transport exited with
+    fn exited(&mut self) -> Error {
+        drop(self.outgoing.take());
+        self.backlog.clear();
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(Err(err))) => err,
+            Some(Err(_)) => Error::illegal_state("The event pump thread panicked"),
+            _ => Error::illegal_state("The event pump transport has exited"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EventPump;
+
+    #[test]
+    fn test_event_pump() {
+        let (unblock, blocked) = tokio::sync::oneshot::channel::<()>();
+        let mut pump = EventPump::spawn(2, |mut outgoing, incoming| async move {
+            // Stall until the fuzzer filled the channel
+            blocked.await.unwrap();
+            while let Some(item) = outgoing.recv().await {
+                incoming.send(item * 2).unwrap();
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        pump.send(1_u32, false).unwrap();
+        pump.send(2, true).unwrap();
+        pump.send(3, true).unwrap();
+        assert_eq!(pump.dropped(), 1);
+        // A full channel never blocks the fuzzer
+        pump.send(4, false).unwrap();
+        pump.send(5, true).unwrap();
+        assert_eq!(pump.dropped(), 2);
+        assert_eq!(pump.backlogged(), 1);
+        assert_eq!(pump.try_recv().unwrap(), None);
+
+        unblock.send(()).unwrap();
+        pump.send(6, false).unwrap();
+        pump.close().unwrap();
+        assert_eq!(pump.backlogged(), 0);
+        let mut received = vec![];
+        while let Ok(Some(item)) = pump.try_recv() {
+            received.push(item);
+        }
+        assert_eq!(received, [2, 4, 8, 12]);
+        assert!(pump.send(7, false).is_err());
+    }
+
+    #[test]
+    fn test_event_pump_in_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut pump = EventPump::spawn(1, |mut outgoing, incoming| async move {
+            while let Some(item) = outgoing.recv().await {
+                incoming.send(item).unwrap();
+            }
+            Ok(())
+        })
+        .unwrap();
+        // Sending from within a runtime must not panic, even with a full channel
+        runtime.block_on(async {
+            for i in 0..64_u32 {
+                pump.send(i, false).unwrap();
+            }
+        });
+        pump.close().unwrap();
+        let mut received = vec![];
+        while let Ok(Some(item)) = pump.try_recv() {
+            received.push(item);
+        }
+        assert!(received.into_iter().eq(0..64));
+    }
+}
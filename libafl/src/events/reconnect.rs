@@ -0,0 +1,139 @@
+//! Reconnecting clients to a restarted broker.
+//!
+//! Without a [`ReconnectPolicy`], clients of a broker that died get stuck or crash.
+//! With one, the [`crate::events::LlmpEventManager`] and [`crate::events::TcpEventManager`]
+//! detect the loss of their broker, retry connecting with exponential backoff,
+//! and re-announce their latest user stats (such as the coverage) to the new broker.
+//! LLMP clients notice the loss by the missing heartbeat of their broker, TCP clients by their broken connection.
+//! Events the lost broker did not forward anymore are lost.
+
+use alloc::borrow::Cow;
+use core::{marker::PhantomData, time::Duration};
+use std::thread;
+
+use hashbrown::HashMap;
+use typed_builder::TypedBuilder;
+
+use crate::{events::Event, inputs::Input, monitors::UserStats, Error};
+
+/// How a client reconnects to its broker, after the broker went away.
+#[derive(Debug, Clone, Copy, TypedBuilder)]
+pub struct ReconnectPolicy {
+    /// How long an LLMP broker may stay silent before it counts as lost.
+    /// Brokers send a heartbeat each [`libafl_bolts::llmp::LLMP_BROKER_HEARTBEAT_INTERVAL`]. Defaults to 10s.
+    #[builder(default = Duration::from_secs(10))]
+    pub broker_timeout: Duration,
+    /// The time to wait after the first failed attempt. Doubles with each further attempt. Defaults to 100ms.
+    #[builder(default = Duration::from_millis(100))]
+    pub initial_backoff: Duration,
+    /// The maximum time to wait between two attempts. Defaults to 30s.
+    #[builder(default = Duration::from_secs(30))]
+    pub max_backoff: Duration,
+    /// Give up after this many attempts. Retries forever by default.
+    #[builder(default, setter(strip_option))]
+    pub max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl ReconnectPolicy {
+    /// Calls `connect` until it succeeds, waiting for an exponentially growing backoff between attempts.
+    /// Returns the last error once `max_attempts` is reached.
+    pub fn retry<F, T>(&self, mut connect: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        let mut backoff = self.initial_backoff;
+        let mut attempt = 1;
+        loop {
+            match connect() {
+                Ok(ret) => return Ok(ret),
+                Err(err) if self.max_attempts.is_some_and(|max| attempt >= max) => {
+                    log::error!(
+                        "Could not reconnect to the broker after {attempt} attempts: {err}"
+                    );
+                    return Err(err);
+                }
+                Err(err) => {
+                    log::warn!("Reconnecting to the broker failed (attempt {attempt}), retrying in {backoff:?}: {err}");
+                }
+            }
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(self.max_backoff);
+            attempt += 1;
+        }
+    }
+}
+
+/// The latest value of each user stat fired by a client, to re-announce them after reconnecting.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct UserStatsCache {
+    stats: HashMap<Cow<'static, str>, UserStats>,
+}
+
+impl UserStatsCache {
+    /// Remembers the value of `event`, if it's an [`Event::UpdateUserStats`]
+    pub(crate) fn record<I>(&mut self, event: &Event<I>)
+    where
+        I: Input,
+    {
+        if let Event::UpdateUserStats { name, value, .. } = event {
+            self.stats.insert(name.clone(), value.clone());
+        }
+    }
+
+    /// The [`Event::UpdateUserStats`] to fire to announce all remembered stats
+    pub(crate) fn events<I>(&self) -> impl Iterator<Item = Event<I>> + '_
+    where
+        I: Input,
+    {
+        self.stats
+            .iter()
+            .map(|(name, value)| Event::UpdateUserStats {
+                name: name.clone(),
+                value: value.clone(),
+                phantom: PhantomData,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::ReconnectPolicy;
+    use crate::Error;
+
+    #[test]
+    fn test_retry() {
+        let policy = ReconnectPolicy::builder()
+            .initial_backoff(Duration::from_millis(1))
+            .max_backoff(Duration::from_millis(2))
+            .max_attempts(3)
+            .build();
+
+        let mut attempts = 0;
+        let res = policy.retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(Error::illegal_state("broker down"))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(res.unwrap(), 3);
+
+        attempts = 0;
+        assert!(policy
+            .retry(|| -> Result<(), Error> {
+                attempts += 1;
+                Err(Error::illegal_state("broker down"))
+            })
+            .is_err());
+        assert_eq!(attempts, 3);
+    }
+}
@@ -0,0 +1,316 @@
+//! Starting the clients of a [`Launcher`](crate::events::Launcher) on other hosts, over SSH.
+//!
+//! Each [`RemoteNode`] runs the fuzzer binary on a host, uploaded by `scp` or deployed there beforehand.
+//! The `ssh` connection tunnels a port of the host back to the local broker, and the remote [`Launcher`](crate::events::Launcher)
+//! finds it in the [`LIBAFL_REMOTE_BROKER_ADDR`] `env` variable: its broker connects to the local broker,
+//! which then shows the stats of the remote clients along with the local ones.
+//! The output of each host is printed locally, prefixed by its name.
+//!
+//! This needs `ssh` and `scp` in the `PATH`, and non-interactive logins to the hosts, for example with keys.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::SocketAddr,
+    path::PathBuf,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread::JoinHandle,
+};
+
+use crate::Error;
+
+/// The `env` variable telling a [`Launcher`](crate::events::Launcher) the address of the broker to connect its own broker to
+pub const LIBAFL_REMOTE_BROKER_ADDR: &str = "LIBAFL_REMOTE_BROKER_ADDR";
+
+/// The default port, on the remote host, tunneled to the local broker
+pub const DEFAULT_TUNNEL_PORT: u16 = 13370;
+
+/// The binary to run on a [`RemoteNode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RemoteBinary {
+    /// Upload the local binary to the path on the host before starting it
+    Upload {
+        /// The local binary
+        local: PathBuf,
+        /// The path to upload it to, on the host
+        remote: String,
+    },
+    /// The binary is already deployed at this path on the host
+    Deployed(String),
+}
+
+impl RemoteBinary {
+    /// The path of the binary on the host
+    #[must_use]
+    pub fn remote_path(&self) -> &str {
+        match self {
+            Self::Upload { remote, .. } | Self::Deployed(remote) => remote,
+        }
+    }
+}
+
+/// A host to start clients on, over SSH, see the [module-level documentation](self)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteNode {
+    host: String,
+    ssh_port: Option<u16>,
+    ssh_options: Vec<String>,
+    binary: RemoteBinary,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    tunnel_port: u16,
+}
+
+impl RemoteNode {
+    /// Creates a new [`RemoteNode`] running `binary` on `host`, as given to `ssh`, such as `user@host`
+    pub fn new<H>(host: H, binary: RemoteBinary) -> Self
+    where
+        H: Into<String>,
+    {
+        Self {
+            host: host.into(),
+            ssh_port: None,
+            ssh_options: Vec::new(),
+            binary,
+            args: Vec::new(),
+            env: Vec::new(),
+            tunnel_port: DEFAULT_TUNNEL_PORT,
+        }
+    }
+
+    /// Connects to `ssh` on this port, instead of the default one
+    #[must_use]
+    pub fn ssh_port(mut self, port: u16) -> Self {
+        self.ssh_port = Some(port);
+        self
+    }
+
+    /// Passes the `option` to `ssh` and `scp`, as with `-o option`
+    #[must_use]
+    pub fn ssh_option<O>(mut self, option: O) -> Self
+    where
+        O: Into<String>,
+    {
+        self.ssh_options.push(option.into());
+        self
+    }
+
+    /// Runs the binary with these arguments, for example to choose the cores of the host
+    #[must_use]
+    pub fn args<I, A>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = A>,
+        A: Into<String>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Sets the `env` variable `key` to `value` for the binary
+    #[must_use]
+    pub fn env<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Tunnels this port of the host to the local broker, instead of [`DEFAULT_TUNNEL_PORT`]
+    #[must_use]
+    pub fn tunnel_port(mut self, port: u16) -> Self {
+        self.tunnel_port = port;
+        self
+    }
+
+    /// The host, as given to `ssh`
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The options shared by `ssh` and `scp`
+    fn common_options(&self, command: &mut Command) {
+        // Never hang on a password prompt
+        command.args(["-o", "BatchMode=yes"]);
+        for option in &self.ssh_options {
+            command.arg("-o").arg(option);
+        }
+    }
+
+    /// The shell command line starting the binary on the host
+    fn remote_command_line(&self) -> String {
+        let broker_addr = SocketAddr::from(([127, 0, 0, 1], self.tunnel_port));
+        let words = self
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}"))
+            .chain([
+                format!("{LIBAFL_REMOTE_BROKER_ADDR}={broker_addr}"),
+                self.binary.remote_path().to_string(),
+            ])
+            .chain(self.args.iter().cloned());
+        let mut line = String::from("env");
+        for word in words {
+            line.push(' ');
+            line.push_str(&shell_quote(&word));
+        }
+        line
+    }
+
+    /// Uploads the binary to the host, if it is not deployed there
+    pub fn upload(&self) -> Result<(), Error> {
+        let RemoteBinary::Upload { local, remote } = &self.binary else {
+            return Ok(());
+        };
+        let mut scp = Command::new("scp");
+        // Keep the mode, so that the binary stays executable
+        scp.arg("-p");
+        if let Some(port) = self.ssh_port {
+            scp.arg("-P").arg(port.to_string());
+        }
+        self.common_options(&mut scp);
+        let status = scp
+            .arg(local)
+            .arg(format!("{}:{remote}", self.host))
+            .stdin(Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(Error::unknown(format!(
+                "Uploading {} to {}:{remote} failed with {status}",
+                local.display(),
+                self.host
+            )));
+        }
+        Ok(())
+    }
+
+    /// Uploads the binary if needed, and starts it on the host, with its broker tunneled to the local `broker_port`
+    pub fn spawn(&self, broker_port: u16) -> Result<RemoteNodeProcess, Error> {
+        self.upload()?;
+        let mut ssh = Command::new("ssh");
+        // A terminal on the host, so that the binary is hung up on when the connection closes
+        ssh.arg("-tt");
+        ssh.args(["-o", "ExitOnForwardFailure=yes"]);
+        if let Some(port) = self.ssh_port {
+            ssh.arg("-p").arg(port.to_string());
+        }
+        self.common_options(&mut ssh);
+        ssh.arg("-R").arg(format!(
+            "127.0.0.1:{}:127.0.0.1:{broker_port}",
+            self.tunnel_port
+        ));
+        let mut child = ssh
+            .arg(&self.host)
+            .arg(self.remote_command_line())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        log::info!("Started {} on {}", self.binary.remote_path(), self.host);
+
+        let mut forwarders = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            forwarders.push(forward_output(self.host.clone(), stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forwarders.push(forward_output(self.host.clone(), stderr));
+        }
+        Ok(RemoteNodeProcess {
+            host: self.host.clone(),
+            child,
+            forwarders,
+        })
+    }
+}
+
+/// The `ssh` process of a running [`RemoteNode`]
+#[derive(Debug)]
+pub struct RemoteNodeProcess {
+    host: String,
+    child: Child,
+    forwarders: Vec<JoinHandle<()>>,
+}
+
+impl RemoteNodeProcess {
+    /// The host this node runs on
+    #[must_use]
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Closes the connection, which stops the binary on the host
+    pub fn kill(&mut self) -> Result<(), Error> {
+        if self.child.try_wait()?.is_none() {
+            self.child.kill()?;
+        }
+        self.wait()?;
+        Ok(())
+    }
+
+    /// Waits for the binary on the host to exit, and for its output to be printed
+    pub fn wait(&mut self) -> Result<ExitStatus, Error> {
+        let status = self.child.wait()?;
+        for forwarder in self.forwarders.drain(..) {
+            let _ = forwarder.join();
+        }
+        Ok(status)
+    }
+}
+
+/// Prints each line of `output`, prefixed by the `host`
+fn forward_output<R>(host: String, output: R) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+{
+    std::thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            // Write whole lines, so that the hosts do not interleave
+            let line = format!("[{host}] {}\n", line.trim_end_matches('\r'));
+            if std::io::stdout().lock().write_all(line.as_bytes()).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Quotes `arg` for a POSIX shell
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// Reads the address of the broker to connect to from [`LIBAFL_REMOTE_BROKER_ADDR`], when started by a [`RemoteNode`]
+pub fn remote_broker_addr_from_env() -> Result<Option<SocketAddr>, Error> {
+    match std::env::var(LIBAFL_REMOTE_BROKER_ADDR) {
+        Ok(addr) => Ok(Some(addr.parse().map_err(|_| {
+            Error::illegal_argument(format!(
+                "Invalid {LIBAFL_REMOTE_BROKER_ADDR} address: {addr}"
+            ))
+        })?)),
+        Err(_) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RemoteBinary, RemoteNode};
+
+    #[test]
+    fn test_remote_command_line() {
+        let node = RemoteNode::new("fuzz@host", RemoteBinary::Deployed("/opt/fuzzer".into()))
+            .args(["--cores", "0-15", "it's"])
+            .env("RUST_LOG", "info")
+            .tunnel_port(4000);
+        assert_eq!(
+            node.remote_command_line(),
+            "env 'RUST_LOG=info' 'LIBAFL_REMOTE_BROKER_ADDR=127.0.0.1:4000' '/opt/fuzzer' '--cores' '0-15' 'it'\\''s'"
+        );
+    }
+}
@@ -0,0 +1,385 @@
+//! A minimal [`EventManager`] for `no_std` targets, sending events through a fixed-size ring buffer.
+//!
+//! The [`RingEventManager`] serializes every fired [`Event`] straight into a ring buffer allocated with the manager,
+//! as a [COBS](https://en.wikipedia.org/wiki/Consistent_Overhead_Byte_Stuffing) frame ending in a `0` byte,
+//! and drains the buffer into an [`EventTransport`], like a UART or a semihosting channel, as fast as it accepts bytes.
+//! The host splits the received bytes at the `0` bytes and decodes the frames with [`decode_event`].
+
+use alloc::vec::Vec;
+use core::{
+    marker::PhantomData,
+    ops::{Index, IndexMut},
+};
+
+use postcard::ser_flavors::{Cobs, Flavor};
+use serde::Serialize;
+
+use crate::{
+    events::{
+        Event, EventFirer, EventManager, EventManagerId, EventProcessor, EventRestarter,
+        HasEventManagerId, ProgressReporter,
+    },
+    inputs::{Input, UsesInput},
+    observers::ObserversTuple,
+    state::{HasExecutions, HasLastReportTime, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// Where a [`RingEventManager`] sends the serialized events to
+pub trait EventTransport {
+    /// Writes a prefix of `buf`, as much as the transport accepts without blocking,
+    /// and returns the number of bytes written.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error>;
+}
+
+/// The bytes queued for the transport, in a buffer of `N` bytes
+#[derive(Debug)]
+struct EventRing<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> EventRing<N> {
+    fn new() -> Self {
+        Self {
+            buf: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Appends `value` as a COBS frame, returns `false`, leaving the ring as it was, if the frame does not fit
+    fn push<T>(&mut self, value: &T) -> Result<bool, Error>
+    where
+        T: Serialize,
+    {
+        let written = Cobs::try_new(RingWriter {
+            ring: self,
+            written: 0,
+        })
+        .and_then(|flavor| postcard::serialize_with_flavor(value, flavor));
+        match written {
+            Ok(written) => {
+                self.len += written;
+                Ok(true)
+            }
+            Err(postcard::Error::SerializeBufferFull) => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the queued bytes to `transport`, as long as it accepts them, and returns the number of bytes written
+    fn drain_into<T>(&mut self, transport: &mut T) -> Result<usize, Error>
+    where
+        T: EventTransport,
+    {
+        let mut drained = 0;
+        while self.len > 0 {
+            let end = (self.head + self.len).min(N);
+            let written = transport
+                .write(&self.buf[self.head..end])?
+                .min(end - self.head);
+            if written == 0 {
+                break;
+            }
+            self.head = (self.head + written) % N;
+            self.len -= written;
+            drained += written;
+        }
+        Ok(drained)
+    }
+}
+
+/// A postcard [`Flavor`] writing a frame behind the queued bytes of an [`EventRing`].
+///
+/// The frame only becomes part of the queue once it is complete, in [`EventRing::push`].
+struct RingWriter<'a, const N: usize> {
+    ring: &'a mut EventRing<N>,
+    written: usize,
+}
+
+impl<const N: usize> RingWriter<'_, N> {
+    /// The position in the ring of the `idx`th byte of the frame
+    fn pos(&self, idx: usize) -> usize {
+        (self.ring.head + self.ring.len + idx) % N
+    }
+}
+
+impl<const N: usize> Flavor for RingWriter<'_, N> {
+    type Output = usize;
+
+    fn try_push(&mut self, data: u8) -> postcard::Result<()> {
+        if self.ring.len + self.written >= N {
+            return Err(postcard::Error::SerializeBufferFull);
+        }
+        let pos = self.pos(self.written);
+        self.ring.buf[pos] = data;
+        self.written += 1;
+        Ok(())
+    }
+
+    fn finalize(self) -> postcard::Result<usize> {
+        Ok(self.written)
+    }
+}
+
+impl<const N: usize> Index<usize> for RingWriter<'_, N> {
+    type Output = u8;
+
+    fn index(&self, idx: usize) -> &u8 {
+        &self.ring.buf[self.pos(idx)]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for RingWriter<'_, N> {
+    fn index_mut(&mut self, idx: usize) -> &mut u8 {
+        let pos = self.pos(idx);
+        &mut self.ring.buf[pos]
+    }
+}
+
+/// Decodes an event sent by a [`RingEventManager`], from a frame without its trailing `0` byte.
+///
+/// The frame is decoded in place, its contents are garbage afterwards.
+pub fn decode_event<I>(frame: &mut [u8]) -> Result<Event<I>, Error>
+where
+    I: Input,
+{
+    Ok(postcard::from_bytes_cobs(frame)?)
+}
+
+/// An [`EventManager`] sending the fired events through an [`EventTransport`], without allocating.
+///
+/// The events are queued in a ring buffer of `N` bytes. If an event does not fit,
+/// the manager waits for the transport to make room, and drops the event once the transport stalls.
+/// The observers of new testcases are not sent along, to save their serialization.
+/// Events from other nodes are not received, so [`EventProcessor::process`] only flushes the queue.
+#[derive(Debug)]
+pub struct RingEventManager<T, S, const N: usize> {
+    transport: T,
+    ring: EventRing<N>,
+    /// The number of events dropped because the transport did not keep up
+    dropped: u64,
+    phantom: PhantomData<S>,
+}
+
+impl<T, S, const N: usize> RingEventManager<T, S, N>
+where
+    T: EventTransport,
+{
+    /// Creates a new [`RingEventManager`], sending the events through `transport`
+    #[must_use]
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            ring: EventRing::new(),
+            dropped: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The transport the events are sent through
+    #[must_use]
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// The transport the events are sent through (mutable)
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    /// The number of events dropped so far, because the transport did not keep up
+    #[must_use]
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped
+    }
+
+    /// The number of bytes waiting for the transport
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.ring.len
+    }
+
+    /// Writes the queued events to the transport, as far as it accepts them
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.ring.drain_into(&mut self.transport)?;
+        Ok(())
+    }
+}
+
+impl<T, S, const N: usize> UsesState for RingEventManager<T, S, N>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<T, S, const N: usize> EventFirer for RingEventManager<T, S, N>
+where
+    T: EventTransport,
+    S: State,
+{
+    fn should_send(&self) -> bool {
+        true
+    }
+
+    fn fire(
+        &mut self,
+        _state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        while !self.ring.push(&event)? {
+            // Make room for the event, or give up once the transport takes no more bytes
+            if self.ring.drain_into(&mut self.transport)? == 0 {
+                self.dropped += 1;
+                break;
+            }
+        }
+        self.flush()
+    }
+
+    /// Observers are not sent, to keep the manager free of allocations
+    fn serialize_observers<OT>(&mut self, _observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<<Self as UsesInput>::Input, Self::State> + Serialize,
+    {
+        Ok(None)
+    }
+}
+
+impl<T, S, const N: usize> EventRestarter for RingEventManager<T, S, N>
+where
+    T: EventTransport,
+    S: State,
+{
+}
+
+impl<E, T, S, Z, const N: usize> EventProcessor<E, Z> for RingEventManager<T, S, N>
+where
+    T: EventTransport,
+    S: State,
+{
+    fn process(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut S,
+        _executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.flush()?;
+        Ok(0)
+    }
+
+    fn on_shutdown(&mut self) -> Result<(), Error> {
+        self.flush()
+    }
+}
+
+impl<E, T, S, Z, const N: usize> EventManager<E, Z> for RingEventManager<T, S, N>
+where
+    T: EventTransport,
+    S: State + HasExecutions + HasLastReportTime + HasMetadata,
+{
+}
+
+impl<T, S, const N: usize> ProgressReporter for RingEventManager<T, S, N>
+where
+    T: EventTransport,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime,
+{
+}
+
+impl<T, S, const N: usize> HasEventManagerId for RingEventManager<T, S, N> {
+    fn mgr_id(&self) -> EventManagerId {
+        EventManagerId(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::{marker::PhantomData, time::Duration};
+
+    use super::{decode_event, EventTransport, RingEventManager};
+    use crate::{
+        events::{Event, EventFirer, LogSeverity},
+        inputs::BytesInput,
+        state::NopState,
+        Error,
+    };
+
+    /// Accepts up to `chunk` bytes per write, as long as `open` is set
+    struct SlowTransport {
+        received: Vec<u8>,
+        chunk: usize,
+        open: bool,
+    }
+
+    impl EventTransport for SlowTransport {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            if !self.open {
+                return Ok(0);
+            }
+            let len = buf.len().min(self.chunk);
+            self.received.extend_from_slice(&buf[..len]);
+            Ok(len)
+        }
+    }
+
+    fn log(message: &'static str) -> Event<BytesInput> {
+        Event::Log {
+            severity_level: LogSeverity::Info,
+            message: message.into(),
+            phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_ring_event_manager() {
+        let mut state = NopState::<BytesInput>::new();
+        let transport = SlowTransport {
+            received: Vec::new(),
+            chunk: 3,
+            open: false,
+        };
+        let mut mgr = RingEventManager::<_, _, 32>::new(transport);
+
+        // With the transport stalled, the second event no longer fits
+        mgr.fire(&mut state, log("first event")).unwrap();
+        mgr.fire(&mut state, log("a second, longer event")).unwrap();
+        assert_eq!(mgr.dropped_events(), 1);
+
+        mgr.transport_mut().open = true;
+        mgr.fire(&mut state, log("a second, longer event")).unwrap();
+        mgr.fire(
+            &mut state,
+            Event::UpdateExecStats {
+                time: Duration::from_secs(1),
+                executions: 1234,
+                phantom: PhantomData,
+            },
+        )
+        .unwrap();
+        assert_eq!(mgr.queued(), 0);
+
+        let mut received = mgr.transport_mut().received.clone();
+        let events: Vec<Event<BytesInput>> = received
+            .split_mut(|byte| *byte == 0)
+            .filter(|frame| !frame.is_empty())
+            .map(|frame| decode_event(frame).unwrap())
+            .collect();
+        assert_eq!(events.len(), 3);
+        let Event::Log { message, .. } = &events[1] else {
+            panic!("Expected a log event, got {}", events[1].name());
+        };
+        assert_eq!(message, "a second, longer event");
+        assert!(matches!(
+            events[2],
+            Event::UpdateExecStats {
+                executions: 1234,
+                ..
+            }
+        ));
+    }
+}
@@ -268,7 +268,7 @@ where
                 Ok(BrokerEventResult::Handled)
             }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
-            Event::Stop => Ok(BrokerEventResult::Forward),
+            Event::Stop | Event::SetStopPolicy { .. } => Ok(BrokerEventResult::Forward),
         }
     }
 
@@ -286,6 +286,8 @@ where
                 state.request_stop();
                 Ok(())
             }
+            // The only client already set the policy when broadcasting it
+            Event::SetStopPolicy { .. } => Ok(()),
             _ => Err(Error::unknown(format!(
                 "Received illegal message that message should not have arrived: {event:?}."
             ))),
@@ -0,0 +1,605 @@
+//! An async client of the [`crate::events::tcp::TcpEventBroker`], doing all network IO on `tokio`.
+//!
+//! The [`AsyncTcpEventManager`] speaks the same protocol as the [`crate::events::tcp::TcpEventManager`],
+//! but hands its events to an [`EventPump`] instead of writing to the socket itself.
+//! Sending never waits for the broker, unless the queue of the pump is full of testcases.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    env,
+    net::{SocketAddr, ToSocketAddrs},
+    sync::mpsc as std_mpsc,
+};
+
+#[cfg(feature = "tcp_compression")]
+use libafl_bolts::compress::GzipCompressor;
+use libafl_bolts::{current_time, ClientId};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+use super::{
+    pump::{EventPump, DEFAULT_PUMP_CAPACITY},
+    tcp::{AsyncStream, TcpSecurity},
+    CustomBufEventResult, CustomBufHandlerFn,
+};
+use crate::{
+    events::{
+        Event, EventConfig, EventFirer, EventManager, EventManagerHooksTuple, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
+    },
+    executors::{Executor, HasObservers},
+    fuzzer::{EvaluatorObservers, ExecutionProcessor},
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasExecutions, HasImported, HasLastReportTime, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// Connects to the broker at `addrs` and runs the client handshake.
+/// Returns the connection and the [`ClientId`] the broker assigned to us.
+async fn connect_to_broker(
+    addrs: &[SocketAddr],
+    security: &TcpSecurity,
+    client_id: ClientId,
+) -> Result<(Box<dyn AsyncStream>, ClientId), Error> {
+    let socket = TcpStream::connect(addrs).await?;
+    let mut stream = security.connect_async(socket).await?;
+
+    let mut our_client_id_buf = client_id.0.to_le_bytes();
+    stream
+        .write_all(&our_client_id_buf)
+        .await
+        .map_err(|err| Error::illegal_state(format!("Cannot write to the broker: {err}")))?;
+    stream
+        .read_exact(&mut our_client_id_buf)
+        .await
+        .map_err(|err| {
+            Error::illegal_state(format!(
+                "Cannot read from the broker, did it reject our auth token or TLS settings? {err}"
+            ))
+        })?;
+    Ok((stream, ClientId(u32::from_le_bytes(our_client_id_buf))))
+}
+
+/// Writes the queued frames to the broker, and hands the events of other clients to the fuzzer,
+/// until the manager closes the queue, or the connection breaks.
+async fn pump_events(
+    stream: Box<dyn AsyncStream>,
+    mut outgoing: mpsc::Receiver<Vec<u8>>,
+    incoming: std_mpsc::Sender<(ClientId, Vec<u8>)>,
+) -> Result<(), Error> {
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let write = async {
+        while let Some(frame) = outgoing.recv().await {
+            writer.write_all(&frame).await?;
+        }
+        writer.flush().await?;
+        Ok::<_, Error>(())
+    };
+    let read = async {
+        loop {
+            let mut len_buf = [0_u8; 4];
+            reader.read_exact(&mut len_buf).await?;
+            let len = u32::from_le_bytes(len_buf);
+            let mut buf = vec![0_u8; 4_usize + len as usize];
+            reader.read_exact(&mut buf).await?;
+
+            let other_client_id = ClientId(u32::from_le_bytes(buf[..4].try_into().unwrap()));
+            buf.drain(..4);
+            if incoming.send((other_client_id, buf)).is_err() {
+                // The manager is gone
+                return Ok(());
+            }
+        }
+    };
+    tokio::select! {
+        res = write => res,
+        res = read => res,
+    }
+}
+
+/// An [`EventManager`] that exchanges all events with other fuzzers through a
+/// [`crate::events::tcp::TcpEventBroker`], doing the network IO on a background `tokio` runtime.
+///
+/// If the broker can't keep up, stats and log events are dropped, see [`EventPump`].
+pub struct AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// We send message every `throttle` second
+    throttle: Option<Duration>,
+    /// When we sent the last message
+    last_sent: Duration,
+    hooks: EMH,
+    /// The frames to send to the broker, and the events of other clients
+    pump: EventPump<Vec<u8>, (ClientId, Vec<u8>)>,
+    /// Our `ClientId`, assigned by the broker
+    client_id: ClientId,
+    /// The custom buf handler
+    custom_buf_handlers: Vec<Box<CustomBufHandlerFn<S>>>,
+    #[cfg(feature = "tcp_compression")]
+    compressor: GzipCompressor,
+    /// The configuration defines this specific fuzzer.
+    /// A node will not re-use the observer values sent over TCP
+    /// from nodes with other configurations.
+    configuration: EventConfig,
+    phantom: PhantomData<S>,
+}
+
+impl<S> AsyncTcpEventManager<(), S>
+where
+    S: State,
+{
+    /// Create a builder for [`AsyncTcpEventManager`]
+    #[must_use]
+    pub fn builder() -> AsyncTcpEventManagerBuilder<(), S> {
+        AsyncTcpEventManagerBuilder::new()
+    }
+}
+
+/// Builder for `AsyncTcpEventManager`
+#[derive(Debug, Clone)]
+pub struct AsyncTcpEventManagerBuilder<EMH, S> {
+    throttle: Option<Duration>,
+    hooks: EMH,
+    security: TcpSecurity,
+    capacity: usize,
+    phantom: PhantomData<S>,
+}
+
+impl<S> Default for AsyncTcpEventManagerBuilder<(), S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> AsyncTcpEventManagerBuilder<(), S> {
+    /// Create a new `AsyncTcpEventManagerBuilder`
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            throttle: None,
+            hooks: (),
+            security: TcpSecurity::default(),
+            capacity: DEFAULT_PUMP_CAPACITY,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Set the hooks
+    #[must_use]
+    pub fn hooks<EMH>(self, hooks: EMH) -> AsyncTcpEventManagerBuilder<EMH, S> {
+        AsyncTcpEventManagerBuilder {
+            throttle: self.throttle,
+            hooks,
+            security: self.security,
+            capacity: self.capacity,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<EMH, S> AsyncTcpEventManagerBuilder<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// Set the throttle
+    #[must_use]
+    pub fn throttle(mut self, throttle: Duration) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Set the authentication and encryption settings, matching the ones of the broker
+    #[must_use]
+    pub fn security(mut self, security: TcpSecurity) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Set how many events are queued for the broker, [`DEFAULT_PUMP_CAPACITY`] by default
+    #[must_use]
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Connects to the broker at `addr`, asking for the given `client_id`
+    pub fn build_from_client<A: ToSocketAddrs>(
+        self,
+        addr: &A,
+        client_id: ClientId,
+        configuration: EventConfig,
+    ) -> Result<AsyncTcpEventManager<EMH, S>, Error> {
+        let addrs: Vec<SocketAddr> = addr.to_socket_addrs()?.collect();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let (stream, client_id) =
+            runtime.block_on(connect_to_broker(&addrs, &self.security, client_id))?;
+        log::info!("Our client id: {client_id:?}");
+
+        let pump = EventPump::with_runtime(runtime, self.capacity, |outgoing, incoming| {
+            pump_events(stream, outgoing, incoming)
+        })?;
+
+        Ok(AsyncTcpEventManager {
+            throttle: self.throttle,
+            last_sent: Duration::from_secs(0),
+            hooks: self.hooks,
+            pump,
+            client_id,
+            custom_buf_handlers: vec![],
+            #[cfg(feature = "tcp_compression")]
+            compressor: GzipCompressor::new(),
+            configuration,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Connects to the broker on a local port, asking for the given `client_id`
+    pub fn build_on_port(
+        self,
+        port: u16,
+        client_id: ClientId,
+        configuration: EventConfig,
+    ) -> Result<AsyncTcpEventManager<EMH, S>, Error> {
+        Self::build_from_client(self, &("127.0.0.1", port), client_id, configuration)
+    }
+
+    /// Connects to the broker at `addr`, as the client whose id is in the env var `env_name`
+    pub fn build_existing_from_env<A: ToSocketAddrs>(
+        self,
+        addr: &A,
+        env_name: &str,
+        configuration: EventConfig,
+    ) -> Result<AsyncTcpEventManager<EMH, S>, Error> {
+        let this_id = ClientId(str::parse::<u32>(&env::var(env_name)?)?);
+        Self::build_from_client(self, addr, this_id, configuration)
+    }
+}
+
+impl<EMH, S> core::fmt::Debug for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AsyncTcpEventManager")
+            .field("client_id", &self.client_id)
+            .field("throttle", &self.throttle)
+            .field("pump", &self.pump)
+            .field("configuration", &self.configuration)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<EMH, S> AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    /// Write the client id for a client [`EventManager`] to env vars
+    pub fn to_env(&self, env_name: &str) {
+        env::set_var(env_name, format!("{}", self.client_id.0));
+    }
+
+    /// The number of stats and log events dropped so far, because the broker could not keep up
+    #[must_use]
+    pub fn dropped_events(&self) -> u64 {
+        self.pump.dropped()
+    }
+}
+
+impl<EMH, S> AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasImported,
+{
+    // Handle arriving events in the client
+    fn handle_in_client<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        client_id: ClientId,
+        event: Event<S::Input>,
+    ) -> Result<(), Error>
+    where
+        E: Executor<Self, Z, State = S> + HasObservers,
+        E::Observers: Serialize + ObserversTuple<S::Input, S>,
+        for<'a> E::Observers: Deserialize<'a>,
+        Z: ExecutionProcessor<Self, E::Observers, State = S>
+            + EvaluatorObservers<Self, E::Observers>,
+    {
+        if !self.hooks.pre_exec_all(state, client_id, &event)? {
+            return Ok(());
+        }
+        match event {
+            Event::NewTestcase {
+                input,
+                client_config,
+                exit_kind,
+                observers_buf,
+                forward_id,
+                ..
+            } => {
+                log::info!("Received new Testcase from {client_id:?} ({client_config:?}, forward {forward_id:?})");
+
+                let res = match observers_buf {
+                    Some(observers_buf) if client_config.match_with(&self.configuration) => {
+                        let observers: E::Observers = postcard::from_bytes(&observers_buf)?;
+                        #[cfg(feature = "scalability_introspection")]
+                        {
+                            state.scalability_monitor_mut().testcase_with_observers += 1;
+                        }
+                        fuzzer
+                            .evaluate_execution(state, self, input, &observers, &exit_kind, false)?
+                    }
+                    _ => {
+                        #[cfg(feature = "scalability_introspection")]
+                        {
+                            state.scalability_monitor_mut().testcase_without_observers += 1;
+                        }
+                        fuzzer.evaluate_input_with_observers::<E>(
+                            state, executor, self, input, false,
+                        )?
+                    }
+                };
+                if let Some(item) = res.1 {
+                    *state.imported_mut() += 1;
+                    log::info!("Added received Testcase as item #{item}");
+                }
+            }
+            Event::CustomBuf { tag, buf } => {
+                for handler in &mut self.custom_buf_handlers {
+                    if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
+                        break;
+                    }
+                }
+            }
+            Event::Stop => {
+                state.request_stop();
+            }
+            Event::SetStopPolicy { policy } => {
+                state.add_metadata(policy);
+            }
+            _ => {
+                return Err(Error::unknown(format!(
+                    "Received illegal message that message should not have arrived: {:?}.",
+                    event.name()
+                )))
+            }
+        }
+        self.hooks.post_exec_all(state, client_id)?;
+        Ok(())
+    }
+}
+
+impl<EMH, S> UsesState for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    type State = S;
+}
+
+impl<EMH, S> EventFirer for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn should_send(&self) -> bool {
+        if let Some(throttle) = self.throttle {
+            current_time().saturating_sub(self.last_sent) > throttle
+        } else {
+            true
+        }
+    }
+
+    fn fire(
+        &mut self,
+        _state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        // The next stats update or log message replaces a dropped one
+        #[cfg(feature = "introspection")]
+        let droppable = matches!(
+            event,
+            Event::UpdateExecStats { .. }
+                | Event::UpdateUserStats { .. }
+                | Event::UpdatePerfMonitor { .. }
+                | Event::Log { .. }
+        );
+        #[cfg(not(feature = "introspection"))]
+        let droppable = matches!(
+            event,
+            Event::UpdateExecStats { .. } | Event::UpdateUserStats { .. } | Event::Log { .. }
+        );
+
+        let serialized = postcard::to_allocvec(&event)?;
+        #[cfg(feature = "tcp_compression")]
+        let serialized = self.compressor.compress(&serialized);
+
+        let size = u32::try_from(serialized.len())
+            .map_err(|_| Error::illegal_argument("Event too large"))?;
+        let mut frame = Vec::with_capacity(8 + serialized.len());
+        frame.extend_from_slice(&size.to_le_bytes());
+        frame.extend_from_slice(&self.client_id.0.to_le_bytes());
+        frame.extend_from_slice(&serialized);
+        self.pump.send(frame, droppable)?;
+
+        self.last_sent = current_time();
+        Ok(())
+    }
+
+    fn configuration(&self) -> EventConfig {
+        self.configuration
+    }
+}
+
+impl<EMH, S> EventRestarter for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+}
+
+impl<E, EMH, S, Z> EventProcessor<E, Z> for AsyncTcpEventManager<EMH, S>
+where
+    E: HasObservers + Executor<Self, Z, State = S>,
+    E::Observers: Serialize + ObserversTuple<S::Input, S>,
+    for<'a> E::Observers: Deserialize<'a>,
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasImported,
+    Z: EvaluatorObservers<Self, E::Observers, State = S>
+        + ExecutionProcessor<Self, E::Observers, State = S>,
+{
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        let mut count = 0;
+        while let Some((other_client_id, buf)) = self.pump.try_recv()? {
+            if other_client_id == self.client_id {
+                return Err(Error::illegal_state(
+                    "Own ID should never have been sent by the broker",
+                ));
+            }
+            #[cfg(feature = "tcp_compression")]
+            let buf = self.compressor.decompress(&buf)?;
+            let event = postcard::from_bytes(&buf)?;
+            self.handle_in_client(fuzzer, executor, state, other_client_id, event)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Sends the queued events to the broker before shutting down
+    fn on_shutdown(&mut self) -> Result<(), Error> {
+        self.pump.close()
+    }
+}
+
+impl<E, EMH, S, Z> EventManager<E, Z> for AsyncTcpEventManager<EMH, S>
+where
+    E: HasObservers + Executor<Self, Z, State = S>,
+    E::Observers: Serialize + ObserversTuple<S::Input, S>,
+    for<'a> E::Observers: Deserialize<'a>,
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime + HasImported,
+    Z: EvaluatorObservers<Self, E::Observers, State = S>
+        + ExecutionProcessor<Self, E::Observers, State = S>,
+{
+}
+
+impl<EMH, S> HasCustomBufHandlers for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<dyn FnMut(&mut S, &str, &[u8]) -> Result<CustomBufEventResult, Error>>,
+    ) {
+        self.custom_buf_handlers.push(handler);
+    }
+}
+
+impl<EMH, S> ProgressReporter for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime,
+{
+}
+
+impl<EMH, S> HasEventManagerId for AsyncTcpEventManager<EMH, S>
+where
+    EMH: EventManagerHooksTuple<S>,
+    S: State,
+{
+    fn mgr_id(&self) -> EventManagerId {
+        EventManagerId(self.client_id.0 as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{marker::PhantomData, time::Duration};
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::AsyncTcpEventManager;
+    use crate::{
+        events::{Event, EventConfig, EventFirer, LogSeverity},
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    #[test]
+    fn test_async_tcp_event_manager() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let addr = listener.local_addr().unwrap();
+        // A broker forwarding the first event back to the client, as if another client sent it
+        let broker = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut buf = [0; 4];
+            socket.read_exact(&mut buf).unwrap();
+            socket.write_all(&7_u32.to_le_bytes()).unwrap();
+
+            socket.read_exact(&mut buf).unwrap();
+            let len = u32::from_le_bytes(buf);
+            socket.read_exact(&mut buf).unwrap();
+            assert_eq!(u32::from_le_bytes(buf), 7);
+            let mut payload = vec![0; len as usize];
+            socket.read_exact(&mut payload).unwrap();
+
+            socket.write_all(&len.to_le_bytes()).unwrap();
+            socket.write_all(&3_u32.to_le_bytes()).unwrap();
+            socket.write_all(&payload).unwrap();
+        });
+
+        let mut mgr = AsyncTcpEventManager::<(), NopState<BytesInput>>::builder()
+            .build_from_client(&addr, ClientId(0), EventConfig::AlwaysUnique)
+            .unwrap();
+        assert_eq!(mgr.client_id, ClientId(7));
+        mgr.fire(
+            &mut NopState::new(),
+            Event::Log {
+                severity_level: LogSeverity::Info,
+                message: "hello".into(),
+                phantom: PhantomData,
+            },
+        )
+        .unwrap();
+        broker.join().unwrap();
+
+        let (client_id, buf) = loop {
+            match mgr.pump.try_recv().unwrap() {
+                Some(received) => break received,
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        assert_eq!(client_id, ClientId(3));
+        #[cfg(feature = "tcp_compression")]
+        let buf = mgr.compressor.decompress(&buf).unwrap();
+        assert!(matches!(
+            postcard::from_bytes::<Event<BytesInput>>(&buf).unwrap(),
+            Event::Log { message, .. } if message == "hello"
+        ));
+    }
+}
@@ -0,0 +1,535 @@
+//! The [`AdbExecutor`] runs a native harness on an Android device, through `adb`.
+//!
+//! For each run, the testcase is pushed to the device and the harness is started with `adb shell`,
+//! wrapped in the `timeout` of the device's toybox.
+//! The harness writes its coverage map to a file on the device, which is pulled back after the run.
+//! A harness killed by a signal is a crash. For other failing runs, the crash buffer of logcat
+//! and, optionally, the tombstones are checked for a native crash of the harness.
+//! If `adb` itself hangs or loses the device, the device is rebooted, see [`AdbConfig::reboot_on_wedge`].
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug, Formatter, Write},
+    marker::PhantomData,
+    ops::IndexMut,
+    time::Duration,
+};
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::{
+    fs::{get_unique_std_input_file, InputFile},
+    tuples::{Handle, MatchName, RefIndexable},
+    AsSlice,
+};
+use typed_builder::TypedBuilder;
+use wait_timeout::ChildExt;
+
+use super::HasTimeout;
+use crate::{
+    executors::{command::INPUT_FILE_PLACEHOLDER, Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{MapObserver, ObserversTuple},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The marker the harness' exit code is echoed after
+const EXIT_MARKER: &str = "libafl_exit:";
+
+/// The exit code of the device's `timeout`, when it killed the harness
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// An Android device, reached through `adb`
+#[derive(Debug, Clone)]
+pub struct AdbDevice {
+    adb: PathBuf,
+    serial: Option<String>,
+}
+
+impl AdbDevice {
+    /// The device with the given `serial`, or the only connected device for `None`
+    #[must_use]
+    pub fn new(serial: Option<String>) -> Self {
+        Self {
+            adb: PathBuf::from("adb"),
+            serial,
+        }
+    }
+
+    /// Picks the first connected device that is online
+    pub fn first() -> Result<Self, Error> {
+        let serial = Self::devices(Path::new("adb"))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::illegal_state("No Android device is connected"))?;
+        Ok(Self::new(Some(serial)))
+    }
+
+    /// The serials of all connected devices that are online, using the `adb` binary at the given path
+    pub fn devices(adb: &Path) -> Result<Vec<String>, Error> {
+        let output = Command::new(adb).arg("devices").output()?;
+        Ok(parse_devices(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Uses the `adb` binary at the given path, instead of the one in `PATH`
+    #[must_use]
+    pub fn with_adb<P>(mut self, adb: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.adb = adb.into();
+        self
+    }
+
+    /// The serial of this device, if set
+    #[must_use]
+    pub fn serial(&self) -> Option<&str> {
+        self.serial.as_deref()
+    }
+
+    /// An `adb` command for this device
+    #[must_use]
+    pub fn command(&self) -> Command {
+        let mut command = Command::new(&self.adb);
+        if let Some(serial) = &self.serial {
+            command.arg("-s").arg(serial);
+        }
+        command
+    }
+
+    /// Runs the shell `command` on the device, and returns its output
+    pub fn shell(&self, command: &str) -> Result<Output, Error> {
+        Ok(self
+            .command()
+            .arg("shell")
+            .arg(command)
+            .stdin(Stdio::null())
+            .output()?)
+    }
+
+    /// Pushes the `local` file to `remote` on the device
+    pub fn push<P>(&self, local: P, remote: &str) -> Result<(), Error>
+    where
+        P: AsRef<OsStr>,
+    {
+        let output = self
+            .command()
+            .arg("push")
+            .arg(local)
+            .arg(remote)
+            .stdin(Stdio::null())
+            .output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(Error::illegal_state(format!(
+                "adb push to {remote} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )))
+        }
+    }
+
+    /// Reads the file at `remote` on the device
+    pub fn read_file(&self, remote: &str) -> Result<Vec<u8>, Error> {
+        let output = self
+            .command()
+            .arg("exec-out")
+            .arg("cat")
+            .arg(remote)
+            .stdin(Stdio::null())
+            .output()?;
+        Ok(output.stdout)
+    }
+
+    /// Reboots the device, and waits until it finished booting, or `timeout` passed
+    pub fn reboot(&self, timeout: Duration) -> Result<(), Error> {
+        log::warn!("Rebooting the Android device {:?}", self.serial);
+        self.command().arg("reboot").stdin(Stdio::null()).output()?;
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            thread::sleep(Duration::from_secs(1));
+            if let Ok(output) = self.shell("getprop sys.boot_completed") {
+                if String::from_utf8_lossy(&output.stdout).trim() == "1" {
+                    return Ok(());
+                }
+            }
+        }
+        Err(Error::illegal_state(format!(
+            "The Android device {:?} did not boot within {timeout:?}",
+            self.serial
+        )))
+    }
+}
+
+/// The configuration of an [`AdbExecutor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct AdbConfig {
+    /// The path of the harness on the device
+    #[builder(setter(into))]
+    pub harness: String,
+    /// The arguments of the harness. Each [`INPUT_FILE_PLACEHOLDER`] (`@@`) is replaced with the testcase path.
+    #[builder(default)]
+    pub args: Vec<String>,
+    /// The directory on the device for the testcase and the coverage map. Defaults to `/data/local/tmp/libafl`.
+    #[builder(default = "/data/local/tmp/libafl".into(), setter(into))]
+    pub remote_dir: String,
+    /// The file on the device the harness writes its coverage map to, relative to the `remote_dir`.
+    /// Defaults to `coverage.map`. Its path is passed to the harness in `LIBAFL_COVERAGE_FILE`.
+    #[builder(default = "coverage.map".into(), setter(into))]
+    pub coverage_file: String,
+    /// The timeout of a single run on the device. Defaults to 5s.
+    #[builder(default = Duration::from_secs(5))]
+    pub timeout: Duration,
+    /// Check the crash buffer of logcat after failing runs. Defaults to `true`.
+    #[builder(default = true)]
+    pub logcat: bool,
+    /// Check for new tombstones after failing runs. This needs a rooted device. Defaults to `false`.
+    #[builder(default)]
+    pub tombstones: bool,
+    /// Reboot the device when `adb` hangs or loses the device. Defaults to `true`.
+    #[builder(default = true)]
+    pub reboot_on_wedge: bool,
+    /// How long to wait for the device to boot. Defaults to 3 minutes.
+    #[builder(default = Duration::from_mins(3))]
+    pub boot_timeout: Duration,
+}
+
+/// An executor running a native harness on an Android device.
+///
+/// The coverage map written by the harness is copied into the map observer `C`.
+pub struct AdbExecutor<C, OT, S> {
+    device: AdbDevice,
+    config: AdbConfig,
+    map_observer: Handle<C>,
+    observers: OT,
+    input_file: InputFile,
+    /// The tombstones seen so far
+    tombstones: Vec<String>,
+    last_tombstone: Option<String>,
+    phantom: PhantomData<S>,
+}
+
+impl<C, OT, S> Debug for AdbExecutor<C, OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AdbExecutor")
+            .field("device", &self.device)
+            .field("config", &self.config)
+            .field("map_observer", &self.map_observer)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, OT, S> AdbExecutor<C, OT, S> {
+    /// Create a new [`AdbExecutor`], preparing the `remote_dir` on the `device`
+    pub fn new(
+        device: AdbDevice,
+        config: AdbConfig,
+        map_observer: Handle<C>,
+        observers: OT,
+    ) -> Result<Self, Error> {
+        let mut executor = Self {
+            device,
+            config,
+            map_observer,
+            observers,
+            input_file: InputFile::create(get_unique_std_input_file())?,
+            tombstones: Vec::new(),
+            last_tombstone: None,
+            phantom: PhantomData,
+        };
+        executor.prepare()?;
+        Ok(executor)
+    }
+
+    /// The device of this executor
+    pub fn device(&self) -> &AdbDevice {
+        &self.device
+    }
+
+    /// The configuration of this executor
+    pub fn config(&self) -> &AdbConfig {
+        &self.config
+    }
+
+    /// The tombstone written for the last crash, if [`AdbConfig::tombstones`] is set
+    pub fn last_tombstone(&self) -> Option<&str> {
+        self.last_tombstone.as_deref()
+    }
+
+    /// Creates the `remote_dir`, and remembers the existing tombstones
+    fn prepare(&mut self) -> Result<(), Error> {
+        self.device.shell(&format!(
+            "mkdir -p {}",
+            shell_quote(&self.config.remote_dir)
+        ))?;
+        if self.config.logcat {
+            self.device.shell("logcat -b crash -c")?;
+        }
+        if self.config.tombstones {
+            self.tombstones = self.list_tombstones()?;
+        }
+        Ok(())
+    }
+
+    /// Reboots the wedged device, and prepares it again
+    fn recover(&mut self) -> Result<(), Error> {
+        self.device.reboot(self.config.boot_timeout)?;
+        self.prepare()
+    }
+
+    /// The path of the testcase on the device
+    fn remote_input(&self) -> String {
+        format!("{}/.cur_input", self.config.remote_dir)
+    }
+
+    /// The path of the coverage map on the device
+    fn remote_coverage(&self) -> String {
+        format!("{}/{}", self.config.remote_dir, self.config.coverage_file)
+    }
+
+    /// The shell command running the harness once
+    fn run_command(&self) -> String {
+        let remote_input = self.remote_input();
+        let mut command = format!(
+            "rm -f {coverage}; LIBAFL_COVERAGE_FILE={coverage} timeout -s KILL {timeout} {harness}",
+            coverage = shell_quote(&self.remote_coverage()),
+            timeout = self.config.timeout.as_secs_f64(),
+            harness = shell_quote(&self.config.harness),
+        );
+        for arg in &self.config.args {
+            command.push(' ');
+            command.push_str(&shell_quote(
+                &arg.replace(INPUT_FILE_PLACEHOLDER, &remote_input),
+            ));
+        }
+        write!(command, " < /dev/null; echo {EXIT_MARKER}$?").unwrap();
+        command
+    }
+
+    /// Runs the harness, and returns its exit code, or `None` if `adb` wedged
+    fn run_harness(&mut self) -> Result<Option<i32>, Error> {
+        let mut child = self
+            .device
+            .command()
+            .arg("shell")
+            .arg(self.run_command())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        // Give adb some slack on top of the timeout on the device
+        let host_timeout = self.config.timeout * 2 + Duration::from_secs(5);
+        if child.wait_timeout(host_timeout)?.is_none() {
+            drop(child.kill());
+            drop(child.wait());
+            return Ok(None);
+        }
+        let output = child.wait_with_output()?;
+        Ok(parse_exit_code(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    /// Checks logcat and the tombstones for a native crash of the harness
+    fn check_crash_logs(&mut self) -> Result<bool, Error> {
+        let mut crashed = false;
+        if self.config.logcat {
+            let output = self.device.shell("logcat -d -b crash")?;
+            let log = String::from_utf8_lossy(&output.stdout);
+            let harness = self
+                .config
+                .harness
+                .rsplit('/')
+                .next()
+                .unwrap_or(&self.config.harness);
+            if log.contains("Fatal signal") && log.contains(harness) {
+                crashed = true;
+            }
+            self.device.shell("logcat -b crash -c")?;
+        }
+        if self.config.tombstones {
+            let tombstones = self.list_tombstones()?;
+            if let Some(new) = tombstones.iter().find(|t| !self.tombstones.contains(t)) {
+                crashed = true;
+                self.last_tombstone = Some(format!("/data/tombstones/{new}"));
+            }
+            self.tombstones = tombstones;
+        }
+        Ok(crashed)
+    }
+
+    /// Lists the tombstones on the device
+    fn list_tombstones(&self) -> Result<Vec<String>, Error> {
+        let output = self.device.shell("ls /data/tombstones")?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(ToString::to_string)
+            .collect())
+    }
+}
+
+impl<C, EM, OT, S, Z> Executor<EM, Z> for AdbExecutor<C, OT, S>
+where
+    C: MapObserver<Entry = u8>,
+    EM: UsesState<State = S>,
+    OT: MatchName,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        self.input_file.write_buf(input.target_bytes().as_slice())?;
+        if let Err(err) = self
+            .device
+            .push(&self.input_file.path, &self.remote_input())
+        {
+            if !self.config.reboot_on_wedge {
+                return Err(err);
+            }
+            log::warn!("Could not push the testcase: {err}");
+            self.recover()?;
+            self.device
+                .push(&self.input_file.path, &self.remote_input())?;
+        }
+
+        let Some(code) = self.run_harness()? else {
+            log::warn!("adb did not return for {:?}", self.device.serial());
+            if self.config.reboot_on_wedge {
+                self.recover()?;
+            }
+            return Ok(ExitKind::Timeout);
+        };
+        let mut exit_kind = exit_kind_from_code(code);
+        if exit_kind == ExitKind::Ok && code != 0 && self.check_crash_logs()? {
+            exit_kind = ExitKind::Crash;
+        }
+
+        let map = self.device.read_file(&self.remote_coverage())?;
+        if !map.is_empty() {
+            let mut observers = RefIndexable::from(&mut self.observers);
+            let map_observer = observers.index_mut(&self.map_observer);
+            let len = map.len().min(map_observer.usable_count());
+            for (idx, &value) in map[..len].iter().enumerate() {
+                map_observer.set(idx, value);
+            }
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<C, OT, S> HasTimeout for AdbExecutor<C, OT, S> {
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.config.timeout = timeout;
+    }
+
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+}
+
+impl<C, OT, S> UsesState for AdbExecutor<C, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<C, OT, S> HasObservers for AdbExecutor<C, OT, S>
+where
+    S: State,
+    OT: ObserversTuple<S::Input, S>,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
+
+/// Parses the online devices from the output of `adb devices`
+fn parse_devices(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .skip_while(|line| !line.starts_with("List of devices"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some(serial), Some("device")) => Some(serial.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Parses the exit code echoed after the [`EXIT_MARKER`]
+fn parse_exit_code(output: &str) -> Option<i32> {
+    output
+        .lines()
+        .rev()
+        .find_map(|line| line.trim().strip_prefix(EXIT_MARKER))
+        .and_then(|code| code.trim().parse().ok())
+}
+
+/// Maps the exit code of the shell to an [`ExitKind`]
+fn exit_kind_from_code(code: i32) -> ExitKind {
+    match code {
+        // SIGKILL by `timeout`, or `timeout` itself
+        TIMEOUT_EXIT_CODE | 137 => ExitKind::Timeout,
+        // Killed by SIGILL, SIGTRAP, SIGABRT, SIGBUS, SIGFPE or SIGSEGV
+        132 | 133 | 134 | 135 | 136 | 139 => ExitKind::Crash,
+        _ => ExitKind::Ok,
+    }
+}
+
+/// Quotes `arg` for the device shell
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_kind_from_code, parse_devices, parse_exit_code, shell_quote};
+    use crate::executors::ExitKind;
+
+    #[test]
+    fn test_adb_parsing() {
+        let devices = "* daemon started successfully\nList of devices attached\nemulator-5554\tdevice\n0123456789\toffline\nR58M\tdevice product:x\n\n";
+        assert_eq!(parse_devices(devices), ["emulator-5554", "R58M"]);
+
+        assert_eq!(parse_exit_code("output\nlibafl_exit:139\n"), Some(139));
+        assert_eq!(parse_exit_code("no marker\n"), None);
+        assert_eq!(exit_kind_from_code(139), ExitKind::Crash);
+        assert_eq!(exit_kind_from_code(137), ExitKind::Timeout);
+        assert_eq!(exit_kind_from_code(1), ExitKind::Ok);
+
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+}
@@ -0,0 +1,275 @@
+//! The [`BatchedExecutor`] runs a batch of inputs back-to-back and keeps a snapshot of the observers of each run.
+//! Executors that can run batches implement [`BatchExecutor`].
+//!
+//! For very fast targets, the per-execution overhead of the fuzzer dominates the actual target execution.
+//! The [`crate::stages::BatchedMutationalStage`] generates a whole batch of mutated inputs,
+//! runs them using [`BatchExecutor::run_batch`], and only afterwards evaluates the feedbacks for each run,
+//! based on the observer snapshots of this executor.
+//! For a persistent forkserver target, the batch runs in the same persistent child without interruption.
+
+use alloc::{format, vec::Vec};
+use core::{num::NonZeroUsize, time::Duration};
+
+use libafl_bolts::tuples::RefIndexable;
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers, HasTimeout},
+    observers::ObserversTuple,
+    state::UsesState,
+    Error,
+};
+
+/// Gives access to the results of each run of the last batch of a [`BatchExecutor`]
+pub trait HasBatchSnapshots: HasObservers {
+    /// The maximum number of inputs in a batch
+    fn batch_size(&self) -> usize;
+
+    /// The exit kind and the observers of the run at `idx` of the last batch
+    fn snapshot(&self, idx: usize) -> Option<(&ExitKind, &Self::Observers)>;
+}
+
+/// An [`Executor`] that runs a batch of inputs at once and keeps the observers of each run of the batch,
+/// to evaluate the feedbacks afterwards.
+pub trait BatchExecutor<EM, Z>: Executor<EM, Z> + HasBatchSnapshots
+where
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+{
+    /// Runs all `inputs`, keeping the exit kind and the observers of each run.
+    ///
+    /// The observers are reset and post-processed for each run, like the fuzzer does for single executions.
+    /// The feedbacks are not evaluated; use [`HasBatchSnapshots::snapshot`] to access the results afterwards.
+    /// Returns an [`Error::IllegalArgument`] if there are more inputs than the batch size.
+    fn run_batch(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        inputs: &[Self::Input],
+    ) -> Result<(), Error>;
+}
+
+/// Wraps an [`Executor`] to run a batch of inputs at once.
+///
+/// The observers are snapshotted after each run of a batch.
+/// The snapshots live in a ring of slots that is reused for each batch, so cloning into them does not allocate
+/// once the ring is full.
+/// If a run of the batch crashes the fuzzer itself, for example in an in-process executor,
+/// the remaining runs of the batch are lost.
+#[derive(Debug)]
+pub struct BatchedExecutor<E>
+where
+    E: HasObservers,
+{
+    executor: E,
+    batch_size: NonZeroUsize,
+    /// The exit kind and observers of each run, reused between batches
+    snapshots: Vec<(ExitKind, E::Observers)>,
+    /// The number of runs in the last batch
+    batch_len: usize,
+}
+
+impl<E> BatchedExecutor<E>
+where
+    E: HasObservers + UsesState,
+{
+    /// Wraps the `executor`, running up to `batch_size` inputs per batch
+    pub fn new(executor: E, batch_size: NonZeroUsize) -> Self {
+        Self {
+            executor,
+            batch_size,
+            snapshots: Vec::with_capacity(batch_size.get()),
+            batch_len: 0,
+        }
+    }
+
+    /// Sets the maximum number of inputs in a batch
+    pub fn set_batch_size(&mut self, batch_size: NonZeroUsize) {
+        self.batch_size = batch_size;
+        self.snapshots.truncate(batch_size.get());
+        self.batch_len = self.batch_len.min(batch_size.get());
+    }
+
+    /// The wrapped executor
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.executor
+    }
+
+    /// The number of runs of the last batch
+    #[must_use]
+    pub fn batch_len(&self) -> usize {
+        self.batch_len
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for BatchedExecutor<E>
+where
+    E: Executor<EM, Z> + HasObservers,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.executor.run_target(fuzzer, state, mgr, input)
+    }
+}
+
+impl<E> HasBatchSnapshots for BatchedExecutor<E>
+where
+    E: HasObservers,
+{
+    fn batch_size(&self) -> usize {
+        self.batch_size.get()
+    }
+
+    fn snapshot(&self, idx: usize) -> Option<(&ExitKind, &E::Observers)> {
+        if idx < self.batch_len {
+            let (exit_kind, observers) = &self.snapshots[idx];
+            Some((exit_kind, observers))
+        } else {
+            None
+        }
+    }
+}
+
+impl<E, EM, Z> BatchExecutor<EM, Z> for BatchedExecutor<E>
+where
+    E: Executor<EM, Z> + HasObservers,
+    E::Observers: ObserversTuple<Self::Input, Self::State> + Clone,
+    EM: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+{
+    /// Runs all `inputs` back-to-back, snapshotting the observers after each run.
+    fn run_batch(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        inputs: &[Self::Input],
+    ) -> Result<(), Error> {
+        if inputs.len() > self.batch_size.get() {
+            return Err(Error::illegal_argument(format!(
+                "Batch of {} inputs exceeds the batch size of {}",
+                inputs.len(),
+                self.batch_size
+            )));
+        }
+
+        self.batch_len = 0;
+        for input in inputs {
+            self.executor.observers_mut().pre_exec_all(state, input)?;
+            let exit_kind = self.executor.run_target(fuzzer, state, mgr, input)?;
+            self.executor
+                .observers_mut()
+                .post_exec_all(state, input, &exit_kind)?;
+
+            let observers = self.executor.observers();
+            if let Some((slot_exit_kind, slot_observers)) = self.snapshots.get_mut(self.batch_len) {
+                *slot_exit_kind = exit_kind;
+                slot_observers.clone_from(&*observers);
+            } else {
+                self.snapshots.push((exit_kind, (*observers).clone()));
+            }
+            self.batch_len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<E> UsesState for BatchedExecutor<E>
+where
+    E: HasObservers + UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> HasObservers for BatchedExecutor<E>
+where
+    E: HasObservers,
+{
+    type Observers = E::Observers;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        self.executor.observers_mut()
+    }
+}
+
+impl<E> HasTimeout for BatchedExecutor<E>
+where
+    E: HasObservers + HasTimeout,
+{
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.executor.timeout()
+    }
+
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.executor.set_timeout(timeout);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::nonzero;
+
+    use crate::{
+        events::NopEventManager,
+        executors::{
+            batched::{BatchExecutor, HasBatchSnapshots},
+            test::NopExecutor,
+            BatchedExecutor, ExitKind, WithObservers,
+        },
+        fuzzer::NopFuzzer,
+        inputs::BytesInput,
+        state::{HasExecutions, NopState},
+    };
+
+    #[test]
+    fn test_run_batch() {
+        let mut executor =
+            BatchedExecutor::new(WithObservers::new(NopExecutor::new(), ()), nonzero!(3));
+        let mut state = NopState::new();
+        let inputs = [
+            BytesInput::new(vec![1]),
+            BytesInput::new(vec![2]),
+            BytesInput::new(vec![3]),
+        ];
+
+        for _ in 0..2 {
+            executor
+                .run_batch(
+                    &mut NopFuzzer::new(),
+                    &mut state,
+                    &mut NopEventManager::new(),
+                    &inputs[..2],
+                )
+                .unwrap();
+        }
+        assert_eq!(executor.batch_len(), 2);
+        assert_eq!(*state.executions(), 4);
+        assert_eq!(executor.snapshot(1).unwrap().0, &ExitKind::Ok);
+        assert!(executor.snapshot(2).is_none());
+
+        executor.set_batch_size(nonzero!(2));
+        assert!(executor
+            .run_batch(
+                &mut NopFuzzer::new(),
+                &mut state,
+                &mut NopEventManager::new(),
+                &inputs,
+            )
+            .is_err());
+    }
+}
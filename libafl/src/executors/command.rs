@@ -1,5 +1,5 @@
 //! The command executor executes a sub program for each run
-use alloc::vec::Vec;
+use alloc::{sync::Arc, vec::Vec};
 use core::{
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
@@ -15,6 +15,7 @@ use std::{
 #[cfg(feature = "std")]
 use std::{
     ffi::{OsStr, OsString},
+    fs,
     io::{Read, Write},
     path::{Path, PathBuf},
     process::Child,
@@ -38,12 +39,14 @@ use typed_builder::TypedBuilder;
 
 use super::HasTimeout;
 #[cfg(all(feature = "std", unix))]
-use crate::executors::{Executor, ExitKind};
+use crate::executors::{Executor, ExitKind, ExitSignalMetadata};
+#[cfg(all(feature = "regex", target_os = "linux"))]
+use crate::observers::HangBacktraceObserver;
 use crate::{
     corpus::Corpus,
     executors::{hooks::ExecutorHooksTuple, HasObservers},
     inputs::{HasTargetBytes, UsesInput},
-    observers::{ObserversTuple, StdErrObserver, StdOutObserver},
+    observers::{ExitCodeObserver, ObserversTuple, StdErrObserver, StdOutObserver},
     state::{HasCorpus, HasExecutions, State, UsesState},
     std::borrow::ToOwned,
 };
@@ -71,6 +74,25 @@ pub enum InputLocation {
     },
 }
 
+/// The placeholder in templated args that gets replaced with the path of the input file
+pub const INPUT_FILE_PLACEHOLDER: &str = "@@";
+
+/// The prefix of the working directories created for each run, see [`CommandExecutorBuilder::cwd_per_run`]
+const RUN_DIR_PREFIX: &str = "libafl_run_";
+
+/// Derives environment variables for a run from the target bytes of the input and the number of executions
+type EnvFn = dyn Fn(&[u8], u64) -> Vec<(OsString, OsString)> + Send + Sync;
+
+/// A shared [`EnvFn`], so that builders and configurators stay [`Clone`] and [`Debug`]
+#[derive(Clone)]
+struct InputEnvFn(Arc<EnvFn>);
+
+impl Debug for InputEnvFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("InputEnvFn")
+    }
+}
+
 /// A simple Configurator that takes the most common parameters
 /// Writes the input either to stdio or to a file
 /// Use [`CommandExecutor::builder()`] to use this configurator.
@@ -87,6 +109,45 @@ pub struct StdCommandConfigurator {
     input_location: InputLocation,
     /// The Command to execute
     command: Command,
+    /// Derives the environment variables of each run
+    env_fn: Option<InputEnvFn>,
+    /// The keys set by the `env_fn` in the last run
+    env_fn_keys: Vec<OsString>,
+    /// The directory to create the working directory of each run in
+    cwd_base: Option<PathBuf>,
+    /// The working directory of the last run, removed before the next one
+    run_dir: Option<PathBuf>,
+    exit_code_observer: Option<Handle<ExitCodeObserver>>,
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    hang_observer: Option<Handle<HangBacktraceObserver>>,
+}
+
+impl StdCommandConfigurator {
+    /// The working directory of the last run, if [`CommandExecutorBuilder::cwd_per_run`] is used
+    #[must_use]
+    pub fn run_dir(&self) -> Option<&Path> {
+        self.run_dir.as_deref()
+    }
+
+    /// Removes the working directory of the last run, if any
+    fn remove_run_dir(&mut self) -> Result<(), Error> {
+        if let Some(run_dir) = self.run_dir.take() {
+            if let Err(err) = fs::remove_dir_all(&run_dir) {
+                if err.kind() != std::io::ErrorKind::NotFound {
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for StdCommandConfigurator {
+    fn drop(&mut self) {
+        if let Err(err) = self.remove_run_dir() {
+            log::warn!("Could not remove the working directory of the last run: {err}");
+        }
+    }
 }
 
 impl<I> CommandConfigurator<I> for StdCommandConfigurator
@@ -101,6 +162,42 @@ where
         self.stderr_observer.clone()
     }
 
+    fn exit_code_observer(&self) -> Option<Handle<ExitCodeObserver>> {
+        self.exit_code_observer.clone()
+    }
+
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    fn hang_observer(&self) -> Option<Handle<HangBacktraceObserver>> {
+        self.hang_observer.clone()
+    }
+
+    fn pre_spawn(&mut self, input: &I, executions: u64) -> Result<(), Error> {
+        if let Some(env_fn) = &self.env_fn {
+            let envs = (env_fn.0)(input.target_bytes().as_slice(), executions);
+            for key in self.env_fn_keys.drain(..) {
+                if !envs.iter().any(|(new_key, _)| *new_key == key) {
+                    self.command.env_remove(key);
+                }
+            }
+            for (key, value) in envs {
+                self.command.env(&key, value);
+                self.env_fn_keys.push(key);
+            }
+        }
+
+        if let Some(base) = &self.cwd_base {
+            let run_dir = base.join(format!(
+                "{RUN_DIR_PREFIX}{}_{executions}",
+                std::process::id()
+            ));
+            self.remove_run_dir()?;
+            fs::create_dir_all(&run_dir)?;
+            self.command.current_dir(&run_dir);
+            self.run_dir = Some(run_dir);
+        }
+        Ok(())
+    }
+
     fn spawn_child(&mut self, input: &I) -> Result<Child, Error> {
         match &mut self.input_location {
             InputLocation::Arg { argnum } => {
@@ -132,11 +229,12 @@ where
                         cmd.arg(arg);
                     }
                 }
-                cmd.envs(
-                    self.command
-                        .get_envs()
-                        .filter_map(|(key, value)| value.map(|value| (key, value))),
-                );
+                for (key, value) in self.command.get_envs() {
+                    match value {
+                        Some(value) => cmd.env(key, value),
+                        None => cmd.env_remove(key),
+                    };
+                }
                 if let Some(cwd) = self.command.get_current_dir() {
                     cmd.current_dir(cwd);
                 }
@@ -330,11 +428,12 @@ where
 
 // this only works on unix because of the reliance on checking the process signal for detecting OOM
 #[cfg(all(feature = "std", unix))]
-impl<I, OT, S, T> CommandExecutor<OT, S, T>
+impl<I, OT, S, T, HT> CommandExecutor<OT, S, T, HT>
 where
     S: State + HasExecutions + UsesInput<Input = I>,
     T: CommandConfigurator<I> + Debug,
     OT: Debug + ObserversTuple<I, S>,
+    HT: ExecutorHooksTuple<S>,
 {
     fn execute_input_with_command(&mut self, state: &mut S, input: &I) -> Result<ExitKind, Error> {
         use std::os::unix::prelude::ExitStatusExt;
@@ -343,28 +442,45 @@ where
 
         *state.executions_mut() += 1;
         self.observers.pre_exec_child_all(state, input)?;
+        if *state.executions() == 1 {
+            self.hooks.init_all::<Self>(state);
+        }
+        self.hooks.pre_exec_all(state, input);
 
+        self.configurer.pre_spawn(input, *state.executions())?;
         let mut child = self.configurer.spawn_child(input)?;
 
-        let res = match child
+        let mut status = child
             .wait_timeout(self.configurer.exec_timeout())
-            .expect("waiting on child failed")
-            .map(|status| status.signal())
-        {
+            .expect("waiting on child failed");
+        let res = match status.map(|status| status.signal()) {
             // for reference: https://www.man7.org/linux/man-pages/man7/signal.7.html
             Some(Some(9)) => Ok(ExitKind::Oom),
-            Some(Some(_)) => Ok(ExitKind::Crash),
+            Some(Some(signal)) => {
+                ExitSignalMetadata::record_last(signal);
+                Ok(ExitKind::Crash)
+            }
             Some(None) => Ok(ExitKind::Ok),
             None => {
+                #[cfg(all(feature = "regex", target_os = "linux"))]
+                if let (Some(h), Ok(pid)) =
+                    (self.configurer.hang_observer(), i32::try_from(child.id()))
+                {
+                    let mut observers = self.observers_mut();
+                    if let Err(err) = observers.index_mut(&h).capture(Pid::from_raw(pid)) {
+                        log::warn!("Could not capture the stacks of the hanging child: {err}");
+                    }
+                }
                 // if this fails, there is not much we can do. let's hope it failed because the process finished
                 // in the meantime.
                 drop(child.kill());
                 // finally, try to wait to properly clean up system resources.
-                drop(child.wait());
+                status = child.wait().ok();
                 Ok(ExitKind::Timeout)
             }
         };
 
+        self.hooks.post_exec_all(state, input);
         if let Ok(exit_kind) = res {
             self.observers
                 .post_exec_child_all(state, input, &exit_kind)?;
@@ -392,17 +508,26 @@ where
             let obs = observers.index_mut(h);
             obs.observe_stderr(&stderr);
         }
+        if let Some(h) = &mut self.configurer.exit_code_observer() {
+            let mut observers = self.observers_mut();
+            let obs = observers.index_mut(h);
+            match status {
+                Some(status) => obs.observe_exit_status(&status),
+                None => obs.observe(None, None),
+            }
+        }
         res
     }
 }
 
 #[cfg(all(feature = "std", unix))]
-impl<EM, OT, S, T, Z> Executor<EM, Z> for CommandExecutor<OT, S, T>
+impl<EM, OT, S, T, Z, HT> Executor<EM, Z> for CommandExecutor<OT, S, T, HT>
 where
     EM: UsesState<State = S>,
     S: State + HasExecutions + UsesInput,
     T: CommandConfigurator<S::Input> + Debug,
     OT: Debug + MatchName + ObserversTuple<S::Input, S>,
+    HT: ExecutorHooksTuple<S>,
     Z: UsesState<State = S>,
 {
     fn run_target(
@@ -417,7 +542,7 @@ where
 }
 
 // this only works on unix because of the reliance on checking the process signal for detecting OOM
-impl<OT, S, T> HasTimeout for CommandExecutor<OT, S, T>
+impl<OT, S, T, HT> HasTimeout for CommandExecutor<OT, S, T, HT>
 where
     S: HasCorpus,
     T: CommandConfigurator<<S::Corpus as Corpus>::Input>,
@@ -466,6 +591,7 @@ where
 
         *state.executions_mut() += 1;
 
+        self.configurer.pre_spawn(input, *state.executions())?;
         let child = self.configurer.spawn_child(input)?;
 
         let wait_status = waitpid(child, Some(WaitPidFlag::WUNTRACED))?;
@@ -490,7 +616,8 @@ where
         self.hooks.pre_exec_all(state, input);
 
         ptrace::detach(child, None)?;
-        let res = match waitpid(child, None)? {
+        let wait_status = waitpid(child, None)?;
+        let res = match wait_status {
             Exited(pid, 0) if pid == child => ExitKind::Ok,
             Exited(pid, _) if pid == child => ExitKind::Crash,
             Signaled(pid, Signal::SIGALRM, _has_coredump) if pid == child => ExitKind::Timeout,
@@ -506,9 +633,22 @@ where
                 ));
             }
         };
+        if let (ExitKind::Crash, Signaled(_, signal, _) | Stopped(_, signal)) = (res, wait_status) {
+            ExitSignalMetadata::record_last(signal as i32);
+        }
 
         self.hooks.post_exec_all(state, input);
         self.observers.post_exec_child_all(state, input, &res)?;
+
+        if let Some(h) = &mut self.configurer.exit_code_observer() {
+            let mut observers = self.observers_mut();
+            let obs = observers.index_mut(h);
+            match wait_status {
+                Exited(_, code) => obs.observe(Some(code), None),
+                Signaled(_, signal, _) => obs.observe(None, Some(signal as i32)),
+                _ => obs.observe(None, None),
+            }
+        }
         Ok(res)
     }
 }
@@ -542,12 +682,19 @@ where
 pub struct CommandExecutorBuilder {
     stdout: Option<Handle<StdOutObserver>>,
     stderr: Option<Handle<StdErrObserver>>,
+    exit_code: Option<Handle<ExitCodeObserver>>,
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    hang: Option<Handle<HangBacktraceObserver>>,
     debug_child: bool,
     program: Option<OsString>,
     args: Vec<OsString>,
+    /// The offsets of the args in which [`INPUT_FILE_PLACEHOLDER`] gets replaced
+    templated_args: Vec<usize>,
     input_location: InputLocation,
     cwd: Option<PathBuf>,
+    cwd_base: Option<PathBuf>,
     envs: Vec<(OsString, OsString)>,
+    env_fn: Option<InputEnvFn>,
     timeout: Duration,
 }
 
@@ -564,11 +711,17 @@ impl CommandExecutorBuilder {
         CommandExecutorBuilder {
             stdout: None,
             stderr: None,
+            exit_code: None,
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang: None,
             program: None,
             args: vec![],
+            templated_args: vec![],
             input_location: InputLocation::StdIn,
             cwd: None,
+            cwd_base: None,
             envs: vec![],
+            env_fn: None,
             timeout: Duration::from_secs(5),
             debug_child: false,
         }
@@ -620,6 +773,19 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Sets the exit code observer
+    pub fn exit_code_observer(&mut self, exit_code: Handle<ExitCodeObserver>) -> &mut Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    /// Sets the observer capturing the stacks of the child when it times out, before it gets killed
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    pub fn hang_observer(&mut self, hang: Handle<HangBacktraceObserver>) -> &mut Self {
+        self.hang = Some(hang);
+        self
+    }
+
     /// Sets the input mode to [`InputLocation::File`]
     /// and adds the filename as arg to at the current position.
     /// Uses a default filename.
@@ -640,6 +806,14 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Sets the input mode to [`InputLocation::File`], without adding the filename as arg.
+    /// Use this with [`Self::arg_template`], or if the target finds the file by other means, such as an environment variable.
+    pub fn input_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        let out_file = InputFile::create(path.as_ref()).unwrap();
+        self.input(InputLocation::File { out_file });
+        self
+    }
+
     /// Adds an argument to the program's commandline.
     pub fn arg<O: AsRef<OsStr>>(&mut self, arg: O) -> &mut CommandExecutorBuilder {
         self.args.push(arg.as_ref().to_owned());
@@ -658,6 +832,28 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Adds an argument in which each [`INPUT_FILE_PLACEHOLDER`] (`@@`) gets replaced with the path of the input file,
+    /// for example `--input=@@` or `cat @@ @@`.
+    ///
+    /// If no input location was set, the input is delivered via a file with a default name,
+    /// as with [`Self::arg_input_file_std`]. Input delivered _as_ an argument cannot be templated.
+    pub fn arg_template<O: AsRef<OsStr>>(&mut self, arg: O) -> &mut CommandExecutorBuilder {
+        self.templated_args.push(self.args.len());
+        self.arg(arg)
+    }
+
+    /// Adds a range of templated arguments to the program's commandline, see [`Self::arg_template`].
+    pub fn args_template<IT, O>(&mut self, args: IT) -> &mut CommandExecutorBuilder
+    where
+        IT: IntoIterator<Item = O>,
+        O: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg_template(arg.as_ref());
+        }
+        self
+    }
+
     /// Adds a range of environment variables to the executed command.
     pub fn envs<IT, K, V>(&mut self, vars: IT) -> &mut CommandExecutorBuilder
     where
@@ -682,12 +878,33 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Sets environment variables derived from each input, and the number of executions so far.
+    ///
+    /// The function gets called with the target bytes before each run.
+    /// Its variables override the ones set with [`Self::env`], variables it no longer returns get removed.
+    pub fn env_fn<F>(&mut self, env_fn: F) -> &mut CommandExecutorBuilder
+    where
+        F: Fn(&[u8], u64) -> Vec<(OsString, OsString)> + Send + Sync + 'static,
+    {
+        self.env_fn = Some(InputEnvFn(Arc::new(env_fn)));
+        self
+    }
+
     /// Sets the working directory for the child process.
     pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut CommandExecutorBuilder {
         self.cwd = Some(dir.as_ref().to_owned());
         self
     }
 
+    /// Runs each execution in a fresh, empty working directory inside `base`.
+    ///
+    /// The directory of a run is kept until the next run starts, so it can be inspected after a crash,
+    /// and is removed once the executor is dropped. Overrides [`Self::current_dir`].
+    pub fn cwd_per_run<P: AsRef<Path>>(&mut self, base: P) -> &mut CommandExecutorBuilder {
+        self.cwd_base = Some(base.as_ref().to_owned());
+        self
+    }
+
     /// If set to true, the child's output won't be redirecited to `/dev/null`.
     /// Defaults to `false`.
     pub fn debug_child(&mut self, debug_child: bool) -> &mut CommandExecutorBuilder {
@@ -710,6 +927,21 @@ impl CommandExecutorBuilder {
         OT: MatchName + ObserversTuple<S::Input, S>,
         S: UsesInput,
         S::Input: Input + HasTargetBytes,
+    {
+        self.build_with_hooks(observers, ())
+    }
+
+    /// Builds the `CommandExecutor`, running the [`ExecutorHooksTuple`] `hooks` before and after each execution
+    pub fn build_with_hooks<OT, S, HT>(
+        &self,
+        observers: OT,
+        hooks: HT,
+    ) -> Result<CommandExecutor<OT, S, StdCommandConfigurator, HT>, Error>
+    where
+        OT: MatchName + ObserversTuple<S::Input, S>,
+        S: UsesInput,
+        S::Input: Input + HasTargetBytes,
+        HT: ExecutorHooksTuple<S>,
     {
         let Some(program) = &self.program else {
             return Err(Error::illegal_argument(
@@ -717,8 +949,34 @@ impl CommandExecutorBuilder {
             ));
         };
 
+        let mut input_location = self.input_location.clone();
+        let mut args = self.args.clone();
+        if !self.templated_args.is_empty() {
+            if matches!(input_location, InputLocation::Arg { .. }) {
+                return Err(Error::illegal_argument(
+                    "CommandExecutor::builder: templated args need the input in a file, not as an argument",
+                ));
+            }
+            if input_location == InputLocation::StdIn {
+                input_location = InputLocation::File {
+                    out_file: InputFile::create(get_unique_std_input_file())?,
+                };
+            }
+            let InputLocation::File { out_file } = &input_location else {
+                unreachable!("input location was set to a file above");
+            };
+            let mut path = out_file.path.clone();
+            // The run dirs are elsewhere, the target needs to find the input from there.
+            if self.cwd_base.is_some() && path.is_relative() {
+                path = std::env::current_dir()?.join(path);
+            }
+            for &argnum in &self.templated_args {
+                args[argnum] = replace_input_placeholder(&args[argnum], path.as_os_str());
+            }
+        }
+
         let mut command = Command::new(program);
-        match &self.input_location {
+        match &input_location {
             InputLocation::StdIn => {
                 command.stdin(Stdio::piped());
             }
@@ -726,7 +984,7 @@ impl CommandExecutorBuilder {
                 command.stdin(Stdio::null());
             }
         }
-        command.args(&self.args);
+        command.args(&args);
         command.envs(
             self.envs
                 .iter()
@@ -752,15 +1010,51 @@ impl CommandExecutorBuilder {
             debug_child: self.debug_child,
             stdout_observer: self.stdout.clone(),
             stderr_observer: self.stderr.clone(),
-            input_location: self.input_location.clone(),
+            input_location,
             timeout: self.timeout,
             command,
+            env_fn: self.env_fn.clone(),
+            env_fn_keys: vec![],
+            cwd_base: self.cwd_base.clone(),
+            run_dir: None,
+            exit_code_observer: self.exit_code.clone(),
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_observer: self.hang.clone(),
         };
         Ok(
-            <StdCommandConfigurator as CommandConfigurator<S::Input>>::into_executor::<OT, S>(
-                configurator,
-                observers,
-            ),
+            <StdCommandConfigurator as CommandConfigurator<S::Input>>::into_executor_with_hooks::<
+                OT,
+                S,
+                HT,
+            >(configurator, observers, hooks),
+        )
+    }
+}
+
+/// Replaces each [`INPUT_FILE_PLACEHOLDER`] in `arg` with `path`
+fn replace_input_placeholder(arg: &OsStr, path: &OsStr) -> OsString {
+    #[cfg(unix)]
+    {
+        let placeholder = INPUT_FILE_PLACEHOLDER.as_bytes();
+        let arg = arg.as_bytes();
+        let mut replaced = Vec::with_capacity(arg.len());
+        let mut i = 0;
+        while i < arg.len() {
+            if arg[i..].starts_with(placeholder) {
+                replaced.extend_from_slice(path.as_bytes());
+                i += placeholder.len();
+            } else {
+                replaced.push(arg[i]);
+                i += 1;
+            }
+        }
+        OsStr::from_bytes(&replaced).to_owned()
+    }
+    #[cfg(not(unix))]
+    {
+        OsString::from(
+            arg.to_string_lossy()
+                .replace(INPUT_FILE_PLACEHOLDER, &path.to_string_lossy()),
         )
     }
 }
@@ -819,6 +1113,21 @@ pub trait CommandConfigurator<I, C = Child>: Sized {
     fn stderr_observer(&self) -> Option<Handle<StdErrObserver>> {
         None
     }
+    /// Get the exit code observer
+    fn exit_code_observer(&self) -> Option<Handle<ExitCodeObserver>> {
+        None
+    }
+    /// Get the observer capturing the stacks of a hanging child
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    fn hang_observer(&self) -> Option<Handle<HangBacktraceObserver>> {
+        None
+    }
+
+    /// Prepares the next run, before [`Self::spawn_child`] gets called.
+    /// `executions` includes the upcoming run.
+    fn pre_spawn(&mut self, _input: &I, _executions: u64) -> Result<(), Error> {
+        Ok(())
+    }
 
     /// Spawns a new process with the given configuration.
     fn spawn_child(&mut self, input: &I) -> Result<C, Error>;
@@ -865,11 +1174,18 @@ pub trait CommandConfigurator<I, C = Child>: Sized {
 
 #[cfg(test)]
 mod tests {
+    use alloc::{rc::Rc, string::ToString};
+    use core::cell::Cell;
+    use std::{env, ffi::OsString, fs};
+
+    use libafl_bolts::tuples::tuple_list;
+
     use crate::{
         events::SimpleEventManager,
         executors::{
             command::{CommandExecutor, InputLocation},
-            Executor,
+            hooks::ClosureExecutorHook,
+            Executor, ExitKind,
         },
         fuzzer::NopFuzzer,
         inputs::BytesInput,
@@ -901,4 +1217,91 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_hooks() {
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|status| {
+            log::info!("{status}");
+        }));
+
+        let pre_execs = Rc::new(Cell::new(0));
+        let post_execs = Rc::new(Cell::new(0));
+        let hook = {
+            let (pre_execs, post_execs) = (pre_execs.clone(), post_execs.clone());
+            ClosureExecutorHook::new(
+                move |_: &mut NopState<BytesInput>, _: &BytesInput| {
+                    pre_execs.set(pre_execs.get() + 1);
+                },
+                move |_: &mut NopState<BytesInput>, input: &BytesInput| {
+                    assert_eq!(*input, BytesInput::new(b"test".to_vec()));
+                    post_execs.set(post_execs.get() + 1);
+                },
+            )
+        };
+
+        let mut executor = CommandExecutor::builder()
+            .program("true")
+            .build_with_hooks((), tuple_list!(hook))
+            .unwrap();
+
+        let mut state = NopState::new();
+        for _ in 0..2 {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut state,
+                    &mut mgr,
+                    &BytesInput::new(b"test".to_vec()),
+                )
+                .unwrap();
+        }
+        assert_eq!(pre_execs.get(), 2);
+        assert_eq!(post_execs.get(), 2);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_templating() {
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|status| {
+            log::info!("{status}");
+        }));
+        let base = env::temp_dir().join(format!("libafl_cwd_per_run_{}", std::process::id()));
+        fs::create_dir_all(&base).unwrap();
+
+        let mut executor = CommandExecutor::builder()
+            .program("sh")
+            .arg("-c")
+            .arg_template(
+                r#"[ "$(cat @@ @@)" = testtest ] && [ "$INPUT_LEN" = 4 ] || exit 1
+                case "$PWD" in */libafl_run_*) kill -9 $$ ;; esac"#,
+            )
+            .env_fn(|bytes, _executions| {
+                vec![(
+                    OsString::from("INPUT_LEN"),
+                    OsString::from(bytes.len().to_string()),
+                )]
+            })
+            .cwd_per_run(&base)
+            .build(())
+            .unwrap();
+
+        let exit_kind = executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut NopState::new(),
+                &mut mgr,
+                &BytesInput::new(b"test".to_vec()),
+            )
+            .unwrap();
+        // The script only kills itself if all checks passed
+        assert_eq!(exit_kind, ExitKind::Oom);
+        assert!(executor.inner().run_dir().unwrap().starts_with(&base));
+
+        drop(executor);
+        assert_eq!(fs::read_dir(&base).unwrap().count(), 0);
+        fs::remove_dir(&base).unwrap();
+    }
 }
@@ -2,7 +2,9 @@
 //!
 //! It wraps two executors that will be run after each other with the same input.
 //! In comparison to the [`crate::executors::CombinedExecutor`] it also runs the secondary executor in `run_target`.
+//! The [`MultiDiffExecutor`] does the same for any number of executors.
 //!
+use alloc::{borrow::Cow, format, vec::Vec};
 use core::{
     cell::UnsafeCell,
     fmt::Debug,
@@ -12,7 +14,7 @@ use core::{
 
 use libafl_bolts::{
     ownedref::OwnedMutPtr,
-    tuples::{MatchName, RefIndexable},
+    tuples::{Handle, MatchName, RefIndexable},
 };
 use serde::{Deserialize, Serialize};
 
@@ -116,6 +118,7 @@ where
             Ok(ExitKind::Diff {
                 primary: ret1.into(),
                 secondary: ret2.into(),
+                diverged: 0b10,
             })
         }
     }
@@ -279,3 +282,510 @@ where
         }
     }
 }
+
+/// The separator between the name of an observer and the index of its executor, see [`diff_observer_name`]
+pub const DIFF_OBSERVER_NAME_SEPARATOR: char = '#';
+
+/// The name of the observer `name` of the executor at `index` in a [`MultiDiffExecutor`].
+///
+/// All executors usually use observers of the same name, this name picks the one of a specific executor.
+#[must_use]
+pub fn diff_observer_name(index: usize, name: &str) -> Cow<'static, str> {
+    Cow::Owned(format!("{name}{DIFF_OBSERVER_NAME_SEPARATOR}{index}"))
+}
+
+/// A [`Handle`] to the observer `handle` of the executor at `index` in a [`MultiDiffExecutor`]
+#[must_use]
+pub fn diff_observer_handle<T>(index: usize, handle: &Handle<T>) -> Handle<T> {
+    Handle::new(diff_observer_name(index, handle.name()))
+}
+
+/// A tuple of executors run by a [`MultiDiffExecutor`], giving access to their observers
+pub trait DiffExecutorsTuple {
+    /// The number of executors
+    const LEN: usize;
+    /// The pointers to the observers of all executors
+    type ObserversPtrs: MatchIndexedName;
+
+    /// Pointers to the observers of all executors, valid as long as the executors are not moved
+    fn observers_ptrs(&self) -> Self::ObserversPtrs;
+}
+
+impl DiffExecutorsTuple for () {
+    const LEN: usize = 0;
+    type ObserversPtrs = ();
+
+    fn observers_ptrs(&self) -> Self::ObserversPtrs {}
+}
+
+impl<Head, Tail> DiffExecutorsTuple for (Head, Tail)
+where
+    Head: HasObservers,
+    Head::Observers: MatchName,
+    Tail: DiffExecutorsTuple,
+{
+    const LEN: usize = 1 + Tail::LEN;
+    type ObserversPtrs = (OwnedMutPtr<Head::Observers>, Tail::ObserversPtrs);
+
+    fn observers_ptrs(&self) -> Self::ObserversPtrs {
+        (
+            OwnedMutPtr::Ptr(ptr::from_ref(&*self.0.observers()).cast_mut()),
+            self.1.observers_ptrs(),
+        )
+    }
+}
+
+/// A tuple of executors, that can all run the same input
+pub trait DiffExecutorsRunTuple<EM, Z, S>: DiffExecutorsTuple
+where
+    S: UsesInput,
+{
+    /// Runs all executors, with their observers, after each other, collecting their [`ExitKind`]s
+    fn run_all(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &S::Input,
+        exit_kinds: &mut Vec<ExitKind>,
+    ) -> Result<(), Error>;
+}
+
+impl<EM, Z, S> DiffExecutorsRunTuple<EM, Z, S> for ()
+where
+    S: UsesInput,
+{
+    fn run_all(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut S,
+        _mgr: &mut EM,
+        _input: &S::Input,
+        _exit_kinds: &mut Vec<ExitKind>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<EM, Head, S, Tail, Z> DiffExecutorsRunTuple<EM, Z, S> for (Head, Tail)
+where
+    Head: Executor<EM, Z, State = S> + HasObservers,
+    Head::Observers: ObserversTuple<S::Input, S>,
+    Tail: DiffExecutorsRunTuple<EM, Z, S>,
+    EM: UsesState<State = S>,
+    S: UsesInput,
+    Z: UsesState<State = S>,
+{
+    fn run_all(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        input: &S::Input,
+        exit_kinds: &mut Vec<ExitKind>,
+    ) -> Result<(), Error> {
+        self.0.observers_mut().pre_exec_all(state, input)?;
+        let exit_kind = self.0.run_target(fuzzer, state, mgr, input)?;
+        self.0
+            .observers_mut()
+            .post_exec_all(state, input, &exit_kind)?;
+        exit_kinds.push(exit_kind);
+        self.1.run_all(fuzzer, state, mgr, input, exit_kinds)
+    }
+}
+
+/// Observer lookup in a tuple of observers of different executors
+pub trait MatchIndexedName {
+    /// Match for a name in the observers of the executor at `index`
+    fn match_indexed_name<T>(&self, index: usize, name: &str) -> Option<&T>;
+    /// Match for a name in the observers of the executor at `index`, mutable
+    fn match_indexed_name_mut<T>(&mut self, index: usize, name: &str) -> Option<&mut T>;
+    /// Match for a name in the observers of all executors, returning the first match
+    fn match_any_name<T>(&self, name: &str) -> Option<&T>;
+    /// Match for a name in the observers of all executors, returning the first match, mutable
+    fn match_any_name_mut<T>(&mut self, name: &str) -> Option<&mut T>;
+}
+
+impl MatchIndexedName for () {
+    fn match_indexed_name<T>(&self, _index: usize, _name: &str) -> Option<&T> {
+        None
+    }
+
+    fn match_indexed_name_mut<T>(&mut self, _index: usize, _name: &str) -> Option<&mut T> {
+        None
+    }
+
+    fn match_any_name<T>(&self, _name: &str) -> Option<&T> {
+        None
+    }
+
+    fn match_any_name_mut<T>(&mut self, _name: &str) -> Option<&mut T> {
+        None
+    }
+}
+
+#[allow(deprecated)]
+impl<Head, Tail> MatchIndexedName for (OwnedMutPtr<Head>, Tail)
+where
+    Head: MatchName,
+    Tail: MatchIndexedName,
+{
+    fn match_indexed_name<T>(&self, index: usize, name: &str) -> Option<&T> {
+        if index == 0 {
+            self.0.as_ref().match_name::<T>(name)
+        } else {
+            self.1.match_indexed_name::<T>(index - 1, name)
+        }
+    }
+
+    fn match_indexed_name_mut<T>(&mut self, index: usize, name: &str) -> Option<&mut T> {
+        if index == 0 {
+            self.0.as_mut().match_name_mut::<T>(name)
+        } else {
+            self.1.match_indexed_name_mut::<T>(index - 1, name)
+        }
+    }
+
+    fn match_any_name<T>(&self, name: &str) -> Option<&T> {
+        if let Some(t) = self.0.as_ref().match_name::<T>(name) {
+            Some(t)
+        } else {
+            self.1.match_any_name::<T>(name)
+        }
+    }
+
+    fn match_any_name_mut<T>(&mut self, name: &str) -> Option<&mut T> {
+        if let Some(t) = self.0.as_mut().match_name_mut::<T>(name) {
+            Some(t)
+        } else {
+            self.1.match_any_name_mut::<T>(name)
+        }
+    }
+}
+
+/// Splits a name created by [`diff_observer_name`] into the index of the executor and the name of the observer
+fn split_diff_observer_name(name: &str) -> Option<(usize, &str)> {
+    let (name, index) = name.rsplit_once(DIFF_OBSERVER_NAME_SEPARATOR)?;
+    Some((index.parse().ok()?, name))
+}
+
+/// A [`MultiDiffExecutor`] runs any number of executors after each other, with the same input.
+///
+/// If all executors agree on the [`ExitKind`], it is returned as is.
+/// Else, the executor returns an [`ExitKind::Diff`] with the exit kind of the first executor as `primary`,
+/// the one of the first diverging executor as `secondary`, and all executors with an exit kind different to the first executor in `diverged`.
+///
+/// The observers of all executors are reachable through the observers of this executor.
+/// As the executors usually use observers of the same name, use [`diff_observer_handle`] to get the one of a specific executor.
+/// The plain name matches the first executor with such an observer.
+#[derive(Debug)]
+pub struct MultiDiffExecutor<ET, OT>
+where
+    ET: DiffExecutorsTuple,
+{
+    executors: ET,
+    observers: UnsafeCell<MultiDiffObserversTuple<ET::ObserversPtrs, OT>>,
+    exit_kinds: Vec<ExitKind>,
+}
+
+impl<ET, OT> MultiDiffExecutor<ET, OT>
+where
+    ET: DiffExecutorsTuple,
+{
+    /// Create a new `MultiDiffExecutor`, wrapping the tuple of `executors`.
+    /// The `observers` are run around all executors, for example to compare their outputs in `post_exec`.
+    pub fn new(executors: ET, observers: OT) -> Self {
+        assert!(
+            ET::LEN <= 64,
+            "A MultiDiffExecutor supports at most 64 executors, got {}",
+            ET::LEN
+        );
+        let ptrs = executors.observers_ptrs();
+        Self {
+            executors,
+            observers: UnsafeCell::new(MultiDiffObserversTuple {
+                executors: ptrs,
+                observers,
+            }),
+            exit_kinds: Vec::with_capacity(ET::LEN),
+        }
+    }
+
+    /// The wrapped executors
+    pub fn executors(&self) -> &ET {
+        &self.executors
+    }
+
+    /// The wrapped executors, mutable
+    pub fn executors_mut(&mut self) -> &mut ET {
+        &mut self.executors
+    }
+
+    /// The [`ExitKind`]s of all executors in the last run
+    pub fn last_exit_kinds(&self) -> &[ExitKind] {
+        &self.exit_kinds
+    }
+
+    /// Merges the exit kinds of all executors, see [`MultiDiffExecutor`]
+    fn merge_exit_kinds(exit_kinds: &[ExitKind]) -> ExitKind {
+        let Some((&first, rest)) = exit_kinds.split_first() else {
+            return ExitKind::Ok;
+        };
+        let mut diverged = 0_u64;
+        let mut secondary = None;
+        for (i, exit_kind) in rest.iter().enumerate() {
+            if *exit_kind != first {
+                diverged |= 1 << (i + 1);
+                secondary.get_or_insert(*exit_kind);
+            }
+        }
+        match secondary {
+            None => first,
+            Some(secondary) => ExitKind::Diff {
+                primary: first.into(),
+                secondary: secondary.into(),
+                diverged,
+            },
+        }
+    }
+}
+
+impl<ET, EM, OT, S, Z> Executor<EM, Z> for MultiDiffExecutor<ET, OT>
+where
+    ET: DiffExecutorsRunTuple<EM, Z, S>,
+    Self: UsesState<State = S>,
+    EM: UsesState<State = S>,
+    S: UsesInput,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.exit_kinds.clear();
+        self.executors
+            .run_all(fuzzer, state, mgr, input, &mut self.exit_kinds)?;
+        Ok(Self::merge_exit_kinds(&self.exit_kinds))
+    }
+}
+
+impl<A, ET, OT> UsesState for MultiDiffExecutor<(A, ET), OT>
+where
+    A: UsesState,
+    (A, ET): DiffExecutorsTuple,
+{
+    type State = A::State;
+}
+
+impl<ET, OT> HasObservers for MultiDiffExecutor<ET, OT>
+where
+    ET: DiffExecutorsTuple,
+{
+    type Observers = MultiDiffObserversTuple<ET::ObserversPtrs, OT>;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        unsafe {
+            let observers = self.observers.get().as_mut().unwrap();
+            observers.executors = self.executors.observers_ptrs();
+            RefIndexable::from(self.observers.get().as_ref().unwrap())
+        }
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        let executors = self.executors.observers_ptrs();
+        let observers = self.observers.get_mut();
+        observers.executors = executors;
+        RefIndexable::from(observers)
+    }
+}
+
+/// The observers of a [`MultiDiffExecutor`]: its own observers, and the ones of all executors
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(
+    bound = "PT: serde::Serialize + serde::de::DeserializeOwned, OT: serde::Serialize + serde::de::DeserializeOwned"
+)]
+pub struct MultiDiffObserversTuple<PT, OT> {
+    executors: PT,
+    observers: OT,
+}
+
+impl<I, OT, PT, S> ObserversTuple<I, S> for MultiDiffObserversTuple<PT, OT>
+where
+    OT: ObserversTuple<I, S>,
+    PT: MatchIndexedName,
+{
+    fn pre_exec_all(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        self.observers.pre_exec_all(state, input)
+    }
+
+    fn post_exec_all(
+        &mut self,
+        state: &mut S,
+        input: &I,
+        exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.observers.post_exec_all(state, input, exit_kind)
+    }
+
+    fn pre_exec_child_all(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        self.observers.pre_exec_child_all(state, input)
+    }
+
+    fn post_exec_child_all(
+        &mut self,
+        state: &mut S,
+        input: &I,
+        exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.observers.post_exec_child_all(state, input, exit_kind)
+    }
+}
+
+impl<OT, PT> Deref for MultiDiffObserversTuple<PT, OT> {
+    type Target = OT;
+
+    fn deref(&self) -> &Self::Target {
+        &self.observers
+    }
+}
+
+impl<OT, PT> DerefMut for MultiDiffObserversTuple<PT, OT> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.observers
+    }
+}
+
+impl<OT, PT> MatchName for MultiDiffObserversTuple<PT, OT>
+where
+    OT: MatchName,
+    PT: MatchIndexedName,
+{
+    #[allow(deprecated)]
+    fn match_name<T>(&self, name: &str) -> Option<&T> {
+        if let Some(t) = self.observers.match_name::<T>(name) {
+            Some(t)
+        } else if let Some((index, inner)) = split_diff_observer_name(name) {
+            self.executors.match_indexed_name::<T>(index, inner)
+        } else {
+            self.executors.match_any_name::<T>(name)
+        }
+    }
+
+    #[allow(deprecated)]
+    fn match_name_mut<T>(&mut self, name: &str) -> Option<&mut T> {
+        if let Some(t) = self.observers.match_name_mut::<T>(name) {
+            Some(t)
+        } else if let Some((index, inner)) = split_diff_observer_name(name) {
+            self.executors.match_indexed_name_mut::<T>(index, inner)
+        } else {
+            self.executors.match_any_name_mut::<T>(name)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use libafl_bolts::tuples::{tuple_list, Handled};
+
+    use super::{diff_observer_handle, split_diff_observer_name, MultiDiffExecutor};
+    use crate::{
+        events::NopEventManager,
+        executors::{DiffExitKind, Executor, ExitKind, WithObservers},
+        fuzzer::NopFuzzer,
+        inputs::BytesInput,
+        observers::StdMapObserver,
+        state::{NopState, State, UsesState},
+        Error,
+    };
+
+    /// Returns a fixed [`ExitKind`]
+    #[derive(Debug)]
+    struct FixedExecutor<S> {
+        exit_kind: ExitKind,
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> FixedExecutor<S> {
+        fn new(exit_kind: ExitKind) -> Self {
+            Self {
+                exit_kind,
+                phantom: PhantomData,
+            }
+        }
+    }
+
+    impl<S> UsesState for FixedExecutor<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<EM, S, Z> Executor<EM, Z> for FixedExecutor<S>
+    where
+        EM: UsesState<State = S>,
+        S: State,
+        Z: UsesState<State = S>,
+    {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut Z,
+            _state: &mut S,
+            _mgr: &mut EM,
+            _input: &S::Input,
+        ) -> Result<ExitKind, Error> {
+            Ok(self.exit_kind)
+        }
+    }
+
+    #[test]
+    fn test_multi_diff_executor() {
+        let observer = |value| StdMapObserver::owned("edges", vec![value; 4]);
+        let edges = observer(0).handle();
+        let mut executor = MultiDiffExecutor::new(
+            tuple_list!(
+                WithObservers::new(FixedExecutor::new(ExitKind::Ok), tuple_list!(observer(0))),
+                WithObservers::new(
+                    FixedExecutor::new(ExitKind::Crash),
+                    tuple_list!(observer(1))
+                ),
+                WithObservers::new(FixedExecutor::new(ExitKind::Ok), tuple_list!(observer(2))),
+            ),
+            (),
+        );
+
+        let mut state = NopState::<BytesInput>::new();
+        let exit_kind = executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut state,
+                &mut NopEventManager::new(),
+                &BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(
+            exit_kind,
+            ExitKind::Diff {
+                primary: DiffExitKind::Ok,
+                secondary: DiffExitKind::Crash,
+                diverged: 0b010,
+            }
+        );
+        assert_eq!(
+            executor.last_exit_kinds(),
+            [ExitKind::Ok, ExitKind::Crash, ExitKind::Ok]
+        );
+
+        let handle = diff_observer_handle(2, &edges);
+        assert_eq!(handle.name(), "edges#2");
+        assert_eq!(split_diff_observer_name(handle.name()), Some((2, "edges")));
+        assert_eq!(split_diff_observer_name("edges"), None);
+    }
+}
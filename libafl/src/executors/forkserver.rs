@@ -9,12 +9,13 @@ use core::{
 use std::{
     env,
     ffi::{OsStr, OsString},
+    fs,
     io::{self, ErrorKind, Read, Write},
     os::{
         fd::{AsRawFd, BorrowedFd},
         unix::{io::RawFd, process::CommandExt},
     },
-    path::Path,
+    path::{Path, PathBuf},
     process::{Child, Command, Stdio},
 };
 
@@ -38,15 +39,15 @@ use nix::{
 };
 
 use super::HasTimeout;
+#[cfg(all(feature = "regex", target_os = "linux"))]
+use crate::observers::HangBacktraceObserver;
 #[cfg(feature = "regex")]
 use crate::observers::{
     get_asan_runtime_flags, get_asan_runtime_flags_with_log_path, AsanBacktraceObserver,
 };
 use crate::{
-    executors::{Executor, ExitKind, HasObservers},
-    inputs::{
-        BytesInput, HasTargetBytes, Input, NopTargetBytesConverter, TargetBytesConverter, UsesInput,
-    },
+    executors::{hooks::ExecutorHooksTuple, Executor, ExitKind, ExitSignalMetadata, HasObservers},
+    inputs::{BytesInput, Input, NopTargetBytesConverter, TargetBytesConverter, UsesInput},
     mutators::Tokens,
     observers::{MapObserver, Observer, ObserversTuple},
     state::{HasExecutions, State, UsesState},
@@ -78,6 +79,27 @@ const FS_NEW_OPT_AUTODTCT: i32 = 0x00000800_u32 as i32;
 #[allow(clippy::cast_possible_wrap)]
 const FS_OPT_AUTODTCT: i32 = 0x10000000_u32 as i32;
 
+/// The option flags of the old forkserver protocol this version of `LibAFL` understands
+#[allow(clippy::cast_sign_loss)]
+const FS_OPT_KNOWN: u32 =
+    (FS_OPT_ENABLED | FS_OPT_MAPSIZE | FS_OPT_SHDMEM_FUZZ | FS_OPT_AUTODTCT) as u32 | 0x00fffffe;
+/// The option flags of the new forkserver protocol this version of `LibAFL` understands
+#[allow(clippy::cast_sign_loss)]
+const FS_NEW_OPT_KNOWN: u32 =
+    (FS_NEW_OPT_MAPSIZE | FS_NEW_OPT_SHDMEM_FUZZ | FS_NEW_OPT_AUTODTCT) as u32;
+
+/// The signature AFL++ compilers embed in targets using persistent mode (`__AFL_LOOP`)
+const PERSIST_SIG: &[u8] = b"##SIG_AFL_PERSISTENT##";
+/// The signature AFL++ compilers embed in targets using a deferred forkserver (`__AFL_INIT`)
+const DEFER_SIG: &[u8] = b"##SIG_AFL_DEFER_FORKSRV##";
+/// Instrumented targets read the id of the coverage map from this environment variable
+const SHM_ENV_VAR: &[u8] = b"__AFL_SHM_ID";
+
+/// Overrides the timeout of the forkserver handshake, in milliseconds
+const FORKSRV_INIT_TMOUT_ENV: &str = "AFL_FORKSRV_INIT_TMOUT";
+/// By default, wait this many execution timeouts for the forkserver handshake, like AFL++ does
+const FORK_WAIT_MULT: u32 = 10;
+
 #[allow(clippy::cast_possible_wrap)]
 const FS_ERROR_MAP_SIZE: i32 = 1_u32 as i32;
 #[allow(clippy::cast_possible_wrap)]
@@ -315,6 +337,136 @@ const fn fs_opt_get_mapsize(x: i32) -> i32 {
     ((x & 0x00fffffe) >> 1) + 1
 }
 
+/// The options a forkserver announced during the handshake
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ForkserverOptions {
+    /// The version of the forkserver protocol, `None` for the old protocol of AFL++ before 4.20c
+    pub version: Option<u32>,
+    /// The coverage map size reported by the target
+    pub map_size: Option<usize>,
+    /// The target can read the testcases from shared memory
+    pub shmem_fuzz: bool,
+    /// The target sends an autodictionary
+    pub autodict: bool,
+    /// The option flags this version of `LibAFL` does not understand
+    pub unknown_flags: u32,
+}
+
+impl ForkserverOptions {
+    /// Parses the option flags sent by a forkserver speaking protocol `version`, AFL++ 4.20c and newer.
+    /// The map size is sent separately in this protocol.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn from_status(version: u32, status: i32) -> Self {
+        Self {
+            version: Some(version),
+            map_size: None,
+            shmem_fuzz: status & FS_NEW_OPT_SHDMEM_FUZZ != 0,
+            autodict: status & FS_NEW_OPT_AUTODTCT != 0,
+            unknown_flags: status as u32 & !FS_NEW_OPT_KNOWN,
+        }
+    }
+
+    /// Parses the hello message of a forkserver speaking the old protocol, AFL++ before 4.20c
+    #[must_use]
+    #[allow(clippy::cast_sign_loss)]
+    pub fn from_old_status(status: i32) -> Self {
+        if status & FS_OPT_ENABLED != FS_OPT_ENABLED {
+            return Self::default();
+        }
+        Self {
+            version: None,
+            map_size: (status & FS_OPT_MAPSIZE == FS_OPT_MAPSIZE)
+                .then(|| fs_opt_get_mapsize(status) as usize),
+            shmem_fuzz: status & FS_OPT_SHDMEM_FUZZ == FS_OPT_SHDMEM_FUZZ,
+            autodict: status & FS_OPT_AUTODTCT == FS_OPT_AUTODTCT,
+            unknown_flags: status as u32 & !FS_OPT_KNOWN,
+        }
+    }
+}
+
+/// The signatures AFL++ compilers embed in a target, telling how it wants to be driven
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TargetSignatures {
+    /// The target is instrumented, it reads the coverage map from `__AFL_SHM_ID`
+    pub instrumented: bool,
+    /// The target uses persistent mode, `__AFL_LOOP`
+    pub persistent: bool,
+    /// The target starts the forkserver itself, with `__AFL_INIT`
+    pub deferred: bool,
+}
+
+impl TargetSignatures {
+    /// Scans the binary of `program`, which is looked up in `PATH` if it is a bare name
+    pub fn scan<P>(program: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let program = program.as_ref();
+        let Some(path) = find_program(program) else {
+            return Err(Error::illegal_argument(format!(
+                "Could not find the target {}",
+                program.display()
+            )));
+        };
+        let binary = fs::read(path)?;
+        Ok(Self::from_binary(&binary))
+    }
+
+    /// Finds the signatures in the contents of a `binary`
+    #[must_use]
+    pub fn from_binary(binary: &[u8]) -> Self {
+        let contains = |sig: &[u8]| binary.windows(sig.len()).any(|window| window == sig);
+        Self {
+            instrumented: contains(SHM_ENV_VAR),
+            persistent: contains(PERSIST_SIG),
+            deferred: contains(DEFER_SIG),
+        }
+    }
+}
+
+/// How often the persistent children of a [`ForkserverExecutor`] were restarted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PersistentStats {
+    /// The executions since the forkserver started
+    pub executions: u64,
+    /// The children started after the first one, because they exited, crashed, timed out,
+    /// or ran out of iterations of `__AFL_LOOP`
+    pub restarts: u64,
+    /// The restarts forced by the persistent loop limit of the executor
+    pub forced_restarts: u64,
+}
+
+impl PersistentStats {
+    /// The average number of restarts per `executions` executions
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn restarts_per(&self, executions: u64) -> f64 {
+        if self.executions == 0 {
+            0.0
+        } else {
+            self.restarts as f64 * executions as f64 / self.executions as f64
+        }
+    }
+
+    /// The average number of executions of a child before it is restarted
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn executions_per_child(&self) -> f64 {
+        self.executions as f64 / (self.restarts + 1) as f64
+    }
+}
+
+/// The path of `program`, searching `PATH` if it is a bare name like the shell does
+fn find_program(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|path| path.is_file())
+}
+
 #[allow(clippy::fn_params_excessive_bools)]
 impl Forkserver {
     /// Create a new [`Forkserver`]
@@ -608,7 +760,7 @@ impl Forkserver {
 ///
 /// Shared memory feature is also available, but you have to set things up in your code.
 /// Please refer to AFL++'s docs. <https://github.com/AFLplusplus/AFLplusplus/blob/stable/instrumentation/README.persistent_mode.md>
-pub struct ForkserverExecutor<TC, OT, S, SP>
+pub struct ForkserverExecutor<TC, OT, S, SP, HT = ()>
 where
     SP: ShMemProvider,
 {
@@ -619,6 +771,8 @@ where
     uses_shmem_testcase: bool,
     forkserver: Forkserver,
     observers: OT,
+    /// The hooks run before and after each execution
+    hooks: HT,
     map: Option<SP::ShMem>,
     phantom: PhantomData<S>,
     map_size: Option<usize>,
@@ -626,11 +780,41 @@ where
     max_input_size: usize,
     #[cfg(feature = "regex")]
     asan_obs: Handle<AsanBacktraceObserver>,
+    /// Captures the stacks of timed out children
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    hang_obs: Option<Handle<HangBacktraceObserver>>,
     timeout: TimeSpec,
     crash_exitcode: Option<i8>,
+    forkserver_options: ForkserverOptions,
+    /// Restart persistent children after this many executions
+    persistent_loop_limit: Option<u64>,
+    /// The executions of the current persistent child
+    persistent_iterations: u64,
+    last_child_pid: Option<Pid>,
+    persistent_stats: PersistentStats,
+    /// Set if the shared memory testcase map may grow
+    shmem_respawn: Option<ShmemRespawn<SP>>,
+}
+
+/// The settings of the [`ForkserverExecutorBuilder`] needed to restart the forkserver
+/// with a larger shared memory testcase map
+#[derive(Debug)]
+#[allow(clippy::struct_excessive_bools)]
+struct ShmemRespawn<SP> {
+    shmem_provider: SP,
+    envs: Vec<(OsString, OsString)>,
+    debug_child: bool,
+    use_stdin: bool,
+    is_persistent: bool,
+    is_deferred_frksrv: bool,
+    memlimit: u64,
+    kill_signal: Option<Signal>,
+    handshake_timeout: Duration,
+    #[cfg(feature = "regex")]
+    asan_obs: Option<Handle<AsanBacktraceObserver>>,
 }
 
-impl<TC, OT, S, SP> Debug for ForkserverExecutor<TC, OT, S, SP>
+impl<TC, OT, S, SP, HT> Debug for ForkserverExecutor<TC, OT, S, SP, HT>
 where
     TC: Debug,
     OT: Debug,
@@ -660,7 +844,7 @@ impl ForkserverExecutor<(), (), (), UnixShMemProvider> {
     }
 }
 
-impl<TC, OT, S, SP> ForkserverExecutor<TC, OT, S, SP>
+impl<TC, OT, S, SP, HT> ForkserverExecutor<TC, OT, S, SP, HT>
 where
     OT: ObserversTuple<S::Input, S>,
     S: UsesInput,
@@ -697,6 +881,41 @@ where
         self.map_size
     }
 
+    /// The options the forkserver announced during the handshake
+    pub fn forkserver_options(&self) -> &ForkserverOptions {
+        &self.forkserver_options
+    }
+
+    /// The number of executions after which a persistent child is restarted, if limited
+    pub fn persistent_loop_limit(&self) -> Option<u64> {
+        self.persistent_loop_limit
+    }
+
+    /// Restart persistent children after `limit` executions, on top of the count passed to `__AFL_LOOP`.
+    /// `None` only restarts them when they exit, crash or time out.
+    pub fn set_persistent_loop_limit(&mut self, limit: Option<u64>) {
+        self.persistent_loop_limit = limit.map(|limit| limit.max(1));
+    }
+
+    /// Halves the persistent loop limit, for example when calibration finds the target unstable.
+    /// Without a limit, this starts from the average executions of a child so far.
+    /// Returns the new limit.
+    pub fn shrink_persistent_loop_limit(&mut self) -> u64 {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let current = self
+            .persistent_loop_limit
+            .unwrap_or(self.persistent_stats.executions_per_child() as u64);
+        let limit = (current / 2).max(1);
+        log::info!("Shrinking the persistent loop limit to {limit}");
+        self.persistent_loop_limit = Some(limit);
+        limit
+    }
+
+    /// How often the persistent children were restarted
+    pub fn persistent_stats(&self) -> &PersistentStats {
+        &self.persistent_stats
+    }
+
     /// Execute input and increase the execution counter.
     #[inline]
     fn execute_input(&mut self, state: &mut S, input: &TC::Input) -> Result<ExitKind, Error>
@@ -705,7 +924,12 @@ where
     {
         *state.executions_mut() += 1;
 
-        self.execute_input_uncounted(input)
+        let exit_kind = self.execute_input_uncounted(input)?;
+        let status = self.forkserver.status();
+        if exit_kind == ExitKind::Crash && libc::WIFSIGNALED(status) {
+            ExitSignalMetadata::record_last(libc::WTERMSIG(status));
+        }
+        Ok(exit_kind)
     }
 
     /// Execute input, but side-step the execution counter.
@@ -713,8 +937,6 @@ where
     fn execute_input_uncounted(&mut self, input: &TC::Input) -> Result<ExitKind, Error> {
         let mut exit_kind = ExitKind::Ok;
 
-        let last_run_timed_out = self.forkserver.last_run_timed_out_raw();
-
         let mut input_bytes = self.target_bytes_converter.to_target_bytes(input);
         let mut input_size = input_bytes.as_slice().len();
         if input_size > self.max_input_size {
@@ -722,11 +944,9 @@ where
             input_size = self.max_input_size;
         } else if input_size < self.min_input_size {
             // Extend like AFL++ does
+            let mut input_bytes_copy = vec![0; self.min_input_size];
+            input_bytes_copy[..input_size].copy_from_slice(input_bytes.as_slice());
             input_size = self.min_input_size;
-            let mut input_bytes_copy = Vec::with_capacity(input_size);
-            input_bytes_copy
-                .as_slice_mut()
-                .copy_from_slice(input_bytes.as_slice());
             input_bytes = OwnedSlice::from(input_bytes_copy);
         }
         let input_size_in_bytes = input_size.to_ne_bytes();
@@ -735,6 +955,9 @@ where
                 self.map.is_some(),
                 "The uses_shmem_testcase() bool can only exist when a map is set"
             );
+            if self.shmem_respawn.is_some() && input_size > self.shmem_testcase_capacity() {
+                self.grow_shmem_testcase(input_size)?;
+            }
             // # Safety
             // Struct can never be created when uses_shmem_testcase is true and map is none.
             let map = unsafe { self.map.as_mut().unwrap_unchecked() };
@@ -748,6 +971,7 @@ where
                 .write_buf(&input_bytes.as_slice()[..input_size])?;
         }
 
+        let last_run_timed_out = self.forkserver.last_run_timed_out_raw();
         self.forkserver.set_last_run_timed_out(false);
         if let Err(err) = self.forkserver.write_ctl(last_run_timed_out) {
             return Err(Error::unknown(format!(
@@ -767,7 +991,17 @@ where
             ));
         }
 
-        self.forkserver.set_child_pid(Pid::from_raw(pid));
+        let pid = Pid::from_raw(pid);
+        self.forkserver.set_child_pid(pid);
+        if self.last_child_pid != Some(pid) {
+            if self.last_child_pid.is_some() {
+                self.persistent_stats.restarts += 1;
+            }
+            self.last_child_pid = Some(pid);
+            self.persistent_iterations = 0;
+        }
+        self.persistent_iterations += 1;
+        self.persistent_stats.executions += 1;
 
         if let Some(status) = self.forkserver.read_st_timed(&self.timeout)? {
             self.forkserver.set_status(status);
@@ -780,12 +1014,21 @@ where
                 exit_kind = ExitKind::Crash;
                 #[cfg(feature = "regex")]
                 if let Some(asan_observer) = self.observers.get_mut(&self.asan_obs) {
-                    asan_observer.parse_asan_output_from_asan_log_file(pid)?;
+                    asan_observer.parse_asan_output_from_asan_log_file(pid.as_raw())?;
                 }
             }
         } else {
             self.forkserver.set_last_run_timed_out(true);
 
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            if let Some(hang_obs) = &self.hang_obs {
+                if let Some(hang_observer) = self.observers.get_mut(hang_obs) {
+                    if let Err(err) = hang_observer.capture(self.forkserver.child_pid()) {
+                        log::warn!("Could not capture the stacks of the hanging child: {err}");
+                    }
+                }
+            }
+
             // We need to kill the child in case he has timed out, or we can't get the correct pid in the next call to self.executor.forkserver_mut().read_st()?
             let _ = kill(self.forkserver().child_pid(), self.forkserver.kill_signal);
             if let Err(err) = self.forkserver.read_st() {
@@ -798,10 +1041,88 @@ where
 
         if !libc::WIFSTOPPED(self.forkserver().status()) {
             self.forkserver.reset_child_pid();
+        } else if self
+            .persistent_loop_limit
+            .is_some_and(|limit| self.persistent_iterations >= limit)
+        {
+            // Kill the stopped child, the forkserver reaps it and forks a fresh one
+            // once we tell it about the kill with the next request.
+            let _ = kill(pid, Signal::SIGKILL);
+            self.forkserver.set_last_run_timed_out(true);
+            self.forkserver.reset_child_pid();
+            self.persistent_stats.forced_restarts += 1;
         }
 
         Ok(exit_kind)
     }
+
+    /// The number of input bytes fitting into the shared memory testcase map
+    fn shmem_testcase_capacity(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| {
+            map.as_slice().len().saturating_sub(SHMEM_FUZZ_HDR_SIZE)
+        })
+    }
+
+    /// Restarts the forkserver with a shared memory testcase map large enough for `input_size` bytes.
+    /// The map grows in powers of two, up to the max input size.
+    fn grow_shmem_testcase(&mut self, input_size: usize) -> Result<(), Error> {
+        let capacity = self.shmem_testcase_capacity();
+        let new_capacity = input_size
+            .next_power_of_two()
+            .max(capacity)
+            .min(self.max_input_size);
+        log::info!(
+            "Input of {input_size} bytes exceeds the shared memory testcase map of {capacity} bytes, \
+            restarting the forkserver with {new_capacity} bytes"
+        );
+
+        let respawn = self
+            .shmem_respawn
+            .as_mut()
+            .expect("The shared memory testcase map can only grow with a respawn config");
+        let mut builder = ForkserverExecutorBuilder {
+            program: Some(self.target.clone()),
+            arguments: self.args.clone(),
+            envs: respawn.envs.clone(),
+            debug_child: respawn.debug_child,
+            use_stdin: respawn.use_stdin,
+            uses_shmem_testcase: false,
+            is_persistent: respawn.is_persistent,
+            is_deferred_frksrv: respawn.is_deferred_frksrv,
+            autotokens: None,
+            input_filename: None,
+            shmem_provider: Some(&mut respawn.shmem_provider),
+            max_input_size: self.max_input_size,
+            min_input_size: self.min_input_size,
+            initial_shmem_size: None,
+            map_size: self.map_size,
+            memlimit: respawn.memlimit,
+            kill_signal: respawn.kill_signal,
+            timeout: None,
+            handshake_timeout: Some(respawn.handshake_timeout),
+            detect_modes: false,
+            forkserver_options: ForkserverOptions::default(),
+            persistent_loop_limit: None,
+            #[cfg(feature = "regex")]
+            asan_obs: respawn.asan_obs.clone(),
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: None,
+            crash_exitcode: self.crash_exitcode,
+            target_bytes_converter: (),
+        };
+        let (forkserver, map) = builder.spawn_forkserver(&self.input_file, new_capacity)?;
+        if !builder.uses_shmem_testcase {
+            return Err(Error::illegal_state(
+                "The restarted target no longer requested the shared memory testcase",
+            ));
+        }
+
+        // Dropping the old forkserver kills it
+        self.forkserver = forkserver;
+        self.map = map;
+        self.forkserver_options = builder.forkserver_options;
+        Ok(())
+    }
 }
 
 /// The builder for `ForkserverExecutor`
@@ -821,11 +1142,19 @@ pub struct ForkserverExecutorBuilder<'a, TC, SP> {
     shmem_provider: Option<&'a mut SP>,
     max_input_size: usize,
     min_input_size: usize,
+    initial_shmem_size: Option<usize>,
     map_size: Option<usize>,
+    memlimit: u64,
     kill_signal: Option<Signal>,
     timeout: Option<Duration>,
+    handshake_timeout: Option<Duration>,
+    detect_modes: bool,
+    forkserver_options: ForkserverOptions,
+    persistent_loop_limit: Option<u64>,
     #[cfg(feature = "regex")]
     asan_obs: Option<Handle<AsanBacktraceObserver>>,
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    hang_obs: Option<Handle<HangBacktraceObserver>>,
     crash_exitcode: Option<i8>,
     target_bytes_converter: TC,
 }
@@ -840,13 +1169,32 @@ where
     /// in case no input file is specified.
     /// If `debug_child` is set, the child will print to `stdout`/`stderr`.
     #[allow(clippy::pedantic)]
-    pub fn build<OT, S>(mut self, observers: OT) -> Result<ForkserverExecutor<TC, OT, S, SP>, Error>
+    pub fn build<OT, S>(self, observers: OT) -> Result<ForkserverExecutor<TC, OT, S, SP>, Error>
+    where
+        OT: ObserversTuple<S::Input, S>,
+        S: UsesInput,
+        S::Input: Input,
+        TC: TargetBytesConverter,
+        SP: ShMemProvider,
+    {
+        self.build_with_hooks(observers, ())
+    }
+
+    /// Builds `ForkserverExecutor` like [`Self::build`],
+    /// running the [`ExecutorHooksTuple`] `hooks` before and after each execution.
+    #[allow(clippy::pedantic)]
+    pub fn build_with_hooks<OT, S, HT>(
+        mut self,
+        observers: OT,
+        hooks: HT,
+    ) -> Result<ForkserverExecutor<TC, OT, S, SP, HT>, Error>
     where
         OT: ObserversTuple<S::Input, S>,
         S: UsesInput,
         S::Input: Input,
         TC: TargetBytesConverter,
         SP: ShMemProvider,
+        HT: ExecutorHooksTuple<S>,
     {
         let (forkserver, input_file, map) = self.build_helper()?;
 
@@ -885,6 +1233,7 @@ where
             uses_shmem_testcase: self.uses_shmem_testcase,
             forkserver,
             observers,
+            hooks,
             map,
             phantom: PhantomData,
             map_size: self.map_size,
@@ -895,7 +1244,15 @@ where
                 .asan_obs
                 .clone()
                 .unwrap_or(AsanBacktraceObserver::default().handle()),
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: self.hang_obs.clone(),
             crash_exitcode: self.crash_exitcode,
+            forkserver_options: self.forkserver_options,
+            persistent_loop_limit: self.persistent_loop_limit,
+            persistent_iterations: 0,
+            last_child_pid: None,
+            persistent_stats: PersistentStats::default(),
+            shmem_respawn: self.shmem_respawn(),
             target_bytes_converter: self.target_bytes_converter,
         })
     }
@@ -912,7 +1269,8 @@ where
         A: Observer<S::Input, S> + AsMut<MO>,
         OT: ObserversTuple<S::Input, S> + Prepend<MO>,
         S: UsesInput,
-        S::Input: Input + HasTargetBytes,
+        S::Input: Input,
+        TC: TargetBytesConverter,
         SP: ShMemProvider,
     {
         let (forkserver, input_file, map) = self.build_helper()?;
@@ -950,6 +1308,7 @@ where
             uses_shmem_testcase: self.uses_shmem_testcase,
             forkserver,
             observers,
+            hooks: (),
             map,
             phantom: PhantomData,
             map_size: self.map_size,
@@ -960,7 +1319,15 @@ where
                 .asan_obs
                 .clone()
                 .unwrap_or(AsanBacktraceObserver::default().handle()),
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: self.hang_obs.clone(),
             crash_exitcode: self.crash_exitcode,
+            forkserver_options: self.forkserver_options,
+            persistent_loop_limit: self.persistent_loop_limit,
+            persistent_iterations: 0,
+            last_child_pid: None,
+            persistent_stats: PersistentStats::default(),
+            shmem_respawn: self.shmem_respawn(),
             target_bytes_converter: self.target_bytes_converter,
         })
     }
@@ -980,14 +1347,36 @@ where
 
         let input_file = InputFile::create(input_filename)?;
 
+        if self.detect_modes {
+            self.apply_target_signatures()?;
+        }
+
+        let shmem_size = self
+            .initial_shmem_size
+            .map_or(self.max_input_size, |size| size.min(self.max_input_size));
+        let (forkserver, map) = self.spawn_forkserver(&input_file, shmem_size)?;
+        Ok((forkserver, input_file, map))
+    }
+
+    /// Spawns the forkserver and performs the handshake,
+    /// with a shared memory testcase map of `shmem_size` bytes if a `shmem_provider` is set.
+    #[allow(clippy::pedantic)]
+    fn spawn_forkserver(
+        &mut self,
+        input_file: &InputFile,
+        shmem_size: usize,
+    ) -> Result<(Forkserver, Option<SP::ShMem>), Error>
+    where
+        SP: ShMemProvider,
+    {
         let map = match &mut self.shmem_provider {
             None => None,
             Some(provider) => {
                 // setup shared memory
-                let mut shmem = provider.new_shmem(self.max_input_size + SHMEM_FUZZ_HDR_SIZE)?;
+                let mut shmem = provider.new_shmem(shmem_size + SHMEM_FUZZ_HDR_SIZE)?;
                 shmem.write_to_env("__AFL_SHM_FUZZ_ID")?;
 
-                let size_in_bytes = (self.max_input_size + SHMEM_FUZZ_HDR_SIZE).to_ne_bytes();
+                let size_in_bytes = (shmem_size + SHMEM_FUZZ_HDR_SIZE).to_ne_bytes();
                 shmem.as_slice_mut()[..4].clone_from_slice(&size_in_bytes[..4]);
                 Some(shmem)
             }
@@ -1000,7 +1389,7 @@ where
                 self.envs.clone(),
                 input_file.as_raw_fd(),
                 self.use_stdin,
-                0,
+                self.memlimit,
                 self.is_persistent,
                 self.is_deferred_frksrv,
                 self.asan_obs.is_some(),
@@ -1016,9 +1405,18 @@ where
         };
 
         // Initial handshake, read 4-bytes hello message from the forkserver.
-        let version_status = forkserver.read_st().map_err(|err| {
-            Error::illegal_state(format!("{FAILED_TO_START_FORKSERVER_MSG}: {err:?}"))
-        })?;
+        // A deferred forkserver only sends it once the target reaches `__AFL_INIT()`.
+        let handshake_timeout = self.effective_handshake_timeout()?;
+        let version_status = match forkserver.read_st_timed(&handshake_timeout.into()) {
+            Ok(Some(status)) => status,
+            Ok(None) => {
+                return Err(self.handshake_error(
+                    &format!("the target did not answer within {handshake_timeout:?}"),
+                    true,
+                ))
+            }
+            Err(err) => return Err(self.handshake_error(&format!("{err:?}"), false)),
+        };
 
         if (version_status & FS_NEW_ERROR) == FS_NEW_ERROR {
             report_error_and_exit(version_status & 0x0000ffff)?;
@@ -1030,7 +1428,99 @@ where
         } else {
             self.initialize_forkserver(version_status, map.as_ref(), &mut forkserver)?;
         }
-        Ok((forkserver, input_file, map))
+        Ok((forkserver, map))
+    }
+
+    /// The timeout of the forkserver handshake: the configured `handshake_timeout`,
+    /// else `AFL_FORKSRV_INIT_TMOUT` in milliseconds, else ten times the execution timeout.
+    fn effective_handshake_timeout(&self) -> Result<Duration, Error> {
+        if let Some(handshake_timeout) = self.handshake_timeout {
+            return Ok(handshake_timeout);
+        }
+        if let Ok(init_tmout) = env::var(FORKSRV_INIT_TMOUT_ENV) {
+            let millis = init_tmout.trim().parse().map_err(|_| {
+                Error::illegal_argument(format!(
+                    "{FORKSRV_INIT_TMOUT_ENV} must be a number of milliseconds, got {init_tmout}"
+                ))
+            })?;
+            return Ok(Duration::from_millis(millis));
+        }
+        Ok(self.timeout.unwrap_or(Duration::from_secs(5)) * FORK_WAIT_MULT)
+    }
+
+    /// Sets persistent mode and the deferred forkserver according to the signatures in the target
+    fn apply_target_signatures(&mut self) -> Result<(), Error> {
+        let Some(program) = &self.program else {
+            return Err(Error::illegal_argument(
+                "ForkserverExecutorBuilder::build: target file not found".to_string(),
+            ));
+        };
+        let signatures = TargetSignatures::scan(program)?;
+        let program = Path::new(program).display();
+        log::info!("Detected forkserver modes of {program}: {signatures:?}");
+        if !signatures.instrumented {
+            log::warn!(
+                "{program} does not seem to be instrumented, the forkserver will probably not start"
+            );
+        }
+        self.is_persistent = signatures.persistent;
+        self.is_deferred_frksrv = signatures.deferred;
+        Ok(())
+    }
+
+    /// Explains why the forkserver handshake failed, comparing the configuration with the target
+    fn handshake_error(&self, reason: &str, timed_out: bool) -> Error {
+        let mut msg = format!("{FAILED_TO_START_FORKSERVER_MSG}: {reason}.");
+        let signatures = self
+            .program
+            .as_ref()
+            .and_then(|program| TargetSignatures::scan(program).ok());
+        if let Some(signatures) = signatures {
+            if !signatures.instrumented {
+                msg.push_str(
+                    " The target does not seem to be instrumented, compile it with afl-cc.",
+                );
+            }
+            if signatures.deferred && !self.is_deferred_frksrv {
+                msg.push_str(" The target starts the forkserver with `__AFL_INIT()`, call `is_deferred_frksrv(true)` or `detect_forkserver_modes(true)` on the builder.");
+            } else if !signatures.deferred && self.is_deferred_frksrv {
+                msg.push_str(
+                    " `is_deferred_frksrv` is set, but the target never calls `__AFL_INIT()`.",
+                );
+            }
+            if signatures.persistent && !self.is_persistent {
+                msg.push_str(" The target uses persistent mode, call `is_persistent(true)` or `detect_forkserver_modes(true)` on the builder.");
+            } else if !signatures.persistent && self.is_persistent {
+                msg.push_str(
+                    " `is_persistent` is set, but the target does not use `__AFL_LOOP()`.",
+                );
+            }
+        }
+        if timed_out && self.is_deferred_frksrv {
+            msg.push_str(" A deferred forkserver only answers once `__AFL_INIT()` is reached, increase the `handshake_timeout` if the target initializes slowly.");
+        }
+        Error::illegal_state(msg)
+    }
+
+    /// The settings to restart the forkserver with, if the shared memory testcase map may grow
+    fn shmem_respawn(&self) -> Option<ShmemRespawn<SP>>
+    where
+        SP: ShMemProvider,
+    {
+        self.initial_shmem_size?;
+        Some(ShmemRespawn {
+            handshake_timeout: self.effective_handshake_timeout().ok()?,
+            shmem_provider: (**self.shmem_provider.as_ref()?).clone(),
+            envs: self.envs.clone(),
+            debug_child: self.debug_child,
+            use_stdin: self.use_stdin,
+            is_persistent: self.is_persistent,
+            is_deferred_frksrv: self.is_deferred_frksrv,
+            memlimit: self.memlimit,
+            kill_signal: self.kill_signal,
+            #[cfg(feature = "regex")]
+            asan_obs: self.asan_obs.clone(),
+        })
     }
 
     fn is_old_forkserver(version_status: i32) -> bool {
@@ -1056,9 +1546,10 @@ where
                 // good, do nothing
             }
             _ => {
-                return Err(Error::illegal_state(
-                    "Fork server version is not supported. Recompile the target.",
-                ));
+                return Err(Error::illegal_state(format!(
+                    "Fork server protocol version {version} is not supported, only versions \
+                    {FS_NEW_VERSION_MIN} to {FS_NEW_VERSION_MAX} are. Recompile the target with a matching afl-cc."
+                )));
             }
         }
 
@@ -1078,24 +1569,37 @@ where
         let status = forkserver.read_st().map_err(|err| {
             Error::illegal_state(format!("Reading from forkserver failed: {err:?}"))
         })?;
+        let mut options = ForkserverOptions::from_status(version, status);
+        if options.unknown_flags != 0 {
+            log::warn!(
+                "The forkserver announced unknown options {:#x}, ignoring them",
+                options.unknown_flags
+            );
+        }
 
         if status & FS_NEW_OPT_MAPSIZE == FS_NEW_OPT_MAPSIZE {
             let fsrv_map_size = forkserver.read_st().map_err(|err| {
                 Error::illegal_state(format!("Failed to read map size from forkserver: {err:?}"))
             })?;
-            self.set_map_size(fsrv_map_size)?;
+            options.map_size = Some(self.set_map_size(fsrv_map_size)?);
         }
 
-        if status & FS_NEW_OPT_SHDMEM_FUZZ != 0 {
+        if options.shmem_fuzz {
             if map.is_some() {
                 log::info!("Using SHARED MEMORY FUZZING feature.");
                 self.uses_shmem_testcase = true;
             } else {
                 return Err(Error::illegal_state(
-                    "Target requested sharedmem fuzzing, but you didn't prepare shmem",
+                    "The target reads its testcases from shared memory (`__AFL_FUZZ_TESTCASE_BUF`), \
+                    but no `shmem_provider` was set on the builder to create it",
                 ));
             }
+        } else if map.is_some() {
+            log::info!(
+                "The target does not support shared memory testcases, passing them via file or stdin"
+            );
         }
+        self.forkserver_options = options;
 
         if status & FS_NEW_OPT_AUTODTCT != 0 {
             // Here unlike shmem input fuzzing, we are forced to read things
@@ -1130,7 +1634,8 @@ where
 
         if aflx != keep {
             return Err(Error::unknown(format!(
-                "Error in forkserver communication ({aflx:?}=>{keep:?})",
+                "Error in forkserver communication ({aflx:?}=>{keep:?}), the target did not confirm the handshake. \
+                Make sure the target was compiled with a single AFL++ version.",
             )));
         }
         Ok(())
@@ -1145,10 +1650,18 @@ where
         map: Option<&SP::ShMem>,
         forkserver: &mut Forkserver,
     ) -> Result<(), Error> {
+        let mut options = ForkserverOptions::from_old_status(status);
+        if options.unknown_flags != 0 {
+            log::warn!(
+                "The forkserver announced unknown options {:#x}, ignoring them",
+                options.unknown_flags
+            );
+        }
         if status & FS_OPT_ENABLED == FS_OPT_ENABLED && status & FS_OPT_MAPSIZE == FS_OPT_MAPSIZE {
             let fsrv_map_size = fs_opt_get_mapsize(status);
-            self.set_map_size(fsrv_map_size)?;
+            options.map_size = Some(self.set_map_size(fsrv_map_size)?);
         }
+        self.forkserver_options = options;
 
         // Only with SHMEM or AUTODTCT we can send send_status back or it breaks!
         // If forkserver is responding, we then check if there's any option enabled.
@@ -1161,10 +1674,14 @@ where
         {
             let mut send_status = FS_OPT_ENABLED;
 
-            if (status & FS_OPT_SHDMEM_FUZZ == FS_OPT_SHDMEM_FUZZ) && map.is_some() {
-                log::info!("Using SHARED MEMORY FUZZING feature.");
-                send_status |= FS_OPT_SHDMEM_FUZZ;
-                self.uses_shmem_testcase = true;
+            if status & FS_OPT_SHDMEM_FUZZ == FS_OPT_SHDMEM_FUZZ {
+                if map.is_some() {
+                    log::info!("Using SHARED MEMORY FUZZING feature.");
+                    send_status |= FS_OPT_SHDMEM_FUZZ;
+                    self.uses_shmem_testcase = true;
+                } else {
+                    log::warn!("The target supports shared memory testcases, set a `shmem_provider` on the builder to use them");
+                }
             }
 
             if (status & FS_OPT_AUTODTCT == FS_OPT_AUTODTCT) && self.autotokens.is_some() {
@@ -1360,6 +1877,14 @@ where
         self
     }
 
+    /// Start with a shared memory testcase map of `size` bytes, instead of the max input size.
+    /// Larger inputs grow the map, up to the max input size, by restarting the forkserver.
+    #[must_use]
+    pub fn initial_shmem_size(mut self, size: usize) -> Self {
+        self.initial_shmem_size = Some(size);
+        self
+    }
+
     /// Adds an environmental var to the harness's commandline
     #[must_use]
     pub fn env<K, V>(mut self, key: K, val: V) -> Self
@@ -1429,6 +1954,22 @@ where
         self
     }
 
+    /// Restart persistent children after `limit` executions, on top of the count passed to `__AFL_LOOP`.
+    /// Can be adjusted later with [`ForkserverExecutor::set_persistent_loop_limit`].
+    #[must_use]
+    pub fn persistent_loop_limit(mut self, limit: u64) -> Self {
+        self.persistent_loop_limit = Some(limit.max(1));
+        self
+    }
+
+    /// Captures the stacks of timed out children into the given observer, before they get killed
+    #[cfg(all(feature = "regex", target_os = "linux"))]
+    #[must_use]
+    pub fn hang_observer(mut self, hang_observer: Handle<HangBacktraceObserver>) -> Self {
+        self.hang_obs = Some(hang_observer);
+        self
+    }
+
     /// Treats an execution as a crash if the provided exitcode is returned
     #[must_use]
     pub fn crash_exitcode(mut self, exitcode: i8) -> Self {
@@ -1443,6 +1984,23 @@ where
         self
     }
 
+    /// How long to wait for the forkserver handshake.
+    /// A deferred forkserver only answers once the target reaches `__AFL_INIT()`, which may take a while.
+    /// Defaults to `AFL_FORKSRV_INIT_TMOUT` milliseconds if set, else ten times the execution timeout.
+    #[must_use]
+    pub fn handshake_timeout(mut self, handshake_timeout: Duration) -> Self {
+        self.handshake_timeout = Some(handshake_timeout);
+        self
+    }
+
+    /// Detect persistent mode and the deferred forkserver from the signatures afl-cc embeds in the target,
+    /// overriding `is_persistent` and `is_deferred_frksrv`; default is false
+    #[must_use]
+    pub fn detect_forkserver_modes(mut self, detect_modes: bool) -> Self {
+        self.detect_modes = detect_modes;
+        self
+    }
+
     /// Call this to set a defauult const coverage map size
     #[must_use]
     pub fn coverage_map_size(mut self, size: usize) -> Self {
@@ -1450,6 +2008,13 @@ where
         self
     }
 
+    /// Limit the address space of the target to `memlimit` MiB, like `afl-fuzz -m`; default is 0, no limit
+    #[must_use]
+    pub fn memlimit(mut self, memlimit: u64) -> Self {
+        self.memlimit = memlimit;
+        self
+    }
+
     /// Call this to set a signal to be used to kill child processes after executions
     #[must_use]
     pub fn kill_signal(mut self, kill_signal: Signal) -> Self {
@@ -1481,11 +2046,19 @@ impl<'a> ForkserverExecutorBuilder<'a, NopTargetBytesConverter<BytesInput>, Unix
             input_filename: None,
             shmem_provider: None,
             map_size: None,
+            memlimit: 0,
             max_input_size: MAX_INPUT_SIZE_DEFAULT,
             min_input_size: MIN_INPUT_SIZE_DEFAULT,
+            initial_shmem_size: None,
             kill_signal: None,
             timeout: None,
+            handshake_timeout: None,
+            detect_modes: false,
+            forkserver_options: ForkserverOptions::default(),
+            persistent_loop_limit: None,
             asan_obs: None,
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: None,
             crash_exitcode: None,
             target_bytes_converter: NopTargetBytesConverter::new(),
         }
@@ -1513,11 +2086,19 @@ impl<'a, TC> ForkserverExecutorBuilder<'a, TC, UnixShMemProvider> {
             autotokens: self.autotokens,
             input_filename: self.input_filename,
             map_size: self.map_size,
+            memlimit: self.memlimit,
             max_input_size: self.max_input_size,
             min_input_size: self.min_input_size,
+            initial_shmem_size: self.initial_shmem_size,
             kill_signal: self.kill_signal,
             timeout: self.timeout,
+            handshake_timeout: self.handshake_timeout,
+            detect_modes: self.detect_modes,
+            forkserver_options: self.forkserver_options,
+            persistent_loop_limit: self.persistent_loop_limit,
             asan_obs: self.asan_obs,
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: self.hang_obs,
             crash_exitcode: self.crash_exitcode,
             target_bytes_converter: self.target_bytes_converter,
         }
@@ -1545,11 +2126,19 @@ impl<'a, TC, SP> ForkserverExecutorBuilder<'a, TC, SP> {
             autotokens: self.autotokens,
             input_filename: self.input_filename,
             map_size: self.map_size,
+            memlimit: self.memlimit,
             max_input_size: self.max_input_size,
             min_input_size: self.min_input_size,
+            initial_shmem_size: self.initial_shmem_size,
             kill_signal: self.kill_signal,
             timeout: self.timeout,
+            handshake_timeout: self.handshake_timeout,
+            detect_modes: self.detect_modes,
+            forkserver_options: self.forkserver_options,
+            persistent_loop_limit: self.persistent_loop_limit,
             asan_obs: self.asan_obs,
+            #[cfg(all(feature = "regex", target_os = "linux"))]
+            hang_obs: self.hang_obs,
             crash_exitcode: self.crash_exitcode,
             target_bytes_converter,
         }
@@ -1564,9 +2153,10 @@ impl Default
     }
 }
 
-impl<EM, TC, OT, S, SP, Z> Executor<EM, Z> for ForkserverExecutor<TC, OT, S, SP>
+impl<EM, TC, OT, S, SP, HT, Z> Executor<EM, Z> for ForkserverExecutor<TC, OT, S, SP, HT>
 where
     OT: ObserversTuple<S::Input, S>,
+    HT: ExecutorHooksTuple<S>,
     SP: ShMemProvider,
     S: State + HasExecutions,
     TC: TargetBytesConverter<Input = S::Input>,
@@ -1581,11 +2171,17 @@ where
         _mgr: &mut EM,
         input: &Self::Input,
     ) -> Result<ExitKind, Error> {
-        self.execute_input(state, input)
+        if *state.executions() == 0 {
+            self.hooks.init_all::<Self>(state);
+        }
+        self.hooks.pre_exec_all(state, input);
+        let exit_kind = self.execute_input(state, input);
+        self.hooks.post_exec_all(state, input);
+        exit_kind
     }
 }
 
-impl<TC, OT, S, SP> HasTimeout for ForkserverExecutor<TC, OT, S, SP>
+impl<TC, OT, S, SP, HT> HasTimeout for ForkserverExecutor<TC, OT, S, SP, HT>
 where
     SP: ShMemProvider,
 {
@@ -1600,7 +2196,7 @@ where
     }
 }
 
-impl<TC, OT, S, SP> UsesState for ForkserverExecutor<TC, OT, S, SP>
+impl<TC, OT, S, SP, HT> UsesState for ForkserverExecutor<TC, OT, S, SP, HT>
 where
     S: State,
     SP: ShMemProvider,
@@ -1608,7 +2204,7 @@ where
     type State = S;
 }
 
-impl<TC, OT, S, SP> HasObservers for ForkserverExecutor<TC, OT, S, SP>
+impl<TC, OT, S, SP, HT> HasObservers for ForkserverExecutor<TC, OT, S, SP, HT>
 where
     OT: ObserversTuple<S::Input, S>,
     S: State,
@@ -1629,6 +2225,7 @@ where
 
 #[cfg(test)]
 mod tests {
+    use core::time::Duration;
     use std::ffi::OsString;
 
     use libafl_bolts::{
@@ -1639,11 +2236,93 @@ mod tests {
     use serial_test::serial;
 
     use crate::{
-        executors::forkserver::{ForkserverExecutor, FAILED_TO_START_FORKSERVER_MSG},
+        executors::forkserver::{
+            ForkserverExecutor, ForkserverOptions, PersistentStats, TargetSignatures, DEFER_SIG,
+            FAILED_TO_START_FORKSERVER_MSG, FS_NEW_OPT_AUTODTCT, FS_NEW_OPT_SHDMEM_FUZZ,
+            FS_OPT_ENABLED, FS_OPT_MAPSIZE, FS_OPT_SHDMEM_FUZZ, SHM_ENV_VAR,
+        },
         observers::{ConstMapObserver, HitcountsMapObserver},
         Error,
     };
 
+    #[test]
+    fn test_forkserver_options() {
+        let options =
+            ForkserverOptions::from_status(1, FS_NEW_OPT_SHDMEM_FUZZ | FS_NEW_OPT_AUTODTCT | 0x100);
+        assert_eq!(options.version, Some(1));
+        assert!(options.shmem_fuzz);
+        assert!(options.autodict);
+        assert_eq!(options.unknown_flags, 0x100);
+
+        // A map size of 65536 is encoded as `(size - 1) << 1`
+        let options = ForkserverOptions::from_old_status(
+            FS_OPT_ENABLED | FS_OPT_MAPSIZE | FS_OPT_SHDMEM_FUZZ | (65535 << 1),
+        );
+        assert_eq!(options.version, None);
+        assert_eq!(options.map_size, Some(65536));
+        assert!(options.shmem_fuzz);
+        assert!(!options.autodict);
+        assert_eq!(options.unknown_flags, 0);
+
+        assert_eq!(
+            ForkserverOptions::from_old_status(0),
+            ForkserverOptions::default()
+        );
+    }
+
+    #[test]
+    fn test_persistent_stats() {
+        let stats = PersistentStats {
+            executions: 1000,
+            restarts: 4,
+            forced_restarts: 2,
+        };
+        assert!((stats.restarts_per(1000) - 4.0).abs() < f64::EPSILON);
+        assert!((stats.executions_per_child() - 200.0).abs() < f64::EPSILON);
+        assert!(PersistentStats::default().restarts_per(1000).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_target_signatures() {
+        let mut binary = b"\x7fELF".to_vec();
+        binary.extend_from_slice(SHM_ENV_VAR);
+        binary.extend_from_slice(DEFER_SIG);
+        let signatures = TargetSignatures::from_binary(&binary);
+        assert!(signatures.instrumented);
+        assert!(signatures.deferred);
+        assert!(!signatures.persistent);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_forkserver_handshake_timeout() {
+        const MAP_SIZE: usize = 65536;
+        let mut shmem_provider = UnixShMemProvider::new().unwrap();
+        let mut shmem = shmem_provider.new_shmem(MAP_SIZE).unwrap();
+        shmem.write_to_env("__AFL_SHM_ID").unwrap();
+        let shmem_buf: &mut [u8; MAP_SIZE] = shmem.as_slice_mut().try_into().unwrap();
+        let edges_observer = HitcountsMapObserver::new(ConstMapObserver::<_, MAP_SIZE>::new(
+            "shared_mem",
+            shmem_buf,
+        ));
+
+        // `sleep` never answers the handshake, like a deferred target that never reaches `__AFL_INIT()`
+        let executor = ForkserverExecutor::builder()
+            .program("sleep")
+            .arg("10")
+            .coverage_map_size(MAP_SIZE)
+            .is_deferred_frksrv(true)
+            .handshake_timeout(Duration::from_millis(100))
+            .build::<_, ()>(tuple_list!(edges_observer));
+
+        let Err(Error::IllegalState(msg, _)) = executor else {
+            panic!("The handshake with `sleep` should time out");
+        };
+        assert!(msg.contains("did not answer within"));
+        assert!(msg.contains("handshake_timeout"));
+    }
+
     #[test]
     #[serial]
     #[cfg_attr(miri, ignore)]
@@ -10,12 +10,21 @@ use core::{
     time::Duration,
 };
 
+#[cfg(all(unix, feature = "std"))]
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::string::String;
 #[cfg(all(target_os = "linux", feature = "std"))]
 use libafl_bolts::current_time;
+
 #[cfg(all(unix, feature = "std", not(miri)))]
 use libafl_bolts::os::unix_signals::setup_signal_handler;
+#[cfg(all(unix, feature = "std"))]
+use libafl_bolts::os::unix_signals::ucontext_t;
 #[cfg(all(windows, feature = "std"))]
 use libafl_bolts::os::windows_exceptions::setup_exception_handler;
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 #[cfg(all(windows, feature = "std"))]
 use windows::Win32::System::Threading::{CRITICAL_SECTION, PTP_TIMER};
 
@@ -48,9 +57,16 @@ pub struct InProcessHooks<S> {
     /// `TImer` struct
     #[cfg(feature = "std")]
     pub timer: TimerStruct,
+    /// Called with the context of the interrupted target on a timeout, see [`Self::set_timeout_stack_hook`]
+    #[cfg(all(unix, feature = "std"))]
+    pub timeout_stack_hook: Option<TimeoutStackHook>,
     phantom: PhantomData<S>,
 }
 
+/// A hook that gets the context of the target, as interrupted by a timeout, for hang triage
+#[cfg(all(unix, feature = "std"))]
+pub type TimeoutStackHook = Box<dyn FnMut(Option<&ucontext_t>)>;
+
 /// Any hooks that is about timeout
 pub trait HasTimeout {
     /// Return ref to timer
@@ -265,6 +281,8 @@ where
                 as *const _,
             #[cfg(feature = "std")]
             timer: TimerStruct::new(exec_tmout),
+            #[cfg(feature = "std")]
+            timeout_stack_hook: None,
             phantom: PhantomData,
         })
     }
@@ -345,6 +363,16 @@ where
         Ok(ret)
     }
 
+    /// Sets a hook that gets called with the context of the interrupted target on each timeout,
+    /// before the timeout is reported, for example [`unix_signal_handler::log_interrupted_stack`].
+    #[cfg(all(unix, feature = "std"))]
+    pub fn set_timeout_stack_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(Option<&ucontext_t>) + 'static,
+    {
+        self.timeout_stack_hook = Some(Box::new(hook));
+    }
+
     /// Replace the handlers with `nop` handlers, deactivating the handlers
     #[must_use]
     #[cfg(not(windows))]
@@ -356,11 +384,39 @@ where
             timeout_handler: ptr::null(),
             #[cfg(feature = "std")]
             timer: TimerStruct::new(Duration::from_millis(5000)),
+            #[cfg(all(unix, feature = "std"))]
+            timeout_stack_hook: None,
             phantom: PhantomData,
         }
     }
 }
 
+/// The crash report of a solution found by an in-process executor: the registers, the top of the stack,
+/// the backtrace and the mapped modules at the time of the crash, as generated by [`libafl_bolts::minibsod`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReportMetadata {
+    report: String,
+}
+
+#[cfg(feature = "std")]
+libafl_bolts::impl_serdeany!(CrashReportMetadata);
+
+#[cfg(feature = "std")]
+impl CrashReportMetadata {
+    /// Creates a new [`CrashReportMetadata`]
+    #[must_use]
+    pub fn new(report: String) -> Self {
+        Self { report }
+    }
+
+    /// The mini-BSOD
+    #[must_use]
+    pub fn report(&self) -> &str {
+        &self.report
+    }
+}
+
 /// The global state of the in-process harness.
 #[derive(Debug)]
 pub struct InProcessExecutorHandlerData {
@@ -388,6 +444,9 @@ pub struct InProcessExecutorHandlerData {
     pub(crate) in_target: u64,
     #[cfg(all(windows, feature = "std"))]
     pub(crate) critical: *mut c_void,
+    /// The mini-BSOD of the last crash, attached to the solution as [`CrashReportMetadata`]
+    #[cfg(feature = "std")]
+    pub(crate) crash_report: Option<String>,
 }
 
 unsafe impl Send for InProcessExecutorHandlerData {}
@@ -431,6 +490,18 @@ impl InProcessExecutorHandlerData {
         r
     }
 
+    /// Keeps the mini-BSOD of a crash, to attach it to the solution
+    #[cfg(feature = "std")]
+    pub(crate) fn set_crash_report(&mut self, report: &[u8]) {
+        self.crash_report = Some(String::from_utf8_lossy(report).into_owned());
+    }
+
+    /// Takes the mini-BSOD of the last crash, if any
+    #[cfg(feature = "std")]
+    pub(crate) fn take_crash_report(&mut self) -> Option<String> {
+        self.crash_report.take()
+    }
+
     #[cfg(any(unix, feature = "std"))]
     pub(crate) fn is_valid(&self) -> bool {
         !self.current_input_ptr.is_null()
@@ -471,6 +542,8 @@ pub(crate) static mut GLOBAL_STATE: InProcessExecutorHandlerData = InProcessExec
     in_target: 0,
     #[cfg(all(windows, feature = "std"))]
     critical: null_mut(),
+    #[cfg(feature = "std")]
+    crash_report: None,
 };
 
 /// Get the inprocess [`crate::state::State`]
@@ -1,5 +1,11 @@
 //! Hooks for the executors.
 //! These will be executed right before and after the executor's harness run.
+//!
+//! Next to the in-process executors, the `CommandExecutor` and the `ForkserverExecutor`
+//! accept an [`ExecutorHooksTuple`], too,
+//! so user code can, for example, reset a mock filesystem or rotate log directories around each run.
+
+use core::fmt::{self, Debug, Formatter};
 
 use crate::{executors::HasObservers, inputs::UsesInput};
 
@@ -82,3 +88,43 @@ where
         self.1.post_exec_all(state, input);
     }
 }
+
+/// An [`ExecutorHook`] calling a closure with the state and the input before and after each run
+pub struct ClosureExecutorHook<PRE, POST> {
+    pre_exec: PRE,
+    post_exec: POST,
+}
+
+impl<PRE, POST> ClosureExecutorHook<PRE, POST> {
+    /// Create a new [`ClosureExecutorHook`] from the closures called before and after each run
+    pub fn new(pre_exec: PRE, post_exec: POST) -> Self {
+        Self {
+            pre_exec,
+            post_exec,
+        }
+    }
+}
+
+impl<PRE, POST> Debug for ClosureExecutorHook<PRE, POST> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClosureExecutorHook")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<PRE, POST, S> ExecutorHook<S> for ClosureExecutorHook<PRE, POST>
+where
+    S: UsesInput,
+    PRE: FnMut(&mut S, &S::Input),
+    POST: FnMut(&mut S, &S::Input),
+{
+    fn init<E: HasObservers>(&mut self, _state: &mut S) {}
+
+    fn pre_exec(&mut self, state: &mut S, input: &S::Input) {
+        (self.pre_exec)(state, input);
+    }
+
+    fn post_exec(&mut self, state: &mut S, input: &S::Input) {
+        (self.post_exec)(state, input);
+    }
+}
@@ -1,4 +1,11 @@
 //! The struct `TimerStruct` will absorb all the difference in timeout implementation in various system.
+//!
+//! On Linux, each [`TimerStruct`] owns a POSIX timer (`timer_create`) on `CLOCK_MONOTONIC`,
+//! armed with nanosecond precision, that delivers its `SIGALRM` to the thread running the target.
+//! This keeps timeouts below 10ms reliable, even if the target runs on another thread than the one that created the executor.
+//! The timeout handler still works on the process-wide `GLOBAL_STATE` of the in-process hooks,
+//! so only one in-process executor per process can run a target at a time.
+//! A `timerfd` would not interrupt the target, so it is not an option for in-process execution.
 use core::time::Duration;
 #[cfg(target_os = "linux")]
 use core::{mem::zeroed, ptr::null_mut};
@@ -58,6 +65,38 @@ pub(crate) struct Itimerval {
     pub it_value: Timeval,
 }
 
+/// Converts a timeout into a one-shot [`Itimerval`], keeping microseconds
+#[cfg(all(unix, not(target_os = "linux")))]
+#[allow(clippy::cast_possible_wrap, clippy::cast_lossless)]
+pub(crate) fn itimerval_from_duration(timeout: Duration) -> Itimerval {
+    Itimerval {
+        it_interval: Timeval {
+            tv_sec: 0,
+            tv_usec: 0,
+        },
+        it_value: Timeval {
+            tv_sec: timeout.as_secs() as i64,
+            tv_usec: timeout.subsec_micros() as i64,
+        },
+    }
+}
+
+/// Converts a timeout into a one-shot [`libc::itimerspec`], keeping nanoseconds
+#[cfg(target_os = "linux")]
+#[allow(clippy::cast_possible_wrap, clippy::cast_lossless)]
+pub(crate) fn itimerspec_from_duration(timeout: Duration) -> libc::itimerspec {
+    libc::itimerspec {
+        it_interval: libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        },
+        it_value: libc::timespec {
+            tv_sec: timeout.as_secs() as _,
+            tv_nsec: timeout.subsec_nanos() as _,
+        },
+    }
+}
+
 #[cfg(all(feature = "std", unix, not(target_os = "linux")))]
 extern "C" {
     pub(crate) fn setitimer(
@@ -80,12 +119,14 @@ pub struct TimerStruct {
     critical: CRITICAL_SECTION,
     #[cfg(target_os = "linux")]
     pub(crate) batch_mode: bool,
-    #[cfg(target_os = "linux")]
     pub(crate) exec_tmout: Duration,
     #[cfg(all(unix, not(target_os = "linux")))]
     itimerval: Itimerval,
     #[cfg(target_os = "linux")]
     pub(crate) timerid: libc::timer_t,
+    /// The thread the timer signals, the timer gets recreated when the target runs on another one
+    #[cfg(target_os = "linux")]
+    pub(crate) timer_thread: Option<libc::pthread_t>,
     #[cfg(target_os = "linux")]
     pub(crate) itimerspec: libc::itimerspec,
     #[cfg(target_os = "linux")]
@@ -154,20 +195,10 @@ impl TimerStruct {
     #[cfg(all(unix, not(target_os = "linux")))]
     #[must_use]
     pub fn new(exec_tmout: Duration) -> Self {
-        let milli_sec = exec_tmout.as_millis();
-        let it_value = Timeval {
-            tv_sec: (milli_sec / 1000) as i64,
-            tv_usec: (milli_sec % 1000) as i64,
-        };
-        let it_interval = Timeval {
-            tv_sec: 0,
-            tv_usec: 0,
-        };
-        let itimerval = Itimerval {
-            it_interval,
-            it_value,
-        };
-        Self { itimerval }
+        Self {
+            exec_tmout,
+            itimerval: itimerval_from_duration(exec_tmout),
+        }
     }
 
     /// Constructor
@@ -194,6 +225,7 @@ impl TimerStruct {
         }
         Self {
             milli_sec,
+            exec_tmout,
             ptp_timer,
             critical,
         }
@@ -201,34 +233,15 @@ impl TimerStruct {
 
     #[cfg(target_os = "linux")]
     #[must_use]
-    #[allow(unused_unsafe)]
-    #[allow(unused_mut)]
     /// Create a `TimerStruct` with the specified timeout
+    ///
+    /// The underlying timer gets created on the first [`Self::set_timer`], by the thread running the target.
     pub fn new(exec_tmout: Duration) -> Self {
-        let milli_sec = exec_tmout.as_millis();
-        let it_value = libc::timespec {
-            tv_sec: (milli_sec / 1000) as _,
-            tv_nsec: ((milli_sec % 1000) * 1000 * 1000) as _,
-        };
-        let it_interval = libc::timespec {
-            tv_sec: 0,
-            tv_nsec: 0,
-        };
-        let itimerspec = libc::itimerspec {
-            it_interval,
-            it_value,
-        };
-        let mut timerid: libc::timer_t = null_mut();
-        unsafe {
-            #[cfg(not(miri))]
-            // creates a new per-process interval timer
-            libc::timer_create(libc::CLOCK_MONOTONIC, null_mut(), &raw mut timerid);
-        }
-
         Self {
             batch_mode: false,
-            itimerspec,
-            timerid,
+            itimerspec: itimerspec_from_duration(exec_tmout),
+            timerid: null_mut(),
+            timer_thread: None,
             exec_tmout,
             executions: 0,
             avg_mul_k: 1,
@@ -253,6 +266,77 @@ impl TimerStruct {
         me
     }
 
+    /// The timeout of a single execution
+    #[must_use]
+    pub fn exec_timeout(&self) -> Duration {
+        self.exec_tmout
+    }
+
+    /// Sets the timeout of a single execution, taking effect with the next [`Self::set_timer`]
+    pub fn set_exec_timeout(&mut self, exec_tmout: Duration) {
+        self.exec_tmout = exec_tmout;
+        #[cfg(target_os = "linux")]
+        {
+            self.itimerspec = itimerspec_from_duration(exec_tmout);
+            // The batch mode statistics were gathered for the old timeout
+            self.executions = 0;
+            self.avg_mul_k = 1;
+        }
+        #[cfg(all(unix, not(target_os = "linux")))]
+        {
+            self.itimerval = itimerval_from_duration(exec_tmout);
+        }
+        #[cfg(windows)]
+        {
+            self.milli_sec = exec_tmout.as_millis() as i64;
+        }
+    }
+
+    /// Creates the timer for the calling thread, unless it already signals this thread.
+    ///
+    /// Falls back to a process-wide timer if thread-directed timers are not supported.
+    #[cfg(target_os = "linux")]
+    fn ensure_timer(&mut self) {
+        // # Safety
+        // Just API calls on our own timer, no user-provided inputs
+        unsafe {
+            let thread = libc::pthread_self();
+            if self.timer_thread == Some(thread) {
+                return;
+            }
+            self.delete_timer();
+
+            let mut sigevent: libc::sigevent = zeroed();
+            sigevent.sigev_notify = libc::SIGEV_THREAD_ID;
+            sigevent.sigev_signo = libc::SIGALRM;
+            sigevent.sigev_notify_thread_id = libc::gettid();
+            if libc::timer_create(
+                libc::CLOCK_MONOTONIC,
+                &raw mut sigevent,
+                &raw mut self.timerid,
+            ) != 0
+            {
+                log::warn!("Could not create a thread-directed timer, using a process-wide one");
+                libc::timer_create(libc::CLOCK_MONOTONIC, null_mut(), &raw mut self.timerid);
+            }
+            self.timer_thread = Some(thread);
+        }
+    }
+
+    /// Deletes the timer, if it was created
+    #[cfg(target_os = "linux")]
+    fn delete_timer(&mut self) {
+        if !self.timerid.is_null() {
+            // # Safety
+            // The timer was created by us and is deleted only once
+            unsafe {
+                libc::timer_delete(self.timerid);
+            }
+            self.timerid = null_mut();
+            self.timer_thread = None;
+        }
+    }
+
     #[cfg(all(unix, not(target_os = "linux")))]
     /// Set up timer
     pub fn set_timer(&mut self) {
@@ -297,6 +381,8 @@ impl TimerStruct {
     /// Set up timer
     #[cfg(target_os = "linux")]
     pub fn set_timer(&mut self) {
+        #[cfg(not(miri))]
+        self.ensure_timer();
         unsafe {
             if self.batch_mode {
                 if self.executions == 0 {
@@ -381,3 +467,39 @@ impl TimerStruct {
         }
     }
 }
+
+#[cfg(target_os = "linux")]
+impl Drop for TimerStruct {
+    fn drop(&mut self) {
+        self.delete_timer();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::TimerStruct;
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_exec_timeout() {
+        let mut timer = TimerStruct::new(Duration::from_micros(2500));
+        assert_eq!(timer.exec_timeout(), Duration::from_micros(2500));
+        #[cfg(target_os = "linux")]
+        assert_eq!(timer.itimerspec.it_value.tv_nsec, 2_500_000);
+
+        timer.set_exec_timeout(Duration::from_micros(1_000_500));
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(timer.itimerspec.it_value.tv_sec, 1);
+            assert_eq!(timer.itimerspec.it_value.tv_nsec, 500_000);
+        }
+        #[cfg(all(unix, not(target_os = "linux")))]
+        assert_eq!(timer.itimerval.it_value.tv_usec, 500);
+
+        timer.set_timer();
+        timer.unset_timer();
+    }
+}
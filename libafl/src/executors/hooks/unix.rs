@@ -15,7 +15,7 @@ pub mod unix_signal_handler {
             common_signals,
             hooks::inprocess::{HasTimeout, InProcessExecutorHandlerData, GLOBAL_STATE},
             inprocess::{run_observers_and_save_state, HasInProcessHooks},
-            Executor, ExitKind, HasObservers,
+            Executor, ExitKind, ExitSignalMetadata, HasObservers,
         },
         feedbacks::Feedback,
         fuzzer::HasObjective,
@@ -126,7 +126,7 @@ pub mod unix_signal_handler {
     pub unsafe fn inproc_timeout_handler<E, EM, OF, Z>(
         _signal: Signal,
         _info: &mut siginfo_t,
-        _context: Option<&mut ucontext_t>,
+        context: Option<&mut ucontext_t>,
         data: &mut InProcessExecutorHandlerData,
     ) where
         E: Executor<EM, Z> + HasInProcessHooks<E::State> + HasObservers,
@@ -161,6 +161,10 @@ pub mod unix_signal_handler {
 
         log::error!("Timeout in fuzz run.");
 
+        if let Some(hook) = &mut executor.inprocess_hooks_mut().timeout_stack_hook {
+            hook(context.as_deref());
+        }
+
         run_observers_and_save_state::<E, EM, OF, Z>(
             executor,
             state,
@@ -173,6 +177,36 @@ pub mod unix_signal_handler {
         libc::_exit(55);
     }
 
+    /// The size of the buffer [`log_interrupted_stack`] formats the context into
+    const INTERRUPTED_STACK_DUMP_SIZE: usize = 8192;
+
+    /// A [`crate::executors::hooks::inprocess::TimeoutStackHook`] that writes the registers
+    /// and the top of the stack of the target at the time it got interrupted by a timeout to `stderr`.
+    ///
+    /// It runs inside the signal handler, so it formats into a buffer on the (signal) stack
+    /// and writes it out with a single `write(2)`, without allocating or going through the logger.
+    pub fn log_interrupted_stack(context: Option<&ucontext_t>) {
+        let mut dump = [0_u8; INTERRUPTED_STACK_DUMP_SIZE];
+        let mut remaining = &mut dump[..];
+        {
+            // A `BufWriter` without capacity does not allocate, it writes straight through
+            let mut writer = std::io::BufWriter::with_capacity(0, &mut remaining);
+            let _ = writeln!(writer, "Target interrupted by a timeout:");
+            match context {
+                Some(context) => {
+                    let _ = libafl_bolts::minibsod::dump_context(&mut writer, context);
+                }
+                None => {
+                    let _ = writeln!(writer, "No context of the target available");
+                }
+            }
+        }
+        let len = INTERRUPTED_STACK_DUMP_SIZE - remaining.len();
+        unsafe {
+            libc::write(libc::STDERR_FILENO, dump.as_ptr().cast(), len);
+        }
+    }
+
     /// Crash-Handler for in-process fuzzing.
     /// Will be used for signal handling.
     /// It will store the current State to shmem, then exit.
@@ -203,6 +237,7 @@ pub mod unix_signal_handler {
         });
 
         log::error!("Crashed with {signal}");
+        ExitSignalMetadata::record_last(signal as i32);
         if data.is_valid() {
             let executor = data.executor_mut::<E>();
             // disarms timeout in case of timeout
@@ -232,6 +267,7 @@ pub mod unix_signal_handler {
                 if let Ok(r) = std::str::from_utf8(&bsod) {
                     log::error!("{}", r);
                 }
+                data.set_crash_report(&bsod);
             }
 
             run_observers_and_save_state::<E, EM, OF, Z>(
@@ -120,7 +120,7 @@ pub mod windows_exception_handler {
 
     use libafl_bolts::os::windows_exceptions::{
         ExceptionCode, ExceptionHandler, CRASH_EXCEPTIONS, EXCEPTION_HANDLERS_SIZE,
-        EXCEPTION_POINTERS,
+        EXCEPTION_POINTERS, LAST_CHANCE_EXCEPTIONS,
     };
     use windows::Win32::System::Threading::{
         EnterCriticalSection, ExitProcess, LeaveCriticalSection, CRITICAL_SECTION,
@@ -173,9 +173,13 @@ pub mod windows_exception_handler {
 
         fn exceptions(&self) -> Vec<ExceptionCode> {
             let crash_list = CRASH_EXCEPTIONS.to_vec();
-            assert!(crash_list.len() < EXCEPTION_HANDLERS_SIZE - 1);
+            assert!(crash_list.len() + LAST_CHANCE_EXCEPTIONS.len() < EXCEPTION_HANDLERS_SIZE - 1);
             crash_list
         }
+
+        fn last_chance_exceptions(&self) -> Vec<ExceptionCode> {
+            LAST_CHANCE_EXCEPTIONS.to_vec()
+        }
     }
 
     /// invokes the `post_exec` hook on all observer in case of panic
@@ -360,14 +364,17 @@ pub mod windows_exception_handler {
             );
 
             let exception_list = data.exceptions();
-            if exception_list.contains(&code) {
+            // Last chance exceptions only arrive here once the target did not handle them
+            if exception_list.contains(&code) || LAST_CHANCE_EXCEPTIONS.contains(&code) {
                 log::error!("Crashed with {code}");
             } else {
                 // log::trace!("Exception code received, but {code} is not in CRASH_EXCEPTIONS");
                 is_crash = false;
             }
         } else {
-            log::error!("Crashed without exception (probably due to SIGABRT)");
+            log::error!(
+                "Crashed without exception (probably due to SIGABRT or an invalid CRT parameter)"
+            );
         };
 
         if data.current_input_ptr.is_null() {
@@ -375,11 +382,8 @@ pub mod windows_exception_handler {
                 log::error!("Double crash\n");
                 let crash_addr = exception_pointers
                     .as_mut()
-                    .unwrap()
-                    .ExceptionRecord
-                    .as_mut()
-                    .unwrap()
-                    .ExceptionAddress as usize;
+                    .and_then(|pointers| pointers.ExceptionRecord.as_mut())
+                    .map_or(0, |record| record.ExceptionAddress as usize);
 
                 log::error!(
                 "We crashed at addr 0x{crash_addr:x}, but are not in the target... Bug in the fuzzer? Exiting."
@@ -420,11 +424,17 @@ pub mod windows_exception_handler {
                     {
                         let mut writer = std::io::BufWriter::new(&mut bsod);
                         writeln!(writer, "input: {:?}", input.generate_name(None)).unwrap();
-                        libafl_bolts::minibsod::generate_minibsod(&mut writer, exception_pointers)
+                        if !exception_pointers.is_null() {
+                            libafl_bolts::minibsod::generate_minibsod(
+                                &mut writer,
+                                exception_pointers,
+                            )
                             .unwrap();
+                        }
                         writer.flush().unwrap();
                     }
                     log::error!("{}", std::str::from_utf8(&bsod).unwrap());
+                    data.set_crash_report(&bsod);
                 }
                 run_observers_and_save_state::<E, EM, OF, Z>(
                     executor,
@@ -275,6 +275,19 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<HT, OT, S> crate::executors::HasTimeout for GenericInProcessExecutorInner<HT, OT, S> {
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.hooks.0.timer.exec_timeout()
+    }
+
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.hooks.0.timer.set_exec_timeout(timeout);
+    }
+}
+
 impl<HT, OT, S> HasInProcessHooks<S> for GenericInProcessExecutorInner<HT, OT, S>
 where
     S: UsesInput,
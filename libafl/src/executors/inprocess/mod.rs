@@ -16,6 +16,8 @@ use core::{
 
 use libafl_bolts::tuples::{tuple_list, RefIndexable};
 
+#[cfg(feature = "std")]
+use crate::executors::hooks::inprocess::CrashReportMetadata;
 #[cfg(any(unix, feature = "std"))]
 use crate::executors::hooks::inprocess::GLOBAL_STATE;
 use crate::{
@@ -24,7 +26,7 @@ use crate::{
     executors::{
         hooks::{inprocess::InProcessHooks, ExecutorHooksTuple},
         inprocess::inner::GenericInProcessExecutorInner,
-        Executor, ExitKind, HasObservers,
+        Executor, ExitKind, HasObservers, HasTimeout,
     },
     feedbacks::Feedback,
     fuzzer::HasObjective,
@@ -131,6 +133,26 @@ where
     }
 }
 
+#[cfg(feature = "std")]
+impl<H, HB, HT, OT, S> HasTimeout for GenericInProcessExecutor<H, HB, HT, OT, S>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    HB: BorrowMut<H>,
+    HT: ExecutorHooksTuple<S>,
+    OT: ObserversTuple<S::Input, S>,
+    S: State,
+{
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.inner.timeout()
+    }
+
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.inner.set_timeout(timeout);
+    }
+}
+
 impl<H, HB, HT, OT, S> HasObservers for GenericInProcessExecutor<H, HB, HT, OT, S>
 where
     H: FnMut(&S::Input) -> ExitKind + ?Sized,
@@ -456,9 +478,20 @@ pub fn run_observers_and_save_state<E, EM, OF, Z>(
         .is_interesting(state, event_mgr, input, &*observers, &exitkind)
         .expect("In run_observers_and_save_state objective failure.");
 
+    // The crash handlers leave the mini-BSOD of the crash behind
+    #[cfg(feature = "std")]
+    let crash_report = {
+        let data = &raw mut GLOBAL_STATE;
+        unsafe { (*data).take_crash_report() }
+    };
+
     if interesting {
         let mut new_testcase = Testcase::from(input.clone());
         new_testcase.add_metadata(exitkind);
+        #[cfg(feature = "std")]
+        if let Some(report) = crash_report {
+            new_testcase.add_metadata(CrashReportMetadata::new(report));
+        }
         new_testcase.set_parent_id_optional(*state.corpus().current());
 
         if let Ok(mut tc) = state.current_testcase_mut() {
@@ -17,8 +17,10 @@ use nix::{
     unistd::Pid,
 };
 
+#[cfg(target_os = "linux")]
+use crate::executors::hooks::timer::itimerspec_from_duration;
 #[cfg(all(unix, not(target_os = "linux")))]
-use crate::executors::hooks::timer::{setitimer, Itimerval, Timeval, ITIMER_REAL};
+use crate::executors::hooks::timer::{itimerval_from_duration, setitimer, Itimerval, ITIMER_REAL};
 use crate::{
     events::{EventFirer, EventRestarter},
     executors::{
@@ -26,7 +28,7 @@ use crate::{
             inprocess_fork::{InChildProcessHooks, FORK_EXECUTOR_GLOBAL_DATA},
             ExecutorHooksTuple,
         },
-        ExitKind, HasObservers,
+        ExitKind, ExitSignalMetadata, HasObservers,
     },
     inputs::UsesInput,
     observers::ObserversTuple,
@@ -82,36 +84,12 @@ where
 
 #[cfg(target_os = "linux")]
 fn parse_itimerspec(timeout: Duration) -> libc::itimerspec {
-    let milli_sec = timeout.as_millis();
-    let it_value = libc::timespec {
-        tv_sec: (milli_sec / 1000) as _,
-        tv_nsec: ((milli_sec % 1000) * 1000 * 1000) as _,
-    };
-    let it_interval = libc::timespec {
-        tv_sec: 0,
-        tv_nsec: 0,
-    };
-    libc::itimerspec {
-        it_interval,
-        it_value,
-    }
+    itimerspec_from_duration(timeout)
 }
 
 #[cfg(not(target_os = "linux"))]
 fn parse_itimerval(timeout: Duration) -> Itimerval {
-    let milli_sec = timeout.as_millis();
-    let it_value = Timeval {
-        tv_sec: (milli_sec / 1000) as i64,
-        tv_usec: (milli_sec % 1000) as i64,
-    };
-    let it_interval = Timeval {
-        tv_sec: 0,
-        tv_usec: 0,
-    };
-    Itimerval {
-        it_interval,
-        it_value,
-    }
+    itimerval_from_duration(timeout)
 }
 
 impl<EM, HT, OT, S, SP, Z> GenericInProcessForkExecutorInner<HT, OT, S, SP, EM, Z>
@@ -181,29 +159,17 @@ where
 
         let res = waitpid(child, None)?;
         log::trace!("{res:#?}");
-        match res {
-            WaitStatus::Signaled(_, signal, _) => match signal {
-                nix::sys::signal::Signal::SIGALRM | nix::sys::signal::Signal::SIGUSR2 => {
-                    Ok(ExitKind::Timeout)
-                }
-                _ => Ok(ExitKind::Crash),
-            },
-            WaitStatus::Exited(_, code) => {
-                if code > 128 && code < 160 {
-                    // Signal exit codes
-                    let signal = code - 128;
-                    if signal == Signal::SigAlarm as libc::c_int
-                        || signal == Signal::SigUser2 as libc::c_int
-                    {
-                        Ok(ExitKind::Timeout)
-                    } else {
-                        Ok(ExitKind::Crash)
-                    }
-                } else {
-                    Ok(ExitKind::Ok)
-                }
-            }
-            _ => Ok(ExitKind::Ok),
+        let signal = match res {
+            WaitStatus::Signaled(_, signal, _) => signal as libc::c_int,
+            // Signal exit codes of the crash handlers
+            WaitStatus::Exited(_, code) if code > 128 && code < 160 => code - 128,
+            _ => return Ok(ExitKind::Ok),
+        };
+        if signal == Signal::SigAlarm as libc::c_int || signal == Signal::SigUser2 as libc::c_int {
+            Ok(ExitKind::Timeout)
+        } else {
+            ExitSignalMetadata::record_last(signal);
+            Ok(ExitKind::Crash)
         }
     }
 }
@@ -2,12 +2,19 @@
 
 #[cfg(unix)]
 use alloc::vec::Vec;
-use core::{fmt::Debug, time::Duration};
+use core::{
+    fmt::Debug,
+    sync::atomic::{AtomicI32, Ordering},
+    time::Duration,
+};
 
+#[cfg(all(feature = "std", any(unix, doc)))]
+pub use adb::AdbExecutor;
+pub use batched::BatchedExecutor;
 pub use combined::CombinedExecutor;
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub use command::CommandExecutor;
-pub use differential::DiffExecutor;
+pub use differential::{DiffExecutor, MultiDiffExecutor};
 #[cfg(all(feature = "std", feature = "fork", unix))]
 pub use forkserver::{Forkserver, ForkserverExecutor};
 pub use inprocess::InProcessExecutor;
@@ -16,12 +23,25 @@ pub use inprocess_fork::InProcessForkExecutor;
 #[cfg(unix)]
 use libafl_bolts::os::unix_signals::Signal;
 use libafl_bolts::tuples::RefIndexable;
+#[cfg(feature = "std")]
+pub use network::NetworkExecutor;
+#[cfg(feature = "std")]
+pub use remote::RemoteExecutor;
 use serde::{Deserialize, Serialize};
 pub use shadow::ShadowExecutor;
+#[cfg(feature = "std")]
+pub use thread_pool::ThreadPoolExecutor;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmExecutor;
+#[cfg(all(feature = "std", any(unix, doc)))]
+pub use wine::WineExecutor;
 pub use with_observers::WithObservers;
 
 use crate::{observers::ObserversTuple, state::UsesState, Error};
 
+#[cfg(all(feature = "std", any(unix, doc)))]
+pub mod adb;
+pub mod batched;
 pub mod combined;
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub mod command;
@@ -34,8 +54,26 @@ pub mod inprocess;
 #[cfg(all(feature = "std", unix))]
 pub mod inprocess_fork;
 
+#[cfg(feature = "std")]
+pub mod network;
+
+#[cfg(feature = "std")]
+pub mod remote;
+
 pub mod shadow;
 
+#[cfg(all(feature = "std", any(unix, doc)))]
+pub mod symqemu;
+
+#[cfg(feature = "std")]
+pub mod thread_pool;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(all(feature = "std", any(unix, doc)))]
+pub mod wine;
+
 pub mod with_observers;
 
 /// The module for all the hooks
@@ -56,12 +94,15 @@ pub enum ExitKind {
     Oom,
     /// The run timed out
     Timeout,
-    /// Special case for [`DiffExecutor`] when both exitkinds don't match
+    /// Special case for [`DiffExecutor`] and [`MultiDiffExecutor`] when the exitkinds don't match
     Diff {
         /// The exitkind of the primary executor
         primary: DiffExitKind,
-        /// The exitkind of the secondary executor
+        /// The exitkind of the secondary executor, the first diverging one for a [`MultiDiffExecutor`]
         secondary: DiffExitKind,
+        /// A bit for each executor whose exitkind differs from the one of the primary executor.
+        /// Bit `1` for the secondary executor of a [`DiffExecutor`].
+        diverged: u64,
     },
     // The run resulted in a custom `ExitKind`.
     // Custom(Box<dyn SerdeAny>),
@@ -104,6 +145,55 @@ impl From<ExitKind> for DiffExitKind {
 
 libafl_bolts::impl_serdeany!(DiffExitKind);
 
+/// The signal that terminated the target in an [`ExitKind::Crash`], if it was killed by a signal.
+///
+/// The executors and the in-process crash handlers record the signal of the last run with [`Self::record_last`],
+/// feedbacks such as [`crate::feedbacks::AflFilenameFeedback`] take it with [`Self::take_last`]
+/// and attach it to the new [`crate::corpus::Testcase`].
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct ExitSignalMetadata {
+    signal: i32,
+}
+
+libafl_bolts::impl_serdeany!(ExitSignalMetadata);
+
+impl ExitSignalMetadata {
+    /// Creates a new [`ExitSignalMetadata`] for the given signal number
+    #[must_use]
+    pub fn new(signal: i32) -> Self {
+        Self { signal }
+    }
+
+    /// The signal number
+    #[must_use]
+    pub fn signal(&self) -> i32 {
+        self.signal
+    }
+
+    /// Records `signal` as the signal that terminated the target in the last run.
+    ///
+    /// There is a single slot for the whole process, so the executors of concurrent fuzzers in one process share it.
+    pub fn record_last(signal: i32) {
+        LAST_EXIT_SIGNAL.store(signal, Ordering::Relaxed);
+    }
+
+    /// Takes the signal recorded by [`Self::record_last`] since the last call, if any
+    #[must_use]
+    pub fn take_last() -> Option<Self> {
+        match LAST_EXIT_SIGNAL.swap(0, Ordering::Relaxed) {
+            0 => None,
+            signal => Some(Self::new(signal)),
+        }
+    }
+}
+
+/// The signal of the last run recorded by [`ExitSignalMetadata::record_last`], 0 if none
+static LAST_EXIT_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
 /// Holds a tuple of Observers
 pub trait HasObservers {
     /// The observer
@@ -0,0 +1,617 @@
+//! The [`NetworkExecutor`] sends each input to a live TCP or UDP server, and waits for its response.
+//!
+//! Connection resets and refused connections are reported as [`ExitKind::Crash`],
+//! a missing response as [`ExitKind::Timeout`].
+//! A [`ServerProcess`] can (re)start the server, when it died or before each execution.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    process::{Child, Command},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::{tuples::RefIndexable, AsSlice};
+use typed_builder::TypedBuilder;
+
+use super::HasTimeout;
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::ObserversTuple,
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The transport protocol of the target server
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkProtocol {
+    /// Open a TCP connection, and send the input as a stream
+    #[default]
+    Tcp,
+    /// Send the input as a single UDP datagram
+    Udp,
+}
+
+/// The size of the length in a [`Framing::LengthPrefix`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefixWidth {
+    /// A single byte
+    U8,
+    /// Two bytes
+    U16,
+    /// Four bytes
+    U32,
+    /// Eight bytes
+    U64,
+}
+
+impl PrefixWidth {
+    /// The size of the length, in bytes
+    #[must_use]
+    pub fn bytes(self) -> usize {
+        match self {
+            PrefixWidth::U8 => 1,
+            PrefixWidth::U16 => 2,
+            PrefixWidth::U32 => 4,
+            PrefixWidth::U64 => 8,
+        }
+    }
+
+    /// The longest input the length can describe
+    #[must_use]
+    pub fn max_len(self) -> usize {
+        match self {
+            PrefixWidth::U8 => u8::MAX.into(),
+            PrefixWidth::U16 => u16::MAX.into(),
+            PrefixWidth::U32 => usize::try_from(u32::MAX).unwrap_or(usize::MAX),
+            PrefixWidth::U64 => usize::MAX,
+        }
+    }
+}
+
+/// How inputs and responses are delimited on the wire
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Send the plain input. A response is whatever arrives in the first read.
+    #[default]
+    Raw,
+    /// Prefix the input with its length, in `width` bytes.
+    /// Responses are expected in the same format.
+    LengthPrefix {
+        /// The size of the length
+        width: PrefixWidth,
+        /// Send the length big endian, as most network protocols do
+        big_endian: bool,
+    },
+    /// Terminate the input with a delimiter, for example `\r\n` for line based protocols.
+    /// Responses are read until the delimiter.
+    Delimiter(Vec<u8>),
+}
+
+impl Framing {
+    /// Frames the `bytes` of an input, inputs too long for the length prefix are truncated
+    #[must_use]
+    pub fn frame(&self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            Framing::Raw => bytes.to_vec(),
+            Framing::LengthPrefix { width, big_endian } => {
+                let bytes = &bytes[..bytes.len().min(width.max_len())];
+                let len = bytes.len() as u64;
+                let width = width.bytes();
+                let mut framed = if *big_endian {
+                    len.to_be_bytes()[8 - width..].to_vec()
+                } else {
+                    len.to_le_bytes()[..width].to_vec()
+                };
+                framed.extend_from_slice(bytes);
+                framed
+            }
+            Framing::Delimiter(delimiter) => {
+                let mut framed = bytes.to_vec();
+                framed.extend_from_slice(delimiter);
+                framed
+            }
+        }
+    }
+
+    /// Reads a single framed response from the `reader`, of at most `max_size` bytes
+    fn read_response<R>(&self, reader: &mut R, max_size: usize) -> io::Result<Vec<u8>>
+    where
+        R: Read,
+    {
+        match self {
+            Framing::Raw => {
+                let mut response = vec![0; max_size];
+                let len = reader.read(&mut response)?;
+                response.truncate(len);
+                Ok(response)
+            }
+            Framing::LengthPrefix { width, big_endian } => {
+                let width = width.bytes();
+                let mut prefix = [0; 8];
+                if *big_endian {
+                    reader.read_exact(&mut prefix[8 - width..])?;
+                } else {
+                    reader.read_exact(&mut prefix[..width])?;
+                }
+                let len = if *big_endian {
+                    u64::from_be_bytes(prefix)
+                } else {
+                    u64::from_le_bytes(prefix)
+                };
+                let len = usize::try_from(len).unwrap_or(usize::MAX);
+                let mut response = vec![0; len.min(max_size)];
+                reader.read_exact(&mut response)?;
+                Ok(response)
+            }
+            Framing::Delimiter(delimiter) => {
+                let mut response = Vec::new();
+                let mut byte = [0];
+                while response.len() < max_size && !response.ends_with(delimiter) {
+                    if reader.read(&mut byte)? == 0 {
+                        break;
+                    }
+                    response.push(byte[0]);
+                }
+                Ok(response)
+            }
+        }
+    }
+}
+
+/// When a [`NetworkExecutor`] restarts its [`ServerProcess`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Only restart the server when it died
+    #[default]
+    OnCrash,
+    /// Restart the server before each execution, for stateful servers
+    EveryExecution,
+}
+
+/// The configuration of a [`NetworkExecutor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct NetworkConfig {
+    /// The `host:port` of the target server
+    #[builder(setter(into))]
+    pub addr: String,
+    /// The transport protocol. Defaults to TCP.
+    #[builder(default)]
+    pub protocol: NetworkProtocol,
+    /// How inputs and responses are delimited. Defaults to [`Framing::Raw`].
+    #[builder(default)]
+    pub framing: Framing,
+    /// The timeout to connect to the server. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub connect_timeout: Duration,
+    /// The timeout to send the input and receive the response. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub timeout: Duration,
+    /// Wait for a response, a missing response is a timeout. Defaults to `true`.
+    #[builder(default = true)]
+    pub wait_for_response: bool,
+    /// The maximum size of a response. Defaults to 64KiB.
+    #[builder(default = 0x10000)]
+    pub max_response_size: usize,
+    /// Reuse the TCP connection for the next execution, as long as the server keeps it open. Defaults to `false`.
+    #[builder(default)]
+    pub keep_alive: bool,
+    /// When to restart the [`ServerProcess`], if any. Defaults to [`RestartPolicy::OnCrash`].
+    #[builder(default)]
+    pub restart_policy: RestartPolicy,
+}
+
+/// A target server process, (re)started by a [`NetworkExecutor`].
+/// The process is killed on drop.
+#[derive(Debug)]
+pub struct ServerProcess {
+    command: Command,
+    child: Option<Child>,
+    startup_timeout: Duration,
+}
+
+impl ServerProcess {
+    /// Create a new [`ServerProcess`], running `command`. It is started by the [`NetworkExecutor`].
+    #[must_use]
+    pub fn new(command: Command) -> Self {
+        Self {
+            command,
+            child: None,
+            startup_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// How long to wait for a TCP server to accept connections after a start. Defaults to 5s.
+    #[must_use]
+    pub fn startup_timeout(mut self, startup_timeout: Duration) -> Self {
+        self.startup_timeout = startup_timeout;
+        self
+    }
+
+    /// Starts the server, and waits until it accepts TCP connections at `addr`.
+    /// UDP servers can not be probed, they are given a moment to start up.
+    pub fn start(&mut self, addr: &SocketAddr, protocol: NetworkProtocol) -> Result<(), Error> {
+        self.stop();
+        self.child = Some(self.command.spawn()?);
+
+        let start = Instant::now();
+        loop {
+            if let Some(status) = self.try_exit_status()? {
+                return Err(Error::illegal_state(format!(
+                    "The server exited during startup: {status}"
+                )));
+            }
+            let up = match protocol {
+                NetworkProtocol::Tcp => {
+                    TcpStream::connect_timeout(addr, Duration::from_millis(100)).is_ok()
+                }
+                NetworkProtocol::Udp => start.elapsed() >= Duration::from_millis(100),
+            };
+            if up {
+                return Ok(());
+            }
+            if start.elapsed() > self.startup_timeout {
+                return Err(Error::illegal_state(format!(
+                    "The server did not accept connections at {addr} within {:?}",
+                    self.startup_timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Kills the server, if it is running
+    pub fn stop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+
+    /// The exit status of the server, if it exited
+    pub fn try_exit_status(&mut self) -> Result<Option<std::process::ExitStatus>, Error> {
+        match &mut self.child {
+            Some(child) => Ok(child.try_wait()?),
+            None => Ok(None),
+        }
+    }
+
+    /// If the server was started and is still running
+    pub fn is_running(&mut self) -> bool {
+        self.child.is_some() && matches!(self.try_exit_status(), Ok(None))
+    }
+}
+
+impl Drop for ServerProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// An executor sending each input to a TCP or UDP server.
+pub struct NetworkExecutor<OT, S> {
+    config: NetworkConfig,
+    addr: SocketAddr,
+    observers: OT,
+    server: Option<ServerProcess>,
+    connection: Option<TcpStream>,
+    socket: Option<UdpSocket>,
+    last_response: Vec<u8>,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for NetworkExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetworkExecutor")
+            .field("config", &self.config)
+            .field("addr", &self.addr)
+            .field("observers", &self.observers)
+            .field("server", &self.server)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> NetworkExecutor<OT, S> {
+    /// Create a new [`NetworkExecutor`], sending to the server in the `config`
+    pub fn new(config: NetworkConfig, observers: OT) -> Result<Self, Error> {
+        let addr =
+            config.addr.to_socket_addrs()?.next().ok_or_else(|| {
+                Error::illegal_argument(format!("Could not resolve {}", config.addr))
+            })?;
+        Ok(Self {
+            config,
+            addr,
+            observers,
+            server: None,
+            connection: None,
+            socket: None,
+            last_response: Vec::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Starts the `server`, and restarts it according to the [`RestartPolicy`]
+    pub fn with_server(mut self, mut server: ServerProcess) -> Result<Self, Error> {
+        server.start(&self.addr, self.config.protocol)?;
+        self.server = Some(server);
+        Ok(self)
+    }
+
+    /// The configuration of this executor
+    pub fn config(&self) -> &NetworkConfig {
+        &self.config
+    }
+
+    /// The server process, if any
+    pub fn server_mut(&mut self) -> Option<&mut ServerProcess> {
+        self.server.as_mut()
+    }
+
+    /// The response to the last input, empty if there was none
+    pub fn last_response(&self) -> &[u8] {
+        &self.last_response
+    }
+
+    /// Sends the framed input, and receives the response
+    fn exchange(&mut self, framed: &[u8]) -> io::Result<()> {
+        self.last_response.clear();
+        match self.config.protocol {
+            NetworkProtocol::Tcp => {
+                let mut stream = if let Some(stream) = self.connection.take() {
+                    stream
+                } else {
+                    let stream =
+                        TcpStream::connect_timeout(&self.addr, self.config.connect_timeout)?;
+                    stream.set_read_timeout(Some(self.config.timeout))?;
+                    stream.set_write_timeout(Some(self.config.timeout))?;
+                    stream.set_nodelay(true)?;
+                    stream
+                };
+                stream.write_all(framed)?;
+                if self.config.wait_for_response {
+                    self.last_response = self
+                        .config
+                        .framing
+                        .read_response(&mut stream, self.config.max_response_size)?;
+                }
+                // A server closing the connection sends an empty response
+                if self.config.keep_alive && !self.last_response.is_empty() {
+                    self.connection = Some(stream);
+                }
+            }
+            NetworkProtocol::Udp => {
+                let socket = if let Some(socket) = self.socket.take() {
+                    socket
+                } else {
+                    let bind_addr = if self.addr.is_ipv4() {
+                        "0.0.0.0:0"
+                    } else {
+                        "[::]:0"
+                    };
+                    let socket = UdpSocket::bind(bind_addr)?;
+                    socket.connect(self.addr)?;
+                    socket.set_read_timeout(Some(self.config.timeout))?;
+                    socket
+                };
+                socket.send(framed)?;
+                if self.config.wait_for_response {
+                    let mut response = vec![0; self.config.max_response_size];
+                    let len = socket.recv(&mut response)?;
+                    response.truncate(len);
+                    self.last_response = response;
+                }
+                self.socket = Some(socket);
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps the outcome of an exchange to an [`ExitKind`], checking the server process
+    fn exit_kind(&mut self, result: io::Result<()>) -> Result<ExitKind, Error> {
+        let mut exit_kind = match result {
+            Ok(()) => ExitKind::Ok,
+            Err(err) => match err.kind() {
+                ErrorKind::TimedOut | ErrorKind::WouldBlock => ExitKind::Timeout,
+                ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::BrokenPipe
+                | ErrorKind::UnexpectedEof => ExitKind::Crash,
+                _ => return Err(err.into()),
+            },
+        };
+        if let Some(server) = &mut self.server {
+            if let Some(status) = server.try_exit_status()? {
+                log::debug!("The server exited: {status}");
+                exit_kind = ExitKind::Crash;
+            }
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for NetworkExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        if let Some(server) = &mut self.server {
+            if self.config.restart_policy == RestartPolicy::EveryExecution || !server.is_running() {
+                self.connection = None;
+                server.start(&self.addr, self.config.protocol)?;
+            }
+        }
+
+        let framed = self.config.framing.frame(input.target_bytes().as_slice());
+        let result = self.exchange(&framed);
+        let exit_kind = self.exit_kind(result)?;
+
+        if exit_kind != ExitKind::Ok {
+            // Do not reuse a connection the server may have given up on
+            self.connection = None;
+        }
+        if exit_kind == ExitKind::Crash {
+            if let Some(server) = &mut self.server {
+                self.socket = None;
+                server.start(&self.addr, self.config.protocol)?;
+            }
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<OT, S> HasTimeout for NetworkExecutor<OT, S> {
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.config.timeout = timeout;
+        self.connection = None;
+        self.socket = None;
+    }
+
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+}
+
+impl<OT, S> UsesState for NetworkExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> HasObservers for NetworkExecutor<OT, S>
+where
+    S: State,
+    OT: ObserversTuple<S::Input, S>,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use core::time::Duration;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::{Framing, NetworkConfig, NetworkExecutor, PrefixWidth};
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        fuzzer::NopFuzzer,
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_network_executor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // Answers a single request with the reversed payload, in the same framing
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut payload = vec![0; u16::from_be_bytes(len).into()];
+            stream.read_exact(&mut payload).unwrap();
+            payload.reverse();
+            stream.write_all(&len).unwrap();
+            stream.write_all(&payload).unwrap();
+        });
+
+        let config = NetworkConfig::builder()
+            .addr(addr.to_string())
+            .framing(Framing::LengthPrefix {
+                width: PrefixWidth::U16,
+                big_endian: true,
+            })
+            .timeout(Duration::from_secs(5))
+            .build();
+        let mut executor = NetworkExecutor::new(config, ()).unwrap();
+        let mut state = NopState::<BytesInput>::new();
+        let mut run = |executor: &mut NetworkExecutor<(), _>| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut state,
+                    &mut NopEventManager::new(),
+                    &BytesInput::new(b"abc".to_vec()),
+                )
+                .unwrap()
+        };
+
+        assert_eq!(run(&mut executor), ExitKind::Ok);
+        assert_eq!(executor.last_response(), b"cba");
+
+        // The server is gone, the connection is refused
+        server.join().unwrap();
+        assert_eq!(run(&mut executor), ExitKind::Crash);
+    }
+
+    #[test]
+    fn test_framing() {
+        let framing = Framing::LengthPrefix {
+            width: PrefixWidth::U32,
+            big_endian: false,
+        };
+        assert_eq!(framing.frame(b"ab"), [2, 0, 0, 0, b'a', b'b']);
+        // Inputs too long for the prefix are truncated
+        let framing = Framing::LengthPrefix {
+            width: PrefixWidth::U8,
+            big_endian: true,
+        };
+        let framed = framing.frame(&[b'a'; 300]);
+        assert_eq!(framed.len(), 256);
+        assert_eq!(framed[0], 255);
+        let mut response = framed.as_slice();
+        assert_eq!(
+            framing.read_response(&mut response, 1024).unwrap(),
+            [b'a'; 255]
+        );
+        let framing = Framing::Delimiter(b"\r\n".to_vec());
+        assert_eq!(framing.frame(b"ab"), b"ab\r\n");
+        let mut response: &[u8] = b"ok\r\nrest";
+        assert_eq!(
+            framing.read_response(&mut response, 1024).unwrap(),
+            b"ok\r\n"
+        );
+    }
+}
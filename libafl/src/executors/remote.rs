@@ -0,0 +1,493 @@
+//! The [`RemoteExecutor`] runs each input on another machine or device, through a thin agent running there.
+//!
+//! The agent is reached over TCP, over the stdin and stdout of a local command (for example `ssh device agent`
+//! or `adb shell agent`), or over a serial device.
+//! It speaks a minimal binary protocol, all integers are little endian:
+//!
+//! - On connect, the executor sends the [`REMOTE_AGENT_MAGIC`] and the agent echoes it back.
+//! - For each run, the executor sends the byte `R`, the `u32` length of the input and the input.
+//! - The agent runs the target and answers with one exit kind byte (`0` ok, `1` crash, `2` timeout, `3` oom),
+//!   the `u32` length of its coverage map, and the map. An agent without coverage sends an empty map.
+//!
+//! A response that does not arrive in time is reported as [`ExitKind::Timeout`],
+//! a connection lost during a run as [`ExitKind::Crash`],
+//! as devices tend to reset on crashes.
+//! In both cases, the executor reconnects before the next run.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::IndexMut,
+    time::Duration,
+};
+use std::{
+    fs::OpenOptions,
+    io::{self, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::{
+    tuples::{Handle, MatchName, RefIndexable},
+    AsSlice,
+};
+use typed_builder::TypedBuilder;
+
+use super::HasTimeout;
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{MapObserver, ObserversTuple},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The handshake exchanged with the agent on each connect
+pub const REMOTE_AGENT_MAGIC: &[u8; 8] = b"LIBAFLRA";
+
+/// The request to run an input
+const RUN_REQUEST: u8 = b'R';
+
+/// How the [`RemoteExecutor`] reaches its agent
+#[derive(Debug)]
+pub enum RemoteTransport {
+    /// Connect to the agent at `host:port`
+    Tcp(String),
+    /// Spawn the command, and talk to the agent over its stdin and stdout, for example `ssh device agent`
+    Command(Command),
+    /// Open the serial device at the path. The baud rate and line settings need to be configured beforehand.
+    Serial(PathBuf),
+}
+
+/// The configuration of a [`RemoteExecutor`]
+#[derive(Debug, TypedBuilder)]
+pub struct RemoteConfig {
+    /// How to reach the agent
+    pub transport: RemoteTransport,
+    /// The timeout to run an input and receive the results. Defaults to 5s.
+    #[builder(default = Duration::from_secs(5))]
+    pub timeout: Duration,
+    /// The timeout to connect and complete the handshake. Defaults to 10s.
+    #[builder(default = Duration::from_secs(10))]
+    pub connect_timeout: Duration,
+    /// How often to try to reconnect before giving up, for example while the device reboots. Defaults to 10.
+    #[builder(default = 10)]
+    pub reconnect_attempts: usize,
+    /// The delay between two connection attempts. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub reconnect_delay: Duration,
+    /// The maximum size of the coverage map sent by the agent. Defaults to 16MiB.
+    #[builder(default = 1 << 24)]
+    pub max_map_size: usize,
+}
+
+/// An open connection to the agent.
+///
+/// A thread forwards everything the agent sends, so reads can time out on any transport.
+struct Connection {
+    writer: Box<dyn Write + Send>,
+    chunks: Receiver<Vec<u8>>,
+    /// Received bytes not consumed yet
+    pending: Vec<u8>,
+    /// Shut down on drop, to stop the reader thread
+    tcp: Option<TcpStream>,
+    /// Killed on drop
+    child: Option<Child>,
+}
+
+impl Connection {
+    /// Opens the `transport`, and completes the handshake
+    fn open(transport: &mut RemoteTransport, connect_timeout: Duration) -> Result<Self, Error> {
+        let (reader, writer, tcp, child): (Box<dyn Read + Send>, Box<dyn Write + Send>, _, _) =
+            match transport {
+                RemoteTransport::Tcp(addr) => {
+                    let sock_addr = addr.to_socket_addrs()?.next().ok_or_else(|| {
+                        Error::illegal_argument(format!("Could not resolve {addr}"))
+                    })?;
+                    let stream = TcpStream::connect_timeout(&sock_addr, connect_timeout)?;
+                    stream.set_nodelay(true)?;
+                    (
+                        Box::new(stream.try_clone()?),
+                        Box::new(stream.try_clone()?),
+                        Some(stream),
+                        None,
+                    )
+                }
+                RemoteTransport::Command(command) => {
+                    let mut child = command
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .spawn()?;
+                    let stdin = child.stdin.take().expect("stdin was piped");
+                    let stdout = child.stdout.take().expect("stdout was piped");
+                    (Box::new(stdout), Box::new(stdin), None, Some(child))
+                }
+                RemoteTransport::Serial(path) => {
+                    let device = OpenOptions::new().read(true).write(true).open(path)?;
+                    (Box::new(device.try_clone()?), Box::new(device), None, None)
+                }
+            };
+
+        let (sender, chunks) = channel();
+        thread::Builder::new()
+            .name("libafl_remote_reader".into())
+            .spawn(move || {
+                let mut reader = reader;
+                let mut buf = [0; 0x1000];
+                loop {
+                    match reader.read(&mut buf) {
+                        Ok(0) | Err(_) => break,
+                        Ok(len) => {
+                            if sender.send(buf[..len].to_vec()).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                }
+            })?;
+
+        let mut connection = Self {
+            writer,
+            chunks,
+            pending: Vec::new(),
+            tcp,
+            child,
+        };
+        connection.writer.write_all(REMOTE_AGENT_MAGIC)?;
+        connection.writer.flush()?;
+        let mut magic = [0; REMOTE_AGENT_MAGIC.len()];
+        connection.read_exact(&mut magic, Instant::now() + connect_timeout)?;
+        if &magic != REMOTE_AGENT_MAGIC {
+            return Err(Error::illegal_state(format!(
+                "Unexpected handshake from the remote agent: {magic:?}"
+            )));
+        }
+        Ok(connection)
+    }
+
+    /// Fills `buf` with the next received bytes, failing with [`ErrorKind::TimedOut`] after the `deadline`
+    fn read_exact(&mut self, buf: &mut [u8], deadline: Instant) -> io::Result<()> {
+        while self.pending.len() < buf.len() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.chunks.recv_timeout(remaining) {
+                Ok(chunk) => self.pending.extend_from_slice(&chunk),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        ErrorKind::TimedOut,
+                        "The remote agent did not respond in time",
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "The remote agent closed the connection",
+                    ))
+                }
+            }
+        }
+        buf.copy_from_slice(&self.pending[..buf.len()]);
+        self.pending.drain(..buf.len());
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(tcp) = self.tcp.take() {
+            let _ = tcp.shutdown(Shutdown::Both);
+        }
+        if let Some(mut child) = self.child.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// An executor running each input through an agent on a remote machine or device.
+///
+/// The coverage map sent by the agent is copied into the map observer `C`, if the agent sends one.
+pub struct RemoteExecutor<C, OT, S> {
+    config: RemoteConfig,
+    map_observer: Handle<C>,
+    observers: OT,
+    connection: Option<Connection>,
+    /// The coverage map of the last run
+    map: Vec<u8>,
+    phantom: PhantomData<S>,
+}
+
+impl<C, OT, S> Debug for RemoteExecutor<C, OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteExecutor")
+            .field("config", &self.config)
+            .field("map_observer", &self.map_observer)
+            .field("observers", &self.observers)
+            .field("connected", &self.connection.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, OT, S> RemoteExecutor<C, OT, S> {
+    /// Create a new [`RemoteExecutor`]. The agent is connected lazily, before the first run.
+    pub fn new(config: RemoteConfig, map_observer: Handle<C>, observers: OT) -> Self {
+        Self {
+            config,
+            map_observer,
+            observers,
+            connection: None,
+            map: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The configuration of this executor
+    pub fn config(&self) -> &RemoteConfig {
+        &self.config
+    }
+
+    /// Connects to the agent, retrying according to the [`RemoteConfig`]
+    pub fn connect(&mut self) -> Result<(), Error> {
+        self.connection = None;
+        let mut attempt = 0;
+        loop {
+            match Connection::open(&mut self.config.transport, self.config.connect_timeout) {
+                Ok(connection) => {
+                    self.connection = Some(connection);
+                    return Ok(());
+                }
+                Err(err) if attempt < self.config.reconnect_attempts => {
+                    attempt += 1;
+                    log::warn!(
+                        "Could not connect to the remote agent ({err}), retrying ({attempt}/{})",
+                        self.config.reconnect_attempts
+                    );
+                    thread::sleep(self.config.reconnect_delay);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// The coverage map received after the last run, empty if there was none
+    pub fn last_map(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Sends the input and receives the results of the run
+    fn exchange(&mut self, input: &[u8]) -> io::Result<ExitKind> {
+        let connection = self
+            .connection
+            .as_mut()
+            .expect("connected before the exchange");
+        let len = u32::try_from(input.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Input too large"))?;
+        let mut request = Vec::with_capacity(5 + input.len());
+        request.push(RUN_REQUEST);
+        request.extend_from_slice(&len.to_le_bytes());
+        request.extend_from_slice(input);
+        connection.writer.write_all(&request)?;
+        connection.writer.flush()?;
+
+        let deadline = Instant::now() + self.config.timeout;
+        let mut header = [0; 5];
+        connection.read_exact(&mut header, deadline)?;
+        let exit_kind = match header[0] {
+            0 => ExitKind::Ok,
+            1 => ExitKind::Crash,
+            2 => ExitKind::Timeout,
+            3 => ExitKind::Oom,
+            kind => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Unknown exit kind {kind} from the remote agent"),
+                ))
+            }
+        };
+        let map_len = u32::from_le_bytes(header[1..].try_into().unwrap()) as usize;
+        if map_len > self.config.max_map_size {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("The remote agent sent a coverage map of {map_len} bytes"),
+            ));
+        }
+        self.map.resize(map_len, 0);
+        connection.read_exact(&mut self.map, deadline)?;
+        Ok(exit_kind)
+    }
+}
+
+impl<C, EM, OT, S, Z> Executor<EM, Z> for RemoteExecutor<C, OT, S>
+where
+    C: MapObserver<Entry = u8>,
+    EM: UsesState<State = S>,
+    OT: MatchName,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        if self.connection.is_none() {
+            self.connect()?;
+        }
+        self.map.clear();
+        let exit_kind = match self.exchange(input.target_bytes().as_slice()) {
+            Ok(exit_kind) => exit_kind,
+            Err(err) => {
+                // The agent is in an unknown state, start over before the next run
+                self.connection = None;
+                match err.kind() {
+                    ErrorKind::TimedOut => ExitKind::Timeout,
+                    ErrorKind::UnexpectedEof
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted => ExitKind::Crash,
+                    _ => return Err(err.into()),
+                }
+            }
+        };
+
+        if !self.map.is_empty() {
+            let mut observers = RefIndexable::from(&mut self.observers);
+            let map_observer = observers.index_mut(&self.map_observer);
+            let len = self.map.len().min(map_observer.usable_count());
+            for (idx, &value) in self.map[..len].iter().enumerate() {
+                map_observer.set(idx, value);
+            }
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<C, OT, S> HasTimeout for RemoteExecutor<C, OT, S> {
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.config.timeout = timeout;
+    }
+
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+}
+
+impl<C, OT, S> UsesState for RemoteExecutor<C, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<C, OT, S> HasObservers for RemoteExecutor<C, OT, S>
+where
+    S: State,
+    OT: ObserversTuple<S::Input, S>,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use core::time::Duration;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use libafl_bolts::{ownedref::OwnedMutSlice, tuples::Handled};
+
+    use super::{RemoteConfig, RemoteExecutor, RemoteTransport};
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        fuzzer::NopFuzzer,
+        inputs::BytesInput,
+        observers::StdMapObserver,
+        state::NopState,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_remote_executor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // An agent that reports a crash for inputs starting with `!` and then drops the connection
+        let agent = thread::spawn(move || {
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut magic = [0; 8];
+                stream.read_exact(&mut magic).unwrap();
+                stream.write_all(&magic).unwrap();
+                loop {
+                    let mut header = [0; 5];
+                    if stream.read_exact(&mut header).is_err() {
+                        break;
+                    }
+                    assert_eq!(header[0], b'R');
+                    let mut input =
+                        vec![0; u32::from_le_bytes(header[1..].try_into().unwrap()) as usize];
+                    stream.read_exact(&mut input).unwrap();
+                    if input.starts_with(b"!") {
+                        break;
+                    }
+                    stream.write_all(&[0, 0, 0, 0, 0]).unwrap();
+                }
+            }
+        });
+
+        let config = RemoteConfig::builder()
+            .transport(RemoteTransport::Tcp(addr.to_string()))
+            .reconnect_delay(Duration::from_millis(10))
+            .build();
+        let map_observer =
+            StdMapObserver::from_ownedref("map", OwnedMutSlice::from(vec![0_u8; 16]));
+        let mut executor = RemoteExecutor::new(config, map_observer.handle(), ());
+        let mut state = NopState::<BytesInput>::new();
+        let mut run = |executor: &mut RemoteExecutor<_, (), _>, input: &[u8]| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut state,
+                    &mut NopEventManager::new(),
+                    &BytesInput::new(input.to_vec()),
+                )
+                .unwrap()
+        };
+
+        assert_eq!(run(&mut executor, b"ok"), ExitKind::Ok);
+        assert_eq!(run(&mut executor, b"!crash"), ExitKind::Crash);
+        // Reconnects to the agent
+        assert_eq!(run(&mut executor, b"ok"), ExitKind::Ok);
+        assert!(executor.last_map().is_empty());
+
+        drop(executor);
+        agent.join().unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! Binary-only concolic tracing with [`SymQEMU`](https://github.com/eurecom-s3/symqemu).
+//!
+//! `SymQEMU` is a `QEMU` user-mode emulator that runs the target under a `SymCC` runtime.
+//! Built against a runtime made with the `symcc_runtime` crate, such as the one of the concolic fuzzer examples,
+//! it writes the same trace of [`SymExpr`](crate::observers::concolic::SymExpr)s to the shared memory
+//! as a target compiled with `SymCC`. The [`CommandExecutor`] built here is then used with a
+//! [`ConcolicObserver`](crate::observers::concolic::ConcolicObserver) in the [`ConcolicTracingStage`](crate::stages::ConcolicTracingStage).
+//!
+//! `SymQEMU` and its runtime can be built with the `symcc_libafl` crate.
+
+use alloc::vec::Vec;
+use core::{ops::Range, time::Duration};
+use std::{
+    borrow::ToOwned,
+    ffi::{OsStr, OsString},
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{fs::get_unique_std_input_file, tuples::MatchName};
+
+use crate::{
+    executors::command::{
+        CommandExecutor, CommandExecutorBuilder, StdCommandConfigurator, INPUT_FILE_PLACEHOLDER,
+    },
+    inputs::{HasTargetBytes, Input, UsesInput},
+    observers::{
+        concolic::{selective_symbolication_env_value, SELECTIVE_SYMBOLICATION_ENV_NAME},
+        ObserversTuple,
+    },
+    Error,
+};
+
+/// The environment variable `SymCC` and `SymQEMU` read the path of the symbolic input file from
+pub const SYMCC_INPUT_FILE_ENV_NAME: &str = "SYMCC_INPUT_FILE";
+
+/// Builds a [`CommandExecutor`] running the target under `SymQEMU`. See the [module-level documentation](self).
+///
+/// If an argument of the target contains [`INPUT_FILE_PLACEHOLDER`] (`@@`), the input is written to a file,
+/// which `SymQEMU` symbolizes, else the input is written to the standard input of the target.
+#[derive(Debug, Clone)]
+pub struct SymQemuExecutorBuilder {
+    symqemu: OsString,
+    target: OsString,
+    args: Vec<OsString>,
+    input_file: PathBuf,
+    symbolic_regions: Vec<Range<usize>>,
+    runtime_dir: Option<PathBuf>,
+    envs: Vec<(OsString, OsString)>,
+    timeout: Duration,
+    debug_child: bool,
+}
+
+impl SymQemuExecutorBuilder {
+    /// Create a new [`SymQemuExecutorBuilder`], running `target` with the `symqemu` binary, for example `symqemu-x86_64`
+    #[must_use]
+    pub fn new<P, T>(symqemu: P, target: T) -> Self
+    where
+        P: AsRef<OsStr>,
+        T: AsRef<OsStr>,
+    {
+        Self {
+            symqemu: symqemu.as_ref().to_owned(),
+            target: target.as_ref().to_owned(),
+            args: Vec::new(),
+            input_file: PathBuf::from(get_unique_std_input_file()),
+            symbolic_regions: Vec::new(),
+            runtime_dir: None,
+            envs: Vec::new(),
+            timeout: Duration::from_secs(5),
+            debug_child: false,
+        }
+    }
+
+    /// Adds an argument to the commandline of the target, [`INPUT_FILE_PLACEHOLDER`] (`@@`) being the input file
+    pub fn arg<O: AsRef<OsStr>>(&mut self, arg: O) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a range of arguments to the commandline of the target, see [`Self::arg`]
+    pub fn args<IT, O>(&mut self, args: IT) -> &mut Self
+    where
+        IT: IntoIterator<Item = O>,
+        O: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg);
+        }
+        self
+    }
+
+    /// Sets the file the input is written to, when the target reads it from a file.
+    /// Defaults to a file unique to the fuzzer process in the current directory.
+    pub fn input_file<P: AsRef<Path>>(&mut self, path: P) -> &mut Self {
+        path.as_ref().clone_into(&mut self.input_file);
+        self
+    }
+
+    /// Symbolizes the input bytes in `region` only, the other bytes being concrete.
+    /// Can be called multiple times. Without regions, the whole input is symbolic.
+    ///
+    /// The runtime needs the `SymbolicRegions` filter of the `symcc_runtime` crate.
+    pub fn symbolic_region(&mut self, region: Range<usize>) -> &mut Self {
+        self.symbolic_regions.push(region);
+        self
+    }
+
+    /// Sets the directory of the `libSymRuntime.so` runtime `SymQEMU` loads, if not the one it was built with
+    pub fn runtime_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.runtime_dir = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an environment variable to the executed command
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs
+            .push((key.as_ref().to_owned(), val.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets the execution timeout duration
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// If set to true, the output of `SymQEMU` won't be redirected to `/dev/null`.
+    /// Defaults to `false`.
+    pub fn debug_child(&mut self, debug_child: bool) -> &mut Self {
+        self.debug_child = debug_child;
+        self
+    }
+
+    /// The [`CommandExecutorBuilder`] running `SymQEMU`, to configure it further
+    pub fn command_builder(&self) -> Result<CommandExecutorBuilder, Error> {
+        let mut builder = CommandExecutor::builder();
+        builder
+            .program(&self.symqemu)
+            .arg(&self.target)
+            .timeout(self.timeout)
+            .debug_child(self.debug_child);
+
+        let file_input = self
+            .args
+            .iter()
+            .any(|arg| arg.to_string_lossy().contains(INPUT_FILE_PLACEHOLDER));
+        if file_input {
+            // `SymQEMU` runs in the same directory, but the target may change it
+            let input_file = if self.input_file.is_relative() {
+                std::env::current_dir()?.join(&self.input_file)
+            } else {
+                self.input_file.clone()
+            };
+            builder
+                .input_file(&input_file)
+                .args_template(&self.args)
+                .env(SYMCC_INPUT_FILE_ENV_NAME, &input_file);
+        } else {
+            builder.args(&self.args);
+        }
+
+        if !self.symbolic_regions.is_empty() {
+            builder.env(
+                SELECTIVE_SYMBOLICATION_ENV_NAME,
+                selective_symbolication_env_value(&self.symbolic_regions),
+            );
+        }
+        if let Some(runtime_dir) = &self.runtime_dir {
+            builder.env("LD_LIBRARY_PATH", runtime_dir);
+        }
+        builder.envs(self.envs.iter().map(|(key, val)| (key, val)));
+        Ok(builder)
+    }
+
+    /// Builds the [`CommandExecutor`] running `SymQEMU`
+    pub fn build<OT, S>(
+        &self,
+        observers: OT,
+    ) -> Result<CommandExecutor<OT, S, StdCommandConfigurator>, Error>
+    where
+        OT: MatchName + ObserversTuple<S::Input, S>,
+        S: UsesInput,
+        S::Input: Input + HasTargetBytes,
+    {
+        self.command_builder()?.build(observers)
+    }
+}
@@ -0,0 +1,355 @@
+//! The [`ThreadPoolExecutor`] runs a pure-Rust harness on a pool of worker threads inside the fuzzer process.
+//!
+//! Each worker has its own set of observers, usually built on top of its own slice of the coverage map.
+//! A batch of inputs is run in parallel, one input per worker, and the feedbacks are evaluated afterwards,
+//! one worker after the other, for example by the [`crate::stages::BatchedMutationalStage`].
+//! This saturates all cores from a single fuzzer process, without the overhead of a process per core.
+//!
+//! There are no signal handlers involved: panics of the harness are caught and reported as [`ExitKind::Crash`],
+//! but a segfault or an endless loop in a worker takes down the whole fuzzer.
+//! The harness should hence only be used for memory-safe, terminating targets.
+
+use alloc::{format, sync::Arc, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use libafl_bolts::tuples::RefIndexable;
+
+use crate::{
+    executors::{
+        batched::{BatchExecutor, HasBatchSnapshots},
+        Executor, ExitKind, HasObservers,
+    },
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// A worker thread of the [`ThreadPoolExecutor`]
+struct Worker<I> {
+    /// Sends the inputs to run, closed on drop to stop the worker
+    jobs: Option<Sender<I>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+/// Runs a harness on a pool of worker threads, each with its own observers.
+///
+/// The harness gets the index of the worker it runs on, so it can record its coverage into the matching map slice.
+/// The observers of worker `0` are the observers of this executor for single executions.
+pub struct ThreadPoolExecutor<H, OT, S>
+where
+    S: UsesInput,
+{
+    workers: Vec<Worker<S::Input>>,
+    /// The exit kinds and worker indices reported by the workers
+    results: Receiver<(usize, ExitKind)>,
+    /// The observers of each worker
+    observers: Vec<OT>,
+    /// The exit kinds of the last batch, one per worker
+    exit_kinds: Vec<ExitKind>,
+    batch_len: usize,
+    phantom: PhantomData<(H, S)>,
+}
+
+impl<H, OT, S> Debug for ThreadPoolExecutor<H, OT, S>
+where
+    OT: Debug,
+    S: UsesInput,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadPoolExecutor")
+            .field("workers", &self.workers.len())
+            .field("observers", &self.observers)
+            .field("exit_kinds", &self.exit_kinds)
+            .field("batch_len", &self.batch_len)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H, OT, S> ThreadPoolExecutor<H, OT, S>
+where
+    H: Fn(usize, &S::Input) -> ExitKind + Send + Sync + 'static,
+    S: UsesInput,
+    S::Input: Send + 'static,
+{
+    /// Create a new [`ThreadPoolExecutor`], spawning one worker thread per entry in `observers`.
+    ///
+    /// The `harness` is called with the index of the worker and the input to run.
+    pub fn new(harness: H, observers: Vec<OT>) -> Result<Self, Error> {
+        if observers.is_empty() {
+            return Err(Error::illegal_argument(
+                "ThreadPoolExecutor needs at least one worker",
+            ));
+        }
+
+        let harness = Arc::new(harness);
+        let (result_sender, results) = channel();
+        let mut workers = Vec::with_capacity(observers.len());
+        for id in 0..observers.len() {
+            let (jobs, job_receiver) = channel::<S::Input>();
+            let harness = harness.clone();
+            let result_sender = result_sender.clone();
+            let handle = thread::Builder::new()
+                .name(format!("libafl_worker_{id}"))
+                .spawn(move || {
+                    while let Ok(input) = job_receiver.recv() {
+                        let exit_kind =
+                            panic::catch_unwind(AssertUnwindSafe(|| harness(id, &input)))
+                                .unwrap_or(ExitKind::Crash);
+                        if result_sender.send((id, exit_kind)).is_err() {
+                            break;
+                        }
+                    }
+                })?;
+            workers.push(Worker {
+                jobs: Some(jobs),
+                handle: Some(handle),
+            });
+        }
+
+        Ok(Self {
+            exit_kinds: vec![ExitKind::Ok; workers.len()],
+            workers,
+            results,
+            observers,
+            batch_len: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<H, OT, S> ThreadPoolExecutor<H, OT, S>
+where
+    S: UsesInput,
+{
+    /// The number of worker threads
+    #[must_use]
+    pub fn workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// The observers of all workers
+    #[must_use]
+    pub fn worker_observers(&self) -> &[OT] {
+        &self.observers
+    }
+
+    /// Sends the `input` to the worker `id`
+    fn dispatch(&self, id: usize, input: S::Input) -> Result<(), Error> {
+        self.workers[id]
+            .jobs
+            .as_ref()
+            .expect("worker is running")
+            .send(input)
+            .map_err(|_| {
+                Error::illegal_state(format!("Worker {id} of the ThreadPoolExecutor is gone"))
+            })
+    }
+
+    /// Waits for the result of the next worker to finish
+    fn receive(&self) -> Result<(usize, ExitKind), Error> {
+        self.results
+            .recv()
+            .map_err(|_| Error::illegal_state("All workers of the ThreadPoolExecutor are gone"))
+    }
+}
+
+impl<H, OT, S> Drop for ThreadPoolExecutor<H, OT, S>
+where
+    S: UsesInput,
+{
+    fn drop(&mut self) {
+        for worker in &mut self.workers {
+            // Closing the channel stops the worker
+            drop(worker.jobs.take());
+        }
+        for worker in &mut self.workers {
+            if let Some(handle) = worker.handle.take() {
+                drop(handle.join());
+            }
+        }
+    }
+}
+
+impl<EM, H, OT, S, Z> Executor<EM, Z> for ThreadPoolExecutor<H, OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: Clone,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+        self.dispatch(0, input.clone())?;
+        let (_, exit_kind) = self.receive()?;
+        Ok(exit_kind)
+    }
+}
+
+impl<H, OT, S> HasBatchSnapshots for ThreadPoolExecutor<H, OT, S>
+where
+    S: UsesInput,
+{
+    /// One input per worker
+    fn batch_size(&self) -> usize {
+        self.workers.len()
+    }
+
+    fn snapshot(&self, idx: usize) -> Option<(&ExitKind, &Self::Observers)> {
+        if idx < self.batch_len {
+            Some((&self.exit_kinds[idx], &self.observers[idx]))
+        } else {
+            None
+        }
+    }
+}
+
+impl<EM, H, OT, S, Z> BatchExecutor<EM, Z> for ThreadPoolExecutor<H, OT, S>
+where
+    EM: UsesState<State = S>,
+    OT: ObserversTuple<S::Input, S>,
+    S: State + HasExecutions,
+    S::Input: Clone,
+    Z: UsesState<State = S>,
+{
+    /// Runs the `inputs` in parallel, one per worker
+    fn run_batch(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        inputs: &[Self::Input],
+    ) -> Result<(), Error> {
+        if inputs.len() > self.workers.len() {
+            return Err(Error::illegal_argument(format!(
+                "Batch of {} inputs exceeds the {} workers",
+                inputs.len(),
+                self.workers.len()
+            )));
+        }
+
+        self.batch_len = 0;
+        for (id, input) in inputs.iter().enumerate() {
+            self.observers[id].pre_exec_all(state, input)?;
+        }
+        for (id, input) in inputs.iter().enumerate() {
+            self.dispatch(id, input.clone())?;
+        }
+        for _ in 0..inputs.len() {
+            let (id, exit_kind) = self.receive()?;
+            self.exit_kinds[id] = exit_kind;
+        }
+        *state.executions_mut() += inputs.len() as u64;
+
+        for (id, input) in inputs.iter().enumerate() {
+            self.observers[id].post_exec_all(state, input, &self.exit_kinds[id])?;
+        }
+        self.batch_len = inputs.len();
+        Ok(())
+    }
+}
+
+impl<H, OT, S> UsesState for ThreadPoolExecutor<H, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<H, OT, S> HasObservers for ThreadPoolExecutor<H, OT, S>
+where
+    S: UsesInput,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers[0])
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::{
+        events::NopEventManager,
+        executors::{
+            batched::{BatchExecutor, HasBatchSnapshots},
+            Executor, ExitKind, ThreadPoolExecutor,
+        },
+        fuzzer::NopFuzzer,
+        inputs::{BytesInput, HasMutatorBytes},
+        state::{HasExecutions, NopState},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_thread_pool() {
+        static RUNS: [AtomicUsize; 3] = [
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+        ];
+
+        let mut executor = ThreadPoolExecutor::new(
+            |worker, input: &BytesInput| {
+                RUNS[worker].fetch_add(1, Ordering::Relaxed);
+                match input.bytes()[0] {
+                    1 => ExitKind::Crash,
+                    2 => panic!("harness panicked"),
+                    _ => ExitKind::Ok,
+                }
+            },
+            vec![(), (), ()],
+        )
+        .unwrap();
+        let mut state: NopState<BytesInput> = NopState::new();
+        let inputs: Vec<BytesInput> = (0..3).map(|i| BytesInput::new(vec![i])).collect();
+
+        executor
+            .run_batch(
+                &mut NopFuzzer::new(),
+                &mut state,
+                &mut NopEventManager::new(),
+                &inputs,
+            )
+            .unwrap();
+        assert_eq!(executor.snapshot(0).unwrap().0, &ExitKind::Ok);
+        assert_eq!(executor.snapshot(1).unwrap().0, &ExitKind::Crash);
+        assert_eq!(executor.snapshot(2).unwrap().0, &ExitKind::Crash);
+
+        let exit_kind = executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut state,
+                &mut NopEventManager::new(),
+                &inputs[0],
+            )
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Ok);
+        assert_eq!(*state.executions(), 4);
+        assert_eq!(RUNS[0].load(Ordering::Relaxed), 2);
+        assert_eq!(RUNS[2].load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,403 @@
+//! The [`WasmExecutor`] runs a harness compiled to WebAssembly in-process, sandboxed by `wasmtime`.
+//!
+//! The module needs to export its `memory`, a `LLVMFuzzerTestOneInput`-style function taking a pointer and a length,
+//! and `malloc` and `free` to place the input in its linear memory.
+//! Coverage is collected from the 8-bit counters of `-fsanitize-coverage=inline-8bit-counters`.
+//! The start and end of the counter section need to be exported as globals, for example by linking with
+//! `-Wl,--export=__start___sancov_cntrs,--export=__stop___sancov_cntrs`.
+//!
+//! Traps of the module, for example an `abort()` or an out-of-bounds access, are reported as [`ExitKind::Crash`].
+//! A run that exceeds the timeout is interrupted and reported as [`ExitKind::Timeout`].
+//! After a crash or a timeout, the module is instantiated anew with fresh memory before the next run.
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::{IndexMut, Range},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+use libafl_bolts::{
+    tuples::{Handle, MatchName, RefIndexable},
+    AsSlice,
+};
+use typed_builder::TypedBuilder;
+use wasmtime::{Config, Engine, Linker, Memory, Module, Store, Trap, TypedFunc};
+
+use super::HasTimeout;
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{MapObserver, ObserversTuple},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The interval at which the epoch of the engine advances, the granularity of the timeout
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+/// Where the [`WasmExecutor`] loads its module from
+#[derive(Debug, Clone)]
+pub enum WasmModuleSource {
+    /// A `.wasm` file
+    File(PathBuf),
+    /// The binary module
+    Bytes(Vec<u8>),
+}
+
+/// The configuration of a [`WasmExecutor`]
+#[derive(Debug, TypedBuilder)]
+pub struct WasmConfig {
+    /// The module to run
+    pub module: WasmModuleSource,
+    /// The exported harness function, taking a pointer to the input and its length
+    #[builder(default = "LLVMFuzzerTestOneInput".to_owned(), setter(into))]
+    pub entry: String,
+    /// The exported allocator function to place the input in memory
+    #[builder(default = "malloc".to_owned(), setter(into))]
+    pub malloc: String,
+    /// The exported function to release the input again
+    #[builder(default = "free".to_owned(), setter(into))]
+    pub free: String,
+    /// The exported linear memory
+    #[builder(default = "memory".to_owned(), setter(into))]
+    pub memory: String,
+    /// The exported global holding the start address of the coverage counters
+    #[builder(default = "__start___sancov_cntrs".to_owned(), setter(into))]
+    pub counters_start: String,
+    /// The exported global holding the end address of the coverage counters
+    #[builder(default = "__stop___sancov_cntrs".to_owned(), setter(into))]
+    pub counters_stop: String,
+    /// The timeout for a single run. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub timeout: Duration,
+}
+
+/// Advances the epoch of the engine in the background, to interrupt runs that take too long
+struct EpochTicker {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl EpochTicker {
+    fn spawn(engine: Engine) -> Result<Self, Error> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = thread::Builder::new()
+            .name("libafl_wasm_epoch".into())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        thread::sleep(EPOCH_TICK);
+                        engine.increment_epoch();
+                    }
+                }
+            })?;
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            drop(handle.join());
+        }
+    }
+}
+
+/// An instance of the module, with the exports the executor needs
+struct WasmInstance {
+    store: Store<()>,
+    memory: Memory,
+    entry: TypedFunc<(u32, u32), i32>,
+    malloc: TypedFunc<u32, u32>,
+    free: TypedFunc<u32, ()>,
+    /// The coverage counters in the linear memory
+    counters: Range<usize>,
+    /// The buffer for the input in the linear memory, reused between runs
+    buf: u32,
+    buf_len: u32,
+}
+
+impl WasmInstance {
+    /// Copies the `input` into the linear memory, growing the buffer if needed
+    fn write_input(&mut self, input: &[u8]) -> wasmtime::Result<(u32, u32)> {
+        let len = u32::try_from(input.len())?;
+        if len > self.buf_len || self.buf == 0 {
+            if self.buf != 0 {
+                self.free.call(&mut self.store, self.buf)?;
+            }
+            self.buf_len = len.max(1);
+            self.buf = self.malloc.call(&mut self.store, self.buf_len)?;
+            if self.buf == 0 {
+                self.buf_len = 0;
+                return Err(wasmtime::Error::msg("malloc failed for the input"));
+            }
+        }
+        self.memory
+            .write(&mut self.store, self.buf as usize, input)?;
+        Ok((self.buf, len))
+    }
+}
+
+/// Runs a WebAssembly harness in-process, sandboxed by `wasmtime`, and collects its coverage counters
+pub struct WasmExecutor<C, OT, S> {
+    config: WasmConfig,
+    engine: Engine,
+    module: Module,
+    linker: Linker<()>,
+    instance: Option<WasmInstance>,
+    _ticker: EpochTicker,
+    map_observer: Handle<C>,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<C, OT, S> Debug for WasmExecutor<C, OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmExecutor")
+            .field("config", &self.config)
+            .field("instantiated", &self.instance.is_some())
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Converts an error of `wasmtime`
+fn wasm_error(context: &str, err: &wasmtime::Error) -> Error {
+    Error::illegal_state(format!("{context}: {err:#}"))
+}
+
+impl<C, OT, S> WasmExecutor<C, OT, S> {
+    /// Create a new [`WasmExecutor`] for a module without imports.
+    ///
+    /// The `map_observer` gets the coverage counters of each run.
+    pub fn new(config: WasmConfig, map_observer: Handle<C>, observers: OT) -> Result<Self, Error> {
+        Self::with_linker(config, |_| Ok(()), map_observer, observers)
+    }
+
+    /// Create a new [`WasmExecutor`], defining the imports of the module with `setup`, for example stubs for WASI.
+    pub fn with_linker<F>(
+        config: WasmConfig,
+        setup: F,
+        map_observer: Handle<C>,
+        observers: OT,
+    ) -> Result<Self, Error>
+    where
+        F: FnOnce(&mut Linker<()>) -> wasmtime::Result<()>,
+    {
+        let mut engine_config = Config::new();
+        engine_config.epoch_interruption(true);
+        let engine = Engine::new(&engine_config)
+            .map_err(|err| wasm_error("Failed to create the wasm engine", &err))?;
+        let module = match &config.module {
+            WasmModuleSource::File(path) => Module::from_file(&engine, path),
+            WasmModuleSource::Bytes(bytes) => Module::new(&engine, bytes),
+        }
+        .map_err(|err| wasm_error("Failed to load the wasm module", &err))?;
+        let mut linker = Linker::new(&engine);
+        setup(&mut linker).map_err(|err| wasm_error("Failed to set up the wasm imports", &err))?;
+
+        let mut executor = Self {
+            config,
+            _ticker: EpochTicker::spawn(engine.clone())?,
+            engine,
+            module,
+            linker,
+            instance: None,
+            map_observer,
+            observers,
+            phantom: PhantomData,
+        };
+        // Fail early on a module with missing exports
+        executor.instance = Some(executor.instantiate()?);
+        Ok(executor)
+    }
+
+    /// The configuration of this executor
+    #[must_use]
+    pub fn config(&self) -> &WasmConfig {
+        &self.config
+    }
+
+    /// Instantiates the module in a fresh store and looks up its exports
+    fn instantiate(&self) -> Result<WasmInstance, Error> {
+        let mut store = Store::new(&self.engine, ());
+        store.epoch_deadline_trap();
+        store.set_epoch_deadline(epoch_ticks(self.config.timeout));
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, &self.module)
+            .map_err(|err| wasm_error("Failed to instantiate the wasm module", &err))?;
+        // Reactor modules run their constructors in `_initialize`
+        if let Ok(initialize) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            initialize
+                .call(&mut store, ())
+                .map_err(|err| wasm_error("The wasm module failed to initialize", &err))?;
+        }
+
+        let memory = instance
+            .get_memory(&mut store, &self.config.memory)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!(
+                    "The wasm module does not export the memory {}",
+                    self.config.memory
+                ))
+            })?;
+        let entry = instance
+            .get_typed_func(&mut store, &self.config.entry)
+            .map_err(|err| wasm_error("No harness function in the wasm module", &err))?;
+        let malloc = instance
+            .get_typed_func(&mut store, &self.config.malloc)
+            .map_err(|err| wasm_error("No malloc in the wasm module", &err))?;
+        let free = instance
+            .get_typed_func(&mut store, &self.config.free)
+            .map_err(|err| wasm_error("No free in the wasm module", &err))?;
+
+        let mut address = |name: &str| {
+            instance
+                .get_global(&mut store, name)
+                .and_then(|global| global.get(&mut store).i32())
+                .map(|address| u32::from_ne_bytes(address.to_ne_bytes()) as usize)
+                .ok_or_else(|| {
+                    Error::illegal_argument(format!(
+                        "The wasm module does not export the i32 global {name}"
+                    ))
+                })
+        };
+        let counters = address(&self.config.counters_start)?..address(&self.config.counters_stop)?;
+        if counters.start > counters.end || counters.end > memory.data_size(&store) {
+            return Err(Error::illegal_argument(format!(
+                "Invalid coverage counters at {counters:?} in the wasm module"
+            )));
+        }
+
+        Ok(WasmInstance {
+            store,
+            memory,
+            entry,
+            malloc,
+            free,
+            counters,
+            buf: 0,
+            buf_len: 0,
+        })
+    }
+}
+
+/// The number of epoch ticks until the `timeout` elapses
+fn epoch_ticks(timeout: Duration) -> u64 {
+    u64::try_from(timeout.as_millis() / EPOCH_TICK.as_millis())
+        .unwrap_or(u64::MAX)
+        .saturating_add(1)
+}
+
+impl<C, EM, OT, S, Z> Executor<EM, Z> for WasmExecutor<C, OT, S>
+where
+    C: MapObserver<Entry = u8>,
+    EM: UsesState<State = S>,
+    OT: MatchName,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        if self.instance.is_none() {
+            self.instance = Some(self.instantiate()?);
+        }
+        let instance = self.instance.as_mut().unwrap();
+        let counters = instance.counters.clone();
+        instance.memory.data_mut(&mut instance.store)[counters.clone()].fill(0);
+
+        let target_bytes = input.target_bytes();
+        instance
+            .store
+            .set_epoch_deadline(epoch_ticks(self.config.timeout));
+        let result = instance
+            .write_input(target_bytes.as_slice())
+            .and_then(|(buf, len)| instance.entry.call(&mut instance.store, (buf, len)));
+        let exit_kind = match result {
+            Ok(_) => ExitKind::Ok,
+            Err(err) => match err.downcast_ref::<Trap>() {
+                Some(Trap::Interrupt) => ExitKind::Timeout,
+                _ => ExitKind::Crash,
+            },
+        };
+
+        {
+            let map = &instance.memory.data(&instance.store)[counters];
+            let mut observers = RefIndexable::from(&mut self.observers);
+            let map_observer = observers.index_mut(&self.map_observer);
+            let len = map.len().min(map_observer.usable_count());
+            for (idx, &value) in map[..len].iter().enumerate() {
+                map_observer.set(idx, value);
+            }
+        }
+
+        if exit_kind != ExitKind::Ok {
+            // The memory of the module may be corrupted, start over before the next run
+            self.instance = None;
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<C, OT, S> HasTimeout for WasmExecutor<C, OT, S> {
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.config.timeout = timeout;
+    }
+
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+}
+
+impl<C, OT, S> UsesState for WasmExecutor<C, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<C, OT, S> HasObservers for WasmExecutor<C, OT, S>
+where
+    S: State,
+    OT: ObserversTuple<S::Input, S>,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
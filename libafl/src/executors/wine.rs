@@ -0,0 +1,310 @@
+//! The [`WineExecutor`] runs a Windows PE harness on the host under Wine.
+//!
+//! For each run, the testcase is written to a file, and its Windows path (on the `Z:` drive,
+//! which Wine maps to `/`) replaces the [`INPUT_FILE_PLACEHOLDER`] (`@@`) in the arguments.
+//! The harness writes its coverage map to the file passed in `LIBAFL_COVERAGE_FILE`, also as a Windows path.
+//! The map is read back after the run.
+//!
+//! Windows exceptions do not reach the host as signals. Wine reports an unhandled exception on stderr,
+//! which is what turns a run into an [`ExitKind::Crash`].
+//! The Wine debugger is disabled, so that a crashing harness exits right away instead of waiting for it.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    ops::IndexMut,
+    time::Duration,
+};
+use std::{
+    fs::{self, File},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use libafl_bolts::{
+    fs::{get_unique_std_input_file, InputFile},
+    tuples::{Handle, MatchName, RefIndexable},
+    AsSlice,
+};
+use typed_builder::TypedBuilder;
+use wait_timeout::ChildExt;
+
+use super::HasTimeout;
+use crate::{
+    executors::{command::INPUT_FILE_PLACEHOLDER, Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{MapObserver, ObserversTuple},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The messages Wine and its debugger print for an unhandled exception
+const UNHANDLED_EXCEPTION_MARKERS: [&str; 2] = ["wine: Unhandled ", "Unhandled exception: "];
+
+/// The configuration of a [`WineExecutor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct WineConfig {
+    /// The Windows executable to run
+    #[builder(setter(into))]
+    pub harness: PathBuf,
+    /// The arguments of the harness. Each [`INPUT_FILE_PLACEHOLDER`] (`@@`) is replaced with
+    /// the Windows path of the testcase.
+    #[builder(default)]
+    pub args: Vec<String>,
+    /// The `wine` loader. Defaults to `wine` from the `PATH`.
+    #[builder(default = "wine".into(), setter(into))]
+    pub wine: PathBuf,
+    /// The Wine prefix to run in, passed as `WINEPREFIX`. Defaults to the prefix of the user.
+    #[builder(default, setter(strip_option, into))]
+    pub prefix: Option<PathBuf>,
+    /// The debug channels of Wine, passed as `WINEDEBUG`. Defaults to `-all`.
+    /// Unhandled exceptions are reported even with all channels off.
+    #[builder(default = "-all".into(), setter(into))]
+    pub debug_channels: String,
+    /// The timeout of a single run. Defaults to 5s.
+    #[builder(default = Duration::from_secs(5))]
+    pub timeout: Duration,
+    /// Show the output of the harness. Defaults to `false`.
+    #[builder(default)]
+    pub debug_child: bool,
+}
+
+/// An executor running a Windows harness under Wine.
+///
+/// The coverage map written by the harness is copied into the map observer `C`.
+pub struct WineExecutor<C, OT, S> {
+    config: WineConfig,
+    map_observer: Handle<C>,
+    observers: OT,
+    input_file: InputFile,
+    /// The Windows path of the testcase
+    wine_input: String,
+    coverage_file: PathBuf,
+    stderr_file: PathBuf,
+    last_stderr: String,
+    phantom: PhantomData<S>,
+}
+
+impl<C, OT, S> Debug for WineExecutor<C, OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WineExecutor")
+            .field("config", &self.config)
+            .field("map_observer", &self.map_observer)
+            .field("observers", &self.observers)
+            .field("input_file", &self.input_file.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<C, OT, S> WineExecutor<C, OT, S> {
+    /// Create a new [`WineExecutor`]
+    pub fn new(config: WineConfig, map_observer: Handle<C>, observers: OT) -> Result<Self, Error> {
+        let input_file = InputFile::create(get_unique_std_input_file())?;
+        let input_path = fs::canonicalize(&input_file.path)?;
+        let wine_input = wine_path(&input_path)?;
+        let coverage_file = input_path.with_extension("coverage");
+        let stderr_file = input_path.with_extension("stderr");
+        Ok(Self {
+            config,
+            map_observer,
+            observers,
+            input_file,
+            wine_input,
+            coverage_file,
+            stderr_file,
+            last_stderr: String::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// The configuration of this executor
+    pub fn config(&self) -> &WineConfig {
+        &self.config
+    }
+
+    /// The stderr of the last run, including the exception report of Wine after a crash
+    pub fn last_stderr(&self) -> &str {
+        &self.last_stderr
+    }
+
+    /// The command running the harness once
+    fn command(&self) -> Result<Command, Error> {
+        let mut command = Command::new(&self.config.wine);
+        command.arg(&self.config.harness);
+        command.args(
+            self.config
+                .args
+                .iter()
+                .map(|arg| arg.replace(INPUT_FILE_PLACEHOLDER, &self.wine_input)),
+        );
+        if let Some(prefix) = &self.config.prefix {
+            command.env("WINEPREFIX", prefix);
+        }
+        command
+            .env("WINEDEBUG", &self.config.debug_channels)
+            // Without the debugger, a crashing process terminates right after the exception report
+            .env("WINEDLLOVERRIDES", "winedbg.exe=d")
+            .env("LIBAFL_COVERAGE_FILE", wine_path(&self.coverage_file)?)
+            .stdin(Stdio::null())
+            .stderr(File::create(&self.stderr_file)?);
+        if !self.config.debug_child {
+            command.stdout(Stdio::null());
+        }
+        Ok(command)
+    }
+}
+
+impl<C, OT, S> Drop for WineExecutor<C, OT, S> {
+    fn drop(&mut self) {
+        drop(fs::remove_file(&self.coverage_file));
+        drop(fs::remove_file(&self.stderr_file));
+    }
+}
+
+impl<C, EM, OT, S, Z> Executor<EM, Z> for WineExecutor<C, OT, S>
+where
+    C: MapObserver<Entry = u8>,
+    EM: UsesState<State = S>,
+    OT: MatchName,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        self.input_file.write_buf(input.target_bytes().as_slice())?;
+        match fs::remove_file(&self.coverage_file) {
+            Err(err) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+
+        let mut child = self.command()?.spawn()?;
+        let timed_out = child.wait_timeout(self.config.timeout)?.is_none();
+        if timed_out {
+            // Windows processes started by the harness are left to the wineserver
+            drop(child.kill());
+        }
+        let status = child.wait()?;
+
+        self.last_stderr = String::from_utf8_lossy(&fs::read(&self.stderr_file)?).into_owned();
+        let exit_kind = if timed_out {
+            ExitKind::Timeout
+        } else if is_unhandled_exception(&self.last_stderr) || status.code().is_none() {
+            // Reported by Wine, or Wine itself died from a signal
+            ExitKind::Crash
+        } else {
+            ExitKind::Ok
+        };
+
+        match fs::read(&self.coverage_file) {
+            Ok(map) => {
+                let mut observers = RefIndexable::from(&mut self.observers);
+                let map_observer = observers.index_mut(&self.map_observer);
+                let len = map.len().min(map_observer.usable_count());
+                for (idx, &value) in map[..len].iter().enumerate() {
+                    map_observer.set(idx, value);
+                }
+            }
+            // A harness killed early may not have written its map
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<C, OT, S> HasTimeout for WineExecutor<C, OT, S> {
+    #[inline]
+    fn set_timeout(&mut self, timeout: Duration) {
+        self.config.timeout = timeout;
+    }
+
+    #[inline]
+    fn timeout(&self) -> Duration {
+        self.config.timeout
+    }
+}
+
+impl<C, OT, S> UsesState for WineExecutor<C, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<C, OT, S> HasObservers for WineExecutor<C, OT, S>
+where
+    S: State,
+    OT: ObserversTuple<S::Input, S>,
+{
+    type Observers = OT;
+
+    #[inline]
+    fn observers(&self) -> RefIndexable<&Self::Observers, Self::Observers> {
+        RefIndexable::from(&self.observers)
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> RefIndexable<&mut Self::Observers, Self::Observers> {
+        RefIndexable::from(&mut self.observers)
+    }
+}
+
+/// The Windows path of the absolute host `path`, on the `Z:` drive Wine maps to `/`
+pub fn wine_path(path: &Path) -> Result<String, Error> {
+    if !path.is_absolute() {
+        return Err(Error::illegal_argument(format!(
+            "{} is not an absolute path",
+            path.display()
+        )));
+    }
+    let path = path
+        .to_str()
+        .ok_or_else(|| Error::illegal_argument(format!("{} is not valid UTF-8", path.display())))?;
+    Ok(format!("Z:{}", path.replace('/', "\\")))
+}
+
+/// Checks the stderr of a run for the report of an unhandled exception
+fn is_unhandled_exception(stderr: &str) -> bool {
+    stderr.lines().any(|line| {
+        UNHANDLED_EXCEPTION_MARKERS
+            .iter()
+            .any(|m| line.starts_with(m))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{is_unhandled_exception, wine_path};
+
+    #[test]
+    fn test_wine_helpers() {
+        assert_eq!(
+            wine_path(Path::new("/tmp/fuzz/.cur_input")).unwrap(),
+            "Z:\\tmp\\fuzz\\.cur_input"
+        );
+        assert!(wine_path(Path::new("relative")).is_err());
+
+        assert!(is_unhandled_exception(
+            "wine: Unhandled page fault on read access to 0000000000000000 at address 0000000140001010 (thread 0024), starting debugger...\n"
+        ));
+        assert!(is_unhandled_exception(
+            "0024:err:module:foo\nUnhandled exception: stack overflow in 64-bit code (0x0000000140001234).\n"
+        ));
+        assert!(!is_unhandled_exception("0024:fixme:ntdll:stub\n"));
+    }
+}
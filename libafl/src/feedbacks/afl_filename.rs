@@ -0,0 +1,222 @@
+//! The [`AflFilenameFeedback`] names [`Testcase`]s following the AFL++ conventions.
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::{current_time, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{AflEntryKind, AflFilenameMetadata, Corpus, HasCurrentCorpusId, Testcase},
+    executors::{ExitKind, ExitSignalMetadata},
+    feedbacks::{Feedback, StateInitializer},
+    mutators::LogMutationMetadata,
+    state::{HasCorpus, HasExecutions, HasStartTime},
+    Error, HasMetadata,
+};
+
+/// Attaches [`AflFilenameMetadata`] to new [`Testcase`]s, so they are named like AFL++ would name them:
+/// `id:000042,src:000007,time:1523,execs:8412,op:havoc,rep:4`.
+///
+/// Use it in the feedback together with [`crate::corpus::AflOutputDir::queue_corpus`],
+/// and in the objective together with [`crate::corpus::AflOutputDir::solutions_corpus`],
+/// which sorts timeouts into `hangs/` and all other objectives into `crashes/`.
+///
+/// Crashes get the `sig:` of the [`ExitSignalMetadata`] the executor or its crash handler recorded,
+/// which is attached to the testcase as well.
+/// The `rep:` is the number of stacked mutations of a [`crate::mutators::LoggerScheduledMutator`].
+///
+/// Is never interesting (use with an Eager OR).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AflFilenameFeedback {
+    op: Cow<'static, str>,
+    last_exit_kind: Option<ExitKind>,
+    last_signal: Option<ExitSignalMetadata>,
+    last_rep: Option<usize>,
+}
+
+impl AflFilenameFeedback {
+    /// Creates a new [`AflFilenameFeedback`], using `havoc` as operation name
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_op("havoc")
+    }
+
+    /// Creates a new [`AflFilenameFeedback`], using the given operation name (`op:` part of the filename)
+    #[must_use]
+    pub fn with_op<O>(op: O) -> Self
+    where
+        O: Into<Cow<'static, str>>,
+    {
+        Self {
+            op: op.into(),
+            last_exit_kind: None,
+            last_signal: None,
+            last_rep: None,
+        }
+    }
+}
+
+impl Default for AflFilenameFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Named for AflFilenameFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("AflFilenameFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for AflFilenameFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for AflFilenameFeedback
+where
+    S: HasCorpus + HasCurrentCorpusId + HasExecutions + HasStartTime,
+{
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        self.last_exit_kind = Some(*exit_kind);
+        // Taken even if this run did not crash, so a stale signal never names a later crash
+        self.last_signal =
+            ExitSignalMetadata::take_last().filter(|_| *exit_kind == ExitKind::Crash);
+        self.last_rep = LogMutationMetadata::current_len();
+        Ok(false)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    #[inline]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let kind = match self.last_exit_kind.take() {
+            Some(ExitKind::Ok) | None => AflEntryKind::Queue,
+            Some(ExitKind::Timeout) => AflEntryKind::Hang,
+            Some(_) => AflEntryKind::Crash,
+        };
+        let signal = self
+            .last_signal
+            .take()
+            .filter(|_| kind == AflEntryKind::Crash);
+        if let Some(signal) = signal {
+            testcase.add_metadata(signal);
+        }
+        let src = state.current_corpus_id()?;
+        let meta = AflFilenameMetadata {
+            kind,
+            src,
+            time: current_time()
+                .saturating_sub(*state.start_time())
+                .as_millis() as u64,
+            execs: *state.executions(),
+            op: self.op.clone(),
+            rep: self.last_rep.take().or_else(|| {
+                testcase
+                    .metadata_map()
+                    .get::<LogMutationMetadata>()
+                    .map(|log| log.len())
+            }),
+            sig: signal.map(|signal| signal.signal()),
+            orig: if src.is_none() {
+                testcase.filename().clone()
+            } else {
+                None
+            },
+        };
+        if kind == AflEntryKind::Queue {
+            // Queue entries are named here; solutions get their per-directory id in the solutions corpus.
+            *testcase.filename_mut() = Some(meta.filename(state.corpus().peek_free_id()));
+        }
+        testcase.add_metadata(meta);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::AflFilenameFeedback;
+    use crate::{
+        corpus::{AflEntryKind, AflFilenameMetadata, InMemoryCorpus, Testcase},
+        executors::{ExitKind, ExitSignalMetadata},
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        state::StdState,
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_afl_filename_crash_signal() {
+        let mut feedback = AflFilenameFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut (),
+        )
+        .unwrap();
+        let input = BytesInput::new(b"crash".to_vec());
+
+        let mut sig_of =
+            |state: &mut StdState<_, _, _, _>, exit_kind, testcase: &mut Testcase<_>| {
+                assert!(!feedback
+                    .is_interesting(state, &mut (), &input, &(), &exit_kind)
+                    .unwrap());
+                feedback
+                    .append_metadata(state, &mut (), &(), testcase)
+                    .unwrap();
+                let meta = testcase.metadata::<AflFilenameMetadata>().unwrap();
+                assert_eq!(
+                    meta.kind == AflEntryKind::Crash,
+                    exit_kind == ExitKind::Crash
+                );
+                meta.sig
+            };
+
+        // Recorded by the executor or the crash handler, and attached to the crash
+        ExitSignalMetadata::record_last(11);
+        let mut testcase = Testcase::new(input.clone());
+        assert_eq!(sig_of(&mut state, ExitKind::Crash, &mut testcase), Some(11));
+        assert_eq!(
+            testcase.metadata::<ExitSignalMetadata>().unwrap().signal(),
+            11
+        );
+        assert!(ExitSignalMetadata::take_last().is_none());
+
+        // A stale signal never names a later entry
+        ExitSignalMetadata::record_last(6);
+        assert_eq!(
+            sig_of(
+                &mut state,
+                ExitKind::Timeout,
+                &mut Testcase::new(input.clone())
+            ),
+            None
+        );
+        assert_eq!(
+            sig_of(
+                &mut state,
+                ExitKind::Crash,
+                &mut Testcase::new(input.clone())
+            ),
+            None
+        );
+    }
+}
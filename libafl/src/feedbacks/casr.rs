@@ -0,0 +1,337 @@
+//! Converts solutions into [CASR](https://github.com/ispras/casr) crash reports.
+//!
+//! The [`CasrFeedback`] attaches a [`CasrReportMetadata`] to each new solution, from the `AddressSanitizer` report in the
+//! stderr of the target or from the mini-BSOD an in-process executor attached as [`CrashReportMetadata`].
+//! The [`crate::stages::CasrStage`] writes them out as `.casrep` files, deduplicated and clustered like `casr-cluster` does.
+
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use libafl_bolts::{
+    hash_std, impl_serdeany,
+    tuples::{Handle, Handled, MatchName, MatchNameRef},
+    Named,
+};
+#[allow(unused_imports)]
+use libcasr::{
+    asan::{AsanContext, AsanStacktrace},
+    constants::{
+        STACK_FRAME_FILEPATH_IGNORE_REGEXES_CPP, STACK_FRAME_FILEPATH_IGNORE_REGEXES_GO,
+        STACK_FRAME_FILEPATH_IGNORE_REGEXES_JAVA, STACK_FRAME_FILEPATH_IGNORE_REGEXES_PYTHON,
+        STACK_FRAME_FILEPATH_IGNORE_REGEXES_RUST, STACK_FRAME_FUNCTION_IGNORE_REGEXES_CPP,
+        STACK_FRAME_FUNCTION_IGNORE_REGEXES_GO, STACK_FRAME_FUNCTION_IGNORE_REGEXES_JAVA,
+        STACK_FRAME_FUNCTION_IGNORE_REGEXES_PYTHON, STACK_FRAME_FUNCTION_IGNORE_REGEXES_RUST,
+    },
+    execution_class::ExecutionClass,
+    init_ignored_frames,
+    report::CrashReport,
+    severity::Severity,
+    stacktrace::{
+        Filter, ParseStacktrace, Stacktrace, StacktraceEntry, STACK_FRAME_FILEPATH_IGNORE_REGEXES,
+        STACK_FRAME_FUNCTION_IGNORE_REGEXES,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    executors::hooks::inprocess::CrashReportMetadata,
+    feedbacks::{stdio::StdErrMetadata, Feedback, StateInitializer},
+    observers::StdErrObserver,
+    stages::{CrashContextBackend, Exploitability, TriageReport},
+    Error, HasMetadata,
+};
+
+/// The number of frames of the mini-BSOD kept in the [`CasrReportMetadata`]
+const MINIBSOD_STACK_DEPTH: usize = 64;
+
+/// The CASR view of a solution: its severity, crash line and filtered stack trace
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CasrReportMetadata {
+    /// The severity class, `EXPLOITABLE`, `PROBABLY_EXPLOITABLE`, `NOT_EXPLOITABLE` or `UNDEFINED`
+    pub severity: String,
+    /// The CASR name of the crash, like `SourceAv` or `heap-buffer-overflow(read)`
+    pub short_description: String,
+    /// A human readable description of the crash
+    pub description: String,
+    /// The source location of the crash, `file:line`, if known
+    pub crashline: String,
+    /// The stack trace, one frame per line, as printed by the sanitizer or the mini-BSOD
+    pub stacktrace: Vec<String>,
+    /// The functions of the target in the stack, innermost first, without the frames of the
+    /// sanitizers, the standard libraries and the fuzzer
+    pub functions: Vec<String>,
+    /// The `AddressSanitizer` report, if the crash comes from one
+    pub asan_report: Vec<String>,
+    /// A hash of [`Self::functions`], equal for duplicates
+    pub stack_hash: u64,
+}
+
+impl_serdeany!(CasrReportMetadata);
+
+impl CasrReportMetadata {
+    /// Builds the report of a solution from the `AddressSanitizer` report in `output`, if there is one
+    #[must_use]
+    pub fn from_asan_output(output: &str) -> Option<Self> {
+        let start = output.lines().position(|line| {
+            line.contains("ERROR: AddressSanitizer") || line.contains("ERROR: LeakSanitizer")
+        })?;
+        let asan_report: Vec<String> = output.lines().skip(start).map(str::to_string).collect();
+        let stacktrace = AsanStacktrace::extract_stacktrace(output).ok()?;
+        let mut entries = AsanStacktrace::parse_stacktrace(&stacktrace).ok()?;
+        entries.filter();
+
+        let (severity, short_description, description) =
+            match AsanContext(asan_report.clone()).severity() {
+                Ok(class) => (class.severity, class.short_description, class.description),
+                Err(_) => ("UNDEFINED".to_string(), String::new(), String::new()),
+            };
+        let crashline = entries
+            .iter()
+            .find(|entry| !entry.debug.file.is_empty())
+            .map(|entry| format!("{}:{}", entry.debug.file, entry.debug.line))
+            .unwrap_or_default();
+
+        Some(Self::with_functions(
+            severity,
+            short_description,
+            description,
+            crashline,
+            stacktrace,
+            entries.iter().map(|entry| entry.function.clone()).collect(),
+            asan_report,
+        ))
+    }
+
+    /// Builds the report of a solution from the mini-BSOD of an in-process executor
+    #[must_use]
+    pub fn from_minibsod(report: &str) -> Self {
+        let triage = TriageReport::new(
+            CrashContextBackend::parse_report(report),
+            MINIBSOD_STACK_DEPTH,
+        );
+        let (severity, short_description, description) = match (
+            triage.exploitability,
+            triage.signal.as_deref().unwrap_or_default(),
+        ) {
+            (Exploitability::Exploitable, _) => (
+                "EXPLOITABLE",
+                "SegFaultOnPc",
+                "Segmentation fault on program counter",
+            ),
+            (_, "SIGILL" | "EXCEPTION_ILLEGAL_INSTRUCTION") => {
+                ("PROBABLY_EXPLOITABLE", "BadInstruction", "Bad instruction")
+            }
+            (Exploitability::ProbablyExploitable, _) => (
+                "PROBABLY_EXPLOITABLE",
+                "AccessViolation",
+                "Access violation",
+            ),
+            (_, "SIGABRT") => ("NOT_EXPLOITABLE", "AbortSignal", "Abort signal"),
+            (_, "SIGFPE") => ("NOT_EXPLOITABLE", "FPE", "Arithmetic exception"),
+            (_, "EXCEPTION_STACK_OVERFLOW") => {
+                ("NOT_EXPLOITABLE", "StackOverflow", "Stack overflow")
+            }
+            (Exploitability::ProbablyNotExploitable, _) => (
+                "NOT_EXPLOITABLE",
+                "SourceAvNearNull",
+                "Access violation near null",
+            ),
+            (Exploitability::Unknown, _) => ("UNDEFINED", "", ""),
+        };
+        let stacktrace = triage
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| format!("#{i} {frame}"))
+            .collect();
+
+        Self::with_functions(
+            severity.to_string(),
+            short_description.to_string(),
+            description.to_string(),
+            String::new(),
+            stacktrace,
+            triage.frames,
+            Vec::new(),
+        )
+    }
+
+    /// Builds the report of a solution from its metadata: the stderr of the target, if it was captured
+    /// as [`StdErrMetadata`], else its [`CrashReportMetadata`]
+    #[must_use]
+    pub fn from_testcase<I>(testcase: &Testcase<I>) -> Option<Self> {
+        testcase
+            .metadata::<StdErrMetadata>()
+            .ok()
+            .and_then(|metadata| Self::from_asan_output(&metadata.stderr))
+            .or_else(|| {
+                testcase
+                    .metadata::<CrashReportMetadata>()
+                    .ok()
+                    .map(|metadata| Self::from_minibsod(metadata.report()))
+            })
+    }
+
+    fn with_functions(
+        severity: String,
+        short_description: String,
+        description: String,
+        crashline: String,
+        stacktrace: Vec<String>,
+        functions: Vec<String>,
+        asan_report: Vec<String>,
+    ) -> Self {
+        let stack_hash = hash_std(functions.join("\n").as_bytes());
+        Self {
+            severity,
+            short_description,
+            description,
+            crashline,
+            stacktrace,
+            functions,
+            asan_report,
+            stack_hash,
+        }
+    }
+
+    /// The stack of the crash as a CASR [`Stacktrace`], for clustering
+    #[must_use]
+    pub fn casr_stacktrace(&self) -> Stacktrace {
+        casr_stacktrace(&self.functions)
+    }
+
+    /// Converts this into a CASR [`CrashReport`], which `casr-cli` and `casr-cluster` read as `.casrep` file
+    #[must_use]
+    pub fn to_crash_report(&self) -> CrashReport {
+        let mut report = CrashReport::new();
+        report.execution_class = ExecutionClass {
+            severity: self.severity.clone(),
+            short_description: self.short_description.clone(),
+            description: self.description.clone(),
+            explanation: String::new(),
+        };
+        report.crashline.clone_from(&self.crashline);
+        report.stacktrace.clone_from(&self.stacktrace);
+        report.asan_report.clone_from(&self.asan_report);
+        report
+    }
+}
+
+/// A CASR [`Stacktrace`] of the given functions, innermost first
+pub(crate) fn casr_stacktrace(functions: &[String]) -> Stacktrace {
+    functions
+        .iter()
+        .map(|function| StacktraceEntry {
+            function: function.clone(),
+            ..StacktraceEntry::default()
+        })
+        .collect()
+}
+
+/// Nop feedback that attaches a [`CasrReportMetadata`] to new solutions. The testcase
+/// is never interesting (use with an OR in the objective).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CasrFeedback {
+    o_ref: Option<Handle<StdErrObserver>>,
+}
+
+impl CasrFeedback {
+    /// Creates a new [`CasrFeedback`], reporting on the [`CrashReportMetadata`] of in-process solutions
+    #[must_use]
+    pub fn new() -> Self {
+        init_ignored_frames!("rust", "cpp", "go");
+        Self { o_ref: None }
+    }
+
+    /// Creates a new [`CasrFeedback`], reporting on the `AddressSanitizer` output the `observer` captured
+    #[must_use]
+    pub fn with_stderr_observer(observer: &StdErrObserver) -> Self {
+        let mut feedback = Self::new();
+        feedback.o_ref = Some(observer.handle());
+        feedback
+    }
+}
+
+impl Default for CasrFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> StateInitializer<S> for CasrFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for CasrFeedback
+where
+    OT: MatchName,
+{
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let from_stderr = self
+            .o_ref
+            .as_ref()
+            .and_then(|o_ref| observers.get(o_ref))
+            .and_then(|observer| observer.stderr.as_ref())
+            .and_then(|stderr| {
+                CasrReportMetadata::from_asan_output(&String::from_utf8_lossy(stderr))
+            });
+        if let Some(report) = from_stderr.or_else(|| CasrReportMetadata::from_testcase(testcase)) {
+            testcase.add_metadata(report);
+        }
+        Ok(())
+    }
+}
+
+impl Named for CasrFeedback {
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CasrFeedback");
+        &NAME
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CasrReportMetadata;
+
+    const REPORT: &str = "━━━ CRASH ━━━
+Received signal SIGABRT at 0x007f2a1b3c4d5e, fault address: 0x00000000000000
+━━━ BACKTRACE ━━━
+   0: libafl_bolts::minibsod::generate_minibsod
+   1: __restore_rt
+   2: abort
+   3: fuzzer::check
+   4: libafl::executors::inprocess::run_target
+━━━ MAPS ━━━
+";
+
+    #[test]
+    fn test_from_minibsod() {
+        let report = CasrReportMetadata::from_minibsod(REPORT);
+        assert_eq!(report.severity, "NOT_EXPLOITABLE");
+        assert_eq!(report.short_description, "AbortSignal");
+        assert_eq!(report.functions, ["abort", "fuzzer::check"]);
+        assert_eq!(report.stacktrace, ["#0 abort", "#1 fuzzer::check"]);
+
+        let other =
+            CasrReportMetadata::from_minibsod(&REPORT.replace("7f2a1b3c4d5e", "7f00aabbccdd"));
+        assert_eq!(report.stack_hash, other.stack_hash);
+    }
+}
@@ -0,0 +1,113 @@
+//! The [`CoverageSummaryFeedback`] announces the coverage of new testcases to the broker.
+
+use alloc::borrow::Cow;
+use core::marker::PhantomData;
+
+use libafl_bolts::{
+    tuples::{Handle, Handled, MatchName, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    events::{Event, EventFirer, COVERAGE_SUMMARY_TAG},
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverHandle, StateInitializer},
+    inputs::UsesInput,
+    observers::MapObserver,
+    Error,
+};
+
+/// Sends the hash of the coverage map of each new [`Testcase`] to the broker, right before the testcase itself.
+///
+/// A [`crate::events::DedupLlmpHook`] in the broker uses it to drop testcases reaching the same coverage
+/// as one recently found by another client, so they are not re-evaluated by all other clients.
+/// Without such a hook, the announcements are forwarded to the clients, which ignore them.
+///
+/// Is never interesting (use with an Eager OR), and should not be part of the objective.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageSummaryFeedback<C, O> {
+    map_ref: Handle<C>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> CoverageSummaryFeedback<C, O>
+where
+    C: Named,
+{
+    /// Creates a new [`CoverageSummaryFeedback`], announcing the hash of the given map observer
+    #[must_use]
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_ref: map_observer.handle(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> Named for CoverageSummaryFeedback<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("CoverageSummaryFeedback");
+        &NAME
+    }
+}
+
+impl<C, O> HasObserverHandle for CoverageSummaryFeedback<C, O> {
+    type Observer = C;
+
+    #[inline]
+    fn observer_handle(&self) -> &Handle<C> {
+        &self.map_ref
+    }
+}
+
+impl<C, O, S> StateInitializer<S> for CoverageSummaryFeedback<C, O> {}
+
+impl<C, EM, I, O, OT, S> Feedback<EM, I, OT, S> for CoverageSummaryFeedback<C, O>
+where
+    C: AsRef<O>,
+    EM: EventFirer<State = S>,
+    O: MapObserver,
+    OT: MatchName,
+    S: UsesInput,
+{
+    #[inline]
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    #[inline]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        observers: &OT,
+        _testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let hash = observers
+            .get(&self.map_ref)
+            .ok_or_else(|| Error::key_not_found("CoverageSummaryFeedback: map observer not found"))?
+            .as_ref()
+            .hash_simple();
+        manager.fire(
+            state,
+            Event::CustomBuf {
+                buf: hash.to_le_bytes().to_vec(),
+                tag: COVERAGE_SUMMARY_TAG.into(),
+            },
+        )
+    }
+}
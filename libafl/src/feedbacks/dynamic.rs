@@ -0,0 +1,326 @@
+//! Feedbacks assembled at runtime, for example from a config file.
+//!
+//! The [`DynFeedback`] combines boxed feedbacks with a [`FeedbackLogic`], as [`CombinedFeedback`](super::CombinedFeedback)
+//! does for two feedbacks known at compile time. As the combined feedbacks are boxed, a [`DynFeedback`] can hold others,
+//! with another logic.
+
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
+use core::{fmt, marker::PhantomData};
+
+use libafl_bolts::Named;
+
+#[cfg(feature = "introspection")]
+use crate::state::HasClientPerfMonitor;
+use crate::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, FeedbackLogic, StateInitializer},
+    Error,
+};
+
+/// A boxed [`Feedback`], as held by a [`DynFeedback`]
+pub type BoxedFeedback<EM, I, OT, S> = Box<dyn Feedback<EM, I, OT, S>>;
+
+/// Boxed feedbacks, combined in order with the [`FeedbackLogic`] `FL`, see the [module-level documentation](self)
+///
+/// Without any feedbacks, no input is interesting.
+pub struct DynFeedback<EM, FL, I, OT, S> {
+    feedbacks: Vec<BoxedFeedback<EM, I, OT, S>>,
+    name: Cow<'static, str>,
+    phantom: PhantomData<FL>,
+}
+
+impl<EM, FL, I, OT, S> fmt::Debug for DynFeedback<EM, FL, I, OT, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynFeedback")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<EM, FL, I, OT, S> Named for DynFeedback<EM, FL, I, OT, S> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<EM, FL, I, OT, S> Default for DynFeedback<EM, FL, I, OT, S>
+where
+    FL: FeedbackLogic,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<EM, FL, I, OT, S> DynFeedback<EM, FL, I, OT, S>
+where
+    FL: FeedbackLogic,
+{
+    /// Creates a new [`DynFeedback`], without any feedbacks
+    #[must_use]
+    pub fn new() -> Self {
+        Self::from(Vec::new())
+    }
+
+    /// Adds a `feedback`, after the others
+    pub fn push<F>(&mut self, feedback: F)
+    where
+        F: Feedback<EM, I, OT, S> + 'static,
+    {
+        self.feedbacks.push(Box::new(feedback));
+        self.name = Self::combined_name(&self.feedbacks);
+    }
+
+    /// Adds a `feedback`, after the others, builder-style
+    #[must_use]
+    pub fn with<F>(mut self, feedback: F) -> Self
+    where
+        F: Feedback<EM, I, OT, S> + 'static,
+    {
+        self.push(feedback);
+        self
+    }
+
+    /// The number of feedbacks
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.feedbacks.len()
+    }
+
+    /// If there are no feedbacks
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.feedbacks.is_empty()
+    }
+
+    /// The name of the logic, followed by the names of the feedbacks, as for a [`CombinedFeedback`](super::CombinedFeedback)
+    fn combined_name(feedbacks: &[BoxedFeedback<EM, I, OT, S>]) -> Cow<'static, str> {
+        let names: Vec<&str> = feedbacks
+            .iter()
+            .map(|feedback| &**feedback.name())
+            .collect();
+        Cow::from(format!("{} ({})", FL::name(), names.join(",")))
+    }
+}
+
+impl<EM, FL, I, OT, S> From<Vec<BoxedFeedback<EM, I, OT, S>>> for DynFeedback<EM, FL, I, OT, S>
+where
+    FL: FeedbackLogic,
+{
+    fn from(feedbacks: Vec<BoxedFeedback<EM, I, OT, S>>) -> Self {
+        let name = Self::combined_name(&feedbacks);
+        Self {
+            feedbacks,
+            name,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// The result of the feedbacks of `feedbacks` on the last run, combined with `FL`
+#[cfg(feature = "track_hit_feedbacks")]
+fn combined_last_result<EM, FL, I, OT, S>(
+    feedbacks: &[BoxedFeedback<EM, I, OT, S>],
+) -> Result<bool, Error>
+where
+    FL: FeedbackLogic,
+{
+    match feedbacks.split_last() {
+        None => Ok(false),
+        Some((last, [])) => last.last_result(),
+        Some((last, init)) => FL::last_result(
+            combined_last_result::<_, FL, _, _, _>(init),
+            last.last_result(),
+        ),
+    }
+}
+
+/// Appends the names of the `feedbacks` that contributed to the last result, combined with `FL`
+#[cfg(feature = "track_hit_feedbacks")]
+fn append_combined_hit_feedbacks<EM, FL, I, OT, S>(
+    feedbacks: &[BoxedFeedback<EM, I, OT, S>],
+    list: &mut Vec<Cow<'static, str>>,
+) -> Result<(), Error>
+where
+    FL: FeedbackLogic,
+{
+    match feedbacks.split_last() {
+        None => Ok(()),
+        Some((last, [])) => last.append_hit_feedbacks(list),
+        Some((last, init)) => FL::append_hit_feedbacks(
+            combined_last_result::<_, FL, _, _, _>(init),
+            |list| append_combined_hit_feedbacks::<_, FL, _, _, _>(init, list),
+            last.last_result(),
+            |list| last.append_hit_feedbacks(list),
+            list,
+        ),
+    }
+}
+
+impl<EM, FL, I, OT, S> StateInitializer<S> for DynFeedback<EM, FL, I, OT, S> {
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        for feedback in &mut self.feedbacks {
+            feedback.init_state(state)?;
+        }
+        Ok(())
+    }
+}
+
+impl<EM, FL, I, OT, S> Feedback<EM, I, OT, S> for DynFeedback<EM, FL, I, OT, S>
+where
+    FL: FeedbackLogic,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        input: &I,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let mut feedbacks = self.feedbacks.iter_mut();
+        let Some(first) = feedbacks.next() else {
+            return Ok(false);
+        };
+        let mut interesting = first.is_interesting(state, manager, input, observers, exit_kind)?;
+        for feedback in feedbacks {
+            interesting = FL::is_pair_interesting(
+                |_, _, _, _, _| Ok(interesting),
+                |state, manager, input, observers, exit_kind| {
+                    feedback.is_interesting(state, manager, input, observers, exit_kind)
+                },
+                state,
+                manager,
+                input,
+                observers,
+                exit_kind,
+            )?;
+        }
+        Ok(interesting)
+    }
+
+    #[cfg(feature = "introspection")]
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting_introspection(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        input: &I,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        S: HasClientPerfMonitor,
+    {
+        let mut feedbacks = self.feedbacks.iter_mut();
+        let Some(first) = feedbacks.next() else {
+            return Ok(false);
+        };
+        let mut interesting =
+            first.is_interesting_introspection(state, manager, input, observers, exit_kind)?;
+        for feedback in feedbacks {
+            interesting = FL::is_pair_interesting(
+                |_, _, _, _, _| Ok(interesting),
+                |state, manager, input, observers, exit_kind| {
+                    feedback
+                        .is_interesting_introspection(state, manager, input, observers, exit_kind)
+                },
+                state,
+                manager,
+                input,
+                observers,
+                exit_kind,
+            )?;
+        }
+        Ok(interesting)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        combined_last_result::<_, FL, _, _, _>(&self.feedbacks)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn append_hit_feedbacks(&self, list: &mut Vec<Cow<'static, str>>) -> Result<(), Error> {
+        append_combined_hit_feedbacks::<_, FL, _, _, _>(&self.feedbacks, list)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        for feedback in &mut self.feedbacks {
+            feedback.append_metadata(state, manager, observers, testcase)?;
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        for feedback in &mut self.feedbacks {
+            feedback.discard_metadata(state, input)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::Named;
+
+    use super::DynFeedback;
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, CrashFeedback, Feedback, LogicEagerAnd, LogicEagerOr},
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    #[test]
+    fn test_dyn_feedback() {
+        type State = NopState<BytesInput>;
+        type Manager = NopEventManager<State>;
+
+        let mut or = DynFeedback::<Manager, LogicEagerOr, BytesInput, (), State>::new()
+            .with(ConstFeedback::new(false))
+            .with(CrashFeedback::new());
+        let mut and = DynFeedback::<Manager, LogicEagerAnd, BytesInput, (), State>::new()
+            .with(ConstFeedback::new(true))
+            .with(CrashFeedback::new());
+        assert_eq!(or.name(), "Eager OR (ConstFeedback,CrashFeedback)");
+
+        let mut state = State::new();
+        let mut manager = Manager::new();
+        let input = BytesInput::new(vec![0]);
+        for (exit_kind, expected) in [(ExitKind::Ok, false), (ExitKind::Crash, true)] {
+            assert_eq!(
+                or.is_interesting(&mut state, &mut manager, &input, &(), &exit_kind)
+                    .unwrap(),
+                expected
+            );
+            assert_eq!(
+                and.is_interesting(&mut state, &mut manager, &input, &(), &exit_kind)
+                    .unwrap(),
+                expected
+            );
+        }
+
+        // Dynamic feedbacks nest, with different logics
+        let mut nested = DynFeedback::<Manager, LogicEagerOr, BytesInput, (), State>::new()
+            .with(ConstFeedback::new(false))
+            .with(and);
+        assert!(nested
+            .is_interesting(&mut state, &mut manager, &input, &(), &ExitKind::Crash)
+            .unwrap());
+        assert!(
+            !DynFeedback::<Manager, LogicEagerAnd, BytesInput, (), State>::new()
+                .is_interesting(&mut state, &mut manager, &input, &(), &ExitKind::Crash)
+                .unwrap()
+        );
+    }
+}
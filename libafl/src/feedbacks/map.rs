@@ -1,16 +1,17 @@
 //! Map feedback, maximizing or minimizing maps, for example the afl-style map observer.
 
 use alloc::{borrow::Cow, vec::Vec};
-#[rustversion::nightly]
-use core::simd::prelude::SimdOrd;
 use core::{
     fmt::Debug,
     marker::PhantomData,
     ops::{BitAnd, BitOr, Deref, DerefMut},
 };
 
-#[rustversion::nightly]
-use libafl_bolts::AsSlice;
+#[cfg(feature = "simd")]
+use libafl_bolts::{
+    simd::{map_novelties, MaxKernel, OrKernel},
+    AsSlice,
+};
 use libafl_bolts::{
     tuples::{Handle, Handled, MatchName, MatchNameRef},
     AsIter, HasRefCnt, Named,
@@ -130,6 +131,29 @@ where
     }
 }
 
+/// A [`Reducer`] of `u8` maps with a vectorized novelty search, see [`libafl_bolts::simd`]
+#[cfg(feature = "simd")]
+pub trait SimdReducer: Reducer<u8> {
+    /// Searches the entries of `map` whose reduction with `history` is novel, see [`map_novelties`]
+    fn novelties(history: &[u8], map: &[u8], novelties: Option<&mut Vec<usize>>) -> bool;
+}
+
+#[cfg(feature = "simd")]
+impl SimdReducer for MaxReducer {
+    #[inline]
+    fn novelties(history: &[u8], map: &[u8], novelties: Option<&mut Vec<usize>>) -> bool {
+        map_novelties::<MaxKernel>(history, map, novelties)
+    }
+}
+
+#[cfg(feature = "simd")]
+impl SimdReducer for OrReducer {
+    #[inline]
+    fn novelties(history: &[u8], map: &[u8], novelties: Option<&mut Vec<usize>>) -> bool {
+        map_novelties::<OrKernel>(history, map, novelties)
+    }
+}
+
 /// A `IsNovel` function is used to discriminate if a reduced value is considered novel.
 pub trait IsNovel<T> {
     /// If a new value in the [`MapFeedback`] was found,
@@ -533,18 +557,18 @@ where
     }
 }
 
-/// Specialize for the common coverage map size, maximization of u8s
+/// Specialize for the common coverage maps of u8s, maximized or combined bit by bit
 #[rustversion::nightly]
-impl<C, O, EM, I, OT, S> Feedback<EM, I, OT, S> for MapFeedback<C, DifferentIsNovel, O, MaxReducer>
+#[cfg(feature = "simd")]
+impl<C, O, EM, I, OT, R, S> Feedback<EM, I, OT, S> for MapFeedback<C, DifferentIsNovel, O, R>
 where
     C: CanTrack + AsRef<O>,
     EM: EventFirer<State = S>,
     O: MapObserver<Entry = u8> + for<'a> AsSlice<'a, Entry = u8> + for<'a> AsIter<'a, Item = u8>,
     OT: MatchName,
+    R: SimdReducer,
     S: HasNamedMetadata + UsesInput,
 {
-    #[allow(clippy::wrong_self_convention)]
-    #[allow(clippy::needless_range_loop)]
     fn is_interesting(
         &mut self,
         state: &mut S,
@@ -553,7 +577,7 @@ where
         observers: &OT,
         _exit_kind: &ExitKind,
     ) -> Result<bool, Error> {
-        Ok(self.is_interesting_u8_simd_optimized(state, observers))
+        Ok(self.is_interesting_simd(state, observers))
     }
 }
 
@@ -609,26 +633,31 @@ where
     }
 }
 
-/// Specialize for the common coverage map size, maximization of u8s
-#[rustversion::nightly]
-impl<C, O> MapFeedback<C, DifferentIsNovel, O, MaxReducer>
+#[cfg(feature = "simd")]
+impl<C, O, R> MapFeedback<C, DifferentIsNovel, O, R>
 where
     O: MapObserver<Entry = u8> + for<'a> AsSlice<'a, Entry = u8> + for<'a> AsIter<'a, Item = u8>,
     C: CanTrack + AsRef<O>,
+    R: SimdReducer,
 {
+    /// Searches the novelties with [`libafl_bolts::simd`], skipping the zero parts of the map
     #[allow(clippy::wrong_self_convention)]
-    #[allow(clippy::needless_range_loop)]
-    fn is_interesting_u8_simd_optimized<S, OT>(&mut self, state: &mut S, observers: &OT) -> bool
+    pub(crate) fn is_interesting_simd<S, OT>(&mut self, state: &mut S, observers: &OT) -> bool
     where
         S: HasNamedMetadata,
         OT: MatchName,
     {
-        // 128 bits vectors
-        type VectorType = core::simd::u8x16;
-
-        let mut interesting = false;
         // TODO Replace with match_name_type when stable
         let observer = observers.get(&self.map_ref).unwrap().as_ref();
+        if observer.initial() != 0 {
+            // Only zero entries can be skipped
+            let interesting = self.is_interesting_default(state, observers);
+            #[cfg(feature = "track_hit_feedbacks")]
+            {
+                self.last_result = Some(interesting);
+            }
+            return interesting;
+        }
 
         let map_state = state
             .named_metadata_map_mut()
@@ -643,77 +672,14 @@ where
         let map = observer.as_slice();
         debug_assert!(map.len() >= size);
 
-        let history_map = map_state.history_map.as_slice();
-
-        // Non vector implementation for reference
-        /*for (i, history) in history_map.iter_mut().enumerate() {
-            let item = map[i];
-            let reduced = MaxReducer::reduce(*history, item);
-            if DifferentIsNovel::is_novel(*history, reduced) {
-                *history = reduced;
-                interesting = true;
-                if self.novelties.is_some() {
-                    self.novelties.as_mut().unwrap().push(i);
-                }
-            }
-        }*/
-
-        let steps = size / VectorType::LEN;
-        let left = size % VectorType::LEN;
-
         if let Some(novelties) = self.novelties.as_mut() {
             novelties.clear();
-            for step in 0..steps {
-                let i = step * VectorType::LEN;
-                let history = VectorType::from_slice(&history_map[i..]);
-                let items = VectorType::from_slice(&map[i..]);
-
-                if items.simd_max(history) != history {
-                    interesting = true;
-                    unsafe {
-                        for j in i..(i + VectorType::LEN) {
-                            let item = *map.get_unchecked(j);
-                            if item > *history_map.get_unchecked(j) {
-                                novelties.push(j);
-                            }
-                        }
-                    }
-                }
-            }
-
-            for j in (size - left)..size {
-                unsafe {
-                    let item = *map.get_unchecked(j);
-                    if item > *history_map.get_unchecked(j) {
-                        interesting = true;
-                        novelties.push(j);
-                    }
-                }
-            }
-        } else {
-            for step in 0..steps {
-                let i = step * VectorType::LEN;
-                let history = VectorType::from_slice(&history_map[i..]);
-                let items = VectorType::from_slice(&map[i..]);
-
-                if items.simd_max(history) != history {
-                    interesting = true;
-                    break;
-                }
-            }
-
-            if !interesting {
-                for j in (size - left)..size {
-                    unsafe {
-                        let item = *map.get_unchecked(j);
-                        if item > *history_map.get_unchecked(j) {
-                            interesting = true;
-                            break;
-                        }
-                    }
-                }
-            }
         }
+        let interesting = R::novelties(
+            &map_state.history_map[..size],
+            &map[..size],
+            self.novelties.as_mut(),
+        );
         #[cfg(feature = "track_hit_feedbacks")]
         {
             self.last_result = Some(interesting);
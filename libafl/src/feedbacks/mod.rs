@@ -11,7 +11,9 @@ use core::{fmt::Debug, marker::PhantomData};
 
 #[cfg(feature = "std")]
 pub use concolic::ConcolicFeedback;
+pub use coverage_summary::CoverageSummaryFeedback;
 pub use differential::DiffFeedback;
+pub use dynamic::{BoxedFeedback, DynFeedback};
 use libafl_bolts::{
     tuples::{Handle, Handled, MatchName, MatchNameRef},
     Named,
@@ -24,19 +26,31 @@ pub use nautilus::*;
 pub use new_hash_feedback::NewHashFeedback;
 #[cfg(feature = "std")]
 pub use new_hash_feedback::NewHashFeedbackMetadata;
+#[cfg(all(feature = "regex", target_os = "linux"))]
+pub use new_hash_feedback::{HangBacktraceMetadata, HangBacktraceToMetadataFeedback};
 use serde::{Deserialize, Serialize};
+pub use target_regions::{
+    TargetRegionsFeedback, TargetRegionsHitMetadata, TargetRegionsMetadata,
+    TARGET_REGIONS_BOOST_DEFAULT,
+};
 
 use crate::{corpus::Testcase, executors::ExitKind, observers::TimeObserver, Error};
 
+#[cfg(feature = "std")]
+pub mod afl_filename;
 #[cfg(feature = "std")]
 pub mod capture_feedback;
+#[cfg(feature = "casr")]
+pub mod casr;
 
 #[cfg(feature = "std")]
 pub mod concolic;
+pub mod coverage_summary;
 #[cfg(feature = "std")]
 /// The module for `CustomFilenameToTestcaseFeedback`
 pub mod custom_filename;
 pub mod differential;
+pub mod dynamic;
 /// The module for list feedback
 pub mod list;
 pub mod map;
@@ -44,12 +58,21 @@ pub mod map;
 pub mod nautilus;
 #[cfg(feature = "std")]
 pub mod new_hash_feedback;
+#[cfg(feature = "simd")]
+pub mod simd;
 #[cfg(feature = "std")]
 pub mod stdio;
+pub mod target_regions;
 pub mod transferred;
 
+#[cfg(feature = "std")]
+pub use afl_filename::AflFilenameFeedback;
 #[cfg(feature = "std")]
 pub use capture_feedback::CaptureTimeoutFeedback;
+#[cfg(feature = "casr")]
+pub use casr::{CasrFeedback, CasrReportMetadata};
+#[cfg(feature = "simd")]
+pub use simd::{SimdAflMapFeedback, SimdMapFeedback, SimdMaxMapFeedback};
 
 #[cfg(feature = "introspection")]
 use crate::state::HasClientPerfMonitor;
@@ -1,9 +1,13 @@
 //! The ``NewHashFeedback`` uses the backtrace hash and a hashset to only keep novel cases
 
+#[cfg(all(feature = "regex", target_os = "linux"))]
+use alloc::string::String;
 use alloc::{borrow::Cow, string::ToString};
 use std::fmt::Debug;
 
 use hashbrown::HashSet;
+#[cfg(all(feature = "regex", target_os = "linux"))]
+use libafl_bolts::impl_serdeany;
 use libafl_bolts::{
     tuples::{Handle, Handled, MatchName, MatchNameRef},
     Named,
@@ -12,6 +16,8 @@ use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "track_hit_feedbacks")]
 use crate::feedbacks::premature_last_result_err;
+#[cfg(all(feature = "regex", target_os = "linux"))]
+use crate::{corpus::Testcase, observers::HangBacktraceObserver, HasMetadata};
 use crate::{
     executors::ExitKind,
     feedbacks::{Feedback, HasObserverHandle, StateInitializer},
@@ -219,3 +225,74 @@ where
         }
     }
 }
+
+/// The stacks of a hanging testcase, captured by a [`HangBacktraceObserver`]
+#[cfg(all(feature = "regex", target_os = "linux"))]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HangBacktraceMetadata {
+    /// The stacks of all threads, one frame per line
+    pub backtrace: String,
+}
+
+#[cfg(all(feature = "regex", target_os = "linux"))]
+impl_serdeany!(HangBacktraceMetadata);
+
+/// Nop feedback that annotates the stacks of a hang in the new testcase. The testcase
+/// is never interesting (use with an OR, next to a [`NewHashFeedback`] for the same observer).
+#[cfg(all(feature = "regex", target_os = "linux"))]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HangBacktraceToMetadataFeedback {
+    o_ref: Handle<HangBacktraceObserver>,
+}
+
+#[cfg(all(feature = "regex", target_os = "linux"))]
+impl HangBacktraceToMetadataFeedback {
+    /// Creates a new [`HangBacktraceToMetadataFeedback`].
+    #[must_use]
+    pub fn new(observer: &HangBacktraceObserver) -> Self {
+        Self {
+            o_ref: observer.handle(),
+        }
+    }
+}
+
+#[cfg(all(feature = "regex", target_os = "linux"))]
+impl<S> StateInitializer<S> for HangBacktraceToMetadataFeedback {}
+
+#[cfg(all(feature = "regex", target_os = "linux"))]
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for HangBacktraceToMetadataFeedback
+where
+    OT: MatchName,
+{
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(false)
+    }
+
+    /// Append the stacks to the testcase, if the run hung
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let observer = observers
+            .get(&self.o_ref)
+            .ok_or(Error::illegal_state("HangBacktraceObserver is missing"))?;
+        if let Some(backtrace) = observer.backtrace() {
+            testcase.metadata_map_mut().insert(HangBacktraceMetadata {
+                backtrace: backtrace.into(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(feature = "regex", target_os = "linux"))]
+impl Named for HangBacktraceToMetadataFeedback {
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        self.o_ref.name()
+    }
+}
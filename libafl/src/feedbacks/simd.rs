@@ -0,0 +1,196 @@
+//! Map feedbacks searching the novelties of `u8` coverage maps with SIMD instructions, see [`libafl_bolts::simd`].
+//!
+//! On nightly, [`MapFeedback`] specializes to the same search by itself.
+//! The [`SimdMapFeedback`] brings it to stable, for the large maps of big targets where the search dominates the profile.
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::{
+    tuples::{Handle, MatchName},
+    AsIter, AsSlice, Named,
+};
+
+use crate::{
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{
+        DifferentIsNovel, Feedback, HasObserverHandle, MapFeedback, MaxReducer, OrReducer,
+        SimdReducer, StateInitializer,
+    },
+    inputs::UsesInput,
+    observers::{CanTrack, MapObserver},
+    Error, HasNamedMetadata,
+};
+
+/// A [`SimdMapFeedback`] that strives to maximize the map contents, the vectorized [`crate::feedbacks::MaxMapFeedback`]
+pub type SimdMaxMapFeedback<C, O> = SimdMapFeedback<C, O, MaxReducer>;
+/// A [`SimdMapFeedback`] combining the bits of the history map, the vectorized [`crate::feedbacks::AflMapFeedback`]
+pub type SimdAflMapFeedback<C, O> = SimdMapFeedback<C, O, OrReducer>;
+
+/// A [`MapFeedback`] over `u8` maps, searching the novelties with SIMD instructions and skipping the zero parts of the map.
+/// It shares the history map with a [`MapFeedback`] of the same name.
+#[derive(Clone, Debug)]
+pub struct SimdMapFeedback<C, O, R> {
+    map: MapFeedback<C, DifferentIsNovel, O, R>,
+}
+
+impl<C, O, R> SimdMapFeedback<C, O, R>
+where
+    C: CanTrack + AsRef<O> + Named,
+{
+    /// Create new `SimdMapFeedback`
+    #[must_use]
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map: MapFeedback::new(map_observer),
+        }
+    }
+
+    /// Create new `SimdMapFeedback` with a specific name, see [`MapFeedback::with_name`]
+    #[must_use]
+    pub fn with_name(name: &'static str, map_observer: &C) -> Self {
+        Self {
+            map: MapFeedback::with_name(name, map_observer),
+        }
+    }
+}
+
+impl<C, O, R> From<MapFeedback<C, DifferentIsNovel, O, R>> for SimdMapFeedback<C, O, R> {
+    fn from(map: MapFeedback<C, DifferentIsNovel, O, R>) -> Self {
+        Self { map }
+    }
+}
+
+impl<C, O, R> Named for SimdMapFeedback<C, O, R> {
+    #[inline]
+    fn name(&self) -> &Cow<'static, str> {
+        self.map.name()
+    }
+}
+
+impl<C, O, R> HasObserverHandle for SimdMapFeedback<C, O, R> {
+    type Observer = C;
+
+    #[inline]
+    fn observer_handle(&self) -> &Handle<C> {
+        self.map.observer_handle()
+    }
+}
+
+impl<C, O, R, S> StateInitializer<S> for SimdMapFeedback<C, O, R>
+where
+    MapFeedback<C, DifferentIsNovel, O, R>: StateInitializer<S>,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        self.map.init_state(state)
+    }
+}
+
+impl<C, EM, I, O, OT, R, S> Feedback<EM, I, OT, S> for SimdMapFeedback<C, O, R>
+where
+    C: CanTrack + AsRef<O>,
+    EM: EventFirer<State = S>,
+    O: MapObserver<Entry = u8> + for<'a> AsSlice<'a, Entry = u8> + for<'a> AsIter<'a, Item = u8>,
+    OT: MatchName,
+    R: SimdReducer,
+    S: HasNamedMetadata + UsesInput,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(self.map.is_interesting_simd(state, observers))
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        Feedback::<EM, I, OT, S>::last_result(&self.map)
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        self.map
+            .append_metadata(state, manager, observers, testcase)
+    }
+
+    fn discard_metadata(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        Feedback::<EM, I, OT, S>::discard_metadata(&mut self.map, state, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use libafl_bolts::{ownedref::OwnedMutSlice, rands::StdRand, tuples::tuple_list};
+
+    use super::SimdMaxMapFeedback;
+    use crate::{
+        corpus::{InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{Feedback, MapNoveltiesMetadata},
+        inputs::BytesInput,
+        observers::{CanTrack, MapObserver, StdMapObserver},
+        state::StdState,
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_simd_map_feedback() {
+        let mut map = [0_u8; 1000];
+        let observer = StdMapObserver::from_ownedref("map", OwnedMutSlice::from(&mut map[..]))
+            .track_novelties();
+        let mut feedback = SimdMaxMapFeedback::<_, StdMapObserver<u8, false>>::new(&observer);
+        let mut observers = tuple_list!(observer);
+
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut (),
+        )
+        .unwrap();
+        let mut manager = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+
+        let mut is_interesting = |feedback: &mut SimdMaxMapFeedback<_, _>, observers: &_| {
+            feedback
+                .is_interesting(&mut state, &mut manager, &input, observers, &ExitKind::Ok)
+                .unwrap()
+        };
+        assert!(!is_interesting(&mut feedback, &observers));
+
+        observers.0.as_mut().set(70, 3);
+        observers.0.as_mut().set(999, 1);
+        assert!(is_interesting(&mut feedback, &observers));
+
+        let mut testcase = Testcase::new(input.clone());
+        feedback
+            .append_metadata(&mut state, &mut manager, &observers, &mut testcase)
+            .unwrap();
+        assert_eq!(
+            testcase.metadata::<MapNoveltiesMetadata>().unwrap().list,
+            [70, 999]
+        );
+        assert!(!feedback
+            .is_interesting(&mut state, &mut manager, &input, &observers, &ExitKind::Ok)
+            .unwrap());
+
+        observers.0.as_mut().set(70, 4);
+        assert!(feedback
+            .is_interesting(&mut state, &mut manager, &input, &observers, &ExitKind::Ok)
+            .unwrap());
+    }
+}
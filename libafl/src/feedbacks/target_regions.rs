@@ -0,0 +1,302 @@
+//! Directs the fuzzer at interesting code regions, like the code changed by a patch.
+//!
+//! Load the regions, found by static analysis or from a diff, into a [`TargetRegionsMetadata`] and add it to the state.
+//! The [`TargetRegionsFeedback`] then keeps the inputs reaching new regions, and tags the inputs reaching any of them
+//! with a [`TargetRegionsHitMetadata`], so that [`crate::schedulers::testcase_score::TargetRegionsTestcaseScore`]
+//! schedules and mutates them more often.
+
+use alloc::{borrow::Cow, format, vec::Vec};
+use core::{marker::PhantomData, ops::Range};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use hashbrown::HashSet;
+use libafl_bolts::{
+    impl_serdeany,
+    tuples::{Handle, Handled, MatchName, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "track_hit_feedbacks")]
+use crate::feedbacks::premature_last_result_err;
+use crate::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverHandle, StateInitializer},
+    observers::MapObserver,
+    Error, HasMetadata,
+};
+
+/// The default factor by which [`crate::schedulers::testcase_score::TargetRegionsTestcaseScore`]
+/// boosts the score of inputs reaching a target region
+pub const TARGET_REGIONS_BOOST_DEFAULT: f64 = 4.0;
+
+/// The target regions, as indices in the coverage map, and the ones reached so far
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetRegionsMetadata {
+    targets: HashSet<usize>,
+    reached: HashSet<usize>,
+    boost: f64,
+}
+
+impl_serdeany!(TargetRegionsMetadata);
+
+impl TargetRegionsMetadata {
+    /// Creates a new [`TargetRegionsMetadata`] for the given indices in the coverage map
+    #[must_use]
+    pub fn new<IT>(targets: IT) -> Self
+    where
+        IT: IntoIterator<Item = usize>,
+    {
+        Self {
+            targets: targets.into_iter().collect(),
+            reached: HashSet::new(),
+            boost: TARGET_REGIONS_BOOST_DEFAULT,
+        }
+    }
+
+    /// Parses a list of target regions, one per line, ignoring empty lines and `#` comments.
+    /// A region is either an index in the coverage map, like a `sancov` guard index (`1234`),
+    /// an address (`0x4011a0`) or a range of addresses (`0x401000-0x401200`).
+    ///
+    /// The addresses are turned into map indices by `resolve`, like
+    /// `libafl_targets::sanitizer_cov_edges_in` for targets built with `sancov` and `-fsanitize-coverage=pc-table`.
+    pub fn parse<R>(regions: &str, mut resolve: R) -> Result<Self, Error>
+    where
+        R: FnMut(Range<usize>) -> Vec<usize>,
+    {
+        let mut targets = Vec::new();
+        for (line_no, line) in regions.lines().enumerate() {
+            let region = line.split('#').next().unwrap_or_default().trim();
+            if region.is_empty() {
+                continue;
+            }
+            let invalid = || {
+                Error::illegal_argument(format!(
+                    "Invalid target region on line {}: {line}",
+                    line_no + 1
+                ))
+            };
+            if let Some((start, end)) = region.split_once('-') {
+                let start = parse_address(start.trim()).ok_or_else(invalid)?;
+                let end = parse_address(end.trim()).ok_or_else(invalid)?;
+                targets.extend(resolve(start..end));
+            } else if region.starts_with("0x") {
+                let address = parse_address(region).ok_or_else(invalid)?;
+                targets.extend(resolve(address..address + 1));
+            } else {
+                targets.push(region.parse().map_err(|_| invalid())?);
+            }
+        }
+        Ok(Self::new(targets))
+    }
+
+    /// Reads a list of target regions from a file, see [`Self::parse`]
+    #[cfg(feature = "std")]
+    pub fn from_file<P, R>(path: P, resolve: R) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        R: FnMut(Range<usize>) -> Vec<usize>,
+    {
+        Self::parse(&fs::read_to_string(path)?, resolve)
+    }
+
+    /// Boosts the score of the inputs reaching a target region by `boost`, [`TARGET_REGIONS_BOOST_DEFAULT`] by default
+    #[must_use]
+    pub fn with_boost(mut self, boost: f64) -> Self {
+        self.boost = boost;
+        self
+    }
+
+    /// The indices of the target regions in the coverage map
+    #[must_use]
+    pub fn targets(&self) -> &HashSet<usize> {
+        &self.targets
+    }
+
+    /// The indices of the target regions reached by an input of the corpus
+    #[must_use]
+    pub fn reached(&self) -> &HashSet<usize> {
+        &self.reached
+    }
+
+    /// The factor by which the score of the inputs reaching a target region is boosted
+    #[must_use]
+    pub fn boost(&self) -> f64 {
+        self.boost
+    }
+}
+
+fn parse_address(address: &str) -> Option<usize> {
+    usize::from_str_radix(address.strip_prefix("0x")?, 16).ok()
+}
+
+/// The number of target regions an input of the corpus reaches
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetRegionsHitMetadata {
+    /// The number of target regions reached
+    pub hits: usize,
+}
+
+impl_serdeany!(TargetRegionsHitMetadata);
+
+/// Keeps the inputs reaching a target region of the [`TargetRegionsMetadata`] no input reached before,
+/// and attaches a [`TargetRegionsHitMetadata`] to all new testcases reaching a target region.
+///
+/// Use it in an OR with the usual map feedback; without [`TargetRegionsMetadata`] in the state, it does nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetRegionsFeedback<C, O> {
+    map_ref: Handle<C>,
+    // The previous run's result of [`Self::is_interesting`]
+    #[cfg(feature = "track_hit_feedbacks")]
+    last_result: Option<bool>,
+    phantom: PhantomData<O>,
+}
+
+impl<C, O> TargetRegionsFeedback<C, O>
+where
+    C: Named,
+{
+    /// Creates a new [`TargetRegionsFeedback`], looking for the target regions in the given map observer
+    #[must_use]
+    pub fn new(map_observer: &C) -> Self {
+        Self {
+            map_ref: map_observer.handle(),
+            #[cfg(feature = "track_hit_feedbacks")]
+            last_result: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<C, O> TargetRegionsFeedback<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver,
+{
+    /// The target regions reached in the last execution
+    fn hits<OT>(
+        &self,
+        observers: &OT,
+        metadata: &TargetRegionsMetadata,
+    ) -> Result<Vec<usize>, Error>
+    where
+        OT: MatchName,
+    {
+        let observer = observers
+            .get(&self.map_ref)
+            .ok_or_else(|| Error::key_not_found("TargetRegionsFeedback: map observer not found"))?
+            .as_ref();
+        let (len, initial) = (observer.len(), observer.initial());
+        Ok(metadata
+            .targets
+            .iter()
+            .copied()
+            .filter(|&idx| idx < len && observer.get(idx) != initial)
+            .collect())
+    }
+}
+
+impl<C, O> Named for TargetRegionsFeedback<C, O> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TargetRegionsFeedback");
+        &NAME
+    }
+}
+
+impl<C, O> HasObserverHandle for TargetRegionsFeedback<C, O> {
+    type Observer = C;
+
+    #[inline]
+    fn observer_handle(&self) -> &Handle<C> {
+        &self.map_ref
+    }
+}
+
+impl<C, O, S> StateInitializer<S> for TargetRegionsFeedback<C, O> {}
+
+impl<C, EM, I, O, OT, S> Feedback<EM, I, OT, S> for TargetRegionsFeedback<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver,
+    OT: MatchName,
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let res = match state.metadata::<TargetRegionsMetadata>() {
+            Ok(metadata) => self
+                .hits(observers, metadata)?
+                .iter()
+                .any(|idx| !metadata.reached.contains(idx)),
+            Err(_) => false,
+        };
+        #[cfg(feature = "track_hit_feedbacks")]
+        {
+            self.last_result = Some(res);
+        }
+        Ok(res)
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        self.last_result.ok_or(premature_last_result_err())
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let Ok(metadata) = state.metadata_mut::<TargetRegionsMetadata>() else {
+            return Ok(());
+        };
+        let hits = self.hits(observers, metadata)?;
+        if !hits.is_empty() {
+            testcase.add_metadata(TargetRegionsHitMetadata { hits: hits.len() });
+            metadata.reached.extend(hits);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::TargetRegionsMetadata;
+
+    #[test]
+    fn test_parse_target_regions() {
+        let regions = "# changed in the last commit
+12
+0x1000 # a single address
+0x2000-0x2003
+";
+        let metadata =
+            TargetRegionsMetadata::parse(regions, |range| range.map(|addr| addr >> 4).collect())
+                .unwrap();
+        let mut targets: Vec<usize> = metadata.targets().iter().copied().collect();
+        targets.sort_unstable();
+        assert_eq!(targets, [12, 0x100, 0x200]);
+
+        assert!(TargetRegionsMetadata::parse("0xzz", |_| Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,471 @@
+//! A builder wiring the components of a single-process, in-process fuzzing campaign.
+//!
+//! The [`CampaignBuilder`] takes the harness, the observers and the feedback, and defaults everything else:
+//! an in-memory corpus and solutions corpus, a [`QueueScheduler`], a [`CrashFeedback`] objective,
+//! the havoc mutations in a [`StdMutationalStage`], a [`SimpleMonitor`] and a timeout of 5 seconds.
+//! Each default can be replaced, changing the type of the builder. [`CampaignBuilder::build`] then
+//! creates the state, the fuzzer, the event manager, the executor and the stages, in the right order,
+//! and returns them as a [`Campaign`], whose fields stay accessible for anything the builder does not cover.
+//!
+//! ```rust
+//! use libafl::{
+//!     executors::ExitKind,
+//!     generators::RandPrintablesGenerator,
+//!     inputs::{BytesInput, HasTargetBytes},
+//!     observers::StdMapObserver,
+//!     CampaignBuilder,
+//! };
+//! use libafl_bolts::{nonzero, AsSlice};
+//!
+//! static mut SIGNALS: [u8; 16] = [0; 16];
+//!
+//! let harness = |input: &BytesInput| {
+//!     let first = input.target_bytes().as_slice().first().copied();
+//!     unsafe { SIGNALS[usize::from(first == Some(b'a'))] = 1 };
+//!     ExitKind::Ok
+//! };
+//! let observer =
+//!     unsafe { StdMapObserver::from_mut_ptr("signals", (&raw mut SIGNALS).cast::<u8>(), 16) };
+//!
+//! let mut campaign = CampaignBuilder::with_map_observer(harness, observer)
+//!     .build()
+//!     .unwrap();
+//! campaign
+//!     .generate_initial_inputs(&mut RandPrintablesGenerator::new(nonzero!(32)), 8)
+//!     .unwrap();
+//! campaign.fuzz_loop_for(16).unwrap();
+//! ```
+use core::time::Duration;
+
+use libafl_bolts::{
+    rands::{Rand, StdRand},
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, InMemoryCorpus},
+    events::{EventFirer, ProgressReporter, SimpleEventManager},
+    executors::{inprocess::GenericInProcessExecutor, Executor, ExitKind, HasObservers},
+    feedbacks::{CrashFeedback, Feedback, MaxMapFeedback, StateInitializer},
+    fuzzer::{Evaluator, Fuzzer, StdFuzzer},
+    generators::Generator,
+    inputs::Input,
+    monitors::{Monitor, SimpleMonitor},
+    mutators::{havoc_mutations, HavocMutationsType, Mutator, StdScheduledMutator},
+    observers::{CanTrack, ObserversTuple},
+    schedulers::{QueueScheduler, Scheduler},
+    stages::{StagesTuple, StdMutationalStage},
+    state::{HasExecutions, HasLastReportTime, State, StdState, UsesState},
+    Error, HasMetadata,
+};
+
+/// The timeout of the executions, if not set with [`CampaignBuilder::timeout`]
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The print function of the default [`SimpleMonitor`]
+fn print_monitor(s: &str) {
+    #[cfg(feature = "std")]
+    println!("{s}");
+    #[cfg(not(feature = "std"))]
+    log::info!("{s}");
+}
+
+/// The state built by a [`CampaignBuilder`]
+pub type CampaignState<C, I, R, SC> = StdState<I, C, R, SC>;
+
+/// The event manager built by a [`CampaignBuilder`]
+pub type CampaignEventManager<C, I, MT, R, SC> = SimpleEventManager<MT, CampaignState<C, I, R, SC>>;
+
+/// The fuzzer built by a [`CampaignBuilder`]
+pub type CampaignFuzzer<C, CS, F, I, OF, R, SC> = StdFuzzer<CS, F, OF, CampaignState<C, I, R, SC>>;
+
+/// The executor built by a [`CampaignBuilder`], owning the harness
+pub type CampaignExecutor<C, H, I, OT, R, SC> =
+    GenericInProcessExecutor<H, H, (), OT, CampaignState<C, I, R, SC>>;
+
+/// The stages built by a [`CampaignBuilder`]
+pub type CampaignStages<C, CS, F, H, I, M, MT, OF, OT, R, SC> = tuple_list_type!(
+    StdMutationalStage<
+        CampaignExecutor<C, H, I, OT, R, SC>,
+        CampaignEventManager<C, I, MT, R, SC>,
+        I,
+        M,
+        CampaignFuzzer<C, CS, F, I, OF, R, SC>,
+    >
+);
+
+/// The [`Campaign`] built by a [`CampaignBuilder`]
+pub type BuiltCampaign<C, CS, F, H, I, M, MT, OF, OT, R, SC> = Campaign<
+    CampaignExecutor<C, H, I, OT, R, SC>,
+    CampaignEventManager<C, I, MT, R, SC>,
+    CampaignState<C, I, R, SC>,
+    CampaignStages<C, CS, F, H, I, M, MT, OF, OT, R, SC>,
+    CampaignFuzzer<C, CS, F, I, OF, R, SC>,
+>;
+
+/// Describes a fuzzing campaign, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct CampaignBuilder<C, CS, F, H, M, MT, OF, OT, R, SC> {
+    harness: H,
+    observers: OT,
+    feedback: F,
+    objective: OF,
+    corpus: C,
+    solutions: SC,
+    rand: R,
+    scheduler: CS,
+    mutator: M,
+    monitor: MT,
+    timeout: Duration,
+}
+
+impl<F, H, I, OT>
+    CampaignBuilder<
+        InMemoryCorpus<I>,
+        QueueScheduler,
+        F,
+        H,
+        StdScheduledMutator<HavocMutationsType>,
+        SimpleMonitor<fn(&str)>,
+        CrashFeedback,
+        OT,
+        StdRand,
+        InMemoryCorpus<I>,
+    >
+where
+    H: FnMut(&I) -> ExitKind,
+{
+    /// Creates a new [`CampaignBuilder`] for the harness, observed by the `observers` and rated by the `feedback`
+    pub fn new(harness: H, observers: OT, feedback: F) -> Self {
+        Self {
+            harness,
+            observers,
+            feedback,
+            objective: CrashFeedback::new(),
+            corpus: InMemoryCorpus::new(),
+            solutions: InMemoryCorpus::new(),
+            rand: StdRand::new(),
+            scheduler: QueueScheduler::new(),
+            mutator: StdScheduledMutator::new(havoc_mutations()),
+            monitor: SimpleMonitor::new(print_monitor),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+}
+
+impl<H, I, O>
+    CampaignBuilder<
+        InMemoryCorpus<I>,
+        QueueScheduler,
+        MaxMapFeedback<O, O>,
+        H,
+        StdScheduledMutator<HavocMutationsType>,
+        SimpleMonitor<fn(&str)>,
+        CrashFeedback,
+        tuple_list_type!(O),
+        StdRand,
+        InMemoryCorpus<I>,
+    >
+where
+    H: FnMut(&I) -> ExitKind,
+    O: CanTrack + AsRef<O> + Named,
+{
+    /// Creates a new [`CampaignBuilder`] for the common case of a harness observed by a coverage map,
+    /// rated by a [`MaxMapFeedback`] of the map
+    pub fn with_map_observer(harness: H, observer: O) -> Self {
+        let feedback = MaxMapFeedback::new(&observer);
+        Self::new(harness, tuple_list!(observer), feedback)
+    }
+}
+
+impl<C, CS, F, H, M, MT, OF, OT, R, SC> CampaignBuilder<C, CS, F, H, M, MT, OF, OT, R, SC> {
+    /// Sets the feedback rating the interestingness of the inputs
+    pub fn feedback<F2>(self, feedback: F2) -> CampaignBuilder<C, CS, F2, H, M, MT, OF, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the objective deciding if an input is a solution, by default a [`CrashFeedback`]
+    pub fn objective<OF2>(
+        self,
+        objective: OF2,
+    ) -> CampaignBuilder<C, CS, F, H, M, MT, OF2, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the corpus of the interesting inputs, by default an [`InMemoryCorpus`]
+    pub fn corpus<C2>(self, corpus: C2) -> CampaignBuilder<C2, CS, F, H, M, MT, OF, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the corpus of the solutions, by default an [`InMemoryCorpus`],
+    /// for example an [`crate::corpus::OnDiskCorpus`] to keep the solutions after the campaign
+    pub fn solutions<SC2>(
+        self,
+        solutions: SC2,
+    ) -> CampaignBuilder<C, CS, F, H, M, MT, OF, OT, R, SC2> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the random number generator, by default a [`StdRand`] with a random seed
+    pub fn rand<R2>(self, rand: R2) -> CampaignBuilder<C, CS, F, H, M, MT, OF, OT, R2, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the scheduler of the corpus, by default a [`QueueScheduler`]
+    pub fn scheduler<CS2>(
+        self,
+        scheduler: CS2,
+    ) -> CampaignBuilder<C, CS2, F, H, M, MT, OF, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler,
+            mutator: self.mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the mutator of the mutational stage, by default the havoc mutations
+    pub fn mutator<M2>(self, mutator: M2) -> CampaignBuilder<C, CS, F, H, M2, MT, OF, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator,
+            monitor: self.monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the monitor of the event manager, by default a [`SimpleMonitor`] printing the stats
+    pub fn monitor<MT2>(self, monitor: MT2) -> CampaignBuilder<C, CS, F, H, M, MT2, OF, OT, R, SC> {
+        CampaignBuilder {
+            harness: self.harness,
+            observers: self.observers,
+            feedback: self.feedback,
+            objective: self.objective,
+            corpus: self.corpus,
+            solutions: self.solutions,
+            rand: self.rand,
+            scheduler: self.scheduler,
+            mutator: self.mutator,
+            monitor,
+            timeout: self.timeout,
+        }
+    }
+
+    /// Sets the timeout of an execution of the harness, by default 5 seconds
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<C, CS, F, H, I, M, MT, OF, OT, R, SC> CampaignBuilder<C, CS, F, H, M, MT, OF, OT, R, SC>
+where
+    C: Corpus<Input = I> + Serialize + DeserializeOwned,
+    CS: Scheduler<I, CampaignState<C, I, R, SC>>,
+    F: Feedback<CampaignEventManager<C, I, MT, R, SC>, I, OT, CampaignState<C, I, R, SC>>
+        + StateInitializer<CampaignState<C, I, R, SC>>,
+    H: FnMut(&I) -> ExitKind,
+    I: Input,
+    M: Mutator<I, CampaignState<C, I, R, SC>>,
+    MT: Monitor,
+    OF: Feedback<CampaignEventManager<C, I, MT, R, SC>, I, OT, CampaignState<C, I, R, SC>>
+        + StateInitializer<CampaignState<C, I, R, SC>>,
+    OT: ObserversTuple<I, CampaignState<C, I, R, SC>>,
+    R: Rand,
+    SC: Corpus<Input = I> + Serialize + DeserializeOwned,
+    CampaignExecutor<C, H, I, OT, R, SC>: Executor<
+            CampaignEventManager<C, I, MT, R, SC>,
+            CampaignFuzzer<C, CS, F, I, OF, R, SC>,
+            State = CampaignState<C, I, R, SC>,
+        > + HasObservers,
+    CampaignFuzzer<C, CS, F, I, OF, R, SC>: Evaluator<
+        CampaignExecutor<C, H, I, OT, R, SC>,
+        CampaignEventManager<C, I, MT, R, SC>,
+        State = CampaignState<C, I, R, SC>,
+    >,
+{
+    /// Builds the state, the fuzzer, the event manager, the executor and the stages of the campaign
+    #[allow(clippy::type_complexity)]
+    pub fn build(mut self) -> Result<BuiltCampaign<C, CS, F, H, I, M, MT, OF, OT, R, SC>, Error> {
+        let mut state = StdState::new(
+            self.rand,
+            self.corpus,
+            self.solutions,
+            &mut self.feedback,
+            &mut self.objective,
+        )?;
+        let mut mgr = SimpleEventManager::new(self.monitor);
+        let mut fuzzer = StdFuzzer::new(self.scheduler, self.feedback, self.objective);
+        let executor = GenericInProcessExecutor::with_timeout_generic(
+            tuple_list!(),
+            self.harness,
+            self.observers,
+            &mut fuzzer,
+            &mut state,
+            &mut mgr,
+            self.timeout,
+        )?;
+        let stages = tuple_list!(StdMutationalStage::new(self.mutator));
+        Ok(Campaign {
+            fuzzer,
+            executor,
+            state,
+            mgr,
+            stages,
+        })
+    }
+}
+
+/// The components of a fuzzing campaign, built by a [`CampaignBuilder`]
+#[derive(Debug)]
+pub struct Campaign<E, EM, S, ST, Z> {
+    /// The fuzzer
+    pub fuzzer: Z,
+    /// The executor, running the harness
+    pub executor: E,
+    /// The state
+    pub state: S,
+    /// The event manager
+    pub mgr: EM,
+    /// The stages
+    pub stages: ST,
+}
+
+impl<C, E, EM, I, R, SC, ST, Z> Campaign<E, EM, StdState<I, C, R, SC>, ST, Z>
+where
+    C: Corpus<Input = I>,
+    E: UsesState<State = StdState<I, C, R, SC>>,
+    EM: EventFirer<State = StdState<I, C, R, SC>>,
+    I: Input,
+    R: Rand,
+    SC: Corpus<Input = I>,
+    Z: Evaluator<E, EM, State = StdState<I, C, R, SC>>,
+{
+    /// Generates `num` initial inputs with the generator, keeping the interesting ones
+    pub fn generate_initial_inputs<G>(&mut self, generator: &mut G, num: usize) -> Result<(), Error>
+    where
+        G: Generator<I, StdState<I, C, R, SC>>,
+    {
+        self.state.generate_initial_inputs(
+            &mut self.fuzzer,
+            &mut self.executor,
+            generator,
+            &mut self.mgr,
+            num,
+        )
+    }
+
+    /// Loads the initial inputs from the files in the directories, keeping the interesting ones
+    #[cfg(feature = "std")]
+    pub fn load_initial_inputs(&mut self, in_dirs: &[std::path::PathBuf]) -> Result<(), Error> {
+        self.state
+            .load_initial_inputs(&mut self.fuzzer, &mut self.executor, &mut self.mgr, in_dirs)
+    }
+}
+
+impl<E, EM, S, ST, Z> Campaign<E, EM, S, ST, Z>
+where
+    E: UsesState<State = S>,
+    EM: ProgressReporter<State = S>,
+    ST: StagesTuple<E, EM, S, Z>,
+    Z: Fuzzer<E, EM, ST, State = S>,
+    S: HasMetadata + HasExecutions + HasLastReportTime + State,
+{
+    /// Fuzzes until stopped, see [`Fuzzer::fuzz_loop`]
+    pub fn fuzz_loop(&mut self) -> Result<(), Error> {
+        self.fuzzer.fuzz_loop(
+            &mut self.stages,
+            &mut self.executor,
+            &mut self.state,
+            &mut self.mgr,
+        )
+    }
+
+    /// Fuzzes for `iters` iterations, see [`Fuzzer::fuzz_loop_for`]
+    pub fn fuzz_loop_for(&mut self, iters: u64) -> Result<CorpusId, Error> {
+        self.fuzzer.fuzz_loop_for(
+            &mut self.stages,
+            &mut self.executor,
+            &mut self.state,
+            &mut self.mgr,
+            iters,
+        )
+    }
+}
@@ -0,0 +1,294 @@
+//! Ensemble fuzzing in a single process: several fuzzers, each with its own state, share one executor and one event manager.
+//!
+//! Each [`EnsembleMember`] holds a fuzzer, its stages and its state, so the members can differ in their
+//! feedbacks, schedulers and mutators. The states are of the same type, as the executor and the event manager are shared.
+//! An [`Ensemble`] drives a tuple of members, switching between them after each iteration,
+//! or after the time slice of each member with [`Ensemble::with_time_slices`].
+//!
+//! The members report their progress to the event manager as the same client,
+//! so the monitor shows the stats of the member that reported last.
+use alloc::{borrow::Cow, vec::Vec};
+use core::time::Duration;
+
+use libafl_bolts::{current_time, tuples::HasConstLen, Named};
+
+use crate::{
+    events::ProgressReporter,
+    fuzzer::{Fuzzer, STATS_TIMEOUT_DEFAULT},
+    stages::StagesTuple,
+    state::{HasExecutions, HasLastReportTime, State, UsesState},
+    Error, HasMetadata,
+};
+
+/// A fuzzer of an [`Ensemble`], with its stages and its state
+#[derive(Debug)]
+pub struct EnsembleMember<S, ST, Z> {
+    name: Cow<'static, str>,
+    fuzzer: Z,
+    stages: ST,
+    state: S,
+}
+
+impl<S, ST, Z> Named for EnsembleMember<S, ST, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<S, ST, Z> EnsembleMember<S, ST, Z> {
+    /// Creates a new [`EnsembleMember`]
+    pub fn new<N>(name: N, fuzzer: Z, stages: ST, state: S) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        Self {
+            name: name.into(),
+            fuzzer,
+            stages,
+            state,
+        }
+    }
+
+    /// The fuzzer of the member
+    pub fn fuzzer(&self) -> &Z {
+        &self.fuzzer
+    }
+
+    /// The fuzzer of the member (mutable)
+    pub fn fuzzer_mut(&mut self) -> &mut Z {
+        &mut self.fuzzer
+    }
+
+    /// The state of the member
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// The state of the member (mutable)
+    pub fn state_mut(&mut self) -> &mut S {
+        &mut self.state
+    }
+
+    /// Returns the fuzzer, the stages and the state of the member
+    pub fn into_parts(self) -> (Z, ST, S) {
+        (self.fuzzer, self.stages, self.state)
+    }
+}
+
+/// A tuple of [`EnsembleMember`]s sharing the executor `E` and the event manager `EM`
+pub trait EnsembleMembersTuple<E, EM>: HasConstLen {
+    /// Runs a fuzzing iteration of the member at `index`
+    fn fuzz_one_at(
+        &mut self,
+        index: usize,
+        executor: &mut E,
+        manager: &mut EM,
+    ) -> Result<(), Error>;
+}
+
+impl<E, EM> EnsembleMembersTuple<E, EM> for () {
+    fn fuzz_one_at(
+        &mut self,
+        index: usize,
+        _executor: &mut E,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        Err(Error::key_not_found(format!(
+            "No ensemble member at index {index}"
+        )))
+    }
+}
+
+impl<E, EM, S, ST, Tail, Z> EnsembleMembersTuple<E, EM> for (EnsembleMember<S, ST, Z>, Tail)
+where
+    E: UsesState<State = S>,
+    EM: ProgressReporter<State = S>,
+    S: HasMetadata + HasExecutions + HasLastReportTime + State,
+    ST: StagesTuple<E, EM, S, Z>,
+    Tail: EnsembleMembersTuple<E, EM>,
+    Z: Fuzzer<E, EM, ST> + UsesState<State = S>,
+{
+    fn fuzz_one_at(
+        &mut self,
+        index: usize,
+        executor: &mut E,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        if index > 0 {
+            return self.1.fuzz_one_at(index - 1, executor, manager);
+        }
+        let member = &mut self.0;
+        manager.maybe_report_progress(&mut member.state, STATS_TIMEOUT_DEFAULT)?;
+        member
+            .fuzzer
+            .fuzz_one(&mut member.stages, executor, &mut member.state, manager)?;
+        Ok(())
+    }
+}
+
+/// How an [`Ensemble`] shares the time between its members
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnsembleSchedule {
+    /// One iteration of each member in turn
+    RoundRobin,
+    /// Iterations of each member in turn, until its time slice elapsed
+    TimeSlices(Vec<Duration>),
+}
+
+/// Drives a tuple of [`EnsembleMember`]s, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct Ensemble<T> {
+    members: T,
+    schedule: EnsembleSchedule,
+    current: usize,
+}
+
+impl<T> Ensemble<T>
+where
+    T: HasConstLen,
+{
+    /// Creates a new [`Ensemble`] running one iteration of each member in turn
+    pub fn new(members: T) -> Result<Self, Error> {
+        if T::LEN == 0 {
+            return Err(Error::illegal_argument("An ensemble needs members"));
+        }
+        Ok(Self {
+            members,
+            schedule: EnsembleSchedule::RoundRobin,
+            current: 0,
+        })
+    }
+
+    /// Creates a new [`Ensemble`] running each member in turn for its time slice, in the order of the members
+    pub fn with_time_slices(members: T, time_slices: Vec<Duration>) -> Result<Self, Error> {
+        if time_slices.len() != T::LEN {
+            return Err(Error::illegal_argument(format!(
+                "Got {} time slices for {} ensemble members",
+                time_slices.len(),
+                T::LEN
+            )));
+        }
+        let mut ensemble = Self::new(members)?;
+        ensemble.schedule = EnsembleSchedule::TimeSlices(time_slices);
+        Ok(ensemble)
+    }
+
+    /// The members
+    pub fn members(&self) -> &T {
+        &self.members
+    }
+
+    /// The members (mutable)
+    pub fn members_mut(&mut self) -> &mut T {
+        &mut self.members
+    }
+
+    /// Returns the members
+    pub fn into_members(self) -> T {
+        self.members
+    }
+
+    /// The schedule of the members
+    pub fn schedule(&self) -> &EnsembleSchedule {
+        &self.schedule
+    }
+
+    /// Runs the next member for one iteration, or for its time slice, and returns its index
+    pub fn fuzz_one<E, EM>(&mut self, executor: &mut E, manager: &mut EM) -> Result<usize, Error>
+    where
+        T: EnsembleMembersTuple<E, EM>,
+    {
+        let index = self.current;
+        match &self.schedule {
+            EnsembleSchedule::RoundRobin => {
+                self.members.fuzz_one_at(index, executor, manager)?;
+            }
+            EnsembleSchedule::TimeSlices(time_slices) => {
+                let end = current_time() + time_slices[index];
+                loop {
+                    self.members.fuzz_one_at(index, executor, manager)?;
+                    if current_time() >= end {
+                        break;
+                    }
+                }
+            }
+        }
+        self.current = (index + 1) % T::LEN;
+        Ok(index)
+    }
+
+    /// Runs the members in turn, until one of them stops
+    pub fn fuzz_loop<E, EM>(&mut self, executor: &mut E, manager: &mut EM) -> Result<(), Error>
+    where
+        T: EnsembleMembersTuple<E, EM>,
+    {
+        loop {
+            self.fuzz_one(executor, manager)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+
+    use libafl_bolts::{
+        rands::StdRand,
+        tuples::{tuple_list, tuple_list_type},
+    };
+
+    use super::{Ensemble, EnsembleMember};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        feedbacks::ConstFeedback,
+        fuzzer::StdFuzzer,
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, StdState, UsesState},
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    /// No stages run, so the executor is never called
+    struct UnusedExecutor(PhantomData<TestState>);
+
+    impl UsesState for UnusedExecutor {
+        type State = TestState;
+    }
+
+    #[test]
+    fn test_ensemble() {
+        let member = |name: &'static str| {
+            let mut feedback = ConstFeedback::new(false);
+            let mut objective = ConstFeedback::new(false);
+            let mut state = StdState::new(
+                StdRand::with_seed(0),
+                InMemoryCorpus::new(),
+                InMemoryCorpus::new(),
+                &mut feedback,
+                &mut objective,
+            )
+            .unwrap();
+            state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(vec![0])))
+                .unwrap();
+            let fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+            EnsembleMember::new(name, fuzzer, tuple_list!(), state)
+        };
+        let mut ensemble = Ensemble::new(tuple_list!(member("first"), member("second"))).unwrap();
+        let mut executor = UnusedExecutor(PhantomData);
+        let mut manager = NopEventManager::<TestState>::new();
+
+        assert_eq!(ensemble.fuzz_one(&mut executor, &mut manager).unwrap(), 0);
+        assert!(ensemble.members().1 .0.state().corpus().current().is_none());
+        assert_eq!(ensemble.fuzz_one(&mut executor, &mut manager).unwrap(), 1);
+        assert!(ensemble.members().1 .0.state().corpus().current().is_some());
+        assert_eq!(ensemble.fuzz_one(&mut executor, &mut manager).unwrap(), 0);
+
+        let members: tuple_list_type!(_, _) = ensemble.into_members();
+        assert!(Ensemble::with_time_slices(members, vec![]).is_err());
+    }
+}
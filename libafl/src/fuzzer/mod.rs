@@ -20,12 +20,19 @@ use crate::{
     stages::{HasCurrentStageId, StagesTuple},
     start_timer,
     state::{
-        HasCorpus, HasCurrentTestcase, HasExecutions, HasLastFoundTime, HasLastReportTime,
-        HasSolutions, State, Stoppable, UsesState,
+        stats, HasCorpus, HasCurrentTestcase, HasExecutions, HasLastFoundTime, HasLastReportTime,
+        HasSolutions, HasStartTime, State, Stoppable, UsesState,
     },
     Error, HasMetadata,
 };
 
+pub mod campaign;
+pub use campaign::*;
+pub mod ensemble;
+pub use ensemble::*;
+pub mod stop;
+pub use stop::*;
+
 /// Send a monitor update all 15 (or more) seconds
 const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15);
 
@@ -368,7 +375,14 @@ where
     CS: Scheduler<S::Input, S>,
     F: Feedback<EM, S::Input, OT, S>,
     OF: Feedback<EM, S::Input, OT, S>,
-    S: HasCorpus + HasSolutions + HasExecutions + HasCorpus + HasCurrentCorpusId + State,
+    S: HasCorpus
+        + HasSolutions
+        + HasExecutions
+        + HasCorpus
+        + HasCurrentCorpusId
+        + HasLastFoundTime
+        + HasMetadata
+        + State,
     S::Corpus: Corpus<Input = S::Input>,    //delete me
     S::Solutions: Corpus<Input = S::Input>, //delete me
 {
@@ -551,6 +565,8 @@ where
                     .append_metadata(state, manager, observers, &mut testcase)?;
                 let id = state.corpus_mut().add(testcase)?;
                 self.scheduler_mut().on_add(state, id)?;
+                *state.last_found_time_mut() = current_time();
+                stats::record_find(state);
 
                 Ok(Some(id))
             }
@@ -570,6 +586,7 @@ where
                 self.objective_mut()
                     .append_metadata(state, manager, observers, &mut testcase)?;
                 state.solutions_mut().add(testcase)?;
+                stats::record_objective(state);
 
                 Ok(None)
             }
@@ -583,7 +600,7 @@ where
     OT: ObserversTuple<S::Input, S> + Serialize + DeserializeOwned,
     F: Feedback<EM, S::Input, OT, S>,
     OF: Feedback<EM, S::Input, OT, S>,
-    S: HasCorpus + HasSolutions + HasExecutions + State,
+    S: HasCorpus + HasSolutions + HasExecutions + HasLastFoundTime + HasMetadata + State,
     S::Corpus: Corpus<Input = S::Input>,    //delete me
     S::Solutions: Corpus<Input = S::Input>, //delete me
 {
@@ -618,7 +635,7 @@ where
     EM: EventFirer<State = S>,
     F: Feedback<EM, S::Input, E::Observers, S>,
     OF: Feedback<EM, S::Input, E::Observers, S>,
-    S: HasCorpus + HasSolutions + HasExecutions + HasLastFoundTime + State,
+    S: HasCorpus + HasSolutions + HasExecutions + HasLastFoundTime + HasMetadata + State,
     S::Corpus: Corpus<Input = S::Input>,    //delete me
     S::Solutions: Corpus<Input = S::Input>, //delete me
 {
@@ -682,6 +699,7 @@ where
             self.objective_mut()
                 .append_metadata(state, manager, &*observers, &mut testcase)?;
             let id = state.solutions_mut().add(testcase)?;
+            stats::record_objective(state);
 
             manager.fire(
                 state,
@@ -720,6 +738,7 @@ where
             .append_metadata(state, manager, &*observers, &mut testcase)?;
         let id = state.corpus_mut().add(testcase)?;
         self.scheduler_mut().on_add(state, id)?;
+        stats::record_find(state);
 
         let observers_buf = if manager.configuration() == EventConfig::AlwaysUnique {
             None
@@ -752,7 +771,10 @@ where
     S: HasExecutions
         + HasMetadata
         + HasCorpus
+        + HasSolutions
         + HasLastReportTime
+        + HasLastFoundTime
+        + HasStartTime
         + HasTestcase
         + HasCurrentCorpusId
         + HasCurrentStageId
@@ -811,8 +833,19 @@ where
 
         state.clear_corpus_id()?;
 
+        // Most campaigns don't set a policy, don't spend any time on it then
+        if state.has_metadata::<StopPolicy>() {
+            if let Some(reason) = StopPolicy::check_state(state) {
+                log::info!("Stopping, the campaign {reason}");
+                state.request_stop();
+            }
+        }
+
         if state.stop_requested() {
             state.discard_stop_request();
+            state.corpus_mut().flush()?;
+            state.solutions_mut().flush()?;
+            manager.report_progress(state)?;
             manager.on_shutdown()?;
             return Err(Error::shutting_down());
         }
@@ -0,0 +1,199 @@
+//! Conditions stopping a campaign cleanly, checked by the [`StdFuzzer`](super::StdFuzzer) after each iteration.
+//!
+//! A [`StopPolicy`] is stored as metadata of the state, so each client can have its own.
+//! When one of its limits is reached, the fuzzer requests a stop: it flushes the corpora,
+//! reports the final stats and returns [`Error::ShuttingDown`](crate::Error::ShuttingDown) from the fuzz loop,
+//! as for an [`Event::Stop`](crate::events::Event::Stop).
+//! [`StopPolicy::broadcast`] sets the policy of all the clients connected to the event manager.
+use core::{fmt, time::Duration};
+
+use libafl_bolts::{current_time, impl_serdeany};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Corpus,
+    events::{Event, EventFirer},
+    state::{HasExecutions, HasLastFoundTime, HasSolutions, HasStartTime},
+    Error, HasMetadata,
+};
+
+/// The limits of a campaign, see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StopPolicy {
+    time: Option<Duration>,
+    executions: Option<u64>,
+    objectives: Option<usize>,
+    time_without_finds: Option<Duration>,
+}
+
+impl_serdeany!(StopPolicy);
+
+/// The limit of a [`StopPolicy`] that was reached
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopReason {
+    /// The campaign ran for the maximum time
+    MaxTime(Duration),
+    /// The target ran the maximum count of executions
+    MaxExecutions(u64),
+    /// The campaign found the maximum count of objectives
+    MaxObjectives(usize),
+    /// Nothing was added to the corpus for the maximum time, the coverage reached a plateau
+    MaxTimeWithoutFinds(Duration),
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MaxTime(max) => write!(f, "ran for the maximum time of {}s", max.as_secs()),
+            Self::MaxExecutions(max) => write!(f, "ran the maximum of {max} executions"),
+            Self::MaxObjectives(max) => write!(f, "found the maximum of {max} objectives"),
+            Self::MaxTimeWithoutFinds(max) => {
+                write!(f, "found nothing new for {}s", max.as_secs())
+            }
+        }
+    }
+}
+
+impl StopPolicy {
+    /// Creates a new [`StopPolicy`] without limits
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stops after the campaign ran for `max_time` since the start of the state
+    #[must_use]
+    pub fn with_max_time(mut self, max_time: Duration) -> Self {
+        self.time = Some(max_time);
+        self
+    }
+
+    /// Stops after `max_executions` executions of this client
+    #[must_use]
+    pub fn with_max_executions(mut self, max_executions: u64) -> Self {
+        self.executions = Some(max_executions);
+        self
+    }
+
+    /// Stops after the solutions corpus of this client holds `max_objectives` solutions
+    #[must_use]
+    pub fn with_max_objectives(mut self, max_objectives: usize) -> Self {
+        self.objectives = Some(max_objectives);
+        self
+    }
+
+    /// Stops after nothing was added to the corpus for `max_time_without_finds`
+    #[must_use]
+    pub fn with_max_time_without_finds(mut self, max_time_without_finds: Duration) -> Self {
+        self.time_without_finds = Some(max_time_without_finds);
+        self
+    }
+
+    /// Returns the limit reached by the state, if any
+    pub fn check<S>(&self, state: &S) -> Option<StopReason>
+    where
+        S: HasExecutions + HasLastFoundTime + HasSolutions + HasStartTime,
+    {
+        let now = current_time();
+        if let Some(max) = self.time {
+            if now.saturating_sub(*state.start_time()) >= max {
+                return Some(StopReason::MaxTime(max));
+            }
+        }
+        if let Some(max) = self.executions {
+            if *state.executions() >= max {
+                return Some(StopReason::MaxExecutions(max));
+            }
+        }
+        if let Some(max) = self.objectives {
+            if state.solutions().count() >= max {
+                return Some(StopReason::MaxObjectives(max));
+            }
+        }
+        if let Some(max) = self.time_without_finds {
+            let last_find = (*state.start_time()).max(*state.last_found_time());
+            if now.saturating_sub(last_find) >= max {
+                return Some(StopReason::MaxTimeWithoutFinds(max));
+            }
+        }
+        None
+    }
+
+    /// Returns the limit of the [`StopPolicy`] of the state reached by the state, if any
+    pub fn check_state<S>(state: &S) -> Option<StopReason>
+    where
+        S: HasExecutions + HasLastFoundTime + HasMetadata + HasSolutions + HasStartTime,
+    {
+        state.metadata_map().get::<Self>()?.check(state)
+    }
+
+    /// Sets this policy for this client, and for all the other clients of the event manager
+    pub fn broadcast<EM>(self, state: &mut EM::State, manager: &mut EM) -> Result<(), Error>
+    where
+        EM: EventFirer,
+        EM::State: HasMetadata,
+    {
+        state.add_metadata(self.clone());
+        manager.fire(state, Event::SetStopPolicy { policy: self })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{StopPolicy, StopReason};
+    use crate::{
+        corpus::{Corpus, Testcase},
+        inputs::BytesInput,
+        state::{HasExecutions, HasLastFoundTime, HasSolutions, HasStartTime, StdState},
+        HasMetadata,
+    };
+
+    #[test]
+    #[allow(unknown_lints, clippy::duration_suboptimal_units)] // `from_mins` is too recent for our MSRV
+    fn test_stop_policy() {
+        let mut state = StdState::nop::<BytesInput>().unwrap();
+        assert_eq!(StopPolicy::check_state(&state), None);
+
+        state.add_metadata(
+            StopPolicy::new()
+                .with_max_executions(100)
+                .with_max_objectives(1)
+                .with_max_time_without_finds(Duration::from_secs(60)),
+        );
+        assert_eq!(StopPolicy::check_state(&state), None);
+
+        *state.executions_mut() = 100;
+        assert_eq!(
+            StopPolicy::check_state(&state),
+            Some(StopReason::MaxExecutions(100))
+        );
+        *state.executions_mut() = 0;
+
+        state
+            .solutions_mut()
+            .add(Testcase::new(BytesInput::new(vec![])))
+            .unwrap();
+        assert_eq!(
+            StopPolicy::check_state(&state),
+            Some(StopReason::MaxObjectives(1))
+        );
+
+        let policy = StopPolicy::new().with_max_time_without_finds(Duration::from_secs(60));
+        *state.start_time_mut() -= Duration::from_secs(120);
+        *state.last_found_time_mut() -= Duration::from_secs(61);
+        assert_eq!(
+            policy.check(&state),
+            Some(StopReason::MaxTimeWithoutFinds(Duration::from_secs(60)))
+        );
+        *state.last_found_time_mut() += Duration::from_secs(2);
+        assert_eq!(policy.check(&state), None);
+        assert_eq!(
+            StopPolicy::new()
+                .with_max_time(Duration::from_secs(100))
+                .check(&state),
+            Some(StopReason::MaxTime(Duration::from_secs(100)))
+        );
+    }
+}
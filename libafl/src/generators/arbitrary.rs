@@ -0,0 +1,54 @@
+//! Generator of [`ArbitraryInput`]s, see [`crate::inputs::arbitrary`]
+use alloc::vec::Vec;
+use core::{marker::PhantomData, num::NonZeroUsize};
+
+use ::arbitrary::Arbitrary;
+use libafl_bolts::rands::Rand;
+
+use crate::{generators::Generator, inputs::ArbitraryInput, nonzero, state::HasRand, Error};
+
+/// The count of random pools of bytes tried before giving up, if no value is derived from them
+const MAX_TRIES: usize = 256;
+
+/// Generates random pools of bytes, from which a `T` is derived with [`Arbitrary`]
+#[derive(Clone, Debug)]
+pub struct ArbitraryGenerator<T> {
+    max_size: NonZeroUsize,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T, S> Generator<ArbitraryInput<T>, S> for ArbitraryGenerator<T>
+where
+    T: for<'a> Arbitrary<'a>,
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<ArbitraryInput<T>, Error> {
+        // at least the bytes needed by the smallest value of `T`
+        let (min_size, _) = T::size_hint(0);
+        let min_size = min_size.clamp(1, self.max_size.get());
+        let rand = state.rand_mut();
+        for _ in 0..MAX_TRIES {
+            let size = rand.between(min_size, self.max_size.get());
+            let bytes: Vec<u8> = (0..size).map(|_| rand.below(nonzero!(256)) as u8).collect();
+            let input = ArbitraryInput::new(bytes);
+            if input.value().is_ok() {
+                return Ok(input);
+            }
+        }
+        Err(Error::illegal_state(format!(
+            "Could not derive a value from {MAX_TRIES} random pools of up to {} bytes",
+            self.max_size
+        )))
+    }
+}
+
+impl<T> ArbitraryGenerator<T> {
+    /// Returns a new [`ArbitraryGenerator`], generating pools of up to `max_size` bytes
+    #[must_use]
+    pub fn new(max_size: NonZeroUsize) -> Self {
+        Self {
+            max_size,
+            phantom: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Generator of bytes within constraints, see [`crate::inputs::constrained`]
+use alloc::vec::Vec;
+
+use libafl_bolts::rands::Rand;
+
+use crate::{
+    generators::Generator,
+    inputs::{ByteConstraints, ConstrainedBytesInput},
+    state::HasRand,
+    Error,
+};
+
+/// Generates random [`ConstrainedBytesInput`]s, of a random length within the constraints
+#[derive(Clone, Debug)]
+pub struct ConstrainedBytesGenerator {
+    constraints: ByteConstraints,
+    /// The allowed bytes, to pick from
+    allowed: Vec<u8>,
+}
+
+impl<S> Generator<ConstrainedBytesInput, S> for ConstrainedBytesGenerator
+where
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<ConstrainedBytesInput, Error> {
+        let rand = state.rand_mut();
+        let len = rand.between(self.constraints.min_len(), self.constraints.max_len());
+        let bytes = (0..len)
+            .map(|_| *rand.choose(&self.allowed).unwrap())
+            .collect();
+        ConstrainedBytesInput::new(bytes, self.constraints.clone())
+    }
+}
+
+impl ConstrainedBytesGenerator {
+    /// Creates a new [`ConstrainedBytesGenerator`], returning an error if no input can satisfy the `constraints`
+    pub fn new(constraints: ByteConstraints) -> Result<Self, Error> {
+        constraints.validate()?;
+        let allowed = (0..=u8::MAX)
+            .filter(|byte| constraints.allows(*byte))
+            .collect();
+        Ok(Self {
+            constraints,
+            allowed,
+        })
+    }
+
+    /// The constraints of the generated inputs
+    #[must_use]
+    pub fn constraints(&self) -> &ByteConstraints {
+        &self.constraints
+    }
+}
@@ -12,6 +12,17 @@ use core::cmp::max;
 
 pub use gramatron::*;
 
+pub mod syscalls;
+pub use syscalls::*;
+
+pub mod constrained;
+pub use constrained::*;
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "arbitrary")]
+pub use self::arbitrary::*;
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 #[cfg(feature = "nautilus")]
@@ -1,17 +1,17 @@
 //! Generators for the [`Nautilus`](https://github.com/RUB-SysSec/nautilus) grammar fuzzer
-use alloc::{
-    string::{String, ToString},
-    vec::Vec,
-};
+use alloc::{string::String, vec::Vec};
 use core::fmt::Debug;
-use std::{fs, io::BufReader, path::Path};
+use std::{ffi::OsStr, fs, io::BufReader, path::Path};
 
 use libafl_bolts::rands::Rand;
 
 pub use crate::common::nautilus::grammartec::newtypes::NTermId;
 use crate::{
-    common::nautilus::grammartec::context::Context, generators::Generator,
-    inputs::nautilus::NautilusInput, nautilus::grammartec::python_grammar_loader, state::HasRand,
+    common::nautilus::grammartec::{context::Context, newtypes::RuleId, rule::RuleChild},
+    generators::Generator,
+    inputs::nautilus::NautilusInput,
+    nautilus::grammartec::python_grammar_loader,
+    state::HasRand,
     Error,
 };
 
@@ -29,18 +29,43 @@ impl Debug for NautilusContext {
 
 impl NautilusContext {
     /// Returns a new [`NautilusGenerator`]
+    ///
+    /// # Panics
+    /// Panics if the `rules` are not a valid grammar, see [`NautilusContext::try_new`].
     #[must_use]
     pub fn new(tree_depth: usize, rules: &[Vec<String>]) -> Self {
-        assert!(!rules.is_empty());
-        assert!(!rules[0].is_empty());
+        Self::try_new(tree_depth, rules).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Returns a new [`NautilusContext`] from `[nonterminal, format]` rules, as found in the json grammars.
+    ///
+    /// The starting rule is `rules[0]`.
+    /// Returns an error pointing at the offending rule if a nonterminal is invalid or has no rule,
+    /// or if a rule never produces a finite tree.
+    pub fn try_new(tree_depth: usize, rules: &[Vec<String>]) -> Result<Self, Error> {
         let mut ctx = Context::new();
-        for rule in rules {
-            ctx.add_rule(&rule[0], rule[1].as_bytes());
+        for (i, rule) in rules.iter().enumerate() {
+            let [nt, format, ..] = rule.as_slice() else {
+                return Err(Error::illegal_argument(format!(
+                    "Invalid grammar rule {i} {rule:?}: expected a nonterminal and a format"
+                )));
+            };
+            ctx.try_add_rule(nt, format.as_bytes())
+                .map_err(|err| invalid_rule(&format!("{i} ({nt:?} => {format:?})"), err))?;
         }
-        let root = "{".to_string() + &rules[0][0] + "}";
-        ctx.add_rule("START", root.as_bytes());
-        ctx.initialize(tree_depth);
-        Self { ctx }
+        let start = rules
+            .first()
+            .ok_or_else(|| Error::illegal_argument("The grammar has no rules"))?;
+        ctx.try_add_rule("START", format!("{{{}}}", start[0]).as_bytes())
+            .map_err(|err| invalid_rule(&format!("0 ({:?})", start[0]), err))?;
+
+        Self::check_and_initialize(ctx, tree_depth, |ctx, rule| {
+            let i = rule.to_i();
+            rules.get(i).map_or_else(
+                || ctx.get_rule(rule).debug_show(ctx),
+                |rule| format!("{i} ({:?} => {:?})", rule[0], rule[1]),
+            )
+        })
     }
 
     /// Returns a new [`NautilusContext`] with support for non UTF-8 rules.
@@ -84,29 +109,258 @@ impl NautilusContext {
         Some(Self { ctx })
     }
 
-    /// Create a new [`NautilusContext`] from a file
+    /// Returns a new [`NautilusContext`] from a grammar in BNF, with the nonterminals named as in the grammar.
+    ///
+    /// The first rule is the starting rule. Terminals are quoted with `"` or `'`, and may contain the escapes
+    /// `\n`, `\r`, `\t`, `\0` and `\xHH`. Comments start with `#`. The errors point at the line of the offending rule.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libafl::generators::nautilus::NautilusContext;
+    ///
+    /// let grammar = r#"
+    ///     <expr>  ::= <term> | <term> "+" <expr>
+    ///     <term>  ::= <digit> | "(" <expr> ")"
+    ///     <digit> ::= "0" | "1" | "\x32" # a comment
+    /// "#;
+    /// let context = NautilusContext::from_bnf(10, grammar).unwrap();
+    ///
+    /// let error = NautilusContext::from_bnf(10, "<a> ::= <b>\n<b> ::= <c>").unwrap_err();
+    /// assert!(error.to_string().contains("line 2"));
+    /// ```
+    pub fn from_bnf(tree_depth: usize, grammar: &str) -> Result<Self, Error> {
+        let mut ctx = Context::new();
+        let mut lines = Vec::new();
+        // the current rule, as its nonterminal, the line of the current alternative and its children
+        let mut current: Option<(&str, usize, Vec<RuleChild>)> = None;
+        let mut start = None;
+
+        let tokens = tokenize_bnf(grammar)?;
+        let mut tokens = tokens.iter().peekable();
+        while let Some((line, token)) = tokens.next() {
+            match token {
+                BnfToken::NTerm(nt) if matches!(tokens.peek(), Some((_, BnfToken::Define))) => {
+                    tokens.next();
+                    if let Some((prev, line, children)) = current.take() {
+                        ctx.add_plain_rule(prev, children);
+                        lines.push(line);
+                    }
+                    start.get_or_insert(nt.as_str());
+                    current = Some((nt.as_str(), *line, Vec::new()));
+                }
+                BnfToken::Define => {
+                    return Err(bnf_error(*line, "expected a nonterminal before `::=`"));
+                }
+                _ => {
+                    let Some((nt, alt_line, children)) = &mut current else {
+                        return Err(bnf_error(*line, "expected a rule, `<nonterminal> ::= ...`"));
+                    };
+                    match token {
+                        BnfToken::Alt => {
+                            ctx.add_plain_rule(nt, core::mem::take(children));
+                            lines.push(*alt_line);
+                            *alt_line = *line;
+                        }
+                        BnfToken::NTerm(child) => {
+                            children.push(RuleChild::NTerm(ctx.aquire_nt_id(child)));
+                        }
+                        BnfToken::Term(term) if !term.is_empty() => {
+                            children.push(RuleChild::from_lit(term));
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+        if let Some((nt, line, children)) = current {
+            ctx.add_plain_rule(nt, children);
+            lines.push(line);
+        }
+        let start = start.ok_or_else(|| Error::illegal_argument("The grammar has no rules"))?;
+        let start = RuleChild::NTerm(ctx.aquire_nt_id(start));
+        ctx.add_plain_rule("START", vec![start]);
+
+        Self::check_and_initialize(ctx, tree_depth, |ctx, rule| {
+            let nt = ctx.nt_id_to_s(ctx.get_rule(rule).nonterm());
+            lines.get(rule.to_i()).map_or_else(
+                || format!("<{nt}>"),
+                |line| format!("<{nt}> at line {line}"),
+            )
+        })
+    }
+
+    /// Create a new [`NautilusContext`] from a file.
+    ///
+    /// The grammar is loaded depending on the extension, as python with `.py`,
+    /// as BNF with `.bnf`, see [`NautilusContext::from_bnf`], and as json otherwise, see [`NautilusContext::try_new`].
     pub fn from_file<P: AsRef<Path>>(tree_depth: usize, grammar_file: P) -> Result<Self, Error> {
         let grammar_file = grammar_file.as_ref();
-        if grammar_file.extension().unwrap_or_default() == "py" {
-            log::debug!("Creating NautilusContext from python grammar");
-            let mut ctx = python_grammar_loader::load_python_grammar(
-                fs::read_to_string(grammar_file)?.as_str(),
-            );
-            ctx.initialize(tree_depth);
-            return Ok(Self { ctx });
+        match grammar_file.extension().and_then(OsStr::to_str) {
+            Some("py") => {
+                log::debug!("Creating NautilusContext from python grammar");
+                let ctx = python_grammar_loader::load_python_grammar(
+                    fs::read_to_string(grammar_file)?.as_str(),
+                );
+                Self::check_and_initialize(ctx, tree_depth, |ctx, rule| {
+                    ctx.get_rule(rule).debug_show(ctx)
+                })
+            }
+            Some("bnf") => {
+                log::debug!("Creating NautilusContext from bnf grammar");
+                Self::from_bnf(tree_depth, &fs::read_to_string(grammar_file)?)
+            }
+            _ => {
+                log::debug!("Creating NautilusContext from json grammar");
+                let file = fs::File::open(grammar_file)?;
+                let reader = BufReader::new(file);
+                let rules: Vec<Vec<String>> = serde_json::from_reader(reader).map_err(|err| {
+                    Error::illegal_argument(format!(
+                        "Error loading context from json grammar file {grammar_file:?}: {err:?}"
+                    ))
+                })?;
+                Self::try_new(tree_depth, &rules)
+            }
         }
-        log::debug!("Creating NautilusContext from json grammar");
-        let file = fs::File::open(grammar_file)?;
-        let reader = BufReader::new(file);
-        let rules: Vec<Vec<String>> = serde_json::from_reader(reader).map_err(|err| {
-            Error::illegal_argument(format!(
-                "Error loading context from json grammar file {grammar_file:?}: {err:?}"
-            ))
-        })?;
-        Ok(Self::new(tree_depth, &rules))
+    }
+
+    /// Checks the rules of the `ctx`, which [`Context::initialize`] would panic on, and initializes it.
+    ///
+    /// The `origin` describes a rule for the errors.
+    fn check_and_initialize<F>(
+        mut ctx: Context,
+        tree_depth: usize,
+        origin: F,
+    ) -> Result<Self, Error>
+    where
+        F: Fn(&Context, RuleId) -> String,
+    {
+        if let Some((rule, nt)) = ctx.find_undefined_nonterm() {
+            return Err(Error::illegal_argument(format!(
+                "Invalid grammar rule {}: the nonterminal {:?} has no rule",
+                origin(&ctx, rule),
+                ctx.nt_id_to_s(nt)
+            )));
+        }
+        if let Some(rule) = ctx.find_unproductive_rules().first() {
+            return Err(Error::illegal_argument(format!(
+                "Invalid grammar rule {}: never produces a finite tree, a non recursive rule is missing",
+                origin(&ctx, *rule)
+            )));
+        }
+        ctx.initialize(tree_depth);
+        Ok(Self { ctx })
+    }
+}
+
+/// Prepends the rule to the error of an invalid rule
+fn invalid_rule(origin: &str, err: Error) -> Error {
+    match err {
+        Error::IllegalArgument(msg, _) => {
+            Error::illegal_argument(format!("Invalid grammar rule {origin}: {msg}"))
+        }
+        err => err,
     }
 }
 
+/// A token of a grammar in BNF, see [`NautilusContext::from_bnf`]
+#[derive(Debug)]
+enum BnfToken {
+    /// `<nonterminal>`
+    NTerm(String),
+    /// `"terminal"`
+    Term(Vec<u8>),
+    /// `::=`
+    Define,
+    /// `|`
+    Alt,
+}
+
+fn bnf_error(line: usize, msg: &str) -> Error {
+    Error::illegal_argument(format!("Invalid grammar at line {line}: {msg}"))
+}
+
+/// Splits a grammar in BNF to its tokens, with their lines
+fn tokenize_bnf(grammar: &str) -> Result<Vec<(usize, BnfToken)>, Error> {
+    let bytes = grammar.as_bytes();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\n' => line += 1,
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'|' => tokens.push((line, BnfToken::Alt)),
+            b':' if bytes[i..].starts_with(b"::=") => {
+                tokens.push((line, BnfToken::Define));
+                i += 2;
+            }
+            b'<' => {
+                let len = bytes[i + 1..]
+                    .iter()
+                    .position(|b| *b == b'>' || *b == b'\n')
+                    .filter(|len| bytes[i + 1 + len] == b'>')
+                    .ok_or_else(|| bnf_error(line, "unterminated nonterminal"))?;
+                let nt = grammar[i + 1..=i + len].trim();
+                if nt.is_empty() {
+                    return Err(bnf_error(line, "empty nonterminal"));
+                }
+                tokens.push((line, BnfToken::NTerm(nt.into())));
+                i += len + 1;
+            }
+            quote @ (b'"' | b'\'') => {
+                let mut term = Vec::new();
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        None | Some(b'\n') => return Err(bnf_error(line, "unterminated terminal")),
+                        Some(b'\\') => {
+                            let (byte, len) = bnf_unescape(&bytes[i + 1..])
+                                .ok_or_else(|| bnf_error(line, "invalid escape"))?;
+                            term.push(byte);
+                            i += len;
+                        }
+                        Some(b) if *b == quote => break,
+                        Some(b) => term.push(*b),
+                    }
+                    i += 1;
+                }
+                tokens.push((line, BnfToken::Term(term)));
+            }
+            b if b.is_ascii_whitespace() => {}
+            _ => {
+                let c = grammar[i..].chars().next().unwrap_or_default();
+                return Err(bnf_error(line, &format!("unexpected {c:?}")));
+            }
+        }
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+/// The byte of the escape at the start of `bytes`, after the `\`, and the length of the escape
+fn bnf_unescape(bytes: &[u8]) -> Option<(u8, usize)> {
+    Some(match bytes.first()? {
+        b'n' => (b'\n', 1),
+        b'r' => (b'\r', 1),
+        b't' => (b'\t', 1),
+        b'0' => (0, 1),
+        b'x' => {
+            let hex = core::str::from_utf8(bytes.get(1..3)?).ok()?;
+            if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            (u8::from_str_radix(hex, 16).ok()?, 3)
+        }
+        b => (*b, 1),
+    })
+}
+
 #[derive(Clone)]
 /// Generates random inputs from a grammar
 pub struct NautilusGenerator<'a> {
@@ -157,3 +411,60 @@ impl<'a> NautilusGenerator<'a> {
             .generate_from_nt(rand, start, len, self.ctx);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use libafl_bolts::rands::StdRand;
+
+    use super::{NautilusContext, NautilusGenerator};
+    use crate::inputs::nautilus::NautilusInput;
+
+    #[test]
+    fn test_nautilus_bnf() {
+        let grammar = "
+            # a comment
+            <list>  ::= '[' <items> ']'
+            <items> ::= ''
+                      | <item> | <item> \", \" <items>
+            <item>  ::= \"\\x41\" | \"{b}\"
+        ";
+        let context = NautilusContext::from_bnf(20, grammar).unwrap();
+        let generator = NautilusGenerator::new(&context);
+        let mut rand = StdRand::with_seed(1337);
+        let mut bytes = Vec::new();
+        for _ in 0..16 {
+            let mut input = NautilusInput::empty();
+            let start = generator.nonterminal("START");
+            generator.generate_from_nonterminal(&mut rand, &mut input, start, 20);
+            input.unparse(&context, &mut bytes);
+            assert!(bytes.starts_with(b"[") && bytes.ends_with(b"]"));
+            assert!(bytes[1..bytes.len() - 1].split(|b| *b == b',').all(|item| [
+                &b""[..],
+                b"A",
+                b"{b}"
+            ]
+            .contains(&item.trim_ascii())));
+        }
+
+        let error = |grammar| {
+            NautilusContext::from_bnf(10, grammar)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(error("<a> ::= 'x'\n<b> ::= <a> <c>").contains("<b> at line 2"));
+        assert!(error("<a> ::= 'x' | <a>\n<b> ::= <b> 'y'").contains("<b> at line 2"));
+        assert!(error("<a> ::= 'x\n").contains("line 1: unterminated terminal"));
+        assert!(error("'x' ::= <a>").contains("line 1"));
+
+        let rules = vec![
+            vec!["A".to_string(), "x{B}".to_string()],
+            vec!["B".to_string(), "{lower}".to_string()],
+        ];
+        let error = NautilusContext::try_new(10, &rules)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("rule 1"));
+    }
+}
@@ -0,0 +1,140 @@
+//! Generator of syscall sequences, see [`crate::inputs::syscalls`]
+use alloc::vec::Vec;
+use core::num::NonZero;
+
+use libafl_bolts::rands::Rand;
+
+use crate::{
+    generators::Generator,
+    inputs::{
+        SyscallArg, SyscallArgType, SyscallCall, SyscallSequenceInput, SyscallTable,
+        INVALID_RESOURCE,
+    },
+    state::HasRand,
+    Error,
+};
+
+/// Integers often found at the boundaries of the checks of the target
+const INTERESTING_INTS: [u64; 8] = [0, 1, 2, 0x7f, 0xff, 0x1000, 0x7fff_ffff, u64::MAX];
+
+/// Generates random [`SyscallSequenceInput`]s from a [`SyscallTable`]
+#[derive(Clone, Debug)]
+pub struct SyscallSequenceGenerator<'a> {
+    table: &'a SyscallTable,
+    max_calls: NonZero<usize>,
+}
+
+impl<S> Generator<SyscallSequenceInput, S> for SyscallSequenceGenerator<'_>
+where
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<SyscallSequenceInput, Error> {
+        if self.table.syscalls.is_empty() {
+            return Err(Error::empty("No syscalls in the table to generate from"));
+        }
+        let rand = state.rand_mut();
+        let count = rand.between(1, self.max_calls.get());
+        let mut input = SyscallSequenceInput::new(Vec::with_capacity(count));
+        for _ in 0..count {
+            let call = self.generate_call(rand, input.calls());
+            input.calls_mut().push(call);
+        }
+        Ok(input)
+    }
+}
+
+impl<'a> SyscallSequenceGenerator<'a> {
+    /// Returns a new [`SyscallSequenceGenerator`], generating up to `max_calls` calls
+    #[must_use]
+    pub fn new(table: &'a SyscallTable, max_calls: NonZero<usize>) -> Self {
+        Self { table, max_calls }
+    }
+
+    /// The [`SyscallTable`] of the generated calls
+    #[must_use]
+    pub fn table(&self) -> &'a SyscallTable {
+        self.table
+    }
+
+    /// The maximum count of calls of the generated inputs
+    #[must_use]
+    pub fn max_calls(&self) -> NonZero<usize> {
+        self.max_calls
+    }
+
+    /// Generates a random call, after the `prior` calls, the results of which its arguments may use
+    ///
+    /// # Panics
+    /// Panics if the table is empty.
+    pub fn generate_call<R: Rand>(&self, rand: &mut R, prior: &[SyscallCall]) -> SyscallCall {
+        let syscall =
+            rand.below(NonZero::new(self.table.syscalls.len()).expect("No syscalls in the table"));
+        let args = self.table.syscalls[syscall]
+            .args
+            .iter()
+            .map(|ty| self.generate_arg(rand, ty, prior))
+            .collect();
+        SyscallCall::new(syscall, args)
+    }
+
+    /// Generates a random argument of type `ty`, for a call after the `prior` calls
+    pub fn generate_arg<R: Rand>(
+        &self,
+        rand: &mut R,
+        ty: &SyscallArgType,
+        prior: &[SyscallCall],
+    ) -> SyscallArg {
+        match ty {
+            SyscallArgType::Int { bits } => {
+                let value = if rand.coinflip(0.25) {
+                    *rand.choose(&INTERESTING_INTS).unwrap()
+                } else {
+                    rand.next()
+                };
+                SyscallArg::Int(truncate_bits(value, *bits))
+            }
+            SyscallArgType::Range { min, max } => {
+                let span = max.saturating_sub(*min);
+                let offset = if span == u64::MAX {
+                    rand.next()
+                } else {
+                    rand.next() % (span + 1)
+                };
+                SyscallArg::Int(min + offset)
+            }
+            SyscallArgType::Flags(flags) => {
+                let value = flags
+                    .iter()
+                    .filter(|_| rand.coinflip(0.5))
+                    .fold(0, |value, flag| value | flag);
+                SyscallArg::Int(value)
+            }
+            SyscallArgType::Buffer { max_len } => {
+                let len = rand.between(0, *max_len);
+                SyscallArg::Buffer((0..len).map(|_| rand.next() as u8).collect())
+            }
+            SyscallArgType::Resource { kind } => self.resource_arg(rand, kind, prior),
+        }
+    }
+
+    /// A reference to a random call of the `prior` calls returning a resource of the given `kind`,
+    /// or [`INVALID_RESOURCE`] if none does
+    pub fn resource_arg<R: Rand>(
+        &self,
+        rand: &mut R,
+        kind: &str,
+        prior: &[SyscallCall],
+    ) -> SyscallArg {
+        rand.choose(self.table.producers(kind, prior))
+            .map_or(SyscallArg::Int(INVALID_RESOURCE), SyscallArg::Result)
+    }
+}
+
+/// Keep the lowest `bits` bits of `value`
+pub(crate) fn truncate_bits(value: u64, bits: u32) -> u64 {
+    if bits >= 64 {
+        value
+    } else {
+        value & ((1 << bits) - 1)
+    }
+}
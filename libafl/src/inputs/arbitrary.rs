@@ -0,0 +1,187 @@
+//! An input deriving a structured value with the [`arbitrary`](https://docs.rs/arbitrary) crate,
+//! for `cargo fuzz`-style structured fuzzing.
+//!
+//! The [`ArbitraryInput`] is a pool of bytes, from which the value is derived with [`Arbitrary::arbitrary_take_rest`],
+//! the way `cargo fuzz` does it. The bytes are mutated by the usual bytes mutators,
+//! wrapped in a [`crate::mutators::ArbitraryMutator`] to keep the inputs from which a value is derived.
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    hash::{BuildHasher, Hash, Hasher},
+    marker::PhantomData,
+};
+use std::{fs::File, io::Read, path::Path};
+
+use ::arbitrary::{Arbitrary, Unstructured};
+use ahash::RandomState;
+use libafl_bolts::{fs::write_file_atomic, ownedref::OwnedSlice, Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasMutatorBytes, HasTargetBytes, Input},
+};
+
+/// A pool of bytes, from which a `T` is derived with [`Arbitrary`], see the [module-level documentation](self)
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ArbitraryInput<T> {
+    bytes: Vec<u8>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> Input for ArbitraryInput<T> {
+    /// Write the bytes of this input to the file
+    fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_atomic(path, &self.bytes)
+    }
+
+    /// Load the bytes of this input from a file
+    fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::open(path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Self::new(bytes))
+    }
+
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(&self.bytes);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl<T> Clone for ArbitraryInput<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.bytes.clone())
+    }
+}
+
+impl<T> Debug for ArbitraryInput<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArbitraryInput")
+            .field("bytes", &self.bytes)
+            .finish()
+    }
+}
+
+impl<T> PartialEq for ArbitraryInput<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<T> Eq for ArbitraryInput<T> {}
+
+impl<T> Hash for ArbitraryInput<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.bytes.hash(state);
+    }
+}
+
+impl<T> HasMutatorBytes for ArbitraryInput<T> {
+    #[inline]
+    fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    #[inline]
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+
+    fn resize(&mut self, new_len: usize, value: u8) {
+        self.bytes.resize(new_len, value);
+    }
+
+    fn extend<'a, I: IntoIterator<Item = &'a u8>>(&mut self, iter: I) {
+        Extend::extend(&mut self.bytes, iter);
+    }
+
+    fn splice<R, I>(&mut self, range: R, replace_with: I) -> alloc::vec::Splice<'_, I::IntoIter>
+    where
+        R: core::ops::RangeBounds<usize>,
+        I: IntoIterator<Item = u8>,
+    {
+        self.bytes.splice(range, replace_with)
+    }
+
+    fn drain<R>(&mut self, range: R) -> alloc::vec::Drain<'_, u8>
+    where
+        R: core::ops::RangeBounds<usize>,
+    {
+        self.bytes.drain(range)
+    }
+}
+
+impl<T> HasTargetBytes for ArbitraryInput<T> {
+    /// The bytes of the pool, for harnesses deriving the value themselves, like the `cargo fuzz` ones
+    #[inline]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(&self.bytes)
+    }
+}
+
+impl<T> HasLen for ArbitraryInput<T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl<T> From<&[u8]> for ArbitraryInput<T> {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes.to_owned())
+    }
+}
+
+impl<T> ArbitraryInput<T> {
+    /// Creates a new [`ArbitraryInput`] from a pool of bytes
+    #[must_use]
+    pub const fn new(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Derives the value from the bytes.
+    ///
+    /// Returns an error if the bytes are not a valid `T`, for example if it has an invariant checked by [`Arbitrary`].
+    pub fn value<'a>(&'a self) -> Result<T, Error>
+    where
+        T: Arbitrary<'a>,
+    {
+        T::arbitrary_take_rest(Unstructured::new(&self.bytes)).map_err(|err| {
+            Error::illegal_argument(format!("Could not derive the value of the input: {err}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use super::ArbitraryInput;
+    use crate::inputs::HasMutatorBytes;
+
+    #[test]
+    fn test_arbitrary_input() {
+        let mut input: ArbitraryInput<(u8, bool, Vec<u16>)> = ArbitraryInput::new(vec![0; 8]);
+        let (byte, flag, _) = input.value().unwrap();
+        assert_eq!((byte, flag), (0, false));
+
+        input.bytes_mut()[0] = 42;
+        assert_eq!(input.value().unwrap().0, 42);
+
+        let input: ArbitraryInput<String> = ArbitraryInput::new(b"libafl".to_vec());
+        assert_eq!(input.value().unwrap(), "libafl");
+    }
+}
@@ -0,0 +1,255 @@
+//! The encodings of the inputs in the files of the on-disk corpora.
+//!
+//! An [`InputCodec`] is selected when constructing the corpus, see for example
+//! [`crate::corpus::InMemoryOnDiskCorpus::with_codec`]. The default [`NativeCodec`] keeps
+//! the [`Input::to_file`] and [`Input::from_file`] of each input type.
+//!
+//! The [`PostcardCodec`] and the [`JsonCodec`] start each file with a version header, for example
+//! `#libafl-input json v1`, so that a corpus written with another codec or an incompatible version of `LibAFL`
+//! is reported as such instead of failing to deserialize, or worse, deserializing to garbage.
+//! The [`RawBytesCodec`] writes the bytes of the inputs as-is, for exchanging the corpus with other tools.
+use alloc::{string::String, vec::Vec};
+use core::{fmt::Debug, marker::PhantomData};
+use std::{fs, path::Path};
+
+use libafl_bolts::fs::write_file_atomic;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    inputs::{HasTargetBytes, Input},
+    Error,
+};
+
+/// The version of the encodings of the [`PostcardCodec`] and the [`JsonCodec`],
+/// written to the header of each encoded input.
+///
+/// Bumped whenever the encoded inputs of a previous version can no longer be decoded.
+pub const INPUT_CODEC_VERSION: u32 = 1;
+
+/// The start of the header of the encoded inputs
+const HEADER_MAGIC: &[u8] = b"#libafl-input ";
+
+/// Encodes and decodes inputs, for the on-disk corpora, see the [module-level documentation](self)
+pub trait InputCodec<I>: Debug {
+    /// Encodes the input to bytes
+    fn encode(&self, input: &I) -> Result<Vec<u8>, Error>;
+
+    /// Decodes an input from bytes, returning an error if they were not encoded by this codec
+    fn decode(&self, bytes: &[u8]) -> Result<I, Error>;
+
+    /// Encodes the input to the file at `path`
+    fn write_file(&self, input: &I, path: &Path) -> Result<(), Error> {
+        write_file_atomic(path, &self.encode(input)?)
+    }
+
+    /// Decodes an input from the file at `path`
+    fn read_file(&self, path: &Path) -> Result<I, Error> {
+        self.decode(&fs::read(path)?)
+    }
+}
+
+/// The [`InputCodec`] using the [`Input::to_file`] and [`Input::from_file`] of the input type, without header.
+///
+/// Since these only work on files, [`InputCodec::encode`] and [`InputCodec::decode`]
+/// use the default encoding of [`Input`], a postcard.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct NativeCodec;
+
+impl<I> InputCodec<I> for NativeCodec
+where
+    I: Input,
+{
+    fn encode(&self, input: &I) -> Result<Vec<u8>, Error> {
+        Ok(postcard::to_allocvec(input)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<I, Error> {
+        Ok(postcard::from_bytes(bytes)?)
+    }
+
+    fn write_file(&self, input: &I, path: &Path) -> Result<(), Error> {
+        input.to_file(path)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<I, Error> {
+        I::from_file(path)
+    }
+}
+
+/// The [`InputCodec`] encoding inputs as postcards, after a version header
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct PostcardCodec;
+
+impl PostcardCodec {
+    /// The name of the format in the header
+    pub const FORMAT: &'static str = "postcard";
+}
+
+impl<I> InputCodec<I> for PostcardCodec
+where
+    I: Serialize + DeserializeOwned,
+{
+    fn encode(&self, input: &I) -> Result<Vec<u8>, Error> {
+        let mut bytes = header(Self::FORMAT);
+        bytes.extend(postcard::to_allocvec(input)?);
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<I, Error> {
+        let bytes = strip_header(Self::FORMAT, bytes)?;
+        postcard::from_bytes(bytes).map_err(|err| {
+            Error::serialize(format!(
+                "Failed to decode the {} input: {err}",
+                Self::FORMAT
+            ))
+        })
+    }
+}
+
+/// The [`InputCodec`] encoding inputs as pretty-printed JSON, after a version header.
+///
+/// The JSON starts on the second line, to read it with other tools.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct JsonCodec;
+
+impl JsonCodec {
+    /// The name of the format in the header
+    pub const FORMAT: &'static str = "json";
+}
+
+impl<I> InputCodec<I> for JsonCodec
+where
+    I: Serialize + DeserializeOwned,
+{
+    fn encode(&self, input: &I) -> Result<Vec<u8>, Error> {
+        let mut bytes = header(Self::FORMAT);
+        serde_json::to_writer_pretty(&mut bytes, input)
+            .map_err(|err| Error::serialize(format!("Failed to json-ify the input: {err}")))?;
+        Ok(bytes)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<I, Error> {
+        let bytes = strip_header(Self::FORMAT, bytes)?;
+        serde_json::from_slice(bytes).map_err(|err| {
+            Error::serialize(format!(
+                "Failed to decode the {} input: {err}",
+                Self::FORMAT
+            ))
+        })
+    }
+}
+
+/// The [`InputCodec`] writing the target bytes of the inputs as-is, without header,
+/// for example for a [`crate::inputs::BytesInput`] corpus shared with other fuzzers
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RawBytesCodec<I> {
+    phantom: PhantomData<fn() -> I>,
+}
+
+impl<I> RawBytesCodec<I> {
+    /// Creates a new [`RawBytesCodec`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<I> Default for RawBytesCodec<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> Clone for RawBytesCodec<I> {
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
+
+impl<I> Debug for RawBytesCodec<I> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("RawBytesCodec")
+    }
+}
+
+impl<I> InputCodec<I> for RawBytesCodec<I>
+where
+    I: HasTargetBytes + for<'a> From<&'a [u8]>,
+{
+    fn encode(&self, input: &I) -> Result<Vec<u8>, Error> {
+        Ok(input.target_bytes().to_vec())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<I, Error> {
+        Ok(I::from(bytes))
+    }
+}
+
+/// The header of the inputs encoded in `format`
+fn header(format: &str) -> Vec<u8> {
+    let mut bytes = HEADER_MAGIC.to_vec();
+    bytes.extend(format!("{format} v{INPUT_CODEC_VERSION}\n").as_bytes());
+    bytes
+}
+
+/// Checks the header of the inputs encoded in `format`, and returns the encoded input after it
+fn strip_header<'a>(format: &str, bytes: &'a [u8]) -> Result<&'a [u8], Error> {
+    let invalid = || {
+        Error::serialize(format!(
+            "The input has no valid {format} header, it was not encoded by the {format} codec"
+        ))
+    };
+    let rest = bytes.strip_prefix(HEADER_MAGIC).ok_or_else(invalid)?;
+    let end = rest.iter().position(|b| *b == b'\n').ok_or_else(invalid)?;
+    let line = String::from_utf8_lossy(&rest[..end]);
+    let (found, version) = line
+        .split_once(" v")
+        .and_then(|(found, version)| Some((found, version.parse::<u32>().ok()?)))
+        .ok_or_else(invalid)?;
+
+    if found != format {
+        return Err(Error::serialize(format!(
+            "The input was encoded by the {found} codec, not by the {format} codec"
+        )));
+    }
+    if version > INPUT_CODEC_VERSION {
+        return Err(Error::serialize(format!(
+            "The input was encoded by version {version} of the {format} codec, \
+            but this version of LibAFL only supports up to version {INPUT_CODEC_VERSION}"
+        )));
+    }
+    Ok(&rest[end + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InputCodec, JsonCodec, PostcardCodec, RawBytesCodec};
+    use crate::inputs::BytesInput;
+
+    #[test]
+    fn test_input_codecs() {
+        let input = BytesInput::new(b"libafl".to_vec());
+
+        let json = JsonCodec.encode(&input).unwrap();
+        assert!(json.starts_with(b"#libafl-input json v1\n"));
+        assert_eq!(JsonCodec.decode(&json).ok(), Some(input.clone()));
+
+        let postcard = PostcardCodec.encode(&input).unwrap();
+        assert_eq!(PostcardCodec.decode(&postcard).ok(), Some(input.clone()));
+
+        // the header tells the codecs and the versions apart
+        let decoded: Result<BytesInput, _> = JsonCodec.decode(&postcard);
+        assert!(decoded.is_err());
+        let decoded: Result<BytesInput, _> = PostcardCodec.decode(b"libafl");
+        assert!(decoded.is_err());
+        let decoded: Result<BytesInput, _> = PostcardCodec.decode(b"#libafl-input postcard v99\n");
+        assert!(decoded.is_err());
+
+        let raw = RawBytesCodec::new();
+        assert_eq!(raw.encode(&input).unwrap(), b"libafl");
+        assert_eq!(raw.decode(b"libafl").ok(), Some(input));
+    }
+}
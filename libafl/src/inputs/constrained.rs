@@ -0,0 +1,244 @@
+//! Bytes inputs declaring the bytes they may contain and the bounds of their length.
+//!
+//! A [`ConstrainedBytesInput`] carries its [`ByteConstraints`], for example the digits for a number,
+//! or printable ASCII of at most 16 bytes for a username. The mutators of [`crate::mutators::constrained`]
+//! and the [`crate::generators::ConstrainedBytesGenerator`] only produce inputs within the constraints,
+//! instead of fixing up arbitrary bytes in the harness, which would map many inputs to the same execution.
+use alloc::{string::String, vec::Vec};
+use core::ops::RangeInclusive;
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input},
+};
+
+/// A class of bytes allowed by [`ByteConstraints`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ByteClass {
+    /// `0` to `9`
+    Digits,
+    /// `0` to `9`, `a` to `f` and `A` to `F`
+    HexDigits,
+    /// `a` to `z`
+    Lowercase,
+    /// `A` to `Z`
+    Uppercase,
+    /// `a` to `z`, `A` to `Z` and `0` to `9`
+    Alphanumeric,
+    /// ASCII whitespace
+    Whitespace,
+    /// The printable ASCII characters, including the space
+    Printable,
+    /// All bytes
+    Any,
+    /// The bytes of the range
+    Range(u8, u8),
+    /// The given bytes
+    Bytes(Vec<u8>),
+}
+
+impl ByteClass {
+    /// Returns `true` if the class contains the byte
+    #[must_use]
+    pub fn contains(&self, byte: u8) -> bool {
+        match self {
+            Self::Digits => byte.is_ascii_digit(),
+            Self::HexDigits => byte.is_ascii_hexdigit(),
+            Self::Lowercase => byte.is_ascii_lowercase(),
+            Self::Uppercase => byte.is_ascii_uppercase(),
+            Self::Alphanumeric => byte.is_ascii_alphanumeric(),
+            Self::Whitespace => byte.is_ascii_whitespace(),
+            Self::Printable => byte.is_ascii_graphic() || byte == b' ',
+            Self::Any => true,
+            Self::Range(start, end) => (*start..=*end).contains(&byte),
+            Self::Bytes(bytes) => bytes.contains(&byte),
+        }
+    }
+}
+
+/// The bytes a [`ConstrainedBytesInput`] may contain and the bounds of its length
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ByteConstraints {
+    /// The allowed bytes, as a bitset
+    allowed: [u64; 4],
+    min_len: usize,
+    max_len: usize,
+}
+
+impl ByteConstraints {
+    /// Creates new [`ByteConstraints`] for lengths in `len`, allowing no bytes yet, see [`Self::with_class`]
+    #[must_use]
+    pub fn new(len: RangeInclusive<usize>) -> Self {
+        Self {
+            allowed: [0; 4],
+            min_len: *len.start(),
+            max_len: *len.end(),
+        }
+    }
+
+    /// Allows the bytes of the given class too
+    #[must_use]
+    pub fn with_class(mut self, class: &ByteClass) -> Self {
+        for byte in 0..=u8::MAX {
+            if class.contains(byte) {
+                self.allowed[usize::from(byte / 64)] |= 1 << (byte % 64);
+            }
+        }
+        self
+    }
+
+    /// Returns `true` if the byte is allowed
+    #[must_use]
+    pub fn allows(&self, byte: u8) -> bool {
+        self.allowed[usize::from(byte / 64)] & (1 << (byte % 64)) != 0
+    }
+
+    /// The count of allowed bytes
+    #[must_use]
+    pub fn allowed_count(&self) -> usize {
+        self.allowed
+            .iter()
+            .map(|bits| bits.count_ones() as usize)
+            .sum()
+    }
+
+    /// The `n`-th allowed byte, in ascending order
+    #[must_use]
+    pub fn nth_allowed(&self, n: usize) -> Option<u8> {
+        (0..=u8::MAX).filter(|byte| self.allows(*byte)).nth(n)
+    }
+
+    /// The minimum length
+    #[must_use]
+    pub fn min_len(&self) -> usize {
+        self.min_len
+    }
+
+    /// The maximum length
+    #[must_use]
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Returns an error if no input can satisfy the constraints
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.min_len > self.max_len {
+            return Err(Error::illegal_argument(format!(
+                "The minimum length {} is larger than the maximum length {}",
+                self.min_len, self.max_len
+            )));
+        }
+        if self.max_len > 0 && self.allowed_count() == 0 {
+            return Err(Error::illegal_argument("No bytes are allowed"));
+        }
+        Ok(())
+    }
+
+    /// Returns an error if the bytes do not satisfy the constraints
+    pub fn check(&self, bytes: &[u8]) -> Result<(), Error> {
+        if !(self.min_len..=self.max_len).contains(&bytes.len()) {
+            return Err(Error::illegal_argument(format!(
+                "The length {} is not between {} and {}",
+                bytes.len(),
+                self.min_len,
+                self.max_len
+            )));
+        }
+        if let Some(pos) = bytes.iter().position(|byte| !self.allows(*byte)) {
+            return Err(Error::illegal_argument(format!(
+                "The byte {:#04x} at {pos} is not allowed",
+                bytes[pos]
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Bytes within [`ByteConstraints`], see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConstrainedBytesInput {
+    bytes: Vec<u8>,
+    constraints: ByteConstraints,
+}
+
+impl Input for ConstrainedBytesInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(&self.bytes);
+        format!("{hash:016x}")
+    }
+}
+
+impl HasTargetBytes for ConstrainedBytesInput {
+    #[inline]
+    fn target_bytes(&self) -> OwnedSlice<'_, u8> {
+        OwnedSlice::from(&self.bytes)
+    }
+}
+
+impl HasLen for ConstrainedBytesInput {
+    #[inline]
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl ConstrainedBytesInput {
+    /// Creates a new [`ConstrainedBytesInput`], returning an error if the bytes do not satisfy the constraints
+    pub fn new(bytes: Vec<u8>, constraints: ByteConstraints) -> Result<Self, Error> {
+        constraints.validate()?;
+        constraints.check(&bytes)?;
+        Ok(Self { bytes, constraints })
+    }
+
+    /// The bytes
+    #[must_use]
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Sets the bytes, returning an error and keeping the old ones if they do not satisfy the constraints
+    pub fn set_bytes(&mut self, bytes: Vec<u8>) -> Result<(), Error> {
+        self.constraints.check(&bytes)?;
+        self.bytes = bytes;
+        Ok(())
+    }
+
+    /// The constraints of the bytes
+    #[must_use]
+    pub fn constraints(&self) -> &ByteConstraints {
+        &self.constraints
+    }
+
+    /// The bytes and the constraints, for the mutators keeping the bytes within the constraints
+    pub(crate) fn bytes_and_constraints_mut(&mut self) -> (&mut Vec<u8>, &ByteConstraints) {
+        (&mut self.bytes, &self.constraints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ByteClass, ByteConstraints, ConstrainedBytesInput};
+
+    #[test]
+    fn test_constrained_bytes_input() {
+        let constraints = ByteConstraints::new(1..=4)
+            .with_class(&ByteClass::Digits)
+            .with_class(&ByteClass::Bytes(b"-".to_vec()));
+        assert_eq!(constraints.allowed_count(), 11);
+        assert_eq!(constraints.nth_allowed(0), Some(b'-'));
+        assert_eq!(constraints.nth_allowed(10), Some(b'9'));
+
+        assert!(ConstrainedBytesInput::new(b"-42".to_vec(), constraints.clone()).is_ok());
+        assert!(ConstrainedBytesInput::new(b"4a".to_vec(), constraints.clone()).is_err());
+        assert!(ConstrainedBytesInput::new(b"12345".to_vec(), constraints.clone()).is_err());
+        assert!(ConstrainedBytesInput::new(vec![], constraints).is_err());
+
+        assert!(ByteConstraints::new(1..=4).validate().is_err());
+        assert!(ByteConstraints::new(0..=0).validate().is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+//! Directed graphs with a payload per node, for fuzzing graph processors, schedulers and query planners.
+//!
+//! A [`GraphInput`] refers to its nodes by index. The edges are kept sorted and without duplicates,
+//! so that equal graphs serialize to the same bytes in the corpus, and for the target with [`GraphInput::to_bytes`].
+//! See the graph mutators in [`crate::mutators::graph`].
+use alloc::{string::String, vec::Vec};
+
+use ahash::RandomState;
+use libafl_bolts::{Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{corpus::CorpusId, inputs::Input};
+
+/// A directed graph with a payload of bytes per node, see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GraphInput {
+    nodes: Vec<Vec<u8>>,
+    /// The edges, as `(from, to)` indices of the nodes, sorted and without duplicates
+    edges: Vec<(u32, u32)>,
+}
+
+impl Input for GraphInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(self);
+        format!("{hash:016x}")
+    }
+}
+
+impl HasLen for GraphInput {
+    /// The count of nodes
+    fn len(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl GraphInput {
+    /// Creates a new [`GraphInput`] with the given payloads of the nodes, and the edges between them.
+    ///
+    /// Returns an error if an edge references a node that does not exist.
+    pub fn new(nodes: Vec<Vec<u8>>, edges: Vec<(u32, u32)>) -> Result<Self, Error> {
+        let mut graph = Self {
+            nodes,
+            edges: Vec::with_capacity(edges.len()),
+        };
+        for (from, to) in edges {
+            if !graph.add_edge(from, to)? {
+                log::debug!("Ignoring the duplicate edge {from} -> {to}");
+            }
+        }
+        Ok(graph)
+    }
+
+    /// The payloads of the nodes
+    #[must_use]
+    pub fn nodes(&self) -> &[Vec<u8>] {
+        &self.nodes
+    }
+
+    /// The payload of the node, mutable
+    #[must_use]
+    pub fn payload_mut(&mut self, node: u32) -> Option<&mut Vec<u8>> {
+        self.nodes.get_mut(node as usize)
+    }
+
+    /// The edges, as `(from, to)` indices of the nodes, sorted
+    #[must_use]
+    pub fn edges(&self) -> &[(u32, u32)] {
+        &self.edges
+    }
+
+    /// The nodes the node has an edge to
+    pub fn successors(&self, node: u32) -> impl Iterator<Item = u32> + '_ {
+        let start = self.edges.partition_point(|(from, _)| *from < node);
+        self.edges[start..]
+            .iter()
+            .take_while(move |(from, _)| *from == node)
+            .map(|(_, to)| *to)
+    }
+
+    /// Adds a node with the given payload, and returns its index
+    pub fn add_node(&mut self, payload: Vec<u8>) -> u32 {
+        self.nodes.push(payload);
+        u32::try_from(self.nodes.len() - 1).expect("Too many nodes")
+    }
+
+    /// Removes the node and its edges, and returns its payload.
+    ///
+    /// The nodes after it move down by one index.
+    pub fn remove_node(&mut self, node: u32) -> Result<Vec<u8>, Error> {
+        self.check_node(node)?;
+        let payload = self.nodes.remove(node as usize);
+        self.edges.retain(|(from, to)| *from != node && *to != node);
+        // shifting the indices down keeps the edges sorted
+        let shift = |index: u32| if index > node { index - 1 } else { index };
+        for (from, to) in &mut self.edges {
+            *from = shift(*from);
+            *to = shift(*to);
+        }
+        Ok(payload)
+    }
+
+    /// Adds an edge, and returns `false` if it already exists
+    pub fn add_edge(&mut self, from: u32, to: u32) -> Result<bool, Error> {
+        self.check_node(from)?;
+        self.check_node(to)?;
+        match self.edges.binary_search(&(from, to)) {
+            Ok(_) => Ok(false),
+            Err(pos) => {
+                self.edges.insert(pos, (from, to));
+                Ok(true)
+            }
+        }
+    }
+
+    /// Removes an edge, and returns `false` if it does not exist
+    pub fn remove_edge(&mut self, from: u32, to: u32) -> bool {
+        match self.edges.binary_search(&(from, to)) {
+            Ok(pos) => {
+                self.edges.remove(pos);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Serializes the graph deterministically for the target: equal graphs have the same bytes.
+    ///
+    /// The format is the count of nodes, the length of each payload, the count of edges,
+    /// the indices of the nodes of each edge, all as `u32` little endian, and then the payloads.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut push = |value: usize| {
+            bytes.extend_from_slice(&u32::try_from(value).unwrap().to_le_bytes());
+        };
+        push(self.nodes.len());
+        for payload in &self.nodes {
+            push(payload.len());
+        }
+        push(self.edges.len());
+        for (from, to) in &self.edges {
+            push(*from as usize);
+            push(*to as usize);
+        }
+        for payload in &self.nodes {
+            bytes.extend_from_slice(payload);
+        }
+        bytes
+    }
+
+    fn check_node(&self, node: u32) -> Result<(), Error> {
+        if (node as usize) < self.nodes.len() {
+            Ok(())
+        } else {
+            Err(Error::illegal_argument(format!(
+                "The node {node} does not exist, the graph has {} nodes",
+                self.nodes.len()
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::GraphInput;
+
+    #[test]
+    fn test_graph_input() {
+        let mut graph = GraphInput::new(
+            vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()],
+            vec![(2, 0), (0, 1), (0, 2), (0, 1)],
+        )
+        .unwrap();
+        assert_eq!(graph.edges(), [(0, 1), (0, 2), (2, 0)]);
+        assert_eq!(graph.successors(0).collect::<Vec<_>>(), [1, 2]);
+        assert!(graph.add_edge(0, 3).is_err());
+
+        // the same graph, built in another order, serializes the same
+        let mut other = GraphInput::default();
+        for payload in [b"a", b"b", b"c"] {
+            other.add_node(payload.to_vec());
+        }
+        for (from, to) in [(0, 2), (2, 0), (0, 1)] {
+            assert!(other.add_edge(from, to).unwrap());
+        }
+        assert_eq!(graph.to_bytes(), other.to_bytes());
+        assert_eq!(
+            postcard::to_allocvec(&graph).unwrap(),
+            postcard::to_allocvec(&other).unwrap()
+        );
+
+        assert_eq!(graph.remove_node(1).unwrap(), b"b");
+        assert_eq!(graph.nodes(), [b"a".to_vec(), b"c".to_vec()]);
+        assert_eq!(graph.edges(), [(0, 1), (1, 0)]);
+    }
+}
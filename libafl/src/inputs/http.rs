@@ -0,0 +1,274 @@
+//! A structured HTTP request input, for fuzzing web services without breaking the framing of the requests.
+//!
+//! The [`HttpRequestInput`] keeps the method, the path segments, the headers and the body apart,
+//! so that the mutators of [`crate::mutators::http`] change one component at a time,
+//! and it is serialized to a well-formed request by [`HttpRequestInput::to_bytes`].
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{HasTargetBytes, Input, MutVecInput},
+};
+
+/// The HTTP version of the serialized requests
+pub const HTTP_VERSION: &[u8] = b"HTTP/1.1";
+
+/// An HTTP request, see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HttpRequestInput {
+    method: Vec<u8>,
+    path: Vec<Vec<u8>>,
+    headers: Vec<(Vec<u8>, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl Input for HttpRequestInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(self);
+        format!("{hash:016x}")
+    }
+}
+
+impl HasTargetBytes for HttpRequestInput {
+    fn target_bytes(&self) -> OwnedSlice<'_, u8> {
+        OwnedSlice::from(self.to_bytes())
+    }
+}
+
+impl HasLen for HttpRequestInput {
+    /// The length of the serialized request
+    fn len(&self) -> usize {
+        self.to_bytes().len()
+    }
+}
+
+impl HttpRequestInput {
+    /// Creates a new [`HttpRequestInput`] with the given method, for example `GET`, and path, for example `/index.html`
+    #[must_use]
+    pub fn new(method: &[u8], path: &[u8]) -> Self {
+        Self {
+            method: method.to_vec(),
+            path: split_path(path),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Parses a raw HTTP request, for example a seed captured from a client.
+    ///
+    /// The body is everything after the headers. The `Content-Length` header is dropped,
+    /// to be computed from the body when serializing, see [`Self::write_bytes`].
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let mut lines = bytes
+            .split(|b| *b == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+        let request_line = lines
+            .next()
+            .ok_or_else(|| Error::illegal_argument("Empty HTTP request"))?;
+        let mut parts = request_line.split(|b| *b == b' ');
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(Error::illegal_argument("Invalid HTTP request line"));
+        };
+        let mut input = Self::new(method, target);
+
+        for line in lines {
+            if line.is_empty() {
+                break;
+            }
+            let colon = line
+                .iter()
+                .position(|b| *b == b':')
+                .ok_or_else(|| Error::illegal_argument("Invalid HTTP header line"))?;
+            let name = &line[..colon];
+            if !name.eq_ignore_ascii_case(b"Content-Length") {
+                input.add_header(name, line[colon + 1..].trim_ascii_start());
+            }
+        }
+        // the lines may end with `\r\n` or `\n`
+        input.body = find_subslice(bytes, b"\r\n\r\n")
+            .map(|pos| pos + 4)
+            .or_else(|| find_subslice(bytes, b"\n\n").map(|pos| pos + 2))
+            .map_or_else(Vec::new, |start| bytes[start.min(bytes.len())..].to_vec());
+        Ok(input)
+    }
+
+    /// The method, for example `GET`
+    #[must_use]
+    pub fn method(&self) -> &[u8] {
+        &self.method
+    }
+
+    /// The method, mutable
+    #[must_use]
+    pub fn method_mut(&mut self) -> &mut Vec<u8> {
+        &mut self.method
+    }
+
+    /// The segments of the path, without the `/` separators
+    #[must_use]
+    pub fn path(&self) -> &[Vec<u8>] {
+        &self.path
+    }
+
+    /// The segments of the path, mutable
+    #[must_use]
+    pub fn path_mut(&mut self) -> &mut Vec<Vec<u8>> {
+        &mut self.path
+    }
+
+    /// The headers, as names and values, in order
+    #[must_use]
+    pub fn headers(&self) -> &[(Vec<u8>, Vec<u8>)] {
+        &self.headers
+    }
+
+    /// The headers, mutable
+    #[must_use]
+    pub fn headers_mut(&mut self) -> &mut Vec<(Vec<u8>, Vec<u8>)> {
+        &mut self.headers
+    }
+
+    /// The value of the first header with the given name, ignoring the case
+    #[must_use]
+    pub fn header(&self, name: &[u8]) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Adds a header, after the existing ones
+    pub fn add_header(&mut self, name: &[u8], value: &[u8]) {
+        self.headers.push((name.to_vec(), value.to_vec()));
+    }
+
+    /// Sets the value of the first header with the given name, ignoring the case, or adds it
+    pub fn set_header(&mut self, name: &[u8], value: &[u8]) {
+        if let Some((_, old)) = self
+            .headers
+            .iter_mut()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        {
+            value.clone_into(old);
+        } else {
+            self.add_header(name, value);
+        }
+    }
+
+    /// Removes the headers with the given name, ignoring the case
+    pub fn remove_header(&mut self, name: &[u8]) {
+        self.headers
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+    }
+
+    /// The body
+    #[must_use]
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The body, for the mutators of bytes, see [`crate::mutators::mapped_havoc_mutations`]
+    #[must_use]
+    pub fn body_mut(&mut self) -> MutVecInput<'_> {
+        (&mut self.body).into()
+    }
+
+    /// Sets the body
+    pub fn set_body(&mut self, body: &[u8]) {
+        body.clone_into(&mut self.body);
+    }
+
+    /// Serializes the request to `bytes`, cleared first.
+    ///
+    /// A `Content-Length` header is added if the body is not empty,
+    /// and neither a `Content-Length` nor a `Transfer-Encoding` header is set.
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.clear();
+        bytes.extend_from_slice(&self.method);
+        bytes.push(b' ');
+        if self.path.is_empty() {
+            bytes.push(b'/');
+        }
+        for segment in &self.path {
+            bytes.push(b'/');
+            bytes.extend_from_slice(segment);
+        }
+        bytes.push(b' ');
+        bytes.extend_from_slice(HTTP_VERSION);
+        bytes.extend_from_slice(b"\r\n");
+
+        for (name, value) in &self.headers {
+            bytes.extend_from_slice(name);
+            bytes.extend_from_slice(b": ");
+            bytes.extend_from_slice(value);
+            bytes.extend_from_slice(b"\r\n");
+        }
+        if !self.body.is_empty()
+            && self.header(b"Content-Length").is_none()
+            && self.header(b"Transfer-Encoding").is_none()
+        {
+            bytes.extend_from_slice(format!("Content-Length: {}\r\n", self.body.len()).as_bytes());
+        }
+        bytes.extend_from_slice(b"\r\n");
+        bytes.extend_from_slice(&self.body);
+    }
+
+    /// Serializes the request, see [`Self::write_bytes`]
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_bytes(&mut bytes);
+        bytes
+    }
+}
+
+/// Splits a path to its segments
+fn split_path(path: &[u8]) -> Vec<Vec<u8>> {
+    let path = path.strip_prefix(b"/").unwrap_or(path);
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        path.split(|b| *b == b'/').map(<[u8]>::to_vec).collect()
+    }
+}
+
+fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpRequestInput;
+
+    #[test]
+    fn test_http_roundtrip() {
+        let mut input = HttpRequestInput::new(b"POST", b"/api/v1/users");
+        input.add_header(b"Host", b"localhost");
+        input.set_body(b"{\"name\":\"a\"}");
+        let bytes = input.to_bytes();
+        assert_eq!(
+            bytes,
+            b"POST /api/v1/users HTTP/1.1\r\nHost: localhost\r\nContent-Length: 12\r\n\r\n{\"name\":\"a\"}"
+        );
+
+        let parsed = HttpRequestInput::parse(&bytes).unwrap();
+        assert_eq!(parsed.method(), b"POST");
+        assert_eq!(parsed.path().len(), 3);
+        assert_eq!(parsed.header(b"host"), Some(&b"localhost"[..]));
+        assert_eq!(parsed.header(b"content-length"), None);
+        assert_eq!(parsed.body(), input.body());
+        assert_eq!(parsed.to_bytes(), bytes);
+
+        assert_eq!(
+            HttpRequestInput::new(b"GET", b"/").to_bytes(),
+            b"GET / HTTP/1.1\r\n\r\n"
+        );
+    }
+}
@@ -6,12 +6,37 @@ pub use bytes::BytesInput;
 pub mod encoded;
 pub use encoded::*;
 
+#[cfg(feature = "std")]
+pub mod codec;
+#[cfg(feature = "std")]
+pub use codec::*;
+
 pub mod gramatron;
 pub use gramatron::*;
 
 pub mod generalized;
 pub use generalized::*;
 
+pub mod syscalls;
+pub use syscalls::*;
+
+pub mod http;
+pub use http::*;
+
+pub mod token_stream;
+pub use token_stream::*;
+
+pub mod constrained;
+pub use constrained::*;
+
+pub mod graph;
+pub use graph::*;
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "arbitrary")]
+pub use self::arbitrary::*;
+
 pub mod bytessub;
 pub use bytessub::BytesSubInput;
 
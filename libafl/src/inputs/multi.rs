@@ -10,9 +10,13 @@ use alloc::{
 };
 
 use arrayvec::ArrayVec;
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice};
 use serde::{Deserialize, Serialize};
 
-use crate::{corpus::CorpusId, inputs::Input};
+use crate::{
+    corpus::CorpusId,
+    inputs::{Input, TargetBytesConverter},
+};
 
 /// An input composed of multiple parts. Use in situations where subcomponents are not necessarily
 /// related, or represent distinct parts of the input.
@@ -163,3 +167,60 @@ where
             .join(",")
     }
 }
+
+/// Converts a [`MultipartInput`] to target bytes, framing the parts for delivery to the target.
+///
+/// Each part is converted by the inner [`TargetBytesConverter`], and prefixed with its length as little-endian `u32`.
+/// The target reads the parts in order, until the input is exhausted.
+#[derive(Debug, Default, Clone)]
+pub struct MultipartTargetBytesConverter<TC> {
+    inner: TC,
+}
+
+impl<TC> MultipartTargetBytesConverter<TC> {
+    /// Create a new [`MultipartTargetBytesConverter`], converting each part with `inner`
+    #[must_use]
+    pub fn new(inner: TC) -> Self {
+        Self { inner }
+    }
+}
+
+impl<TC> TargetBytesConverter for MultipartTargetBytesConverter<TC>
+where
+    TC: TargetBytesConverter,
+{
+    type Input = MultipartInput<TC::Input>;
+
+    fn to_target_bytes<'a>(&mut self, input: &'a Self::Input) -> OwnedSlice<'a, u8> {
+        let mut bytes = Vec::new();
+        for part in input.parts() {
+            let part_bytes = self.inner.to_target_bytes(part);
+            let part_bytes = part_bytes.as_slice();
+            let len = u32::try_from(part_bytes.len()).expect("Multipart input part exceeds 4GiB");
+            bytes.extend_from_slice(&len.to_le_bytes());
+            bytes.extend_from_slice(part_bytes);
+        }
+        OwnedSlice::from(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::AsSlice;
+
+    use super::{MultipartInput, MultipartTargetBytesConverter};
+    use crate::inputs::{BytesInput, NopTargetBytesConverter, TargetBytesConverter};
+
+    #[test]
+    fn test_multipart_target_bytes() {
+        let input = MultipartInput::from([
+            ("header", BytesInput::new(vec![1, 2])),
+            ("body", BytesInput::new(vec![3])),
+        ]);
+        let mut converter = MultipartTargetBytesConverter::new(NopTargetBytesConverter::new());
+        assert_eq!(
+            converter.to_target_bytes(&input).as_slice(),
+            &[2, 0, 0, 0, 1, 2, 1, 0, 0, 0, 3]
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Sequences of syscalls, or of calls to any API, for kernel and API fuzzing.
+//!
+//! Like [syzkaller](https://github.com/google/syzkaller), a [`SyscallSequenceInput`] is a list of typed calls,
+//! described by a [`SyscallTable`]. Arguments may reference the result of a prior call of the sequence,
+//! such as a file descriptor returned by `open`, so that the calls operate on the same resources.
+//! See the [`SyscallSequenceGenerator`](crate::generators::SyscallSequenceGenerator) and the syscall mutators.
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use ahash::RandomState;
+use libafl_bolts::HasLen;
+use serde::{Deserialize, Serialize};
+
+use crate::{corpus::CorpusId, inputs::Input};
+
+/// The value of a resource argument, when there is no prior call producing the resource, `-1`
+pub const INVALID_RESOURCE: u64 = u64::MAX;
+
+/// The type of an argument of a syscall
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyscallArgType {
+    /// An integer of the given count of bits
+    Int {
+        /// The count of bits, up to 64
+        bits: u32,
+    },
+    /// An integer in the given range, the end included
+    Range {
+        /// The smallest value
+        min: u64,
+        /// The largest value
+        max: u64,
+    },
+    /// A combination of the given flags
+    Flags(Vec<u64>),
+    /// A buffer of bytes, passed by pointer
+    Buffer {
+        /// The maximum length of the buffer
+        max_len: usize,
+    },
+    /// A resource, returned by a prior call
+    Resource {
+        /// The kind of the resource, the same as the [`SyscallDescription::ret`] of the producing calls
+        kind: String,
+    },
+}
+
+/// The description of a syscall
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SyscallDescription {
+    /// The name, for printing
+    pub name: String,
+    /// The syscall number, or any identifier of the call for the harness
+    pub nr: u64,
+    /// The types of the arguments
+    pub args: Vec<SyscallArgType>,
+    /// The kind of resource this syscall returns, if any
+    pub ret: Option<String>,
+}
+
+impl SyscallDescription {
+    /// Creates a new [`SyscallDescription`]
+    #[must_use]
+    pub fn new(name: &str, nr: u64, args: Vec<SyscallArgType>, ret: Option<&str>) -> Self {
+        Self {
+            name: name.into(),
+            nr,
+            args,
+            ret: ret.map(Into::into),
+        }
+    }
+}
+
+/// The syscalls a [`SyscallSequenceInput`] can use
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SyscallTable {
+    /// The syscalls, a [`SyscallCall`] refers to them by index
+    pub syscalls: Vec<SyscallDescription>,
+}
+
+impl SyscallTable {
+    /// Creates a new [`SyscallTable`]
+    #[must_use]
+    pub fn new(syscalls: Vec<SyscallDescription>) -> Self {
+        Self { syscalls }
+    }
+
+    /// The description of the syscall at index `syscall`
+    #[must_use]
+    pub fn get(&self, syscall: usize) -> Option<&SyscallDescription> {
+        self.syscalls.get(syscall)
+    }
+
+    /// The kind of resource the `call` returns, if any
+    #[must_use]
+    pub fn ret_kind(&self, call: &SyscallCall) -> Option<&str> {
+        self.get(call.syscall)?.ret.as_deref()
+    }
+
+    /// The indices of the `calls` returning a resource of the given `kind`
+    pub fn producers<'a>(
+        &'a self,
+        kind: &'a str,
+        calls: &'a [SyscallCall],
+    ) -> impl Iterator<Item = usize> + 'a {
+        calls
+            .iter()
+            .enumerate()
+            .filter(move |(_, call)| self.ret_kind(call) == Some(kind))
+            .map(|(i, _)| i)
+    }
+}
+
+/// The value of an argument of a [`SyscallCall`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SyscallArg {
+    /// An integer
+    Int(u64),
+    /// A buffer of bytes
+    Buffer(Vec<u8>),
+    /// The result of the call at this index of the sequence, always before the call using it
+    Result(usize),
+}
+
+impl SyscallArg {
+    /// The integer value of this argument, given the `results` of the prior calls.
+    /// Returns [`None`] for a buffer, or if the result is not known.
+    #[must_use]
+    pub fn value(&self, results: &[u64]) -> Option<u64> {
+        match self {
+            SyscallArg::Int(value) => Some(*value),
+            SyscallArg::Buffer(_) => None,
+            SyscallArg::Result(call) => results.get(*call).copied(),
+        }
+    }
+}
+
+/// A call in a [`SyscallSequenceInput`]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SyscallCall {
+    /// The index of the syscall in the [`SyscallTable`]
+    pub syscall: usize,
+    /// The arguments, one for each argument type of the syscall
+    pub args: Vec<SyscallArg>,
+}
+
+impl SyscallCall {
+    /// Creates a new [`SyscallCall`]
+    #[must_use]
+    pub fn new(syscall: usize, args: Vec<SyscallArg>) -> Self {
+        Self { syscall, args }
+    }
+}
+
+/// An input for syscall sequence fuzzing. See the [module-level documentation](self).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SyscallSequenceInput {
+    calls: Vec<SyscallCall>,
+}
+
+impl Input for SyscallSequenceInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(&self.calls);
+        format!("{hash:016x}")
+    }
+}
+
+impl HasLen for SyscallSequenceInput {
+    #[inline]
+    fn len(&self) -> usize {
+        self.calls.len()
+    }
+}
+
+impl SyscallSequenceInput {
+    /// Creates a new [`SyscallSequenceInput`] with the given calls
+    #[must_use]
+    pub fn new(calls: Vec<SyscallCall>) -> Self {
+        Self { calls }
+    }
+
+    /// The calls of this input
+    #[must_use]
+    pub fn calls(&self) -> &[SyscallCall] {
+        &self.calls
+    }
+
+    /// The calls of this input, mutable.
+    ///
+    /// The [`SyscallArg::Result`] arguments must keep referencing prior calls,
+    /// prefer [`Self::insert_call`] and [`Self::remove_call`] to change the sequence.
+    #[must_use]
+    pub fn calls_mut(&mut self) -> &mut Vec<SyscallCall> {
+        &mut self.calls
+    }
+
+    /// Inserts a call at `index`, updating the references to the following calls
+    pub fn insert_call(&mut self, index: usize, call: SyscallCall) {
+        for later in &mut self.calls[index..] {
+            for arg in &mut later.args {
+                if let SyscallArg::Result(referenced) = arg {
+                    if *referenced >= index {
+                        *referenced += 1;
+                    }
+                }
+            }
+        }
+        self.calls.insert(index, call);
+    }
+
+    /// Removes the call at `index`, updating the references to the following calls.
+    /// The references to the removed call become [`INVALID_RESOURCE`].
+    pub fn remove_call(&mut self, index: usize) -> SyscallCall {
+        let call = self.calls.remove(index);
+        for later in &mut self.calls[index..] {
+            for arg in &mut later.args {
+                if let SyscallArg::Result(referenced) = arg {
+                    if *referenced == index {
+                        *arg = SyscallArg::Int(INVALID_RESOURCE);
+                    } else if *referenced > index {
+                        *referenced -= 1;
+                    }
+                }
+            }
+        }
+        call
+    }
+
+    /// Displays this input in a readable form, with the syscall names of the `table`, for example
+    /// ```text
+    /// r0 = open(b"/tmp/a", 0x42)
+    /// write(r0, b"hello", 0x5)
+    /// close(r0)
+    /// ```
+    #[must_use]
+    pub fn display<'a>(&'a self, table: &'a SyscallTable) -> SyscallSequenceDisplay<'a> {
+        SyscallSequenceDisplay { input: self, table }
+    }
+}
+
+/// Pretty-printer of a [`SyscallSequenceInput`], see [`SyscallSequenceInput::display`]
+#[derive(Debug, Clone, Copy)]
+pub struct SyscallSequenceDisplay<'a> {
+    input: &'a SyscallSequenceInput,
+    table: &'a SyscallTable,
+}
+
+impl fmt::Display for SyscallSequenceDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, call) in self.input.calls.iter().enumerate() {
+            let description = self.table.get(call.syscall);
+            if description.is_some_and(|description| description.ret.is_some()) {
+                write!(f, "r{i} = ")?;
+            }
+            if let Some(description) = description {
+                write!(f, "{}(", description.name)?;
+            } else {
+                write!(f, "syscall_{}(", call.syscall)?;
+            }
+            for (j, arg) in call.args.iter().enumerate() {
+                if j > 0 {
+                    write!(f, ", ")?;
+                }
+                match arg {
+                    SyscallArg::Int(value) => write!(f, "{value:#x}")?,
+                    SyscallArg::Buffer(buf) => write!(f, "b\"{}\"", buf.escape_ascii())?,
+                    SyscallArg::Result(call) => write!(f, "r{call}")?,
+                }
+            }
+            writeln!(f, ")")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec, vec::Vec};
+
+    use super::{
+        SyscallArg, SyscallArgType, SyscallCall, SyscallDescription, SyscallSequenceInput,
+        SyscallTable, INVALID_RESOURCE,
+    };
+
+    fn table() -> SyscallTable {
+        SyscallTable::new(vec![
+            SyscallDescription::new(
+                "open",
+                2,
+                vec![
+                    SyscallArgType::Buffer { max_len: 16 },
+                    SyscallArgType::Flags(vec![1, 2, 0x40]),
+                ],
+                Some("fd"),
+            ),
+            SyscallDescription::new(
+                "close",
+                3,
+                vec![SyscallArgType::Resource { kind: "fd".into() }],
+                None,
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_display() {
+        let input = SyscallSequenceInput::new(vec![
+            SyscallCall::new(
+                0,
+                vec![
+                    SyscallArg::Buffer(b"/tmp/a".to_vec()),
+                    SyscallArg::Int(0x42),
+                ],
+            ),
+            SyscallCall::new(1, vec![SyscallArg::Result(0)]),
+        ]);
+        assert_eq!(
+            input.display(&table()).to_string(),
+            "r0 = open(b\"/tmp/a\", 0x42)\nclose(r0)\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let open = SyscallCall::new(0, vec![SyscallArg::Buffer(vec![]), SyscallArg::Int(0)]);
+        let mut input = SyscallSequenceInput::new(vec![
+            open.clone(),
+            SyscallCall::new(1, vec![SyscallArg::Result(0)]),
+        ]);
+
+        input.insert_call(0, open);
+        assert_eq!(input.calls()[2].args, vec![SyscallArg::Result(1)]);
+        assert_eq!(
+            table().producers("fd", input.calls()).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+
+        input.remove_call(1);
+        assert_eq!(
+            input.calls()[1].args,
+            vec![SyscallArg::Int(INVALID_RESOURCE)]
+        );
+    }
+}
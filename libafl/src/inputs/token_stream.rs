@@ -0,0 +1,239 @@
+//! Streams of tokens, for fuzzing interpreters and compilers past their lexer.
+//!
+//! A [`TokenStreamInput`] is a sequence of ids into a [`TokenTable`], such as the keywords, operators
+//! and identifiers of a language, so that the token stream mutators never produce a lexer error.
+//! The [`Detokenizer`] turns the tokens back into the bytes for the target, with a configurable separator.
+use alloc::{string::String, vec::Vec};
+
+use ahash::RandomState;
+use hashbrown::HashMap;
+use libafl_bolts::{Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{Input, Tokenizer},
+};
+
+/// The tokens a [`TokenStreamInput`] can use
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TokenTable {
+    /// The tokens, a [`TokenStreamInput`] refers to them by index
+    tokens: Vec<Vec<u8>>,
+    /// The ids of the tokens
+    ids: HashMap<Vec<u8>, u32>,
+}
+
+impl TokenTable {
+    /// Creates a new [`TokenTable`] with the given tokens, ignoring duplicates
+    #[must_use]
+    pub fn new<T>(tokens: impl IntoIterator<Item = T>) -> Self
+    where
+        T: AsRef<[u8]>,
+    {
+        let mut table = Self::default();
+        for token in tokens {
+            table.add(token.as_ref());
+        }
+        table
+    }
+
+    /// Adds a token to the table if it is not in it yet, and returns its id
+    pub fn add(&mut self, token: &[u8]) -> u32 {
+        if let Some(id) = self.ids.get(token) {
+            return *id;
+        }
+        let id = u32::try_from(self.tokens.len()).expect("Too many tokens");
+        self.tokens.push(token.to_vec());
+        self.ids.insert(token.to_vec(), id);
+        id
+    }
+
+    /// The token with the given id
+    #[must_use]
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        self.tokens.get(id as usize).map(Vec::as_slice)
+    }
+
+    /// The id of the given token
+    #[must_use]
+    pub fn id(&self, token: &[u8]) -> Option<u32> {
+        self.ids.get(token).copied()
+    }
+
+    /// The count of tokens
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns `true` if the table has no tokens
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Tokenizes `bytes` with the `tokenizer`, for example a seed, adding the new tokens to the table
+    pub fn tokenize<T>(&mut self, bytes: &[u8], tokenizer: &T) -> Result<TokenStreamInput, Error>
+    where
+        T: Tokenizer,
+    {
+        let ids = tokenizer
+            .tokenize(bytes)?
+            .iter()
+            .map(|token| self.add(token.as_bytes()))
+            .collect();
+        Ok(TokenStreamInput::new(ids))
+    }
+}
+
+/// A sequence of ids into a [`TokenTable`], see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TokenStreamInput {
+    ids: Vec<u32>,
+}
+
+impl Input for TokenStreamInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _id: Option<CorpusId>) -> String {
+        let hash = RandomState::with_seeds(0, 0, 0, 0).hash_one(self);
+        format!("{hash:016x}")
+    }
+}
+
+impl HasLen for TokenStreamInput {
+    /// The count of tokens
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl From<Vec<u32>> for TokenStreamInput {
+    fn from(ids: Vec<u32>) -> Self {
+        Self::new(ids)
+    }
+}
+
+impl TokenStreamInput {
+    /// Creates a new [`TokenStreamInput`] from the ids of its tokens
+    #[must_use]
+    pub fn new(ids: Vec<u32>) -> Self {
+        Self { ids }
+    }
+
+    /// The ids of the tokens
+    #[must_use]
+    pub fn ids(&self) -> &[u32] {
+        &self.ids
+    }
+
+    /// The ids of the tokens, mutable
+    #[must_use]
+    pub fn ids_mut(&mut self) -> &mut Vec<u32> {
+        &mut self.ids
+    }
+}
+
+/// Turns [`TokenStreamInput`]s to the bytes for the target, joining the tokens of a [`TokenTable`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Detokenizer {
+    table: TokenTable,
+    separator: Vec<u8>,
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+}
+
+impl Detokenizer {
+    /// Creates a new [`Detokenizer`] for the tokens of the `table`, separated by a space
+    #[must_use]
+    pub fn new(table: TokenTable) -> Self {
+        Self {
+            table,
+            separator: b" ".to_vec(),
+            prefix: Vec::new(),
+            suffix: Vec::new(),
+        }
+    }
+
+    /// Sets the bytes between two tokens, for example nothing for a tokenizer keeping the whitespace
+    #[must_use]
+    pub fn with_separator(mut self, separator: &[u8]) -> Self {
+        self.separator = separator.to_vec();
+        self
+    }
+
+    /// Sets the bytes before the first token, for example a header
+    #[must_use]
+    pub fn with_prefix(mut self, prefix: &[u8]) -> Self {
+        self.prefix = prefix.to_vec();
+        self
+    }
+
+    /// Sets the bytes after the last token, for example a newline
+    #[must_use]
+    pub fn with_suffix(mut self, suffix: &[u8]) -> Self {
+        self.suffix = suffix.to_vec();
+        self
+    }
+
+    /// The [`TokenTable`] of the tokens
+    #[must_use]
+    pub fn table(&self) -> &TokenTable {
+        &self.table
+    }
+
+    /// Writes the bytes of the input to `bytes`, cleared first.
+    ///
+    /// Returns an error if an id is not in the [`TokenTable`], for example for an input of another table.
+    pub fn write_bytes(&self, input: &TokenStreamInput, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        bytes.clear();
+        bytes.extend_from_slice(&self.prefix);
+        for (i, id) in input.ids().iter().enumerate() {
+            let token = self
+                .table
+                .get(*id)
+                .ok_or_else(|| Error::illegal_argument(format!("Token {id} not in the table")))?;
+            if i > 0 {
+                bytes.extend_from_slice(&self.separator);
+            }
+            bytes.extend_from_slice(token);
+        }
+        bytes.extend_from_slice(&self.suffix);
+        Ok(())
+    }
+
+    /// Returns the bytes of the input, see [`Self::write_bytes`]
+    pub fn to_bytes(&self, input: &TokenStreamInput) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        self.write_bytes(input, &mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Detokenizer, TokenStreamInput, TokenTable};
+
+    #[test]
+    fn test_token_stream() {
+        let mut table = TokenTable::new(["let", "x", "=", "1", ";", "x"]);
+        assert_eq!(table.len(), 5);
+        assert_eq!(table.id(b"="), Some(2));
+        assert_eq!(table.add(b"print"), 5);
+
+        let input = TokenStreamInput::new(vec![0, 1, 2, 3, 4, 5, 1, 4]);
+        let detokenizer = Detokenizer::new(table.clone());
+        assert_eq!(
+            detokenizer.to_bytes(&input).unwrap(),
+            b"let x = 1 ; print x ;"
+        );
+
+        let detokenizer = Detokenizer::new(table)
+            .with_separator(b"")
+            .with_suffix(b"\n");
+        assert_eq!(detokenizer.to_bytes(&input).unwrap(), b"letx=1;printx;\n");
+        assert!(detokenizer
+            .to_bytes(&TokenStreamInput::new(vec![42]))
+            .is_err());
+    }
+}
@@ -1,4 +1,4 @@
-//! Monitors that wrap a base monitor and also log to disk using different formats like `JSON` and `TOML`.
+//! Monitors that wrap a base monitor and also log to disk using different formats like `JSON`, `TOML` and AFL++'s `plot_data`.
 
 use alloc::{string::String, vec::Vec};
 use core::time::Duration;
@@ -11,7 +11,7 @@ use std::{
 use libafl_bolts::{current_time, format_duration_hms, ClientId};
 use serde_json::json;
 
-use crate::monitors::{ClientStats, Monitor, NopMonitor};
+use crate::monitors::{ClientStats, Monitor, NopMonitor, UserStatsValue};
 
 /// Wrap a monitor and log the current state of the monitor into a Toml file.
 #[derive(Debug, Clone)]
@@ -230,3 +230,217 @@ where
         self.base.display(event_msg, sender_id);
     }
 }
+
+/// The header of AFL++'s `plot_data`
+const AFL_PLOT_DATA_HEADER: &str = "# relative_time, cycles_done, cur_item, corpus_count, pending_total, pending_favs, map_size, saved_crashes, saved_hangs, max_depth, execs_per_sec, total_execs, edges_found";
+
+/// Wraps a base monitor and periodically appends rows in the format of AFL++'s `plot_data`,
+/// so `afl-plot` and other tooling built around it work with `LibAFL` campaigns.
+///
+/// The stats of all clients are combined into a single row. The coverage is read from the `edges` user stat.
+/// Clients may report the AFL-specific values as numeric user stats: `cycles_done` and `max_depth` are the maximum
+/// over all clients, `pending_total`, `pending_favs` and `saved_hangs` are summed up. Missing values are written as `0`.
+#[derive(Debug, Clone)]
+pub struct AflPlotDataMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    filename: PathBuf,
+    last_update: Duration,
+    update_interval: Duration,
+}
+
+impl<M> Monitor for AflPlotDataMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        let cur_time = current_time();
+
+        if cur_time.saturating_sub(self.last_update) >= self.update_interval {
+            self.last_update = cur_time;
+            let row = self.plot_row(cur_time);
+            if let Err(err) = self.append(&row) {
+                log::warn!("Could not write to {}: {err}", self.filename.display());
+            }
+        }
+
+        self.base.display(event_msg, sender_id);
+    }
+}
+
+impl<M> AflPlotDataMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create new [`AflPlotDataMonitor`], appending a row every 5 seconds, like AFL++
+    #[must_use]
+    pub fn new<P>(filename: P, base: M) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::with_update_interval(filename, base, Duration::from_secs(5))
+    }
+
+    /// Create new [`AflPlotDataMonitor`] with custom update interval
+    #[must_use]
+    pub fn with_update_interval<P>(filename: P, base: M, update_interval: Duration) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            base,
+            filename: filename.into(),
+            last_update: Duration::ZERO,
+            update_interval,
+        }
+    }
+
+    /// Sums up the numeric user stat `name` of all clients
+    fn user_stats_sum(&self, name: &str) -> u64 {
+        self.user_stats_values(name).sum()
+    }
+
+    /// The maximum of the numeric user stat `name` of all clients
+    fn user_stats_max(&self, name: &str) -> u64 {
+        self.user_stats_values(name).max().unwrap_or(0)
+    }
+
+    fn user_stats_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = u64> + 'a {
+        self.client_stats()
+            .iter()
+            .filter(|client| client.enabled)
+            .filter_map(move |client| match client.get_user_stats(name)?.value() {
+                UserStatsValue::Number(n) => Some(*n),
+                _ => None,
+            })
+    }
+
+    /// Renders the current stats as a `plot_data` row
+    #[allow(clippy::cast_precision_loss)]
+    fn plot_row(&mut self, cur_time: Duration) -> String {
+        // The client with the highest coverage, as `(found, total)` edges
+        let (edges_found, total_edges) = self
+            .client_stats()
+            .iter()
+            .filter(|client| client.enabled)
+            .filter_map(|client| match client.get_user_stats("edges")?.value() {
+                UserStatsValue::Ratio(found, total) => Some((*found, *total)),
+                _ => None,
+            })
+            .max_by_key(|(found, _)| *found)
+            .unwrap_or((0, 0));
+        let map_size = if total_edges == 0 {
+            0.0
+        } else {
+            edges_found as f64 / total_edges as f64 * 100.0
+        };
+
+        format!(
+            "{}, {}, {}, {}, {}, {}, {map_size:.2}%, {}, {}, {}, {:.2}, {}, {edges_found}",
+            cur_time.saturating_sub(self.start_time()).as_secs(),
+            self.user_stats_max("cycles_done"),
+            0,
+            self.corpus_size(),
+            self.user_stats_sum("pending_total"),
+            self.user_stats_sum("pending_favs"),
+            self.objective_size(),
+            self.user_stats_sum("saved_hangs"),
+            self.user_stats_max("max_depth"),
+            self.execs_per_sec(),
+            self.total_execs(),
+        )
+    }
+
+    /// Appends the row, writing the header first if the file is new
+    fn append(&self, row: &str) -> std::io::Result<()> {
+        let mut file = OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&self.filename)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{AFL_PLOT_DATA_HEADER}")?;
+        }
+        writeln!(file, "{row}")
+    }
+}
+
+impl AflPlotDataMonitor<NopMonitor> {
+    /// Create new [`AflPlotDataMonitor`] without a base
+    #[must_use]
+    pub fn nop<P>(filename: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::new(filename, NopMonitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, vec::Vec};
+    use core::time::Duration;
+    use std::{env, fs};
+
+    use libafl_bolts::ClientId;
+
+    use super::{AflPlotDataMonitor, AFL_PLOT_DATA_HEADER};
+    use crate::monitors::{AggregatorOps, Monitor, NopMonitor, UserStats, UserStatsValue};
+
+    #[test]
+    fn test_afl_plot_data_monitor() {
+        let path = env::temp_dir().join(format!("libafl_plot_data_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut monitor =
+            AflPlotDataMonitor::with_update_interval(&path, NopMonitor::new(), Duration::ZERO);
+        monitor.client_stats_insert(ClientId(0));
+        let client = monitor.client_stats_mut_for(ClientId(0));
+        client.update_corpus_size(12);
+        client.update_objective_size(1);
+        client.update_user_stats(
+            Cow::Borrowed("edges"),
+            UserStats::new(UserStatsValue::Ratio(25, 100), AggregatorOps::Avg),
+        );
+        client.update_user_stats(
+            Cow::Borrowed("pending_total"),
+            UserStats::new(UserStatsValue::Number(4), AggregatorOps::Sum),
+        );
+        monitor.display("Testcase", ClientId(0));
+        monitor.display("Testcase", ClientId(0));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], AFL_PLOT_DATA_HEADER);
+        let columns: Vec<&str> = lines[1].split(", ").collect();
+        assert_eq!(columns.len(), 13);
+        assert_eq!(columns[3], "12");
+        assert_eq!(columns[4], "4");
+        assert_eq!(columns[6], "25.00%");
+        assert_eq!(columns[7], "1");
+        assert_eq!(columns[12], "25");
+    }
+}
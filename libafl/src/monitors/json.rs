@@ -0,0 +1,217 @@
+//! The [`JsonMonitor`] appends the fuzzer stats as JSON lines to a file, or prints them to `stdout`,
+//! for external tools to tail the campaign.
+//!
+//! Each stats update produces one `global` record, followed by one `client` record for the client that sent the update.
+//! All records carry the [`JSON_MONITOR_SCHEMA_VERSION`] in their `schema` field,
+//! which is bumped whenever fields are removed or change their meaning. New fields may be added at any time.
+//!
+//! ```json
+//! {"schema":1,"type":"global","event":"Testcase","timestamp":1700000000.5,"run_time":12,"clients":2,"corpus":40,"objectives":0,"executions":51200,"exec_sec":4266.6}
+//! {"schema":1,"type":"client","event":"Testcase","timestamp":1700000000.5,"run_time":12,"client":1,"corpus":21,"objectives":0,"executions":26000,"exec_sec":2166.6,"user_stats":{"edges":12.5}}
+//! ```
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use libafl_bolts::{current_time, ClientId};
+use serde_json::{json, Map, Value};
+
+use crate::monitors::{ClientStats, Monitor, NopMonitor};
+
+/// The version of the records written by the [`JsonMonitor`]
+pub const JSON_MONITOR_SCHEMA_VERSION: u32 = 1;
+
+/// Wraps a base monitor and writes one JSON line per stats update, globally and for the updated client.
+#[derive(Debug, Clone)]
+pub struct JsonMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    /// The file to append to, or `None` for `stdout`
+    path: Option<PathBuf>,
+}
+
+impl<M> Monitor for JsonMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.base.display(event_msg, sender_id);
+
+        let records = self.records(event_msg, sender_id);
+        if let Err(err) = self.write(&records) {
+            log::warn!("Could not write the JSON stats: {err}");
+        }
+    }
+}
+
+impl<M> JsonMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`JsonMonitor`], appending to the file at `path`
+    pub fn new<P>(path: P, base: M) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            base,
+            path: Some(path.into()),
+        }
+    }
+
+    /// Create a new [`JsonMonitor`], printing to `stdout`
+    #[must_use]
+    pub fn stdout(base: M) -> Self {
+        Self { base, path: None }
+    }
+
+    /// The records of a stats update of the client `sender_id`
+    fn records(&mut self, event_msg: &str, sender_id: ClientId) -> Vec<Value> {
+        let cur_time = current_time();
+        let timestamp = cur_time.as_secs_f64();
+        let run_time = cur_time.saturating_sub(self.start_time()).as_secs();
+
+        let mut records = vec![json!({
+            "schema": JSON_MONITOR_SCHEMA_VERSION,
+            "type": "global",
+            "event": event_msg,
+            "timestamp": timestamp,
+            "run_time": run_time,
+            "clients": self.client_stats_count(),
+            "corpus": self.corpus_size(),
+            "objectives": self.objective_size(),
+            "executions": self.total_execs(),
+            "exec_sec": self.execs_per_sec(),
+        })];
+
+        if (sender_id.0 as usize) < self.client_stats().len() {
+            let client = self.base.client_stats_mut_for(sender_id);
+            let user_stats: Map<String, Value> = client
+                .user_monitor
+                .iter()
+                .map(|(name, stat)| {
+                    let value = stat
+                        .value()
+                        .as_metric()
+                        .map_or_else(|| Value::from(stat.value().to_string()), Value::from);
+                    (name.to_string(), value)
+                })
+                .collect();
+            records.push(json!({
+                "schema": JSON_MONITOR_SCHEMA_VERSION,
+                "type": "client",
+                "event": event_msg,
+                "timestamp": timestamp,
+                "run_time": run_time,
+                "client": sender_id.0,
+                "corpus": client.corpus_size,
+                "objectives": client.objective_size,
+                "executions": client.executions,
+                "exec_sec": client.execs_per_sec(cur_time),
+                "user_stats": user_stats,
+            }));
+        }
+        records
+    }
+
+    /// Writes the records, one per line
+    fn write(&self, records: &[Value]) -> io::Result<()> {
+        let mut lines = String::new();
+        for record in records {
+            lines.push_str(&record.to_string());
+            lines.push('\n');
+        }
+        match &self.path {
+            Some(path) => OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)?
+                .write_all(lines.as_bytes()),
+            None => io::stdout().lock().write_all(lines.as_bytes()),
+        }
+    }
+}
+
+impl JsonMonitor<NopMonitor> {
+    /// Create a new [`JsonMonitor`] without a base, appending to the file at `path`
+    pub fn nop<P>(path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self::new(path, NopMonitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, vec::Vec};
+    use std::{env, fs};
+
+    use libafl_bolts::ClientId;
+
+    use super::{JsonMonitor, JSON_MONITOR_SCHEMA_VERSION};
+    use crate::monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue};
+
+    #[test]
+    fn test_json_monitor() {
+        let path =
+            env::temp_dir().join(format!("libafl_json_monitor_{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut monitor = JsonMonitor::nop(&path);
+        monitor.client_stats_insert(ClientId(0));
+        let client = monitor.client_stats_mut_for(ClientId(0));
+        client.update_corpus_size(3);
+        client.update_user_stats(
+            Cow::Borrowed("edges"),
+            UserStats::new(UserStatsValue::Ratio(1, 8), AggregatorOps::Avg),
+        );
+        monitor.display("Testcase", ClientId(0));
+        monitor.display("Testcase", ClientId(0));
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let records: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0]["schema"], JSON_MONITOR_SCHEMA_VERSION);
+        assert_eq!(records[0]["type"], "global");
+        assert_eq!(records[0]["corpus"], 3);
+        assert_eq!(records[1]["type"], "client");
+        assert_eq!(records[1]["client"], 0);
+        assert_eq!(records[1]["event"], "Testcase");
+        assert_eq!(records[1]["user_stats"]["edges"], 12.5);
+    }
+}
@@ -12,14 +12,34 @@ use alloc::string::ToString;
 
 #[cfg(all(feature = "prometheus_monitor", feature = "std"))]
 pub use prometheus::PrometheusMonitor;
+#[cfg(feature = "webhook_monitor")]
+pub mod webhook;
+#[cfg(feature = "webhook_monitor")]
+pub use webhook::{WebhookConfig, WebhookMonitor};
+#[cfg(feature = "otel_monitor")]
+pub mod otel;
+#[cfg(feature = "otel_monitor")]
+pub use otel::{OtelConfig, OtelMonitor};
+#[cfg(feature = "statsd_monitor")]
+pub mod statsd;
+#[cfg(feature = "statsd_monitor")]
+pub use statsd::{StatsdConfig, StatsdMonitor};
+#[cfg(feature = "web_monitor")]
+pub mod web;
+#[cfg(feature = "web_monitor")]
+pub use web::WebMonitor;
 #[cfg(feature = "std")]
 pub mod disk;
-use alloc::{borrow::Cow, fmt::Debug, string::String, vec::Vec};
+#[cfg(feature = "std")]
+pub mod json;
+use alloc::{borrow::Cow, collections::VecDeque, fmt::Debug, string::String, vec::Vec};
 use core::{fmt, fmt::Write, time::Duration};
 
 #[cfg(feature = "std")]
-pub use disk::{OnDiskJsonMonitor, OnDiskTomlMonitor};
+pub use disk::{AflPlotDataMonitor, OnDiskJsonMonitor, OnDiskTomlMonitor};
 use hashbrown::HashMap;
+#[cfg(feature = "std")]
+pub use json::{JsonMonitor, JSON_MONITOR_SCHEMA_VERSION};
 use libafl_bolts::{current_time, format_duration_hms, ClientId};
 use serde::{Deserialize, Serialize};
 
@@ -39,6 +59,12 @@ pub enum AggregatorOps {
     Min,
     /// Get the max
     Max,
+    /// Add up the numerators and the denominators of [`UserStatsValue::Ratio`]s,
+    /// for a ratio over all clients, instead of the average of the ratios of each client
+    Ratio,
+    /// The rate per second of the sum of the [`UserStatsValue::Number`] counters of all clients,
+    /// over the given sliding time window
+    WindowRate(Duration),
 }
 
 /// The standard aggregator, plug this into the monitor to use
@@ -47,6 +73,8 @@ pub struct Aggregator {
     // this struct could also have hashmap or vec for caching but for now i'll just keep it simple
     // for example to calculate the sum you don't have to iterate over all clients (obviously)
     aggregated: HashMap<String, UserStatsValue>,
+    /// The samples of the summed up counters, for [`AggregatorOps::WindowRate`]
+    rate_samples: HashMap<String, VecDeque<(Duration, u64)>>,
 }
 
 impl Aggregator {
@@ -55,9 +83,16 @@ impl Aggregator {
     pub fn new() -> Self {
         Self {
             aggregated: HashMap::new(),
+            rate_samples: HashMap::new(),
         }
     }
 
+    /// The aggregated value of the stat `name`
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&UserStatsValue> {
+        self.aggregated.get(name)
+    }
+
     /// takes the key and the ref to clients stats then aggregate them all.
     fn aggregate(&mut self, name: &str, client_stats: &[ClientStats]) {
         let mut gather = client_stats
@@ -73,12 +108,39 @@ impl Aggregator {
             }
         };
 
+        if let AggregatorOps::WindowRate(window) = op {
+            let mut total = 0;
+            for item in core::iter::once(&init).chain(gather.map(UserStats::value)) {
+                match item {
+                    UserStatsValue::Number(n) => total += n,
+                    _ => return,
+                }
+            }
+            let rate = self.window_rate(name, window, total, current_time());
+            self.aggregated
+                .insert(name.to_string(), UserStatsValue::Float(rate));
+            return;
+        }
+
         for item in gather {
             match op {
-                AggregatorOps::None => {
+                AggregatorOps::None | AggregatorOps::WindowRate(_) => {
                     // Nothing
                     return;
                 }
+                AggregatorOps::Ratio => {
+                    init = match (&init, item.value()) {
+                        (UserStatsValue::Ratio(x, a), UserStatsValue::Ratio(y, b)) => {
+                            UserStatsValue::Ratio(x + y, a + b)
+                        }
+                        _ => match init.stats_add(item.value()) {
+                            Some(x) => x,
+                            _ => {
+                                return;
+                            }
+                        },
+                    };
+                }
                 AggregatorOps::Avg | AggregatorOps::Sum => {
                     init = match init.stats_add(item.value()) {
                         Some(x) => x,
@@ -118,6 +180,71 @@ impl Aggregator {
 
         self.aggregated.insert(name.to_string(), init);
     }
+
+    /// Records the `total` of the counter `name` at `now`, and returns its rate per second over the last `window`
+    #[allow(clippy::cast_precision_loss)]
+    fn window_rate(&mut self, name: &str, window: Duration, total: u64, now: Duration) -> f64 {
+        let samples = self.rate_samples.entry(name.to_string()).or_default();
+        samples.push_back((now, total));
+        // Keep the newest sample older than the window as the baseline
+        while samples.len() > 2 && now.saturating_sub(samples[1].0) >= window {
+            samples.pop_front();
+        }
+        let (start, start_total) = samples[0];
+        let elapsed = now.saturating_sub(start).as_secs_f64();
+        if elapsed == 0.0 {
+            0.0
+        } else {
+            total.saturating_sub(start_total) as f64 / elapsed
+        }
+    }
+}
+
+/// A user stat with a fixed name and aggregation, declared once and then updated by value,
+/// see [`crate::events::EventFirer::fire_user_stats`].
+///
+/// ```
+/// # use libafl::monitors::{AggregatorOps, UserStatsDef};
+/// const SOLVED_CMPS: UserStatsDef = UserStatsDef::new("solved_cmps", AggregatorOps::Sum);
+/// let stats = SOLVED_CMPS.with_value(7_u64);
+/// assert_eq!(stats.to_string(), "7");
+/// ```
+#[derive(Debug, Clone)]
+pub struct UserStatsDef {
+    name: Cow<'static, str>,
+    aggregator_op: AggregatorOps,
+}
+
+impl UserStatsDef {
+    /// Declare a new user stat
+    #[must_use]
+    pub const fn new(name: &'static str, aggregator_op: AggregatorOps) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            aggregator_op,
+        }
+    }
+
+    /// The name of this stat
+    #[must_use]
+    pub fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+
+    /// The aggregation of this stat
+    #[must_use]
+    pub fn aggregator_op(&self) -> &AggregatorOps {
+        &self.aggregator_op
+    }
+
+    /// This stat with the given value
+    #[must_use]
+    pub fn with_value<V>(&self, value: V) -> UserStats
+    where
+        V: Into<UserStatsValue>,
+    {
+        UserStats::new(value.into(), self.aggregator_op.clone())
+    }
 }
 
 /// user defined stats enum
@@ -161,6 +288,198 @@ pub enum UserStatsValue {
     Ratio(u64, u64),
     /// Percent
     Percent(f64),
+    /// A distribution of values
+    Histogram(Histogram),
+}
+
+impl From<u64> for UserStatsValue {
+    fn from(value: u64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<f64> for UserStatsValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<(u64, u64)> for UserStatsValue {
+    fn from((numerator, denominator): (u64, u64)) -> Self {
+        Self::Ratio(numerator, denominator)
+    }
+}
+
+impl From<&'static str> for UserStatsValue {
+    fn from(value: &'static str) -> Self {
+        Self::String(Cow::Borrowed(value))
+    }
+}
+
+impl From<String> for UserStatsValue {
+    fn from(value: String) -> Self {
+        Self::String(Cow::Owned(value))
+    }
+}
+
+impl From<Histogram> for UserStatsValue {
+    fn from(value: Histogram) -> Self {
+        Self::Histogram(value)
+    }
+}
+
+/// A histogram of values, with fixed buckets.
+///
+/// Histograms of different clients are merged bucket by bucket when aggregated with [`AggregatorOps::Sum`] or [`AggregatorOps::Avg`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Histogram {
+    /// The inclusive upper bounds of the buckets, sorted
+    bounds: Vec<f64>,
+    /// The counts of the buckets, the last one counts the values above all bounds
+    counts: Vec<u64>,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    /// Create a new [`Histogram`] with buckets up to the given bounds, and a bucket for all larger values
+    #[must_use]
+    pub fn new(mut bounds: Vec<f64>) -> Self {
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        let counts = vec![0; bounds.len() + 1];
+        Self {
+            bounds,
+            counts,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// Create a new [`Histogram`] with `buckets` bounds, starting at `start`, each `factor` times the previous one
+    #[must_use]
+    pub fn exponential(start: f64, factor: f64, buckets: usize) -> Self {
+        let mut bound = start;
+        let bounds = (0..buckets)
+            .map(|_| {
+                let cur = bound;
+                bound *= factor;
+                cur
+            })
+            .collect();
+        Self::new(bounds)
+    }
+
+    /// Records a value
+    pub fn record(&mut self, value: f64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records a value `n` times
+    #[allow(clippy::cast_precision_loss)]
+    fn record_n(&mut self, value: f64, n: u64) {
+        let bucket = self.bounds.partition_point(|bound| *bound < value);
+        self.counts[bucket] += n;
+        self.count += n;
+        self.sum += value * n as f64;
+    }
+
+    /// Merges the `other` histogram into this one.
+    /// If the buckets differ, the values of each bucket of `other` are counted at its upper bound.
+    pub fn merge(&mut self, other: &Self) {
+        if self.bounds == other.bounds {
+            for (count, other_count) in self.counts.iter_mut().zip(&other.counts) {
+                *count += other_count;
+            }
+        } else {
+            for (i, other_count) in other.counts.iter().enumerate() {
+                if *other_count == 0 {
+                    continue;
+                }
+                let value = other.bounds.get(i).copied().unwrap_or(f64::INFINITY);
+                let bucket = self.bounds.partition_point(|bound| *bound < value);
+                self.counts[bucket] += other_count;
+            }
+        }
+        self.count += other.count;
+        self.sum += other.sum;
+    }
+
+    /// The inclusive upper bounds of the buckets
+    #[must_use]
+    pub fn bounds(&self) -> &[f64] {
+        &self.bounds
+    }
+
+    /// The counts of the buckets, the last one counts all values above the last bound
+    #[must_use]
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// The number of recorded values
+    #[must_use]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of all recorded values
+    #[must_use]
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// The mean of all recorded values
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum / self.count as f64)
+    }
+
+    /// The upper bound of the bucket containing the `quantile`, between `0.0` and `1.0`.
+    /// Is infinite, if the quantile lies above all bounds.
+    #[must_use]
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn quantile(&self, quantile: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        // Rounds up, `f64::ceil` needs `std`
+        let target = self.count as f64 * quantile;
+        let mut rank = target as u64;
+        if (rank as f64) < target {
+            rank += 1;
+        }
+        let rank = rank.max(1);
+        let mut seen = 0;
+        for (i, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(self.bounds.get(i).copied().unwrap_or(f64::INFINITY));
+            }
+        }
+        Some(f64::INFINITY)
+    }
+}
+
+impl fmt::Display for Histogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.mean(), self.quantile(0.5), self.quantile(0.99)) {
+            (Some(mean), Some(p50), Some(p99)) => write!(
+                f,
+                "n: {}, mean: {}, p50: <={}, p99: <={}",
+                self.count,
+                prettify_float(mean),
+                prettify_float(p50),
+                prettify_float(p99)
+            ),
+            _ => write!(f, "n: 0"),
+        }
+    }
 }
 
 impl UserStatsValue {
@@ -169,7 +488,22 @@ impl UserStatsValue {
     pub fn is_numeric(&self) -> bool {
         match &self {
             Self::Number(_) | Self::Float(_) | Self::Ratio(_, _) | Self::Percent(_) => true,
-            Self::String(_) => false,
+            Self::String(_) | Self::Histogram(_) => false,
+        }
+    }
+
+    /// The value as float, for exporting it as metric. Ratios and percentages are in percent, histograms are their mean.
+    /// Returns `None` for strings, empty histograms, and for ratios with a zero denominator.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn as_metric(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n as f64),
+            Self::Float(f) => Some(*f),
+            Self::Ratio(_, 0) | Self::String(_) => None,
+            Self::Ratio(a, b) => Some(*a as f64 / *b as f64 * 100.0),
+            Self::Percent(p) => Some(*p * 100.0),
+            Self::Histogram(h) => h.mean(),
         }
     }
 
@@ -181,6 +515,8 @@ impl UserStatsValue {
             Self::Float(x) => Some(Self::Float(*x / divisor as f64)),
             Self::Percent(x) => Some(Self::Percent(*x / divisor as f64)),
             Self::Ratio(x, y) => Some(Self::Percent((*x as f64 / divisor as f64) / *y as f64)),
+            // The merged histogram already is the distribution over all clients
+            Self::Histogram(h) => Some(Self::Histogram(h.clone())),
             Self::String(_) => None,
         }
     }
@@ -281,6 +617,11 @@ impl UserStatsValue {
                 let ratio = *x as f64 / *a as f64;
                 Some(Self::Percent(ratio + *y))
             }
+            (Self::Histogram(x), Self::Histogram(y)) => {
+                let mut merged = x.clone();
+                merged.merge(y);
+                Some(Self::Histogram(merged))
+            }
             _ => None,
         }
     }
@@ -299,6 +640,7 @@ impl fmt::Display for UserStatsValue {
             UserStatsValue::Float(n) => write!(f, "{}", prettify_float(*n)),
             UserStatsValue::Percent(n) => write!(f, "{:.3}%", n * 100.0),
             UserStatsValue::String(s) => write!(f, "{s}"),
+            UserStatsValue::Histogram(h) => write!(f, "{h}"),
             UserStatsValue::Ratio(a, b) => {
                 if *b == 0 {
                     write!(f, "{a}/{b}")
@@ -1311,3 +1653,74 @@ impl Default for ClientPerfMonitor {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    use super::{Aggregator, AggregatorOps, ClientStats, Histogram, UserStatsDef, UserStatsValue};
+
+    fn clients_with(stat: &UserStatsDef, values: &[UserStatsValue]) -> Vec<ClientStats> {
+        values
+            .iter()
+            .map(|value| {
+                let mut client = ClientStats::default();
+                client.update_user_stats(stat.name().clone(), stat.with_value(value.clone()));
+                client
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_histogram_aggregation() {
+        const LATENCY: UserStatsDef = UserStatsDef::new("latency", AggregatorOps::Sum);
+        let mut first = Histogram::new(vec![1.0, 10.0, 100.0]);
+        first.record(0.5);
+        first.record(5.0);
+        let mut second = Histogram::new(vec![1.0, 10.0, 100.0]);
+        second.record(50.0);
+        second.record(500.0);
+        let mut coarse = Histogram::new(vec![10.0]);
+        coarse.record(3.0);
+
+        let clients = clients_with(&LATENCY, &[first.into(), second.into(), coarse.into()]);
+        let mut aggregator = Aggregator::new();
+        aggregator.aggregate("latency", &clients);
+        let Some(UserStatsValue::Histogram(merged)) = aggregator.get("latency") else {
+            panic!("Expected a histogram");
+        };
+        assert_eq!(merged.counts(), &[1, 2, 1, 1]);
+        assert_eq!(merged.count(), 5);
+        assert_eq!(merged.quantile(0.5), Some(10.0));
+        assert_eq!(merged.quantile(1.0), Some(f64::INFINITY));
+    }
+
+    #[test]
+    fn test_ratio_aggregation() {
+        const STABILITY: UserStatsDef = UserStatsDef::new("stability", AggregatorOps::Ratio);
+        let clients = clients_with(&STABILITY, &[(1, 2).into(), (9, 18).into(), (0, 0).into()]);
+        let mut aggregator = Aggregator::new();
+        aggregator.aggregate("stability", &clients);
+        assert!(matches!(
+            aggregator.get("stability"),
+            Some(UserStatsValue::Ratio(10, 20))
+        ));
+    }
+
+    #[test]
+    fn test_window_rate() {
+        let window = Duration::from_secs(10);
+        let mut aggregator = Aggregator::new();
+        let mut rate_at = |secs, total| {
+            aggregator.window_rate("solutions", window, total, Duration::from_secs(secs))
+        };
+        let close = |rate: f64, expected: f64| (rate - expected).abs() < f64::EPSILON;
+        assert!(close(rate_at(100, 0), 0.0));
+        assert!(close(rate_at(105, 20), 4.0));
+        assert!(close(rate_at(110, 40), 4.0));
+        // Only the newest sample at the start of the window remains as baseline
+        assert!(close(rate_at(120, 60), 2.0));
+        assert!(close(rate_at(130, 60), 0.0));
+    }
+}
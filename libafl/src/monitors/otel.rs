@@ -0,0 +1,309 @@
+//! The [`OtelMonitor`] exports the fuzzer stats as `OpenTelemetry` metrics, via OTLP/gRPC.
+//!
+//! ## Overview
+//!
+//! The monitor wraps a base monitor and records the stats in `OpenTelemetry` gauges whenever the base monitor is updated.
+//! A periodic reader, running on a small background runtime, pushes them to an OTLP collector,
+//! such as the `OpenTelemetry` Collector, Grafana Alloy, or any other OTLP-based observability stack.
+//!
+//! Global stats are exported as `libafl.*` metrics, the stats of each client as `libafl.client.*` metrics,
+//! with the client id in the `libafl.client.id` attribute.
+//! Numeric user stats, such as the `edges` coverage, are exported as `libafl.client.user_stat`,
+//! with the name of the stat in the `libafl.stat` attribute. Ratios are exported as percentages.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::{fmt, time::Duration};
+
+use libafl_bolts::{current_time, ClientId};
+use opentelemetry::{
+    metrics::{Gauge, MeterProvider},
+    KeyValue,
+};
+use opentelemetry_otlp::{MetricExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    runtime, Resource,
+};
+use tokio::runtime::Runtime;
+use typed_builder::TypedBuilder;
+
+use crate::{
+    monitors::{ClientStats, Monitor, NopMonitor},
+    Error,
+};
+
+/// The configuration of an [`OtelMonitor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct OtelConfig {
+    /// The OTLP/gRPC endpoint of the collector. Defaults to `http://localhost:4317`.
+    #[builder(default = "http://localhost:4317".into(), setter(into))]
+    pub endpoint: String,
+    /// The `service.name` resource attribute. Defaults to `libafl`.
+    #[builder(default = "libafl".into(), setter(into))]
+    pub service_name: String,
+    /// Additional resource attributes, for example the name of the target or the campaign
+    #[builder(default)]
+    pub resource_attributes: Vec<(String, String)>,
+    /// How often the metrics are pushed to the collector. Defaults to 10s.
+    #[builder(default = Duration::from_secs(10))]
+    pub export_interval: Duration,
+    /// The timeout of an export. Defaults to 10s.
+    #[builder(default = Duration::from_secs(10))]
+    pub timeout: Duration,
+}
+
+/// The gauges exported by an [`OtelMonitor`]
+#[derive(Debug, Clone)]
+struct OtelMetrics {
+    corpus_size: Gauge<u64>,
+    objectives: Gauge<u64>,
+    executions: Gauge<u64>,
+    execs_per_sec: Gauge<f64>,
+    clients: Gauge<u64>,
+    run_time: Gauge<u64>,
+    client_corpus_size: Gauge<u64>,
+    client_objectives: Gauge<u64>,
+    client_executions: Gauge<u64>,
+    client_execs_per_sec: Gauge<f64>,
+    client_user_stat: Gauge<f64>,
+}
+
+/// Wraps a base monitor and exports all stats as `OpenTelemetry` metrics.
+#[derive(Clone)]
+pub struct OtelMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    metrics: OtelMetrics,
+    // The provider has to shut down before the runtime its reader runs on
+    provider: SdkMeterProvider,
+    runtime: Arc<Runtime>,
+}
+
+impl<M> fmt::Debug for OtelMonitor<M>
+where
+    M: Monitor + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtelMonitor")
+            .field("base", &self.base)
+            .field("metrics", &self.metrics)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M> Monitor for OtelMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.base.display(event_msg, sender_id);
+        self.record(sender_id);
+    }
+}
+
+impl<M> OtelMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`OtelMonitor`], exporting to the collector in the `config`
+    pub fn new(config: &OtelConfig, base: M) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("libafl-otel")
+            .enable_all()
+            .build()?;
+
+        // The exporter and the reader spawn their tasks on the runtime they are created in
+        let provider = {
+            let _guard = runtime.enter();
+            let exporter = MetricExporter::builder()
+                .with_tonic()
+                .with_endpoint(config.endpoint.clone())
+                .with_timeout(config.timeout)
+                .build()
+                .map_err(|err| {
+                    Error::illegal_argument(format!(
+                        "Could not create the OTLP exporter for {}: {err}",
+                        config.endpoint
+                    ))
+                })?;
+            let reader = PeriodicReader::builder(exporter, runtime::Tokio)
+                .with_interval(config.export_interval)
+                .build();
+            let mut attributes = vec![KeyValue::new("service.name", config.service_name.clone())];
+            attributes.extend(
+                config
+                    .resource_attributes
+                    .iter()
+                    .map(|(key, value)| KeyValue::new(key.clone(), value.clone())),
+            );
+            SdkMeterProvider::builder()
+                .with_reader(reader)
+                .with_resource(Resource::new(attributes))
+                .build()
+        };
+
+        let meter = provider.meter("libafl");
+        let metrics = OtelMetrics {
+            corpus_size: meter
+                .u64_gauge("libafl.corpus.size")
+                .with_description("The number of testcases in the corpora of all clients")
+                .build(),
+            objectives: meter
+                .u64_gauge("libafl.objectives")
+                .with_description("The number of objectives found by all clients")
+                .build(),
+            executions: meter
+                .u64_gauge("libafl.executions")
+                .with_description("The executions of all clients")
+                .build(),
+            execs_per_sec: meter
+                .f64_gauge("libafl.execs_per_sec")
+                .with_description("The executions per second of all clients")
+                .build(),
+            clients: meter
+                .u64_gauge("libafl.clients")
+                .with_description("The number of clients")
+                .build(),
+            run_time: meter
+                .u64_gauge("libafl.run_time")
+                .with_description("The time since the start of the fuzzing campaign")
+                .with_unit("s")
+                .build(),
+            client_corpus_size: meter
+                .u64_gauge("libafl.client.corpus.size")
+                .with_description("The number of testcases in the corpus of a client")
+                .build(),
+            client_objectives: meter
+                .u64_gauge("libafl.client.objectives")
+                .with_description("The number of objectives found by a client")
+                .build(),
+            client_executions: meter
+                .u64_gauge("libafl.client.executions")
+                .with_description("The executions of a client")
+                .build(),
+            client_execs_per_sec: meter
+                .f64_gauge("libafl.client.execs_per_sec")
+                .with_description("The executions per second of a client")
+                .build(),
+            client_user_stat: meter
+                .f64_gauge("libafl.client.user_stat")
+                .with_description("A numeric user stat of a client, ratios are in percent")
+                .build(),
+        };
+
+        Ok(Self {
+            base,
+            metrics,
+            provider,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    /// Pushes all recorded metrics to the collector right away
+    pub fn flush(&self) -> Result<(), Error> {
+        let _guard = self.runtime.enter();
+        self.provider
+            .force_flush()
+            .map_err(|err| Error::unknown(format!("Could not export the metrics: {err}")))
+    }
+
+    /// Records the current stats, and the stats of the client `sender_id`
+    fn record(&mut self, sender_id: ClientId) {
+        let corpus_size = self.corpus_size();
+        let objectives = self.objective_size();
+        let executions = self.total_execs();
+        let execs_per_sec = self.execs_per_sec();
+        let clients = self.client_stats_count() as u64;
+        let run_time = current_time().saturating_sub(self.start_time()).as_secs();
+        self.metrics.corpus_size.record(corpus_size, &[]);
+        self.metrics.objectives.record(objectives, &[]);
+        self.metrics.executions.record(executions, &[]);
+        self.metrics.execs_per_sec.record(execs_per_sec, &[]);
+        self.metrics.clients.record(clients, &[]);
+        self.metrics.run_time.record(run_time, &[]);
+
+        if sender_id.0 as usize >= self.client_stats().len() {
+            return;
+        }
+        let client = self.base.client_stats_mut_for(sender_id);
+        let attributes = [KeyValue::new("libafl.client.id", i64::from(sender_id.0))];
+        self.metrics
+            .client_corpus_size
+            .record(client.corpus_size, &attributes);
+        self.metrics
+            .client_objectives
+            .record(client.objective_size, &attributes);
+        self.metrics
+            .client_executions
+            .record(client.executions, &attributes);
+        self.metrics
+            .client_execs_per_sec
+            .record(client.execs_per_sec(current_time()), &attributes);
+        for (name, stat) in &client.user_monitor {
+            if let Some(value) = stat.value().as_metric() {
+                self.metrics.client_user_stat.record(
+                    value,
+                    &[
+                        attributes[0].clone(),
+                        KeyValue::new("libafl.stat", name.to_string()),
+                    ],
+                );
+            }
+        }
+    }
+}
+
+impl OtelMonitor<NopMonitor> {
+    /// Create a new [`OtelMonitor`] without a base
+    pub fn nop(config: &OtelConfig) -> Result<Self, Error> {
+        Self::new(config, NopMonitor::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::ClientId;
+
+    use super::{OtelConfig, OtelMonitor};
+    use crate::monitors::Monitor;
+
+    #[test]
+    fn test_otel_monitor_records() {
+        // The exporter connects lazily, so recording works without a collector
+        let config = OtelConfig::builder().endpoint("http://127.0.0.1:1").build();
+        let mut monitor = OtelMonitor::nop(&config).unwrap();
+        monitor.client_stats_insert(ClientId(0));
+        monitor
+            .client_stats_mut_for(ClientId(0))
+            .update_corpus_size(3);
+        monitor.display("Testcase", ClientId(0));
+        assert_eq!(monitor.corpus_size(), 3);
+    }
+}
@@ -184,6 +184,7 @@ where
                 UserStatsValue::String(_s) => 0.0,
                 UserStatsValue::Ratio(a, b) => (*a as f64 / *b as f64) * 100.0,
                 UserStatsValue::Percent(p) => *p * 100.0,
+                UserStatsValue::Histogram(h) => h.mean().unwrap_or(0.0),
             };
             self.custom_stat
                 .get_or_create(&Labels {
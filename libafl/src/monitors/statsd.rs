@@ -0,0 +1,338 @@
+//! The [`StatsdMonitor`] pushes the fuzzer stats over UDP, in the statsd or `DogStatsD` line format.
+//!
+//! Global stats are sent as `<prefix>.<stat>` gauges.
+//! The stats of each client are sent as `<prefix>.client.<stat>`, tagged with `client:<id>` and the configured client tags.
+//! Plain statsd has no tags, so there the client id becomes part of the name: `<prefix>.client.<id>.<stat>`.
+//! Executions are additionally counted in the `executions.count` counter, for rates computed by the statsd server.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Write, time::Duration};
+use std::net::UdpSocket;
+
+use hashbrown::HashMap;
+use libafl_bolts::{current_time, ClientId};
+use typed_builder::TypedBuilder;
+
+use crate::{
+    monitors::{ClientStats, Monitor, NopMonitor},
+    Error,
+};
+
+/// The maximum size of a datagram, to stay below common MTUs
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+/// The configuration of a [`StatsdMonitor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct StatsdConfig {
+    /// The `host:port` of the statsd server. Defaults to `127.0.0.1:8125`.
+    #[builder(default = "127.0.0.1:8125".into(), setter(into))]
+    pub addr: String,
+    /// The prefix of all metric names. Defaults to `libafl`.
+    #[builder(default = "libafl".into(), setter(into))]
+    pub prefix: String,
+    /// Use the `DogStatsD` extension for tags. Defaults to `true`.
+    #[builder(default = true)]
+    pub dogstatsd: bool,
+    /// Tags added to all metrics, `DogStatsD` only
+    #[builder(default)]
+    pub tags: Vec<(String, String)>,
+    /// Tags added to the metrics of a specific client, `DogStatsD` only
+    #[builder(default)]
+    pub client_tags: HashMap<ClientId, Vec<(String, String)>>,
+    /// Send the stats at most once per interval. Defaults to 1s.
+    #[builder(default = Duration::from_secs(1))]
+    pub interval: Duration,
+}
+
+/// Wraps a base monitor and pushes all stats to a statsd server.
+#[derive(Debug)]
+pub struct StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    config: StatsdConfig,
+    socket: UdpSocket,
+    last_sent: Duration,
+    /// The executions of each client at the time of the last report, for the counters
+    reported_executions: HashMap<ClientId, u64>,
+}
+
+impl<M> Clone for StatsdMonitor<M>
+where
+    M: Monitor + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            base: self.base.clone(),
+            config: self.config.clone(),
+            socket: self
+                .socket
+                .try_clone()
+                .expect("Could not clone the statsd socket"),
+            last_sent: self.last_sent,
+            reported_executions: self.reported_executions.clone(),
+        }
+    }
+}
+
+impl<M> Monitor for StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.base.display(event_msg, sender_id);
+
+        let cur_time = current_time();
+        if cur_time.saturating_sub(self.last_sent) >= self.config.interval {
+            self.last_sent = cur_time;
+            let lines = self.lines(cur_time);
+            if let Err(err) = self.send(&lines) {
+                log::warn!("Could not send stats to {}: {err}", self.config.addr);
+            }
+        }
+    }
+}
+
+impl<M> StatsdMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`StatsdMonitor`], sending to the server in the `config`
+    pub fn new(config: StatsdConfig, base: M) -> Result<Self, Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.connect(&config.addr)?;
+        Ok(Self {
+            base,
+            config,
+            socket,
+            last_sent: Duration::ZERO,
+            reported_executions: HashMap::new(),
+        })
+    }
+
+    /// The configuration of this monitor
+    #[must_use]
+    pub fn config(&self) -> &StatsdConfig {
+        &self.config
+    }
+
+    /// Renders all current stats as statsd lines
+    #[allow(clippy::cast_precision_loss)]
+    fn lines(&mut self, cur_time: Duration) -> Vec<String> {
+        let mut lines = Vec::new();
+        let global_tags = self.tags(None);
+        let run_time = cur_time.saturating_sub(self.start_time()).as_secs();
+        let execs_per_sec = self.execs_per_sec();
+        for (stat, value) in [
+            ("corpus_size", self.corpus_size() as f64),
+            ("objectives", self.objective_size() as f64),
+            ("executions", self.total_execs() as f64),
+            ("execs_per_sec", execs_per_sec),
+            ("clients", self.client_stats_count() as f64),
+            ("run_time", run_time as f64),
+        ] {
+            lines.push(self.line(stat, None, value, "g", &global_tags));
+        }
+
+        for id in 0..self.client_stats().len() {
+            let client_id = ClientId(id as u32);
+            let tags = self.tags(Some(client_id));
+            let client = &mut self.base.client_stats_mut()[id];
+            if !client.enabled {
+                continue;
+            }
+            let mut stats = vec![
+                ("corpus_size".to_string(), client.corpus_size as f64),
+                ("objectives".to_string(), client.objective_size as f64),
+                ("executions".to_string(), client.executions as f64),
+                ("execs_per_sec".to_string(), client.execs_per_sec(cur_time)),
+            ];
+            stats.extend(client.user_monitor.iter().filter_map(|(name, stat)| {
+                stat.value()
+                    .as_metric()
+                    .map(|value| (sanitize(name), value))
+            }));
+            let executions = client.executions;
+
+            let reported = self.reported_executions.entry(client_id).or_default();
+            let new_executions = executions.saturating_sub(*reported);
+            *reported = executions;
+
+            for (stat, value) in stats {
+                lines.push(self.line(&stat, Some(client_id), value, "g", &tags));
+            }
+            lines.push(self.line(
+                "executions.count",
+                Some(client_id),
+                new_executions as f64,
+                "c",
+                &tags,
+            ));
+        }
+        lines
+    }
+
+    /// The `DogStatsD` tag suffix for global metrics, or for the metrics of `client_id`
+    fn tags(&self, client_id: Option<ClientId>) -> String {
+        if !self.config.dogstatsd {
+            return String::new();
+        }
+        let mut tags: Vec<String> = self
+            .config
+            .tags
+            .iter()
+            .map(|(key, value)| format!("{key}:{value}"))
+            .collect();
+        if let Some(client_id) = client_id {
+            tags.push(format!("client:{}", client_id.0));
+            if let Some(client_tags) = self.config.client_tags.get(&client_id) {
+                tags.extend(
+                    client_tags
+                        .iter()
+                        .map(|(key, value)| format!("{key}:{value}")),
+                );
+            }
+        }
+        if tags.is_empty() {
+            String::new()
+        } else {
+            format!("|#{}", tags.join(","))
+        }
+    }
+
+    /// A single statsd line
+    fn line(
+        &self,
+        stat: &str,
+        client_id: Option<ClientId>,
+        value: f64,
+        kind: &str,
+        tags: &str,
+    ) -> String {
+        let mut line = self.config.prefix.clone();
+        if let Some(client_id) = client_id {
+            line.push_str(".client");
+            if !self.config.dogstatsd {
+                write!(line, ".{}", client_id.0).unwrap();
+            }
+        }
+        write!(line, ".{stat}:{value}|{kind}{tags}").unwrap();
+        line
+    }
+
+    /// Sends the lines, packed into as few datagrams as possible
+    fn send(&self, lines: &[String]) -> Result<(), Error> {
+        let mut datagram = String::new();
+        for line in lines {
+            if !datagram.is_empty() && datagram.len() + 1 + line.len() > MAX_DATAGRAM_SIZE {
+                self.socket.send(datagram.as_bytes())?;
+                datagram.clear();
+            }
+            if !datagram.is_empty() {
+                datagram.push('\n');
+            }
+            datagram.push_str(line);
+        }
+        if !datagram.is_empty() {
+            self.socket.send(datagram.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl StatsdMonitor<NopMonitor> {
+    /// Create a new [`StatsdMonitor`] without a base
+    pub fn nop(config: StatsdConfig) -> Result<Self, Error> {
+        Self::new(config, NopMonitor::new())
+    }
+}
+
+/// Replaces all characters statsd treats specially in metric names
+fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        borrow::Cow,
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::time::Duration;
+    use std::net::UdpSocket;
+
+    use hashbrown::HashMap;
+    use libafl_bolts::ClientId;
+
+    use super::{StatsdConfig, StatsdMonitor};
+    use crate::monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue};
+
+    #[test]
+    fn test_statsd_monitor() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        server
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        let mut client_tags = HashMap::new();
+        client_tags.insert(ClientId(0), vec![("target".into(), "png".into())]);
+        let config = StatsdConfig::builder()
+            .addr(server.local_addr().unwrap().to_string())
+            .tags(vec![("campaign".into(), "nightly".into())])
+            .client_tags(client_tags)
+            .build();
+        let mut monitor = StatsdMonitor::nop(config).unwrap();
+
+        monitor.client_stats_insert(ClientId(0));
+        let client = monitor.client_stats_mut_for(ClientId(0));
+        client.update_corpus_size(7);
+        client.update_user_stats(
+            Cow::Borrowed("edges"),
+            UserStats::new(UserStatsValue::Ratio(1, 2), AggregatorOps::Avg),
+        );
+        monitor.display("Testcase", ClientId(0));
+
+        let mut buf = [0; 2048];
+        let len = server.recv(&mut buf).unwrap();
+        let datagram = String::from_utf8_lossy(&buf[..len]);
+        let lines: Vec<&str> = datagram.lines().collect();
+        assert!(lines.contains(&"libafl.corpus_size:7|g|#campaign:nightly"));
+        assert!(
+            lines.contains(&"libafl.client.corpus_size:7|g|#campaign:nightly,client:0,target:png")
+        );
+        assert!(lines.contains(&"libafl.client.edges:50|g|#campaign:nightly,client:0,target:png"));
+    }
+}
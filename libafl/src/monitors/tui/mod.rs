@@ -37,7 +37,7 @@ pub mod ui;
 use ui::TuiUi;
 
 const DEFAULT_TIME_WINDOW: u64 = 60 * 10; // 10 min
-const DEFAULT_LOGS_NUMBER: usize = 128;
+const DEFAULT_LOGS_NUMBER: usize = 1024;
 
 #[derive(Debug, Clone, TypedBuilder)]
 #[builder(build_method(into = TuiMonitor), builder_method(vis = "pub(crate)",
@@ -124,6 +124,12 @@ impl TimedStats {
     }
 }
 
+impl Default for TimedStats {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(DEFAULT_TIME_WINDOW))
+    }
+}
+
 /// The context to show performance metrics
 #[cfg(feature = "introspection")]
 #[derive(Debug, Default, Clone)]
@@ -265,6 +271,11 @@ pub struct ClientTuiContext {
     pub process_timing: ProcessTiming,
     pub item_geometry: ItemGeometry,
     pub user_stats: HashMap<Cow<'static, str>, UserStats>,
+
+    /// The executions per second over time, for the sparkline
+    pub execs_per_sec_timed: TimedStats,
+    /// The found edges over time, for the sparkline
+    pub edges_timed: TimedStats,
 }
 
 impl ClientTuiContext {
@@ -454,8 +465,14 @@ impl Monitor for TuiMonitor {
         }
 
         self.client_stats_insert(sender_id);
+        let run_time = cur_time.saturating_sub(self.start_time);
         let client = self.client_stats_mut_for(sender_id);
         let exec_sec = client.execs_per_sec_pretty(cur_time);
+        let exec_sec_num = client.execs_per_sec(cur_time) as u64;
+        let edges = match client.get_user_stats("edges").map(UserStats::value) {
+            Some(UserStatsValue::Ratio(found, _)) => Some(*found),
+            _ => None,
+        };
 
         let sender = format!("#{}", sender_id.0);
         let pad = if event_msg.len() + sender.len() < 13 {
@@ -478,10 +495,12 @@ impl Monitor for TuiMonitor {
         {
             let client = &self.client_stats()[sender_id.0 as usize];
             let mut ctx = self.context.write().unwrap();
-            ctx.clients
-                .entry(sender_id.0 as usize)
-                .or_default()
-                .grab_data(client, exec_sec);
+            let client_ctx = ctx.clients.entry(sender_id.0 as usize).or_default();
+            client_ctx.grab_data(client, exec_sec);
+            client_ctx.execs_per_sec_timed.add(run_time, exec_sec_num);
+            if let Some(edges) = edges {
+                client_ctx.edges_timed.add(run_time, edges);
+            }
             while ctx.client_logs.len() >= DEFAULT_LOGS_NUMBER {
                 ctx.client_logs.pop_front();
             }
@@ -699,9 +718,14 @@ fn run_tui_thread<W: Write + Send + Sync + 'static>(
                     match key.code {
                         KeyCode::Char(c) => ui.on_key(c),
                         KeyCode::Left => ui.on_left(),
-                        //KeyCode::Up => ui.on_up(),
+                        KeyCode::Up => ui.on_up(),
                         KeyCode::Right => ui.on_right(),
-                        //KeyCode::Down => ui.on_down(),
+                        KeyCode::Down => ui.on_down(),
+                        KeyCode::PageUp => ui.on_page_up(),
+                        KeyCode::PageDown => ui.on_page_down(),
+                        KeyCode::Enter => ui.on_enter(),
+                        KeyCode::Esc => ui.on_esc(),
+                        KeyCode::Backspace => ui.on_backspace(),
                         _ => {}
                     }
                 }
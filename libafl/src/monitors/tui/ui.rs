@@ -10,7 +10,8 @@ use ratatui::{
     symbols,
     text::{Line, Span},
     widgets::{
-        Axis, Block, Borders, Cell, Chart, Dataset, List, ListItem, Paragraph, Row, Table, Tabs,
+        Axis, Block, Borders, Cell, Chart, Dataset, List, ListItem, Paragraph, Row, Sparkline,
+        Table, TableState, Tabs,
     },
     Frame,
 };
@@ -21,6 +22,7 @@ use super::{
 };
 
 #[derive(Default, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct TuiUi {
     title: String,
     version: String,
@@ -30,10 +32,23 @@ pub struct TuiUi {
     clients: usize,
     charts_tab_idx: usize,
     graph_data: Vec<(f64, f64)>,
+    client_list_state: TableState,
+    /// How many lines the logs are scrolled up
+    logs_scroll: usize,
+    /// Only show the logs containing this
+    logs_search: String,
+    /// If the keys are typed into the search
+    searching: bool,
+    paused: bool,
+    /// The context frozen when pausing
+    snapshot: Option<Arc<RwLock<TuiContext>>>,
 
     pub should_quit: bool,
 }
 
+/// The lines scrolled by page up and page down
+const LOGS_PAGE: usize = 10;
+
 impl TuiUi {
     #[must_use]
     pub fn new(title: String, enhanced_graphics: bool) -> Self {
@@ -53,6 +68,11 @@ impl TuiUi {
         }
     }
     pub fn on_key(&mut self, c: char) {
+        if self.searching {
+            self.logs_search.push(c);
+            self.logs_scroll = 0;
+            return;
+        }
         match c {
             'q' => {
                 self.should_quit = true;
@@ -63,13 +83,48 @@ impl TuiUi {
             't' => {
                 self.show_logs = !self.show_logs;
             }
+            'p' => {
+                self.paused = !self.paused;
+            }
+            '/' => {
+                self.searching = true;
+                self.show_logs = true;
+            }
             _ => {}
         }
     }
 
-    //pub fn on_up(&mut self) {}
+    pub fn on_enter(&mut self) {
+        self.searching = false;
+    }
+
+    pub fn on_esc(&mut self) {
+        self.searching = false;
+        self.logs_search.clear();
+        self.logs_scroll = 0;
+    }
+
+    pub fn on_backspace(&mut self) {
+        if self.searching {
+            self.logs_search.pop();
+        }
+    }
+
+    pub fn on_page_up(&mut self) {
+        self.logs_scroll += LOGS_PAGE;
+    }
+
+    pub fn on_page_down(&mut self) {
+        self.logs_scroll = self.logs_scroll.saturating_sub(LOGS_PAGE);
+    }
 
-    //pub fn on_down(&mut self) {}
+    pub fn on_up(&mut self) {
+        self.on_left();
+    }
+
+    pub fn on_down(&mut self) {
+        self.on_right();
+    }
 
     pub fn on_right(&mut self) {
         if self.clients != 0 {
@@ -94,6 +149,13 @@ impl TuiUi {
     }
 
     pub fn draw(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>) {
+        // While paused, draw the context as it was when pausing
+        if !self.paused {
+            self.snapshot = None;
+        } else if self.snapshot.is_none() {
+            self.snapshot = Some(Arc::new(RwLock::new(app.read().unwrap().clone())));
+        }
+        let app = &self.snapshot.clone().unwrap_or_else(|| app.clone());
         self.clients = app.read().unwrap().clients_num;
 
         let body = Layout::default()
@@ -120,8 +182,14 @@ impl TuiUi {
         let top_body = body[0];
         let mid_body = body[1];
 
+        let client_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(mid_body);
+
         self.draw_overall_ui(f, app, top_body);
-        self.draw_client_ui(f, app, mid_body);
+        self.draw_client_list(f, app, client_layout[0]);
+        self.draw_client_ui(f, app, client_layout[1]);
 
         if self.show_logs {
             let bottom_body = body[2];
@@ -148,7 +216,15 @@ impl TuiUi {
             .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
             .split(left_top_layout[0]);
 
-        let status_bar: String = format!("{} ({})", self.title, self.version.as_str());
+        let status_bar: String = if self.paused {
+            format!(
+                "{} ({}) - paused (`p` to resume)",
+                self.title,
+                self.version.as_str()
+            )
+        } else {
+            format!("{} ({})", self.title, self.version.as_str())
+        };
 
         let text = vec![Line::from(Span::styled(
             &status_bar,
@@ -244,19 +320,71 @@ impl TuiUi {
         self.draw_overall_generic_text(f, app, bottom_layout);
     }
 
+    fn draw_client_list(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let ctx = app.read().unwrap();
+        let mut ids: Vec<&usize> = ctx.clients.keys().collect();
+        ids.sort_unstable();
+
+        let rows: Vec<Row> = ids
+            .iter()
+            .map(|id| {
+                let client = &ctx.clients[*id];
+                Row::new(vec![
+                    Cell::from(Span::raw(format!("#{id}"))),
+                    Cell::from(Span::raw(format!("{}", client.corpus))),
+                    Cell::from(Span::raw(format!("{}", client.objectives))),
+                    Cell::from(Span::raw(client.process_timing.exec_speed.clone())),
+                    Cell::from(Span::raw(client.map_density.clone())),
+                ])
+            })
+            .collect();
+        self.client_list_state
+            .select(ids.iter().position(|id| **id == self.clients_idx));
+
+        let header = Row::new(vec!["client", "corpus", "obj", "exec/s", "edges"])
+            .style(Style::default().fg(Color::LightGreen));
+        let table = Table::default()
+            .rows(rows)
+            .header(header)
+            .block(
+                Block::default()
+                    .title(Span::styled(
+                        "clients (arrows to select)",
+                        Style::default()
+                            .fg(Color::LightCyan)
+                            .add_modifier(Modifier::BOLD),
+                    ))
+                    .borders(Borders::ALL),
+            )
+            .row_highlight_style(Style::default().fg(Color::LightYellow))
+            .widths([
+                Constraint::Length(7),
+                Constraint::Length(8),
+                Constraint::Length(5),
+                Constraint::Length(8),
+                Constraint::Min(0),
+            ]);
+        f.render_stateful_widget(table, area, &mut self.client_list_state);
+    }
+
     fn draw_client_ui(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
         let client_block = Block::default()
             .title(Span::styled(
-                format!("client #{} (l/r arrows to switch)", self.clients_idx),
+                format!("client #{}", self.clients_idx),
                 Style::default()
                     .fg(Color::LightCyan)
                     .add_modifier(Modifier::BOLD),
             ))
             .borders(Borders::ALL);
 
+        let client_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(11), Constraint::Min(0)].as_ref())
+            .split(client_block.inner(area));
         #[allow(unused_mut)]
-        let mut client_area = client_block.inner(area);
+        let mut client_area = client_layout[0];
         f.render_widget(client_block, area);
+        self.draw_client_sparklines(f, app, client_layout[1]);
 
         #[cfg(feature = "introspection")]
         {
@@ -292,6 +420,42 @@ impl TuiUi {
         self.draw_client_results_text(f, app, right_bottom_layout);
     }
 
+    fn draw_client_sparklines(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
+        let ctx = app.read().unwrap();
+        let Some(client) = ctx.clients.get(&self.clients_idx) else {
+            return;
+        };
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+            .split(area);
+        for ((title, stats), area) in [
+            ("exec/sec", &client.execs_per_sec_timed),
+            ("edges", &client.edges_timed),
+        ]
+        .into_iter()
+        .zip(layout.iter())
+        {
+            // Only the most recent values fit
+            let width = usize::from(area.width.saturating_sub(2));
+            let skip = stats.series.len().saturating_sub(width);
+            let sparkline = Sparkline::default()
+                .block(
+                    Block::default()
+                        .title(Span::styled(
+                            title,
+                            Style::default()
+                                .fg(Color::LightCyan)
+                                .add_modifier(Modifier::BOLD),
+                        ))
+                        .borders(Borders::ALL),
+                )
+                .data(stats.series.iter().skip(skip).map(|stat| stat.item))
+                .style(Style::default().fg(Color::LightYellow));
+            f.render_widget(sparkline, *area);
+        }
+    }
+
     #[allow(clippy::too_many_lines, clippy::cast_precision_loss)]
     fn draw_time_chart(
         &mut self,
@@ -776,17 +940,36 @@ impl TuiUi {
             .widths([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)]);
         f.render_widget(table, area);
     }
-    #[allow(clippy::unused_self)]
     fn draw_logs(&mut self, f: &mut Frame, app: &Arc<RwLock<TuiContext>>, area: Rect) {
         let app = app.read().unwrap();
-        let logs: Vec<ListItem> = app
+        let matching: Vec<&String> = app
             .client_logs
             .iter()
-            .map(|msg| ListItem::new(Span::raw(msg)))
+            .filter(|msg| msg.contains(self.logs_search.as_str()))
             .collect();
+
+        // Show the lines above the scroll position, as many as fit
+        let height = usize::from(area.height.saturating_sub(2));
+        self.logs_scroll = self.logs_scroll.min(matching.len().saturating_sub(height));
+        let end = matching.len() - self.logs_scroll;
+        let logs: Vec<ListItem> = matching[end.saturating_sub(height)..end]
+            .iter()
+            .map(|msg| ListItem::new(Span::raw(*msg)))
+            .collect();
+
+        let mut title = String::from("clients logs (`t` show/hide, `/` search, PgUp/PgDn scroll)");
+        if self.searching || !self.logs_search.is_empty() {
+            title = format!("{title} search: {}", self.logs_search);
+            if self.searching {
+                title.push('_');
+            }
+        }
+        if self.logs_scroll > 0 {
+            title = format!("{title} [-{}]", self.logs_scroll);
+        }
         let logs = List::new(logs).block(
             Block::default().borders(Borders::ALL).title(Span::styled(
-                "clients logs (`t` to show/hide)",
+                title,
                 Style::default()
                     .fg(Color::LightCyan)
                     .add_modifier(Modifier::BOLD),
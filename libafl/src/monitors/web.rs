@@ -0,0 +1,540 @@
+//! The [`WebMonitor`] serves a live dashboard of the fuzzer stats over HTTP, for headless machines.
+//!
+//! The dashboard at `/` shows the stats of each client, charts of the executions per second and the coverage over time,
+//! and the most recent objectives. The same data is available as JSON at `/api/stats`.
+//! The server is a tiny blocking HTTP/1.1 server on a background thread, without further dependencies.
+//! With the `async_io` feature, [`WebMonitor::new_async`] serves all connections concurrently on `tokio` instead.
+
+use alloc::{
+    collections::VecDeque,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::time::Duration;
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::Mutex,
+    thread,
+};
+
+use hashbrown::HashMap;
+use libafl_bolts::{current_time, ClientId};
+use serde::Serialize;
+#[cfg(feature = "async_io")]
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+use crate::{
+    monitors::{ClientStats, Monitor, NopMonitor},
+    Error,
+};
+
+/// The number of samples kept for the charts, by default
+pub const DEFAULT_WEB_HISTORY_LEN: usize = 720;
+
+/// How long the server waits for a client to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of objectives listed on the dashboard
+const RECENT_OBJECTIVES_LEN: usize = 50;
+
+/// The dashboard page, polling `/api/stats`
+const DASHBOARD_HTML: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>LibAFL</title>
+<style>
+body { font-family: sans-serif; margin: 2em; background: #fafafa; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: right; }
+th { background: #eee; }
+canvas { border: 1px solid #ccc; background: #fff; margin-right: 1em; }
+</style>
+</head>
+<body>
+<h1>LibAFL</h1>
+<p id="global"></p>
+<canvas id="execs" width="600" height="200"></canvas>
+<canvas id="coverage" width="600" height="200"></canvas>
+<h2>Clients</h2>
+<table id="clients"></table>
+<h2>Recent objectives</h2>
+<table id="objectives"></table>
+<script>
+function chart(id, label, points) {
+  const canvas = document.getElementById(id);
+  const ctx = canvas.getContext("2d");
+  ctx.clearRect(0, 0, canvas.width, canvas.height);
+  ctx.fillText(label, 10, 15);
+  if (points.length < 2) return;
+  const maxX = points[points.length - 1][0], minX = points[0][0];
+  const maxY = Math.max(...points.map(p => p[1]), 1e-9);
+  ctx.fillText(maxY.toFixed(1), canvas.width - 60, 15);
+  ctx.beginPath();
+  points.forEach((p, i) => {
+    const x = (p[0] - minX) / Math.max(maxX - minX, 1) * (canvas.width - 20) + 10;
+    const y = canvas.height - 10 - p[1] / maxY * (canvas.height - 30);
+    if (i == 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+  });
+  ctx.stroke();
+}
+function row(cells, tag) {
+  return "<tr>" + cells.map(c => "<" + tag + ">" + c + "</" + tag + ">").join("") + "</tr>";
+}
+async function update() {
+  const s = await (await fetch("/api/stats")).json();
+  const cov = c => c.coverage == null ? "-" : c.coverage.toFixed(2) + "%";
+  document.getElementById("global").textContent =
+    `run time: ${s.run_time_secs}s, clients: ${s.clients}, corpus: ${s.corpus_size}, objectives: ${s.objectives}, ` +
+    `executions: ${s.executions}, exec/sec: ${s.execs_per_sec.toFixed(1)}, coverage: ${cov(s)}`;
+  document.getElementById("clients").innerHTML =
+    row(["client", "corpus", "objectives", "executions", "exec/sec", "coverage"], "th") +
+    s.client_stats.map(c => row([c.id, c.corpus_size, c.objectives, c.executions, c.execs_per_sec.toFixed(1), cov(c)], "td")).join("");
+  document.getElementById("objectives").innerHTML =
+    row(["time (s)", "client", "objectives of client"], "th") +
+    s.recent_objectives.slice().reverse().map(o => row([o.time_secs, o.client, o.objectives], "td")).join("");
+  chart("execs", "exec/sec", s.history.map(h => [h.time_secs, h.execs_per_sec]));
+  chart("coverage", "coverage %", s.history.filter(h => h.coverage != null).map(h => [h.time_secs, h.coverage]));
+}
+update();
+setInterval(update, 2000);
+</script>
+</body>
+</html>
+"#;
+
+/// The stats of a client, as shown on the dashboard
+#[derive(Debug, Clone, Serialize)]
+struct DashboardClient {
+    id: u32,
+    corpus_size: u64,
+    objectives: u64,
+    executions: u64,
+    execs_per_sec: f64,
+    coverage: Option<f64>,
+}
+
+/// A sample of the charts
+#[derive(Debug, Clone, Copy, Serialize)]
+struct HistorySample {
+    time_secs: u64,
+    execs_per_sec: f64,
+    coverage: Option<f64>,
+    corpus_size: u64,
+}
+
+/// A new objective
+#[derive(Debug, Clone, Copy, Serialize)]
+struct ObjectiveEntry {
+    time_secs: u64,
+    client: u32,
+    objectives: u64,
+}
+
+/// All data served by the [`WebMonitor`]
+#[derive(Debug, Default, Serialize)]
+struct Dashboard {
+    run_time_secs: u64,
+    clients: usize,
+    corpus_size: u64,
+    objectives: u64,
+    executions: u64,
+    execs_per_sec: f64,
+    coverage: Option<f64>,
+    client_stats: Vec<DashboardClient>,
+    history: VecDeque<HistorySample>,
+    recent_objectives: VecDeque<ObjectiveEntry>,
+}
+
+/// Wraps a base monitor and serves a live dashboard of all stats via HTTP.
+#[derive(Debug, Clone)]
+pub struct WebMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    local_addr: SocketAddr,
+    dashboard: Arc<Mutex<Dashboard>>,
+    history_len: usize,
+    sample_interval: Duration,
+    last_sample: Duration,
+    /// The objectives of each client, to detect new ones
+    client_objectives: HashMap<ClientId, u64>,
+}
+
+impl<M> Monitor for WebMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.base.display(event_msg, sender_id);
+        self.update(sender_id);
+    }
+}
+
+impl<M> WebMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`WebMonitor`], serving the dashboard at `addr`, for example `0.0.0.0:8080`
+    pub fn new<A>(addr: A, base: M) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::with_history(addr, base, DEFAULT_WEB_HISTORY_LEN, Duration::from_secs(5))
+    }
+
+    /// Create a new [`WebMonitor`], keeping `history_len` samples for the charts, taken every `sample_interval`
+    pub fn with_history<A>(
+        addr: A,
+        base: M,
+        history_len: usize,
+        sample_interval: Duration,
+    ) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr)?;
+        let local_addr = listener.local_addr()?;
+        let dashboard = Arc::new(Mutex::new(Dashboard::default()));
+        let served = dashboard.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = serve(stream, &served) {
+                            log::debug!("Could not serve the dashboard: {err}");
+                        }
+                    }
+                    Err(err) => log::warn!("Dashboard connection failed: {err}"),
+                }
+            }
+        });
+        Ok(Self::serving(
+            base,
+            local_addr,
+            dashboard,
+            history_len,
+            sample_interval,
+        ))
+    }
+
+    /// Create a new [`WebMonitor`], serving the dashboard at `addr` from a `tokio` runtime
+    #[cfg(feature = "async_io")]
+    pub fn new_async<A>(addr: A, base: M) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::with_history_async(addr, base, DEFAULT_WEB_HISTORY_LEN, Duration::from_secs(5))
+    }
+
+    /// Like [`Self::with_history`], but serves each connection in its own task on a `tokio` runtime,
+    /// so that a slow client does not hold up the others
+    #[cfg(feature = "async_io")]
+    pub fn with_history_async<A>(
+        addr: A,
+        base: M,
+        history_len: usize,
+        sample_interval: Duration,
+    ) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let listener = {
+            let _guard = runtime.enter();
+            tokio::net::TcpListener::from_std(listener)?
+        };
+        let dashboard = Arc::new(Mutex::new(Dashboard::default()));
+        let served = dashboard.clone();
+        thread::spawn(move || {
+            runtime.block_on(async move {
+                loop {
+                    match listener.accept().await {
+                        Ok((stream, _)) => {
+                            let served = served.clone();
+                            tokio::spawn(async move {
+                                if let Err(err) = serve_async(stream, &served).await {
+                                    log::debug!("Could not serve the dashboard: {err}");
+                                }
+                            });
+                        }
+                        Err(err) => log::warn!("Dashboard connection failed: {err}"),
+                    }
+                }
+            });
+        });
+        Ok(Self::serving(
+            base,
+            local_addr,
+            dashboard,
+            history_len,
+            sample_interval,
+        ))
+    }
+
+    /// The monitor for a server already serving `dashboard` at `local_addr`
+    fn serving(
+        base: M,
+        local_addr: SocketAddr,
+        dashboard: Arc<Mutex<Dashboard>>,
+        history_len: usize,
+        sample_interval: Duration,
+    ) -> Self {
+        log::info!("Serving the dashboard at http://{local_addr}/");
+        Self {
+            base,
+            local_addr,
+            dashboard,
+            history_len,
+            sample_interval,
+            last_sample: Duration::ZERO,
+            client_objectives: HashMap::new(),
+        }
+    }
+
+    /// The address the dashboard is served at
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Updates the served data with the current stats
+    fn update(&mut self, sender_id: ClientId) {
+        let cur_time = current_time();
+        let time_secs = cur_time.saturating_sub(self.start_time()).as_secs();
+
+        let mut client_stats = Vec::new();
+        for (id, client) in self.base.client_stats_mut().iter_mut().enumerate() {
+            if !client.enabled {
+                continue;
+            }
+            client_stats.push(DashboardClient {
+                id: id as u32,
+                corpus_size: client.corpus_size,
+                objectives: client.objective_size,
+                executions: client.executions,
+                execs_per_sec: client.execs_per_sec(cur_time),
+                coverage: client
+                    .get_user_stats("edges")
+                    .and_then(|edges| edges.value().as_metric()),
+            });
+        }
+        let coverage = client_stats
+            .iter()
+            .filter_map(|client| client.coverage)
+            .reduce(f64::max);
+
+        let new_objective = client_stats
+            .iter()
+            .find(|client| client.id == sender_id.0)
+            .and_then(|client| {
+                let known = self.client_objectives.entry(sender_id).or_default();
+                let new = client.objectives > *known;
+                *known = client.objectives;
+                new.then_some(ObjectiveEntry {
+                    time_secs,
+                    client: client.id,
+                    objectives: client.objectives,
+                })
+            });
+
+        let sample = cur_time.saturating_sub(self.last_sample) >= self.sample_interval;
+        if sample {
+            self.last_sample = cur_time;
+        }
+
+        let mut dashboard = self.dashboard.lock().unwrap();
+        dashboard.run_time_secs = time_secs;
+        dashboard.clients = self.base.client_stats_count();
+        dashboard.corpus_size = self.base.corpus_size();
+        dashboard.objectives = self.base.objective_size();
+        dashboard.executions = self.base.total_execs();
+        dashboard.execs_per_sec = self.base.execs_per_sec();
+        dashboard.coverage = coverage;
+        dashboard.client_stats = client_stats;
+        if let Some(entry) = new_objective {
+            if dashboard.recent_objectives.len() == RECENT_OBJECTIVES_LEN {
+                dashboard.recent_objectives.pop_front();
+            }
+            dashboard.recent_objectives.push_back(entry);
+        }
+        if sample {
+            if dashboard.history.len() == self.history_len {
+                dashboard.history.pop_front();
+            }
+            let sample = HistorySample {
+                time_secs,
+                execs_per_sec: dashboard.execs_per_sec,
+                coverage,
+                corpus_size: dashboard.corpus_size,
+            };
+            dashboard.history.push_back(sample);
+        }
+    }
+}
+
+impl WebMonitor<NopMonitor> {
+    /// Create a new [`WebMonitor`] without a base
+    pub fn nop<A>(addr: A) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::new(addr, NopMonitor::new())
+    }
+}
+
+/// Answers a single HTTP request
+fn serve(stream: TcpStream, dashboard: &Mutex<Dashboard>) -> Result<(), Error> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, all requests are bodyless `GET`s
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    reader
+        .get_mut()
+        .write_all(&response(&request_line, dashboard)?)?;
+    Ok(())
+}
+
+/// Answers a single HTTP request, without blocking the runtime
+#[cfg(feature = "async_io")]
+async fn serve_async(
+    stream: tokio::net::TcpStream,
+    dashboard: &Mutex<Dashboard>,
+) -> Result<(), Error> {
+    let mut reader = tokio::io::BufReader::new(stream);
+    let mut request_line = String::new();
+    let read_request = async {
+        reader.read_line(&mut request_line).await?;
+        // Skip the headers, all requests are bodyless `GET`s
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                return Ok::<_, Error>(());
+            }
+        }
+    };
+    tokio::time::timeout(REQUEST_TIMEOUT, read_request)
+        .await
+        .map_err(|_| std::io::Error::from(std::io::ErrorKind::TimedOut))??;
+    reader
+        .get_mut()
+        .write_all(&response(&request_line, dashboard)?)
+        .await?;
+    Ok(())
+}
+
+/// The full HTTP response to the request starting with `request_line`
+fn response(request_line: &str, dashboard: &Mutex<Dashboard>) -> Result<Vec<u8>, Error> {
+    let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let (status, content_type, body) = match path {
+        "/" | "/index.html" => ("200 OK", "text/html", DASHBOARD_HTML.to_string()),
+        "/api/stats" => {
+            let json = serde_json::to_string(&*dashboard.lock().unwrap())
+                .map_err(|err| Error::serialize(format!("Could not serialize stats: {err:?}")))?;
+            ("200 OK", "application/json", json)
+        }
+        _ => ("404 Not Found", "text/plain", "Not found".to_string()),
+    };
+    let mut response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body.as_bytes());
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::Cow, string::String};
+    use std::{
+        io::{Read, Write},
+        net::TcpStream,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::WebMonitor;
+    #[cfg(feature = "async_io")]
+    use crate::monitors::NopMonitor;
+    use crate::monitors::{AggregatorOps, Monitor, UserStats, UserStatsValue};
+
+    fn get(monitor: &WebMonitor<impl Monitor>, path: &str) -> String {
+        let mut stream = TcpStream::connect(monitor.local_addr()).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_web_monitor() {
+        let mut monitor = WebMonitor::nop("127.0.0.1:0").unwrap();
+        monitor.client_stats_insert(ClientId(0));
+        let client = monitor.client_stats_mut_for(ClientId(0));
+        client.update_corpus_size(5);
+        client.update_objective_size(1);
+        client.update_user_stats(
+            Cow::Borrowed("edges"),
+            UserStats::new(UserStatsValue::Ratio(1, 4), AggregatorOps::Avg),
+        );
+        monitor.display("Objective", ClientId(0));
+
+        assert!(get(&monitor, "/").contains("<canvas"));
+        assert!(get(&monitor, "/nope").starts_with("HTTP/1.1 404"));
+
+        let response = get(&monitor, "/api/stats");
+        let body = response.split("\r\n\r\n").nth(1).unwrap();
+        let stats: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(stats["corpus_size"], 5);
+        assert_eq!(stats["coverage"], 25.0);
+        assert_eq!(stats["client_stats"][0]["objectives"], 1);
+        assert_eq!(stats["recent_objectives"][0]["client"], 0);
+        assert_eq!(stats["history"].as_array().unwrap().len(), 1);
+    }
+
+    #[cfg(feature = "async_io")]
+    #[test]
+    fn test_web_monitor_async() {
+        let monitor = WebMonitor::new_async("127.0.0.1:0", NopMonitor::new()).unwrap();
+        // A client that never sends its request does not hold up the others
+        let _stalled = TcpStream::connect(monitor.local_addr()).unwrap();
+        assert!(get(&monitor, "/").contains("<canvas"));
+        assert!(get(&monitor, "/api/stats").contains("\"corpus_size\":0"));
+    }
+}
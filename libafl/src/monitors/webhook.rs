@@ -0,0 +1,293 @@
+//! A monitor wrapping a base monitor, and posting notifications to an HTTP webhook,
+//! such as a Slack, Discord or Mattermost incoming webhook.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::time::Duration;
+use std::{
+    sync::mpsc::{self, Sender},
+    thread,
+};
+
+use libafl_bolts::{current_time, format_duration_hms, ClientId};
+use typed_builder::TypedBuilder;
+
+use crate::monitors::{ClientStats, Monitor, NopMonitor};
+
+/// The default body of a notification, understood by Slack and Mattermost incoming webhooks
+pub const DEFAULT_WEBHOOK_TEMPLATE: &str = r#"{"text": "{message}"}"#;
+
+/// The configuration of a [`WebhookMonitor`]
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct WebhookConfig {
+    /// The url to `POST` the notifications to
+    #[builder(setter(into))]
+    pub url: String,
+    /// The JSON body of a notification.
+    /// The placeholders `{event}`, `{message}`, `{objectives}`, `{new_objectives}`, `{corpus}`,
+    /// `{executions}`, `{exec_sec}`, `{run_time}` and `{clients}` are replaced by JSON-escaped values.
+    /// Defaults to [`DEFAULT_WEBHOOK_TEMPLATE`], for Discord use `{"content": "{message}"}`.
+    #[builder(default = DEFAULT_WEBHOOK_TEMPLATE.into(), setter(into))]
+    pub template: String,
+    /// Notify when new objectives are found. Defaults to `true`.
+    #[builder(default = true)]
+    pub on_objective: bool,
+    /// Notify when the executions per second drop below this threshold
+    #[builder(default, setter(strip_option))]
+    pub min_execs_per_sec: Option<f64>,
+    /// Send at most one notification per interval, objectives found in between are reported together. Defaults to 30s.
+    #[builder(default = Duration::from_secs(30))]
+    pub min_interval: Duration,
+    /// The timeout of a request to the webhook. Defaults to 10s.
+    #[builder(default = Duration::from_secs(10))]
+    pub timeout: Duration,
+    /// Additional headers sent with each request, for example for authorization
+    #[builder(default)]
+    pub headers: Vec<(String, String)>,
+}
+
+/// Wraps a base monitor and posts to an HTTP webhook when objectives are found,
+/// or when the executions per second drop below a threshold.
+///
+/// Requests are sent from a background thread, so an unreachable webhook never stalls the broker.
+#[derive(Debug, Clone)]
+pub struct WebhookMonitor<M>
+where
+    M: Monitor,
+{
+    base: M,
+    config: WebhookConfig,
+    reported_objectives: u64,
+    slow: bool,
+    last_sent: Option<Duration>,
+    sender: Option<Sender<String>>,
+}
+
+impl<M> Monitor for WebhookMonitor<M>
+where
+    M: Monitor,
+{
+    fn client_stats_mut(&mut self) -> &mut Vec<ClientStats> {
+        self.base.client_stats_mut()
+    }
+
+    fn client_stats(&self) -> &[ClientStats] {
+        self.base.client_stats()
+    }
+
+    fn start_time(&self) -> Duration {
+        self.base.start_time()
+    }
+
+    fn set_start_time(&mut self, time: Duration) {
+        self.base.set_start_time(time);
+    }
+
+    fn aggregate(&mut self, name: &str) {
+        self.base.aggregate(name);
+    }
+
+    fn display(&mut self, event_msg: &str, sender_id: ClientId) {
+        self.base.display(event_msg, sender_id);
+        self.maybe_notify();
+    }
+}
+
+impl<M> WebhookMonitor<M>
+where
+    M: Monitor,
+{
+    /// Create a new [`WebhookMonitor`]
+    #[must_use]
+    pub fn new(config: WebhookConfig, base: M) -> Self {
+        Self {
+            base,
+            config,
+            reported_objectives: 0,
+            slow: false,
+            last_sent: None,
+            sender: None,
+        }
+    }
+
+    /// The configuration of this monitor
+    #[must_use]
+    pub fn config(&self) -> &WebhookConfig {
+        &self.config
+    }
+
+    fn maybe_notify(&mut self) {
+        let now = current_time();
+        if self
+            .last_sent
+            .is_some_and(|last| now.saturating_sub(last) < self.config.min_interval)
+        {
+            return;
+        }
+
+        let objectives = self.objective_size();
+        if self.config.on_objective && objectives > self.reported_objectives {
+            let new_objectives = objectives - self.reported_objectives;
+            self.reported_objectives = objectives;
+            self.notify(
+                "objective",
+                &format!("Found {new_objectives} new objective(s), {objectives} in total"),
+                new_objectives,
+            );
+            return;
+        }
+
+        if let Some(min_execs_per_sec) = self.config.min_execs_per_sec {
+            // Skip the warmup, the executions per second are meaningless right after the start
+            if now.saturating_sub(self.start_time()) < self.config.min_interval {
+                return;
+            }
+            let execs_per_sec = self.execs_per_sec();
+            if execs_per_sec >= min_execs_per_sec {
+                self.slow = false;
+            } else if !self.slow {
+                self.slow = true;
+                self.notify(
+                    "slow",
+                    &format!(
+                        "Executions dropped to {execs_per_sec:.1}/s, below {min_execs_per_sec}/s"
+                    ),
+                    0,
+                );
+            }
+        }
+    }
+
+    /// Renders the template, and hands the notification to the background thread
+    fn notify(&mut self, event: &str, message: &str, new_objectives: u64) {
+        let run_time = format_duration_hms(&current_time().saturating_sub(self.start_time()));
+        let values = [
+            ("event", event.to_string()),
+            ("objectives", self.objective_size().to_string()),
+            ("new_objectives", new_objectives.to_string()),
+            ("corpus", self.corpus_size().to_string()),
+            ("executions", self.total_execs().to_string()),
+            ("exec_sec", self.execs_per_sec_pretty()),
+            ("run_time", run_time),
+            ("clients", self.client_stats_count().to_string()),
+            ("message", message.to_string()),
+        ];
+        let mut body = self.config.template.clone();
+        for (key, value) in values {
+            body = body.replace(&format!("{{{key}}}"), &json_escape(&value));
+        }
+
+        self.last_sent = Some(current_time());
+        let sender = self
+            .sender
+            .get_or_insert_with(|| spawn_webhook_sender(&self.config));
+        if sender.send(body).is_err() {
+            log::warn!("The webhook sender thread is gone, dropping notification: {message}");
+        }
+    }
+}
+
+impl WebhookMonitor<NopMonitor> {
+    /// Create a new [`WebhookMonitor`] without a base
+    #[must_use]
+    pub fn nop(config: WebhookConfig) -> Self {
+        Self::new(config, NopMonitor::new())
+    }
+}
+
+/// Escapes `value` to be placed inside a JSON string
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+/// Spawns the thread posting the rendered notifications to the webhook
+fn spawn_webhook_sender(config: &WebhookConfig) -> Sender<String> {
+    let (sender, receiver) = mpsc::channel::<String>();
+    let agent = ureq::AgentBuilder::new().timeout(config.timeout).build();
+    let url = config.url.clone();
+    let headers = config.headers.clone();
+    thread::spawn(move || {
+        for body in receiver {
+            let mut request = agent.post(&url).set("Content-Type", "application/json");
+            for (key, value) in &headers {
+                request = request.set(key, value);
+            }
+            if let Err(err) = request.send_string(&body) {
+                log::warn!("Posting to the webhook at {url} failed: {err}");
+            }
+        }
+    });
+    sender
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::String;
+    use core::time::Duration;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use libafl_bolts::ClientId;
+
+    use super::{WebhookConfig, WebhookMonitor};
+    use crate::monitors::Monitor;
+
+    /// Answers a single request with `200 OK`, returning its body
+    fn serve_once(listener: &TcpListener) -> String {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((key, value)) = line.split_once(':') {
+                if key.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        reader
+            .get_mut()
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+            .unwrap();
+        String::from_utf8(body).unwrap()
+    }
+
+    #[test]
+    fn test_webhook_monitor() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let server = thread::spawn(move || serve_once(&listener));
+
+        let config = WebhookConfig::builder()
+            .url(url)
+            .template(r#"{"kind": "{event}", "text": "{message}", "total": {objectives}}"#)
+            .min_interval(Duration::ZERO)
+            .build();
+        let mut monitor = WebhookMonitor::nop(config);
+
+        monitor.client_stats_insert(ClientId(0));
+        monitor.display("Stats", ClientId(0));
+        monitor
+            .client_stats_mut_for(ClientId(0))
+            .update_objective_size(2);
+        monitor.display("Objective", ClientId(0));
+
+        let body = server.join().unwrap();
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["kind"], "objective");
+        assert_eq!(body["text"], "Found 2 new objective(s), 2 in total");
+        assert_eq!(body["total"], 2);
+    }
+}
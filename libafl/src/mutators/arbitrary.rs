@@ -0,0 +1,168 @@
+//! A [`Mutator`] for [`ArbitraryInput`]s, keeping only the mutated bytes from which a value is derived.
+//!
+//! # Example
+//!
+//! ```
+//! use libafl::{
+//!     generators::ArbitraryGenerator,
+//!     inputs::ArbitraryInput,
+//!     mutators::{havoc_mutations, ArbitraryMutator, StdScheduledMutator},
+//! };
+//! use libafl_bolts::nonzero;
+//!
+//! // a sequence of operations, a `Push(u32)` if the `bool` is set, else a `Pop`
+//! type Ops = Vec<(bool, u32)>;
+//!
+//! // generates the initial inputs
+//! let generator = ArbitraryGenerator::<Ops>::new(nonzero!(64));
+//! // the havoc mutations, on the bytes the operations are derived from
+//! let mutator = ArbitraryMutator::<Ops, _>::new(StdScheduledMutator::new(havoc_mutations()));
+//!
+//! // the harness gets the operations, like a `cargo fuzz` target
+//! let harness = |input: &ArbitraryInput<Ops>| {
+//!     let mut stack = vec![];
+//!     for (push, value) in input.value().unwrap() {
+//!         if push {
+//!             stack.push(value);
+//!         } else {
+//!             stack.pop();
+//!         }
+//!     }
+//! };
+//! ```
+use alloc::{borrow::Cow, vec::Vec};
+use core::marker::PhantomData;
+
+use ::arbitrary::Arbitrary;
+use libafl_bolts::Named;
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{ArbitraryInput, HasMutatorBytes},
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+
+/// A [`Mutator`] mutating the bytes of an [`ArbitraryInput`] with an `inner` bytes [`Mutator`],
+/// reverting the mutation if no value is derived from the mutated bytes.
+#[derive(Debug)]
+pub struct ArbitraryMutator<T, M> {
+    inner: M,
+    backup: Vec<u8>,
+    name: Cow<'static, str>,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<T, M> ArbitraryMutator<T, M> {
+    /// Creates a new [`ArbitraryMutator`], mutating the bytes with the `inner` mutator
+    pub fn new(inner: M) -> Self
+    where
+        M: Named,
+    {
+        let name = Cow::Owned(format!("ArbitraryMutator<{}>", inner.name()));
+        Self {
+            inner,
+            backup: Vec::new(),
+            name,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, M, S> Mutator<ArbitraryInput<T>, S> for ArbitraryMutator<T, M>
+where
+    T: for<'a> Arbitrary<'a>,
+    M: Mutator<ArbitraryInput<T>, S>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ArbitraryInput<T>,
+    ) -> Result<MutationResult, Error> {
+        self.backup.clear();
+        self.backup.extend_from_slice(input.bytes());
+
+        let result = self.inner.mutate(state, input)?;
+        if result == MutationResult::Mutated && input.value().is_err() {
+            input.drain(..);
+            input.extend(&self.backup);
+            return Ok(MutationResult::Skipped);
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.inner.post_exec(state, new_corpus_id)
+    }
+}
+
+impl<T, M> Named for ArbitraryMutator<T, M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::arbitrary::{Arbitrary, Unstructured};
+    use libafl_bolts::rands::StdRand;
+
+    use super::ArbitraryMutator;
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        generators::{ArbitraryGenerator, Generator},
+        inputs::ArbitraryInput,
+        mutators::{havoc_mutations, MutationResult, Mutator, StdScheduledMutator},
+        nonzero,
+        state::{HasCorpus, StdState},
+    };
+
+    /// An even byte, the odd ones are invalid
+    #[derive(Debug)]
+    struct Even(u8);
+
+    impl<'a> Arbitrary<'a> for Even {
+        fn arbitrary(u: &mut Unstructured<'a>) -> ::arbitrary::Result<Self> {
+            let value = u8::arbitrary(u)?;
+            if value % 2 == 0 {
+                Ok(Self(value))
+            } else {
+                Err(::arbitrary::Error::IncorrectFormat)
+            }
+        }
+    }
+
+    #[test]
+    fn test_arbitrary_mutator() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut generator = ArbitraryGenerator::<Even>::new(nonzero!(8));
+        for _ in 0..4 {
+            let input: ArbitraryInput<Even> = generator.generate(&mut state).unwrap();
+            assert!(input.value().is_ok());
+            state.corpus_mut().add(Testcase::new(input)).unwrap();
+        }
+
+        let mut mutator = ArbitraryMutator::new(StdScheduledMutator::new(havoc_mutations()));
+        let mut input = generator.generate(&mut state).unwrap();
+        let mut skipped = 0;
+        for _ in 0..256 {
+            if mutator.mutate(&mut state, &mut input).unwrap() == MutationResult::Skipped {
+                skipped += 1;
+            }
+            assert_eq!(input.value().unwrap().0 % 2, 0);
+        }
+        assert!(skipped > 0);
+    }
+}
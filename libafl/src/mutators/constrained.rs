@@ -0,0 +1,308 @@
+//! Mutators for [`ConstrainedBytesInput`]s, keeping the bytes within their [`ByteConstraints`] by construction.
+//!
+//! The mutators only insert allowed bytes, and only change the length within the bounds,
+//! so no mutated input is rejected, or has to be fixed up by the harness.
+use alloc::{borrow::Cow, vec::Vec};
+use core::{cmp::min, num::NonZero};
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+
+use crate::{
+    inputs::{ByteConstraints, ConstrainedBytesInput},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+
+/// The maximum count of bytes inserted or deleted at once
+const MAX_BLOCK_LEN: usize = 16;
+
+/// A random allowed byte
+fn rand_allowed<R: Rand>(rand: &mut R, constraints: &ByteConstraints) -> Option<u8> {
+    let count = NonZero::new(constraints.allowed_count())?;
+    constraints.nth_allowed(rand.below(count))
+}
+
+/// A [`Mutator`] replacing a random byte of a [`ConstrainedBytesInput`] by another allowed byte
+#[derive(Debug, Default)]
+pub struct ConstrainedByteRandMutator;
+
+impl<S> Mutator<ConstrainedBytesInput, S> for ConstrainedByteRandMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ConstrainedBytesInput,
+    ) -> Result<MutationResult, Error> {
+        let (bytes, constraints) = input.bytes_and_constraints_mut();
+        let (Some(len), Some(other_count)) = (
+            NonZero::new(bytes.len()),
+            NonZero::new(constraints.allowed_count().saturating_sub(1)),
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let rand = state.rand_mut();
+        let index = rand.below(len);
+        let old = bytes[index];
+        // pick any allowed byte but the old one, the allowed bytes being in ascending order
+        let n = rand.below(other_count);
+        let new = match constraints.nth_allowed(n) {
+            Some(new) if new < old => new,
+            _ => constraints.nth_allowed(n + 1).unwrap(),
+        };
+        bytes[index] = new;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ConstrainedByteRandMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ConstrainedByteRandMutator");
+        &NAME
+    }
+}
+
+impl ConstrainedByteRandMutator {
+    /// Creates a new [`ConstrainedByteRandMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] inserting random allowed bytes in a [`ConstrainedBytesInput`], up to the maximum length
+#[derive(Debug, Default)]
+pub struct ConstrainedInsertMutator;
+
+impl<S> Mutator<ConstrainedBytesInput, S> for ConstrainedInsertMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ConstrainedBytesInput,
+    ) -> Result<MutationResult, Error> {
+        let (bytes, constraints) = input.bytes_and_constraints_mut();
+        let room = constraints.max_len().saturating_sub(bytes.len());
+        if room == 0 || constraints.allowed_count() == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let count = rand.between(1, min(room, MAX_BLOCK_LEN));
+        let index = rand.between(0, bytes.len());
+        let inserted: Vec<u8> = (0..count)
+            .map(|_| rand_allowed(rand, constraints).unwrap())
+            .collect();
+        bytes.splice(index..index, inserted);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ConstrainedInsertMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ConstrainedInsertMutator");
+        &NAME
+    }
+}
+
+impl ConstrainedInsertMutator {
+    /// Creates a new [`ConstrainedInsertMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] deleting a random range of a [`ConstrainedBytesInput`], down to the minimum length
+#[derive(Debug, Default)]
+pub struct ConstrainedDeleteMutator;
+
+impl<S> Mutator<ConstrainedBytesInput, S> for ConstrainedDeleteMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ConstrainedBytesInput,
+    ) -> Result<MutationResult, Error> {
+        let (bytes, constraints) = input.bytes_and_constraints_mut();
+        let excess = bytes.len().saturating_sub(constraints.min_len());
+        if excess == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let count = rand.between(1, min(excess, MAX_BLOCK_LEN));
+        let index = rand.between(0, bytes.len() - count);
+        bytes.drain(index..index + count);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ConstrainedDeleteMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ConstrainedDeleteMutator");
+        &NAME
+    }
+}
+
+impl ConstrainedDeleteMutator {
+    /// Creates a new [`ConstrainedDeleteMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] copying a random range of a [`ConstrainedBytesInput`] to a random position, up to the maximum length
+#[derive(Debug, Default)]
+pub struct ConstrainedDuplicateMutator;
+
+impl<S> Mutator<ConstrainedBytesInput, S> for ConstrainedDuplicateMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ConstrainedBytesInput,
+    ) -> Result<MutationResult, Error> {
+        let (bytes, constraints) = input.bytes_and_constraints_mut();
+        let room = constraints.max_len().saturating_sub(bytes.len());
+        let Some(len) = NonZero::new(bytes.len()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        if room == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let start = rand.below(len);
+        let count = rand.between(1, min(min(len.get() - start, room), MAX_BLOCK_LEN));
+        let index = rand.between(0, len.get());
+        let copied = bytes[start..start + count].to_vec();
+        bytes.splice(index..index, copied);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ConstrainedDuplicateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ConstrainedDuplicateMutator");
+        &NAME
+    }
+}
+
+impl ConstrainedDuplicateMutator {
+    /// Creates a new [`ConstrainedDuplicateMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] swapping two random bytes of a [`ConstrainedBytesInput`]
+#[derive(Debug, Default)]
+pub struct ConstrainedSwapMutator;
+
+impl<S> Mutator<ConstrainedBytesInput, S> for ConstrainedSwapMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut ConstrainedBytesInput,
+    ) -> Result<MutationResult, Error> {
+        let (bytes, _) = input.bytes_and_constraints_mut();
+        let Some(len) = NonZero::new(bytes.len()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let rand = state.rand_mut();
+        let (first, second) = (rand.below(len), rand.below(len));
+        if bytes[first] == bytes[second] {
+            return Ok(MutationResult::Skipped);
+        }
+        bytes.swap(first, second);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ConstrainedSwapMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ConstrainedSwapMutator");
+        &NAME
+    }
+}
+
+impl ConstrainedSwapMutator {
+    /// Creates a new [`ConstrainedSwapMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Get the mutations for [`ConstrainedBytesInput`]s
+#[must_use]
+pub fn constrained_bytes_mutations() -> tuple_list_type!(
+    ConstrainedByteRandMutator,
+    ConstrainedInsertMutator,
+    ConstrainedDeleteMutator,
+    ConstrainedDuplicateMutator,
+    ConstrainedSwapMutator,
+) {
+    tuple_list!(
+        ConstrainedByteRandMutator::new(),
+        ConstrainedInsertMutator::new(),
+        ConstrainedDeleteMutator::new(),
+        ConstrainedDuplicateMutator::new(),
+        ConstrainedSwapMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::constrained_bytes_mutations;
+    use crate::{
+        corpus::InMemoryCorpus,
+        feedbacks::ConstFeedback,
+        generators::{ConstrainedBytesGenerator, Generator},
+        inputs::{ByteClass, ByteConstraints, ConstrainedBytesInput},
+        mutators::MutatorsTuple,
+        state::StdState,
+    };
+
+    #[test]
+    fn test_constrained_bytes_mutations() {
+        let constraints = ByteConstraints::new(2..=8).with_class(&ByteClass::HexDigits);
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<ConstrainedBytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut generator = ConstrainedBytesGenerator::new(constraints.clone()).unwrap();
+        let mut mutations = constrained_bytes_mutations();
+        for i in 0..1000 {
+            let mut input = generator.generate(&mut state).unwrap();
+            mutations
+                .get_and_mutate((i % 5).into(), &mut state, &mut input)
+                .unwrap();
+            constraints.check(input.bytes()).unwrap();
+        }
+    }
+}
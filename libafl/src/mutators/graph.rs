@@ -0,0 +1,361 @@
+//! Mutators for [`GraphInput`]s, changing the structure of the graph and the payloads of its nodes.
+//!
+//! The structural mutators add and remove nodes and edges, the [`GraphPayloadMutator`] applies
+//! a [`Mutator`] for bytes to the payload of a random node, and the [`GraphCrossoverMutator`]
+//! copies a connected subgraph of another graph of the corpus.
+//! The count of nodes is bounded by the [`HasMaxSize::max_size`] of the state.
+use alloc::{borrow::Cow, collections::VecDeque, vec, vec::Vec};
+use core::num::NonZero;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+
+use crate::{
+    corpus::Corpus,
+    inputs::{GraphInput, MutVecInput},
+    mutators::{
+        havoc_mutations_no_crossover, HavocMutationsNoCrossoverType, MutationResult, Mutator,
+        StdScheduledMutator,
+    },
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// The maximum count of nodes the [`GraphCrossoverMutator`] copies at once
+const MAX_SUBGRAPH_NODES: usize = 8;
+
+/// A random node of the graph, or `None` if it has no nodes
+fn rand_node<R: Rand>(rand: &mut R, input: &GraphInput) -> Option<u32> {
+    let count = NonZero::new(input.nodes().len())?;
+    Some(rand.below(count) as u32)
+}
+
+/// A [`Mutator`] adding a node to a [`GraphInput`], with the payload of a random node,
+/// and an edge to it from a random node
+#[derive(Debug, Default)]
+pub struct GraphAddNodeMutator;
+
+impl<S> Mutator<GraphInput, S> for GraphAddNodeMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        if input.nodes().len() >= state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        let parent = rand_node(state.rand_mut(), input);
+        let payload = parent.map_or_else(Vec::new, |parent| input.nodes()[parent as usize].clone());
+        let node = input.add_node(payload);
+        if let Some(parent) = parent {
+            input.add_edge(parent, node)?;
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphAddNodeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphAddNodeMutator");
+        &NAME
+    }
+}
+
+impl GraphAddNodeMutator {
+    /// Creates a new [`GraphAddNodeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] removing a random node and its edges from a [`GraphInput`], keeping at least one node
+#[derive(Debug, Default)]
+pub struct GraphRemoveNodeMutator;
+
+impl<S> Mutator<GraphInput, S> for GraphRemoveNodeMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        if input.nodes().len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        let node = rand_node(state.rand_mut(), input).unwrap();
+        input.remove_node(node)?;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphRemoveNodeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphRemoveNodeMutator");
+        &NAME
+    }
+}
+
+impl GraphRemoveNodeMutator {
+    /// Creates a new [`GraphRemoveNodeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] adding an edge between two random nodes of a [`GraphInput`]
+#[derive(Debug, Default)]
+pub struct GraphAddEdgeMutator;
+
+impl<S> Mutator<GraphInput, S> for GraphAddEdgeMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        let rand = state.rand_mut();
+        let (Some(from), Some(to)) = (rand_node(rand, input), rand_node(rand, input)) else {
+            return Ok(MutationResult::Skipped);
+        };
+        if input.add_edge(from, to)? {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for GraphAddEdgeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphAddEdgeMutator");
+        &NAME
+    }
+}
+
+impl GraphAddEdgeMutator {
+    /// Creates a new [`GraphAddEdgeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] removing a random edge of a [`GraphInput`]
+#[derive(Debug, Default)]
+pub struct GraphRemoveEdgeMutator;
+
+impl<S> Mutator<GraphInput, S> for GraphRemoveEdgeMutator
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        let Some(count) = NonZero::new(input.edges().len()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (from, to) = input.edges()[state.rand_mut().below(count)];
+        input.remove_edge(from, to);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphRemoveEdgeMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphRemoveEdgeMutator");
+        &NAME
+    }
+}
+
+impl GraphRemoveEdgeMutator {
+    /// Creates a new [`GraphRemoveEdgeMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Mapping [`Mutator`] applying a [`Mutator`] for bytes to the payload of a random node of a [`GraphInput`].
+///
+/// Returns [`MutationResult::Skipped`] if the graph has no nodes.
+#[derive(Debug)]
+pub struct GraphPayloadMutator<M> {
+    inner: M,
+    name: Cow<'static, str>,
+}
+
+impl<M> GraphPayloadMutator<M> {
+    /// Creates a new [`GraphPayloadMutator`]
+    pub fn new(inner: M) -> Self
+    where
+        M: Named,
+    {
+        let name = Cow::Owned(format!("GraphPayloadMutator<{}>", inner.name()));
+        Self { inner, name }
+    }
+}
+
+impl<M, S> Mutator<GraphInput, S> for GraphPayloadMutator<M>
+where
+    S: HasRand,
+    for<'a> M: Mutator<MutVecInput<'a>, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        let Some(node) = rand_node(state.rand_mut(), input) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let mut mapped: MutVecInput<'_> = input.payload_mut(node).unwrap().into();
+        self.inner.mutate(state, &mut mapped)
+    }
+}
+
+impl<M> Named for GraphPayloadMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+/// A [`Mutator`] copying a connected subgraph of another [`GraphInput`] of the corpus into the input.
+///
+/// The subgraph holds the nodes reachable from a random node, in breadth-first order, up to
+/// a few nodes, and the edges between them. It is connected by an edge from a random node of the input.
+#[derive(Debug, Default)]
+pub struct GraphCrossoverMutator;
+
+impl<S> Mutator<GraphInput, S> for GraphCrossoverMutator
+where
+    S: HasCorpus + HasRand + HasMaxSize,
+    S::Corpus: Corpus<Input = GraphInput>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut GraphInput) -> Result<MutationResult, Error> {
+        let room = state.max_size().saturating_sub(input.nodes().len());
+        if room == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let id = random_corpus_id!(state.corpus(), state.rand_mut());
+        let other = {
+            let mut other_testcase = state.corpus().get(id)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.clone()
+        };
+        let rand = state.rand_mut();
+        let Some(start) = rand_node(rand, &other) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let parent = rand_node(rand, input);
+
+        // the index in the input of each copied node of the other graph
+        let mut copied: Vec<Option<u32>> = vec![None; other.nodes().len()];
+        let mut queue = VecDeque::from([start]);
+        let mut count = 0;
+        while let Some(node) = queue.pop_front() {
+            if copied[node as usize].is_some() {
+                continue;
+            }
+            copied[node as usize] = Some(input.add_node(other.nodes()[node as usize].clone()));
+            count += 1;
+            if count == room.min(MAX_SUBGRAPH_NODES) {
+                break;
+            }
+            queue.extend(other.successors(node));
+        }
+        for (from, to) in other.edges() {
+            if let (Some(from), Some(to)) = (copied[*from as usize], copied[*to as usize]) {
+                input.add_edge(from, to)?;
+            }
+        }
+        if let Some(parent) = parent {
+            input.add_edge(parent, copied[start as usize].unwrap())?;
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for GraphCrossoverMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("GraphCrossoverMutator");
+        &NAME
+    }
+}
+
+impl GraphCrossoverMutator {
+    /// Creates a new [`GraphCrossoverMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Get the mutations for [`GraphInput`]s, mutating the payloads with the havoc mutations
+#[must_use]
+pub fn graph_mutations() -> tuple_list_type!(
+    GraphAddNodeMutator,
+    GraphRemoveNodeMutator,
+    GraphAddEdgeMutator,
+    GraphRemoveEdgeMutator,
+    GraphPayloadMutator<StdScheduledMutator<HavocMutationsNoCrossoverType>>,
+    GraphCrossoverMutator,
+) {
+    tuple_list!(
+        GraphAddNodeMutator::new(),
+        GraphRemoveNodeMutator::new(),
+        GraphAddEdgeMutator::new(),
+        GraphRemoveEdgeMutator::new(),
+        GraphPayloadMutator::new(StdScheduledMutator::new(havoc_mutations_no_crossover())),
+        GraphCrossoverMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::graph_mutations;
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::GraphInput,
+        mutators::MutatorsTuple,
+        state::{HasCorpus, HasMaxSize, StdState},
+    };
+
+    /// Checks that the edges are sorted, unique, and between existing nodes
+    fn assert_valid(input: &GraphInput) {
+        assert!(input.edges().windows(2).all(|pair| pair[0] < pair[1]));
+        let count = input.nodes().len() as u32;
+        assert!(input
+            .edges()
+            .iter()
+            .all(|(from, to)| *from < count && *to < count));
+    }
+
+    #[test]
+    fn test_graph_mutations() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<GraphInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state.set_max_size(32);
+        let other = GraphInput::new(
+            vec![b"x".to_vec(), b"y".to_vec(), b"z".to_vec()],
+            vec![(0, 1), (1, 2), (2, 0)],
+        )
+        .unwrap();
+        state.corpus_mut().add(Testcase::new(other)).unwrap();
+
+        let mut mutations = graph_mutations();
+        let mut input = GraphInput::new(vec![b"a".to_vec(), b"b".to_vec()], vec![(0, 1)]).unwrap();
+        for i in 0..1000 {
+            mutations
+                .get_and_mutate((i % 6).into(), &mut state, &mut input)
+                .unwrap();
+            assert_valid(&input);
+            assert!(!input.nodes().is_empty());
+            assert!(input.nodes().len() <= 32);
+        }
+    }
+}
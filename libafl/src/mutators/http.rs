@@ -0,0 +1,446 @@
+//! Mutators for [`HttpRequestInput`]s, changing one component of the request at a time.
+//!
+//! The [`HttpMethodMutator`], [`HttpPathMutator`] and [`HttpHeaderMutator`] change the structure of the request,
+//! the [`HttpPartMappingMutator`] applies the mutators for bytes to a component, for example the havoc mutations
+//! to the body, so that they cannot destroy the framing of the request.
+//!
+//! # Example
+#![cfg_attr(feature = "std", doc = " ```")]
+#![cfg_attr(not(feature = "std"), doc = " ```ignore")]
+//! use libafl::mutators::{
+//!     havoc_mutations_no_crossover, HttpHeaderMutator, HttpMethodMutator, HttpPart,
+//!     HttpPathMutator, StdScheduledMutator, ToHttpPartMappingMutatorMapper,
+//! };
+//! use libafl_bolts::tuples::{tuple_list, Map, Merge};
+//!
+//! let mutations = tuple_list!(
+//!     HttpMethodMutator::new(),
+//!     HttpPathMutator::new(),
+//!     HttpHeaderMutator::default()
+//! )
+//! .merge(havoc_mutations_no_crossover().map(ToHttpPartMappingMutatorMapper::new(HttpPart::Body)))
+//! .merge(
+//!     havoc_mutations_no_crossover()
+//!         .map(ToHttpPartMappingMutatorMapper::new(HttpPart::HeaderValue)),
+//! );
+//! let mutator = StdScheduledMutator::new(mutations);
+//! ```
+use alloc::{
+    borrow::{Cow, ToOwned},
+    vec::Vec,
+};
+use core::num::NonZero;
+
+use libafl_bolts::{rands::Rand, tuples::MappingFunctor, Named};
+
+use crate::{
+    inputs::{HttpRequestInput, MutVecInput},
+    mutators::{MutationResult, Mutator},
+    nonzero,
+    state::HasRand,
+    Error,
+};
+
+/// The methods the [`HttpMethodMutator`] picks from
+pub const HTTP_METHODS: [&[u8]; 9] = [
+    b"GET", b"HEAD", b"POST", b"PUT", b"DELETE", b"CONNECT", b"OPTIONS", b"TRACE", b"PATCH",
+];
+
+/// The path segments the [`HttpPathMutator`] inserts, often handled specially by the services
+pub const HTTP_PATH_SEGMENTS: [&[u8]; 8] = [
+    b"..",
+    b".",
+    b"",
+    b"%2e%2e",
+    b"%00",
+    b"%2f",
+    b"index.html",
+    b"?a=1",
+];
+
+/// The headers, and values, of the [`Default`] [`HttpHeaderDictionary`]
+pub const HTTP_HEADERS: &[(&[u8], &[&[u8]])] = &[
+    (b"Host", &[b"localhost", b"127.0.0.1", b""]),
+    (b"User-Agent", &[b"libafl", b"Mozilla/5.0"]),
+    (b"Accept", &[b"*/*", b"text/html", b"application/json"]),
+    (b"Accept-Encoding", &[b"gzip", b"deflate", b"identity"]),
+    (
+        b"Content-Type",
+        &[
+            b"application/json",
+            b"application/x-www-form-urlencoded",
+            b"multipart/form-data; boundary=x",
+            b"text/plain",
+        ],
+    ),
+    (b"Content-Length", &[b"0", b"-1", b"4294967296"]),
+    (b"Transfer-Encoding", &[b"chunked", b"identity"]),
+    (b"Connection", &[b"close", b"keep-alive", b"upgrade"]),
+    (b"Cookie", &[b"session=0", b"a=b; c=d"]),
+    (b"Authorization", &[b"Basic YWRtaW46YWRtaW4=", b"Bearer x"]),
+    (b"Range", &[b"bytes=0-0", b"bytes=-1", b"bytes=0-1,1-2"]),
+    (b"Expect", &[b"100-continue"]),
+    (b"Upgrade", &[b"websocket", b"h2c"]),
+];
+
+/// A [`Mutator`] replacing the method of a [`HttpRequestInput`] with another of [`HTTP_METHODS`]
+#[derive(Debug, Default)]
+pub struct HttpMethodMutator;
+
+impl<S> Mutator<HttpRequestInput, S> for HttpMethodMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut HttpRequestInput,
+    ) -> Result<MutationResult, Error> {
+        let method = state.rand_mut().choose(HTTP_METHODS).unwrap();
+        if method == input.method() {
+            return Ok(MutationResult::Skipped);
+        }
+        method.clone_into(input.method_mut());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for HttpMethodMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("HttpMethodMutator");
+        &NAME
+    }
+}
+
+impl HttpMethodMutator {
+    /// Creates a new [`HttpMethodMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] inserting one of [`HTTP_PATH_SEGMENTS`] in the path of a [`HttpRequestInput`],
+/// or removing or duplicating a segment
+#[derive(Debug, Default)]
+pub struct HttpPathMutator;
+
+impl<S> Mutator<HttpRequestInput, S> for HttpPathMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut HttpRequestInput,
+    ) -> Result<MutationResult, Error> {
+        let rand = state.rand_mut();
+        let path = input.path_mut();
+        let Some(len) = NonZero::new(path.len()) else {
+            path.push(rand.choose(HTTP_PATH_SEGMENTS).unwrap().to_vec());
+            return Ok(MutationResult::Mutated);
+        };
+        let idx = rand.below(len);
+        match rand.below(nonzero!(3)) {
+            0 => {
+                let segment = rand.choose(HTTP_PATH_SEGMENTS).unwrap().to_vec();
+                path.insert(idx, segment);
+            }
+            1 => {
+                path.remove(idx);
+            }
+            _ => {
+                let segment = path[idx].clone();
+                path.insert(idx, segment);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for HttpPathMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("HttpPathMutator");
+        &NAME
+    }
+}
+
+impl HttpPathMutator {
+    /// Creates a new [`HttpPathMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// The header names, and values for each, the [`HttpHeaderMutator`] uses
+#[derive(Debug, Clone)]
+pub struct HttpHeaderDictionary {
+    entries: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+}
+
+impl HttpHeaderDictionary {
+    /// Creates a new empty [`HttpHeaderDictionary`]. The [`Default`] one has the common headers.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds a value for the header `name`
+    pub fn add(&mut self, name: &[u8], value: &[u8]) {
+        if let Some((_, values)) = self
+            .entries
+            .iter_mut()
+            .find(|(entry, _)| entry.as_slice() == name)
+        {
+            values.push(value.to_vec());
+        } else {
+            self.entries.push((name.to_vec(), vec![value.to_vec()]));
+        }
+    }
+
+    /// The header names, with their values
+    #[must_use]
+    pub fn entries(&self) -> &[(Vec<u8>, Vec<Vec<u8>>)] {
+        &self.entries
+    }
+
+    /// The values of the header `name`, ignoring the case
+    #[must_use]
+    pub fn values(&self, name: &[u8]) -> Option<&[Vec<u8>]> {
+        self.entries
+            .iter()
+            .find(|(entry, _)| entry.eq_ignore_ascii_case(name))
+            .map(|(_, values)| values.as_slice())
+    }
+}
+
+impl Default for HttpHeaderDictionary {
+    fn default() -> Self {
+        let mut dictionary = Self::new();
+        for (name, values) in HTTP_HEADERS {
+            for value in *values {
+                dictionary.add(name, value);
+            }
+        }
+        dictionary
+    }
+}
+
+/// A [`Mutator`] adding a header of the [`HttpHeaderDictionary`] to a [`HttpRequestInput`],
+/// replacing the value of a header with one of the dictionary, or removing a header
+#[derive(Debug, Default)]
+pub struct HttpHeaderMutator {
+    dictionary: HttpHeaderDictionary,
+}
+
+impl<S> Mutator<HttpRequestInput, S> for HttpHeaderMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut HttpRequestInput,
+    ) -> Result<MutationResult, Error> {
+        let rand = state.rand_mut();
+        let headers_len = NonZero::new(input.headers().len());
+        match (rand.below(nonzero!(3)), headers_len) {
+            (1, Some(len)) => {
+                // replace the value of a header, if the dictionary knows it
+                let idx = rand.below(len);
+                let (name, value) = &mut input.headers_mut()[idx];
+                let Some(new_value) = self
+                    .dictionary
+                    .values(name)
+                    .and_then(|values| rand.choose(values))
+                else {
+                    return Ok(MutationResult::Skipped);
+                };
+                new_value.clone_into(value);
+            }
+            (2, Some(len)) => {
+                input.headers_mut().remove(rand.below(len));
+            }
+            _ => {
+                let Some((name, values)) = rand.choose(self.dictionary.entries()) else {
+                    return Ok(MutationResult::Skipped);
+                };
+                let value = rand.choose(values).unwrap();
+                input.add_header(name, value);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for HttpHeaderMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("HttpHeaderMutator");
+        &NAME
+    }
+}
+
+impl HttpHeaderMutator {
+    /// Creates a new [`HttpHeaderMutator`] with the given dictionary
+    #[must_use]
+    pub fn new(dictionary: HttpHeaderDictionary) -> Self {
+        Self { dictionary }
+    }
+}
+
+/// A component of a [`HttpRequestInput`], for the [`HttpPartMappingMutator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpPart {
+    /// The method
+    Method,
+    /// A random segment of the path
+    PathSegment,
+    /// The name of a random header
+    HeaderName,
+    /// The value of a random header
+    HeaderValue,
+    /// The body
+    Body,
+}
+
+/// Mapping [`Mutator`] applying a [`Mutator`] for bytes to a [`HttpPart`] of a [`HttpRequestInput`].
+///
+/// Returns [`MutationResult::Skipped`] if the request has no such part, for example no headers.
+#[derive(Debug)]
+pub struct HttpPartMappingMutator<M> {
+    part: HttpPart,
+    inner: M,
+    name: Cow<'static, str>,
+}
+
+impl<M> HttpPartMappingMutator<M> {
+    /// Creates a new [`HttpPartMappingMutator`]
+    pub fn new(part: HttpPart, inner: M) -> Self
+    where
+        M: Named,
+    {
+        let name = Cow::Owned(format!(
+            "HttpPartMappingMutator<{part:?}, {}>",
+            inner.name()
+        ));
+        Self { part, inner, name }
+    }
+}
+
+impl<M, S> Mutator<HttpRequestInput, S> for HttpPartMappingMutator<M>
+where
+    S: HasRand,
+    for<'a> M: Mutator<MutVecInput<'a>, S>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut HttpRequestInput,
+    ) -> Result<MutationResult, Error> {
+        let mut mapped: MutVecInput<'_> = match self.part {
+            HttpPart::Method => input.method_mut().into(),
+            HttpPart::Body => input.body_mut(),
+            HttpPart::PathSegment => {
+                let Some(len) = NonZero::new(input.path().len()) else {
+                    return Ok(MutationResult::Skipped);
+                };
+                let idx = state.rand_mut().below(len);
+                (&mut input.path_mut()[idx]).into()
+            }
+            HttpPart::HeaderName | HttpPart::HeaderValue => {
+                let Some(len) = NonZero::new(input.headers().len()) else {
+                    return Ok(MutationResult::Skipped);
+                };
+                let idx = state.rand_mut().below(len);
+                let (name, value) = &mut input.headers_mut()[idx];
+                if self.part == HttpPart::HeaderName {
+                    name.into()
+                } else {
+                    value.into()
+                }
+            }
+        };
+        self.inner.mutate(state, &mut mapped)
+    }
+}
+
+impl<M> Named for HttpPartMappingMutator<M> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+/// Mapper to use to map a [`tuple_list`](libafl_bolts::tuples::tuple_list) of [`Mutator`]s using [`HttpPartMappingMutator`]s.
+///
+/// See the [module-level documentation](self) for an example.
+#[derive(Debug, Clone, Copy)]
+pub struct ToHttpPartMappingMutatorMapper {
+    part: HttpPart,
+}
+
+impl ToHttpPartMappingMutatorMapper {
+    /// Creates a new [`ToHttpPartMappingMutatorMapper`], mapping the mutators to the given [`HttpPart`]
+    #[must_use]
+    pub fn new(part: HttpPart) -> Self {
+        Self { part }
+    }
+}
+
+impl<M> MappingFunctor<M> for ToHttpPartMappingMutatorMapper
+where
+    M: Named,
+{
+    type Output = HttpPartMappingMutator<M>;
+
+    fn apply(&mut self, from: M) -> Self::Output {
+        HttpPartMappingMutator::new(self.part, from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::{tuple_list, Map, Merge};
+
+    use super::{
+        HttpHeaderMutator, HttpMethodMutator, HttpPart, HttpPathMutator,
+        ToHttpPartMappingMutatorMapper,
+    };
+    use crate::{
+        inputs::HttpRequestInput,
+        mutators::{havoc_mutations_no_crossover, Mutator, StdScheduledMutator},
+        state::NopState,
+    };
+
+    #[test]
+    fn test_http_mutators_keep_framing() {
+        let mut mutator = StdScheduledMutator::new(
+            tuple_list!(
+                HttpMethodMutator::new(),
+                HttpPathMutator::new(),
+                HttpHeaderMutator::default()
+            )
+            .merge(
+                havoc_mutations_no_crossover()
+                    .map(ToHttpPartMappingMutatorMapper::new(HttpPart::Body)),
+            ),
+        );
+
+        let mut state: NopState<HttpRequestInput> = NopState::new();
+        let mut input = HttpRequestInput::new(b"GET", b"/index.html");
+        input.set_body(b"hello");
+        for _ in 0..1024 {
+            mutator.mutate(&mut state, &mut input).unwrap();
+            // the structural mutators never add line breaks, so the request parses back
+            let bytes = input.to_bytes();
+            let parsed = HttpRequestInput::parse(&bytes).unwrap();
+            assert_eq!(parsed.method(), input.method());
+            if input.header(b"Content-Length").is_none() {
+                assert_eq!(parsed.body(), input.body());
+                assert_eq!(parsed.to_bytes(), bytes);
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! The mutations of the fields of composite inputs, as generated by `#[derive(MappedMutations)]`.
+//!
+//! The derive implements [`MappedMutations`] for a struct, mapping the mutations of each field to the struct:
+//! - `Vec<u8>`: the havoc mutations, see [`crate::mutators::mapped_havoc_mutations`]
+//! - `Option<Vec<u8>>`: the havoc mutations, see [`crate::mutators::optional_mapped_havoc_mutations`], and the [`ToggleOptionalBytesMutator`]
+//! - `bool`: the [`ToggleBoolMutator`]
+//! - integers: the [`IntMutator`]
+//! - other types: the [`MappedMutations::mapped_mutations_no_crossover`] of the nested input, which must derive [`MappedMutations`] too
+//!
+//! Fields annotated with `#[mapped_mutations(skip)]` are not mutated.
+//! For the `Vec<u8>` and `Option<Vec<u8>>` fields, the derive also adds the accessors `field()` and `field_mut()`, used by the mapped havoc mutations.
+use alloc::{borrow::Cow, vec::Vec};
+use core::{mem::size_of, num::NonZero};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    mutators::{MutationResult, Mutator},
+    nonzero,
+    state::HasRand,
+    Error,
+};
+
+/// The mutations of the fields of a composite input, mapped to the input.
+///
+/// Usually implemented with `#[derive(MappedMutations)]`, see the [module-level documentation](self).
+///
+/// # Example
+///
+#[cfg_attr(feature = "derive", doc = " ```")]
+#[cfg_attr(not(feature = "derive"), doc = " ```ignore")]
+/// use libafl::{inputs::HasMutatorBytes, mutators::MappedMutations, MappedMutations};
+///
+/// #[derive(MappedMutations)]
+/// pub struct Header {
+///     pub version: u16,
+///     pub compressed: bool,
+/// }
+///
+/// #[derive(MappedMutations)]
+/// pub struct Packet {
+///     pub header: Header,
+///     pub payload: Vec<u8>,
+///     pub trailer: Option<Vec<u8>>,
+/// }
+///
+/// // the mutations of all the fields, to schedule with a `StdScheduledMutator`
+/// let mutations = Packet::mapped_mutations();
+///
+/// let mut packet = Packet {
+///     header: Header { version: 1, compressed: false },
+///     payload: vec![],
+///     trailer: None,
+/// };
+/// packet.payload_mut().extend(b"data");
+/// assert_eq!(packet.payload(), b"data");
+/// ```
+pub trait MappedMutations {
+    /// The [`crate::mutators::MutatorsTuple`] of the mutations of the fields, including the crossovers with the corpus
+    type Mutations;
+    /// The [`crate::mutators::MutatorsTuple`] of the mutations of the fields without the crossovers, for nesting this input in another one
+    type MutationsNoCrossover;
+
+    /// The mutations of the fields, including the crossovers with the corpus
+    fn mapped_mutations() -> Self::Mutations;
+
+    /// The mutations of the fields without the crossovers.
+    ///
+    /// The crossovers get the other inputs from the corpus, and so only work for the input type of the corpus,
+    /// not for the inputs nested in it.
+    fn mapped_mutations_no_crossover() -> Self::MutationsNoCrossover;
+}
+
+/// A [`Mutator`] toggling a `bool`
+#[derive(Debug, Default)]
+pub struct ToggleBoolMutator;
+
+impl<S> Mutator<bool, S> for ToggleBoolMutator {
+    fn mutate(&mut self, _state: &mut S, input: &mut bool) -> Result<MutationResult, Error> {
+        *input = !*input;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ToggleBoolMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ToggleBoolMutator");
+        &NAME
+    }
+}
+
+impl ToggleBoolMutator {
+    /// Creates a new [`ToggleBoolMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] toggling an `Option<Vec<u8>>`, setting it to [`None`] if it is not, and to a random byte if it is
+#[derive(Debug, Default)]
+pub struct ToggleOptionalBytesMutator;
+
+impl<S> Mutator<Option<Vec<u8>>, S> for ToggleOptionalBytesMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut Option<Vec<u8>>,
+    ) -> Result<MutationResult, Error> {
+        *input = match input {
+            None => Some(vec![state.rand_mut().below(nonzero!(256)) as u8]),
+            Some(_) => None,
+        };
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for ToggleOptionalBytesMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("ToggleOptionalBytesMutator");
+        &NAME
+    }
+}
+
+impl ToggleOptionalBytesMutator {
+    /// Creates a new [`ToggleOptionalBytesMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] for the primitive integers, adding or subtracting a small value,
+/// flipping a bit, or setting a random value
+#[derive(Debug, Default)]
+pub struct IntMutator;
+
+macro_rules! impl_int_mutator {
+    ($($int:ty),*) => {
+        $(
+            impl<S> Mutator<$int, S> for IntMutator
+            where
+                S: HasRand,
+            {
+                fn mutate(&mut self, state: &mut S, input: &mut $int) -> Result<MutationResult, Error> {
+                    let rand = state.rand_mut();
+                    let old = *input;
+                    // Every integer type holds the steps, and takes its bytes of the random `u64` without a cast
+                    *input = match rand.below(nonzero!(4)) {
+                        0 => input.wrapping_add(<$int>::try_from(rand.between(1, 16)).unwrap()),
+                        1 => input.wrapping_sub(<$int>::try_from(rand.between(1, 16)).unwrap()),
+                        2 => *input ^ (1 << rand.below(NonZero::new(<$int>::BITS as usize).unwrap())),
+                        _ => <$int>::from_ne_bytes(
+                            rand.next().to_ne_bytes()[..size_of::<$int>()].try_into().unwrap(),
+                        ),
+                    };
+                    if *input == old {
+                        Ok(MutationResult::Skipped)
+                    } else {
+                        Ok(MutationResult::Mutated)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_int_mutator!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Named for IntMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("IntMutator");
+        &NAME
+    }
+}
+
+impl IntMutator {
+    /// Creates a new [`IntMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
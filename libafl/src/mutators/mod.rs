@@ -21,10 +21,31 @@ pub mod gramatron;
 pub use gramatron::*;
 pub mod grimoire;
 pub use grimoire::*;
+pub mod http;
+pub use http::*;
+pub mod syscalls;
+pub use syscalls::*;
+pub mod token_stream;
+pub use token_stream::*;
+pub mod constrained;
+pub use constrained::*;
+pub mod graph;
+pub use graph::*;
+pub mod replay;
+pub use replay::*;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "arbitrary")]
+pub use self::arbitrary::*;
+
 pub mod mapping;
 pub use mapping::*;
+pub mod mapped_mutations;
+pub use mapped_mutations::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod rand_stream;
+pub use rand_stream::*;
 
 #[cfg(feature = "unicode")]
 pub mod unicode;
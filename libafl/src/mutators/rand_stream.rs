@@ -0,0 +1,107 @@
+//! A [`Mutator`] drawing all its random decisions from its own stream of [`libafl_bolts::rands::RandStreams`].
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::{rands::RandStreamHandle, Named};
+
+use crate::{
+    corpus::CorpusId,
+    mutators::{MutationResult, Mutator},
+    state::HasRandStreams,
+    Error,
+};
+
+/// Runs the `inner` [`Mutator`] on its own random stream, see [`HasRandStreams::with_rand_stream`].
+///
+/// Other components drawing from the rand of the state, or from other streams, do not change
+/// the mutations of the `inner` mutator for a given campaign seed, and vice versa.
+#[derive(Debug)]
+pub struct RandStreamMutator<M> {
+    inner: M,
+    handle: RandStreamHandle,
+}
+
+impl<M> RandStreamMutator<M>
+where
+    M: Named,
+{
+    /// Creates a new [`RandStreamMutator`], drawing from the stream named like the `inner` mutator
+    pub fn new(inner: M) -> Self {
+        let handle = RandStreamHandle::with_name(inner.name().clone());
+        Self { inner, handle }
+    }
+}
+
+impl<M> RandStreamMutator<M> {
+    /// Creates a new [`RandStreamMutator`], drawing from the stream of `handle`
+    pub fn with_handle(inner: M, handle: RandStreamHandle) -> Self {
+        Self { inner, handle }
+    }
+
+    /// The handle of the stream the inner mutator draws from
+    pub fn handle(&self) -> &RandStreamHandle {
+        &self.handle
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for RandStreamMutator<M>
+where
+    M: Mutator<I, S>,
+    S: HasRandStreams,
+    S::Rand: Default,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        state.with_rand_stream(&self.handle, |state| self.inner.mutate(state, input))
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        state.with_rand_stream(&self.handle, |state| {
+            self.inner.post_exec(state, new_corpus_id)
+        })
+    }
+}
+
+impl<M> Named for RandStreamMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &Cow<'static, str> {
+        self.inner.name()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::{Rand, RandStreamHandle};
+
+    use super::RandStreamMutator;
+    use crate::{
+        inputs::BytesInput,
+        mutators::{BitFlipMutator, Mutator},
+        state::{HasRand, HasRandStreams, NopState},
+    };
+
+    #[test]
+    fn test_rand_stream_mutator() {
+        let mutate = |perturb: bool| {
+            let mut state = NopState::<BytesInput>::new();
+            state.rand_streams_mut().set_seed(1337);
+            if perturb {
+                state.rand_mut().next();
+                state
+                    .rand_stream_mut(&RandStreamHandle::new("other"))
+                    .next();
+            }
+            let mut main = *state.rand();
+            let mut mutator = RandStreamMutator::new(BitFlipMutator::new());
+            let mut input = BytesInput::new(vec![0; 64]);
+            for _ in 0..16 {
+                mutator.mutate(&mut state, &mut input).unwrap();
+            }
+            // The rand of the state is left alone
+            assert_eq!(state.rand_mut().next(), main.next());
+            input
+        };
+        assert_eq!(mutate(false), mutate(true));
+    }
+}
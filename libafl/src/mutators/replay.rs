@@ -0,0 +1,109 @@
+//! The [`ReplayScheduledMutator`] records or replays the mutations chosen by a [`ScheduledMutator`],
+//! see [`crate::state::replay`].
+
+use alloc::borrow::Cow;
+
+use libafl_bolts::Named;
+
+use super::MutationId;
+use crate::{
+    corpus::CorpusId,
+    mutators::{ComposedByMutations, MutationResult, Mutator, MutatorsTuple, ScheduledMutator},
+    state::{
+        replay::{replay_decision, ReplayDecision},
+        Stoppable,
+    },
+    Error, HasMetadata,
+};
+
+/// Wraps a [`ScheduledMutator`], recording the mutations it stacks, or stacking the replayed ones instead
+#[derive(Debug)]
+pub struct ReplayScheduledMutator<SM> {
+    name: Cow<'static, str>,
+    scheduled: SM,
+}
+
+impl<SM> Named for ReplayScheduledMutator<SM> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S, SM> Mutator<I, S> for ReplayScheduledMutator<SM>
+where
+    S: HasMetadata + Stoppable,
+    SM: ScheduledMutator<I, S>,
+    SM::Mutations: MutatorsTuple<I, S>,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        self.scheduled_mutate(state, input)
+    }
+
+    fn post_exec(&mut self, state: &mut S, new_corpus_id: Option<CorpusId>) -> Result<(), Error> {
+        self.scheduled.post_exec(state, new_corpus_id)
+    }
+}
+
+impl<SM> ComposedByMutations for ReplayScheduledMutator<SM>
+where
+    SM: ComposedByMutations,
+{
+    type Mutations = SM::Mutations;
+    #[inline]
+    fn mutations(&self) -> &SM::Mutations {
+        self.scheduled.mutations()
+    }
+
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut SM::Mutations {
+        self.scheduled.mutations_mut()
+    }
+}
+
+impl<I, S, SM> ScheduledMutator<I, S> for ReplayScheduledMutator<SM>
+where
+    S: HasMetadata + Stoppable,
+    SM: ScheduledMutator<I, S>,
+    SM::Mutations: MutatorsTuple<I, S>,
+{
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.scheduled.iterations(state, input)
+    }
+
+    fn schedule(&self, state: &mut S, input: &I) -> MutationId {
+        self.scheduled.schedule(state, input)
+    }
+
+    fn scheduled_mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        // The wrapped mutator always decides, so that it consumes the RNG as when recording
+        let live = ReplayDecision::Iterations(self.iterations(state, input));
+        let ReplayDecision::Iterations(num) = replay_decision(state, live)? else {
+            unreachable!("The replayed decisions are checked to be of the same kind");
+        };
+        for _ in 0..num {
+            let live = ReplayDecision::Mutation(self.schedule(state, input));
+            let ReplayDecision::Mutation(idx) = replay_decision(state, live)? else {
+                unreachable!("The replayed decisions are checked to be of the same kind");
+            };
+            let outcome = self.mutations_mut().get_and_mutate(idx, state, input)?;
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+            }
+        }
+        Ok(r)
+    }
+}
+
+impl<SM> ReplayScheduledMutator<SM>
+where
+    SM: Named,
+{
+    /// Creates a new [`ReplayScheduledMutator`] wrapping the `scheduled` mutator
+    pub fn new(scheduled: SM) -> Self {
+        Self {
+            name: Cow::from(format!("ReplayScheduledMutator[{}]", scheduled.name())),
+            scheduled,
+        }
+    }
+}
@@ -5,6 +5,7 @@ use core::{
     fmt::Debug,
     num::NonZero,
     ops::{Deref, DerefMut},
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
 use libafl_bolts::{
@@ -58,8 +59,24 @@ impl LogMutationMetadata {
     pub fn new(list: Vec<Cow<'static, str>>) -> Self {
         Self { list }
     }
+
+    /// The number of stacked mutations of the input currently evaluated, if a [`LoggerScheduledMutator`] mutated it.
+    ///
+    /// The new testcase only gets its [`struct@LogMutationMetadata`] in [`Mutator::post_exec`],
+    /// after the feedbacks ran, so they read the length here instead.
+    /// There is a single slot for the whole process.
+    #[must_use]
+    pub fn current_len() -> Option<usize> {
+        match CURRENT_MUTATION_STACK.load(Ordering::Relaxed) {
+            0 => None,
+            len => Some(len),
+        }
+    }
 }
 
+/// The number of stacked mutations of the input currently evaluated, 0 if not mutated by a [`LoggerScheduledMutator`]
+static CURRENT_MUTATION_STACK: AtomicUsize = AtomicUsize::new(0);
+
 /// A [`Mutator`] that composes multiple mutations into one.
 pub trait ComposedByMutations {
     /// The mutations of this
@@ -236,6 +253,7 @@ where
         };
         // Always reset the log for each run
         self.mutation_log.clear();
+        CURRENT_MUTATION_STACK.store(0, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -290,6 +308,7 @@ where
                 r = MutationResult::Mutated;
             }
         }
+        CURRENT_MUTATION_STACK.store(self.mutation_log.len(), Ordering::Relaxed);
         Ok(r)
     }
 }
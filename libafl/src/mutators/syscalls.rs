@@ -0,0 +1,316 @@
+//! Mutators for [`SyscallSequenceInput`]s, inserting calls, mutating arguments and splicing sequences.
+//!
+//! The mutators keep the [`SyscallArg::Result`] arguments referencing prior calls producing the right resource.
+use alloc::{borrow::Cow, vec::Vec};
+use core::num::NonZero;
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::Corpus,
+    generators::{syscalls::truncate_bits, SyscallSequenceGenerator},
+    inputs::{SyscallArg, SyscallArgType, SyscallCall, SyscallSequenceInput, INVALID_RESOURCE},
+    mutators::{MutationResult, Mutator},
+    nonzero, random_corpus_id,
+    state::{HasCorpus, HasRand},
+    Error,
+};
+
+/// A [`Mutator`] inserting a random call at a random position of a [`SyscallSequenceInput`]
+#[derive(Debug)]
+pub struct SyscallInsertMutator<'a> {
+    generator: &'a SyscallSequenceGenerator<'a>,
+}
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallInsertMutator<'_>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.calls().len();
+        if len >= self.generator.max_calls().get() || self.generator.table().syscalls.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let index = rand.between(0, len);
+        let call = self.generator.generate_call(rand, &input.calls()[..index]);
+        input.insert_call(index, call);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for SyscallInsertMutator<'_> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallInsertMutator");
+        &NAME
+    }
+}
+
+impl<'a> SyscallInsertMutator<'a> {
+    /// Creates a new [`SyscallInsertMutator`], generating the calls with the `generator`
+    #[must_use]
+    pub fn new(generator: &'a SyscallSequenceGenerator<'a>) -> Self {
+        Self { generator }
+    }
+}
+
+/// A [`Mutator`] mutating a random argument of a random call of a [`SyscallSequenceInput`]
+#[derive(Debug)]
+pub struct SyscallMutateArgMutator<'a> {
+    generator: &'a SyscallSequenceGenerator<'a>,
+}
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallMutateArgMutator<'_>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        let table = self.generator.table();
+        let rand = state.rand_mut();
+        let Some(index) = rand.choose(
+            input
+                .calls()
+                .iter()
+                .enumerate()
+                .filter(|(_, call)| !call.args.is_empty())
+                .map(|(i, _)| i),
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let (prior, calls) = input.calls_mut().split_at_mut(index);
+        let call = &mut calls[0];
+        // # Safety
+        // The call has arguments, checked above.
+        let arg_idx = rand.below(unsafe { NonZero::new(call.args.len()).unwrap_unchecked() });
+        let Some(ty) = table
+            .get(call.syscall)
+            .and_then(|description| description.args.get(arg_idx))
+        else {
+            return Ok(MutationResult::Skipped);
+        };
+        let arg = &mut call.args[arg_idx];
+
+        match (ty, &mut *arg) {
+            // tweak the current value, half of the time
+            (SyscallArgType::Int { bits }, SyscallArg::Int(value)) if rand.coinflip(0.5) => {
+                let delta = rand.between(1, 16) as u64;
+                let bit = rand.below(NonZero::new((*bits).clamp(1, 64) as usize).unwrap());
+                *value = match rand.below(nonzero!(3)) {
+                    0 => *value ^ (1 << bit),
+                    1 => value.wrapping_add(delta),
+                    _ => value.wrapping_sub(delta),
+                };
+                *value = truncate_bits(*value, *bits);
+            }
+            (SyscallArgType::Buffer { .. }, SyscallArg::Buffer(buf))
+                if !buf.is_empty() && rand.coinflip(0.5) =>
+            {
+                // # Safety
+                // The buffer is not empty, checked above.
+                let idx = rand.below(unsafe { NonZero::new(buf.len()).unwrap_unchecked() });
+                buf[idx] ^= 1 << rand.below(nonzero!(8));
+            }
+            _ => *arg = self.generator.generate_arg(rand, ty, prior),
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for SyscallMutateArgMutator<'_> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallMutateArgMutator");
+        &NAME
+    }
+}
+
+impl<'a> SyscallMutateArgMutator<'a> {
+    /// Creates a new [`SyscallMutateArgMutator`], generating the new arguments with the `generator`
+    #[must_use]
+    pub fn new(generator: &'a SyscallSequenceGenerator<'a>) -> Self {
+        Self { generator }
+    }
+}
+
+/// A [`Mutator`] splicing the tail of the calls of another [`SyscallSequenceInput`] of the corpus
+/// after a random prefix of the input.
+///
+/// The references to the calls of the other input that are not copied are
+/// replaced by references to prior calls of the input producing the same resource.
+#[derive(Debug)]
+pub struct SyscallSpliceMutator<'a> {
+    generator: &'a SyscallSequenceGenerator<'a>,
+}
+
+impl<S> Mutator<SyscallSequenceInput, S> for SyscallSpliceMutator<'_>
+where
+    S: HasRand + HasCorpus,
+    S::Corpus: Corpus<Input = SyscallSequenceInput>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut SyscallSequenceInput,
+    ) -> Result<MutationResult, Error> {
+        let id = random_corpus_id!(state.corpus(), state.rand_mut());
+        let other_calls = {
+            let mut other_testcase = state.corpus().get(id)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.calls().to_vec()
+        };
+        let Some(other_len) = NonZero::new(other_calls.len()) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let rand = state.rand_mut();
+        let split_at = rand.between(0, input.calls().len());
+        let from = rand.below(other_len);
+
+        let table = self.generator.table();
+        let mut calls: Vec<SyscallCall> = input.calls()[..split_at].to_vec();
+        for mut call in other_calls.into_iter().skip(from) {
+            for (arg_idx, arg) in call.args.iter_mut().enumerate() {
+                let SyscallArg::Result(referenced) = *arg else {
+                    continue;
+                };
+                *arg = if referenced >= from {
+                    SyscallArg::Result(referenced - from + split_at)
+                } else if let Some(SyscallArgType::Resource { kind }) = table
+                    .get(call.syscall)
+                    .and_then(|description| description.args.get(arg_idx))
+                {
+                    self.generator.resource_arg(rand, kind, &calls)
+                } else {
+                    SyscallArg::Int(INVALID_RESOURCE)
+                };
+            }
+            calls.push(call);
+        }
+        calls.truncate(self.generator.max_calls().get());
+
+        if calls == input.calls() {
+            return Ok(MutationResult::Skipped);
+        }
+        *input.calls_mut() = calls;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for SyscallSpliceMutator<'_> {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallSpliceMutator");
+        &NAME
+    }
+}
+
+impl<'a> SyscallSpliceMutator<'a> {
+    /// Creates a new [`SyscallSpliceMutator`], with the table and the maximum count of calls of the `generator`
+    #[must_use]
+    pub fn new(generator: &'a SyscallSequenceGenerator<'a>) -> Self {
+        Self { generator }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use libafl_bolts::rands::StdRand;
+
+    use super::{SyscallInsertMutator, SyscallMutateArgMutator, SyscallSpliceMutator};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        generators::{Generator, SyscallSequenceGenerator},
+        inputs::{
+            SyscallArg, SyscallArgType, SyscallDescription, SyscallSequenceInput, SyscallTable,
+        },
+        mutators::Mutator,
+        nonzero,
+        state::{HasCorpus, StdState},
+    };
+
+    fn table() -> SyscallTable {
+        SyscallTable::new(vec![
+            SyscallDescription::new(
+                "open",
+                2,
+                vec![
+                    SyscallArgType::Buffer { max_len: 8 },
+                    SyscallArgType::Flags(vec![1, 2, 0x40]),
+                ],
+                Some("fd"),
+            ),
+            SyscallDescription::new(
+                "write",
+                1,
+                vec![
+                    SyscallArgType::Resource { kind: "fd".into() },
+                    SyscallArgType::Buffer { max_len: 8 },
+                    SyscallArgType::Int { bits: 16 },
+                ],
+                None,
+            ),
+            SyscallDescription::new(
+                "close",
+                3,
+                vec![SyscallArgType::Resource { kind: "fd".into() }],
+                None,
+            ),
+        ])
+    }
+
+    /// Checks that every result argument references a prior call producing the resource
+    fn assert_valid(table: &SyscallTable, input: &SyscallSequenceInput) {
+        for (i, call) in input.calls().iter().enumerate() {
+            for arg in &call.args {
+                if let SyscallArg::Result(referenced) = arg {
+                    assert!(*referenced < i);
+                    assert_eq!(table.ret_kind(&input.calls()[*referenced]), Some("fd"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_syscall_mutators() {
+        let table = table();
+        let mut generator = SyscallSequenceGenerator::new(&table, nonzero!(8));
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        for _ in 0..4 {
+            let input = generator.generate(&mut state).unwrap();
+            assert_valid(&table, &input);
+            state.corpus_mut().add(Testcase::new(input)).unwrap();
+        }
+
+        let mut input = generator.generate(&mut state).unwrap();
+        let mut insert = SyscallInsertMutator::new(&generator);
+        let mut mutate_arg = SyscallMutateArgMutator::new(&generator);
+        let mut splice = SyscallSpliceMutator::new(&generator);
+        for _ in 0..256 {
+            insert.mutate(&mut state, &mut input).unwrap();
+            assert_valid(&table, &input);
+            mutate_arg.mutate(&mut state, &mut input).unwrap();
+            assert_valid(&table, &input);
+            splice.mutate(&mut state, &mut input).unwrap();
+            assert_valid(&table, &input);
+            assert!(input.calls().len() <= 8);
+        }
+    }
+}
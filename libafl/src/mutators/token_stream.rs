@@ -0,0 +1,267 @@
+//! Mutators for [`TokenStreamInput`]s, inserting, replacing, duplicating and rotating tokens.
+//!
+//! The mutators only use the ids of the [`TokenTable`], so the mutated streams stay lexically valid.
+use alloc::borrow::Cow;
+use core::num::NonZero;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+
+use crate::{
+    inputs::{TokenStreamInput, TokenTable},
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// A [`Mutator`] inserting a random token at a random position of a [`TokenStreamInput`]
+#[derive(Debug)]
+pub struct TokenStreamInsertMutator {
+    token_count: usize,
+}
+
+impl<S> Mutator<TokenStreamInput, S> for TokenStreamInsertMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenStreamInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.ids().len();
+        let Some(token_count) = NonZero::new(self.token_count) else {
+            return Ok(MutationResult::Skipped);
+        };
+        if len >= state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let index = rand.between(0, len);
+        let id = rand.below(token_count) as u32;
+        input.ids_mut().insert(index, id);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for TokenStreamInsertMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TokenStreamInsertMutator");
+        &NAME
+    }
+}
+
+impl TokenStreamInsertMutator {
+    /// Creates a new [`TokenStreamInsertMutator`], inserting the tokens of the `table`
+    #[must_use]
+    pub fn new(table: &TokenTable) -> Self {
+        Self {
+            token_count: table.len(),
+        }
+    }
+}
+
+/// A [`Mutator`] replacing a random token of a [`TokenStreamInput`] by another token
+#[derive(Debug)]
+pub struct TokenStreamReplaceMutator {
+    token_count: usize,
+}
+
+impl<S> Mutator<TokenStreamInput, S> for TokenStreamReplaceMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenStreamInput,
+    ) -> Result<MutationResult, Error> {
+        let (Some(len), Some(other_count)) = (
+            NonZero::new(input.ids().len()),
+            NonZero::new(self.token_count.saturating_sub(1)),
+        ) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let rand = state.rand_mut();
+        let index = rand.below(len);
+        let old = input.ids()[index];
+        // pick any token but the old one
+        let mut id = rand.below(other_count) as u32;
+        if id >= old {
+            id += 1;
+        }
+        input.ids_mut()[index] = id;
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for TokenStreamReplaceMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TokenStreamReplaceMutator");
+        &NAME
+    }
+}
+
+impl TokenStreamReplaceMutator {
+    /// Creates a new [`TokenStreamReplaceMutator`], replacing by the tokens of the `table`
+    #[must_use]
+    pub fn new(table: &TokenTable) -> Self {
+        Self {
+            token_count: table.len(),
+        }
+    }
+}
+
+/// A [`Mutator`] duplicating a random range of tokens of a [`TokenStreamInput`], for example a statement
+#[derive(Debug, Default)]
+pub struct TokenStreamDuplicateMutator;
+
+impl<S> Mutator<TokenStreamInput, S> for TokenStreamDuplicateMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenStreamInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.ids().len();
+        let max_size = state.max_size();
+        let Some(non_zero_len) = NonZero::new(len) else {
+            return Ok(MutationResult::Skipped);
+        };
+        if len >= max_size {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let start = rand.below(non_zero_len);
+        let end = rand.between(start + 1, len.min(start + max_size - len));
+        let range = input.ids()[start..end].to_vec();
+        input.ids_mut().splice(end..end, range);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for TokenStreamDuplicateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TokenStreamDuplicateMutator");
+        &NAME
+    }
+}
+
+impl TokenStreamDuplicateMutator {
+    /// Creates a new [`TokenStreamDuplicateMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A [`Mutator`] rotating a random range of tokens of a [`TokenStreamInput`], moving tokens around
+#[derive(Debug, Default)]
+pub struct TokenStreamRotateMutator;
+
+impl<S> Mutator<TokenStreamInput, S> for TokenStreamRotateMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut TokenStreamInput,
+    ) -> Result<MutationResult, Error> {
+        let len = input.ids().len();
+        if len < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+        let rand = state.rand_mut();
+        let start = rand.between(0, len - 2);
+        let end = rand.between(start + 2, len);
+        let mid = rand.between(1, end - start - 1);
+        let range = &mut input.ids_mut()[start..end];
+        if range.iter().all(|id| *id == range[0]) {
+            return Ok(MutationResult::Skipped);
+        }
+        range.rotate_left(mid);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for TokenStreamRotateMutator {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("TokenStreamRotateMutator");
+        &NAME
+    }
+}
+
+impl TokenStreamRotateMutator {
+    /// Creates a new [`TokenStreamRotateMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Get the mutations for [`TokenStreamInput`]s of the tokens of the `table`
+#[must_use]
+pub fn token_stream_mutations(
+    table: &TokenTable,
+) -> tuple_list_type!(
+    TokenStreamInsertMutator,
+    TokenStreamReplaceMutator,
+    TokenStreamDuplicateMutator,
+    TokenStreamRotateMutator,
+) {
+    tuple_list!(
+        TokenStreamInsertMutator::new(table),
+        TokenStreamReplaceMutator::new(table),
+        TokenStreamDuplicateMutator::new(),
+        TokenStreamRotateMutator::new(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::token_stream_mutations;
+    use crate::{
+        corpus::InMemoryCorpus,
+        feedbacks::ConstFeedback,
+        inputs::{TokenStreamInput, TokenTable},
+        mutators::{MutationResult, MutatorsTuple},
+        state::{HasMaxSize, StdState},
+    };
+
+    #[test]
+    fn test_token_stream_mutations() {
+        let table = TokenTable::new(["(", ")", "+", "1", "x"]);
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<TokenStreamInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state.set_max_size(64);
+
+        let mut mutations = token_stream_mutations(&table);
+        let mut input = TokenStreamInput::new(vec![0, 3, 2, 4, 1]);
+        for i in 0..1000 {
+            let result = mutations
+                .get_and_mutate((i % 4).into(), &mut state, &mut input)
+                .unwrap();
+            if result == MutationResult::Mutated {
+                assert!(!input.ids().is_empty());
+            }
+            assert!(input.ids().len() <= 64);
+            assert!(input.ids().iter().all(|id| table.get(*id).is_some()));
+        }
+    }
+}
@@ -19,6 +19,18 @@ impl ConcolicMetadata {
         std::iter::from_fn(move || parser.next_message()).flatten()
     }
 
+    /// The size of the trace, in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Whether the trace is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     pub(crate) fn from_buffer(buffer: Vec<u8>) -> Self {
         Self { buffer }
     }
@@ -1,6 +1,8 @@
 //! # Concolic Tracing
 #[cfg(feature = "std")]
-use alloc::vec::Vec;
+use alloc::{format, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use core::ops::Range;
 use core::{
     fmt::{Debug, Display, Error, Formatter},
     num::NonZeroUsize,
@@ -369,6 +371,97 @@ pub enum SymExpr {
     },
 }
 
+#[cfg(feature = "std")]
+impl SymExpr {
+    /// The references to the expressions this message uses, for example to rewrite them
+    #[allow(clippy::too_many_lines)]
+    pub fn operands_mut(&mut self) -> Vec<&mut SymExprRef> {
+        match self {
+            SymExpr::InputByte { .. }
+            | SymExpr::Integer { .. }
+            | SymExpr::Integer128 { .. }
+            | SymExpr::IntegerFromBuffer { .. }
+            | SymExpr::Float { .. }
+            | SymExpr::NullPointer
+            | SymExpr::True
+            | SymExpr::False
+            | SymExpr::Bool { .. }
+            | SymExpr::Call { .. }
+            | SymExpr::Return { .. }
+            | SymExpr::BasicBlock { .. } => vec![],
+            SymExpr::Neg { op }
+            | SymExpr::FloatAbs { op }
+            | SymExpr::FloatNeg { op }
+            | SymExpr::Not { op }
+            | SymExpr::Sext { op, .. }
+            | SymExpr::Zext { op, .. }
+            | SymExpr::Trunc { op, .. }
+            | SymExpr::IntToFloat { op, .. }
+            | SymExpr::FloatToFloat { op, .. }
+            | SymExpr::BitsToFloat { op, .. }
+            | SymExpr::FloatToBits { op }
+            | SymExpr::FloatToSignedInteger { op, .. }
+            | SymExpr::FloatToUnsignedInteger { op, .. }
+            | SymExpr::BoolToBit { op, .. }
+            | SymExpr::Extract { op, .. }
+            | SymExpr::PathConstraint { constraint: op, .. } => vec![op],
+            SymExpr::Add { a, b }
+            | SymExpr::Sub { a, b }
+            | SymExpr::Mul { a, b }
+            | SymExpr::UnsignedDiv { a, b }
+            | SymExpr::SignedDiv { a, b }
+            | SymExpr::UnsignedRem { a, b }
+            | SymExpr::SignedRem { a, b }
+            | SymExpr::ShiftLeft { a, b }
+            | SymExpr::LogicalShiftRight { a, b }
+            | SymExpr::ArithmeticShiftRight { a, b }
+            | SymExpr::SignedLessThan { a, b }
+            | SymExpr::SignedLessEqual { a, b }
+            | SymExpr::SignedGreaterThan { a, b }
+            | SymExpr::SignedGreaterEqual { a, b }
+            | SymExpr::UnsignedLessThan { a, b }
+            | SymExpr::UnsignedLessEqual { a, b }
+            | SymExpr::UnsignedGreaterThan { a, b }
+            | SymExpr::UnsignedGreaterEqual { a, b }
+            | SymExpr::Equal { a, b }
+            | SymExpr::NotEqual { a, b }
+            | SymExpr::BoolAnd { a, b }
+            | SymExpr::BoolOr { a, b }
+            | SymExpr::BoolXor { a, b }
+            | SymExpr::And { a, b }
+            | SymExpr::Or { a, b }
+            | SymExpr::Xor { a, b }
+            | SymExpr::FloatOrdered { a, b }
+            | SymExpr::FloatOrderedGreaterThan { a, b }
+            | SymExpr::FloatOrderedGreaterEqual { a, b }
+            | SymExpr::FloatOrderedLessThan { a, b }
+            | SymExpr::FloatOrderedLessEqual { a, b }
+            | SymExpr::FloatOrderedEqual { a, b }
+            | SymExpr::FloatOrderedNotEqual { a, b }
+            | SymExpr::FloatUnordered { a, b }
+            | SymExpr::FloatUnorderedGreaterThan { a, b }
+            | SymExpr::FloatUnorderedGreaterEqual { a, b }
+            | SymExpr::FloatUnorderedLessThan { a, b }
+            | SymExpr::FloatUnorderedLessEqual { a, b }
+            | SymExpr::FloatUnorderedEqual { a, b }
+            | SymExpr::FloatUnorderedNotEqual { a, b }
+            | SymExpr::FloatAdd { a, b }
+            | SymExpr::FloatSub { a, b }
+            | SymExpr::FloatMul { a, b }
+            | SymExpr::FloatDiv { a, b }
+            | SymExpr::FloatRem { a, b }
+            | SymExpr::Concat { a, b }
+            | SymExpr::Insert {
+                target: a,
+                to_insert: b,
+                ..
+            } => vec![a, b],
+            SymExpr::ExpressionsUnreachable { exprs } => exprs.iter_mut().collect(),
+            SymExpr::Ite { cond, a, b } => vec![cond, a, b],
+        }
+    }
+}
+
 #[cfg(feature = "std")]
 pub mod serialization_format;
 
@@ -376,14 +469,73 @@ pub mod serialization_format;
 pub const HITMAP_ENV_NAME: &str = "LIBAFL_CONCOLIC_HITMAP";
 
 /// The name of the environment variable that contains the byte offsets to be symbolized.
+///
+/// The value is a comma-separated list of offsets and `start-end` ranges, the end excluded,
+/// see [`selective_symbolication_env_value`] and [`parse_selective_symbolication`].
 pub const SELECTIVE_SYMBOLICATION_ENV_NAME: &str = "LIBAFL_SELECTIVE_SYMBOLICATION";
 
+/// Formats the input `regions` to be symbolized as the value of [`SELECTIVE_SYMBOLICATION_ENV_NAME`]
+#[cfg(feature = "std")]
+#[must_use]
+pub fn selective_symbolication_env_value(regions: &[Range<usize>]) -> String {
+    regions
+        .iter()
+        .filter(|region| !region.is_empty())
+        .map(|region| {
+            if region.len() == 1 {
+                format!("{}", region.start)
+            } else {
+                format!("{}-{}", region.start, region.end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses the value of [`SELECTIVE_SYMBOLICATION_ENV_NAME`] into the input regions to be symbolized
+#[cfg(feature = "std")]
+pub fn parse_selective_symbolication(value: &str) -> Result<Vec<Range<usize>>, crate::Error> {
+    let parse = |offset: &str| {
+        offset.trim().parse::<usize>().map_err(|_| {
+            crate::Error::illegal_argument(format!("Invalid offset to symbolize: {offset}"))
+        })
+    };
+    value
+        .split(',')
+        .filter(|item| !item.trim().is_empty())
+        .map(|item| {
+            if let Some((start, end)) = item.split_once('-') {
+                Ok(parse(start)?..parse(end)?)
+            } else {
+                let offset = parse(item)?;
+                Ok(offset..offset + 1)
+            }
+        })
+        .collect()
+}
+
 /// The name of the environment variable that signals the runtime to concretize floating point operations.
 pub const NO_FLOAT_ENV_NAME: &str = "LIBAFL_CONCOLIC_NO_FLOAT";
 
 /// The name of the environment variable that signals the runtime to perform expression pruning.
 pub const EXPRESSION_PRUNING: &str = "LIBAFL_CONCOLIC_EXPRESSION_PRUNING";
 
+/// The name of the environment variable that signals the tracing runtime to only trace the path constraints,
+/// and the expressions they depend on.
+pub const ONLY_PATH_CONSTRAINTS_ENV_NAME: &str = "LIBAFL_CONCOLIC_ONLY_PATH_CONSTRAINTS";
+
+/// The name of the environment variable that contains the maximum depth of the traced expressions.
+/// Deeper expressions get concretized.
+pub const MAX_EXPRESSION_DEPTH_ENV_NAME: &str = "LIBAFL_CONCOLIC_MAX_EXPRESSION_DEPTH";
+
+/// The name of the environment variable that contains the maximum count of path constraints traced per site,
+/// for example for the branch of a loop.
+pub const MAX_CONSTRAINTS_PER_SITE_ENV_NAME: &str = "LIBAFL_CONCOLIC_MAX_CONSTRAINTS_PER_SITE";
+
+/// The name of the environment variable that signals the tracing runtime to trace a path constraint only once
+/// per site, direction and call stack.
+pub const DEDUPLICATE_CONSTRAINTS_ENV_NAME: &str = "LIBAFL_CONCOLIC_DEDUPLICATE_CONSTRAINTS";
+
 #[cfg(feature = "std")]
 mod metadata;
 #[cfg(feature = "std")]
@@ -393,3 +545,24 @@ pub use metadata::ConcolicMetadata;
 mod observer;
 #[cfg(feature = "std")]
 pub use observer::ConcolicObserver;
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::{parse_selective_symbolication, selective_symbolication_env_value};
+
+    #[test]
+    fn test_selective_symbolication_env_value() {
+        let regions = vec![0..1, 4..8, 9..9, 16..17];
+        let value = selective_symbolication_env_value(&regions);
+        assert_eq!(value, "0,4-8,16");
+        assert_eq!(
+            parse_selective_symbolication(&value).unwrap(),
+            vec![0..1, 4..8, 16..17]
+        );
+        assert_eq!(
+            parse_selective_symbolication("1, 2,3").unwrap(),
+            vec![1..2, 2..3, 3..4]
+        );
+        assert!(parse_selective_symbolication("1-x").is_err());
+    }
+}
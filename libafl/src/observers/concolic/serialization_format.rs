@@ -384,6 +384,7 @@ impl<W: Write + Seek> MessageFileWriter<W> {
                 *cond = self.make_relative(*cond);
                 *a = self.make_relative(*a);
                 *b = self.make_relative(*b);
+                self.id_counter += 1;
             }
         }
         self.serialization_options
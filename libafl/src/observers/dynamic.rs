@@ -0,0 +1,235 @@
+//! Observers assembled at runtime, for example from a config file.
+//!
+//! The tuple lists of observers need to know all observers at compile time.
+//! A [`DynObserversTuple`] holds boxed observers instead, so that plugin-style fuzzers can pick them at runtime.
+//! Feedbacks still find the observers by their [`Handle`](libafl_bolts::tuples::Handle), as in a tuple list.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    any::type_name,
+    fmt::Debug,
+    mem::{size_of, size_of_val},
+};
+
+use libafl_bolts::{serdeany::Wrap, tuples::MatchName};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    executors::ExitKind,
+    observers::{Observer, ObserversTuple},
+    Error,
+};
+
+/// An [`Observer`] that can be boxed in a [`DynObserversTuple`]
+///
+/// This is implemented for all serializable observers.
+pub trait DynObserver<I, S>: Observer<I, S> + erased_serde::Serialize + Debug {
+    /// The [`core::any::type_name`] of the observer, to find it by type in a [`DynObserversTuple`]
+    fn observer_type_name(&self) -> &'static str;
+}
+
+impl<I, O, S> DynObserver<I, S> for O
+where
+    O: Observer<I, S> + Serialize + Debug,
+{
+    fn observer_type_name(&self) -> &'static str {
+        type_name::<O>()
+    }
+}
+
+/// [`ObserversTuple`] holding boxed observers, see the [module-level documentation](self)
+///
+/// The observers can be serialized, but not deserialized, so the event managers must not send them to other clients:
+/// use a [`EventConfig::AlwaysUnique`](crate::events::EventConfig::AlwaysUnique), so that the other clients run the
+/// inputs themselves.
+#[derive(Debug)]
+pub struct DynObserversTuple<I, S> {
+    observers: Vec<Box<dyn DynObserver<I, S>>>,
+}
+
+impl<I, S> Default for DynObserversTuple<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> DynObserversTuple<I, S> {
+    /// Creates a new, empty [`DynObserversTuple`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            observers: Vec::new(),
+        }
+    }
+
+    /// Adds an `observer`, after the others
+    pub fn push<O>(&mut self, observer: O)
+    where
+        O: DynObserver<I, S> + 'static,
+    {
+        self.observers.push(Box::new(observer));
+    }
+
+    /// Adds an `observer`, after the others, builder-style
+    #[must_use]
+    pub fn with<O>(mut self, observer: O) -> Self
+    where
+        O: DynObserver<I, S> + 'static,
+    {
+        self.push(observer);
+        self
+    }
+
+    /// The number of observers
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// If there are no observers
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    /// Iterates over the observers
+    pub fn iter(&self) -> impl Iterator<Item = &dyn DynObserver<I, S>> {
+        self.observers.iter().map(AsRef::as_ref)
+    }
+
+    /// The index of the observer called `name`, if it is of type `T`
+    fn position<T>(&self, name: &str) -> Option<usize> {
+        self.observers.iter().position(|observer| {
+            observer.name() == name
+                && observer.observer_type_name() == type_name::<T>()
+                && size_of_val(&**observer) == size_of::<T>()
+        })
+    }
+}
+
+impl<I, S> From<Vec<Box<dyn DynObserver<I, S>>>> for DynObserversTuple<I, S> {
+    fn from(observers: Vec<Box<dyn DynObserver<I, S>>>) -> Self {
+        Self { observers }
+    }
+}
+
+impl<I, S> MatchName for DynObserversTuple<I, S> {
+    fn match_name<T>(&self, name: &str) -> Option<&T> {
+        let observer = &self.observers[self.position::<T>(name)?];
+        // # Safety
+        // The observer has the type name and the size of `T`.
+        // As with the tuple lists, types that only differ by their lifetimes are not told apart.
+        unsafe { (&raw const **observer).cast::<T>().as_ref() }
+    }
+
+    fn match_name_mut<T>(&mut self, name: &str) -> Option<&mut T> {
+        let idx = self.position::<T>(name)?;
+        // # Safety
+        // Same as in `match_name`
+        unsafe { (&raw mut *self.observers[idx]).cast::<T>().as_mut() }
+    }
+}
+
+impl<I, S> ObserversTuple<I, S> for DynObserversTuple<I, S> {
+    fn pre_exec_all(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        for observer in &mut self.observers {
+            observer.pre_exec(state, input)?;
+        }
+        Ok(())
+    }
+
+    fn post_exec_all(
+        &mut self,
+        state: &mut S,
+        input: &I,
+        exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        for observer in &mut self.observers {
+            observer.post_exec(state, input, exit_kind)?;
+        }
+        Ok(())
+    }
+
+    fn pre_exec_child_all(&mut self, state: &mut S, input: &I) -> Result<(), Error> {
+        for observer in &mut self.observers {
+            observer.pre_exec_child(state, input)?;
+        }
+        Ok(())
+    }
+
+    fn post_exec_child_all(
+        &mut self,
+        state: &mut S,
+        input: &I,
+        exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        for observer in &mut self.observers {
+            observer.post_exec_child(state, input, exit_kind)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I, S> Serialize for DynObserversTuple<I, S> {
+    fn serialize<SE>(&self, serializer: SE) -> Result<SE::Ok, SE::Error>
+    where
+        SE: Serializer,
+    {
+        serializer.collect_seq(self.observers.iter().map(|observer| Wrap(&**observer)))
+    }
+}
+
+impl<'de, I, S> Deserialize<'de> for DynObserversTuple<I, S> {
+    fn deserialize<D>(_deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Err(D::Error::custom(
+            "DynObserversTuple can not be deserialized, use EventConfig::AlwaysUnique",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::{Handle, Handled, MatchNameRef};
+
+    use super::DynObserversTuple;
+    use crate::{
+        executors::ExitKind,
+        inputs::BytesInput,
+        observers::{MapObserver, ObserversTuple, StdMapObserver, TimeObserver},
+        state::NopState,
+    };
+
+    #[test]
+    fn test_dyn_observers_tuple() {
+        let edges = StdMapObserver::owned("edges", vec![0_u8; 16]);
+        let edges_handle = edges.handle();
+        let time = TimeObserver::new("time");
+        let time_handle = time.handle();
+
+        let mut observers = DynObserversTuple::<BytesInput, NopState<BytesInput>>::new()
+            .with(edges)
+            .with(time);
+        assert_eq!(observers.len(), 2);
+
+        let mut state = NopState::new();
+        let input = BytesInput::new(vec![1]);
+        observers.pre_exec_all(&mut state, &input).unwrap();
+        observers
+            .post_exec_all(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+
+        assert!(observers
+            .get(&time_handle)
+            .unwrap()
+            .last_runtime()
+            .is_some());
+        observers.get_mut(&edges_handle).unwrap().set(3, 1);
+        assert_eq!(observers.get(&edges_handle).unwrap().get(3), 1);
+        // The type has to match, not only the name
+        let wrong = Handle::<TimeObserver>::new("edges".into());
+        assert!(observers.get(&wrong).is_none());
+    }
+}
@@ -9,11 +9,16 @@ use core::{
 
 use ahash::RandomState;
 use libafl_bolts::{ownedref::OwnedMutSlice, AsSlice, AsSliceMut, HasLen, Named, Truncate};
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde::{
+    de::DeserializeOwned, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer,
+};
 
 use crate::{
     executors::ExitKind,
-    observers::{DifferentialObserver, Observer},
+    observers::{
+        map::sparse::{SparseMap, SparseMapBuf},
+        DifferentialObserver, Observer,
+    },
     Error,
 };
 
@@ -32,6 +37,8 @@ pub use multi_map::*;
 pub mod owned_map;
 pub use owned_map::*;
 
+pub mod sparse;
+
 /// A trait indicating tracking of observed map values after testcase execution
 ///
 /// Trait marker which indicates that this [`MapObserver`] is tracked for indices or novelties.
@@ -436,14 +443,56 @@ where
 /// The Map Observer retrieves the state of a map,
 /// that will get updated by the target.
 /// A well-known example is the AFL-Style coverage map.
-#[derive(Clone, Serialize, Deserialize, Debug)]
-#[allow(clippy::unsafe_derive_deserialize)]
+///
+/// When serialized, for example to send it with a new testcase, the map is encoded sparsely, see [`sparse`].
+#[derive(Clone, Debug)]
 pub struct StdMapObserver<'a, T, const DIFFERENTIAL: bool> {
     map: OwnedMutSlice<'a, T>,
     initial: T,
     name: Cow<'static, str>,
 }
 
+impl<T, const DIFFERENTIAL: bool> Serialize for StdMapObserver<'_, T, DIFFERENTIAL>
+where
+    T: Serialize + PartialEq,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut observer = serializer.serialize_struct("StdMapObserver", 3)?;
+        observer.serialize_field("map", &SparseMap::new(&self.map, &self.initial))?;
+        observer.serialize_field("initial", &self.initial)?;
+        observer.serialize_field("name", &self.name)?;
+        observer.end()
+    }
+}
+
+impl<'de, T, const DIFFERENTIAL: bool> Deserialize<'de> for StdMapObserver<'_, T, DIFFERENTIAL>
+where
+    T: Deserialize<'de> + Clone,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "StdMapObserver")]
+        struct Buf<T> {
+            map: SparseMapBuf<T>,
+            initial: T,
+            name: Cow<'static, str>,
+        }
+
+        let buf = Buf::<T>::deserialize(deserializer)?;
+        Ok(Self {
+            map: buf.map.into_vec(buf.initial.clone())?.into(),
+            initial: buf.initial,
+            name: buf.name,
+        })
+    }
+}
+
 impl<I, S, T> Observer<I, S> for StdMapObserver<'_, T, false>
 where
     Self: MapObserver,
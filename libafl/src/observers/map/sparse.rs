@@ -0,0 +1,203 @@
+//! Serializes coverage maps as their entries that differ from the initial value.
+//!
+//! Map observers are serialized with every new testcase sent to the other clients.
+//! The map of a large target is mostly untouched after an execution, so only the touched entries are encoded,
+//! straight out of the (shared) map without copying it first, and the receiver fills in the rest.
+//! Maps with more than [`SPARSE_MAX_FILLED_PERCENT`] touched entries are encoded as they are.
+
+use alloc::vec::Vec;
+
+use serde::{de, ser::SerializeSeq, Deserialize, Serialize, Serializer};
+
+/// The share of entries differing from the initial value, in percent, above which a map is encoded densely
+pub const SPARSE_MAX_FILLED_PERCENT: usize = 25;
+
+/// The largest length of a sparse map that gets restored, so a malformed message cannot make the receiver
+/// allocate an arbitrary amount of memory. Far above the size of any coverage map.
+pub const SPARSE_MAX_LEN: usize = 1 << 26;
+
+/// Serializes a borrowed map as the entries differing from `initial`, see [`SparseMapBuf`] for the other side
+#[derive(Debug)]
+pub struct SparseMap<'a, T> {
+    map: &'a [T],
+    initial: &'a T,
+}
+
+impl<'a, T> SparseMap<'a, T> {
+    /// Wraps `map`, to serialize it relative to `initial`
+    #[must_use]
+    pub fn new(map: &'a [T], initial: &'a T) -> Self {
+        Self { map, initial }
+    }
+}
+
+impl<T> Serialize for SparseMap<'_, T>
+where
+    T: Serialize + PartialEq,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let filled = self.map.iter().filter(|x| *x != self.initial).count();
+        if filled * 100 > self.map.len() * SPARSE_MAX_FILLED_PERCENT {
+            SparseMapRef::Dense(self.map).serialize(serializer)
+        } else {
+            SparseMapRef::Sparse {
+                len: self.map.len(),
+                entries: Entries {
+                    map: self.map,
+                    initial: self.initial,
+                    filled,
+                },
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+/// The serialized layout of a [`SparseMap`], mirrored by [`SparseMapBuf`]
+#[derive(Serialize)]
+#[serde(rename = "SparseMap", bound = "T: Serialize + PartialEq")]
+enum SparseMapRef<'a, T> {
+    Dense(&'a [T]),
+    Sparse { len: usize, entries: Entries<'a, T> },
+}
+
+/// The entries of a map differing from the initial value, with their index
+struct Entries<'a, T> {
+    map: &'a [T],
+    initial: &'a T,
+    filled: usize,
+}
+
+impl<T> Serialize for Entries<'_, T>
+where
+    T: Serialize + PartialEq,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.filled))?;
+        for entry in self
+            .map
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| *x != self.initial)
+        {
+            seq.serialize_element(&entry)?;
+        }
+        seq.end()
+    }
+}
+
+/// A deserialized [`SparseMap`]
+#[derive(Debug, Deserialize)]
+#[serde(rename = "SparseMap")]
+pub enum SparseMapBuf<T> {
+    /// The map as it is
+    Dense(Vec<T>),
+    /// The entries differing from the initial value
+    Sparse {
+        /// The length of the map
+        len: usize,
+        /// The index and value of each entry differing from the initial value
+        entries: Vec<(usize, T)>,
+    },
+}
+
+impl<T> SparseMapBuf<T>
+where
+    T: Clone,
+{
+    /// Restores the map, filling the entries not serialized with `initial`.
+    ///
+    /// Fails for a map longer than [`SPARSE_MAX_LEN`], or with more entries than its length.
+    pub fn into_vec<E>(self, initial: T) -> Result<Vec<T>, E>
+    where
+        E: de::Error,
+    {
+        match self {
+            Self::Dense(map) => Ok(map),
+            Self::Sparse { len, entries } => {
+                if len > SPARSE_MAX_LEN {
+                    return Err(E::custom(format_args!(
+                        "map length {len} exceeds the maximum of {SPARSE_MAX_LEN}"
+                    )));
+                }
+                if entries.len() > len {
+                    return Err(E::custom(format_args!(
+                        "{} map entries for a map of length {len}",
+                        entries.len()
+                    )));
+                }
+                let mut map = vec![initial; len];
+                for (idx, entry) in entries {
+                    *map.get_mut(idx).ok_or_else(|| {
+                        E::custom(format_args!("map entry {idx} out of bounds ({len})"))
+                    })? = entry;
+                }
+                Ok(map)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::{SparseMap, SparseMapBuf, SPARSE_MAX_LEN};
+
+    fn roundtrip(map: &[u8], initial: u8) -> (usize, Vec<u8>) {
+        let buf = postcard::to_allocvec(&SparseMap::new(map, &initial)).unwrap();
+        let restored = postcard::from_bytes::<SparseMapBuf<u8>>(&buf)
+            .unwrap()
+            .into_vec::<postcard::Error>(initial)
+            .unwrap();
+        (buf.len(), restored)
+    }
+
+    #[test]
+    fn test_sparse_map() {
+        let mut map = vec![0_u8; 1 << 16];
+        map[7] = 1;
+        map[60_000] = 128;
+        let (len, restored) = roundtrip(&map, 0);
+        assert!(len < 16);
+        assert_eq!(restored, map);
+
+        map.fill(0xff);
+        map[3] = 0;
+        let (len, restored) = roundtrip(&map, 0xff);
+        assert!(len < 16);
+        assert_eq!(restored, map);
+
+        let map: Vec<u8> = (0..=255).collect();
+        let (len, restored) = roundtrip(&map, 0);
+        assert!(len > 256);
+        assert_eq!(restored, map);
+    }
+
+    #[test]
+    fn test_sparse_map_malformed() {
+        let too_long = SparseMapBuf::Sparse {
+            len: SPARSE_MAX_LEN + 1,
+            entries: vec![],
+        };
+        assert!(too_long.into_vec::<postcard::Error>(0_u8).is_err());
+
+        let too_many_entries = SparseMapBuf::Sparse {
+            len: 1,
+            entries: vec![(0, 1_u8), (0, 2)],
+        };
+        assert!(too_many_entries.into_vec::<postcard::Error>(0).is_err());
+
+        let out_of_bounds = SparseMapBuf::Sparse {
+            len: 1,
+            entries: vec![(1, 1_u8)],
+        };
+        assert!(out_of_bounds.into_vec::<postcard::Error>(0).is_err());
+    }
+}
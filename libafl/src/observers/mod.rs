@@ -7,7 +7,7 @@ pub use cmp::*;
 #[cfg(feature = "std")]
 pub mod stdio;
 #[cfg(feature = "std")]
-pub use stdio::{StdErrObserver, StdOutObserver};
+pub use stdio::{ExitCodeObserver, StdErrObserver, StdOutObserver};
 
 #[cfg(feature = "regex")]
 pub mod stacktrace;
@@ -21,6 +21,8 @@ pub mod profiling;
 pub use profiling::*;
 
 pub mod concolic;
+pub mod dynamic;
+pub use dynamic::{DynObserver, DynObserversTuple};
 pub mod map;
 pub use map::*;
 
@@ -408,7 +410,7 @@ mod tests {
         Named,
     };
 
-    use crate::observers::{StdMapObserver, TimeObserver};
+    use crate::observers::{MapObserver, StdMapObserver, TimeObserver};
 
     static mut MAP: [u32; 4] = [0; 4];
 
@@ -427,5 +429,6 @@ mod tests {
         let obv2: tuple_list_type!(TimeObserver, StdMapObserver<u32, false>) =
             postcard::from_bytes(&vec).unwrap();
         assert_eq!(obv.0.name(), obv2.0.name());
+        assert_eq!(obv.1 .0.to_vec(), obv2.1 .0.to_vec());
     }
 }
@@ -1,11 +1,14 @@
 //! the ``StacktraceObserver`` looks up the stacktrace on the execution thread and computes a hash for it for dedupe
 
 use alloc::{borrow::Cow, string::String, vec::Vec};
+#[cfg(target_os = "linux")]
+use core::fmt::Write;
 #[cfg(feature = "casr")]
+use std::string::ToString;
+#[cfg(any(feature = "casr", target_os = "linux"))]
 use std::{
     collections::hash_map::DefaultHasher,
     hash::{Hash, Hasher},
-    string::ToString,
 };
 use std::{
     fmt::Debug,
@@ -34,6 +37,14 @@ use libcasr::{
         STACK_FRAME_FUNCTION_IGNORE_REGEXES,
     },
 };
+#[cfg(target_os = "linux")]
+use nix::{
+    sys::{
+        ptrace,
+        wait::{waitpid, WaitPidFlag},
+    },
+    unistd::Pid,
+};
 #[cfg(not(feature = "casr"))]
 use regex::Regex;
 use serde::{Deserialize, Serialize};
@@ -365,3 +376,247 @@ impl Named for AsanBacktraceObserver {
         &self.observer_name
     }
 }
+
+/// How the [`HangBacktraceObserver`] captures the stacks of a hanging child
+#[cfg(target_os = "linux")]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HangCaptureMethod {
+    /// Snapshot the user-space stack of each thread with `ptrace`, walking the frame pointers.
+    /// The target should be built with `-fno-omit-frame-pointer`.
+    Ptrace,
+    /// Read the kernel stack of each thread from `/proc/<pid>/task/<tid>/stack`,
+    /// for targets stuck in a syscall. Needs root.
+    ProcStack,
+}
+
+/// The maximum number of frames captured per thread
+#[cfg(target_os = "linux")]
+const HANG_MAX_FRAMES: usize = 64;
+
+/// An observer capturing the stacks of a timed out child before it gets killed.
+///
+/// The hash of the stacks lets a [`crate::feedbacks::NewHashFeedback`] dedup hangs,
+/// and the stacks record where the target was stuck.
+/// This observer is only compatible with a `CommandExecutor` or `ForkserverExecutor` that got its handle
+/// passed as `hang_observer`.
+#[cfg(target_os = "linux")]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HangBacktraceObserver {
+    observer_name: Cow<'static, str>,
+    method: HangCaptureMethod,
+    hash: Option<u64>,
+    backtrace: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl HangBacktraceObserver {
+    /// Creates a new [`HangBacktraceObserver`] with the given name, capturing the stacks with `method`.
+    #[must_use]
+    pub fn new<S>(observer_name: S, method: HangCaptureMethod) -> Self
+    where
+        S: Into<Cow<'static, str>>,
+    {
+        Self {
+            observer_name: observer_name.into(),
+            method,
+            hash: None,
+            backtrace: None,
+        }
+    }
+
+    /// The stacks of the threads of the last run, if it timed out
+    #[must_use]
+    pub fn backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+
+    /// Captures the stacks of all threads of the hanging process `pid`, which needs to be still alive.
+    pub fn capture(&mut self, pid: Pid) -> Result<(), Error> {
+        let maps = match self.method {
+            HangCaptureMethod::Ptrace => fs::read_to_string(format!("/proc/{pid}/maps"))?,
+            HangCaptureMethod::ProcStack => String::new(),
+        };
+        let mut tids = fs::read_dir(format!("/proc/{pid}/task"))?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<i32>().ok())
+            .collect::<Vec<_>>();
+        // Threads are numbered in the order they got spawned, keep it stable for the hash
+        tids.sort_unstable();
+
+        let mut hasher = DefaultHasher::new();
+        let mut backtrace = String::new();
+        for tid in tids {
+            let frames = match self.method {
+                HangCaptureMethod::Ptrace => ptrace_frames(Pid::from_raw(tid))?
+                    .into_iter()
+                    .map(|address| symbolize(&maps, address))
+                    .collect::<Vec<_>>(),
+                HangCaptureMethod::ProcStack => {
+                    fs::read_to_string(format!("/proc/{pid}/task/{tid}/stack"))?
+                        .lines()
+                        .map(|line| line.split_once("] ").map_or(line, |(_, frame)| frame))
+                        .map(String::from)
+                        .collect()
+                }
+            };
+            frames.hash(&mut hasher);
+            writeln!(backtrace, "Thread {tid}:").unwrap();
+            for (idx, frame) in frames.iter().enumerate() {
+                writeln!(backtrace, "    #{idx} {frame}").unwrap();
+            }
+        }
+
+        self.hash = Some(hasher.finish());
+        self.backtrace = Some(backtrace);
+        Ok(())
+    }
+
+    /// Clears the stacks of the last run
+    fn clear(&mut self) {
+        self.hash = None;
+        self.backtrace = None;
+    }
+}
+
+/// Stops the thread `tid` with `ptrace` and collects the return addresses on its stack
+#[cfg(target_os = "linux")]
+fn ptrace_frames(tid: Pid) -> Result<Vec<u64>, Error> {
+    ptrace::seize(tid, ptrace::Options::empty())?;
+    let addresses = ptrace::interrupt(tid)
+        .and_then(|()| waitpid(tid, Some(WaitPidFlag::__WALL)))
+        .and_then(|_| walk_frame_pointers(tid));
+    // A failing detach means the thread is gone, and it gets killed right after anyway
+    let _ = ptrace::detach(tid, None);
+    Ok(addresses?)
+}
+
+/// Follows the chain of frame pointers of the stopped thread `tid`
+#[cfg(target_os = "linux")]
+pub(crate) fn walk_frame_pointers(tid: Pid) -> nix::Result<Vec<u64>> {
+    let read_word = |address: u64| {
+        ptrace::read(tid, address as usize as ptrace::AddressType)
+            .map(|word| u64::from_ne_bytes(word.to_ne_bytes()))
+    };
+
+    let (pc, mut fp) = pc_and_fp(tid)?;
+    let mut addresses = vec![pc];
+    while addresses.len() < HANG_MAX_FRAMES && fp != 0 {
+        // The saved frame pointer is followed by the return address on both x86_64 and aarch64
+        let (Ok(next_fp), Ok(return_address)) = (read_word(fp), read_word(fp + 8)) else {
+            break;
+        };
+        if return_address == 0 {
+            break;
+        }
+        addresses.push(return_address);
+        // Stacks grow down, anything else is a broken chain
+        if next_fp <= fp {
+            break;
+        }
+        fp = next_fp;
+    }
+    Ok(addresses)
+}
+
+/// The program counter and frame pointer of the stopped thread `tid`
+#[cfg(all(
+    target_os = "linux",
+    target_arch = "x86_64",
+    any(target_env = "gnu", target_env = "musl")
+))]
+fn pc_and_fp(tid: Pid) -> nix::Result<(u64, u64)> {
+    let regs = ptrace::getregs(tid)?;
+    Ok((regs.rip, regs.rbp))
+}
+
+/// The program counter and frame pointer of the stopped thread `tid`
+#[cfg(all(target_os = "linux", target_arch = "aarch64", target_env = "gnu"))]
+fn pc_and_fp(tid: Pid) -> nix::Result<(u64, u64)> {
+    let regs = ptrace::getregs(tid)?;
+    Ok((regs.pc, regs.regs[29]))
+}
+
+/// The program counter and frame pointer of the stopped thread `tid`
+#[cfg(all(
+    target_os = "linux",
+    not(any(
+        all(target_arch = "x86_64", any(target_env = "gnu", target_env = "musl")),
+        all(target_arch = "aarch64", target_env = "gnu")
+    ))
+))]
+fn pc_and_fp(_tid: Pid) -> nix::Result<(u64, u64)> {
+    Err(nix::errno::Errno::ENOTSUP)
+}
+
+/// Formats `address` relative to the mapped file it lies in, so it stays the same across runs with ASLR
+#[cfg(target_os = "linux")]
+pub(crate) fn symbolize(maps: &str, address: u64) -> String {
+    for line in maps.lines() {
+        // start-end perms offset dev inode [path]
+        let mut fields = line.split_whitespace();
+        let (Some(range), Some(offset)) = (fields.next(), fields.nth(1)) else {
+            continue;
+        };
+        let Some((start, end)) = range.split_once('-') else {
+            continue;
+        };
+        let (Ok(start), Ok(end), Ok(offset)) = (
+            u64::from_str_radix(start, 16),
+            u64::from_str_radix(end, 16),
+            u64::from_str_radix(offset, 16),
+        ) else {
+            continue;
+        };
+        if (start..end).contains(&address) {
+            let path = fields.nth(2).unwrap_or("[anon]");
+            return format!("{path}+{:#x}", address - start + offset);
+        }
+    }
+    format!("{address:#x}")
+}
+
+#[cfg(target_os = "linux")]
+impl ObserverWithHashField for HangBacktraceObserver {
+    /// Gets the hash of the stacks of the last run, if it timed out
+    fn hash(&self) -> Option<u64> {
+        self.hash
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl<I, S> Observer<I, S> for HangBacktraceObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.clear();
+        Ok(())
+    }
+
+    fn pre_exec_child(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.clear();
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Named for HangBacktraceObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.observer_name
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::symbolize;
+
+    #[test]
+    fn test_symbolize() {
+        let maps = "\
+55d0c0000000-55d0c0001000 r--p 00000000 08:01 1234 /usr/bin/target
+55d0c0001000-55d0c0005000 r-xp 00001000 08:01 1234 /usr/bin/target
+7ffd10000000-7ffd10021000 rw-p 00000000 00:00 0 [stack]
+7ffd10100000-7ffd10101000 rw-p 00000000 00:00 0
+";
+        assert_eq!(symbolize(maps, 0x55d0c0001234), "/usr/bin/target+0x1234");
+        assert_eq!(symbolize(maps, 0x7ffd10000010), "[stack]+0x10");
+        assert_eq!(symbolize(maps, 0x7ffd10100010), "[anon]+0x10");
+        assert_eq!(symbolize(maps, 0x1000), "0x1000");
+    }
+}
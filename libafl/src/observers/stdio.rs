@@ -1,11 +1,12 @@
-//! Observers for `stdout` and `stderr`
+//! Observers for `stdout`, `stderr` and the exit code
 //!
-//! The [`StdOutObserver`] and [`StdErrObserver`] observers look at the stdout of a program
+//! The [`StdOutObserver`] and [`StdErrObserver`] observers look at the stdout of a program,
+//! the [`ExitCodeObserver`] at how it exited.
 //! The executor must explicitly support these observers.
 //! For example, they are supported on the [`crate::executors::CommandExecutor`].
 
 use alloc::borrow::Cow;
-use std::vec::Vec;
+use std::{process::ExitStatus, vec::Vec};
 
 use libafl_bolts::Named;
 use serde::{Deserialize, Serialize};
@@ -257,3 +258,60 @@ impl<I, S> Observer<I, S> for StdErrObserver {
         Ok(())
     }
 }
+
+/// An observer that captures the exit code of a target, or the signal that killed it.
+/// Only works for supported executors, such as the [`crate::executors::CommandExecutor`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExitCodeObserver {
+    /// The name of the observer.
+    pub name: Cow<'static, str>,
+    /// The exit code of the target during its last execution, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// The signal that terminated the target during its last execution, if any.
+    pub signal: Option<i32>,
+}
+
+impl ExitCodeObserver {
+    /// Create a new [`ExitCodeObserver`] with the given name.
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name: Cow::from(name),
+            exit_code: None,
+            signal: None,
+        }
+    }
+
+    /// React to the exit code, or the terminating signal, of the target
+    pub fn observe(&mut self, exit_code: Option<i32>, signal: Option<i32>) {
+        self.exit_code = exit_code;
+        self.signal = signal;
+    }
+
+    /// React to the [`ExitStatus`] of the target
+    pub fn observe_exit_status(&mut self, status: &ExitStatus) {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(status);
+        #[cfg(not(unix))]
+        let signal = None;
+        self.observe(status.code(), signal);
+    }
+}
+
+impl Named for ExitCodeObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Observer<I, S> for ExitCodeObserver {
+    fn pre_exec_child(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.observe(None, None);
+        Ok(())
+    }
+
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.observe(None, None);
+        Ok(())
+    }
+}
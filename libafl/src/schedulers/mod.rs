@@ -4,7 +4,7 @@ use alloc::{borrow::ToOwned, string::ToString};
 use core::marker::PhantomData;
 
 pub mod testcase_score;
-pub use testcase_score::{LenTimeMulTestcaseScore, TestcaseScore};
+pub use testcase_score::{LenTimeMulTestcaseScore, TargetRegionsTestcaseScore, TestcaseScore};
 
 pub mod queue;
 pub use queue::QueueScheduler;
@@ -26,6 +26,9 @@ pub use accounting::CoverageAccountingScheduler;
 pub mod weighted;
 pub use weighted::{StdWeightedScheduler, WeightedScheduler};
 
+pub mod replay;
+pub use replay::ReplayScheduler;
+
 pub mod tuneable;
 use libafl_bolts::{
     rands::Rand,
@@ -0,0 +1,88 @@
+//! The [`ReplayScheduler`] records or replays the testcases scheduled by another scheduler,
+//! see [`crate::state::replay`].
+
+use libafl_bolts::tuples::MatchName;
+
+use crate::{
+    corpus::{CorpusId, Testcase},
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{
+        replay::{replay_decision, ReplayDecision},
+        Stoppable,
+    },
+    Error, HasMetadata,
+};
+
+/// Wraps a scheduler, recording the testcases it schedules, or scheduling the replayed ones instead
+#[derive(Debug, Clone)]
+pub struct ReplayScheduler<CS> {
+    inner: CS,
+}
+
+impl<CS> ReplayScheduler<CS> {
+    /// Creates a new [`ReplayScheduler`] wrapping the `inner` scheduler
+    #[must_use]
+    pub fn new(inner: CS) -> Self {
+        Self { inner }
+    }
+
+    /// The wrapped scheduler
+    pub fn inner(&self) -> &CS {
+        &self.inner
+    }
+}
+
+impl<CS, I, S> RemovableScheduler<I, S> for ReplayScheduler<CS>
+where
+    CS: RemovableScheduler<I, S>,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut S,
+        id: CorpusId,
+        testcase: &Option<Testcase<I>>,
+    ) -> Result<(), Error> {
+        self.inner.on_remove(state, id, testcase)
+    }
+
+    fn on_replace(&mut self, state: &mut S, id: CorpusId, prev: &Testcase<I>) -> Result<(), Error> {
+        self.inner.on_replace(state, id, prev)
+    }
+}
+
+impl<CS, I, S> Scheduler<I, S> for ReplayScheduler<CS>
+where
+    CS: Scheduler<I, S>,
+    S: HasMetadata + Stoppable,
+{
+    fn on_add(&mut self, state: &mut S, id: CorpusId) -> Result<(), Error> {
+        self.inner.on_add(state, id)
+    }
+
+    fn on_evaluation<OT>(&mut self, state: &mut S, input: &I, observers: &OT) -> Result<(), Error>
+    where
+        OT: MatchName,
+    {
+        self.inner.on_evaluation(state, input, observers)
+    }
+
+    fn next(&mut self, state: &mut S) -> Result<CorpusId, Error> {
+        // The inner scheduler always runs, so that it consumes the RNG as when recording
+        let live = self.inner.next(state)?;
+        match replay_decision(state, ReplayDecision::Scheduled(live))? {
+            ReplayDecision::Scheduled(id) if id != live => {
+                self.inner.set_current_scheduled(state, Some(id))?;
+                Ok(id)
+            }
+            _ => Ok(live),
+        }
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut S,
+        next_id: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.set_current_scheduled(state, next_id)
+    }
+}
@@ -1,11 +1,12 @@
 //! The `TestcaseScore` is an evaluator providing scores of corpus items.
 use alloc::string::{String, ToString};
+use core::marker::PhantomData;
 
 use libafl_bolts::{HasLen, HasRefCnt};
 
 use crate::{
     corpus::{Corpus, SchedulerTestcaseMetadata, Testcase},
-    feedbacks::MapIndexesMetadata,
+    feedbacks::{MapIndexesMetadata, TargetRegionsHitMetadata, TargetRegionsMetadata},
     schedulers::{
         minimizer::{IsFavoredMetadata, TopRatedsMetadata},
         powersched::{BaseSchedule, SchedulerMetadata},
@@ -351,3 +352,32 @@ where
         Ok(weight)
     }
 }
+
+/// Boosts the score `F` computes for the testcases reaching a target region, by the
+/// [`TargetRegionsMetadata::boost`] factor, to direct the fuzzer at them.
+///
+/// Use it in place of `F`, for example in a [`crate::schedulers::WeightedScheduler`] or a [`crate::stages::PowerMutationalStage`],
+/// with a [`crate::feedbacks::TargetRegionsFeedback`] tagging the testcases.
+#[derive(Debug, Clone)]
+pub struct TargetRegionsTestcaseScore<F> {
+    phantom: PhantomData<F>,
+}
+
+impl<F, S> TestcaseScore<S> for TargetRegionsTestcaseScore<F>
+where
+    F: TestcaseScore<S>,
+    S: HasCorpus + HasMetadata,
+{
+    fn compute(
+        state: &S,
+        entry: &mut Testcase<<S::Corpus as Corpus>::Input>,
+    ) -> Result<f64, Error> {
+        let score = F::compute(state, entry)?;
+        match state.metadata::<TargetRegionsMetadata>() {
+            Ok(targets) if entry.has_metadata::<TargetRegionsHitMetadata>() => {
+                Ok(score * targets.boost())
+            }
+            _ => Ok(score),
+        }
+    }
+}
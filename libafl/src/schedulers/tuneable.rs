@@ -64,6 +64,14 @@ impl TuneableScheduler {
             .unwrap()
     }
 
+    /// Whether a [`TuneableScheduler`] was created for the state
+    pub fn is_in_use<S>(state: &S) -> bool
+    where
+        S: HasMetadata,
+    {
+        state.has_metadata::<TuneableSchedulerMetadata>()
+    }
+
     /// Sets the next corpus id to be used
     pub fn set_next<S>(state: &mut S, next: CorpusId)
     where
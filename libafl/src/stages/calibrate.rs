@@ -173,8 +173,9 @@ where
                 )
             })?,
         };
+        // The only copy of the map, the later runs are compared to it in place
         let map_first_entries = map_first.to_vec();
-        let map_first_len = map_first.to_vec().len();
+        let map_first_len = map_first.len();
         let mut unstable_entries: Vec<usize> = vec![];
         // Run CAL_STAGE_START - 1 times, increase by 2 for every time a new
         // run is found to be unstable or to crash with CAL_STAGE_MAX total runs.
@@ -211,9 +212,8 @@ where
                 .post_exec_all(state, &input, &exit_kind)?;
 
             if self.track_stability && exit_kind != ExitKind::Timeout {
-                let map = &executor.observers()[&self.map_observer_handle]
-                    .as_ref()
-                    .to_vec();
+                let observers = executor.observers();
+                let map = observers[&self.map_observer_handle].as_ref();
 
                 let map_state = state
                     .named_metadata_map_mut()
@@ -225,12 +225,13 @@ where
                     history_map.resize(map_first_len, O::Entry::default());
                 }
 
-                for (idx, (first, (cur, history))) in map_first_entries
+                for (idx, (first, history)) in map_first_entries
                     .iter()
-                    .zip(map.iter().zip(history_map.iter_mut()))
+                    .zip(history_map.iter_mut())
+                    .take(map.len())
                     .enumerate()
                 {
-                    if *first != *cur && *history != O::Entry::max_value() {
+                    if *first != map.get(idx) && *history != O::Entry::max_value() {
                         // If we just hit a history map entry that was not covered before, but is now flagged as flaky,
                         // we need to make sure the `num_covered_map_indexes` is kept in sync.
                         map_state.num_covered_map_indexes +=
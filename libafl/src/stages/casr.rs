@@ -0,0 +1,199 @@
+//! The [`CasrStage`] writes new solutions out as [CASR](https://github.com/ispras/casr) reports, deduplicated
+//! and clustered by stack trace, in the layout of `casr-cluster`:
+//!
+//! ```text
+//! casr/
+//! ├── cl1/crash-0.casrep
+//! ├── cl1/crash-0
+//! ├── cl2/crash-4.casrep
+//! └── cl2/crash-4
+//! ```
+
+use alloc::{string::String, vec::Vec};
+use core::marker::PhantomData;
+use std::{fs, path::PathBuf};
+
+use hashbrown::HashMap;
+use libafl_bolts::impl_serdeany;
+use libcasr::stacktrace::similarity;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    feedbacks::casr::{casr_stacktrace, CasrReportMetadata},
+    inputs::Input,
+    stages::Stage,
+    state::{HasSolutions, UsesState},
+    Error, HasMetadata,
+};
+
+/// The default similarity of two stack traces, between 0 and 1, above which their solutions are in the same cluster
+pub const CASR_CLUSTER_SIMILARITY_DEFAULT: f64 = 0.7;
+
+/// The clusters of the solutions written out so far
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CasrClustersMetadata {
+    last_solution: Option<CorpusId>,
+    /// The functions in the stack of the first solution of each cluster
+    clusters: Vec<Vec<String>>,
+    /// The cluster of each stack hash seen so far
+    stack_hashes: HashMap<u64, usize>,
+    duplicates: usize,
+}
+
+impl_serdeany!(CasrClustersMetadata);
+
+impl CasrClustersMetadata {
+    /// The number of clusters
+    #[must_use]
+    pub fn clusters(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// The number of solutions skipped, as another one had the same stack
+    #[must_use]
+    pub fn duplicates(&self) -> usize {
+        self.duplicates
+    }
+
+    /// The cluster a solution with `report` belongs to, `None` if it duplicates a solution seen before
+    fn cluster(&mut self, report: &CasrReportMetadata, min_similarity: f64) -> Option<usize> {
+        if self.stack_hashes.contains_key(&report.stack_hash) {
+            self.duplicates += 1;
+            return None;
+        }
+        let stacktrace = report.casr_stacktrace();
+        let cluster = self
+            .clusters
+            .iter()
+            .position(|functions| {
+                similarity(&stacktrace, &casr_stacktrace(functions)) >= min_similarity
+            })
+            .unwrap_or_else(|| {
+                self.clusters.push(report.functions.clone());
+                self.clusters.len() - 1
+            });
+        self.stack_hashes.insert(report.stack_hash, cluster);
+        Some(cluster)
+    }
+}
+
+/// The [`CasrStage`] writes the [`CasrReportMetadata`] of each new solution as `.casrep` file, next to its input,
+/// in a directory per cluster. Solutions without a report get one from their metadata, see [`CasrReportMetadata::from_testcase`].
+#[derive(Debug)]
+pub struct CasrStage<EM, Z> {
+    out_dir: PathBuf,
+    min_similarity: f64,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, Z> UsesState for CasrStage<EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for CasrStage<EM, Z>
+where
+    EM: UsesState,
+    E: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    EM::State: HasSolutions + HasMetadata,
+    <<EM as UsesState>::State as HasSolutions>::Solutions: Corpus<Input = Self::Input>, //delete me
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let mut metadata = state
+            .metadata_map()
+            .get::<CasrClustersMetadata>()
+            .cloned()
+            .unwrap_or_default();
+        let mut solution_id = match metadata.last_solution {
+            Some(last) => state.solutions().next(last),
+            None => state.solutions().first(),
+        };
+
+        while let Some(id) = solution_id {
+            let mut testcase = state.solutions().get(id)?.borrow_mut();
+            state.solutions().load_input_into(&mut testcase)?;
+            let report = match testcase.metadata::<CasrReportMetadata>() {
+                Ok(report) => Some(report.clone()),
+                Err(_) => CasrReportMetadata::from_testcase(&testcase),
+            };
+            if let Some(report) = report {
+                if let Some(cluster) = metadata.cluster(&report, self.min_similarity) {
+                    let cluster_dir = self.out_dir.join(format!("cl{}", cluster + 1));
+                    fs::create_dir_all(&cluster_dir)?;
+                    let name = testcase
+                        .filename()
+                        .clone()
+                        .unwrap_or_else(|| format!("crash-{id}"));
+                    let json =
+                        serde_json::to_vec_pretty(&report.to_crash_report()).map_err(|err| {
+                            Error::serialize(format!("Failed to json-ify casr report: {err:?}"))
+                        })?;
+                    fs::write(cluster_dir.join(format!("{name}.casrep")), json)?;
+                    if let Some(input) = testcase.input() {
+                        input.to_file(cluster_dir.join(&name))?;
+                    }
+                    log::info!(
+                        "Solution {id} is in casr cluster {}: {} {}",
+                        cluster + 1,
+                        report.severity,
+                        report.short_description
+                    );
+                }
+                testcase.add_metadata(report);
+            } else {
+                log::info!("No casr report for solution {id}");
+            }
+            drop(testcase);
+            metadata.last_solution = Some(id);
+            solution_id = state.solutions().next(id);
+        }
+
+        state.add_metadata(metadata);
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Solutions get reported once
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<EM, Z> CasrStage<EM, Z> {
+    /// Creates a new [`CasrStage`], writing the reports to `out_dir`
+    #[must_use]
+    pub fn new(out_dir: PathBuf) -> Self {
+        Self {
+            out_dir,
+            min_similarity: CASR_CLUSTER_SIMILARITY_DEFAULT,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Puts solutions whose stack traces are at least `min_similarity` similar, between 0 and 1, in the same cluster,
+    /// [`CASR_CLUSTER_SIMILARITY_DEFAULT`] by default
+    #[must_use]
+    pub fn with_min_similarity(mut self, min_similarity: f64) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+}
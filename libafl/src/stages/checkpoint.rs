@@ -0,0 +1,176 @@
+//! The [`CheckpointStage`] checkpoints the state on `SIGUSR1`, and shuts the fuzzer down gracefully on `SIGTERM`.
+//!
+//! The signal handlers only take note of the signal, the stage acts on it between two executions, so that the
+//! checkpoint is consistent: it flushes the corpora, writes a [`snapshot`](crate::state::snapshot) of the state
+//! and reports the progress to the monitor. On `SIGTERM`, it then requests the fuzzer to stop,
+//! which returns [`Error::ShuttingDown`] from the fuzz loop, and the event manager does not restart the client.
+//! The state is resumed with [`State::restore_from`].
+//!
+//! This lets orchestrators, such as Kubernetes or slurm, preempt the fuzzer without losing progress.
+//! The signals have to reach the fuzzing process itself: with a restarting event manager, send them to the
+//! process group, or to the client, rather than to the restarter.
+
+use alloc::vec::Vec;
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use std::path::PathBuf;
+
+use libafl_bolts::os::unix_signals::{
+    setup_signal_handler, siginfo_t, ucontext_t, Signal, SignalHandler,
+};
+
+use crate::{
+    corpus::Corpus,
+    events::ProgressReporter,
+    stages::Stage,
+    state::{
+        HasCorpus, HasExecutions, HasLastReportTime, HasSolutions, State, Stoppable, UsesState,
+    },
+    Error, HasMetadata,
+};
+
+static CHECKPOINT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+static HANDLERS_INSTALLED: AtomicBool = AtomicBool::new(false);
+
+/// The signal handler of the [`CheckpointStage`], noting `SIGUSR1` and `SIGTERM`
+#[derive(Debug, Clone)]
+pub struct CheckpointSignalData {}
+
+/// The handler installed by [`install_checkpoint_signal_handlers`]
+static mut CHECKPOINT_SIGHANDLER_STATE: CheckpointSignalData = CheckpointSignalData {};
+
+impl SignalHandler for CheckpointSignalData {
+    unsafe fn handle(
+        &mut self,
+        signal: Signal,
+        _info: &mut siginfo_t,
+        _context: Option<&mut ucontext_t>,
+    ) {
+        // Only async-signal-safe operations in here
+        if signal == Signal::SigTerm {
+            SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+        } else {
+            CHECKPOINT_REQUESTED.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn signals(&self) -> Vec<Signal> {
+        vec![Signal::SigUser1, Signal::SigTerm]
+    }
+}
+
+/// Installs the handlers of `SIGUSR1` and `SIGTERM` for the [`CheckpointStage`], once per process
+pub fn install_checkpoint_signal_handlers() -> Result<(), Error> {
+    if HANDLERS_INSTALLED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+    // # Safety
+    // The handler only stores to atomics, and lives in a static.
+    let res = unsafe { setup_signal_handler(&raw mut CHECKPOINT_SIGHANDLER_STATE) };
+    if res.is_err() {
+        HANDLERS_INSTALLED.store(false, Ordering::SeqCst);
+    }
+    res
+}
+
+/// Requests a checkpoint, as `SIGUSR1` does
+pub fn request_checkpoint() {
+    CHECKPOINT_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// Requests a checkpoint and a graceful shutdown, as `SIGTERM` does
+pub fn request_checkpoint_and_shutdown() {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+/// A [`Stage`] checkpointing the state on request, see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct CheckpointStage<EM, Z> {
+    path: PathBuf,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, Z> UsesState for CheckpointStage<EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for CheckpointStage<EM, Z>
+where
+    EM: ProgressReporter,
+    E: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    EM::State: HasCorpus + HasSolutions + HasExecutions + HasLastReportTime + HasMetadata + State,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let shutdown = SHUTDOWN_REQUESTED.swap(false, Ordering::SeqCst);
+        if !CHECKPOINT_REQUESTED.swap(false, Ordering::SeqCst) && !shutdown {
+            return Ok(());
+        }
+        state.corpus_mut().flush()?;
+        state.solutions_mut().flush()?;
+        state.snapshot_to(&self.path)?;
+        manager.report_progress(state)?;
+        log::info!("Checkpointed the state to {}", self.path.display());
+        if shutdown {
+            log::info!("Shutting down gracefully");
+            state.request_stop();
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<EM, Z> CheckpointStage<EM, Z> {
+    /// Creates a new [`CheckpointStage`] writing the snapshots of the state to `path`,
+    /// and installs the signal handlers
+    pub fn new<P>(path: P) -> Result<Self, Error>
+    where
+        P: Into<PathBuf>,
+    {
+        install_checkpoint_signal_handlers()?;
+        Ok(Self {
+            path: path.into(),
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use super::{install_checkpoint_signal_handlers, CHECKPOINT_REQUESTED, SHUTDOWN_REQUESTED};
+
+    #[test]
+    fn test_checkpoint_signal() {
+        install_checkpoint_signal_handlers().unwrap();
+        unsafe {
+            libc::raise(libc::SIGUSR1);
+        }
+        assert!(CHECKPOINT_REQUESTED.swap(false, Ordering::SeqCst));
+        assert!(!SHUTDOWN_REQUESTED.load(Ordering::SeqCst));
+    }
+}
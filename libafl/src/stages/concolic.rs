@@ -4,13 +4,14 @@
 use alloc::borrow::{Cow, ToOwned};
 #[cfg(feature = "concolic_mutation")]
 use alloc::{string::ToString, vec::Vec};
-#[cfg(feature = "concolic_mutation")]
 use core::marker::PhantomData;
 
 use libafl_bolts::{
+    impl_serdeany,
     tuples::{Handle, MatchNameRef},
     Named,
 };
+use serde::{Deserialize, Serialize};
 
 #[cfg(all(feature = "concolic_mutation", feature = "introspection"))]
 use crate::monitors::PerfFeature;
@@ -18,7 +19,9 @@ use crate::monitors::PerfFeature;
 use crate::state::HasClientPerfMonitor;
 use crate::{
     corpus::Corpus,
+    events::{Event, EventFirer},
     executors::{Executor, HasObservers},
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
     observers::{concolic::ConcolicObserver, ObserversTuple},
     stages::{RetryCountRestartHelper, Stage, TracingStage},
     state::{HasCorpus, HasCurrentTestcase, HasExecutions, UsesState},
@@ -34,6 +37,37 @@ use crate::{
     Evaluator,
 };
 
+/// Statistics on the size of the concolic traces, updated by the [`ConcolicTracingStage`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConcolicTraceStatsMetadata {
+    /// The count of traces
+    pub traces: u64,
+    /// The total size of the traces, in bytes
+    pub total_bytes: u64,
+    /// The size of the largest trace, in bytes
+    pub max_bytes: u64,
+}
+impl_serdeany!(ConcolicTraceStatsMetadata);
+
+impl ConcolicTraceStatsMetadata {
+    /// Adds a trace of `bytes` bytes
+    pub fn add_trace(&mut self, bytes: u64) {
+        self.traces += 1;
+        self.total_bytes += bytes;
+        self.max_bytes = self.max_bytes.max(bytes);
+    }
+
+    /// The mean size of the traces, in bytes
+    #[must_use]
+    pub fn mean_bytes(&self) -> u64 {
+        self.total_bytes.checked_div(self.traces).unwrap_or(0)
+    }
+}
+
 /// Wraps a [`TracingStage`] to add concolic observing.
 #[derive(Clone, Debug)]
 pub struct ConcolicTracingStage<'a, EM, TE, Z> {
@@ -61,10 +95,10 @@ impl<EM, TE, Z> Named for ConcolicTracingStage<'_, EM, TE, Z> {
 impl<E, EM, TE, Z> Stage<E, EM, Z> for ConcolicTracingStage<'_, EM, TE, Z>
 where
     E: UsesState<State = Self::State>,
-    EM: UsesState<State = Self::State>,
+    EM: EventFirer<State = Self::State>,
     TE: Executor<EM, Z> + HasObservers,
     TE::Observers: ObserversTuple<TE::Input, <Self as UsesState>::State>,
-    TE::State: HasExecutions + HasCorpus + HasNamedMetadata + HasCurrentTestcase,
+    TE::State: HasExecutions + HasCorpus + HasMetadata + HasNamedMetadata + HasCurrentTestcase,
     Z: UsesState<State = Self::State>,
     <<Self as UsesState>::State as HasCorpus>::Corpus: Corpus<Input = Self::Input>, //delete me
 {
@@ -79,10 +113,23 @@ where
         self.inner.trace(fuzzer, state, manager)?;
         if let Some(observer) = self.inner.executor().observers().get(&self.observer_handle) {
             let metadata = observer.create_metadata_from_current_map();
+            let bytes = metadata.len() as u64;
             state
                 .current_testcase_mut()?
                 .metadata_map_mut()
                 .insert(metadata);
+
+            let stats = state.metadata_or_insert_with(ConcolicTraceStatsMetadata::default);
+            stats.add_trace(bytes);
+            let mean_bytes = stats.mean_bytes();
+            manager.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: Cow::from("concolic_trace_bytes"),
+                    value: UserStats::new(UserStatsValue::Number(mean_bytes), AggregatorOps::Avg),
+                    phantom: PhantomData,
+                },
+            )?;
         }
         Ok(())
     }
@@ -0,0 +1,493 @@
+//! The [`ControlStage`] listens on a socket for commands adjusting the tuneables of a client at runtime.
+//!
+//! Each line sent to the socket is a command, answered with a line starting with `ok` or `error:`:
+//! - `iters <n> [stage]` sets the iterations of a [`TuneableMutationalStage`](super::TuneableMutationalStage),
+//!   by default the one named [`STD_TUNEABLE_MUTATIONAL_STAGE_NAME`]
+//! - `mutation-weights <w0> <w1> ...` sets the weights of the mutations of a
+//!   [`TuneableScheduledMutator`](crate::mutators::TuneableScheduledMutator), normalized to probabilities
+//! - `next <corpus id>` sets the next testcase scheduled by a [`TuneableScheduler`]
+//! - `max-size <n>` sets the maximum size of the inputs
+//! - `dump-corpus <dir>` writes the inputs of the corpus to the directory
+//!
+//! For example, `echo "iters 64" | nc -N localhost 1337`.
+//! The commands are handled between two fuzzing iterations. So that they do not stall the fuzzer,
+//! a connection idle for [`CONTROL_READ_TIMEOUT`] or sending more than [`CONTROL_MAX_COMMANDS`] commands is closed,
+//! and the connections left after [`CONTROL_SERVE_TIMEOUT`] wait for the next iteration.
+
+use alloc::{string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::{
+    fs, io,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::Input,
+    mutators::TuneableScheduledMutatorMetadata,
+    schedulers::TuneableScheduler,
+    stages::{set_iters_by_name, Stage, STD_TUNEABLE_MUTATIONAL_STAGE_NAME},
+    state::{HasCorpus, HasMaxSize, UsesState},
+    Error, HasMetadata, HasNamedMetadata,
+};
+
+/// The time after which an idle connection to a [`ControlListener`] is closed
+pub const CONTROL_READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The maximum number of commands handled per connection to a [`ControlListener`]
+pub const CONTROL_MAX_COMMANDS: usize = 64;
+
+/// The longest a [`ControlListener`] spends on its connections each time it serves them
+pub const CONTROL_SERVE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+const CONTROL_COMMANDS: &str = "iters, mutation-weights, next, max-size, dump-corpus";
+
+/// A command received by a [`ControlListener`], see the [module-level documentation](self)
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    /// Sets the iterations of the named tuneable mutational stage
+    SetIters {
+        /// The iterations
+        iters: u64,
+        /// The name of the stage
+        stage: String,
+    },
+    /// Sets the weights of the mutations of the tuneable scheduled mutator
+    SetMutationWeights(Vec<f32>),
+    /// Sets the next testcase of the tuneable scheduler
+    SetNextScheduled(CorpusId),
+    /// Sets the maximum size of the inputs
+    SetMaxSize(usize),
+    /// Writes the inputs of the corpus to the directory
+    DumpCorpus(PathBuf),
+}
+
+fn parse_arg<T>(arg: Option<&str>, what: &str) -> Result<T, Error>
+where
+    T: core::str::FromStr,
+{
+    arg.and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| Error::illegal_argument(format!("Expected {what}")))
+}
+
+impl ControlCommand {
+    /// Parses a command line
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let mut args = line.split_whitespace();
+        let command = match args.next() {
+            Some("iters") => Self::SetIters {
+                iters: parse_arg(args.next(), "the iterations")?,
+                stage: args
+                    .next()
+                    .unwrap_or(STD_TUNEABLE_MUTATIONAL_STAGE_NAME)
+                    .into(),
+            },
+            Some("mutation-weights") => Self::SetMutationWeights(
+                args.by_ref()
+                    .map(|weight| parse_arg(Some(weight), "the weights of the mutations"))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some("next") => {
+                Self::SetNextScheduled(CorpusId(parse_arg(args.next(), "a corpus id")?))
+            }
+            Some("max-size") => Self::SetMaxSize(parse_arg(args.next(), "the maximum size")?),
+            Some("dump-corpus") => Self::DumpCorpus(parse_arg(
+                args.next(),
+                "the directory to dump the corpus to",
+            )?),
+            Some(command) => {
+                return Err(Error::illegal_argument(format!(
+                    "Unknown command {command}, expected one of {CONTROL_COMMANDS}"
+                )))
+            }
+            None => return Err(Error::illegal_argument("Empty command")),
+        };
+        if let Some(arg) = args.next() {
+            return Err(Error::illegal_argument(format!(
+                "Unexpected argument {arg}"
+            )));
+        }
+        Ok(command)
+    }
+
+    /// Applies the command to the state
+    pub fn apply<S>(self, state: &mut S) -> Result<(), Error>
+    where
+        S: HasCorpus + HasMaxSize + HasMetadata + HasNamedMetadata,
+        <S::Corpus as Corpus>::Input: Input,
+    {
+        match self {
+            Self::SetIters { iters, stage } => set_iters_by_name(state, iters, &stage),
+            Self::SetMutationWeights(mut weights) => {
+                let total: f32 = weights.iter().sum();
+                if total <= 0.0 {
+                    return Err(Error::illegal_argument(
+                        "The weights of the mutations must add up to more than 0",
+                    ));
+                }
+                for weight in &mut weights {
+                    *weight /= total;
+                }
+                libafl_bolts::math::calculate_cumulative_distribution_in_place(&mut weights)?;
+                let metadata = TuneableScheduledMutatorMetadata::get_mut(state)?;
+                metadata.mutation_ids.clear();
+                metadata.next_id = 0.into();
+                metadata.mutation_probabilities_cumulative = weights;
+                Ok(())
+            }
+            Self::SetNextScheduled(id) => {
+                if !TuneableScheduler::is_in_use(state) {
+                    return Err(Error::illegal_state("TuneableScheduler not in use"));
+                }
+                // Fails here, rather than when scheduling it
+                state.corpus().get(id)?;
+                TuneableScheduler::set_next(state, id);
+                Ok(())
+            }
+            Self::SetMaxSize(max_size) => {
+                state.set_max_size(max_size);
+                Ok(())
+            }
+            Self::DumpCorpus(dir) => dump_corpus(state, &dir),
+        }
+    }
+}
+
+fn dump_corpus<S>(state: &S, dir: &Path) -> Result<(), Error>
+where
+    S: HasCorpus,
+    <S::Corpus as Corpus>::Input: Input,
+{
+    fs::create_dir_all(dir)?;
+    for id in state.corpus().ids() {
+        let input = state.corpus().cloned_input_for_id(id)?;
+        input.to_file(dir.join(input.generate_name(Some(id))))?;
+    }
+    Ok(())
+}
+
+/// A socket receiving [`ControlCommand`]s, see the [module-level documentation](self)
+#[derive(Debug)]
+pub enum ControlListener {
+    /// A TCP listener
+    Tcp(TcpListener),
+    /// A unix socket listener
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl ControlListener {
+    /// Listens for commands on a TCP address, for example `127.0.0.1:1337`
+    pub fn bind_tcp<A>(addr: A) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Tcp(listener))
+    }
+
+    /// Listens for commands on a unix socket at `path`
+    #[cfg(unix)]
+    pub fn bind_unix<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let listener = UnixListener::bind(path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self::Unix(listener))
+    }
+
+    /// The address of the TCP listener
+    #[must_use]
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(listener) => listener.local_addr().ok(),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Handles the commands of the pending connections, without waiting for new ones.
+    ///
+    /// Returns after at most about [`CONTROL_SERVE_TIMEOUT`], leaving the remaining connections for the next call.
+    pub fn serve<S>(&mut self, state: &mut S) -> Result<(), Error>
+    where
+        S: HasCorpus + HasMaxSize + HasMetadata + HasNamedMetadata,
+        <S::Corpus as Corpus>::Input: Input,
+    {
+        let deadline = Instant::now() + CONTROL_SERVE_TIMEOUT;
+        while Instant::now() < deadline {
+            let accepted = match self {
+                Self::Tcp(listener) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(false)?;
+                    handle_connection(state, &stream, deadline);
+                    Ok(())
+                }),
+                #[cfg(unix)]
+                Self::Unix(listener) => listener.accept().and_then(|(stream, _)| {
+                    stream.set_nonblocking(false)?;
+                    handle_connection(state, &stream, deadline);
+                    Ok(())
+                }),
+            };
+            match accepted {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
+                Err(err) => log::warn!("Failed to accept a control connection: {err}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The connections accepted by a [`ControlListener`]
+trait ControlStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl ControlStream for TcpStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+}
+
+#[cfg(unix)]
+impl ControlStream for UnixStream {
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+}
+
+/// Reads the next line into `line`, without the newline.
+///
+/// Returns `false` at the end of the stream, and fails if the line is not complete at the `deadline`.
+fn read_line<T>(
+    reader: &mut BufReader<&T>,
+    line: &mut Vec<u8>,
+    deadline: Instant,
+) -> io::Result<bool>
+where
+    T: ControlStream,
+    for<'a> &'a T: Read,
+{
+    line.clear();
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "out of time for the control commands",
+            ));
+        }
+        reader
+            .get_ref()
+            .set_read_timeout(Some(remaining.min(CONTROL_READ_TIMEOUT)))?;
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            return Ok(!line.is_empty());
+        }
+        if let Some(newline) = buf.iter().position(|&byte| byte == b'\n') {
+            line.extend_from_slice(&buf[..newline]);
+            reader.consume(newline + 1);
+            return Ok(true);
+        }
+        let len = buf.len();
+        line.extend_from_slice(buf);
+        reader.consume(len);
+    }
+}
+
+fn handle_connection<S, T>(state: &mut S, stream: &T, deadline: Instant)
+where
+    S: HasCorpus + HasMaxSize + HasMetadata + HasNamedMetadata,
+    <S::Corpus as Corpus>::Input: Input,
+    T: ControlStream,
+    for<'a> &'a T: Read + Write,
+{
+    let mut reader = BufReader::new(stream);
+    let mut line = Vec::new();
+    let mut commands = 0;
+    loop {
+        match read_line(&mut reader, &mut line, deadline) {
+            Ok(true) => {}
+            Ok(false) => return,
+            Err(err) => {
+                log::debug!("Closing the control connection: {err}");
+                return;
+            }
+        }
+        let Ok(line) = core::str::from_utf8(&line) else {
+            log::debug!("Closing the control connection: the command is not valid UTF-8");
+            return;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let too_many = commands == CONTROL_MAX_COMMANDS;
+        let reply = if too_many {
+            format!("error: More than {CONTROL_MAX_COMMANDS} commands, closing the connection\n")
+        } else {
+            commands += 1;
+            log::info!("Control command: {line}");
+            match ControlCommand::parse(line).and_then(|command| command.apply(state)) {
+                Ok(()) => "ok\n".into(),
+                Err(err) => format!("error: {err}\n"),
+            }
+        };
+        let mut writer = stream;
+        if let Err(err) = writer.write_all(reply.as_bytes()) {
+            log::debug!("Closing the control connection: {err}");
+            return;
+        }
+        if too_many {
+            return;
+        }
+    }
+}
+
+/// A [`Stage`] handling the [`ControlCommand`]s received by a [`ControlListener`]
+#[derive(Debug)]
+pub struct ControlStage<EM, Z> {
+    listener: ControlListener,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, Z> UsesState for ControlStage<EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for ControlStage<EM, Z>
+where
+    EM: UsesState,
+    E: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    EM::State: HasCorpus + HasMaxSize + HasMetadata + HasNamedMetadata,
+    <<EM as UsesState>::State as HasCorpus>::Corpus: Corpus<Input = Self::Input>, //delete me
+{
+    #[inline]
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        self.listener.serve(state)
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<EM, Z> ControlStage<EM, Z> {
+    /// Creates a new [`ControlStage`] handling the commands received by the `listener`
+    #[must_use]
+    pub fn new(listener: ControlListener) -> Self {
+        Self {
+            listener,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+    use core::time::Duration;
+    use std::{
+        io::{Read, Write},
+        net::{Shutdown, TcpStream},
+        thread,
+        time::Instant,
+    };
+
+    use super::{ControlCommand, ControlListener, CONTROL_MAX_COMMANDS, CONTROL_SERVE_TIMEOUT};
+    use crate::{
+        corpus::CorpusId,
+        inputs::BytesInput,
+        schedulers::TuneableScheduler,
+        state::{HasMaxSize, StdState},
+    };
+
+    #[test]
+    fn test_control_listener() {
+        assert_eq!(
+            ControlCommand::parse("next 3").unwrap(),
+            ControlCommand::SetNextScheduled(CorpusId(3))
+        );
+        assert!(ControlCommand::parse("max-size").is_err());
+        assert!(ControlCommand::parse("max-size 1 2").is_err());
+
+        let mut state = StdState::nop::<BytesInput>().unwrap();
+        let _scheduler = TuneableScheduler::new(&mut state);
+        let mut listener = ControlListener::bind_tcp("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.tcp_addr().unwrap()).unwrap();
+        client
+            .write_all(b"max-size 4096\nnext 0\nmutation-weights 1 3\n")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        listener.serve(&mut state).unwrap();
+        assert_eq!(state.max_size(), 4096);
+        let mut replies = String::new();
+        client.read_to_string(&mut replies).unwrap();
+        let replies = replies.lines().collect::<Vec<_>>();
+        assert_eq!(replies[0], "ok");
+        // the corpus is empty, and no tuneable mutator is in use
+        assert!(replies[1].starts_with("error:"));
+        assert!(replies[2].starts_with("error:"));
+
+        // The connection is closed after too many commands
+        let mut client = TcpStream::connect(listener.tcp_addr().unwrap()).unwrap();
+        let commands = "max-size 8\n".repeat(CONTROL_MAX_COMMANDS + 2);
+        client.write_all(commands.as_bytes()).unwrap();
+        listener.serve(&mut state).unwrap();
+        let mut replies = String::new();
+        client.read_to_string(&mut replies).unwrap();
+        let replies = replies.lines().collect::<Vec<_>>();
+        assert_eq!(replies.len(), CONTROL_MAX_COMMANDS + 1);
+        assert!(replies[..CONTROL_MAX_COMMANDS]
+            .iter()
+            .all(|reply| *reply == "ok"));
+        assert!(replies[CONTROL_MAX_COMMANDS].starts_with("error:"));
+    }
+
+    #[test]
+    fn test_control_listener_deadline() {
+        let mut state = StdState::nop::<BytesInput>().unwrap();
+        let mut listener = ControlListener::bind_tcp("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.tcp_addr().unwrap()).unwrap();
+        // A client trickling a command that never ends is never idle for long
+        let trickle = thread::spawn(move || {
+            for _ in 0..50 {
+                if client.write_all(b"x").is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(100));
+            }
+        });
+
+        let start = Instant::now();
+        listener.serve(&mut state).unwrap();
+        assert!(start.elapsed() < CONTROL_SERVE_TIMEOUT + Duration::from_millis(500));
+        trickle.join().unwrap();
+    }
+}
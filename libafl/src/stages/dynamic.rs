@@ -0,0 +1,265 @@
+//! Stages assembled at runtime, for example from a config file.
+//!
+//! A [`DynStagesTuple`] holds boxed stages, so that plugin-style fuzzers can pick them at runtime.
+//! Unlike a plain `Vec` of boxed stages, it keeps track of the current stage, as the tuple lists do,
+//! so that a restarted fuzzer resumes in the stage it crashed in.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+use crate::{
+    events::EventProcessor,
+    inputs::UsesInput,
+    stages::{HasCurrentStageId, Stage, StageId, StagesTuple},
+    state::{State, UsesState},
+    Error,
+};
+
+/// A boxed [`Stage`], as held by a [`DynStagesTuple`]
+pub type BoxedStage<E, EM, S, Z> =
+    Box<dyn Stage<E, EM, Z, State = S, Input = <S as UsesInput>::Input>>;
+
+/// [`StagesTuple`] holding boxed stages, see the [module-level documentation](self)
+pub struct DynStagesTuple<E, EM, S, Z>
+where
+    S: UsesInput,
+{
+    stages: Vec<BoxedStage<E, EM, S, Z>>,
+}
+
+impl<E, EM, S, Z> fmt::Debug for DynStagesTuple<E, EM, S, Z>
+where
+    S: UsesInput,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DynStagesTuple")
+            .field("len", &self.stages.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E, EM, S, Z> Default for DynStagesTuple<E, EM, S, Z>
+where
+    S: UsesInput,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E, EM, S, Z> DynStagesTuple<E, EM, S, Z>
+where
+    S: UsesInput,
+{
+    /// Creates a new, empty [`DynStagesTuple`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    /// The number of stages
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// If there are no stages
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+}
+
+impl<E, EM, S, Z> DynStagesTuple<E, EM, S, Z>
+where
+    E: UsesState<State = S>,
+    EM: UsesState<State = S>,
+    Z: UsesState<State = S>,
+    S: State,
+{
+    /// Adds a `stage`, run after the others
+    pub fn push<ST>(&mut self, stage: ST)
+    where
+        ST: Stage<E, EM, Z, State = S> + 'static,
+    {
+        self.stages.push(Box::new(stage));
+    }
+
+    /// Adds a `stage`, run after the others, builder-style
+    #[must_use]
+    pub fn with<ST>(mut self, stage: ST) -> Self
+    where
+        ST: Stage<E, EM, Z, State = S> + 'static,
+    {
+        self.push(stage);
+        self
+    }
+}
+
+impl<E, EM, S, Z> From<Vec<BoxedStage<E, EM, S, Z>>> for DynStagesTuple<E, EM, S, Z>
+where
+    S: UsesInput,
+{
+    fn from(stages: Vec<BoxedStage<E, EM, S, Z>>) -> Self {
+        Self { stages }
+    }
+}
+
+impl<E, EM, S, Z> StagesTuple<E, EM, S, Z> for DynStagesTuple<E, EM, S, Z>
+where
+    E: UsesState<State = S>,
+    EM: UsesState<State = S> + EventProcessor<E, Z>,
+    Z: UsesState<State = S>,
+    S: HasCurrentStageId + State,
+{
+    /// Performs all stages, in order, resuming in the current stage after a restart.
+    /// Checks after every stage if state wants to stop
+    /// and returns an [`Error::ShuttingDown`] if so
+    fn perform_all(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let len = self.stages.len();
+        for (idx, stage) in self.stages.iter_mut().enumerate() {
+            // Numbered from the end, as in the tuple lists
+            let id = StageId(len - idx);
+            match state.current_stage_id()? {
+                Some(current) if current < id => {
+                    // do nothing; we are resuming a later stage
+                }
+                Some(current) if current == id => {
+                    // perform the stage, but don't set it
+                    stage.perform_restartable(fuzzer, executor, state, manager)?;
+                    state.clear_stage_id()?;
+                }
+                Some(_) => {
+                    unreachable!("We should clear the stage index before we get here...");
+                }
+                None => {
+                    state.set_current_stage_id(id)?;
+                    stage.perform_restartable(fuzzer, executor, state, manager)?;
+                    state.clear_stage_id()?;
+                }
+            }
+
+            if state.stop_requested() {
+                state.discard_stop_request();
+                manager.on_shutdown()?;
+                return Err(Error::shutting_down());
+            }
+        }
+
+        if state.current_stage_id()?.is_some() {
+            Err(Error::illegal_state(
+                "Got to the end of the stages without completing resume.",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::marker::PhantomData;
+
+    use libafl_bolts::impl_serdeany;
+    use serde::{Deserialize, Serialize};
+
+    use super::DynStagesTuple;
+    use crate::{
+        events::NopEventManager,
+        fuzzer::NopFuzzer,
+        inputs::NopInput,
+        stages::{HasCurrentStageId, Stage, StageId, StagesTuple},
+        state::{State, StdState, UsesState},
+        Error, HasMetadata,
+    };
+
+    /// The stages that ran, in order
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct RanStages(Vec<usize>);
+
+    impl_serdeany!(RanStages);
+
+    struct RecordingStage<S> {
+        id: usize,
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> UsesState for RecordingStage<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<E, EM, Z> Stage<E, EM, Z> for RecordingStage<Z::State>
+    where
+        E: UsesState<State = Z::State>,
+        EM: UsesState<State = Z::State>,
+        Z: UsesState,
+        Z::State: HasMetadata,
+    {
+        fn perform(
+            &mut self,
+            _fuzzer: &mut Z,
+            _executor: &mut E,
+            state: &mut Self::State,
+            _manager: &mut EM,
+        ) -> Result<(), Error> {
+            state
+                .metadata_or_insert_with(RanStages::default)
+                .0
+                .push(self.id);
+            Ok(())
+        }
+
+        fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_dyn_stages_resume() {
+        #[cfg(any(not(feature = "serdeany_autoreg"), miri))]
+        unsafe {
+            RanStages::register();
+        }
+
+        let mut state = StdState::nop::<NopInput>().unwrap();
+        let mut fuzzer = NopFuzzer::new();
+        // The stages do not run the target, any executor will do
+        let mut executor = NopFuzzer::new();
+        let mut manager = NopEventManager::new();
+
+        let mut stages = DynStagesTuple::new();
+        for id in 0..3 {
+            stages.push(RecordingStage {
+                id,
+                phantom: PhantomData,
+            });
+        }
+        stages
+            .perform_all(&mut fuzzer, &mut executor, &mut state, &mut manager)
+            .unwrap();
+        assert_eq!(state.metadata::<RanStages>().unwrap().0, [0, 1, 2]);
+
+        // Restarted in the second stage
+        state.metadata_mut::<RanStages>().unwrap().0.clear();
+        state.set_current_stage_id(StageId(2)).unwrap();
+        stages
+            .perform_all(&mut fuzzer, &mut executor, &mut state, &mut manager)
+            .unwrap();
+        assert_eq!(state.metadata::<RanStages>().unwrap().0, [1, 2]);
+        assert!(state.current_stage_id().unwrap().is_none());
+    }
+}
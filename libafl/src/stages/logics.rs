@@ -1,8 +1,9 @@
 //! Stage wrappers that add logics to stage list
 
-use core::marker::PhantomData;
+use core::{marker::PhantomData, time::Duration};
 
 use crate::{
+    executors::HasTimeout,
     stages::{HasCurrentStageId, HasNestedStageStatus, Stage, StageId, StagesTuple},
     state::UsesState,
     Error,
@@ -317,3 +318,188 @@ impl<E, EM, ST, Z> OptionalStage<E, EM, ST, Z> {
         }
     }
 }
+
+/// A stage wrapper that runs the wrapped stages with a different executor timeout,
+/// restoring the previous timeout afterwards.
+///
+/// Useful for stages that need more (or less) time per execution than the main fuzzing loop,
+/// such as calibration or minimization.
+#[derive(Debug)]
+pub struct TimeoutStage<E, EM, ST, Z> {
+    timeout: Duration,
+    stages: ST,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, ST, Z> UsesState for TimeoutStage<E, EM, ST, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, ST, Z> Stage<E, EM, Z> for TimeoutStage<E, EM, ST, Z>
+where
+    E: UsesState + HasTimeout,
+    EM: UsesState<State = Self::State>,
+    ST: StagesTuple<E, EM, Self::State, Z>,
+    Z: UsesState<State = Self::State>,
+    Self::State: HasNestedStageStatus,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let previous = executor.timeout();
+        executor.set_timeout(self.timeout);
+        let res = self.stages.perform_all(fuzzer, executor, state, manager);
+        executor.set_timeout(previous);
+        res
+    }
+
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        NestedStageRetryCountRestartHelper::should_restart(state, self)
+    }
+
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        NestedStageRetryCountRestartHelper::clear_progress(state, self)
+    }
+}
+
+impl<E, EM, ST, Z> TimeoutStage<E, EM, ST, Z> {
+    /// Constructor for stages that run with the given executor `timeout`
+    #[must_use]
+    pub fn new(timeout: Duration, stages: ST) -> Self {
+        Self {
+            timeout,
+            stages,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The timeout the wrapped stages run with
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{marker::PhantomData, time::Duration};
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::TimeoutStage;
+    use crate::{
+        events::NopEventManager,
+        executors::HasTimeout,
+        fuzzer::NopFuzzer,
+        inputs::NopInput,
+        stages::{Stage, StagesTuple},
+        state::{State, StdState, UsesState},
+        Error,
+    };
+
+    /// An executor that only has a timeout
+    struct TimeoutExecutor<S> {
+        timeout: Duration,
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> UsesState for TimeoutExecutor<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<S> HasTimeout for TimeoutExecutor<S> {
+        fn timeout(&self) -> Duration {
+            self.timeout
+        }
+
+        fn set_timeout(&mut self, timeout: Duration) {
+            self.timeout = timeout;
+        }
+    }
+
+    /// Remembers the timeout of the executor it ran with, and fails if told to
+    struct RecordingStage<S> {
+        seen: Option<Duration>,
+        fail: bool,
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> UsesState for RecordingStage<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<E, EM, Z> Stage<E, EM, Z> for RecordingStage<Z::State>
+    where
+        E: UsesState<State = Z::State> + HasTimeout,
+        EM: UsesState<State = Z::State>,
+        Z: UsesState,
+    {
+        fn perform(
+            &mut self,
+            _fuzzer: &mut Z,
+            executor: &mut E,
+            _state: &mut Self::State,
+            _manager: &mut EM,
+        ) -> Result<(), Error> {
+            self.seen = Some(executor.timeout());
+            if self.fail {
+                return Err(Error::illegal_state("The stage failed"));
+            }
+            Ok(())
+        }
+
+        fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+            Ok(true)
+        }
+
+        fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_timeout_stage() {
+        let mut state = StdState::nop::<NopInput>().unwrap();
+        let mut fuzzer = NopFuzzer::new();
+        let mut manager = NopEventManager::new();
+        let mut executor = TimeoutExecutor {
+            timeout: Duration::from_millis(1500),
+            phantom: PhantomData,
+        };
+        let mut stages = tuple_list!(TimeoutStage::new(
+            Duration::from_millis(50),
+            tuple_list!(RecordingStage {
+                seen: None,
+                fail: false,
+                phantom: PhantomData,
+            })
+        ));
+
+        stages
+            .perform_all(&mut fuzzer, &mut executor, &mut state, &mut manager)
+            .unwrap();
+        assert_eq!(stages.0.stages.0.seen, Some(Duration::from_millis(50)));
+        assert_eq!(executor.timeout(), Duration::from_millis(1500));
+
+        // The previous timeout is restored, even if a wrapped stage fails
+        stages.0.stages.0.fail = true;
+        assert!(stages
+            .0
+            .perform(&mut fuzzer, &mut executor, &mut state, &mut manager)
+            .is_err());
+        assert_eq!(executor.timeout(), Duration::from_millis(1500));
+    }
+}
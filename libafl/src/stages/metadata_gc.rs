@@ -0,0 +1,270 @@
+//! Size accounting and garbage collection of the metadata.
+//!
+//! The metadata of the state only grows, unless the component that added it removes it again:
+//! concolic traces, generalized inputs or the progress of stages on testcases that left the corpus
+//! all end up in the serialized state, which can reach gigabytes over a long campaign.
+//! The [`MetadataGcStage`] periodically runs a tuple of [`MetadataGc`] hooks, that drop the stale metadata,
+//! and reports the serialized size of the metadata, by type, to the monitor.
+
+use alloc::{borrow::Cow, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+
+use hashbrown::HashMap;
+use libafl_bolts::current_time;
+
+use crate::{
+    corpus::Corpus,
+    events::{Event, EventFirer},
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
+    stages::{RetryCountRestartHelper, Stage},
+    state::{HasCorpus, UsesState},
+    Error, HasMetadata, HasNamedMetadata,
+};
+
+/// A hook dropping stale metadata, run by the [`MetadataGcStage`]
+pub trait MetadataGc<S> {
+    /// Removes the metadata that is no longer needed from the `state`
+    fn collect_garbage(&mut self, state: &mut S) -> Result<(), Error>;
+}
+
+/// A tuple of [`MetadataGc`] hooks
+pub trait MetadataGcTuple<S> {
+    /// Runs all the hooks, in order
+    fn collect_garbage_all(&mut self, state: &mut S) -> Result<(), Error>;
+}
+
+impl<S> MetadataGcTuple<S> for () {
+    fn collect_garbage_all(&mut self, _state: &mut S) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<Head, Tail, S> MetadataGcTuple<S> for (Head, Tail)
+where
+    Head: MetadataGc<S>,
+    Tail: MetadataGcTuple<S>,
+{
+    fn collect_garbage_all(&mut self, state: &mut S) -> Result<(), Error> {
+        self.0.collect_garbage(state)?;
+        self.1.collect_garbage_all(state)
+    }
+}
+
+/// Drops the testcases removed from the corpus from the progress kept by the stages,
+/// see [`RetryCountRestartHelper`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RemovedTestcasesGc;
+
+impl<S> MetadataGc<S> for RemovedTestcasesGc
+where
+    S: HasCorpus + HasNamedMetadata,
+{
+    fn collect_garbage(&mut self, state: &mut S) -> Result<(), Error> {
+        let Some(helpers) = state
+            .named_metadata_map()
+            .get_all::<RetryCountRestartHelper>()
+        else {
+            return Ok(());
+        };
+        let removed: Vec<_> = helpers
+            .flat_map(|helper| helper.skipped.iter().copied())
+            .filter(|id| state.corpus().get_from_all(*id).is_err())
+            .collect();
+        if removed.is_empty() {
+            return Ok(());
+        }
+        for helper in state
+            .named_metadata_map_mut()
+            .get_all_mut::<RetryCountRestartHelper>()
+            .into_iter()
+            .flatten()
+        {
+            helper.skipped.retain(|id| !removed.contains(id));
+        }
+        Ok(())
+    }
+}
+
+/// The serialized size, in bytes, of the metadata by type name, summed over the metadata of the state,
+/// its named metadata and the metadata of the testcases of the corpus that are in memory
+pub fn metadata_sizes<S>(state: &S) -> Result<HashMap<&'static str, usize>, Error>
+where
+    S: HasCorpus + HasMetadata + HasNamedMetadata,
+{
+    let mut sizes = HashMap::new();
+    let mut account = |(type_name, size): (&'static str, usize)| {
+        *sizes.entry(type_name).or_insert(0) += size;
+    };
+    state
+        .metadata_map()
+        .serialized_sizes()
+        .for_each(&mut account);
+    state
+        .named_metadata_map()
+        .serialized_sizes()
+        .for_each(&mut account);
+    for id in state.corpus().ids() {
+        let testcase = state.corpus().get(id)?.borrow();
+        testcase
+            .metadata_map()
+            .serialized_sizes()
+            .for_each(&mut account);
+    }
+    Ok(sizes)
+}
+
+/// The type name without its path, as shown to the monitor
+fn short_type_name(type_name: &str) -> &str {
+    let base = type_name.split('<').next().unwrap_or(type_name);
+    base.rsplit("::").next().unwrap_or(base)
+}
+
+/// A [`Stage`] running the [`MetadataGc`] hooks and reporting the size of the metadata,
+/// see the [module-level documentation](self)
+#[derive(Debug)]
+pub struct MetadataGcStage<GC, EM, Z> {
+    hooks: GC,
+    interval: Duration,
+    last_run: Duration,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<GC, EM, Z> UsesState for MetadataGcStage<GC, EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<E, EM, GC, Z> Stage<E, EM, Z> for MetadataGcStage<GC, EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: EventFirer,
+    GC: MetadataGcTuple<Self::State>,
+    Z: UsesState<State = Self::State>,
+    EM::State: HasCorpus + HasMetadata + HasNamedMetadata,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_run) < self.interval {
+            return Ok(());
+        }
+        self.last_run = now;
+
+        self.hooks.collect_garbage_all(state)?;
+
+        let sizes = metadata_sizes(state)?;
+        let mut total = 0;
+        for (type_name, size) in sizes {
+            total += size;
+            let name = format!("metadata_bytes_{}", short_type_name(type_name));
+            manager.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: Cow::from(name),
+                    value: UserStats::new(UserStatsValue::Number(size as u64), AggregatorOps::Sum),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        manager.fire(
+            state,
+            Event::UpdateUserStats {
+                name: Cow::from("metadata_bytes"),
+                value: UserStats::new(UserStatsValue::Number(total as u64), AggregatorOps::Sum),
+                phantom: PhantomData,
+            },
+        )?;
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
+
+impl<GC, EM, Z> MetadataGcStage<GC, EM, Z> {
+    /// Creates a new [`MetadataGcStage`] running the `hooks` and reporting the sizes at most once per `interval`
+    #[must_use]
+    pub fn new(hooks: GC, interval: Duration) -> Self {
+        Self {
+            hooks,
+            interval,
+            last_run: Duration::ZERO,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashSet;
+    use libafl_bolts::rands::StdRand;
+
+    use super::{metadata_sizes, short_type_name, MetadataGc, RemovedTestcasesGc};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        stages::RetryCountRestartHelper,
+        state::{HasCorpus, StdState},
+        HasNamedMetadata,
+    };
+
+    #[test]
+    fn test_metadata_gc() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let kept = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0])))
+            .unwrap();
+        let removed = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![1])))
+            .unwrap();
+        state.corpus_mut().remove(removed).unwrap();
+        state.add_named_metadata(
+            "stage",
+            RetryCountRestartHelper {
+                tries_remaining: None,
+                skipped: HashSet::from([kept, removed]),
+            },
+        );
+
+        let before = metadata_sizes(&state).unwrap();
+        RemovedTestcasesGc.collect_garbage(&mut state).unwrap();
+        let after = metadata_sizes(&state).unwrap();
+
+        let helper = state
+            .named_metadata::<RetryCountRestartHelper>("stage")
+            .unwrap();
+        assert_eq!(helper.skipped, HashSet::from([kept]));
+
+        let type_name = core::any::type_name::<RetryCountRestartHelper>();
+        assert!(after[type_name] < before[type_name]);
+        assert_eq!(short_type_name(type_name), "RetryCountRestartHelper");
+    }
+}
@@ -15,13 +15,26 @@ use core::{fmt, marker::PhantomData};
 #[cfg(feature = "std")]
 pub use afl_stats::{AflStatsStage, CalibrationTime, FuzzTime, SyncTime};
 pub use calibrate::CalibrationStage;
+#[cfg(feature = "casr")]
+pub use casr::{CasrClustersMetadata, CasrStage};
+#[cfg(all(unix, feature = "std"))]
+pub use checkpoint::{
+    install_checkpoint_signal_handlers, request_checkpoint, request_checkpoint_and_shutdown,
+    CheckpointStage,
+};
 pub use colorization::*;
 #[cfg(all(feature = "std", unix))]
 pub use concolic::ConcolicTracingStage;
 #[cfg(all(feature = "std", feature = "concolic_mutation", unix))]
 pub use concolic::SimpleConcolicMutationalStage;
 #[cfg(feature = "std")]
+pub use control::{
+    ControlCommand, ControlListener, ControlStage, CONTROL_MAX_COMMANDS, CONTROL_READ_TIMEOUT,
+    CONTROL_SERVE_TIMEOUT,
+};
+#[cfg(feature = "std")]
 pub use dump::*;
+pub use dynamic::{BoxedStage, DynStagesTuple};
 pub use generalization::GeneralizationStage;
 use hashbrown::HashSet;
 use libafl_bolts::{
@@ -30,18 +43,29 @@ use libafl_bolts::{
     Named,
 };
 pub use logics::*;
-pub use mutational::{MutationalStage, StdMutationalStage};
+pub use metadata_gc::{
+    metadata_sizes, MetadataGc, MetadataGcStage, MetadataGcTuple, RemovedTestcasesGc,
+};
+pub use mutational::{BatchedMutationalStage, MutationalStage, StdMutationalStage};
 pub use power::{PowerMutationalStage, StdPowerMutationalStage};
 use serde::{Deserialize, Serialize};
 pub use stats::StatsStage;
 #[cfg(feature = "std")]
 pub use sync::*;
+pub use taint::{InfluentialBytesMetadata, TaintMutationalStage, TaintTracingStage, TaintTracker};
 #[cfg(feature = "std")]
 pub use time_tracker::TimeTrackingStageWrapper;
 pub use tmin::{
     MapEqualityFactory, MapEqualityFeedback, StdTMinMutationalStage, TMinMutationalStage,
 };
 pub use tracing::{ShadowTracingStage, TracingStage};
+#[cfg(all(target_os = "linux", feature = "regex"))]
+pub use triage::PtraceBackend;
+#[cfg(feature = "std")]
+pub use triage::{
+    CrashContextBackend, CrashDetails, Exploitability, TriageBackend, TriageMetadata, TriageReport,
+    TriageStage,
+};
 pub use tuneable::*;
 use tuple_list::NonEmptyTuple;
 #[cfg(feature = "unicode")]
@@ -70,21 +94,32 @@ pub mod tmin;
 #[cfg(feature = "std")]
 pub mod afl_stats;
 pub mod calibrate;
+#[cfg(feature = "casr")]
+pub mod casr;
+#[cfg(all(unix, feature = "std"))]
+pub mod checkpoint;
 pub mod colorization;
 #[cfg(all(feature = "std", unix))]
 pub mod concolic;
 #[cfg(feature = "std")]
+pub mod control;
+#[cfg(feature = "std")]
 pub mod dump;
+pub mod dynamic;
 pub mod generalization;
 pub mod generation;
 pub mod logics;
+pub mod metadata_gc;
 pub mod power;
 pub mod stats;
 #[cfg(feature = "std")]
 pub mod sync;
+pub mod taint;
 #[cfg(feature = "std")]
 pub mod time_tracker;
 pub mod tracing;
+#[cfg(feature = "std")]
+pub mod triage;
 pub mod tuneable;
 #[cfg(feature = "unicode")]
 pub mod unicode;
@@ -4,18 +4,24 @@
 use alloc::{
     borrow::{Cow, ToOwned},
     string::ToString,
+    vec::Vec,
 };
 use core::{marker::PhantomData, num::NonZeroUsize};
 
 use libafl_bolts::{rands::Rand, Named};
+use serde::Serialize;
 
 use crate::{
     corpus::{Corpus, CorpusId, Testcase},
-    fuzzer::Evaluator,
+    events::EventFirer,
+    executors::batched::BatchExecutor,
+    fuzzer::{Evaluator, ExecutionProcessor, HasScheduler},
     inputs::Input,
     mark_feature_time,
     mutators::{MultiMutator, MutationResult, Mutator},
     nonzero,
+    observers::ObserversTuple,
+    schedulers::Scheduler,
     stages::{RetryCountRestartHelper, Stage},
     start_timer,
     state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasRand, UsesState},
@@ -414,3 +420,147 @@ impl<E, EM, I, M, Z> MultiMutationalStage<E, EM, I, M, Z> {
         }
     }
 }
+
+/// A mutational stage for a [`BatchExecutor`], such as the [`crate::executors::BatchedExecutor`].
+///
+/// It generates a batch of mutated inputs, runs them back-to-back, and only then evaluates the feedbacks
+/// for each run, using the observer snapshots of the executor.
+/// The number of mutations per round is chosen like in the [`StdMutationalStage`].
+#[derive(Clone, Debug)]
+pub struct BatchedMutationalStage<E, EM, I, M, Z> {
+    name: Cow<'static, str>,
+    mutator: M,
+    max_iterations: NonZeroUsize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, I, Z)>,
+}
+
+/// The unique id for batched mutational stage
+static mut BATCHED_MUTATIONAL_STAGE_ID: usize = 0;
+/// The name for batched mutational stage
+pub static BATCHED_MUTATIONAL_STAGE_NAME: &str = "batchedmutational";
+
+impl<E, EM, I, M, Z> UsesState for BatchedMutationalStage<E, EM, I, M, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, I, M, Z> Named for BatchedMutationalStage<E, EM, I, M, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, I, M, Z> Stage<E, EM, Z> for BatchedMutationalStage<E, EM, I, M, Z>
+where
+    E: BatchExecutor<EM, Z, State = Self::State>,
+    E::Observers: ObserversTuple<Self::Input, Self::State> + Serialize,
+    EM: EventFirer<State = Self::State>,
+    M: Mutator<I, Self::State>,
+    Z: ExecutionProcessor<EM, E::Observers> + HasScheduler,
+    Z::State: HasCorpus + HasRand + HasNamedMetadata + HasCurrentTestcase,
+    I: MutatedTransform<Self::Input, Self::State> + Clone,
+    <<Self as UsesState>::State as HasCorpus>::Corpus: Corpus<Input = Self::Input>, //delete me
+{
+    #[inline]
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        RetryCountRestartHelper::should_restart(state, &self.name, 3)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        RetryCountRestartHelper::clear_progress(state, &self.name)
+    }
+
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let mut remaining = 1 + state.rand_mut().below(self.max_iterations);
+        let mut testcase = state.current_testcase_mut()?;
+        let Ok(input) = I::try_transform_from(&mut testcase, state) else {
+            return Ok(());
+        };
+        drop(testcase);
+
+        let mut batch = Vec::with_capacity(executor.batch_size());
+        let mut posts = Vec::with_capacity(executor.batch_size());
+        while remaining > 0 {
+            let batch_len = remaining.min(executor.batch_size());
+            remaining -= batch_len;
+
+            for _ in 0..batch_len {
+                let mut input = input.clone();
+                if self.mutator.mutate(state, &mut input)? == MutationResult::Skipped {
+                    continue;
+                }
+                let (untransformed, post) = input.try_transform_into(state)?;
+                batch.push(untransformed);
+                posts.push(post);
+            }
+
+            executor.run_batch(fuzzer, state, manager, &batch)?;
+
+            for (idx, (untransformed, post)) in batch.drain(..).zip(posts.drain(..)).enumerate() {
+                let (exit_kind, observers) = executor
+                    .snapshot(idx)
+                    .expect("the batch was run completely");
+                fuzzer
+                    .scheduler_mut()
+                    .on_evaluation(state, &untransformed, observers)?;
+                let (_, corpus_id) = fuzzer.evaluate_execution(
+                    state,
+                    manager,
+                    untransformed,
+                    observers,
+                    exit_kind,
+                    true,
+                )?;
+                self.mutator.post_exec(state, corpus_id)?;
+                post.post_exec(state, corpus_id)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<E, EM, M, Z> BatchedMutationalStage<E, EM, Z::Input, M, Z>
+where
+    Z: UsesState,
+{
+    /// Creates a new [`BatchedMutationalStage`] with the default max iterations
+    pub fn new(mutator: M) -> Self {
+        Self::transforming_with_max_iterations(mutator, nonzero!(DEFAULT_MUTATIONAL_MAX_ITERATIONS))
+    }
+
+    /// Creates a new [`BatchedMutationalStage`] with the given max iterations
+    pub fn with_max_iterations(mutator: M, max_iterations: NonZeroUsize) -> Self {
+        Self::transforming_with_max_iterations(mutator, max_iterations)
+    }
+}
+
+impl<E, EM, I, M, Z> BatchedMutationalStage<E, EM, I, M, Z> {
+    /// Creates a new transforming [`BatchedMutationalStage`] with the given max iterations
+    pub fn transforming_with_max_iterations(mutator: M, max_iterations: NonZeroUsize) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = BATCHED_MUTATIONAL_STAGE_ID;
+            BATCHED_MUTATIONAL_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(
+                BATCHED_MUTATIONAL_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
+            ),
+            mutator,
+            max_iterations,
+            phantom: PhantomData,
+        }
+    }
+}
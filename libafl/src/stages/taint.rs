@@ -0,0 +1,510 @@
+//! Taint tracking stages, focusing the mutations on the input bytes that influence the comparisons of the target,
+//! in the spirit of [FairFuzz](https://arxiv.org/abs/1709.07101) and [Angora](https://arxiv.org/abs/1803.01307).
+//!
+//! The [`TaintTracingStage`] runs a tracer executor built with taint tracking, for example with the
+//! `DataFlowSanitizer` configuration of `libafl_cc` and the `dfsan` runtime of `libafl_targets`,
+//! and stores the influential byte ranges of each testcase in its [`InfluentialBytesMetadata`].
+//! The [`TaintMutationalStage`] then only mutates these ranges.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    collections::VecDeque,
+    string::ToString,
+    vec::Vec,
+};
+use core::{marker::PhantomData, num::NonZeroUsize, ops::Range};
+
+use libafl_bolts::{rands::Rand, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Corpus,
+    executors::{Executor, HasObservers},
+    fuzzer::Evaluator,
+    inputs::{BytesSubInput, HasMutatorBytes, HasTargetBytes, UsesInput},
+    mark_feature_time,
+    mutators::{MutationResult, Mutator},
+    nonzero,
+    observers::ObserversTuple,
+    stages::{mutational::DEFAULT_MUTATIONAL_MAX_ITERATIONS, RetryCountRestartHelper, Stage},
+    start_timer,
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasRand, UsesState},
+    Error, HasMetadata, HasNamedMetadata,
+};
+#[cfg(feature = "introspection")]
+use crate::{monitors::PerfFeature, state::HasClientPerfMonitor};
+
+/// A taint tracking runtime, labelling ranges of the input bytes, and reporting the labels that reached a comparison
+pub trait TaintTracker {
+    /// The maximum count of ranges labelled in one execution, at most 64
+    fn max_labels(&self) -> usize;
+
+    /// Label the `ranges` of the input bytes in the next executions, the range at index `i` with the label `i`
+    fn set_ranges(&mut self, ranges: &[Range<usize>]);
+
+    /// The labels that reached a comparison in the last execution, bit `i` for the label `i`
+    fn influential_labels(&self) -> u64;
+}
+
+/// The ranges of the input bytes that influence the comparisons of the target, sorted and not overlapping
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct InfluentialBytesMetadata {
+    ranges: Vec<Range<usize>>,
+}
+
+libafl_bolts::impl_serdeany!(InfluentialBytesMetadata);
+
+impl InfluentialBytesMetadata {
+    /// Creates a new [`InfluentialBytesMetadata`], sorting and merging the `ranges`
+    #[must_use]
+    pub fn new(ranges: Vec<Range<usize>>) -> Self {
+        Self {
+            ranges: merge_ranges(ranges),
+        }
+    }
+
+    /// The influential ranges
+    #[must_use]
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// The count of influential bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ranges.iter().map(ExactSizeIterator::len).sum()
+    }
+
+    /// No byte is influential
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+/// Sort the ranges, and merge the overlapping and adjacent ones
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    ranges.retain(|range| !range.is_empty());
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+/// Split `range` in at most `parts` ranges of about the same length
+fn split_range(range: Range<usize>, parts: usize) -> impl Iterator<Item = Range<usize>> {
+    let parts = parts.clamp(1, range.len().max(1));
+    let len = range.len();
+    (0..parts).map(move |i| range.start + i * len / parts..range.start + (i + 1) * len / parts)
+}
+
+/// Find the influential bytes of an input of `len` bytes, labelling at most `labels` ranges per execution.
+///
+/// The ranges whose label reached a comparison get bisected until they are single bytes.
+/// The ranges still to bisect after `max_executions` are kept as they are.
+fn find_influential_ranges<F>(
+    len: usize,
+    labels: usize,
+    max_executions: usize,
+    mut run: F,
+) -> Result<Vec<Range<usize>>, Error>
+where
+    F: FnMut(&[Range<usize>]) -> Result<u64, Error>,
+{
+    if labels == 0 || labels > 64 {
+        return Err(Error::illegal_argument(format!(
+            "A taint tracker must provide between 1 and 64 labels, not {labels}"
+        )));
+    }
+
+    let mut pending: VecDeque<Range<usize>> = split_range(0..len, labels)
+        .filter(|range| !range.is_empty())
+        .collect();
+    let mut influential = Vec::new();
+
+    for _ in 0..max_executions {
+        if pending.is_empty() {
+            break;
+        }
+        let batch: Vec<Range<usize>> = pending.drain(..labels.min(pending.len())).collect();
+        let mask = run(&batch)?;
+        for (label, range) in batch.into_iter().enumerate() {
+            if mask & (1 << label) == 0 {
+                continue;
+            }
+            if range.len() == 1 {
+                influential.push(range);
+            } else {
+                pending.extend(split_range(range, 2));
+            }
+        }
+    }
+    influential.extend(pending);
+
+    Ok(merge_ranges(influential))
+}
+
+/// The default maximum count of executions to find the influential bytes of a testcase
+pub const DEFAULT_TAINT_MAX_EXECUTIONS: usize = 64;
+
+/// The counter for giving this stage unique id
+static mut TAINT_TRACING_STAGE_ID: usize = 0;
+/// The name for taint tracing stage
+pub static TAINT_TRACING_STAGE_NAME: &str = "taint_tracing";
+
+/// A stage that runs a tracer executor with taint tracking, to find the input bytes that influence the comparisons.
+/// The result is stored in the [`InfluentialBytesMetadata`] of the testcase.
+#[derive(Clone, Debug)]
+pub struct TaintTracingStage<EM, T, TE, Z> {
+    name: Cow<'static, str>,
+    tracer_executor: TE,
+    tracker: T,
+    max_executions: NonZeroUsize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(EM, TE, Z)>,
+}
+
+impl<EM, T, TE, Z> UsesState for TaintTracingStage<EM, T, TE, Z>
+where
+    TE: UsesState,
+{
+    type State = TE::State;
+}
+
+impl<EM, T, TE, Z> Named for TaintTracingStage<EM, T, TE, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<EM, T, TE, Z> TaintTracingStage<EM, T, TE, Z>
+where
+    T: TaintTracker,
+    TE: Executor<EM, Z> + HasObservers,
+    TE::Input: HasTargetBytes,
+    TE::Observers: ObserversTuple<TE::Input, <Self as UsesState>::State>,
+    <TE as UsesState>::State: HasExecutions + HasCorpus,
+    EM: UsesState<State = <Self as UsesState>::State>,
+    Z: UsesState<State = <Self as UsesState>::State>,
+{
+    /// Find the ranges of `input` that influence the comparisons of the target
+    pub fn trace(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut <Self as UsesState>::State,
+        manager: &mut EM,
+        input: &TE::Input,
+    ) -> Result<Vec<Range<usize>>, Error> {
+        let len = input.target_bytes().as_slice().len();
+        let labels = self.tracker.max_labels();
+
+        let ranges = find_influential_ranges(len, labels, self.max_executions.get(), |ranges| {
+            self.tracker.set_ranges(ranges);
+
+            start_timer!(state);
+            self.tracer_executor
+                .observers_mut()
+                .pre_exec_all(state, input)?;
+            mark_feature_time!(state, PerfFeature::PreExecObservers);
+
+            start_timer!(state);
+            let exit_kind = self
+                .tracer_executor
+                .run_target(fuzzer, state, manager, input)?;
+            mark_feature_time!(state, PerfFeature::TargetExecution);
+
+            start_timer!(state);
+            self.tracer_executor
+                .observers_mut()
+                .post_exec_all(state, input, &exit_kind)?;
+            mark_feature_time!(state, PerfFeature::PostExecObservers);
+
+            Ok(self.tracker.influential_labels())
+        });
+        self.tracker.set_ranges(&[]);
+
+        ranges
+    }
+}
+
+impl<E, EM, T, TE, Z> Stage<E, EM, Z> for TaintTracingStage<EM, T, TE, Z>
+where
+    E: UsesState<State = <Self as UsesState>::State>,
+    T: TaintTracker,
+    TE: Executor<EM, Z> + HasObservers,
+    TE::Input: HasTargetBytes,
+    TE::Observers: ObserversTuple<TE::Input, <Self as UsesState>::State>,
+    <TE as UsesState>::State: HasExecutions + HasCorpus + HasNamedMetadata + HasCurrentTestcase,
+    EM: UsesState<State = <Self as UsesState>::State>,
+    Z: UsesState<State = <Self as UsesState>::State>,
+    <<TE as UsesState>::State as HasCorpus>::Corpus: Corpus<Input = TE::Input>, //delete me
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut <Self as UsesState>::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        if state
+            .current_testcase()?
+            .has_metadata::<InfluentialBytesMetadata>()
+        {
+            return Ok(());
+        }
+
+        start_timer!(state);
+        let input = state.current_input_cloned()?;
+        mark_feature_time!(state, PerfFeature::GetInputFromCorpus);
+
+        let ranges = self.trace(fuzzer, state, manager, &input)?;
+        state
+            .current_testcase_mut()?
+            .add_metadata(InfluentialBytesMetadata::new(ranges));
+
+        Ok(())
+    }
+
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        RetryCountRestartHelper::no_retry(state, &self.name)
+    }
+
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        RetryCountRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<EM, T, TE, Z> TaintTracingStage<EM, T, TE, Z> {
+    /// Creates a new [`TaintTracingStage`], running `tracer_executor` with the labels of `tracker`
+    pub fn new(tracer_executor: TE, tracker: T) -> Self {
+        Self::with_max_executions(
+            tracer_executor,
+            tracker,
+            nonzero!(DEFAULT_TAINT_MAX_EXECUTIONS),
+        )
+    }
+
+    /// Creates a new [`TaintTracingStage`], running the tracer at most `max_executions` times per testcase
+    pub fn with_max_executions(
+        tracer_executor: TE,
+        tracker: T,
+        max_executions: NonZeroUsize,
+    ) -> Self {
+        // unsafe but impossible that you create two threads both instantiating this instance
+        let stage_id = unsafe {
+            let ret = TAINT_TRACING_STAGE_ID;
+            TAINT_TRACING_STAGE_ID += 1;
+            ret
+        };
+
+        Self {
+            name: Cow::Owned(
+                TAINT_TRACING_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_ref(),
+            ),
+            tracer_executor,
+            tracker,
+            max_executions,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Gets the underlying tracer executor
+    pub fn executor(&self) -> &TE {
+        &self.tracer_executor
+    }
+
+    /// Gets the underlying tracer executor (mut)
+    pub fn executor_mut(&mut self) -> &mut TE {
+        &mut self.tracer_executor
+    }
+
+    /// Gets the taint tracker
+    pub fn tracker(&self) -> &T {
+        &self.tracker
+    }
+
+    /// Gets the taint tracker (mut)
+    pub fn tracker_mut(&mut self) -> &mut T {
+        &mut self.tracker
+    }
+}
+
+/// The unique id for taint mutational stage
+static mut TAINT_MUTATIONAL_STAGE_ID: usize = 0;
+/// The name for taint mutational stage
+pub static TAINT_MUTATIONAL_STAGE_NAME: &str = "taint_mutational";
+
+/// A mutational stage that only mutates the influential bytes of the testcase, found by the [`TaintTracingStage`].
+/// Each iteration applies the mutator to one random influential range, that may grow or shrink.
+/// Testcases without influential bytes are skipped.
+#[derive(Clone, Debug)]
+pub struct TaintMutationalStage<E, EM, M, Z> {
+    name: Cow<'static, str>,
+    mutator: M,
+    max_iterations: NonZeroUsize,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, M, Z> UsesState for TaintMutationalStage<E, EM, M, Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+impl<E, EM, M, Z> Named for TaintMutationalStage<E, EM, M, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<E, EM, M, Z> Stage<E, EM, Z> for TaintMutationalStage<E, EM, M, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState<State = Self::State>,
+    M: for<'a> Mutator<BytesSubInput<'a, Self::Input>, Self::State>,
+    Z: Evaluator<E, EM>,
+    Z::State: HasCorpus + HasRand + HasCurrentTestcase + HasNamedMetadata,
+    <Self::State as UsesInput>::Input: HasMutatorBytes + Clone,
+    <<Self as UsesState>::State as HasCorpus>::Corpus: Corpus<Input = Self::Input>, //delete me
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Self::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let ranges = match state
+            .current_testcase()?
+            .metadata::<InfluentialBytesMetadata>()
+        {
+            Ok(meta) if !meta.is_empty() => meta.ranges().to_vec(),
+            _ => return Ok(()),
+        };
+
+        start_timer!(state);
+        let input = state.current_input_cloned()?;
+        mark_feature_time!(state, PerfFeature::GetInputFromCorpus);
+
+        let num = 1 + state.rand_mut().below(self.max_iterations);
+        for _ in 0..num {
+            let mut input = input.clone();
+            let range = state.rand_mut().choose(&ranges).unwrap().clone();
+
+            start_timer!(state);
+            let mutated = self.mutator.mutate(state, &mut input.sub_input(range))?;
+            mark_feature_time!(state, PerfFeature::Mutate);
+
+            if mutated == MutationResult::Skipped {
+                continue;
+            }
+
+            // Time is measured directly the `evaluate_input` function
+            let (_, corpus_id) = fuzzer.evaluate_input(state, executor, manager, input)?;
+
+            start_timer!(state);
+            Mutator::<BytesSubInput<'_, Self::Input>, Self::State>::post_exec(
+                &mut self.mutator,
+                state,
+                corpus_id,
+            )?;
+            mark_feature_time!(state, PerfFeature::MutatePostExec);
+        }
+
+        #[cfg(feature = "introspection")]
+        state.introspection_monitor_mut().finish_stage();
+
+        Ok(())
+    }
+
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        RetryCountRestartHelper::should_restart(state, &self.name, 3)
+    }
+
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        RetryCountRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<E, EM, M, Z> TaintMutationalStage<E, EM, M, Z> {
+    /// Creates a new [`TaintMutationalStage`] with the default max iterations
+    pub fn new(mutator: M) -> Self {
+        Self::with_max_iterations(mutator, nonzero!(DEFAULT_MUTATIONAL_MAX_ITERATIONS))
+    }
+
+    /// Creates a new [`TaintMutationalStage`] with the given max iterations
+    pub fn with_max_iterations(mutator: M, max_iterations: NonZeroUsize) -> Self {
+        let stage_id = unsafe {
+            let ret = TAINT_MUTATIONAL_STAGE_ID;
+            TAINT_MUTATIONAL_STAGE_ID += 1;
+            ret
+        };
+        Self {
+            name: Cow::Owned(
+                TAINT_MUTATIONAL_STAGE_NAME.to_owned() + ":" + stage_id.to_string().as_str(),
+            ),
+            mutator,
+            max_iterations,
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::ops::Range;
+
+    use super::{find_influential_ranges, merge_ranges, split_range};
+
+    #[test]
+    fn test_merge_ranges() {
+        assert_eq!(
+            merge_ranges(vec![5..7, 0..1, 1..3, 6..9, 10..10, 12..13]),
+            vec![0..3, 5..9, 12..13]
+        );
+    }
+
+    #[test]
+    fn test_split_range() {
+        let parts: Vec<Range<usize>> = split_range(0..10, 4).collect();
+        assert_eq!(parts, vec![0..2, 2..5, 5..7, 7..10]);
+        let parts: Vec<Range<usize>> = split_range(3..5, 8).collect();
+        assert_eq!(parts, vec![3..4, 4..5]);
+    }
+
+    #[test]
+    fn test_find_influential_ranges() {
+        // The target compares the bytes 3, 10 and 11 of its input
+        let influential = [3, 10, 11];
+        let mut executions = 0;
+        let ranges = find_influential_ranges(100, 8, 64, |ranges| {
+            executions += 1;
+            let mut mask = 0;
+            for (label, range) in ranges.iter().enumerate() {
+                if influential.iter().any(|byte| range.contains(byte)) {
+                    mask |= 1 << label;
+                }
+            }
+            Ok(mask)
+        })
+        .unwrap();
+        assert_eq!(ranges, vec![3..4, 10..12]);
+        assert!(executions < 10);
+
+        // Out of executions, the ranges still to bisect are kept
+        let ranges = find_influential_ranges(100, 8, 1, |_| Ok(1)).unwrap();
+        assert_eq!(ranges, vec![0..12]);
+    }
+}
@@ -0,0 +1,603 @@
+//! The [`TriageStage`] triages new solutions: it extracts the signal, the faulting address and a normalized stack hash,
+//! guesses how exploitable the crash is and buckets the solutions by stack hash.
+//!
+//! The details come from a [`TriageBackend`]: the [`CrashContextBackend`] reads the mini-BSOD the in-process executors
+//! attach as [`CrashReportMetadata`], the [`PtraceBackend`] re-runs the solution under `ptrace`.
+
+use alloc::{borrow::ToOwned, string::String, vec::Vec};
+use core::{fmt, marker::PhantomData};
+use std::fs;
+#[cfg(all(target_os = "linux", feature = "regex"))]
+use std::{
+    ffi::OsString,
+    fs::File,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use hashbrown::HashMap;
+#[cfg(all(target_os = "linux", feature = "regex"))]
+use libafl_bolts::AsSlice;
+use libafl_bolts::{hash_std, impl_serdeany};
+#[cfg(all(target_os = "linux", feature = "regex"))]
+use nix::{
+    sys::{
+        ptrace,
+        signal::{kill, Signal},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase},
+    executors::hooks::inprocess::CrashReportMetadata,
+    stages::Stage,
+    state::{HasSolutions, UsesState},
+    Error, HasMetadata,
+};
+#[cfg(all(target_os = "linux", feature = "regex"))]
+use crate::{
+    inputs::HasTargetBytes,
+    observers::stacktrace::{symbolize, walk_frame_pointers},
+};
+
+/// The default number of frames hashed into the stack hash
+pub const TRIAGE_STACK_DEPTH_DEFAULT: usize = 5;
+
+/// Frames of the fuzzer itself, skipped by the stack hash
+const IGNORED_FRAME_PREFIXES: [&str; 7] = [
+    "backtrace::",
+    "libafl::",
+    "libafl_bolts::",
+    "libafl_targets::",
+    "std::",
+    "core::",
+    "alloc::",
+];
+
+/// Crashes accessing the first 64 KiB of memory are most likely null pointer dereferences
+const NEAR_NULL: u64 = 0x10000;
+
+/// What a [`TriageBackend`] found out about a crash
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrashDetails {
+    /// The signal (or, on Windows, the exception) the target crashed with, like `SIGSEGV`
+    pub signal: Option<String>,
+    /// The address of the faulting instruction
+    pub pc: Option<u64>,
+    /// The address the target failed to access
+    pub fault_address: Option<u64>,
+    /// The stack of the crashing thread, innermost frame first
+    pub frames: Vec<String>,
+}
+
+/// Extracts the [`CrashDetails`] of solutions for the [`TriageStage`]
+pub trait TriageBackend<I> {
+    /// The details of the crash `testcase` triggered, or `None` if they are not available or it does not crash again.
+    /// The input of the `testcase` is loaded.
+    fn crash_details(&mut self, testcase: &Testcase<I>) -> Result<Option<CrashDetails>, Error>;
+}
+
+/// A heuristic guess of how exploitable a crash is, along the lines of `!exploitable`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Exploitability {
+    /// The target jumped to the faulting address
+    Exploitable,
+    /// The target executed an illegal instruction, or crashed on a wild pointer
+    ProbablyExploitable,
+    /// The target aborted, divided by zero, overflowed its stack or dereferenced a null pointer
+    ProbablyNotExploitable,
+    /// Not enough details to tell
+    Unknown,
+}
+
+impl Exploitability {
+    /// Classifies the crash described by `details`
+    #[must_use]
+    pub fn classify(details: &CrashDetails) -> Self {
+        match details.signal.as_deref() {
+            Some("SIGSEGV" | "SIGBUS" | "EXCEPTION_ACCESS_VIOLATION") => {
+                match (details.pc, details.fault_address) {
+                    (Some(pc), Some(fault_address)) if pc == fault_address => Self::Exploitable,
+                    (_, Some(fault_address)) if fault_address < NEAR_NULL => {
+                        Self::ProbablyNotExploitable
+                    }
+                    (_, Some(_)) => Self::ProbablyExploitable,
+                    (_, None) => Self::Unknown,
+                }
+            }
+            Some("SIGILL" | "EXCEPTION_ILLEGAL_INSTRUCTION") => Self::ProbablyExploitable,
+            Some("SIGABRT" | "SIGFPE" | "EXCEPTION_STACK_OVERFLOW") => Self::ProbablyNotExploitable,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Exploitability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exploitable => write!(f, "exploitable"),
+            Self::ProbablyExploitable => write!(f, "probably exploitable"),
+            Self::ProbablyNotExploitable => write!(f, "probably not exploitable"),
+            Self::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// The triage of a solution, attached to its testcase and written next to it as `.<name>.triage.json`
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TriageReport {
+    /// The signal (or, on Windows, the exception) the target crashed with
+    pub signal: Option<String>,
+    /// The address of the faulting instruction
+    pub pc: Option<u64>,
+    /// The address the target failed to access
+    pub fault_address: Option<u64>,
+    /// The normalized frames the stack hash is computed from, innermost first
+    pub frames: Vec<String>,
+    /// The hash of the normalized frames, the bucket of this solution
+    pub stack_hash: u64,
+    /// How exploitable the crash probably is
+    pub exploitability: Exploitability,
+}
+
+impl_serdeany!(TriageReport);
+
+impl TriageReport {
+    /// Triages the crash described by `details`, hashing the `stack_depth` innermost frames of the target
+    #[must_use]
+    pub fn new(details: CrashDetails, stack_depth: usize) -> Self {
+        let exploitability = Exploitability::classify(&details);
+        let frames = details
+            .frames
+            .into_iter()
+            .filter(|frame| {
+                !IGNORED_FRAME_PREFIXES
+                    .iter()
+                    .any(|prefix| frame.starts_with(prefix))
+            })
+            .take(stack_depth)
+            .collect::<Vec<_>>();
+        let stack_hash = hash_std(frames.join("\n").as_bytes());
+        Self {
+            signal: details.signal,
+            pc: details.pc,
+            fault_address: details.fault_address,
+            frames,
+            stack_hash,
+            exploitability,
+        }
+    }
+}
+
+/// The solutions triaged so far, by stack hash
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriageMetadata {
+    last_solution: Option<CorpusId>,
+    buckets: HashMap<u64, Vec<CorpusId>>,
+}
+
+impl_serdeany!(TriageMetadata);
+
+impl TriageMetadata {
+    /// The solutions, bucketed by the stack hash of their [`TriageReport`]
+    #[must_use]
+    pub fn buckets(&self) -> &HashMap<u64, Vec<CorpusId>> {
+        &self.buckets
+    }
+}
+
+/// A [`TriageBackend`] that reads the [`CrashReportMetadata`] an in-process executor attached to the solution,
+/// without running it again
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CrashContextBackend;
+
+impl CrashContextBackend {
+    /// Creates a new [`CrashContextBackend`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Extracts the [`CrashDetails`] from a mini-BSOD
+    #[must_use]
+    pub fn parse_report(report: &str) -> CrashDetails {
+        let mut details = CrashDetails::default();
+        let mut in_backtrace = false;
+        for line in report.lines() {
+            let line = line.trim();
+            if let Some(crash) = line.strip_prefix("Received signal ") {
+                // Received signal SIGSEGV at 0x..., fault address: 0x...
+                let mut words = crash.split([' ', ',']).filter(|word| !word.is_empty());
+                details.signal = words.next().map(ToOwned::to_owned);
+                details.pc = words.nth(1).and_then(parse_hex);
+                details.fault_address = words.nth(2).and_then(parse_hex);
+            } else if let Some(crash) = line.strip_prefix("Received exception ") {
+                // Received exception c0000005 at address 7ff6...
+                let mut words = crash.split(' ');
+                details.signal = words.next().map(exception_name);
+                details.pc = words.nth(2).and_then(parse_hex);
+            } else if let Some(fault) = line.strip_prefix("Fault address: ") {
+                details.fault_address = fault.split(' ').next().and_then(parse_hex);
+            } else if line.contains(" BACKTRACE ") {
+                in_backtrace = true;
+            } else if line.contains(" MAPS ") {
+                in_backtrace = false;
+            } else if in_backtrace {
+                // `   3: symbol`, inlined frames and source locations are on their own lines
+                if let Some((index, symbol)) = line.split_once(": ") {
+                    if index.bytes().all(|b| b.is_ascii_digit()) {
+                        details.frames.push(symbol.to_owned());
+                    }
+                }
+            }
+        }
+        // The backtrace is taken in the signal handler, the target's frames start after the signal trampoline
+        if let Some(trampoline) = details.frames.iter().rposition(|frame| {
+            frame.contains("__restore_rt")
+                || frame.contains("_sigtramp")
+                || frame.contains("KiUserExceptionDispatcher")
+        }) {
+            details.frames.drain(..=trampoline);
+        }
+        details
+    }
+}
+
+impl<I> TriageBackend<I> for CrashContextBackend {
+    fn crash_details(&mut self, testcase: &Testcase<I>) -> Result<Option<CrashDetails>, Error> {
+        Ok(testcase
+            .metadata::<CrashReportMetadata>()
+            .ok()
+            .map(|metadata| Self::parse_report(metadata.report())))
+    }
+}
+
+fn parse_hex(word: &str) -> Option<u64> {
+    u64::from_str_radix(word.trim_start_matches("0x"), 16).ok()
+}
+
+/// Names the Windows exceptions [`Exploitability::classify`] knows about
+fn exception_name(code: &str) -> String {
+    match code {
+        "c0000005" => "EXCEPTION_ACCESS_VIOLATION".to_owned(),
+        "c000001d" => "EXCEPTION_ILLEGAL_INSTRUCTION".to_owned(),
+        "c00000fd" => "EXCEPTION_STACK_OVERFLOW".to_owned(),
+        code => format!("EXCEPTION_{code}"),
+    }
+}
+
+/// A [`TriageBackend`] that re-runs the solution in a target program under `ptrace`,
+/// catching the fatal signal and walking the frame pointers of the crashing thread.
+/// The target should be built with `-fno-omit-frame-pointer`.
+#[cfg(all(target_os = "linux", feature = "regex"))]
+#[derive(Debug, Clone)]
+pub struct PtraceBackend {
+    program: OsString,
+    args: Vec<OsString>,
+    timeout: Duration,
+    input_file: PathBuf,
+}
+
+#[cfg(all(target_os = "linux", feature = "regex"))]
+impl PtraceBackend {
+    /// Creates a new [`PtraceBackend`] running `program` with `args`.
+    /// An `@@` argument is replaced by the path of the input, else the input is passed on stdin.
+    #[must_use]
+    pub fn new<P, IT, A>(program: P, args: IT, timeout: Duration) -> Self
+    where
+        P: Into<OsString>,
+        IT: IntoIterator<Item = A>,
+        A: Into<OsString>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            timeout,
+            input_file: std::env::temp_dir().join(format!("libafl_triage_{}", std::process::id())),
+        }
+    }
+
+    /// Runs the target on `input` until it dies, or stops on a fatal signal
+    fn run(&self, input: &[u8]) -> Result<Option<CrashDetails>, Error> {
+        fs::write(&self.input_file, input)?;
+        let mut command = Command::new(&self.program);
+        let mut uses_file = false;
+        for arg in &self.args {
+            if arg == "@@" {
+                uses_file = true;
+                command.arg(&self.input_file);
+            } else {
+                command.arg(arg);
+            }
+        }
+        let stdin = if uses_file {
+            Stdio::null()
+        } else {
+            Stdio::from(File::open(&self.input_file)?)
+        };
+        // The child stops with a `SIGTRAP` once it called `execve`
+        let child = unsafe {
+            use std::os::unix::process::CommandExt;
+            command
+                .stdin(stdin)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .pre_exec(|| ptrace::traceme().map_err(Into::into))
+                .spawn()?
+        };
+        let pid = Pid::from_raw(i32::try_from(child.id())?);
+        let res = self.trace(pid);
+        // The target is either dead or stopped, make sure it is gone for good
+        let _ = kill(pid, Signal::SIGKILL);
+        let _ = waitpid(pid, None);
+        res
+    }
+
+    fn trace(&self, pid: Pid) -> Result<Option<CrashDetails>, Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let status = waitpid(pid, Some(WaitPidFlag::WNOHANG))?;
+            match status {
+                WaitStatus::StillAlive => {
+                    if Instant::now() > deadline {
+                        return Ok(None);
+                    }
+                    thread::sleep(Duration::from_millis(1));
+                }
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => ptrace::cont(pid, None)?,
+                WaitStatus::Stopped(
+                    _,
+                    signal @ (Signal::SIGSEGV
+                    | Signal::SIGBUS
+                    | Signal::SIGILL
+                    | Signal::SIGFPE
+                    | Signal::SIGABRT),
+                ) => return Self::crash_details_of(pid, signal).map(Some),
+                // Pass on the signals the target handles itself
+                WaitStatus::Stopped(_, signal) => ptrace::cont(pid, signal)?,
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /// The details of the crash of the stopped `pid`
+    fn crash_details_of(pid: Pid, signal: Signal) -> Result<CrashDetails, Error> {
+        let maps = fs::read_to_string(format!("/proc/{pid}/maps"))?;
+        let siginfo = ptrace::getsiginfo(pid)?;
+        let fault_address = match signal {
+            Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGILL | Signal::SIGFPE => {
+                Some(unsafe { siginfo.si_addr() } as usize as u64)
+            }
+            _ => None,
+        };
+        let addresses = walk_frame_pointers(pid).unwrap_or_default();
+        Ok(CrashDetails {
+            signal: Some(signal.as_str().to_owned()),
+            pc: addresses.first().copied(),
+            fault_address,
+            frames: addresses
+                .into_iter()
+                .map(|address| symbolize(&maps, address))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "regex"))]
+impl Drop for PtraceBackend {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.input_file);
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "regex"))]
+impl<I> TriageBackend<I> for PtraceBackend
+where
+    I: HasTargetBytes,
+{
+    fn crash_details(&mut self, testcase: &Testcase<I>) -> Result<Option<CrashDetails>, Error> {
+        let Some(input) = testcase.input() else {
+            return Ok(None);
+        };
+        self.run(input.target_bytes().as_slice())
+    }
+}
+
+/// The [`TriageStage`] triages each new solution with a [`TriageBackend`], attaching a [`TriageReport`] to it
+/// and bucketing it in the [`TriageMetadata`] by stack hash
+#[derive(Debug)]
+pub struct TriageStage<B, EM, Z> {
+    backend: B,
+    stack_depth: usize,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<B, EM, Z> UsesState for TriageStage<B, EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<B, E, EM, Z> Stage<E, EM, Z> for TriageStage<B, EM, Z>
+where
+    B: TriageBackend<Self::Input>,
+    EM: UsesState,
+    E: UsesState<State = Self::State>,
+    Z: UsesState<State = Self::State>,
+    EM::State: HasSolutions + HasMetadata,
+    <<EM as UsesState>::State as HasSolutions>::Solutions: Corpus<Input = Self::Input>, //delete me
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let mut metadata = state
+            .metadata_map()
+            .get::<TriageMetadata>()
+            .cloned()
+            .unwrap_or_default();
+        let mut solution_id = match metadata.last_solution {
+            Some(last) => state.solutions().next(last),
+            None => state.solutions().first(),
+        };
+
+        while let Some(id) = solution_id {
+            let mut testcase = state.solutions().get(id)?.borrow_mut();
+            state.solutions().load_input_into(&mut testcase)?;
+            if let Some(details) = self.backend.crash_details(&testcase)? {
+                let report = TriageReport::new(details, self.stack_depth);
+                if let Some(path) = testcase.file_path() {
+                    if let Some(name) = path.file_name() {
+                        let report_path =
+                            path.with_file_name(format!(".{}.triage.json", name.to_string_lossy()));
+                        let json = serde_json::to_vec_pretty(&report).map_err(|err| {
+                            Error::serialize(format!("Failed to json-ify triage report: {err:?}"))
+                        })?;
+                        fs::write(report_path, json)?;
+                    }
+                }
+                let bucket = metadata.buckets.entry(report.stack_hash).or_default();
+                if bucket.is_empty() {
+                    log::info!(
+                        "New crash bucket {:016x}: {} ({})",
+                        report.stack_hash,
+                        report.signal.as_deref().unwrap_or("unknown signal"),
+                        report.exploitability
+                    );
+                }
+                bucket.push(id);
+                testcase.add_metadata(report);
+            } else {
+                log::info!("Could not triage solution {id}");
+            }
+            drop(testcase);
+            metadata.last_solution = Some(id);
+            solution_id = state.solutions().next(id);
+        }
+
+        state.add_metadata(metadata);
+        Ok(())
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Solutions get triaged once, even if the backend crashes
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<B, EM, Z> TriageStage<B, EM, Z> {
+    /// Creates a new [`TriageStage`], triaging the solutions with `backend`
+    #[must_use]
+    pub fn new(backend: B) -> Self {
+        Self {
+            backend,
+            stack_depth: TRIAGE_STACK_DEPTH_DEFAULT,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Hash the `stack_depth` innermost frames of the target into the stack hash,
+    /// [`TRIAGE_STACK_DEPTH_DEFAULT`] by default
+    #[must_use]
+    pub fn with_stack_depth(mut self, stack_depth: usize) -> Self {
+        self.stack_depth = stack_depth;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrashContextBackend, CrashDetails, Exploitability, TriageReport};
+
+    const REPORT: &str = "━━━ CRASH ━━━
+Received signal SIGSEGV at 0x0055d4c1a2b3c4, fault address: 0x00000000000010
+━━━ REGISTERS ━━━
+r8 : 0x0000000000000000
+━━━ BACKTRACE ━━━
+   0: backtrace::backtrace::trace
+             at /backtrace/src/backtrace/mod.rs:66:5
+   1: libafl_bolts::minibsod::generate_minibsod
+   2: __restore_rt
+   3: fuzzer::parse_header
+      fuzzer::parse
+             at src/parser.rs:12:5
+   4: fuzzer::main::{{closure}}
+   5: libafl::executors::inprocess::run_target
+━━━ MAPS ━━━
+";
+
+    #[test]
+    fn test_parse_report() {
+        let details = CrashContextBackend::parse_report(REPORT);
+        assert_eq!(details.signal.as_deref(), Some("SIGSEGV"));
+        assert_eq!(details.pc, Some(0x55d4_c1a2_b3c4));
+        assert_eq!(details.fault_address, Some(0x10));
+        assert_eq!(
+            details.frames,
+            [
+                "fuzzer::parse_header",
+                "fuzzer::main::{{closure}}",
+                "libafl::executors::inprocess::run_target"
+            ]
+        );
+
+        let report = TriageReport::new(details, 5);
+        assert_eq!(
+            report.exploitability,
+            Exploitability::ProbablyNotExploitable
+        );
+        assert_eq!(
+            report.frames,
+            ["fuzzer::parse_header", "fuzzer::main::{{closure}}"]
+        );
+    }
+
+    #[test]
+    fn test_classify() {
+        let crash = |signal: &str, pc, fault_address| CrashDetails {
+            signal: Some(signal.into()),
+            pc,
+            fault_address,
+            frames: vec![],
+        };
+        assert_eq!(
+            Exploitability::classify(&crash("SIGSEGV", Some(0x4141_4141), Some(0x4141_4141))),
+            Exploitability::Exploitable
+        );
+        assert_eq!(
+            Exploitability::classify(&crash("SIGSEGV", Some(0x1000), Some(0x7fff_0000))),
+            Exploitability::ProbablyExploitable
+        );
+        assert_eq!(
+            Exploitability::classify(&crash("SIGABRT", Some(0x1000), None)),
+            Exploitability::ProbablyNotExploitable
+        );
+        assert_eq!(
+            Exploitability::classify(&crash("SIGTRAP", None, None)),
+            Exploitability::Unknown
+        );
+    }
+}
@@ -18,7 +18,7 @@ use std::{
 #[cfg(feature = "std")]
 use libafl_bolts::core_affinity::{CoreId, Cores};
 use libafl_bolts::{
-    rands::{Rand, StdRand},
+    rands::{Rand, RandStreamHandle, RandStreams, StdRand},
     serdeany::{NamedSerdeAnyMap, SerdeAnyMap},
 };
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -26,6 +26,14 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 mod stack;
 pub use stack::StageStack;
 
+pub(crate) mod stats;
+pub use stats::{CampaignStats, DEFAULT_RECENT_FINDS, DEFAULT_RECENT_OBJECTIVES};
+
+#[cfg(feature = "std")]
+pub mod snapshot;
+
+pub mod replay;
+
 #[cfg(feature = "introspection")]
 use crate::monitors::ClientPerfMonitor;
 #[cfg(feature = "scalability_introspection")]
@@ -57,6 +65,24 @@ pub trait State:
     + HasCurrentStageId
     + Stoppable
 {
+    /// Writes a snapshot of this state to the file at `path`, to resume the campaign later
+    /// with [`State::restore_from`], see [`snapshot`].
+    #[cfg(feature = "std")]
+    fn snapshot_to(&self, path: &Path) -> Result<(), Error> {
+        snapshot::write_snapshot(self, path)
+    }
+
+    /// Restores a state from the snapshot at `path`, written by [`State::snapshot_to`].
+    ///
+    /// Returns an error if the snapshot was written for another state type or by an incompatible
+    /// version or build of `LibAFL`, see [`snapshot`].
+    #[cfg(feature = "std")]
+    fn restore_from(path: &Path) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        snapshot::read_snapshot(path)
+    }
 }
 
 /// Structs which implement this trait are aware of the state. This is used for type enforcement.
@@ -129,6 +155,41 @@ pub trait HasRand {
     fn rand_mut(&mut self) -> &mut Self::Rand;
 }
 
+/// Trait for elements offering named, independent random streams next to their rand, see [`RandStreams`]
+///
+/// Components drawing from their own stream, such as a stage or a mutator, do not perturb the random
+/// decisions of the others. [`StdState::new`] derives the campaign seed from the seed of its rand;
+/// set another one with [`RandStreams::set_seed`].
+pub trait HasRandStreams: HasRand {
+    /// The random streams
+    fn rand_streams(&self) -> &RandStreams<Self::Rand>;
+    /// The random streams (mutable)
+    fn rand_streams_mut(&mut self) -> &mut RandStreams<Self::Rand>;
+
+    /// The random stream of `handle`, created on first use
+    fn rand_stream_mut(&mut self, handle: &RandStreamHandle) -> &mut Self::Rand
+    where
+        Self::Rand: Default,
+    {
+        self.rand_streams_mut().stream_mut(handle)
+    }
+
+    /// Runs `f` with the random stream of `handle` in place of the rand of `self`,
+    /// so that all the random decisions of `f` are drawn from that stream, see [`crate::mutators::RandStreamMutator`]
+    fn with_rand_stream<T, F>(&mut self, handle: &RandStreamHandle, f: F) -> T
+    where
+        Self::Rand: Default,
+        F: FnOnce(&mut Self) -> T,
+    {
+        let mut rand = core::mem::take(self.rand_stream_mut(handle));
+        core::mem::swap(self.rand_mut(), &mut rand);
+        let ret = f(self);
+        core::mem::swap(self.rand_mut(), &mut rand);
+        *self.rand_stream_mut(handle) = rand;
+        ret
+    }
+}
+
 #[cfg(feature = "introspection")]
 /// Trait for offering a [`ClientPerfMonitor`]
 pub trait HasClientPerfMonitor {
@@ -251,6 +312,8 @@ impl<I, S, Z> Debug for LoadConfig<'_, I, S, Z> {
 pub struct StdState<I, C, R, SC> {
     /// RNG instance
     rand: R,
+    /// The named random streams, independent of `rand`
+    rand_streams: RandStreams<R>,
     /// How many times the executor ran the harness/target
     executions: u64,
     /// At what time the fuzzing started
@@ -331,6 +394,23 @@ where
     }
 }
 
+impl<I, C, R, SC> HasRandStreams for StdState<I, C, R, SC>
+where
+    R: Rand,
+{
+    /// The random streams
+    #[inline]
+    fn rand_streams(&self) -> &RandStreams<Self::Rand> {
+        &self.rand_streams
+    }
+
+    /// The random streams (mutable)
+    #[inline]
+    fn rand_streams_mut(&mut self) -> &mut RandStreams<Self::Rand> {
+        &mut self.rand_streams
+    }
+}
+
 impl<I, C, R, SC> HasCorpus for StdState<I, C, R, SC>
 where
     C: Corpus,
@@ -597,7 +677,11 @@ impl<I, C, R, SC> Stoppable for StdState<I, C, R, SC> {
     }
 }
 
-impl<I, C, R, SC> HasCurrentStageId for StdState<I, C, R, SC> {
+impl<I, C, R, SC> HasCurrentStageId for StdState<I, C, R, SC>
+where
+    C: Corpus,
+    SC: Corpus,
+{
     fn set_current_stage_id(&mut self, idx: StageId) -> Result<(), Error> {
         self.stage_stack.set_current_stage_id(idx)
     }
@@ -611,11 +695,18 @@ impl<I, C, R, SC> HasCurrentStageId for StdState<I, C, R, SC> {
     }
 
     fn on_restart(&mut self) -> Result<(), Error> {
+        // Writes still queued by the corpora would die with this process
+        self.corpus.flush()?;
+        self.solutions.flush()?;
         self.stage_stack.on_restart()
     }
 }
 
-impl<I, C, R, SC> HasNestedStageStatus for StdState<I, C, R, SC> {
+impl<I, C, R, SC> HasNestedStageStatus for StdState<I, C, R, SC>
+where
+    C: Corpus,
+    SC: Corpus,
+{
     fn enter_inner_stage(&mut self) -> Result<(), Error> {
         self.stage_stack.enter_inner_stage()
     }
@@ -1154,7 +1245,7 @@ where
 
     /// Creates a new `State`, taking ownership of all of the individual components during fuzzing.
     pub fn new<F, O>(
-        rand: R,
+        mut rand: R,
         corpus: C,
         solutions: SC,
         feedback: &mut F,
@@ -1166,8 +1257,11 @@ where
         C: Serialize + DeserializeOwned,
         SC: Serialize + DeserializeOwned,
     {
+        // Derived from the rand, so a fixed seed fixes the streams as well
+        let rand_streams = RandStreams::with_seed(rand.next());
         let mut state = Self {
             rand,
+            rand_streams,
             executions: 0,
             imported: 0,
             start_time: libafl_bolts::current_time(),
@@ -1245,6 +1339,7 @@ pub struct NopState<I> {
     execution: u64,
     stop_requested: bool,
     rand: StdRand,
+    rand_streams: RandStreams<StdRand>,
     phantom: PhantomData<I>,
 }
 
@@ -1256,6 +1351,7 @@ impl<I> NopState<I> {
             metadata: SerdeAnyMap::new(),
             execution: 0,
             rand: StdRand::default(),
+            rand_streams: RandStreams::new(),
             stop_requested: false,
             phantom: PhantomData,
         }
@@ -1335,6 +1431,16 @@ impl<I> HasRand for NopState<I> {
     }
 }
 
+impl<I> HasRandStreams for NopState<I> {
+    fn rand_streams(&self) -> &RandStreams<Self::Rand> {
+        &self.rand_streams
+    }
+
+    fn rand_streams_mut(&mut self) -> &mut RandStreams<Self::Rand> {
+        &mut self.rand_streams
+    }
+}
+
 impl<I> State for NopState<I> where I: Input {}
 
 impl<I> HasCurrentCorpusId for NopState<I> {
@@ -1395,4 +1501,55 @@ mod test {
     fn test_std_state() {
         StdState::nop::<BytesInput>().expect("couldn't instantiate the test state");
     }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_on_restart_flushes_write_behind() {
+        use std::{env, fs};
+
+        use libafl_bolts::rands::StdRand;
+
+        use crate::{
+            corpus::{Corpus, InMemoryOnDiskCorpus, Testcase},
+            state::{HasCorpus, HasCurrentStageId, HasSolutions},
+        };
+
+        let dir = env::temp_dir().join("libafl_on_restart_flush_test");
+        _ = fs::remove_dir_all(&dir);
+        let write_behind = |name| {
+            InMemoryOnDiskCorpus::<BytesInput>::no_meta(dir.join(name))
+                .unwrap()
+                .with_write_behind(256, 256)
+                .unwrap()
+        };
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            write_behind("queue"),
+            write_behind("crashes"),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+
+        for i in 0..=255_u8 {
+            let mut testcase = Testcase::new(BytesInput::new(vec![i; 64]));
+            *testcase.filename_mut() = Some(format!("tc{i}"));
+            state.corpus_mut().add(testcase.clone()).unwrap();
+            state.solutions_mut().add(testcase).unwrap();
+        }
+
+        // The restarting event managers call this right before the process exits
+        state.on_restart().unwrap();
+        for i in 0..=255_u8 {
+            for name in ["queue", "crashes"] {
+                assert_eq!(
+                    fs::read(dir.join(name).join(format!("tc{i}"))).unwrap(),
+                    [i; 64]
+                );
+            }
+        }
+
+        drop(state);
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
@@ -0,0 +1,245 @@
+//! Recording and replaying the decisions of a campaign, to reproduce a find or a bug of the fuzzer.
+//!
+//! A [`ReplayLog`] holds the seed of the RNG of the state, and the sequence of decisions taken by the
+//! [`ReplayScheduler`](crate::schedulers::ReplayScheduler) and the
+//! [`ReplayScheduledMutator`](crate::mutators::ReplayScheduledMutator): the ids of the scheduled testcases,
+//! the count of stacked mutations, and the ids of the mutations applied.
+//!
+//! Start a campaign with [`start_recording`] to log the decisions in the [`ReplayMetadata`] of the state.
+//! Later, [`start_replay`] reseeds the RNG with the logged seed, and the wrappers take the logged decisions,
+//! instead of their own, until the log is exhausted and the fuzzer is asked to stop.
+//! Against the same target, this re-executes exactly the same inputs.
+//! A wrapped component deciding differently than logged means that the campaign is not deterministic,
+//! this is reported as a divergence.
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+#[cfg(feature = "std")]
+use libafl_bolts::fs::write_file_atomic;
+use libafl_bolts::{impl_serdeany, rands::Rand};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    mutators::MutationId,
+    state::{HasRand, Stoppable},
+    Error, HasMetadata,
+};
+
+/// A decision of the campaign, recorded in a [`ReplayLog`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayDecision {
+    /// The scheduler picked this testcase
+    Scheduled(CorpusId),
+    /// The scheduled mutator stacks this count of mutations
+    Iterations(u64),
+    /// The scheduled mutator applies this mutation
+    Mutation(MutationId),
+}
+
+/// The seed of the RNG and the decisions of a campaign, see the [module-level documentation](self)
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayLog {
+    seed: u64,
+    decisions: Vec<ReplayDecision>,
+}
+
+impl ReplayLog {
+    /// Creates an empty [`ReplayLog`] for a campaign with the RNG seeded with `seed`
+    #[must_use]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            decisions: Vec::new(),
+        }
+    }
+
+    /// The seed of the RNG
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The recorded decisions, in order
+    #[must_use]
+    pub fn decisions(&self) -> &[ReplayDecision] {
+        &self.decisions
+    }
+
+    /// Records a decision
+    pub fn push(&mut self, decision: ReplayDecision) {
+        self.decisions.push(decision);
+    }
+
+    /// Writes the log to the file at `path`, as a postcard
+    #[cfg(feature = "std")]
+    pub fn to_file(&self, path: &Path) -> Result<(), Error> {
+        write_file_atomic(path, &postcard::to_allocvec(self)?)
+    }
+
+    /// Reads a log written by [`ReplayLog::to_file`]
+    #[cfg(feature = "std")]
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        Ok(postcard::from_bytes(&fs::read(path)?)?)
+    }
+}
+
+/// Whether the [`ReplayMetadata`] records or replays the decisions
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayMode {
+    /// The decisions are appended to the log
+    Record,
+    /// The decisions are taken from the log
+    Replay,
+}
+
+/// The metadata of the state recording or replaying a [`ReplayLog`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplayMetadata {
+    mode: ReplayMode,
+    log: ReplayLog,
+    cursor: usize,
+    divergences: usize,
+}
+
+impl_serdeany!(ReplayMetadata);
+
+impl ReplayMetadata {
+    /// The mode of the metadata
+    #[must_use]
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// The recorded, or replayed, log
+    #[must_use]
+    pub fn log(&self) -> &ReplayLog {
+        &self.log
+    }
+
+    /// The count of replayed decisions
+    #[must_use]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// The count of replayed decisions the wrapped components took differently than logged
+    #[must_use]
+    pub fn divergences(&self) -> usize {
+        self.divergences
+    }
+
+    /// Returns the decision to take, instead of the `live` one taken by the wrapped component
+    fn decide(&mut self, live: ReplayDecision) -> Result<Option<ReplayDecision>, Error> {
+        if self.mode == ReplayMode::Record {
+            self.log.push(live);
+            return Ok(Some(live));
+        }
+        let Some(logged) = self.log.decisions.get(self.cursor).copied() else {
+            return Ok(None);
+        };
+        self.cursor += 1;
+        if core::mem::discriminant(&logged) != core::mem::discriminant(&live) {
+            return Err(Error::illegal_state(format!(
+                "The replay diverged at decision {}: logged {logged:?}, but the campaign is at {live:?}. Are the scheduler and the mutator wrapped as when recording?",
+                self.cursor - 1
+            )));
+        }
+        if logged != live {
+            log::warn!(
+                "The replay diverged at decision {}: logged {logged:?}, the campaign decided {live:?}",
+                self.cursor - 1
+            );
+            self.divergences += 1;
+        }
+        Ok(Some(logged))
+    }
+}
+
+/// Seeds the RNG of the state with `seed`, and starts recording the decisions in its [`ReplayMetadata`]
+pub fn start_recording<S>(state: &mut S, seed: u64)
+where
+    S: HasMetadata + HasRand,
+{
+    state.rand_mut().set_seed(seed);
+    state.add_metadata(ReplayMetadata {
+        mode: ReplayMode::Record,
+        log: ReplayLog::new(seed),
+        cursor: 0,
+        divergences: 0,
+    });
+}
+
+/// Seeds the RNG of the state with the seed of the log, and starts replaying its decisions
+pub fn start_replay<S>(state: &mut S, log: ReplayLog)
+where
+    S: HasMetadata + HasRand,
+{
+    state.rand_mut().set_seed(log.seed);
+    state.add_metadata(ReplayMetadata {
+        mode: ReplayMode::Replay,
+        log,
+        cursor: 0,
+        divergences: 0,
+    });
+}
+
+/// Records the `live` decision of a wrapped component, or returns the logged decision to take instead.
+///
+/// Does nothing without [`ReplayMetadata`] in the state.
+/// Once the replayed log is exhausted, the fuzzer is asked to stop and the `live` decision is returned.
+pub fn replay_decision<S>(state: &mut S, live: ReplayDecision) -> Result<ReplayDecision, Error>
+where
+    S: HasMetadata + Stoppable,
+{
+    let Ok(meta) = state.metadata_mut::<ReplayMetadata>() else {
+        return Ok(live);
+    };
+    if let Some(decision) = meta.decide(live)? {
+        return Ok(decision);
+    }
+    if !state.stop_requested() {
+        log::info!("The replay log is exhausted, stopping");
+        state.request_stop();
+    }
+    Ok(live)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{replay_decision, start_recording, start_replay, ReplayDecision, ReplayMetadata};
+    use crate::{
+        corpus::CorpusId,
+        inputs::BytesInput,
+        state::{StdState, Stoppable},
+        HasMetadata,
+    };
+
+    #[test]
+    fn test_replay_log() {
+        let mut state = StdState::nop::<BytesInput>().unwrap();
+        start_recording(&mut state, 1337);
+        for id in [3, 1, 4] {
+            let live = ReplayDecision::Scheduled(CorpusId(id));
+            assert_eq!(replay_decision(&mut state, live).unwrap(), live);
+        }
+        let log = state.metadata::<ReplayMetadata>().unwrap().log().clone();
+        assert_eq!(log.decisions().len(), 3);
+
+        start_replay(&mut state, log);
+        let scheduled = ReplayDecision::Scheduled(CorpusId(3));
+        assert_eq!(replay_decision(&mut state, scheduled).unwrap(), scheduled);
+        // a divergence takes the logged decision
+        assert_eq!(
+            replay_decision(&mut state, ReplayDecision::Scheduled(CorpusId(0))).unwrap(),
+            ReplayDecision::Scheduled(CorpusId(1))
+        );
+        assert_eq!(state.metadata::<ReplayMetadata>().unwrap().divergences(), 1);
+        assert!(replay_decision(&mut state, ReplayDecision::Iterations(2)).is_err());
+
+        assert!(!state.stop_requested());
+        replay_decision(&mut state, scheduled).unwrap();
+        assert!(state.stop_requested());
+    }
+}
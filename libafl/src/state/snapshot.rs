@@ -0,0 +1,223 @@
+//! Snapshots of the [`State`](super::State) in a versioned file, for resuming a campaign.
+//!
+//! A snapshot holds the whole serialized state: the corpora, or the paths of their files for the on-disk corpora,
+//! the metadata of the feedbacks and other components, the state of the RNG and the count of executions.
+//! It starts with a [`SnapshotHeader`], so that a snapshot of another state type, of an incompatible version
+//! of the format, or of a build with other layout-changing features is rejected with a clear error,
+//! instead of failing to deserialize, or worse, deserializing to garbage.
+//!
+//! The file starts with [`SNAPSHOT_MAGIC`] and the [`SNAPSHOT_VERSION`] as `u32` little endian,
+//! followed by the header and the state as postcards.
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use std::{fs, path::Path};
+
+use libafl_bolts::fs::write_file_atomic;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::Error;
+
+/// The magic bytes at the start of every snapshot
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"LIBAFLSS";
+
+/// The version of the snapshot format.
+///
+/// Bumped whenever the snapshots of a previous version can no longer be restored.
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+/// The `introspection` feature, which adds the performance monitor to the state
+pub const SNAPSHOT_FEATURE_INTROSPECTION: u32 = 1 << 0;
+/// The `scalability_introspection` feature, which adds the scalability monitor to the state
+pub const SNAPSHOT_FEATURE_SCALABILITY_INTROSPECTION: u32 = 1 << 1;
+/// The `corpus_btreemap` feature, which changes the layout of the in-memory corpora
+pub const SNAPSHOT_FEATURE_CORPUS_BTREEMAP: u32 = 1 << 2;
+
+/// The features of this build changing the serialized layout of the state
+#[must_use]
+pub fn snapshot_features() -> u32 {
+    let mut features = 0;
+    if cfg!(feature = "introspection") {
+        features |= SNAPSHOT_FEATURE_INTROSPECTION;
+    }
+    if cfg!(feature = "scalability_introspection") {
+        features |= SNAPSHOT_FEATURE_SCALABILITY_INTROSPECTION;
+    }
+    if cfg!(feature = "corpus_btreemap") {
+        features |= SNAPSHOT_FEATURE_CORPUS_BTREEMAP;
+    }
+    features
+}
+
+/// The header of a snapshot, describing the snapshotted state
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    /// The version of `LibAFL` that wrote the snapshot, for the error messages
+    pub libafl_version: String,
+    /// The features of the build that wrote the snapshot, see [`snapshot_features`]
+    pub features: u32,
+    /// The type name of the snapshotted state
+    pub state_type: String,
+}
+
+impl SnapshotHeader {
+    /// The header for a snapshot of the state type `S` by this build
+    #[must_use]
+    pub fn new<S>() -> Self {
+        Self {
+            libafl_version: env!("CARGO_PKG_VERSION").to_string(),
+            features: snapshot_features(),
+            state_type: core::any::type_name::<S>().to_string(),
+        }
+    }
+}
+
+/// Writes a snapshot of the state to the file at `path`, replacing it atomically
+pub fn write_snapshot<S>(state: &S, path: &Path) -> Result<(), Error>
+where
+    S: Serialize,
+{
+    let mut bytes = SNAPSHOT_MAGIC.to_vec();
+    bytes.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&postcard::to_allocvec(&SnapshotHeader::new::<S>())?);
+    bytes.extend_from_slice(&postcard::to_allocvec(state)?);
+    write_file_atomic(path, &bytes)
+}
+
+/// Splits a snapshot into its header and the serialized state, checking the magic and the version
+fn parse_snapshot<'a>(bytes: &'a [u8], path: &Path) -> Result<(SnapshotHeader, &'a [u8]), Error> {
+    let Some(rest) = bytes.strip_prefix(&SNAPSHOT_MAGIC) else {
+        return Err(Error::illegal_argument(format!(
+            "{} is not a state snapshot",
+            path.display()
+        )));
+    };
+    let (version, rest) = rest
+        .split_first_chunk::<4>()
+        .ok_or_else(|| Error::illegal_argument(format!("{} is truncated", path.display())))?;
+    let version = u32::from_le_bytes(*version);
+    if version != SNAPSHOT_VERSION {
+        return Err(Error::unsupported(format!(
+            "{} is a snapshot of version {version}, this version of LibAFL only restores snapshots of version {SNAPSHOT_VERSION}",
+            path.display()
+        )));
+    }
+    Ok(postcard::take_from_bytes(rest)?)
+}
+
+/// Reads the header of the snapshot at `path`, for example to show what it holds
+pub fn read_snapshot_header(path: &Path) -> Result<SnapshotHeader, Error> {
+    let bytes = fs::read(path)?;
+    Ok(parse_snapshot(&bytes, path)?.0)
+}
+
+/// Restores the state from the snapshot at `path`.
+///
+/// Returns an error if the snapshot is of another state type, of another version of the format,
+/// or was written by a build with other layout-changing features, see [`snapshot_features`].
+pub fn read_snapshot<S>(path: &Path) -> Result<S, Error>
+where
+    S: DeserializeOwned,
+{
+    let bytes = fs::read(path)?;
+    let (header, state) = parse_snapshot(&bytes, path)?;
+    let expected = SnapshotHeader::new::<S>();
+    if header.state_type != expected.state_type {
+        return Err(Error::illegal_argument(format!(
+            "{} is a snapshot of a {}, not of a {}",
+            path.display(),
+            header.state_type,
+            expected.state_type
+        )));
+    }
+    if header.features != expected.features {
+        return Err(Error::unsupported(format!(
+            "{} was written by LibAFL {} with the features {:#x}, this build has the features {:#x}, see `snapshot_features`",
+            path.display(),
+            header.libafl_version,
+            header.features,
+            expected.features
+        )));
+    }
+    postcard::from_bytes(state).map_err(|err| {
+        Error::serialize(format!(
+            "Failed to restore the state from {}, written by LibAFL {}: {err}",
+            path.display(),
+            header.libafl_version
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use libafl_bolts::rands::{Rand, StdRand, XkcdRand};
+
+    use super::{read_snapshot_header, SNAPSHOT_MAGIC};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        state::{HasCorpus, HasExecutions, HasRand, State, StdState},
+        Error,
+    };
+
+    #[test]
+    fn test_snapshot() {
+        let path = env::temp_dir().join("libafl_snapshot_test");
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"libafl".to_vec())))
+            .unwrap();
+        *state.executions_mut() = 42;
+        state.snapshot_to(&path).unwrap();
+        assert!(read_snapshot_header(&path)
+            .unwrap()
+            .state_type
+            .contains("StdState"));
+
+        let mut restored: StdState<
+            BytesInput,
+            InMemoryCorpus<BytesInput>,
+            StdRand,
+            InMemoryCorpus<BytesInput>,
+        > = StdState::restore_from(&path).unwrap();
+        assert_eq!(*restored.executions(), 42);
+        assert_eq!(restored.corpus().count(), 1);
+        assert_eq!(restored.rand_mut().next(), state.rand_mut().next());
+
+        // another state type
+        assert!(StdState::<
+            BytesInput,
+            InMemoryCorpus<BytesInput>,
+            XkcdRand,
+            InMemoryCorpus<BytesInput>,
+        >::restore_from(&path)
+        .is_err());
+
+        // another version of the format
+        let mut bytes = fs::read(&path).unwrap();
+        bytes[SNAPSHOT_MAGIC.len()] += 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            read_snapshot_header(&path),
+            Err(Error::Unsupported(..))
+        ));
+
+        fs::write(&path, b"garbage").unwrap();
+        assert!(read_snapshot_header(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
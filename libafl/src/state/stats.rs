@@ -0,0 +1,166 @@
+//! Statistics of the whole campaign, kept in the state by the fuzzer.
+
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+use libafl_bolts::current_time;
+use serde::{Deserialize, Serialize};
+
+use crate::HasMetadata;
+
+/// The default count of finds kept by [`CampaignStats`]
+pub const DEFAULT_RECENT_FINDS: usize = 1024;
+
+/// The default count of objectives kept by [`CampaignStats`]
+pub const DEFAULT_RECENT_OBJECTIVES: usize = 1024;
+
+/// The finds and objectives of a campaign over time.
+///
+/// Opt-in: once added to the metadata of the state, the [`crate::fuzzer::StdFuzzer`] records each find and objective in it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct CampaignStats {
+    /// The times of the latest finds, oldest first
+    recent_finds: VecDeque<Duration>,
+    max_recent_finds: usize,
+    total_finds: u64,
+    /// The times of the latest objectives, oldest first
+    recent_objectives: VecDeque<Duration>,
+    max_recent_objectives: usize,
+    total_objectives: u64,
+}
+
+libafl_bolts::impl_serdeany!(CampaignStats);
+
+impl Default for CampaignStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_FINDS, DEFAULT_RECENT_OBJECTIVES)
+    }
+}
+
+impl CampaignStats {
+    /// Creates new [`CampaignStats`], keeping the times of the `max_recent_finds` latest finds
+    /// and of the `max_recent_objectives` latest objectives
+    #[must_use]
+    pub fn new(max_recent_finds: usize, max_recent_objectives: usize) -> Self {
+        Self {
+            recent_finds: VecDeque::new(),
+            max_recent_finds,
+            total_finds: 0,
+            recent_objectives: VecDeque::new(),
+            max_recent_objectives,
+            total_objectives: 0,
+        }
+    }
+
+    /// Records a testcase added to the corpus at `time`
+    pub fn record_find(&mut self, time: Duration) {
+        push_capped(&mut self.recent_finds, self.max_recent_finds, time);
+        self.total_finds += 1;
+    }
+
+    /// Records an objective added to the solutions at `time`
+    pub fn record_objective(&mut self, time: Duration) {
+        push_capped(
+            &mut self.recent_objectives,
+            self.max_recent_objectives,
+            time,
+        );
+        self.total_objectives += 1;
+    }
+
+    /// The count of testcases added to the corpus by the fuzzer
+    #[must_use]
+    pub fn total_finds(&self) -> u64 {
+        self.total_finds
+    }
+
+    /// The times of the latest finds, oldest first
+    pub fn recent_finds(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.recent_finds.iter().copied()
+    }
+
+    /// The count of finds since `time`.
+    ///
+    /// Saturates at the count of recent finds kept, see [`CampaignStats::new`].
+    #[must_use]
+    pub fn finds_since(&self, time: Duration) -> usize {
+        self.recent_finds.len() - self.recent_finds.partition_point(|find| *find < time)
+    }
+
+    /// The count of objectives added to the solutions by the fuzzer
+    #[must_use]
+    pub fn total_objectives(&self) -> u64 {
+        self.total_objectives
+    }
+
+    /// The times of the latest objectives, oldest first
+    pub fn recent_objectives(&self) -> impl Iterator<Item = Duration> + '_ {
+        self.recent_objectives.iter().copied()
+    }
+}
+
+/// Appends `time`, dropping the oldest entry once `max` entries are kept
+fn push_capped(times: &mut VecDeque<Duration>, max: usize, time: Duration) {
+    if max == 0 {
+        return;
+    }
+    if times.len() == max {
+        times.pop_front();
+    }
+    times.push_back(time);
+}
+
+/// Records a find in the [`CampaignStats`] of the `state`, if it has any
+pub(crate) fn record_find<S>(state: &mut S)
+where
+    S: HasMetadata,
+{
+    if let Ok(stats) = state.metadata_mut::<CampaignStats>() {
+        stats.record_find(current_time());
+    }
+}
+
+/// Records an objective in the [`CampaignStats`] of the `state`, if it has any
+pub(crate) fn record_objective<S>(state: &mut S)
+where
+    S: HasMetadata,
+{
+    if let Ok(stats) = state.metadata_mut::<CampaignStats>() {
+        stats.record_objective(current_time());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::CampaignStats;
+
+    #[test]
+    fn test_campaign_stats() {
+        let mut stats = CampaignStats::new(3, 2);
+        for secs in 1..=5 {
+            stats.record_find(Duration::from_secs(secs));
+        }
+        assert_eq!(stats.total_finds(), 5);
+        assert_eq!(
+            stats.recent_finds().collect::<alloc::vec::Vec<_>>(),
+            [3, 4, 5].map(Duration::from_secs)
+        );
+        assert_eq!(stats.finds_since(Duration::from_secs(4)), 2);
+        assert_eq!(stats.finds_since(Duration::ZERO), 3);
+
+        for secs in 6..=8 {
+            stats.record_objective(Duration::from_secs(secs));
+        }
+        assert_eq!(stats.total_objectives(), 3);
+        assert_eq!(
+            stats.recent_objectives().collect::<alloc::vec::Vec<_>>(),
+            [7, 8].map(Duration::from_secs)
+        );
+    }
+}
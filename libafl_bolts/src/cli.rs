@@ -65,6 +65,8 @@
 use alloc::{boxed::Box, string::ToString};
 use alloc::{string::String, vec::Vec};
 #[cfg(feature = "frida_cli")]
+use core::ops::Range;
+#[cfg(feature = "frida_cli")]
 use std::error;
 use std::{net::SocketAddr, path::PathBuf, time::Duration};
 
@@ -104,6 +106,22 @@ fn parse_instrumentation_location(
     ))
 }
 
+/// helper function to go from 0x1000-0x2000 to a `Range<usize>` of addresses
+#[cfg(feature = "frida_cli")]
+fn parse_address_range(
+    range: &str,
+) -> Result<Range<usize>, Box<dyn error::Error + Send + Sync + 'static>> {
+    let (start, end) = range
+        .split_once('-')
+        .ok_or("Expected a '-' in range specifier")?;
+    let start = usize::from_str_radix(start.trim_start_matches("0x"), 16)?;
+    let end = usize::from_str_radix(end.trim_start_matches("0x"), 16)?;
+    if start >= end {
+        return Err("The start of the range must be below its end".into());
+    }
+    Ok(start..end)
+}
+
 /// The scripting engine to use for JavaScript scripting support
 #[cfg(feature = "frida_cli")]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Hash, ValueEnum, Default)]
@@ -237,6 +255,12 @@ pub struct FuzzerOptions {
     #[arg(long, help_heading = "ASan Options")]
     pub max_allocation_panics: bool,
 
+    /// Instruct `ASan` to poison the padding between the globals of the instrumented modules,
+    /// to detect out-of-bounds accesses to globals
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub poison_globals: bool,
+
     /// Disable coverage
     #[cfg(feature = "frida_cli")]
     #[arg(long, help_heading = "Frida Options")]
@@ -259,6 +283,45 @@ pub struct FuzzerOptions {
     #[arg(short = 'D', long, help_heading = "Frida Options", value_parser = parse_instrumentation_location)]
     pub dont_instrument: Vec<(String, usize)>,
 
+    /// Libraries which will not be instrumented, even if they are the harness or in `libs_to_instrument`
+    #[cfg(feature = "frida_cli")]
+    #[arg(
+        long,
+        help_heading = "Frida Options",
+        env = "LIBAFL_FRIDA_DONT_INSTRUMENT_LIBS",
+        value_delimiter = ','
+    )]
+    pub dont_instrument_libs: Vec<String>,
+
+    /// Address ranges which will not be instrumented (ex: `0x1000-0x2000`)
+    #[cfg(feature = "frida_cli")]
+    #[arg(
+        long,
+        help_heading = "Frida Options",
+        env = "LIBAFL_FRIDA_DONT_INSTRUMENT_RANGES",
+        value_delimiter = ',',
+        value_parser = parse_address_range
+    )]
+    pub dont_instrument_ranges: Vec<Range<usize>>,
+
+    /// Only instrument the functions whose symbol matches one of these regexes, in the instrumented libraries
+    #[cfg(feature = "frida_cli")]
+    #[arg(
+        long,
+        help_heading = "Frida Options",
+        env = "LIBAFL_FRIDA_INSTRUMENT_SYMBOLS"
+    )]
+    pub instrument_symbols: Vec<String>,
+
+    /// Functions whose symbol matches one of these regexes will not be instrumented
+    #[cfg(feature = "frida_cli")]
+    #[arg(
+        long,
+        help_heading = "Frida Options",
+        env = "LIBAFL_FRIDA_DONT_INSTRUMENT_SYMBOLS"
+    )]
+    pub dont_instrument_symbols: Vec<String>,
+
     /// Trailing arguments (after "`--`"); can be passed directly to QEMU
     #[cfg(feature = "qemu_cli")]
     #[arg(last = true)]
@@ -410,6 +473,19 @@ mod tests {
         assert_eq!(parsed.broker_port, 1336);
     }
 
+    /// pass address ranges to `parse_address_range`, with and without 0x, and empty ones
+    #[test]
+    #[cfg(feature = "frida_cli")]
+    fn parse_address_range_works() {
+        assert_eq!(
+            parse_address_range("0x1000-0x2000").unwrap(),
+            0x1000..0x2000
+        );
+        assert_eq!(parse_address_range("1000-2000").unwrap(), 0x1000..0x2000);
+        parse_address_range("0x2000-0x1000").unwrap_err();
+        parse_address_range("0x1000").unwrap_err();
+    }
+
     /// pass module without @ to `parse_instrumentation_location`, expect error
     #[test]
     #[cfg(feature = "frida_cli")]
@@ -1,13 +1,15 @@
 //! Compression of events passed between a broker and clients.
-//! Currently we use the gzip compression algorithm for its fast decompression performance.
+//! By default, we use the gzip compression algorithm for its fast decompression performance.
+//! With the `zstd` and `lz4` features, additional [`CompressionCodec`]s become usable.
 
-use alloc::vec::Vec;
+use alloc::{format, vec::Vec};
 use core::fmt::Debug;
 
 use miniz_oxide::{
     deflate::{compress_to_vec, CompressionLevel},
-    inflate::decompress_to_vec,
+    inflate::{decompress_to_vec_with_limit, TINFLStatus},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::Error;
 
@@ -61,20 +63,201 @@ impl GzipCompressor {
     }
 
     /// Decompression.
+    /// Fails if the result would be larger than [`MAX_DECOMPRESSED_SIZE`].
     #[allow(clippy::unused_self)]
     pub fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
-        let decompressed = decompress_to_vec(buf);
+        decompress_gzip(buf)
+    }
+}
+
+/// The zstd compression level used by [`CompressionCodec::Zstd`], favoring speed
+#[cfg(feature = "zstd")]
+const ZSTD_LEVEL: i32 = 1;
 
-        match decompressed {
-            Ok(buf) => Ok(buf),
-            Err(_) => Err(Error::compression()),
+/// The largest buffer a compressed message may decompress to (256 MiB),
+/// so that a malicious or broken peer cannot make us allocate unbounded memory.
+pub const MAX_DECOMPRESSED_SIZE: usize = 1 << 28;
+
+/// The error for a message that would decompress to more than [`MAX_DECOMPRESSED_SIZE`] bytes
+fn too_large() -> Error {
+    Error::illegal_argument(format!(
+        "Refusing to decompress more than {MAX_DECOMPRESSED_SIZE} bytes"
+    ))
+}
+
+/// The compression algorithm used by a [`Compressor`]
+///
+/// All codecs exist in every build, and are serialized as their stable `u8` id, see [`CompressionCodec::id`].
+/// Compressing or decompressing with a codec whose feature is not enabled returns [`Error::unsupported`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+#[repr(u8)]
+pub enum CompressionCodec {
+    /// Never compress
+    None = 0,
+    /// Gzip (deflate), with fast decompression
+    #[default]
+    Gzip = 1,
+    /// Zstd, usually a better ratio than gzip at a similar speed. Needs the `zstd` feature.
+    Zstd = 2,
+    /// Lz4, a worse ratio than gzip, but much faster. Needs the `lz4` feature.
+    Lz4 = 3,
+}
+
+impl CompressionCodec {
+    /// The stable id of this codec, used when serializing it
+    #[must_use]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Compresses `buf` using this codec.
+    /// For [`CompressionCodec::None`], the buffer is returned as-is.
+    pub fn compress(self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::None => Ok(buf.to_vec()),
+            Self::Gzip => Ok(compress_to_vec(buf, CompressionLevel::BestSpeed as u8)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => zstd::bulk::compress(buf, ZSTD_LEVEL).map_err(|_| Error::compression()),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(lz4_flex::compress_prepend_size(buf)),
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
+        }
+    }
+
+    /// Decompresses `buf`, previously compressed using this codec.
+    ///
+    /// Fails if the result would be larger than [`MAX_DECOMPRESSED_SIZE`].
+    pub fn decompress(self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::None => Ok(buf.to_vec()),
+            Self::Gzip => decompress_gzip(buf),
+            #[cfg(feature = "zstd")]
+            Self::Zstd => {
+                use std::io::Read;
+
+                let mut decoder =
+                    zstd::stream::read::Decoder::new(buf).map_err(|_| Error::compression())?;
+                let mut decompressed = Vec::new();
+                (&mut decoder)
+                    .take(MAX_DECOMPRESSED_SIZE as u64 + 1)
+                    .read_to_end(&mut decompressed)
+                    .map_err(|_| Error::compression())?;
+                if decompressed.len() > MAX_DECOMPRESSED_SIZE {
+                    return Err(too_large());
+                }
+                Ok(decompressed)
+            }
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => {
+                let (size, compressed) =
+                    lz4_flex::block::uncompressed_size(buf).map_err(|_| Error::compression())?;
+                if size > MAX_DECOMPRESSED_SIZE {
+                    return Err(too_large());
+                }
+                lz4_flex::decompress(compressed, size).map_err(|_| Error::compression())
+            }
+            #[allow(unreachable_patterns)]
+            _ => Err(self.unsupported()),
         }
     }
+
+    /// The error for a codec whose feature is not enabled in this build
+    fn unsupported(self) -> Error {
+        Error::unsupported(format!(
+            "The {self:?} compression codec is not enabled in this build"
+        ))
+    }
+}
+
+impl From<CompressionCodec> for u8 {
+    fn from(codec: CompressionCodec) -> Self {
+        codec.id()
+    }
+}
+
+impl TryFrom<u8> for CompressionCodec {
+    type Error = Error;
+
+    fn try_from(id: u8) -> Result<Self, Self::Error> {
+        match id {
+            0 => Ok(Self::None),
+            1 => Ok(Self::Gzip),
+            2 => Ok(Self::Zstd),
+            3 => Ok(Self::Lz4),
+            _ => Err(Error::illegal_argument(format!(
+                "Unknown compression codec id {id}"
+            ))),
+        }
+    }
+}
+
+/// Inflates `buf`, up to [`MAX_DECOMPRESSED_SIZE`] bytes
+fn decompress_gzip(buf: &[u8]) -> Result<Vec<u8>, Error> {
+    decompress_to_vec_with_limit(buf, MAX_DECOMPRESSED_SIZE).map_err(|err| match err.status {
+        TINFLStatus::HasMoreOutput => too_large(),
+        _ => Error::compression(),
+    })
+}
+
+/// Compresses buffers of at least `threshold` bytes, using the configured [`CompressionCodec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compressor {
+    codec: CompressionCodec,
+    /// If less bytes than threshold are being passed to `maybe_compress`, the payload is not getting compressed.
+    threshold: usize,
+}
+
+impl Compressor {
+    /// Create a new [`Compressor`] using `codec` for all buffers at least as large as `threshold`.
+    #[must_use]
+    pub fn new(codec: CompressionCodec, threshold: usize) -> Self {
+        Self { codec, threshold }
+    }
+
+    /// The codec used by this compressor
+    #[must_use]
+    pub fn codec(&self) -> CompressionCodec {
+        self.codec
+    }
+
+    /// The minimum size of buffers that get compressed
+    #[must_use]
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Compression.
+    /// Returns `None` if the codec is [`CompressionCodec::None`], the buffer is smaller than the threshold,
+    /// or compressing did not make the buffer any smaller. Else, the compressed buffer is returned.
+    #[must_use]
+    pub fn maybe_compress(&self, buf: &[u8]) -> Option<Vec<u8>> {
+        if self.codec == CompressionCodec::None || buf.len() < self.threshold {
+            return None;
+        }
+        self.codec
+            .compress(buf)
+            .ok()
+            .filter(|compressed| compressed.len() < buf.len())
+    }
+
+    /// Decompression of a buffer compressed by this compressor
+    pub fn decompress(&self, buf: &[u8]) -> Result<Vec<u8>, Error> {
+        self.codec.decompress(buf)
+    }
+}
+
+impl Default for Compressor {
+    /// A gzip [`Compressor`] that will always compress
+    fn default() -> Self {
+        Self::new(CompressionCodec::Gzip, 0)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::compress::GzipCompressor;
+    use crate::compress::{CompressionCodec, Compressor, GzipCompressor, MAX_DECOMPRESSED_SIZE};
 
     #[test]
     fn test_compression() {
@@ -93,4 +276,71 @@ mod tests {
         assert!(compressor.maybe_compress(&[1u8; 1023]).is_none());
         assert!(compressor.maybe_compress(&[1u8; 1024]).is_some());
     }
+
+    #[test]
+    fn test_codecs() {
+        let buf = [2u8; 4096];
+        let codecs = [
+            CompressionCodec::Gzip,
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd,
+            #[cfg(feature = "lz4")]
+            CompressionCodec::Lz4,
+        ];
+        for codec in codecs {
+            let compressor = Compressor::new(codec, 1024);
+            assert!(compressor.maybe_compress(&buf[..1023]).is_none());
+            let compressed = compressor.maybe_compress(&buf).unwrap();
+            assert!(compressed.len() < buf.len());
+            assert_eq!(compressor.decompress(&compressed).unwrap(), buf);
+        }
+        assert!(Compressor::new(CompressionCodec::None, 0)
+            .maybe_compress(&buf)
+            .is_none());
+        // Incompressible data is not worth sending compressed
+        assert!(Compressor::new(CompressionCodec::Gzip, 0)
+            .maybe_compress(&[1, 2, 3])
+            .is_none());
+        #[cfg(not(feature = "zstd"))]
+        assert!(CompressionCodec::Zstd.compress(&buf).is_err());
+    }
+
+    #[test]
+    fn test_codec_ids() {
+        for (id, codec) in [
+            CompressionCodec::None,
+            CompressionCodec::Gzip,
+            CompressionCodec::Zstd,
+            CompressionCodec::Lz4,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            assert_eq!(codec.id(), id as u8);
+            assert_eq!(CompressionCodec::try_from(codec.id()).unwrap(), codec);
+            let serialized = postcard::to_allocvec(&codec).unwrap();
+            assert_eq!(serialized, [codec.id()]);
+            assert_eq!(
+                postcard::from_bytes::<CompressionCodec>(&serialized).unwrap(),
+                codec
+            );
+        }
+        assert!(postcard::from_bytes::<CompressionCodec>(&[4]).is_err());
+    }
+
+    #[test]
+    fn test_decompression_limit() {
+        let buf = vec![0u8; MAX_DECOMPRESSED_SIZE + 1];
+        let codecs = [
+            CompressionCodec::Gzip,
+            #[cfg(feature = "zstd")]
+            CompressionCodec::Zstd,
+            #[cfg(feature = "lz4")]
+            CompressionCodec::Lz4,
+        ];
+        for codec in codecs {
+            let compressed = codec.compress(&buf).unwrap();
+            assert!(codec.decompress(&compressed).is_err());
+        }
+    }
 }
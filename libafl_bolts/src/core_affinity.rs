@@ -203,6 +203,126 @@ impl TryFrom<&str> for Cores {
     }
 }
 
+/// A NUMA node: the cores sharing the same local memory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct NumaNode {
+    /// The id of the node, as used by the OS
+    pub id: usize,
+    /// The cores of this node
+    pub cores: Vec<CoreId>,
+}
+
+#[cfg(feature = "std")]
+impl NumaNode {
+    /// Makes the current process allocate its memory on this node from now on, falling back to other nodes
+    /// when this node is full. This includes shared memory mapped later.
+    ///
+    /// Note: This will *_not_* fail if the target platform does not support NUMA memory policies.
+    pub fn set_memory_policy(&self) -> Result<(), Error> {
+        match set_memory_policy_helper(self.id) {
+            Ok(()) | Err(Error::Unsupported(_, _)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Places the pages of `mem`, such as a shared memory map, on this node, moving the ones already allocated
+    pub fn bind_memory(&self, mem: &mut [u8]) -> Result<(), Error> {
+        bind_memory_helper(self.id, mem)
+    }
+}
+
+impl From<&NumaNode> for Cores {
+    fn from(node: &NumaNode) -> Self {
+        let ids: Vec<usize> = node.cores.iter().map(|core_id| core_id.0).collect();
+        Self::from(ids)
+    }
+}
+
+/// The NUMA nodes of a system
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
+pub struct NumaTopology {
+    /// The nodes, by increasing id
+    pub nodes: Vec<NumaNode>,
+}
+
+impl NumaTopology {
+    /// Creates a [`NumaTopology`] from the given nodes, to override the detected one
+    #[must_use]
+    pub fn new(nodes: Vec<NumaNode>) -> Self {
+        Self { nodes }
+    }
+
+    /// Returns the node of the given core, if any
+    #[must_use]
+    pub fn node_of(&self, core_id: CoreId) -> Option<&NumaNode> {
+        self.nodes.iter().find(|node| node.cores.contains(&core_id))
+    }
+
+    /// Returns `true` if there is more than one node, so that the placement matters
+    #[must_use]
+    pub fn is_numa(&self) -> bool {
+        self.nodes.len() > 1
+    }
+}
+
+#[cfg(feature = "std")]
+impl NumaTopology {
+    /// Detects the NUMA nodes of this system, and their cores.
+    ///
+    /// On systems without NUMA support, all cores are on a single node `0`.
+    pub fn detect() -> Result<Self, Error> {
+        let nodes = get_numa_nodes_helper()?;
+        if nodes.is_empty() {
+            return Ok(Self::new(vec![NumaNode {
+                id: 0,
+                cores: get_core_ids()?,
+            }]));
+        }
+        Ok(Self::new(nodes))
+    }
+}
+
+#[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+#[inline]
+fn get_numa_nodes_helper() -> Result<Vec<NumaNode>, Error> {
+    linux::get_numa_nodes()
+}
+
+#[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+#[inline]
+fn set_memory_policy_helper(node: usize) -> Result<(), Error> {
+    linux::set_memory_policy(node)
+}
+
+#[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+#[inline]
+fn bind_memory_helper(node: usize, mem: &mut [u8]) -> Result<(), Error> {
+    linux::bind_memory(node, mem)
+}
+
+#[cfg(all(feature = "std", not(any(target_os = "android", target_os = "linux"))))]
+#[allow(clippy::unnecessary_wraps)]
+#[inline]
+fn get_numa_nodes_helper() -> Result<Vec<NumaNode>, Error> {
+    Ok(vec![])
+}
+
+#[cfg(all(feature = "std", not(any(target_os = "android", target_os = "linux"))))]
+#[inline]
+fn set_memory_policy_helper(_node: usize) -> Result<(), Error> {
+    Err(Error::unsupported(
+        "NUMA memory policies are not supported on this platform",
+    ))
+}
+
+#[cfg(all(feature = "std", not(any(target_os = "android", target_os = "linux"))))]
+#[inline]
+fn bind_memory_helper(_node: usize, _mem: &mut [u8]) -> Result<(), Error> {
+    Err(Error::unsupported(
+        "NUMA memory policies are not supported on this platform",
+    ))
+}
+
 // Linux Section
 
 #[cfg(any(
@@ -315,10 +435,128 @@ mod linux {
         unsafe { zeroed::<cpu_set_t>() }
     }
 
+    /// The directory listing the NUMA nodes, with the cores of each in `node<id>/cpulist`
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    const NUMA_NODES_DIR: &str = "/sys/devices/system/node";
+
+    /// Prefer the node, but fall back to others when it is full
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    const MPOL_PREFERRED: libc::c_int = 1;
+
+    /// Strictly allocate on the node
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    const MPOL_BIND: libc::c_int = 2;
+
+    /// Move the pages of the range already allocated elsewhere
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    const MPOL_MF_MOVE: libc::c_uint = 1 << 1;
+
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    pub fn get_numa_nodes() -> Result<Vec<super::NumaNode>, Error> {
+        use std::fs;
+
+        use super::{Cores, NumaNode};
+
+        let Ok(entries) = fs::read_dir(NUMA_NODES_DIR) else {
+            // No NUMA support in this kernel
+            return Ok(Vec::new());
+        };
+        let mut nodes = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let Some(id) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix("node"))
+                .and_then(|id| id.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let cpulist = fs::read_to_string(entry.path().join("cpulist"))?;
+            let cpulist = cpulist.trim();
+            if cpulist.is_empty() {
+                // A node with memory, but without cores
+                continue;
+            }
+            nodes.push(NumaNode {
+                id,
+                cores: Cores::from_cmdline(cpulist)?.ids,
+            });
+        }
+        nodes.sort_unstable_by_key(|node| node.id);
+        Ok(nodes)
+    }
+
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    fn node_mask(node: usize) -> Result<[libc::c_ulong; 16], Error> {
+        let mut mask: [libc::c_ulong; 16] = [0; 16];
+        let bits = libc::c_ulong::BITS as usize;
+        if node >= mask.len() * bits {
+            return Err(Error::illegal_argument(format!(
+                "NUMA node {node} is out of range"
+            )));
+        }
+        mask[node / bits] |= 1 << (node % bits);
+        Ok(mask)
+    }
+
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    pub fn set_memory_policy(node: usize) -> Result<(), Error> {
+        let mask = node_mask(node)?;
+        // # Safety
+        // The kernel only reads the mask, of the given size in bits.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_set_mempolicy,
+                MPOL_PREFERRED,
+                mask.as_ptr(),
+                mask.len() * libc::c_ulong::BITS as usize,
+            )
+        };
+        if result < 0 {
+            Err(Error::last_os_error("Failed to set_mempolicy"))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(all(feature = "std", any(target_os = "android", target_os = "linux")))]
+    pub fn bind_memory(node: usize, mem: &mut [u8]) -> Result<(), Error> {
+        let mask = node_mask(node)?;
+        // # Safety
+        // The range is borrowed mutably for the call, and `mbind` only changes where its pages are placed.
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                mem.as_mut_ptr(),
+                mem.len(),
+                MPOL_BIND,
+                mask.as_ptr(),
+                mask.len() * libc::c_ulong::BITS as usize,
+                MPOL_MF_MOVE,
+            )
+        };
+        if result < 0 {
+            Err(Error::last_os_error("Failed to mbind"))
+        } else {
+            Ok(())
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
 
+        #[test]
+        #[cfg(feature = "std")]
+        #[cfg_attr(miri, ignore)]
+        fn test_linux_numa_topology() {
+            let topology = super::super::NumaTopology::detect().unwrap();
+            assert!(!topology.nodes.is_empty());
+            let node = topology.node_of(get_core_ids().unwrap()[0]).unwrap();
+            node.set_memory_policy().unwrap();
+        }
+
         #[test]
         #[cfg_attr(miri, ignore)]
         fn test_linux_get_affinity_mask() {
@@ -106,6 +106,8 @@ pub mod rands;
 #[cfg(feature = "alloc")]
 pub mod serdeany;
 pub mod shmem;
+#[cfg(feature = "simd")]
+pub mod simd;
 #[cfg(feature = "std")]
 pub mod staterestore;
 #[cfg(feature = "alloc")]
@@ -72,9 +72,11 @@ use core::{
     num::NonZeroUsize,
     ops::{BitAnd, BitOr, Not},
     ptr, slice,
-    sync::atomic::{fence, AtomicU16, Ordering},
+    sync::atomic::{fence, AtomicU16, AtomicUsize, Ordering},
     time::Duration,
 };
+#[cfg(all(windows, feature = "std"))]
+use std::fs::File;
 #[cfg(feature = "std")]
 use std::{
     boxed::Box,
@@ -94,12 +96,16 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use tuple_list::tuple_list;
 
+#[cfg(feature = "llmp_compression")]
+use crate::compress::CompressionCodec;
 #[cfg(all(unix, not(miri)))]
 use crate::os::unix_signals::setup_signal_handler;
 #[cfg(unix)]
 use crate::os::unix_signals::{siginfo_t, ucontext_t, Signal, SignalHandler};
 #[cfg(all(windows, feature = "std"))]
 use crate::os::windows_exceptions::{setup_ctrl_handler, CtrlHandler};
+#[cfg(all(windows, feature = "std"))]
+use crate::os::windows_pipes::{connect_named_pipe, llmp_pipe_name, NamedPipeListener};
 #[cfg(feature = "std")]
 use crate::{current_time, IP_LOCALHOST};
 use crate::{
@@ -117,6 +123,13 @@ const LLMP_CFG_INITIAL_MAP_SIZE: usize = 1 << 28;
 /// If the `llmp_small_maps` feature is set, we start off with 1 meg.
 #[cfg(feature = "llmp_small_maps")]
 const LLMP_CFG_INITIAL_MAP_SIZE: usize = 1 << 20;
+/// The smallest page size [`set_llmp_page_size`] accepts
+pub const LLMP_MIN_PAGE_SIZE: usize = 1 << 16;
+/// The `env` variable setting the [`llmp_page_size`], in bytes, for processes that do not call [`set_llmp_page_size`]
+#[cfg(feature = "std")]
+pub const LLMP_PAGE_SIZE_ENV: &str = "LLMP_PAGE_SIZE";
+/// The [`llmp_page_size`], or `0` if it was not set or read from the `env` yet
+static LLMP_PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
 /// What byte count to align messages to
 /// [`LlmpMsg`] sizes (including header) will always be rounded up to be a multiple of this value.
 const LLMP_CFG_ALIGNNMENT: usize = 64;
@@ -136,15 +149,25 @@ const LLMP_TAG_CLIENT_EXIT: Tag = Tag(0xC11E472);
 const LLMP_TAG_EXITING: Tag = Tag(0x13C5171);
 /// Client gave up as the receiver/broker was too slow
 const LLMP_SLOW_RECEIVER_PANIC: Tag = Tag(0x70051041);
+/// The broker is still alive. Consumed by the receiver, never handed out.
+const LLMP_TAG_BROKER_HEARTBEAT: Tag = Tag(0xBEA7B4EA);
+
+/// How often the broker sends a heartbeat to its clients.
+/// A client that didn't see one for a multiple of this can assume its broker died.
+pub const LLMP_BROKER_HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
 
 /// Unused...
 pub const LLMP_FLAG_INITIALIZED: Flags = Flags(0x0);
-/// This message was compressed in transit
+/// This message was compressed in transit (using gzip)
 pub const LLMP_FLAG_COMPRESSED: Flags = Flags(0x1);
 /// From another broker.
 pub const LLMP_FLAG_FROM_B2B: Flags = Flags(0x2);
 /// From another machine (with the `multi_machine` mode)
 pub const LLMP_FLAG_FROM_MM: Flags = Flags(0x4);
+/// This message was compressed in transit using zstd
+pub const LLMP_FLAG_COMPRESSED_ZSTD: Flags = Flags(0x8);
+/// This message was compressed in transit using lz4
+pub const LLMP_FLAG_COMPRESSED_LZ4: Flags = Flags(0x10);
 
 /// Timt the broker 2 broker connection waits for incoming data,
 /// before checking for own data to forward again.
@@ -161,8 +184,9 @@ const _LLMP_BIND_ADDR: &str = "127.0.0.1";
 /// An env var of this value indicates that the set value was a NULL PTR
 const _NULL_ENV_STR: &str = "_NULL";
 
-/// Magic indicating that a got initialized correctly
-const PAGE_INITIALIZED_MAGIC: u64 = 0x1A1A1A1A1A1A1AF1;
+/// Magic indicating that a got initialized correctly.
+/// Changes with each incompatible change to the map protocol, so that mismatching brokers and clients don't attach.
+const PAGE_INITIALIZED_MAGIC: u64 = 0x1A1A1A1A1A1A1AF2;
 
 /// Magic indicating that a got deinitialized correctly, after use
 const PAGE_DEINITIALIZED_MAGIC: u64 = 0xDEADC0FEAF1BEEF1;
@@ -208,6 +232,12 @@ impl Debug for Flags {
         if *self & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
             f.write_str("COMPRESSED")?;
         }
+        if *self & LLMP_FLAG_COMPRESSED_ZSTD == LLMP_FLAG_COMPRESSED_ZSTD {
+            f.write_str("COMPRESSED_ZSTD")?;
+        }
+        if *self & LLMP_FLAG_COMPRESSED_LZ4 == LLMP_FLAG_COMPRESSED_LZ4 {
+            f.write_str("COMPRESSED_LZ4")?;
+        }
         if *self & LLMP_FLAG_FROM_B2B == LLMP_FLAG_FROM_B2B {
             f.write_str("FROM_B2B")?;
         }
@@ -215,6 +245,35 @@ impl Debug for Flags {
     }
 }
 
+#[cfg(feature = "llmp_compression")]
+impl Flags {
+    /// The flag marking a message as compressed with `codec`
+    #[must_use]
+    pub fn compressed_with(codec: CompressionCodec) -> Self {
+        match codec {
+            CompressionCodec::None => LLMP_FLAG_INITIALIZED,
+            CompressionCodec::Gzip => LLMP_FLAG_COMPRESSED,
+            CompressionCodec::Zstd => LLMP_FLAG_COMPRESSED_ZSTD,
+            CompressionCodec::Lz4 => LLMP_FLAG_COMPRESSED_LZ4,
+        }
+    }
+
+    /// The codec a message with these flags was compressed with, or `None` if it is uncompressed.
+    #[must_use]
+    pub fn compression_codec(self) -> Option<CompressionCodec> {
+        if self & LLMP_FLAG_COMPRESSED == LLMP_FLAG_COMPRESSED {
+            return Some(CompressionCodec::Gzip);
+        }
+        if self & LLMP_FLAG_COMPRESSED_ZSTD == LLMP_FLAG_COMPRESSED_ZSTD {
+            return Some(CompressionCodec::Zstd);
+        }
+        if self & LLMP_FLAG_COMPRESSED_LZ4 == LLMP_FLAG_COMPRESSED_LZ4 {
+            return Some(CompressionCodec::Lz4);
+        }
+        None
+    }
+}
+
 impl BitAnd for Flags {
     type Output = Self;
 
@@ -368,6 +427,9 @@ impl TryFrom<Vec<u8>> for TcpResponse {
 pub enum Listener {
     /// Listener listening on `tcp`.
     Tcp(TcpListener),
+    /// Listener listening on a named pipe, on Windows.
+    #[cfg(windows)]
+    NamedPipe(NamedPipeListener),
 }
 
 /// A listener stream abstraction
@@ -376,13 +438,16 @@ pub enum Listener {
 pub enum ListenerStream {
     /// Listener listening on `tcp`.
     Tcp(TcpStream, SocketAddr),
+    /// A connection to a named pipe, on Windows.
+    #[cfg(windows)]
+    NamedPipe(File),
     /// No listener provided.
     Empty(),
 }
 
 #[cfg(feature = "std")]
 impl Listener {
-    fn accept(&self) -> ListenerStream {
+    fn accept(&mut self) -> ListenerStream {
         match self {
             Listener::Tcp(inner) => match inner.accept() {
                 Ok(res) => ListenerStream::Tcp(res.0, res.1),
@@ -391,6 +456,14 @@ impl Listener {
                     ListenerStream::Empty()
                 }
             },
+            #[cfg(windows)]
+            Listener::NamedPipe(inner) => match inner.accept() {
+                Ok(pipe) => ListenerStream::NamedPipe(pipe),
+                Err(err) => {
+                    log::warn!("Ignoring failed accept: {err:?}");
+                    ListenerStream::Empty()
+                }
+            },
         }
     }
 }
@@ -460,10 +533,11 @@ fn tcp_bind(port: u16) -> Result<TcpListener, Error> {
     Ok(listener)
 }
 
-/// Send one message as `u32` len and `[u8;len]` bytes
+/// Send one message as `u32` len and `[u8;len]` bytes, over tcp or, on Windows, a named pipe
 #[cfg(feature = "std")]
-pub fn send_tcp_msg<T>(stream: &mut TcpStream, msg: &T) -> Result<(), Error>
+pub fn send_tcp_msg<S, T>(stream: &mut S, msg: &T) -> Result<(), Error>
 where
+    S: Write,
     T: Serialize,
 {
     let msg = postcard::to_allocvec(msg)?;
@@ -487,16 +561,16 @@ where
     Ok(())
 }
 
-/// Receive one message of `u32` len and `[u8; len]` bytes
+/// Receive one message of `u32` len and `[u8; len]` bytes, over tcp or, on Windows, a named pipe
 #[cfg(feature = "std")]
-pub fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
+pub fn recv_tcp_msg<S>(stream: &mut S) -> Result<Vec<u8>, Error>
+where
+    S: Read,
+{
     // Always receive one be u32 of size, then the command.
 
     #[cfg(feature = "llmp_debug")]
-    log::trace!(
-        "LLMP TCP: Waiting for packet... (Timeout: {:?})",
-        stream.read_timeout().unwrap_or(None)
-    );
+    log::trace!("LLMP TCP: Waiting for packet...");
 
     let mut size_bytes = [0_u8; 4];
     stream.read_exact(&mut size_bytes)?;
@@ -512,6 +586,45 @@ pub fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
     Ok(bytes)
 }
 
+/// The size of the first page of each [`LlmpSender`], in bytes.
+/// Later pages grow to fit the largest messages sent, see [`next_shmem_size`].
+///
+/// Defaults to 256 megabytes, or 1 megabyte with the `llmp_small_maps` feature.
+/// Smaller pages spare memory with many clients, larger pages are allocated less often in high-throughput campaigns.
+/// It is set for the current process with [`set_llmp_page_size`], or in the [`LLMP_PAGE_SIZE_ENV`] `env` variable.
+pub fn llmp_page_size() -> usize {
+    let page_size = LLMP_PAGE_SIZE.load(Ordering::Relaxed);
+    if page_size != 0 {
+        return page_size;
+    }
+    #[cfg(feature = "std")]
+    if let Ok(value) = env::var(LLMP_PAGE_SIZE_ENV) {
+        let res = value
+            .parse()
+            .map_err(|_| Error::illegal_argument("Not a number of bytes"))
+            .and_then(set_llmp_page_size);
+        match res {
+            Ok(()) => return LLMP_PAGE_SIZE.load(Ordering::Relaxed),
+            Err(err) => log::warn!("Ignoring {LLMP_PAGE_SIZE_ENV}={value}: {err}"),
+        }
+    }
+    // Only look at the env once
+    LLMP_PAGE_SIZE.store(LLMP_CFG_INITIAL_MAP_SIZE, Ordering::Relaxed);
+    LLMP_CFG_INITIAL_MAP_SIZE
+}
+
+/// Sets the [`llmp_page_size`] of the senders created afterwards in this process, and in the children it forks.
+/// The size is rounded up to the next power of two.
+pub fn set_llmp_page_size(page_size: usize) -> Result<(), Error> {
+    if page_size < LLMP_MIN_PAGE_SIZE {
+        return Err(Error::illegal_argument(format!(
+            "The llmp page size {page_size} is smaller than {LLMP_MIN_PAGE_SIZE}"
+        )));
+    }
+    LLMP_PAGE_SIZE.store(page_size.next_power_of_two(), Ordering::Relaxed);
+    Ok(())
+}
+
 /// In case we don't have enough space, make sure the next page will be large
 /// enough. For now, we want to have at least enough space to store 2 of the
 /// largest messages we encountered (plus message one `new_page` message).
@@ -519,7 +632,7 @@ pub fn recv_tcp_msg(stream: &mut TcpStream) -> Result<Vec<u8>, Error> {
 fn next_shmem_size(max_alloc: usize) -> usize {
     max(
         max_alloc * 2 + EOP_MSG_SIZE + LLMP_PAGE_HEADER_LEN,
-        LLMP_CFG_INITIAL_MAP_SIZE - 1,
+        llmp_page_size() - 1,
     )
     .next_power_of_two()
 }
@@ -741,6 +854,10 @@ where
                 let _listener_thread = broker
                     .inner_mut()
                     .launch_listener(Listener::Tcp(listener))?;
+                #[cfg(windows)]
+                if let Err(e) = broker.inner_mut().launch_named_pipe_listener_on(port) {
+                    log::warn!("Not listening on a named pipe: {e}");
+                }
                 Ok(LlmpConnection::IsBroker { broker })
             }
             Err(Error::OsError(e, ..)) if e.kind() == ErrorKind::AddrInUse => {
@@ -917,6 +1034,26 @@ where
     has_unsent_message: bool,
     /// The sharedmem provider to get new sharaed maps if we're full
     shmem_provider: SP,
+    /// The page churn of this sender
+    page_stats: LlmpPageStats,
+}
+
+/// Counters of the pages of an [`LlmpSender`], to measure its page churn.
+///
+/// Each new page is either allocated or reused from the pages the receivers are done with.
+/// Many allocations mean that the [`llmp_page_size`] is too small for the traffic.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmpPageStats {
+    /// Pages allocated from the [`ShMemProvider`], after the first one
+    pub pages_allocated: u64,
+    /// The bytes of the pages allocated from the [`ShMemProvider`], after the first one
+    pub bytes_allocated: u64,
+    /// Pages reused from the cache of pages the receivers are done with
+    pub pages_reused: u64,
+    /// Pages moved to the cache, after the receivers mapped them
+    pub pages_released: u64,
+    /// Cached pages dropped, as they were too small for the next page
+    pub pages_dropped: u64,
 }
 
 /// An actor on the sending part of the shared map
@@ -943,13 +1080,14 @@ where
             last_msg_sent: ptr::null_mut(),
             out_shmems: vec![LlmpSharedMap::new(
                 id,
-                shmem_provider.new_shmem(LLMP_CFG_INITIAL_MAP_SIZE)?,
+                shmem_provider.new_shmem(llmp_page_size())?,
             )],
             // drop pages to the broker if it already read them
             keep_pages_forever,
             has_unsent_message: false,
             shmem_provider,
             unused_shmem_cache: vec![],
+            page_stats: LlmpPageStats::default(),
         })
     }
 
@@ -959,6 +1097,12 @@ where
         self.id
     }
 
+    /// The page churn of this sender, see [`LlmpPageStats`]
+    #[must_use]
+    pub fn page_stats(&self) -> LlmpPageStats {
+        self.page_stats
+    }
+
     /// Completely reset the current sender map.
     /// Afterwards, no receiver should read from it at a different location.
     /// This is only useful if all connected llmp parties start over, for example after a crash.
@@ -1093,6 +1237,7 @@ where
             has_unsent_message: false,
             shmem_provider,
             unused_shmem_cache: vec![],
+            page_stats: LlmpPageStats::default(),
         })
     }
 
@@ -1135,6 +1280,7 @@ where
             log::debug!("Moving unused map to cache: {map:?} {:x?}", map.page());
             self.unused_shmem_cache
                 .insert(self.unused_shmem_cache.len(), map);
+            self.page_stats.pages_released += 1;
         }
     }
 
@@ -1339,6 +1485,7 @@ where
                 #[cfg(feature = "llmp_debug")]
                 log::info!("Dropping too small shmem {cached_shmem:?}");
                 drop(cached_shmem);
+                self.page_stats.pages_dropped += 1;
                 self.new_or_unused_shmem(sender_id, next_min_shmem_size)
             } else {
                 #[cfg(feature = "llmp_debug")]
@@ -1346,14 +1493,15 @@ where
                 unsafe {
                     llmp_page_init(&mut cached_shmem.shmem, sender_id, false);
                 }
+                self.page_stats.pages_reused += 1;
                 Ok(cached_shmem)
             }
         } else {
             // No cached maps that fit our need, let's allocate a new one.
-            Ok(LlmpSharedMap::new(
-                sender_id,
-                self.shmem_provider.new_shmem(next_min_shmem_size)?,
-            ))
+            let shmem = self.shmem_provider.new_shmem(next_min_shmem_size)?;
+            self.page_stats.pages_allocated += 1;
+            self.page_stats.bytes_allocated += shmem.len() as u64;
+            Ok(LlmpSharedMap::new(sender_id, shmem))
         }
     }
 
@@ -1510,6 +1658,7 @@ where
             || tag == LLMP_TAG_END_OF_PAGE
             || tag == LLMP_TAG_UNINITIALIZED
             || tag == LLMP_TAG_UNSET
+            || tag == LLMP_TAG_BROKER_HEARTBEAT
         {
             return Err(Error::unknown(format!(
                 "Reserved tag supplied to send_buf ({tag:?})"
@@ -1533,6 +1682,7 @@ where
             || tag == LLMP_TAG_END_OF_PAGE
             || tag == LLMP_TAG_UNINITIALIZED
             || tag == LLMP_TAG_UNSET
+            || tag == LLMP_TAG_BROKER_HEARTBEAT
         {
             return Err(Error::unknown(format!(
                 "Reserved tag supplied to send_buf ({tag:?})"
@@ -1659,9 +1809,26 @@ where
     /// Read next message.
     /// Returns a pointer to the [`LlmpMsg`], `None` of no message exists, or an [`Error`].
     ///
-    /// Will *not* update `self.last_msg_time`.
+    /// Heartbeats of the broker are consumed here, updating `self.last_msg_time`.
+    /// Will *not* update `self.last_msg_time` for other messages.
     #[inline(never)]
     unsafe fn recv(&mut self) -> Result<Option<*mut LlmpMsg>, Error> {
+        loop {
+            match self.recv_next()? {
+                Some(msg) if (*msg).tag == LLMP_TAG_BROKER_HEARTBEAT => {
+                    #[cfg(feature = "std")]
+                    {
+                        self.last_msg_time = current_time();
+                    }
+                }
+                ret => return Ok(ret),
+            }
+        }
+    }
+
+    /// Read the next message, including heartbeats.
+    #[inline(never)]
+    unsafe fn recv_next(&mut self) -> Result<Option<*mut LlmpMsg>, Error> {
         /* DBG("recv %p %p\n", page, last_msg); */
         let page = self.current_recv_shmem.page_mut();
         let last_msg = self.last_msg_recvd;
@@ -1769,7 +1936,7 @@ where
                         self.current_recv_shmem.shmem.len()
                     );
                     // After we mapped the new page, return the next message, if available
-                    return self.recv();
+                    return self.recv_next();
                 }
                 _ => (),
             }
@@ -1785,28 +1952,36 @@ where
     /// # Safety
     /// Returns a raw ptr, on the recv map. Should be safe in general
     pub unsafe fn recv_blocking(&mut self) -> Result<*mut LlmpMsg, Error> {
-        let mut current_msg_id = MessageId(0);
-        let page = self.current_recv_shmem.page_mut();
-        let last_msg = self.last_msg_recvd;
-        if !last_msg.is_null() {
-            assert!(
-                (*last_msg).tag != LLMP_TAG_END_OF_PAGE || llmp_msg_in_page(page, last_msg),
-                "BUG: full page passed to await_message_blocking or reset failed"
-            );
-
-            current_msg_id = (*last_msg).message_id;
-        }
         loop {
-            if (*page).current_msg_id.load(Ordering::Relaxed) != current_msg_id.0 {
-                return match self.recv()? {
-                    Some(msg) => Ok(msg),
-                    None => panic!("BUG: blocking llmp message should never be NULL"),
-                };
+            let mut current_msg_id = MessageId(0);
+            let page = self.current_recv_shmem.page_mut();
+            let last_msg = self.last_msg_recvd;
+            if !last_msg.is_null() {
+                assert!(
+                    (*last_msg).tag != LLMP_TAG_END_OF_PAGE || llmp_msg_in_page(page, last_msg),
+                    "BUG: full page passed to await_message_blocking or reset failed"
+                );
+
+                current_msg_id = (*last_msg).message_id;
+            }
+            while (*page).current_msg_id.load(Ordering::Relaxed) == current_msg_id.0 {
+                hint::spin_loop();
+            }
+            // If all we got was a heartbeat of the broker, keep waiting.
+            if let Some(msg) = self.recv()? {
+                return Ok(msg);
             }
-            hint::spin_loop();
         }
     }
 
+    /// When the last sign of life of the sender was received.
+    /// For the clients of a broker, this is the last message; for a client, the last heartbeat of its broker.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn last_msg_time(&self) -> Duration {
+        self.last_msg_time
+    }
+
     /// Returns the next message, tag, buf, if available, else None
     #[allow(clippy::type_complexity)]
     #[inline]
@@ -1951,7 +2126,7 @@ where
         unsafe {
             assert!(
                 (*ret.page()).magic == PAGE_INITIALIZED_MAGIC,
-                "Map was not priviously initialized at {:?}",
+                "Map was not priviously initialized at {:?}, or by an incompatible LLMP version",
                 &ret.shmem
             );
             #[cfg(feature = "llmp_debug")]
@@ -2075,6 +2250,9 @@ where
     clients_to_remove: Vec<ClientId>,
     /// The `ShMemProvider` to use
     shmem_provider: SP,
+    /// When we last sent a heartbeat to our clients
+    #[cfg(feature = "std")]
+    last_heartbeat: Duration,
 }
 
 /// The broker (node 0)
@@ -2237,6 +2415,12 @@ where
     fn on_timeout(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Hook called on every round of the broker, even if no client sent a message.
+    /// The messages added to `new_msgs` are sent to all clients, like the ones added in [`Self::on_new_message`].
+    fn poll_new_msgs(&mut self, _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// A tuple of Llmp hooks. They are evaluated sequentially, and returns if one decides to filter out the evaluated message.
@@ -2257,6 +2441,10 @@ where
 
     /// Call all hook callbacks on timeout.
     fn on_timeout_all(&mut self) -> Result<(), Error>;
+
+    /// Call all hook callbacks polling for new messages.
+    fn poll_new_msgs_all(&mut self, new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>)
+        -> Result<(), Error>;
 }
 
 impl<SP> LlmpHookTuple<SP> for ()
@@ -2278,6 +2466,13 @@ where
     fn on_timeout_all(&mut self) -> Result<(), Error> {
         Ok(())
     }
+
+    fn poll_new_msgs_all(
+        &mut self,
+        _new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 impl<Head, Tail, SP> LlmpHookTuple<SP> for (Head, Tail)
@@ -2315,6 +2510,14 @@ where
         self.0.on_timeout()?;
         self.1.on_timeout_all()
     }
+
+    fn poll_new_msgs_all(
+        &mut self,
+        new_msgs: &mut Vec<(Tag, Flags, Vec<u8>)>,
+    ) -> Result<(), Error> {
+        self.0.poll_new_msgs(new_msgs)?;
+        self.1.poll_new_msgs_all(new_msgs)
+    }
 }
 
 impl<SP> LlmpBroker<(), SP>
@@ -2628,6 +2831,19 @@ where
             }
         }
 
+        // The hooks may have received messages from elsewhere, even if no client sent anything
+        let mut polled_msgs: Vec<(Tag, Flags, Vec<u8>)> = Vec::new();
+        self.hooks.poll_new_msgs_all(&mut polled_msgs)?;
+        if !polled_msgs.is_empty() {
+            new_messages = true;
+        }
+        for (tag, flags, msg) in polled_msgs {
+            self.inner.llmp_out.send_buf_with_flags(tag, flags, &msg)?;
+        }
+
+        #[cfg(feature = "std")]
+        self.inner.maybe_send_heartbeat()?;
+
         let possible_remove = self.inner.clients_to_remove.len();
         if possible_remove > 0 {
             self.inner.clients_to_remove.sort_unstable();
@@ -2853,6 +3069,7 @@ where
                 has_unsent_message: false,
                 shmem_provider: shmem_provider.clone(),
                 unused_shmem_cache: vec![],
+                page_stats: LlmpPageStats::default(),
             },
             llmp_clients: vec![],
             clients_to_remove: Vec::new(),
@@ -2860,9 +3077,28 @@ where
             exit_cleanly_after: None,
             num_clients_seen: 0,
             shmem_provider,
+            #[cfg(feature = "std")]
+            last_heartbeat: current_time(),
         })
     }
 
+    /// Lets our clients know we are alive, each [`LLMP_BROKER_HEARTBEAT_INTERVAL`].
+    #[cfg(feature = "std")]
+    fn maybe_send_heartbeat(&mut self) -> Result<(), Error> {
+        let now = current_time();
+        if now.saturating_sub(self.last_heartbeat) >= LLMP_BROKER_HEARTBEAT_INTERVAL {
+            self.last_heartbeat = now;
+            // The tag is reserved, so we can't use `send_buf`
+            unsafe {
+                let msg = self.llmp_out.alloc_next(0)?;
+                (*msg).tag = LLMP_TAG_BROKER_HEARTBEAT;
+                (*msg).flags = LLMP_FLAG_INITIALIZED;
+                self.llmp_out.send(msg, true)?;
+            }
+        }
+        Ok(())
+    }
+
     /// Gets the [`ClientId`] the next client attaching to this broker will get.
     /// In its current implementation, the inner value of the next [`ClientId`]
     /// is equal to `self.num_clients_seen`.
@@ -2877,6 +3113,12 @@ where
         )
     }
 
+    /// The page churn of the broadcast map of this broker, see [`LlmpPageStats`]
+    #[must_use]
+    pub fn page_stats(&self) -> LlmpPageStats {
+        self.llmp_out.page_stats()
+    }
+
     /// Create a new [`LlmpBrokerInner`] attaching to a TCP port
     #[cfg(feature = "std")]
     pub fn create_attach_to_tcp(shmem_provider: SP, port: u16) -> Result<Self, Error> {
@@ -2895,6 +3137,19 @@ where
                 let mut broker =
                     LlmpBrokerInner::with_keep_pages(shmem_provider, keep_pages_forever)?;
                 let _listener_thread = broker.launch_listener(Listener::Tcp(listener))?;
+                #[cfg(windows)]
+                if let Err(e) = broker.launch_named_pipe_listener_on(port) {
+                    log::warn!("Not listening on a named pipe: {e}");
+                }
+                Ok(broker)
+            }
+            // Local tcp may be blocked by a policy, the clients fall back to the named pipe then.
+            #[cfg(windows)]
+            Err(Error::OsError(e, ..)) if e.kind() != ErrorKind::AddrInUse => {
+                log::warn!("Could not bind tcp port {port} ({e}), only listening on a named pipe");
+                let mut broker =
+                    LlmpBrokerInner::with_keep_pages(shmem_provider, keep_pages_forever)?;
+                let _listener_thread = broker.launch_named_pipe_listener_on(port)?;
                 Ok(broker)
             }
             Err(e) => Err(e),
@@ -3083,6 +3338,19 @@ where
         self.launch_listener(Listener::Tcp(listener))
     }
 
+    /// Launches a thread using a named pipe listener, on which new clients on this machine may connect to this broker.
+    /// The clients connecting to `port` fall back to this pipe if they cannot connect over tcp.
+    #[cfg(all(windows, feature = "std"))]
+    pub fn launch_named_pipe_listener_on(
+        &mut self,
+        port: u16,
+    ) -> Result<thread::JoinHandle<()>, Error> {
+        let name = llmp_pipe_name(port);
+        let listener = NamedPipeListener::bind(&name)?;
+        log::info!("Server listening on named pipe {name}");
+        self.launch_listener(Listener::NamedPipe(listener))
+    }
+
     /// Announces a new client on the given shared map.
     /// Called from a background thread, typically.
     /// Upon receiving this message, the broker should map the announced page and start tracking it for new messages.
@@ -3272,15 +3540,48 @@ where
         ret
     }
 
-    /// handles a single tcp request in the current context.
+    /// Sends the `broker_hello` to a new connection, and receives its request.
+    /// Logs and returns `None` on errors.
     #[cfg(feature = "std")]
-    fn handle_tcp_request(
-        mut stream: TcpStream,
+    fn greet<S>(stream: &mut S, broker_hello: &TcpResponse) -> Option<TcpRequest>
+    where
+        S: Read + Write,
+    {
+        // Send initial information, without anyone asking.
+        // This makes it a tiny bit easier to map the broker map for new Clients.
+        if let Err(e) = send_tcp_msg(stream, broker_hello) {
+            log::error!("Error sending initial hello: {e:?}");
+            return None;
+        }
+
+        let buf = match recv_tcp_msg(stream) {
+            Ok(buf) => buf,
+            Err(e) => {
+                log::error!("Error receving from tcp: {e:?}");
+                return None;
+            }
+        };
+
+        // log::info!("{:#?}", buf);
+        match buf.try_into() {
+            Ok(req) => Some(req),
+            Err(e) => {
+                log::error!("Could not deserialize tcp message: {e:?}");
+                None
+            }
+        }
+    }
+
+    /// handles a single request of a client on this machine, over tcp or a named pipe.
+    #[cfg(feature = "std")]
+    fn handle_local_request<S>(
+        stream: &mut S,
         request: &TcpRequest,
         current_client_id: &mut ClientId,
         sender: &mut LlmpSender<SP>,
-        broker_shmem_description: &ShMemDescription,
-    ) {
+    ) where
+        S: Write,
+    {
         match request {
             TcpRequest::ClientQuit { client_id } => {
                 // todo search the ancestor_id and remove it.
@@ -3296,7 +3597,7 @@ where
                 };
 
                 if let Err(e) = send_tcp_msg(
-                    &mut stream,
+                    stream,
                     &TcpResponse::LocalClientAccepted {
                         client_id: *current_client_id,
                     },
@@ -3305,6 +3606,22 @@ where
                 };
                 current_client_id.0 += 1;
             }
+            TcpRequest::RemoteBrokerHello { hostname } => {
+                log::warn!("Ignoring broker {hostname}, brokers only connect over tcp");
+            }
+        };
+    }
+
+    /// handles a single tcp request in the current context.
+    #[cfg(feature = "std")]
+    fn handle_tcp_request(
+        mut stream: TcpStream,
+        request: &TcpRequest,
+        current_client_id: &mut ClientId,
+        sender: &mut LlmpSender<SP>,
+        broker_shmem_description: &ShMemDescription,
+    ) {
+        match request {
             TcpRequest::RemoteBrokerHello { hostname } => {
                 log::info!("B2B new client: {hostname}");
 
@@ -3330,12 +3647,16 @@ where
                     current_client_id.0 += 1;
                 }
             }
+            _ => Self::handle_local_request(&mut stream, request, current_client_id, sender),
         };
     }
 
     #[cfg(feature = "std")]
     /// Launches a thread using a listener socket, on which new clients may connect to this broker
-    pub fn launch_listener(&mut self, listener: Listener) -> Result<thread::JoinHandle<()>, Error> {
+    pub fn launch_listener(
+        &mut self,
+        mut listener: Listener,
+    ) -> Result<thread::JoinHandle<()>, Error> {
         // Later in the execution, after the initial map filled up,
         // the current broadcast map will point to a different map.
         // However, the original map is (as of now) never freed, new clients will start
@@ -3357,7 +3678,7 @@ where
         // Tcp out map sends messages from background thread tcp server to foreground client
         let tcp_out_shmem = LlmpSharedMap::new(
             llmp_tcp_id,
-            self.shmem_provider.new_shmem(LLMP_CFG_INITIAL_MAP_SIZE)?,
+            self.shmem_provider.new_shmem(llmp_page_size())?,
         );
         let tcp_out_shmem_description = tcp_out_shmem.shmem.description();
         let listener_id = self.register_client(tcp_out_shmem);
@@ -3381,6 +3702,7 @@ where
                 has_unsent_message: false,
                 shmem_provider: shmem_provider_bg.clone(),
                 unused_shmem_cache: vec![],
+                page_stats: LlmpPageStats::default(),
             };
 
             loop {
@@ -3392,31 +3714,8 @@ where
                             stream.peer_addr().unwrap()
                         );
 
-                        // Send initial information, without anyone asking.
-                        // This makes it a tiny bit easier to map the broker map for new Clients.
-                        match send_tcp_msg(&mut stream, &broker_hello) {
-                            Ok(()) => {}
-                            Err(e) => {
-                                log::error!("Error sending initial hello: {e:?}");
-                                continue;
-                            }
-                        }
-
-                        let buf = match recv_tcp_msg(&mut stream) {
-                            Ok(buf) => buf,
-                            Err(e) => {
-                                log::error!("Error receving from tcp: {e:?}");
-                                continue;
-                            }
-                        };
-
-                        // log::info!("{:#?}", buf);
-                        let req = match buf.try_into() {
-                            Ok(req) => req,
-                            Err(e) => {
-                                log::error!("Could not deserialize tcp message: {e:?}");
-                                continue;
-                            }
+                        let Some(req) = Self::greet(&mut stream, &broker_hello) else {
+                            continue;
                         };
 
                         Self::handle_tcp_request(
@@ -3427,6 +3726,21 @@ where
                             &broker_shmem_description,
                         );
                     }
+                    #[cfg(windows)]
+                    ListenerStream::NamedPipe(mut pipe) => {
+                        log::info!("New connection on the named pipe");
+
+                        let Some(req) = Self::greet(&mut pipe, &broker_hello) else {
+                            continue;
+                        };
+
+                        Self::handle_local_request(
+                            &mut pipe,
+                            &req,
+                            &mut current_client_id,
+                            &mut tcp_incoming_sender,
+                        );
+                    }
                     ListenerStream::Empty() => {
                         continue;
                     }
@@ -3596,13 +3910,14 @@ where
                 id: sender_id,
                 last_msg_sent: ptr::null_mut(),
                 out_shmems: vec![LlmpSharedMap::new(sender_id, {
-                    shmem_provider.new_shmem(LLMP_CFG_INITIAL_MAP_SIZE)?
+                    shmem_provider.new_shmem(llmp_page_size())?
                 })],
                 // drop pages to the broker if it already read them
                 keep_pages_forever: false,
                 has_unsent_message: false,
                 shmem_provider: shmem_provider.clone(),
                 unused_shmem_cache: vec![],
+                page_stats: LlmpPageStats::default(),
             },
 
             receiver: LlmpReceiver {
@@ -3706,11 +4021,42 @@ where
         Self::new(shmem_provider, map, client_id)
     }
 
+    /// Replaces this client with a new one, attached to the broker on `port`.
+    ///
+    /// Use this to recover from a broker restart. Unlike [`Self::create_attach_to_tcp`], this tries to connect only once,
+    /// so the caller can retry with a backoff.
+    /// Messages the old broker did not read yet are lost, and so are incoming messages we did not read before.
+    #[cfg(feature = "std")]
+    pub fn reattach_to_tcp(&mut self, port: u16) -> Result<(), Error> {
+        let shmem_provider = self.sender.shmem_provider.clone();
+        *self = match TcpStream::connect((IP_LOCALHOST, port)) {
+            Ok(stream) => Self::attach_over(shmem_provider, stream)?,
+            #[cfg(windows)]
+            Err(e) => match connect_named_pipe(&llmp_pipe_name(port))? {
+                Some(pipe) => Self::attach_over(shmem_provider, pipe)?,
+                None => {
+                    return Err(Error::illegal_state(format!(
+                        "No broker listening on port {port}: {e}"
+                    )))
+                }
+            },
+            #[cfg(not(windows))]
+            Err(e) => {
+                return Err(Error::illegal_state(format!(
+                    "No broker listening on port {port}: {e}"
+                )))
+            }
+        };
+        Ok(())
+    }
+
     #[cfg(feature = "std")]
     /// Create a [`LlmpClient`], getting the ID from a given port, then also tell the restarter's ID so we ask to be removed later
     /// This is called when, for the first time, the restarter attaches to this process.
-    pub fn create_attach_to_tcp(mut shmem_provider: SP, port: u16) -> Result<Self, Error> {
-        let mut stream = match TcpStream::connect((IP_LOCALHOST, port)) {
+    ///
+    /// On Windows, it falls back to the named pipe of the broker, if it cannot connect over tcp.
+    pub fn create_attach_to_tcp(shmem_provider: SP, port: u16) -> Result<Self, Error> {
+        let stream = match TcpStream::connect((IP_LOCALHOST, port)) {
             Ok(stream) => stream,
             Err(e) => {
                 match e.kind() {
@@ -3720,6 +4066,12 @@ where
                             if let Ok(stream) = TcpStream::connect((IP_LOCALHOST, port)) {
                                 break stream;
                             }
+                            // A broker that could not bind the port only serves the named pipe
+                            #[cfg(windows)]
+                            if let Some(pipe) = connect_named_pipe(&llmp_pipe_name(port))? {
+                                log::info!("Connected to the named pipe of port {port}");
+                                return Self::attach_over(shmem_provider, pipe);
+                            }
 
                             log::debug!("Connection Refused. Retrying...");
 
@@ -3727,12 +4079,34 @@ where
                             thread::sleep(Duration::from_millis(50));
                         }
                     }
+                    #[cfg(windows)]
+                    _ => {
+                        log::warn!("Could not connect to port {port} ({e}), trying the named pipe");
+                        let name = llmp_pipe_name(port);
+                        let pipe = loop {
+                            if let Some(pipe) = connect_named_pipe(&name)? {
+                                break pipe;
+                            }
+                            thread::sleep(Duration::from_millis(50));
+                        };
+                        log::info!("Connected to {name}");
+                        return Self::attach_over(shmem_provider, pipe);
+                    }
+                    #[cfg(not(windows))]
                     _ => return Err(Error::illegal_state(e.to_string())),
                 }
             }
         };
         log::info!("Connected to port {port}");
+        Self::attach_over(shmem_provider, stream)
+    }
 
+    /// Attaches to the broker that sent its hello over the `stream`
+    #[cfg(feature = "std")]
+    fn attach_over<S>(mut shmem_provider: SP, mut stream: S) -> Result<Self, Error>
+    where
+        S: Read + Write,
+    {
         let TcpResponse::BrokerConnectHello {
             broker_shmem_description,
             hostname: _,
@@ -3787,9 +4161,9 @@ mod tests {
     use serial_test::serial;
 
     use super::{
-        LlmpClient,
+        llmp_page_size, set_llmp_page_size, ClientId, LlmpClient,
         LlmpConnection::{self, IsBroker, IsClient},
-        Tag,
+        LlmpPageStats, Tag, LLMP_MIN_PAGE_SIZE, LLMP_TAG_BROKER_HEARTBEAT,
     };
     use crate::shmem::{ShMemProvider, StdShMemProvider};
 
@@ -3841,4 +4215,56 @@ mod tests {
         // We want at least the tcp and sender clients.
         assert_eq!(broker.inner.llmp_clients.len(), 2);
     }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_llmp_broker_heartbeat() {
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let mut broker = match LlmpConnection::on_port(shmem_provider.clone(), 1338).unwrap() {
+            IsClient { client: _ } => panic!("Could not bind to port as broker"),
+            IsBroker { broker } => broker,
+        };
+        let mut client = match LlmpConnection::on_port(shmem_provider, 1338).unwrap() {
+            IsBroker { broker: _ } => panic!("Second connect should be a client!"),
+            IsClient { client } => client,
+        };
+        sleep(Duration::from_millis(100));
+        broker.broker_once().unwrap();
+
+        // Users can't fake heartbeats
+        assert!(client.send_buf(LLMP_TAG_BROKER_HEARTBEAT, &[]).is_err());
+
+        let before = client.receiver.last_msg_time();
+        sleep(Duration::from_millis(10));
+        broker.inner.last_heartbeat = Duration::ZERO;
+        broker.broker_once().unwrap();
+        let tag = Tag(0x1337);
+        broker.inner.llmp_out.send_buf(tag, &[1]).unwrap();
+
+        // The heartbeat is consumed, only the real message is handed out
+        let (_sender_id, tag2, buf) = client.recv_buf_blocking().unwrap();
+        assert_eq!((tag, &[1_u8][..]), (tag2, buf));
+        assert!(client.receiver.last_msg_time() > before);
+        assert!(client.recv_buf().unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    fn test_llmp_page_stats() {
+        assert!(set_llmp_page_size(LLMP_MIN_PAGE_SIZE - 1).is_err());
+
+        let shmem_provider = StdShMemProvider::new().unwrap();
+        let mut client = LlmpClient::new_p2p(shmem_provider, ClientId(0)).unwrap();
+        assert_eq!(client.sender().page_stats(), LlmpPageStats::default());
+
+        // A message larger than the first page forces a new, larger, page
+        let buf = vec![0_u8; llmp_page_size()];
+        client.send_buf(Tag(0x1337), &buf).unwrap();
+        let stats = client.sender().page_stats();
+        assert_eq!(stats.pages_allocated, 1);
+        assert!(stats.bytes_allocated > llmp_page_size() as u64);
+        assert_eq!(stats.pages_reused, 0);
+    }
 }
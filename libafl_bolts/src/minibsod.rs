@@ -1,8 +1,13 @@
 //! Implements a mini-bsod generator.
-//! It dumps all important registers and prints a stacktrace.
+//! It dumps all important registers, the top of the stack, and prints a stacktrace and the memory maps.
 
-#[cfg(any(target_vendor = "apple", target_os = "openbsd"))]
+#[cfg(any(windows, target_vendor = "apple"))]
+use alloc::string::String;
+#[cfg(windows)]
+use core::ffi::c_void;
 use core::mem::size_of;
+#[cfg(target_vendor = "apple")]
+use std::ffi::CStr;
 use std::io::{BufWriter, Write};
 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
 use std::process::Command;
@@ -19,11 +24,23 @@ use mach::{
     vm_types::{mach_vm_address_t, mach_vm_size_t, natural_t},
 };
 #[cfg(windows)]
-use windows::Win32::System::Diagnostics::Debug::{CONTEXT, EXCEPTION_POINTERS};
+use windows::Win32::{
+    Foundation::{EXCEPTION_ACCESS_VIOLATION, EXCEPTION_IN_PAGE_ERROR, HMODULE},
+    System::{
+        Diagnostics::Debug::{ReadProcessMemory, CONTEXT, EXCEPTION_POINTERS},
+        ProcessStatus::{
+            EnumProcessModules, GetModuleFileNameExW, GetModuleInformation, MODULEINFO,
+        },
+        Threading::GetCurrentProcess,
+    },
+};
 
 #[cfg(unix)]
 use crate::os::unix_signals::{ucontext_t, Signal};
 
+/// The number of bytes of the stack, from the stack pointer up, in a mini-BSOD
+pub const STACK_DUMP_SIZE: usize = 256;
+
 /// Write the content of all important registers
 #[cfg(all(
     any(target_os = "linux", target_os = "android"),
@@ -175,18 +192,20 @@ pub fn dump_registers<W: Write>(
 ) -> Result<(), std::io::Error> {
     let mcontext = unsafe { &*ucontext.uc_mcontext };
     for reg in 0..29_u8 {
-        writeln!(
+        write!(
             writer,
             "x{:02}: 0x{:016x} ",
             reg, mcontext.__ss.__x[reg as usize]
         )?;
-        if reg % 4 == 3 {
+        if reg % 4 == 3 || reg == 28 {
             writeln!(writer)?;
         }
     }
     write!(writer, "fp: 0x{:016x} ", mcontext.__ss.__fp)?;
     write!(writer, "lr: 0x{:016x} ", mcontext.__ss.__lr)?;
-    write!(writer, "pc: 0x{:016x} ", mcontext.__ss.__pc)?;
+    write!(writer, "sp: 0x{:016x} ", mcontext.__ss.__sp)?;
+    writeln!(writer, "pc: 0x{:016x} ", mcontext.__ss.__pc)?;
+    writeln!(writer, "cpsr: 0x{:08x}", mcontext.__ss.__cpsr)?;
 
     Ok(())
 }
@@ -219,6 +238,9 @@ pub fn dump_registers<W: Write>(
     writeln!(writer, "rsp: {:#016x}, ", ss.__rsp)?;
     write!(writer, "rip: {:#016x}, ", ss.__rip)?;
     writeln!(writer, "efl: {:#016x}, ", ss.__rflags)?;
+    write!(writer, "cs : {:#06x}, ", ss.__cs)?;
+    write!(writer, "fs : {:#06x}, ", ss.__fs)?;
+    writeln!(writer, "gs : {:#06x}", ss.__gs)?;
 
     Ok(())
 }
@@ -478,6 +500,12 @@ pub fn dump_registers<W: Write>(
     writeln!(writer, "rsp: {:#018x}, ", context.Rsp)?;
     write!(writer, "rip: {:#018x}, ", context.Rip)?;
     writeln!(writer, "efl: {:#018x}", context.EFlags)?;
+    write!(writer, "cs : {:#06x}, ", context.SegCs)?;
+    write!(writer, "ss : {:#06x}, ", context.SegSs)?;
+    write!(writer, "ds : {:#06x}, ", context.SegDs)?;
+    write!(writer, "es : {:#06x}, ", context.SegEs)?;
+    write!(writer, "fs : {:#06x}, ", context.SegFs)?;
+    writeln!(writer, "gs : {:#06x}", context.SegGs)?;
 
     Ok(())
 }
@@ -499,6 +527,12 @@ pub fn dump_registers<W: Write>(
     writeln!(writer, "ebp: {:#010x}, ", context.Ebp)?;
     write!(writer, "eip: {:#010x}, ", context.Eip)?;
     writeln!(writer, "efl: {:#010x} ", context.EFlags)?;
+    write!(writer, "cs : {:#06x}, ", context.SegCs)?;
+    write!(writer, "ss : {:#06x}, ", context.SegSs)?;
+    write!(writer, "ds : {:#06x}, ", context.SegDs)?;
+    write!(writer, "es : {:#06x}, ", context.SegEs)?;
+    write!(writer, "fs : {:#06x}, ", context.SegFs)?;
+    writeln!(writer, "gs : {:#06x}", context.SegGs)?;
     Ok(())
 }
 
@@ -525,6 +559,7 @@ pub fn dump_registers<W: Write>(
     writeln!(writer, "lr : 0x{:016x} ", unsafe {
         context.Anonymous.Anonymous.Lr
     })?;
+    writeln!(writer, "cpsr: 0x{:08x}", context.Cpsr)?;
 
     Ok(())
 }
@@ -677,10 +712,15 @@ fn write_crash<W: Write>(
     ucontext: &ucontext_t,
 ) -> Result<(), std::io::Error> {
     let mcontext = unsafe { &*ucontext.uc_mcontext };
+    // The Mach exception the kernel turned into the signal
     writeln!(
         writer,
-        "Received signal {} at 0x{:016x}, fault address: 0x{:016x}",
-        signal, mcontext.__ss.__pc, mcontext.__es.__far
+        "Received signal {} at 0x{:016x}, fault address: 0x{:016x}, esr: 0x{:08x}, exception: 0x{:x}",
+        signal,
+        mcontext.__ss.__pc,
+        mcontext.__es.__far,
+        mcontext.__es.__esr,
+        mcontext.__es.__exception
     )?;
 
     Ok(())
@@ -850,24 +890,34 @@ fn write_crash<W: Write>(
     writer: &mut BufWriter<W>,
     exception_pointers: *mut EXCEPTION_POINTERS,
 ) -> Result<(), std::io::Error> {
-    // TODO add fault addr for other platforms.
-    unsafe {
+    let Some(record) = (unsafe { exception_pointers.as_ref() })
+        .and_then(|pointers| unsafe { pointers.ExceptionRecord.as_ref() })
+    else {
+        writeln!(writer, "Received an exception without a record")?;
+        return Ok(());
+    };
+    writeln!(
+        writer,
+        "Received exception {:0x} at address {:x}",
+        record.ExceptionCode.0, record.ExceptionAddress as usize
+    )?;
+    // The access violations also tell the kind of access, and the address it faulted on
+    if (record.ExceptionCode == EXCEPTION_ACCESS_VIOLATION
+        || record.ExceptionCode == EXCEPTION_IN_PAGE_ERROR)
+        && record.NumberParameters >= 2
+    {
+        let access = match record.ExceptionInformation[0] {
+            0 => "read",
+            1 => "write",
+            8 => "execute",
+            _ => "unknown access",
+        };
         writeln!(
             writer,
-            "Received exception {:0x} at address {:x}",
-            (*exception_pointers)
-                .ExceptionRecord
-                .as_mut()
-                .unwrap()
-                .ExceptionCode
-                .0,
-            (*exception_pointers)
-                .ExceptionRecord
-                .as_mut()
-                .unwrap()
-                .ExceptionAddress as usize
-        )
-    }?;
+            "Fault address: 0x{:016x} ({access})",
+            record.ExceptionInformation[1]
+        )?;
+    }
 
     Ok(())
 }
@@ -1042,15 +1092,53 @@ fn write_minibsod<W: Write>(writer: &mut BufWriter<W>) -> Result<(), std::io::Er
         let vminfo = unsafe { pvminfo.assume_init() };
         // We are only interested by the first level of the maps
         if vminfo.is_submap == 0 {
-            let i = format!("{}-{}\n", addr, addr + sz);
+            let i = format!(
+                "{:016x}-{:016x} {}\n",
+                addr,
+                addr + sz,
+                protection_flags(vminfo.protection)
+            );
             writer.write_all(&i.into_bytes())?;
         }
         addr += sz;
     }
 
+    // The regions have no paths, the loaded images tell where the code comes from
+    writeln!(writer, "{:━^100}", " MODULES ")?;
+    for image in 0..unsafe { libc::_dyld_image_count() } {
+        let header = unsafe { libc::_dyld_get_image_header(image) };
+        let slide = unsafe { libc::_dyld_get_image_vmaddr_slide(image) };
+        let name = unsafe { libc::_dyld_get_image_name(image) };
+        if header.is_null() || name.is_null() {
+            continue;
+        }
+        let name = unsafe { CStr::from_ptr(name) };
+        writeln!(
+            writer,
+            "{:016x} (slide 0x{slide:x}) {}",
+            header as usize,
+            name.to_string_lossy()
+        )?;
+    }
+
     Ok(())
 }
 
+/// The `rwx` flags of a mach `vm_prot_t`
+#[cfg(target_vendor = "apple")]
+fn protection_flags(protection: mach::vm_prot::vm_prot_t) -> String {
+    use mach::vm_prot::{VM_PROT_EXECUTE, VM_PROT_READ, VM_PROT_WRITE};
+
+    [
+        (VM_PROT_READ, 'r'),
+        (VM_PROT_WRITE, 'w'),
+        (VM_PROT_EXECUTE, 'x'),
+    ]
+    .iter()
+    .map(|(flag, c)| if protection & flag == 0 { '-' } else { *c })
+    .collect()
+}
+
 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
 fn write_minibsod<W: Write>(writer: &mut BufWriter<W>) -> Result<(), std::io::Error> {
     let pid = format!("{}", unsafe { libc::getpid() });
@@ -1088,12 +1176,54 @@ fn write_minibsod<W: Write>(writer: &mut BufWriter<W>) -> Result<(), std::io::Er
     Ok(())
 }
 
+#[cfg(windows)]
+fn write_minibsod<W: Write>(writer: &mut BufWriter<W>) -> Result<(), std::io::Error> {
+    let process = unsafe { GetCurrentProcess() };
+    let mut modules = vec![HMODULE::default(); 1024];
+    let mut needed = 0_u32;
+    let size = u32::try_from(modules.len() * size_of::<HMODULE>()).unwrap();
+    if unsafe { EnumProcessModules(process, modules.as_mut_ptr(), size, &raw mut needed) }.is_err()
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+    let count = (needed as usize / size_of::<HMODULE>()).min(modules.len());
+
+    let mut name = [0_u16; 1024];
+    for module in &modules[..count] {
+        let mut info = MODULEINFO::default();
+        if unsafe {
+            GetModuleInformation(
+                process,
+                *module,
+                &raw mut info,
+                size_of::<MODULEINFO>() as u32,
+            )
+        }
+        .is_err()
+        {
+            continue;
+        }
+        let len = unsafe { GetModuleFileNameExW(process, *module, &mut name) } as usize;
+        let start = info.lpBaseOfDll as usize;
+        writeln!(
+            writer,
+            "{:016x}-{:016x} {}",
+            start,
+            start + info.SizeOfImage as usize,
+            String::from_utf16_lossy(&name[..len])
+        )?;
+    }
+
+    Ok(())
+}
+
 #[cfg(not(any(
     target_os = "freebsd",
     target_os = "openbsd",
     target_os = "netbsd",
     target_os = "haiku",
     target_vendor = "apple",
+    windows,
     any(target_os = "linux", target_os = "android"),
     any(target_os = "solaris", target_os = "illumos"),
 )))]
@@ -1103,6 +1233,211 @@ fn write_minibsod<W: Write>(writer: &mut BufWriter<W>) -> Result<(), std::io::Er
     Ok(())
 }
 
+/// The stack pointer of the crashed thread
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    target_arch = "x86_64"
+))]
+#[allow(clippy::unnecessary_wraps, clippy::cast_sign_loss)]
+fn stack_pointer(ucontext: &ucontext_t) -> Option<usize> {
+    Some(ucontext.uc_mcontext.gregs[libc::REG_RSP as usize] as usize)
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(
+    any(target_os = "linux", target_os = "android"),
+    target_arch = "aarch64"
+))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(ucontext: &ucontext_t) -> Option<usize> {
+    Some(ucontext.uc_mcontext.sp as usize)
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(target_vendor = "apple", target_arch = "aarch64"))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(ucontext: &ucontext_t) -> Option<usize> {
+    Some(unsafe { (*ucontext.uc_mcontext).__ss.__sp } as usize)
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(target_vendor = "apple", target_arch = "x86_64"))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(ucontext: &ucontext_t) -> Option<usize> {
+    Some(unsafe { (*ucontext.uc_mcontext).__ss.__rsp } as usize)
+}
+
+/// The stack pointer of the crashed thread, unknown on this platform
+#[cfg(all(
+    unix,
+    not(any(
+        all(
+            any(target_os = "linux", target_os = "android", target_vendor = "apple"),
+            target_arch = "x86_64"
+        ),
+        all(
+            any(target_os = "linux", target_os = "android", target_vendor = "apple"),
+            target_arch = "aarch64"
+        ),
+    ))
+))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(_ucontext: &ucontext_t) -> Option<usize> {
+    None
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(windows, target_arch = "x86_64"))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(context: &CONTEXT) -> Option<usize> {
+    Some(context.Rsp as usize)
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(windows, target_arch = "x86"))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(context: &CONTEXT) -> Option<usize> {
+    Some(context.Esp as usize)
+}
+
+/// The stack pointer of the crashed thread
+#[cfg(all(windows, target_arch = "aarch64"))]
+#[allow(clippy::unnecessary_wraps)]
+fn stack_pointer(context: &CONTEXT) -> Option<usize> {
+    Some(context.Sp as usize)
+}
+
+/// Copies the memory at `addr` to `buf`, without faulting on unmapped memory.
+/// Returns the number of bytes read, 0 on failure.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_memory(addr: usize, buf: &mut [u8]) -> usize {
+    let local = libc::iovec {
+        iov_base: buf.as_mut_ptr().cast(),
+        iov_len: buf.len(),
+    };
+    let remote = libc::iovec {
+        iov_base: addr as *mut libc::c_void,
+        iov_len: buf.len(),
+    };
+    let read = unsafe {
+        libc::process_vm_readv(libc::getpid(), &raw const local, 1, &raw const remote, 1, 0)
+    };
+    usize::try_from(read).unwrap_or(0)
+}
+
+/// Copies the memory at `addr` to `buf`, without faulting on unmapped memory.
+/// Returns the number of bytes read, 0 on failure.
+#[cfg(target_vendor = "apple")]
+fn read_memory(addr: usize, buf: &mut [u8]) -> usize {
+    let mut read: mach_vm_size_t = 0;
+    let ret = unsafe {
+        mach::vm::mach_vm_read_overwrite(
+            mach_task_self(),
+            addr as mach_vm_address_t,
+            buf.len() as mach_vm_size_t,
+            buf.as_mut_ptr() as mach_vm_address_t,
+            &mut read,
+        )
+    };
+    if ret == libc::KERN_SUCCESS {
+        read as usize
+    } else {
+        0
+    }
+}
+
+/// Copies the memory at `addr` to `buf`, without faulting on unmapped memory.
+/// Returns the number of bytes read, 0 on failure.
+#[cfg(windows)]
+fn read_memory(addr: usize, buf: &mut [u8]) -> usize {
+    let mut read = 0;
+    let ret = unsafe {
+        ReadProcessMemory(
+            GetCurrentProcess(),
+            addr as *const c_void,
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+            Some(&raw mut read),
+        )
+    };
+    if ret.is_ok() {
+        read
+    } else {
+        0
+    }
+}
+
+/// Reading the memory of the own process is not supported on this platform
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    windows
+)))]
+fn read_memory(_addr: usize, _buf: &mut [u8]) -> usize {
+    0
+}
+
+/// Writes the top [`STACK_DUMP_SIZE`] bytes of the stack, from the stack pointer `sp` up, in pointer-sized words
+fn write_stack<W: Write>(writer: &mut BufWriter<W>, sp: usize) -> Result<(), std::io::Error> {
+    let mut stack = [0_u8; STACK_DUMP_SIZE];
+    let mut len = read_memory(sp, &mut stack);
+    if len == 0 {
+        // The stack may end within the dump, stay on the page of the stack pointer
+        let page_left = 0x1000 - (sp & 0xfff);
+        len = read_memory(sp, &mut stack[..page_left.min(STACK_DUMP_SIZE)]);
+    }
+    if len == 0 {
+        writeln!(writer, "Couldn't read the stack at 0x{sp:016x}")?;
+        return Ok(());
+    }
+
+    for (idx, line) in stack[..len].chunks(16).enumerate() {
+        write!(writer, "0x{:016x}:", sp + idx * 16)?;
+        for word in line.chunks(size_of::<usize>()) {
+            match <[u8; size_of::<usize>()]>::try_from(word) {
+                Ok(word) => write!(
+                    writer,
+                    " {:0width$x}",
+                    usize::from_ne_bytes(word),
+                    width = 2 * size_of::<usize>()
+                )?,
+                Err(_) => {
+                    for byte in word {
+                        write!(writer, " {byte:02x}")?;
+                    }
+                }
+            }
+        }
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the registers and the top of the stack of `ucontext`.
+///
+/// Unlike [`generate_minibsod`], this neither captures a backtrace nor reads the memory maps, so it does not allocate.
+/// Given a writer that does not allocate either, it can be called from a signal handler.
+#[cfg(unix)]
+#[allow(clippy::non_ascii_literal)]
+pub fn dump_context<W: Write>(
+    writer: &mut BufWriter<W>,
+    ucontext: &ucontext_t,
+) -> Result<(), std::io::Error> {
+    #[cfg(target_pointer_width = "64")]
+    {
+        writeln!(writer, "{:━^100}", " REGISTERS ")?;
+        dump_registers(writer, ucontext)?;
+    }
+
+    if let Some(sp) = stack_pointer(ucontext) {
+        writeln!(writer, "{:━^100}", " STACK ")?;
+        write_stack(writer, sp)?;
+    }
+    Ok(())
+}
+
 /// Generates a mini-BSOD given a signal and context.
 #[cfg(unix)]
 #[allow(clippy::non_ascii_literal, clippy::too_many_lines)]
@@ -1115,12 +1450,7 @@ pub fn generate_minibsod<W: Write>(
     writeln!(writer, "{:━^100}", " CRASH ")?;
     if let Some(uctx) = ucontext {
         write_crash(writer, signal, uctx)?;
-
-        #[cfg(target_pointer_width = "64")]
-        {
-            writeln!(writer, "{:━^100}", " REGISTERS ")?;
-            dump_registers(writer, uctx)?;
-        }
+        dump_context(writer, uctx)?;
     } else {
         writeln!(writer, "Received signal {signal}")?;
     }
@@ -1143,10 +1473,16 @@ pub fn generate_minibsod<W: Write>(
 ) -> Result<(), std::io::Error> {
     writeln!(writer, "{:━^100}", " CRASH ")?;
     write_crash(writer, exception_pointers)?;
-    writeln!(writer, "{:━^100}", " REGISTERS ")?;
-    dump_registers(writer, unsafe {
-        (*exception_pointers).ContextRecord.as_mut().unwrap()
-    })?;
+    if let Some(context) = unsafe { exception_pointers.as_ref() }
+        .and_then(|pointers| unsafe { pointers.ContextRecord.as_ref() })
+    {
+        writeln!(writer, "{:━^100}", " REGISTERS ")?;
+        dump_registers(writer, context)?;
+        if let Some(sp) = stack_pointer(context) {
+            writeln!(writer, "{:━^100}", " STACK ")?;
+            write_stack(writer, sp)?;
+        }
+    }
     writeln!(writer, "{:━^100}", " BACKTRACE ")?;
     writeln!(writer, "{:?}", backtrace::Backtrace::new())?;
     writeln!(writer, "{:━^100}", " MAPS ")?;
@@ -1157,9 +1493,13 @@ pub fn generate_minibsod<W: Write>(
 #[cfg(test)]
 mod tests {
 
-    use std::io::{stdout, BufWriter};
+    use alloc::{format, string::String, vec::Vec};
+    use std::io::{stdout, BufWriter, Write};
 
-    use crate::{minibsod::dump_registers, os::unix_signals::ucontext};
+    use crate::{
+        minibsod::{dump_context, dump_registers, write_stack},
+        os::unix_signals::ucontext,
+    };
 
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -1168,6 +1508,38 @@ mod tests {
         let mut writer = BufWriter::new(stdout());
         dump_registers(&mut writer, &ucontext).unwrap();
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+    fn test_write_stack() {
+        let words = [0x4141_4141_usize; 8];
+        let mut dump = Vec::new();
+        {
+            let mut writer = BufWriter::new(&mut dump);
+            write_stack(&mut writer, words.as_ptr() as usize).unwrap();
+            writer.flush().unwrap();
+        }
+        let dump = String::from_utf8(dump).unwrap();
+        assert!(dump.contains(&format!("{:016x}:", words.as_ptr() as usize)));
+        assert!(dump.contains("41414141 "));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(any(target_os = "linux", target_os = "android", target_vendor = "apple"))]
+    fn test_dump_context_into_buffer() {
+        let ucontext = ucontext().unwrap();
+        let mut dump = [0_u8; 8192];
+        let mut remaining = &mut dump[..];
+        {
+            let mut writer = BufWriter::with_capacity(0, &mut remaining);
+            dump_context(&mut writer, &ucontext).unwrap();
+        }
+        let len = 8192 - remaining.len();
+        let dump = core::str::from_utf8(&dump[..len]).unwrap();
+        assert!(dump.contains(" STACK "));
+    }
 }
 
 #[cfg(windows)]
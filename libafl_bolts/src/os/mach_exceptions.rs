@@ -0,0 +1,366 @@
+//! Mach exception handling for Apple targets.
+//!
+//! On `iOS` and `MacOS`, a fault is first raised as a Mach exception, and only becomes a BSD signal
+//! once no exception port handled it. The system crash reporter registers such a port, which slows
+//! every crash down considerably and, on `iOS`, may kill the process before our signal handler runs.
+//!
+//! The [`MachExceptionHandler`] takes over the task exception ports: it records the exception,
+//! with the faulting address, and declines it, so that the signal reaches the `LibAFL` signal handlers right away.
+//! Install it after forking, as the handling thread does not survive a `fork`.
+
+use core::{mem, ptr};
+use std::{
+    sync::Mutex,
+    thread::{self, JoinHandle},
+};
+
+use crate::Error;
+
+#[allow(non_camel_case_types)]
+type mach_port_t = u32;
+#[allow(non_camel_case_types)]
+type kern_return_t = i32;
+
+const KERN_SUCCESS: kern_return_t = 0;
+const KERN_FAILURE: kern_return_t = 5;
+
+const MACH_PORT_NULL: mach_port_t = 0;
+const MACH_PORT_RIGHT_RECEIVE: u32 = 1;
+const MACH_MSG_TYPE_MAKE_SEND: u32 = 20;
+
+const MACH_SEND_MSG: i32 = 1;
+const MACH_RCV_MSG: i32 = 2;
+const MACH_MSG_TIMEOUT_NONE: u32 = 0;
+
+/// The bad memory accesses, e.g. `SIGSEGV` and `SIGBUS`
+pub const EXC_BAD_ACCESS: i32 = 1;
+/// The illegal instructions, e.g. `SIGILL`
+pub const EXC_BAD_INSTRUCTION: i32 = 2;
+/// The arithmetic exceptions, e.g. `SIGFPE`
+pub const EXC_ARITHMETIC: i32 = 3;
+/// The breakpoints and traps, e.g. `SIGTRAP` from `__builtin_trap`
+pub const EXC_BREAKPOINT: i32 = 6;
+
+const EXC_MASK_CRASHES: u32 = (1 << EXC_BAD_ACCESS)
+    | (1 << EXC_BAD_INSTRUCTION)
+    | (1 << EXC_ARITHMETIC)
+    | (1 << EXC_BREAKPOINT);
+
+const EXCEPTION_DEFAULT: i32 = 1;
+/// Send 64 bit codes, the subcode of a bad access being the faulting address. `0x8000_0000`
+const MACH_EXCEPTION_CODES: i32 = i32::MIN;
+
+#[cfg(target_arch = "aarch64")]
+const THREAD_STATE_NONE: i32 = 5;
+#[cfg(not(target_arch = "aarch64"))]
+const THREAD_STATE_NONE: i32 = 13;
+
+/// The capacity for the saved exception ports, larger than `EXC_TYPES_COUNT`
+const MAX_SAVED_PORTS: usize = 32;
+
+extern "C" {
+    static mach_task_self_: mach_port_t;
+
+    fn mach_port_allocate(task: mach_port_t, right: u32, name: *mut mach_port_t) -> kern_return_t;
+    fn mach_port_insert_right(
+        task: mach_port_t,
+        name: mach_port_t,
+        right: mach_port_t,
+        right_type: u32,
+    ) -> kern_return_t;
+    fn mach_port_mod_refs(
+        task: mach_port_t,
+        name: mach_port_t,
+        right: u32,
+        delta: i32,
+    ) -> kern_return_t;
+    fn mach_port_deallocate(task: mach_port_t, name: mach_port_t) -> kern_return_t;
+    fn task_swap_exception_ports(
+        task: mach_port_t,
+        exception_mask: u32,
+        new_port: mach_port_t,
+        behavior: i32,
+        new_flavor: i32,
+        masks: *mut u32,
+        masks_cnt: *mut u32,
+        old_handlers: *mut mach_port_t,
+        old_behaviors: *mut i32,
+        old_flavors: *mut i32,
+    ) -> kern_return_t;
+    fn task_set_exception_ports(
+        task: mach_port_t,
+        exception_mask: u32,
+        new_port: mach_port_t,
+        behavior: i32,
+        new_flavor: i32,
+    ) -> kern_return_t;
+    fn mach_msg(
+        msg: *mut MachMsgHeader,
+        option: i32,
+        send_size: u32,
+        rcv_size: u32,
+        rcv_name: mach_port_t,
+        timeout: u32,
+        notify: mach_port_t,
+    ) -> kern_return_t;
+}
+
+fn mach_task_self() -> mach_port_t {
+    unsafe { mach_task_self_ }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct MachMsgHeader {
+    bits: u32,
+    size: u32,
+    remote_port: mach_port_t,
+    local_port: mach_port_t,
+    voucher_port: mach_port_t,
+    id: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct MachMsgPortDescriptor {
+    name: mach_port_t,
+    pad1: u32,
+    pad2: u16,
+    disposition: u8,
+    descriptor_type: u8,
+}
+
+/// The `mach_exception_raise` request, as sent with [`MACH_EXCEPTION_CODES`]
+#[repr(C, packed(4))]
+#[derive(Debug, Default, Clone, Copy)]
+struct ExceptionRequest {
+    header: MachMsgHeader,
+    descriptor_count: u32,
+    thread: MachMsgPortDescriptor,
+    task: MachMsgPortDescriptor,
+    ndr: [u8; 8],
+    exception: i32,
+    code_count: u32,
+    code: [i64; 2],
+}
+
+/// The request with room for the trailer appended by the kernel
+#[repr(C)]
+struct ExceptionRequestBuffer {
+    request: ExceptionRequest,
+    trailer: [u8; 128],
+}
+
+#[repr(C, packed(4))]
+#[derive(Debug, Clone, Copy)]
+struct ExceptionReply {
+    header: MachMsgHeader,
+    ndr: [u8; 8],
+    ret_code: kern_return_t,
+}
+
+/// The `NDR_record` for the native byte order, sent back in replies
+const NDR_RECORD: [u8; 8] = [0, 0, 0, 0, 1, 0, 0, 0];
+
+/// A Mach exception raised in this task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MachException {
+    /// The exception type, e.g. [`EXC_BAD_ACCESS`]
+    pub exception: i32,
+    /// The code, e.g. `KERN_INVALID_ADDRESS` for a bad access
+    pub code: i64,
+    /// The subcode, the faulting address for a bad access
+    pub subcode: i64,
+}
+
+/// The last exception raised in this task
+static LAST_EXCEPTION: Mutex<Option<MachException>> = Mutex::new(None);
+
+/// The last Mach exception raised in this task, if any, recorded by the [`MachExceptionHandler`]
+#[must_use]
+pub fn last_exception() -> Option<MachException> {
+    *LAST_EXCEPTION.lock().unwrap()
+}
+
+/// The exception ports of the task before we installed ours
+#[derive(Debug)]
+struct SavedPorts {
+    count: u32,
+    masks: [u32; MAX_SAVED_PORTS],
+    handlers: [mach_port_t; MAX_SAVED_PORTS],
+    behaviors: [i32; MAX_SAVED_PORTS],
+    flavors: [i32; MAX_SAVED_PORTS],
+}
+
+/// Handles the Mach exceptions of this task. See the [module-level documentation](self).
+///
+/// The previous exception ports are restored on drop.
+#[derive(Debug)]
+pub struct MachExceptionHandler {
+    port: mach_port_t,
+    saved: SavedPorts,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MachExceptionHandler {
+    /// Take over the exception ports of this task, for the exceptions turning into crash signals
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn install() -> Result<Self, Error> {
+        let task = mach_task_self();
+        let mut port = MACH_PORT_NULL;
+        let mut saved = SavedPorts {
+            count: MAX_SAVED_PORTS as u32,
+            masks: [0; MAX_SAVED_PORTS],
+            handlers: [MACH_PORT_NULL; MAX_SAVED_PORTS],
+            behaviors: [0; MAX_SAVED_PORTS],
+            flavors: [0; MAX_SAVED_PORTS],
+        };
+
+        unsafe {
+            let ret = mach_port_allocate(task, MACH_PORT_RIGHT_RECEIVE, &mut port);
+            if ret != KERN_SUCCESS {
+                return Err(Error::unknown(format!(
+                    "Failed to allocate the Mach exception port: {ret}"
+                )));
+            }
+            let ret = mach_port_insert_right(task, port, port, MACH_MSG_TYPE_MAKE_SEND);
+            if ret != KERN_SUCCESS {
+                mach_port_mod_refs(task, port, MACH_PORT_RIGHT_RECEIVE, -1);
+                return Err(Error::unknown(format!(
+                    "Failed to insert a send right for the Mach exception port: {ret}"
+                )));
+            }
+            let ret = task_swap_exception_ports(
+                task,
+                EXC_MASK_CRASHES,
+                port,
+                EXCEPTION_DEFAULT | MACH_EXCEPTION_CODES,
+                THREAD_STATE_NONE,
+                saved.masks.as_mut_ptr(),
+                &mut saved.count,
+                saved.handlers.as_mut_ptr(),
+                saved.behaviors.as_mut_ptr(),
+                saved.flavors.as_mut_ptr(),
+            );
+            if ret != KERN_SUCCESS {
+                mach_port_deallocate(task, port);
+                mach_port_mod_refs(task, port, MACH_PORT_RIGHT_RECEIVE, -1);
+                return Err(Error::unknown(format!(
+                    "Failed to set the Mach exception ports: {ret}"
+                )));
+            }
+        }
+
+        // Restores the previous ports on drop, should the thread fail to spawn
+        let mut handler = Self {
+            port,
+            saved,
+            thread: None,
+        };
+        handler.thread = Some(
+            thread::Builder::new()
+                .name("mach_exceptions".into())
+                .spawn(move || handle_exceptions(port))?,
+        );
+        Ok(handler)
+    }
+}
+
+impl Drop for MachExceptionHandler {
+    fn drop(&mut self) {
+        let task = mach_task_self();
+        unsafe {
+            for i in 0..self.saved.count as usize {
+                task_set_exception_ports(
+                    task,
+                    self.saved.masks[i],
+                    self.saved.handlers[i],
+                    self.saved.behaviors[i],
+                    self.saved.flavors[i],
+                );
+            }
+            // Destroying the receive right ends the handling thread
+            mach_port_deallocate(task, self.port);
+            mach_port_mod_refs(task, self.port, MACH_PORT_RIGHT_RECEIVE, -1);
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Receive the exceptions on `port` until it is destroyed, and decline each of them
+#[allow(clippy::cast_possible_truncation)]
+fn handle_exceptions(port: mach_port_t) {
+    let task = mach_task_self();
+    loop {
+        let mut buffer = ExceptionRequestBuffer {
+            request: ExceptionRequest::default(),
+            trailer: [0; 128],
+        };
+        let ret = unsafe {
+            mach_msg(
+                ptr::addr_of_mut!(buffer.request.header),
+                MACH_RCV_MSG,
+                0,
+                mem::size_of::<ExceptionRequestBuffer>() as u32,
+                port,
+                MACH_MSG_TIMEOUT_NONE,
+                MACH_PORT_NULL,
+            )
+        };
+        if ret != KERN_SUCCESS {
+            log::debug!("Mach exception port closed: {ret:#x}");
+            return;
+        }
+
+        let request = buffer.request;
+        let code = request.code;
+        let exception = MachException {
+            exception: request.exception,
+            code: if request.code_count > 0 { code[0] } else { 0 },
+            subcode: if request.code_count > 1 { code[1] } else { 0 },
+        };
+        log::info!(
+            "Mach exception {} (code {:#x}, subcode {:#x})",
+            exception.exception,
+            exception.code,
+            exception.subcode
+        );
+        *LAST_EXCEPTION.lock().unwrap() = Some(exception);
+
+        unsafe {
+            mach_port_deallocate(task, request.thread.name);
+            mach_port_deallocate(task, request.task.name);
+        }
+
+        // Decline the exception, the kernel then raises the matching signal in the faulting thread
+        let header = request.header;
+        let mut reply = ExceptionReply {
+            header: MachMsgHeader {
+                bits: header.bits & 0x1f,
+                size: mem::size_of::<ExceptionReply>() as u32,
+                remote_port: header.remote_port,
+                local_port: MACH_PORT_NULL,
+                voucher_port: MACH_PORT_NULL,
+                id: header.id + 100,
+            },
+            ndr: NDR_RECORD,
+            ret_code: KERN_FAILURE,
+        };
+        let ret = unsafe {
+            mach_msg(
+                ptr::addr_of_mut!(reply.header),
+                MACH_SEND_MSG,
+                mem::size_of::<ExceptionReply>() as u32,
+                0,
+                MACH_PORT_NULL,
+                MACH_MSG_TIMEOUT_NONE,
+                MACH_PORT_NULL,
+            )
+        };
+        if ret != KERN_SUCCESS {
+            log::error!("Failed to reply to the Mach exception: {ret:#x}");
+        }
+    }
+}
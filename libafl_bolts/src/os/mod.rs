@@ -15,6 +15,9 @@ pub use unix_signals::CTRL_C_EXIT;
 #[cfg(all(unix, feature = "alloc"))]
 pub mod pipes;
 
+#[cfg(all(feature = "std", target_vendor = "apple"))]
+pub mod mach_exceptions;
+
 #[cfg(all(unix, feature = "std"))]
 use alloc::borrow::Cow;
 #[cfg(all(unix, feature = "std"))]
@@ -35,6 +38,12 @@ use libc::pid_t;
 #[cfg(all(windows, feature = "std"))]
 pub use windows_exceptions::CTRL_C_EXIT;
 
+#[cfg(all(windows, feature = "std"))]
+pub mod windows_jobs;
+
+#[cfg(all(windows, feature = "std"))]
+pub mod windows_pipes;
+
 /// A file that we keep open, pointing to /dev/null
 #[cfg(all(feature = "std", unix))]
 static NULL_FILE: OnceLock<File> = OnceLock::new();
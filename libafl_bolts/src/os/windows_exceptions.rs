@@ -16,7 +16,8 @@ pub use windows::Win32::{
     System::{
         Console::{SetConsoleCtrlHandler, CTRL_BREAK_EVENT, CTRL_C_EVENT, PHANDLER_ROUTINE},
         Diagnostics::Debug::{
-            AddVectoredExceptionHandler, UnhandledExceptionFilter, EXCEPTION_POINTERS,
+            AddVectoredContinueHandler, AddVectoredExceptionHandler, SetUnhandledExceptionFilter,
+            UnhandledExceptionFilter, EXCEPTION_POINTERS,
         },
         Threading::{IsProcessorFeaturePresent, PROCESSOR_FEATURE_ID},
     },
@@ -219,9 +220,11 @@ pub enum ExceptionCode {
 pub static CRASH_EXCEPTIONS: &[ExceptionCode] = &[
     ExceptionCode::AccessViolation,
     ExceptionCode::ArrayBoundsExceeded,
+    ExceptionCode::DatatypeMisalignment,
     ExceptionCode::FloatDivideByZero,
     ExceptionCode::GuardPageViolation,
     ExceptionCode::IllegalInstruction,
+    ExceptionCode::IllegalFloatContext,
     ExceptionCode::InPageError,
     ExceptionCode::IntegerDivideByZero,
     ExceptionCode::InvalidHandle,
@@ -230,9 +233,27 @@ pub static CRASH_EXCEPTIONS: &[ExceptionCode] = &[
     ExceptionCode::StackOverflow,
     ExceptionCode::HeapCorruption,
     ExceptionCode::StackBufferOverrun,
+    ExceptionCode::FatalAppExit,
+    ExceptionCode::InvalidCruntimeParameter,
+    ExceptionCode::InvalidParameter,
     ExceptionCode::AssertionFailure,
 ];
 
+/// Exceptions that are only crashes if the target does not handle them, such as C++ exceptions.
+/// They are reported by the unhandled exception filter, never on first chance.
+pub static LAST_CHANCE_EXCEPTIONS: &[ExceptionCode] =
+    &[ExceptionCode::CppEhException, ExceptionCode::ClrException];
+
+/// Exceptions the target cannot meaningfully continue from.
+/// If any other handler resumes execution after one of them, the vectored continue handler still reports it.
+pub static FATAL_EXCEPTIONS: &[ExceptionCode] = &[
+    ExceptionCode::StackBufferOverrun,
+    ExceptionCode::FatalAppExit,
+    ExceptionCode::NoncontinuableException,
+    ExceptionCode::HeapCorruption,
+    ExceptionCode::InvalidCruntimeParameter,
+];
+
 impl PartialEq for ExceptionCode {
     fn eq(&self, other: &Self) -> bool {
         *self as i32 == *other as i32
@@ -435,10 +456,17 @@ pub trait ExceptionHandler {
     );
     /// Return a list of exceptions to handle
     fn exceptions(&self) -> Vec<ExceptionCode>;
+    /// Return a list of exceptions to handle only once they are unhandled by the target,
+    /// see [`LAST_CHANCE_EXCEPTIONS`]
+    fn last_chance_exceptions(&self) -> Vec<ExceptionCode> {
+        Vec::new()
+    }
 }
 
 struct HandlerHolder {
     handler: UnsafeCell<*mut dyn ExceptionHandler>,
+    /// Only handle the exception once it is unhandled
+    last_chance: bool,
 }
 
 pub const EXCEPTION_HANDLERS_SIZE: usize = 96;
@@ -455,15 +483,47 @@ static mut EXCEPTION_HANDLERS: [Option<HandlerHolder>; EXCEPTION_HANDLERS_SIZE]
     None, None, None, None, None, None, None, None, None, None, None, None, None, None, None, None,
 ];
 
+/// The exception record we last dispatched, so that the continue handler does not report it twice
+static mut LAST_DISPATCHED_RECORD: usize = 0;
+
+/// How an exception arrived at our handlers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dispatch {
+    /// First chance, from the vectored exception handler
+    FirstChance,
+    /// After another handler resumed execution, from the vectored continue handler
+    Continued,
+    /// Unhandled by the target, from the unhandled exception filter
+    LastChance,
+}
+
 unsafe fn internal_handle_exception(
     exception_code: ExceptionCode,
     exception_pointers: *mut EXCEPTION_POINTERS,
+) -> i32 {
+    internal_dispatch_exception(exception_code, exception_pointers, Dispatch::FirstChance)
+}
+
+unsafe fn internal_dispatch_exception(
+    exception_code: ExceptionCode,
+    exception_pointers: *mut EXCEPTION_POINTERS,
+    dispatch: Dispatch,
 ) -> i32 {
     let index = EXCEPTION_CODES_MAPPING
         .iter()
         .position(|x| *x == exception_code)
         .unwrap();
+    if let Some(pointers) = exception_pointers.as_ref() {
+        write_volatile(
+            &raw mut LAST_DISPATCHED_RECORD,
+            pointers.ExceptionRecord as usize,
+        );
+    }
     if let Some(handler_holder) = &EXCEPTION_HANDLERS[index] {
+        if handler_holder.last_chance && dispatch == Dispatch::FirstChance {
+            // Let the target handle it first
+            return EXCEPTION_CONTINUE_SEARCH;
+        }
         log::info!(
             "{:?}: Handling exception {}",
             std::process::id(),
@@ -503,9 +563,94 @@ pub unsafe extern "system" fn handle_exception(
         .ExceptionCode;
     let exception_code = From::from(code.0);
     log::info!("Received exception; code: {}", exception_code);
+    if exception_code == ExceptionCode::StackOverflow {
+        return handle_stack_overflow(exception_pointers);
+    }
     internal_handle_exception(exception_code, exception_pointers)
 }
 
+/// Handles a stack overflow on a fresh thread, since the faulting thread has (almost) no stack left.
+///
+/// If the handler returns, the guard page of the faulting thread gets reset, so the next overflow is caught again.
+unsafe fn handle_stack_overflow(exception_pointers: *mut EXCEPTION_POINTERS) -> c_long {
+    let pointers = exception_pointers as usize;
+    let res = std::thread::Builder::new()
+        .name("libafl_stack_overflow".into())
+        .stack_size(STACK_OVERFLOW_HANDLER_STACK_SIZE)
+        .spawn(move || {
+            internal_handle_exception(
+                ExceptionCode::StackOverflow,
+                pointers as *mut EXCEPTION_POINTERS,
+            )
+        })
+        .map(|handle| handle.join());
+    let res = match res {
+        Ok(Ok(res)) => res,
+        _ => {
+            // Could not get a new thread, try on what is left of the stack
+            internal_handle_exception(ExceptionCode::StackOverflow, exception_pointers)
+        }
+    };
+    reset_stack_guard_page();
+    res
+}
+
+/// The stack size of the thread handling stack overflows
+const STACK_OVERFLOW_HANDLER_STACK_SIZE: usize = 4 * 1024 * 1024;
+
+/// Function that is being called when another handler resumed execution after an exception (stdcall).
+/// Reports [`FATAL_EXCEPTIONS`] we have not seen on first chance.
+/// # Safety
+/// This function is unsafe because it is called by the OS
+pub unsafe extern "system" fn handle_continue(
+    exception_pointers: *mut EXCEPTION_POINTERS,
+) -> c_long {
+    let Some(record) = exception_pointers
+        .as_ref()
+        .and_then(|pointers| pointers.ExceptionRecord.as_ref())
+    else {
+        return EXCEPTION_CONTINUE_SEARCH;
+    };
+    let exception_code = ExceptionCode::from(record.ExceptionCode.0);
+    let already_dispatched =
+        ptr::read_volatile(&raw const LAST_DISPATCHED_RECORD) == ptr::from_ref(record) as usize;
+    if already_dispatched || !FATAL_EXCEPTIONS.contains(&exception_code) {
+        return EXCEPTION_CONTINUE_SEARCH;
+    }
+    log::info!("Execution continued after fatal exception {exception_code}");
+    internal_dispatch_exception(exception_code, exception_pointers, Dispatch::Continued);
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Function that is being called for exceptions the target did not handle (stdcall).
+/// # Safety
+/// This function is unsafe because it is called by the OS
+pub unsafe extern "system" fn handle_unhandled_exception(
+    exception_pointers: *const EXCEPTION_POINTERS,
+) -> c_long {
+    let exception_pointers = exception_pointers.cast_mut();
+    let Some(record) = exception_pointers
+        .as_ref()
+        .and_then(|pointers| pointers.ExceptionRecord.as_ref())
+    else {
+        return EXCEPTION_CONTINUE_SEARCH;
+    };
+    let exception_code = ExceptionCode::from(record.ExceptionCode.0);
+    log::info!("Unhandled exception; code: {exception_code}");
+    internal_dispatch_exception(exception_code, exception_pointers, Dispatch::LastChance);
+    EXCEPTION_CONTINUE_SEARCH
+}
+
+/// Restores the guard page at the end of the stack of the current thread after a stack overflow,
+/// so that the next overflow raises [`ExceptionCode::StackOverflow`] again instead of terminating the process.
+///
+/// Returns `false` if the guard page could not be restored.
+pub fn reset_stack_guard_page() -> bool {
+    // # Safety
+    // Only touches the stack guard of the current thread
+    unsafe { _resetstkoflw() != 0 }
+}
+
 /// Return `SIGIGN` this is 1 (when represented as u64)
 /// Check `https://github.com/ziglang/zig/blob/956f53beb09c07925970453d4c178c6feb53ba70/lib/libc/include/any-windows-any/signal.h#L51`
 /// # Safety
@@ -516,8 +661,38 @@ pub const unsafe fn sig_ign() -> NativeSignalHandlerType {
 }
 
 type NativeSignalHandlerType = unsafe extern "C" fn(i32);
+type InvalidParameterHandlerType =
+    unsafe extern "C" fn(*const u16, *const u16, *const u16, u32, usize);
+type PurecallHandlerType = unsafe extern "C" fn();
 extern "C" {
     pub fn signal(signum: i32, func: NativeSignalHandlerType) -> *const c_void;
+    fn _set_abort_behavior(flags: u32, mask: u32) -> u32;
+    fn _set_invalid_parameter_handler(
+        handler: Option<InvalidParameterHandlerType>,
+    ) -> Option<InvalidParameterHandlerType>;
+    fn _set_purecall_handler(handler: Option<PurecallHandlerType>) -> Option<PurecallHandlerType>;
+    fn _resetstkoflw() -> i32;
+}
+
+/// `abort()` writes a message
+const WRITE_ABORT_MSG: u32 = 0x1;
+/// `abort()` fast-fails into Windows Error Reporting, instead of raising `SIGABRT`
+const CALL_REPORTFAULT: u32 = 0x2;
+
+unsafe extern "C" fn handle_invalid_parameter(
+    _expression: *const u16,
+    _function: *const u16,
+    _file: *const u16,
+    _line: u32,
+    _reserved: usize,
+) {
+    // Without a handler, the CRT would fast-fail with `STATUS_INVALID_CRUNTIME_PARAMETER`
+    internal_handle_exception(ExceptionCode::InvalidCruntimeParameter, ptr::null_mut());
+}
+
+unsafe extern "C" fn handle_purecall() {
+    // Without a handler, the CRT would abort
+    internal_handle_exception(ExceptionCode::AssertionFailure, ptr::null_mut());
 }
 
 unsafe extern "C" fn handle_signal(_signum: i32) {
@@ -533,11 +708,20 @@ pub unsafe fn setup_exception_handler<T: 'static + ExceptionHandler>(
     handler: *mut T,
 ) -> Result<(), Error> {
     let exceptions = (*handler).exceptions();
+    let last_chance_exceptions = (*handler).last_chance_exceptions();
     let mut catch_assertions = false;
-    for exception_code in exceptions {
+    let mut catch_invalid_parameters = false;
+    let exceptions = exceptions
+        .into_iter()
+        .map(|code| (code, false))
+        .chain(last_chance_exceptions.into_iter().map(|code| (code, true)));
+    for (exception_code, last_chance) in exceptions {
         if exception_code == ExceptionCode::AssertionFailure {
             catch_assertions = true;
         }
+        if exception_code == ExceptionCode::InvalidCruntimeParameter {
+            catch_invalid_parameters = true;
+        }
         let index = EXCEPTION_CODES_MAPPING
             .iter()
             .position(|x| *x == exception_code)
@@ -546,6 +730,7 @@ pub unsafe fn setup_exception_handler<T: 'static + ExceptionHandler>(
             &raw mut EXCEPTION_HANDLERS[index],
             Some(HandlerHolder {
                 handler: UnsafeCell::new(handler as *mut dyn ExceptionHandler),
+                last_chance,
             }),
         );
     }
@@ -554,11 +739,18 @@ pub unsafe fn setup_exception_handler<T: 'static + ExceptionHandler>(
         &raw mut (EXCEPTION_HANDLERS[EXCEPTION_HANDLERS_SIZE - 1]),
         Some(HandlerHolder {
             handler: UnsafeCell::new(handler as *mut dyn ExceptionHandler),
+            last_chance: false,
         }),
     );
     compiler_fence(Ordering::SeqCst);
     if catch_assertions {
         signal(SIGABRT, handle_signal);
+        // By default, `abort()` fast-fails, which no handler can catch. Raise `SIGABRT` instead.
+        _set_abort_behavior(0, WRITE_ABORT_MSG | CALL_REPORTFAULT);
+        _set_purecall_handler(Some(handle_purecall));
+    }
+    if catch_invalid_parameters {
+        _set_invalid_parameter_handler(Some(handle_invalid_parameter));
     }
     // SetUnhandledFilter does not work with frida since the stack is changed and exception handler is lost with Stalker enabled.
     // See https://github.com/AFLplusplus/LibAFL/pull/403
@@ -569,6 +761,17 @@ pub unsafe fn setup_exception_handler<T: 'static + ExceptionHandler>(
             unsafe extern "system" fn(*mut EXCEPTION_POINTERS) -> i32,
         >(handle_exception as *const c_void)),
     );
+    AddVectoredContinueHandler(
+        0,
+        Some(core::mem::transmute::<
+            *const core::ffi::c_void,
+            unsafe extern "system" fn(*mut EXCEPTION_POINTERS) -> i32,
+        >(handle_continue as *const c_void)),
+    );
+    SetUnhandledExceptionFilter(Some(core::mem::transmute::<
+        *const core::ffi::c_void,
+        unsafe extern "system" fn(*const EXCEPTION_POINTERS) -> i32,
+    >(handle_unhandled_exception as *const c_void)));
     Ok(())
 }
 
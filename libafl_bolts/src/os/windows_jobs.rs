@@ -0,0 +1,49 @@
+//! Job objects for Windows, tying the lifetime of the child processes to their parent.
+
+use core::mem::size_of;
+
+use windows::{
+    core::PCSTR,
+    Win32::{
+        Foundation::CloseHandle,
+        System::{
+            JobObjects::{
+                AssignProcessToJobObject, CreateJobObjectA, JobObjectExtendedLimitInformation,
+                SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+                JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+            },
+            Threading::GetCurrentProcess,
+        },
+    },
+};
+
+use crate::Error;
+
+/// Puts the current process in a new job object, which kills all of its processes once the last handle to it is closed.
+///
+/// The processes spawned afterwards join the job too. Windows has no process groups to signal,
+/// so this is how they get killed when the current process exits, even if it crashes,
+/// instead of running on as orphans that keep their shared maps alive.
+/// The handle to the job is never closed: the kernel closes it when the current process exits.
+#[allow(clippy::cast_possible_truncation)]
+pub fn kill_children_on_exit() -> Result<(), Error> {
+    // # Safety
+    // FFI calls, with a job object we own, and a limit that lives on the stack for the duration of the call.
+    unsafe {
+        let job = CreateJobObjectA(None, PCSTR::null())?;
+        let mut limits = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+        limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+        let res = SetInformationJobObject(
+            job,
+            JobObjectExtendedLimitInformation,
+            (&raw const limits).cast(),
+            size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+        )
+        .and_then(|()| AssignProcessToJobObject(job, GetCurrentProcess()));
+        if let Err(err) = res {
+            let _ = CloseHandle(job);
+            return Err(err.into());
+        }
+    }
+    Ok(())
+}
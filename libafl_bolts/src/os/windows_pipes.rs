@@ -0,0 +1,118 @@
+//! Named pipes for Windows, a local transport to the llmp broker next to tcp.
+//!
+//! Policies on some hosts block or filter local tcp connections; named pipes keep working there.
+//! A [`NamedPipeListener`] serves a pipe, [`connect_named_pipe`] connects to it.
+//! Both ends of a connection are [`File`]s, read and written like a [`std::net::TcpStream`].
+
+use alloc::string::String;
+use core::ffi::CStr;
+use std::{
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::ErrorKind,
+    os::windows::io::{AsRawHandle, FromRawHandle},
+};
+
+use windows::{
+    core::{HRESULT, PCSTR},
+    Win32::{
+        Foundation::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED, HANDLE},
+        Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+        System::Pipes::{
+            ConnectNamedPipe, CreateNamedPipeA, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+        },
+    },
+};
+
+use crate::Error;
+
+/// The size of the buffers of each pipe instance, in each direction
+const PIPE_BUFFER_SIZE: u32 = 64 * 1024;
+
+/// The name of the named pipe the llmp broker on `port` serves
+#[must_use]
+pub fn llmp_pipe_name(port: u16) -> String {
+    format!(r"\\.\pipe\libafl_llmp_{port}")
+}
+
+/// The server side of a named pipe, with one pipe instance per connection
+#[derive(Debug)]
+pub struct NamedPipeListener {
+    name: CString,
+    /// The instance created by [`Self::bind`], waiting for the first client
+    first: Option<File>,
+}
+
+impl NamedPipeListener {
+    /// Creates the named pipe `name`, failing if another process already serves it
+    pub fn bind(name: &str) -> Result<Self, Error> {
+        let name = CString::new(name)
+            .map_err(|_| Error::illegal_argument(format!("Invalid pipe name {name}")))?;
+        let first = create_instance(&name, true)?;
+        Ok(Self {
+            name,
+            first: Some(first),
+        })
+    }
+
+    /// Waits for the next client to connect, and returns the connection
+    pub fn accept(&mut self) -> Result<File, Error> {
+        let pipe = match self.first.take() {
+            Some(pipe) => pipe,
+            None => create_instance(&self.name, false)?,
+        };
+        // # Safety
+        // The handle belongs to the pipe instance, which outlives the call.
+        match unsafe { ConnectNamedPipe(HANDLE(pipe.as_raw_handle()), None) } {
+            Ok(()) => Ok(pipe),
+            // The client connected between the creation of the instance and this call
+            Err(err) if err.code() == HRESULT::from_win32(ERROR_PIPE_CONNECTED.0) => Ok(pipe),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Creates a new instance of the pipe `name`.
+/// The `first` instance fails if the pipe exists already.
+fn create_instance(name: &CStr, first: bool) -> Result<File, Error> {
+    let open_mode = if first {
+        PIPE_ACCESS_DUPLEX | FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+        PIPE_ACCESS_DUPLEX
+    };
+    // # Safety
+    // The name is null-terminated, and outlives the call.
+    let handle = unsafe {
+        CreateNamedPipeA(
+            PCSTR(name.as_ptr().cast()),
+            open_mode,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            0,
+            None,
+        )?
+    };
+    // # Safety
+    // The handle was just created, nothing else owns it.
+    Ok(unsafe { File::from_raw_handle(handle.0) })
+}
+
+/// Connects to the named pipe `name`.
+///
+/// Returns `None` if nobody serves the pipe, or if all its instances are busy, so that the caller can retry later.
+#[allow(clippy::cast_possible_wrap)]
+pub fn connect_named_pipe(name: &str) -> Result<Option<File>, Error> {
+    match OpenOptions::new().read(true).write(true).open(name) {
+        Ok(pipe) => Ok(Some(pipe)),
+        Err(err)
+            if err.kind() == ErrorKind::NotFound
+                || err.raw_os_error() == Some(ERROR_PIPE_BUSY.0 as i32) =>
+        {
+            Ok(None)
+        }
+        Err(err) => Err(Error::os_error(err, format!("Failed to connect to {name}"))),
+    }
+}
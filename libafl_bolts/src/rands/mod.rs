@@ -12,6 +12,10 @@ use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 #[cfg(feature = "alloc")]
 pub mod loaded_dice;
+#[cfg(feature = "alloc")]
+pub mod streams;
+#[cfg(feature = "alloc")]
+pub use streams::{RandStreamHandle, RandStreams};
 
 #[cfg(all(not(feature = "std"), target_has_atomic = "ptr"))]
 static SEED_COUNTER: AtomicUsize = AtomicUsize::new(0);
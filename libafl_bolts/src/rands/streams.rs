@@ -0,0 +1,211 @@
+//! Named, independent random streams, all derived from a single campaign seed.
+//!
+//! Components drawing from a shared [`Rand`] perturb each other: enabling a mutator shifts every
+//! random decision taken after it, by every other component. With [`RandStreams`], each component
+//! draws from its own stream, identified by a [`RandStreamHandle`], and seeded from the campaign
+//! seed and the name of the stream only. Adding, removing or reordering components does not change
+//! the random decisions of the others, which makes A/B experiments and research runs reproducible.
+//!
+//! ```rust
+//! # extern crate libafl_bolts;
+//! use libafl_bolts::rands::{Rand, RandStreamHandle, RandStreams, StdRand};
+//!
+//! const HAVOC: RandStreamHandle = RandStreamHandle::new("havoc");
+//!
+//! let mut streams = RandStreams::<StdRand>::with_seed(1337);
+//! let first = streams.stream_mut(&HAVOC).next();
+//!
+//! let mut other = RandStreams::<StdRand>::with_seed(1337);
+//! other.stream_mut(&RandStreamHandle::new("splice")).next();
+//! assert_eq!(other.stream_mut(&HAVOC).next(), first);
+//! ```
+
+use alloc::{borrow::Cow, string::String};
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use super::{random_seed, splitmix64, Rand};
+
+/// The 64 bit FNV-1a hash of `bytes`, stable across builds and platforms, unlike [`crate::hash_std`]
+const fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    hash
+}
+
+/// Identifies a named random stream of [`RandStreams`]
+///
+/// The name is hashed once, on creation, so that looking the stream up stays cheap.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RandStreamHandle {
+    name: Cow<'static, str>,
+    key: u64,
+}
+
+impl RandStreamHandle {
+    /// Creates a new [`RandStreamHandle`] for the stream called `name`
+    #[must_use]
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            key: fnv1a64(name.as_bytes()),
+        }
+    }
+
+    /// Creates a new [`RandStreamHandle`] for the stream called `name`, such as the name of a stage
+    #[must_use]
+    pub fn with_name<N>(name: N) -> Self
+    where
+        N: Into<Cow<'static, str>>,
+    {
+        let name = name.into();
+        let key = fnv1a64(name.as_bytes());
+        Self { name, key }
+    }
+
+    /// The name of the stream
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The stable key of the stream, derived from its name
+    #[must_use]
+    pub fn key(&self) -> u64 {
+        self.key
+    }
+}
+
+impl From<String> for RandStreamHandle {
+    fn from(name: String) -> Self {
+        Self::with_name(name)
+    }
+}
+
+impl From<&'static str> for RandStreamHandle {
+    fn from(name: &'static str) -> Self {
+        Self::new(name)
+    }
+}
+
+/// Independent random streams, see the [module-level documentation](self)
+///
+/// The streams are created on first use, so that only the streams that are drawn from end up in the state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandStreams<R> {
+    seed: u64,
+    streams: HashMap<u64, R>,
+}
+
+impl<R> Default for RandStreams<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R> RandStreams<R> {
+    /// Creates new [`RandStreams`] with a random campaign seed
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_seed(random_seed())
+    }
+
+    /// Creates new [`RandStreams`] derived from the campaign `seed`
+    #[must_use]
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// The campaign seed all streams are derived from
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The seed of the stream of `handle`, for this campaign seed
+    #[must_use]
+    pub fn stream_seed(&self, handle: &RandStreamHandle) -> u64 {
+        let mut state = self.seed ^ handle.key;
+        splitmix64(&mut state)
+    }
+
+    /// Sets the campaign seed, restarting all streams from their new seeds
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.streams.clear();
+    }
+
+    /// The number of streams drawn from so far
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// If no stream has been drawn from so far
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+}
+
+impl<R> RandStreams<R>
+where
+    R: Rand + Default,
+{
+    /// The stream of `handle`, created from its seed on first use
+    pub fn stream_mut(&mut self, handle: &RandStreamHandle) -> &mut R {
+        let seed = self.stream_seed(handle);
+        self.streams.entry(handle.key).or_insert_with(|| {
+            let mut rand = R::default();
+            rand.set_seed(seed);
+            rand
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use super::{RandStreamHandle, RandStreams};
+    use crate::rands::{Rand, StdRand};
+
+    #[test]
+    fn test_rand_streams_independent() {
+        let havoc = RandStreamHandle::new("havoc");
+        let splice = RandStreamHandle::with_name(String::from("splice"));
+
+        let mut streams = RandStreams::<StdRand>::with_seed(42);
+        let expected: Vec<u64> = (0..8).map(|_| streams.stream_mut(&havoc).next()).collect();
+
+        // Drawing from, and creating, other streams does not perturb this one
+        let mut interleaved = RandStreams::<StdRand>::with_seed(42);
+        let got: Vec<u64> = (0..8)
+            .map(|_| {
+                interleaved.stream_mut(&splice).next();
+                interleaved.stream_mut(&havoc).next()
+            })
+            .collect();
+        assert_eq!(expected, got);
+        assert_eq!(interleaved.len(), 2);
+
+        let mut other = RandStreams::<StdRand>::with_seed(42);
+        assert_ne!(other.stream_mut(&splice).next(), expected[0]);
+
+        // A new campaign seed restarts the streams
+        interleaved.set_seed(43);
+        assert!(interleaved.is_empty());
+        assert_ne!(interleaved.stream_mut(&havoc).next(), expected[0]);
+        interleaved.set_seed(42);
+        assert_eq!(interleaved.stream_mut(&havoc).next(), expected[0]);
+    }
+}
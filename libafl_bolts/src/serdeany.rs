@@ -414,6 +414,14 @@ pub mod serdeany_registry {
             self.map.contains_key(type_repr)
         }
 
+        /// Returns the type name and the serialized size, in bytes, of each element in this map.
+        pub fn serialized_sizes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+            self.map.values().map(|x| {
+                let size = postcard::experimental::serialized_size(&**x).unwrap_or_default();
+                (x.type_name(), size)
+            })
+        }
+
         /// Create a new [`SerdeAnyMap`].
         #[must_use]
         pub fn new() -> Self {
@@ -742,6 +750,14 @@ pub mod serdeany_registry {
             }
         }
 
+        /// Returns the type name and the serialized size, in bytes, of each element in this map.
+        pub fn serialized_sizes(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+            self.map.values().flat_map(HashMap::values).map(|x| {
+                let size = postcard::experimental::serialized_size(&**x).unwrap_or_default();
+                (x.type_name(), size)
+            })
+        }
+
         /// Create a new `SerdeAny` map.
         #[must_use]
         pub fn new() -> Self {
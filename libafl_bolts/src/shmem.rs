@@ -631,8 +631,11 @@ pub mod unix_shmem {
     #[cfg(not(target_os = "android"))]
     pub use default::MmapShMemProvider;
 
+    use core::str::FromStr;
+
     #[cfg(doc)]
     use crate::shmem::{ShMem, ShMemProvider};
+    use crate::Error;
 
     /// Shared memory provider for Android, allocating and forwarding maps over unix domain sockets.
     #[cfg(target_os = "android")]
@@ -647,6 +650,93 @@ pub mod unix_shmem {
     #[cfg(not(target_os = "android"))]
     pub type UnixShMem = default::CommonUnixShMem;
 
+    /// The `env` variable choosing the [`HugePages`] of the providers created with [`ShMemProvider::new`],
+    /// one of `off`, `transparent` or `hugetlb`
+    pub const LIBAFL_HUGE_PAGES: &str = "LIBAFL_HUGE_PAGES";
+
+    /// The size of a huge page, the default on `x86_64` and `aarch64` with 4k pages.
+    /// Maps backed by [`HugePages::HugeTlb`] are rounded up to a multiple of it.
+    pub const HUGE_PAGE_SIZE: usize = 2 * 1024 * 1024;
+
+    /// How shared maps are backed by huge pages, which lowers the TLB pressure of large maps, such as the llmp pages
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub enum HugePages {
+        /// Regular pages
+        #[default]
+        Off,
+        /// Transparent huge pages, advised with `madvise(MADV_HUGEPAGE)`.
+        /// The kernel only follows the advice for shared maps if `/sys/kernel/mm/transparent_hugepage/shmem_enabled`
+        /// is `advise` or `always`.
+        Transparent,
+        /// Pages of the `hugetlbfs` pool, which have to be reserved beforehand, in `/proc/sys/vm/nr_hugepages`.
+        /// Allocating a map fails if the pool is exhausted.
+        HugeTlb,
+    }
+
+    impl HugePages {
+        /// Reads the [`HugePages`] from the [`LIBAFL_HUGE_PAGES`] `env` variable, [`HugePages::Off`] if it is not set
+        pub fn from_env() -> Result<Self, Error> {
+            match std::env::var(LIBAFL_HUGE_PAGES) {
+                Ok(value) => value.parse(),
+                Err(_) => Ok(Self::Off),
+            }
+        }
+
+        /// The size of a map of at least `map_size` bytes, backed by these pages
+        #[must_use]
+        pub fn map_size(self, map_size: usize) -> usize {
+            match self {
+                Self::HugeTlb => map_size.next_multiple_of(HUGE_PAGE_SIZE),
+                Self::Off | Self::Transparent => map_size,
+            }
+        }
+    }
+
+    impl FromStr for HugePages {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "off" => Ok(Self::Off),
+                "transparent" => Ok(Self::Transparent),
+                "hugetlb" => Ok(Self::HugeTlb),
+                _ => Err(Error::illegal_argument(format!(
+                    "Invalid huge pages {s}, expected off, transparent or hugetlb"
+                ))),
+            }
+        }
+    }
+
+    /// Advises the kernel to back the map by transparent huge pages.
+    /// Only logs a failure, as the map works with regular pages too.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn advise_huge_pages(map: *mut u8, map_size: usize) {
+        // # Safety
+        // The advice does not change the content of the map
+        if unsafe { libc::madvise(map.cast(), map_size, libc::MADV_HUGEPAGE) } != 0 {
+            log::warn!(
+                "Could not advise transparent huge pages for a map of {map_size} bytes: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    /// Checks that a provider supports the `huge_pages`, given whether it supports [`HugePages::HugeTlb`]
+    fn check_huge_pages(huge_pages: HugePages, hugetlb: bool) -> Result<(), Error> {
+        match huge_pages {
+            HugePages::Off => Ok(()),
+            HugePages::Transparent
+                if cfg!(any(target_os = "linux", target_os = "android")) =>
+            {
+                Ok(())
+            }
+            HugePages::HugeTlb if hugetlb => Ok(()),
+            _ => Err(Error::unsupported(format!(
+                "{huge_pages:?} huge pages are not supported by this shared memory provider on this OS"
+            ))),
+        }
+    }
+
     #[cfg(all(unix, feature = "std", not(target_os = "android")))]
     mod default {
         #[cfg(target_vendor = "apple")]
@@ -659,10 +749,14 @@ pub mod unix_shmem {
         use std::{io, process};
 
         use libc::{
-            c_int, c_uchar, close, fcntl, ftruncate, mmap, munmap, shm_open, shm_unlink, shmat,
-            shmctl, shmdt, shmget,
+            c_int, c_uchar, close, fcntl, ftruncate, mmap, munmap, shmat, shmctl, shmdt, shmget,
         };
+        #[cfg(not(target_os = "ios"))]
+        use libc::{shm_open, shm_unlink};
 
+        #[cfg(target_os = "linux")]
+        use super::advise_huge_pages;
+        use super::{check_huge_pages, HugePages};
         use crate::{
             rands::{Rand, StdRand},
             shmem::{ShMem, ShMemId, ShMemProvider},
@@ -692,6 +786,10 @@ pub mod unix_shmem {
         impl MmapShMem {
             /// Create a new [`MmapShMem`]
             /// This will *NOT* automatically delete the shmem files, meaning that it's user's responsibility to delete all `/dev/shm/libafl_*` after fuzzing
+            ///
+            /// On `iOS`, the sandbox denies [`shm_open`], so the map is backed by a file in the temp dir instead,
+            /// unlinked right away. Its file descriptor is shared through the served provider, as on `MacOS`.
+            #[cfg(not(target_os = "ios"))]
             pub fn new(map_size: usize, rand_id: u32) -> Result<Self, Error> {
                 // # Safety
                 // No user-provided potentially unsafe parameters.
@@ -766,6 +864,71 @@ pub mod unix_shmem {
                 }
             }
 
+            /// Create a new [`MmapShMem`], backed by an unlinked file in the temp dir
+            #[cfg(target_os = "ios")]
+            pub fn new(map_size: usize, rand_id: u32) -> Result<Self, Error> {
+                let file_path = std::env::temp_dir()
+                    .join(format!("libafl_{}_{}", process::id(), rand_id))
+                    .into_os_string()
+                    .into_string()
+                    .map_err(|path| {
+                        Error::illegal_state(format!("Invalid shmem file path {path:?}"))
+                    })?;
+                let file_path = std::ffi::CString::new(file_path)
+                    .map_err(|_| Error::illegal_state("Shmem file path contains a null byte"))?;
+                log::info!("{map_size} Creating shmem {} {file_path:?}", process::id());
+
+                // # Safety
+                // No user-provided potentially unsafe parameters.
+                // FFI Calls.
+                unsafe {
+                    let shm_fd = libc::open(
+                        file_path.as_ptr(),
+                        libc::O_CREAT | libc::O_RDWR | libc::O_EXCL,
+                        0o600,
+                    );
+                    if shm_fd == -1 {
+                        return Err(Error::last_os_error(format!(
+                            "Failed to open shmem file {file_path:?}",
+                        )));
+                    }
+                    // The fd keeps the file alive, no need to leave it around after we are gone
+                    libc::unlink(file_path.as_ptr());
+
+                    /* configure the size of the shared memory segment */
+                    if ftruncate(shm_fd, map_size.try_into()?) != 0 {
+                        close(shm_fd);
+                        return Err(Error::last_os_error(format!(
+                            "setup_shm(): ftruncate() failed for shmem file {file_path:?}",
+                        )));
+                    }
+
+                    /* map the shared memory segment to the address space of the process */
+                    let map = mmap(
+                        ptr::null_mut(),
+                        map_size,
+                        libc::PROT_READ | libc::PROT_WRITE,
+                        libc::MAP_SHARED,
+                        shm_fd,
+                        0,
+                    );
+                    if map == libc::MAP_FAILED || map.is_null() {
+                        close(shm_fd);
+                        return Err(Error::last_os_error(format!(
+                            "mmap() failed for shmem file {file_path:?}",
+                        )));
+                    }
+
+                    Ok(Self {
+                        filename_path: None,
+                        map: map as *mut u8,
+                        map_size,
+                        shm_fd,
+                        id: ShMemId::from_string(&format!("{shm_fd}")),
+                    })
+                }
+            }
+
             #[allow(clippy::unnecessary_wraps)]
             fn shmem_from_id_and_size(id: ShMemId, map_size: usize) -> Result<Self, Error> {
                 // # Safety
@@ -896,11 +1059,40 @@ pub mod unix_shmem {
         }
 
         /// A [`ShMemProvider`] which uses [`shm_open`] and [`mmap`] to provide shared memory mappings.
+        ///
+        /// On Linux, the maps can be backed by [`HugePages::Transparent`].
         #[cfg(unix)]
         #[derive(Clone, Debug)]
-        pub struct MmapShMemProvider {}
+        pub struct MmapShMemProvider {
+            huge_pages: HugePages,
+        }
 
         impl MmapShMemProvider {
+            /// Creates a new [`MmapShMemProvider`] backing its maps by `huge_pages`.
+            ///
+            /// The maps of [`shm_open`] live on `tmpfs`, so only [`HugePages::Transparent`] is supported, on Linux.
+            pub fn with_huge_pages(huge_pages: HugePages) -> Result<Self, Error> {
+                check_huge_pages(huge_pages, false)?;
+                Ok(Self { huge_pages })
+            }
+
+            /// The [`HugePages`] backing the maps
+            #[must_use]
+            pub fn huge_pages(&self) -> HugePages {
+                self.huge_pages
+            }
+
+            /// Advises huge pages for the `shmem`, if configured
+            #[allow(clippy::unused_self)]
+            fn advise(&self, shmem: &MmapShMem) {
+                #[cfg(target_os = "linux")]
+                if self.huge_pages == HugePages::Transparent {
+                    advise_huge_pages(shmem.map, shmem.map_size);
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = shmem;
+            }
+
             /// Creates a new shared memory mapping, which is available in other processes.
             ///
             /// Only available on UNIX systems at the moment.
@@ -958,13 +1150,16 @@ pub mod unix_shmem {
         impl ShMemProvider for MmapShMemProvider {
             type ShMem = MmapShMem;
 
+            /// Creates a new [`MmapShMemProvider`], with the [`HugePages`] of the [`super::LIBAFL_HUGE_PAGES`] `env` variable
             fn new() -> Result<Self, Error> {
-                Ok(Self {})
+                Self::with_huge_pages(HugePages::from_env()?)
             }
             fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
                 let mut rand = StdRand::with_seed(crate::rands::random_seed());
                 let id = rand.next() as u32;
-                MmapShMem::new(map_size, id)
+                let shmem = MmapShMem::new(map_size, id)?;
+                self.advise(&shmem);
+                Ok(shmem)
             }
 
             fn shmem_from_id_and_size(
@@ -972,7 +1167,9 @@ pub mod unix_shmem {
                 id: ShMemId,
                 size: usize,
             ) -> Result<Self::ShMem, Error> {
-                MmapShMem::shmem_from_id_and_size(id, size)
+                let shmem = MmapShMem::shmem_from_id_and_size(id, size)?;
+                self.advise(&shmem);
+                Ok(shmem)
             }
 
             fn release_shmem(&mut self, shmem: &mut Self::ShMem) {
@@ -996,8 +1193,14 @@ pub mod unix_shmem {
 
         impl CommonUnixShMem {
             /// Create a new shared memory mapping, using shmget/shmat
-            #[allow(unused_qualifications)]
             pub fn new(map_size: usize) -> Result<Self, Error> {
+                Self::with_huge_pages(map_size, HugePages::Off)
+            }
+
+            /// Create a new shared memory mapping, using shmget/shmat, backed by `huge_pages`.
+            /// [`HugePages::HugeTlb`] rounds the size up to a multiple of [`super::HUGE_PAGE_SIZE`].
+            #[allow(unused_qualifications)]
+            pub fn with_huge_pages(map_size: usize, huge_pages: HugePages) -> Result<Self, Error> {
                 #[cfg(any(target_os = "solaris", target_os = "illumos"))]
                 const SHM_R: libc::c_int = 0o400;
                 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
@@ -1007,15 +1210,20 @@ pub mod unix_shmem {
                 #[cfg(not(any(target_os = "solaris", target_os = "illumos")))]
                 const SHM_W: libc::c_int = libc::SHM_W;
 
+                check_huge_pages(huge_pages, cfg!(target_os = "linux"))?;
+                let map_size = huge_pages.map_size(map_size);
+                #[allow(unused_mut)]
+                let mut flags = libc::IPC_CREAT | libc::IPC_EXCL | SHM_R | SHM_W;
+                #[cfg(target_os = "linux")]
+                if huge_pages == HugePages::HugeTlb {
+                    flags |= libc::SHM_HUGETLB;
+                }
+
                 unsafe {
-                    let os_id = shmget(
-                        libc::IPC_PRIVATE,
-                        map_size,
-                        libc::IPC_CREAT | libc::IPC_EXCL | SHM_R | SHM_W,
-                    );
+                    let os_id = shmget(libc::IPC_PRIVATE, map_size, flags);
 
                     if os_id < 0_i32 {
-                        return Err(Error::unknown(format!("Failed to allocate a shared mapping of size {map_size} - check OS limits (i.e shmall, shmmax)")));
+                        return Err(Error::unknown(format!("Failed to allocate a shared mapping of size {map_size} - check OS limits (i.e shmall, shmmax, nr_hugepages)")));
                     }
 
                     let map = shmat(os_id, ptr::null(), 0) as *mut c_uchar;
@@ -1024,6 +1232,11 @@ pub mod unix_shmem {
                         return Err(Error::last_os_error("Failed to map the shared mapping"));
                     }
 
+                    #[cfg(target_os = "linux")]
+                    if huge_pages == HugePages::Transparent {
+                        advise_huge_pages(map, map_size);
+                    }
+
                     Ok(Self {
                         id: ShMemId::from_int(os_id),
                         map,
@@ -1084,9 +1297,27 @@ pub mod unix_shmem {
         }
 
         /// A [`ShMemProvider`] which uses `shmget`/`shmat`/`shmctl` to provide shared memory mappings.
+        ///
+        /// On Linux, the maps can be backed by [`HugePages`].
         #[cfg(unix)]
         #[derive(Clone, Debug)]
-        pub struct CommonUnixShMemProvider {}
+        pub struct CommonUnixShMemProvider {
+            huge_pages: HugePages,
+        }
+
+        impl CommonUnixShMemProvider {
+            /// Creates a new [`CommonUnixShMemProvider`] backing its maps by `huge_pages`
+            pub fn with_huge_pages(huge_pages: HugePages) -> Result<Self, Error> {
+                check_huge_pages(huge_pages, cfg!(target_os = "linux"))?;
+                Ok(Self { huge_pages })
+            }
+
+            /// The [`HugePages`] backing the maps
+            #[must_use]
+            pub fn huge_pages(&self) -> HugePages {
+                self.huge_pages
+            }
+        }
 
         unsafe impl Send for CommonUnixShMemProvider {}
 
@@ -1102,11 +1333,12 @@ pub mod unix_shmem {
         impl ShMemProvider for CommonUnixShMemProvider {
             type ShMem = CommonUnixShMem;
 
+            /// Creates a new [`CommonUnixShMemProvider`], with the [`HugePages`] of the [`super::LIBAFL_HUGE_PAGES`] `env` variable
             fn new() -> Result<Self, Error> {
-                Ok(Self {})
+                Self::with_huge_pages(HugePages::from_env()?)
             }
             fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
-                CommonUnixShMem::new(map_size)
+                CommonUnixShMem::with_huge_pages(map_size, self.huge_pages)
             }
 
             fn shmem_from_id_and_size(
@@ -1114,7 +1346,12 @@ pub mod unix_shmem {
                 id: ShMemId,
                 size: usize,
             ) -> Result<Self::ShMem, Error> {
-                CommonUnixShMem::shmem_from_id_and_size(id, size)
+                let shmem = CommonUnixShMem::shmem_from_id_and_size(id, size)?;
+                #[cfg(target_os = "linux")]
+                if self.huge_pages == HugePages::Transparent {
+                    advise_huge_pages(shmem.map, shmem.map_size);
+                }
+                Ok(shmem)
             }
         }
     }
@@ -1360,6 +1597,9 @@ pub mod unix_shmem {
         };
         use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
 
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        use super::advise_huge_pages;
+        use super::{check_huge_pages, HugePages};
         use crate::{
             shmem::{ShMem, ShMemId, ShMemProvider},
             Error,
@@ -1377,9 +1617,26 @@ pub mod unix_shmem {
         impl MemfdShMem {
             /// Create a new shared memory mapping, using shmget/shmat
             pub fn new(map_size: usize) -> Result<Self, Error> {
+                Self::with_huge_pages(map_size, HugePages::Off)
+            }
+
+            /// Create a new shared memory mapping, using memfd, backed by `huge_pages`.
+            /// [`HugePages::HugeTlb`] rounds the size up to a multiple of [`super::HUGE_PAGE_SIZE`].
+            pub fn with_huge_pages(map_size: usize, huge_pages: HugePages) -> Result<Self, Error> {
+                check_huge_pages(
+                    huge_pages,
+                    cfg!(any(target_os = "linux", target_os = "android")),
+                )?;
+                let map_size = huge_pages.map_size(map_size);
+                #[allow(unused_mut)]
+                let mut flags = MemFdCreateFlag::empty();
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                if huge_pages == HugePages::HugeTlb {
+                    flags |= MemFdCreateFlag::MFD_HUGETLB;
+                }
                 unsafe {
                     let c_str = CString::new("libAFL").unwrap();
-                    let Ok(fd) = memfd_create(&c_str, MemFdCreateFlag::empty()) else {
+                    let Ok(fd) = memfd_create(&c_str, flags) else {
                         return Err(Error::last_os_error("Failed to create memfd".to_string()));
                     };
                     let fd = fd.into_raw_fd();
@@ -1405,6 +1662,10 @@ pub mod unix_shmem {
                             "Failed to map the memfd mapping".to_string(),
                         ));
                     }
+                    #[cfg(any(target_os = "linux", target_os = "android"))]
+                    if huge_pages == HugePages::Transparent {
+                        advise_huge_pages(map as *mut u8, map_size);
+                    }
                     Ok(Self {
                         id: ShMemId::from_int(fd),
                         map: map as *mut u8,
@@ -1486,9 +1747,30 @@ pub mod unix_shmem {
         }
 
         /// A [`ShMemProvider`] which uses memfd to provide shared memory mappings.
+        ///
+        /// On Linux and Android, the maps can be backed by [`HugePages`].
         #[cfg(unix)]
         #[derive(Clone, Debug)]
-        pub struct MemfdShMemProvider {}
+        pub struct MemfdShMemProvider {
+            huge_pages: HugePages,
+        }
+
+        impl MemfdShMemProvider {
+            /// Creates a new [`MemfdShMemProvider`] backing its maps by `huge_pages`
+            pub fn with_huge_pages(huge_pages: HugePages) -> Result<Self, Error> {
+                check_huge_pages(
+                    huge_pages,
+                    cfg!(any(target_os = "linux", target_os = "android")),
+                )?;
+                Ok(Self { huge_pages })
+            }
+
+            /// The [`HugePages`] backing the maps
+            #[must_use]
+            pub fn huge_pages(&self) -> HugePages {
+                self.huge_pages
+            }
+        }
 
         unsafe impl Send for MemfdShMemProvider {}
 
@@ -1504,12 +1786,13 @@ pub mod unix_shmem {
         impl ShMemProvider for MemfdShMemProvider {
             type ShMem = MemfdShMem;
 
+            /// Creates a new [`MemfdShMemProvider`], with the [`HugePages`] of the [`super::LIBAFL_HUGE_PAGES`] `env` variable
             fn new() -> Result<Self, Error> {
-                Ok(Self {})
+                Self::with_huge_pages(HugePages::from_env()?)
             }
 
             fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
-                let mapping = MemfdShMem::new(map_size)?;
+                let mapping = MemfdShMem::with_huge_pages(map_size, self.huge_pages)?;
                 Ok(mapping)
             }
 
@@ -1518,7 +1801,12 @@ pub mod unix_shmem {
                 id: ShMemId,
                 size: usize,
             ) -> Result<Self::ShMem, Error> {
-                MemfdShMem::shmem_from_id_and_size(id, size)
+                let shmem = MemfdShMem::shmem_from_id_and_size(id, size)?;
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                if self.huge_pages == HugePages::Transparent {
+                    advise_huge_pages(shmem.map, shmem.map_size);
+                }
+                Ok(shmem)
             }
         }
     }
@@ -1531,15 +1819,17 @@ pub mod win32_shmem {
     use core::{
         ffi::c_void,
         fmt::{self, Debug, Formatter},
+        mem::size_of,
         ops::{Deref, DerefMut},
-        slice,
+        ptr, slice,
     };
 
     use uuid::Uuid;
     use windows::{
         core::PCSTR,
         Win32::{
-            Foundation::{CloseHandle, BOOL, HANDLE},
+            Foundation::{CloseHandle, GetLastError, BOOL, ERROR_ALREADY_EXISTS, HANDLE},
+            Security::SECURITY_ATTRIBUTES,
             System::Memory::{
                 CreateFileMappingA, MapViewOfFile, OpenFileMappingA, UnmapViewOfFile,
                 FILE_MAP_ALL_ACCESS, MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
@@ -1575,24 +1865,42 @@ pub mod win32_shmem {
     }
 
     impl Win32ShMem {
-        fn new_shmem(map_size: usize) -> Result<Self, Error> {
+        /// Creates a new mapping, backed by the paging file.
+        /// If `inheritable`, the child processes inherit its handle.
+        #[allow(clippy::cast_possible_truncation)]
+        fn new_shmem(map_size: usize, inheritable: bool) -> Result<Self, Error> {
             unsafe {
                 let uuid = Uuid::new_v4();
                 let mut map_str = format!("libafl_{}", uuid.simple());
                 let map_str_bytes = map_str.as_mut_vec();
                 map_str_bytes[19] = 0; // Trucate to size 20
+                let security_attributes = SECURITY_ATTRIBUTES {
+                    nLength: size_of::<SECURITY_ATTRIBUTES>() as u32,
+                    lpSecurityDescriptor: ptr::null_mut(),
+                    bInheritHandle: BOOL(i32::from(inheritable)),
+                };
+                // The size is passed as its high and low 32 bits, so that maps may exceed 4 GB
                 let handle = CreateFileMappingA(
                     HANDLE(INVALID_HANDLE_VALUE),
-                    None,
+                    Some(&raw const security_attributes),
                     PAGE_READWRITE,
-                    0,
+                    ((map_size as u64) >> 32) as u32,
                     map_size as u32,
                     PCSTR(map_str_bytes.as_mut_ptr()),
                 )?;
+                // Never share a mapping by accident, if the truncated name collides
+                if GetLastError() == ERROR_ALREADY_EXISTS {
+                    let _ = CloseHandle(handle);
+                    return Err(Error::illegal_state(format!(
+                        "Shared memory {} exists already",
+                        String::from_utf8_lossy(map_str_bytes)
+                    )));
+                }
 
                 let map =
                     MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, map_size).Value as *mut u8;
                 if map.is_null() {
+                    let _ = CloseHandle(handle);
                     return Err(Error::unknown(format!(
                         "Cannot map shared memory {}",
                         String::from_utf8_lossy(map_str_bytes)
@@ -1621,6 +1929,7 @@ pub mod win32_shmem {
                 let map =
                     MapViewOfFile(handle, FILE_MAP_ALL_ACCESS, 0, 0, map_size).Value as *mut u8;
                 if map.is_null() {
+                    let _ = CloseHandle(handle);
                     return Err(Error::unknown(format!(
                         "Cannot map shared memory {}",
                         String::from_utf8_lossy(&map_str_bytes)
@@ -1636,6 +1945,15 @@ pub mod win32_shmem {
         }
     }
 
+    impl Win32ShMem {
+        /// The handle of the mapping, to pass to a child process inheriting it,
+        /// see [`Win32ShMemProvider::new_shmem_persistent`]
+        #[must_use]
+        pub fn handle(&self) -> HANDLE {
+            self.handle
+        }
+    }
+
     impl ShMem for Win32ShMem {
         fn id(&self) -> ShMemId {
             self.id
@@ -1678,6 +1996,20 @@ pub mod win32_shmem {
     #[derive(Clone, Debug)]
     pub struct Win32ShMemProvider {}
 
+    impl Win32ShMemProvider {
+        /// Creates a new shared memory mapping, whose handle is inherited by the child processes spawned afterwards.
+        ///
+        /// A mapping lives as long as a handle to it is open, so the children, such as executors,
+        /// keep it alive even if this process exits first.
+        /// They still open it by its [`ShMemId`], or use the inherited [`Win32ShMem::handle`] directly.
+        pub fn new_shmem_persistent(
+            &mut self,
+            map_size: usize,
+        ) -> Result<<Self as ShMemProvider>::ShMem, Error> {
+            Win32ShMem::new_shmem(map_size, true)
+        }
+    }
+
     impl Default for Win32ShMemProvider {
         fn default() -> Self {
             Self::new().unwrap()
@@ -1692,7 +2024,7 @@ pub mod win32_shmem {
             Ok(Self {})
         }
         fn new_shmem(&mut self, map_size: usize) -> Result<Self::ShMem, Error> {
-            Win32ShMem::new_shmem(map_size)
+            Win32ShMem::new_shmem(map_size, false)
         }
 
         fn shmem_from_id_and_size(
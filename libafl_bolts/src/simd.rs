@@ -0,0 +1,251 @@
+//! Vectorized novelty search over `u8` coverage maps, the hot loop of a map feedback.
+//!
+//! The maps are walked in chunks of [`CHUNK_LEN`] bytes. Chunks of the coverage map that are all zero cannot be novel
+//! and are skipped without touching the history map, which pays off for the large, sparse maps of big targets.
+//! The other chunks are compared with SSE2 on `x86_64`, NEON on `aarch64`, and with plain scalar code elsewhere.
+
+use alloc::vec::Vec;
+#[cfg(target_arch = "aarch64")]
+use core::arch::aarch64::{
+    uint8x16_t, vaddv_u8, vandq_u8, vceqq_u8, vget_high_u8, vget_low_u8, vld1q_u8, vmaxq_u8,
+    vmvnq_u8, vorrq_u8,
+};
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::{
+    __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_max_epu8, _mm_movemask_epi8, _mm_or_si128,
+};
+
+/// The number of bytes compared at once, one bit per byte in the novelty mask
+pub const CHUNK_LEN: usize = 64;
+
+/// How the history map and the coverage map are reduced, and compared, by the novelty search
+pub trait NoveltyKernel {
+    /// Reduces a history entry with the new entry
+    fn reduce(history: u8, item: u8) -> u8;
+
+    /// Reduces 16 history entries with the new entries
+    ///
+    /// # Safety
+    /// Needs SSE2, which all `x86_64` CPUs have.
+    #[cfg(target_arch = "x86_64")]
+    unsafe fn reduce_sse2(history: __m128i, items: __m128i) -> __m128i;
+
+    /// Reduces 16 history entries with the new entries
+    ///
+    /// # Safety
+    /// Needs NEON, which all `aarch64` CPUs have.
+    #[cfg(target_arch = "aarch64")]
+    unsafe fn reduce_neon(history: uint8x16_t, items: uint8x16_t) -> uint8x16_t;
+}
+
+/// Keeps the maximum of the entries, as `MaxMapFeedback` does
+#[derive(Debug, Clone, Copy)]
+pub struct MaxKernel;
+
+impl NoveltyKernel for MaxKernel {
+    #[inline]
+    fn reduce(history: u8, item: u8) -> u8 {
+        history.max(item)
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[inline]
+    unsafe fn reduce_sse2(history: __m128i, items: __m128i) -> __m128i {
+        _mm_max_epu8(history, items)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[inline]
+    unsafe fn reduce_neon(history: uint8x16_t, items: uint8x16_t) -> uint8x16_t {
+        vmaxq_u8(history, items)
+    }
+}
+
+/// Combines the bits of the entries, as `AflMapFeedback` does
+#[derive(Debug, Clone, Copy)]
+pub struct OrKernel;
+
+impl NoveltyKernel for OrKernel {
+    #[inline]
+    fn reduce(history: u8, item: u8) -> u8 {
+        history | item
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[inline]
+    unsafe fn reduce_sse2(history: __m128i, items: __m128i) -> __m128i {
+        _mm_or_si128(history, items)
+    }
+
+    #[cfg(target_arch = "aarch64")]
+    #[inline]
+    unsafe fn reduce_neon(history: uint8x16_t, items: uint8x16_t) -> uint8x16_t {
+        vorrq_u8(history, items)
+    }
+}
+
+/// Whether a chunk of the coverage map is all zero
+#[inline]
+fn is_zero(chunk: &[u8; CHUNK_LEN]) -> bool {
+    chunk.chunks_exact(8).fold(0, |acc, word| {
+        acc | u64::from_ne_bytes(word.try_into().unwrap())
+    }) == 0
+}
+
+/// The mask of the entries of a chunk whose reduction differs from the history, bit `i` for byte `i`
+#[inline]
+fn novelty_mask<K: NoveltyKernel>(history: &[u8; CHUNK_LEN], items: &[u8; CHUNK_LEN]) -> u64 {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let mut mask = 0;
+        for lane in 0..CHUNK_LEN / 16 {
+            // SAFETY: SSE2 is part of `x86_64`, the loads are unaligned and within the chunks
+            let same = unsafe {
+                let history = _mm_loadu_si128(history.as_ptr().add(lane * 16).cast());
+                let items = _mm_loadu_si128(items.as_ptr().add(lane * 16).cast());
+                _mm_movemask_epi8(_mm_cmpeq_epi8(K::reduce_sse2(history, items), history))
+            };
+            #[allow(clippy::cast_sign_loss)]
+            let differs = !(same as u32) & 0xffff;
+            mask |= u64::from(differs) << (lane * 16);
+        }
+        mask
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        const BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+        let mut mask = 0;
+        for lane in 0..CHUNK_LEN / 16 {
+            // SAFETY: NEON is part of `aarch64`, the loads are within the chunks
+            let differs = unsafe {
+                let history = vld1q_u8(history.as_ptr().add(lane * 16));
+                let items = vld1q_u8(items.as_ptr().add(lane * 16));
+                let differs = vmvnq_u8(vceqq_u8(K::reduce_neon(history, items), history));
+                let bits = vandq_u8(differs, vld1q_u8(BITS.as_ptr()));
+                u64::from(vaddv_u8(vget_low_u8(bits)))
+                    | (u64::from(vaddv_u8(vget_high_u8(bits))) << 8)
+            };
+            mask |= differs << (lane * 16);
+        }
+        mask
+    }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let mut mask = 0;
+        for (i, (history, item)) in history.iter().zip(items).enumerate() {
+            mask |= u64::from(K::reduce(*history, *item) != *history) << i;
+        }
+        mask
+    }
+}
+
+/// Searches the entries of `map` whose reduction with `history` differs from `history`, that is the novelties of a
+/// map feedback with a zero initial value, up to the length of the shorter map.
+///
+/// Returns on the first novelty if `novelties` is `None`, else pushes the index of each novelty to it.
+#[must_use]
+pub fn map_novelties<K: NoveltyKernel>(
+    history: &[u8],
+    map: &[u8],
+    mut novelties: Option<&mut Vec<usize>>,
+) -> bool {
+    let size = history.len().min(map.len());
+    let (history_chunks, history_rest) = history[..size].split_at(size - size % CHUNK_LEN);
+    let (map_chunks, map_rest) = map[..size].split_at(size - size % CHUNK_LEN);
+
+    let mut interesting = false;
+    for (chunk, (history, items)) in history_chunks
+        .chunks_exact(CHUNK_LEN)
+        .zip(map_chunks.chunks_exact(CHUNK_LEN))
+        .enumerate()
+    {
+        let history = history.try_into().unwrap();
+        let items = items.try_into().unwrap();
+        if is_zero(items) {
+            continue;
+        }
+        let mut mask = novelty_mask::<K>(history, items);
+        if mask == 0 {
+            continue;
+        }
+        interesting = true;
+        let Some(novelties) = novelties.as_deref_mut() else {
+            return true;
+        };
+        while mask != 0 {
+            novelties.push(chunk * CHUNK_LEN + mask.trailing_zeros() as usize);
+            mask &= mask - 1;
+        }
+    }
+
+    let offset = size - map_rest.len();
+    for (i, (history, item)) in history_rest.iter().zip(map_rest).enumerate() {
+        if K::reduce(*history, *item) != *history {
+            interesting = true;
+            match novelties.as_deref_mut() {
+                Some(novelties) => novelties.push(offset + i),
+                None => return true,
+            }
+        }
+    }
+    interesting
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{vec, vec::Vec};
+
+    use super::{map_novelties, MaxKernel, NoveltyKernel, OrKernel};
+
+    fn scalar_novelties<K: NoveltyKernel>(history: &[u8], map: &[u8]) -> Vec<usize> {
+        (0..history.len().min(map.len()))
+            .filter(|&i| K::reduce(history[i], map[i]) != history[i])
+            .collect()
+    }
+
+    #[test]
+    fn test_map_novelties() {
+        let mut history = vec![0_u8; 1000];
+        let mut map = vec![0_u8; 1003];
+        assert!(!map_novelties::<MaxKernel>(&history, &map, None));
+
+        for (i, entry) in [3, 63, 64, 130, 500, 998]
+            .into_iter()
+            .zip([1, 2, 4, 8, 16, 32])
+        {
+            map[i] = entry;
+        }
+        history[130] = 8;
+        history[500] = 0x0f;
+        // out of the history
+        map[1001] = 1;
+
+        let mut novelties = vec![];
+        assert!(map_novelties::<MaxKernel>(
+            &history,
+            &map,
+            Some(&mut novelties)
+        ));
+        assert_eq!(novelties, [3, 63, 64, 500, 998]);
+        assert_eq!(novelties, scalar_novelties::<MaxKernel>(&history, &map));
+
+        novelties.clear();
+        assert!(map_novelties::<OrKernel>(
+            &history,
+            &map,
+            Some(&mut novelties)
+        ));
+        assert_eq!(novelties, [3, 63, 64, 500, 998]);
+        assert_eq!(novelties, scalar_novelties::<OrKernel>(&history, &map));
+
+        history[500] = 0x1f;
+        novelties.clear();
+        assert!(map_novelties::<OrKernel>(
+            &history,
+            &map,
+            Some(&mut novelties)
+        ));
+        assert_eq!(novelties, [3, 63, 64, 998]);
+        assert!(map_novelties::<MaxKernel>(&history, &map, None));
+    }
+}
@@ -19,7 +19,7 @@ use ahash::RandomState;
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    shmem::{ShMem, ShMemProvider},
+    shmem::{ShMem, ShMemDescription, ShMemProvider},
     AsSlice, Error,
 };
 
@@ -95,6 +95,22 @@ where
         })
     }
 
+    /// The description of the [`ShMem`] of this [`StateRestorer`], to attach to it with [`Self::from_description`]
+    pub fn description(&self) -> ShMemDescription {
+        self.shmem.description()
+    }
+
+    /// Attach to the [`ShMem`] of an existing [`StateRestorer`], keeping its contents
+    pub fn from_description(
+        shmem_provider: &mut SP,
+        description: ShMemDescription,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            shmem: shmem_provider.shmem_from_description(description)?,
+            phantom: PhantomData,
+        })
+    }
+
     /// Create a new [`StateRestorer`].
     pub fn new(shmem: SP::ShMem) -> Self {
         let mut ret = Self {
@@ -316,6 +332,18 @@ mod tests {
         assert_eq!(restored, "hello world");
         assert!(!state_restorer.content().is_disk);
 
+        // Attaching to the map keeps the saved state
+        let attached = StateRestorer::<StdShMemProvider>::from_description(
+            &mut shmem_provider,
+            state_restorer.description(),
+        )
+        .unwrap();
+        assert_eq!(
+            attached.restore::<String>().unwrap().unwrap(),
+            "hello world"
+        );
+        drop(attached);
+
         state_restorer.reset();
 
         assert!(!state_restorer.has_content());
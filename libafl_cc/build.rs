@@ -453,6 +453,18 @@ pub const LIBAFL_CC_LLVM_VERSION: Option<usize> = None;
         true,
     );
 
+    #[cfg(feature = "cmp-coverage")]
+    build_pass(
+        bindir_path,
+        out_dir,
+        &cxxflags,
+        &ldflags,
+        src_dir,
+        "cmp-coverage-pass.cc",
+        None,
+        true,
+    );
+
     #[cfg(feature = "autotokens")]
     build_pass(
         bindir_path,
@@ -30,6 +30,9 @@ pub enum LLVMPasses {
     //CmpLogIns,
     /// The `CmpLog` pass
     CmpLogRtn,
+    /// The comparison coverage pass, calls the `cmp_coverage` runtime of `libafl_targets` on memory and string comparisons.
+    /// Add the `-cmpcov_split_integers` passes arg to also split the integer comparisons against constants into bytes.
+    CmpCoverage,
     /// The Autotoken pass
     AutoTokens,
     /// The Coverage Accouting (BB metric) pass
@@ -56,6 +59,8 @@ impl LLVMPasses {
         match self {
             LLVMPasses::CmpLogRtn => PathBuf::from(env!("OUT_DIR"))
                 .join(format!("cmplog-routines-pass.{}", dll_extension())),
+            LLVMPasses::CmpCoverage => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("cmp-coverage-pass.{}", dll_extension())),
             LLVMPasses::AutoTokens => {
                 PathBuf::from(env!("OUT_DIR")).join(format!("autotokens-pass.{}", dll_extension()))
             }
@@ -0,0 +1,328 @@
+/*
+   LibAFL - Comparison coverage LLVM pass
+   --------------------------------------------------
+
+   Copyright 2024 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   Gives coverage for the progress through comparisons against magic values,
+   in the spirit of laf-intel:
+
+   - the calls to memcmp, bcmp, strcmp, strncmp, strcasecmp and strncasecmp
+     are replaced with the __libafl_cmpcov_* wrappers of libafl_targets
+     (feature `cmp_coverage`), that log the operands for cmplog and set one
+     byte of the cmpcov map for each matching leading byte,
+   - with -cmpcov_split_integers, the equality comparisons of 16, 32 and 64 bit
+     integers against constants are split into a chain of byte comparisons,
+     so that the edges coverage sees each matching byte.
+
+   The pass runs at the end of the optimizations, before the SanitizerCoverage
+   instrumentation, which then covers the blocks of the split comparisons.
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#ifndef _WIN32
+  #include <unistd.h>
+  #include <sys/time.h>
+#endif
+
+#include <time.h>
+#include <string>
+#include <vector>
+
+#include "common-llvm.h"
+
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Instructions.h"
+#include "llvm/IR/Module.h"
+#include "llvm/IR/Verifier.h"
+#include "llvm/Support/CommandLine.h"
+#include "llvm/Support/raw_ostream.h"
+#include "llvm/Pass.h"
+
+using namespace llvm;
+
+static cl::opt<bool> SplitIntegers(
+    "cmpcov_split_integers",
+    cl::desc("Split the integer comparisons against constants into bytes"),
+    cl::init(false), cl::NotHidden);
+
+namespace {
+
+#if USE_NEW_PM
+class CmpCoverage : public PassInfoMixin<CmpCoverage> {
+ public:
+  CmpCoverage() {
+#else
+class CmpCoverage : public ModulePass {
+ public:
+  static char ID;
+  CmpCoverage() : ModulePass(ID) {
+#endif
+  }
+
+#if USE_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool runOnModule(Module &M) override;
+
+  #if LLVM_VERSION_MAJOR < 4
+  const char *getPassName() const override {
+  #else
+  StringRef getPassName() const override {
+  #endif
+    return "comparison coverage";
+  }
+#endif
+
+ private:
+  bool hookRtns(Module &M);
+  bool splitIntegerCompares(Module &M);
+};
+
+}  // namespace
+
+#if USE_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "CmpCoverage", "v0.1",
+          [](PassBuilder &PB) {
+  #if LLVM_VERSION_MAJOR <= 13
+            using OptimizationLevel = typename PassBuilder::OptimizationLevel;
+  #endif
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(CmpCoverage());
+                });
+          }};
+}
+#else
+char CmpCoverage::ID = 0;
+#endif
+
+bool CmpCoverage::hookRtns(Module &M) {
+  std::vector<CallInst *> Memcmp, Strcmp, Strncmp, Strcasecmp, Strncasecmp;
+  LLVMContext            &C = M.getContext();
+
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  IntegerType *Int64Ty = IntegerType::getInt64Ty(C);
+  PointerType *i8PtrTy = PointerType::get(Int8Ty, 0);
+
+  FunctionCallee cmpcovMemcmp = M.getOrInsertFunction(
+      "__libafl_cmpcov_memcmp", Int32Ty, i8PtrTy, i8PtrTy, Int64Ty, Int32Ty);
+  FunctionCallee cmpcovStrcmp = M.getOrInsertFunction(
+      "__libafl_cmpcov_strcmp", Int32Ty, i8PtrTy, i8PtrTy, Int32Ty);
+  FunctionCallee cmpcovStrncmp = M.getOrInsertFunction(
+      "__libafl_cmpcov_strncmp", Int32Ty, i8PtrTy, i8PtrTy, Int64Ty, Int32Ty);
+  FunctionCallee cmpcovStrcasecmp = M.getOrInsertFunction(
+      "__libafl_cmpcov_strcasecmp", Int32Ty, i8PtrTy, i8PtrTy, Int32Ty);
+  FunctionCallee cmpcovStrncasecmp =
+      M.getOrInsertFunction("__libafl_cmpcov_strncasecmp", Int32Ty, i8PtrTy,
+                            i8PtrTy, Int64Ty, Int32Ty);
+
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F)) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        CallInst *callInst = dyn_cast<CallInst>(&IN);
+        if (!callInst) { continue; }
+
+        Function *Callee = callInst->getCalledFunction();
+        if (!Callee) { continue; }
+        if (callInst->getCallingConv() != llvm::CallingConv::C) { continue; }
+
+        FunctionType *FT = Callee->getFunctionType();
+        std::string   FuncName = Callee->getName().str();
+
+        if (!FT->getReturnType()->isIntegerTy(32)) { continue; }
+
+        bool isPtrPtr = FT->getNumParams() >= 2 &&
+                        FT->getParamType(0)->isPointerTy() &&
+                        FT->getParamType(1)->isPointerTy();
+        bool isTwoArgs = isPtrPtr && FT->getNumParams() == 2;
+        bool isThreeArgs = isPtrPtr && FT->getNumParams() == 3 &&
+                           FT->getParamType(2)->isIntegerTy();
+
+        if (isThreeArgs &&
+            (!FuncName.compare("memcmp") || !FuncName.compare("bcmp"))) {
+          Memcmp.push_back(callInst);
+        } else if (isTwoArgs && !FuncName.compare("strcmp")) {
+          Strcmp.push_back(callInst);
+        } else if (isThreeArgs && !FuncName.compare("strncmp")) {
+          Strncmp.push_back(callInst);
+        } else if (isTwoArgs && !FuncName.compare("strcasecmp")) {
+          Strcasecmp.push_back(callInst);
+        } else if (isThreeArgs && !FuncName.compare("strncasecmp")) {
+          Strncasecmp.push_back(callInst);
+        }
+      }
+    }
+  }
+
+  auto replace = [&](CallInst *callInst, FunctionCallee &wrapper,
+                     bool hasLen) {
+    IRBuilder<> IRB(callInst);
+
+    std::vector<Value *> args;
+    args.push_back(IRB.CreatePointerCast(callInst->getArgOperand(0), i8PtrTy));
+    args.push_back(IRB.CreatePointerCast(callInst->getArgOperand(1), i8PtrTy));
+    if (hasLen) {
+      args.push_back(
+          IRB.CreateIntCast(callInst->getArgOperand(2), Int64Ty, false));
+    }
+    args.push_back(ConstantInt::get(Int32Ty, (uint32_t)rand()));
+
+    CallInst *wrapped = IRB.CreateCall(wrapper, args);
+    callInst->replaceAllUsesWith(wrapped);
+    callInst->eraseFromParent();
+  };
+
+  for (auto &callInst : Memcmp) {
+    replace(callInst, cmpcovMemcmp, true);
+  }
+  for (auto &callInst : Strcmp) {
+    replace(callInst, cmpcovStrcmp, false);
+  }
+  for (auto &callInst : Strncmp) {
+    replace(callInst, cmpcovStrncmp, true);
+  }
+  for (auto &callInst : Strcasecmp) {
+    replace(callInst, cmpcovStrcasecmp, false);
+  }
+  for (auto &callInst : Strncasecmp) {
+    replace(callInst, cmpcovStrncasecmp, true);
+  }
+
+  return Memcmp.size() || Strcmp.size() || Strncmp.size() ||
+         Strcasecmp.size() || Strncasecmp.size();
+}
+
+/* Split `a == C` into `a[0] == C[0] && a[1] == C[1] && ...`, one block per
+   byte, starting with the first byte in memory on little-endian targets */
+bool CmpCoverage::splitIntegerCompares(Module &M) {
+  std::vector<ICmpInst *> cmps;
+  LLVMContext            &C = M.getContext();
+  IntegerType            *Int1Ty = IntegerType::getInt1Ty(C);
+  IntegerType            *Int8Ty = IntegerType::getInt8Ty(C);
+
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F)) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        ICmpInst *cmpInst = dyn_cast<ICmpInst>(&IN);
+        if (!cmpInst || !cmpInst->isEquality()) { continue; }
+
+        IntegerType *intTy =
+            dyn_cast<IntegerType>(cmpInst->getOperand(0)->getType());
+        if (!intTy) { continue; }
+        unsigned width = intTy->getBitWidth();
+        if (width != 16 && width != 32 && width != 64) { continue; }
+
+        bool lhsConst = isa<ConstantInt>(cmpInst->getOperand(0));
+        bool rhsConst = isa<ConstantInt>(cmpInst->getOperand(1));
+        // Comparisons of two constants get folded, of two variables have no
+        // magic value to find
+        if (lhsConst == rhsConst) { continue; }
+
+        cmps.push_back(cmpInst);
+      }
+    }
+  }
+
+  for (auto &cmpInst : cmps) {
+    Value      *var = cmpInst->getOperand(0);
+    ConstantInt *cst = dyn_cast<ConstantInt>(cmpInst->getOperand(1));
+    if (!cst) {
+      var = cmpInst->getOperand(1);
+      cst = cast<ConstantInt>(cmpInst->getOperand(0));
+    }
+
+    BasicBlock *BB = cmpInst->getParent();
+    Function   *F = BB->getParent();
+    unsigned    bytes = cst->getBitWidth() / 8;
+
+    // BB now ends with an unconditional branch to end, that starts with the
+    // original comparison
+    BasicBlock *end = BB->splitBasicBlock(cmpInst, "cmpcov.end");
+    BB->getTerminator()->eraseFromParent();
+
+    IRBuilder<> EndIRB(cmpInst);
+    PHINode    *equal = EndIRB.CreatePHI(Int1Ty, bytes);
+
+    BasicBlock *cur = BB;
+    for (unsigned i = 0; i < bytes; i++) {
+      IRBuilder<> IRB(cur);
+
+      Value *shifted = i ? IRB.CreateLShr(var, i * 8) : var;
+      Value *byte = IRB.CreateTrunc(shifted, Int8Ty);
+      Value *cstByte = ConstantInt::get(
+          Int8Ty, cst->getValue().lshr(i * 8).trunc(8).getZExtValue());
+      Value *cmp = IRB.CreateICmpEQ(byte, cstByte);
+
+      if (i == bytes - 1) {
+        IRB.CreateBr(end);
+        equal->addIncoming(cmp, cur);
+      } else {
+        BasicBlock *next = BasicBlock::Create(C, "cmpcov.byte", F, end);
+        IRB.CreateCondBr(cmp, next, end);
+        equal->addIncoming(ConstantInt::getFalse(C), cur);
+        cur = next;
+      }
+    }
+
+    Value *result = equal;
+    if (cmpInst->getPredicate() == CmpInst::ICMP_NE) {
+      result = EndIRB.CreateNot(equal);
+    }
+    cmpInst->replaceAllUsesWith(result);
+    cmpInst->eraseFromParent();
+  }
+
+  return !cmps.empty();
+}
+
+#if USE_NEW_PM
+PreservedAnalyses CmpCoverage::run(Module &M, ModuleAnalysisManager &MAM) {
+#else
+bool CmpCoverage::runOnModule(Module &M) {
+#endif
+  srand(time(NULL));
+
+  bool modified = hookRtns(M);
+  if (SplitIntegers) { modified |= splitIntegerCompares(M); }
+
+  verifyModule(M);
+
+#if USE_NEW_PM
+  return modified ? PreservedAnalyses::none() : PreservedAnalyses::all();
+#else
+  return modified;
+#endif
+}
+
+#if USE_NEW_PM
+#else
+static void registerCmpCoveragePass(const PassManagerBuilder &,
+                                    legacy::PassManagerBase &PM) {
+  PM.add(new CmpCoverage());
+}
+
+static RegisterStandardPasses RegisterCmpCoveragePass(
+    PassManagerBuilder::EP_OptimizerLast, registerCmpCoveragePass);
+
+static RegisterStandardPasses RegisterCmpCoveragePass0(
+    PassManagerBuilder::EP_EnabledOnOptLevel0, registerCmpCoveragePass);
+
+static RegisterStandardPasses RegisterCmpCoveragePassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerCmpCoveragePass);
+#endif
@@ -63,6 +63,10 @@ pub enum Error {
     Unknown(String),
 }
 
+/// The `DataFlowSanitizer` ABI list of [`Configuration::DataFlowSanitizer`],
+/// keeping the native ABI of the harness entry points and of the `libafl_targets` callbacks
+pub const DFSAN_ABILIST: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/dfsan_abilist.txt");
+
 /// `LibAFL` target configuration
 #[derive(Debug, Clone)]
 pub enum Configuration {
@@ -78,6 +82,9 @@ pub enum Configuration {
     GenerateCoverageProfile,
     /// Instrumenting for cmplog/redqueen
     CmpLog,
+    /// Tracking the input bytes that reach the comparisons, with the `DataFlowSanitizer`.
+    /// The runtime is in `libafl_targets`, feature `dfsan`.
+    DataFlowSanitizer,
     /// A compound `Configuration`, made up of a list of other `Configuration`s
     Compound(Vec<Self>),
 }
@@ -103,6 +110,12 @@ impl Configuration {
                 vec!["-fsanitize-coverage=trace-pc-guard".to_string()]
             }
             Configuration::CmpLog => vec!["-fsanitize-coverage=trace-cmp".to_string()],
+            Configuration::DataFlowSanitizer => vec![
+                "-fsanitize=dataflow".to_string(),
+                format!("-fsanitize-ignorelist={DFSAN_ABILIST}"),
+                "-mllvm".to_string(),
+                "-dfsan-event-callbacks".to_string(),
+            ],
             Configuration::GenerateCoverageProfile => {
                 vec![
                     "-fprofile-instr-generate".to_string(),
@@ -154,6 +167,7 @@ impl std::str::FromStr for Configuration {
             "coverage" => Configuration::GenerateCoverageMap,
             "llvm-cov" => Configuration::GenerateCoverageProfile,
             "cmplog" => Configuration::CmpLog,
+            "dfsan" => Configuration::DataFlowSanitizer,
             _ => Configuration::Default,
         })
     }
@@ -168,6 +182,7 @@ impl std::fmt::Display for Configuration {
             Configuration::GenerateCoverageMap => write!(f, "coverage"),
             Configuration::GenerateCoverageProfile => write!(f, "llvm-cov"),
             Configuration::CmpLog => write!(f, "cmplog"),
+            Configuration::DataFlowSanitizer => write!(f, "dfsan"),
             Configuration::Compound(configurations) => {
                 let mut result: Vec<String> = vec![];
                 for configuration in configurations {
@@ -1,11 +1,17 @@
 //! This is a 'meta-package' for libafl that exposes a consistent URL and commit hash for the
-//! [`SymCC` fork](https://github.com/AFLplusplus/symcc).
+//! [`SymCC` fork](https://github.com/AFLplusplus/symcc), and the URL of [`SymQEMU`](https://github.com/eurecom-s3/symqemu)
+//! for binary-only concolic tracing.
 
 /// The URL of the `LibAFL` `SymCC` fork.
 pub const SYMCC_REPO_URL: &str = "https://github.com/AFLplusplus/symcc.git";
 /// The commit of the `LibAFL` `SymCC` fork.
 pub const SYMCC_REPO_COMMIT: &str = "1330e29d28bce706d9f7c0864da3b0a5ae218e03";
 
+/// The URL of `SymQEMU`.
+pub const SYMQEMU_REPO_URL: &str = "https://github.com/eurecom-s3/symqemu.git";
+/// The branch of `SymQEMU` that gets checked out, use [`clone_symqemu_at_version`] to pin a commit.
+pub const SYMQEMU_REPO_BRANCH: &str = "master";
+
 #[cfg(feature = "clone")]
 mod clone {
     use std::{
@@ -16,7 +22,7 @@ mod clone {
 
     use which::which;
 
-    use crate::{SYMCC_REPO_COMMIT, SYMCC_REPO_URL};
+    use crate::{SYMCC_REPO_COMMIT, SYMCC_REPO_URL, SYMQEMU_REPO_BRANCH, SYMQEMU_REPO_URL};
 
     /// Checks out the repository into the given directory with the given URL and commit hash.
     /// Any errors will trigger a panic.
@@ -56,10 +62,22 @@ mod clone {
     pub fn clone_symcc(path: &Path) {
         clone_symcc_at_version(path, SYMCC_REPO_URL, SYMCC_REPO_COMMIT);
     }
+
+    /// Checks out the `SymQEMU` repository into the given directory with the given URL and commit hash or branch.
+    /// Any errors will trigger a panic.
+    pub fn clone_symqemu_at_version(path: &Path, url: &str, commit: &str) {
+        clone_symcc_at_version(path, url, commit);
+    }
+
+    /// Checks out the `SymQEMU` repository into the given directory.
+    /// Any errors will trigger a panic.
+    pub fn clone_symqemu(path: &Path) {
+        clone_symqemu_at_version(path, SYMQEMU_REPO_URL, SYMQEMU_REPO_BRANCH);
+    }
 }
 
 #[cfg(feature = "clone")]
-pub use clone::clone_symcc;
+pub use clone::{clone_symcc, clone_symqemu, clone_symqemu_at_version};
 
 #[cfg(feature = "build")]
 mod build {
@@ -79,7 +97,51 @@ mod build {
             .build()
             .join("build")
     }
+
+    /// Builds `SymQEMU` at the given directory for the user-mode `target`, for example `x86_64`,
+    /// against the `SymCC` sources and the `SymCC` build artifact directory returned by [`build_symcc`].
+    /// Returns the path of the `symqemu-<target>` binary.
+    ///
+    /// `SymQEMU` loads the `libSymRuntime.so` of the `SymCC` build, set `LD_LIBRARY_PATH` to use another runtime.
+    /// Any errors will trigger a panic.
+    #[must_use]
+    pub fn build_symqemu(
+        path: &Path,
+        target: &str,
+        symcc_src: &Path,
+        symcc_build: &Path,
+    ) -> PathBuf {
+        use std::{fs, process::Command};
+
+        let build_dir = path.join("build");
+        fs::create_dir_all(&build_dir).expect("failed to create the SymQEMU build directory");
+
+        let status = Command::new(path.join("configure"))
+            .current_dir(&build_dir)
+            .arg("--audio-drv-list=")
+            .arg("--disable-werror")
+            .arg(format!("--target-list={target}-linux-user"))
+            .arg(format!("--symcc-source={}", symcc_src.display()))
+            .arg(format!("--symcc-build={}", symcc_build.display()))
+            .status()
+            .expect("failed to run the SymQEMU configure script");
+        assert!(status.success(), "failed to configure SymQEMU");
+
+        let status = Command::new("make")
+            .current_dir(&build_dir)
+            .arg(format!(
+                "-j{}",
+                std::thread::available_parallelism().map_or(1, usize::from)
+            ))
+            .status()
+            .expect("failed to run make for SymQEMU");
+        assert!(status.success(), "failed to build SymQEMU");
+
+        build_dir
+            .join(format!("{target}-linux-user"))
+            .join(format!("symqemu-{target}"))
+    }
 }
 
 #[cfg(feature = "build")]
-pub use build::build_symcc;
+pub use build::{build_symcc, build_symqemu};
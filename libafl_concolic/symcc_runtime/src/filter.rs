@@ -1,6 +1,10 @@
 //! [`Filter`]s are ergonomic abstractions over [`Runtime`] that facilitate filtering expressions.
 
-use std::collections::HashSet;
+use std::{collections::HashSet, ops::Range};
+
+use libafl::observers::concolic::{
+    parse_selective_symbolication, SELECTIVE_SYMBOLICATION_ENV_NAME,
+};
 
 #[allow(clippy::wildcard_imports)]
 use crate::*;
@@ -158,6 +162,47 @@ impl Filter for SelectiveSymbolication {
     }
 }
 
+/// A [`Filter`] that concretizes all input byte expressions out of the input regions set by the fuzzer in
+/// [`SELECTIVE_SYMBOLICATION_ENV_NAME`], for example with the `SymQemuExecutorBuilder` of `LibAFL`.
+/// If the variable is not set, the whole input is symbolic.
+pub struct SymbolicRegions {
+    regions: Option<Vec<Range<usize>>>,
+}
+
+impl SymbolicRegions {
+    /// Create a new [`SymbolicRegions`] symbolizing the input bytes in `regions` only
+    #[must_use]
+    pub fn new(regions: Vec<Range<usize>>) -> Self {
+        Self {
+            regions: Some(regions),
+        }
+    }
+
+    /// Create a new [`SymbolicRegions`] from the regions in [`SELECTIVE_SYMBOLICATION_ENV_NAME`]
+    ///
+    /// # Panics
+    /// Panics if the variable is set, but is not a list of offsets and ranges.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let regions = std::env::var(SELECTIVE_SYMBOLICATION_ENV_NAME)
+            .ok()
+            .map(|value| {
+                parse_selective_symbolication(&value).expect("invalid symbolic input regions")
+            });
+        Self { regions }
+    }
+}
+
+impl Filter for SymbolicRegions {
+    fn get_input_byte(&mut self, offset: usize, _value: u8) -> bool {
+        if let Some(regions) = &self.regions {
+            regions.iter().any(|region| region.contains(&offset))
+        } else {
+            true
+        }
+    }
+}
+
 /// Concretizes all floating point operations.
 pub struct NoFloat;
 
@@ -1,16 +1,76 @@
 //! Tracing of expressions in a serialized form.
 
+use std::collections::{HashMap, HashSet};
+
 pub use libafl::observers::concolic::serialization_format::StdShMemMessageFileWriter;
-use libafl::observers::concolic::SymExpr;
+use libafl::observers::concolic::{
+    SymExpr, DEDUPLICATE_CONSTRAINTS_ENV_NAME, MAX_CONSTRAINTS_PER_SITE_ENV_NAME,
+    MAX_EXPRESSION_DEPTH_ENV_NAME, ONLY_PATH_CONSTRAINTS_ENV_NAME,
+};
 
 use crate::{RSymExpr, Runtime};
 
+/// Options of the [`TracingRuntime`], mostly to keep the traces small.
+#[derive(Debug, Clone, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct TracingOptions {
+    /// Trace location information for calls, returns and basic blocks.
+    /// Tracing location information can drastically increase trace size.
+    pub trace_locations: bool,
+    /// Only trace the path constraints and the expressions they depend on.
+    /// The expressions are kept in memory until a path constraint uses them.
+    pub only_path_constraints: bool,
+    /// Concretize the expressions deeper than this
+    pub max_expression_depth: Option<usize>,
+    /// Only trace the first path constraints of each site, for example for the branch of a loop
+    pub max_constraints_per_site: Option<usize>,
+    /// Only trace a path constraint once per site, direction and call stack
+    pub deduplicate_constraints: bool,
+}
+
+impl TracingOptions {
+    /// Reads the options from the environment variables the fuzzer sets,
+    /// [`ONLY_PATH_CONSTRAINTS_ENV_NAME`], [`MAX_EXPRESSION_DEPTH_ENV_NAME`], [`MAX_CONSTRAINTS_PER_SITE_ENV_NAME`]
+    /// and [`DEDUPLICATE_CONSTRAINTS_ENV_NAME`]. Locations are not traced.
+    ///
+    /// # Panics
+    /// Panics if a limit is set, but is not a number.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let limit = |name: &str| {
+            std::env::var(name).ok().map(|value| {
+                value
+                    .parse()
+                    .unwrap_or_else(|_| panic!("{name} is not a number: {value}"))
+            })
+        };
+        Self {
+            trace_locations: false,
+            only_path_constraints: std::env::var(ONLY_PATH_CONSTRAINTS_ENV_NAME).is_ok(),
+            max_expression_depth: limit(MAX_EXPRESSION_DEPTH_ENV_NAME),
+            max_constraints_per_site: limit(MAX_CONSTRAINTS_PER_SITE_ENV_NAME),
+            deduplicate_constraints: std::env::var(DEDUPLICATE_CONSTRAINTS_ENV_NAME).is_ok(),
+        }
+    }
+}
+
 /// Traces the expressions according to the format described in [`libafl::observers::concolic::serialization_format`].
 ///
 /// The format can be read from elsewhere to perform processing of the expressions outside of the runtime.
+/// The [`TracingOptions`] filter the trace before it gets written.
 pub struct TracingRuntime {
     writer: StdShMemMessageFileWriter,
-    trace_locations: bool,
+    options: TracingOptions,
+    /// The depth of each expression, by id, if limited
+    depths: Vec<usize>,
+    /// The expressions not yet written, by id, if only tracing the path constraints
+    pending: Vec<Option<SymExpr>>,
+    /// The id in the trace of the written expressions, by id, if only tracing the path constraints
+    written: Vec<Option<RSymExpr>>,
+    constraints_per_site: HashMap<usize, usize>,
+    traced_constraints: HashSet<(usize, bool, u64)>,
+    /// The call sites and the hash of the call stack up to them
+    call_stack: Vec<(usize, u64)>,
 }
 
 impl TracingRuntime {
@@ -19,9 +79,27 @@ impl TracingRuntime {
     /// Tracing location information can drastically increase trace size. It is therefore recommended to not active this if not needed.
     #[must_use]
     pub fn new(writer: StdShMemMessageFileWriter, trace_locations: bool) -> Self {
+        Self::with_options(
+            writer,
+            TracingOptions {
+                trace_locations,
+                ..TracingOptions::default()
+            },
+        )
+    }
+
+    /// Creates the runtime, tracing using the given writer, with the given [`TracingOptions`].
+    #[must_use]
+    pub fn with_options(writer: StdShMemMessageFileWriter, options: TracingOptions) -> Self {
         Self {
             writer,
-            trace_locations,
+            options,
+            depths: Vec::new(),
+            pending: Vec::new(),
+            written: Vec::new(),
+            constraints_per_site: HashMap::new(),
+            traced_constraints: HashSet::new(),
+            call_stack: Vec::new(),
         }
     }
 
@@ -29,6 +107,101 @@ impl TracingRuntime {
     fn write_message(&mut self, message: SymExpr) -> Option<RSymExpr> {
         Some(self.writer.write_message(message).unwrap())
     }
+
+    /// Writes an expression, or keeps it until a path constraint uses it.
+    /// Returns [`None`], concretizing the expression, if it is too deep.
+    fn write_expression(&mut self, mut message: SymExpr) -> Option<RSymExpr> {
+        let depth = if let Some(max_depth) = self.options.max_expression_depth {
+            let depth = message
+                .operands_mut()
+                .into_iter()
+                .map(|op| self.depths.get(op.get()).copied().unwrap_or(0))
+                .max()
+                .unwrap_or(0)
+                + 1;
+            if depth > max_depth {
+                return None;
+            }
+            Some(depth)
+        } else {
+            None
+        };
+
+        let id = if self.options.only_path_constraints {
+            self.pending.push(Some(message));
+            self.written.push(None);
+            RSymExpr::new(self.pending.len()).unwrap()
+        } else {
+            self.writer.write_message(message).unwrap()
+        };
+
+        if let Some(depth) = depth {
+            if self.depths.len() <= id.get() {
+                self.depths.resize(id.get() + 1, 0);
+            }
+            self.depths[id.get()] = depth;
+        }
+        Some(id)
+    }
+
+    /// Writes the kept expression `id` and the expressions it depends on, returning its id in the trace
+    fn write_pending(&mut self, id: RSymExpr) -> RSymExpr {
+        let mut stack = vec![id];
+        while let Some(&top) = stack.last() {
+            let index = top.get() - 1;
+            if self.written[index].is_some() {
+                stack.pop();
+                continue;
+            }
+            let message = self.pending[index]
+                .as_mut()
+                .expect("expression neither kept nor written");
+            let missing: Vec<RSymExpr> = message
+                .operands_mut()
+                .into_iter()
+                .map(|op| *op)
+                .filter(|op| self.written[op.get() - 1].is_none())
+                .collect();
+            if missing.is_empty() {
+                let mut message = self.pending[index].take().unwrap();
+                for op in message.operands_mut() {
+                    *op = self.written[op.get() - 1].unwrap();
+                }
+                self.written[index] = Some(self.writer.write_message(message).unwrap());
+                stack.pop();
+            } else {
+                stack.extend(missing);
+            }
+        }
+        self.written[id.get() - 1].unwrap()
+    }
+
+    fn call_stack_hash(&self) -> u64 {
+        self.call_stack.last().map_or(0, |(_, hash)| *hash)
+    }
+
+    /// Whether to trace the path constraint at `site_id`, counting it if so
+    fn should_trace_constraint(&mut self, taken: bool, site_id: usize) -> bool {
+        if self.options.deduplicate_constraints
+            && !self
+                .traced_constraints
+                .insert((site_id, taken, self.call_stack_hash()))
+        {
+            return false;
+        }
+        if let Some(max_constraints) = self.options.max_constraints_per_site {
+            let count = self.constraints_per_site.entry(site_id).or_insert(0);
+            if *count >= max_constraints {
+                return false;
+            }
+            *count += 1;
+        }
+        true
+    }
+
+    fn trace_locations(&self) -> bool {
+        self.options.trace_locations && !self.options.only_path_constraints
+    }
 }
 
 /// A macro to generate the boilerplate for declaring a runtime function for `SymCC` that simply logs the function call
@@ -38,14 +211,14 @@ macro_rules! expression_builder {
         #[allow(clippy::missing_safety_doc)]
         #[no_mangle]
         fn $method_name(&mut self, $( $param_name : $param_type, )+ ) -> Option<RSymExpr> {
-            self.write_message(SymExpr::$message { $($param_name,)+ })
+            self.write_expression(SymExpr::$message { $($param_name,)+ })
         }
     };
     ($method_name:ident () => $message:ident) => {
         #[allow(clippy::missing_safety_doc)]
         #[no_mangle]
         fn $method_name(&mut self) -> Option<RSymExpr> {
-            self.write_message(SymExpr::$message)
+            self.write_expression(SymExpr::$message)
         }
     };
 }
@@ -71,7 +244,7 @@ impl Runtime for TracingRuntime {
         _num_bits: core::ffi::c_uint,
     ) -> Option<RSymExpr> {
         // todo
-        self.write_message(SymExpr::IntegerFromBuffer {})
+        self.write_expression(SymExpr::IntegerFromBuffer {})
     }
 
     expression_builder!(get_input_byte(offset: usize, value: u8) => InputByte);
@@ -164,7 +337,12 @@ impl Runtime for TracingRuntime {
     expression_builder!(extract_helper(op: RSymExpr, first_bit:usize, last_bit:usize) => Extract);
 
     fn notify_call(&mut self, site_id: usize) {
-        if self.trace_locations {
+        if self.options.deduplicate_constraints {
+            let hash =
+                (self.call_stack_hash() ^ site_id as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+            self.call_stack.push((site_id, hash));
+        }
+        if self.trace_locations() {
             self.write_message(SymExpr::Call {
                 location: site_id.into(),
             });
@@ -172,7 +350,17 @@ impl Runtime for TracingRuntime {
     }
 
     fn notify_ret(&mut self, site_id: usize) {
-        if self.trace_locations {
+        if self.options.deduplicate_constraints {
+            // unwind to the matching call, in case of longjmp or missed returns
+            if let Some(position) = self
+                .call_stack
+                .iter()
+                .rposition(|(call, _)| *call == site_id)
+            {
+                self.call_stack.truncate(position);
+            }
+        }
+        if self.trace_locations() {
             self.write_message(SymExpr::Return {
                 location: site_id.into(),
             });
@@ -180,7 +368,7 @@ impl Runtime for TracingRuntime {
     }
 
     fn notify_basic_block(&mut self, site_id: usize) {
-        if self.trace_locations {
+        if self.trace_locations() {
             self.write_message(SymExpr::BasicBlock {
                 location: site_id.into(),
             });
@@ -188,12 +376,31 @@ impl Runtime for TracingRuntime {
     }
 
     fn expression_unreachable(&mut self, exprs: &[RSymExpr]) {
-        self.write_message(SymExpr::ExpressionsUnreachable {
-            exprs: exprs.to_owned(),
-        });
+        if self.options.only_path_constraints {
+            // the kept expressions may still be used by later expressions, only report the written ones
+            let exprs: Vec<RSymExpr> = exprs
+                .iter()
+                .filter_map(|expr| self.written[expr.get() - 1])
+                .collect();
+            if !exprs.is_empty() {
+                self.write_message(SymExpr::ExpressionsUnreachable { exprs });
+            }
+        } else {
+            self.write_message(SymExpr::ExpressionsUnreachable {
+                exprs: exprs.to_owned(),
+            });
+        }
     }
 
     fn push_path_constraint(&mut self, constraint: RSymExpr, taken: bool, site_id: usize) {
+        if !self.should_trace_constraint(taken, site_id) {
+            return;
+        }
+        let constraint = if self.options.only_path_constraints {
+            self.write_pending(constraint)
+        } else {
+            constraint
+        };
         self.write_message(SymExpr::PathConstraint {
             constraint,
             taken,
@@ -13,7 +13,9 @@ use std::{
 use clap::Parser;
 use libafl::observers::concolic::{
     serialization_format::{MessageFileReader, MessageFileWriter, DEFAULT_ENV_NAME},
-    EXPRESSION_PRUNING, HITMAP_ENV_NAME, NO_FLOAT_ENV_NAME, SELECTIVE_SYMBOLICATION_ENV_NAME,
+    DEDUPLICATE_CONSTRAINTS_ENV_NAME, EXPRESSION_PRUNING, HITMAP_ENV_NAME,
+    MAX_CONSTRAINTS_PER_SITE_ENV_NAME, MAX_EXPRESSION_DEPTH_ENV_NAME, NO_FLOAT_ENV_NAME,
+    ONLY_PATH_CONSTRAINTS_ENV_NAME, SELECTIVE_SYMBOLICATION_ENV_NAME,
 };
 use libafl_bolts::{
     shmem::{ShMem, ShMemProvider, StdShMemProvider},
@@ -46,6 +48,22 @@ struct Opt {
     #[arg(long)]
     prune: bool,
 
+    /// Only trace the path constraints and the expressions they depend on.
+    #[arg(long)]
+    only_path_constraints: bool,
+
+    /// Concretize expressions deeper than the given depth.
+    #[arg(long)]
+    max_depth: Option<usize>,
+
+    /// Only trace the first path constraints of each code location.
+    #[arg(long)]
+    max_per_site: Option<usize>,
+
+    /// Only trace a path constraint once per code location, direction and call stack.
+    #[arg(long)]
+    dedupe: bool,
+
     /// Trace file path, "trace" by default.
     #[arg(short, long)]
     output: Option<PathBuf>,
@@ -94,6 +112,22 @@ fn main() {
         std::env::set_var(EXPRESSION_PRUNING, "1");
     }
 
+    if opt.only_path_constraints {
+        std::env::set_var(ONLY_PATH_CONSTRAINTS_ENV_NAME, "1");
+    }
+
+    if let Some(max_depth) = opt.max_depth {
+        std::env::set_var(MAX_EXPRESSION_DEPTH_ENV_NAME, max_depth.to_string());
+    }
+
+    if let Some(max_per_site) = opt.max_per_site {
+        std::env::set_var(MAX_CONSTRAINTS_PER_SITE_ENV_NAME, max_per_site.to_string());
+    }
+
+    if opt.dedupe {
+        std::env::set_var(DEDUPLICATE_CONSTRAINTS_ENV_NAME, "1");
+    }
+
     let res = Command::new(opt.program.first().expect("no program argument given"))
         .args(opt.program.iter().skip(1))
         .status()
@@ -6,16 +6,16 @@
 use symcc_runtime::{
     export_runtime,
     filter::NoFloat,
-    tracing::{self, StdShMemMessageFileWriter},
+    tracing::{self, StdShMemMessageFileWriter, TracingOptions},
     Runtime,
 };
 
 export_runtime!(
     NoFloat => NoFloat;
-    tracing::TracingRuntime::new(
+    tracing::TracingRuntime::with_options(
         StdShMemMessageFileWriter::from_stdshmem_default_env()
             .expect("unable to construct tracing runtime writer. (missing env?)"),
-        false
+        TracingOptions::from_env()
     )
     => tracing::TracingRuntime
 );
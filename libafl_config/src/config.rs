@@ -0,0 +1,474 @@
+//! The campaign description, as parsed from TOML or YAML.
+//!
+//! All fields but the target and the corpus directories have defaults, and unknown fields are rejected,
+//! so that a typo does not silently fall back to a default.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use libafl::{schedulers::powersched::PowerSchedule, Error};
+use libafl_bolts::core_affinity::Cores;
+use serde::{Deserialize, Serialize};
+
+/// A fuzzing campaign, see the [crate-level documentation](crate)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CampaignConfig {
+    /// The seed of the random number generators, random if not set.
+    /// Each client adds its core id to it, so that the clients do not all take the same random decisions.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// The target to fuzz
+    pub target: TargetConfig,
+    /// Where the inputs come from and go to
+    pub corpus: CorpusConfig,
+    /// How the next input to fuzz is chosen
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    /// The mutators of the mutational stages
+    #[serde(default)]
+    pub mutators: MutatorsConfig,
+    /// The stages, run in order on each input, by default a calibration and a power stage
+    #[serde(default = "default_stages")]
+    pub stages: Vec<StageConfig>,
+    /// The broker and the clients
+    #[serde(default)]
+    pub broker: BrokerConfig,
+}
+
+/// An AFL-instrumented target, fuzzed through its forkserver
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TargetConfig {
+    /// The instrumented binary
+    pub program: PathBuf,
+    /// Its arguments, where `@@` is replaced by the path of the input file; the input goes to `stdin` without `@@`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The timeout of each run, in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    /// The size of the coverage map, shrunk to the size the target reports, if any
+    #[serde(default = "default_map_size")]
+    pub map_size: usize,
+    /// If the target runs in persistent mode
+    #[serde(default)]
+    pub persistent: bool,
+    /// Keeps the output of the target, instead of redirecting it to `/dev/null`
+    #[serde(default)]
+    pub debug_child: bool,
+    /// Also keeps the inputs that time out, not only the crashes, as solutions
+    #[serde(default)]
+    pub timeouts_as_solutions: bool,
+}
+
+/// The corpus directories
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CorpusConfig {
+    /// The directories of the initial inputs
+    pub input_dirs: Vec<PathBuf>,
+    /// The directory of the results, with the corpus in `queue` and the solutions in `crashes`
+    pub output_dir: PathBuf,
+}
+
+impl CorpusConfig {
+    /// The directory of the corpus
+    #[must_use]
+    pub fn queue_dir(&self) -> PathBuf {
+        self.output_dir.join("queue")
+    }
+
+    /// The directory of the solutions
+    #[must_use]
+    pub fn crashes_dir(&self) -> PathBuf {
+        self.output_dir.join("crashes")
+    }
+}
+
+/// The scheduler, always wrapped in a minimizer favoring small and fast inputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// The kind of scheduler
+    #[serde(default)]
+    pub kind: SchedulerKind,
+    /// The power schedule, for the schedulers that have one
+    #[serde(default)]
+    pub schedule: ScheduleKind,
+}
+
+/// The kinds of schedulers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulerKind {
+    /// Goes through the corpus in order, see [`libafl::schedulers::QueueScheduler`]
+    Queue,
+    /// Picks the inputs by their weight, see [`libafl::schedulers::StdWeightedScheduler`]
+    #[default]
+    Weighted,
+    /// Goes through the corpus in order, with a power schedule, see [`libafl::schedulers::PowerQueueScheduler`]
+    PowerQueue,
+}
+
+impl SchedulerKind {
+    /// If this scheduler keeps the metadata needed by the power and calibration stages
+    #[must_use]
+    pub fn has_power_schedule(self) -> bool {
+        matches!(self, Self::Weighted | Self::PowerQueue)
+    }
+}
+
+/// The power schedules, see [`PowerSchedule`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleKind {
+    /// The `explore` power schedule
+    Explore,
+    /// The `exploit` power schedule
+    Exploit,
+    /// The `fast` power schedule
+    #[default]
+    Fast,
+    /// The `coe` power schedule
+    Coe,
+    /// The `lin` power schedule
+    Lin,
+    /// The `quad` power schedule
+    Quad,
+}
+
+impl From<ScheduleKind> for PowerSchedule {
+    fn from(kind: ScheduleKind) -> Self {
+        match kind {
+            ScheduleKind::Explore => PowerSchedule::explore(),
+            ScheduleKind::Exploit => PowerSchedule::exploit(),
+            ScheduleKind::Fast => PowerSchedule::fast(),
+            ScheduleKind::Coe => PowerSchedule::coe(),
+            ScheduleKind::Lin => PowerSchedule::lin(),
+            ScheduleKind::Quad => PowerSchedule::quad(),
+        }
+    }
+}
+
+/// The mutators, stacked by the mutational and power stages: the havoc mutations, and optionally the token mutations
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MutatorsConfig {
+    /// Adds the token mutations to the havoc mutations, with the dictionaries and the tokens the target reports
+    #[serde(default = "default_true")]
+    pub tokens: bool,
+    /// The dictionaries of tokens, in the AFL format
+    #[serde(default)]
+    pub dictionaries: Vec<PathBuf>,
+    /// At most `2^max_stack_pow` mutations are stacked on an input
+    #[serde(default = "default_max_stack_pow")]
+    pub max_stack_pow: usize,
+}
+
+impl Default for MutatorsConfig {
+    fn default() -> Self {
+        Self {
+            tokens: true,
+            dictionaries: Vec::new(),
+            max_stack_pow: default_max_stack_pow(),
+        }
+    }
+}
+
+/// A stage, run on each input the scheduler picks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StageConfig {
+    /// Runs new inputs several times, to find their unstable edges and their speed, see [`libafl::stages::CalibrationStage`]
+    Calibration,
+    /// Mutates the input a random number of times, see [`libafl::stages::StdMutationalStage`]
+    Mutational {
+        /// The maximum number of mutated inputs per input
+        #[serde(default = "default_max_iterations")]
+        max_iterations: usize,
+    },
+    /// Mutates the input as many times as the power schedule says, see [`libafl::stages::StdPowerMutationalStage`]
+    Power,
+    /// Imports the new inputs of other fuzzers, see [`libafl::stages::SyncFromDiskStage`]
+    Sync {
+        /// The directories to import from, such as the `queue` of other fuzzers
+        dirs: Vec<PathBuf>,
+        /// How often to look for new inputs, in seconds
+        #[serde(default = "default_sync_interval_secs")]
+        interval_secs: u64,
+    },
+}
+
+/// The broker, and the clients fuzzing on the cores
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BrokerConfig {
+    /// The port of the broker
+    #[serde(default = "default_broker_port")]
+    pub port: u16,
+    /// The cores to run clients on, such as `0-3,6` or `all`
+    #[serde(default = "default_cores")]
+    pub cores: String,
+    /// The broker to connect the broker to, to fuzz on several machines
+    #[serde(default)]
+    pub remote_broker_addr: Option<SocketAddr>,
+    /// The file to redirect the output of the clients to
+    #[serde(default)]
+    pub client_stdout: Option<String>,
+}
+
+impl Default for BrokerConfig {
+    fn default() -> Self {
+        Self {
+            port: default_broker_port(),
+            cores: default_cores(),
+            remote_broker_addr: None,
+            client_stdout: None,
+        }
+    }
+}
+
+impl BrokerConfig {
+    /// The parsed [`Self::cores`]
+    pub fn cores(&self) -> Result<Cores, Error> {
+        Cores::from_cmdline(&self.cores)
+    }
+}
+
+fn default_stages() -> Vec<StageConfig> {
+    vec![StageConfig::Calibration, StageConfig::Power]
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+fn default_map_size() -> usize {
+    65536
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_stack_pow() -> usize {
+    7
+}
+
+fn default_max_iterations() -> usize {
+    128
+}
+
+fn default_sync_interval_secs() -> u64 {
+    60
+}
+
+fn default_broker_port() -> u16 {
+    1337
+}
+
+fn default_cores() -> String {
+    "0".into()
+}
+
+impl CampaignConfig {
+    /// Parses and checks a campaign description in TOML
+    pub fn from_toml_str(description: &str) -> Result<Self, Error> {
+        let config: Self = toml::from_str(description).map_err(|err| {
+            Error::illegal_argument(format!("Invalid campaign description: {err}"))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses and checks a campaign description in YAML
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml_str(description: &str) -> Result<Self, Error> {
+        let config: Self = serde_yaml::from_str(description).map_err(|err| {
+            Error::illegal_argument(format!("Invalid campaign description: {err}"))
+        })?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reads, parses and checks the campaign description at `path`, in YAML for `.yaml` and `.yml` files,
+    /// else in TOML
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let description = fs::read_to_string(path)?;
+        let is_yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        if is_yaml {
+            #[cfg(feature = "yaml")]
+            return Self::from_yaml_str(&description);
+            #[cfg(not(feature = "yaml"))]
+            return Err(Error::illegal_argument(format!(
+                "Cannot read {}, YAML needs the `yaml` feature",
+                path.display()
+            )));
+        }
+        Self::from_toml_str(&description)
+    }
+
+    /// Checks that the campaign can be assembled into a fuzzer
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.corpus.input_dirs.is_empty() {
+            return Err(Error::illegal_argument(
+                "The campaign needs at least one directory of initial inputs",
+            ));
+        }
+        if self.target.timeout_ms == 0 || self.target.map_size == 0 {
+            return Err(Error::illegal_argument(
+                "The timeout and the map size of the target must not be 0",
+            ));
+        }
+        if self.mutators.max_stack_pow == 0 {
+            return Err(Error::illegal_argument(
+                "The mutators need to stack at least 2^1 mutations",
+            ));
+        }
+        if self.stages.is_empty() {
+            return Err(Error::illegal_argument(
+                "The campaign needs at least one stage",
+            ));
+        }
+        let calibrations = self
+            .stages
+            .iter()
+            .filter(|stage| **stage == StageConfig::Calibration)
+            .count();
+        if calibrations > 1 {
+            return Err(Error::illegal_argument(
+                "The calibration stage can only run once per input",
+            ));
+        }
+        for stage in &self.stages {
+            match stage {
+                StageConfig::Calibration | StageConfig::Power
+                    if !self.scheduler.kind.has_power_schedule() =>
+                {
+                    return Err(Error::illegal_argument(format!(
+                        "The {stage:?} stage needs a scheduler with a power schedule, not {:?}",
+                        self.scheduler.kind
+                    )));
+                }
+                StageConfig::Mutational { max_iterations: 0 } => {
+                    return Err(Error::illegal_argument(
+                        "The mutational stage needs at least one iteration",
+                    ));
+                }
+                StageConfig::Sync { dirs, .. } if dirs.is_empty() => {
+                    return Err(Error::illegal_argument(
+                        "The sync stage needs at least one directory to import from",
+                    ));
+                }
+                _ => {}
+            }
+        }
+        self.broker.cores()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{CampaignConfig, ScheduleKind, SchedulerKind, StageConfig};
+
+    const CAMPAIGN: &str = r#"
+seed = 1337
+
+[target]
+program = "./target"
+args = ["--input", "@@"]
+timeout_ms = 500
+
+[corpus]
+input_dirs = ["./seeds"]
+output_dir = "./out"
+
+[scheduler]
+kind = "power_queue"
+schedule = "explore"
+
+[[stages]]
+kind = "calibration"
+
+[[stages]]
+kind = "mutational"
+max_iterations = 64
+
+[[stages]]
+kind = "sync"
+dirs = ["./other/queue"]
+"#;
+
+    #[test]
+    fn test_campaign_from_toml() {
+        let config = CampaignConfig::from_toml_str(CAMPAIGN).unwrap();
+        assert_eq!(config.seed, Some(1337));
+        assert_eq!(config.target.args, ["--input", "@@"]);
+        assert_eq!(config.target.timeout_ms, 500);
+        assert_eq!(config.target.map_size, 65536);
+        assert_eq!(config.corpus.queue_dir(), PathBuf::from("./out/queue"));
+        assert_eq!(config.scheduler.kind, SchedulerKind::PowerQueue);
+        assert_eq!(config.scheduler.schedule, ScheduleKind::Explore);
+        assert!(config.mutators.tokens);
+        assert_eq!(
+            config.stages,
+            [
+                StageConfig::Calibration,
+                StageConfig::Mutational { max_iterations: 64 },
+                StageConfig::Sync {
+                    dirs: vec!["./other/queue".into()],
+                    interval_secs: 60
+                }
+            ]
+        );
+        assert_eq!(config.broker.port, 1337);
+    }
+
+    #[test]
+    fn test_campaign_invalid() {
+        // Typos are not silently ignored
+        assert!(CampaignConfig::from_toml_str(&CAMPAIGN.replace("timeout_ms", "timeout")).is_err());
+        // The queue scheduler keeps no metadata for the calibration stage
+        assert!(
+            CampaignConfig::from_toml_str(&CAMPAIGN.replace("\"power_queue\"", "\"queue\""))
+                .is_err()
+        );
+        assert!(CampaignConfig::from_toml_str(
+            &CAMPAIGN.replace("max_iterations = 64", "max_iterations = 0")
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_campaign_from_yaml() {
+        let yaml = "
+target:
+  program: ./target
+corpus:
+  input_dirs: [./seeds]
+  output_dir: ./out
+stages:
+  - kind: power
+broker:
+  cores: 0-1
+";
+        let config = CampaignConfig::from_yaml_str(yaml).unwrap();
+        assert_eq!(config.stages, [StageConfig::Power]);
+        assert_eq!(config.scheduler.kind, SchedulerKind::Weighted);
+        assert_eq!(config.broker.cores().unwrap().ids.len(), 2);
+    }
+}
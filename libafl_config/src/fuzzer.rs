@@ -0,0 +1,343 @@
+//! Assembles the fuzzer a [`CampaignConfig`] describes, and runs it on the configured cores.
+
+use core::{num::NonZeroUsize, time::Duration};
+use std::fs;
+
+use libafl::{
+    corpus::{Corpus, CorpusId, InMemoryOnDiskCorpus, OnDiskCorpus, Testcase},
+    events::{launcher::Launcher, EventConfig, LlmpRestartingEventManager},
+    executors::{forkserver::ForkserverExecutor, HasObservers},
+    feedback_and_fast, feedback_or, feedback_or_fast,
+    feedbacks::{ConstFeedback, CrashFeedback, MaxMapFeedback, TimeFeedback, TimeoutFeedback},
+    fuzzer::{Fuzzer, StdFuzzer},
+    inputs::BytesInput,
+    monitors::MultiMonitor,
+    mutators::{havoc_mutations, tokens_mutations, StdScheduledMutator, Tokens},
+    observers::{
+        CanTrack, DynObserversTuple, HitcountsMapObserver, MapObserver, StdMapObserver,
+        TimeObserver,
+    },
+    schedulers::{
+        IndexesLenTimeMinimizerScheduler, PowerQueueScheduler, QueueScheduler, RemovableScheduler,
+        Scheduler, StdWeightedScheduler,
+    },
+    stages::{
+        CalibrationStage, DynStagesTuple, StdMutationalStage, StdPowerMutationalStage,
+        SyncFromDiskStage,
+    },
+    state::{HasCorpus, HasRandStreams, StdState},
+    Error, HasMetadata,
+};
+use libafl_bolts::{
+    core_affinity::CoreId,
+    current_nanos,
+    rands::StdRand,
+    shmem::{ShMem, ShMemProvider, StdShMemProvider, UnixShMemProvider},
+    tuples::{Handled, MatchName, Merge},
+    Named, Truncate,
+};
+
+use crate::config::{CampaignConfig, SchedulerKind, StageConfig};
+
+/// The state of the clients
+type ConfigState =
+    StdState<BytesInput, InMemoryOnDiskCorpus<BytesInput>, StdRand, OnDiskCorpus<BytesInput>>;
+
+/// The scheduler picked by the [`crate::SchedulerConfig`], wrapped in a minimizer by the clients
+#[derive(Debug)]
+enum ConfiguredScheduler<C, O> {
+    Queue(QueueScheduler),
+    Weighted(StdWeightedScheduler<C, O>),
+    PowerQueue(PowerQueueScheduler<C, O>),
+}
+
+impl<C, O> ConfiguredScheduler<C, O>
+where
+    C: AsRef<O> + Named,
+    O: MapObserver,
+{
+    fn new(state: &mut ConfigState, map_observer: &C, config: &CampaignConfig) -> Self {
+        let schedule = config.scheduler.schedule.into();
+        match config.scheduler.kind {
+            SchedulerKind::Queue => Self::Queue(QueueScheduler::new()),
+            SchedulerKind::Weighted => Self::Weighted(StdWeightedScheduler::with_schedule(
+                state,
+                map_observer,
+                Some(schedule),
+            )),
+            SchedulerKind::PowerQueue => {
+                Self::PowerQueue(PowerQueueScheduler::new(state, map_observer, schedule))
+            }
+        }
+    }
+}
+
+impl<C, O> RemovableScheduler<BytesInput, ConfigState> for ConfiguredScheduler<C, O>
+where
+    C: AsRef<O>,
+    O: MapObserver,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut ConfigState,
+        id: CorpusId,
+        testcase: &Option<Testcase<BytesInput>>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Queue(scheduler) => scheduler.on_remove(state, id, testcase),
+            Self::Weighted(scheduler) => scheduler.on_remove(state, id, testcase),
+            Self::PowerQueue(scheduler) => scheduler.on_remove(state, id, testcase),
+        }
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut ConfigState,
+        id: CorpusId,
+        prev: &Testcase<BytesInput>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Queue(scheduler) => scheduler.on_replace(state, id, prev),
+            Self::Weighted(scheduler) => scheduler.on_replace(state, id, prev),
+            Self::PowerQueue(scheduler) => scheduler.on_replace(state, id, prev),
+        }
+    }
+}
+
+impl<C, O> Scheduler<BytesInput, ConfigState> for ConfiguredScheduler<C, O>
+where
+    C: AsRef<O> + Named,
+    O: MapObserver,
+{
+    fn on_add(&mut self, state: &mut ConfigState, id: CorpusId) -> Result<(), Error> {
+        match self {
+            Self::Queue(scheduler) => Scheduler::<BytesInput, _>::on_add(scheduler, state, id),
+            Self::Weighted(scheduler) => scheduler.on_add(state, id),
+            Self::PowerQueue(scheduler) => Scheduler::<BytesInput, _>::on_add(scheduler, state, id),
+        }
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut ConfigState,
+        input: &BytesInput,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: MatchName,
+    {
+        match self {
+            Self::Queue(scheduler) => scheduler.on_evaluation(state, input, observers),
+            Self::Weighted(scheduler) => scheduler.on_evaluation(state, input, observers),
+            Self::PowerQueue(scheduler) => scheduler.on_evaluation(state, input, observers),
+        }
+    }
+
+    fn next(&mut self, state: &mut ConfigState) -> Result<CorpusId, Error> {
+        match self {
+            Self::Queue(scheduler) => Scheduler::<BytesInput, _>::next(scheduler, state),
+            Self::Weighted(scheduler) => scheduler.next(state),
+            Self::PowerQueue(scheduler) => Scheduler::<BytesInput, _>::next(scheduler, state),
+        }
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut ConfigState,
+        next_id: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Queue(scheduler) => {
+                Scheduler::<BytesInput, _>::set_current_scheduled(scheduler, state, next_id)
+            }
+            Self::Weighted(scheduler) => scheduler.set_current_scheduled(state, next_id),
+            Self::PowerQueue(scheduler) => {
+                Scheduler::<BytesInput, _>::set_current_scheduled(scheduler, state, next_id)
+            }
+        }
+    }
+}
+
+/// Runs the campaign: spawns the broker, and a client on each of the configured cores.
+///
+/// Returns once the fuzzing stops, with an [`Error::ShuttingDown`] turned into `Ok`.
+#[allow(clippy::too_many_lines, clippy::similar_names)]
+pub fn run(config: &CampaignConfig) -> Result<(), Error> {
+    config.validate()?;
+    let cores = config.broker.cores()?;
+    fs::create_dir_all(&config.corpus.output_dir)?;
+
+    let shmem_provider = StdShMemProvider::new()?;
+    let monitor = MultiMonitor::new(|s| println!("{s}"));
+
+    let time_observer = TimeObserver::new("time");
+    let time_ref = time_observer.handle();
+
+    let mut run_client = |state: Option<ConfigState>,
+                          mut mgr: LlmpRestartingEventManager<_, _, _>,
+                          core_id: CoreId| {
+        let time_observer = time_observer.clone();
+        let target = &config.target;
+
+        // The coverage map, in the shared memory format of AFL++
+        let mut map_shmem_provider = UnixShMemProvider::new()?;
+        let mut shmem = map_shmem_provider.new_shmem(target.map_size)?;
+        shmem.write_to_env("__AFL_SHM_ID")?;
+        std::env::set_var("AFL_MAP_SIZE", format!("{}", target.map_size));
+        // The observers and the stages are boxed, so the observers may not borrow the map.
+        // The map outlives the executor and its observers, which are declared after it.
+        let edges_observer = unsafe {
+            HitcountsMapObserver::new(StdMapObserver::from_mut_ptr(
+                "shared_mem",
+                shmem.as_mut_ptr(),
+                target.map_size,
+            ))
+            .track_indices()
+        };
+        let edges_ref = edges_observer.handle();
+
+        let map_feedback = MaxMapFeedback::new(&edges_observer);
+        let mut calibration = Some(CalibrationStage::new(&map_feedback));
+        // Unlike the observers and the stages, the feedbacks are not boxed in a `DynFeedback`:
+        // it is bound to one event manager, and the restarting manager also runs them with the one it wraps.
+        let mut feedback = feedback_or!(map_feedback, TimeFeedback::new(&time_observer));
+
+        // Only keep the solutions that hit new edges, as AFL does
+        let mut objective = feedback_and_fast!(
+            feedback_or_fast!(
+                CrashFeedback::new(),
+                feedback_and_fast!(
+                    ConstFeedback::new(target.timeouts_as_solutions),
+                    TimeoutFeedback::new()
+                )
+            ),
+            MaxMapFeedback::with_name("mapfeedback_metadata_objective", &edges_observer)
+        );
+
+        let seed = config.seed.unwrap_or_else(current_nanos);
+        let core_seed = seed.wrapping_add(core_id.0 as u64);
+        // A restarted client keeps its state, and its random streams
+        let mut state = if let Some(state) = state {
+            state
+        } else {
+            let mut state = StdState::new(
+                StdRand::with_seed(core_seed),
+                InMemoryOnDiskCorpus::new(config.corpus.queue_dir())?,
+                OnDiskCorpus::new(config.corpus.crashes_dir())?,
+                &mut feedback,
+                &mut objective,
+            )?;
+            state.rand_streams_mut().set_seed(core_seed);
+            state
+        };
+
+        let scheduler = IndexesLenTimeMinimizerScheduler::new(
+            &edges_observer,
+            ConfiguredScheduler::new(&mut state, &edges_observer, config),
+        );
+        let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+        let mut tokens = Tokens::new();
+        let mut executor = ForkserverExecutor::builder()
+            .program(target.program.clone())
+            .parse_afl_cmdline(&target.args)
+            .is_persistent(target.persistent)
+            .debug_child(target.debug_child)
+            .autotokens(&mut tokens)
+            .coverage_map_size(target.map_size)
+            .timeout(Duration::from_millis(target.timeout_ms))
+            .build(
+                DynObserversTuple::new()
+                    .with(edges_observer)
+                    .with(time_observer),
+            )?;
+        if let Some(dynamic_map_size) = executor.coverage_map_size() {
+            executor.observers_mut()[&edges_ref]
+                .as_mut()
+                .truncate(dynamic_map_size);
+        }
+
+        // The token mutations skip the inputs if the state has no tokens
+        if config.mutators.tokens {
+            for dictionary in &config.mutators.dictionaries {
+                tokens.add_from_file(dictionary)?;
+            }
+            if !tokens.is_empty() {
+                state.add_metadata(tokens);
+            }
+        }
+
+        let mut stages = DynStagesTuple::new();
+        for stage in &config.stages {
+            match stage {
+                StageConfig::Calibration => {
+                    // Checked by `validate`, there is only one calibration stage
+                    if let Some(calibration) = calibration.take() {
+                        stages.push(calibration);
+                    }
+                }
+                StageConfig::Mutational { max_iterations } => {
+                    let max_iterations =
+                        NonZeroUsize::new(*max_iterations).unwrap_or(NonZeroUsize::MIN);
+                    stages.push(StdMutationalStage::with_max_iterations(
+                        StdScheduledMutator::with_max_stack_pow(
+                            havoc_mutations().merge(tokens_mutations()),
+                            config.mutators.max_stack_pow,
+                        ),
+                        max_iterations,
+                    ));
+                }
+                StageConfig::Power => {
+                    let power: StdPowerMutationalStage<_, _, BytesInput, _, _> =
+                        StdPowerMutationalStage::new(StdScheduledMutator::with_max_stack_pow(
+                            havoc_mutations().merge(tokens_mutations()),
+                            config.mutators.max_stack_pow,
+                        ));
+                    stages.push(power);
+                }
+                StageConfig::Sync {
+                    dirs,
+                    interval_secs,
+                } => {
+                    stages.push(SyncFromDiskStage::with_from_file(
+                        dirs.clone(),
+                        Duration::from_secs(*interval_secs),
+                    ));
+                }
+            }
+        }
+
+        if state.must_load_initial_inputs() {
+            state.load_initial_inputs(
+                &mut fuzzer,
+                &mut executor,
+                &mut mgr,
+                &config.corpus.input_dirs,
+            )?;
+            log::info!("We imported {} inputs from disk.", state.corpus().count());
+        }
+
+        fuzzer.fuzz_loop(&mut stages, &mut executor, &mut state, &mut mgr)?;
+        Ok(())
+    };
+
+    // The boxed observers can not be deserialized, so the clients always rerun the inputs of the others
+    let launcher = Launcher::builder()
+        .shmem_provider(shmem_provider)
+        .configuration(EventConfig::AlwaysUnique)
+        .monitor(monitor)
+        .run_client(&mut run_client)
+        .cores(&cores)
+        .broker_port(config.broker.port)
+        .remote_broker_addr(config.broker.remote_broker_addr)
+        .stdout_file(config.broker.client_stdout.as_deref())
+        .time_ref(Some(time_ref));
+
+    match launcher.build().launch() {
+        Err(Error::ShuttingDown) => {
+            log::info!("Fuzzing stopped by user. Good bye.");
+            Ok(())
+        }
+        res => res,
+    }
+}
@@ -0,0 +1,69 @@
+//! Assemble and run `LibAFL` fuzzers from a campaign description, without writing a `main.rs`.
+//!
+//! A campaign is described in a TOML (or, with the `yaml` feature, YAML) file: the target, the corpus directories,
+//! the scheduler, the mutators, the stages and the broker settings. The [`CampaignConfig`] parses and checks it,
+//! and [`run`] assembles and starts the fuzzer, one client per core, fuzzing an AFL-instrumented target
+//! through its forkserver.
+//!
+//! ```toml
+//! seed = 1337
+//!
+//! [target]
+//! program = "./target"
+//! args = ["@@"]
+//! timeout_ms = 1000
+//!
+//! [corpus]
+//! input_dirs = ["./seeds"]
+//! output_dir = "./out"
+//!
+//! [scheduler]
+//! kind = "weighted"
+//! schedule = "explore"
+//!
+//! [mutators]
+//! dictionaries = ["./target.dict"]
+//!
+//! [[stages]]
+//! kind = "calibration"
+//!
+//! [[stages]]
+//! kind = "power"
+//!
+//! [broker]
+//! port = 1337
+//! cores = "0-3"
+//! ```
+//!
+//! The `libafl-config` binary runs such a file: `libafl-config campaign.toml`.
+#![cfg_attr(not(test), warn(
+    missing_debug_implementations,
+    missing_docs,
+    //trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    //unused_results
+))]
+#![cfg_attr(test, deny(
+    missing_debug_implementations,
+    //trivial_casts,
+    trivial_numeric_casts,
+    unused_extern_crates,
+    unused_import_braces,
+    unused_qualifications,
+    unused_must_use,
+    //unused_results
+))]
+
+pub mod config;
+pub use config::{
+    BrokerConfig, CampaignConfig, CorpusConfig, MutatorsConfig, ScheduleKind, SchedulerConfig,
+    SchedulerKind, StageConfig, TargetConfig,
+};
+
+#[cfg(unix)]
+pub mod fuzzer;
+#[cfg(unix)]
+pub use fuzzer::run;
@@ -0,0 +1,51 @@
+//! Runs the fuzzing campaign described in a TOML or YAML file, see [`libafl_config`].
+
+use std::path::PathBuf;
+
+use clap::Parser;
+
+/// The commandline args of `libafl-config`
+#[derive(Debug, Parser)]
+#[command(
+    name = "libafl-config",
+    about = "Assembles and runs a LibAFL fuzzer from a campaign description"
+)]
+struct Opt {
+    #[arg(
+        help = "The campaign description, in TOML, or in YAML for .yaml and .yml files",
+        name = "CAMPAIGN",
+        required = true
+    )]
+    campaign: PathBuf,
+
+    #[arg(
+        help = "Only check the campaign description, without fuzzing",
+        short = 'c',
+        long = "check",
+        default_value = "false"
+    )]
+    check: bool,
+}
+
+#[cfg(unix)]
+fn main() -> Result<(), libafl::Error> {
+    let opt = Opt::parse();
+    let config = libafl_config::CampaignConfig::from_file(&opt.campaign)?;
+    if opt.check {
+        println!("{} is a valid campaign.", opt.campaign.display());
+        return Ok(());
+    }
+    libafl_config::run(&config)
+}
+
+#[cfg(not(unix))]
+fn main() {
+    let opt = Opt::parse();
+    match libafl_config::CampaignConfig::from_file(&opt.campaign) {
+        Ok(_) => println!(
+            "{} is a valid campaign, but forkserver fuzzing is only supported on unix.",
+            opt.campaign.display()
+        ),
+        Err(err) => eprintln!("{err}"),
+    }
+}
@@ -41,9 +41,16 @@
     )
 )]
 
+extern crate alloc;
+
+use alloc::{vec, vec::Vec};
+
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, Data::Struct, DeriveInput, Field, Fields::Named, Type};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Data::Struct, DeriveInput, Field, Fields::Named, GenericArgument, Ident,
+    PathArguments, Type,
+};
 
 /// Derive macro to implement `SerdeAny`, to use a type in a `SerdeAnyMap`
 #[proc_macro_derive(SerdeAny)]
@@ -144,3 +151,391 @@ fn libafl_display_field_by_type(it: &Field) -> proc_macro2::TokenStream {
         write!(f, #fmt, self.#ident)?;
     }
 }
+
+/// Derive macro to implement `libafl::mutators::MappedMutations` for a struct of inputs,
+/// mapping the mutations of each field to the struct.
+///
+/// The fields are mutated according to their types:
+/// - `Vec<u8>`: the mapped havoc mutations, with the accessors `field()` and `field_mut()` added to the struct
+/// - `Option<Vec<u8>>`: the optional mapped havoc mutations and a toggle, with the accessors `field()` and `field_mut()`
+/// - `bool`: a toggle
+/// - `u8` to `u64`, `i8` to `i64`, `usize` and `isize`: the `IntMutator`
+/// - other types: the mutations without crossover of the nested input, which must implement `MappedMutations` too
+///
+/// Fields annotated with `#[mapped_mutations(skip)]` are not mutated.
+/// The generated code uses `libafl` and `libafl_bolts`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use libafl::{mutators::MappedMutations, MappedMutations};
+///
+/// #[derive(MappedMutations)]
+/// struct MyInput {
+///     data: Vec<u8>,
+///     flag: bool,
+///     #[mapped_mutations(skip)]
+///     id: String,
+/// }
+///
+/// let mutations = MyInput::mapped_mutations();
+/// ```
+///
+/// # Panics
+/// Panics for any non-structs, structs with generics or without named fields.
+#[proc_macro_derive(MappedMutations, attributes(mapped_mutations))]
+pub fn libafl_mapped_mutations(input: TokenStream) -> TokenStream {
+    let DeriveInput {
+        ident,
+        data,
+        generics,
+        ..
+    } = parse_macro_input!(input as DeriveInput);
+    assert!(
+        generics.params.is_empty(),
+        "Structs with generics are not supported"
+    );
+    let Struct(s) = data else {
+        panic!("Only structs are supported");
+    };
+    let Named(fields) = s.fields else {
+        panic!("Only structs with named fields are supported");
+    };
+
+    let mut accessors = vec![];
+    let mut mappers = vec![];
+    let mut mappers_no_crossover = vec![];
+    let mut mutations = vec![];
+    let mut mutations_no_crossover = vec![];
+    for field in fields
+        .named
+        .iter()
+        .filter(|field| !mapped_mutations_skip(field))
+    {
+        let field = MappedField::new(&ident, field);
+        accessors.push(field.accessors());
+        mappers.push(field.mappers(true));
+        mappers_no_crossover.push(field.mappers(false));
+        mutations.extend(field.mutations(true));
+        mutations_no_crossover.extend(field.mutations(false));
+    }
+
+    let merge = |parts: Vec<(proc_macro2::TokenStream, proc_macro2::TokenStream)>| {
+        parts
+            .into_iter()
+            .fold((quote!(()), quote!(())), |(acc_ty, acc), (ty, expr)| {
+                (
+                    quote!(<#acc_ty as ::libafl_bolts::tuples::Merge<#ty>>::MergeResult),
+                    quote!(::libafl_bolts::tuples::Merge::merge(#acc, #expr)),
+                )
+            })
+    };
+    let (mutations_ty, mutations) = merge(mutations);
+    let (mutations_no_crossover_ty, mutations_no_crossover) = merge(mutations_no_crossover);
+
+    quote! {
+        impl #ident {
+            #(#accessors)*
+        }
+
+        impl ::libafl::mutators::MappedMutations for #ident {
+            type Mutations = #mutations_ty;
+            type MutationsNoCrossover = #mutations_no_crossover_ty;
+
+            #[allow(clippy::unnecessary_cast, unused_parens)]
+            fn mapped_mutations() -> Self::Mutations {
+                #(#mappers)*
+                #mutations
+            }
+
+            #[allow(clippy::unnecessary_cast, unused_parens)]
+            fn mapped_mutations_no_crossover() -> Self::MutationsNoCrossover {
+                #(#mappers_no_crossover)*
+                #mutations_no_crossover
+            }
+        }
+    }
+    .into()
+}
+
+/// Checks for `#[mapped_mutations(skip)]`
+fn mapped_mutations_skip(field: &Field) -> bool {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("mapped_mutations"))
+        .any(|attr| {
+            let mut skip = false;
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported mapped_mutations attribute"))
+                }
+            })
+            .expect("Invalid mapped_mutations attribute");
+            skip
+        })
+}
+
+/// The kinds of fields supported by `MappedMutations`
+enum MappedFieldKind {
+    Bytes,
+    OptionalBytes,
+    Bool,
+    Int,
+    Nested,
+}
+
+/// A field of a struct deriving `MappedMutations`
+struct MappedField<'a> {
+    input: &'a Ident,
+    ident: &'a Ident,
+    ty: &'a Type,
+    kind: MappedFieldKind,
+}
+
+impl<'a> MappedField<'a> {
+    fn new(input: &'a Ident, field: &'a Field) -> Self {
+        let ty = &field.ty;
+        let kind = if is_type(ty, &["Vec", "u8"]) {
+            MappedFieldKind::Bytes
+        } else if is_type(ty, &["Option", "Vec", "u8"]) {
+            MappedFieldKind::OptionalBytes
+        } else if is_type(ty, &["bool"]) {
+            MappedFieldKind::Bool
+        } else if [
+            "u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize",
+        ]
+        .iter()
+        .any(|int| is_type(ty, &[int]))
+        {
+            MappedFieldKind::Int
+        } else {
+            MappedFieldKind::Nested
+        };
+        Self {
+            input,
+            ident: field.ident.as_ref().unwrap(),
+            ty,
+            kind,
+        }
+    }
+
+    fn mapper_name(&self, suffix: &str) -> Ident {
+        format_ident!("{}_{}", self.ident, suffix)
+    }
+
+    /// The accessors of the bytes fields, used by the mapped havoc mutations
+    fn accessors(&self) -> proc_macro2::TokenStream {
+        let ident = self.ident;
+        let ident_mut = format_ident!("{}_mut", ident);
+        match self.kind {
+            MappedFieldKind::Bytes => quote! {
+                #[doc = concat!("Returns a mutable reference to `", stringify!(#ident), "`")]
+                pub fn #ident_mut(&mut self) -> ::libafl::inputs::MutVecInput<'_> {
+                    (&mut self.#ident).into()
+                }
+
+                #[doc = concat!("Returns an immutable reference to `", stringify!(#ident), "`")]
+                pub fn #ident(&self) -> &[u8] {
+                    &self.#ident
+                }
+            },
+            MappedFieldKind::OptionalBytes => quote! {
+                #[doc = concat!("Returns a mutable reference to `", stringify!(#ident), "`")]
+                pub fn #ident_mut(&mut self) -> Option<::libafl::inputs::MutVecInput<'_>> {
+                    self.#ident.as_mut().map(::core::convert::Into::into)
+                }
+
+                #[doc = concat!("Returns an immutable reference to `", stringify!(#ident), "`")]
+                pub fn #ident(&self) -> Option<&[u8]> {
+                    self.#ident.as_deref()
+                }
+            },
+            _ => quote!(),
+        }
+    }
+
+    /// The types of the functions mapping the struct to the field
+    fn mapper_types(&self) -> Vec<proc_macro2::TokenStream> {
+        let input = self.input;
+        let ty = self.ty;
+        let field_mapper = quote!(for<'a> fn(&'a mut #input) -> &'a mut #ty);
+        match self.kind {
+            MappedFieldKind::Bytes => vec![
+                quote!(for<'a> fn(&'a mut #input) -> ::libafl::inputs::MutVecInput<'a>),
+                quote!(for<'a> fn(&'a #input) -> &'a [u8]),
+            ],
+            MappedFieldKind::OptionalBytes => vec![
+                quote!(for<'a> fn(&'a mut #input) -> Option<::libafl::inputs::MutVecInput<'a>>),
+                quote!(for<'a> fn(&'a #input) -> Option<&'a [u8]>),
+                field_mapper,
+            ],
+            MappedFieldKind::Bool | MappedFieldKind::Int | MappedFieldKind::Nested => {
+                vec![field_mapper]
+            }
+        }
+    }
+
+    /// The functions mapping the struct to the field, as function pointers to name their types.
+    /// The mapping to the fields of the inputs of the corpus is only needed for the crossovers.
+    fn mappers(&self, crossover: bool) -> proc_macro2::TokenStream {
+        let input = self.input;
+        let ident = self.ident;
+        let ident_mut = format_ident!("{}_mut", ident);
+        let types = self.mapper_types();
+        let (current, corpus, field) = (
+            self.mapper_name("current"),
+            self.mapper_name("corpus"),
+            self.mapper_name("field"),
+        );
+        let corpus_mapper = |corpus_ty| {
+            if crossover {
+                quote!(let #corpus: #corpus_ty = #input::#ident;)
+            } else {
+                quote!()
+            }
+        };
+        match self.kind {
+            MappedFieldKind::Bytes => {
+                let current_ty = &types[0];
+                let corpus_mapper = corpus_mapper(&types[1]);
+                quote! {
+                    let #current: #current_ty = #input::#ident_mut;
+                    #corpus_mapper
+                }
+            }
+            MappedFieldKind::OptionalBytes => {
+                let (current_ty, field_ty) = (&types[0], &types[2]);
+                let corpus_mapper = corpus_mapper(&types[1]);
+                quote! {
+                    let #current: #current_ty = #input::#ident_mut;
+                    #corpus_mapper
+                    let #field: #field_ty = |input| &mut input.#ident;
+                }
+            }
+            MappedFieldKind::Bool | MappedFieldKind::Int | MappedFieldKind::Nested => {
+                let field_ty = &types[0];
+                quote! {
+                    let #field: #field_ty = |input| &mut input.#ident;
+                }
+            }
+        }
+    }
+
+    /// The types and the values of the mutations of the field, with or without the crossovers
+    fn mutations(
+        &self,
+        crossover: bool,
+    ) -> Vec<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+        let ty = self.ty;
+        let types = self.mapper_types();
+        let (current, corpus, field) = (
+            self.mapper_name("current"),
+            self.mapper_name("corpus"),
+            self.mapper_name("field"),
+        );
+        let mutators = quote!(::libafl::mutators);
+        let mut_vec_input = quote!(::libafl::inputs::MutVecInput<'static>);
+        let field_mutation = |mutator: proc_macro2::TokenStream, field_ty| {
+            (
+                quote!((#mutators::FunctionMappingMutator<#mutators::#mutator, #field_ty>, ())),
+                quote!((#mutators::FunctionMappingMutator::new(#field, #mutators::#mutator::new()), ())),
+            )
+        };
+        match self.kind {
+            MappedFieldKind::Bytes if crossover => {
+                let (current_ty, corpus_ty) = (&types[0], &types[1]);
+                vec![(
+                    quote!(#mutators::MappedHavocMutationsType<#current_ty, #corpus_ty, #mut_vec_input, &'static [u8]>),
+                    quote!(#mutators::mapped_havoc_mutations(#current, #corpus)),
+                )]
+            }
+            MappedFieldKind::Bytes => {
+                let current_ty = &types[0];
+                vec![(
+                    quote!(<#mutators::HavocMutationsNoCrossoverType as ::libafl_bolts::tuples::Map<
+                        #mutators::ToMappedInputFunctionMappingMutatorMapper<#current_ty, #mut_vec_input>,
+                    >>::MapResult),
+                    quote!(::libafl_bolts::tuples::Map::map(
+                        #mutators::havoc_mutations_no_crossover(),
+                        #mutators::ToMappedInputFunctionMappingMutatorMapper::new(#current),
+                    )),
+                )]
+            }
+            MappedFieldKind::OptionalBytes => {
+                let (current_ty, corpus_ty, field_ty) = (&types[0], &types[1], &types[2]);
+                let havoc = if crossover {
+                    (
+                        quote!(#mutators::OptionMappedHavocMutationsType<
+                            #current_ty,
+                            #corpus_ty,
+                            Option<#mut_vec_input>,
+                            Option<&'static [u8]>,
+                        >),
+                        quote!(#mutators::optional_mapped_havoc_mutations(#current, #corpus)),
+                    )
+                } else {
+                    (
+                        quote!(<<#mutators::HavocMutationsNoCrossoverType as ::libafl_bolts::tuples::Map<
+                            #mutators::ToOptionMappingMutatorMapper,
+                        >>::MapResult as ::libafl_bolts::tuples::Map<
+                            #mutators::ToMappedInputFunctionMappingMutatorMapper<#current_ty, Option<#mut_vec_input>>,
+                        >>::MapResult),
+                        quote!(::libafl_bolts::tuples::Map::map(
+                            ::libafl_bolts::tuples::Map::map(
+                                #mutators::havoc_mutations_no_crossover(),
+                                #mutators::ToOptionMappingMutatorMapper,
+                            ),
+                            #mutators::ToMappedInputFunctionMappingMutatorMapper::new(#current),
+                        )),
+                    )
+                };
+                vec![
+                    havoc,
+                    field_mutation(quote!(ToggleOptionalBytesMutator), field_ty),
+                ]
+            }
+            MappedFieldKind::Bool => vec![field_mutation(quote!(ToggleBoolMutator), &types[0])],
+            MappedFieldKind::Int => vec![field_mutation(quote!(IntMutator), &types[0])],
+            MappedFieldKind::Nested => {
+                let field_ty = &types[0];
+                vec![(
+                    quote!(<<#ty as #mutators::MappedMutations>::MutationsNoCrossover as ::libafl_bolts::tuples::Map<
+                        #mutators::ToFunctionMappingMutatorMapper<#field_ty>,
+                    >>::MapResult),
+                    quote!(::libafl_bolts::tuples::Map::map(
+                        <#ty as #mutators::MappedMutations>::mapped_mutations_no_crossover(),
+                        #mutators::ToFunctionMappingMutatorMapper::new(#field),
+                    )),
+                )]
+            }
+        }
+    }
+}
+
+/// Checks if `ty` is a path type nested as in `path`, for example `Option<Vec<u8>>` for `["Option", "Vec", "u8"]`
+fn is_type(ty: &Type, path: &[&str]) -> bool {
+    let Some((first, rest)) = path.split_first() else {
+        return false;
+    };
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    if type_path.qself.is_some() || type_path.path.segments.len() != 1 {
+        return false;
+    }
+    let segment = &type_path.path.segments[0];
+    if segment.ident != first {
+        return false;
+    }
+    match (&segment.arguments, rest.is_empty()) {
+        (PathArguments::None, true) => true,
+        (PathArguments::AngleBracketed(args), false) if args.args.len() == 1 => {
+            matches!(&args.args[0], GenericArgument::Type(inner) if is_type(inner, rest))
+        }
+        _ => false,
+    }
+}
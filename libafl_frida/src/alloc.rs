@@ -359,6 +359,21 @@ impl Allocator {
         }
     }
 
+    /// Gets the size of the live allocation at the given allocated pointer, if any
+    #[must_use]
+    pub fn allocation_size(&self, ptr: *mut c_void) -> Option<usize> {
+        self.allocations
+            .get(&(ptr as usize))
+            .filter(|metadata| !metadata.freed)
+            .map(|metadata| metadata.size)
+    }
+
+    /// The page size of the allocations, which are all aligned to it
+    #[must_use]
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
     /// Unpoison an area in memory
     ///
     /// # Safety
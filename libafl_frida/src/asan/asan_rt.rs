@@ -48,7 +48,9 @@ use crate::utils::{instruction_width, writer_register};
 use crate::utils::{operand_details, AccessType};
 use crate::{
     alloc::Allocator,
-    asan::errors::{AsanError, AsanErrors, AsanReadWriteError, ASAN_ERRORS},
+    asan::errors::{
+        AsanError, AsanErrors, AsanGlobalError, AsanReadWriteError, GlobalMetadata, ASAN_ERRORS,
+    },
     helper::{FridaRuntime, SkipRange},
     utils::disas_count,
 };
@@ -139,6 +141,9 @@ pub struct AsanRuntime {
     pc: Option<usize>,
     hooks: Vec<NativePointer>,
     pub(crate) hooks_enabled: bool,
+    poison_globals: bool,
+    /// The globals followed by a poisoned redzone, by the range of the global and its redzone
+    globals: RangeMap<usize, GlobalMetadata>,
     #[cfg(target_arch = "aarch64")]
     eh_frame: [u32; ASAN_EH_FRAME_DWORD_COUNT],
 }
@@ -151,6 +156,7 @@ impl Debug for AsanRuntime {
             .field("module_map", &"<ModuleMap>")
             .field("skip_ranges", &self.skip_ranges)
             .field("suppressed_addresses", &self.suppressed_addresses)
+            .field("poison_globals", &self.poison_globals)
             .finish_non_exhaustive()
     }
 }
@@ -183,6 +189,9 @@ impl FridaRuntime for AsanRuntime {
         self.register_hooks(gum);
         self.generate_instrumentation_blobs();
         self.unpoison_all_existing_memory();
+        if self.poison_globals {
+            self.poison_global_redzones(gum);
+        }
         self.register_thread();
     }
 
@@ -242,6 +251,7 @@ impl AsanRuntime {
             allocator: Allocator::new(options),
             skip_ranges,
             continue_on_error,
+            poison_globals: options.poison_globals,
             ..Self::default()
         }
     }
@@ -312,6 +322,87 @@ impl AsanRuntime {
         self.allocator.unpoison_all_existing_memory();
     }
 
+    /// Poison the padding between the globals of the instrumented modules, so that an access
+    /// running past the end of a global is reported, like with the redzones of compiler `ASan`.
+    ///
+    /// The globals are the sized symbols in the writable mappings of the modules.
+    /// Binaries without a symbol table have no globals to protect.
+    pub fn poison_global_redzones(&mut self, gum: &Gum) {
+        let Some(module_map) = self.module_map.clone() else {
+            return;
+        };
+
+        let mut writable = Vec::new();
+        RangeDetails::enumerate_with_prot(
+            PageProtection::ReadWrite,
+            &mut |range: &RangeDetails| {
+                let start = range.memory_range().base_address().0 as usize;
+                writable.push(start..start + range.memory_range().size());
+                true
+            },
+        );
+
+        let frida_module = Module::obtain(gum);
+        for module in module_map.values() {
+            let symbols = frida_module.enumerate_symbols(&module.name());
+            for range in &writable {
+                let mut globals: Vec<GlobalMetadata> = symbols
+                    .iter()
+                    .filter(|symbol| {
+                        symbol.size > 0
+                            && range.start <= symbol.address
+                            && symbol.address + symbol.size <= range.end
+                    })
+                    .map(|symbol| GlobalMetadata {
+                        name: symbol.name.clone(),
+                        address: symbol.address,
+                        size: symbol.size,
+                    })
+                    .collect();
+                globals.sort_by_key(|global| global.address);
+
+                // aliases and nested symbols share the redzone of the outermost global
+                let mut previous: Option<GlobalMetadata> = None;
+                for global in globals {
+                    if let Some(prev) = &previous {
+                        let end = prev.address + prev.size;
+                        if global.address < end {
+                            if global.address + global.size > end {
+                                previous = Some(global);
+                            }
+                            continue;
+                        }
+                        self.poison_global_redzone(prev, global.address);
+                    }
+                    previous = Some(global);
+                }
+            }
+        }
+        log::info!("Poisoned the redzones of {} globals", self.globals.len());
+    }
+
+    /// Poison the padding after `global`, up to `redzone_end`. The shadow is poisoned from the
+    /// next 8-byte boundary, so that no byte of the global is ever poisoned.
+    fn poison_global_redzone(&mut self, global: &GlobalMetadata, redzone_end: usize) {
+        let redzone_start = (global.address + global.size + 7) & !7;
+        if redzone_start >= redzone_end {
+            return;
+        }
+        // # Safety
+        // The shadow of the writable mappings was mapped by `unpoison_all_existing_memory`.
+        unsafe {
+            self.poison(redzone_start, redzone_end - redzone_start);
+        }
+        self.globals
+            .insert(global.address..redzone_end, global.clone());
+    }
+
+    /// The global whose bounds or redzone contain `address`, if globals are poisoned
+    #[must_use]
+    pub fn global_for_address(&self, address: usize) -> Option<&GlobalMetadata> {
+        self.globals.get(&address)
+    }
+
     /// Enable all function hooks
     pub fn enable_hooks(&mut self) {
         self.hooks_enabled = true;
@@ -646,19 +737,33 @@ impl AsanRuntime {
         #[cfg(not(windows))]
         hook_func!(calloc, (nmemb: usize, size: usize), *mut c_void);
         #[cfg(not(windows))]
-        hook_func!(realloc, (ptr: *mut c_void, size: usize), *mut c_void);
+        hook_func_with_check!(realloc, (ptr: *mut c_void, size: usize), *mut c_void);
         #[cfg(not(windows))]
         hook_func_with_check!(free, (ptr: *mut c_void), usize);
         #[cfg(not(any(target_vendor = "apple", windows)))]
-        hook_func!(memalign, (size: usize, alignment: usize), *mut c_void);
+        hook_func!(memalign, (alignment: usize, size: usize), *mut c_void);
         #[cfg(not(windows))]
         hook_func!(
             posix_memalign,
-            (pptr: *mut *mut c_void, size: usize, alignment: usize),
+            (pptr: *mut *mut c_void, alignment: usize, size: usize),
             i32
         );
+        #[cfg(not(windows))]
+        hook_func!(aligned_alloc, (alignment: usize, size: usize), *mut c_void);
+        #[cfg(any(target_os = "linux", target_vendor = "apple"))]
+        hook_func!(valloc, (size: usize), *mut c_void);
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        hook_func!(pvalloc, (size: usize), *mut c_void);
+        #[cfg(all(target_os = "linux", target_env = "gnu"))]
+        hook_func_with_check!(
+            reallocarray,
+            (ptr: *mut c_void, nmemb: usize, size: usize),
+            *mut c_void
+        );
+        #[cfg(target_vendor = "apple")]
+        hook_func_with_check!(reallocf, (ptr: *mut c_void, size: usize), *mut c_void);
         #[cfg(not(any(target_vendor = "apple", windows)))]
-        hook_func!(malloc_usable_size, (ptr: *mut c_void), usize);
+        hook_func_with_check!(malloc_usable_size, (ptr: *mut c_void), usize);
         // // #[cfg(windows)]
         // hook_priv_func!(
         //     "c:\\windows\\system32\\ntdll.dll",
@@ -824,10 +929,10 @@ impl AsanRuntime {
                         hook_func!($libname, $lib_ident, _o_calloc, (nmemb: usize, size: usize), *mut c_void);
                     }
                     "realloc" => {
-                        hook_func!($libname, $lib_ident, realloc, (ptr: *mut c_void, size: usize), *mut c_void);
+                        hook_func_with_check!($libname, $lib_ident, realloc, (ptr: *mut c_void, size: usize), *mut c_void);
                     }
                     "_o_realloc" | "o_realloc" => {
-                        hook_func!($libname, $lib_ident, _o_realloc, (ptr: *mut c_void, size: usize), *mut c_void);
+                        hook_func_with_check!($libname, $lib_ident, _o_realloc, (ptr: *mut c_void, size: usize), *mut c_void);
                     }
                     "free" => {
                         hook_func_with_check!($libname, $lib_ident, free, (ptr: *mut c_void), usize);
@@ -1361,6 +1466,24 @@ impl AsanRuntime {
                         backtrace,
                     )),
                 }
+            } else if let Some(global) = self.globals.get(&fault_address).cloned() {
+                let asan_global_error = AsanGlobalError {
+                    registers: self.regs,
+                    pc: actual_pc,
+                    fault: (base_idx, index_idx, disp as usize, fault_address),
+                    global,
+                    backtrace,
+                };
+                match access_type {
+                    Some(AccessType::Read) => AsanError::GlobalOobRead(asan_global_error),
+                    Some(AccessType::Write) => AsanError::GlobalOobWrite(asan_global_error),
+                    None => AsanError::Unknown((
+                        asan_global_error.registers,
+                        asan_global_error.pc,
+                        asan_global_error.fault,
+                        asan_global_error.backtrace,
+                    )),
+                }
             } else if base_value.is_some() {
                 if let Some(metadata) = self
                     .allocator
@@ -1515,6 +1638,24 @@ impl AsanRuntime {
                     backtrace,
                 ))
             }
+        } else if let Some(global) = self.globals.get(&fault_address).cloned() {
+            let asan_global_error = AsanGlobalError {
+                registers: self.regs,
+                pc: actual_pc,
+                fault: (
+                    Some(base_reg),
+                    Some(index_reg.unwrap_or(0xffff)),
+                    displacement as usize,
+                    fault_address,
+                ),
+                global,
+                backtrace,
+            };
+            if insn.opcode.to_string().starts_with('l') {
+                AsanError::GlobalOobRead(asan_global_error)
+            } else {
+                AsanError::GlobalOobWrite(asan_global_error)
+            }
         } else if let Some(metadata) = self
             .allocator
             .find_metadata(fault_address, self.regs[base_reg as usize])
@@ -2728,6 +2869,8 @@ impl Default for AsanRuntime {
             pc: None,
             hooks: Vec::new(),
             hooks_enabled: false,
+            poison_globals: false,
+            globals: RangeMap::new(),
         }
     }
 }
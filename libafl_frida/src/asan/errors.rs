@@ -51,6 +51,80 @@ pub(crate) struct AsanReadWriteError {
     pub backtrace: Backtrace,
 }
 
+/// A global of an instrumented module, followed by a poisoned redzone
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GlobalMetadata {
+    /// The symbol of the global
+    pub name: String,
+    /// The address of the global
+    pub address: usize,
+    /// The size of the global, without its redzone
+    pub size: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct AsanGlobalError {
+    pub registers: [usize; ASAN_SAVE_REGISTER_COUNT],
+    pub pc: usize,
+    pub fault: (Option<u16>, Option<u16>, usize, usize),
+    pub global: GlobalMetadata,
+    pub backtrace: Backtrace,
+}
+
+/// The kind of a memory error caught by the `ASan` runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AsanErrorKind {
+    /// A read past the bounds of a heap allocation
+    HeapOobRead,
+    /// A write past the bounds of a heap allocation
+    HeapOobWrite,
+    /// A read of a freed heap allocation
+    HeapReadAfterFree,
+    /// A write to a freed heap allocation
+    HeapWriteAfterFree,
+    /// A heap allocation freed twice
+    DoubleFree,
+    /// A free of a pointer that was never allocated
+    UnallocatedFree,
+    /// A read past the end of a global
+    GlobalOobRead,
+    /// A write past the end of a global
+    GlobalOobWrite,
+    /// A read past the bounds of a stack frame
+    StackOobRead,
+    /// A write past the bounds of a stack frame
+    StackOobWrite,
+    /// A function argument resulting in a bad read
+    BadFuncArgRead,
+    /// A function argument resulting in a bad write
+    BadFuncArgWrite,
+    /// A heap allocation not freed at the end of the run
+    Leak,
+    /// A poisoned access the runtime could not attribute
+    Unknown,
+}
+
+/// A structured report of a memory error, see [`AsanErrorsObserver::reports`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AsanErrorReport {
+    /// The kind of the error
+    pub kind: AsanErrorKind,
+    /// The human-readable kind of the error, e.g. "heap out-of-bounds read"
+    pub description: String,
+    /// The faulting instruction, or the call with a bad argument
+    pub pc: Option<usize>,
+    /// The faulting address, or the freed or leaked pointer
+    pub address: usize,
+    /// The path of the module containing `pc`, and the offset of `pc` in it
+    pub module: Option<(String, usize)>,
+    /// The address and size of the heap allocation the error is about
+    pub allocation: Option<(usize, usize)>,
+    /// The global the access ran past
+    pub global: Option<GlobalMetadata>,
+    /// The function called with a bad argument
+    pub function: Option<String>,
+}
+
 #[allow(clippy::type_complexity)]
 #[derive(Debug, Clone, Serialize, Deserialize, SerdeAny)]
 pub(crate) enum AsanError {
@@ -58,6 +132,8 @@ pub(crate) enum AsanError {
     OobWrite(AsanReadWriteError),
     ReadAfterFree(AsanReadWriteError),
     WriteAfterFree(AsanReadWriteError),
+    GlobalOobRead(AsanGlobalError),
+    GlobalOobWrite(AsanGlobalError),
     DoubleFree((usize, AllocationMetadata, Backtrace)),
     UnallocatedFree((usize, Backtrace)),
     Unknown(
@@ -98,6 +174,8 @@ impl AsanError {
             AsanError::UnallocatedFree(_) => "unallocated-free",
             AsanError::WriteAfterFree(_) => "heap use-after-free write",
             AsanError::ReadAfterFree(_) => "heap use-after-free read",
+            AsanError::GlobalOobRead(_) => "global out-of-bounds read",
+            AsanError::GlobalOobWrite(_) => "global out-of-bounds write",
             AsanError::Unknown(_) => "heap unknown",
             AsanError::Leak(_) => "memory-leak",
             AsanError::StackOobRead(_) => "stack out-of-bounds read",
@@ -106,6 +184,87 @@ impl AsanError {
             AsanError::BadFuncArgWrite(_) => "function arg resulting in bad write",
         }
     }
+
+    /// The structured report of this error
+    fn report(&self) -> AsanErrorReport {
+        let mut report = AsanErrorReport {
+            kind: AsanErrorKind::Unknown,
+            description: self.description().to_string(),
+            pc: None,
+            address: 0,
+            module: None,
+            allocation: None,
+            global: None,
+            function: None,
+        };
+        match self {
+            AsanError::OobRead(error)
+            | AsanError::OobWrite(error)
+            | AsanError::ReadAfterFree(error)
+            | AsanError::WriteAfterFree(error) => {
+                report.kind = match self {
+                    AsanError::OobRead(_) => AsanErrorKind::HeapOobRead,
+                    AsanError::OobWrite(_) => AsanErrorKind::HeapOobWrite,
+                    AsanError::ReadAfterFree(_) => AsanErrorKind::HeapReadAfterFree,
+                    _ => AsanErrorKind::HeapWriteAfterFree,
+                };
+                report.pc = Some(error.pc);
+                report.address = error.fault.3;
+                report.allocation = Some((error.metadata.address + 0x1000, error.metadata.size));
+            }
+            AsanError::GlobalOobRead(error) | AsanError::GlobalOobWrite(error) => {
+                report.kind = if matches!(self, AsanError::GlobalOobRead(_)) {
+                    AsanErrorKind::GlobalOobRead
+                } else {
+                    AsanErrorKind::GlobalOobWrite
+                };
+                report.pc = Some(error.pc);
+                report.address = error.fault.3;
+                report.global = Some(error.global.clone());
+            }
+            AsanError::DoubleFree((ptr, metadata, _)) => {
+                report.kind = AsanErrorKind::DoubleFree;
+                report.address = *ptr;
+                report.allocation = Some((metadata.address + 0x1000, metadata.size));
+            }
+            AsanError::UnallocatedFree((ptr, _)) => {
+                report.kind = AsanErrorKind::UnallocatedFree;
+                report.address = *ptr;
+            }
+            AsanError::Leak((ptr, metadata)) => {
+                report.kind = AsanErrorKind::Leak;
+                report.address = *ptr;
+                report.allocation = Some((metadata.address + 0x1000, metadata.size));
+            }
+            AsanError::Unknown((_, pc, fault, _))
+            | AsanError::StackOobRead((_, pc, fault, _))
+            | AsanError::StackOobWrite((_, pc, fault, _)) => {
+                report.kind = match self {
+                    AsanError::StackOobRead(_) => AsanErrorKind::StackOobRead,
+                    AsanError::StackOobWrite(_) => AsanErrorKind::StackOobWrite,
+                    _ => AsanErrorKind::Unknown,
+                };
+                report.pc = Some(*pc);
+                report.address = fault.3;
+            }
+            AsanError::BadFuncArgRead((name, pc, address, _, _))
+            | AsanError::BadFuncArgWrite((name, pc, address, _, _)) => {
+                report.kind = if matches!(self, AsanError::BadFuncArgRead(_)) {
+                    AsanErrorKind::BadFuncArgRead
+                } else {
+                    AsanErrorKind::BadFuncArgWrite
+                };
+                report.pc = Some(*pc);
+                report.address = *address;
+                report.function = Some(name.clone());
+            }
+        }
+        report.module = report.pc.and_then(|pc| {
+            ModuleDetails::with_address(pc as u64)
+                .map(|module| (module.path(), pc - module.range().base_address().0 as usize))
+        });
+        report
+    }
 }
 
 /// A struct holding errors that occurred during frida address sanitizer runs
@@ -114,6 +273,7 @@ impl AsanError {
 pub struct AsanErrors {
     continue_on_error: bool,
     pub(crate) errors: Vec<AsanError>,
+    reports: Vec<AsanErrorReport>,
 }
 
 impl AsanErrors {
@@ -122,6 +282,7 @@ impl AsanErrors {
     pub const fn new(continue_on_error: bool) -> Self {
         Self {
             errors: Vec::new(),
+            reports: Vec::new(),
             continue_on_error,
         }
     }
@@ -129,6 +290,7 @@ impl AsanErrors {
     /// Clears this `AsanErrors` struct
     pub fn clear(&mut self) {
         self.errors.clear();
+        self.reports.clear();
     }
 
     /// Gets the amount of `AsanErrors` in this struct
@@ -143,6 +305,12 @@ impl AsanErrors {
         self.errors.is_empty()
     }
 
+    /// The structured reports of the errors, in the order they occurred
+    #[must_use]
+    pub fn reports(&self) -> &[AsanErrorReport] {
+        &self.reports
+    }
+
     /// Get a mutable reference to the global [`struct@AsanErrors`] object
     pub fn get_mut_blocking() -> MutexGuard<'static, Self> {
         ASAN_ERRORS.lock().unwrap()
@@ -380,6 +548,43 @@ impl AsanErrors {
 
                 backtrace_printer.print_trace(backtrace, output).unwrap();
             }
+            AsanError::GlobalOobRead(error) | AsanError::GlobalOobWrite(error) => {
+                let fault_address = error.fault.3;
+                if let Some(module_details) = ModuleDetails::with_address(error.pc as u64) {
+                    writeln!(
+                        output,
+                        " at 0x{:x} ({}@0x{:04x}), faulting address 0x{:x}",
+                        error.pc,
+                        module_details.path(),
+                        error.pc - module_details.range().base_address().0 as usize,
+                        fault_address
+                    )
+                    .unwrap();
+                } else {
+                    writeln!(
+                        output,
+                        " at 0x{:x}, faulting address 0x{fault_address:x}",
+                        error.pc
+                    )
+                    .unwrap();
+                }
+                output.reset().unwrap();
+                backtrace_printer
+                    .print_trace(&error.backtrace, output)
+                    .unwrap();
+
+                #[allow(clippy::non_ascii_literal)]
+                writeln!(output, "{:━^100}", " GLOBAL INFO ").unwrap();
+                writeln!(
+                    output,
+                    "access is {:#x} to the right of the {:#x} byte global {} at {:#x}",
+                    fault_address.saturating_sub(error.global.address + error.global.size),
+                    error.global.size,
+                    error.global.name,
+                    error.global.address
+                )
+                .unwrap();
+            }
             AsanError::DoubleFree((ptr, metadata, backtrace)) => {
                 writeln!(output, " of {ptr:?}").unwrap();
                 output.reset().unwrap();
@@ -556,6 +761,7 @@ impl AsanErrors {
             }
         };
 
+        self.reports.push(error.report());
         self.errors.push(error);
 
         #[allow(clippy::manual_assert)]
@@ -637,6 +843,18 @@ impl AsanErrorsObserver {
             Self::Static => AsanErrors::get_mut_blocking().clone(),
         }
     }
+
+    /// Gets the structured reports of the errors from the previous run
+    #[must_use]
+    pub fn reports(&self) -> Vec<AsanErrorReport> {
+        match self {
+            Self::Ptr(errors) => match errors {
+                OwnedPtr::Ptr(p) => unsafe { p.as_ref().unwrap().reports().to_vec() },
+                OwnedPtr::Owned(b) => b.reports().to_vec(),
+            },
+            Self::Static => AsanErrors::get_mut_blocking().reports().to_vec(),
+        }
+    }
 }
 
 /// A feedback reporting potential [`struct@AsanErrors`] from an `AsanErrorsObserver`
@@ -1,7 +1,7 @@
 #![allow(clippy::used_underscore_items)]
 
 //! The allocator hooks for address sanitizer.
-use std::ffi::c_void;
+use std::{ffi::c_void, mem::size_of};
 
 use backtrace::Backtrace;
 use libc::{c_char, wchar_t};
@@ -849,14 +849,7 @@ impl AsanRuntime {
         nmemb: usize,
         size: usize,
     ) -> *mut c_void {
-        extern "system" {
-            fn memset(s: *mut c_void, c: i32, n: usize) -> *mut c_void;
-        }
-        let ret = unsafe { self.allocator_mut().alloc(size * nmemb, 8) };
-        unsafe {
-            memset(ret, 0, size * nmemb);
-        }
-        ret
+        self.asan_calloc(nmemb, size)
     }
 
     #[allow(non_snake_case)]
@@ -867,56 +860,45 @@ impl AsanRuntime {
         nmemb: usize,
         size: usize,
     ) -> *mut c_void {
-        extern "system" {
-            fn memset(s: *mut c_void, c: i32, n: usize) -> *mut c_void;
-        }
-        let ret = unsafe { self.allocator_mut().alloc(size * nmemb, 8) };
-        unsafe {
-            memset(ret, 0, size * nmemb);
+        self.asan_calloc(nmemb, size)
+    }
+
+    /// Only reallocate the pointers of the `ASan` allocator, even out of the target,
+    /// and new allocations while the hooks are enabled
+    #[inline]
+    pub fn hook_check_realloc(&mut self, ptr: *mut c_void, _size: usize) -> bool {
+        if ptr.is_null() {
+            self.hooks_enabled
+        } else {
+            self.allocator_mut().is_managed(ptr)
         }
-        ret
     }
 
-    #[allow(non_snake_case)]
     #[inline]
-    #[allow(clippy::cmp_null)]
     pub fn hook_realloc(
         &mut self,
         _original: extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void,
         ptr: *mut c_void,
         size: usize,
     ) -> *mut c_void {
-        unsafe {
-            let ret = self.allocator_mut().alloc(size, 0x8);
-            if ptr != std::ptr::null_mut() && ret != std::ptr::null_mut() {
-                let old_size = self.allocator_mut().get_usable_size(ptr);
-                let copy_size = if size < old_size { size } else { old_size };
-                (ptr as *mut u8).copy_to(ret as *mut u8, copy_size);
-            }
-            self.allocator_mut().release(ptr);
-            ret
-        }
+        self.asan_realloc(ptr, size)
+    }
+
+    #[allow(non_snake_case)]
+    #[inline]
+    pub fn hook_check__o_realloc(&mut self, ptr: *mut c_void, size: usize) -> bool {
+        self.hook_check_realloc(ptr, size)
     }
 
     #[allow(non_snake_case)]
     #[inline]
-    #[allow(clippy::cmp_null)]
     pub fn hook__o_realloc(
         &mut self,
         _original: extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void,
         ptr: *mut c_void,
         size: usize,
     ) -> *mut c_void {
-        unsafe {
-            let ret = self.allocator_mut().alloc(size, 0x8);
-            if ptr != std::ptr::null_mut() && ret != std::ptr::null_mut() {
-                let old_size = self.allocator_mut().get_usable_size(ptr);
-                let copy_size = if size < old_size { size } else { old_size };
-                (ptr as *mut u8).copy_to(ret as *mut u8, copy_size);
-            }
-            self.allocator_mut().release(ptr);
-            ret
-        }
+        self.asan_realloc(ptr, size)
     }
 
     #[allow(non_snake_case)]
@@ -975,12 +957,115 @@ impl AsanRuntime {
         alignment: usize,
         size: usize,
     ) -> i32 {
+        if !alignment.is_power_of_two() || alignment < size_of::<*mut c_void>() {
+            return libc::EINVAL;
+        }
+        let ret = unsafe { self.allocator_mut().alloc(size, alignment) };
+        if ret.is_null() {
+            return libc::ENOMEM;
+        }
         unsafe {
-            *pptr = self.allocator_mut().alloc(size, alignment);
+            *pptr = ret;
         }
         0
     }
 
+    #[cfg(not(windows))]
+    #[inline]
+    pub fn hook_aligned_alloc(
+        &mut self,
+        _original: extern "C" fn(alignment: usize, size: usize) -> *mut c_void,
+        alignment: usize,
+        size: usize,
+    ) -> *mut c_void {
+        if !alignment.is_power_of_two() {
+            return std::ptr::null_mut();
+        }
+        unsafe { self.allocator_mut().alloc(size, alignment) }
+    }
+
+    #[cfg(any(target_os = "linux", target_vendor = "apple"))]
+    #[inline]
+    pub fn hook_valloc(
+        &mut self,
+        _original: extern "C" fn(size: usize) -> *mut c_void,
+        size: usize,
+    ) -> *mut c_void {
+        unsafe {
+            self.allocator_mut()
+                .alloc(size, self.allocator().page_size())
+        }
+    }
+
+    /// Like `valloc`, rounding the size up to a multiple of the page size
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[inline]
+    pub fn hook_pvalloc(
+        &mut self,
+        _original: extern "C" fn(size: usize) -> *mut c_void,
+        size: usize,
+    ) -> *mut c_void {
+        let page_size = self.allocator().page_size();
+        let Some(size) = size.max(1).checked_next_multiple_of(page_size) else {
+            return std::ptr::null_mut();
+        };
+        unsafe { self.allocator_mut().alloc(size, page_size) }
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[inline]
+    pub fn hook_check_reallocarray(
+        &mut self,
+        ptr: *mut c_void,
+        _nmemb: usize,
+        size: usize,
+    ) -> bool {
+        self.hook_check_realloc(ptr, size)
+    }
+
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    #[inline]
+    pub fn hook_reallocarray(
+        &mut self,
+        _original: extern "C" fn(ptr: *mut c_void, nmemb: usize, size: usize) -> *mut c_void,
+        ptr: *mut c_void,
+        nmemb: usize,
+        size: usize,
+    ) -> *mut c_void {
+        match nmemb.checked_mul(size) {
+            Some(total) => self.asan_realloc(ptr, total),
+            None => std::ptr::null_mut(),
+        }
+    }
+
+    #[cfg(target_vendor = "apple")]
+    #[inline]
+    pub fn hook_check_reallocf(&mut self, ptr: *mut c_void, size: usize) -> bool {
+        self.hook_check_realloc(ptr, size)
+    }
+
+    /// Like `realloc`, freeing the allocation if it cannot be reallocated
+    #[cfg(target_vendor = "apple")]
+    #[inline]
+    pub fn hook_reallocf(
+        &mut self,
+        _original: extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void,
+        ptr: *mut c_void,
+        size: usize,
+    ) -> *mut c_void {
+        let ret = self.asan_realloc(ptr, size);
+        if ret.is_null() && !ptr.is_null() && self.allocator().allocation_size(ptr).is_some() {
+            unsafe { self.allocator_mut().release(ptr) };
+        }
+        ret
+    }
+
+    #[inline]
+    #[cfg(not(target_vendor = "apple"))]
+    pub fn hook_check_malloc_usable_size(&mut self, ptr: *mut c_void) -> bool {
+        self.allocator_mut().is_managed(ptr)
+    }
+
     #[inline]
     #[cfg(not(target_vendor = "apple"))]
     pub fn hook_malloc_usable_size(
@@ -988,7 +1073,45 @@ impl AsanRuntime {
         _original: extern "C" fn(ptr: *mut c_void) -> usize,
         ptr: *mut c_void,
     ) -> usize {
-        self.allocator_mut().get_usable_size(ptr)
+        self.allocator().allocation_size(ptr).unwrap_or(0)
+    }
+
+    /// `calloc` on the `ASan` allocator, failing if `nmemb * size` overflows
+    fn asan_calloc(&mut self, nmemb: usize, size: usize) -> *mut c_void {
+        let Some(total) = nmemb.checked_mul(size) else {
+            return std::ptr::null_mut();
+        };
+        let ret = unsafe { self.allocator_mut().alloc(total, 8) };
+        if !ret.is_null() {
+            unsafe { ret.cast::<u8>().write_bytes(0, total) };
+        }
+        ret
+    }
+
+    /// `realloc` on the `ASan` allocator. A null `ptr` allocates, and a zero `size` frees.
+    /// Reallocating a pointer that is not a live allocation is reported as a bad free.
+    fn asan_realloc(&mut self, ptr: *mut c_void, size: usize) -> *mut c_void {
+        if ptr.is_null() {
+            return unsafe { self.allocator_mut().alloc(size, 8) };
+        }
+        let Some(old_size) = self.allocator().allocation_size(ptr) else {
+            unsafe { self.allocator_mut().release(ptr) };
+            return std::ptr::null_mut();
+        };
+        if size == 0 {
+            unsafe { self.allocator_mut().release(ptr) };
+            return std::ptr::null_mut();
+        }
+        let ret = unsafe { self.allocator_mut().alloc(size, 8) };
+        // on failure, the old allocation stays valid
+        if !ret.is_null() {
+            unsafe {
+                ptr.cast::<u8>()
+                    .copy_to_nonoverlapping(ret.cast::<u8>(), size.min(old_size));
+                self.allocator_mut().release(ptr);
+            }
+        }
+        ret
     }
 
     #[inline]
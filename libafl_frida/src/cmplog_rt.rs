@@ -5,15 +5,16 @@
 //! This allows the fuzzer to potentially solve the compares, if a compare value is directly
 //! related to the input.
 //! Read the [`RedQueen`](https://www.ndss-symposium.org/ndss-paper/redqueen-fuzzing-with-input-to-state-correspondence/) paper for the general concepts.
+//!
+//! Besides the compare instructions, the runtime intercepts `memcmp`, `strcmp` and `strncmp`,
+//! so that the compares of closed-source libraries calling them are logged as routines.
 
-#[cfg(target_arch = "aarch64")]
-use core::ffi::c_void;
-use std::rc::Rc;
+use core::ffi::{c_char, c_int, c_void};
+use std::{cell::Cell, rc::Rc, sync::OnceLock};
 
 use dynasmrt::dynasm;
 #[cfg(target_arch = "aarch64")]
 use dynasmrt::{DynasmApi, DynasmLabelApi};
-use frida_gum::ModuleMap;
 #[cfg(target_arch = "x86_64")]
 use frida_gum::{instruction_writer::InstructionWriter, stalker::StalkerOutput};
 #[cfg(target_arch = "aarch64")]
@@ -21,6 +22,7 @@ use frida_gum::{
     instruction_writer::{Aarch64Register, IndexMode, InstructionWriter},
     stalker::StalkerOutput,
 };
+use frida_gum::{interceptor::Interceptor, Gum, Module, ModuleMap, NativePointer};
 use frida_gum_sys::Insn;
 #[cfg(all(feature = "cmplog", target_arch = "x86_64"))]
 use hashbrown::HashMap;
@@ -33,7 +35,11 @@ use libafl::{
     inputs::{HasTargetBytes, Input},
     Error,
 };
-use libafl_targets::{cmps::__libafl_targets_cmplog_instructions, CMPLOG_MAP_W};
+use libafl_targets::{
+    cmps::__libafl_targets_cmplog_instructions, sancov_cmp::__sanitizer_weak_hook_memcmp,
+    sancov_cmp::__sanitizer_weak_hook_strcmp, sancov_cmp::__sanitizer_weak_hook_strncmp,
+    CMPLOG_MAP_W,
+};
 use rangemap::RangeMap;
 
 use crate::helper::FridaRuntime;
@@ -74,6 +80,65 @@ fn gum_red_zone_size_i32() -> i32 {
     frida_gum_sys::GUM_RED_ZONE_SIZE as i32
 }
 
+/// The bytes pushed by the `save_registers` blob, including the skipped red zone
+#[cfg(all(feature = "cmplog", windows, target_arch = "x86_64"))]
+const SAVED_REGISTERS_SIZE: i64 = 0x38;
+
+/// The bytes pushed by the `save_registers` blob, including the skipped red zone
+#[cfg(all(feature = "cmplog", unix, target_arch = "x86_64"))]
+const SAVED_REGISTERS_SIZE: i64 = 0x48 + 0x80;
+
+/// Is `reg` a 32-bit register?
+#[cfg(all(feature = "cmplog", target_arch = "aarch64"))]
+fn is_w_register(reg: Aarch64Register) -> bool {
+    (Aarch64Register::W0 as u32..=Aarch64Register::W30 as u32).contains(&(reg as u32))
+        || matches!(reg, Aarch64Register::Wsp | Aarch64Register::Wzr)
+}
+
+thread_local! {
+    static CMPLOG_IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+type MemcmpFn = unsafe extern "C" fn(*const c_void, *const c_void, usize) -> c_int;
+type StrcmpFn = unsafe extern "C" fn(*const c_char, *const c_char) -> c_int;
+type StrncmpFn = unsafe extern "C" fn(*const c_char, *const c_char, usize) -> c_int;
+
+static MEMCMP_PTR: OnceLock<MemcmpFn> = OnceLock::new();
+static STRCMP_PTR: OnceLock<StrcmpFn> = OnceLock::new();
+static STRNCMP_PTR: OnceLock<StrncmpFn> = OnceLock::new();
+
+/// Log a routine compare, unless this thread is already logging one.
+/// The compare is keyed by the return address of the intercepted call.
+fn log_routine<F>(log: F)
+where
+    F: FnOnce(*const c_void),
+{
+    if !CMPLOG_IN_HOOK.get() {
+        CMPLOG_IN_HOOK.set(true);
+        let called_pc = Interceptor::current_invocation().return_addr() as *const c_void;
+        log(called_pc);
+        CMPLOG_IN_HOOK.set(false);
+    }
+}
+
+unsafe extern "C" fn replacement_memcmp(s1: *const c_void, s2: *const c_void, n: usize) -> c_int {
+    let result = (MEMCMP_PTR.get().unwrap())(s1, s2, n);
+    log_routine(|called_pc| __sanitizer_weak_hook_memcmp(called_pc, s1, s2, n, result));
+    result
+}
+
+unsafe extern "C" fn replacement_strcmp(s1: *const c_char, s2: *const c_char) -> c_int {
+    let result = (STRCMP_PTR.get().unwrap())(s1, s2);
+    log_routine(|called_pc| __sanitizer_weak_hook_strcmp(called_pc, s1, s2, result));
+    result
+}
+
+unsafe extern "C" fn replacement_strncmp(s1: *const c_char, s2: *const c_char, n: usize) -> c_int {
+    let result = (STRNCMP_PTR.get().unwrap())(s1, s2, n);
+    log_routine(|called_pc| __sanitizer_weak_hook_strncmp(called_pc, s1, s2, n, result));
+    result
+}
+
 /// The type of an operand loggged during `CmpLog`
 #[derive(Debug, Clone, Copy)]
 #[cfg(all(feature = "cmplog", target_arch = "aarch64"))]
@@ -107,6 +172,8 @@ pub struct CmpLogRuntime {
     ops_save_register_and_blr_to_populate: Option<Box<[u8]>>,
     ops_handle_tbz_masking: Option<Box<[u8]>>,
     ops_handle_tbnz_masking: Option<Box<[u8]>>,
+    hook_routines: bool,
+    hooks: Vec<NativePointer>,
 }
 
 /// `Frida`-based binary-only innstrumentation that logs compares to the fuzzer
@@ -116,6 +183,10 @@ pub struct CmpLogRuntime {
 pub struct CmpLogRuntime {
     save_registers: Option<Box<[u8]>>,
     restore_registers: Option<Box<[u8]>>,
+    align_stack: Option<Box<[u8]>>,
+    restore_stack: Option<Box<[u8]>>,
+    hook_routines: bool,
+    hooks: Vec<NativePointer>,
 }
 
 impl FridaRuntime for CmpLogRuntime {
@@ -123,14 +194,22 @@ impl FridaRuntime for CmpLogRuntime {
     /// This will generate the instrumentation blobs for the current arch.
     fn init(
         &mut self,
-        _gum: &frida_gum::Gum,
+        gum: &Gum,
         _ranges: &RangeMap<u64, (u16, String)>,
         _module_map: &Rc<ModuleMap>,
     ) {
         self.generate_instrumentation_blobs();
+        if self.hook_routines {
+            self.register_hooks(gum);
+        }
     }
 
-    fn deinit(&mut self, _gum: &frida_gum::Gum) {}
+    fn deinit(&mut self, gum: &Gum) {
+        let mut interceptor = Interceptor::obtain(gum);
+        for hook in self.hooks.drain(..) {
+            interceptor.revert(hook);
+        }
+    }
 
     fn pre_exec<I: Input + HasTargetBytes>(&mut self, _input: &I) -> Result<(), Error> {
         Ok(())
@@ -150,6 +229,8 @@ impl CmpLogRuntime {
             ops_save_register_and_blr_to_populate: None,
             ops_handle_tbz_masking: None,
             ops_handle_tbnz_masking: None,
+            hook_routines: true,
+            hooks: Vec::new(),
         }
     }
 
@@ -160,9 +241,50 @@ impl CmpLogRuntime {
         Self {
             save_registers: None,
             restore_registers: None,
+            align_stack: None,
+            restore_stack: None,
+            hook_routines: true,
+            hooks: Vec::new(),
         }
     }
 
+    /// Whether to intercept `memcmp`, `strcmp` and `strncmp` to log their operands. Enabled by default.
+    #[must_use]
+    pub fn hook_routines(mut self, hook_routines: bool) -> Self {
+        self.hook_routines = hook_routines;
+        self
+    }
+
+    /// Intercept the compare routines exported by the loaded modules
+    fn register_hooks(&mut self, gum: &Gum) {
+        let mut interceptor = Interceptor::obtain(gum);
+        let module = Module::obtain(gum);
+
+        macro_rules! hook_routine {
+            ($name:literal, $ptr:ident, $fn_type:ty, $replacement:ident) => {
+                if let Some(target_function) = module.find_export_by_name(None, $name) {
+                    let _ = $ptr.set(unsafe {
+                        std::mem::transmute::<*mut c_void, $fn_type>(target_function.0)
+                    });
+                    match interceptor.replace(
+                        target_function,
+                        NativePointer($replacement as *mut c_void),
+                        NativePointer(core::ptr::null_mut()),
+                    ) {
+                        Ok(_) => self.hooks.push(target_function),
+                        Err(err) => log::warn!("CmpLog: failed to hook {}: {err:?}", $name),
+                    }
+                } else {
+                    log::warn!("CmpLog: {} not found, its compares are not logged", $name);
+                }
+            };
+        }
+
+        hook_routine!("memcmp", MEMCMP_PTR, MemcmpFn, replacement_memcmp);
+        hook_routine!("strcmp", STRCMP_PTR, StrcmpFn, replacement_strcmp);
+        hook_routine!("strncmp", STRNCMP_PTR, StrncmpFn, replacement_strncmp);
+    }
+
     /// Call the external function that populates the `cmplog_map` with the relevant values
     #[allow(clippy::unused_self)]
     #[cfg(target_arch = "aarch64")]
@@ -344,21 +466,50 @@ impl CmpLogRuntime {
         let mut restore_registers = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
         restore_registers!(restore_registers);
         self.restore_registers = Some(restore_registers.finalize().unwrap().into_boxed_slice());
+
+        // the callee may use the 32 bytes of shadow space above the return address
+        macro_rules! align_stack {
+            ($ops:ident) => {dynasm!($ops
+                ; .arch x64
+                ; push rbx
+                ; mov rbx, rsp
+                ; and rsp, -16
+                ; sub rsp, 0x20
+            );};
+        }
+        let mut align_stack = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        align_stack!(align_stack);
+        self.align_stack = Some(align_stack.finalize().unwrap().into_boxed_slice());
+
+        macro_rules! restore_stack {
+            ($ops:ident) => {dynasm!($ops
+                ; .arch x64
+                ; mov rsp, rbx
+                ; pop rbx
+            );};
+        }
+        let mut restore_stack = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        restore_stack!(restore_stack);
+        self.restore_stack = Some(restore_stack.finalize().unwrap().into_boxed_slice());
     }
 
     #[allow(clippy::similar_names)]
     #[cfg(all(unix, target_arch = "x86_64"))]
     fn generate_instrumentation_blobs(&mut self) {
+        // skip the red zone, which the pushes would clobber, and save rax last
         macro_rules! save_registers {
             ($ops:ident) => {dynasm!($ops
                 ; .arch x64
-                ; push rax
+                ; lea rsp, [rsp - 0x80]
                 ; push rdi
                 ; push rsi
                 ; push rdx
                 ; push rcx
                 ; push r8
                 ; push r9
+                ; push r10
+                ; push r11
+                ; push rax
             );};
         }
         let mut save_registers = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
@@ -368,18 +519,44 @@ impl CmpLogRuntime {
         macro_rules! restore_registers {
             ($ops:ident) => {dynasm!($ops
                 ; .arch x64
+                ; pop rax
+                ; pop r11
+                ; pop r10
                 ; pop r9
                 ; pop r8
                 ; pop rcx
                 ; pop rdx
                 ; pop rsi
                 ; pop rdi
-                ; pop rax
+                ; lea rsp, [rsp + 0x80]
             );};
         }
         let mut restore_registers = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
         restore_registers!(restore_registers);
         self.restore_registers = Some(restore_registers.finalize().unwrap().into_boxed_slice());
+
+        macro_rules! align_stack {
+            ($ops:ident) => {dynasm!($ops
+                ; .arch x64
+                ; push rbx
+                ; mov rbx, rsp
+                ; and rsp, -16
+            );};
+        }
+        let mut align_stack = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        align_stack!(align_stack);
+        self.align_stack = Some(align_stack.finalize().unwrap().into_boxed_slice());
+
+        macro_rules! restore_stack {
+            ($ops:ident) => {dynasm!($ops
+                ; .arch x64
+                ; mov rsp, rbx
+                ; pop rbx
+            );};
+        }
+        let mut restore_stack = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        restore_stack!(restore_stack);
+        self.restore_stack = Some(restore_stack.finalize().unwrap().into_boxed_slice());
     }
 
     /// Get the blob which saves the context, jumps to the populate function and restores the context
@@ -448,7 +625,7 @@ impl CmpLogRuntime {
         }
         #[cfg(unix)]
         {
-            arg_reg_1 = Register::DL;
+            arg_reg_1 = Register::DIL;
             arg_reg_2 = Register::RSI;
             arg_reg_3 = Register::RDX;
             arg_reg_4 = Register::RCX;
@@ -509,8 +686,7 @@ impl CmpLogRuntime {
                     let mut disp_adjusted = *disp;
                     let mut reg_base = *reg_base;
                     if reg_base == Register::RSP {
-                        // 0x38 is an amount of bytes used by save_registers()
-                        disp_adjusted = disp_adjusted + 0x38 + 8_i64 * op_num;
+                        disp_adjusted = disp_adjusted + SAVED_REGISTERS_SIZE + 8_i64 * op_num;
                     }
                     let tmp_reg_adjusted = *tmp_reg.get(&size).unwrap();
                     // in case of RIP, disp is an absolute address already calculated
@@ -553,7 +729,9 @@ impl CmpLogRuntime {
         let block = InstructionBlock::new(&insts, 0);
         let block = BlockEncoder::encode(64, block, DecoderOptions::NONE).unwrap();
         writer.put_bytes(block.code_buffer.as_slice());
+        writer.put_bytes(self.align_stack.as_ref().unwrap());
         writer.put_call_address((CmpLogRuntime::populate_lists as usize).try_into().unwrap());
+        writer.put_bytes(self.restore_stack.as_ref().unwrap());
 
         writer.put_bytes(&self.restore_registers.clone().unwrap());
     }
@@ -568,7 +746,7 @@ impl CmpLogRuntime {
         output: &StalkerOutput,
         op1: &CmplogOperandType, //first operand of the comparsion
         op2: &CmplogOperandType, //second operand of the comparsion
-        _shift: &Option<(ShiftStyle, u8)>,
+        shift: &Option<(ShiftStyle, u8)>,
         special_case: &Option<SpecialCmpLogCase>,
     ) {
         let writer = output.writer();
@@ -628,6 +806,28 @@ impl CmpLogRuntime {
             },
         }
 
+        // apply the shift of a shifted register operand, extended registers are logged as is
+        if let (CmplogOperandType::Regid(reg), Some((shift_type, amount))) = (op2, shift) {
+            let shift_encoding: Option<u32> = match shift_type {
+                ShiftStyle::LSL => Some(0b00),
+                ShiftStyle::LSR => Some(0b01),
+                ShiftStyle::ASR => Some(0b10),
+                ShiftStyle::ROR => Some(0b11),
+                _ => None,
+            };
+            if let Some(shift_encoding) = shift_encoding {
+                // https://developer.arm.com/documentation/ddi0602/latest/Base-Instructions/ORR--shifted-register---Bitwise-OR--shifted-register--
+                let orr = if is_w_register(*reg) {
+                    0x2a0103e1 //orr w1, wzr, w1
+                } else {
+                    0xaa0103e1 //orr x1, xzr, x1
+                };
+                writer.put_bytes(
+                    &(orr | (shift_encoding << 22) | (u32::from(*amount) << 10)).to_le_bytes(),
+                ); //orr x1, xzr, x1, [shift] #[amount]
+            }
+        }
+
         //call cmplog runtime to populate the values map
         writer.put_bytes(self.ops_save_register_and_blr_to_populate());
 
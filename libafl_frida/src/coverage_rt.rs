@@ -1,11 +1,32 @@
 //! Functionality regarding binary-only coverage collection.
-
-use std::{cell::RefCell, marker::PhantomPinned, pin::Pin, rc::Rc};
+//!
+//! With [`CoverageRuntime::jit`], the code emitted at runtime, e.g. by JS engines or regex JITs,
+//! is covered too. The executable memory allocated by the target is tracked, and each region gets
+//! a slot: its blocks are keyed by the slot and their offset in the region, so that the coverage
+//! of a region allocated at another address in the next run is the same.
+//! The slots of the freed regions are recycled.
+
+use core::ffi::{c_int, c_void};
+use std::{
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    marker::PhantomPinned,
+    ops::Range,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard, OnceLock,
+    },
+};
 
 #[cfg(target_arch = "aarch64")]
 use dynasmrt::DynasmLabelApi;
 use dynasmrt::{dynasm, DynasmApi};
-use frida_gum::{instruction_writer::InstructionWriter, stalker::StalkerOutput, ModuleMap};
+use frida_gum::{
+    instruction_writer::InstructionWriter, interceptor::Interceptor, stalker::StalkerOutput, Gum,
+    Module, ModuleDetails, ModuleMap, NativePointer,
+};
 use libafl_bolts::hash_std;
 use rangemap::RangeMap;
 
@@ -14,10 +35,247 @@ use crate::helper::FridaRuntime;
 /// (Default) map size for frida coverage reporting
 pub const MAP_SIZE: usize = 64 * 1024;
 
+/// The executable regions allocated at runtime, with their slot
+#[derive(Debug, Default)]
+struct JitRegions {
+    regions: RangeMap<u64, usize>,
+    free_slots: BTreeSet<usize>,
+    next_slot: usize,
+    changed: bool,
+}
+
+impl JitRegions {
+    /// Track the executable region `range`. The parts already tracked keep their slot.
+    fn add(&mut self, range: Range<u64>) {
+        if range.is_empty() {
+            return;
+        }
+        let gaps: Vec<Range<u64>> = self.regions.gaps(&range).collect();
+        for gap in gaps {
+            let slot = self.free_slots.pop_first().unwrap_or_else(|| {
+                self.next_slot += 1;
+                self.next_slot - 1
+            });
+            log::debug!("JIT region {:#x}-{:#x} in slot {slot}", gap.start, gap.end);
+            self.regions.insert(gap, slot);
+        }
+    }
+
+    /// Stop tracking `range`, recycling the slots no region uses anymore
+    fn remove(&mut self, range: Range<u64>) {
+        let slots: BTreeSet<usize> = self
+            .regions
+            .overlapping(&range)
+            .map(|(_, slot)| *slot)
+            .collect();
+        if slots.is_empty() {
+            return;
+        }
+        self.regions.remove(range);
+        for slot in slots {
+            if !self.regions.iter().any(|(_, used)| *used == slot) {
+                self.free_slots.insert(slot);
+            }
+        }
+        self.changed = true;
+    }
+
+    /// The code of `range` may be rewritten, its instrumented blocks are stale
+    fn invalidate(&mut self, range: &Range<u64>) {
+        if self.regions.overlaps(range) {
+            self.changed = true;
+        }
+    }
+
+    /// The coverage key of the block at `address`, if it is in a tracked region
+    fn location(&self, address: u64) -> Option<u64> {
+        self.regions.get_key_value(&address).map(|(range, slot)| {
+            hash_std(
+                &[*slot as u64, address - range.start]
+                    .map(u64::to_le_bytes)
+                    .concat(),
+            )
+        })
+    }
+}
+
+fn jit_regions() -> MutexGuard<'static, JitRegions> {
+    static JIT_REGIONS: OnceLock<Mutex<JitRegions>> = OnceLock::new();
+    JIT_REGIONS
+        .get_or_init(|| Mutex::new(JitRegions::default()))
+        .lock()
+        .unwrap()
+}
+
+thread_local! {
+    static JIT_IN_HOOK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the target runs, only its allocations are tracked
+static JIT_TRACKING: AtomicBool = AtomicBool::new(false);
+
+/// Update the tracked regions, unless called by the runtime itself or out of the target
+fn track_jit<F>(update: F)
+where
+    F: FnOnce(&mut JitRegions),
+{
+    if JIT_TRACKING.load(Ordering::Relaxed) && !JIT_IN_HOOK.get() {
+        JIT_IN_HOOK.set(true);
+        update(&mut jit_regions());
+        JIT_IN_HOOK.set(false);
+    }
+}
+
+fn address_range(addr: *mut c_void, len: usize) -> Range<u64> {
+    let start = addr as u64;
+    start..start.saturating_add(len as u64)
+}
+
+#[cfg(unix)]
+type MmapFn =
+    unsafe extern "C" fn(*mut c_void, usize, c_int, c_int, c_int, libc::off_t) -> *mut c_void;
+#[cfg(unix)]
+type MprotectFn = unsafe extern "C" fn(*mut c_void, usize, c_int) -> c_int;
+#[cfg(unix)]
+type MunmapFn = unsafe extern "C" fn(*mut c_void, usize) -> c_int;
+
+#[cfg(unix)]
+static MMAP_PTR: OnceLock<MmapFn> = OnceLock::new();
+#[cfg(unix)]
+static MPROTECT_PTR: OnceLock<MprotectFn> = OnceLock::new();
+#[cfg(unix)]
+static MUNMAP_PTR: OnceLock<MunmapFn> = OnceLock::new();
+
+#[cfg(unix)]
+unsafe extern "C" fn replacement_mmap(
+    addr: *mut c_void,
+    len: usize,
+    prot: c_int,
+    flags: c_int,
+    fd: c_int,
+    offset: libc::off_t,
+) -> *mut c_void {
+    let ret = (MMAP_PTR.get().unwrap())(addr, len, prot, flags, fd, offset);
+    // file mappings are modules, loaded by the dynamic linker
+    if ret != libc::MAP_FAILED && prot & libc::PROT_EXEC != 0 && flags & libc::MAP_ANONYMOUS != 0 {
+        track_jit(|regions| regions.add(address_range(ret, len)));
+    }
+    ret
+}
+
+#[cfg(unix)]
+unsafe extern "C" fn replacement_mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int {
+    let ret = (MPROTECT_PTR.get().unwrap())(addr, len, prot);
+    if ret == 0 {
+        let range = address_range(addr, len);
+        if prot & libc::PROT_EXEC == 0 {
+            track_jit(|regions| regions.invalidate(&range));
+        } else {
+            track_jit(|regions| {
+                if ModuleDetails::with_address(addr as u64).is_none() {
+                    regions.add(range);
+                }
+            });
+        }
+    }
+    ret
+}
+
+#[cfg(unix)]
+unsafe extern "C" fn replacement_munmap(addr: *mut c_void, len: usize) -> c_int {
+    let ret = (MUNMAP_PTR.get().unwrap())(addr, len);
+    if ret == 0 {
+        track_jit(|regions| regions.remove(address_range(addr, len)));
+    }
+    ret
+}
+
+#[cfg(windows)]
+const PAGE_EXECUTE_ANY: u32 = 0x10 | 0x20 | 0x40 | 0x80;
+#[cfg(windows)]
+const MEM_RELEASE: u32 = 0x8000;
+
+#[cfg(windows)]
+type VirtualAllocFn = unsafe extern "system" fn(*mut c_void, usize, u32, u32) -> *mut c_void;
+#[cfg(windows)]
+type VirtualProtectFn = unsafe extern "system" fn(*mut c_void, usize, u32, *mut u32) -> i32;
+#[cfg(windows)]
+type VirtualFreeFn = unsafe extern "system" fn(*mut c_void, usize, u32) -> i32;
+
+#[cfg(windows)]
+static VIRTUAL_ALLOC_PTR: OnceLock<VirtualAllocFn> = OnceLock::new();
+#[cfg(windows)]
+static VIRTUAL_PROTECT_PTR: OnceLock<VirtualProtectFn> = OnceLock::new();
+#[cfg(windows)]
+static VIRTUAL_FREE_PTR: OnceLock<VirtualFreeFn> = OnceLock::new();
+
+#[cfg(windows)]
+unsafe extern "system" fn replacement_virtual_alloc(
+    addr: *mut c_void,
+    size: usize,
+    allocation_type: u32,
+    protect: u32,
+) -> *mut c_void {
+    let ret = (VIRTUAL_ALLOC_PTR.get().unwrap())(addr, size, allocation_type, protect);
+    if !ret.is_null() && protect & PAGE_EXECUTE_ANY != 0 {
+        track_jit(|regions| regions.add(address_range(ret, size)));
+    }
+    ret
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn replacement_virtual_protect(
+    addr: *mut c_void,
+    size: usize,
+    new_protect: u32,
+    old_protect: *mut u32,
+) -> i32 {
+    let ret = (VIRTUAL_PROTECT_PTR.get().unwrap())(addr, size, new_protect, old_protect);
+    if ret != 0 {
+        let range = address_range(addr, size);
+        if new_protect & PAGE_EXECUTE_ANY == 0 {
+            track_jit(|regions| regions.invalidate(&range));
+        } else {
+            track_jit(|regions| {
+                if ModuleDetails::with_address(addr as u64).is_none() {
+                    regions.add(range);
+                }
+            });
+        }
+    }
+    ret
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn replacement_virtual_free(
+    addr: *mut c_void,
+    size: usize,
+    free_type: u32,
+) -> i32 {
+    let ret = (VIRTUAL_FREE_PTR.get().unwrap())(addr, size, free_type);
+    if ret != 0 {
+        track_jit(|regions| {
+            // releasing frees the whole allocation, whose size is 0 here
+            let range = if free_type & MEM_RELEASE != 0 {
+                regions
+                    .regions
+                    .get_key_value(&(addr as u64))
+                    .map_or_else(|| address_range(addr, size), |(range, _)| range.clone())
+            } else {
+                address_range(addr, size)
+            };
+            regions.remove(range);
+        });
+    }
+    ret
+}
+
 #[derive(Debug)]
 struct CoverageRuntimeInner {
     map: [u8; MAP_SIZE],
     previous_pc: u64,
+    jit: bool,
+    hooks: Vec<NativePointer>,
     _pinned: PhantomPinned,
 }
 
@@ -36,18 +294,29 @@ impl FridaRuntime for CoverageRuntime {
     /// The struct MUST NOT be moved after this function is called, as the generated assembly references it
     fn init(
         &mut self,
-        _gum: &frida_gum::Gum,
+        gum: &frida_gum::Gum,
         _ranges: &RangeMap<u64, (u16, String)>,
         _module_map: &Rc<ModuleMap>,
     ) {
+        if self.0.borrow().jit {
+            self.register_jit_hooks(gum);
+        }
     }
 
-    fn deinit(&mut self, _gum: &frida_gum::Gum) {}
+    fn deinit(&mut self, gum: &frida_gum::Gum) {
+        let mut interceptor = Interceptor::obtain(gum);
+        for hook in self.0.borrow_mut().hooks.drain(..) {
+            interceptor.revert(hook);
+        }
+    }
 
     fn pre_exec<I: libafl::inputs::Input + libafl::inputs::HasTargetBytes>(
         &mut self,
         _input: &I,
     ) -> Result<(), libafl::Error> {
+        if self.0.borrow().jit {
+            JIT_TRACKING.store(true, Ordering::Relaxed);
+        }
         Ok(())
     }
 
@@ -55,6 +324,7 @@ impl FridaRuntime for CoverageRuntime {
         &mut self,
         _input: &I,
     ) -> Result<(), libafl::Error> {
+        JIT_TRACKING.store(false, Ordering::Relaxed);
         Ok(())
     }
 }
@@ -67,10 +337,99 @@ impl CoverageRuntime {
         Self(Rc::pin(RefCell::new(CoverageRuntimeInner {
             map: [0_u8; MAP_SIZE],
             previous_pc: 0,
+            jit: false,
+            hooks: Vec::new(),
             _pinned: PhantomPinned,
         })))
     }
 
+    /// Also cover the code emitted at runtime, see the [module-level documentation](self).
+    ///
+    /// The `Stalker` then follows the anonymous memory, which slows down targets calling into
+    /// code that is not instrumented. A region whose code is rewritten or freed during a run
+    /// keeps its instrumentation until the next run.
+    #[must_use]
+    pub fn jit(self, jit: bool) -> Self {
+        self.0.borrow_mut().jit = jit;
+        self
+    }
+
+    /// Whether the code emitted at runtime is covered
+    #[must_use]
+    pub fn jit_enabled(&self) -> bool {
+        self.0.borrow().jit
+    }
+
+    /// Whether code emitted at runtime was freed or rewritten since the last call.
+    /// If so, the code instrumented before must be discarded.
+    #[allow(clippy::unused_self)]
+    pub fn take_jit_regions_changed(&mut self) -> bool {
+        core::mem::take(&mut jit_regions().changed)
+    }
+
+    /// Whether `address` is in executable memory allocated at runtime
+    #[must_use]
+    pub fn is_jit_code(&self, address: u64) -> bool {
+        self.0.borrow().jit && jit_regions().regions.contains_key(&address)
+    }
+
+    /// Intercept the executable memory allocation APIs, to track the code emitted at runtime
+    fn register_jit_hooks(&mut self, gum: &Gum) {
+        let mut interceptor = Interceptor::obtain(gum);
+        let module = Module::obtain(gum);
+        let mut hooks = Vec::new();
+
+        macro_rules! hook_jit {
+            ($name:literal, $ptr:ident, $fn_type:ty, $replacement:ident) => {
+                if let Some(target_function) = module.find_export_by_name(None, $name) {
+                    let _ = $ptr.set(unsafe {
+                        std::mem::transmute::<*mut c_void, $fn_type>(target_function.0)
+                    });
+                    match interceptor.replace(
+                        target_function,
+                        NativePointer($replacement as *mut c_void),
+                        NativePointer(core::ptr::null_mut()),
+                    ) {
+                        Ok(_) => hooks.push(target_function),
+                        Err(err) => log::warn!("Coverage: failed to hook {}: {err:?}", $name),
+                    }
+                } else {
+                    log::warn!("Coverage: {} not found, JIT regions may be missed", $name);
+                }
+            };
+        }
+
+        #[cfg(unix)]
+        {
+            hook_jit!("mmap", MMAP_PTR, MmapFn, replacement_mmap);
+            hook_jit!("mprotect", MPROTECT_PTR, MprotectFn, replacement_mprotect);
+            hook_jit!("munmap", MUNMAP_PTR, MunmapFn, replacement_munmap);
+        }
+        #[cfg(windows)]
+        {
+            hook_jit!(
+                "VirtualAlloc",
+                VIRTUAL_ALLOC_PTR,
+                VirtualAllocFn,
+                replacement_virtual_alloc
+            );
+            hook_jit!(
+                "VirtualProtect",
+                VIRTUAL_PROTECT_PTR,
+                VirtualProtectFn,
+                replacement_virtual_protect
+            );
+            hook_jit!(
+                "VirtualFree",
+                VIRTUAL_FREE_PTR,
+                VirtualFreeFn,
+                replacement_virtual_free
+            );
+        }
+
+        self.0.borrow_mut().hooks = hooks;
+    }
+
     /// Retrieve the coverage map pointer
     pub fn map_mut_ptr(&mut self) -> *mut u8 {
         self.0.borrow_mut().map.as_mut_ptr()
@@ -189,7 +548,12 @@ impl CoverageRuntime {
     /// Emits coverage mapping into the current basic block.
     #[inline]
     pub fn emit_coverage_mapping(&mut self, address: u64, output: &StalkerOutput) {
-        let h64 = hash_std(&address.to_le_bytes());
+        let jit_location = if self.0.borrow().jit {
+            jit_regions().location(address)
+        } else {
+            None
+        };
+        let h64 = jit_location.unwrap_or_else(|| hash_std(&address.to_le_bytes()));
         let writer = output.writer();
 
         // Since the AARCH64 instruction set requires that a register be used if
@@ -216,3 +580,30 @@ impl CoverageRuntime {
         writer.put_bytes(&code);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::JitRegions;
+
+    #[test]
+    fn test_jit_regions_recycle_slots() {
+        let mut regions = JitRegions::default();
+        regions.add(0x1000..0x2000);
+        regions.add(0x4000..0x5000);
+        assert_eq!(regions.regions.get(&0x1800), Some(&0));
+        assert_eq!(regions.regions.get(&0x4800), Some(&1));
+
+        // a region allocated at another address in the next run keeps its coverage keys
+        let location = regions.location(0x1010);
+        regions.remove(0x1000..0x2000);
+        assert!(regions.changed);
+        assert_eq!(regions.location(0x1010), None);
+        regions.add(0x8000..0x9000);
+        assert_eq!(regions.location(0x8010), location);
+
+        // growing a region keeps the slot of its tracked part
+        regions.add(0x4000..0x6000);
+        assert_eq!(regions.regions.get(&0x4800), Some(&1));
+        assert_eq!(regions.regions.get(&0x5800), Some(&2));
+    }
+}
@@ -1,6 +1,7 @@
 //! Generates `DrCov` traces
 use std::{
     hash::{BuildHasher, Hasher},
+    mem,
     path::{Path, PathBuf},
     rc::Rc,
 };
@@ -12,7 +13,7 @@ use libafl::{
     Error,
 };
 use libafl_bolts::AsSlice;
-use libafl_targets::drcov::{DrCovBasicBlock, DrCovWriter};
+use libafl_targets::drcov::{publish_drcov_run, DrCovBasicBlock, DrCovWriter};
 use rangemap::RangeMap;
 
 use crate::helper::FridaRuntime;
@@ -25,6 +26,8 @@ pub struct DrCovRuntime {
     /// The memory ranges of this target
     ranges: RangeMap<u64, (u16, String)>,
     coverage_directory: PathBuf,
+    /// Publish the runs to the `DrCovObserver` instead of writing them
+    publish_runs: bool,
 }
 
 impl FridaRuntime for DrCovRuntime {
@@ -36,6 +39,9 @@ impl FridaRuntime for DrCovRuntime {
         _module_map: &Rc<ModuleMap>,
     ) {
         self.ranges = ranges.clone();
+        if self.publish_runs {
+            return;
+        }
         std::fs::create_dir_all(&self.coverage_directory)
             .expect("failed to create directory for coverage files");
     }
@@ -49,7 +55,13 @@ impl FridaRuntime for DrCovRuntime {
 
     /// Called after execution, writes the trace to a unique `DrCov` file for this trace
     /// into `./coverage/<input_hash>_<coverage_hash>.drcov`. Empty coverages will be skipped.
+    /// With [`DrCovRuntime::publishing`], publishes the trace to the `DrCovObserver` instead.
     fn post_exec<I: Input + HasTargetBytes>(&mut self, input: &I) -> Result<(), Error> {
+        if self.publish_runs {
+            publish_drcov_run(&self.ranges, mem::take(&mut self.drcov_basic_blocks));
+            return Ok(());
+        }
+
         // We don't need empty coverage files
         if self.drcov_basic_blocks.is_empty() {
             return Ok(());
@@ -90,6 +102,16 @@ impl DrCovRuntime {
             ..Self::default()
         }
     }
+
+    /// Create a new [`DrCovRuntime`] that publishes the trace of each run to the `DrCovObserver`
+    /// of `libafl_targets`, e.g. for the `DrCovStage`, instead of writing it to a file
+    #[must_use]
+    pub fn publishing() -> Self {
+        Self {
+            publish_runs: true,
+            ..Self::default()
+        }
+    }
 }
 
 impl Default for DrCovRuntime {
@@ -98,6 +120,7 @@ impl Default for DrCovRuntime {
             drcov_basic_blocks: vec![],
             ranges: RangeMap::new(),
             coverage_directory: PathBuf::from("./coverage"),
+            publish_runs: false,
         }
     }
 }
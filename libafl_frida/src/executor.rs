@@ -1,4 +1,7 @@
-use core::fmt::{self, Debug, Formatter};
+use core::{
+    fmt::{self, Debug, Formatter},
+    ops::Range,
+};
 #[cfg(all(windows, not(test)))]
 use std::process::abort;
 use std::{ffi::c_void, marker::PhantomData};
@@ -85,6 +88,16 @@ where
     ) -> Result<ExitKind, Error> {
         self.helper.pre_exec(input)?;
         if self.helper.stalker_enabled() {
+            let ranges_changed = self.helper.take_ranges_changed();
+            if self.followed && ranges_changed {
+                // Discard the code instrumented with the previous ranges
+                if let Some(thread_id) = self.thread_id {
+                    self.stalker.unfollow(thread_id.try_into().unwrap());
+                } else {
+                    self.stalker.unfollow_me();
+                }
+                self.followed = false;
+            }
             if self.followed {
                 self.stalker.activate(NativePointer(core::ptr::null_mut()));
             } else {
@@ -187,7 +200,8 @@ where
         // Include the current module (the fuzzer) in stalked ranges. We clone the ranges so that
         // we don't add it to the INSTRUMENTED ranges.
         let mut ranges = helper.ranges().clone();
-        for module in frida_gum::Module::obtain(gum).enumerate_modules() {
+        let modules = frida_gum::Module::obtain(gum).enumerate_modules();
+        for module in &modules {
             if module.base_address < Self::new as usize
                 && (Self::new as usize as u64) < module.base_address as u64 + module.size as u64
             {
@@ -201,7 +215,21 @@ where
 
         log::info!("disable_excludes: {:}", helper.disable_excludes);
         if !helper.disable_excludes {
-            for range in ranges.gaps(&(0..u64::MAX)) {
+            let excluded: Vec<Range<u64>> = if helper.jit_enabled() {
+                // Only exclude the modules, the code emitted at runtime is in anonymous memory
+                modules
+                    .iter()
+                    .flat_map(|module| {
+                        let start = module.base_address as u64;
+                        ranges
+                            .gaps(&(start..start + module.size as u64))
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            } else {
+                ranges.gaps(&(0..u64::MAX)).collect()
+            };
+            for range in excluded {
                 log::info!("excluding range: {:x}-{:x}", range.start, range.end);
                 stalker.exclude(&MemoryRange::new(
                     NativePointer(range.start as *mut c_void),
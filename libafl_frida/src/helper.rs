@@ -1,4 +1,7 @@
-use core::fmt::{self, Debug, Formatter};
+use core::{
+    fmt::{self, Debug, Formatter},
+    ops::Range,
+};
 use std::{
     cell::{Ref, RefCell, RefMut},
     ffi::CStr,
@@ -10,7 +13,7 @@ use std::{
 use frida_gum::{
     instruction_writer::InstructionWriter,
     stalker::{StalkerIterator, StalkerOutput, Transformer},
-    Backend, Gum, ModuleDetails, ModuleMap, Script,
+    Backend, Gum, Module, ModuleDetails, ModuleMap, Script,
 };
 use frida_gum_sys::gchar;
 use libafl::{
@@ -25,6 +28,7 @@ use libafl_targets::drcov::DrCovBasicBlock;
 #[cfg(unix)]
 use nix::sys::mman::{mmap_anonymous, MapFlags, ProtFlags};
 use rangemap::RangeMap;
+use regex::Regex;
 #[cfg(target_arch = "aarch64")]
 use yaxpeax_arch::Arch;
 #[cfg(all(target_arch = "aarch64", unix))]
@@ -140,6 +144,125 @@ pub enum SkipRange {
     },
 }
 
+impl SkipRange {
+    /// The absolute address range, [`None`] if the module is not loaded
+    #[must_use]
+    pub fn resolve(&self) -> Option<Range<u64>> {
+        match self {
+            SkipRange::Absolute(range) => Some(range.start as u64..range.end as u64),
+            SkipRange::ModuleRelative { name, range } => {
+                let lib_start = ModuleDetails::with_name(name.clone())?
+                    .range()
+                    .base_address()
+                    .0 as u64;
+                Some((lib_start + range.start as u64)..(lib_start + range.end as u64))
+            }
+        }
+    }
+}
+
+/// Code to include in, or exclude from, the instrumentation.
+///
+/// See [`FridaInstrumentationHelperBuilder::exclude`] and [`FridaInstrumentationHelper::exclude`].
+#[derive(Debug, Clone)]
+pub enum InstrumentationRule {
+    /// A loaded module, by name or path
+    Module(String),
+    /// All the modules selected for instrumentation
+    SelectedModules,
+    /// An address range, absolute or relative to a module
+    Range(SkipRange),
+    /// The functions whose symbol matches, in the modules selected for instrumentation
+    Symbols(Regex),
+}
+
+/// Include (or exclude) the code of `rule` in the instrumented `ranges`
+fn apply_instrumentation_rule(
+    gum: &Gum,
+    module_map: &ModuleMap,
+    ranges: &mut RangeMap<u64, (u16, String)>,
+    rule: &InstrumentationRule,
+    include: bool,
+) -> Result<(), Error> {
+    let modules = module_map.values();
+    let mut rule_ranges = Vec::new();
+    match rule {
+        InstrumentationRule::Module(name) => {
+            let module = ModuleDetails::with_name(name.clone())
+                .ok_or_else(|| Error::key_not_found(format!("Module {name} is not loaded")))?;
+            let range = module.range();
+            let start = range.base_address().0 as u64;
+            rule_ranges.push((start..(start + range.size() as u64), module.path()));
+        }
+        InstrumentationRule::SelectedModules => {
+            for module in &modules {
+                let range = module.range();
+                let start = range.base_address().0 as u64;
+                rule_ranges.push((start..(start + range.size() as u64), module.path()));
+            }
+        }
+        InstrumentationRule::Range(range) => {
+            let range = range.resolve().ok_or_else(|| {
+                Error::key_not_found(format!("The module of {range:?} is not loaded"))
+            })?;
+            let path = ModuleDetails::with_address(range.start)
+                .map(|module| module.path())
+                .unwrap_or_default();
+            rule_ranges.push((range, path));
+        }
+        InstrumentationRule::Symbols(regex) => {
+            let frida_module = Module::obtain(gum);
+            for module in &modules {
+                for symbol in frida_module.enumerate_symbols(&module.name()) {
+                    if symbol.size > 0 && regex.is_match(&symbol.name) {
+                        let start = symbol.address as u64;
+                        rule_ranges.push((start..(start + symbol.size as u64), module.path()));
+                    }
+                }
+            }
+            if rule_ranges.is_empty() {
+                log::warn!("No symbol matches {regex} in the instrumented modules");
+            }
+        }
+    }
+
+    for (range, path) in rule_ranges {
+        if range.is_empty() {
+            continue;
+        }
+        if include {
+            // keep the ids of the modules the ranges were built from, new modules come after
+            let id = modules
+                .iter()
+                .position(|module| module.path() == path)
+                .or_else(|| {
+                    ranges
+                        .iter()
+                        .find(|(_, (_, range_path))| *range_path == path)
+                        .map(|(_, (id, _))| usize::from(*id))
+                })
+                .unwrap_or_else(|| {
+                    ranges
+                        .iter()
+                        .map(|(_, (id, _))| usize::from(*id) + 1)
+                        .max()
+                        .unwrap_or_default()
+                        .max(modules.len())
+                });
+            log::debug!("Instrumenting {:x}-{:x} ({path})", range.start, range.end);
+            ranges.insert(range, (id as u16, path));
+        } else {
+            log::debug!(
+                "Not instrumenting {:x}-{:x} ({path})",
+                range.start,
+                range.end
+            );
+            ranges.remove(range);
+        }
+    }
+    Ok(())
+}
+
 /// Builder for [`FridaInstrumentationHelper`]
 pub struct FridaInstrumentationHelperBuilder {
     stalker_enabled: bool,
@@ -148,6 +271,7 @@ pub struct FridaInstrumentationHelperBuilder {
     instrument_module_predicate: Option<Box<dyn FnMut(&ModuleDetails) -> bool>>,
     skip_module_predicate: Box<dyn FnMut(&ModuleDetails) -> bool>,
     skip_ranges: Vec<SkipRange>,
+    rules: Vec<(InstrumentationRule, bool)>,
 }
 
 impl FridaInstrumentationHelperBuilder {
@@ -275,6 +399,35 @@ impl FridaInstrumentationHelperBuilder {
         self
     }
 
+    /// Instrument the code of `rule`, after the modules and skipped ranges are selected.
+    ///
+    /// The rules apply in the order they are given, so a later rule overrides an earlier one.
+    ///
+    /// # Example
+    /// Instrument only the parsing functions of `libfoo.so`:
+    /// ```
+    ///# use libafl_frida::helper::{FridaInstrumentationHelper, InstrumentationRule};
+    ///# use regex::Regex;
+    /// let builder = FridaInstrumentationHelper::builder()
+    ///     .instrument_module_if(|module| module.name() == "libfoo.so")
+    ///     .exclude(InstrumentationRule::SelectedModules)
+    ///     .include(InstrumentationRule::Symbols(Regex::new("^parse_").unwrap()));
+    /// ```
+    #[must_use]
+    pub fn include(mut self, rule: InstrumentationRule) -> Self {
+        self.rules.push((rule, true));
+        self
+    }
+
+    /// Do not instrument the code of `rule`, after the modules and skipped ranges are selected.
+    ///
+    /// The rules apply in the order they are given, so a later rule overrides an earlier one.
+    #[must_use]
+    pub fn exclude(mut self, rule: InstrumentationRule) -> Self {
+        self.rules.push((rule, false));
+        self
+    }
+
     /// Build a [`FridaInstrumentationHelper`]
     pub fn build<RT: FridaRuntimeTuple>(
         self,
@@ -287,6 +440,7 @@ impl FridaInstrumentationHelperBuilder {
             mut instrument_module_predicate,
             mut skip_module_predicate,
             skip_ranges,
+            rules,
         } = self;
 
         let mut module_filter = Box::new(move |module| {
@@ -324,17 +478,20 @@ impl FridaInstrumentationHelperBuilder {
                 );
             }
             for skip in skip_ranges {
-                match skip {
-                    SkipRange::Absolute(range) => ranges
-                        .borrow_mut()
-                        .remove(range.start as u64..range.end as u64),
-                    SkipRange::ModuleRelative { name, range } => {
-                        let module_details = ModuleDetails::with_name(name).unwrap();
-                        let lib_start = module_details.range().base_address().0 as u64;
-                        ranges.borrow_mut().remove(
-                            (lib_start + range.start as u64)..(lib_start + range.end as u64),
-                        );
-                    }
+                let range = skip
+                    .resolve()
+                    .unwrap_or_else(|| panic!("The module of {skip:?} is not loaded"));
+                ranges.borrow_mut().remove(range);
+            }
+            for (rule, include) in &rules {
+                if let Err(err) = apply_instrumentation_rule(
+                    gum,
+                    &module_map,
+                    &mut ranges.borrow_mut(),
+                    rule,
+                    *include,
+                ) {
+                    log::warn!("Failed to apply the instrumentation rule {rule:?}: {err}");
                 }
             }
             runtimes
@@ -349,7 +506,10 @@ impl FridaInstrumentationHelperBuilder {
 
         FridaInstrumentationHelper {
             transformer,
+            gum,
+            module_map,
             ranges,
+            ranges_changed: false,
             runtimes,
             stalker_enabled,
             disable_excludes,
@@ -365,6 +525,7 @@ impl Debug for FridaInstrumentationHelperBuilder {
             .field("instrument_module_predicate", &"<closure>")
             .field("skip_module_predicate", &"<closure>")
             .field("skip_ranges", &self.skip_ranges)
+            .field("rules", &self.rules)
             .field("disable_excludes", &self.disable_excludes);
         dbg_me.finish()
     }
@@ -384,6 +545,7 @@ impl Default for FridaInstrumentationHelperBuilder {
                 range.contains(&(Self::new as usize))
             }),
             skip_ranges: Vec::new(),
+            rules: Vec::new(),
         }
     }
 }
@@ -391,7 +553,10 @@ impl Default for FridaInstrumentationHelperBuilder {
 /// An helper that feeds `FridaInProcessExecutor` with edge-coverage instrumentation
 pub struct FridaInstrumentationHelper<'a, RT: 'a> {
     transformer: Transformer<'a>,
+    gum: &'a Gum,
+    module_map: Rc<ModuleMap>,
     ranges: Rc<RefCell<RangeMap<u64, (u16, String)>>>,
+    ranges_changed: bool,
     runtimes: Rc<RefCell<RT>>,
     stalker_enabled: bool,
     pub(crate) disable_excludes: bool,
@@ -436,6 +601,19 @@ where
     })
 }
 
+/// A regex matching any of `patterns`, [`None`] if there are none
+fn symbols_regex(patterns: &[String]) -> Option<Regex> {
+    if patterns.is_empty() {
+        return None;
+    }
+    let pattern = patterns
+        .iter()
+        .map(|pattern| format!("(?:{pattern})"))
+        .collect::<Vec<_>>()
+        .join("|");
+    Some(Regex::new(&pattern).unwrap_or_else(|err| panic!("Invalid symbol regex: {err}")))
+}
+
 impl FridaInstrumentationHelper<'_, ()> {
     /// Create a builder to initialize a [`FridaInstrumentationHelper`].
     ///
@@ -461,19 +639,41 @@ where
             .iter()
             .map(PathBuf::from)
             .collect::<Vec<_>>();
-        let builder = FridaInstrumentationHelper::builder()
+        let dont_instrument_libs = options
+            .dont_instrument_libs
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>();
+        let mut builder = FridaInstrumentationHelper::builder()
             .enable_stalker(options.cmplog || options.asan || !options.disable_coverage)
             .disable_excludes(options.disable_excludes)
             .instrument_module_if(move |module| pathlist_contains_module(&harness, module))
             .instrument_module_if(move |module| {
                 pathlist_contains_module(&libs_to_instrument, module)
             })
+            .skip_module_if(move |module| pathlist_contains_module(&dont_instrument_libs, module))
             .skip_ranges(options.dont_instrument.iter().map(|(name, offset)| {
                 SkipRange::ModuleRelative {
                     name: name.clone(),
                     range: *offset..*offset + 4,
                 }
-            }));
+            }))
+            .skip_ranges(
+                options
+                    .dont_instrument_ranges
+                    .iter()
+                    .cloned()
+                    .map(SkipRange::Absolute),
+            );
+
+        if let Some(regex) = symbols_regex(&options.instrument_symbols) {
+            builder = builder
+                .exclude(InstrumentationRule::SelectedModules)
+                .include(InstrumentationRule::Symbols(regex));
+        }
+        if let Some(regex) = symbols_regex(&options.dont_instrument_symbols) {
+            builder = builder.exclude(InstrumentationRule::Symbols(regex));
+        }
 
         let builder = if let Some(script) = &options.script {
             builder.load_script(
@@ -627,6 +827,16 @@ where
                 if let Some(_rt) = runtimes.match_first_type_mut::<DrCovRuntime>() {
                     basic_block_size += instr_size;
                 }
+            } else if first {
+                first = false;
+                // code emitted at runtime is only covered
+                let mut runtimes = (*runtimes_unborrowed).borrow_mut();
+                if let Some(rt) = runtimes.match_first_type_mut::<CoverageRuntime>() {
+                    if rt.is_jit_code(address) {
+                        log::trace!("JIT block @ {:x}", address);
+                        rt.emit_coverage_mapping(address, output);
+                    }
+                }
             }
             instruction.keep();
         }
@@ -740,6 +950,46 @@ where
 
     /// Mutable ranges
     pub fn ranges_mut(&mut self) -> RefMut<RangeMap<u64, (u16, String)>> {
+        self.ranges_changed = true;
         (*self.ranges).borrow_mut()
     }
+
+    /// Instrument the code of `rule` from the next execution on.
+    ///
+    /// Code excluded from the [`Stalker`](https://frida.re/docs/stalker/) when the executor was
+    /// created cannot be included again, see [`FridaInstrumentationHelperBuilder::disable_excludes`].
+    /// The runtimes are not initialized again: e.g. `DrCov` keeps the ranges it was initialized with.
+    pub fn include(&mut self, rule: &InstrumentationRule) -> Result<(), Error> {
+        self.apply_rule(rule, true)
+    }
+
+    /// Stop instrumenting the code of `rule` from the next execution on,
+    /// e.g. to silence a noisy library once it is discovered.
+    pub fn exclude(&mut self, rule: &InstrumentationRule) -> Result<(), Error> {
+        self.apply_rule(rule, false)
+    }
+
+    fn apply_rule(&mut self, rule: &InstrumentationRule, include: bool) -> Result<(), Error> {
+        let module_map = Rc::clone(&self.module_map);
+        apply_instrumentation_rule(self.gum, &module_map, &mut self.ranges_mut(), rule, include)
+    }
+
+    /// Whether the instrumented ranges, or the code emitted at runtime, changed since the last call.
+    /// If so, the code instrumented before must be discarded.
+    pub fn take_ranges_changed(&mut self) -> bool {
+        let jit_changed = (*self.runtimes)
+            .borrow_mut()
+            .match_first_type_mut::<CoverageRuntime>()
+            .is_some_and(CoverageRuntime::take_jit_regions_changed);
+        core::mem::take(&mut self.ranges_changed) | jit_changed
+    }
+
+    /// Whether the code emitted at runtime is covered, see [`CoverageRuntime::jit`]
+    #[must_use]
+    pub fn jit_enabled(&self) -> bool {
+        self.runtimes
+            .borrow()
+            .match_first_type::<CoverageRuntime>()
+            .is_some_and(CoverageRuntime::jit_enabled)
+    }
 }
@@ -0,0 +1,249 @@
+//! The programs the fuzzer sends to the agent in the guest, which executes their syscalls.
+//!
+//! A [`SyscallSequenceInput`] refers to its syscalls by their index in the [`SyscallTable`],
+//! the agent needs their numbers instead, and the buffers inline. The [`SyscallProgramConverter`]
+//! encodes the inputs into this format for the executor. All integers are little endian:
+//!
+//! ```text
+//! program := PROGRAM_MAGIC:u32 call_count:u32 call*
+//! call    := nr:u64 arg_count:u32 arg*
+//! arg     := ARG_INT:u8 value:u64
+//!          | ARG_BUFFER:u8 len:u32 bytes
+//!          | ARG_RESULT:u8 call:u32
+//! ```
+//!
+//! The agent passes buffers by pointer, and an [`ARG_RESULT`] as the return value of the earlier call at that index.
+//! Calls to syscalls missing from the table are encoded with [`INVALID_SYSCALL`], and skipped by the agent.
+//! Agents written in Rust can read the programs with [`decode_program`].
+
+use std::vec::Vec;
+
+use libafl::{
+    inputs::{
+        SyscallArg, SyscallSequenceInput, SyscallTable, TargetBytesConverter, INVALID_RESOURCE,
+    },
+    Error,
+};
+use libafl_bolts::ownedref::OwnedSlice;
+
+/// The first four bytes of every program, `LKSP`
+pub const PROGRAM_MAGIC: u32 = u32::from_le_bytes(*b"LKSP");
+
+/// The tag of an integer argument
+pub const ARG_INT: u8 = 0;
+/// The tag of a buffer argument
+pub const ARG_BUFFER: u8 = 1;
+/// The tag of an argument referencing the result of an earlier call
+pub const ARG_RESULT: u8 = 2;
+
+/// The number of a call to a syscall missing from the [`SyscallTable`]
+pub const INVALID_SYSCALL: u64 = u64::MAX;
+
+/// Encodes the calls of `input` into the program format, looking their numbers up in `table`
+pub fn encode_program(input: &SyscallSequenceInput, table: &SyscallTable, program: &mut Vec<u8>) {
+    program.extend_from_slice(&PROGRAM_MAGIC.to_le_bytes());
+    program.extend_from_slice(&len_u32(input.calls().len()).to_le_bytes());
+    for call in input.calls() {
+        let nr = table
+            .get(call.syscall)
+            .map_or(INVALID_SYSCALL, |description| description.nr);
+        program.extend_from_slice(&nr.to_le_bytes());
+        program.extend_from_slice(&len_u32(call.args.len()).to_le_bytes());
+        for arg in &call.args {
+            match arg {
+                SyscallArg::Int(value) => {
+                    program.push(ARG_INT);
+                    program.extend_from_slice(&value.to_le_bytes());
+                }
+                SyscallArg::Buffer(buf) => {
+                    program.push(ARG_BUFFER);
+                    program.extend_from_slice(&len_u32(buf.len()).to_le_bytes());
+                    program.extend_from_slice(buf);
+                }
+                SyscallArg::Result(call) => {
+                    program.push(ARG_RESULT);
+                    program.extend_from_slice(&len_u32(*call).to_le_bytes());
+                }
+            }
+        }
+    }
+}
+
+fn len_u32(len: usize) -> u32 {
+    u32::try_from(len).expect("Syscall programs are limited to 4GB")
+}
+
+/// A call of a program, see [`decode_program`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentCall<'a> {
+    /// The syscall number, [`INVALID_SYSCALL`] if the fuzzer did not know the syscall
+    pub nr: u64,
+    /// The arguments
+    pub args: Vec<AgentArg<'a>>,
+}
+
+/// An argument of an [`AgentCall`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgentArg<'a> {
+    /// An integer
+    Int(u64),
+    /// A buffer, to pass by pointer
+    Buffer(&'a [u8]),
+    /// The result of the earlier call at this index
+    Result(usize),
+}
+
+impl AgentArg<'_> {
+    /// The value to pass for this argument, given the `results` of the earlier calls
+    #[must_use]
+    pub fn value(&self, results: &[u64]) -> u64 {
+        match self {
+            Self::Int(value) => *value,
+            Self::Buffer(buf) => buf.as_ptr() as u64,
+            Self::Result(call) => results.get(*call).copied().unwrap_or(INVALID_RESOURCE),
+        }
+    }
+}
+
+/// Reads the calls of a program encoded by [`encode_program`], borrowing the buffers from `program`
+pub fn decode_program(program: &[u8]) -> Result<Vec<AgentCall<'_>>, Error> {
+    let mut reader = Reader { program, pos: 0 };
+    if u32::from_le_bytes(reader.array()?) != PROGRAM_MAGIC {
+        return Err(Error::illegal_argument("Not a syscall program"));
+    }
+    let call_count = u32::from_le_bytes(reader.array()?);
+    let mut calls = Vec::new();
+    for idx in 0..call_count as usize {
+        let nr = u64::from_le_bytes(reader.array()?);
+        let arg_count = u32::from_le_bytes(reader.array()?);
+        let mut args = Vec::new();
+        for _ in 0..arg_count {
+            let [tag] = reader.array()?;
+            args.push(match tag {
+                ARG_INT => AgentArg::Int(u64::from_le_bytes(reader.array()?)),
+                ARG_BUFFER => {
+                    let len = u32::from_le_bytes(reader.array()?);
+                    AgentArg::Buffer(reader.bytes(len as usize)?)
+                }
+                ARG_RESULT => {
+                    let call = u32::from_le_bytes(reader.array()?) as usize;
+                    if call >= idx {
+                        return Err(Error::illegal_argument(format!(
+                            "Call {idx} uses the result of call {call}, which does not run before it"
+                        )));
+                    }
+                    AgentArg::Result(call)
+                }
+                _ => {
+                    return Err(Error::illegal_argument(format!(
+                        "Unknown argument tag {tag}"
+                    )))
+                }
+            });
+        }
+        calls.push(AgentCall { nr, args });
+    }
+    Ok(calls)
+}
+
+/// Reads the fields of a program
+struct Reader<'a> {
+    program: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes = self
+            .program
+            .get(self.pos..self.pos.saturating_add(len))
+            .ok_or_else(|| Error::illegal_argument("Truncated syscall program"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+}
+
+/// Turns [`SyscallSequenceInput`]s into programs for the agent, for executors taking a [`TargetBytesConverter`]
+#[derive(Debug, Clone)]
+pub struct SyscallProgramConverter {
+    table: SyscallTable,
+}
+
+impl SyscallProgramConverter {
+    /// Creates a new [`SyscallProgramConverter`] for the syscalls of `table`
+    #[must_use]
+    pub fn new(table: SyscallTable) -> Self {
+        Self { table }
+    }
+
+    /// The syscalls of the programs
+    #[must_use]
+    pub fn table(&self) -> &SyscallTable {
+        &self.table
+    }
+}
+
+impl TargetBytesConverter for SyscallProgramConverter {
+    type Input = SyscallSequenceInput;
+
+    fn to_target_bytes<'a>(&mut self, input: &'a Self::Input) -> OwnedSlice<'a, u8> {
+        let mut program = Vec::new();
+        encode_program(input, &self.table, &mut program);
+        OwnedSlice::from(program)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::inputs::{
+        SyscallArg, SyscallArgType, SyscallCall, SyscallDescription, SyscallSequenceInput,
+        SyscallTable, TargetBytesConverter,
+    };
+    use libafl_bolts::AsSlice;
+
+    use super::{decode_program, AgentArg, AgentCall, SyscallProgramConverter, INVALID_SYSCALL};
+
+    #[test]
+    fn test_program_roundtrip() {
+        let table = SyscallTable::new(vec![SyscallDescription::new(
+            "open",
+            2,
+            vec![
+                SyscallArgType::Buffer { max_len: 16 },
+                SyscallArgType::Int { bits: 32 },
+            ],
+            Some("fd"),
+        )]);
+        let input = SyscallSequenceInput::new(vec![
+            SyscallCall::new(
+                0,
+                vec![SyscallArg::Buffer(b"/dev/tty".to_vec()), SyscallArg::Int(2)],
+            ),
+            SyscallCall::new(7, vec![SyscallArg::Result(0)]),
+        ]);
+
+        let mut converter = SyscallProgramConverter::new(table);
+        let program = converter.to_target_bytes(&input);
+        let calls = decode_program(program.as_slice()).unwrap();
+        assert_eq!(
+            calls,
+            [
+                AgentCall {
+                    nr: 2,
+                    args: vec![AgentArg::Buffer(b"/dev/tty"), AgentArg::Int(2)],
+                },
+                AgentCall {
+                    nr: INVALID_SYSCALL,
+                    args: vec![AgentArg::Result(0)],
+                },
+            ]
+        );
+        assert_eq!(calls[1].args[0].value(&[3]), 3);
+
+        assert!(decode_program(&program.as_slice()[..program.as_slice().len() - 1]).is_err());
+    }
+}
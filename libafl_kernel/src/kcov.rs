@@ -0,0 +1,177 @@
+//! Kernel coverage from `kcov`, for agents running in the guest.
+//!
+//! A kernel built with `CONFIG_KCOV` records the program counters the current thread reaches in the kernel.
+//! The agent enables [`Kcov`] around the calls of a program, and folds the recorded program counters
+//! into the coverage map shared with the fuzzer with [`kcov_edges_into`],
+//! as an alternative to tracing the whole VM with Intel PT.
+
+#[cfg(target_os = "linux")]
+use std::{
+    ffi::CString,
+    mem::size_of,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    ptr, slice,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(target_os = "linux")]
+use libafl::Error;
+#[cfg(target_os = "linux")]
+use libc::c_ulong;
+
+/// The `debugfs` file to open `kcov` with
+pub const KCOV_PATH: &str = "/sys/kernel/debug/kcov";
+
+/// `_IOR('c', 1, unsigned long)`
+#[cfg(target_os = "linux")]
+const KCOV_INIT_TRACE: c_ulong =
+    (2 << 30) | ((size_of::<c_ulong>() as c_ulong) << 16) | (0x63 << 8) | 1;
+/// `_IO('c', 100)`
+#[cfg(target_os = "linux")]
+const KCOV_ENABLE: c_ulong = (0x63 << 8) | 0x64;
+/// `_IO('c', 101)`
+#[cfg(target_os = "linux")]
+const KCOV_DISABLE: c_ulong = (0x63 << 8) | 0x65;
+/// Collect the program counters, instead of the comparison operands
+#[cfg(target_os = "linux")]
+const KCOV_TRACE_PC: c_ulong = 0;
+
+/// A `kcov` handle, recording the kernel program counters the current thread reaches while enabled
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct Kcov {
+    fd: OwnedFd,
+    /// The first entry counts the program counters following it
+    area: *mut c_ulong,
+    size: usize,
+    enabled: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl Kcov {
+    /// Opens `kcov`, recording up to `size` program counters per execution
+    pub fn open(size: usize) -> Result<Self, Error> {
+        let path = CString::new(KCOV_PATH).unwrap();
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDWR) };
+        if fd < 0 {
+            return Err(Error::last_os_error(format!("Failed to open {KCOV_PATH}")));
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        if unsafe { libc::ioctl(fd.as_raw_fd(), KCOV_INIT_TRACE as _, size as c_ulong) } != 0 {
+            return Err(Error::last_os_error("Failed to initialize kcov"));
+        }
+        let area = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                size * size_of::<c_ulong>(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if area == libc::MAP_FAILED {
+            return Err(Error::last_os_error("Failed to map the kcov buffer"));
+        }
+        Ok(Self {
+            fd,
+            area: area.cast(),
+            size,
+            enabled: false,
+        })
+    }
+
+    /// Starts recording the program counters of the current thread, from an empty trace
+    pub fn enable(&mut self) -> Result<(), Error> {
+        self.reset();
+        if !self.enabled {
+            if unsafe { libc::ioctl(self.fd.as_raw_fd(), KCOV_ENABLE as _, KCOV_TRACE_PC) } != 0 {
+                return Err(Error::last_os_error("Failed to enable kcov"));
+            }
+            self.enabled = true;
+        }
+        Ok(())
+    }
+
+    /// Stops recording
+    pub fn disable(&mut self) -> Result<(), Error> {
+        if self.enabled {
+            if unsafe { libc::ioctl(self.fd.as_raw_fd(), KCOV_DISABLE as _, 0) } != 0 {
+                return Err(Error::last_os_error("Failed to disable kcov"));
+            }
+            self.enabled = false;
+        }
+        Ok(())
+    }
+
+    /// Empties the trace
+    pub fn reset(&mut self) {
+        self.counter().store(0, Ordering::Relaxed);
+    }
+
+    /// The program counters recorded since the trace was emptied
+    #[must_use]
+    pub fn pcs(&self) -> &[c_ulong] {
+        let len = self.counter().load(Ordering::Relaxed).min(self.size - 1);
+        unsafe { slice::from_raw_parts(self.area.add(1), len) }
+    }
+
+    /// The kernel updates the count of recorded program counters concurrently
+    fn counter(&self) -> &AtomicUsize {
+        unsafe { &*self.area.cast::<AtomicUsize>() }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Kcov {
+    fn drop(&mut self) {
+        let _ = self.disable();
+        unsafe {
+            libc::munmap(self.area.cast(), self.size * size_of::<c_ulong>());
+        }
+    }
+}
+
+/// Folds a trace of program counters into an edge coverage `map`, like the one of AFL:
+/// each pair of consecutive program counters increments the hit count of an entry, saturating at 255
+pub fn kcov_edges_into<PC>(pcs: &[PC], map: &mut [u8])
+where
+    PC: Copy + Into<u64>,
+{
+    if map.is_empty() {
+        return;
+    }
+    let mut prev = 0;
+    for pc in pcs {
+        let cur = mix((*pc).into());
+        let idx = ((cur ^ prev) % map.len() as u64) as usize;
+        map[idx] = map[idx].saturating_add(1);
+        prev = cur >> 1;
+    }
+}
+
+/// Spreads the bits of a program counter
+fn mix(pc: u64) -> u64 {
+    (pc ^ (pc >> 33)).wrapping_mul(0xff51_afd7_ed55_8ccd) >> 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::kcov_edges_into;
+
+    #[test]
+    fn test_kcov_edges() {
+        let mut map = [0_u8; 64];
+        let pcs: [u64; 4] = [
+            0xffff_ffff_8100_0010,
+            0xffff_ffff_8100_0020,
+            0xffff_ffff_8100_0010,
+            0xffff_ffff_8100_0020,
+        ];
+        kcov_edges_into(&pcs, &mut map);
+        assert_eq!(map.iter().map(|&hits| u32::from(hits)).sum::<u32>(), 4);
+
+        // The second `a -> b` edge hits the same entry again
+        assert!(map.contains(&2));
+    }
+}
@@ -0,0 +1,18 @@
+/*! */
+#![doc = include_str!("../README.md")]
+#![cfg_attr(feature = "document-features", doc = document_features::document_features!())]
+
+pub mod agent;
+pub use agent::{decode_program, encode_program, SyscallProgramConverter};
+
+pub mod kcov;
+#[cfg(target_os = "linux")]
+pub use kcov::Kcov;
+
+#[cfg(all(feature = "nyx", target_os = "linux"))]
+pub mod nyx;
+#[cfg(all(feature = "nyx", target_os = "linux"))]
+pub use nyx::KernelVm;
+
+pub mod oops;
+pub use oops::{parse_oops, KernelCrash, KernelOopsFeedback};
@@ -0,0 +1,108 @@
+//! Runs the kernel in a Nyx VM, restored from a snapshot for each program.
+//!
+//! The Nyx share directory holds the kernel, the initramfs with the agent, and the Nyx configuration.
+//! The agent boots with the kernel, requests the first input from Nyx, which takes the snapshot of the VM at this point,
+//! and then executes one program per run. After each run, Nyx restores the snapshot,
+//! so every program runs on the same freshly booted kernel.
+//!
+//! The coverage map is shared with the VM: Nyx fills it from Intel PT,
+//! or the agent fills it from `kcov`, see [`crate::kcov`].
+
+use std::{fmt, path::Path, time::Duration};
+
+use libafl::{
+    inputs::SyscallTable,
+    observers::{StdMapObserver, StdOutObserver},
+    Error,
+};
+use libafl_bolts::{core_affinity::CoreId, tuples::Handle};
+use libafl_nyx::{executor::NyxExecutor, helper::NyxHelper, settings::NyxSettings};
+
+use crate::agent::SyscallProgramConverter;
+
+/// The size of the buffer passing the programs to the agent, by default
+pub const DEFAULT_PROGRAM_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// A kernel running in a Nyx VM
+pub struct KernelVm {
+    helper: NyxHelper,
+}
+
+impl fmt::Debug for KernelVm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KernelVm")
+            .field("timeout", &self.helper.timeout)
+            .field("bitmap_size", &self.helper.bitmap_size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KernelVm {
+    /// Boots the kernel of the Nyx `share_dir` in a VM on `core_id`, or restores it from the snapshot.
+    ///
+    /// With a `snapshot_core`, the VM on that core boots the kernel and takes the snapshot,
+    /// and the VMs on the other cores start from the snapshot, once it is written to the Nyx work directory.
+    /// Without, the VM boots on its own, and keeps its snapshot in memory.
+    pub fn launch<P>(
+        share_dir: P,
+        core_id: CoreId,
+        snapshot_core: Option<CoreId>,
+        timeout: Duration,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let settings = NyxSettings::builder()
+            .cpu_id(core_id.0)
+            .parent_cpu_id(snapshot_core.map(|core| core.0))
+            .input_buffer_size(DEFAULT_PROGRAM_BUFFER_SIZE)
+            .timeout_secs(u8::try_from(timeout.as_secs()).unwrap_or(u8::MAX))
+            .timeout_micro_secs(timeout.subsec_micros())
+            .build();
+        Ok(Self {
+            helper: NyxHelper::new(share_dir, settings)?,
+        })
+    }
+
+    /// An observer of the coverage map shared with the VM
+    ///
+    /// # Safety
+    /// The observer borrows the map of the VM: use it only as long as the VM runs, and only one at a time.
+    #[must_use]
+    pub unsafe fn coverage_observer(
+        &self,
+        name: &'static str,
+    ) -> StdMapObserver<'static, u8, false> {
+        unsafe {
+            StdMapObserver::from_mut_ptr(name, self.helper.bitmap_buffer, self.helper.bitmap_size)
+        }
+    }
+
+    /// The underlying [`NyxHelper`]
+    #[must_use]
+    pub fn helper(&self) -> &NyxHelper {
+        &self.helper
+    }
+
+    /// The underlying [`NyxHelper`] (mutable)
+    pub fn helper_mut(&mut self) -> &mut NyxHelper {
+        &mut self.helper
+    }
+
+    /// Creates the executor, sending the syscall programs of `table` to the agent.
+    ///
+    /// The `console` observer, which has to be in the `observers`, receives the kernel messages the agent forwards,
+    /// for the [`crate::oops::KernelOopsFeedback`].
+    pub fn executor<S, OT>(
+        self,
+        table: SyscallTable,
+        console: Handle<StdOutObserver>,
+        observers: OT,
+    ) -> NyxExecutor<S, OT, SyscallProgramConverter> {
+        let mut builder = NyxExecutor::builder();
+        builder.stdout(console);
+        builder
+            .target_bytes_converter(SyscallProgramConverter::new(table))
+            .build(self.helper, observers)
+    }
+}
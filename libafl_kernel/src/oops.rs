@@ -0,0 +1,468 @@
+//! Detects kernel crashes in the console output of the guest, like `KASAN` reports, `BUG`s, `WARNING`s and panics.
+//!
+//! Many kernel bugs do not bring the VM down: a `WARNING` or a `KASAN` report is printed, and the kernel carries on.
+//! The agent in the guest forwards the new kernel messages, from `/dev/kmsg`, after each execution,
+//! and the [`KernelOopsFeedback`] parses them with [`parse_oops`] and reports each crash as an objective,
+//! with a [`KernelCrash`] attached to the testcase.
+//! Like [syzkaller](https://github.com/google/syzkaller), a crash gets a title like `KASAN: use-after-free in foo`,
+//! to tell the different bugs apart.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    observers::StdOutObserver,
+    Error, HasMetadata,
+};
+use libafl_bolts::{
+    impl_serdeany,
+    tuples::{Handle, Handled, MatchNameRef},
+    Named,
+};
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of lines of a crash report kept in the [`KernelCrash::report`]
+pub const REPORT_MAX_LINES: usize = 200;
+
+/// The kind of a [`KernelCrash`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KernelCrashKind {
+    /// A memory error found by the Kernel Address Sanitizer
+    Kasan,
+    /// A use of uninitialized memory found by the Kernel Memory Sanitizer
+    Kmsan,
+    /// A memory error found by the Kernel Electric-Fence
+    Kfence,
+    /// Undefined behavior found by the Undefined Behavior Sanitizer
+    Ubsan,
+    /// An invalid memory access, like a `NULL` pointer dereference
+    PageFault,
+    /// A general protection fault
+    GeneralProtectionFault,
+    /// A `BUG()` or another `BUG:` report
+    Bug,
+    /// A `WARN()`, or a report of lockdep or another debugging facility
+    Warning,
+    /// A CPU stuck in the kernel
+    Lockup,
+    /// A task or RCU stalled
+    Hang,
+    /// A kernel panic, without a report before it
+    Panic,
+}
+
+/// A crash of the kernel, parsed from its console output by [`parse_oops`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct KernelCrash {
+    /// The kind of crash
+    pub kind: KernelCrashKind,
+    /// The title, telling this bug apart from others, like `KASAN: use-after-free in foo`
+    pub title: String,
+    /// The function the crash happened in, if the report names it
+    pub function: Option<String>,
+    /// The lines of the report, without timestamps
+    pub report: String,
+}
+
+impl_serdeany!(KernelCrash);
+
+/// Parses the first crash report in the console output of the kernel, if any.
+///
+/// The lines may start with `printk` timestamps, or be records of `/dev/kmsg`.
+#[must_use]
+pub fn parse_oops(log: &str) -> Option<KernelCrash> {
+    let lines: Vec<&str> = log.lines().map(strip_line_prefix).collect();
+    let (start, (kind, header)) = lines
+        .iter()
+        .enumerate()
+        .find_map(|(idx, line)| Some((idx, report_start(line)?)))?;
+
+    let mut report = Vec::new();
+    for line in lines[start..].iter().take(REPORT_MAX_LINES) {
+        report.push(*line);
+        if is_report_end(line) {
+            break;
+        }
+    }
+
+    let function = header.function.or_else(|| crash_function(&report));
+    let title = match (header.title, &function) {
+        (Title::Exact(title), _) => title,
+        (Title::InFunction(prefix), Some(function)) => format!("{prefix} in {function}"),
+        (Title::InFunction(prefix), None) => prefix,
+    };
+    Some(KernelCrash {
+        kind,
+        title,
+        function,
+        report: report.join("\n"),
+    })
+}
+
+/// The title of a crash, possibly still missing the function
+enum Title {
+    Exact(String),
+    InFunction(String),
+}
+
+/// What the first line of a report tells about the crash
+struct Header {
+    title: Title,
+    function: Option<String>,
+}
+
+impl Header {
+    fn exact(title: &str) -> Self {
+        Self {
+            title: Title::Exact(title.into()),
+            function: None,
+        }
+    }
+
+    fn in_function(prefix: &str) -> Self {
+        Self {
+            title: Title::InFunction(prefix.into()),
+            function: None,
+        }
+    }
+}
+
+/// Removes `printk` timestamps and caller ids, like `[   12.345678] [  T123] `, and the fields of `/dev/kmsg` records
+fn strip_line_prefix(line: &str) -> &str {
+    let mut line = line.trim_end();
+    // `/dev/kmsg` records: `<level>,<sequence>,<timestamp>,<flags>;<message>`
+    if let Some((fields, message)) = line.split_once(';') {
+        let fields: Vec<&str> = fields.split(',').collect();
+        if fields.len() >= 4
+            && fields[..3]
+                .iter()
+                .all(|field| !field.is_empty() && field.bytes().all(|b| b.is_ascii_digit()))
+        {
+            line = message;
+        }
+    }
+    loop {
+        let trimmed = line.trim_start();
+        let Some((inner, rest)) = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+        else {
+            return trimmed;
+        };
+        let inner = inner.trim();
+        let is_prefix = !inner.is_empty()
+            && inner
+                .bytes()
+                .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'T' | b'C'));
+        if !is_prefix {
+            return trimmed;
+        }
+        line = rest;
+    }
+}
+
+/// The kind and header of the crash, if the `line` starts a report
+fn report_start(line: &str) -> Option<(KernelCrashKind, Header)> {
+    let line = line.strip_prefix("Oops: ").unwrap_or(line);
+    let line = line.strip_prefix("watchdog: ").unwrap_or(line);
+
+    if let Some(rest) = line.strip_prefix("BUG: ") {
+        for (sanitizer, kind) in [
+            ("KASAN: ", KernelCrashKind::Kasan),
+            ("KMSAN: ", KernelCrashKind::Kmsan),
+            ("KFENCE: ", KernelCrashKind::Kfence),
+        ] {
+            if let Some(report) = rest.strip_prefix(sanitizer) {
+                // `BUG: KASAN: slab-out-of-bounds in foo+0x12/0x34`
+                let (bug, function) = match report.rsplit_once(" in ") {
+                    Some((bug, location)) => (bug, symbol(location)),
+                    None => (report, None),
+                };
+                let prefix = format!("{sanitizer}{bug}");
+                return Some((
+                    kind,
+                    Header {
+                        title: Title::InFunction(prefix),
+                        function,
+                    },
+                ));
+            }
+        }
+        if rest.starts_with("kernel NULL pointer dereference") {
+            return Some((
+                KernelCrashKind::PageFault,
+                Header::in_function("BUG: kernel NULL pointer dereference"),
+            ));
+        }
+        if rest.starts_with("unable to handle") {
+            return Some((
+                KernelCrashKind::PageFault,
+                Header::in_function("BUG: unable to handle page fault"),
+            ));
+        }
+        if rest.starts_with("soft lockup") {
+            return Some((
+                KernelCrashKind::Lockup,
+                Header::in_function("BUG: soft lockup"),
+            ));
+        }
+        return Some((KernelCrashKind::Bug, Header::exact(line)));
+    }
+    if line.starts_with("general protection fault") {
+        return Some((
+            KernelCrashKind::GeneralProtectionFault,
+            Header::in_function("general protection fault"),
+        ));
+    }
+    if line.starts_with("kernel BUG at ") {
+        return Some((KernelCrashKind::Bug, Header::in_function("kernel BUG")));
+    }
+    if line.starts_with("UBSAN: ") {
+        // `UBSAN: array-index-out-of-bounds in fs/foo.c:12:3`, the location is stable enough
+        return Some((KernelCrashKind::Ubsan, Header::exact(line)));
+    }
+    if let Some(rest) = line.strip_prefix("WARNING: ") {
+        // `WARNING: CPU: 0 PID: 1 at net/core/dev.c:123 dev_foo+0x1a/0x30`
+        if rest.starts_with("CPU: ") || rest.starts_with("at ") {
+            let function = rest.rsplit(' ').next().and_then(symbol);
+            return Some((
+                KernelCrashKind::Warning,
+                Header {
+                    title: Title::InFunction("WARNING".into()),
+                    function,
+                },
+            ));
+        }
+        // Lockdep and other checkers, like `WARNING: possible circular locking dependency detected`
+        return Some((KernelCrashKind::Warning, Header::exact(line)));
+    }
+    if line.starts_with("INFO: task ") && line.contains(" blocked for more than ") {
+        return Some((KernelCrashKind::Hang, Header::exact("INFO: task hung")));
+    }
+    if line.contains("rcu detected stall") || line.contains("detected stalls on CPUs") {
+        return Some((
+            KernelCrashKind::Hang,
+            Header::exact("INFO: rcu detected stall"),
+        ));
+    }
+    if let Some(message) = line.strip_prefix("Kernel panic - not syncing: ") {
+        return Some((
+            KernelCrashKind::Panic,
+            Header::exact(&format!("kernel panic: {message}")),
+        ));
+    }
+    None
+}
+
+/// If `line` is the last line of a report
+fn is_report_end(line: &str) -> bool {
+    line.contains("---[ end trace") || line.contains("---[ end Kernel panic")
+}
+
+/// The function the report names as the location of the crash,
+/// from the instruction pointer or else from the first reliable frame of the call trace
+fn crash_function(report: &[&str]) -> Option<String> {
+    for line in report {
+        // x86: `RIP: 0010:foo+0x1a/0x30`, arm64: `pc : foo+0x1c/0x40`, riscv: `epc : foo+0x1c/0x40`
+        let location = line
+            .strip_prefix("RIP: ")
+            .and_then(|rip| rip.rsplit_once(':'))
+            .map(|(_, location)| location)
+            .or_else(|| line.strip_prefix("pc : "))
+            .or_else(|| line.strip_prefix("epc : "));
+        if let Some(function) = location.and_then(symbol) {
+            return Some(function);
+        }
+    }
+    report
+        .iter()
+        .skip_while(|line| !line.starts_with("Call Trace:"))
+        .skip(1)
+        .map(|line| line.trim())
+        .filter(|frame| !frame.starts_with('<') && !frame.starts_with('?'))
+        .find_map(symbol)
+}
+
+/// The function name of a symbol, like `foo` for `foo+0x12/0x34 [module]`
+fn symbol(location: &str) -> Option<String> {
+    let name = location.split_whitespace().next()?.split('+').next()?;
+    let valid = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        && location.contains('+');
+    valid.then(|| name.to_string())
+}
+
+/// The titles of the kernel crashes reported so far, for [`KernelOopsFeedback::unique`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct KernelCrashTitlesMetadata {
+    /// The titles of the reported crashes
+    pub titles: HashSet<String>,
+}
+
+impl_serdeany!(KernelCrashTitlesMetadata);
+
+/// Reports the executions printing a kernel crash report to the console as objectives,
+/// and attaches the parsed [`KernelCrash`] to the testcase.
+///
+/// The console output is read from a [`StdOutObserver`], which the `NyxExecutor` fills with the output of the agent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KernelOopsFeedback {
+    console: Handle<StdOutObserver>,
+    unique: bool,
+    crash: Option<KernelCrash>,
+}
+
+impl KernelOopsFeedback {
+    /// Creates a new [`KernelOopsFeedback`], parsing the output captured by the given observer
+    #[must_use]
+    pub fn new(console: &StdOutObserver) -> Self {
+        Self {
+            console: console.handle(),
+            unique: false,
+            crash: None,
+        }
+    }
+
+    /// Only reports crashes with a title not reported before, to not fill the solutions with the same bug
+    #[must_use]
+    pub fn unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+}
+
+impl Named for KernelOopsFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("KernelOopsFeedback");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for KernelOopsFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for KernelOopsFeedback
+where
+    OT: MatchNameRef,
+    S: HasMetadata,
+{
+    fn is_interesting(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        let console = observers.get(&self.console).ok_or_else(|| {
+            Error::key_not_found("KernelOopsFeedback: console observer not found")
+        })?;
+        self.crash = console
+            .stdout
+            .as_ref()
+            .and_then(|output| parse_oops(&String::from_utf8_lossy(output)))
+            .filter(|crash| {
+                !self.unique
+                    || !state
+                        .metadata::<KernelCrashTitlesMetadata>()
+                        .is_ok_and(|reported| reported.titles.contains(&crash.title))
+            });
+        if let Some(crash) = &self.crash {
+            log::info!("Kernel crash: {}", crash.title);
+        }
+        Ok(self.crash.is_some())
+    }
+
+    fn append_metadata(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        if let Some(crash) = self.crash.take() {
+            state
+                .metadata_or_insert_with(KernelCrashTitlesMetadata::default)
+                .titles
+                .insert(crash.title.clone());
+            testcase.add_metadata(crash);
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.crash = None;
+        Ok(())
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(self.crash.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_oops, KernelCrashKind};
+
+    #[test]
+    fn test_parse_oops() {
+        let kasan = "\
+[   42.123456] random: crng init done
+[   43.000001] ==================================================================
+[   43.000002] BUG: KASAN: slab-use-after-free in tty_release+0x1a/0x30
+[   43.000003] Read of size 8 at addr ffff888012345678 by task agent/123
+[   43.000004] Call Trace:
+[   43.000005]  <TASK>
+[   43.000006]  dump_stack_lvl+0x5c/0x80
+[   43.000007] ---[ end trace 0000000000000000 ]---
+[   43.000008] agent: next program
+";
+        let crash = parse_oops(kasan).unwrap();
+        assert_eq!(crash.kind, KernelCrashKind::Kasan);
+        assert_eq!(crash.title, "KASAN: slab-use-after-free in tty_release");
+        assert_eq!(crash.report.lines().count(), 6);
+
+        // A `/dev/kmsg` record, the function comes from the instruction pointer
+        let gpf = "\
+4,1021,5000123,-;general protection fault, probably for non-canonical address 0xdffffc0000000001: 0000 [#1] SMP KASAN
+4,1022,5000124,-;CPU: 0 PID: 123 Comm: agent Not tainted 6.11.0
+4,1023,5000125,-;RIP: 0010:ext4_fill_super+0x2b5/0x1230
+";
+        let crash = parse_oops(gpf).unwrap();
+        assert_eq!(crash.kind, KernelCrashKind::GeneralProtectionFault);
+        assert_eq!(crash.title, "general protection fault in ext4_fill_super");
+        assert_eq!(crash.function.as_deref(), Some("ext4_fill_super"));
+
+        let warning =
+            "[    7.1] WARNING: CPU: 1 PID: 77 at net/core/dev.c:1234 dev_close_many+0x1a/0x2c0";
+        assert_eq!(
+            parse_oops(warning).unwrap().title,
+            "WARNING in dev_close_many"
+        );
+
+        assert!(
+            parse_oops("[    1.0] Booting the kernel\n[    2.0] Run /init as init process")
+                .is_none()
+        );
+    }
+}
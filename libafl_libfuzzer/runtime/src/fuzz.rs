@@ -7,29 +7,33 @@ use std::{
     net::TcpListener,
     os::fd::AsRawFd,
     str::FromStr,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use libafl::{
     corpus::Corpus,
     events::{
-        launcher::Launcher, EventConfig, EventProcessor, ProgressReporter, SimpleEventManager,
-        SimpleRestartingEventManager,
+        launcher::Launcher, EventConfig, EventProcessor, EventRestarter, ProgressReporter,
+        SimpleEventManager, SimpleRestartingEventManager,
     },
     executors::ExitKind,
     inputs::UsesInput,
     monitors::{tui::TuiMonitor, Monitor, MultiMonitor},
     stages::{HasCurrentStageId, StagesTuple},
-    state::{HasExecutions, HasLastReportTime, HasSolutions, Stoppable, UsesState},
+    state::{HasExecutions, HasLastReportTime, HasSolutions, HasStartTime, Stoppable, UsesState},
     Error, Fuzzer, HasMetadata,
 };
 use libafl_bolts::{
     core_affinity::Cores,
+    current_time,
     shmem::{ShMemProvider, StdShMemProvider},
 };
 
 use crate::{feedbacks::LibfuzzerCrashCauseMetadata, fuzz_with, options::LibfuzzerOptions};
 
+/// How often to report progress when fuzzing for a limited number of runs or time
+const STATS_TIMEOUT: Duration = Duration::from_secs(15);
+
 fn destroy_output_fds(options: &LibfuzzerOptions) {
     #[cfg(unix)]
     {
@@ -69,9 +73,10 @@ where
         + HasSolutions
         + HasLastReportTime
         + HasCurrentStageId
+        + HasStartTime
         + Stoppable,
     E: UsesState<State = S>,
-    EM: ProgressReporter<State = S> + EventProcessor<E, F>,
+    EM: ProgressReporter<State = S> + EventProcessor<E, F> + EventRestarter,
     ST: StagesTuple<E, EM, S, F>,
 {
     if let Some(solution) = state.solutions().last() {
@@ -97,8 +102,31 @@ where
             return Err(Error::shutting_down());
         }
     }
-    fuzzer.fuzz_loop(stages, executor, state, mgr)?;
-    Ok(())
+    if options.runs().is_none() && options.max_total_time().is_none() {
+        fuzzer.fuzz_loop(stages, executor, state, mgr)?;
+        return Ok(());
+    }
+
+    // As in libFuzzer, the runs are the executions of the harness, including the initial inputs
+    loop {
+        if let Some(runs) = options.runs() {
+            if *state.executions() >= runs as u64 {
+                log::info!("Done {runs} runs, halting.");
+                break;
+            }
+        }
+        if let Some(max_total_time) = options.max_total_time() {
+            if current_time().saturating_sub(*state.start_time()) >= max_total_time {
+                log::info!("Done {} seconds, halting.", max_total_time.as_secs());
+                break;
+            }
+        }
+        mgr.maybe_report_progress(state, STATS_TIMEOUT)?;
+        fuzzer.fuzz_one(stages, executor, state, mgr)?;
+    }
+    // Do not get restarted, the limits apply to the whole campaign
+    mgr.send_exiting()?;
+    Err(Error::shutting_down())
 }
 
 fn fuzz_single_forking<M>(
@@ -166,7 +166,7 @@ macro_rules! fuzz_with {
                 CalibrationStage, GeneralizationStage, IfStage, StdMutationalStage,
                 StdPowerMutationalStage, UnicodeIdentificationStage, TracingStage,
             },
-            state::{HasCorpus, StdState},
+            state::{HasCorpus, HasMaxSize, StdState},
             StdFuzzer,
         };
         use libafl_targets::{CmpLogObserver, LLVMCustomMutator, OomFeedback, OomObserver, CMP_MAP};
@@ -252,7 +252,7 @@ macro_rules! fuzz_with {
 
             // A feedback to choose if an input is a solution or not
             let mut objective = feedback_or_fast!(
-                LibfuzzerCrashCauseFeedback::new($options.artifact_prefix().clone()),
+                LibfuzzerCrashCauseFeedback::new($options.artifact_prefix().clone(), $options.exact_artifact_path().cloned()),
                 OomFeedback,
                 feedback_and_fast!(
                     CrashFeedback::new(),
@@ -284,7 +284,7 @@ macro_rules! fuzz_with {
             let mut state = state.unwrap_or_else(|| {
                 StdState::new(
                     // RNG
-                    StdRand::new(),
+                    $options.seed().map_or_else(StdRand::new, StdRand::with_seed),
                     // Corpus that will be evolved, we keep it in memory for performance
                     LibfuzzerCorpus::new(corpus_dir.clone(), 4096),
                     // Corpus in which we store solutions (crashes in this example),
@@ -298,6 +298,9 @@ macro_rules! fuzz_with {
                 .expect("Failed to create state")
             });
             state.metadata_map_mut().insert_boxed(grimoire_metadata);
+            if let Some(max_len) = $options.max_len() {
+                state.set_max_size(max_len);
+            }
 
             // Set up a string category analysis stage for unicode mutations
             let unicode_used = $options.unicode();
@@ -425,7 +428,7 @@ macro_rules! fuzz_with {
             // The wrapped harness function, calling out to the LLVM-style harness
             let mut harness = |input: &BytesInput| {
                 let target = input.target_bytes();
-                let buf = target.as_slice();
+                let buf = $options.truncate(target.as_slice());
 
                 let result = unsafe { crate::libafl_libfuzzer_test_one_input(Some(*$harness), buf.as_ptr(), buf.len()) };
                 match result {
@@ -467,8 +470,9 @@ macro_rules! fuzz_with {
                     println!("We imported {} inputs from disk.", state.corpus().count());
                 }
                 if state.corpus().count() < 1 {
-                    // Generator of bytearrays of max size 64
-                    let mut generator = RandBytesGenerator::from(RandBytesGenerator::new(nonzero!(64)));
+                    // Generator of bytearrays of max size 64, or the max length if smaller
+                    let max_len = NonZeroUsize::new($options.max_len().unwrap_or(64).min(64)).unwrap_or(nonzero!(64));
+                    let mut generator = RandBytesGenerator::from(RandBytesGenerator::new(max_len));
 
                     // Generate 1024 initial inputs
                     state
@@ -668,7 +672,9 @@ pub unsafe extern "C" fn LLVMFuzzerRunDriver(
             let input = BytesInput::from_file(input).unwrap_or_else(|_| {
                 panic!("Couldn't load input {}", input.to_string_lossy().as_ref())
             });
-            libafl_targets::libfuzzer::libfuzzer_test_one_input(input.target_bytes().as_slice());
+            libafl_targets::libfuzzer::libfuzzer_test_one_input(
+                options.truncate(input.target_bytes().as_slice()),
+            );
         }
         return 0;
     }
@@ -115,7 +115,10 @@ pub fn merge(
 
     // A feedback to choose if an input is a solution or not
     let mut objective = feedback_or_fast!(
-        LibfuzzerCrashCauseFeedback::new(options.artifact_prefix().clone()),
+        LibfuzzerCrashCauseFeedback::new(
+            options.artifact_prefix().clone(),
+            options.exact_artifact_path().cloned(),
+        ),
         OomFeedback,
         CrashFeedback::new(),
         TimeoutFeedback::new()
@@ -162,7 +165,7 @@ pub fn merge(
     let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective); // The wrapped harness function, calling out to the LLVM-style harness
     let mut harness = |input: &BytesInput| {
         let target = input.target_bytes();
-        let buf = target.as_slice();
+        let buf = options.truncate(target.as_slice());
 
         let result = unsafe {
             crate::libafl_libfuzzer_test_one_input(Some(*harness), buf.as_ptr(), buf.len())
@@ -105,7 +105,11 @@ pub struct LibfuzzerOptions {
     fuzzer_name: String,
     mode: LibfuzzerMode,
     artifact_prefix: ArtifactPrefix,
+    exact_artifact_path: Option<PathBuf>,
     timeout: Duration,
+    max_total_time: Option<Duration>,
+    max_len: Option<usize>,
+    seed: Option<u64>,
     grimoire: Option<bool>,
     use_value_profile: bool,
     unicode: bool,
@@ -121,7 +125,7 @@ pub struct LibfuzzerOptions {
     shrink: bool,
     skip_tracing: bool,
     tui: bool,
-    runs: usize,
+    runs: Option<usize>,
     close_fd_mask: u8,
     unknown: Vec<String>,
 }
@@ -156,10 +160,34 @@ impl LibfuzzerOptions {
         &self.artifact_prefix
     }
 
+    /// The file to write the single artifact to, instead of a file in the [`ArtifactPrefix`]
+    pub fn exact_artifact_path(&self) -> Option<&PathBuf> {
+        self.exact_artifact_path.as_ref()
+    }
+
     pub fn timeout(&self) -> Duration {
         self.timeout
     }
 
+    pub fn max_total_time(&self) -> Option<Duration> {
+        self.max_total_time
+    }
+
+    /// The maximum length of the inputs. Longer inputs are truncated before they are run.
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    /// Truncate `input` to [`Self::max_len`]
+    pub fn truncate<'b>(&self, input: &'b [u8]) -> &'b [u8] {
+        self.max_len
+            .map_or(input, |max_len| &input[..input.len().min(max_len)])
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
     pub fn grimoire(&self) -> Option<bool> {
         self.grimoire
     }
@@ -220,7 +248,8 @@ impl LibfuzzerOptions {
         self.tui
     }
 
-    pub fn runs(&self) -> usize {
+    /// The number of runs, [`None`] to fuzz until stopped
+    pub fn runs(&self) -> Option<usize> {
         self.runs
     }
 
@@ -238,11 +267,17 @@ impl LibfuzzerOptions {
 struct LibfuzzerOptionsBuilder<'a> {
     mode: Option<LibfuzzerMode>,
     artifact_prefix: Option<&'a str>,
+    exact_artifact_path: Option<&'a str>,
     timeout: Option<Duration>,
+    max_total_time: Option<Duration>,
+    max_len: Option<usize>,
+    seed: Option<u64>,
     grimoire: Option<bool>,
     use_value_profile: Option<bool>,
     unicode: Option<bool>,
     forks: Option<usize>,
+    jobs: Option<usize>,
+    workers: Option<usize>,
     dict: Option<&'a str>,
     dirs: Vec<&'a str>,
     ignore_crashes: Option<bool>,
@@ -255,7 +290,7 @@ struct LibfuzzerOptionsBuilder<'a> {
     shrink: bool,
     skip_tracing: bool,
     tui: bool,
-    runs: usize,
+    runs: Option<usize>,
     close_fd_mask: u8,
     unknown: Vec<&'a str>,
 }
@@ -311,16 +346,40 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                         "artifact_prefix" => {
                             self.artifact_prefix = Some(value);
                         }
+                        "exact_artifact_path" => self.exact_artifact_path = Some(value),
                         "timeout" => {
                             self.timeout =
                                 Some(value.parse().map(Duration::from_secs_f64).map_err(|_| {
                                     OptionsParseError::OptionValueParseFailed(name, value)
                                 })?);
                         }
+                        "max_total_time" => {
+                            // 0, the default of libFuzzer, is no limit
+                            self.max_total_time = match parse_or_bail!(name, value, u64) {
+                                0 => None,
+                                secs => Some(Duration::from_secs(secs)),
+                            };
+                        }
+                        "max_len" => {
+                            // 0, the default of libFuzzer, lets it pick the length
+                            self.max_len = match parse_or_bail!(name, value, usize) {
+                                0 => None,
+                                max_len => Some(max_len),
+                            };
+                        }
+                        "seed" => {
+                            // 0, the default of libFuzzer, is a random seed
+                            self.seed = match parse_or_bail!(name, value, u64) {
+                                0 => None,
+                                seed => Some(seed),
+                            };
+                        }
                         "dict" => self.dict = Some(value),
-                        "fork" | "jobs" => {
+                        "fork" => {
                             self.forks = Some(parse_or_bail!(name, value, usize));
                         }
+                        "jobs" => self.jobs = Some(parse_or_bail!(name, value, usize)),
+                        "workers" => self.workers = Some(parse_or_bail!(name, value, usize)),
                         "ignore_crashes" => {
                             self.ignore_crashes = Some(parse_or_bail!(name, value, u64) > 0);
                         }
@@ -356,7 +415,14 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                                 }
                             }
                         }
-                        "runs" => self.runs = parse_or_bail!(name, value, usize),
+                        "runs" => {
+                            // libFuzzer runs until stopped with a negative count
+                            self.runs = if value.starts_with('-') {
+                                None
+                            } else {
+                                Some(parse_or_bail!(name, value, usize))
+                            };
+                        }
                         "close_fd_mask" => self.close_fd_mask = parse_or_bail!(name, value, u8),
                         _ => {
                             self.unknown.push(arg);
@@ -378,11 +444,23 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                 .artifact_prefix
                 .map(ArtifactPrefix::new)
                 .unwrap_or_default(),
+            exact_artifact_path: self.exact_artifact_path.map(PathBuf::from),
             timeout: self.timeout.unwrap_or(Duration::from_secs(1200)),
+            max_total_time: self.max_total_time,
+            max_len: self.max_len,
+            seed: self.seed,
             grimoire: self.grimoire,
             use_value_profile: self.use_value_profile.unwrap_or(false),
             unicode: self.unicode.unwrap_or(true),
-            forks: self.forks,
+            // The jobs of libFuzzer run on `workers` processes, half of the cores by default
+            forks: self.forks.or_else(|| {
+                self.jobs.map(|jobs| {
+                    self.workers.unwrap_or_else(|| {
+                        let cores = std::thread::available_parallelism().map_or(1, usize::from);
+                        jobs.min(cores / 2).max(1)
+                    })
+                })
+            }),
             dict: self.dict.map(|path| {
                 Tokens::from_file(path).expect("Couldn't load tokens from specified tokens file")
             }),
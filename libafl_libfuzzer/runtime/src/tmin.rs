@@ -83,11 +83,7 @@ fn minimize_crash_with_mutator<M: Mutator<BytesInput, TMinState>>(
             let tmin = StdTMinMutationalStage::new(
                 mutator,
                 factory,
-                if options.runs() == 0 {
-                    128
-                } else {
-                    options.runs()
-                },
+                options.runs().filter(|runs| *runs > 0).unwrap_or(128),
             );
             let mut stages = tuple_list!(tmin);
             fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)?;
@@ -97,11 +93,7 @@ fn minimize_crash_with_mutator<M: Mutator<BytesInput, TMinState>>(
             let tmin = StdTMinMutationalStage::new(
                 mutator,
                 factory,
-                if options.runs() == 0 {
-                    128
-                } else {
-                    options.runs()
-                },
+                options.runs().filter(|runs| *runs > 0).unwrap_or(128),
             );
             let mut stages = tuple_list!(tmin);
             fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr)?;
@@ -118,12 +110,15 @@ fn minimize_crash_with_mutator<M: Mutator<BytesInput, TMinState>>(
             options.dirs()[0].as_path().as_os_str().to_str().unwrap()
         );
     } else {
-        let mut dest = options.artifact_prefix().dir().clone();
-        dest.push(format!(
-            "{}minimized-from-{}",
-            options.artifact_prefix().filename_prefix(),
-            options.dirs()[0].file_name().unwrap().to_str().unwrap()
-        ));
+        let dest = if let Some(path) = options.exact_artifact_path() {
+            path.clone()
+        } else {
+            options.artifact_prefix().dir().join(format!(
+                "{}minimized-from-{}",
+                options.artifact_prefix().filename_prefix(),
+                options.dirs()[0].file_name().unwrap().to_str().unwrap()
+            ))
+        };
         write(&dest, input)?;
         println!(
             "Wrote minimised input to {}",
@@ -6,26 +6,31 @@ use std::{
 
 use libafl::{
     executors::{Executor, ExitKind, HasObservers, HasTimeout},
-    inputs::HasTargetBytes,
+    inputs::{BytesInput, NopTargetBytesConverter, TargetBytesConverter},
     observers::{ObserversTuple, StdOutObserver},
     state::{HasExecutions, State, UsesState},
     Error,
 };
-use libafl_bolts::{tuples::RefIndexable, AsSlice};
+use libafl_bolts::{
+    tuples::{Handle, MatchNameRef, RefIndexable},
+    AsSlice,
+};
 use libnyx::NyxReturnValue;
 
 use crate::helper::NyxHelper;
 
 /// executor for nyx standalone mode
-pub struct NyxExecutor<S, OT> {
+pub struct NyxExecutor<S, OT, TC = NopTargetBytesConverter<BytesInput>> {
     /// implement nyx function
     pub helper: NyxHelper,
-    /// stdout
-    stdout: Option<StdOutObserver>,
+    /// The observer receiving the `hprintf` output of the guest
+    stdout: Option<Handle<StdOutObserver>>,
     /// stderr
     // stderr: Option<StdErrObserver>,
     /// observers
     observers: OT,
+    /// Turns the inputs into the bytes passed to the agent in the VM
+    target_bytes_converter: TC,
     /// phantom data to keep generic type <I,S>
     phantom: PhantomData<S>,
 }
@@ -38,20 +43,20 @@ impl NyxExecutor<(), ()> {
     }
 }
 
-impl<S, OT> UsesState for NyxExecutor<S, OT>
+impl<S, OT, TC> UsesState for NyxExecutor<S, OT, TC>
 where
     S: State,
 {
     type State = S;
 }
 
-impl<EM, S, Z, OT> Executor<EM, Z> for NyxExecutor<S, OT>
+impl<EM, S, Z, OT, TC> Executor<EM, Z> for NyxExecutor<S, OT, TC>
 where
     EM: UsesState<State = S>,
     S: State + HasExecutions,
-    S::Input: HasTargetBytes,
     Z: UsesState<State = S>,
     OT: ObserversTuple<S::Input, S>,
+    TC: TargetBytesConverter<Input = S::Input>,
 {
     fn run_target(
         &mut self,
@@ -62,7 +67,7 @@ where
     ) -> Result<ExitKind, Error> {
         *state.executions_mut() += 1;
 
-        let bytes = input.target_bytes();
+        let bytes = self.target_bytes_converter.to_target_bytes(input);
         let buffer = bytes.as_slice();
 
         if buffer.len() > self.helper.nyx_process.input_buffer_size() {
@@ -114,7 +119,7 @@ where
             }
         };
 
-        if let Some(ob) = self.stdout.as_mut() {
+        if let Some(handle) = &self.stdout {
             let mut stdout = Vec::new();
             self.helper.nyx_stdout.rewind()?;
             self.helper
@@ -122,14 +127,17 @@ where
                 .read_to_end(&mut stdout)
                 .map_err(|e| Error::illegal_state(format!("Failed to read Nyx stdout: {e}")))?;
 
-            ob.observe_stdout(&stdout);
+            self.observers
+                .get_mut(handle)
+                .ok_or_else(|| Error::key_not_found("NyxExecutor: stdout observer not found"))?
+                .observe_stdout(&stdout);
         }
 
         Ok(exit_kind)
     }
 }
 
-impl<S, OT> HasTimeout for NyxExecutor<S, OT> {
+impl<S, OT, TC> HasTimeout for NyxExecutor<S, OT, TC> {
     fn timeout(&self) -> std::time::Duration {
         self.helper.timeout
     }
@@ -151,7 +159,7 @@ impl<S, OT> HasTimeout for NyxExecutor<S, OT> {
     }
 }
 
-impl<S, OT> NyxExecutor<S, OT> {
+impl<S, OT, TC> NyxExecutor<S, OT, TC> {
     /// Convert `trace_bits` ptr into real trace map
     ///
     /// # Safety
@@ -163,9 +171,10 @@ impl<S, OT> NyxExecutor<S, OT> {
     }
 }
 
-pub struct NyxExecutorBuilder {
-    stdout: Option<StdOutObserver>,
+pub struct NyxExecutorBuilder<TC = NopTargetBytesConverter<BytesInput>> {
+    stdout: Option<Handle<StdOutObserver>>,
     // stderr: Option<StdErrObserver>,
+    target_bytes_converter: TC,
 }
 
 impl Default for NyxExecutorBuilder {
@@ -180,14 +189,30 @@ impl NyxExecutorBuilder {
         Self {
             stdout: None,
             // stderr: None,
+            target_bytes_converter: NopTargetBytesConverter::new(),
         }
     }
+}
 
-    pub fn stdout(&mut self, stdout: StdOutObserver) -> &mut Self {
+impl<TC> NyxExecutorBuilder<TC> {
+    /// Passes the `hprintf` output of the guest to this observer, which has to be in the observers of the executor
+    pub fn stdout(&mut self, stdout: Handle<StdOutObserver>) -> &mut Self {
         self.stdout = Some(stdout);
         self
     }
 
+    /// Turns the inputs into the bytes passed to the agent with this converter,
+    /// for inputs without [`libafl::inputs::HasTargetBytes`]
+    pub fn target_bytes_converter<TC2>(
+        self,
+        target_bytes_converter: TC2,
+    ) -> NyxExecutorBuilder<TC2> {
+        NyxExecutorBuilder {
+            stdout: self.stdout,
+            target_bytes_converter,
+        }
+    }
+
     /*
     pub fn stderr(&mut self, stderr: StdErrObserver) -> &mut Self {
         self.stderr = Some(stderr);
@@ -195,18 +220,19 @@ impl NyxExecutorBuilder {
     }
     */
 
-    pub fn build<S, OT>(&self, helper: NyxHelper, observers: OT) -> NyxExecutor<S, OT> {
+    pub fn build<S, OT>(self, helper: NyxHelper, observers: OT) -> NyxExecutor<S, OT, TC> {
         NyxExecutor {
             helper,
-            stdout: self.stdout.clone(),
+            stdout: self.stdout,
             // stderr: self.stderr.clone(),
             observers,
+            target_bytes_converter: self.target_bytes_converter,
             phantom: PhantomData,
         }
     }
 }
 
-impl<S, OT> HasObservers for NyxExecutor<S, OT>
+impl<S, OT, TC> HasObservers for NyxExecutor<S, OT, TC>
 where
     S: State,
     OT: ObserversTuple<S::Input, S>,
@@ -17,7 +17,7 @@ void __libafl_qemu_testfile() {}
 pub fn build() {
     // Note: Unique features are checked in libafl_qemu_sys
     println!(
-        r#"cargo::rustc-check-cfg=cfg(cpu_target, values("arm", "aarch64", "hexagon", "i386", "mips", "ppc", "riscv32", "riscv64", "x86_64"))"#
+        r#"cargo::rustc-check-cfg=cfg(cpu_target, values("arm", "aarch64", "hexagon", "i386", "mips", "mips64", "ppc", "ppc64", "riscv32", "riscv64", "x86_64"))"#
     );
 
     let emulation_mode = if cfg!(feature = "usermode") {
@@ -90,8 +90,12 @@ pub fn build() {
         "i386".to_string()
     } else if cfg!(feature = "mips") {
         "mips".to_string()
+    } else if cfg!(feature = "mips64") {
+        "mips64".to_string()
     } else if cfg!(feature = "ppc") {
         "ppc".to_string()
+    } else if cfg!(feature = "ppc64") {
+        "ppc64".to_string()
     } else if cfg!(feature = "riscv32") {
         "riscv32".to_string()
     } else if cfg!(feature = "riscv64") {
@@ -103,7 +107,7 @@ pub fn build() {
     };
     println!("cargo:rerun-if-env-changed=CPU_TARGET");
     println!("cargo:rustc-cfg=cpu_target=\"{cpu_target}\"");
-    println!("cargo::rustc-check-cfg=cfg(cpu_target, values(\"x86_64\", \"arm\", \"aarch64\", \"i386\", \"mips\", \"ppc\", \"hexagon\", \"riscv32\", \"riscv64\"))");
+    println!("cargo::rustc-check-cfg=cfg(cpu_target, values(\"x86_64\", \"arm\", \"aarch64\", \"i386\", \"mips\", \"mips64\", \"ppc\", \"ppc64\", \"hexagon\", \"riscv32\", \"riscv64\"))");
 
     let cross_cc = if cfg!(feature = "usermode") && (qemu_asan || qemu_asan_guest) {
         // TODO try to autodetect a cross compiler with the arch name (e.g. aarch64-linux-gnu-gcc)
@@ -133,7 +137,6 @@ pub fn build() {
     .expect("Could not copy libafl_qemu.h to out directory.");
 
     fs::copy(
-
         libafl_qemu_arch_hdr.clone(),
         include_dir.join(libafl_qemu_arch_hdr_name),
     )
@@ -57,6 +57,8 @@ const WRAPPER_HEADER: &str = r#"
 #include "migration/savevm.h"
 #include "hw/core/sysemu-cpu-ops.h"
 #include "exec/address-spaces.h"
+#include "exec/memory.h"
+#include "hw/irq.h"
 #include "sysemu/tcg.h"
 #include "sysemu/runstate.h"
 #include "sysemu/replay.h"
@@ -172,6 +174,15 @@ pub fn generate(
         .allowlist_function("qemu_target_page_size")
         .allowlist_function("syx_.*")
         .allowlist_function("device_list_all")
+        .allowlist_type("MemoryRegion")
+        .allowlist_type("MemoryRegionOps")
+        .allowlist_function("get_system_memory")
+        .allowlist_function("memory_region_init_io")
+        .allowlist_function("memory_region_add_subregion_overlap")
+        .allowlist_function("object_resolve_path")
+        .allowlist_function("object_dynamic_cast")
+        .allowlist_function("qdev_get_gpio_in_named")
+        .allowlist_function("qemu_set_irq")
         .allowlist_function("libafl_.*")
         .allowlist_function("read_self_maps")
         .allowlist_function("free_self_maps")
@@ -268,10 +268,18 @@ pub fn build(
         cpu_target += "eb";
     }
 
-    if !is_big_endian && cpu_target == "mips" && !cfg!(feature = "clippy") {
+    if !is_big_endian
+        && (cpu_target == "mips" || cpu_target == "mips64")
+        && !cfg!(feature = "clippy")
+    {
         cpu_target += "el";
     }
 
+    // Unlike ppc, ppc64 is mostly run little endian
+    if !is_big_endian && cpu_target == "ppc64" && !cfg!(feature = "clippy") {
+        cpu_target += "le";
+    }
+
     let libafl_qemu_dir = env::var_os("LIBAFL_QEMU_DIR").map(|x| x.to_string_lossy().to_string());
     let libafl_qemu_clone_dir =
         env::var_os("LIBAFL_QEMU_CLONE_DIR").map(|x| x.to_string_lossy().to_string());
@@ -224,6 +224,8 @@ fn qemu_bindgen_clang_args(
         "x86_64" => format!("-I{}/target/i386", qemu_dir.display()),
         "aarch64" => format!("-I{}/target/arm", qemu_dir.display()),
         "riscv32" | "riscv64" => format!("-I{}/target/riscv", qemu_dir.display()),
+        "mips64" => format!("-I{}/target/mips", qemu_dir.display()),
+        "ppc64" => format!("-I{}/target/ppc", qemu_dir.display()),
         _ => format!("-I{}/target/{cpu_target}", qemu_dir.display()),
     };
 
@@ -42,10 +42,11 @@ pub fn build() {
     // Make sure we have at most one architecutre feature set
     // Else, we default to `x86_64` - having a default makes CI easier :)
     assert_unique_feature!(
-        "arm", "aarch64", "i386", "x86_64", "mips", "ppc", "hexagon", "riscv32", "riscv64"
+        "arm", "aarch64", "i386", "x86_64", "mips", "mips64", "ppc", "ppc64", "hexagon", "riscv32",
+        "riscv64"
     );
 
-    // Make sure that we don't have BE set for any architecture other than arm and mips
+    // Make sure that we don't have BE set for any architecture other than arm, mips and ppc
     // Sure aarch64 may support BE, but its not in common usage and we don't
     // need it yet and so haven't tested it
     assert_unique_feature!("be", "aarch64", "i386", "x86_64", "hexagon", "riscv32", "riscv64");
@@ -60,8 +61,12 @@ pub fn build() {
         "i386".to_string()
     } else if cfg!(feature = "mips") {
         "mips".to_string()
+    } else if cfg!(feature = "mips64") {
+        "mips64".to_string()
     } else if cfg!(feature = "ppc") {
         "ppc".to_string()
+    } else if cfg!(feature = "ppc64") {
+        "ppc64".to_string()
     } else if cfg!(feature = "riscv32") {
         "riscv32".to_string()
     } else if cfg!(feature = "riscv64") {
@@ -71,7 +76,7 @@ pub fn build() {
     } else {
         env::var("CPU_TARGET").unwrap_or_else(|_| {
             println!(
-                "cargo:warning=No architecture feature enabled or CPU_TARGET env specified for libafl_qemu, supported: arm, aarch64, hexagon, i386, mips, ppc, riscv32, riscv64, x86_64 - defaulting to x86_64"
+                "cargo:warning=No architecture feature enabled or CPU_TARGET env specified for libafl_qemu, supported: arm, aarch64, hexagon, i386, mips, mips64, ppc, ppc64, riscv32, riscv64, x86_64 - defaulting to x86_64"
             );
             "x86_64".to_string()
         })
@@ -79,7 +84,7 @@ pub fn build() {
     println!("cargo:rerun-if-env-changed=CPU_TARGET");
     println!("cargo:rerun-if-env-changed=LIBAFL_QEMU_GEN_STUBS");
     println!("cargo:rustc-cfg=cpu_target=\"{cpu_target}\"");
-    println!("cargo::rustc-check-cfg=cfg(cpu_target, values(\"x86_64\", \"arm\", \"aarch64\", \"i386\", \"mips\", \"ppc\", \"hexagon\", \"riscv32\", \"riscv64\"))");
+    println!("cargo::rustc-check-cfg=cfg(cpu_target, values(\"x86_64\", \"arm\", \"aarch64\", \"i386\", \"mips\", \"mips64\", \"ppc\", \"ppc64\", \"hexagon\", \"riscv32\", \"riscv64\"))");
 
     let jobs = env::var("NUM_JOBS")
         .ok()
@@ -103,6 +103,50 @@ uint8_t *__libqasan_patch_jump(uint8_t *addr, uint8_t *dest) {
   return &addr[16];
 }
 
+#elif __riscv
+
+// in RISC-V, t1 is a temporary register not preserved across calls,
+// so let's use it in our stub
+
+uint8_t *__libqasan_patch_jump(uint8_t *addr, uint8_t *dest) {
+  // auipc t1, 0
+  addr[0] = 0x17;
+  addr[1] = 0x03;
+  addr[2] = 0x0;
+  addr[3] = 0x0;
+
+  #if __riscv_xlen == 64
+  // ld t1, 16(t1)
+  addr[4] = 0x03;
+  addr[5] = 0x33;
+  addr[6] = 0x03;
+  addr[7] = 0x01;
+  #else
+  // lw t1, 16(t1)
+  addr[4] = 0x03;
+  addr[5] = 0x23;
+  addr[6] = 0x03;
+  addr[7] = 0x01;
+  #endif
+
+  // jr t1
+  addr[8] = 0x67;
+  addr[9] = 0x0;
+  addr[10] = 0x03;
+  addr[11] = 0x0;
+
+  // nop
+  addr[12] = 0x13;
+  addr[13] = 0x0;
+  addr[14] = 0x0;
+  addr[15] = 0x0;
+
+  // OFF: .dword dest
+  *(uintptr_t *)&addr[16] = (uintptr_t)dest;
+
+  return &addr[16 + sizeof(uintptr_t)];
+}
+
 #else
 
   #define CANNOT_HOTPATCH
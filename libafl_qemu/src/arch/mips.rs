@@ -1,15 +1,22 @@
-use std::sync::OnceLock;
+use std::{mem::size_of, sync::OnceLock};
 
+use capstone::arch::{BuildsCapstone, BuildsCapstoneEndian};
 use enum_map::{enum_map, EnumMap};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 pub use strum_macros::EnumIter;
+#[cfg(not(feature = "mips64"))]
 pub use syscall_numbers::mips::*;
+#[cfg(feature = "mips64")]
+pub use syscall_numbers::mips64::*;
 
-use crate::{sync_exit::ExitArgs, CallingConvention, QemuRWError, QemuRWErrorKind};
+use crate::{
+    arch::BranchKind, sync_exit::ExitArgs, CallingConvention, GuestAddr, QemuRWError,
+    QemuRWErrorKind, CPU,
+};
 
-/// Registers for the MIPS instruction set.
+/// Registers for the MIPS and MIPS64 instruction sets.
 #[derive(IntoPrimitive, TryFromPrimitive, Debug, Clone, Copy, EnumIter)]
 #[repr(i32)]
 pub enum Regs {
@@ -46,6 +53,11 @@ pub enum Regs {
     Fp = 30,
     Ra = 31,
 
+    Status = 32,
+    Lo = 33,
+    Hi = 34,
+    Badvaddr = 35,
+    Cause = 36,
     Pc = 37,
 }
 
@@ -70,6 +82,16 @@ pub fn get_exit_arch_regs() -> &'static EnumMap<ExitArgs, Regs> {
 #[allow(non_upper_case_globals)]
 impl Regs {
     pub const Zero: Regs = Regs::R0;
+    pub const S8: Regs = Regs::Fp;
+    /// The n64 ABI passes the arguments 4 to 7 in `$t0`-`$t3`, named `$a4`-`$a7`
+    #[cfg(feature = "mips64")]
+    pub const A4: Regs = Regs::T0;
+    #[cfg(feature = "mips64")]
+    pub const A5: Regs = Regs::T1;
+    #[cfg(feature = "mips64")]
+    pub const A6: Regs = Regs::T2;
+    #[cfg(feature = "mips64")]
+    pub const A7: Regs = Regs::T3;
 }
 
 #[cfg(feature = "python")]
@@ -82,10 +104,65 @@ impl IntoPy<PyObject> for Regs {
 
 /// Return an MIPS ArchCapstoneBuilder
 pub fn capstone() -> capstone::arch::mips::ArchCapstoneBuilder {
-    capstone::Capstone::new().mips()
+    #[cfg(not(feature = "mips64"))]
+    let mode = capstone::arch::mips::ArchMode::Mips32;
+    #[cfg(feature = "mips64")]
+    let mode = capstone::arch::mips::ArchMode::Mips64;
+    #[cfg(not(feature = "be"))]
+    let endian = capstone::Endian::Little;
+    #[cfg(feature = "be")]
+    let endian = capstone::Endian::Big;
+
+    capstone::Capstone::new().mips().mode(mode).endian(endian)
 }
 
+#[cfg(not(feature = "mips64"))]
 pub type GuestReg = u32;
+#[cfg(feature = "mips64")]
+pub type GuestReg = u64;
+
+/// The registers passing the first arguments of a function
+#[cfg(not(feature = "mips64"))]
+const ARGUMENT_REGS: [Regs; 4] = [Regs::A0, Regs::A1, Regs::A2, Regs::A3];
+#[cfg(feature = "mips64")]
+const ARGUMENT_REGS: [Regs; 8] = [
+    Regs::A0,
+    Regs::A1,
+    Regs::A2,
+    Regs::A3,
+    Regs::A4,
+    Regs::A5,
+    Regs::A6,
+    Regs::A7,
+];
+
+/// The address on the stack of the argument `idx`, at the entry of a function.
+///
+/// In the o32 ABI, the caller reserves a slot on the stack for each argument, including the ones passed in registers.
+/// In the n64 ABI, the arguments passed in registers have no slot.
+fn stack_argument_addr(cpu: &CPU, idx: usize) -> Result<GuestAddr, QemuRWError> {
+    let sp: GuestAddr = cpu.read_reg(Regs::Sp)?;
+    #[cfg(not(feature = "mips64"))]
+    let slot = idx;
+    #[cfg(feature = "mips64")]
+    let slot = idx - ARGUMENT_REGS.len();
+    Ok(sp + (slot * size_of::<GuestReg>()) as GuestAddr)
+}
+
+/// Classify a branch, from its capstone mnemonic and operands.
+/// The calls return after their delay slot, except for the compact branches of MIPS r6.
+#[must_use]
+pub fn branch_kind(mnemonic: &str, op_str: &str) -> Option<BranchKind> {
+    match mnemonic {
+        "jr" | "jr.hb" | "jrc" if op_str == "$ra" => Some(BranchKind::Ret),
+        "balc" | "jalrc" | "jialc" => Some(BranchKind::Call { return_offset: 4 }),
+        "jal" | "jalr" | "jalr.hb" | "jalx" | "bal" | "bgezal" | "bltzal" | "bgezall"
+        | "bltzall" => Some(BranchKind::Call { return_offset: 8 }),
+        "break" | "bitswap" => None,
+        _ if mnemonic.starts_with('b') || mnemonic.starts_with('j') => Some(BranchKind::Jump),
+        _ => None,
+    }
+}
 
 impl crate::ArchExtras for crate::CPU {
     fn read_return_address(&self) -> Result<GuestReg, QemuRWError> {
@@ -106,21 +183,17 @@ impl crate::ArchExtras for crate::CPU {
     ) -> Result<GuestReg, QemuRWError> {
         QemuRWError::check_conv(QemuRWErrorKind::Read, CallingConvention::Cdecl, conv)?;
 
-        let reg_id = match idx {
-            0 => Regs::A0,
-            1 => Regs::A1,
-            2 => Regs::A2,
-            3 => Regs::A3,
-            // 4.. would be on the stack, let's not do this for now
-            r => {
-                return Err(QemuRWError::new_argument_error(
-                    QemuRWErrorKind::Read,
-                    i32::from(r),
-                ))
-            }
-        };
+        if let Some(reg) = ARGUMENT_REGS.get(usize::from(idx)) {
+            return self.read_reg(*reg);
+        }
 
-        self.read_reg(reg_id)
+        let mut val = [0u8; size_of::<GuestReg>()];
+        self.read_mem(stack_argument_addr(self, usize::from(idx))?, &mut val)?;
+        #[cfg(feature = "be")]
+        let val = GuestReg::from_be_bytes(val);
+        #[cfg(not(feature = "be"))]
+        let val = GuestReg::from_le_bytes(val);
+        Ok(val)
     }
 
     fn write_function_argument<T>(
@@ -135,10 +208,17 @@ impl crate::ArchExtras for crate::CPU {
         QemuRWError::check_conv(QemuRWErrorKind::Write, CallingConvention::Cdecl, conv)?;
 
         let val: GuestReg = val.into();
-        match idx {
-            0 => self.write_reg(Regs::A0, val),
-            1 => self.write_reg(Regs::A1, val),
-            r => Err(QemuRWError::new_argument_error(QemuRWErrorKind::Write, r)),
+        let Ok(slot) = usize::try_from(idx) else {
+            return Err(QemuRWError::new_argument_error(QemuRWErrorKind::Write, idx));
+        };
+        if let Some(reg) = ARGUMENT_REGS.get(slot) {
+            return self.write_reg(*reg, val);
         }
+
+        #[cfg(feature = "be")]
+        let bytes = val.to_be_bytes();
+        #[cfg(not(feature = "be"))]
+        let bytes = val.to_le_bytes();
+        self.write_mem(stack_argument_addr(self, slot)?, &bytes)
     }
 }
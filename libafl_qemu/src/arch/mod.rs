@@ -18,14 +18,14 @@ pub mod x86_64;
 #[cfg(cpu_target = "x86_64")]
 pub use x86_64::*;
 
-#[cfg(cpu_target = "mips")]
+#[cfg(any(cpu_target = "mips", cpu_target = "mips64"))]
 pub mod mips;
-#[cfg(cpu_target = "mips")]
+#[cfg(any(cpu_target = "mips", cpu_target = "mips64"))]
 pub use mips::*;
 
-#[cfg(cpu_target = "ppc")]
+#[cfg(any(cpu_target = "ppc", cpu_target = "ppc64"))]
 pub mod ppc;
-#[cfg(cpu_target = "ppc")]
+#[cfg(any(cpu_target = "ppc", cpu_target = "ppc64"))]
 pub use ppc::*;
 
 #[cfg(cpu_target = "hexagon")]
@@ -37,3 +37,21 @@ pub use hexagon::*;
 pub mod riscv;
 #[cfg(any(cpu_target = "riscv32", cpu_target = "riscv64"))]
 pub use riscv::*;
+
+/// The kind of a branch, for the architectures whose calls and returns are not all in the capstone groups
+#[cfg(any(
+    cpu_target = "mips",
+    cpu_target = "mips64",
+    cpu_target = "ppc",
+    cpu_target = "ppc64"
+))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    /// A call, returning `return_offset` bytes after it
+    Call {
+        return_offset: usize,
+    },
+    Ret,
+    /// Any other branch, ending the block
+    Jump,
+}
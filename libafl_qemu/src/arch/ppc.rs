@@ -1,15 +1,22 @@
-use std::sync::OnceLock;
+use std::{mem::size_of, sync::OnceLock};
 
+use capstone::arch::{BuildsCapstone, BuildsCapstoneEndian};
 use enum_map::{enum_map, EnumMap};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 #[cfg(feature = "python")]
 use pyo3::prelude::*;
 pub use strum_macros::EnumIter;
+#[cfg(not(feature = "ppc64"))]
 pub use syscall_numbers::powerpc::*;
+#[cfg(feature = "ppc64")]
+pub use syscall_numbers::powerpc64::*;
 
-use crate::{sync_exit::ExitArgs, CallingConvention, QemuRWError, QemuRWErrorKind};
+use crate::{
+    arch::BranchKind, sync_exit::ExitArgs, CallingConvention, GuestAddr, QemuRWError,
+    QemuRWErrorKind, CPU,
+};
 
-/// Registers for the MIPS instruction set.
+/// Registers for the PowerPC and PowerPC64 instruction sets.
 #[derive(IntoPrimitive, TryFromPrimitive, Debug, Clone, Copy, EnumIter)]
 #[repr(i32)]
 pub enum Regs {
@@ -110,6 +117,9 @@ pub fn get_exit_arch_regs() -> &'static EnumMap<ExitArgs, Regs> {
 impl Regs {
     pub const Pc: Regs = Regs::Nip;
     pub const Sp: Regs = Regs::R1;
+    /// The table of contents pointer of the 64-bit ABIs
+    #[cfg(feature = "ppc64")]
+    pub const Toc: Regs = Regs::R2;
 }
 
 #[cfg(feature = "python")]
@@ -120,12 +130,65 @@ impl IntoPy<PyObject> for Regs {
     }
 }
 
-/// Return an MIPS ArchCapstoneBuilder
+/// Return a PowerPC ArchCapstoneBuilder
 pub fn capstone() -> capstone::arch::ppc::ArchCapstoneBuilder {
-    capstone::Capstone::new().ppc()
+    #[cfg(not(feature = "ppc64"))]
+    let mode = capstone::arch::ppc::ArchMode::Mode32;
+    #[cfg(feature = "ppc64")]
+    let mode = capstone::arch::ppc::ArchMode::Mode64;
+    #[cfg(not(feature = "be"))]
+    let endian = capstone::Endian::Little;
+    #[cfg(feature = "be")]
+    let endian = capstone::Endian::Big;
+
+    capstone::Capstone::new().ppc().mode(mode).endian(endian)
 }
 
+#[cfg(not(feature = "ppc64"))]
 pub type GuestReg = u32;
+#[cfg(feature = "ppc64")]
+pub type GuestReg = u64;
+
+/// The registers passing the first arguments of a function
+const ARGUMENT_REGS: [Regs; 8] = [
+    Regs::R3,
+    Regs::R4,
+    Regs::R5,
+    Regs::R6,
+    Regs::R7,
+    Regs::R8,
+    Regs::R9,
+    Regs::R10,
+];
+
+/// The address on the stack of the argument `idx`, at the entry of a function.
+///
+/// The 32-bit ABI stores the arguments after the back chain and the LR save word,
+/// without a slot for the ones passed in registers.
+/// The 64-bit ABIs have a parameter save area with a slot for each argument,
+/// after a header of 48 bytes with ELFv1 (big endian), and 32 bytes with ELFv2 (little endian).
+fn stack_argument_addr(cpu: &CPU, idx: usize) -> Result<GuestAddr, QemuRWError> {
+    let sp: GuestAddr = cpu.read_reg(Regs::Sp)?;
+    #[cfg(not(feature = "ppc64"))]
+    let (header, slot) = (8, idx - ARGUMENT_REGS.len());
+    #[cfg(all(feature = "ppc64", feature = "be"))]
+    let (header, slot) = (48, idx);
+    #[cfg(all(feature = "ppc64", not(feature = "be")))]
+    let (header, slot) = (32, idx);
+    Ok(sp + (header + slot * size_of::<GuestReg>()) as GuestAddr)
+}
+
+/// Classify a branch, from its capstone mnemonic and operands
+#[must_use]
+pub fn branch_kind(mnemonic: &str, _op_str: &str) -> Option<BranchKind> {
+    match mnemonic {
+        "blr" => Some(BranchKind::Ret),
+        "bl" | "bla" | "bctrl" | "blrl" => Some(BranchKind::Call { return_offset: 4 }),
+        // Conditional returns and calls, and `bcl` getting the PC, end the block like the jumps
+        _ if mnemonic.starts_with('b') => Some(BranchKind::Jump),
+        _ => None,
+    }
+}
 
 impl crate::ArchExtras for crate::CPU {
     fn read_return_address(&self) -> Result<GuestReg, QemuRWError> {
@@ -146,22 +209,17 @@ impl crate::ArchExtras for crate::CPU {
     ) -> Result<GuestReg, QemuRWError> {
         QemuRWError::check_conv(QemuRWErrorKind::Read, CallingConvention::Cdecl, conv)?;
 
-        let reg_id = match idx {
-            0 => Regs::R3,
-            1 => Regs::R4,
-            2 => Regs::R5,
-            3 => Regs::R6,
-            4 => Regs::R7,
-            5 => Regs::R8,
-            r => {
-                return Err(QemuRWError::new_argument_error(
-                    QemuRWErrorKind::Read,
-                    i32::from(r),
-                ))
-            }
-        };
+        if let Some(reg) = ARGUMENT_REGS.get(usize::from(idx)) {
+            return self.read_reg(*reg);
+        }
 
-        self.read_reg(reg_id)
+        let mut val = [0u8; size_of::<GuestReg>()];
+        self.read_mem(stack_argument_addr(self, usize::from(idx))?, &mut val)?;
+        #[cfg(feature = "be")]
+        let val = GuestReg::from_be_bytes(val);
+        #[cfg(not(feature = "be"))]
+        let val = GuestReg::from_le_bytes(val);
+        Ok(val)
     }
 
     fn write_function_argument<T>(
@@ -176,10 +234,17 @@ impl crate::ArchExtras for crate::CPU {
         QemuRWError::check_conv(QemuRWErrorKind::Write, CallingConvention::Cdecl, conv)?;
 
         let val: GuestReg = val.into();
-        match idx {
-            0 => self.write_reg(Regs::R3, val),
-            1 => self.write_reg(Regs::R4, val),
-            r => Err(QemuRWError::new_argument_error(QemuRWErrorKind::Write, r)),
+        let Ok(slot) = usize::try_from(idx) else {
+            return Err(QemuRWError::new_argument_error(QemuRWErrorKind::Write, idx));
+        };
+        if let Some(reg) = ARGUMENT_REGS.get(slot) {
+            return self.write_reg(*reg, val);
         }
+
+        #[cfg(feature = "be")]
+        let bytes = val.to_be_bytes();
+        #[cfg(not(feature = "be"))]
+        let bytes = val.to_le_bytes();
+        self.write_mem(stack_argument_addr(self, slot)?, &bytes)
     }
 }
@@ -2,7 +2,7 @@
 
 use std::{fs::File, io::Read, ops::Range, path::Path, str};
 
-use goblin::elf::{header::ET_DYN, Elf};
+use goblin::elf::{header::ET_DYN, sym::STT_OBJECT, Elf};
 use libafl::Error;
 use libafl_qemu_sys::GuestAddr;
 
@@ -74,6 +74,34 @@ impl<'a> EasyElf<'a> {
         None
     }
 
+    /// The symbol containing `addr`, and the offset of `addr` in it
+    #[must_use]
+    pub fn symbol_at(&self, addr: GuestAddr, load_addr: GuestAddr) -> Option<(&'a str, GuestAddr)> {
+        let addr = if self.is_pic() {
+            addr.checked_sub(load_addr)?
+        } else {
+            addr
+        };
+        for sym in &self.elf.syms {
+            if sym.st_value == 0
+                || sym.st_size == 0
+                || !(sym.is_function() || sym.st_type() == STT_OBJECT)
+            {
+                continue;
+            }
+            #[cfg(cpu_target = "arm")]
+            // Required because of arm interworking addresses aka bit(0) for thumb mode
+            let start = (sym.st_value as GuestAddr) & !(0x1 as GuestAddr);
+            #[cfg(not(cpu_target = "arm"))]
+            let start = sym.st_value as GuestAddr;
+            if addr >= start && addr - start < sym.st_size as GuestAddr {
+                let name = self.elf.strtab.get_at(sym.st_name)?;
+                return Some((name, addr - start));
+            }
+        }
+        None
+    }
+
     #[must_use]
     pub fn get_section(&self, name: &str, load_addr: GuestAddr) -> Option<Range<GuestAddr>> {
         for section in &self.elf.section_headers {
@@ -19,7 +19,7 @@ use crate::{
     command::{CommandError, CommandManager, NopCommandManager, StdCommandManager},
     modules::EmulatorModuleTuple,
     sync_exit::SyncExit,
-    Qemu, QemuExitError, QemuExitReason, QemuHooks, QemuInitError, QemuMemoryChunk,
+    Qemu, QemuExitError, QemuExitReason, QemuHooks, QemuInitError, QemuMemoryChunk, QemuRWError,
     QemuShutdownCause, Regs, CPU,
 };
 
@@ -302,6 +302,25 @@ where
     pub fn command_manager_mut(&mut self) -> &mut CM {
         &mut self.command_manager
     }
+
+    /// Read guest memory, see [`Qemu::read_mem`]
+    pub fn read_mem(&self, addr: GuestAddr, buf: &mut [u8]) -> Result<(), QemuRWError> {
+        self.qemu.read_mem(addr, buf)
+    }
+
+    /// Write guest memory, see [`Qemu::write_mem`]
+    pub fn write_mem(&self, addr: GuestAddr, buf: &[u8]) -> Result<(), QemuRWError> {
+        self.qemu.write_mem(addr, buf)
+    }
+
+    pub fn read_mem_vec(&self, addr: GuestAddr, len: usize) -> Result<Vec<u8>, QemuRWError> {
+        self.qemu.read_mem_vec(addr, len)
+    }
+
+    /// Read a NUL-terminated string from guest memory, see [`Qemu::read_cstring`]
+    pub fn read_cstring(&self, addr: GuestAddr, max_len: usize) -> Result<Vec<u8>, QemuRWError> {
+        self.qemu.read_cstring(addr, max_len)
+    }
 }
 
 impl<CM, ED, ET, S, SM> Emulator<CM, ED, ET, S, SM>
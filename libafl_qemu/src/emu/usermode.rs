@@ -1,5 +1,5 @@
 use libafl::inputs::UsesInput;
-use libafl_qemu_sys::{GuestAddr, MmapPerms, VerifyAccess};
+use libafl_qemu_sys::{GuestAddr, MapInfo, MmapPerms, VerifyAccess};
 
 use crate::{command::CommandManager, Emulator, GuestMaps, NopSnapshotManager};
 
@@ -16,6 +16,24 @@ where
         self.qemu.mappings()
     }
 
+    /// The mapping of the guest containing `addr`
+    #[must_use]
+    pub fn mapping(&self, addr: GuestAddr) -> Option<MapInfo> {
+        self.qemu.mapping(addr)
+    }
+
+    /// The addresses of the symbol `name` in the ELF files mapped in the guest
+    #[must_use]
+    pub fn find_symbol(&self, name: &str) -> Vec<GuestAddr> {
+        self.qemu.find_symbol(name)
+    }
+
+    /// The symbol at `addr`, and the offset of `addr` in it
+    #[must_use]
+    pub fn symbol_at(&self, addr: GuestAddr) -> Option<(String, GuestAddr)> {
+        self.qemu.symbol_at(addr)
+    }
+
     #[must_use]
     pub fn g2h<T>(&self, addr: GuestAddr) -> *mut T {
         self.qemu.g2h(addr)
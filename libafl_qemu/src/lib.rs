@@ -42,6 +42,7 @@ pub use emu::*;
 
 pub mod breakpoint;
 pub mod command;
+pub mod persistent;
 pub mod sync_exit;
 
 pub use libafl_qemu_sys::{GuestAddr, MmapPerms};
@@ -321,24 +321,56 @@ where
                     break;
                 }
                 let insn = insns.first().unwrap();
-                let insn_detail: InsnDetail = h.cs.insn_detail(insn).unwrap();
-                for detail in insn_detail.groups() {
-                    match u32::from(detail.0) {
-                        capstone::InsnGroupType::CS_GRP_CALL => {
-                            let call_len = insn.bytes().len();
-                            call_addrs.push((insn.address() as GuestAddr, call_len));
-                        }
-                        capstone::InsnGroupType::CS_GRP_RET => {
-                            ret_addrs.push(insn.address() as GuestAddr);
-                            break 'disasm;
-                        }
-                        capstone::InsnGroupType::CS_GRP_INVALID
-                        | capstone::InsnGroupType::CS_GRP_JUMP
-                        | capstone::InsnGroupType::CS_GRP_IRET
-                        | capstone::InsnGroupType::CS_GRP_PRIVILEGE => {
-                            break 'disasm;
+                // Capstone does not put all the calls and returns of these architectures in their groups.
+                // Their calls may return after a delay slot.
+                #[cfg(any(
+                    cpu_target = "mips",
+                    cpu_target = "mips64",
+                    cpu_target = "ppc",
+                    cpu_target = "ppc64"
+                ))]
+                match crate::branch_kind(
+                    insn.mnemonic().unwrap_or_default(),
+                    insn.op_str().unwrap_or_default(),
+                ) {
+                    Some(crate::BranchKind::Call { return_offset }) => {
+                        call_addrs.push((insn.address() as GuestAddr, return_offset));
+                    }
+                    Some(crate::BranchKind::Ret) => {
+                        ret_addrs.push(insn.address() as GuestAddr);
+                        break 'disasm;
+                    }
+                    Some(crate::BranchKind::Jump) => {
+                        break 'disasm;
+                    }
+                    None => {}
+                }
+                #[cfg(not(any(
+                    cpu_target = "mips",
+                    cpu_target = "mips64",
+                    cpu_target = "ppc",
+                    cpu_target = "ppc64"
+                )))]
+                {
+                    let insn_detail: InsnDetail = h.cs.insn_detail(insn).unwrap();
+                    for detail in insn_detail.groups() {
+                        match u32::from(detail.0) {
+                            capstone::InsnGroupType::CS_GRP_CALL => {
+                                let call_len = insn.bytes().len();
+                                call_addrs.push((insn.address() as GuestAddr, call_len));
+                            }
+                            capstone::InsnGroupType::CS_GRP_RET => {
+                                ret_addrs.push(insn.address() as GuestAddr);
+                                break 'disasm;
+                            }
+                            capstone::InsnGroupType::CS_GRP_INVALID
+                            | capstone::InsnGroupType::CS_GRP_JUMP
+                            | capstone::InsnGroupType::CS_GRP_IRET
+                            | capstone::InsnGroupType::CS_GRP_PRIVILEGE => {
+                                break 'disasm;
+                            }
+                            _ => {}
                         }
-                        _ => {}
                     }
                 }
 
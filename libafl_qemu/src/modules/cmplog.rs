@@ -1,5 +1,5 @@
 #[cfg(feature = "usermode")]
-use capstone::{arch::BuildsCapstone, Capstone, InsnDetail};
+use capstone::{arch::BuildsCapstone, Capstone};
 use hashbrown::HashMap;
 use libafl::{inputs::UsesInput, HasMetadata};
 use libafl_qemu_sys::GuestAddr;
@@ -342,26 +342,49 @@ impl CmpLogRoutinesModule {
                     break;
                 }
                 let insn = insns.first().unwrap();
-                let insn_detail: InsnDetail = h.cs.insn_detail(insn).unwrap();
-                for detail in insn_detail.groups() {
-                    match u32::from(detail.0) {
-                        capstone::InsnGroupType::CS_GRP_CALL => {
-                            let k = (hash_me(pc.into())) & (CMPLOG_MAP_W as u64 - 1);
-                            qemu.hooks().add_instruction_hooks(
-                                k,
-                                insn.address() as GuestAddr,
-                                Self::on_call,
-                                false,
-                            );
-                        }
-                        capstone::InsnGroupType::CS_GRP_RET
-                        | capstone::InsnGroupType::CS_GRP_INVALID
-                        | capstone::InsnGroupType::CS_GRP_JUMP
-                        | capstone::InsnGroupType::CS_GRP_IRET
-                        | capstone::InsnGroupType::CS_GRP_PRIVILEGE => {
-                            break 'disasm;
+                // Capstone does not put all the calls of PowerPC in the call group
+                #[cfg(any(cpu_target = "ppc", cpu_target = "ppc64"))]
+                match crate::branch_kind(
+                    insn.mnemonic().unwrap_or_default(),
+                    insn.op_str().unwrap_or_default(),
+                ) {
+                    Some(crate::BranchKind::Call { .. }) => {
+                        let k = (hash_me(pc.into())) & (CMPLOG_MAP_W as u64 - 1);
+                        qemu.hooks().add_instruction_hooks(
+                            k,
+                            insn.address() as GuestAddr,
+                            Self::on_call,
+                            false,
+                        );
+                    }
+                    Some(crate::BranchKind::Ret | crate::BranchKind::Jump) => {
+                        break 'disasm;
+                    }
+                    None => {}
+                }
+                #[cfg(not(any(cpu_target = "ppc", cpu_target = "ppc64")))]
+                {
+                    let insn_detail: capstone::InsnDetail = h.cs.insn_detail(insn).unwrap();
+                    for detail in insn_detail.groups() {
+                        match u32::from(detail.0) {
+                            capstone::InsnGroupType::CS_GRP_CALL => {
+                                let k = (hash_me(pc.into())) & (CMPLOG_MAP_W as u64 - 1);
+                                qemu.hooks().add_instruction_hooks(
+                                    k,
+                                    insn.address() as GuestAddr,
+                                    Self::on_call,
+                                    false,
+                                );
+                            }
+                            capstone::InsnGroupType::CS_GRP_RET
+                            | capstone::InsnGroupType::CS_GRP_INVALID
+                            | capstone::InsnGroupType::CS_GRP_JUMP
+                            | capstone::InsnGroupType::CS_GRP_IRET
+                            | capstone::InsnGroupType::CS_GRP_PRIVILEGE => {
+                                break 'disasm;
+                            }
+                            _ => {}
                         }
-                        _ => {}
                     }
                 }
 
@@ -0,0 +1,608 @@
+//! `CmpLog` for ARM, Thumb and `AArch64` targets, e.g. firmware, without TCG compare helpers.
+//!
+//! The [`ArmCmpLogModule`] disassembles each translated block and hooks its compare instructions
+//! (`cmp`, `cmn`, `ccmp`, `ccmn` and `subs`), logging their operands in the `CmpLog` map.
+//! Compares in Thumb `IT` blocks and conditional compares are logged whether or not their condition holds.
+//!
+//! It also hooks the entry of `memcmp`-like routines, logging the first bytes of both buffers.
+//! In usermode, the usual libc routines are found by symbol with [`ArmCmpLogModule::with_routines`].
+
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+
+use capstone::{arch::BuildsCapstone, Capstone, InsnDetail};
+use hashbrown::HashSet;
+use libafl::inputs::UsesInput;
+use libafl_qemu_sys::GuestAddr;
+use libafl_targets::cmps::{
+    __libafl_targets_cmplog_instructions, __libafl_targets_cmplog_routines, CMPLOG_ENABLED,
+    CMPLOG_MAP_W, CMPLOG_RTN_LEN,
+};
+
+#[cfg(feature = "systemmode")]
+use crate::modules::{NopPageFilter, NOP_PAGE_FILTER};
+use crate::{
+    capstone,
+    emu::EmulatorModules,
+    modules::{hash_me, AddressFilter, EmulatorModule, EmulatorModuleTuple, StdAddressFilter},
+    qemu::{ArchExtras, Hook},
+    CallingConvention, Qemu,
+};
+
+/// The `memcmp`-like routines of the C library
+pub const LIBC_CMP_ROUTINES: [&str; 6] = [
+    "memcmp",
+    "bcmp",
+    "strcmp",
+    "strncmp",
+    "strcasecmp",
+    "strncasecmp",
+];
+
+/// The bytes disassembled at most per block
+const MAX_BLOCK_SIZE: usize = 512;
+
+/// The Thumb bit of the CPSR
+#[cfg(cpu_target = "arm")]
+const CPSR_THUMB: u32 = 1 << 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shift {
+    Lsl(u32),
+    Lsr(u32),
+    Asr(u32),
+    Ror(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOperand {
+    /// A register, by its QEMU index
+    Reg(i32, Option<Shift>),
+    Imm(u64),
+}
+
+/// The operands of a hooked compare instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ArmCmp {
+    size: usize,
+    op0: CmpOperand,
+    op1: CmpOperand,
+    /// `cmn` compares with the negation of the second operand
+    negate: bool,
+}
+
+impl ArmCmp {
+    fn mask(&self) -> u64 {
+        if self.size == 8 {
+            u64::MAX
+        } else {
+            u64::from(u32::MAX)
+        }
+    }
+
+    fn shift(&self, value: u64, shift: Shift) -> u64 {
+        let bits = self.size as u32 * 8;
+        let value = value & self.mask();
+        let shifted = match shift {
+            Shift::Lsl(n) => value.checked_shl(n).unwrap_or(0),
+            Shift::Lsr(n) => value.checked_shr(n).unwrap_or(0),
+            Shift::Asr(n) => {
+                let signed = if bits == 32 {
+                    i64::from(value as u32 as i32)
+                } else {
+                    value as i64
+                };
+                (signed >> n.min(63)) as u64
+            }
+            Shift::Ror(n) if bits == 32 => u64::from((value as u32).rotate_right(n)),
+            Shift::Ror(n) => value.rotate_right(n),
+        };
+        shifted & self.mask()
+    }
+
+    fn value(&self, qemu: Qemu, op: CmpOperand) -> Option<u64> {
+        match op {
+            CmpOperand::Imm(imm) => Some(imm & self.mask()),
+            CmpOperand::Reg(reg, shift) => {
+                #[allow(clippy::useless_conversion)]
+                let value = u64::from(qemu.read_reg(reg).ok()?);
+                Some(match shift {
+                    Some(shift) => self.shift(value, shift),
+                    None => value & self.mask(),
+                })
+            }
+        }
+    }
+
+    fn log(&self, qemu: Qemu, id: u64) {
+        if unsafe { CMPLOG_ENABLED } == 0 {
+            return;
+        }
+        let (Some(v0), Some(mut v1)) = (self.value(qemu, self.op0), self.value(qemu, self.op1))
+        else {
+            return;
+        };
+        if self.negate {
+            v1 = v1.wrapping_neg() & self.mask();
+        }
+        unsafe {
+            __libafl_targets_cmplog_instructions(id as usize, self.size as u8, v0, v1);
+        }
+    }
+}
+
+/// The QEMU index of a register, by its capstone name
+#[cfg(cpu_target = "aarch64")]
+fn parse_reg(name: &str) -> Option<CmpOperand> {
+    let reg = match name {
+        "xzr" | "wzr" => return Some(CmpOperand::Imm(0)),
+        "sp" | "wsp" => 31,
+        "fp" => 29,
+        "lr" => 30,
+        _ => name
+            .strip_prefix(['x', 'w'])?
+            .parse::<i32>()
+            .ok()
+            .filter(|reg| *reg <= 30)?,
+    };
+    Some(CmpOperand::Reg(reg, None))
+}
+
+/// The QEMU index of a register, by its capstone name
+#[cfg(cpu_target = "arm")]
+fn parse_reg(name: &str) -> Option<CmpOperand> {
+    let reg = match name {
+        "sb" => 9,
+        "sl" => 10,
+        "fp" => 11,
+        "ip" => 12,
+        "sp" => 13,
+        "lr" => 14,
+        "pc" => 15,
+        _ => name
+            .strip_prefix('r')?
+            .parse::<i32>()
+            .ok()
+            .filter(|reg| *reg <= 15)?,
+    };
+    Some(CmpOperand::Reg(reg, None))
+}
+
+/// The size of the compare, from the name of its first register
+fn operand_size(name: &str) -> usize {
+    if cfg!(cpu_target = "aarch64") && !name.starts_with('w') {
+        8
+    } else {
+        4
+    }
+}
+
+fn parse_imm(imm: &str) -> Option<u64> {
+    let imm = imm.strip_prefix('#')?;
+    let (negative, imm) = match imm.strip_prefix('-') {
+        Some(imm) => (true, imm),
+        None => (false, imm),
+    };
+    let value = match imm.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => imm.parse::<u64>().ok()?,
+    };
+    Some(if negative {
+        value.wrapping_neg()
+    } else {
+        value
+    })
+}
+
+fn parse_shift(shift: &str) -> Option<Shift> {
+    let (kind, amount) = shift.split_once(' ')?;
+    let amount = u32::try_from(parse_imm(amount)?).ok()?;
+    match kind {
+        "lsl" => Some(Shift::Lsl(amount)),
+        "lsr" => Some(Shift::Lsr(amount)),
+        "asr" => Some(Shift::Asr(amount)),
+        "ror" => Some(Shift::Ror(amount)),
+        _ => None,
+    }
+}
+
+fn parse_operand(op: &str, shift: Option<&str>) -> Option<CmpOperand> {
+    let shift = match shift {
+        Some(shift) => Some(parse_shift(shift)?),
+        None => None,
+    };
+    if op.starts_with('#') {
+        let imm = parse_imm(op)?;
+        return Some(CmpOperand::Imm(match shift {
+            Some(Shift::Lsl(n)) => imm.checked_shl(n)?,
+            Some(_) => return None,
+            None => imm,
+        }));
+    }
+    match parse_reg(op)? {
+        CmpOperand::Reg(reg, _) => Some(CmpOperand::Reg(reg, shift)),
+        // The zero register
+        CmpOperand::Imm(imm) => Some(CmpOperand::Imm(imm)),
+    }
+}
+
+/// Strip the width qualifier and the condition code of an ARM mnemonic, e.g. `cmpeq.w`
+fn base_mnemonic(mnemonic: &str) -> &str {
+    const CONDS: [&str; 17] = [
+        "eq", "ne", "hs", "cs", "lo", "cc", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt",
+        "le", "al",
+    ];
+
+    let mnemonic = mnemonic
+        .strip_suffix(".w")
+        .or_else(|| mnemonic.strip_suffix(".n"))
+        .unwrap_or(mnemonic);
+    for base in ["cmp", "cmn", "subs"] {
+        if let Some(cond) = mnemonic.strip_prefix(base) {
+            if CONDS.contains(&cond) {
+                return base;
+            }
+        }
+    }
+    mnemonic
+}
+
+/// Decode a compare instruction from its capstone text
+fn parse_cmp(mnemonic: &str, op_str: &str) -> Option<ArmCmp> {
+    let ops: Vec<&str> = op_str.split(',').map(str::trim).collect();
+    let (negate, first, conditional) = match base_mnemonic(mnemonic) {
+        "cmp" => (false, 0, false),
+        "cmn" => (true, 0, false),
+        "ccmp" => (false, 0, true),
+        "ccmn" => (true, 0, true),
+        // `subs rd, rn, op` compares `rn` with `op`, `subs rdn, op` compares `rdn` with `op`
+        "subs" if ops.len() >= 3 && !ops[2].contains(' ') => (false, 1, false),
+        "subs" => (false, 0, false),
+        _ => return None,
+    };
+
+    let op0 = *ops.get(first)?;
+    let op1 = *ops.get(first + 1)?;
+    // `ccmp x0, #imm, #nzcv, cond` has no shift, any other extra operand is one
+    let shift = if conditional {
+        None
+    } else {
+        ops.get(first + 2).copied()
+    };
+    if !conditional && ops.len() > first + 3 {
+        return None;
+    }
+
+    Some(ArmCmp {
+        size: operand_size(op0),
+        op0: parse_operand(op0, None)?,
+        op1: parse_operand(op1, shift)?,
+        negate,
+    })
+}
+
+/// Hooks the compare instructions and the compare routines of ARM, Thumb and `AArch64` targets.
+/// See the [module-level documentation](self).
+#[derive(Debug)]
+pub struct ArmCmpLogModule {
+    address_filter: StdAddressFilter,
+    cs: Capstone,
+    #[cfg(cpu_target = "arm")]
+    cs_thumb: Capstone,
+    /// The instructions hooked so far, kept across retranslations
+    hooked: HashSet<GuestAddr>,
+    routines: Vec<GuestAddr>,
+    #[cfg(feature = "usermode")]
+    routine_symbols: Vec<String>,
+}
+
+impl ArmCmpLogModule {
+    #[must_use]
+    pub fn new(address_filter: StdAddressFilter) -> Self {
+        Self {
+            address_filter,
+            cs: capstone().detail(true).build().unwrap(),
+            #[cfg(cpu_target = "arm")]
+            cs_thumb: crate::capstone_thumb().detail(true).build().unwrap(),
+            hooked: HashSet::new(),
+            routines: Vec::new(),
+            #[cfg(feature = "usermode")]
+            routine_symbols: Vec::new(),
+        }
+    }
+
+    /// Also hook the compare routines of the C library, see [`LIBC_CMP_ROUTINES`]
+    #[cfg(feature = "usermode")]
+    #[must_use]
+    pub fn with_routines(address_filter: StdAddressFilter) -> Self {
+        let mut module = Self::new(address_filter);
+        module.routine_symbols = LIBC_CMP_ROUTINES.iter().map(ToString::to_string).collect();
+        module
+    }
+
+    /// Hook the routine comparing the buffers of its first two arguments at `addr`.
+    /// For Thumb routines, set the lowest bit of `addr` as in a function pointer.
+    #[must_use]
+    pub fn routine(mut self, addr: GuestAddr) -> Self {
+        self.routines.push(addr);
+        self
+    }
+
+    /// Hook the routine `name`, found by symbol when the fuzzing starts
+    #[cfg(feature = "usermode")]
+    #[must_use]
+    pub fn routine_symbol(mut self, name: &str) -> Self {
+        self.routine_symbols.push(name.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn must_instrument(&self, addr: GuestAddr) -> bool {
+        self.address_filter.allowed(&addr)
+    }
+
+    #[cfg(cpu_target = "arm")]
+    fn capstone(&self, qemu: Qemu) -> &Capstone {
+        let cpsr = qemu.read_reg(crate::Regs::Cpsr).unwrap_or(0);
+        if cpsr & CPSR_THUMB == 0 {
+            &self.cs
+        } else {
+            &self.cs_thumb
+        }
+    }
+
+    #[cfg(cpu_target = "aarch64")]
+    fn capstone(&self, _qemu: Qemu) -> &Capstone {
+        &self.cs
+    }
+
+    /// The compare instructions of the block at `pc`
+    fn find_cmps(&self, qemu: Qemu, pc: GuestAddr) -> Vec<(GuestAddr, ArmCmp)> {
+        // The block may end before a page the guest cannot read
+        let mut code = vec![0; MAX_BLOCK_SIZE];
+        let mut len = MAX_BLOCK_SIZE;
+        while qemu.read_mem(pc, &mut code[..len]).is_err() {
+            len /= 2;
+            if len < 4 {
+                return Vec::new();
+            }
+        }
+
+        let cs = self.capstone(qemu);
+        // Disassembled at once, so that capstone tracks the Thumb IT blocks
+        let Ok(insns) = cs.disasm_all(&code[..len], pc.into()) else {
+            return Vec::new();
+        };
+
+        let mut cmps = Vec::new();
+        for insn in insns.iter() {
+            if let (Some(mnemonic), Some(op_str)) = (insn.mnemonic(), insn.op_str()) {
+                if let Some(cmp) = parse_cmp(mnemonic, op_str) {
+                    cmps.push((insn.address() as GuestAddr, cmp));
+                }
+            }
+
+            let Ok(detail): Result<InsnDetail, _> = cs.insn_detail(insn) else {
+                break;
+            };
+            let ends_block = detail.groups().any(|group| {
+                matches!(
+                    u32::from(group.0),
+                    capstone::InsnGroupType::CS_GRP_JUMP
+                        | capstone::InsnGroupType::CS_GRP_CALL
+                        | capstone::InsnGroupType::CS_GRP_RET
+                        | capstone::InsnGroupType::CS_GRP_INVALID
+                        | capstone::InsnGroupType::CS_GRP_IRET
+                        | capstone::InsnGroupType::CS_GRP_PRIVILEGE
+                )
+            });
+            if ends_block {
+                break;
+            }
+        }
+        cmps
+    }
+
+    fn gen_blocks_cmps<ET, S>(
+        emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: Option<&mut S>,
+        pc: GuestAddr,
+    ) -> Option<u64>
+    where
+        S: Unpin + UsesInput,
+        ET: EmulatorModuleTuple<S>,
+    {
+        let qemu = emulator_modules.qemu();
+        let h = emulator_modules.get_mut::<Self>()?;
+        if !h.must_instrument(pc) {
+            return None;
+        }
+
+        let cmps: Vec<_> = h
+            .find_cmps(qemu, pc)
+            .into_iter()
+            .filter(|(addr, _)| h.hooked.insert(*addr))
+            .collect();
+
+        for (addr, cmp) in cmps {
+            let id = hash_me(addr.into()) & (CMPLOG_MAP_W as u64 - 1);
+            // The block is being translated, no need to invalidate it
+            emulator_modules.instructions(
+                addr,
+                Hook::Closure(Box::new(move |modules, _state, _pc| {
+                    cmp.log(modules.qemu(), id);
+                })),
+                false,
+            );
+        }
+
+        None
+    }
+}
+
+impl Default for ArmCmpLogModule {
+    fn default() -> Self {
+        Self::new(StdAddressFilter::default())
+    }
+}
+
+/// Log the buffers of the first two arguments of a compare routine
+fn on_routine(qemu: Qemu) {
+    if unsafe { CMPLOG_ENABLED } == 0 {
+        return;
+    }
+
+    let a0: GuestAddr = qemu
+        .read_function_argument(CallingConvention::Cdecl, 0)
+        .unwrap_or(0);
+    let a1: GuestAddr = qemu
+        .read_function_argument(CallingConvention::Cdecl, 1)
+        .unwrap_or(0);
+    if a0 == 0 || a1 == 0 {
+        return;
+    }
+
+    let mut buf0 = [0u8; CMPLOG_RTN_LEN];
+    let mut buf1 = [0u8; CMPLOG_RTN_LEN];
+    if qemu.read_mem(a0, &mut buf0).is_err() || qemu.read_mem(a1, &mut buf1).is_err() {
+        return;
+    }
+
+    // One entry per call site
+    let ret_addr: GuestAddr = qemu.read_return_address().unwrap_or(0);
+    let k = hash_me(ret_addr.into()) & (CMPLOG_MAP_W as u64 - 1);
+    unsafe {
+        __libafl_targets_cmplog_routines(k as usize, buf0.as_ptr(), buf1.as_ptr());
+    }
+}
+
+impl<S> EmulatorModule<S> for ArmCmpLogModule
+where
+    S: Unpin + UsesInput,
+{
+    type ModuleAddressFilter = StdAddressFilter;
+    #[cfg(feature = "systemmode")]
+    type ModulePageFilter = NopPageFilter;
+
+    fn first_exec<ET>(&mut self, emulator_modules: &mut EmulatorModules<ET, S>, _state: &mut S)
+    where
+        ET: EmulatorModuleTuple<S>,
+    {
+        emulator_modules.blocks(
+            Hook::Function(Self::gen_blocks_cmps::<ET, S>),
+            Hook::Empty,
+            Hook::Empty,
+        );
+
+        #[allow(unused_mut)]
+        let mut routines = self.routines.clone();
+        #[cfg(feature = "usermode")]
+        for name in &self.routine_symbols {
+            let addrs = emulator_modules.qemu().find_symbol(name);
+            if addrs.is_empty() {
+                log::debug!("ArmCmpLog: routine {name} not found");
+            }
+            routines.extend(addrs);
+        }
+
+        for addr in routines {
+            // The hooks are on the instruction, without the Thumb bit of the function pointer
+            #[cfg(cpu_target = "arm")]
+            let addr = addr & !1;
+            emulator_modules.instructions(
+                addr,
+                Hook::Closure(Box::new(move |modules, _state, _pc| {
+                    on_routine(modules.qemu());
+                })),
+                true,
+            );
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &self.address_filter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        &mut self.address_filter
+    }
+
+    #[cfg(feature = "systemmode")]
+    fn page_filter(&self) -> &Self::ModulePageFilter {
+        &NopPageFilter
+    }
+
+    #[cfg(feature = "systemmode")]
+    fn page_filter_mut(&mut self) -> &mut Self::ModulePageFilter {
+        unsafe { (&raw mut NOP_PAGE_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_cmp, parse_imm, ArmCmp, CmpOperand};
+
+    #[test]
+    fn test_parse_imm() {
+        assert_eq!(parse_imm("#0x10"), Some(0x10));
+        assert_eq!(parse_imm("#42"), Some(42));
+        assert_eq!(parse_imm("#-1"), Some(u64::MAX));
+        assert_eq!(parse_imm("r0"), None);
+    }
+
+    #[cfg(cpu_target = "arm")]
+    #[test]
+    fn test_parse_cmp_arm() {
+        assert_eq!(
+            parse_cmp("cmp", "r0, #0x41"),
+            Some(ArmCmp {
+                size: 4,
+                op0: CmpOperand::Reg(0, None),
+                op1: CmpOperand::Imm(0x41),
+                negate: false,
+            })
+        );
+        // In an IT block
+        assert_eq!(
+            parse_cmp("cmpeq.w", "r1, r2, lsl #2").map(|cmp| cmp.op1),
+            Some(CmpOperand::Reg(2, Some(super::Shift::Lsl(2))))
+        );
+        assert_eq!(
+            parse_cmp("subs", "r0, r3, ip").map(|cmp| (cmp.op0, cmp.op1)),
+            Some((CmpOperand::Reg(3, None), CmpOperand::Reg(12, None)))
+        );
+        assert!(parse_cmp("cmn", "r0, #1").unwrap().negate);
+        assert_eq!(parse_cmp("add", "r0, r1, r2"), None);
+    }
+
+    #[cfg(cpu_target = "aarch64")]
+    #[test]
+    fn test_parse_cmp_aarch64() {
+        assert_eq!(
+            parse_cmp("cmp", "w8, #0x41"),
+            Some(ArmCmp {
+                size: 4,
+                op0: CmpOperand::Reg(8, None),
+                op1: CmpOperand::Imm(0x41),
+                negate: false,
+            })
+        );
+        assert_eq!(
+            parse_cmp("ccmp", "x0, x1, #0, ne").map(|cmp| (cmp.size, cmp.op1)),
+            Some((8, CmpOperand::Reg(1, None)))
+        );
+        assert_eq!(
+            parse_cmp("subs", "x0, x1, xzr").map(|cmp| cmp.op1),
+            Some(CmpOperand::Imm(0))
+        );
+        assert_eq!(
+            parse_cmp("cmp", "x0, #1, lsl #12").map(|cmp| cmp.op1),
+            Some(CmpOperand::Imm(0x1000))
+        );
+        // Extended registers are not supported
+        assert_eq!(parse_cmp("cmp", "x0, w1, uxtw"), None);
+    }
+}
@@ -1,9 +1,9 @@
 use std::{path::PathBuf, sync::Mutex};
 
-use hashbrown::{hash_map::Entry, HashMap};
+use hashbrown::{hash_map::Entry, HashMap, HashSet};
 use libafl::{executors::ExitKind, inputs::UsesInput, observers::ObserversTuple, HasMetadata};
 use libafl_qemu_sys::{GuestAddr, GuestUsize};
-use libafl_targets::drcov::{DrCovBasicBlock, DrCovWriter};
+use libafl_targets::drcov::{publish_drcov_run, DrCovBasicBlock, DrCovWriter};
 use rangemap::RangeMap;
 use serde::{Deserialize, Serialize};
 
@@ -18,6 +18,8 @@ use crate::{
 static DRCOV_IDS: Mutex<Option<Vec<u64>>> = Mutex::new(None);
 static DRCOV_MAP: Mutex<Option<HashMap<GuestAddr, u64>>> = Mutex::new(None);
 static DRCOV_LENGTHS: Mutex<Option<HashMap<GuestAddr, GuestUsize>>> = Mutex::new(None);
+/// The block ids of the current run, for the `DrCovObserver`
+static DRCOV_RUN_IDS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
 
 #[cfg_attr(
     any(not(feature = "serdeany_autoreg"), miri),
@@ -43,6 +45,7 @@ pub struct DrCovModuleBuilder<F> {
     module_mapping: Option<RangeMap<u64, (u16, String)>>,
     filename: Option<PathBuf>,
     full_trace: Option<bool>,
+    publish_runs: bool,
 }
 
 impl<F> DrCovModuleBuilder<F>
@@ -50,12 +53,14 @@ where
     F: AddressFilter,
 {
     pub fn build(self) -> DrCovModule<F> {
-        DrCovModule::new(
+        let mut module = DrCovModule::new(
             self.filter.unwrap(),
-            self.filename.unwrap(),
+            self.filename.unwrap_or_default(),
             self.module_mapping,
             self.full_trace.unwrap(),
-        )
+        );
+        module.publish_runs = self.publish_runs;
+        module
     }
 
     pub fn filter<F2>(self, filter: F2) -> DrCovModuleBuilder<F2> {
@@ -64,6 +69,7 @@ where
             module_mapping: self.module_mapping,
             filename: self.filename,
             full_trace: self.full_trace,
+            publish_runs: self.publish_runs,
         }
     }
 
@@ -74,9 +80,12 @@ where
             module_mapping: Some(module_mapping),
             filename: self.filename,
             full_trace: self.full_trace,
+            publish_runs: self.publish_runs,
         }
     }
 
+    /// The file the blocks of all the runs are written to.
+    /// Without one, the blocks are only published to the `DrCovObserver`, see [`Self::publish_runs`].
     #[must_use]
     pub fn filename(self, filename: PathBuf) -> Self {
         Self {
@@ -84,6 +93,7 @@ where
             module_mapping: self.module_mapping,
             filename: Some(filename),
             full_trace: self.full_trace,
+            publish_runs: self.publish_runs,
         }
     }
 
@@ -94,6 +104,16 @@ where
             module_mapping: self.module_mapping,
             filename: self.filename,
             full_trace: Some(full_trace),
+            publish_runs: self.publish_runs,
+        }
+    }
+
+    /// Publish the blocks of each run to the `DrCovObserver` of `libafl_targets`, e.g. for the `DrCovStage`
+    #[must_use]
+    pub fn publish_runs(self, publish_runs: bool) -> Self {
+        Self {
+            publish_runs,
+            ..self
         }
     }
 }
@@ -104,6 +124,7 @@ pub struct DrCovModule<F> {
     module_mapping: Option<RangeMap<u64, (u16, String)>>,
     filename: PathBuf,
     full_trace: bool,
+    publish_runs: bool,
     drcov_len: usize,
 }
 
@@ -115,6 +136,7 @@ impl DrCovModule<NopAddressFilter> {
             module_mapping: None,
             full_trace: None,
             filename: None,
+            publish_runs: false,
         }
     }
 }
@@ -137,11 +159,53 @@ impl<F> DrCovModule<F> {
             module_mapping,
             filename,
             full_trace,
+            publish_runs: false,
             drcov_len: 0,
         }
     }
 
+    /// Publish the blocks of the current run, in the order they were first executed
+    #[allow(clippy::unnecessary_cast)] // for GuestAddr -> u64
+    fn publish_run(&self) {
+        let Some(module_mapping) = self.module_mapping.as_ref() else {
+            return;
+        };
+        let pcs: HashMap<u64, GuestAddr> = DRCOV_MAP
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|(pc, id)| (*id, *pc))
+            .collect();
+        let lengths_opt = DRCOV_LENGTHS.lock().unwrap();
+        let lengths = lengths_opt.as_ref().unwrap();
+
+        let mut seen = HashSet::new();
+        let mut blocks = Vec::new();
+        for id in DRCOV_RUN_IDS.lock().unwrap().drain(..) {
+            if !seen.insert(id) {
+                continue;
+            }
+            let Some(pc) = pcs.get(&id) else {
+                continue;
+            };
+            if let Some(block_length) = lengths.get(pc) {
+                blocks.push(DrCovBasicBlock::new(
+                    *pc as u64,
+                    *pc as u64 + *block_length as u64,
+                ));
+            }
+        }
+        publish_drcov_run(module_mapping, blocks);
+    }
+
     pub fn write(&mut self) {
+        // Only publishing the runs
+        if self.filename.as_os_str().is_empty() {
+            return;
+        }
+
         let lengths_opt = DRCOV_LENGTHS.lock().unwrap();
         let lengths = lengths_opt.as_ref().unwrap();
         if self.full_trace {
@@ -317,6 +381,19 @@ where
         );
     }
 
+    fn pre_exec<ET>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+    ) where
+        ET: EmulatorModuleTuple<S>,
+    {
+        if self.publish_runs {
+            DRCOV_RUN_IDS.lock().unwrap().clear();
+        }
+    }
+
     fn post_exec<OT, ET>(
         &mut self,
         _emulator_modules: &mut EmulatorModules<ET, S>,
@@ -328,6 +405,9 @@ where
         OT: ObserversTuple<S::Input, S>,
         ET: EmulatorModuleTuple<S>,
     {
+        if self.publish_runs {
+            self.publish_run();
+        }
         self.write();
     }
 
@@ -386,7 +466,7 @@ where
     match DRCOV_MAP.lock().unwrap().as_mut().unwrap().entry(pc) {
         Entry::Occupied(e) => {
             let id = *e.get();
-            if drcov_module.full_trace {
+            if drcov_module.full_trace || drcov_module.publish_runs {
                 Some(id)
             } else {
                 None
@@ -396,7 +476,7 @@ where
             let id = meta.current_id;
             e.insert(id);
             meta.current_id = id + 1;
-            if drcov_module.full_trace {
+            if drcov_module.full_trace || drcov_module.publish_runs {
                 // GuestAddress is u32 for 32 bit guests
                 #[allow(clippy::unnecessary_cast)]
                 Some(id as u64)
@@ -438,7 +518,11 @@ pub fn exec_trace_block<ET, F, S>(
     ET: EmulatorModuleTuple<S>,
     S: Unpin + UsesInput + HasMetadata,
 {
-    if emulator_modules.get::<DrCovModule<F>>().unwrap().full_trace {
+    let drcov_module = emulator_modules.get::<DrCovModule<F>>().unwrap();
+    if drcov_module.full_trace {
         DRCOV_IDS.lock().unwrap().as_mut().unwrap().push(id);
     }
+    if drcov_module.publish_runs {
+        DRCOV_RUN_IDS.lock().unwrap().push(id);
+    }
 }
@@ -30,11 +30,16 @@ pub mod calls;
 #[cfg(not(cpu_target = "hexagon"))]
 pub use calls::CallTracerModule;
 
-#[cfg(not(any(cpu_target = "mips", cpu_target = "hexagon")))]
+#[cfg(not(any(cpu_target = "mips", cpu_target = "mips64", cpu_target = "hexagon")))]
 pub mod cmplog;
-#[cfg(not(any(cpu_target = "mips", cpu_target = "hexagon")))]
+#[cfg(not(any(cpu_target = "mips", cpu_target = "mips64", cpu_target = "hexagon")))]
 pub use cmplog::CmpLogModule;
 
+#[cfg(any(cpu_target = "arm", cpu_target = "aarch64"))]
+pub mod cmplog_arm;
+#[cfg(any(cpu_target = "arm", cpu_target = "aarch64"))]
+pub use cmplog_arm::ArmCmpLogModule;
+
 #[cfg(not(cpu_target = "hexagon"))]
 pub mod drcov;
 #[cfg(not(cpu_target = "hexagon"))]
@@ -549,7 +554,6 @@ impl PageFilter for NopPageFilter {
     }
 }
 
-#[cfg(feature = "usermode")]
 static mut NOP_ADDRESS_FILTER: UnsafeCell<NopAddressFilter> = UnsafeCell::new(NopAddressFilter);
 #[cfg(feature = "systemmode")]
 static mut NOP_PAGE_FILTER: UnsafeCell<NopPageFilter> = UnsafeCell::new(NopPageFilter);
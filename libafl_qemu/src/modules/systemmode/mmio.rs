@@ -0,0 +1,380 @@
+//! Fuzz the MMIO interface of emulated devices, e.g. to fuzz firmware and drivers.
+//!
+//! The [`MmioFuzzModule`] overlays the declared [`MmioRegion`]s with I/O regions answering guest
+//! reads with bytes of the input, so that the fuzzed code sees the input as the values of device registers.
+//! Guest writes to the regions are dropped.
+//!
+//! The input is consumed in this order:
+//! 1. each [`DmaBuffer`] is filled with the next `size` bytes before the run;
+//! 2. the rest is a stream, from which each MMIO read takes as many bytes as it reads (little endian).
+//!
+//! If interrupt lines are declared, one more byte is taken from the stream every `irq_interval` reads.
+//! It selects the line to pulse, or none of them.
+//! Reads after the end of the input return `0`.
+//!
+//! By default, the module takes a fast snapshot of the VM on the first run and restores it before every run.
+
+use core::{
+    cell::UnsafeCell,
+    ffi::{c_uint, c_void},
+    fmt::Debug,
+    mem::MaybeUninit,
+};
+use std::ffi::CString;
+
+use libafl::inputs::{HasTargetBytes, UsesInput};
+use libafl_bolts::AsSlice;
+use libafl_qemu_sys::GuestPhysAddr;
+
+use crate::{
+    emu::EmulatorModules,
+    modules::{
+        EmulatorModule, EmulatorModuleTuple, NopAddressFilter, NopPageFilter, NOP_ADDRESS_FILTER,
+        NOP_PAGE_FILTER,
+    },
+    FastSnapshotPtr, IrqLine,
+};
+
+/// The priority of the overlays, above any region of the machine
+const MMIO_OVERLAY_PRIORITY: i32 = 1000;
+
+/// A range of guest physical memory whose reads are fed from the input
+#[derive(Debug, Clone)]
+pub struct MmioRegion {
+    pub name: String,
+    pub base: GuestPhysAddr,
+    pub size: u64,
+}
+
+/// A buffer in guest physical memory, filled from the input before each run
+#[derive(Debug, Clone, Copy)]
+pub struct DmaBuffer {
+    pub addr: GuestPhysAddr,
+    pub size: usize,
+}
+
+/// An interrupt line of a device, pulsed as the input decides.
+/// See [`crate::Qemu::device_irq`] for the meaning of the fields.
+#[derive(Debug, Clone)]
+pub struct MmioIrq {
+    pub device: String,
+    pub name: Option<String>,
+    pub line: i32,
+}
+
+/// The input stream the MMIO reads are served from
+#[derive(Debug, Default)]
+struct MmioStream {
+    data: Vec<u8>,
+    pos: usize,
+    reads: u64,
+    irqs: Vec<IrqLine>,
+    irq_interval: u64,
+}
+
+impl MmioStream {
+    fn reset(&mut self, data: &[u8]) {
+        self.data.clear();
+        self.data.extend_from_slice(data);
+        self.pos = 0;
+        self.reads = 0;
+    }
+
+    fn take(&mut self, len: usize) -> &[u8] {
+        let start = self.pos.min(self.data.len());
+        let end = (start + len).min(self.data.len());
+        self.pos = end;
+        &self.data[start..end]
+    }
+
+    /// Serve a read of `size` bytes, and pulse an interrupt line if it is time to
+    fn read(&mut self, size: usize) -> u64 {
+        self.reads += 1;
+        if self.irq_interval != 0 && self.reads % self.irq_interval == 0 && !self.irqs.is_empty() {
+            let choices = self.irqs.len() + 1;
+            if let Some(&selector) = self.take(1).first() {
+                // The last choice leaves all lines alone
+                if let Some(irq) = self.irqs.get(usize::from(selector) % choices) {
+                    irq.pulse();
+                }
+            }
+        }
+
+        let mut value = [0u8; 8];
+        let bytes = self.take(size.min(8));
+        value[..bytes.len()].copy_from_slice(bytes);
+        u64::from_le_bytes(value)
+    }
+}
+
+unsafe extern "C" fn mmio_read(opaque: *mut c_void, _addr: u64, size: c_uint) -> u64 {
+    let stream = &mut *opaque.cast::<MmioStream>();
+    stream.read(size as usize)
+}
+
+unsafe extern "C" fn mmio_write(_opaque: *mut c_void, _addr: u64, _data: u64, _size: c_uint) {}
+
+#[derive(Debug)]
+pub struct MmioFuzzModuleBuilder {
+    regions: Vec<MmioRegion>,
+    dma_buffers: Vec<DmaBuffer>,
+    irqs: Vec<MmioIrq>,
+    irq_interval: u64,
+    snapshot: bool,
+}
+
+impl MmioFuzzModuleBuilder {
+    #[must_use]
+    pub fn build(self) -> MmioFuzzModule {
+        MmioFuzzModule::new(
+            self.regions,
+            self.dma_buffers,
+            self.irqs,
+            self.irq_interval,
+            self.snapshot,
+        )
+    }
+
+    /// Feed the reads of `size` bytes at `base` from the input
+    #[must_use]
+    pub fn region(mut self, name: &str, base: GuestPhysAddr, size: u64) -> Self {
+        self.regions.push(MmioRegion {
+            name: name.to_string(),
+            base,
+            size,
+        });
+        self
+    }
+
+    /// Fill `size` bytes at `addr` from the input before each run
+    #[must_use]
+    pub fn dma_buffer(mut self, addr: GuestPhysAddr, size: usize) -> Self {
+        self.dma_buffers.push(DmaBuffer { addr, size });
+        self
+    }
+
+    /// Let the input pulse the input GPIO line `line` of the device at `device`
+    #[must_use]
+    pub fn irq(mut self, device: &str, name: Option<&str>, line: i32) -> Self {
+        self.irqs.push(MmioIrq {
+            device: device.to_string(),
+            name: name.map(ToString::to_string),
+            line,
+        });
+        self
+    }
+
+    /// Choose an interrupt every `irq_interval` MMIO reads. `0` disables interrupts.
+    #[must_use]
+    pub fn irq_interval(mut self, irq_interval: u64) -> Self {
+        self.irq_interval = irq_interval;
+        self
+    }
+
+    /// Restore a fast snapshot before each run.
+    /// Disable it if the emulator driver already restores one.
+    #[must_use]
+    pub fn snapshot(mut self, snapshot: bool) -> Self {
+        self.snapshot = snapshot;
+        self
+    }
+}
+
+/// Feeds the MMIO reads and DMA buffers of emulated devices from the input.
+/// See the [module-level documentation](self).
+#[derive(Debug)]
+pub struct MmioFuzzModule {
+    regions: Vec<MmioRegion>,
+    dma_buffers: Vec<DmaBuffer>,
+    irqs: Vec<MmioIrq>,
+    /// Shared with the I/O callbacks of QEMU, which get its address
+    stream: Box<UnsafeCell<MmioStream>>,
+    snapshot: bool,
+    fast_snapshot: Option<FastSnapshotPtr>,
+}
+
+impl MmioFuzzModule {
+    #[must_use]
+    pub fn builder() -> MmioFuzzModuleBuilder {
+        MmioFuzzModuleBuilder {
+            regions: Vec::new(),
+            dma_buffers: Vec::new(),
+            irqs: Vec::new(),
+            irq_interval: 64,
+            snapshot: true,
+        }
+    }
+
+    #[must_use]
+    pub fn new(
+        regions: Vec<MmioRegion>,
+        dma_buffers: Vec<DmaBuffer>,
+        irqs: Vec<MmioIrq>,
+        irq_interval: u64,
+        snapshot: bool,
+    ) -> Self {
+        let stream = MmioStream {
+            irq_interval,
+            ..MmioStream::default()
+        };
+        Self {
+            regions,
+            dma_buffers,
+            irqs,
+            stream: Box::new(UnsafeCell::new(stream)),
+            snapshot,
+            fast_snapshot: None,
+        }
+    }
+
+    #[must_use]
+    pub fn regions(&self) -> &[MmioRegion] {
+        &self.regions
+    }
+
+    #[must_use]
+    pub fn dma_buffers(&self) -> &[DmaBuffer] {
+        &self.dma_buffers
+    }
+
+    /// The number of MMIO reads of the current run
+    #[must_use]
+    pub fn reads(&self) -> u64 {
+        unsafe { (*self.stream.get()).reads }
+    }
+
+    /// Map an I/O region served by the stream over `region`
+    fn overlay(&self, region: &MmioRegion) {
+        // The regions live as long as the VM, like the memory map of the machine
+        let ops = Box::leak(Box::new(libafl_qemu_sys::MemoryRegionOps {
+            read: Some(mmio_read),
+            write: Some(mmio_write),
+            endianness: libafl_qemu_sys::device_endian_DEVICE_LITTLE_ENDIAN,
+            ..Default::default()
+        }));
+        ops.valid.min_access_size = 1;
+        ops.valid.max_access_size = 8;
+        ops.impl_.min_access_size = 1;
+        ops.impl_.max_access_size = 8;
+
+        let mr = Box::leak(Box::new(
+            MaybeUninit::<libafl_qemu_sys::MemoryRegion>::uninit(),
+        ))
+        .as_mut_ptr();
+        let name = CString::new(region.name.as_str()).expect("Invalid MMIO region name");
+
+        unsafe {
+            libafl_qemu_sys::memory_region_init_io(
+                mr,
+                core::ptr::null_mut(),
+                ops,
+                self.stream.get().cast(),
+                name.as_ptr(),
+                region.size,
+            );
+            libafl_qemu_sys::memory_region_add_subregion_overlap(
+                libafl_qemu_sys::get_system_memory(),
+                region.base,
+                mr,
+                MMIO_OVERLAY_PRIORITY,
+            );
+        }
+    }
+}
+
+impl<S> EmulatorModule<S> for MmioFuzzModule
+where
+    S: Unpin + UsesInput,
+    S::Input: HasTargetBytes,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+    type ModulePageFilter = NopPageFilter;
+
+    fn post_qemu_init<ET>(&self, emulator_modules: &mut EmulatorModules<ET, S>)
+    where
+        ET: EmulatorModuleTuple<S>,
+    {
+        let qemu = emulator_modules.qemu();
+
+        for region in &self.regions {
+            self.overlay(region);
+        }
+
+        let irqs = self
+            .irqs
+            .iter()
+            .map(|irq| {
+                qemu.device_irq(&irq.device, irq.name.as_deref(), irq.line)
+                    .unwrap_or_else(|| panic!("No device at {}", irq.device))
+            })
+            .collect();
+        unsafe { (*self.stream.get()).irqs = irqs };
+    }
+
+    fn first_exec<ET>(&mut self, emulator_modules: &mut EmulatorModules<ET, S>, _state: &mut S)
+    where
+        ET: EmulatorModuleTuple<S>,
+    {
+        if self.snapshot {
+            self.fast_snapshot = Some(emulator_modules.qemu().create_fast_snapshot(true));
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        input: &S::Input,
+    ) where
+        ET: EmulatorModuleTuple<S>,
+    {
+        let qemu = emulator_modules.qemu();
+        if let Some(fast_snapshot) = self.fast_snapshot {
+            unsafe { qemu.restore_fast_snapshot(fast_snapshot) };
+        }
+
+        let stream = self.stream.get_mut();
+        stream.reset(input.target_bytes().as_slice());
+        for dma in &self.dma_buffers {
+            let mut buf = stream.take(dma.size).to_vec();
+            buf.resize(dma.size, 0);
+            unsafe { qemu.write_phys_mem(dma.addr, &buf) };
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+
+    fn page_filter(&self) -> &Self::ModulePageFilter {
+        &NopPageFilter
+    }
+
+    fn page_filter_mut(&mut self) -> &mut Self::ModulePageFilter {
+        unsafe { (&raw mut NOP_PAGE_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MmioStream;
+
+    #[test]
+    fn test_mmio_stream() {
+        let mut stream = MmioStream::default();
+        stream.reset(&[0x11, 0x22, 0x33, 0x44, 0x55]);
+        assert_eq!(stream.take(1), &[0x11]);
+        assert_eq!(stream.read(2), 0x3322);
+        assert_eq!(stream.read(4), 0x5544);
+        assert_eq!(stream.read(8), 0);
+        assert_eq!(stream.reads, 3);
+
+        stream.reset(&[0xaa]);
+        assert_eq!(stream.read(1), 0xaa);
+        assert_eq!(stream.reads, 1);
+    }
+}
@@ -1 +1,2 @@
-
+pub mod mmio;
+pub use mmio::{DmaBuffer, MmioFuzzModule, MmioFuzzModuleBuilder, MmioIrq, MmioRegion};
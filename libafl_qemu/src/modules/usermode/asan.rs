@@ -3,13 +3,20 @@
 use std::{borrow::Cow, env, fs, path::PathBuf, sync::Mutex};
 
 use hashbrown::{HashMap, HashSet};
-use libafl::{executors::ExitKind, inputs::UsesInput, observers::ObserversTuple};
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{Observer, ObserverWithHashField, ObserversTuple},
+    Error,
+};
+use libafl_bolts::{hash_std, Named};
 use libc::{
     c_void, MAP_ANON, MAP_FAILED, MAP_FIXED, MAP_NORESERVE, MAP_PRIVATE, PROT_READ, PROT_WRITE,
 };
 use meminterval::{Interval, IntervalTree};
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     modules::{
@@ -145,6 +152,138 @@ impl AllocTreeItem {
         self.free_backtrace = backtrace;
         self.allocated = false;
     }
+
+    #[must_use]
+    pub fn allocated(&self) -> bool {
+        self.allocated
+    }
+
+    #[must_use]
+    pub fn backtrace(&self) -> &[GuestAddr] {
+        &self.backtrace
+    }
+
+    #[must_use]
+    pub fn free_backtrace(&self) -> &[GuestAddr] {
+        &self.free_backtrace
+    }
+}
+
+/// The reports of the current run, filled by [`AsanModule::with_report_observer`]
+pub static ASAN_REPORTS: Mutex<Vec<AsanReport>> = Mutex::new(Vec::new());
+
+/// A structured `ASan` report, to triage and dedup the errors found in the guest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AsanReport {
+    /// The kind of error: `read`, `write`, `bad-free`, `leak` or `signal`
+    pub kind: String,
+    /// The error, as printed by the `ASan` report
+    pub description: String,
+    pub pc: GuestAddr,
+    /// The faulting address, if any
+    pub addr: Option<GuestAddr>,
+    /// The backtrace of the error, innermost frame last
+    pub backtrace: Vec<GuestAddr>,
+    /// The backtrace of the allocation of the chunk the error is about, if any
+    pub alloc_backtrace: Vec<GuestAddr>,
+    /// The backtrace of the deallocation of the chunk the error is about, if it was freed
+    pub free_backtrace: Vec<GuestAddr>,
+}
+
+impl AsanReport {
+    /// Build the report of `err`, collecting the backtraces from [`FullBacktraceCollector`] and `rt`
+    #[must_use]
+    pub fn new(rt: &AsanGiovese, pc: GuestAddr, err: &AsanError) -> Self {
+        let (kind, addr) = match err {
+            AsanError::Read(addr, _) => ("read", Some(*addr)),
+            AsanError::Write(addr, _) => ("write", Some(*addr)),
+            AsanError::BadFree(addr, _) => ("bad-free", Some(*addr)),
+            AsanError::MemLeak(chunk) => ("leak", Some(chunk.start)),
+            AsanError::Signal(_) => ("signal", None),
+        };
+        let backtrace = FullBacktraceCollector::backtrace()
+            .map(|r| {
+                let mut v = r.to_vec();
+                v.push(pc);
+                v
+            })
+            .unwrap_or(vec![pc]);
+        let (alloc_backtrace, free_backtrace) = addr
+            .and_then(|addr| rt.alloc_get_clone(addr))
+            .map(|(_, item)| (item.backtrace, item.free_backtrace))
+            .unwrap_or_default();
+        Self {
+            kind: kind.to_string(),
+            description: err.to_string(),
+            pc,
+            addr,
+            backtrace,
+            alloc_backtrace,
+            free_backtrace,
+        }
+    }
+
+    /// The hash of the kind and the backtrace of the error, independent of the faulting address
+    #[must_use]
+    #[allow(clippy::unnecessary_cast)]
+    pub fn hash(&self) -> u64 {
+        let mut bytes = self.kind.as_bytes().to_vec();
+        for frame in &self.backtrace {
+            bytes.extend_from_slice(&(*frame as u64).to_le_bytes());
+        }
+        hash_std(&bytes)
+    }
+}
+
+/// Observes the [`AsanReport`]s of a run of an [`AsanModule`] built with [`AsanModule::with_report_observer`].
+///
+/// Its hash is the one of the first report, to dedup the objectives with a `NewHashFeedback`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AsanReportObserver {
+    reports: Vec<AsanReport>,
+}
+
+impl AsanReportObserver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The reports of the last run
+    #[must_use]
+    pub fn reports(&self) -> &[AsanReport] {
+        &self.reports
+    }
+}
+
+impl<I, S> Observer<I, S> for AsanReportObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.reports.clear();
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &I,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.reports.clone_from(&ASAN_REPORTS.lock().unwrap());
+        Ok(())
+    }
+}
+
+impl ObserverWithHashField for AsanReportObserver {
+    fn hash(&self) -> Option<u64> {
+        self.reports.first().map(AsanReport::hash)
+    }
+}
+
+impl Named for AsanReportObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("AsanReports");
+        &NAME
+    }
 }
 use std::pin::Pin;
 
@@ -818,6 +957,25 @@ impl AsanModule {
         )
     }
 
+    /// Report the errors to an [`AsanReportObserver`] instead of aborting.
+    /// A run with errors ends as a crash, but the guest keeps running after each error until then.
+    #[must_use]
+    pub fn with_report_observer(
+        rt: Pin<Box<AsanGiovese>>,
+        filter: StdAddressFilter,
+        options: &QemuAsanOptions,
+    ) -> Self {
+        Self::with_error_callback(
+            rt,
+            filter,
+            Box::new(|rt, _qemu, pc, err| {
+                let report = AsanReport::new(rt, pc, &err);
+                ASAN_REPORTS.lock().unwrap().push(report);
+            }),
+            options,
+        )
+    }
+
     #[must_use]
     pub fn must_instrument(&self, addr: GuestAddr) -> bool {
         self.filter.allowed(&addr)
@@ -917,6 +1075,16 @@ impl AsanModule {
         AsanGiovese::unpoison(qemu, addr, size);
     }
 
+    /// The chunk `addr` is in, with its allocation state
+    #[must_use]
+    pub fn chunk(&self, addr: GuestAddr) -> Option<(Interval<GuestAddr>, AllocTreeItem)> {
+        self.rt.alloc_get_clone(addr)
+    }
+
+    pub fn report(&mut self, qemu: Qemu, pc: GuestAddr, error: AsanError) {
+        self.rt.report_or_crash(qemu, pc, error);
+    }
+
     pub fn reset(&mut self, qemu: Qemu) -> AsanRollback {
         self.rt.rollback(qemu, self.detect_leaks)
     }
@@ -987,6 +1155,7 @@ where
             self.rt.snapshot(emulator_modules.qemu());
             self.empty = false;
         }
+        ASAN_REPORTS.lock().unwrap().clear();
     }
 
     fn post_exec<OT, ET>(
@@ -1000,7 +1169,9 @@ where
         OT: ObserversTuple<S::Input, S>,
         ET: EmulatorModuleTuple<S>,
     {
-        if self.reset(emulator_modules.qemu()) == AsanRollback::HasLeaks {
+        if self.reset(emulator_modules.qemu()) == AsanRollback::HasLeaks
+            || !ASAN_REPORTS.lock().unwrap().is_empty()
+        {
             *exit_kind = ExitKind::Crash;
         }
     }
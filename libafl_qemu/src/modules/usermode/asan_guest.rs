@@ -126,6 +126,8 @@ pub struct AsanGuestModule<F> {
 #[cfg(any(
     cpu_target = "aarch64",
     cpu_target = "x86_64",
+    cpu_target = "mips64",
+    cpu_target = "ppc64",
     cpu_target = "riscv64",
     feature = "clippy"
 ))]
@@ -0,0 +1,379 @@
+//! Intercept the allocator of the guest for the [`AsanModule`].
+//!
+//! `libqasan` can only replace the allocator of dynamically linked targets, with `LD_PRELOAD`.
+//! The [`AsanHeapModule`] instead hooks the allocator entry points of the guest itself,
+//! resolved by symbol, by byte pattern, or given by address, on any architecture.
+//! It tracks the chunks they return and poisons the freed ones, so that use-after-frees,
+//! double frees and invalid frees are reported.
+//!
+//! The guest allocator places the chunks, so there are no redzones between them:
+//! overflows are only caught when they reach a freed chunk.
+//! The checks of the [`AsanModule`] are disabled while the allocator runs.
+
+#![allow(clippy::cast_possible_truncation)]
+
+use hashbrown::HashSet;
+use libafl::{inputs::UsesInput, Error};
+use libafl_qemu_sys::GuestAddr;
+
+use crate::{
+    emu::EmulatorModules,
+    get_exit_arch_regs,
+    modules::{
+        asan::{AsanError, AsanModule, PoisonKind},
+        EmulatorModule, EmulatorModuleTuple, NopAddressFilter, NOP_ADDRESS_FILTER,
+    },
+    qemu::{ArchExtras, Hook},
+    sync_exit::ExitArgs,
+    CallingConvention, Qemu,
+};
+
+/// An allocator entry point, by the arguments it takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocatorFunction {
+    /// `malloc(size)`, or any function allocating a chunk of its first argument
+    Malloc,
+    /// `calloc(nmemb, size)`
+    Calloc,
+    /// `realloc(ptr, size)`
+    Realloc,
+    /// `memalign(alignment, size)` and `aligned_alloc(alignment, size)`
+    Memalign,
+    /// `free(ptr)`
+    Free,
+}
+
+/// Where to find an allocator entry point in the guest
+#[derive(Debug, Clone)]
+pub enum AllocatorLocation {
+    /// A symbol, looked up in every ELF file mapped in the guest
+    Symbol(String),
+    /// A sequence of bytes in the executable mappings of the guest, [`None`] matching any byte
+    Pattern(Vec<Option<u8>>),
+    /// A fixed address
+    Address(GuestAddr),
+}
+
+impl AllocatorLocation {
+    /// Parse a pattern of hex bytes separated by spaces, with `??` for any byte, e.g. `"55 48 89 e5 ?? 83"`
+    pub fn pattern(pattern: &str) -> Result<Self, Error> {
+        let bytes = pattern
+            .split_whitespace()
+            .map(|byte| {
+                if byte == "??" {
+                    Ok(None)
+                } else {
+                    u8::from_str_radix(byte, 16).map(Some).map_err(|_| {
+                        Error::illegal_argument(format!("Invalid byte {byte} in pattern {pattern}"))
+                    })
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if bytes.is_empty() {
+            return Err(Error::illegal_argument("Empty allocator pattern"));
+        }
+        Ok(Self::Pattern(bytes))
+    }
+}
+
+/// An intercepted call, between the entry of the function and its return
+#[derive(Debug, Clone, Copy)]
+struct PendingCall {
+    function: AllocatorFunction,
+    pc: GuestAddr,
+    ret_addr: GuestAddr,
+    args: [GuestAddr; 2],
+    /// Whether the checks of the [`AsanModule`] were enabled at the entry
+    asan_enabled: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct AsanHeapModule {
+    interceptors: Vec<(AllocatorFunction, AllocatorLocation)>,
+    entries: Vec<(AllocatorFunction, GuestAddr)>,
+    return_hooks: HashSet<GuestAddr>,
+    pending: Option<PendingCall>,
+}
+
+impl AsanHeapModule {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Intercept the allocator functions of the C library by their usual symbols
+    #[must_use]
+    pub fn libc() -> Self {
+        Self::new()
+            .intercept(AllocatorFunction::Malloc, symbol("malloc"))
+            .intercept(AllocatorFunction::Malloc, symbol("valloc"))
+            .intercept(AllocatorFunction::Calloc, symbol("calloc"))
+            .intercept(AllocatorFunction::Realloc, symbol("realloc"))
+            .intercept(AllocatorFunction::Memalign, symbol("memalign"))
+            .intercept(AllocatorFunction::Memalign, symbol("aligned_alloc"))
+            .intercept(AllocatorFunction::Free, symbol("free"))
+    }
+
+    /// Intercept `function` at `location`
+    #[must_use]
+    pub fn intercept(mut self, function: AllocatorFunction, location: AllocatorLocation) -> Self {
+        self.interceptors.push((function, location));
+        self
+    }
+
+    /// The entry points found in the guest
+    #[must_use]
+    pub fn entries(&self) -> &[(AllocatorFunction, GuestAddr)] {
+        &self.entries
+    }
+
+    fn resolve(&self, qemu: Qemu) -> Vec<(AllocatorFunction, GuestAddr)> {
+        let mut entries = Vec::new();
+        for (function, location) in &self.interceptors {
+            let addrs = match location {
+                AllocatorLocation::Symbol(name) => qemu.find_symbol(name),
+                AllocatorLocation::Pattern(pattern) => find_pattern(qemu, pattern),
+                AllocatorLocation::Address(addr) => vec![*addr],
+            };
+            if addrs.is_empty() {
+                log::warn!("AsanHeap: {function:?} not found at {location:?}");
+            }
+            for addr in addrs {
+                log::info!("AsanHeap: intercepting {function:?} at {addr:#x}");
+                if !entries.iter().any(|&(_, entry)| entry == addr) {
+                    entries.push((*function, addr));
+                }
+            }
+        }
+        entries
+    }
+}
+
+impl<S> EmulatorModule<S> for AsanHeapModule
+where
+    S: Unpin + UsesInput,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+    const HOOKS_DO_SIDE_EFFECTS: bool = false;
+
+    fn first_exec<ET>(&mut self, emulator_modules: &mut EmulatorModules<ET, S>, _state: &mut S)
+    where
+        ET: EmulatorModuleTuple<S>,
+    {
+        assert!(
+            emulator_modules.get::<AsanModule>().is_some(),
+            "The AsanHeapModule needs an AsanModule"
+        );
+
+        self.entries = self.resolve(emulator_modules.qemu());
+        for &(function, addr) in &self.entries {
+            emulator_modules.instructions(
+                addr,
+                Hook::Closure(Box::new(move |modules, _state, pc| {
+                    on_allocator_entry(modules, function, pc);
+                })),
+                true,
+            );
+        }
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+    ) where
+        ET: EmulatorModuleTuple<S>,
+    {
+        // The last run ended inside the allocator
+        if let Some(call) = self.pending.take() {
+            if let Some(asan) = emulator_modules.get_mut::<AsanModule>() {
+                asan.set_enabled(call.asan_enabled);
+            }
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[allow(clippy::unnecessary_cast)]
+fn on_allocator_entry<ET, S>(
+    emulator_modules: &mut EmulatorModules<ET, S>,
+    function: AllocatorFunction,
+    pc: GuestAddr,
+) where
+    ET: EmulatorModuleTuple<S>,
+    S: Unpin + UsesInput,
+{
+    let h = emulator_modules.get_mut::<AsanHeapModule>().unwrap();
+    if h.pending.is_some() {
+        // Called by the allocator itself
+        return;
+    }
+
+    let qemu = emulator_modules.qemu();
+    let cpu = qemu.current_cpu().unwrap();
+    let ret_addr = cpu.read_return_address().unwrap() as GuestAddr;
+    #[cfg(cpu_target = "arm")]
+    // Required because of arm interworking addresses aka bit(0) for thumb mode
+    let ret_addr = ret_addr & !(0x1 as GuestAddr);
+    let args = [0, 1].map(|idx| {
+        cpu.read_function_argument(CallingConvention::Cdecl, idx)
+            .unwrap() as GuestAddr
+    });
+
+    let asan = emulator_modules.get_mut::<AsanModule>().unwrap();
+    let asan_enabled = asan.enabled();
+    asan.set_enabled(false);
+
+    let h = emulator_modules.get_mut::<AsanHeapModule>().unwrap();
+    h.pending = Some(PendingCall {
+        function,
+        pc,
+        ret_addr,
+        args,
+        asan_enabled,
+    });
+    if h.return_hooks.insert(ret_addr) {
+        emulator_modules.instructions(ret_addr, Hook::Function(on_allocator_return::<ET, S>), true);
+    }
+}
+
+fn on_allocator_return<ET, S>(
+    emulator_modules: &mut EmulatorModules<ET, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) where
+    ET: EmulatorModuleTuple<S>,
+    S: Unpin + UsesInput,
+{
+    let h = emulator_modules.get_mut::<AsanHeapModule>().unwrap();
+    let Some(call) = h.pending.filter(|call| call.ret_addr == pc) else {
+        return;
+    };
+    h.pending = None;
+
+    let qemu = emulator_modules.qemu();
+    let ret: GuestAddr = qemu.read_reg(get_exit_arch_regs()[ExitArgs::Ret]).unwrap();
+    let asan = emulator_modules.get_mut::<AsanModule>().unwrap();
+    asan.set_enabled(call.asan_enabled);
+
+    let [arg0, arg1] = call.args;
+    match call.function {
+        AllocatorFunction::Malloc => on_alloc(qemu, asan, call.pc, ret, arg0 as usize),
+        AllocatorFunction::Calloc => {
+            if let Some(size) = (arg0 as usize).checked_mul(arg1 as usize) {
+                on_alloc(qemu, asan, call.pc, ret, size);
+            }
+        }
+        AllocatorFunction::Memalign => on_alloc(qemu, asan, call.pc, ret, arg1 as usize),
+        AllocatorFunction::Realloc => {
+            // A failed realloc leaves the chunk alone
+            if ret != 0 || arg1 == 0 {
+                if arg0 != 0 {
+                    on_free(qemu, asan, call.pc, arg0);
+                }
+                on_alloc(qemu, asan, call.pc, ret, arg1 as usize);
+            }
+        }
+        AllocatorFunction::Free => on_free(qemu, asan, call.pc, arg0),
+    }
+}
+
+fn on_alloc(qemu: Qemu, asan: &mut AsanModule, pc: GuestAddr, addr: GuestAddr, size: usize) {
+    if addr == 0 || size == 0 {
+        return;
+    }
+    asan.alloc(pc, addr, addr + size as GuestAddr);
+    asan.unpoison(qemu, addr, size);
+}
+
+fn on_free(qemu: Qemu, asan: &mut AsanModule, pc: GuestAddr, addr: GuestAddr) {
+    if addr == 0 {
+        return;
+    }
+    // Chunks allocated before the hooks were installed are unknown, and not reported
+    let Some((chunk, item)) = asan.chunk(addr) else {
+        return;
+    };
+    if !item.allocated() {
+        asan.report(qemu, pc, AsanError::BadFree(addr, Some(chunk)));
+        return;
+    }
+    asan.dealloc(qemu, pc, addr);
+    if chunk.start == addr {
+        asan.poison(
+            qemu,
+            chunk.start,
+            (chunk.end - chunk.start) as usize,
+            PoisonKind::HeapFreed,
+        );
+    }
+}
+
+fn symbol(name: &str) -> AllocatorLocation {
+    AllocatorLocation::Symbol(name.to_string())
+}
+
+/// The addresses of `pattern` in the executable mappings of the guest
+fn find_pattern(qemu: Qemu, pattern: &[Option<u8>]) -> Vec<GuestAddr> {
+    let mut addrs = Vec::new();
+    for map in qemu.mappings() {
+        if !map.flags().executable() || !map.flags().readable() {
+            continue;
+        }
+        let mut code = vec![0; (map.end() - map.start()) as usize];
+        if qemu.read_mem(map.start(), &mut code).is_err() {
+            continue;
+        }
+        addrs.extend(
+            pattern_offsets(&code, pattern).map(|offset| map.start() + offset as GuestAddr),
+        );
+    }
+    addrs
+}
+
+fn pattern_offsets<'a>(
+    haystack: &'a [u8],
+    pattern: &'a [Option<u8>],
+) -> impl Iterator<Item = usize> + 'a {
+    haystack
+        .windows(pattern.len())
+        .enumerate()
+        .filter(|(_, window)| {
+            window
+                .iter()
+                .zip(pattern)
+                .all(|(byte, expected)| expected.map_or(true, |expected| *byte == expected))
+        })
+        .map(|(offset, _)| offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pattern_offsets, AllocatorLocation};
+
+    #[test]
+    fn test_allocator_pattern() {
+        let AllocatorLocation::Pattern(pattern) =
+            AllocatorLocation::pattern("55 ?? 89 e5").unwrap()
+        else {
+            panic!("Not a pattern");
+        };
+        assert_eq!(pattern, vec![Some(0x55), None, Some(0x89), Some(0xe5)]);
+        assert!(AllocatorLocation::pattern("55 zz").is_err());
+        assert!(AllocatorLocation::pattern("").is_err());
+
+        let code = [0x90, 0x55, 0x48, 0x89, 0xe5, 0x55, 0x31, 0x89, 0xe5, 0x55];
+        assert_eq!(
+            pattern_offsets(&code, &pattern).collect::<Vec<_>>(),
+            vec![1, 5]
+        );
+    }
+}
@@ -1,9 +1,14 @@
 //! Detect injection vulnerabilities
+//!
+//! Each injection kind, e.g. `sql`, declares the tokens to add to the fuzzer, the markers revealing an injection,
+//! and the sink functions to check. When an argument of a sink contains a marker (case insensitive),
+//! the [`InjectionModule`] records an [`InjectionFinding`] and reports the run as a crash.
+//! The [`InjectionFeedback`] makes the findings objectives, and attaches them to the testcase as [`InjectionMetadata`].
+//!
+//! The kinds are declared with the [`InjectionModuleBuilder`], or in a TOML or YAML file,
+//! see `fuzzers/binary_only/qemu_launcher/injections.toml`.
 
 /*
- * TODOs:
- *  - read in export addresses of shared libraries to resolve functions
- *
  * Maybe:
  *  - return code analysis support (not needed currently)
  *  - regex support (not needed currently)
@@ -11,10 +16,18 @@
  *
  */
 
-use std::{ffi::CStr, fmt::Display, fs, os::raw::c_char, path::Path};
+use std::{borrow::Cow, fmt::Display, fs, mem::size_of, path::Path, sync::Mutex};
 
 use hashbrown::HashMap;
-use libafl::{inputs::UsesInput, Error};
+use libafl::{
+    corpus::Testcase,
+    executors::ExitKind,
+    feedbacks::{Feedback, StateInitializer},
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    Error, HasMetadata,
+};
+use libafl_bolts::Named;
 use libafl_qemu_sys::GuestAddr;
 use serde::{Deserialize, Serialize};
 
@@ -33,6 +46,9 @@ use crate::{
 /// <https://github.com/qemu/qemu/blob/11be70677c70fdccd452a3233653949b79e97908/linux-user/hexagon/syscall_nr.h#L230>
 const SYS_execve: u8 = 221;
 
+/// The maximum length of the arguments read from the guest
+const MAX_ARGUMENT_LEN: usize = 0x10000;
+
 /// Parses `injections.yaml`
 fn parse_yaml<P: AsRef<Path> + Display>(path: P) -> Result<Vec<YamlInjectionEntry>, Error> {
     serde_yaml::from_str(&fs::read_to_string(&path)?)
@@ -129,7 +145,7 @@ struct FunctionDescription {
     param: u8,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct InjectionDefinition {
     tokens: Vec<String>,
     matches: Vec<String>,
@@ -149,14 +165,197 @@ pub struct Match {
     original_value: String,
 }
 
+/// An injection found at a sink
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InjectionFinding {
+    /// The kind of the injection, e.g. `sql`
+    pub kind: String,
+    /// The sink function, or syscall, whose argument contains the marker
+    pub sink: String,
+    /// The address of the sink, or the syscall number
+    pub sink_addr: GuestAddr,
+    pub marker: String,
+    /// The argument of the sink, decoded lossily
+    pub argument: String,
+}
+
+/// The injections found during the current run
+pub static INJECTION_FINDINGS: Mutex<Vec<InjectionFinding>> = Mutex::new(Vec::new());
+
+fn report_finding(finding: InjectionFinding) {
+    log::info!(
+        "Injections: found {} marker {:?} in {:?} at sink {} ({:#x})",
+        finding.kind,
+        finding.marker,
+        finding.argument,
+        finding.sink,
+        finding.sink_addr
+    );
+    INJECTION_FINDINGS.lock().unwrap().push(finding);
+}
+
+/// The injections found by a run, attached to the objectives by the [`InjectionFeedback`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InjectionMetadata {
+    pub findings: Vec<InjectionFinding>,
+}
+
+libafl_bolts::impl_serdeany!(InjectionMetadata);
+
+/// Reports the runs in which the [`InjectionModule`] found an injection as interesting,
+/// and attaches the findings to the testcase as [`InjectionMetadata`]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct InjectionFeedback;
+
+impl InjectionFeedback {
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Whether an injection was found in the last run
+    #[must_use]
+    pub fn found() -> bool {
+        !INJECTION_FINDINGS.lock().unwrap().is_empty()
+    }
+}
+
+impl Named for InjectionFeedback {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("Injections");
+        &NAME
+    }
+}
+
+impl<S> StateInitializer<S> for InjectionFeedback {}
+
+impl<EM, I, OT, S> Feedback<EM, I, OT, S> for InjectionFeedback {
+    fn is_interesting(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &I,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error> {
+        Ok(Self::found())
+    }
+
+    #[cfg(feature = "track_hit_feedbacks")]
+    fn last_result(&self) -> Result<bool, Error> {
+        Ok(Self::found())
+    }
+
+    fn append_metadata(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _observers: &OT,
+        testcase: &mut Testcase<I>,
+    ) -> Result<(), Error> {
+        let findings = INJECTION_FINDINGS.lock().unwrap().clone();
+        if !findings.is_empty() {
+            testcase
+                .metadata_map_mut()
+                .insert(InjectionMetadata { findings });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct InjectionModuleBuilder {
+    definitions: HashMap<String, InjectionDefinition>,
+    execve: bool,
+}
+
+impl InjectionModuleBuilder {
+    pub fn build(self) -> Result<InjectionModule, Error> {
+        let mut module = InjectionModule::new(self.definitions)?;
+        module.execve = self.execve;
+        Ok(module)
+    }
+
+    /// Add the injection kinds of a TOML or YAML file, chosen by its extension
+    pub fn file<P: AsRef<Path> + Display>(mut self, path: P) -> Result<Self, Error> {
+        let lower = path.to_string().to_lowercase();
+        let definitions = if lower.ends_with("yaml") || lower.ends_with("yml") {
+            yaml_entries_to_definition(&parse_yaml(path)?)?
+        } else if lower.ends_with("toml") {
+            parse_toml(path)?
+        } else {
+            return Err(Error::illegal_argument(format!(
+                "Injections file {path} must end in .toml or .yaml/.yml"
+            )));
+        };
+
+        for (kind, definition) in definitions {
+            if self.definitions.insert(kind.clone(), definition).is_some() {
+                return Err(Error::illegal_argument(format!(
+                    "Injection {kind} was multiply defined!"
+                )));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Declare the injection `kind`, with the `tokens` to add to the fuzzer,
+    /// and the `markers` revealing it when found in an argument of a sink
+    #[must_use]
+    pub fn injection(mut self, kind: &str, tokens: &[&str], markers: &[&str]) -> Self {
+        let definition = self.definitions.entry(kind.to_string()).or_default();
+        definition
+            .tokens
+            .extend(tokens.iter().map(ToString::to_string));
+        definition
+            .matches
+            .extend(markers.iter().map(ToString::to_string));
+        self
+    }
+
+    /// Check the argument `param` (`0` is the first) of the function `sink` for the markers of `kind`.
+    /// `sink` is a symbol, or a guest address starting with `0x`.
+    #[must_use]
+    pub fn sink(mut self, kind: &str, sink: &str, param: u8) -> Self {
+        self.definitions
+            .entry(kind.to_string())
+            .or_default()
+            .functions
+            .insert(sink.to_string(), FunctionDescription { param });
+        self
+    }
+
+    /// Skip the `execve` syscalls, and check their command line for command injections.
+    /// Defaults to `true`.
+    #[must_use]
+    pub fn execve(mut self, execve: bool) -> Self {
+        self.execve = execve;
+        self
+    }
+}
+
 #[derive(Debug)]
 pub struct InjectionModule {
     pub tokens: Vec<String>,
     definitions: HashMap<String, InjectionDefinition>,
     matches_list: Vec<Matches>,
+    execve: bool,
+    found: bool,
 }
 
 impl InjectionModule {
+    #[must_use]
+    pub fn builder() -> InjectionModuleBuilder {
+        InjectionModuleBuilder {
+            definitions: HashMap::new(),
+            execve: true,
+        }
+    }
+
     /// `configure_injections` is the main function to activate the injection
     /// vulnerability detection feature.
     pub fn from_yaml<P: AsRef<Path> + Display>(yaml_file: P) -> Result<Self, Error> {
@@ -173,6 +372,14 @@ impl InjectionModule {
     }
 
     pub fn new(definitions: HashMap<String, InjectionDefinition>) -> Result<Self, Error> {
+        if let Some((lib_name, _)) = definitions.iter().find(|(_, definition)| {
+            definition.matches.is_empty() && !definition.functions.is_empty()
+        }) {
+            return Err(Error::illegal_argument(format!(
+                "Injection {lib_name} has sinks but no markers to find in them"
+            )));
+        }
+
         let tokens = definitions
             .iter()
             .flat_map(|(_lib_name, definition)| &definition.tokens)
@@ -208,11 +415,18 @@ impl InjectionModule {
             tokens,
             definitions,
             matches_list,
+            execve: true,
+            found: false,
         })
     }
 
-    fn on_call_check<ET, S>(emulator_modules: &mut EmulatorModules<ET, S>, id: usize, parameter: u8)
-    where
+    fn on_call_check<ET, S>(
+        emulator_modules: &mut EmulatorModules<ET, S>,
+        id: usize,
+        sink: &str,
+        sink_addr: GuestAddr,
+        parameter: u8,
+    ) where
         ET: EmulatorModuleTuple<S>,
         S: Unpin + UsesInput,
     {
@@ -226,32 +440,31 @@ impl InjectionModule {
         let module = emulator_modules.get_mut::<Self>().unwrap();
         let matches = &module.matches_list[id];
 
-        //println!("reg value = {:x}", reg);
-
-        if reg != 0x00 {
-            let mut query = unsafe {
-                let c_str_ptr = reg as *const c_char;
-                let c_str = CStr::from_ptr(c_str_ptr);
-                c_str.to_bytes().to_vec()
-            };
-            query.make_ascii_lowercase();
-
-            //println!("query={}", query);
-            log::trace!("Checking {}", matches.lib_name);
+        if reg == 0x00 {
+            return;
+        }
+        let Ok(argument) = qemu.read_cstring(reg, MAX_ARGUMENT_LEN) else {
+            log::debug!("Injections: argument {parameter} of {sink} at {reg:#x} is not readable");
+            return;
+        };
+        let mut query = argument.clone();
+        query.make_ascii_lowercase();
 
-            for match_value in &matches.matches {
-                if match_value.bytes_lower.len() > matches.matches.len() {
-                    continue;
-                }
+        log::trace!("Checking {}", matches.lib_name);
 
-                // "crash" if we found the right value
-                assert!(
-                    find_subsequence(&query, &match_value.bytes_lower).is_none(),
-                    "Found value \"{}\" for {query:?} in {}",
-                    match_value.original_value,
-                    matches.lib_name
-                );
-            }
+        if let Some(match_value) = matches
+            .matches
+            .iter()
+            .find(|match_value| find_subsequence(&query, &match_value.bytes_lower).is_some())
+        {
+            report_finding(InjectionFinding {
+                kind: matches.lib_name.clone(),
+                sink: sink.to_string(),
+                sink_addr,
+                marker: match_value.original_value.clone(),
+                argument: String::from_utf8_lossy(&argument).into_owned(),
+            });
+            module.found = true;
         }
     }
 }
@@ -266,7 +479,9 @@ where
     where
         ET: EmulatorModuleTuple<S>,
     {
-        emulator_modules.syscalls(Hook::Function(syscall_hook::<ET, S>));
+        if self.execve {
+            emulator_modules.syscalls(Hook::Function(syscall_hook::<ET, S>));
+        }
     }
 
     fn first_exec<ET>(&mut self, emulator_modules: &mut EmulatorModules<ET, S>, _state: &mut S)
@@ -322,10 +537,11 @@ where
                 let param = func_definition.param;
 
                 for hook_addr in hook_addrs {
+                    let sink = name.clone();
                     emulator_modules.instructions(
                         hook_addr,
-                        Hook::Closure(Box::new(move |hooks, _state, _guest_addr| {
-                            Self::on_call_check(hooks, id, param);
+                        Hook::Closure(Box::new(move |hooks, _state, guest_addr| {
+                            Self::on_call_check(hooks, id, &sink, guest_addr, param);
                         })),
                         true,
                     );
@@ -334,6 +550,34 @@ where
         }
     }
 
+    fn pre_exec<ET>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+    ) where
+        ET: EmulatorModuleTuple<S>,
+    {
+        self.found = false;
+        INJECTION_FINDINGS.lock().unwrap().clear();
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+        _observers: &mut OT,
+        exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S::Input, S>,
+        ET: EmulatorModuleTuple<S>,
+    {
+        if self.found {
+            *exit_kind = ExitKind::Crash;
+        }
+    }
+
     fn address_filter(&self) -> &Self::ModuleAddressFilter {
         &NopAddressFilter
     }
@@ -343,7 +587,34 @@ where
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Read a pointer of the guest
+fn read_guest_ptr(qemu: Qemu, addr: GuestAddr) -> Option<GuestAddr> {
+    let mut buf = [0; size_of::<GuestAddr>()];
+    qemu.read_mem(addr, &mut buf).ok()?;
+    #[cfg(feature = "be")]
+    let ptr = GuestAddr::from_be_bytes(buf);
+    #[cfg(not(feature = "be"))]
+    let ptr = GuestAddr::from_le_bytes(buf);
+    Some(ptr)
+}
+
+/// The command injection marker found in the command line of an `execve`
+fn find_command_injection(cmd: &str, argv: &[String]) -> Option<&'static str> {
+    if cmd.to_lowercase() == "fuzz" {
+        return Some("fuzz");
+    }
+    if let [_, first, second, ..] = argv {
+        if first == "-c" {
+            let second = second.to_lowercase();
+            return ["';fuzz;'", "\";fuzz;\""]
+                .into_iter()
+                .find(|marker| second.contains(marker));
+        }
+    }
+    None
+}
+
+#[allow(clippy::too_many_arguments, clippy::cast_sign_loss)]
 fn syscall_hook<ET, S>(
     // Our instantiated [`EmulatorModules`]
     emulator_modules: &mut EmulatorModules<ET, S>,
@@ -366,46 +637,38 @@ where
 {
     log::trace!("syscall_hook {syscall} {SYS_execve}");
     debug_assert!(i32::try_from(SYS_execve).is_ok());
-    if syscall == SYS_execve as i32 {
-        let _module = emulator_modules.get_mut::<InjectionModule>().unwrap();
-        if x0 > 0 && x1 > 0 {
-            let c_array = x1 as *const *const c_char;
-            let cmd = unsafe {
-                let c_str_ptr = x0 as *const c_char;
-                CStr::from_ptr(c_str_ptr).to_string_lossy()
-            };
-            assert_ne!(
-                cmd.to_lowercase(),
-                "fuzz",
-                "Found verified command injection!"
-            );
-            //println!("CMD {}", cmd);
+    if syscall != SYS_execve as i32 {
+        return SyscallHookResult::new(None);
+    }
 
-            let first_parameter = unsafe {
-                if (*c_array.offset(1)).is_null() {
-                    return SyscallHookResult::new(None);
-                }
-                CStr::from_ptr(*c_array.offset(1)).to_string_lossy()
-            };
-            let second_parameter = unsafe {
-                if (*c_array.offset(2)).is_null() {
-                    return SyscallHookResult::new(None);
-                }
-                CStr::from_ptr(*c_array.offset(2)).to_string_lossy()
-            };
-            if first_parameter == "-c"
-                && (second_parameter.to_lowercase().contains("';fuzz;'")
-                    || second_parameter.to_lowercase().contains("\";fuzz;\""))
-            {
-                panic!("Found command injection!");
-            }
+    let qemu = emulator_modules.qemu();
+    if x0 > 0 && x1 > 0 {
+        let cmd = qemu
+            .read_cstring(x0, MAX_ARGUMENT_LEN)
+            .map(|cmd| String::from_utf8_lossy(&cmd).into_owned())
+            .unwrap_or_default();
+        // The first arguments are enough to find `sh -c '...'`
+        let argv: Vec<String> = (0..3)
+            .map_while(|i| {
+                let arg = read_guest_ptr(qemu, x1 + i * size_of::<GuestAddr>() as GuestAddr)?;
+                (arg != 0).then_some(arg)
+            })
+            .filter_map(|arg| qemu.read_cstring(arg, MAX_ARGUMENT_LEN).ok())
+            .map(|arg| String::from_utf8_lossy(&arg).into_owned())
+            .collect();
 
-            //println!("PARAMETERS First {} Second {}", first_parameter, second_
+        if let Some(marker) = find_command_injection(&cmd, &argv) {
+            report_finding(InjectionFinding {
+                kind: "cmd".to_string(),
+                sink: "execve".to_string(),
+                sink_addr: syscall as GuestAddr,
+                marker: marker.to_string(),
+                argument: argv.join(" "),
+            });
+            emulator_modules.get_mut::<InjectionModule>().unwrap().found = true;
         }
-        SyscallHookResult::new(Some(0))
-    } else {
-        SyscallHookResult::new(None)
     }
+    SyscallHookResult::new(Some(0))
 }
 
 fn find_function(
@@ -425,6 +688,9 @@ fn find_function(
 }
 
 fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.len() > haystack.len() {
+        return None;
+    }
     haystack
         .windows(needle.len())
         .position(|window| window == needle)
@@ -434,7 +700,10 @@ fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
 mod tests {
     use hashbrown::HashMap;
 
-    use super::{yaml_entries_to_definition, InjectionDefinition, YamlInjectionEntry};
+    use super::{
+        find_command_injection, yaml_entries_to_definition, InjectionDefinition, InjectionModule,
+        YamlInjectionEntry,
+    };
 
     #[test]
     fn test_yaml_parsing() {
@@ -450,7 +719,7 @@ mod tests {
               tests:
                 - input_value: "*)(FUZZ=*))(|"
                   match_value: "*)(FUZZ=*))(|"
-            
+
             # XSS injection tests
             # This is a minimal example that only checks for libxml2
             - name: "xss"
@@ -498,4 +767,39 @@ mod tests {
         .unwrap();
         assert_eq!(injections.len(), 2);
     }
+
+    #[test]
+    fn test_injection_builder() {
+        let module = InjectionModule::builder()
+            .injection("sql", &["' OR 1=1 --"], &["OR 1=1"])
+            .sink("sql", "sqlite3_exec", 1)
+            .sink("sql", "0x1000", 0)
+            .build()
+            .unwrap();
+        assert_eq!(module.tokens, ["' OR 1=1 --"]);
+        assert_eq!(module.definitions["sql"].functions.len(), 2);
+
+        assert!(InjectionModule::builder()
+            .sink("sql", "sqlite3_exec", 1)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn test_command_injection() {
+        let argv = |args: &[&str]| args.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            find_command_injection("FUZZ", &argv(&["FUZZ"])),
+            Some("fuzz")
+        );
+        assert_eq!(
+            find_command_injection("/bin/sh", &argv(&["sh", "-c", "echo ';FUZZ;'"])),
+            Some("';fuzz;'")
+        );
+        assert_eq!(
+            find_command_injection("/bin/sh", &argv(&["sh", "-c", "echo fuzz"])),
+            None
+        );
+        assert_eq!(find_command_injection("/bin/ls", &argv(&["ls"])), None);
+    }
 }
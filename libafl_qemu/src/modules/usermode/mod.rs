@@ -1,17 +1,28 @@
 #[cfg(feature = "injections")]
 pub mod injections;
 #[cfg(feature = "injections")]
-pub use injections::InjectionModule;
+pub use injections::{InjectionFeedback, InjectionModule, InjectionModuleBuilder};
 
 #[cfg(not(cpu_target = "hexagon"))]
 pub mod snapshot;
 #[cfg(not(cpu_target = "hexagon"))]
-pub use snapshot::{IntervalSnapshotFilter, SnapshotModule};
+pub use snapshot::{IntervalSnapshotFilter, SnapshotModule, SnapshotStats};
 
 #[cfg(not(cpu_target = "hexagon"))]
 pub mod asan;
 #[cfg(not(cpu_target = "hexagon"))]
-pub use asan::{init_qemu_with_asan, AsanModule};
+pub use asan::{init_qemu_with_asan, AsanModule, AsanReport, AsanReportObserver};
+
+#[cfg(not(cpu_target = "hexagon"))]
+pub mod asan_heap;
+#[cfg(not(cpu_target = "hexagon"))]
+pub use asan_heap::{AllocatorFunction, AllocatorLocation, AsanHeapModule};
+
+pub mod syscall_policy;
+pub use syscall_policy::{
+    SyscallAction, SyscallPolicyModule, SyscallPolicyModuleBuilder, SyscallRecord,
+    SyscallTraceObserver,
+};
 
 #[cfg(not(cpu_target = "hexagon"))]
 pub mod asan_guest;
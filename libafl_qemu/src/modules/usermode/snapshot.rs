@@ -1,6 +1,6 @@
 use std::{cell::UnsafeCell, mem::MaybeUninit, sync::Mutex};
 
-use hashbrown::{HashMap, HashSet};
+use hashbrown::HashMap;
 use libafl::inputs::UsesInput;
 use libafl_qemu_sys::{GuestAddr, MmapPerms};
 use meminterval::{Interval, IntervalTree};
@@ -50,17 +50,81 @@ pub struct SnapshotPageInfo {
 pub struct SnapshotAccessInfo {
     pub access_cache: [GuestAddr; 4],
     pub access_cache_idx: usize,
-    pub dirty: HashSet<GuestAddr>,
+    /// One bit per snapshotted page, set once the page got dirty
+    pub dirty_bitmap: Vec<u64>,
+    /// The dirty snapshotted pages, each listed once
+    pub dirty: Vec<GuestAddr>,
+    /// The words of the bitmap with bits set, to clear them without touching the whole bitmap
+    dirty_words: Vec<usize>,
 }
 
 impl SnapshotAccessInfo {
+    /// Marks the `page`, snapshotted at `idx` in the bitmap, dirty
+    pub fn mark_dirty(&mut self, page: GuestAddr, idx: usize) {
+        let (word, bit) = (idx / 64, 1u64 << (idx % 64));
+        if self.dirty_bitmap.len() <= word {
+            self.dirty_bitmap.resize(word + 1, 0);
+        }
+        if self.dirty_bitmap[word] & bit == 0 {
+            self.dirty_bitmap[word] |= bit;
+            self.dirty_words.push(word);
+            self.dirty.push(page);
+        }
+    }
+
     pub fn clear(&mut self) {
         self.access_cache_idx = 0;
         self.access_cache = [GuestAddr::MAX; 4];
+        for word in self.dirty_words.drain(..) {
+            self.dirty_bitmap[word] = 0;
+        }
         self.dirty.clear();
     }
 }
 
+/// A range of snapshotted pages, and the bitmap index of its first page
+#[derive(Clone, Debug)]
+struct SnapshotPageRange {
+    start: GuestAddr,
+    end: GuestAddr,
+    first_idx: usize,
+}
+
+/// Statistics about the restores of the [`SnapshotModule`]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SnapshotStats {
+    /// The pages in the snapshot
+    pub snapshot_pages: usize,
+    /// The number of restores
+    pub restores: u64,
+    /// The pages restored over all restores
+    pub pages_restored: u64,
+    /// The pages restored by the last restore
+    pub last_pages_restored: usize,
+    /// The most pages restored by a single restore
+    pub max_pages_restored: usize,
+}
+
+impl SnapshotStats {
+    /// The average number of pages restored per execution
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn pages_per_restore(&self) -> f64 {
+        if self.restores == 0 {
+            0.0
+        } else {
+            self.pages_restored as f64 / self.restores as f64
+        }
+    }
+
+    fn record_restore(&mut self, pages: usize) {
+        self.restores += 1;
+        self.pages_restored += pages as u64;
+        self.last_pages_restored = pages;
+        self.max_pages_restored = self.max_pages_restored.max(pages);
+    }
+}
+
 #[derive(Clone, Default, Debug)]
 pub struct MemoryRegionInfo {
     pub perms: Option<MmapPerms>,
@@ -96,6 +160,9 @@ pub struct SnapshotModule {
     pub empty: bool,
     pub accurate_unmap: bool,
     pub interval_filter: Vec<IntervalSnapshotFilter>,
+    /// The snapshotted ranges, sorted, to find the bit of a page in the dirty bitmaps
+    page_ranges: Vec<SnapshotPageRange>,
+    stats: SnapshotStats,
 }
 
 impl core::fmt::Debug for SnapshotModule {
@@ -108,6 +175,7 @@ impl core::fmt::Debug for SnapshotModule {
             .field("mmap_start", &self.mmap_start)
             .field("mmap_limit", &self.mmap_limit)
             .field("empty", &self.empty)
+            .field("stats", &self.stats)
             .finish_non_exhaustive()
     }
 }
@@ -127,6 +195,8 @@ impl SnapshotModule {
             empty: true,
             accurate_unmap: false,
             interval_filter: Vec::<IntervalSnapshotFilter>::new(),
+            page_ranges: Vec::new(),
+            stats: SnapshotStats::default(),
         }
     }
 
@@ -144,6 +214,8 @@ impl SnapshotModule {
             empty: true,
             accurate_unmap: false,
             interval_filter,
+            page_ranges: Vec::new(),
+            stats: SnapshotStats::default(),
         }
     }
 
@@ -161,6 +233,8 @@ impl SnapshotModule {
             empty: true,
             accurate_unmap: false,
             interval_filter: Vec::<IntervalSnapshotFilter>::new(),
+            page_ranges: Vec::new(),
+            stats: SnapshotStats::default(),
         }
     }
 
@@ -168,6 +242,20 @@ impl SnapshotModule {
         self.accurate_unmap = true;
     }
 
+    /// Statistics about the pages restored per execution
+    #[must_use]
+    pub fn stats(&self) -> &SnapshotStats {
+        &self.stats
+    }
+
+    /// The index of the snapshotted `page` in the dirty bitmaps
+    fn page_index(&self, page: GuestAddr) -> Option<usize> {
+        let pos = self.page_ranges.partition_point(|range| range.end <= page);
+        let range = self.page_ranges.get(pos)?;
+        (range.start <= page)
+            .then(|| range.first_idx + (page - range.start) as usize / SNAPSHOT_PAGE_SIZE)
+    }
+
     pub fn to_skip(&self, addr: GuestAddr) -> bool {
         for filter in &self.interval_filter {
             match filter {
@@ -193,6 +281,8 @@ impl SnapshotModule {
         self.brk = qemu.get_brk();
         self.mmap_start = qemu.get_mmap_start();
         self.pages.clear();
+        self.page_ranges.clear();
+        let mut page_count = 0;
         for map in qemu.mappings() {
             let mut addr = map.start();
             while addr < map.end() {
@@ -225,7 +315,19 @@ impl SnapshotModule {
                 },
             );
             self.maps.size += (map.end() - map.start()) as usize;
+            self.page_ranges.push(SnapshotPageRange {
+                start: map.start(),
+                end: map.end(),
+                first_idx: page_count,
+            });
+            page_count += (map.end() - map.start()) as usize / SNAPSHOT_PAGE_SIZE;
+        }
+        self.page_ranges.sort_unstable_by_key(|range| range.start);
+        // Pages dirtied so far are relative to the previous snapshot, if any
+        for acc in &mut self.accesses {
+            unsafe { (*acc.get()).clear() };
         }
+        self.stats.snapshot_pages = self.pages.len();
         self.empty = false;
         *self.new_maps.lock().unwrap() = self.maps.clone();
         log::info!("End snapshot");
@@ -244,14 +346,19 @@ impl SnapshotModule {
             let idx = (*acc).access_cache_idx;
             (*acc).access_cache[idx] = page;
             (*acc).access_cache_idx = (idx + 1) & 3;
-            (*acc).dirty.insert(page);
+            // Pages mapped after the snapshot have nothing to restore
+            if let Some(page_idx) = self.page_index(page) {
+                (*acc).mark_dirty(page, page_idx);
+            }
         }
     }
 
     pub fn page_access_no_cache(&self, page: GuestAddr) {
-        unsafe {
-            let acc = self.accesses.get_or_default().get();
-            (*acc).dirty.insert(page);
+        if let Some(page_idx) = self.page_index(page) {
+            unsafe {
+                let acc = self.accesses.get_or_default().get();
+                (*acc).mark_dirty(page, page_idx);
+            }
         }
     }
 
@@ -382,81 +489,81 @@ impl SnapshotModule {
     }
 
     pub fn reset(&mut self, qemu: Qemu) {
+        let mut restored = 0;
+        // Dirty pages in regions unmapped during execution, restored once the region is mapped again
+        let mut deferred = Vec::new();
         {
             let new_maps = self.new_maps.get_mut().unwrap();
 
             log::debug!("Start restore");
 
             for acc in &mut self.accesses {
-                unsafe { &mut (*acc.get()) }.dirty.retain(|page| {
-                    if let Some(info) = self.pages.get_mut(page) {
-                        // TODO avoid duplicated memcpy
-                        if let Some(data) = info.data.as_ref() {
-                            // Change segment perms to RW if not writeable in current mapping
-                            let mut found = false;
-                            for entry in new_maps
-                                .tree
-                                .query_mut(*page..(page + SNAPSHOT_PAGE_SIZE as GuestAddr))
-                            {
-                                if !entry.value.perms.unwrap_or(MmapPerms::None).writable() {
-                                    drop(qemu.mprotect(
-                                        entry.interval.start,
-                                        (entry.interval.end - entry.interval.start) as usize,
-                                        MmapPerms::ReadWrite,
-                                    ));
-                                    entry.value.changed = true;
-                                    entry.value.perms = Some(MmapPerms::ReadWrite);
-                                }
-                                found = true;
-                            }
-
-                            if !found {
-                                return true; // Restore later
-                            }
+                for page in &unsafe { &*acc.get() }.dirty {
+                    let Some(info) = self.pages.get(page) else {
+                        // Not snapshotted because of the interval filter
+                        continue;
+                    };
+                    let Some(data) = info.data.as_ref() else {
+                        panic!("Cannot restored a dirty but unsaved page");
+                    };
 
-                            unsafe { qemu.write_mem_unchecked(*page, &data[..]) };
-                        } else {
-                            panic!("Cannot restored a dirty but unsaved page");
+                    // Change segment perms to RW if not writeable in current mapping
+                    let mut found = false;
+                    for entry in new_maps
+                        .tree
+                        .query_mut(*page..(page + SNAPSHOT_PAGE_SIZE as GuestAddr))
+                    {
+                        if !entry.value.perms.unwrap_or(MmapPerms::None).writable() {
+                            drop(qemu.mprotect(
+                                entry.interval.start,
+                                (entry.interval.end - entry.interval.start) as usize,
+                                MmapPerms::ReadWrite,
+                            ));
+                            entry.value.changed = true;
+                            entry.value.perms = Some(MmapPerms::ReadWrite);
                         }
+                        found = true;
                     }
-                    false
-                });
+
+                    if found {
+                        unsafe { qemu.write_mem_unchecked(*page, &data[..]) };
+                        restored += 1;
+                    } else {
+                        deferred.push(*page);
+                    }
+                }
             }
         }
 
         self.reset_maps(qemu);
 
         // This one is after that we remapped potential regions mapped at snapshot time but unmapped during execution
-        for acc in &mut self.accesses {
-            for page in unsafe { &(*acc.get()).dirty } {
-                for entry in self
-                    .maps
-                    .tree
-                    .query_mut(*page..(page + SNAPSHOT_PAGE_SIZE as GuestAddr))
+        for page in deferred {
+            for entry in self
+                .maps
+                .tree
+                .query_mut(page..(page + SNAPSHOT_PAGE_SIZE as GuestAddr))
+            {
+                if !entry.value.perms.unwrap_or(MmapPerms::None).writable() && !entry.value.changed
                 {
-                    if !entry.value.perms.unwrap_or(MmapPerms::None).writable()
-                        && !entry.value.changed
-                    {
-                        drop(qemu.mprotect(
-                            entry.interval.start,
-                            (entry.interval.end - entry.interval.start) as usize,
-                            MmapPerms::ReadWrite,
-                        ));
-                        entry.value.changed = true;
-                    }
+                    drop(qemu.mprotect(
+                        entry.interval.start,
+                        (entry.interval.end - entry.interval.start) as usize,
+                        MmapPerms::ReadWrite,
+                    ));
+                    entry.value.changed = true;
                 }
+            }
 
-                if let Some(info) = self.pages.get_mut(page) {
-                    // TODO avoid duplicated memcpy
-                    if let Some(data) = info.data.as_ref() {
-                        unsafe { qemu.write_mem_unchecked(*page, &data[..]) };
-                    } else {
-                        panic!("Cannot restored a dirty but unsaved page");
-                    }
-                }
+            if let Some(data) = self.pages.get(&page).and_then(|info| info.data.as_ref()) {
+                unsafe { qemu.write_mem_unchecked(page, &data[..]) };
+                restored += 1;
             }
+        }
+        for acc in &mut self.accesses {
             unsafe { (*acc.get()).clear() };
         }
+        self.stats.record_restore(restored);
 
         for entry in self.maps.tree.query_mut(0..GuestAddr::MAX) {
             if entry.value.changed {
@@ -475,7 +582,7 @@ impl SnapshotModule {
         #[cfg(feature = "paranoid_debug")]
         self.check_snapshot(qemu);
 
-        log::debug!("End restore");
+        log::debug!("End restore, {restored} pages restored");
     }
 
     pub fn is_unmap_allowed(&mut self, start: GuestAddr, mut size: usize) -> bool {
@@ -0,0 +1,370 @@
+//! A declarative policy for the syscalls of the guest.
+//!
+//! The [`SyscallPolicyModule`] applies a [`SyscallAction`] to each syscall, by number:
+//! run it, skip it with a fake result, record it, or report the run as a crash.
+//! Faking the syscalls touching the network or the clock removes a common source of flaky runs,
+//! and a crash on e.g. `execve` turns a sandbox escape into an objective.
+//!
+//! The recorded syscalls of a run are exposed by the [`SyscallTraceObserver`].
+
+use std::{borrow::Cow, sync::Mutex};
+
+use hashbrown::HashMap;
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{Observer, ObserverWithHashField, ObserversTuple},
+    Error,
+};
+use libafl_bolts::{hash_std, Named};
+use libafl_qemu_sys::GuestAddr;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::EmulatorModules,
+    modules::{EmulatorModule, EmulatorModuleTuple, NopAddressFilter, NOP_ADDRESS_FILTER},
+    qemu::{Hook, SyscallHookResult},
+};
+
+/// What to do with a syscall of the guest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyscallAction {
+    /// Run the syscall
+    Allow,
+    /// Skip the syscall, returning this value to the guest
+    Fake(GuestAddr),
+    /// Run the syscall, and record it with its result
+    Record,
+    /// Skip the syscall, returning `-EPERM` to the guest, record it and report the run as a crash
+    Crash,
+}
+
+/// The return value of a syscall failing with `errno`
+#[must_use]
+#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+pub fn errno_result(errno: i32) -> GuestAddr {
+    (-i64::from(errno)) as GuestAddr
+}
+
+/// A syscall recorded by the [`SyscallPolicyModule`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyscallRecord {
+    pub sys_num: i32,
+    pub args: [GuestAddr; 8],
+    /// The result returned to the guest, [`None`] if the run ended during the syscall
+    pub result: Option<GuestAddr>,
+    pub action: SyscallAction,
+}
+
+/// The syscalls recorded during the current run
+pub static SYSCALL_TRACE: Mutex<Vec<SyscallRecord>> = Mutex::new(Vec::new());
+
+/// Observes the syscalls recorded by a [`SyscallPolicyModule`] during a run.
+///
+/// Its hash is the one of the sequence of the recorded syscall numbers.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyscallTraceObserver {
+    trace: Vec<SyscallRecord>,
+}
+
+impl SyscallTraceObserver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The syscalls recorded during the last run
+    #[must_use]
+    pub fn trace(&self) -> &[SyscallRecord] {
+        &self.trace
+    }
+}
+
+impl<I, S> Observer<I, S> for SyscallTraceObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.trace.clear();
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &I,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.trace.clone_from(&SYSCALL_TRACE.lock().unwrap());
+        Ok(())
+    }
+}
+
+impl ObserverWithHashField for SyscallTraceObserver {
+    fn hash(&self) -> Option<u64> {
+        if self.trace.is_empty() {
+            return None;
+        }
+        let bytes: Vec<u8> = self
+            .trace
+            .iter()
+            .flat_map(|record| record.sys_num.to_le_bytes())
+            .collect();
+        Some(hash_std(&bytes))
+    }
+}
+
+impl Named for SyscallTraceObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        static NAME: Cow<'static, str> = Cow::Borrowed("SyscallTrace");
+        &NAME
+    }
+}
+
+#[derive(Debug)]
+pub struct SyscallPolicyModuleBuilder {
+    actions: HashMap<i64, SyscallAction>,
+    default_action: SyscallAction,
+}
+
+impl SyscallPolicyModuleBuilder {
+    #[must_use]
+    pub fn build(self) -> SyscallPolicyModule {
+        SyscallPolicyModule::new(self.actions, self.default_action)
+    }
+
+    /// Apply `action` to the syscall `sys_num`, e.g. [`crate::SYS_execve`]
+    #[must_use]
+    pub fn action(mut self, sys_num: i64, action: SyscallAction) -> Self {
+        self.actions.insert(sys_num, action);
+        self
+    }
+
+    #[must_use]
+    pub fn allow(self, sys_num: i64) -> Self {
+        self.action(sys_num, SyscallAction::Allow)
+    }
+
+    #[must_use]
+    pub fn fake(self, sys_num: i64, retval: GuestAddr) -> Self {
+        self.action(sys_num, SyscallAction::Fake(retval))
+    }
+
+    /// Skip the syscall `sys_num`, failing with `errno`
+    #[must_use]
+    pub fn fake_errno(self, sys_num: i64, errno: i32) -> Self {
+        self.fake(sys_num, errno_result(errno))
+    }
+
+    #[must_use]
+    pub fn record(self, sys_num: i64) -> Self {
+        self.action(sys_num, SyscallAction::Record)
+    }
+
+    #[must_use]
+    pub fn crash(self, sys_num: i64) -> Self {
+        self.action(sys_num, SyscallAction::Crash)
+    }
+
+    /// The action of the syscalls without one. Defaults to [`SyscallAction::Allow`].
+    #[must_use]
+    pub fn default_action(mut self, default_action: SyscallAction) -> Self {
+        self.default_action = default_action;
+        self
+    }
+}
+
+/// Applies a [`SyscallAction`] to each syscall of the guest.
+/// See the [module-level documentation](self).
+#[derive(Debug)]
+pub struct SyscallPolicyModule {
+    actions: HashMap<i64, SyscallAction>,
+    default_action: SyscallAction,
+    /// The index in [`SYSCALL_TRACE`] of the recorded syscall running
+    pending: Option<usize>,
+    crashed: bool,
+}
+
+impl SyscallPolicyModule {
+    #[must_use]
+    pub fn builder() -> SyscallPolicyModuleBuilder {
+        SyscallPolicyModuleBuilder {
+            actions: HashMap::new(),
+            default_action: SyscallAction::Allow,
+        }
+    }
+
+    #[must_use]
+    pub fn new(actions: HashMap<i64, SyscallAction>, default_action: SyscallAction) -> Self {
+        Self {
+            actions,
+            default_action,
+            pending: None,
+            crashed: false,
+        }
+    }
+
+    /// The action applied to the syscall `sys_num`
+    #[must_use]
+    pub fn action(&self, sys_num: i64) -> SyscallAction {
+        self.actions
+            .get(&sys_num)
+            .copied()
+            .unwrap_or(self.default_action)
+    }
+}
+
+impl<S> EmulatorModule<S> for SyscallPolicyModule
+where
+    S: Unpin + UsesInput,
+{
+    type ModuleAddressFilter = NopAddressFilter;
+
+    fn post_qemu_init<ET>(&self, emulator_modules: &mut EmulatorModules<ET, S>)
+    where
+        ET: EmulatorModuleTuple<S>,
+    {
+        emulator_modules.syscalls(Hook::Function(pre_syscall_policy::<ET, S>));
+        emulator_modules.after_syscalls(Hook::Function(post_syscall_policy::<ET, S>));
+    }
+
+    fn pre_exec<ET>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+    ) where
+        ET: EmulatorModuleTuple<S>,
+    {
+        self.pending = None;
+        self.crashed = false;
+        SYSCALL_TRACE.lock().unwrap().clear();
+    }
+
+    fn post_exec<OT, ET>(
+        &mut self,
+        _emulator_modules: &mut EmulatorModules<ET, S>,
+        _state: &mut S,
+        _input: &S::Input,
+        _observers: &mut OT,
+        exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S::Input, S>,
+        ET: EmulatorModuleTuple<S>,
+    {
+        if self.crashed {
+            *exit_kind = ExitKind::Crash;
+        }
+    }
+
+    fn address_filter(&self) -> &Self::ModuleAddressFilter {
+        &NopAddressFilter
+    }
+
+    fn address_filter_mut(&mut self) -> &mut Self::ModuleAddressFilter {
+        unsafe { (&raw mut NOP_ADDRESS_FILTER).as_mut().unwrap().get_mut() }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pre_syscall_policy<ET, S>(
+    emulator_modules: &mut EmulatorModules<ET, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    a3: GuestAddr,
+    a4: GuestAddr,
+    a5: GuestAddr,
+    a6: GuestAddr,
+    a7: GuestAddr,
+) -> SyscallHookResult
+where
+    ET: EmulatorModuleTuple<S>,
+    S: Unpin + UsesInput,
+{
+    let Some(h) = emulator_modules.get_mut::<SyscallPolicyModule>() else {
+        return SyscallHookResult::new(None);
+    };
+
+    let action = h.action(i64::from(sys_num));
+    let result = match action {
+        SyscallAction::Allow => return SyscallHookResult::new(None),
+        SyscallAction::Fake(retval) => return SyscallHookResult::new(Some(retval)),
+        SyscallAction::Record => None,
+        SyscallAction::Crash => {
+            log::info!("SyscallPolicy: syscall {sys_num} reported as a crash");
+            h.crashed = true;
+            Some(errno_result(libc::EPERM))
+        }
+    };
+
+    let mut trace = SYSCALL_TRACE.lock().unwrap();
+    trace.push(SyscallRecord {
+        sys_num,
+        args: [a0, a1, a2, a3, a4, a5, a6, a7],
+        result,
+        action,
+    });
+    // The result of a recorded syscall is filled after it ran
+    h.pending = result.is_none().then(|| trace.len() - 1);
+
+    SyscallHookResult::new(result)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn post_syscall_policy<ET, S>(
+    emulator_modules: &mut EmulatorModules<ET, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    sys_num: i32,
+    _a0: GuestAddr,
+    _a1: GuestAddr,
+    _a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> GuestAddr
+where
+    ET: EmulatorModuleTuple<S>,
+    S: Unpin + UsesInput,
+{
+    if let Some(h) = emulator_modules.get_mut::<SyscallPolicyModule>() {
+        if let Some(idx) = h.pending.take() {
+            if let Some(record) = SYSCALL_TRACE.lock().unwrap().get_mut(idx) {
+                if record.sys_num == sys_num {
+                    record.result = Some(result);
+                }
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_qemu_sys::GuestAddr;
+
+    use super::{errno_result, SyscallAction, SyscallPolicyModule};
+
+    #[test]
+    fn test_syscall_policy_actions() {
+        let module = SyscallPolicyModule::builder()
+            .fake_errno(1, libc::ECONNREFUSED)
+            .record(2)
+            .crash(3)
+            .default_action(SyscallAction::Record)
+            .allow(4)
+            .build();
+
+        assert_eq!(
+            module.action(1),
+            SyscallAction::Fake(errno_result(libc::ECONNREFUSED))
+        );
+        assert_eq!(module.action(2), SyscallAction::Record);
+        assert_eq!(module.action(3), SyscallAction::Crash);
+        assert_eq!(module.action(4), SyscallAction::Allow);
+        assert_eq!(module.action(5), SyscallAction::Record);
+        assert_eq!(errno_result(1), GuestAddr::MAX);
+    }
+}
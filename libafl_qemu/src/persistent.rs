@@ -0,0 +1,268 @@
+//! The bounds of a persistent loop, given by address, by symbol, or as the return of the function the loop starts in.
+//!
+//! Symbols are resolved when the loop is set up, so that the harness keeps working when the target is rebuilt.
+//! The [`PersistentLoop`] reports the bounds that are never reached, instead of looping silently.
+
+use core::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
+
+use libafl::Error;
+use libafl_qemu_sys::GuestAddr;
+
+use crate::{elf::EasyElf, ArchExtras, GuestReg, Qemu, QemuExitReason, Regs};
+
+/// The number of runs after which a loop whose stop was never reached is reported
+const DEFAULT_WARN_AFTER: u64 = 100;
+
+/// Where a persistent loop starts or stops
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoopBound {
+    Address(GuestAddr),
+    /// A symbol of the guest, see [`PersistentLoopBuilder::elf`]
+    Symbol(String),
+    /// The return address of the function the loop starts in. Only valid as the stop.
+    ReturnOfStart,
+}
+
+impl Display for LoopBound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Address(addr) => write!(f, "{addr:#x}"),
+            Self::Symbol(name) => write!(f, "symbol {name}"),
+            Self::ReturnOfStart => write!(f, "the return of the start"),
+        }
+    }
+}
+
+impl From<GuestAddr> for LoopBound {
+    fn from(addr: GuestAddr) -> Self {
+        Self::Address(addr)
+    }
+}
+
+impl From<&str> for LoopBound {
+    fn from(name: &str) -> Self {
+        Self::Symbol(name.to_string())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PersistentLoopBuilder {
+    start: LoopBound,
+    stop: LoopBound,
+    elf: Option<(PathBuf, GuestAddr)>,
+    warn_after: u64,
+}
+
+impl PersistentLoopBuilder {
+    /// Where the loop stops. Defaults to [`LoopBound::ReturnOfStart`].
+    #[must_use]
+    pub fn stop<B>(mut self, stop: B) -> Self
+    where
+        B: Into<LoopBound>,
+    {
+        self.stop = stop.into();
+        self
+    }
+
+    /// Resolve the symbols in the ELF file at `path`, loaded at `load_addr`.
+    /// In usermode, they are looked up in the ELF files mapped in the guest by default.
+    #[must_use]
+    pub fn elf<P>(mut self, path: P, load_addr: GuestAddr) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        self.elf = Some((path.into(), load_addr));
+        self
+    }
+
+    /// Report a stop never reached after `warn_after` runs. Defaults to 100.
+    #[must_use]
+    pub fn warn_after(mut self, warn_after: u64) -> Self {
+        self.warn_after = warn_after;
+        self
+    }
+
+    fn resolve(&self, qemu: Qemu, bound: &LoopBound) -> Result<GuestAddr, Error> {
+        let name = match bound {
+            LoopBound::Address(addr) => return Ok(*addr),
+            LoopBound::Symbol(name) => name,
+            LoopBound::ReturnOfStart => {
+                return Err(Error::illegal_argument(
+                    "A persistent loop cannot start at the return of its start",
+                ))
+            }
+        };
+
+        let addr = if let Some((path, load_addr)) = &self.elf {
+            let mut buffer = Vec::new();
+            EasyElf::from_file(path, &mut buffer)?.resolve_symbol(name, *load_addr)
+        } else {
+            #[cfg(feature = "usermode")]
+            {
+                let addrs = qemu.find_symbol(name);
+                if addrs.len() > 1 {
+                    log::warn!(
+                        "Persistent loop: symbol {name} found at {addrs:#x?}, using the first one"
+                    );
+                }
+                addrs.first().copied()
+            }
+            #[cfg(feature = "systemmode")]
+            {
+                let _ = qemu;
+                return Err(Error::illegal_argument(format!(
+                    "Persistent loop: no ELF file to resolve symbol {name} in"
+                )));
+            }
+        };
+        addr.ok_or_else(|| {
+            Error::illegal_argument(format!("Persistent loop: symbol {name} not found"))
+        })
+    }
+
+    /// Run the guest to the start of the loop, and break at its stop
+    #[allow(clippy::unnecessary_cast)]
+    pub fn init(self, qemu: Qemu) -> Result<PersistentLoop, Error> {
+        let start = self.resolve(qemu, &self.start)?;
+        log::debug!("Persistent loop: start {} at {start:#x}", self.start);
+
+        qemu.set_breakpoint(start);
+        let reason = unsafe { qemu.run() };
+        qemu.remove_breakpoint(start);
+        match reason {
+            Ok(QemuExitReason::Breakpoint(addr)) if addr == start => {}
+            Ok(reason) => {
+                return Err(Error::illegal_state(format!(
+                    "Persistent loop: the start, {} at {start:#x}, was never reached ({reason})",
+                    self.start
+                )))
+            }
+            Err(err) => {
+                return Err(Error::illegal_state(format!(
+                    "Persistent loop: the start, {} at {start:#x}, was never reached ({err:?})",
+                    self.start
+                )))
+            }
+        }
+
+        let rw_error = |what: &str, err| Error::unknown(format!("Failed to read {what}: {err:?}"));
+        let pc = qemu.read_reg(Regs::Pc).map_err(|e| rw_error("PC", e))?;
+        let sp = qemu.read_reg(Regs::Sp).map_err(|e| rw_error("SP", e))?;
+        let ret_addr = qemu
+            .read_return_address()
+            .map_err(|e| rw_error("the return address", e))?;
+
+        let stop = match self.stop {
+            LoopBound::ReturnOfStart => ret_addr as GuestAddr,
+            _ => self.resolve(qemu, &self.stop)?,
+        };
+        log::debug!("Persistent loop: stop {} at {stop:#x}", self.stop);
+        qemu.set_breakpoint(stop);
+
+        Ok(PersistentLoop {
+            start,
+            stop,
+            start_bound: self.start,
+            stop_bound: self.stop,
+            pc,
+            sp,
+            ret_addr,
+            runs: 0,
+            stops: 0,
+            warn_after: self.warn_after,
+            warned: false,
+        })
+    }
+}
+
+/// A persistent loop, running the guest from its start to its stop for each input
+#[derive(Debug)]
+pub struct PersistentLoop {
+    start: GuestAddr,
+    stop: GuestAddr,
+    start_bound: LoopBound,
+    stop_bound: LoopBound,
+    pc: GuestReg,
+    sp: GuestReg,
+    ret_addr: GuestReg,
+    runs: u64,
+    stops: u64,
+    warn_after: u64,
+    warned: bool,
+}
+
+impl PersistentLoop {
+    /// A loop starting at `start`, and stopping at its return by default
+    #[must_use]
+    pub fn builder<B>(start: B) -> PersistentLoopBuilder
+    where
+        B: Into<LoopBound>,
+    {
+        PersistentLoopBuilder {
+            start: start.into(),
+            stop: LoopBound::ReturnOfStart,
+            elf: None,
+            warn_after: DEFAULT_WARN_AFTER,
+        }
+    }
+
+    #[must_use]
+    pub fn start(&self) -> GuestAddr {
+        self.start
+    }
+
+    #[must_use]
+    pub fn stop(&self) -> GuestAddr {
+        self.stop
+    }
+
+    /// The number of runs, and of runs that reached the stop
+    #[must_use]
+    pub fn stats(&self) -> (u64, u64) {
+        (self.runs, self.stops)
+    }
+
+    /// Restore the registers of the start. Write the input and the arguments after this.
+    pub fn restart(&self, qemu: Qemu) -> Result<(), Error> {
+        let rw_error = |what: &str, err| Error::unknown(format!("Failed to write {what}: {err:?}"));
+        qemu.write_reg(Regs::Pc, self.pc)
+            .map_err(|e| rw_error("PC", e))?;
+        qemu.write_reg(Regs::Sp, self.sp)
+            .map_err(|e| rw_error("SP", e))?;
+        // The start is a function entry, whose return address may have been overwritten
+        if self.stop_bound == LoopBound::ReturnOfStart {
+            qemu.write_return_address(self.ret_addr)
+                .map_err(|e| rw_error("the return address", e))?;
+        }
+        Ok(())
+    }
+
+    /// Run the guest until the stop, or until it exits for another reason.
+    ///
+    /// # Safety
+    /// See [`Qemu::run`].
+    pub unsafe fn run(&mut self, qemu: Qemu) -> Result<QemuExitReason, Error> {
+        let reason = unsafe { qemu.run() }
+            .map_err(|err| Error::unknown(format!("Persistent loop: QEMU exited: {err:?}")))?;
+        self.runs += 1;
+        if matches!(reason, QemuExitReason::Breakpoint(addr) if addr == self.stop) {
+            self.stops += 1;
+        } else {
+            log::debug!("Persistent loop: run ended before the stop ({reason})");
+        }
+
+        if !self.warned && self.stops == 0 && self.runs >= self.warn_after {
+            log::warn!(
+                "Persistent loop: the stop, {} at {:#x}, was not reached in {} runs from {}. \
+                The target may exit before, or the bound may be wrong.",
+                self.stop_bound,
+                self.stop,
+                self.runs,
+                self.start_bound
+            );
+            self.warned = true;
+        }
+        Ok(reason)
+    }
+}
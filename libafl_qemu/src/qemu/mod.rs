@@ -778,6 +778,36 @@ impl Qemu {
             .write_mem(addr, buf)
     }
 
+    /// Read `len` bytes from a guest address, see [`Qemu::read_mem`]
+    pub fn read_mem_vec(&self, addr: GuestAddr, len: usize) -> Result<Vec<u8>, QemuRWError> {
+        let mut buf = vec![0; len];
+        self.read_mem(addr, &mut buf)?;
+        Ok(buf)
+    }
+
+    /// Read a NUL-terminated string from a guest address, without the NUL.
+    ///
+    /// The string is read page by page, so that it may end right before an unmapped page.
+    /// It is truncated to `max_len` bytes.
+    pub fn read_cstring(&self, addr: GuestAddr, max_len: usize) -> Result<Vec<u8>, QemuRWError> {
+        const CHUNK: usize = 0x1000;
+
+        let mut string = Vec::new();
+        let mut chunk = [0u8; CHUNK];
+        while string.len() < max_len {
+            let cur = addr.wrapping_add(string.len() as GuestAddr);
+            let to_boundary = CHUNK - (cur as usize % CHUNK);
+            let len = to_boundary.min(max_len - string.len());
+            self.read_mem(cur, &mut chunk[..len])?;
+            if let Some(nul) = chunk[..len].iter().position(|&b| b == 0) {
+                string.extend_from_slice(&chunk[..nul]);
+                return Ok(string);
+            }
+            string.extend_from_slice(&chunk[..len]);
+        }
+        Ok(string)
+    }
+
     /// Read a value from a guest address.
     ///
     /// # Safety
@@ -2,7 +2,7 @@ use std::{
     ffi::{c_void, CStr, CString},
     marker::PhantomData,
     mem::MaybeUninit,
-    ptr::null_mut,
+    ptr::{null, null_mut},
     slice,
 };
 
@@ -41,6 +41,12 @@ pub struct PhysMemoryChunk {
     cpu: CPU,
 }
 
+/// An input GPIO line of a device, usually one of its interrupt lines.
+#[derive(Debug, Clone, Copy)]
+pub struct IrqLine {
+    irq: libafl_qemu_sys::qemu_irq,
+}
+
 pub struct PhysMemoryIter {
     addr: GuestAddrKind, // This address is correct when the iterator enters next, except if the remaining len is 0
     remaining_len: usize,
@@ -85,6 +91,19 @@ impl DeviceSnapshotFilter {
     }
 }
 
+impl IrqLine {
+    /// Set the level of the line.
+    pub fn set(&self, level: bool) {
+        unsafe { libafl_qemu_sys::qemu_set_irq(self.irq, i32::from(level)) }
+    }
+
+    /// Raise the line and lower it right after, like an edge-triggered interrupt.
+    pub fn pulse(&self) {
+        self.set(true);
+        self.set(false);
+    }
+}
+
 impl CPU {
     #[must_use]
     pub fn get_phys_addr(&self, vaddr: GuestVirtAddr) -> Option<GuestPhysAddr> {
@@ -294,6 +313,36 @@ impl Qemu {
         }
     }
 
+    /// Get the input GPIO line `n` of the device at the QOM path `device_path`
+    /// (e.g. `/machine/unattached/device[3]` or `/machine/peripheral/uart0`).
+    ///
+    /// `name` selects a named GPIO list. The unnamed list holds the interrupt lines of most devices.
+    /// Returns [`None`] if there is no device at `device_path`.
+    /// QEMU aborts if the device has no line `n`.
+    #[must_use]
+    pub fn device_irq(&self, device_path: &str, name: Option<&str>, n: i32) -> Option<IrqLine> {
+        let path = CString::new(device_path).ok()?;
+        let name = name.map(CString::new).transpose().ok()?;
+        unsafe {
+            let mut ambiguous = false;
+            let obj = libafl_qemu_sys::object_resolve_path(path.as_ptr(), &raw mut ambiguous);
+            if obj.is_null() {
+                return None;
+            }
+            let dev = libafl_qemu_sys::object_dynamic_cast(obj, c"device".as_ptr());
+            if dev.is_null() {
+                return None;
+            }
+
+            let irq = libafl_qemu_sys::qdev_get_gpio_in_named(
+                dev.cast(),
+                name.as_ref().map_or(null(), |name| name.as_ptr()),
+                n,
+            );
+            Some(IrqLine { irq })
+        }
+    }
+
     #[must_use]
     pub fn target_page_size(&self) -> usize {
         unsafe { libafl_qemu_sys::qemu_target_page_size() }
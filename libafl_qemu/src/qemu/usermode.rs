@@ -13,7 +13,7 @@ use libc::{c_int, c_uchar, strlen};
 #[cfg(feature = "python")]
 use pyo3::{pyclass, pymethods, IntoPy, PyObject, PyRef, PyRefMut, Python};
 
-use crate::{Qemu, CPU};
+use crate::{elf::EasyElf, Qemu, CPU};
 
 #[cfg_attr(feature = "python", pyclass(unsendable))]
 pub struct GuestMaps {
@@ -166,6 +166,57 @@ impl Qemu {
         }
     }
 
+    /// The addresses of the symbol `name` in the ELF files mapped in the guest
+    #[must_use]
+    pub fn find_symbol(&self, name: &str) -> Vec<GuestAddr> {
+        let mut addrs = Vec::new();
+        for (path, base) in self.mapped_files() {
+            let mut buffer = Vec::new();
+            let Ok(elf) = EasyElf::from_file(&path, &mut buffer) else {
+                continue;
+            };
+            if let Some(addr) = elf.resolve_symbol(name, base) {
+                addrs.push(addr);
+            }
+        }
+        addrs
+    }
+
+    /// The mapping of the guest containing `addr`
+    #[must_use]
+    pub fn mapping(&self, addr: GuestAddr) -> Option<MapInfo> {
+        self.mappings()
+            .find(|map| map.start() <= addr && addr < map.end())
+    }
+
+    /// The symbol of the ELF file mapped at `addr`, and the offset of `addr` in it
+    #[must_use]
+    pub fn symbol_at(&self, addr: GuestAddr) -> Option<(String, GuestAddr)> {
+        let map = self.mapping(addr)?;
+        let path = map.path().filter(|path| path.starts_with('/'))?;
+        let (_, base) = self.mapped_files().into_iter().find(|(p, _)| p == path)?;
+
+        let mut buffer = Vec::new();
+        let elf = EasyElf::from_file(path, &mut buffer).ok()?;
+        elf.symbol_at(addr, base)
+            .map(|(name, offset)| (name.to_string(), offset))
+    }
+
+    /// The files mapped in the guest, with the address they are loaded at
+    fn mapped_files(&self) -> Vec<(String, GuestAddr)> {
+        let mut bases: Vec<(String, GuestAddr)> = Vec::new();
+        for map in self.mappings() {
+            let Some(path) = map.path().filter(|path| path.starts_with('/')) else {
+                continue;
+            };
+            match bases.iter_mut().find(|(p, _)| p == path) {
+                Some((_, base)) => *base = (*base).min(map.start() - map.offset()),
+                None => bases.push((path.clone(), map.start() - map.offset())),
+            }
+        }
+        bases
+    }
+
     #[must_use]
     pub fn load_addr(&self) -> GuestAddr {
         unsafe { libafl_load_addr() as GuestAddr }
@@ -56,8 +56,18 @@ fn main() {
         .map_or(Ok(SIXTY_FIVE_KB), str::parse)
         .expect("Could not parse LIBAFL_DDG_MAP_SIZE");
 
+    let cmpcov_map_size: usize = option_env!("LIBAFL_CMPCOV_MAP_SIZE")
+        .map_or(Ok(SIXTY_FIVE_KB), str::parse)
+        .expect("Could not parse LIBAFL_CMPCOV_MAP_SIZE");
+
+    let dfsan_map_size: usize = option_env!("LIBAFL_DFSAN_MAP_SIZE")
+        .map_or(Ok(SIXTY_FIVE_KB), str::parse)
+        .expect("Could not parse LIBAFL_DFSAN_MAP_SIZE");
+
     assert!(edges_map_default_size <= edges_map_allocated_size);
     assert!(edges_map_default_size.is_power_of_two());
+    assert!(cmpcov_map_size.is_power_of_two());
+    assert!(dfsan_map_size.is_power_of_two());
 
     write!(
         constants_file,
@@ -76,7 +86,11 @@ fn main() {
         /// The size of the accounting maps
         pub const ACCOUNTING_MAP_SIZE: usize = {acc_map_size};
         /// The size of the accounting maps
-        pub const DDG_MAP_SIZE: usize = {ddg_map_size};        
+        pub const DDG_MAP_SIZE: usize = {ddg_map_size};
+        /// The size of the map of the comparisons progress
+        pub const CMPCOV_MAP_SIZE: usize = {cmpcov_map_size};
+        /// The size of the map of the labels reaching each comparison site
+        pub const DFSAN_MAP_SIZE: usize = {dfsan_map_size};
 "
     )
     .expect("Could not write file");
@@ -90,6 +104,8 @@ fn main() {
     println!("cargo:rerun-if-env-changed=LIBAFL_CMPLOG_MAP_H");
     println!("cargo:rerun-if-env-changed=LIBAFL_ACCOUNTING_MAP_SIZE");
     println!("cargo:rerun-if-env-changed=LIBAFL_DDG_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_CMPCOV_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_DFSAN_MAP_SIZE");
 
     #[cfg(feature = "common")]
     {
@@ -214,6 +230,28 @@ fn main() {
         }
     }
 
+    #[cfg(feature = "cmp_coverage")]
+    {
+        println!("cargo:rerun-if-changed=src/cmpcov.c");
+
+        cc::Build::new()
+            .define("CMPCOV_MAP_SIZE", Some(&*format!("{cmpcov_map_size}")))
+            .define("CMPLOG_MAP_W", Some(&*format!("{cmplog_map_w}")))
+            .define("CMPLOG_MAP_H", Some(&*format!("{cmplog_map_h}")))
+            .file(src_dir.join("cmpcov.c"))
+            .compile("cmpcov");
+    }
+
+    #[cfg(feature = "dfsan")]
+    {
+        println!("cargo:rerun-if-changed=src/dfsan.c");
+
+        cc::Build::new()
+            .define("DFSAN_MAP_SIZE", Some(&*format!("{dfsan_map_size}")))
+            .file(src_dir.join("dfsan.c"))
+            .compile("dfsan");
+    }
+
     #[cfg(any(feature = "forkserver", feature = "windows_asan"))]
     let target_family = std::env::var("CARGO_CFG_TARGET_FAMILY").unwrap();
 
@@ -0,0 +1,95 @@
+// Runtime for the libafl_cc `CmpCoverage` pass.
+// The pass replaces the calls to memory and string comparisons with the
+// wrappers below. Each wrapper does the comparison itself, logs the operands
+// to the cmplog map, and sets one byte of the cmpcov map for every leading
+// byte that matched, so that getting closer to a magic value is new coverage.
+
+#include "common.h"
+#include "cmplog.h"
+#include <ctype.h>
+#include <string.h>
+
+#ifndef CMPCOV_MAP_SIZE
+  #define CMPCOV_MAP_SIZE 65536
+#endif
+
+// The count of leading bytes that give coverage, longer matches saturate
+#define CMPCOV_MAX_PROGRESS ((size_t)32)
+
+extern uint8_t __libafl_cmpcov_map_local[CMPCOV_MAP_SIZE];
+uint8_t       *__libafl_cmpcov_area_ptr = __libafl_cmpcov_map_local;
+
+static inline void cmpcov_progress(uint32_t id, size_t matched) {
+  matched = MIN(matched, CMPCOV_MAX_PROGRESS);
+  for (size_t i = 0; i < matched; i++) {
+    __libafl_cmpcov_area_ptr[(id + i) & (CMPCOV_MAP_SIZE - 1)] = 1;
+  }
+}
+
+// Log the operands, padded with zeroes to the longest one, so that we never
+// read past the end of the shorter operand
+static inline void cmpcov_cmplog(uint32_t id, const uint8_t *ptr1,
+                                 size_t len1, const uint8_t *ptr2,
+                                 size_t len2) {
+  if (!libafl_cmplog_enabled) { return; }
+
+  uint8_t buf1[CMPLOG_RTN_LEN] = {0};
+  uint8_t buf2[CMPLOG_RTN_LEN] = {0};
+  len1 = MIN(len1, (size_t)CMPLOG_RTN_LEN - 1);
+  len2 = MIN(len2, (size_t)CMPLOG_RTN_LEN - 1);
+  MEMCPY(buf1, ptr1, len1);
+  MEMCPY(buf2, ptr2, len2);
+
+  size_t l = MAX(len1, len2);
+  if (l < 2) { return; }
+
+  uintptr_t k = (uintptr_t)id & (CMPLOG_MAP_W - 1);
+  cmplog_routines_checked(k, buf1, buf2, l);
+}
+
+int __libafl_cmpcov_memcmp(const uint8_t *s1, const uint8_t *s2, size_t n,
+                           uint32_t id) {
+  size_t i = 0;
+  while (i < n && s1[i] == s2[i]) {
+    i++;
+  }
+  cmpcov_progress(id, i);
+  cmpcov_cmplog(id, s1, n, s2, n);
+  return i == n ? 0 : (int)s1[i] - (int)s2[i];
+}
+
+int __libafl_cmpcov_strncmp(const uint8_t *s1, const uint8_t *s2, size_t n,
+                            uint32_t id) {
+  size_t i = 0;
+  while (i < n && s1[i] && s1[i] == s2[i]) {
+    i++;
+  }
+  cmpcov_progress(id, i);
+  size_t l = MIN(n, (size_t)CMPLOG_RTN_LEN - 1);
+  cmpcov_cmplog(id, s1, strnlen((const char *)s1, l), s2,
+                strnlen((const char *)s2, l));
+  return i == n ? 0 : (int)s1[i] - (int)s2[i];
+}
+
+int __libafl_cmpcov_strcmp(const uint8_t *s1, const uint8_t *s2,
+                           uint32_t id) {
+  return __libafl_cmpcov_strncmp(s1, s2, SIZE_MAX, id);
+}
+
+int __libafl_cmpcov_strncasecmp(const uint8_t *s1, const uint8_t *s2,
+                                size_t n, uint32_t id) {
+  size_t i = 0;
+  while (i < n && s1[i] && tolower(s1[i]) == tolower(s2[i])) {
+    i++;
+  }
+  cmpcov_progress(id, i);
+  size_t l = MIN(n, (size_t)CMPLOG_RTN_LEN - 1);
+  cmpcov_cmplog(id, s1, strnlen((const char *)s1, l), s2,
+                strnlen((const char *)s2, l));
+  return i == n ? 0 : tolower(s1[i]) - tolower(s2[i]);
+}
+
+int __libafl_cmpcov_strcasecmp(const uint8_t *s1, const uint8_t *s2,
+                               uint32_t id) {
+  return __libafl_cmpcov_strncasecmp(s1, s2, SIZE_MAX, id);
+}
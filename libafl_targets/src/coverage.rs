@@ -12,6 +12,8 @@ use alloc::borrow::Cow;
 #[cfg(any(target_os = "linux", target_vendor = "apple"))]
 use libafl::{mutators::Tokens, Error};
 
+#[cfg(feature = "cmp_coverage")]
+use crate::CMPCOV_MAP_SIZE;
 use crate::{ACCOUNTING_MAP_SIZE, DDG_MAP_SIZE, EDGES_MAP_ALLOCATED_SIZE, EDGES_MAP_DEFAULT_SIZE};
 
 /// The map for edges.
@@ -26,6 +28,14 @@ pub use __afl_area_ptr_local as EDGES_MAP;
 pub static mut __ddg_area_ptr_local: [u8; DDG_MAP_SIZE] = [0; DDG_MAP_SIZE];
 pub use __ddg_area_ptr_local as DDG_MAP;
 
+/// The map for the progress through comparisons, filled by the `CmpCoverage` pass of `libafl_cc`
+#[cfg(feature = "cmp_coverage")]
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static mut __libafl_cmpcov_map_local: [u8; CMPCOV_MAP_SIZE] = [0; CMPCOV_MAP_SIZE];
+#[cfg(feature = "cmp_coverage")]
+pub use __libafl_cmpcov_map_local as CMPCOV_MAP;
+
 /// The map for accounting mem writes.
 #[no_mangle]
 #[allow(non_upper_case_globals)]
@@ -48,6 +58,10 @@ extern "C" {
     /// The area pointer points to the accounting mem operations map.
     pub static mut __afl_acc_memop_ptr: *mut u32;
 
+    /// The area pointer points to the comparisons progress map
+    #[cfg(feature = "cmp_coverage")]
+    pub static mut __libafl_cmpcov_area_ptr: *mut u8;
+
     /// Start of libafl token section
     #[cfg(any(target_os = "linux", target_vendor = "apple"))]
     pub static __token_start: *const u8;
@@ -59,6 +73,8 @@ extern "C" {
 pub use __afl_acc_memop_ptr as ACCOUNTING_MEMOP_MAP_PTR;
 pub use __afl_area_ptr as EDGES_MAP_PTR;
 pub use __ddg_area_ptr as DDG_MAP_PTR;
+#[cfg(feature = "cmp_coverage")]
+pub use __libafl_cmpcov_area_ptr as CMPCOV_MAP_PTR;
 
 /// Return Tokens from the compile-time token section
 #[cfg(any(target_os = "linux", target_vendor = "apple"))]
@@ -76,6 +92,41 @@ pub fn autotokens() -> Result<Tokens, Error> {
     }
 }
 
+/// Add the tokens found at compile time by the `libafl_cc` `AutoTokens` pass to the [`Tokens`] metadata of `state`,
+/// creating it if missing. Call this at the startup of the fuzzer, before the first mutational stage.
+///
+/// The tokens come from the [`autotokens`] section of the target, and from the `dict2file` file if any,
+/// which the pass writes instead of the section when `AFL_LLVM_DICT2FILE` is set to an absolute path during the build.
+/// Returns the count of new tokens.
+#[cfg(feature = "std")]
+pub fn load_autotokens<S, P>(state: &mut S, dict2file: Option<P>) -> Result<usize, libafl::Error>
+where
+    S: libafl::HasMetadata,
+    P: AsRef<std::path::Path>,
+{
+    let mut toks = libafl::mutators::Tokens::default();
+    #[cfg(any(target_os = "linux", target_vendor = "apple"))]
+    {
+        toks += autotokens()?;
+    }
+    if let Some(dict2file) = dict2file {
+        toks.add_from_file(dict2file)?;
+    }
+
+    if let Ok(existing) = state.metadata_mut::<libafl::mutators::Tokens>() {
+        Ok(toks
+            .iter()
+            .filter(|token| existing.add_token(token))
+            .count())
+    } else {
+        let count = toks.len();
+        if count > 0 {
+            state.add_metadata(toks);
+        }
+        Ok(count)
+    }
+}
+
 /// The actual size we use for the map of edges.
 /// This is used for forkserver backend
 #[allow(non_upper_case_globals)]
@@ -159,6 +210,21 @@ where
     StdMapObserver::from_mut_slice(name, edges_map_mut_slice())
 }
 
+/// Gets a new [`StdMapObserver`](libafl::observers::StdMapObserver) for the [`CMPCOV_MAP`],
+/// the progress of the target through the comparisons instrumented by the `CmpCoverage` pass of `libafl_cc`.
+///
+/// # Safety
+/// This will dereference [`CMPCOV_MAP_PTR`] and crash if it is not a valid address.
+#[cfg(feature = "cmp_coverage")]
+pub unsafe fn std_cmpcov_map_observer<'a, S>(
+    name: S,
+) -> libafl::observers::StdMapObserver<'a, u8, false>
+where
+    S: Into<alloc::borrow::Cow<'static, str>>,
+{
+    libafl::observers::StdMapObserver::from_mut_ptr(name, CMPCOV_MAP_PTR, CMPCOV_MAP_SIZE)
+}
+
 /// Gets the current edges map pt
 /// It will usually take `EDGES_MAP`, but `EDGES_MAP_PTR`,
 /// if built with the `pointer_maps` feature.
@@ -0,0 +1,602 @@
+//! Function and file coverage summaries, from the `SanitizerCoverage` [PC table](https://clang.llvm.org/docs/SanitizerCoverage.html#pc-table).
+//!
+//! Build the target with `-fsanitize-coverage=trace-pc-guard,pc-table`. The [`CoverageSummary`] groups the edges
+//! of the table by function, and symbolizes the functions with the debug info of the target,
+//! to tell which functions, files and modules a map, or a set of edges, covers.
+//!
+//! The summary can be written as an lcov tracefile, for `genhtml` and the like, or as a simple HTML page.
+//! The [`CoverageReportStage`] periodically writes both for the history map of a map feedback,
+//! standalone tools can write them for any map, e.g. after running a corpus:
+//!
+//! ```rust,ignore
+//! CoverageSummary::from_map(edges_map).write_to_dir("./coverage")?;
+//! ```
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    ffi::c_void,
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    ops::Range,
+    time::Duration,
+};
+#[cfg(unix)]
+use std::{ffi::CStr, ffi::OsStr, os::unix::ffi::OsStrExt};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    feedbacks::MapFeedbackMetadata,
+    stages::Stage,
+    state::{State, UsesState},
+    Error, HasNamedMetadata,
+};
+use libafl_bolts::{current_time, Named};
+
+use crate::{sanitizer_cov_functions, sanitizer_cov_pc_for_edge};
+
+/// The default interval of the [`CoverageReportStage`]
+pub const COVERAGE_REPORT_INTERVAL_DEFAULT: Duration = Duration::from_secs(60);
+
+/// The symbol of an address of the target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    /// The name of the function, or its address if unknown
+    pub name: String,
+    /// The source file, if the target has debug info
+    pub file: Option<PathBuf>,
+    /// The line in the source file
+    pub line: Option<u32>,
+    /// The executable or shared library containing the address
+    pub module: Option<PathBuf>,
+}
+
+/// Symbolize `addr` with the debug info of the target
+#[must_use]
+pub fn symbolize(addr: usize) -> Symbol {
+    let mut symbol = Symbol {
+        name: format!("{addr:#x}"),
+        file: None,
+        line: None,
+        module: module_of(addr),
+    };
+    let mut found = false;
+    backtrace::resolve(addr as *mut c_void, |resolved| {
+        // The innermost frame comes first, keep it over the inlined callers
+        if found {
+            return;
+        }
+        found = true;
+        if let Some(name) = resolved.name() {
+            symbol.name = format!("{name:#}");
+        }
+        symbol.file = resolved.filename().map(ToOwned::to_owned);
+        symbol.line = resolved.lineno();
+    });
+    symbol
+}
+
+/// The executable or shared library containing `addr`
+#[cfg(unix)]
+fn module_of(addr: usize) -> Option<PathBuf> {
+    let mut info: libc::Dl_info = unsafe { core::mem::zeroed() };
+    if unsafe { libc::dladdr(addr as *const c_void, &raw mut info) } == 0
+        || info.dli_fname.is_null()
+    {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(info.dli_fname) };
+    Some(PathBuf::from(OsStr::from_bytes(name.to_bytes())))
+}
+
+/// The executable or shared library containing `addr`
+#[cfg(not(unix))]
+fn module_of(_addr: usize) -> Option<PathBuf> {
+    None
+}
+
+/// The coverage of a function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    /// The address of the entry of the function
+    pub entry: usize,
+    /// The symbol of the function
+    pub symbol: Symbol,
+    /// The edges of the function, indices in the edges map
+    pub edges: Range<usize>,
+    /// The hits of each of the [`Self::edges`], as counted by the map
+    pub edge_hits: Vec<u64>,
+    /// The count of covered edges
+    pub covered: usize,
+}
+
+impl FunctionCoverage {
+    /// Whether any edge of the function is covered
+    #[must_use]
+    pub fn is_covered(&self) -> bool {
+        self.covered > 0
+    }
+
+    /// The hits of the function, summed over its edges
+    #[must_use]
+    pub fn hits(&self) -> u64 {
+        self.edge_hits.iter().sum()
+    }
+}
+
+/// The coverage of a source file, summed over its functions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileCoverage {
+    /// The source file, [`None`] for the functions without debug info
+    pub file: Option<PathBuf>,
+    /// The count of functions
+    pub functions: usize,
+    /// The count of covered functions
+    pub covered_functions: usize,
+    /// The count of edges
+    pub edges: usize,
+    /// The count of covered edges
+    pub covered: usize,
+    /// The hits, summed over the edges
+    pub hits: u64,
+}
+
+/// The coverage of an executable or shared library, summed over its functions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleCoverage {
+    /// The module, [`None`] for the functions it could not be found for
+    pub module: Option<PathBuf>,
+    /// The count of functions
+    pub functions: usize,
+    /// The count of covered functions
+    pub covered_functions: usize,
+    /// The count of edges
+    pub edges: usize,
+    /// The count of covered edges
+    pub covered: usize,
+    /// The hits, summed over the edges
+    pub hits: u64,
+}
+
+/// Function, file and module coverage. See the [module-level documentation](self).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoverageSummary {
+    functions: Vec<FunctionCoverage>,
+}
+
+impl CoverageSummary {
+    /// The coverage of the edges map `map`, an edge being covered if its entry is not zero.
+    /// The entries are the hits of the edges, for a history map the highest (bucketed) ones seen.
+    #[must_use]
+    pub fn from_map<T>(map: &[T]) -> Self
+    where
+        T: Copy + Into<u64>,
+    {
+        Self::with(|edge| map.get(edge).map_or(0, |hits| (*hits).into()))
+    }
+
+    /// The coverage of the edges with the indices `covered`
+    #[must_use]
+    pub fn from_indices<I>(covered: I) -> Self
+    where
+        I: IntoIterator<Item = usize>,
+    {
+        let covered: HashSet<usize> = covered.into_iter().collect();
+        Self::with(|edge| u64::from(covered.contains(&edge)))
+    }
+
+    fn with<F>(hits: F) -> Self
+    where
+        F: Fn(usize) -> u64,
+    {
+        let functions = sanitizer_cov_functions()
+            .map(|(entry, edges)| {
+                let edge_hits: Vec<u64> = edges.clone().map(&hits).collect();
+                FunctionCoverage {
+                    entry,
+                    symbol: symbolize(entry),
+                    covered: edge_hits.iter().filter(|hits| **hits != 0).count(),
+                    edge_hits,
+                    edges,
+                }
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// The coverage of each function
+    #[must_use]
+    pub fn functions(&self) -> &[FunctionCoverage] {
+        &self.functions
+    }
+
+    /// The functions with at least one covered edge
+    pub fn covered_functions(&self) -> impl Iterator<Item = &FunctionCoverage> {
+        self.functions
+            .iter()
+            .filter(|function| function.is_covered())
+    }
+
+    /// The coverage of each source file, sorted by path
+    #[must_use]
+    pub fn files(&self) -> Vec<FileCoverage> {
+        let mut files: HashMap<Option<PathBuf>, FileCoverage> = HashMap::new();
+        for function in &self.functions {
+            let file = files
+                .entry(function.symbol.file.clone())
+                .or_insert_with(|| FileCoverage {
+                    file: function.symbol.file.clone(),
+                    ..FileCoverage::default()
+                });
+            file.functions += 1;
+            file.covered_functions += usize::from(function.is_covered());
+            file.edges += function.edges.len();
+            file.covered += function.covered;
+            file.hits += function.hits();
+        }
+        let mut files: Vec<FileCoverage> = files.into_values().collect();
+        files.sort_by(|a, b| a.file.cmp(&b.file));
+        files
+    }
+
+    /// The coverage of each module, sorted by path
+    #[must_use]
+    pub fn modules(&self) -> Vec<ModuleCoverage> {
+        let mut modules: HashMap<Option<PathBuf>, ModuleCoverage> = HashMap::new();
+        for function in &self.functions {
+            let module = modules
+                .entry(function.symbol.module.clone())
+                .or_insert_with(|| ModuleCoverage {
+                    module: function.symbol.module.clone(),
+                    ..ModuleCoverage::default()
+                });
+            module.functions += 1;
+            module.covered_functions += usize::from(function.is_covered());
+            module.edges += function.edges.len();
+            module.covered += function.covered;
+            module.hits += function.hits();
+        }
+        let mut modules: Vec<ModuleCoverage> = modules.into_values().collect();
+        modules.sort_by(|a, b| a.module.cmp(&b.module));
+        modules
+    }
+
+    /// The count of edges, and of covered edges
+    #[must_use]
+    pub fn edges(&self) -> (usize, usize) {
+        self.functions
+            .iter()
+            .fold((0, 0), |(edges, covered), function| {
+                (edges + function.edges.len(), covered + function.covered)
+            })
+    }
+}
+
+impl CoverageSummary {
+    /// Writes the coverage as an lcov tracefile named `test_name`.
+    ///
+    /// The line coverage comes from symbolizing the PC of each edge, which takes a while for large targets.
+    /// Functions and lines without debug info are left out.
+    pub fn write_lcov<W>(&self, writer: &mut W, test_name: &str) -> io::Result<()>
+    where
+        W: Write,
+    {
+        // The hits of each line, by source file
+        let mut lines: BTreeMap<PathBuf, BTreeMap<u32, u64>> = BTreeMap::new();
+        for function in &self.functions {
+            for (edge, hits) in function.edges.clone().zip(&function.edge_hits) {
+                let Some(entry) = sanitizer_cov_pc_for_edge(edge) else {
+                    continue;
+                };
+                let symbol = symbolize(entry.addr());
+                if let (Some(file), Some(line)) = (symbol.file, symbol.line) {
+                    *lines.entry(file).or_default().entry(line).or_default() += hits;
+                }
+            }
+        }
+        for function in &self.functions {
+            if let Some(file) = &function.symbol.file {
+                lines.entry(file.clone()).or_default();
+            }
+        }
+
+        for (file, lines) in &lines {
+            writeln!(writer, "TN:{test_name}")?;
+            writeln!(writer, "SF:{}", file.display())?;
+            let functions: Vec<&FunctionCoverage> = self
+                .functions
+                .iter()
+                .filter(|function| function.symbol.file.as_ref() == Some(file))
+                .collect();
+            for function in &functions {
+                writeln!(
+                    writer,
+                    "FN:{},{}",
+                    function.symbol.line.unwrap_or_default(),
+                    function.symbol.name
+                )?;
+            }
+            for function in &functions {
+                writeln!(writer, "FNDA:{},{}", function.hits(), function.symbol.name)?;
+            }
+            writeln!(writer, "FNF:{}", functions.len())?;
+            writeln!(
+                writer,
+                "FNH:{}",
+                functions
+                    .iter()
+                    .filter(|function| function.is_covered())
+                    .count()
+            )?;
+            for (line, hits) in lines {
+                writeln!(writer, "DA:{line},{hits}")?;
+            }
+            writeln!(writer, "LF:{}", lines.len())?;
+            writeln!(
+                writer,
+                "LH:{}",
+                lines.values().filter(|hits| **hits != 0).count()
+            )?;
+            writeln!(writer, "end_of_record")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the coverage as an HTML page, with a table for the modules, the files and the functions
+    pub fn write_html<W>(&self, writer: &mut W) -> io::Result<()>
+    where
+        W: Write,
+    {
+        fn row<W: Write>(
+            writer: &mut W,
+            name: &str,
+            (covered_functions, functions): (usize, usize),
+            (covered, edges): (usize, usize),
+            hits: u64,
+        ) -> io::Result<()> {
+            let class = if covered == 0 { "uncovered" } else { "covered" };
+            writeln!(
+                writer,
+                "<tr class=\"{class}\"><td>{}</td><td>{covered_functions}/{functions}</td>\
+                 <td>{covered}/{edges} ({:.2}%)</td><td>{hits}</td></tr>",
+                escape_html(name),
+                percent(covered, edges)
+            )
+        }
+        let header = "<tr><th>Name</th><th>Functions</th><th>Edges</th><th>Hits</th></tr>";
+        let unknown = |path: Option<&PathBuf>| {
+            path.map_or_else(|| "<unknown>".into(), |path| path.display().to_string())
+        };
+
+        let (edges, covered) = self.edges();
+        writeln!(
+            writer,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Coverage report</title>\n\
+             <style>table {{ border-collapse: collapse; }} td, th {{ border: 1px solid #ccc; padding: 2px 8px; }} \
+             tr.covered {{ background: #dfd; }} tr.uncovered {{ background: #fdd; }}</style>\n</head>\n<body>"
+        )?;
+        writeln!(writer, "<h1>Coverage report</h1>")?;
+        writeln!(
+            writer,
+            "<p>Edges: {covered}/{edges} ({:.2}%), functions: {}/{}</p>",
+            percent(covered, edges),
+            self.covered_functions().count(),
+            self.functions.len()
+        )?;
+
+        writeln!(writer, "<h2>Modules</h2>\n<table>\n{header}")?;
+        for module in self.modules() {
+            row(
+                writer,
+                &unknown(module.module.as_ref()),
+                (module.covered_functions, module.functions),
+                (module.covered, module.edges),
+                module.hits,
+            )?;
+        }
+        writeln!(writer, "</table>\n<h2>Files</h2>\n<table>\n{header}")?;
+        for file in self.files() {
+            row(
+                writer,
+                &unknown(file.file.as_ref()),
+                (file.covered_functions, file.functions),
+                (file.covered, file.edges),
+                file.hits,
+            )?;
+        }
+        writeln!(writer, "</table>\n<h2>Functions</h2>\n<table>\n{header}")?;
+        for function in &self.functions {
+            let name = match (&function.symbol.file, function.symbol.line) {
+                (Some(file), Some(line)) => {
+                    format!("{} ({}:{line})", function.symbol.name, file.display())
+                }
+                _ => function.symbol.name.clone(),
+            };
+            row(
+                writer,
+                &name,
+                (usize::from(function.is_covered()), 1),
+                (function.covered, function.edges.len()),
+                function.hits(),
+            )?;
+        }
+        writeln!(writer, "</table>\n</body>\n</html>")
+    }
+
+    /// Writes the coverage to `coverage.lcov` and `coverage.html` in `dir`, creating it if needed
+    pub fn write_to_dir<P>(&self, dir: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let mut lcov = BufWriter::new(File::create(dir.join("coverage.lcov"))?);
+        self.write_lcov(&mut lcov, "libafl")?;
+        lcov.flush()?;
+        let mut html = BufWriter::new(File::create(dir.join("coverage.html"))?);
+        self.write_html(&mut html)?;
+        html.flush()?;
+        Ok(())
+    }
+}
+
+/// Escapes `text` for HTML
+fn escape_html(text: &str) -> Cow<'_, str> {
+    if !text.contains(['&', '<', '>', '"']) {
+        return Cow::Borrowed(text);
+    }
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    Cow::Owned(escaped)
+}
+
+/// The share of `covered` in `total`, in percent
+#[allow(clippy::cast_precision_loss)]
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        covered as f64 * 100.0 / total as f64
+    }
+}
+
+impl Display for CoverageSummary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (edges, covered) = self.edges();
+        let covered_functions = self.covered_functions().count();
+        writeln!(
+            f,
+            "Edges: {covered}/{edges} ({:.2}%), functions: {covered_functions}/{} ({:.2}%)",
+            percent(covered, edges),
+            self.functions.len(),
+            percent(covered_functions, self.functions.len())
+        )?;
+        for file in self.files() {
+            let name = file
+                .file
+                .as_ref()
+                .map_or_else(|| "<unknown>".into(), |file| file.display().to_string());
+            writeln!(
+                f,
+                "{name}: edges {}/{} ({:.2}%), functions {}/{}",
+                file.covered,
+                file.edges,
+                percent(file.covered, file.edges),
+                file.covered_functions,
+                file.functions
+            )?;
+        }
+        for function in self.covered_functions() {
+            writeln!(
+                f,
+                "  {} ({:#x}): edges {}/{}",
+                function.symbol.name,
+                function.entry,
+                function.covered,
+                function.edges.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The [`CoverageReportStage`] periodically writes the coverage of the history map of a map feedback
+/// to `coverage.lcov` and `coverage.html`, see [`CoverageSummary::write_to_dir`]
+#[derive(Debug)]
+pub struct CoverageReportStage<EM, Z> {
+    map_name: Cow<'static, str>,
+    out_dir: PathBuf,
+    interval: Duration,
+    last_report: Option<Duration>,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, Z> CoverageReportStage<EM, Z> {
+    /// Creates a new [`CoverageReportStage`] for the history map of `map_feedback`, writing to `out_dir`
+    /// every [`COVERAGE_REPORT_INTERVAL_DEFAULT`]
+    #[must_use]
+    pub fn new<F, P>(map_feedback: &F, out_dir: P) -> Self
+    where
+        F: Named,
+        P: Into<PathBuf>,
+    {
+        Self {
+            map_name: map_feedback.name().clone(),
+            out_dir: out_dir.into(),
+            interval: COVERAGE_REPORT_INTERVAL_DEFAULT,
+            last_report: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Write the coverage every `interval`
+    #[must_use]
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+}
+
+impl<EM, Z> UsesState for CoverageReportStage<EM, Z>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for CoverageReportStage<EM, Z>
+where
+    E: UsesState<State = Self::State>,
+    EM: UsesState,
+    EM::State: State + HasNamedMetadata,
+    Z: UsesState<State = Self::State>,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut Self::State,
+        _manager: &mut EM,
+    ) -> Result<(), Error> {
+        let now = current_time();
+        if self
+            .last_report
+            .is_some_and(|last_report| now.saturating_sub(last_report) < self.interval)
+        {
+            return Ok(());
+        }
+        self.last_report = Some(now);
+
+        let history = state.named_metadata::<MapFeedbackMetadata<u8>>(&self.map_name)?;
+        CoverageSummary::from_map(&history.history_map).write_to_dir(&self.out_dir)
+    }
+
+    #[inline]
+    fn should_restart(&mut self, _state: &mut Self::State) -> Result<bool, Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(true)
+    }
+
+    #[inline]
+    fn clear_progress(&mut self, _state: &mut Self::State) -> Result<(), Error> {
+        // Not executing the target, so restart safety is not needed
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+// Runtime for the targets built with the libafl_cc `DataFlowSanitizer`
+// configuration, that is with `-fsanitize=dataflow -mllvm -dfsan-event-callbacks`.
+// The fuzzer labels the input bytes before running the target, DFSan then
+// calls `__dfsan_cmp_callback` with the union of the labels of the operands of
+// each comparison, that we record per comparison site.
+
+#include "common.h"
+#include <stddef.h>
+
+#ifndef DFSAN_MAP_SIZE
+  #define DFSAN_MAP_SIZE 65536
+#endif
+
+// The labels of the LLVM 14+ DFSan runtime are 8 bits, one bit per label
+typedef uint8_t dfsan_label;
+
+extern dfsan_label __libafl_dfsan_cmp_map[DFSAN_MAP_SIZE];
+
+void __dfsan_cmp_callback(dfsan_label label) {
+  if (!label) { return; }
+
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= DFSAN_MAP_SIZE - 1;
+
+  __libafl_dfsan_cmp_map[k] |= label;
+}
+
+// The other event callbacks are emitted as well, but we only care about the
+// comparisons
+void __dfsan_load_callback(dfsan_label label, void *addr) {
+  (void)label;
+  (void)addr;
+}
+
+void __dfsan_store_callback(dfsan_label label, void *addr) {
+  (void)label;
+  (void)addr;
+}
+
+void __dfsan_mem_transfer_callback(dfsan_label *start, size_t len) {
+  (void)start;
+  (void)len;
+}
@@ -0,0 +1,123 @@
+//! Taint tracking with the [`DataFlowSanitizer`](https://clang.llvm.org/docs/DataFlowSanitizer.html).
+//!
+//! Build the target with the `libafl_cc` `DataFlowSanitizer` configuration, and link the fuzzer with
+//! `-fsanitize=dataflow`. The harness runs the target with [`dfsan_run_labelled`], that labels the
+//! ranges of the input set by the [`DfsanTaintTracker`], and the comparison callback of `dfsan.c`
+//! records which labels reached each comparison site of the target.
+//! The [`DfsanTaintTracker`] is meant for the [`TaintTracingStage`](libafl::stages::TaintTracingStage).
+
+use alloc::vec::Vec;
+use core::{ffi::c_void, ops::Range};
+use std::sync::Mutex;
+
+use libafl::stages::TaintTracker;
+
+use crate::DFSAN_MAP_SIZE;
+
+/// The count of labels of the `DataFlowSanitizer`, one bit of the 8 bits label each
+pub const DFSAN_LABELS: usize = 8;
+
+/// The labels that reached each comparison site, bit `i` for the label `i`
+#[no_mangle]
+#[allow(non_upper_case_globals)]
+pub static mut __libafl_dfsan_cmp_map: [u8; DFSAN_MAP_SIZE] = [0; DFSAN_MAP_SIZE];
+pub use __libafl_dfsan_cmp_map as DFSAN_CMP_MAP;
+
+/// The ranges of the input labelled by [`dfsan_run_labelled`], the range at index `i` with the label `i`
+static DFSAN_RANGES: Mutex<Vec<Range<usize>>> = Mutex::new(Vec::new());
+
+extern "C" {
+    /// Set the label of `size` bytes at `addr`, from the `DataFlowSanitizer` runtime
+    fn dfsan_set_label(label: u8, addr: *mut c_void, size: usize);
+}
+
+/// Run `harness` with a copy of `input`, labelled with the ranges set by the [`DfsanTaintTracker`].
+///
+/// The bytes out of the ranges are not labelled, and the labels are removed after the run.
+pub fn dfsan_run_labelled<F, R>(input: &[u8], harness: F) -> R
+where
+    F: FnOnce(&[u8]) -> R,
+{
+    let mut buf = input.to_vec();
+    let ranges = DFSAN_RANGES.lock().unwrap().clone();
+    // # Safety
+    // The ranges are clamped to the copy of the input, that we own.
+    unsafe {
+        dfsan_set_label(0, buf.as_mut_ptr().cast(), buf.len());
+        for (i, range) in ranges.iter().enumerate().take(DFSAN_LABELS) {
+            let end = range.end.min(buf.len());
+            if range.start < end {
+                dfsan_set_label(
+                    1 << i,
+                    buf.as_mut_ptr().add(range.start).cast(),
+                    end - range.start,
+                );
+            }
+        }
+    }
+    let ret = harness(&buf);
+    unsafe {
+        dfsan_set_label(0, buf.as_mut_ptr().cast(), buf.len());
+    }
+    ret
+}
+
+/// A [`TaintTracker`] for the targets built with the `DataFlowSanitizer`. See the [module-level documentation](self).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DfsanTaintTracker;
+
+impl DfsanTaintTracker {
+    /// Create a new [`DfsanTaintTracker`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// The comparison sites reached by labelled bytes in the last execution, with their labels
+    pub fn comparison_sites(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        // # Safety
+        // The map is only written by the comparison callback, during the runs of the target.
+        let map_ptr = &raw const DFSAN_CMP_MAP;
+        let map = unsafe { &*map_ptr };
+        map.iter()
+            .enumerate()
+            .filter(|(_, labels)| **labels != 0)
+            .map(|(site, labels)| (site, *labels))
+    }
+
+    /// The ranges of the input that reached each comparison site in the last execution
+    pub fn influencing_ranges(&self) -> Vec<(usize, Vec<Range<usize>>)> {
+        let ranges = DFSAN_RANGES.lock().unwrap();
+        self.comparison_sites()
+            .map(|(site, labels)| {
+                let influencing = ranges
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| *i < DFSAN_LABELS && labels & (1 << i) != 0)
+                    .map(|(_, range)| range.clone())
+                    .collect();
+                (site, influencing)
+            })
+            .collect()
+    }
+}
+
+impl TaintTracker for DfsanTaintTracker {
+    fn max_labels(&self) -> usize {
+        DFSAN_LABELS
+    }
+
+    fn set_ranges(&mut self, ranges: &[Range<usize>]) {
+        *DFSAN_RANGES.lock().unwrap() = ranges.to_vec();
+        let map_ptr = &raw mut DFSAN_CMP_MAP;
+        unsafe {
+            (*map_ptr).fill(0);
+        }
+    }
+
+    fn influential_labels(&self) -> u64 {
+        self.comparison_sites().fold(0, |labels, (_, site_labels)| {
+            labels | u64::from(site_labels)
+        })
+    }
+}
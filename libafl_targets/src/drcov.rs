@@ -2,18 +2,36 @@
 //!
 //! It's writing basic-block trace files to be read by coverage analysis tools, such as [Lighthouse](https://github.com/gaasedelen/lighthouse),
 //! [bncov](https://github.com/ForAllSecure/bncov), [cartographer](https://github.com/nccgroup/Cartographer), etc.
-
-use alloc::{string::String, vec::Vec};
-use core::{fmt::Debug, num::ParseIntError, ptr};
+//!
+//! The [`DrCovStage`] exports the trace of selected testcases only, e.g. the new finds and the objectives,
+//! from the runs published to a [`DrCovObserver`] by the `DrCov` module of `libafl_qemu` or runtime of `libafl_frida`.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    format,
+    string::String,
+    vec::Vec,
+};
+use core::{fmt::Debug, marker::PhantomData, num::ParseIntError, ptr};
 use std::{
-    fs::File,
+    fs::{self, File},
     io::{BufRead, BufReader, BufWriter, Read, Write},
     path::{Path, PathBuf},
+    sync::Mutex,
 };
 
 use hashbrown::HashSet;
-use libafl::Error;
+use libafl::{
+    corpus::{Corpus, CorpusId, HasCurrentCorpusId},
+    executors::{Executor, ExitKind, HasObservers},
+    observers::{Observer, ObserversTuple},
+    stages::{RetryCountRestartHelper, Stage},
+    state::{HasCorpus, HasCurrentTestcase, HasExecutions, HasSolutions, UsesState},
+    Error, HasNamedMetadata,
+};
+use libafl_bolts::{tuples::Handle, Named};
 use rangemap::RangeMap;
+use serde::{Deserialize, Serialize};
 
 /// A basic block struct
 /// This can be used to keep track of new addresses.
@@ -521,6 +539,285 @@ impl DrCovReader {
     }
 }
 
+/// The run published for the [`DrCovObserver`]: the module mapping and the basic blocks
+type DrCovRun = (RangeMap<u64, (u16, String)>, Vec<DrCovBasicBlock>);
+
+static DRCOV_LAST_RUN: Mutex<Option<DrCovRun>> = Mutex::new(None);
+
+/// Publish the basic blocks of the current run, executed in the modules of `module_mapping`, to the [`DrCovObserver`]
+pub fn publish_drcov_run(
+    module_mapping: &RangeMap<u64, (u16, String)>,
+    basic_blocks: Vec<DrCovBasicBlock>,
+) {
+    *DRCOV_LAST_RUN.lock().unwrap() = Some((module_mapping.clone(), basic_blocks));
+}
+
+/// Observes the basic blocks of a run, as published with [`publish_drcov_run`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DrCovObserver {
+    name: Cow<'static, str>,
+    #[serde(skip)]
+    module_mapping: RangeMap<u64, (u16, String)>,
+    #[serde(skip)]
+    basic_blocks: Vec<DrCovBasicBlock>,
+}
+
+impl DrCovObserver {
+    /// Create a new [`DrCovObserver`]
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name: Cow::Borrowed(name),
+            module_mapping: RangeMap::new(),
+            basic_blocks: Vec::new(),
+        }
+    }
+
+    /// The modules of the last run
+    #[must_use]
+    pub fn module_mapping(&self) -> &RangeMap<u64, (u16, String)> {
+        &self.module_mapping
+    }
+
+    /// The basic blocks of the last run
+    #[must_use]
+    pub fn basic_blocks(&self) -> &[DrCovBasicBlock] {
+        &self.basic_blocks
+    }
+
+    /// Write the last run to a `DrCov` file, skipping the blocks outside of the modules
+    pub fn write<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let basic_blocks: Vec<DrCovBasicBlock> = self
+            .basic_blocks
+            .iter()
+            .filter(|block| self.module_mapping.contains_key(&block.start))
+            .copied()
+            .collect();
+        DrCovWriter::new(&self.module_mapping).write(path, &basic_blocks)
+    }
+}
+
+impl Named for DrCovObserver {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
+impl<I, S> Observer<I, S> for DrCovObserver {
+    fn pre_exec(&mut self, _state: &mut S, _input: &I) -> Result<(), Error> {
+        self.basic_blocks.clear();
+        *DRCOV_LAST_RUN.lock().unwrap() = None;
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &I,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        if let Some((module_mapping, basic_blocks)) = DRCOV_LAST_RUN.lock().unwrap().take() {
+            self.module_mapping = module_mapping;
+            self.basic_blocks = basic_blocks;
+        }
+        Ok(())
+    }
+}
+
+/// The name of the [`DrCovStage`]
+pub static DRCOV_STAGE_NAME: &str = "drcov";
+
+/// Runs each scheduled testcase once on a tracer executor and writes its `DrCov` trace
+/// to `<out_dir>/queue/<testcase>.drcov`.
+///
+/// With [`DrCovStage::export_solutions`], the new objectives are traced as well, into `<out_dir>/solutions`.
+/// They are run on the tracer executor, which then has to survive crashes and timeouts, e.g. a fork executor.
+/// Testcases with a trace file already are not run again.
+#[derive(Debug)]
+pub struct DrCovStage<EM, TE, Z> {
+    name: Cow<'static, str>,
+    tracer_executor: TE,
+    observer_handle: Handle<DrCovObserver>,
+    out_dir: PathBuf,
+    solutions: bool,
+    exported_solutions: HashSet<CorpusId>,
+    phantom: PhantomData<(EM, Z)>,
+}
+
+impl<EM, TE, Z> DrCovStage<EM, TE, Z> {
+    /// Create a new [`DrCovStage`], writing to `out_dir`
+    pub fn new<P>(
+        tracer_executor: TE,
+        observer_handle: Handle<DrCovObserver>,
+        out_dir: P,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let out_dir = out_dir.as_ref().to_path_buf();
+        fs::create_dir_all(out_dir.join("queue"))?;
+        Ok(Self {
+            name: Cow::Owned(DRCOV_STAGE_NAME.to_owned() + ":" + observer_handle.name().as_ref()),
+            tracer_executor,
+            observer_handle,
+            out_dir,
+            solutions: false,
+            exported_solutions: HashSet::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Also trace the objectives
+    pub fn export_solutions(mut self, solutions: bool) -> Result<Self, Error> {
+        if solutions {
+            fs::create_dir_all(self.out_dir.join("solutions"))?;
+        }
+        self.solutions = solutions;
+        Ok(self)
+    }
+
+    /// Gets the underlying tracer executor
+    pub fn executor(&self) -> &TE {
+        &self.tracer_executor
+    }
+
+    /// Gets the underlying tracer executor (mut)
+    pub fn executor_mut(&mut self) -> &mut TE {
+        &mut self.tracer_executor
+    }
+}
+
+impl<EM, TE, Z> DrCovStage<EM, TE, Z>
+where
+    TE: Executor<EM, Z> + HasObservers,
+    TE::Observers: ObserversTuple<TE::Input, TE::State>,
+    EM: UsesState<State = TE::State>,
+    Z: UsesState<State = TE::State>,
+{
+    /// Run `input` on the tracer executor and write its trace to `path`
+    fn trace(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut TE::State,
+        manager: &mut EM,
+        input: &TE::Input,
+        path: &Path,
+    ) -> Result<(), Error> {
+        self.tracer_executor
+            .observers_mut()
+            .pre_exec_all(state, input)?;
+        let exit_kind = self
+            .tracer_executor
+            .run_target(fuzzer, state, manager, input)?;
+        self.tracer_executor
+            .observers_mut()
+            .post_exec_all(state, input, &exit_kind)?;
+
+        let observers = self.tracer_executor.observers();
+        let observer = &observers[&self.observer_handle];
+        if observer.basic_blocks().is_empty() {
+            log::warn!("No DrCov trace published for {}", path.display());
+            return Ok(());
+        }
+        observer.write(path)
+    }
+}
+
+/// The name of the trace file of a testcase
+fn trace_file_name(filename: Option<&String>, id: CorpusId) -> String {
+    match filename {
+        Some(filename) => format!("{filename}.drcov"),
+        None => format!("id_{id}.drcov"),
+    }
+}
+
+impl<E, EM, TE, Z> Stage<E, EM, Z> for DrCovStage<EM, TE, Z>
+where
+    E: UsesState<State = TE::State>,
+    TE: Executor<EM, Z> + HasObservers,
+    TE::Observers: ObserversTuple<TE::Input, TE::State>,
+    TE::State: HasExecutions
+        + HasCorpus
+        + HasSolutions
+        + HasCurrentCorpusId
+        + HasCurrentTestcase
+        + HasNamedMetadata,
+    TE::Input: Clone,
+    EM: UsesState<State = TE::State>,
+    Z: UsesState<State = TE::State>,
+    <TE::State as HasCorpus>::Corpus: Corpus<Input = TE::Input>,
+    <TE::State as HasSolutions>::Solutions: Corpus<Input = TE::Input>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut TE::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let Some(id) = state.current_corpus_id()? else {
+            return Err(Error::illegal_state(
+                "state is not currently processing a corpus index",
+            ));
+        };
+        let filename = state.current_testcase()?.filename().clone();
+        let path = self
+            .out_dir
+            .join("queue")
+            .join(trace_file_name(filename.as_ref(), id));
+        if !path.exists() {
+            let input = state.current_input_cloned()?;
+            self.trace(fuzzer, state, manager, &input, &path)?;
+        }
+
+        if self.solutions {
+            let ids: Vec<CorpusId> = state
+                .solutions()
+                .ids()
+                .filter(|id| !self.exported_solutions.contains(id))
+                .collect();
+            for id in ids {
+                self.exported_solutions.insert(id);
+                let filename = state.solutions().get(id)?.borrow().filename().clone();
+                let path = self
+                    .out_dir
+                    .join("solutions")
+                    .join(trace_file_name(filename.as_ref(), id));
+                if path.exists() {
+                    continue;
+                }
+                let input = state.solutions().cloned_input_for_id(id)?;
+                self.trace(fuzzer, state, manager, &input, &path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn should_restart(&mut self, state: &mut Self::State) -> Result<bool, Error> {
+        RetryCountRestartHelper::no_retry(state, &self.name)
+    }
+
+    fn clear_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        RetryCountRestartHelper::clear_progress(state, &self.name)
+    }
+}
+
+impl<EM, TE, Z> UsesState for DrCovStage<EM, TE, Z>
+where
+    TE: UsesState,
+{
+    type State = TE::State;
+}
+
+impl<EM, TE, Z> Named for DrCovStage<EM, TE, Z> {
+    fn name(&self) -> &Cow<'static, str> {
+        &self.name
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::{
@@ -532,7 +829,9 @@ mod test {
 
     use rangemap::RangeMap;
 
-    use super::{DrCovModuleEntry, DrCovReader, DrCovWriter};
+    use libafl::{executors::ExitKind, observers::Observer};
+
+    use super::{publish_drcov_run, DrCovModuleEntry, DrCovObserver, DrCovReader, DrCovWriter};
     use crate::drcov::{DrCovBasicBlock, DrCovBasicBlockEntry};
 
     #[test]
@@ -608,4 +907,32 @@ mod test {
         first.merge(&second, true).unwrap();
         assert_eq!(first.basic_block_entries.len(), 2);
     }
+
+    #[test]
+    fn test_drcov_observer() {
+        let mut ranges = RangeMap::<u64, (u16, String)>::new();
+        ranges.insert(0x1000..0x2000, (0, "target".to_string()));
+
+        let mut observer = DrCovObserver::new("drcov");
+        Observer::<(), ()>::pre_exec(&mut observer, &mut (), &()).unwrap();
+        publish_drcov_run(
+            &ranges,
+            vec![
+                DrCovBasicBlock::new(0x1010, 0x1020),
+                DrCovBasicBlock::new(0x3000, 0x3010),
+            ],
+        );
+        Observer::<(), ()>::post_exec(&mut observer, &mut (), &(), &ExitKind::Ok).unwrap();
+        assert_eq!(observer.basic_blocks().len(), 2);
+
+        // The block outside of the modules is skipped
+        let drcov_tmp_file = temp_dir().join("drcov_observer_test.drcov");
+        observer.write(&drcov_tmp_file).unwrap();
+        let reader = DrCovReader::read(&drcov_tmp_file).unwrap();
+        assert_eq!(
+            reader.basic_blocks(),
+            vec![DrCovBasicBlock::new(0x1010, 0x1020)]
+        );
+        fs::remove_file(&drcov_tmp_file).unwrap();
+    }
 }
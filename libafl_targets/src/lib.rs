@@ -68,6 +68,18 @@ pub mod sancov_pcguard;
 ))]
 pub use sancov_pcguard::*;
 
+#[cfg(all(
+    feature = "sancov_pcguard_report",
+    any(
+        feature = "sancov_pcguard_edges",
+        feature = "sancov_pcguard_hitcounts",
+        feature = "sancov_ngram4",
+        feature = "sancov_ngram8",
+        feature = "sancov_ctx"
+    )
+))]
+pub mod coverage_report;
+
 #[cfg(any(feature = "sancov_cmplog", feature = "sancov_value_profile"))]
 pub mod sancov_cmp;
 #[cfg(any(feature = "sancov_cmplog", feature = "sancov_value_profile"))]
@@ -122,6 +134,11 @@ pub use cmps::*;
 #[cfg(feature = "std")]
 pub mod drcov;
 
+#[cfg(feature = "dfsan")]
+pub mod dfsan;
+#[cfg(feature = "dfsan")]
+pub use dfsan::*;
+
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
 pub mod windows_asan;
 #[cfg(all(windows, feature = "std", feature = "windows_asan"))]
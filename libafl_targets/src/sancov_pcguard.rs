@@ -3,7 +3,7 @@
 #[rustversion::nightly]
 #[cfg(any(feature = "sancov_ngram4", feature = "sancov_ngram8"))]
 use core::simd::num::SimdUint;
-use core::{mem::align_of, slice};
+use core::{mem::align_of, ops::Range, slice};
 
 #[cfg(any(
     feature = "sancov_ngram4",
@@ -68,6 +68,12 @@ pub static SHR_8: Ngram8 = Ngram8::from_array([1, 1, 1, 1, 1, 1, 1, 1]);
 
 static mut PC_TABLES: Vec<&'static [PcTableEntry]> = Vec::new();
 
+/// The first edge of each of the [`PC_TABLES`], [`None`] if the guards of its module were not numbered by us
+static mut PC_TABLES_FIRST_EDGE: Vec<Option<usize>> = Vec::new();
+
+/// The first edge and the count of the guards numbered by the last [`__sanitizer_cov_trace_pc_guard_init`]
+static mut PENDING_GUARDS: Option<(usize, usize)> = None;
+
 use alloc::vec::Vec;
 #[cfg(any(
     feature = "sancov_ngram4",
@@ -286,6 +292,10 @@ pub unsafe extern "C" fn __sanitizer_cov_trace_pc_guard_init(mut start: *mut u32
         return;
     }
 
+    // The PC table of the module, if any, follows in `__sanitizer_cov_pcs_init`
+    let guards = usize::try_from(stop.offset_from(start)).unwrap_or_default();
+    PENDING_GUARDS = Some((MAX_EDGES_FOUND, guards));
+
     while start < stop {
         *start = MAX_EDGES_FOUND as u32;
         start = start.offset(1);
@@ -322,9 +332,26 @@ unsafe extern "C" fn __sanitizer_cov_pcs_init(pcs_beg: *const usize, pcs_end: *c
         "Unaligned PC Table - start: {pcs_beg:x?} end: {pcs_end:x?}"
     );
 
+    // Each entry is a pc and its flags
+    let entries = len / 2;
+    let pending_guards_ptr = &raw mut PENDING_GUARDS;
+    let first_edge = match (*pending_guards_ptr).take() {
+        Some((first_edge, guards)) if guards == entries => Some(first_edge),
+        Some((_, guards)) => {
+            log::warn!("PC Table of {entries} entries for {guards} guards, ignoring its edges");
+            None
+        }
+        None => None,
+    };
+
     let pc_tables_ptr = &raw mut PC_TABLES;
     let pc_tables = &mut *pc_tables_ptr;
-    pc_tables.push(slice::from_raw_parts(pcs_beg as *const PcTableEntry, len));
+    pc_tables.push(slice::from_raw_parts(
+        pcs_beg as *const PcTableEntry,
+        entries,
+    ));
+    let first_edges_ptr = &raw mut PC_TABLES_FIRST_EDGE;
+    (*first_edges_ptr).push(first_edge);
 }
 
 /// An entry to the `sanitizer_cov` `pc_table`
@@ -359,3 +386,71 @@ pub fn sanitizer_cov_pc_table<'a>() -> impl Iterator<Item = &'a [PcTableEntry]>
         pc_tables.iter().copied()
     }
 }
+
+/// Returns an iterator over the PC tables, with the edge of their first entry.
+/// The following entries are the following edges.
+///
+/// The edges are the indices assigned to the guards, which are the ones in the [`EDGES_MAP`]
+/// unless `sancov_ngram4`, `sancov_ngram8` or `sancov_ctx` hash them.
+/// Tables whose module was not numbered by us, e.g. as it was initialized before, are skipped.
+pub fn sanitizer_cov_pc_table_edges<'a>() -> impl Iterator<Item = (usize, &'a [PcTableEntry])> {
+    // SAFETY: see [`sanitizer_cov_pc_table`]
+    unsafe {
+        let first_edges_ptr = &raw const PC_TABLES_FIRST_EDGE;
+        let first_edges = &*first_edges_ptr;
+        sanitizer_cov_pc_table()
+            .zip(first_edges.iter())
+            .filter_map(|(table, first_edge)| first_edge.map(|first_edge| (first_edge, table)))
+    }
+}
+
+/// Returns the PC table entry of `edge`, if any
+#[must_use]
+pub fn sanitizer_cov_pc_for_edge(edge: usize) -> Option<&'static PcTableEntry> {
+    sanitizer_cov_pc_table_edges().find_map(|(first_edge, table)| {
+        edge.checked_sub(first_edge)
+            .and_then(|offset| table.get(offset))
+    })
+}
+
+/// Returns an iterator over the functions in the PC tables: the address of their entry, and the range of their edges
+pub fn sanitizer_cov_functions() -> impl Iterator<Item = (usize, Range<usize>)> {
+    sanitizer_cov_pc_table_edges().flat_map(|(first_edge, table)| {
+        let mut functions: Vec<(usize, Range<usize>)> = Vec::new();
+        for (offset, entry) in table.iter().enumerate() {
+            let edge = first_edge + offset;
+            match functions.last_mut() {
+                Some((_, edges)) if !entry.is_function_entry() => edges.end = edge + 1,
+                _ => functions.push((entry.addr(), edge..edge + 1)),
+            }
+        }
+        functions
+    })
+}
+
+/// Returns the address of the entry of the function containing `edge`, if any
+#[must_use]
+pub fn sanitizer_cov_function_for_edge(edge: usize) -> Option<usize> {
+    sanitizer_cov_functions()
+        .find(|(_, edges)| edges.contains(&edge))
+        .map(|(entry, _)| entry)
+}
+
+/// Returns the edges whose PC is in `pcs`, e.g. to resolve the addresses of
+/// `libafl::feedbacks::TargetRegionsMetadata::parse`.
+///
+/// The PCs are the addresses at runtime, so for position independent targets the
+/// addresses found by static analysis need to be relocated first.
+#[must_use]
+pub fn sanitizer_cov_edges_in(pcs: Range<usize>) -> Vec<usize> {
+    sanitizer_cov_pc_table_edges()
+        .flat_map(|(first_edge, table)| {
+            table
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| pcs.contains(&entry.addr()))
+                .map(move |(offset, _)| first_edge + offset)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
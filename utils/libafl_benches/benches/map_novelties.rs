@@ -0,0 +1,90 @@
+//! Compare the speed of the scalar and the vectorized novelty search over sparse coverage maps
+
+use std::num::NonZero;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use libafl_bolts::{
+    rands::{Rand, StdRand},
+    simd::{map_novelties, MaxKernel},
+};
+
+/// The novelty search of the generic `MapFeedback`, for reference
+fn scalar_novelties(history: &[u8], map: &[u8], novelties: &mut Vec<usize>) -> bool {
+    let mut interesting = false;
+    for (i, (history, item)) in history.iter().zip(map).enumerate() {
+        if *item != 0 && (*history).max(*item) != *history {
+            interesting = true;
+            novelties.push(i);
+        }
+    }
+    interesting
+}
+
+fn criterion_benchmark(c: &mut Criterion) {
+    let mut rand = StdRand::with_seed(0);
+    let size = 1 << 20;
+    let mut group = c.benchmark_group("map_novelties");
+
+    // The share of map entries hit per run, from a typical large target to a saturated map
+    for hit_permille in [1, 10, 100] {
+        let mut history = vec![0_u8; size];
+        let mut map = vec![0_u8; size];
+        for _ in 0..size * hit_permille / 1000 {
+            let idx = rand.below(NonZero::new(size).unwrap());
+            map[idx] = rand.below(NonZero::new(255).unwrap()) as u8 + 1;
+            history[idx] = map[idx];
+        }
+        // Some new coverage
+        for _ in 0..16 {
+            let idx = rand.below(NonZero::new(size).unwrap());
+            map[idx] = map[idx].saturating_add(1);
+        }
+
+        let mut novelties = Vec::new();
+        group.bench_with_input(
+            BenchmarkId::new("scalar", hit_permille),
+            &hit_permille,
+            |b, _| {
+                b.iter(|| {
+                    novelties.clear();
+                    black_box(scalar_novelties(
+                        black_box(&history),
+                        black_box(&map),
+                        &mut novelties,
+                    ))
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("simd", hit_permille),
+            &hit_permille,
+            |b, _| {
+                b.iter(|| {
+                    novelties.clear();
+                    black_box(map_novelties::<MaxKernel>(
+                        black_box(&history),
+                        black_box(&map),
+                        Some(&mut novelties),
+                    ))
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("simd_no_novelties", hit_permille),
+            &hit_permille,
+            |b, _| {
+                b.iter(|| {
+                    black_box(map_novelties::<MaxKernel>(
+                        black_box(&history),
+                        black_box(&map),
+                        None,
+                    ))
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);